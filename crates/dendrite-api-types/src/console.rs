@@ -0,0 +1,23 @@
+//! Serial console of a device, over `GET /api/devices/:id/console`
+
+use serde::{Deserialize, Serialize};
+
+/// Message from the daemon on a console WebSocket
+///
+/// The client sends its input as plain text frames, written to the port as
+/// they are (end a command with `\r`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ConsoleMessage {
+    /// Console output, with SMP packets left out and invalid UTF-8 replaced
+    Output {
+        data: String,
+        /// Bytes dropped so far because the client didn't keep up
+        dropped: u64,
+    },
+    /// The session ended: the port failed, or another session holds it
+    Closed {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+}
@@ -0,0 +1,138 @@
+//! Device deltas (WebSocket protocol 2)
+//!
+//! A client connecting with `?protocol=2` is sent every device once, in a
+//! `full_sync` message, and after that only what changes: `device_added`
+//! with the whole record for a device it hasn't seen, `device_delta` with
+//! the top-level fields that changed, `status_changed` when only the status
+//! did, and `device_removed`. Both ends keep the last record per device in a
+//! [`DeviceRecords`] to compute and apply the deltas. A client that loses
+//! track can send `{"type": "sync"}` to get a new `full_sync`.
+
+use crate::CompactSnapshot;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+/// First WebSocket protocol version with device deltas
+pub const DELTA_PROTOCOL_VERSION: u32 = 2;
+
+/// A JSON object as sent over the WebSocket
+pub type Record = Map<String, Value>;
+
+/// Every device, sent on connect and when the client asks for it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FullSync {
+    /// Protocol version the daemon is speaking
+    pub protocol: u32,
+    /// One device record (full, or a summary for lite clients) per device
+    pub devices: CompactSnapshot,
+}
+
+/// Fields of a device that changed since it was last sent
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceDelta {
+    pub id: String,
+    pub changed_fields: Record,
+}
+
+/// A device's status changed and nothing else did
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatusChange {
+    pub id: String,
+    /// `online`, `offline`, `probing` or `unknown`
+    pub status: String,
+}
+
+/// How a device record compares to the one last seen
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecordChange {
+    /// No record for the device yet
+    Added,
+    /// Top-level fields that differ
+    Changed(Record),
+    Unchanged,
+}
+
+/// The last record seen per device ID
+#[derive(Debug, Clone, Default)]
+pub struct DeviceRecords(HashMap<String, Record>);
+
+impl DeviceRecords {
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Record> {
+        self.0.get(id)
+    }
+
+    /// IDs of every device with a record
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.0.keys().map(String::as_str)
+    }
+
+    pub fn insert(&mut self, id: &str, record: Record) {
+        self.0.insert(id.to_string(), record);
+    }
+
+    pub fn remove(&mut self, id: &str) {
+        self.0.remove(id);
+    }
+
+    /// Store a device's latest record and say what changed
+    ///
+    /// Fields missing from `record` are kept, so a summary following a full
+    /// record leaves the visuals, frames, ports and sensors in place.
+    pub fn update(&mut self, id: &str, record: Record) -> RecordChange {
+        let Some(last) = self.0.get_mut(id) else {
+            self.0.insert(id.to_string(), record);
+            return RecordChange::Added;
+        };
+        let changed: Record = record.into_iter().filter(|(key, value)| last.get(key) != Some(value)).collect();
+        if changed.is_empty() {
+            return RecordChange::Unchanged;
+        }
+        last.extend(changed.clone());
+        RecordChange::Changed(changed)
+    }
+
+    /// Apply changed fields, returning the whole record; None for a device with no record
+    pub fn apply(&mut self, id: &str, changed: Record) -> Option<&Record> {
+        let record = self.0.get_mut(id)?;
+        record.extend(changed);
+        Some(record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn record(value: Value) -> Record {
+        let Value::Object(record) = value else { panic!("not an object") };
+        record
+    }
+
+    #[test]
+    fn test_records_diff_and_apply() {
+        let full = record(json!({ "id": "a", "status": "online", "pose": [0.0, 0.0, 0.0, 0.0, 0.0, 0.0], "visuals": [1] }));
+        let mut daemon = DeviceRecords::default();
+        let mut client = DeviceRecords::default();
+        assert_eq!(daemon.update("a", full.clone()), RecordChange::Added);
+        client.insert("a", full.clone());
+        assert_eq!(daemon.update("a", full), RecordChange::Unchanged);
+
+        // A summary only carries what it has; the visuals survive
+        let summary = record(json!({ "id": "a", "status": "offline", "pose": [0.0, 0.0, 0.0, 0.0, 0.0, 0.0] }));
+        let RecordChange::Changed(changed) = daemon.update("a", summary) else {
+            panic!("expected a change");
+        };
+        assert_eq!(changed, record(json!({ "status": "offline" })));
+
+        let merged = client.apply("a", changed).unwrap();
+        assert_eq!(merged, daemon.get("a").unwrap());
+        assert_eq!(merged["visuals"], json!([1]));
+        assert!(client.apply("b", Record::new()).is_none());
+    }
+}
@@ -0,0 +1,195 @@
+//! Device payloads as the frontend reads them
+//!
+//! `GET /api/devices` and the `device_*` WebSocket messages carry
+//! [`dendrite_core::Device`] records; these types are the subset the web UI
+//! uses (see the crate docs for what is left out and why).
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceJson {
+    pub id: IdJson,
+    pub name: String,
+    /// `online`, `offline`, `probing` or `unknown`
+    pub status: String,
+    pub discovery: DiscoveryJson,
+    pub info: InfoJson,
+    pub firmware: FirmwareJson,
+    pub model_path: Option<String>,
+    pub pose: Option<[f64; 6]>,
+    #[serde(default)]
+    pub pose_provisional: bool,
+    /// Limits applied while the pose is edited
+    #[serde(default)]
+    pub constraint: ConstraintJson,
+    /// False once switched off by a user
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Composite visuals with individual poses
+    #[serde(default)]
+    pub visuals: Vec<VisualJson>,
+    /// Reference frames for this device
+    #[serde(default)]
+    pub frames: Vec<FrameJson>,
+    /// Ports on this device
+    #[serde(default)]
+    pub ports: Vec<PortJson>,
+    /// Sensors on this device
+    #[serde(default)]
+    pub sensors: Vec<SensorJson>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IdJson(pub String);
+
+/// Pose edit limits (`PUT /api/devices/:id/constraint` takes the same shape)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConstraintJson {
+    /// Keep the bounding box above the ground plane
+    #[serde(default)]
+    pub keep_above_ground: bool,
+    /// ID of the device whose upper face this one rests on
+    #[serde(default)]
+    pub stack_on: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiscoveryJson {
+    pub ip: String,
+    /// Zone (interface index) of a link-local IPv6 `ip`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope_id: Option<u32>,
+    pub port: u16,
+    pub switch_port: Option<u8>,
+    /// RFC 3339
+    pub last_seen: Option<String>,
+    /// `arp`, `icmp`, `smp-echo` or `tcp-connect:<port>` (None = auto-select)
+    #[serde(default)]
+    pub heartbeat_method: Option<String>,
+    /// Bus address of a CAN node (its `ip` is then unspecified)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub can: Option<CanAddressJson>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CanAddressJson {
+    pub bus: String,
+    pub node_id: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InfoJson {
+    pub board: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FirmwareJson {
+    pub version: Option<String>,
+}
+
+/// Visual element with its own model and pose
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VisualJson {
+    pub name: String,
+    #[serde(default)]
+    pub toggle: Option<String>,
+    #[serde(default)]
+    pub default_hidden: bool,
+    #[serde(default)]
+    pub pose: Option<[f64; 6]>,
+    #[serde(default)]
+    pub model_path: Option<String>,
+    #[serde(default)]
+    pub model_sha: Option<String>,
+}
+
+/// Reference frame
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrameJson {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub pose: Option<[f64; 6]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+}
+
+/// Port (ethernet, CAN, SPI, ...)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PortJson {
+    pub name: String,
+    pub port_type: String,
+    #[serde(default)]
+    pub pose: Option<[f64; 6]>,
+    #[serde(default)]
+    pub geometry: Vec<GeometryJson>,
+    /// Reference to visual containing the mesh (e.g., "board")
+    #[serde(default)]
+    pub visual_name: Option<String>,
+    /// GLTF mesh node name within the visual (e.g., "port_eth0")
+    #[serde(default)]
+    pub mesh_name: Option<String>,
+    /// Pose from the device's HCDF before a local edit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_pose: Option<[f64; 6]>,
+}
+
+/// Sensor driver axis alignment
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AxisAlignJson {
+    pub x: String,
+    pub y: String,
+    pub z: String,
+}
+
+/// Geometry (tagged by `type`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GeometryJson {
+    Box { size: [f64; 3] },
+    Cylinder { radius: f64, length: f64 },
+    Sphere { radius: f64 },
+    Cone { radius: f64, length: f64 },
+    Frustum { near: f64, far: f64, hfov: f64, vfov: f64 },
+    ConicalFrustum { near: f64, far: f64, fov: f64 },
+    PyramidalFrustum { near: f64, far: f64, hfov: f64, vfov: f64 },
+}
+
+/// Named sensor field of view
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FovJson {
+    pub name: String,
+    #[serde(default)]
+    pub color: Option<[f32; 3]>,
+    #[serde(default)]
+    pub pose: Option<[f64; 6]>,
+    #[serde(default)]
+    pub geometry: Option<GeometryJson>,
+}
+
+/// Sensor
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SensorJson {
+    pub name: String,
+    pub category: String,
+    pub sensor_type: String,
+    #[serde(default)]
+    pub driver: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub driver_label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub datasheet: Option<String>,
+    #[serde(default)]
+    pub pose: Option<[f64; 6]>,
+    #[serde(default)]
+    pub axis_align: Option<AxisAlignJson>,
+    #[serde(default)]
+    pub geometry: Option<GeometryJson>,
+    #[serde(default)]
+    pub fovs: Vec<FovJson>,
+}
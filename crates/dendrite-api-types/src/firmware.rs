@@ -0,0 +1,65 @@
+//! Firmware check and image slot responses
+
+use serde::{Deserialize, Serialize};
+
+pub use dendrite_core::FirmwareStatus;
+
+/// `GET /api/firmware/:id/check`, and one entry of `GET /api/firmware/check`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FirmwareCheckResponse {
+    pub device_id: String,
+    pub current_version: Option<String>,
+    /// MCUboot image hash from the device (what MCUmgr reports)
+    pub current_mcuboot_hash: Option<String>,
+    pub latest_version: Option<String>,
+    /// MCUboot image hash for the latest release (for verification after OTA)
+    pub latest_mcuboot_hash: Option<String>,
+    pub status: FirmwareStatus,
+    pub changelog: Option<String>,
+}
+
+/// One firmware image slot of a device
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImageSlotInfo {
+    pub slot: u32,
+    pub version: String,
+    /// MCUboot image hash (hex)
+    pub hash: String,
+    /// Running now
+    pub active: bool,
+    /// Kept across resets (otherwise MCUboot reverts on the next one)
+    pub confirmed: bool,
+    /// Marked for test on the next boot
+    pub pending: bool,
+}
+
+/// `GET /api/devices/:id/images`
+///
+/// Devices that don't answer, or don't have the image management group,
+/// give an empty list with `error` saying why.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct DeviceImagesResponse {
+    pub device_id: String,
+    pub images: Vec<ImageSlotInfo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl DeviceImagesResponse {
+    /// The running image, if it booted for test and still needs confirming
+    pub fn unconfirmed_active(&self) -> Option<&ImageSlotInfo> {
+        self.images.iter().find(|i| i.active && !i.confirmed)
+    }
+
+    /// Whether an image waits in a slot to be tested on the next boot
+    pub fn has_pending(&self) -> bool {
+        self.images.iter().any(|i| i.pending)
+    }
+}
+
+/// `POST /api/devices/:id/images/confirm`; without a hash the running image is confirmed
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ImageConfirmRequest {
+    #[serde(default)]
+    pub hash: Option<String>,
+}
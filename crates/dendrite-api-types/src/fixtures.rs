@@ -0,0 +1,16 @@
+//! Captured daemon payloads
+//!
+//! Each is exactly what the daemon sends for the fixture device
+//! `0x1a2b3c4d`; the daemon's contract tests keep them that way.
+
+/// A full device record (`GET /api/devices/:id`, `data` of `device_discovered`)
+pub const DEVICE: &str = include_str!("../fixtures/device.json");
+
+/// `GET /api/firmware/:id/check` with an update available
+pub const FIRMWARE_CHECK: &str = include_str!("../fixtures/firmware_check.json");
+
+/// `ota_progress` WebSocket message
+pub const OTA_PROGRESS: &str = include_str!("../fixtures/ota_progress.json");
+
+/// `POST /api/scan` after an ARP scan and one ICMP sweep
+pub const SCAN: &str = include_str!("../fixtures/scan.json");
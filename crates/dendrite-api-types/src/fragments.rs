@@ -0,0 +1,24 @@
+//! Fragment database reloads
+
+use serde::{Deserialize, Serialize};
+
+/// `POST /api/fragments/reload`
+///
+/// Index entries are given as `board/app`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FragmentReloadResponse {
+    /// Entries new to the index
+    pub added: Vec<String>,
+    /// Entries pointing at another file, or whose file changed or stopped loading
+    pub changed: Vec<String>,
+    /// Entries no longer in the index
+    pub removed: Vec<String>,
+    /// Fragments that didn't load, with the reason; the others are in use
+    #[serde(default)]
+    pub failed: Vec<String>,
+    /// Entries in the index now
+    pub fragments: usize,
+    /// Devices matched again
+    #[serde(default)]
+    pub devices: Vec<String>,
+}
@@ -0,0 +1,58 @@
+//! Dendrite API Types - REST and WebSocket payloads shared by the daemon and
+//! the web frontend
+//!
+//! The daemon builds its firmware, image slot, OTA, scan, LLDP neighbor,
+//! shell and fragment reload responses (and the compact device snapshot and device deltas sent over
+//! the WebSocket, and the device console messages) from these types and the frontend parses them with the same types, so a field
+//! renamed on one side fails to compile on the other. The crate only depends on serde,
+//! serde_json and dendrite-core and builds for WASM.
+//!
+//! Contract tests on both sides check the types against captured payloads in
+//! `fixtures/` (enabled with the `fixtures` feature): the daemon asserts its
+//! responses serialize exactly to them, the frontend that it parses them.
+//!
+//! # Intentional divergences
+//!
+//! - Device payloads: the daemon serializes [`dendrite_core::Device`] (or
+//!   [`dendrite_core::DeviceSummary`] for lite clients) as the canonical
+//!   record; [`DeviceJson`] is the frontend's view of it and leaves out what
+//!   the UI doesn't use (MAC, first seen, discovery method, parent, firmware
+//!   status and manifest URI, most of `info` and `firmware`). The daemon
+//!   contract test checks every field [`DeviceJson`] does have against the
+//!   daemon's output.
+//! - [`DeviceJson::status`], [`DiscoveryJson::ip`], [`DiscoveryJson::last_seen`]
+//!   and [`DiscoveryJson::heartbeat_method`] are plain strings, so a status or
+//!   heartbeat method added by a newer daemon shows as unknown instead of
+//!   failing the whole device list.
+//! - The visuals, frames, ports and sensors of [`DeviceJson`] default to
+//!   empty, since lite summaries leave them out.
+
+pub mod console;
+pub mod delta;
+pub mod device;
+pub mod firmware;
+pub mod fragments;
+pub mod lldp;
+pub mod ota;
+pub mod scan;
+pub mod shell;
+pub mod snapshot;
+
+#[cfg(feature = "fixtures")]
+pub mod fixtures;
+
+pub use console::ConsoleMessage;
+pub use delta::{DeviceDelta, DeviceRecords, FullSync, Record, RecordChange, StatusChange, DELTA_PROTOCOL_VERSION};
+pub use device::{
+    AxisAlignJson, CanAddressJson, ConstraintJson, DeviceJson, DiscoveryJson, FirmwareJson, FovJson, FrameJson, GeometryJson, IdJson, InfoJson,
+    PortJson, SensorJson, VisualJson,
+};
+pub use firmware::{DeviceImagesResponse, FirmwareCheckResponse, FirmwareStatus, ImageConfirmRequest, ImageSlotInfo};
+pub use fragments::FragmentReloadResponse;
+pub use lldp::LldpNeighborJson;
+pub use ota::{
+    OtaBatchRequest, OtaEvent, OtaProgressResponse, OtaReadiness, OtaStartQuery, OtaStartResponse, Readiness, UpdateState,
+};
+pub use scan::{MethodStats, ScanResponse, ScanStats};
+pub use shell::{ShellRequest, ShellResponse};
+pub use snapshot::{CompactSnapshot, SNAPSHOT_MESSAGE_TYPE};
@@ -0,0 +1,25 @@
+//! LLDP neighbors
+
+use serde::{Deserialize, Serialize};
+
+/// A neighbor heard over LLDP (`GET /api/lldp/neighbors`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LldpNeighborJson {
+    /// Local interface the neighbor was heard on
+    pub interface: String,
+    pub chassis_id: String,
+    pub port_id: String,
+    /// Switch port parsed from the port ID, when numeric
+    #[serde(default)]
+    pub port: Option<u8>,
+    #[serde(default)]
+    pub system_name: Option<String>,
+    /// Advertised time-to-live (None if the neighbor never expires)
+    #[serde(default)]
+    pub ttl_secs: Option<u64>,
+    /// When the neighbor was last heard from (RFC 3339)
+    pub last_refresh: String,
+    /// Registered device whose MAC is the chassis ID, if any
+    #[serde(default)]
+    pub device_id: Option<String>,
+}
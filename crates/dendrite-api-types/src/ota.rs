@@ -0,0 +1,198 @@
+//! OTA update state, progress events and responses
+
+use serde::{Deserialize, Serialize};
+
+/// Update state for a device
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum UpdateState {
+    /// Downloading firmware binary from upstream
+    Downloading { progress: f32 },
+    /// Uploading firmware to device via MCUmgr
+    Uploading {
+        progress: f32,
+        /// Times the upload was interrupted and retried
+        #[serde(default, skip_serializing_if = "is_zero")]
+        retries: u32,
+        /// Progress the last retry resumed from
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        resumed_at: Option<f32>,
+    },
+    /// Confirming (marking image as pending test)
+    Confirming,
+    /// Rebooting device
+    Rebooting,
+    /// Verifying update was successful
+    Verifying,
+    /// Update completed successfully
+    Complete,
+    /// Update failed
+    Failed { error: String },
+    /// Update was cancelled
+    Cancelled,
+}
+
+impl UpdateState {
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, UpdateState::Complete | UpdateState::Failed { .. } | UpdateState::Cancelled)
+    }
+
+    pub fn progress_text(&self) -> String {
+        match self {
+            UpdateState::Downloading { progress } => format!("Downloading... {:.0}%", progress * 100.0),
+            UpdateState::Uploading { progress, resumed_at: Some(resumed_at), .. } => {
+                format!("Uploading... {:.0}% (resumed at {:.0}%)", progress * 100.0, resumed_at * 100.0)
+            }
+            UpdateState::Uploading { progress, .. } => format!("Uploading... {:.0}%", progress * 100.0),
+            UpdateState::Confirming => "Confirming image...".to_string(),
+            UpdateState::Rebooting => "Rebooting device...".to_string(),
+            UpdateState::Verifying => "Verifying update...".to_string(),
+            UpdateState::Complete => "Update complete!".to_string(),
+            UpdateState::Failed { error } => format!("Failed: {}", error),
+            UpdateState::Cancelled => "Cancelled".to_string(),
+        }
+    }
+
+    pub fn progress_value(&self) -> Option<f32> {
+        match self {
+            UpdateState::Downloading { progress } => Some(*progress),
+            UpdateState::Uploading { progress, .. } => Some(*progress),
+            _ => None,
+        }
+    }
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
+
+/// OTA progress, sent as the `ota_progress` WebSocket message
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OtaEvent {
+    pub device_id: String,
+    pub state: UpdateState,
+}
+
+/// `POST /api/ota/:id/start` and `/api/ota/:id/upload-local`, and one entry of `POST /api/ota/batch`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OtaStartResponse {
+    pub device_id: String,
+    pub status: String,
+    /// Why a batch update didn't start for this device
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Query parameters of `POST /api/ota/:id/start` and `/api/ota/batch`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct OtaStartQuery {
+    /// Run every step before the upload and report readiness instead of updating
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// `POST /api/ota/batch` request body
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OtaBatchRequest {
+    pub device_ids: Vec<String>,
+}
+
+/// Whether an update would go through, as far as a dry run can tell
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Readiness {
+    Ok,
+    WouldFail { reason: String },
+}
+
+/// Dry-run result for one device: `POST /api/ota/:id/start?dry_run=true`,
+/// and one entry of `POST /api/ota/batch?dry_run=true`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OtaReadiness {
+    pub device_id: String,
+    #[serde(flatten)]
+    pub readiness: Readiness,
+    /// Version the update would install, once the manifest resolved
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Size of the downloaded image
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_bytes: Option<u64>,
+    /// Upload, reboot and verification time, from the device's round trip time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimated_secs: Option<f32>,
+}
+
+impl OtaReadiness {
+    /// A device that failed a check, with nothing else known about the update
+    pub fn would_fail(device_id: impl Into<String>, reason: impl Into<String>) -> Self {
+        Self {
+            device_id: device_id.into(),
+            readiness: Readiness::WouldFail { reason: reason.into() },
+            version: None,
+            image_bytes: None,
+            estimated_secs: None,
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.readiness == Readiness::Ok
+    }
+}
+
+/// `GET /api/ota/:id/progress`, and one entry of `GET /api/ota`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OtaProgressResponse {
+    pub device_id: String,
+    pub state: Option<UpdateState>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_state_is_terminal() {
+        assert!(!UpdateState::Downloading { progress: 0.5 }.is_terminal());
+        assert!(!UpdateState::Uploading { progress: 0.5, retries: 0, resumed_at: None }.is_terminal());
+        assert!(!UpdateState::Confirming.is_terminal());
+        assert!(!UpdateState::Rebooting.is_terminal());
+        assert!(!UpdateState::Verifying.is_terminal());
+        assert!(UpdateState::Complete.is_terminal());
+        assert!(UpdateState::Failed { error: "test".to_string() }.is_terminal());
+        assert!(UpdateState::Cancelled.is_terminal());
+    }
+
+    #[test]
+    fn test_update_state_is_tagged() {
+        let json = serde_json::to_value(UpdateState::Uploading { progress: 0.5, retries: 0, resumed_at: None }).unwrap();
+        assert_eq!(json, serde_json::json!({"state": "uploading", "progress": 0.5}));
+        let state: UpdateState = serde_json::from_value(serde_json::json!({"state": "cancelled"})).unwrap();
+        assert_eq!(state, UpdateState::Cancelled);
+        assert_eq!(state.progress_value(), None);
+    }
+
+    #[test]
+    fn test_resumed_upload_progress() {
+        let state = UpdateState::Uploading { progress: 0.75, retries: 2, resumed_at: Some(0.62) };
+        assert_eq!(state.progress_text(), "Uploading... 75% (resumed at 62%)");
+        let json = serde_json::to_value(&state).unwrap();
+        assert_eq!(json["retries"], 2);
+        assert_eq!(serde_json::from_value::<UpdateState>(json).unwrap(), state);
+    }
+
+    #[test]
+    fn test_readiness_is_flattened() {
+        let failed = OtaReadiness::would_fail("0x01", "Device not reachable");
+        let json = serde_json::to_value(&failed).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"device_id": "0x01", "status": "would_fail", "reason": "Device not reachable"})
+        );
+        let json = serde_json::json!({"device_id": "0x02", "status": "ok", "image_bytes": 4096});
+        let ok: OtaReadiness = serde_json::from_value(json).unwrap();
+        assert!(ok.is_ok());
+        assert_eq!(ok.image_bytes, Some(4096));
+        assert!(!failed.is_ok());
+    }
+}
@@ -0,0 +1,45 @@
+//! Scan results
+
+use serde::{Deserialize, Serialize};
+
+/// What one discovery method contributed to a scan
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MethodStats {
+    /// Hosts that answered
+    pub responders: usize,
+    /// Responders that turned out to be MCUmgr devices
+    pub devices: usize,
+    /// Hosts skipped by the exclusion list
+    pub excluded: usize,
+    pub duration_ms: u64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+}
+
+/// Per-method breakdown of the last scan (None for methods that didn't run)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScanStats {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arp: Option<MethodStats>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icmp: Option<MethodStats>,
+    /// mDNS browse: responders are resolved service instances
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mdns: Option<MethodStats>,
+    /// CAN buses: responders are nodes found, devices the nodes registered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub can: Option<MethodStats>,
+    /// IPv6 neighbor discovery: responders are neighbors found on the interface
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ndp: Option<MethodStats>,
+}
+
+/// `POST /api/scan`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScanResponse {
+    /// Always `completed`; failed scans are an error response
+    pub status: String,
+    pub devices_found: usize,
+    #[serde(default)]
+    pub methods: ScanStats,
+}
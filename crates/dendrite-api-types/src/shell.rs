@@ -0,0 +1,21 @@
+//! Shell commands run on a device
+
+use serde::{Deserialize, Serialize};
+
+/// `POST /api/devices/:id/shell` body
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShellRequest {
+    /// Zephyr shell command, e.g. `device list`; must be on the daemon's allowlist
+    pub command: String,
+}
+
+/// `POST /api/devices/:id/shell`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShellResponse {
+    pub device_id: String,
+    pub command: String,
+    /// The command's return code (0 on success)
+    pub ret: i32,
+    /// Everything the command printed
+    pub output: String,
+}
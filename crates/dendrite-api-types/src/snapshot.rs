@@ -0,0 +1,135 @@
+//! Compact device snapshot
+//!
+//! On connect a WebSocket client is sent one `device_*` message per device,
+//! and most of those bytes are repeated object keys and nulls. A client that
+//! connects with `?compact=true` gets the list as one `device_snapshot`
+//! message instead. Nulls are dropped (every nullable field the frontend reads
+//! is an `Option`, so they come back as `None`), and each object key is
+//! replaced by a short code indexing a key table sent with the messages.
+//! [`CompactSnapshot::expand`] turns it back into the original messages.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+/// `type` of the WebSocket message carrying a [`CompactSnapshot`]
+pub const SNAPSHOT_MESSAGE_TYPE: &str = "device_snapshot";
+
+/// Characters key codes are made of
+const CODE_CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+
+/// Several WebSocket messages with nulls dropped and keys shortened
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CompactSnapshot {
+    /// Original key for each code, in code order (`a`, `b`, ... `9`, `ba`, ...)
+    pub keys: Vec<String>,
+    pub messages: Vec<Value>,
+}
+
+/// Short code for the key at `index`
+fn key_code(mut index: usize) -> String {
+    let base = CODE_CHARS.len();
+    let mut code = vec![CODE_CHARS[index % base]];
+    index /= base;
+    while index > 0 {
+        code.push(CODE_CHARS[index % base]);
+        index /= base;
+    }
+    code.reverse();
+    String::from_utf8(code).unwrap()
+}
+
+impl CompactSnapshot {
+    /// Compact serialized messages
+    pub fn compact(messages: impl IntoIterator<Item = Value>) -> Self {
+        let mut codes = HashMap::new();
+        let mut keys = Vec::new();
+        let messages = messages.into_iter().map(|message| shorten(message, &mut codes, &mut keys)).collect();
+        Self { keys, messages }
+    }
+
+    /// The original messages, minus their nulls
+    pub fn expand(self) -> Result<Vec<Value>, String> {
+        let keys: HashMap<String, String> =
+            self.keys.into_iter().enumerate().map(|(index, key)| (key_code(index), key)).collect();
+        self.messages.into_iter().map(|message| lengthen(message, &keys)).collect()
+    }
+}
+
+fn shorten(value: Value, codes: &mut HashMap<String, String>, keys: &mut Vec<String>) -> Value {
+    match value {
+        Value::Object(object) => Value::Object(
+            object
+                .into_iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(key, v)| {
+                    let code = codes
+                        .entry(key.clone())
+                        .or_insert_with(|| {
+                            keys.push(key);
+                            key_code(keys.len() - 1)
+                        })
+                        .clone();
+                    (code, shorten(v, codes, keys))
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(|v| shorten(v, codes, keys)).collect()),
+        other => other,
+    }
+}
+
+fn lengthen(value: Value, keys: &HashMap<String, String>) -> Result<Value, String> {
+    match value {
+        Value::Object(object) => {
+            let mut expanded = Map::new();
+            for (code, v) in object {
+                let key = keys.get(&code).ok_or_else(|| format!("unknown key code {:?}", code))?;
+                expanded.insert(key.clone(), lengthen(v, keys)?);
+            }
+            Ok(Value::Object(expanded))
+        }
+        Value::Array(items) => items.into_iter().map(|v| lengthen(v, keys)).collect::<Result<_, _>>().map(Value::Array),
+        other => Ok(other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_key_codes() {
+        assert_eq!(key_code(0), "a");
+        assert_eq!(key_code(61), "9");
+        assert_eq!(key_code(62), "ba");
+    }
+
+    #[test]
+    fn test_round_trip_drops_only_nulls() {
+        let messages = vec![
+            json!({"type": "device_discovered", "data": {"id": "a", "parent_id": null, "pose": [0.0, 1.5], "ports": [{"name": "ETH0"}]}}),
+            json!({"type": "device_summary", "data": {"id": "b", "name": "gps"}}),
+        ];
+        let snapshot = CompactSnapshot::compact(messages);
+        let mut keys = snapshot.keys.clone();
+        keys.sort();
+        assert_eq!(keys, vec!["data", "id", "name", "ports", "pose", "type"]);
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert!(!json.contains("null") && !json.contains("parent_id"));
+        let expanded = serde_json::from_str::<CompactSnapshot>(&json).unwrap().expand().unwrap();
+        assert_eq!(
+            expanded[0],
+            json!({"type": "device_discovered", "data": {"id": "a", "pose": [0.0, 1.5], "ports": [{"name": "ETH0"}]}})
+        );
+        assert_eq!(expanded[1], json!({"type": "device_summary", "data": {"id": "b", "name": "gps"}}));
+    }
+
+    #[test]
+    fn test_unknown_code_is_an_error() {
+        let snapshot = CompactSnapshot { keys: vec!["type".to_string()], messages: vec![json!({"b": 1})] };
+        assert!(snapshot.expand().is_err());
+    }
+}
@@ -0,0 +1,123 @@
+//! HCDF parse, serialize, diff and merge benchmarks
+//!
+//! Run with `cargo bench -p dendrite-core`. Before the criterion runs, a
+//! baseline table with the size and allocation cost of parsing each fixture
+//! is printed; allocation counts are deterministic, so a jump there points at
+//! a regression even when timings are noisy. Compare timings across commits
+//! with `--save-baseline main` and `--baseline main`.
+
+use criterion::{BatchSize, BenchmarkId, Criterion, Throughput};
+use dendrite_core::Hcdf;
+use dendrite_test_support::{device_fragment, hwid, CountingAllocator, FixtureSize, LARGE_PARSE_BUDGET};
+use std::hint::black_box;
+
+#[global_allocator]
+static ALLOC: CountingAllocator = CountingAllocator::new();
+
+fn print_baselines() {
+    println!("HCDF parse baselines (one parse per fixture)");
+    println!(
+        "{:<8} {:>6} {:>6} {:>10} {:>12} {:>12}",
+        "fixture", "mcus", "comps", "xml bytes", "allocations", "peak bytes"
+    );
+    for size in FixtureSize::ALL {
+        let (mcus, comps) = size.counts();
+        let xml = size.xml();
+        let (hcdf, stats) = ALLOC.measure(|| Hcdf::from_xml(&xml).expect("fixture parses"));
+        drop(hcdf);
+        println!(
+            "{:<8} {:>6} {:>6} {:>10} {:>12} {:>12}",
+            size.name(),
+            mcus,
+            comps,
+            xml.len(),
+            stats.allocations,
+            stats.peak_bytes
+        );
+    }
+    println!(
+        "large fixture budget: {:?}, {} allocations, {} peak bytes\n",
+        LARGE_PARSE_BUDGET.max_duration, LARGE_PARSE_BUDGET.max_allocations, LARGE_PARSE_BUDGET.max_peak_bytes
+    );
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    for size in FixtureSize::ALL {
+        let xml = size.xml();
+        group.throughput(Throughput::Bytes(xml.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size.name()), &xml, |b, xml| {
+            b.iter(|| Hcdf::from_xml(black_box(xml)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_serialize(c: &mut Criterion) {
+    let mut group = c.benchmark_group("serialize");
+    for size in FixtureSize::ALL {
+        let hcdf = Hcdf::from_xml(&size.xml()).unwrap();
+        group.bench_with_input(BenchmarkId::from_parameter(size.name()), &hcdf, |b, hcdf| {
+            b.iter(|| black_box(hcdf).to_xml().unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_diff(c: &mut Criterion) {
+    let mut group = c.benchmark_group("diff");
+    for size in [FixtureSize::Medium, FixtureSize::Large] {
+        let old = Hcdf::from_xml(&size.xml()).unwrap();
+        let mut new = old.clone();
+        for mcu in new.mcu.iter_mut().step_by(4) {
+            mcu.description = Some("Edited".to_string());
+        }
+        new.comp.pop();
+        new.mcu.push(old.mcu[0].clone());
+        new.mcu.last_mut().unwrap().hwid = Some("0xffffffff".to_string());
+
+        group.bench_with_input(BenchmarkId::from_parameter(size.name()), &(old, new), |b, (old, new)| {
+            b.iter(|| black_box(old).diff(black_box(new)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_merge(c: &mut Criterion) {
+    let mut group = c.benchmark_group("merge_all_devices");
+    for size in [FixtureSize::Medium, FixtureSize::Large] {
+        let hcdf = Hcdf::from_xml(&size.xml()).unwrap();
+        let fragments: Vec<_> = (0..hcdf.mcu.len())
+            .map(|i| {
+                let comp = Hcdf::from_xml(&device_fragment(i)).unwrap().comp.remove(0);
+                (hwid(i), comp)
+            })
+            .collect();
+
+        group.throughput(Throughput::Elements(fragments.len() as u64));
+        group.bench_function(BenchmarkId::from_parameter(size.name()), |b| {
+            b.iter_batched(
+                || hcdf.clone(),
+                |mut hcdf| {
+                    for (hwid, comp) in &fragments {
+                        hcdf.merge_device_fragment(hwid, comp);
+                    }
+                    hcdf
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn main() {
+    print_baselines();
+
+    let mut criterion = Criterion::default().configure_from_args();
+    bench_parse(&mut criterion);
+    bench_serialize(&mut criterion);
+    bench_diff(&mut criterion);
+    bench_merge(&mut criterion);
+    criterion.final_summary();
+}
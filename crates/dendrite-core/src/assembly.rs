@@ -0,0 +1,310 @@
+//! Named sub-assemblies
+//!
+//! `<assembly name="front_pod">` at the HCDF root lists member devices that
+//! move as one rigid unit. Members reference an `<mcu>` or `<comp>` by hwid
+//! or element name (comps also by their `comp-<name>` scene ID). A device
+//! belongs to at most one assembly.
+//!
+//! Moving an assembly computes the transform from its old origin to the new
+//! one and applies it to every placed member, so the members keep their
+//! poses relative to each other and to the assembly origin.
+
+use thiserror::Error;
+
+use crate::hcdf::{Assembly, Hcdf, Pose};
+
+/// Invalid assembly definitions
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum AssemblyError {
+    #[error("assembly name '{0}' is used more than once")]
+    DuplicateName(String),
+    #[error("device '{device}' is claimed by assemblies '{first}' and '{second}'")]
+    ClaimedTwice { device: String, first: String, second: String },
+    #[error("assembly '{0}' not found")]
+    NotFound(String),
+}
+
+/// A member's pose after moving its assembly
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemberPose {
+    /// Member device ID as listed in the assembly
+    pub device: String,
+    pub pose: Pose,
+}
+
+/// Pose of `member` after the frame it moves with goes from `from` to `to`
+pub fn rigid_transform(from: &Pose, to: &Pose, member: &Pose) -> Pose {
+    to.compose(&from.inverse().compose(member))
+}
+
+impl Assembly {
+    pub fn contains(&self, device_id: &str) -> bool {
+        self.member.iter().any(|m| m.device == device_id)
+    }
+}
+
+impl Hcdf {
+    /// Check that assembly names are unique and no device is in two assemblies
+    pub fn validate_assemblies(&self) -> Result<(), AssemblyError> {
+        for (i, assembly) in self.assembly.iter().enumerate() {
+            let earlier = &self.assembly[..i];
+            if earlier.iter().any(|a| a.name == assembly.name) {
+                return Err(AssemblyError::DuplicateName(assembly.name.clone()));
+            }
+            for member in &assembly.member {
+                if let Some(first) = earlier.iter().find(|a| a.contains(&member.device)) {
+                    return Err(AssemblyError::ClaimedTwice {
+                        device: member.device.clone(),
+                        first: first.name.clone(),
+                        second: assembly.name.clone(),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The assembly a device belongs to
+    pub fn assembly_of(&self, device_id: &str) -> Option<&Assembly> {
+        self.assembly.iter().find(|a| a.contains(device_id))
+    }
+
+    /// Add an assembly, or replace the one with the same name
+    ///
+    /// The document is left unchanged if the result would be invalid.
+    pub fn set_assembly(&mut self, assembly: Assembly) -> Result<(), AssemblyError> {
+        let mut assemblies = self.assembly.clone();
+        match assemblies.iter_mut().find(|a| a.name == assembly.name) {
+            Some(existing) => *existing = assembly,
+            None => assemblies.push(assembly),
+        }
+        let previous = std::mem::replace(&mut self.assembly, assemblies);
+        if let Err(e) = self.validate_assemblies() {
+            self.assembly = previous;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Remove an assembly; its members stay where they are
+    pub fn remove_assembly(&mut self, name: &str) -> bool {
+        let before = self.assembly.len();
+        self.assembly.retain(|a| a.name != name);
+        self.assembly.len() != before
+    }
+
+    /// Current pose of a member device, if it has been placed
+    pub fn member_pose(&self, device_id: &str) -> Option<Pose> {
+        let comp_id = |name: &str| format!("comp-{}", name);
+        let pose_cg = self
+            .mcu
+            .iter()
+            .find(|m| m.hwid.as_deref() == Some(device_id) || m.name == device_id)
+            .map(|m| &m.pose_cg)
+            .or_else(|| {
+                self.comp
+                    .iter()
+                    .find(|c| c.hwid.as_deref() == Some(device_id) || c.name == device_id || comp_id(&c.name) == device_id)
+                    .map(|c| &c.pose_cg)
+            })?;
+        pose_cg.as_deref().and_then(crate::hcdf::parse_pose_string)
+    }
+
+    /// Origin of an assembly: its own pose, or the centroid of its placed
+    /// members (unrotated) when it has none
+    pub fn assembly_origin(&self, name: &str) -> Option<Pose> {
+        let assembly = self.assembly.iter().find(|a| a.name == name)?;
+        if let Some(pose) = assembly.parse_pose() {
+            return Some(pose);
+        }
+        let placed: Vec<Pose> = assembly.member.iter().filter_map(|m| self.member_pose(&m.device)).collect();
+        let n = placed.len().max(1) as f64;
+        Some(Pose {
+            x: placed.iter().map(|p| p.x).sum::<f64>() / n,
+            y: placed.iter().map(|p| p.y).sum::<f64>() / n,
+            z: placed.iter().map(|p| p.z).sum::<f64>() / n,
+            ..Pose::default()
+        })
+    }
+
+    /// Move an assembly's origin to `pose`, carrying its members along
+    ///
+    /// Members that haven't been placed yet are left for the layout. Moved
+    /// members are no longer provisional. Returns the new member poses.
+    pub fn move_assembly(&mut self, name: &str, pose: &Pose) -> Result<Vec<MemberPose>, AssemblyError> {
+        let from = self
+            .assembly_origin(name)
+            .ok_or_else(|| AssemblyError::NotFound(name.to_string()))?;
+        let members: Vec<String> = self
+            .assembly
+            .iter()
+            .find(|a| a.name == name)
+            .map(|a| a.member.iter().map(|m| m.device.clone()).collect())
+            .unwrap_or_default();
+
+        let moved: Vec<MemberPose> = members
+            .into_iter()
+            .filter_map(|device| {
+                let current = self.member_pose(&device)?;
+                Some(MemberPose { pose: rigid_transform(&from, pose, &current), device })
+            })
+            .collect();
+
+        for member in &moved {
+            self.set_member_pose(&member.device, &member.pose);
+        }
+        if let Some(assembly) = self.assembly.iter_mut().find(|a| a.name == name) {
+            assembly.pose = Some(pose.to_pose_string_like(assembly.pose.as_deref()));
+        }
+        Ok(moved)
+    }
+
+    /// Write the effective origin into assemblies that have no pose, so an
+    /// exported document keeps the origin its member poses were laid out around
+    pub fn pin_assembly_origins(&mut self) {
+        let origins: Vec<Option<Pose>> = self.assembly.iter().map(|a| self.assembly_origin(&a.name)).collect();
+        for (assembly, origin) in self.assembly.iter_mut().zip(origins) {
+            if assembly.pose.is_none() {
+                assembly.pose = origin.map(|o| o.to_pose_string());
+            }
+        }
+    }
+
+    fn set_member_pose(&mut self, device_id: &str, pose: &Pose) {
+        let comp_id = |name: &str| format!("comp-{}", name);
+        if let Some(mcu) = self
+            .mcu
+            .iter_mut()
+            .find(|m| m.hwid.as_deref() == Some(device_id) || m.name == device_id)
+        {
+            mcu.pose_cg = Some(pose.to_pose_string_like(mcu.pose_cg.as_deref()));
+            mcu.provisional = None;
+        } else if let Some(comp) = self
+            .comp
+            .iter_mut()
+            .find(|c| c.hwid.as_deref() == Some(device_id) || c.name == device_id || comp_id(&c.name) == device_id)
+        {
+            comp.pose_cg = Some(pose.to_pose_string_like(comp.pose_cg.as_deref()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hcdf::AssemblyMember;
+
+    const POD: &str = r#"<?xml version='1.0'?>
+<hcdf version="1.2">
+  <mcu name="cam-left" hwid="0x01">
+    <pose_cg>1 0.1 0 0 0 0</pose_cg>
+  </mcu>
+  <mcu name="cam-right" hwid="0x02">
+    <pose_cg>1 -0.1 0 0 0 0</pose_cg>
+  </mcu>
+  <mcu name="unplaced" hwid="0x03"/>
+  <comp name="pod-frame">
+    <pose_cg>1 0 0.05 0 0 0</pose_cg>
+  </comp>
+  <assembly name="front_pod">
+    <pose>1 0 0 0 0 0</pose>
+    <member device="0x01"/>
+    <member device="0x02"/>
+    <member device="0x03"/>
+    <member device="comp-pod-frame"/>
+  </assembly>
+</hcdf>"#;
+
+    fn close(a: &Pose, b: [f64; 6]) -> bool {
+        a.to_array().iter().zip(b).all(|(x, y)| (x - y).abs() < 1e-9)
+    }
+
+    #[test]
+    fn test_inverse_undoes_pose() {
+        let pose = Pose::from_array([0.3, -1.2, 0.5, 0.2, -0.4, 1.1]);
+        assert!(close(&pose.compose(&pose.inverse()), [0.0; 6]));
+        assert!(close(&pose.inverse().compose(&pose), [0.0; 6]));
+    }
+
+    #[test]
+    fn test_parse_and_round_trip() {
+        let hcdf = Hcdf::from_xml(POD).unwrap();
+        let pod = &hcdf.assembly[0];
+        assert_eq!(pod.name, "front_pod");
+        assert_eq!(pod.member.len(), 4);
+        assert_eq!(hcdf.assembly_of("0x02").map(|a| a.name.as_str()), Some("front_pod"));
+        assert!(hcdf.assembly_of("0x09").is_none());
+
+        let reparsed = Hcdf::from_xml(&hcdf.to_xml().unwrap()).unwrap();
+        assert_eq!(reparsed.assembly[0].member, pod.member);
+        assert_eq!(reparsed.assembly[0].pose.as_deref(), Some("1 0 0 0 0 0"));
+    }
+
+    #[test]
+    fn test_move_is_rigid() {
+        let mut hcdf = Hcdf::from_xml(POD).unwrap();
+        // Swing the pod a quarter turn about its origin and lift it
+        let target = Pose::from_array([1.0, 0.0, 0.5, 0.0, 0.0, std::f64::consts::FRAC_PI_2]);
+        let moved = hcdf.move_assembly("front_pod", &target).unwrap();
+
+        let devices: Vec<_> = moved.iter().map(|m| m.device.as_str()).collect();
+        assert_eq!(devices, vec!["0x01", "0x02", "comp-pod-frame"]);
+        let quarter = std::f64::consts::FRAC_PI_2;
+        assert!(close(&moved[0].pose, [0.9, 0.0, 0.5, 0.0, 0.0, quarter]));
+        assert!(close(&moved[1].pose, [1.1, 0.0, 0.5, 0.0, 0.0, quarter]));
+        assert!(close(&hcdf.member_pose("comp-pod-frame").unwrap(), [1.0, 0.0, 0.55, 0.0, 0.0, quarter]));
+        assert!(hcdf.member_pose("0x03").is_none());
+        assert!(close(&hcdf.assembly_origin("front_pod").unwrap(), target.to_array()));
+
+        // Moving back restores the original layout
+        hcdf.move_assembly("front_pod", &Pose::from_array([1.0, 0.0, 0.0, 0.0, 0.0, 0.0])).unwrap();
+        assert!(close(&hcdf.member_pose("0x01").unwrap(), [1.0, 0.1, 0.0, 0.0, 0.0, 0.0]));
+
+        assert_eq!(
+            hcdf.move_assembly("rear_pod", &target),
+            Err(AssemblyError::NotFound("rear_pod".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_origin_defaults_to_member_centroid() {
+        let mut hcdf = Hcdf::from_xml(POD).unwrap();
+        hcdf.assembly[0].pose = None;
+        let origin = hcdf.assembly_origin("front_pod").unwrap();
+        assert!(close(&origin, [1.0, 0.0, 0.05 / 3.0, 0.0, 0.0, 0.0]));
+
+        hcdf.pin_assembly_origins();
+        assert!(close(&hcdf.assembly[0].parse_pose().unwrap(), origin.to_array()));
+    }
+
+    #[test]
+    fn test_device_claimed_twice_is_rejected() {
+        let mut hcdf = Hcdf::from_xml(POD).unwrap();
+        let rear = |devices: &[&str]| Assembly {
+            name: "rear_pod".to_string(),
+            description: None,
+            pose: None,
+            member: devices.iter().map(|d| AssemblyMember { device: d.to_string() }).collect(),
+        };
+
+        assert_eq!(
+            hcdf.set_assembly(rear(&["0x04", "0x02"])),
+            Err(AssemblyError::ClaimedTwice {
+                device: "0x02".to_string(),
+                first: "front_pod".to_string(),
+                second: "rear_pod".to_string(),
+            })
+        );
+        assert_eq!(hcdf.assembly.len(), 1);
+
+        hcdf.set_assembly(rear(&["0x04"])).unwrap();
+        assert_eq!(hcdf.assembly.len(), 2);
+
+        // Replacing by name is not a duplicate
+        hcdf.set_assembly(rear(&["0x04", "0x05"])).unwrap();
+        assert_eq!(hcdf.assembly[1].member.len(), 2);
+
+        hcdf.assembly.push(rear(&[]));
+        assert_eq!(hcdf.validate_assemblies(), Err(AssemblyError::DuplicateName("rear_pod".to_string())));
+    }
+}
@@ -0,0 +1,282 @@
+//! Offline model bundles
+//!
+//! A bundle is a zip holding an HCDF document and the model files it
+//! references, for viewing on machines without internet access. The archive
+//! contains:
+//!
+//! - `manifest.json`: a [`BundleManifest`] mapping each model href in the
+//!   document to its path in the archive and its SHA256
+//! - the document itself (`hcdf.hcdf`)
+//! - the models, under `models/`
+//!
+//! Reading a bundle never fails because of a single model: models missing
+//! from the archive or whose content doesn't match the manifest's SHA are
+//! reported per model as [`BundleIssue`]s, and mismatched models are still
+//! returned so the scene shows something.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{Cursor, Read, Write};
+use thiserror::Error;
+use zip::write::SimpleFileOptions;
+
+use crate::cache::sha256_hex;
+use crate::hcdf::{Hcdf, ModelRef};
+
+/// Bundle format version written to the manifest
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// Archive path of the manifest
+pub const BUNDLE_MANIFEST: &str = "manifest.json";
+
+/// Archive path of the HCDF document
+const BUNDLE_HCDF: &str = "hcdf.hcdf";
+
+#[derive(Error, Debug)]
+pub enum BundleError {
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("Zip error: {0}")]
+    ZipError(#[from] zip::result::ZipError),
+    #[error("Invalid manifest: {0}")]
+    ManifestError(#[from] serde_json::Error),
+    #[error("Unsupported bundle version {0}")]
+    UnsupportedVersion(u32),
+    #[error("HCDF document is not valid UTF-8")]
+    InvalidHcdf,
+}
+
+/// `manifest.json` of a bundle
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundleManifest {
+    pub version: u32,
+    /// Archive path of the HCDF document
+    pub hcdf: String,
+    pub models: Vec<BundledModel>,
+}
+
+/// One model in a bundle
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundledModel {
+    /// Href as written in the HCDF document
+    pub href: String,
+    /// Path of the file in the archive
+    pub path: String,
+    /// SHA256 of the file
+    pub sha: String,
+}
+
+/// A model the bundle promised but couldn't deliver as described
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BundleIssue {
+    /// The manifest lists a file the archive doesn't contain
+    Missing { href: String, path: String },
+    /// The file's content doesn't match the manifest's SHA
+    ShaMismatch { href: String, expected: String, actual: String },
+}
+
+impl fmt::Display for BundleIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BundleIssue::Missing { href, path } => write!(f, "{}: {} missing from bundle", href, path),
+            BundleIssue::ShaMismatch { href, expected, actual } => {
+                write!(f, "{}: SHA mismatch (expected {}, got {})", href, expected, actual)
+            }
+        }
+    }
+}
+
+/// Contents of a bundle read with [`read_bundle`]
+#[derive(Debug, Clone)]
+pub struct ModelBundle {
+    /// HCDF document as stored, with its original hrefs
+    pub hcdf: String,
+    /// Models found in the archive, with their content
+    pub models: Vec<(BundledModel, Vec<u8>)>,
+    pub issues: Vec<BundleIssue>,
+}
+
+impl Hcdf {
+    /// Every model reference in the document: MCU and comp models and visuals
+    pub fn model_refs(&self) -> impl Iterator<Item = &ModelRef> {
+        let mcus = self.mcu.iter().flat_map(|m| m.model.iter().chain(m.visual.iter().filter_map(|v| v.model.as_ref())));
+        let comps = self.comp.iter().flat_map(|c| c.model.iter().chain(c.visual.iter().filter_map(|v| v.model.as_ref())));
+        mcus.chain(comps)
+    }
+
+    /// Replace model hrefs for which `rewrite` returns a new one
+    pub fn rewrite_model_hrefs(&mut self, rewrite: impl Fn(&str) -> Option<String>) {
+        let mcus = self.mcu.iter_mut().flat_map(|m| m.model.iter_mut().chain(m.visual.iter_mut().filter_map(|v| v.model.as_mut())));
+        let comps = self.comp.iter_mut().flat_map(|c| c.model.iter_mut().chain(c.visual.iter_mut().filter_map(|v| v.model.as_mut())));
+        for model in mcus.chain(comps) {
+            if let Some(href) = rewrite(&model.href) {
+                model.href = href;
+            }
+        }
+    }
+}
+
+/// Archive path for a model file: `models/{file name}`, numbered when two
+/// models share a file name
+pub fn bundle_model_path(file_name: &str, taken: &[BundledModel]) -> String {
+    let path = format!("models/{}", file_name);
+    if !taken.iter().any(|m| m.path == path) {
+        return path;
+    }
+    (2..)
+        .map(|n| format!("models/{}-{}", n, file_name))
+        .find(|p| !taken.iter().any(|m| &m.path == p))
+        .unwrap_or(path)
+}
+
+/// Write a bundle of `hcdf_xml` and the given models
+///
+/// Each model is stored at its `path`, once when several hrefs share a path;
+/// its `sha` is recorded as given.
+pub fn write_bundle(hcdf_xml: &str, models: &[(BundledModel, Vec<u8>)]) -> Result<Vec<u8>, BundleError> {
+    let manifest = BundleManifest {
+        version: BUNDLE_VERSION,
+        hcdf: BUNDLE_HCDF.to_string(),
+        models: models.iter().map(|(model, _)| model.clone()).collect(),
+    };
+
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    zip.start_file(BUNDLE_MANIFEST, options)?;
+    zip.write_all(serde_json::to_string_pretty(&manifest)?.as_bytes())?;
+    zip.start_file(BUNDLE_HCDF, options)?;
+    zip.write_all(hcdf_xml.as_bytes())?;
+    let mut written = HashSet::new();
+    for (model, content) in models {
+        if !written.insert(model.path.as_str()) {
+            continue;
+        }
+        zip.start_file(model.path.as_str(), options)?;
+        zip.write_all(content)?;
+    }
+    Ok(zip.finish()?.into_inner())
+}
+
+/// Read a bundle, checking every model against the manifest
+///
+/// Fails only if the archive, manifest or document can't be read.
+pub fn read_bundle(data: &[u8]) -> Result<ModelBundle, BundleError> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(data))?;
+    let mut read_file = |path: &str| -> Result<Option<Vec<u8>>, BundleError> {
+        let mut file = match archive.by_name(path) {
+            Ok(file) => file,
+            Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let mut content = Vec::new();
+        file.read_to_end(&mut content)?;
+        Ok(Some(content))
+    };
+
+    let manifest_data = read_file(BUNDLE_MANIFEST)?.ok_or(zip::result::ZipError::FileNotFound)?;
+    let manifest: BundleManifest = serde_json::from_slice(&manifest_data)?;
+    if manifest.version > BUNDLE_VERSION {
+        return Err(BundleError::UnsupportedVersion(manifest.version));
+    }
+    let hcdf_data = read_file(&manifest.hcdf)?.ok_or(zip::result::ZipError::FileNotFound)?;
+    let hcdf = String::from_utf8(hcdf_data).map_err(|_| BundleError::InvalidHcdf)?;
+
+    let mut models = Vec::new();
+    let mut issues = Vec::new();
+    // Models shared by several hrefs are stored once
+    let mut contents: HashMap<String, Option<Vec<u8>>> = HashMap::new();
+    for model in manifest.models {
+        if !contents.contains_key(&model.path) {
+            contents.insert(model.path.clone(), read_file(&model.path)?);
+        }
+        let Some(content) = contents[&model.path].clone() else {
+            issues.push(BundleIssue::Missing { href: model.href, path: model.path });
+            continue;
+        };
+        let actual = sha256_hex(&content);
+        if !actual.eq_ignore_ascii_case(&model.sha) {
+            issues.push(BundleIssue::ShaMismatch { href: model.href.clone(), expected: model.sha.clone(), actual });
+        }
+        models.push((model, content));
+    }
+
+    Ok(ModelBundle { hcdf, models, issues })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HCDF: &str = r#"<hcdf version="2.1">
+  <mcu name="flow" hwid="abc"><visual name="board"><model href="https://hcdf.cognipilot.org/models/flow.glb"/></visual></mcu>
+  <comp name="frame"><model href="models/frame.glb"/><visual name="arm"><model href="models/arm.glb"/></visual></comp>
+</hcdf>"#;
+
+    fn model(href: &str, path: &str, content: &[u8]) -> (BundledModel, Vec<u8>) {
+        let model = BundledModel { href: href.to_string(), path: path.to_string(), sha: sha256_hex(content) };
+        (model, content.to_vec())
+    }
+
+    #[test]
+    fn test_model_refs_and_rewrite() {
+        let mut hcdf = Hcdf::from_xml(HCDF).unwrap();
+        let hrefs: Vec<_> = hcdf.model_refs().map(|m| m.href.clone()).collect();
+        assert_eq!(hrefs, ["https://hcdf.cognipilot.org/models/flow.glb", "models/frame.glb", "models/arm.glb"]);
+
+        hcdf.rewrite_model_hrefs(|href| href.starts_with("models/").then(|| format!("bundle://{}", href)));
+        let hrefs: Vec<_> = hcdf.model_refs().map(|m| m.href.as_str()).collect();
+        assert_eq!(hrefs, ["https://hcdf.cognipilot.org/models/flow.glb", "bundle://models/frame.glb", "bundle://models/arm.glb"]);
+    }
+
+    #[test]
+    fn test_bundle_round_trip() {
+        let models = vec![
+            model("https://hcdf.cognipilot.org/models/flow.glb", "models/flow.glb", b"glTF-flow"),
+            model("models/frame.glb", "models/frame.glb", b"glTF-frame"),
+            // Same file under another href
+            model("./models/frame.glb", "models/frame.glb", b"glTF-frame"),
+        ];
+        let data = write_bundle(HCDF, &models).unwrap();
+
+        let bundle = read_bundle(&data).unwrap();
+        assert_eq!(bundle.hcdf, HCDF);
+        assert_eq!(bundle.models, models);
+        assert!(bundle.issues.is_empty());
+    }
+
+    #[test]
+    fn test_bad_models_are_reported_individually() {
+        let mut wrong = model("models/arm.glb", "models/arm.glb", b"glTF-arm");
+        wrong.0.sha = sha256_hex(b"glTF-arm-v2");
+        let mut data = write_bundle(HCDF, &[model("models/frame.glb", "models/frame.glb", b"glTF-frame"), wrong]).unwrap();
+
+        // Drop the frame from the archive by rewriting it without the file
+        let mut archive = zip::ZipArchive::new(Cursor::new(data.clone())).unwrap();
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for i in 0..archive.len() {
+            let file = archive.by_index(i).unwrap();
+            if file.name() != "models/frame.glb" {
+                zip.raw_copy_file(file).unwrap();
+            }
+        }
+        data = zip.finish().unwrap().into_inner();
+
+        let bundle = read_bundle(&data).unwrap();
+        assert_eq!(bundle.models.len(), 1);
+        assert_eq!(bundle.models[0].1, b"glTF-arm");
+        assert_eq!(bundle.issues.len(), 2);
+        assert_eq!(
+            bundle.issues[0],
+            BundleIssue::Missing { href: "models/frame.glb".to_string(), path: "models/frame.glb".to_string() }
+        );
+        assert!(matches!(&bundle.issues[1], BundleIssue::ShaMismatch { href, .. } if href == "models/arm.glb"));
+    }
+
+    #[test]
+    fn test_bundle_model_paths_are_unique() {
+        let (first, _) = model("a/body.glb", "models/body.glb", b"a");
+        assert_eq!(bundle_model_path("body.glb", &[]), "models/body.glb");
+        assert_eq!(bundle_model_path("body.glb", &[first]), "models/2-body.glb");
+    }
+}
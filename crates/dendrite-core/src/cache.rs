@@ -12,12 +12,32 @@
 //! Model files are stored with SHA-prefixed names: `models/{short_sha}-{name}.glb`
 //! This allows multiple versions of the same logical model to coexist and
 //! enables instant cache lookups by SHA.
+//!
+//! Models uploaded to replace a wrong fragment model are stored the same way
+//! and linked to a device hwid, or to a board for every device of that board,
+//! in the manifest's override tables.
+//!
+//! Entries record the SHA they were stored under, so staleness is a manifest
+//! lookup rather than a rehash: [`FragmentCache::is_fresh`] checks one entry
+//! and [`FragmentCache::prune_stale`] drops every entry whose source now
+//! serves a different SHA, along with models nothing refers to any more.
+//!
+//! Entries also keep the `ETag` and `Last-Modified` headers they were fetched
+//! with, so the next fetch can be conditional: a `304 Not Modified` answer
+//! only bumps `last_checked` ([`FragmentCache::mark_checked`]), and a file
+//! fetched again under a different ETag replaces the entry for its URL.
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use thiserror::Error;
 
+use crate::device::DeviceVisual;
+use crate::hcdf::{ModelRef, Visual};
+
+/// Name of the visual standing in for a device's model while an override is set
+pub const MODEL_OVERRIDE_VISUAL: &str = "override";
+
 #[derive(Error, Debug)]
 pub enum CacheError {
     #[error("IO error: {0}")]
@@ -47,6 +67,12 @@ pub struct CachedHcdf {
     pub path: String,
     /// When this was fetched (ISO 8601)
     pub fetched_at: String,
+    /// When the source last confirmed this is current (ISO 8601)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_checked: Option<String>,
+    /// Headers of the fetch, for conditional requests
+    #[serde(flatten)]
+    pub validators: HttpValidators,
     /// Model files referenced by this HCDF, with their SHAs
     pub models: HashMap<String, CachedModel>,
 }
@@ -64,6 +90,75 @@ pub struct CachedModel {
     pub name: String,
     /// Local file path (relative to cache directory): models/{short_sha}-{name}
     pub path: String,
+    /// When the source last confirmed this is current (ISO 8601)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_checked: Option<String>,
+    /// Headers of the fetch, for conditional requests
+    #[serde(flatten)]
+    pub validators: HttpValidators,
+}
+
+/// `ETag` and `Last-Modified` of a fetched file, sent back as `If-None-Match`
+/// and `If-Modified-Since` when fetching it again
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HttpValidators {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+}
+
+impl HttpValidators {
+    /// Whether the source sent neither header, so a fetch can't be conditional
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+
+    /// Whether `newer` was fetched under a different ETag
+    fn etag_changed(&self, newer: &HttpValidators) -> bool {
+        self.etag.is_some() && self.etag != newer.etag
+    }
+}
+
+/// A locally uploaded model that takes precedence over the fragment's
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelOverride {
+    /// SHA256 hash of the uploaded file, computed on upload
+    pub sha: String,
+    /// Uploaded file name (sanitized)
+    pub name: String,
+    /// Local file path (relative to cache directory): models/{short_sha}-{name}
+    pub path: String,
+    /// When this was uploaded (ISO 8601)
+    pub uploaded_at: String,
+}
+
+impl ModelOverride {
+    /// Device visual replacing the fragment's visuals
+    pub fn device_visual(&self) -> DeviceVisual {
+        DeviceVisual {
+            name: MODEL_OVERRIDE_VISUAL.to_string(),
+            toggle: None,
+            default_hidden: false,
+            pose: None,
+            model_path: Some(self.path.clone()),
+            model_sha: Some(self.sha.clone()),
+        }
+    }
+
+    /// HCDF visual referencing the local copy by href and hash
+    pub fn visual(&self) -> Visual {
+        Visual {
+            name: MODEL_OVERRIDE_VISUAL.to_string(),
+            toggle: None,
+            default_hidden: None,
+            pose: None,
+            model: Some(ModelRef {
+                href: self.path.clone(),
+                sha: Some(self.sha.clone()),
+            }),
+        }
+    }
 }
 
 /// The cache manifest tracks all cached HCDF files and their models
@@ -79,6 +174,12 @@ pub struct CacheManifest {
     /// Index from board/app to latest HCDF SHA (for fallback lookups)
     #[serde(default)]
     pub latest_by_board_app: HashMap<String, String>, // "{board}/{app}" -> SHA
+    /// Uploaded model overrides keyed by device hwid
+    #[serde(default)]
+    pub device_model_overrides: HashMap<String, ModelOverride>,
+    /// Uploaded model overrides for every device of a board, keyed by board
+    #[serde(default)]
+    pub board_model_overrides: HashMap<String, ModelOverride>,
 }
 
 fn default_version() -> String {
@@ -93,6 +194,8 @@ impl CacheManifest {
             hcdf: HashMap::new(),
             models_by_sha: HashMap::new(),
             latest_by_board_app: HashMap::new(),
+            device_model_overrides: HashMap::new(),
+            board_model_overrides: HashMap::new(),
         }
     }
 
@@ -174,6 +277,13 @@ impl CacheManifest {
     pub fn get_hcdf_path(&self, sha: &str) -> Option<String> {
         self.hcdf.get(sha).map(|e| e.path.clone())
     }
+
+    /// Get the model override for a device, falling back to its board's
+    pub fn get_model_override(&self, hwid: &str, board: Option<&str>) -> Option<&ModelOverride> {
+        self.device_model_overrides
+            .get(hwid)
+            .or_else(|| board.and_then(|b| self.board_model_overrides.get(b)))
+    }
 }
 
 /// Cache directory manager
@@ -246,6 +356,8 @@ impl FragmentCache {
     ///
     /// Files are stored as: `{board}/{app}/{short_sha}-{app}.hcdf`
     /// with a symlink: `{board}/{app}/{app}.hcdf` -> `{short_sha}-{app}.hcdf`
+    ///
+    /// An entry cached for `url` under a different ETag is removed.
     pub fn store_hcdf(
         &mut self,
         url: &str,
@@ -253,9 +365,24 @@ impl FragmentCache {
         board: &str,
         app: &str,
         content: &[u8],
+        validators: HttpValidators,
     ) -> Result<PathBuf, CacheError> {
         let short_sha = Self::short_sha(sha);
 
+        let superseded: Vec<String> = self
+            .manifest
+            .hcdf
+            .values()
+            .filter(|e| e.url == url && e.sha != sha && e.validators.etag_changed(&validators))
+            .map(|e| e.sha.clone())
+            .collect();
+        for old in superseded {
+            if let Some(entry) = self.manifest.hcdf.remove(&old) {
+                remove_cached_file(&self.base_dir.join(&entry.path))?;
+            }
+            self.manifest.latest_by_board_app.retain(|_, latest| *latest != old);
+        }
+
         // Create directory structure: {board}/{app}/
         let dir = self.base_dir.join(board).join(app);
         std::fs::create_dir_all(&dir)?;
@@ -282,13 +409,16 @@ impl FragmentCache {
         }
 
         let relative_path = format!("{}/{}/{}", board, app, sha_filename);
+        let now = chrono::Utc::now().to_rfc3339();
         let entry = CachedHcdf {
             url: url.to_string(),
             sha: sha.to_string(),
             board: board.to_string(),
             app: app.to_string(),
             path: relative_path,
-            fetched_at: chrono::Utc::now().to_rfc3339(),
+            fetched_at: now.clone(),
+            last_checked: Some(now),
+            validators,
             models: HashMap::new(),
         };
 
@@ -301,6 +431,9 @@ impl FragmentCache {
     /// Store a model file in the cache
     /// If model_name already has a SHA prefix (8 hex chars followed by dash), use as-is
     /// Otherwise store as: models/{short_sha}-{name}
+    ///
+    /// Models cached for `href` under a different ETag are dropped from every
+    /// HCDF entry, and their files removed once nothing refers to them.
     pub fn store_model(
         &mut self,
         hcdf_sha: &str,
@@ -308,7 +441,27 @@ impl FragmentCache {
         model_sha: &str,
         href: &str,
         content: &[u8],
+        validators: HttpValidators,
     ) -> Result<PathBuf, CacheError> {
+        let mut superseded = HashSet::new();
+        for entry in self.manifest.hcdf.values_mut() {
+            entry.models.retain(|_, m| {
+                let stale = m.href == href && m.sha != model_sha && m.validators.etag_changed(&validators);
+                if stale {
+                    superseded.insert(m.sha.clone());
+                }
+                !stale
+            });
+        }
+        for old in superseded {
+            let referenced = self.manifest.hcdf.values().any(|e| e.models.values().any(|m| m.sha == old));
+            if !referenced {
+                if let Some(path) = self.manifest.models_by_sha.remove(&old) {
+                    remove_cached_file(&self.base_dir.join(path))?;
+                }
+            }
+        }
+
         // Create flat models directory
         let models_dir = self.models_dir();
         std::fs::create_dir_all(&models_dir)?;
@@ -345,6 +498,8 @@ impl FragmentCache {
                     short_sha: short_sha.clone(),
                     name: model_name.to_string(),
                     path: relative_path,
+                    last_checked: Some(chrono::Utc::now().to_rfc3339()),
+                    validators,
                 },
             );
         }
@@ -408,6 +563,256 @@ impl FragmentCache {
     pub fn get_latest_hcdf(&self, board: &str, app: &str) -> Option<&CachedHcdf> {
         self.manifest.get_latest_hcdf(board, app)
     }
+
+    /// Store an uploaded model as the override for a device, and for every
+    /// device of `board` when given
+    ///
+    /// The file is stored as `models/{short_sha}-{name}` like fetched models.
+    pub fn store_model_override(
+        &mut self,
+        hwid: &str,
+        board: Option<&str>,
+        file_name: &str,
+        content: &[u8],
+    ) -> Result<ModelOverride, CacheError> {
+        let models_dir = self.models_dir();
+        std::fs::create_dir_all(&models_dir)?;
+
+        let sha = sha256_hex(content);
+        let name = sanitize_model_name(file_name);
+        let cached_name = format!("{}-{}", Self::short_sha(&sha), name);
+        std::fs::write(models_dir.join(&cached_name), content)?;
+
+        let model = ModelOverride {
+            sha: sha.clone(),
+            name,
+            path: format!("models/{}", cached_name),
+            uploaded_at: chrono::Utc::now().to_rfc3339(),
+        };
+        self.manifest.models_by_sha.insert(sha, model.path.clone());
+        self.manifest.device_model_overrides.insert(hwid.to_string(), model.clone());
+        if let Some(board) = board {
+            self.manifest.board_model_overrides.insert(board.to_string(), model.clone());
+        }
+        self.manifest.save(&self.manifest_path)?;
+
+        Ok(model)
+    }
+
+    /// Remove a device's model override, and its board's when given
+    ///
+    /// The model file stays in the cache. Returns false if there was nothing
+    /// to remove.
+    pub fn remove_model_override(&mut self, hwid: &str, board: Option<&str>) -> Result<bool, CacheError> {
+        let mut removed = self.manifest.device_model_overrides.remove(hwid).is_some();
+        if let Some(board) = board {
+            removed |= self.manifest.board_model_overrides.remove(board).is_some();
+        }
+        if removed {
+            self.manifest.save(&self.manifest_path)?;
+        }
+        Ok(removed)
+    }
+
+    /// Get the model override for a device, falling back to its board's
+    pub fn model_override(&self, hwid: &str, board: Option<&str>) -> Option<&ModelOverride> {
+        self.manifest.get_model_override(hwid, board)
+    }
+
+    /// Stop treating the cached HCDF of `board`/`app` as the latest
+    ///
+    /// `app` may be `*` for every app of the board. The SHA-named files stay
+    /// but the `{app}.hcdf` links go, so the next lookup for the pair goes to
+    /// the server instead of the cache. Returns false if nothing was cached
+    /// for it.
+    pub fn forget_board_app(&mut self, board: &str, app: &str) -> Result<bool, CacheError> {
+        let forgotten: Vec<String> = self
+            .manifest
+            .latest_by_board_app
+            .keys()
+            .filter(|key| {
+                let (b, a) = key.split_once('/').unwrap_or((key.as_str(), ""));
+                b.eq_ignore_ascii_case(board) && (app == "*" || a.eq_ignore_ascii_case(app))
+            })
+            .cloned()
+            .collect();
+        for key in &forgotten {
+            self.manifest.latest_by_board_app.remove(key);
+            if let Some((b, a)) = key.split_once('/') {
+                remove_cached_file(&self.base_dir.join(b).join(a).join(format!("{}.hcdf", a)))?;
+            }
+        }
+        if !forgotten.is_empty() {
+            self.manifest.save(&self.manifest_path)?;
+        }
+        Ok(!forgotten.is_empty())
+    }
+
+    /// Latest HCDF cached for `url` whose file still exists
+    pub fn hcdf_for_url(&self, url: &str) -> Option<&CachedHcdf> {
+        self.manifest
+            .hcdf
+            .values()
+            .filter(|e| e.url == url && self.base_dir.join(&e.path).exists())
+            .max_by(|a, b| a.fetched_at.cmp(&b.fetched_at))
+    }
+
+    /// Model cached for `href` whose file still exists
+    pub fn model_for_href(&self, href: &str) -> Option<&CachedModel> {
+        self.manifest
+            .hcdf
+            .values()
+            .flat_map(|e| e.models.values())
+            .find(|m| m.href == href && self.base_dir.join(&m.path).exists())
+    }
+
+    /// Cached file for a model reference: by SHA when it has one, then by href
+    ///
+    /// Hrefs of uploaded overrides (`models/...`) are paths in the cache
+    /// directory and are found directly.
+    pub fn model_file(&self, href: &str, sha: Option<&str>) -> Option<PathBuf> {
+        let by_sha = sha.and_then(|sha| self.get_cached_model_path(sha)).filter(|p| p.exists());
+        let by_href = || self.model_for_href(href).map(|m| self.base_dir.join(&m.path));
+        let local = || {
+            let relative = Path::new(href);
+            let inside = href.starts_with("models/")
+                && relative.components().all(|c| matches!(c, std::path::Component::Normal(_)));
+            Some(self.base_dir.join(relative)).filter(|p| inside && p.is_file())
+        };
+        by_sha.or_else(by_href).or_else(local)
+    }
+
+    /// Record that the source of `key` (an HCDF URL or a model href) answered
+    /// `304 Not Modified`; false if nothing is cached for it
+    pub fn mark_checked(&mut self, key: &str) -> Result<bool, CacheError> {
+        let now = chrono::Utc::now().to_rfc3339();
+        let mut found = false;
+        for entry in self.manifest.hcdf.values_mut() {
+            if entry.url == key {
+                entry.last_checked = Some(now.clone());
+                found = true;
+            }
+            for model in entry.models.values_mut().filter(|m| m.href == key) {
+                model.last_checked = Some(now.clone());
+                found = true;
+            }
+        }
+        if found {
+            self.manifest.save(&self.manifest_path)?;
+        }
+        Ok(found)
+    }
+
+    /// Check the cache holds `expected_sha` for `key` without reading the file
+    ///
+    /// `key` is an HCDF URL, a `{board}/{app}` pair or a model href. The
+    /// entry's file must still exist.
+    pub fn is_fresh(&self, key: &str, expected_sha: &str) -> bool {
+        let latest = self.manifest.latest_by_board_app.get(key).and_then(|sha| self.manifest.hcdf.get(sha));
+        let hcdfs = self.manifest.hcdf.values().filter(|e| e.url == key).chain(latest).map(|e| (&e.sha, &e.path));
+        let models = self
+            .manifest
+            .hcdf
+            .values()
+            .flat_map(|e| e.models.values())
+            .filter(|m| m.href == key)
+            .map(|m| (&m.sha, &m.path));
+
+        hcdfs
+            .chain(models)
+            .any(|(sha, path)| sha.eq_ignore_ascii_case(expected_sha) && self.base_dir.join(path).exists())
+    }
+
+    /// Drop entries whose recorded SHA no longer matches `manifest`
+    ///
+    /// An HCDF is stale when `manifest` has its URL under a different SHA,
+    /// and a model when `manifest` has its href under a different SHA; URLs
+    /// and hrefs `manifest` doesn't know are kept. Model files that no
+    /// remaining HCDF or override refers to are removed too. Returns the
+    /// number of entries removed.
+    pub fn prune_stale(&mut self, manifest: &CacheManifest) -> Result<usize, CacheError> {
+        let mut current_hcdfs: HashMap<&str, HashSet<&str>> = HashMap::new();
+        let mut current_models: HashMap<&str, HashSet<&str>> = HashMap::new();
+        for entry in manifest.hcdf.values() {
+            current_hcdfs.entry(entry.url.as_str()).or_default().insert(entry.sha.as_str());
+            for model in entry.models.values() {
+                current_models.entry(model.href.as_str()).or_default().insert(model.sha.as_str());
+            }
+        }
+        let is_stale = |current: &HashMap<&str, HashSet<&str>>, key: &str, sha: &str| {
+            current.get(key).is_some_and(|shas| !shas.contains(sha))
+        };
+
+        let mut removed = 0;
+
+        let stale_hcdfs: Vec<String> = self
+            .manifest
+            .hcdf
+            .values()
+            .filter(|e| is_stale(&current_hcdfs, e.url.as_str(), e.sha.as_str()))
+            .map(|e| e.sha.clone())
+            .collect();
+        for sha in stale_hcdfs {
+            if let Some(entry) = self.manifest.hcdf.remove(&sha) {
+                remove_cached_file(&self.base_dir.join(&entry.path))?;
+                removed += 1;
+            }
+            self.manifest.latest_by_board_app.retain(|_, latest| *latest != sha);
+        }
+
+        for entry in self.manifest.hcdf.values_mut() {
+            let before = entry.models.len();
+            entry.models.retain(|_, m| !is_stale(&current_models, m.href.as_str(), m.sha.as_str()));
+            removed += before - entry.models.len();
+        }
+
+        // Models orphaned by the removals above, or by earlier re-hosting
+        let referenced: HashSet<String> = self
+            .manifest
+            .hcdf
+            .values()
+            .flat_map(|e| e.models.values().map(|m| m.sha.clone()))
+            .chain(self.manifest.device_model_overrides.values().map(|o| o.sha.clone()))
+            .chain(self.manifest.board_model_overrides.values().map(|o| o.sha.clone()))
+            .collect();
+        let orphans: Vec<String> =
+            self.manifest.models_by_sha.keys().filter(|sha| !referenced.contains(*sha)).cloned().collect();
+        for sha in orphans {
+            if let Some(path) = self.manifest.models_by_sha.remove(&sha) {
+                remove_cached_file(&self.base_dir.join(path))?;
+                removed += 1;
+            }
+        }
+
+        if removed > 0 {
+            self.manifest.save(&self.manifest_path)?;
+        }
+        Ok(removed)
+    }
+}
+
+/// Remove a cache file that may already be gone
+fn remove_cached_file(path: &Path) -> Result<(), CacheError> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Reduce an uploaded file name to a safe `.glb` file name
+fn sanitize_model_name(file_name: &str) -> String {
+    let base = file_name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let stem = base.strip_suffix(".glb").unwrap_or(base);
+    let stem: String = stem
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' { c } else { '_' })
+        .collect();
+    let stem = stem.trim_start_matches('.');
+    if stem.is_empty() {
+        "model.glb".to_string()
+    } else {
+        format!("{}.glb", stem)
+    }
 }
 
 /// Compute SHA256 hash of data and return as hex string
@@ -435,6 +840,8 @@ mod tests {
             app: "default".to_string(),
             path: "abc123.hcdf".to_string(),
             fetched_at: "2026-01-10T12:00:00Z".to_string(),
+            last_checked: None,
+            validators: HttpValidators::default(),
             models: HashMap::new(),
         };
 
@@ -452,7 +859,8 @@ mod tests {
         // Store an HCDF
         let content = b"<hcdf>test</hcdf>";
         let sha = sha256_hex(content);
-        cache.store_hcdf("https://example.com/test.hcdf", &sha, "test_board", "test_app", content).unwrap();
+        let url = "https://example.com/test.hcdf";
+        cache.store_hcdf(url, &sha, "test_board", "test_app", content, HttpValidators::default()).unwrap();
 
         assert!(cache.has_hcdf(&sha));
 
@@ -463,6 +871,133 @@ mod tests {
         // Read it back by board/app
         let read_content2 = cache.read_hcdf_by_board_app("test_board", "test_app").unwrap();
         assert_eq!(read_content2, "<hcdf>test</hcdf>");
+
+        // Forgetting the board's apps leaves the file, reachable by SHA only
+        assert!(!cache.forget_board_app("other_board", "*").unwrap());
+        assert!(cache.forget_board_app("TEST_BOARD", "*").unwrap());
+        assert!(cache.read_hcdf_by_board_app("test_board", "test_app").is_err());
+        assert!(cache.has_hcdf(&sha));
+    }
+
+    #[test]
+    fn test_model_override_survives_reload() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut cache = FragmentCache::new(temp_dir.path().to_path_buf()).unwrap();
+
+        let content = b"glTF-board-only";
+        let board = cache.store_model_override("0x1", Some("mr_mcxn_t1"), "../Fixed Board.glb", content).unwrap();
+        assert_eq!(board.sha, sha256_hex(content));
+        assert_eq!(board.path, format!("models/{}-Fixed_Board.glb", &board.sha[..8]));
+        assert!(cache.has_model(&board.sha));
+
+        // A device override beats the board's; other devices of the board get the board's
+        let device = cache.store_model_override("0x2", None, "spare.glb", b"glTF-device").unwrap();
+        let reloaded = FragmentCache::new(temp_dir.path().to_path_buf()).unwrap();
+        assert_eq!(reloaded.model_override("0x2", Some("mr_mcxn_t1")), Some(&device));
+        assert_eq!(reloaded.model_override("0x3", Some("mr_mcxn_t1")), Some(&board));
+        assert_eq!(reloaded.model_override("0x3", Some("other")), None);
+        assert_eq!(std::fs::read(temp_dir.path().join(&board.path)).unwrap(), content);
+
+        // Removing only the device link leaves the board override in place
+        let mut cache = reloaded;
+        assert!(cache.remove_model_override("0x1", None).unwrap());
+        assert_eq!(cache.model_override("0x1", Some("mr_mcxn_t1")), Some(&board));
+        assert!(cache.remove_model_override("0x1", Some("mr_mcxn_t1")).unwrap());
+        assert_eq!(cache.model_override("0x1", Some("mr_mcxn_t1")), None);
+        assert!(!cache.remove_model_override("0x1", Some("mr_mcxn_t1")).unwrap());
+    }
+
+    #[test]
+    fn test_prune_stale_after_rehost() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut cache = FragmentCache::new(temp_dir.path().to_path_buf()).unwrap();
+
+        let url = "https://example.com/spinali.hcdf";
+        let content = b"<hcdf>v1</hcdf>";
+        let sha = sha256_hex(content);
+        let hcdf_path = cache.store_hcdf(url, &sha, "spinali", "default", content, HttpValidators::default()).unwrap();
+        let model_sha = sha256_hex(b"glTF-v1");
+        let model_path = cache
+            .store_model(&sha, "board.glb", &model_sha, "models/board.glb", b"glTF-v1", HttpValidators::default())
+            .unwrap();
+
+        assert!(cache.is_fresh(url, &sha));
+        assert!(cache.is_fresh("spinali/default", &sha));
+        assert!(cache.is_fresh("models/board.glb", &model_sha));
+        assert!(!cache.is_fresh(url, &sha256_hex(b"<hcdf>v2</hcdf>")));
+
+        // Nothing to drop while the manifest agrees
+        let mut current = cache.manifest.clone();
+        assert_eq!(cache.prune_stale(&current).unwrap(), 0);
+
+        // The URL is re-hosted with new content
+        let mut entry = current.hcdf.remove(&sha).unwrap();
+        entry.sha = sha256_hex(b"<hcdf>v2</hcdf>");
+        entry.models.clear();
+        current.hcdf.insert(entry.sha.clone(), entry);
+
+        assert_eq!(cache.prune_stale(&current).unwrap(), 2);
+        assert!(!cache.is_fresh(url, &sha));
+        assert!(!cache.has_hcdf(&sha));
+        assert!(!cache.has_model(&model_sha));
+        assert!(!hcdf_path.exists());
+        assert!(!model_path.exists());
+        assert_eq!(cache.manifest.get_latest_sha("spinali", "default"), None);
+
+        let reloaded = FragmentCache::new(temp_dir.path().to_path_buf()).unwrap();
+        assert!(reloaded.manifest.hcdf.is_empty());
+        assert!(reloaded.manifest.models_by_sha.is_empty());
+    }
+
+    #[test]
+    fn test_changed_etag_replaces_entry() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut cache = FragmentCache::new(temp_dir.path().to_path_buf()).unwrap();
+        let url = "https://example.com/spinali.hcdf";
+        let etag = |tag: &str| HttpValidators { etag: Some(tag.to_string()), last_modified: None };
+
+        let v1 = sha256_hex(b"<hcdf>v1</hcdf>");
+        cache.store_hcdf(url, &v1, "spinali", "default", b"<hcdf>v1</hcdf>", etag("\"1\"")).unwrap();
+        let model_v1 = sha256_hex(b"glTF-v1");
+        cache.store_model(&v1, "board.glb", &model_v1, "models/board.glb", b"glTF-v1", etag("\"m1\"")).unwrap();
+        assert_eq!(cache.hcdf_for_url(url).unwrap().validators, etag("\"1\""));
+        assert!(cache.mark_checked(url).unwrap());
+        assert!(!cache.mark_checked("https://example.com/other.hcdf").unwrap());
+
+        let v2 = sha256_hex(b"<hcdf>v2</hcdf>");
+        cache.store_hcdf(url, &v2, "spinali", "default", b"<hcdf>v2</hcdf>", etag("\"2\"")).unwrap();
+        assert!(!cache.has_hcdf(&v1));
+        assert_eq!(cache.hcdf_for_url(url).unwrap().sha, v2);
+        assert_eq!(cache.manifest.get_latest_sha("spinali", "default"), Some(v2.as_str()));
+
+        let model_v2 = sha256_hex(b"glTF-v2");
+        cache.store_model(&v2, "board.glb", &model_v1, "models/board.glb", b"glTF-v1", etag("\"m1\"")).unwrap();
+        cache.store_model(&v2, "board.glb", &model_v2, "models/board.glb", b"glTF-v2", etag("\"m2\"")).unwrap();
+        assert!(!cache.has_model(&model_v1));
+        assert_eq!(cache.model_for_href("models/board.glb").unwrap().sha, model_v2);
+
+        // Validators survive a reload
+        let reloaded = FragmentCache::new(temp_dir.path().to_path_buf()).unwrap();
+        assert_eq!(reloaded.model_for_href("models/board.glb").unwrap().validators, etag("\"m2\""));
+    }
+
+    #[test]
+    fn test_model_file_by_sha_href_or_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut cache = FragmentCache::new(temp_dir.path().to_path_buf()).unwrap();
+        let sha = sha256_hex(b"<hcdf>v1</hcdf>");
+        cache.store_hcdf("https://example.com/spinali.hcdf", &sha, "spinali", "default", b"<hcdf>v1</hcdf>", HttpValidators::default()).unwrap();
+        let href = "https://example.com/models/board.glb";
+        let model_sha = sha256_hex(b"glTF-v1");
+        let stored = cache.store_model(&sha, "board.glb", &model_sha, href, b"glTF-v1", HttpValidators::default()).unwrap();
+
+        assert_eq!(cache.model_file("elsewhere/board.glb", Some(&model_sha)), Some(stored.clone()));
+        assert_eq!(cache.model_file(href, None), Some(stored));
+        assert_eq!(cache.model_file("https://example.com/models/other.glb", None), None);
+
+        let upload = cache.store_model_override("0x1", None, "fixed.glb", b"glTF-fixed").unwrap();
+        assert_eq!(cache.model_file(&upload.path, None), Some(temp_dir.path().join(&upload.path)));
+        assert_eq!(cache.model_file("models/../manifest.json", None), None);
     }
 
     #[test]
@@ -1,11 +1,17 @@
 //! Device types for tracking discovered hardware
 
+use crate::cache::sha256_hex;
 use crate::firmware::FirmwareStatus;
+use crate::heartbeat::HeartbeatMethod;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::net::IpAddr;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV6};
 use uuid::Uuid;
 
+const TEMPORARY_ID_PREFIX: &str = "temp-";
+const MAC_ID_PREFIX: &str = "mac-";
+const BOARD_ID_PREFIX: &str = "board-";
+
 /// Unique identifier for a device, derived from hardware ID
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct DeviceId(pub String);
@@ -23,7 +29,41 @@ impl DeviceId {
 
     /// Generate a temporary ID for devices where hardware ID is unknown
     pub fn temporary() -> Self {
-        Self(format!("temp-{}", Uuid::new_v4()))
+        Self(format!("{}{}", TEMPORARY_ID_PREFIX, Uuid::new_v4()))
+    }
+
+    /// Stable ID from whatever identifies a device, best first
+    ///
+    /// - A hardware ID is used as is, so ids match the `hwid` in HCDF files.
+    /// - Without one, the MAC address is hashed: `mac-` and the first 16 hex
+    ///   digits of the SHA-256 of its lowercase hex digits, so
+    ///   `AA:BB:CC:00:11:22` and `aa-bb-cc-00-11-22` give the same id.
+    /// - Without either, the board name and the device's SMP address give
+    ///   `board-<board>@<addr>`, so two boards of the same type on routed
+    ///   hosts (where no MAC resolves) stay apart while their addresses do.
+    /// - With nothing at all the id is `board-unknown@<addr>`; callers that
+    ///   know nothing use [`DeviceId::temporary`].
+    ///
+    /// Empty values count as absent. The result depends only on the
+    /// arguments, so a device keeps its id across daemon restarts.
+    pub fn derive(board: Option<&str>, mac: Option<&str>, hwid: Option<&str>, addr: SocketAddr) -> Self {
+        let present = |value: Option<&str>| value.map(str::trim).filter(|v| !v.is_empty());
+        if let Some(hwid) = present(hwid) {
+            return Self::from_hwid(hwid);
+        }
+        let mac_digits = present(mac).map(|mac| {
+            mac.chars().filter(char::is_ascii_hexdigit).map(|c| c.to_ascii_lowercase()).collect::<String>()
+        });
+        if let Some(digits) = mac_digits.filter(|d| !d.is_empty()) {
+            return Self(format!("{}{}", MAC_ID_PREFIX, &sha256_hex(digits.as_bytes())[..16]));
+        }
+        Self(format!("{}{}@{}", BOARD_ID_PREFIX, present(board).unwrap_or("unknown"), addr))
+    }
+
+    /// Whether this ID stands in for a hardware ID that wasn't known (a
+    /// temporary, MAC or board ID); it is replaced once the hardware ID is
+    pub fn is_derived(&self) -> bool {
+        [TEMPORARY_ID_PREFIX, MAC_ID_PREFIX, BOARD_ID_PREFIX].iter().any(|p| self.0.starts_with(p))
     }
 
     pub fn as_str(&self) -> &str {
@@ -57,6 +97,34 @@ impl Default for DeviceStatus {
     }
 }
 
+/// Fleet-level status shown by indicators: device coloring in the UI and
+/// hardware status LEDs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndicatorStatus {
+    /// Running the latest firmware
+    UpToDate,
+    /// Newer firmware is available
+    UpdateAvailable,
+    /// Device needs attention (offline)
+    Attention,
+    /// Firmware status not known yet
+    Unknown,
+}
+
+impl IndicatorStatus {
+    /// Summarise a device's state; offline wins over an outdated firmware,
+    /// which wins over up to date
+    pub fn from_status(status: DeviceStatus, firmware: &FirmwareStatus) -> Self {
+        match (status, firmware) {
+            (DeviceStatus::Offline, _) => Self::Attention,
+            (_, FirmwareStatus::UpdateAvailable { .. }) => Self::UpdateAvailable,
+            (_, FirmwareStatus::UpToDate) => Self::UpToDate,
+            _ => Self::Unknown,
+        }
+    }
+}
+
 /// Information about firmware running on a device
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct FirmwareInfo {
@@ -81,6 +149,9 @@ pub struct FirmwareInfo {
 pub struct DiscoveryInfo {
     /// IP address of the device
     pub ip: IpAddr,
+    /// Zone (interface index) of a link-local IPv6 `ip`, as in `fe80::1%3`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope_id: Option<u32>,
     /// MCUmgr port (typically 1337)
     pub port: u16,
     /// Physical port on parent switch (if known)
@@ -93,6 +164,77 @@ pub struct DiscoveryInfo {
     pub last_seen: DateTime<Utc>,
     /// How the device was discovered
     pub discovery_method: DiscoveryMethod,
+    /// Heartbeat method override (None = auto-select)
+    #[serde(default)]
+    pub heartbeat_method: Option<HeartbeatMethod>,
+    /// Bus and node id of a device on a CAN bus; such devices have no IP
+    /// (`ip` is unspecified and `port` is 0)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub can: Option<CanAddress>,
+    /// Hostname the device advertised over mDNS (e.g., "jetson-01.local")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+}
+
+impl DiscoveryInfo {
+    /// Address of the device's MCUmgr server, scoped to its interface for a
+    /// link-local IPv6 address
+    pub fn socket_addr(&self) -> SocketAddr {
+        self.scoped_addr(self.port)
+    }
+
+    /// `port` on the device, scoped like [`socket_addr`](Self::socket_addr)
+    pub fn scoped_addr(&self, port: u16) -> SocketAddr {
+        match self.ip {
+            IpAddr::V6(ip) => SocketAddr::V6(SocketAddrV6::new(ip, port, 0, self.scope_id.unwrap_or(0))),
+            IpAddr::V4(_) => SocketAddr::new(self.ip, port),
+        }
+    }
+
+    /// The address with its zone, e.g. "fe80::1%3" (just the address without one)
+    pub fn scoped_ip(&self) -> String {
+        match (self.ip, self.scope_id) {
+            (IpAddr::V6(ip), Some(scope)) if scope != 0 => format!("{}%{}", ip, scope),
+            _ => self.ip.to_string(),
+        }
+    }
+
+    /// Parse what [`scoped_ip`](Self::scoped_ip) writes: an address, with a
+    /// numeric zone after `%` for IPv6
+    pub fn parse_scoped_ip(text: &str) -> Option<(IpAddr, Option<u32>)> {
+        match text.split_once('%') {
+            Some((ip, scope)) => {
+                let ip: std::net::Ipv6Addr = ip.parse().ok()?;
+                Some((IpAddr::V6(ip), Some(scope.parse().ok()?)))
+            }
+            None => Some((text.parse().ok()?, None)),
+        }
+    }
+}
+
+/// Where a node sits on a CAN bus
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CanAddress {
+    /// SocketCAN interface the bus is attached to (e.g. "can0")
+    pub bus: String,
+    /// Node id on the bus
+    pub node_id: u8,
+}
+
+impl CanAddress {
+    /// Registry ID of the node, which override rules match as its hwid
+    pub fn device_id(&self) -> DeviceId {
+        DeviceId(format!("can:{}:{}", self.bus, self.node_id))
+    }
+
+    /// Parse a registry ID of the form `can:<bus>:<node id>`
+    pub fn from_device_id(id: &str) -> Option<Self> {
+        let (bus, node_id) = id.strip_prefix("can:")?.rsplit_once(':')?;
+        if bus.is_empty() {
+            return None;
+        }
+        Some(Self { bus: bus.to_string(), node_id: node_id.parse().ok()? })
+    }
 }
 
 /// How a device was discovered
@@ -107,6 +249,10 @@ pub enum DiscoveryMethod {
     Probe,
     /// Manually configured
     Manual,
+    /// Answered a CAN identification request
+    Can,
+    /// Advertised itself over mDNS/DNS-SD
+    Mdns,
 }
 
 /// Complete device information
@@ -132,6 +278,9 @@ pub struct DeviceVisual {
     /// Toggle group name for visibility control (e.g., "case")
     #[serde(default)]
     pub toggle: Option<String>,
+    /// Whether the toggle group starts hidden when first loaded
+    #[serde(default)]
+    pub default_hidden: bool,
     /// Pose offset: (x, y, z, roll, pitch, yaw) in meters/radians
     #[serde(default)]
     pub pose: Option<[f64; 6]>,
@@ -151,9 +300,13 @@ pub struct DeviceFrame {
     /// Human-readable description
     #[serde(default)]
     pub description: Option<String>,
-    /// Pose offset: (x, y, z, roll, pitch, yaw) in meters/radians
+    /// Pose offset: (x, y, z, roll, pitch, yaw) in meters/radians, relative
+    /// to `parent` if set, otherwise to the device origin
     #[serde(default)]
     pub pose: Option<[f64; 6]>,
+    /// Frame this one is positioned relative to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
 }
 
 /// Geometry for visualization (box, cylinder, sphere, cone, frustum)
@@ -183,6 +336,9 @@ pub struct DevicePort {
     pub name: String,
     /// Port type (e.g., "ethernet", "CAN", "SPI")
     pub port_type: String,
+    /// Speed, bitrate or baud rate from the HCDF capabilities (e.g., "100 Mbps")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speed: Option<String>,
     /// Pose offset: (x, y, z, roll, pitch, yaw) in meters/radians
     #[serde(default)]
     pub pose: Option<[f64; 6]>,
@@ -195,6 +351,16 @@ pub struct DevicePort {
     /// GLTF mesh node name within the visual (e.g., "port_eth0")
     #[serde(default)]
     pub mesh_name: Option<String>,
+    /// Pose from the device's HCDF before a local edit (None if unedited)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_pose: Option<[f64; 6]>,
+}
+
+impl DevicePort {
+    /// Whether the pose has been edited away from the device's HCDF
+    pub fn is_pose_edited(&self) -> bool {
+        self.original_pose.is_some()
+    }
 }
 
 /// Axis alignment for sensor driver transforms
@@ -259,6 +425,12 @@ pub struct DeviceSensor {
     /// Driver name (e.g., "icm45686", "bmm350")
     #[serde(default)]
     pub driver: Option<String>,
+    /// Human-readable driver name from the driver registry (e.g., "Bosch BMI088 (IMU)")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub driver_label: Option<String>,
+    /// Driver datasheet URL from the driver registry
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub datasheet: Option<String>,
     /// Pose offset: (x, y, z, roll, pitch, yaw) in meters/radians
     #[serde(default)]
     pub pose: Option<[f64; 6]>,
@@ -285,6 +457,31 @@ impl Default for DeviceInfo {
     }
 }
 
+/// Limits on where a device can be placed while its pose is edited
+///
+/// Stored on the device's MCU in the HCDF (`keep_above_ground`, `stack_on`).
+/// Only edits are constrained; a loaded pose that breaks a constraint is
+/// reported, not moved.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PoseConstraint {
+    /// Keep the bounding box above the ground plane (Z = 0)
+    #[serde(default)]
+    pub keep_above_ground: bool,
+    /// Rest on the upper face of this device's bounding box, sliding freely across it
+    #[serde(default)]
+    pub stack_on: Option<DeviceId>,
+}
+
+impl PoseConstraint {
+    pub fn is_none(&self) -> bool {
+        !self.keep_above_ground && self.stack_on.is_none()
+    }
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
 /// A discovered device in the Dendrite system
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Device {
@@ -314,6 +511,15 @@ pub struct Device {
     pub model_path: Option<String>,
     /// Pose relative to parent (x, y, z, roll, pitch, yaw)
     pub pose: Option<[f64; 6]>,
+    /// Pose was suggested by the layout heuristic, not placed by a user
+    #[serde(default)]
+    pub pose_provisional: bool,
+    /// Placement limits applied while the pose is edited
+    #[serde(default)]
+    pub constraint: PoseConstraint,
+    /// Switched off by a user (stored on the device's MCU in the HCDF)
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
     /// Composite visual elements with individual poses
     #[serde(default)]
     pub visuals: Vec<DeviceVisual>,
@@ -338,12 +544,16 @@ impl Device {
             status: DeviceStatus::Unknown,
             discovery: DiscoveryInfo {
                 ip,
+                scope_id: None,
                 port,
                 switch_port: None,
                 mac: None,
                 first_seen: now,
                 last_seen: now,
                 discovery_method: DiscoveryMethod::Probe,
+                heartbeat_method: None,
+                can: None,
+                hostname: None,
             },
             info: DeviceInfo::default(),
             firmware: FirmwareInfo::default(),
@@ -352,6 +562,9 @@ impl Device {
             parent_id: None,
             model_path: None,
             pose: None,
+            pose_provisional: false,
+            constraint: PoseConstraint::default(),
+            enabled: true,
             visuals: Vec::new(),
             frames: Vec::new(),
             ports: Vec::new(),
@@ -359,6 +572,25 @@ impl Device {
         }
     }
 
+    /// Create a node found on a CAN bus
+    pub fn new_can(address: CanAddress, name: String) -> Self {
+        let mut device = Self::new(address.device_id(), name, IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0);
+        device.discovery.discovery_method = DiscoveryMethod::Can;
+        device.discovery.can = Some(address);
+        device
+    }
+
+    /// Whether the device sits on a CAN bus rather than the network, so
+    /// MCUmgr and IP heartbeats don't apply
+    pub fn is_can(&self) -> bool {
+        self.discovery.can.is_some()
+    }
+
+    /// Status to show on indicators
+    pub fn indicator_status(&self) -> IndicatorStatus {
+        IndicatorStatus::from_status(self.status, &self.firmware_status)
+    }
+
     /// Update the last seen timestamp
     pub fn touch(&mut self) {
         self.discovery.last_seen = Utc::now();
@@ -371,6 +603,52 @@ impl Device {
     }
 }
 
+/// A device without its visuals, frames, ports and sensors
+///
+/// Serves clients on slow links (`/api/devices?lite=true` and lite
+/// WebSocket subscriptions): enough to list devices and show their status
+/// and pose, a fraction of the size of the full record.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceSummary {
+    pub id: DeviceId,
+    pub name: String,
+    pub status: DeviceStatus,
+    pub discovery: DiscoveryInfo,
+    pub info: DeviceInfo,
+    pub firmware: FirmwareInfo,
+    #[serde(default)]
+    pub firmware_status: FirmwareStatus,
+    pub parent_id: Option<DeviceId>,
+    pub model_path: Option<String>,
+    pub pose: Option<[f64; 6]>,
+    #[serde(default)]
+    pub pose_provisional: bool,
+    #[serde(default)]
+    pub constraint: PoseConstraint,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+impl From<&Device> for DeviceSummary {
+    fn from(device: &Device) -> Self {
+        Self {
+            id: device.id.clone(),
+            name: device.name.clone(),
+            status: device.status,
+            discovery: device.discovery.clone(),
+            info: device.info.clone(),
+            firmware: device.firmware.clone(),
+            firmware_status: device.firmware_status.clone(),
+            parent_id: device.parent_id.clone(),
+            model_path: device.model_path.clone(),
+            pose: device.pose,
+            pose_provisional: device.pose_provisional,
+            constraint: device.constraint.clone(),
+            enabled: device.enabled,
+        }
+    }
+}
+
 // Need hex for DeviceId::from_bytes
 mod hex {
     const HEX_CHARS: &[u8; 16] = b"0123456789abcdef";
@@ -396,6 +674,68 @@ mod tests {
         assert_eq!(id.as_str(), "0x12345678");
     }
 
+    #[test]
+    fn test_derive_device_id() {
+        let addr: SocketAddr = "10.0.0.5:1337".parse().unwrap();
+
+        // The hardware ID wins when everything is known
+        let id = DeviceId::derive(Some("spinali"), Some("AA:BB:CC:00:11:22"), Some("0x1234"), addr);
+        assert_eq!(id, DeviceId::from_hwid("0x1234"));
+        assert!(!id.is_derived());
+
+        // MAC only: hashed, stable, and independent of how the MAC is written
+        let id = DeviceId::derive(None, Some("AA:BB:CC:00:11:22"), None, addr);
+        assert_eq!(id.as_str().len(), "mac-".len() + 16);
+        assert!(id.as_str().starts_with("mac-"));
+        let moved: SocketAddr = "10.0.0.6:1337".parse().unwrap();
+        assert_eq!(id, DeviceId::derive(Some("spinali"), Some("aa-bb-cc-00-11-22"), Some(""), moved));
+        assert_ne!(id, DeviceId::derive(None, Some("aa:bb:cc:00:11:23"), None, addr));
+        assert!(id.is_derived());
+
+        // Board name only, then nothing
+        assert_eq!(DeviceId::derive(Some("spinali"), None, None, addr).as_str(), "board-spinali@10.0.0.5:1337");
+        assert_eq!(DeviceId::derive(Some("spinali"), Some(""), None, addr).as_str(), "board-spinali@10.0.0.5:1337");
+        assert_eq!(DeviceId::derive(None, None, None, addr).as_str(), "board-unknown@10.0.0.5:1337");
+        assert!(DeviceId::derive(Some("spinali"), None, None, addr).is_derived());
+        assert!(DeviceId::temporary().is_derived());
+    }
+
+    #[test]
+    fn test_can_address_device_id() {
+        let address = CanAddress { bus: "can0".to_string(), node_id: 12 };
+        assert_eq!(address.device_id().as_str(), "can:can0:12");
+        assert_eq!(CanAddress::from_device_id("can:can0:12"), Some(address));
+        assert_eq!(CanAddress::from_device_id("can:can0:300"), None);
+        assert_eq!(CanAddress::from_device_id("can::12"), None);
+        assert_eq!(CanAddress::from_device_id("0x12345678"), None);
+
+        let device = Device::new_can(CanAddress { bus: "vcan0".to_string(), node_id: 3 }, "esc".to_string());
+        assert!(device.is_can());
+        assert!(device.discovery.ip.is_unspecified());
+        assert_eq!(device.discovery.discovery_method, DiscoveryMethod::Can);
+    }
+
+    #[test]
+    fn test_scoped_link_local_address() {
+        let ip: IpAddr = "fe80::1c2:3ff:fe04:506".parse().unwrap();
+        let mut device = Device::new(DeviceId::from_hwid("v6"), "v6".to_string(), ip, 1337);
+        assert_eq!(device.discovery.scoped_ip(), "fe80::1c2:3ff:fe04:506");
+
+        device.discovery.scope_id = Some(3);
+        assert_eq!(device.discovery.scoped_ip(), "fe80::1c2:3ff:fe04:506%3");
+        assert_eq!(device.discovery.socket_addr().to_string(), "[fe80::1c2:3ff:fe04:506%3]:1337");
+
+        let json = serde_json::to_value(&device.discovery).unwrap();
+        assert_eq!(json["scope_id"], 3);
+        let parsed: DiscoveryInfo = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.socket_addr(), device.discovery.socket_addr());
+
+        assert_eq!(DiscoveryInfo::parse_scoped_ip("fe80::1c2:3ff:fe04:506%3"), Some((ip, Some(3))));
+        assert_eq!(DiscoveryInfo::parse_scoped_ip("10.0.0.4"), Some(("10.0.0.4".parse().unwrap(), None)));
+        assert_eq!(DiscoveryInfo::parse_scoped_ip("10.0.0.4%3"), None);
+        assert_eq!(DiscoveryInfo::parse_scoped_ip("fe80::1%eth0"), None);
+    }
+
     #[test]
     fn test_device_id_from_bytes() {
         let id = DeviceId::from_bytes(&[0x12, 0x34, 0x56, 0x78]);
@@ -414,4 +754,40 @@ mod tests {
         assert_eq!(device.id, id);
         assert_eq!(device.status, DeviceStatus::Unknown);
     }
+
+    #[test]
+    fn test_summary_omits_heavy_fields() {
+        let mut device = Device::new(
+            DeviceId::from_hwid("test-001"),
+            "Test Device".to_string(),
+            IpAddr::V4(Ipv4Addr::new(192, 168, 1, 100)),
+            1337,
+        );
+        device.pose = Some([0.1, 0.0, 0.0, 0.0, 0.0, 1.57]);
+        device.frames.push(DeviceFrame {
+            name: "imu".to_string(),
+            description: None,
+            pose: None,
+            parent: None,
+        });
+
+        let json = serde_json::to_value(DeviceSummary::from(&device)).unwrap();
+        assert_eq!(json["id"], "test-001");
+        assert_eq!(json["pose"][5], 1.57);
+        for heavy in ["visuals", "frames", "ports", "sensors"] {
+            assert!(json.get(heavy).is_none(), "{heavy} in summary");
+        }
+    }
+
+    #[test]
+    fn test_indicator_status_priority() {
+        let outdated = FirmwareStatus::UpdateAvailable {
+            latest_version: "1.2.0".to_string(),
+            changelog: None,
+        };
+        assert_eq!(IndicatorStatus::from_status(DeviceStatus::Online, &FirmwareStatus::UpToDate), IndicatorStatus::UpToDate);
+        assert_eq!(IndicatorStatus::from_status(DeviceStatus::Online, &outdated), IndicatorStatus::UpdateAvailable);
+        assert_eq!(IndicatorStatus::from_status(DeviceStatus::Offline, &outdated), IndicatorStatus::Attention);
+        assert_eq!(IndicatorStatus::from_status(DeviceStatus::Online, &FirmwareStatus::CheckDisabled), IndicatorStatus::Unknown);
+    }
 }
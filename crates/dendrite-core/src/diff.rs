@@ -0,0 +1,601 @@
+//! Structural diff between two HCDF documents
+//!
+//! Elements are matched by key (MCUs and comps by hwid, falling back to
+//! name; links, sensors, motors and power sources by name) and compared
+//! field by field, so a preview can say "spinali-001: visual, frame changed"
+//! rather than showing a raw XML diff.
+//!
+//! Devices (`<mcu>` and `<comp>`) are compared in more detail: how far a
+//! device moved, which model it points at now, and which sensors and ports
+//! it gained, lost or changed. Pose changes within a [`PoseTolerance`] are
+//! taken as noise, and runtime `<discovered>` blocks are ignored.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeSet;
+
+use crate::hcdf::{parse_pose_string, Comp, Hcdf, Mcu, ModelRef, Port, Pose, Sensor, Visual};
+
+/// How an element changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+/// Smallest pose change [`Hcdf::diff_with`] reports
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PoseTolerance {
+    /// Meters
+    pub position: f64,
+    /// Radians
+    pub angle: f64,
+}
+
+impl Default for PoseTolerance {
+    /// 1 mm and 0.1°
+    fn default() -> Self {
+        Self { position: 0.001, angle: 0.1_f64.to_radians() }
+    }
+}
+
+/// Differences between two HCDF documents
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HcdfDiff {
+    /// Changed elements by type (MCUs, comps, links, sensors, motors, power
+    /// sources), each in the first document's order then additions
+    pub changes: Vec<HcdfChange>,
+}
+
+impl HcdfDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    /// Changed MCUs and comps
+    pub fn devices(&self) -> impl Iterator<Item = &HcdfChange> {
+        self.changes.iter().filter(|c| c.is_device())
+    }
+
+    /// The change to the device with this key (hwid, or name without one)
+    pub fn device(&self, key: &str) -> Option<&HcdfChange> {
+        self.devices().find(|c| c.key == key)
+    }
+}
+
+/// An element added, removed or modified
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HcdfChange {
+    /// Element type ("mcu", "comp", "link", "sensor", "motor", "power")
+    pub element: String,
+    /// Element name
+    pub name: String,
+    /// Matching key (hwid for devices that have one, otherwise the name)
+    pub key: String,
+    pub kind: ChangeKind,
+    /// What changed (only for `Modified`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub changes: Vec<FieldChange>,
+}
+
+impl HcdfChange {
+    /// Whether this is an `<mcu>` or `<comp>`
+    pub fn is_device(&self) -> bool {
+        self.element == "mcu" || self.element == "comp"
+    }
+
+    /// Short names of what changed ("pose_cg", "sensor imu"), in order
+    pub fn fields(&self) -> Vec<String> {
+        let mut fields: Vec<String> = Vec::new();
+        for field in self.changes.iter().map(FieldChange::field) {
+            if !fields.contains(&field) {
+                fields.push(field);
+            }
+        }
+        fields
+    }
+}
+
+/// A single change to a modified element
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum FieldChange {
+    /// `pose_cg` moved or turned by more than the tolerance
+    Pose {
+        from: Option<String>,
+        to: Option<String>,
+        /// Translation from the old position to the new one, meters
+        offset: [f64; 3],
+        /// Angle between the old and new orientation, radians
+        rotation: f64,
+    },
+    /// A model reference points somewhere else
+    Model {
+        /// Visual holding the model (None for the legacy `<model>`)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        visual: Option<String>,
+        from: Option<String>,
+        to: Option<String>,
+    },
+    SensorAdded { name: String, to: Value },
+    SensorRemoved { name: String, from: Value },
+    SensorModified { name: String, from: Value, to: Value },
+    PortAdded { name: String, to: Value },
+    PortRemoved { name: String, from: Value },
+    PortModified { name: String, from: Value, to: Value },
+    /// Any other top-level field, `@` prefix stripped; None where the
+    /// element doesn't have it
+    Field { name: String, from: Option<Value>, to: Option<Value> },
+}
+
+impl FieldChange {
+    /// Short name of the changed field
+    pub fn field(&self) -> String {
+        match self {
+            FieldChange::Pose { .. } => "pose_cg".to_string(),
+            FieldChange::Model { visual: None, .. } => "model".to_string(),
+            FieldChange::Model { visual: Some(visual), .. } => format!("visual {} model", visual),
+            FieldChange::SensorAdded { name, .. }
+            | FieldChange::SensorRemoved { name, .. }
+            | FieldChange::SensorModified { name, .. } => format!("sensor {}", name),
+            FieldChange::PortAdded { name, .. } | FieldChange::PortRemoved { name, .. } | FieldChange::PortModified { name, .. } => {
+                format!("port {}", name)
+            }
+            FieldChange::Field { name, .. } => name.clone(),
+        }
+    }
+}
+
+impl Hcdf {
+    /// Changes needed to turn `self` into `other`, ignoring pose noise
+    /// below the default [`PoseTolerance`]
+    pub fn diff(&self, other: &Hcdf) -> HcdfDiff {
+        self.diff_with(other, &PoseTolerance::default())
+    }
+
+    /// [`Hcdf::diff`] with an explicit pose tolerance
+    pub fn diff_with(&self, other: &Hcdf, tolerance: &PoseTolerance) -> HcdfDiff {
+        let mut changes = Vec::new();
+        for (old, new) in element_views(self).into_iter().zip(element_views(other)) {
+            for old_element in &old {
+                let (kind, fields) = match new.iter().find(|e| e.key == old_element.key) {
+                    None => (ChangeKind::Removed, Vec::new()),
+                    Some(new_element) => {
+                        let fields = element_changes(old_element, new_element, tolerance);
+                        if fields.is_empty() {
+                            continue;
+                        }
+                        (ChangeKind::Modified, fields)
+                    }
+                };
+                changes.push(old_element.change(kind, fields));
+            }
+            for new_element in &new {
+                if !old.iter().any(|e| e.key == new_element.key) {
+                    changes.push(new_element.change(ChangeKind::Added, Vec::new()));
+                }
+            }
+        }
+        HcdfDiff { changes }
+    }
+}
+
+/// The parts of an element the diff looks at; parts an element type
+/// doesn't have are left empty
+#[derive(Default)]
+struct ElementView<'a> {
+    element: &'static str,
+    name: &'a str,
+    key: &'a str,
+    pose_cg: Option<&'a str>,
+    model: Option<&'a ModelRef>,
+    visual: &'a [Visual],
+    sensor: &'a [Sensor],
+    port: &'a [Port],
+    /// The whole element, for fields without a dedicated change
+    value: Value,
+}
+
+impl ElementView<'_> {
+    fn change(&self, kind: ChangeKind, changes: Vec<FieldChange>) -> HcdfChange {
+        HcdfChange {
+            element: self.element.to_string(),
+            name: self.name.to_string(),
+            key: self.key.to_string(),
+            kind,
+            changes,
+        }
+    }
+}
+
+/// Views of each element type, in the order changes are reported
+fn element_views(hcdf: &Hcdf) -> [Vec<ElementView<'_>>; 6] {
+    let mcus = hcdf.mcu.iter().map(|m: &Mcu| ElementView {
+        element: "mcu",
+        name: &m.name,
+        key: m.hwid.as_deref().unwrap_or(&m.name),
+        pose_cg: m.pose_cg.as_deref(),
+        model: m.model.as_ref(),
+        visual: &m.visual,
+        port: &m.port,
+        value: to_value(m),
+        ..Default::default()
+    });
+    let comps = hcdf.comp.iter().map(|c: &Comp| ElementView {
+        element: "comp",
+        name: &c.name,
+        key: c.hwid.as_deref().unwrap_or(&c.name),
+        pose_cg: c.pose_cg.as_deref(),
+        model: c.model.as_ref(),
+        visual: &c.visual,
+        sensor: &c.sensor,
+        port: &c.port,
+        value: to_value(c),
+    });
+    let links = hcdf.link.iter().map(|l| ElementView {
+        element: "link",
+        name: &l.name,
+        key: &l.name,
+        value: to_value(l),
+        ..Default::default()
+    });
+    let sensors = hcdf.sensor.iter().map(|s| ElementView {
+        element: "sensor",
+        name: &s.name,
+        key: &s.name,
+        pose_cg: s.pose_cg.as_deref(),
+        value: to_value(s),
+        ..Default::default()
+    });
+    let motors = hcdf.motor.iter().map(|m| ElementView {
+        element: "motor",
+        name: &m.name,
+        key: &m.name,
+        pose_cg: m.pose_cg.as_deref(),
+        value: to_value(m),
+        ..Default::default()
+    });
+    let power = hcdf.power.iter().map(|p| ElementView {
+        element: "power",
+        name: &p.name,
+        key: &p.name,
+        value: to_value(p),
+        ..Default::default()
+    });
+    [mcus.collect(), comps.collect(), links.collect(), sensors.collect(), motors.collect(), power.collect()]
+}
+
+fn to_value<T: Serialize>(element: &T) -> Value {
+    serde_json::to_value(element).unwrap_or(Value::Null)
+}
+
+/// Top-level fields that differ, `@` attribute prefix stripped, with their
+/// old and new values
+fn changed_fields(old: &Value, new: &Value) -> Vec<FieldChange> {
+    let (Value::Object(old), Value::Object(new)) = (old, new) else {
+        return if old == new {
+            Vec::new()
+        } else {
+            vec![FieldChange::Field { name: "value".to_string(), from: Some(old.clone()), to: Some(new.clone()) }]
+        };
+    };
+    let keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    keys.into_iter()
+        .filter(|k| old.get(*k) != new.get(*k))
+        .map(|k| FieldChange::Field {
+            name: k.trim_start_matches('@').to_string(),
+            from: old.get(k).cloned(),
+            to: new.get(k).cloned(),
+        })
+        .collect()
+}
+
+/// Fields compared by dedicated changes, or not compared at all
+const FIELDS_HANDLED: &[&str] = &["pose_cg", "model", "visual", "sensor", "port", "discovered"];
+
+fn element_changes(old: &ElementView, new: &ElementView, tolerance: &PoseTolerance) -> Vec<FieldChange> {
+    let mut changes = Vec::new();
+
+    if let Some(change) = pose_change(old.pose_cg, new.pose_cg, tolerance) {
+        changes.push(change);
+    }
+
+    let href = |model: Option<&ModelRef>| model.map(|m| m.href.clone());
+    if href(old.model) != href(new.model) {
+        changes.push(FieldChange::Model { visual: None, from: href(old.model), to: href(new.model) });
+    }
+    for visual in new.visual {
+        let before = old.visual.iter().find(|v| v.name == visual.name);
+        let from = before.and_then(|v| href(v.model.as_ref()));
+        let to = href(visual.model.as_ref());
+        if from != to {
+            changes.push(FieldChange::Model { visual: Some(visual.name.clone()), from, to });
+        }
+    }
+    for visual in old.visual {
+        if !new.visual.iter().any(|v| v.name == visual.name) && visual.model.is_some() {
+            changes.push(FieldChange::Model { visual: Some(visual.name.clone()), from: href(visual.model.as_ref()), to: None });
+        }
+    }
+
+    for sensor in old.sensor {
+        let from = to_value(sensor);
+        match new.sensor.iter().find(|s| s.name == sensor.name).map(to_value) {
+            None => changes.push(FieldChange::SensorRemoved { name: sensor.name.clone(), from }),
+            Some(to) if to != from => changes.push(FieldChange::SensorModified { name: sensor.name.clone(), from, to }),
+            Some(_) => {}
+        }
+    }
+    for sensor in new.sensor {
+        if !old.sensor.iter().any(|s| s.name == sensor.name) {
+            changes.push(FieldChange::SensorAdded { name: sensor.name.clone(), to: to_value(sensor) });
+        }
+    }
+
+    for port in old.port {
+        let from = to_value(port);
+        match new.port.iter().find(|p| p.name == port.name).map(to_value) {
+            None => changes.push(FieldChange::PortRemoved { name: port.name.clone(), from }),
+            Some(to) if to != from => changes.push(FieldChange::PortModified { name: port.name.clone(), from, to }),
+            Some(_) => {}
+        }
+    }
+    for port in new.port {
+        if !old.port.iter().any(|p| p.name == port.name) {
+            changes.push(FieldChange::PortAdded { name: port.name.clone(), to: to_value(port) });
+        }
+    }
+
+    // Visuals moved, added or restyled; a new href alone is a Model change
+    if without_models(old.visual) != without_models(new.visual) {
+        changes.push(FieldChange::Field {
+            name: "visual".to_string(),
+            from: Some(to_value(&old.visual)),
+            to: Some(to_value(&new.visual)),
+        });
+    }
+    for change in changed_fields(&old.value, &new.value) {
+        if !matches!(&change, FieldChange::Field { name, .. } if FIELDS_HANDLED.contains(&name.as_str())) {
+            changes.push(change);
+        }
+    }
+
+    changes
+}
+
+/// A pose change beyond `tolerance`; a missing pose is the origin, and
+/// text that doesn't parse is compared as written
+fn pose_change(from: Option<&str>, to: Option<&str>, tolerance: &PoseTolerance) -> Option<FieldChange> {
+    let parse = |text: Option<&str>| match text {
+        None => Some(Pose::default()),
+        Some(text) => parse_pose_string(text),
+    };
+    let change = |offset, rotation| FieldChange::Pose {
+        from: from.map(str::to_string),
+        to: to.map(str::to_string),
+        offset,
+        rotation,
+    };
+    let (Some(old), Some(new)) = (parse(from), parse(to)) else {
+        return (from != to).then(|| change([0.0; 3], 0.0));
+    };
+
+    let offset = [new.x - old.x, new.y - old.y, new.z - old.z];
+    let distance = offset.iter().map(|d| d * d).sum::<f64>().sqrt();
+    let dot: f64 = old.orientation().iter().zip(new.orientation()).map(|(a, b)| a * b).sum();
+    let rotation = 2.0 * dot.abs().min(1.0).acos();
+    (distance > tolerance.position || rotation > tolerance.angle).then(|| change(offset, rotation))
+}
+
+fn without_models(visuals: &[Visual]) -> Vec<Value> {
+    visuals
+        .iter()
+        .map(|visual| {
+            let mut value = to_value(visual);
+            if let Value::Object(fields) = &mut value {
+                fields.remove("model");
+            }
+            value
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hcdf::Motor;
+    use serde_json::json;
+
+    const BASE: &str = r#"<?xml version='1.0'?>
+<hcdf version="1.2">
+    <mcu name="spinali-001" hwid="0x1234">
+        <board>spinali</board>
+    </mcu>
+    <mcu name="mr-canhubk3" hwid="0x5678">
+        <board>mr_canhubk3</board>
+    </mcu>
+    <comp name="navq95" role="parent"/>
+</hcdf>"#;
+
+    #[test]
+    fn test_identical_documents_have_no_changes() {
+        let hcdf = Hcdf::from_xml(BASE).unwrap();
+        assert!(hcdf.diff(&hcdf.clone()).is_empty());
+    }
+
+    #[test]
+    fn test_diff_reports_added_removed_and_modified() {
+        let old = Hcdf::from_xml(BASE).unwrap();
+        let mut new = old.clone();
+        new.mcu[0].description = Some("IMU board".to_string());
+        new.mcu.remove(1);
+        new.comp[0].name = "navq95-b".to_string();
+
+        let changes = old.diff(&new).changes;
+        assert_eq!(changes.len(), 4);
+
+        assert_eq!(changes[0].key, "0x1234");
+        assert_eq!(changes[0].kind, ChangeKind::Modified);
+        assert_eq!(changes[0].fields(), vec!["description".to_string()]);
+
+        assert_eq!(changes[1].name, "mr-canhubk3");
+        assert_eq!(changes[1].kind, ChangeKind::Removed);
+
+        assert_eq!((changes[2].name.as_str(), changes[2].kind), ("navq95", ChangeKind::Removed));
+        assert_eq!((changes[3].name.as_str(), changes[3].kind), ("navq95-b", ChangeKind::Added));
+    }
+
+    #[test]
+    fn test_device_diff_ignores_pose_noise() {
+        let old = Hcdf::from_xml(BASE).unwrap();
+        let mut new = old.clone();
+        // Half a millimeter and a twentieth of a degree: noise
+        new.mcu[0].pose_cg = Some("0.0005 0 0 0 0 0.05deg".to_string());
+        assert!(old.diff(&new).is_empty());
+
+        new.mcu[0].pose_cg = Some("0.01 0 0 0 0 90deg".to_string());
+        let result = old.diff(&new);
+        assert_eq!(result.len(), 1);
+        let Some(FieldChange::Pose { offset, rotation, .. }) = result.changes[0].changes.first() else {
+            panic!("expected a pose change: {:?}", result);
+        };
+        assert!((offset[0] - 0.01).abs() < 1e-9);
+        assert!((rotation.to_degrees() - 90.0).abs() < 1e-6);
+
+        // A looser tolerance takes the same move as noise
+        let loose = PoseTolerance { position: 0.1, angle: std::f64::consts::PI };
+        assert!(old.diff_with(&new, &loose).is_empty());
+    }
+
+    #[test]
+    fn test_device_diff_reports_models_and_sensors() {
+        let old = Hcdf::from_xml(
+            r#"<hcdf version="1.2">
+    <mcu name="spinali-001" hwid="0x1234">
+        <visual name="board"><model href="spinali.glb"/></visual>
+        <discovered><ip>192.168.186.10</ip></discovered>
+    </mcu>
+    <comp name="navq95" role="parent">
+        <sensor name="imu"><inertial type="accel_gyro"/></sensor>
+    </comp>
+</hcdf>"#,
+        )
+        .unwrap();
+        let new = Hcdf::from_xml(
+            r#"<hcdf version="1.2">
+    <mcu name="spinali-001" hwid="0x1234">
+        <visual name="board"><model href="spinali-v2.glb"/></visual>
+        <discovered><ip>192.168.186.11</ip></discovered>
+    </mcu>
+    <comp name="navq95" role="parent">
+        <description>Flight computer</description>
+        <sensor name="imu"><inertial type="accel_gyro"/></sensor>
+        <sensor name="camera"><optical type="camera"/></sensor>
+    </comp>
+    <comp name="lidar"/>
+</hcdf>"#,
+        )
+        .unwrap();
+
+        let result = old.diff(&new);
+        assert_eq!(result.len(), 3);
+        // The new IP is a runtime field, not a change
+        assert_eq!(
+            result.device("0x1234").unwrap().changes,
+            [FieldChange::Model {
+                visual: Some("board".to_string()),
+                from: Some("spinali.glb".to_string()),
+                to: Some("spinali-v2.glb".to_string()),
+            }]
+        );
+        assert_eq!(
+            result.device("navq95").unwrap().changes,
+            [
+                FieldChange::SensorAdded { name: "camera".to_string(), to: to_value(&new.comp[0].sensor[1]) },
+                FieldChange::Field {
+                    name: "description".to_string(),
+                    from: None,
+                    to: Some(Value::from("Flight computer")),
+                },
+            ]
+        );
+        assert_eq!(result.device("lidar").unwrap().kind, ChangeKind::Added);
+    }
+
+    #[test]
+    fn test_renamed_mcu_matches_by_hwid() {
+        let old = Hcdf::from_xml(BASE).unwrap();
+        let mut new = old.clone();
+        new.mcu[0].name = "imu-board".to_string();
+
+        let changes = old.diff(&new).changes;
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].kind, ChangeKind::Modified);
+        assert_eq!(changes[0].fields(), vec!["name".to_string()]);
+        assert_eq!(
+            changes[0].changes,
+            [FieldChange::Field {
+                name: "name".to_string(),
+                from: Some(Value::from("spinali-001")),
+                to: Some(Value::from("imu-board")),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_diff_reports_port_changes() {
+        let old = Hcdf::from_xml(
+            r#"<hcdf version="1.2">
+    <mcu name="spinali-001" hwid="0x1234">
+        <port name="eth0" type="ethernet"/>
+        <port name="can0" type="CAN"/>
+    </mcu>
+</hcdf>"#,
+        )
+        .unwrap();
+        let mut new = old.clone();
+        new.mcu[0].port[0].mesh = Some("port_eth0".to_string());
+        new.mcu[0].port.remove(1);
+        let uart = Port { name: "uart0".to_string(), port_type: "uart".to_string(), ..old.mcu[0].port[0].clone() };
+        new.mcu[0].port.push(uart);
+
+        let result = old.diff(&new);
+        let change = result.device("0x1234").unwrap();
+        assert_eq!(
+            change.changes,
+            [
+                FieldChange::PortModified {
+                    name: "eth0".to_string(),
+                    from: json!({"@name": "eth0", "@type": "ethernet"}),
+                    to: json!({"@name": "eth0", "@type": "ethernet", "@mesh": "port_eth0"}),
+                },
+                FieldChange::PortRemoved { name: "can0".to_string(), from: json!({"@name": "can0", "@type": "CAN"}) },
+                FieldChange::PortAdded { name: "uart0".to_string(), to: json!({"@name": "uart0", "@type": "uart"}) },
+            ]
+        );
+        assert_eq!(change.fields(), ["port eth0", "port can0", "port uart0"]);
+    }
+
+    #[test]
+    fn test_diff_covers_non_device_elements() {
+        let mut old = Hcdf::from_xml(BASE).unwrap();
+        old.motor.push(Motor { name: "m1".to_string(), pose_cg: Some("0.1 0 0 0 0 0".to_string()) });
+        let mut new = old.clone();
+        // Motors get the same pose tolerance as devices
+        new.motor[0].pose_cg = Some("0.1003 0 0 0 0 0".to_string());
+        assert!(old.diff(&new).is_empty());
+
+        new.motor[0].pose_cg = Some("0.2 0 0 0 0 0".to_string());
+        let result = old.diff(&new);
+        assert_eq!(result.len(), 1);
+        assert_eq!((result.changes[0].element.as_str(), result.changes[0].key.as_str()), ("motor", "m1"));
+        assert_eq!(result.changes[0].fields(), ["pose_cg"]);
+        assert_eq!(result.devices().count(), 0);
+    }
+}
@@ -0,0 +1,310 @@
+//! Sensor driver registry
+//!
+//! Maps Zephyr driver names referenced by HCDF sensors (e.g., "bmi08x") to a
+//! human-readable label, datasheet link, default axis convention and the
+//! sensor types the driver supports. A built-in table ships with dendrite and
+//! can be extended by `[[driver]]` entries in the fragment index.
+//!
+//! Unknown drivers are passed through untouched.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::OnceLock;
+
+use crate::device::{DeviceAxisAlign, DeviceSensor};
+use crate::hcdf::{Hcdf, Sensor, SensorDriver};
+
+/// Built-in driver table
+const BUILTIN_DRIVERS: &str = include_str!("drivers.toml");
+
+/// Metadata for a known sensor driver
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DriverInfo {
+    /// Zephyr driver name (e.g., "bmi08x")
+    pub name: String,
+    /// Part name (e.g., "Bosch BMI088")
+    pub label: String,
+    /// Kind of sensor (e.g., "IMU", "Magnetometer")
+    pub kind: String,
+    /// Datasheet or product page URL
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub datasheet: Option<String>,
+    /// Axis alignment assumed when an HCDF sensor doesn't specify one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_axis_align: Option<DeviceAxisAlign>,
+    /// Sensor types this driver provides (empty = any)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sensor_types: Vec<String>,
+}
+
+impl DriverInfo {
+    /// Display name such as "Bosch BMI088 (IMU)"
+    pub fn display_name(&self) -> String {
+        format!("{} ({})", self.label, self.kind)
+    }
+
+    /// Whether the driver can back the given sensor type
+    pub fn supports(&self, sensor_type: &str) -> bool {
+        self.sensor_types.is_empty()
+            || self.sensor_types.iter().any(|t| t.eq_ignore_ascii_case(sensor_type))
+    }
+}
+
+/// A sensor whose declared driver doesn't support its sensor type
+#[derive(Debug, Clone, PartialEq)]
+pub struct DriverLint {
+    /// Sensor name from the HCDF
+    pub sensor: String,
+    /// Declared sensor type (e.g., "accel_gyro")
+    pub sensor_type: String,
+    /// Declared driver name
+    pub driver: String,
+    /// Sensor types the driver actually supports
+    pub supported: Vec<String>,
+}
+
+impl fmt::Display for DriverLint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "sensor '{}' uses driver '{}' for type '{}', but the driver only supports: {}",
+            self.sensor,
+            self.driver,
+            self.sensor_type,
+            self.supported.join(", ")
+        )
+    }
+}
+
+/// TOML layout shared by the built-in table and the fragment index
+#[derive(Debug, Deserialize)]
+struct DriverTable {
+    #[serde(default)]
+    driver: Vec<DriverInfo>,
+}
+
+/// Registry of known sensor drivers, keyed by lowercase driver name
+#[derive(Debug, Clone, Default)]
+pub struct DriverRegistry {
+    drivers: HashMap<String, DriverInfo>,
+}
+
+impl DriverRegistry {
+    /// The built-in registry (parsed once)
+    pub fn builtin() -> &'static DriverRegistry {
+        static BUILTIN: OnceLock<DriverRegistry> = OnceLock::new();
+        BUILTIN.get_or_init(|| {
+            DriverRegistry::from_toml(BUILTIN_DRIVERS).expect("built-in driver table is valid TOML")
+        })
+    }
+
+    /// Parse a registry from `[[driver]]` TOML tables
+    pub fn from_toml(content: &str) -> Result<Self, toml::de::Error> {
+        let table: DriverTable = toml::from_str(content)?;
+        let mut registry = Self::default();
+        registry.extend(table.driver);
+        Ok(registry)
+    }
+
+    /// Add drivers, replacing existing entries with the same name
+    pub fn extend(&mut self, drivers: impl IntoIterator<Item = DriverInfo>) {
+        for driver in drivers {
+            self.drivers.insert(driver.name.to_ascii_lowercase(), driver);
+        }
+    }
+
+    /// Look up a driver by name (case-insensitive)
+    pub fn get(&self, name: &str) -> Option<&DriverInfo> {
+        self.drivers.get(&name.to_ascii_lowercase())
+    }
+
+    pub fn len(&self) -> usize {
+        self.drivers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.drivers.is_empty()
+    }
+
+    /// Human-readable driver name, or the raw name for unknown drivers
+    pub fn display_name(&self, name: &str) -> String {
+        self.get(name)
+            .map(|d| d.display_name())
+            .unwrap_or_else(|| name.to_string())
+    }
+
+    /// Check a single sensor declaration; unknown drivers never lint
+    pub fn lint_sensor(&self, sensor: &str, sensor_type: &str, driver: &str) -> Option<DriverLint> {
+        let info = self.get(driver)?;
+        if info.supports(sensor_type) {
+            return None;
+        }
+        Some(DriverLint {
+            sensor: sensor.to_string(),
+            sensor_type: sensor_type.to_string(),
+            driver: driver.to_string(),
+            supported: info.sensor_types.clone(),
+        })
+    }
+
+    /// Check every sensor in an HCDF document
+    pub fn lint_hcdf(&self, hcdf: &Hcdf) -> Vec<DriverLint> {
+        hcdf.sensor
+            .iter()
+            .chain(hcdf.comp.iter().flat_map(|c| c.sensor.iter()))
+            .flat_map(|sensor| {
+                sensor_drivers(sensor)
+                    .into_iter()
+                    .filter_map(|(sensor_type, driver)| {
+                        self.lint_sensor(&sensor.name, sensor_type, &driver.name)
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// Fill driver labels and default axis alignment on device sensors,
+    /// returning lints for sensors whose driver doesn't match their type
+    pub fn enrich_sensors(&self, sensors: &mut [DeviceSensor]) -> Vec<DriverLint> {
+        let mut lints = Vec::new();
+        for sensor in sensors.iter_mut() {
+            let Some(driver) = sensor.driver.as_deref() else {
+                continue;
+            };
+            let Some(info) = self.get(driver) else {
+                continue;
+            };
+
+            sensor.driver_label = Some(info.display_name());
+            sensor.datasheet = info.datasheet.clone();
+            if sensor.axis_align.is_none() {
+                sensor.axis_align = info.default_axis_align.clone();
+            }
+            if let Some(lint) = self.lint_sensor(&sensor.name, &sensor.sensor_type, driver) {
+                lints.push(lint);
+            }
+        }
+        lints
+    }
+}
+
+/// (sensor_type, driver) pairs declared by an HCDF sensor element
+fn sensor_drivers(sensor: &Sensor) -> Vec<(&str, &SensorDriver)> {
+    let mut pairs = Vec::new();
+    macro_rules! collect {
+        ($list:expr) => {
+            for s in &$list {
+                if let Some(driver) = &s.driver {
+                    pairs.push((s.sensor_type.as_str(), driver));
+                }
+            }
+        };
+    }
+    collect!(sensor.inertial);
+    collect!(sensor.em);
+    collect!(sensor.optical);
+    collect!(sensor.rf);
+    collect!(sensor.chemical);
+    collect!(sensor.force);
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dendrite_test_support::{FixtureBuilder, SensorCategory, SensorFixture};
+
+    #[test]
+    fn test_builtin_registry_loads() {
+        let registry = DriverRegistry::builtin();
+        assert!(!registry.is_empty());
+
+        let bmi = registry.get("bmi08x").unwrap();
+        assert_eq!(bmi.display_name(), "Bosch BMI088 (IMU)");
+        assert!(bmi.supports("accel_gyro"));
+        assert!(registry.get("BMI08X").is_some());
+
+        // Unknown drivers pass through
+        assert!(registry.get("not_a_driver").is_none());
+        assert_eq!(registry.display_name("not_a_driver"), "not_a_driver");
+    }
+
+    #[test]
+    fn test_extend_overrides_builtin() {
+        let mut registry = DriverRegistry::builtin().clone();
+        let extra = DriverRegistry::from_toml(
+            r#"
+[[driver]]
+name = "bmi08x"
+label = "Custom BMI"
+kind = "IMU"
+
+[[driver]]
+name = "vl53l1x"
+label = "ST VL53L1X"
+kind = "Time-of-Flight"
+sensor_types = ["tof"]
+"#,
+        )
+        .unwrap();
+        registry.extend(extra.drivers.into_values());
+
+        assert_eq!(registry.display_name("bmi08x"), "Custom BMI (IMU)");
+        assert_eq!(registry.display_name("vl53l1x"), "ST VL53L1X (Time-of-Flight)");
+    }
+
+    #[test]
+    fn test_lint_driver_type_mismatch() {
+        let xml = FixtureBuilder::device()
+            .with_name("board")
+            .with_sensor(SensorFixture::new("imu", SensorCategory::Inertial, "accel_gyro").with_driver("bmm350"))
+            .with_sensor(SensorFixture::new("mag", SensorCategory::Em, "mag").with_driver("bmm350"))
+            .with_sensor(SensorFixture::new("custom", SensorCategory::Inertial, "accel_gyro").with_driver("my_custom_imu"))
+            .hcdf();
+        let hcdf = Hcdf::from_xml(&xml).unwrap();
+        let lints = DriverRegistry::builtin().lint_hcdf(&hcdf);
+
+        assert_eq!(lints.len(), 1);
+        assert_eq!(lints[0].sensor, "imu");
+        assert_eq!(lints[0].driver, "bmm350");
+        assert_eq!(lints[0].supported, vec!["mag".to_string()]);
+    }
+
+    #[test]
+    fn test_enrich_sensors() {
+        let mut sensors = vec![
+            DeviceSensor {
+                name: "imu".to_string(),
+                category: "inertial".to_string(),
+                sensor_type: "accel_gyro".to_string(),
+                driver: Some("icm45686".to_string()),
+                driver_label: None,
+                datasheet: None,
+                pose: None,
+                axis_align: None,
+                geometry: None,
+                fovs: Vec::new(),
+            },
+            DeviceSensor {
+                name: "other".to_string(),
+                category: "inertial".to_string(),
+                sensor_type: "accel_gyro".to_string(),
+                driver: Some("unknown".to_string()),
+                driver_label: None,
+                datasheet: None,
+                pose: None,
+                axis_align: None,
+                geometry: None,
+                fovs: Vec::new(),
+            },
+        ];
+
+        let lints = DriverRegistry::builtin().enrich_sensors(&mut sensors);
+        assert!(lints.is_empty());
+        assert_eq!(sensors[0].driver_label.as_deref(), Some("TDK InvenSense ICM-45686 (IMU)"));
+        assert!(sensors[0].axis_align.is_some());
+        assert!(sensors[1].driver_label.is_none());
+        assert_eq!(sensors[1].driver.as_deref(), Some("unknown"));
+    }
+}
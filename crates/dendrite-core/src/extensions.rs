@@ -0,0 +1,490 @@
+//! Unknown XML kept across an HCDF round trip
+//!
+//! The serde model only covers the HCDF schema, so vendor extensions
+//! (`<acme:tuning>`, `acme:asset="A-1001"`) used to vanish when a document
+//! was parsed and written back. `Hcdf::from_xml` now sets them aside: it
+//! compares the document with what the model writes for it, and whatever the
+//! original has that the model's version lacks is recorded against the
+//! element it belongs to. Elements are kept with their whole subtree as raw
+//! XML and attributes with their value as written. `Hcdf::to_xml` writes them
+//! back verbatim, each after the known sibling element or attribute it
+//! followed in the original.
+//!
+//! Elements are identified by a path of tag names qualified by their `hwid`
+//! or `name` attribute (`comp@navq95/port@CAN0`), so extensions stay with
+//! their device or port when others are added, removed or reordered, but are
+//! lost if the element they sit on is renamed.
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+use crate::hcdf::{HcdfError, COMP_CHILD_ELEMENTS};
+
+/// An attribute the schema doesn't have
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtensionAttribute {
+    /// Known attribute it followed (None = first)
+    pub after: Option<String>,
+    pub name: String,
+    /// Value as written, still escaped
+    pub raw_value: String,
+}
+
+/// An element the schema doesn't have, with everything inside it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtensionElement {
+    /// Path segment of the known sibling it followed (None = first child)
+    pub after: Option<String>,
+    /// Its own path segment (`acme:tuning`, `acme:gain#1`)
+    pub segment: String,
+    /// The element as written
+    pub xml: String,
+}
+
+/// Unknown XML on one known element
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ElementExtensions {
+    pub attributes: Vec<ExtensionAttribute>,
+    pub children: Vec<ExtensionElement>,
+}
+
+/// Unknown XML of a whole document, by element path
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HcdfExtensions {
+    elements: BTreeMap<String, ElementExtensions>,
+}
+
+impl HcdfExtensions {
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// Unknown XML on the element at `path` (`""` for the root)
+    pub fn get(&self, path: &str) -> Option<&ElementExtensions> {
+        self.elements.get(path)
+    }
+
+    /// Every element carrying unknown XML, by path
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &ElementExtensions)> {
+        self.elements.iter().map(|(path, extensions)| (path.as_str(), extensions))
+    }
+
+    /// What `original` has that `canonical`, the model's version of it, lacks
+    pub(crate) fn collect(original: &str, canonical: &str) -> Result<Self, HcdfError> {
+        let (original_root, canonical_root) = (parse_tree(original)?, parse_tree(canonical)?);
+        let mut extensions = Self::default();
+        extensions.compare(original, &original_root, &canonical_root, String::new());
+        Ok(extensions)
+    }
+
+    fn compare(&mut self, source: &str, original: &Node, canonical: &Node, path: String) {
+        let mut found = ElementExtensions::default();
+
+        let mut after = None;
+        for (name, raw_value) in &original.attributes {
+            if canonical.attributes.iter().any(|(known, _)| known == name) {
+                after = Some(name.clone());
+            } else {
+                found.attributes.push(ExtensionAttribute {
+                    after: after.clone(),
+                    name: name.clone(),
+                    raw_value: raw_value.clone(),
+                });
+            }
+        }
+
+        let known: HashMap<&str, &Node> = canonical.children.iter().map(|c| (c.segment.as_str(), c)).collect();
+        let mut after = None;
+        for child in &original.children {
+            match known.get(child.segment.as_str()) {
+                Some(counterpart) => {
+                    self.compare(source, child, counterpart, child_path(&path, &child.segment));
+                    after = Some(child.segment.clone());
+                }
+                None => found.children.push(ExtensionElement {
+                    after: after.clone(),
+                    segment: child.segment.clone(),
+                    xml: source[child.start..child.end].to_string(),
+                }),
+            }
+        }
+
+        if !found.attributes.is_empty() || !found.children.is_empty() {
+            self.elements.insert(path, found);
+        }
+    }
+
+    /// `canonical` with the unknown XML put back
+    pub(crate) fn apply<'a>(&self, canonical: &'a str) -> Result<Cow<'a, str>, HcdfError> {
+        if self.elements.is_empty() {
+            return Ok(Cow::Borrowed(canonical));
+        }
+        let root = parse_tree(canonical)?;
+        let mut edits = Vec::new();
+        self.plan(&root, "", 0, &mut edits);
+        Ok(Cow::Owned(splice(canonical, edits)))
+    }
+
+    /// Edits writing this element's extensions and its descendants'
+    fn plan(&self, node: &Node, path: &str, depth: usize, edits: &mut Vec<Edit>) {
+        for child in &node.children {
+            self.plan(child, &child_path(path, &child.segment), depth + 1, edits);
+        }
+        let Some(extensions) = self.elements.get(path) else {
+            return;
+        };
+
+        // Skip anything the model now writes itself, so nothing is doubled
+        let attributes: Vec<&ExtensionAttribute> = extensions
+            .attributes
+            .iter()
+            .filter(|a| !node.attributes.iter().any(|(name, _)| *name == a.name))
+            .collect();
+        let children: Vec<&ExtensionElement> = extensions
+            .children
+            .iter()
+            .filter(|c| !node.children.iter().any(|known| known.segment == c.segment))
+            .collect();
+        let indent = "  ".repeat(depth + 1);
+        let written = |after: Option<&str>, orphans: bool| {
+            children
+                .iter()
+                .filter(|c| match c.after.as_deref() {
+                    None => after.is_none(),
+                    Some(anchor) => {
+                        Some(anchor) == after
+                            || (orphans && !node.children.iter().any(|known| known.segment == anchor))
+                    }
+                })
+                .fold(String::new(), |mut text, c| {
+                    let _ = write!(text, "\n{}{}", indent, c.xml);
+                    text
+                })
+        };
+
+        // Start tag, rebuilt with the attributes where they were, and the
+        // children that came first (all of them if nothing is known)
+        let mut tag = format!("<{}", node.tag);
+        for attribute in attributes.iter().filter(|a| a.after.is_none()) {
+            push_attribute(&mut tag, &attribute.name, &attribute.raw_value);
+        }
+        for (name, raw_value) in &node.attributes {
+            push_attribute(&mut tag, name, raw_value);
+            for attribute in attributes.iter().filter(|a| a.after.as_ref() == Some(name)) {
+                push_attribute(&mut tag, &attribute.name, &attribute.raw_value);
+            }
+        }
+        for attribute in attributes.iter().filter(|a| {
+            a.after.as_ref().is_some_and(|anchor| !node.attributes.iter().any(|(name, _)| name == anchor))
+        }) {
+            push_attribute(&mut tag, &attribute.name, &attribute.raw_value);
+        }
+        let first = written(None, node.children.is_empty());
+        if node.empty && !first.is_empty() {
+            let _ = write!(tag, ">{}\n{}</{}>", first, "  ".repeat(depth), node.tag);
+        } else {
+            tag.push_str(if node.empty { "/>" } else { ">" });
+            tag.push_str(&first);
+        }
+        edits.push(Edit { start: node.start, end: node.tag_end, text: tag });
+
+        // The rest after the known sibling they followed; those whose
+        // sibling is gone go after the last child
+        let last = node.children.len().saturating_sub(1);
+        for (i, child) in node.children.iter().enumerate() {
+            let text = written(Some(child.segment.as_str()), i == last);
+            if !text.is_empty() {
+                edits.push(Edit { start: child.end, end: child.end, text });
+            }
+        }
+    }
+}
+
+/// The document minus what the serde model can't read past: namespaced
+/// elements and attributes, and unknown children of `<comp>`, which is read
+/// through an enum of its children. Nothing is lost; [`HcdfExtensions`]
+/// collects all of it from the original afterwards.
+pub(crate) fn strip_unknown(xml: &str) -> Result<Cow<'_, str>, HcdfError> {
+    let mut reader = Reader::from_str(xml);
+    let mut edits = Vec::new();
+    // Tags of the open elements that are kept
+    let mut open: Vec<String> = Vec::new();
+    // Depth inside a dropped element, and where it started
+    let mut dropped = 0usize;
+    let mut dropped_start = 0;
+    loop {
+        let start = reader.buffer_position() as usize;
+        let event = reader.read_event().map_err(|e| malformed(&reader, e))?;
+        let end = reader.buffer_position() as usize;
+        let (element, empty) = match event {
+            Event::Start(element) => (element, false),
+            Event::Empty(element) => (element, true),
+            Event::End(_) if dropped > 0 => {
+                dropped -= 1;
+                if dropped == 0 {
+                    edits.push(Edit { start: dropped_start, end, text: String::new() });
+                }
+                continue;
+            }
+            Event::End(_) => {
+                open.pop();
+                continue;
+            }
+            Event::Eof => break,
+            _ => continue,
+        };
+        if dropped > 0 {
+            dropped += usize::from(!empty);
+            continue;
+        }
+
+        let tag = String::from_utf8_lossy(element.name().as_ref()).into_owned();
+        let unknown_comp_child =
+            open.last().is_some_and(|parent| parent == "comp") && !COMP_CHILD_ELEMENTS.contains(&tag.as_str());
+        if tag.contains(':') || unknown_comp_child {
+            if empty {
+                edits.push(Edit { start, end, text: String::new() });
+            } else {
+                dropped = 1;
+                dropped_start = start;
+            }
+            continue;
+        }
+
+        let attributes = attributes(&element)?;
+        if attributes.iter().any(|(name, _)| is_namespaced(name)) {
+            let mut kept = format!("<{}", tag);
+            for (name, raw_value) in attributes.iter().filter(|(name, _)| !is_namespaced(name)) {
+                push_attribute(&mut kept, name, raw_value);
+            }
+            kept.push_str(if empty { "/>" } else { ">" });
+            edits.push(Edit { start, end, text: kept });
+        }
+        if !empty {
+            open.push(tag);
+        }
+    }
+
+    if edits.is_empty() {
+        Ok(Cow::Borrowed(xml))
+    } else {
+        Ok(Cow::Owned(splice(xml, edits)))
+    }
+}
+
+/// A prefixed attribute or a namespace declaration
+fn is_namespaced(name: &str) -> bool {
+    name.contains(':') || name == "xmlns"
+}
+
+/// Replace `start..end` of the source with `text`
+struct Edit {
+    start: usize,
+    end: usize,
+    text: String,
+}
+
+/// Apply non-overlapping edits
+fn splice(source: &str, mut edits: Vec<Edit>) -> String {
+    // Insertions at a position go before a replacement starting there
+    edits.sort_by_key(|edit| (edit.start, edit.end));
+    let mut out = String::with_capacity(source.len() + edits.iter().map(|e| e.text.len()).sum::<usize>());
+    let mut copied = 0;
+    for edit in edits {
+        out.push_str(&source[copied..edit.start]);
+        out.push_str(&edit.text);
+        copied = edit.end;
+    }
+    out.push_str(&source[copied..]);
+    out
+}
+
+fn push_attribute(tag: &mut String, name: &str, raw_value: &str) {
+    let quote = if raw_value.contains('"') { '\'' } else { '"' };
+    let _ = write!(tag, " {}={}{}{}", name, quote, raw_value, quote);
+}
+
+fn child_path(parent: &str, segment: &str) -> String {
+    if parent.is_empty() {
+        segment.to_string()
+    } else {
+        format!("{}/{}", parent, segment)
+    }
+}
+
+fn malformed(reader: &Reader<&[u8]>, e: quick_xml::Error) -> HcdfError {
+    HcdfError::ParseError(format!("malformed XML at byte {}: {}", reader.error_position(), e))
+}
+
+/// Attribute names and raw (escaped) values, in document order
+fn attributes(element: &BytesStart) -> Result<Vec<(String, String)>, HcdfError> {
+    element
+        .attributes()
+        .map(|attribute| {
+            let attribute = attribute.map_err(|e| HcdfError::ParseError(format!("malformed attribute: {}", e)))?;
+            Ok((
+                String::from_utf8_lossy(attribute.key.as_ref()).into_owned(),
+                String::from_utf8_lossy(&attribute.value).into_owned(),
+            ))
+        })
+        .collect()
+}
+
+/// An element of a parsed document, with byte offsets into its source
+struct Node {
+    tag: String,
+    /// Tag qualified by `hwid` or `name`, plus `#n` for repeats after the first
+    segment: String,
+    start: usize,
+    /// Just past the start tag
+    tag_end: usize,
+    /// Just past the end tag
+    end: usize,
+    empty: bool,
+    attributes: Vec<(String, String)>,
+    children: Vec<Node>,
+}
+
+fn parse_tree(xml: &str) -> Result<Node, HcdfError> {
+    let mut reader = Reader::from_str(xml);
+    // Open elements with how often each segment has been seen among their children
+    let mut stack: Vec<(Node, HashMap<String, usize>)> = Vec::new();
+    let mut root = None;
+    loop {
+        let start = reader.buffer_position() as usize;
+        let event = reader.read_event().map_err(|e| malformed(&reader, e))?;
+        let tag_end = reader.buffer_position() as usize;
+        let (element, empty) = match event {
+            Event::Start(element) => (element, false),
+            Event::Empty(element) => (element, true),
+            Event::End(_) => {
+                if let Some((mut node, _)) = stack.pop() {
+                    node.end = tag_end;
+                    attach(&mut stack, &mut root, node);
+                }
+                continue;
+            }
+            Event::Eof => break,
+            _ => continue,
+        };
+
+        let tag = String::from_utf8_lossy(element.name().as_ref()).into_owned();
+        let attributes = attributes(&element)?;
+        // Empty counts as absent: the model writes `name=""` for a missing
+        // name it defaults
+        let identity = ["hwid", "name"]
+            .iter()
+            .find_map(|key| attributes.iter().find(|(name, raw)| name == key && !raw.is_empty()))
+            .map(|(_, raw)| quick_xml::escape::unescape(raw).map_or_else(|_| raw.clone(), |v| v.into_owned()));
+        let base = match identity {
+            Some(identity) => format!("{}@{}", tag, identity),
+            None => tag.clone(),
+        };
+        let segment = match stack.last_mut() {
+            Some((_, seen)) => {
+                let count = seen.entry(base.clone()).or_insert(0);
+                *count += 1;
+                if *count == 1 { base } else { format!("{}#{}", base, *count - 1) }
+            }
+            None => String::new(),
+        };
+
+        let node = Node { tag, segment, start, tag_end, end: tag_end, empty, attributes, children: Vec::new() };
+        if empty {
+            attach(&mut stack, &mut root, node);
+        } else {
+            stack.push((node, HashMap::new()));
+        }
+    }
+    root.ok_or_else(|| HcdfError::ParseError("document has no root element".to_string()))
+}
+
+fn attach(stack: &mut [(Node, HashMap<String, usize>)], root: &mut Option<Node>, node: Node) {
+    match stack.last_mut() {
+        Some((parent, _)) => parent.children.push(node),
+        None => *root = Some(node),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hcdf::{Hcdf, Pose};
+
+    const FIXTURE: &str = include_str!("../../../hcdf/examples/vendor_extensions.hcdf");
+
+    /// The document as the model alone writes it
+    fn known_fields(hcdf: &Hcdf) -> String {
+        let mut known = hcdf.clone();
+        known.extensions = Default::default();
+        known.to_xml().unwrap()
+    }
+
+    #[test]
+    fn test_round_trip_keeps_namespaced_extensions() {
+        let parsed = Hcdf::from_xml(FIXTURE).unwrap();
+        assert_eq!(parsed.comp[0].port[0].name, "CAN0");
+        assert_eq!(parsed.mcu[0].board.as_deref(), Some("spinali"));
+
+        let root = parsed.extensions.get("").unwrap();
+        assert_eq!(root.attributes[0].name, "xmlns:acme");
+        assert_eq!(root.children[0].segment, "acme:fleet");
+        let port = parsed.extensions.get("comp@navq95/port@CAN0").unwrap();
+        assert_eq!(port.attributes[0].name, "acme:termination");
+        assert_eq!(port.attributes[0].after.as_deref(), Some("type"));
+        assert_eq!(port.children[0].after, None);
+        let mcu = parsed.extensions.get("mcu@0x12345678abcdef").unwrap();
+        assert_eq!(mcu.children[0].after.as_deref(), Some("board"));
+        assert!(parsed.extensions.get("mcu@0x12345678abcdef/visual@board/model").is_some());
+
+        let exported = parsed.to_xml().unwrap();
+        assert!(exported.contains(r#"<hcdf version="2.0" xmlns:acme="https://example.com/acme-hcdf">"#));
+        assert!(exported.contains(r#"<port name="CAN0" type="CAN" acme:termination="120">"#));
+        assert!(exported.contains(r#"<acme:gain axis="x">0.8</acme:gain>"#));
+
+        let reparsed = Hcdf::from_xml(&exported).unwrap();
+        assert_eq!(known_fields(&reparsed), known_fields(&parsed));
+        assert_eq!(reparsed.extensions, parsed.extensions);
+        assert_eq!(reparsed.to_xml().unwrap(), exported);
+    }
+
+    #[test]
+    fn test_extensions_follow_their_element_through_edits() {
+        let mut hcdf = Hcdf::from_xml(FIXTURE).unwrap();
+        let extra = Hcdf::from_xml(r#"<hcdf version="2.0"><comp name="extra"/></hcdf>"#).unwrap();
+        hcdf.comp.insert(0, extra.comp[0].clone());
+        hcdf.mcu[0].pose_cg = Some(Pose { x: 0.2, ..Default::default() }.to_pose_string());
+
+        let exported = hcdf.to_xml().unwrap();
+        let reparsed = Hcdf::from_xml(&exported).unwrap();
+        assert_eq!(reparsed.mcu[0].pose_cg.as_deref(), Some("0.2 0 0 0 0 0"));
+        assert_eq!(reparsed.extensions, hcdf.extensions);
+
+        // Still between the known siblings it sat between
+        let mcu = &exported[exported.find("<mcu").unwrap()..];
+        let calibration = mcu.find("<acme:calibration").unwrap();
+        assert!(mcu.find("<board>").unwrap() < calibration);
+        assert!(calibration < mcu.find("<visual").unwrap());
+    }
+
+    #[test]
+    fn test_unknown_plain_elements_and_attributes_are_kept() {
+        let xml = r#"<hcdf version="2.0"><comp name="a" color="red"><notes>keep me</notes><board>b</board></comp><mcu name="m" rack="3"/></hcdf>"#;
+        let hcdf = Hcdf::from_xml(xml).unwrap();
+        assert_eq!(hcdf.comp[0].board.as_deref(), Some("b"));
+
+        let exported = hcdf.to_xml().unwrap();
+        assert!(exported.contains(r#"<comp name="a" color="red">"#));
+        assert!(exported.contains("<notes>keep me</notes>"));
+        assert!(exported.contains(r#"<mcu name="m" rack="3"/>"#));
+        assert!(exported.find("<notes>").unwrap() < exported.find("<board>").unwrap());
+    }
+
+    #[test]
+    fn test_documents_without_extensions_export_as_before() {
+        let hcdf = Hcdf::from_xml(r#"<hcdf version="2.0"><comp name="a"><board>b</board></comp></hcdf>"#).unwrap();
+        assert!(hcdf.extensions.is_empty());
+    }
+}
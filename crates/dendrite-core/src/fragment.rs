@@ -8,10 +8,12 @@
 //! containing the full fragment definitions with visuals and frames.
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
 use thiserror::Error;
 
+use crate::cache::sha256_hex;
+use crate::drivers::{DriverInfo, DriverRegistry};
 use crate::hcdf::{Comp, Frame, Hcdf, Port, Sensor, Visual};
 
 #[derive(Error, Debug)]
@@ -28,6 +30,10 @@ pub enum FragmentError {
     NoMatch(String, String),
     #[error("HCDF file has no comp element: {0}")]
     NoComp(String),
+    #[error("Failed to load fragment {path}: {reason}")]
+    LoadFailed { path: String, reason: String },
+    #[error("{failed} of {total} fragments failed to load")]
+    PartialReload { failed: usize, total: usize },
 }
 
 /// A fragment index entry - maps board/app to an HCDF file
@@ -65,6 +71,8 @@ pub struct Fragment {
     pub sensors: Vec<Sensor>,
     /// Path to the source HCDF file
     pub hcdf_path: PathBuf,
+    /// SHA256 of the source HCDF, to tell whether a reload changed it
+    pub sha: String,
 }
 
 fn default_wildcard() -> String {
@@ -80,6 +88,9 @@ pub struct FragmentIndex {
     /// List of fragment entries mapping board/app to HCDF files
     #[serde(default)]
     pub fragment: Vec<FragmentIndexEntry>,
+    /// Additional sensor drivers (extends the built-in driver registry)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub driver: Vec<DriverInfo>,
 }
 
 fn default_version() -> String {
@@ -91,6 +102,7 @@ impl Default for FragmentIndex {
         Self {
             version: default_version(),
             fragment: Vec::new(),
+            driver: Vec::new(),
         }
     }
 }
@@ -152,6 +164,51 @@ impl FragmentIndex {
     }
 }
 
+/// Built-in driver registry extended with the index's `[[driver]]` entries
+fn build_driver_registry(index: &FragmentIndex) -> DriverRegistry {
+    let mut drivers = DriverRegistry::builtin().clone();
+    drivers.extend(index.driver.iter().cloned());
+    drivers
+}
+
+/// What [`FragmentDatabase::reload`] changed
+///
+/// Entries are `(board, app)` pairs, lowercased.
+#[derive(Debug, Default)]
+pub struct FragmentReload {
+    /// Entries new to the index
+    pub added: Vec<(String, String)>,
+    /// Entries pointing at another file, or whose file changed or stopped loading
+    pub changed: Vec<(String, String)>,
+    /// Entries no longer in the index
+    pub removed: Vec<(String, String)>,
+    /// A [`FragmentError::LoadFailed`] for each fragment that didn't load
+    pub failed: Vec<FragmentError>,
+    /// Entries in the new index
+    pub total: usize,
+}
+
+impl FragmentReload {
+    /// Whether any entry was added, changed or removed
+    pub fn has_changes(&self) -> bool {
+        !(self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty())
+    }
+
+    /// Whether a device running `app` on `board` may now resolve to another fragment
+    pub fn affects(&self, board: &str, app: &str) -> bool {
+        self.added
+            .iter()
+            .chain(&self.changed)
+            .chain(&self.removed)
+            .any(|(b, a)| b.eq_ignore_ascii_case(board) && (a == "*" || a.eq_ignore_ascii_case(app)))
+    }
+
+    /// [`FragmentError::PartialReload`] if any fragment failed to load
+    pub fn partial_error(&self) -> Option<FragmentError> {
+        (!self.failed.is_empty()).then(|| FragmentError::PartialReload { failed: self.failed.len(), total: self.total })
+    }
+}
+
 /// Fragment database with loaded HCDF fragments
 #[derive(Debug, Clone)]
 pub struct FragmentDatabase {
@@ -163,16 +220,20 @@ pub struct FragmentDatabase {
     fragments: HashMap<PathBuf, Fragment>,
     /// Cache of board+app -> fragment lookup
     lookup_cache: HashMap<(String, String), Option<PathBuf>>,
+    /// Built-in drivers plus any declared in the index
+    drivers: DriverRegistry,
 }
 
 impl FragmentDatabase {
     /// Create a new fragment database from an index
     pub fn new(index: FragmentIndex, base_dir: PathBuf) -> Self {
+        let drivers = build_driver_registry(&index);
         Self {
             index,
             base_dir,
             fragments: HashMap::new(),
             lookup_cache: HashMap::new(),
+            drivers,
         }
     }
 
@@ -191,21 +252,56 @@ impl FragmentDatabase {
     }
 
     /// Load all HCDF fragments referenced by the index
+    ///
+    /// A fragment that fails to load is skipped; [`FragmentDatabase::reload`]
+    /// reports which.
     pub fn load_all_fragments(&mut self) -> Result<(), FragmentError> {
+        self.load_indexed_fragments();
+        Ok(())
+    }
+
+    /// Load the index's fragments not loaded yet, returning a
+    /// [`FragmentError::LoadFailed`] for each one that failed
+    fn load_indexed_fragments(&mut self) -> Vec<FragmentError> {
+        let mut failed = Vec::new();
         for entry in &self.index.fragment.clone() {
             let hcdf_path = self.base_dir.join(&entry.hcdf);
             if !self.fragments.contains_key(&hcdf_path) {
-                if let Ok(fragment) = self.load_fragment_file(&hcdf_path, entry) {
-                    self.fragments.insert(hcdf_path, fragment);
+                match self.load_fragment_file(&hcdf_path, entry) {
+                    Ok(fragment) => {
+                        self.fragments.insert(hcdf_path, fragment);
+                    }
+                    Err(e) => failed.push(FragmentError::LoadFailed {
+                        path: hcdf_path.display().to_string(),
+                        reason: e.to_string(),
+                    }),
                 }
             }
         }
-        Ok(())
+        failed
+    }
+
+    /// SHA of the fragment each index entry resolves to, None if it didn't load
+    fn entry_shas(&self) -> BTreeMap<(String, String), Option<String>> {
+        self.index
+            .fragment
+            .iter()
+            .map(|entry| {
+                let sha = self.fragments.get(&self.base_dir.join(&entry.hcdf)).map(|f| f.sha.clone());
+                ((entry.board.to_lowercase(), entry.app.to_lowercase()), sha)
+            })
+            .collect()
+    }
+
+    /// The index's HCDF files, for watching them for changes
+    pub fn source_files(&self) -> Vec<PathBuf> {
+        self.index.fragment.iter().map(|entry| self.base_dir.join(&entry.hcdf)).collect()
     }
 
     /// Load a single HCDF file into a Fragment
     fn load_fragment_file(&self, path: &Path, entry: &FragmentIndexEntry) -> Result<Fragment, FragmentError> {
-        let hcdf = Hcdf::from_file(path)?;
+        let content = std::fs::read_to_string(path)?;
+        let hcdf = Hcdf::from_xml(&content)?;
 
         // Get the first comp element (fragments should have exactly one)
         let comp = hcdf.comp.into_iter().next()
@@ -215,8 +311,10 @@ impl FragmentDatabase {
                     name: m.name,
                     role: None,
                     hwid: m.hwid,
+                    enabled: m.enabled,
                     description: m.description,
                     pose_cg: m.pose_cg,
+                    bbox: m.bbox,
                     mass: m.mass,
                     board: m.board,
                     software: m.software,
@@ -243,6 +341,7 @@ impl FragmentDatabase {
             ports: comp.port,
             sensors: comp.sensor,
             hcdf_path: path.to_path_buf(),
+            sha: sha256_hex(content.as_bytes()),
         })
     }
 
@@ -304,19 +403,43 @@ impl FragmentDatabase {
         &self.index
     }
 
+    /// Sensor driver registry (built-in plus index-declared drivers)
+    pub fn drivers(&self) -> &DriverRegistry {
+        &self.drivers
+    }
+
     /// Clear the lookup cache
     pub fn clear_cache(&mut self) {
         self.lookup_cache.clear();
     }
 
-    /// Reload the database from a file
-    pub fn reload(&mut self, path: &Path) -> Result<(), FragmentError> {
-        self.index = FragmentIndex::from_file(path)?;
+    /// Reload the database from a file, reporting which entries changed
+    ///
+    /// An index that can't be read or parsed is an error and leaves the
+    /// database as it was. Fragments that fail to load are listed in
+    /// [`FragmentReload::failed`] while the others are used.
+    pub fn reload(&mut self, path: &Path) -> Result<FragmentReload, FragmentError> {
+        let index = FragmentIndex::from_file(path)?;
+        let before = self.entry_shas();
+
+        self.index = index;
+        self.drivers = build_driver_registry(&self.index);
         self.base_dir = path.parent().unwrap_or(Path::new(".")).to_path_buf();
         self.fragments.clear();
         self.lookup_cache.clear();
-        self.load_all_fragments()?;
-        Ok(())
+        let failed = self.load_indexed_fragments();
+        let after = self.entry_shas();
+
+        let mut reload = FragmentReload { failed, total: self.index.fragment.len(), ..Default::default() };
+        for (key, sha) in &after {
+            match before.get(key) {
+                None => reload.added.push(key.clone()),
+                Some(old) if old != sha => reload.changed.push(key.clone()),
+                Some(_) => {}
+            }
+        }
+        reload.removed = before.into_keys().filter(|key| !after.contains_key(key)).collect();
+        Ok(reload)
     }
 
     /// Add a fragment from an HCDF content string (for remote loading)
@@ -335,8 +458,10 @@ impl FragmentDatabase {
                     name: m.name,
                     role: None,
                     hwid: m.hwid,
+                    enabled: m.enabled,
                     description: m.description,
                     pose_cg: m.pose_cg,
+                    bbox: m.bbox,
                     mass: m.mass,
                     board: m.board,
                     software: m.software,
@@ -363,6 +488,7 @@ impl FragmentDatabase {
             ports: comp.port,
             sensors: comp.sensor,
             hcdf_path: source_path.clone(),
+            sha: sha256_hex(hcdf_content.as_bytes()),
         };
 
         self.fragments.insert(source_path.clone(), fragment);
@@ -478,4 +604,81 @@ hcdf = "optical_flow.hcdf"
         assert_eq!(fragment.frames[0].name, "sensor_frame");
         assert_eq!(fragment.frames[0].description, Some("Sensor reference frame".to_string()));
     }
+
+    #[test]
+    fn test_reload_reports_changes_and_skips_bad_fragments() {
+        let dir = tempfile::tempdir().unwrap();
+        let comp = |name: &str| format!(r#"<hcdf version="1.2"><comp name="{}"/></hcdf>"#, name);
+        std::fs::write(dir.path().join("a.hcdf"), comp("a")).unwrap();
+        std::fs::write(dir.path().join("b.hcdf"), comp("b")).unwrap();
+        let index = dir.path().join("index.toml");
+        std::fs::write(&index, r#"
+[[fragment]]
+board = "board_a"
+hcdf = "a.hcdf"
+
+[[fragment]]
+board = "board_b"
+app = "flow"
+hcdf = "b.hcdf"
+"#).unwrap();
+        let mut db = FragmentDatabase::from_file(&index).unwrap();
+
+        let reload = db.reload(&index).unwrap();
+        assert!(!reload.has_changes());
+        assert!(reload.partial_error().is_none());
+
+        // a.hcdf changes, board_b is dropped, board_c points at a broken file
+        std::fs::write(dir.path().join("a.hcdf"), comp("a2")).unwrap();
+        std::fs::write(dir.path().join("c.hcdf"), "<hcdf").unwrap();
+        std::fs::write(&index, r#"
+[[fragment]]
+board = "board_a"
+hcdf = "a.hcdf"
+
+[[fragment]]
+board = "Board_C"
+app = "nav"
+hcdf = "c.hcdf"
+"#).unwrap();
+        let reload = db.reload(&index).unwrap();
+        assert_eq!(reload.changed, [("board_a".to_string(), "*".to_string())]);
+        assert_eq!(reload.removed, [("board_b".to_string(), "flow".to_string())]);
+        assert_eq!(reload.added, [("board_c".to_string(), "nav".to_string())]);
+        assert!(matches!(reload.failed[..], [FragmentError::LoadFailed { .. }]));
+        assert!(matches!(reload.partial_error(), Some(FragmentError::PartialReload { failed: 1, total: 2 })));
+        assert!(reload.affects("BOARD_A", "anything"));
+        assert!(reload.affects("board_b", "flow"));
+        assert!(!reload.affects("board_b", "other"));
+
+        // The good fragment is in use despite the bad one
+        assert_eq!(db.find_fragment("board_a", "x").unwrap().name, "a2");
+        assert!(db.find_fragment("board_c", "nav").is_none());
+
+        // A broken index keeps what was loaded
+        std::fs::write(&index, "[[fragment").unwrap();
+        assert!(db.reload(&index).is_err());
+        assert_eq!(db.find_fragment("board_a", "x").unwrap().name, "a2");
+    }
+
+    #[test]
+    fn test_index_extends_driver_registry() {
+        let toml = r#"
+version = "1.0"
+
+[[driver]]
+name = "vl53l1x"
+label = "ST VL53L1X"
+kind = "Time-of-Flight"
+sensor_types = ["tof"]
+"#;
+
+        let index = FragmentIndex::from_toml(toml).unwrap();
+        assert_eq!(index.driver.len(), 1);
+
+        let db = FragmentDatabase::new(index, PathBuf::new());
+        assert_eq!(db.drivers().display_name("vl53l1x"), "ST VL53L1X (Time-of-Flight)");
+        // Built-in drivers are still available
+        assert!(db.drivers().get("bmi08x").is_some());
+    }
 }
@@ -0,0 +1,208 @@
+//! Reference frame hierarchy
+//!
+//! A `<frame>` pose is relative to the device origin unless the frame names a
+//! `parent` frame in the same device, in which case it is relative to that
+//! frame. Chains can be any depth (lens -> sensor -> mount) but must not
+//! loop or reference a frame that doesn't exist.
+//!
+//! The resolver works on anything implementing `FrameNode`, so the daemon,
+//! the web frontend and the standalone viewer share one implementation.
+
+use thiserror::Error;
+
+use crate::device::DeviceFrame;
+use crate::hcdf::{Frame, Pose};
+
+/// Invalid frame hierarchy
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum FrameError {
+    #[error("frame '{frame}' references unknown parent frame '{parent}'")]
+    MissingParent { frame: String, parent: String },
+    #[error("frame parent chain forms a cycle: {}", .0.join(" -> "))]
+    Cycle(Vec<String>),
+}
+
+/// A frame that may be positioned relative to another frame
+pub trait FrameNode {
+    fn frame_name(&self) -> &str;
+    /// Name of the frame this one is relative to (None = device origin)
+    fn parent_frame(&self) -> Option<&str>;
+    /// Pose relative to the parent frame (or device origin)
+    fn local_pose(&self) -> Pose;
+}
+
+impl FrameNode for Frame {
+    fn frame_name(&self) -> &str {
+        &self.name
+    }
+
+    fn parent_frame(&self) -> Option<&str> {
+        self.parent.as_deref()
+    }
+
+    fn local_pose(&self) -> Pose {
+        self.parse_pose().unwrap_or_default()
+    }
+}
+
+impl FrameNode for DeviceFrame {
+    fn frame_name(&self) -> &str {
+        &self.name
+    }
+
+    fn parent_frame(&self) -> Option<&str> {
+        self.parent.as_deref()
+    }
+
+    fn local_pose(&self) -> Pose {
+        self.pose.map(Pose::from_array).unwrap_or_default()
+    }
+}
+
+/// Index of each frame's parent, checking that every parent exists and no
+/// chain loops
+fn parent_indices<F: FrameNode>(frames: &[F]) -> Result<Vec<Option<usize>>, FrameError> {
+    let parents = frames
+        .iter()
+        .map(|frame| {
+            let Some(parent) = frame.parent_frame() else {
+                return Ok(None);
+            };
+            frames
+                .iter()
+                .position(|f| f.frame_name() == parent)
+                .map(Some)
+                .ok_or_else(|| FrameError::MissingParent {
+                    frame: frame.frame_name().to_string(),
+                    parent: parent.to_string(),
+                })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for start in 0..frames.len() {
+        let mut chain = vec![start];
+        let mut current = start;
+        while let Some(parent) = parents[current] {
+            if let Some(pos) = chain.iter().position(|&i| i == parent) {
+                let mut names: Vec<String> = chain[pos..].iter().map(|&i| frames[i].frame_name().to_string()).collect();
+                names.push(frames[parent].frame_name().to_string());
+                return Err(FrameError::Cycle(names));
+            }
+            chain.push(parent);
+            current = parent;
+        }
+    }
+
+    Ok(parents)
+}
+
+/// Check that every parent reference resolves and no chain loops
+pub fn validate_frames<F: FrameNode>(frames: &[F]) -> Result<(), FrameError> {
+    parent_indices(frames).map(|_| ())
+}
+
+/// Device-relative pose of each frame, in input order
+pub fn resolve_frame_poses<F: FrameNode>(frames: &[F]) -> Result<Vec<Pose>, FrameError> {
+    let parents = parent_indices(frames)?;
+    let mut resolved: Vec<Option<Pose>> = vec![None; frames.len()];
+
+    fn resolve<F: FrameNode>(i: usize, frames: &[F], parents: &[Option<usize>], resolved: &mut [Option<Pose>]) -> Pose {
+        if let Some(pose) = &resolved[i] {
+            return pose.clone();
+        }
+        let local = frames[i].local_pose();
+        let pose = match parents[i] {
+            Some(parent) => resolve(parent, frames, parents, resolved).compose(&local),
+            None => local,
+        };
+        resolved[i] = Some(pose.clone());
+        pose
+    }
+
+    Ok((0..frames.len())
+        .map(|i| resolve(i, frames, &parents, &mut resolved))
+        .collect())
+}
+
+/// Frames in hierarchy order as (index, depth) pairs: each frame follows its
+/// parent, children keep their input order. An invalid hierarchy is listed
+/// flat in input order.
+pub fn frame_tree_order<F: FrameNode>(frames: &[F]) -> Vec<(usize, usize)> {
+    let Ok(parents) = parent_indices(frames) else {
+        return (0..frames.len()).map(|i| (i, 0)).collect();
+    };
+
+    fn visit(i: usize, depth: usize, parents: &[Option<usize>], order: &mut Vec<(usize, usize)>) {
+        order.push((i, depth));
+        for child in (0..parents.len()).filter(|&c| parents[c] == Some(i)) {
+            visit(child, depth + 1, parents, order);
+        }
+    }
+
+    let mut order = Vec::with_capacity(frames.len());
+    for root in (0..frames.len()).filter(|&i| parents[i].is_none()) {
+        visit(root, 0, &parents, &mut order);
+    }
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::FRAC_PI_2;
+
+    fn frame(name: &str, parent: Option<&str>, pose: [f64; 6]) -> DeviceFrame {
+        DeviceFrame {
+            name: name.to_string(),
+            description: None,
+            pose: Some(pose),
+            parent: parent.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_three_deep_chain() {
+        // Listed child-first to check order doesn't matter
+        let frames = vec![
+            frame("lens", Some("sensor"), [0.0, 0.0, 0.005, 0.0, 0.0, 0.0]),
+            frame("sensor", Some("mount"), [0.01, 0.0, 0.0, 0.0, 0.0, 0.0]),
+            frame("mount", None, [0.1, 0.0, 0.0, 0.0, 0.0, FRAC_PI_2]),
+            frame("imu", None, [0.0, 0.0, 0.01, 0.0, 0.0, 0.0]),
+        ];
+
+        let poses = resolve_frame_poses(&frames).unwrap();
+        // Mount is yawed 90 degrees, so the sensor's +X offset becomes +Y
+        let sensor = poses[1].to_array();
+        assert!((sensor[0] - 0.1).abs() < 1e-9);
+        assert!((sensor[1] - 0.01).abs() < 1e-9);
+        assert!((sensor[5] - FRAC_PI_2).abs() < 1e-9);
+        let lens = poses[0].to_array();
+        assert!((lens[1] - 0.01).abs() < 1e-9);
+        assert!((lens[2] - 0.005).abs() < 1e-9);
+        assert!((poses[3].z - 0.01).abs() < 1e-9);
+
+        assert_eq!(frame_tree_order(&frames), vec![(2, 0), (1, 1), (0, 2), (3, 0)]);
+    }
+
+    #[test]
+    fn test_cycle_and_missing_parent_errors() {
+        let frames = vec![
+            frame("a", Some("c"), [0.0; 6]),
+            frame("b", Some("a"), [0.0; 6]),
+            frame("c", Some("b"), [0.0; 6]),
+        ];
+        let err = validate_frames(&frames).unwrap_err();
+        assert_eq!(
+            err,
+            FrameError::Cycle(vec!["a".into(), "c".into(), "b".into(), "a".into()])
+        );
+        assert!(resolve_frame_poses(&frames).is_err());
+        assert_eq!(frame_tree_order(&frames), vec![(0, 0), (1, 0), (2, 0)]);
+
+        let frames = vec![frame("lens", Some("sensor"), [0.0; 6])];
+        assert_eq!(
+            validate_frames(&frames).unwrap_err().to_string(),
+            "frame 'lens' references unknown parent frame 'sensor'"
+        );
+    }
+}
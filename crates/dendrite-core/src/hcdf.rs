@@ -133,12 +133,15 @@
 
 use quick_xml::de::from_str;
 use quick_xml::se::Serializer;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::path::Path;
 use thiserror::Error;
 
-use crate::device::Device;
+use crate::device::{Device, DeviceId, PoseConstraint};
+use crate::extensions::{self, HcdfExtensions};
+use crate::quantity::{self, Quantity, RatedQuantity};
 
 #[derive(Error, Debug)]
 pub enum HcdfError {
@@ -153,6 +156,13 @@ pub enum HcdfError {
 }
 
 /// Pose in 3D space (x, y, z, roll, pitch, yaw)
+///
+/// Orientation may instead come as a quaternion, which is then kept as
+/// given and used for every rotation; roll, pitch and yaw are derived from
+/// it for display. Clear `quaternion` before editing the angles.
+///
+/// Angles are always held in radians; `degrees` only says how the pose
+/// string writes them.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Pose {
     #[serde(default)]
@@ -167,6 +177,12 @@ pub struct Pose {
     pub pitch: f64,
     #[serde(default)]
     pub yaw: f64,
+    /// Orientation as given in the document, [w, x, y, z]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quaternion: Option<[f64; 4]>,
+    /// The document wrote the angles in degrees
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub degrees: bool,
 }
 
 impl Pose {
@@ -178,12 +194,191 @@ impl Pose {
             roll: arr[3],
             pitch: arr[4],
             yaw: arr[5],
+            quaternion: None,
+            degrees: false,
         }
     }
 
+    /// Pose with a quaternion orientation [w, x, y, z], kept as given
+    pub fn from_quaternion(position: [f64; 3], quaternion: [f64; 4]) -> Self {
+        use std::f64::consts::{FRAC_PI_2, PI, TAU};
+
+        let [w, x, y, z] = normalized(quaternion);
+        let sin_pitch = 2.0 * (w * y - z * x);
+        let (roll, pitch, yaw) = if sin_pitch.abs() >= 1.0 - 1e-12 {
+            // Pitched straight up or down only roll - yaw is defined; put it
+            // all in yaw rather than let rounding pick ±180° for both
+            let yaw = -2.0 * sin_pitch.signum() * x.atan2(w);
+            (0.0, FRAC_PI_2.copysign(sin_pitch), (yaw + PI).rem_euclid(TAU) - PI)
+        } else {
+            (
+                (2.0 * (w * x + y * z)).atan2(1.0 - 2.0 * (x * x + y * y)),
+                sin_pitch.asin(),
+                (2.0 * (w * z + x * y)).atan2(1.0 - 2.0 * (y * y + z * z)),
+            )
+        };
+        Self { x: position[0], y: position[1], z: position[2], roll, pitch, yaw, quaternion: Some(quaternion), degrees: false }
+    }
+
+    /// Position and roll/pitch/yaw; the angles are derived from the
+    /// quaternion if there is one
     pub fn to_array(&self) -> [f64; 6] {
         [self.x, self.y, self.z, self.roll, self.pitch, self.yaw]
     }
+
+    /// Format as an HCDF pose string, "x y z roll pitch yaw" (with `deg`
+    /// suffixes if `degrees` is set) or "x y z qw qx qy qz" for a quaternion pose
+    pub fn to_pose_string(&self) -> String {
+        match self.quaternion {
+            Some([w, x, y, z]) => format!("{} {} {} {} {} {} {}", self.x, self.y, self.z, w, x, y, z),
+            None if self.degrees => format!(
+                "{} {} {} {}deg {}deg {}deg",
+                self.x,
+                self.y,
+                self.z,
+                rounded_degrees(self.roll),
+                rounded_degrees(self.pitch),
+                rounded_degrees(self.yaw)
+            ),
+            None => format!("{} {} {} {} {} {}", self.x, self.y, self.z, self.roll, self.pitch, self.yaw),
+        }
+    }
+
+    /// Format as the pose string replacing `original`, in the form it was
+    /// written in: degrees stay degrees and a quaternion stays a quaternion.
+    /// `original` itself is returned if this is the pose it describes.
+    pub fn to_pose_string_like(&self, original: Option<&str>) -> String {
+        let Some((text, previous)) = original.and_then(|text| Some((text, parse_pose_result(text).ok()?))) else {
+            return self.to_pose_string();
+        };
+        let same = previous.to_array().iter().zip(self.to_array()).all(|(a, b)| (a - b).abs() < 1e-9);
+        if same && (self.quaternion.is_none() || self.quaternion == previous.quaternion) {
+            return text.to_string();
+        }
+        let mut pose = self.clone();
+        pose.degrees = previous.degrees;
+        if previous.quaternion.is_some() && pose.quaternion.is_none() {
+            pose.quaternion = Some(pose.orientation());
+        }
+        pose.to_pose_string()
+    }
+
+    /// Unit quaternion [w, x, y, z] of the orientation
+    pub fn orientation(&self) -> [f64; 4] {
+        if let Some(quaternion) = self.quaternion {
+            return normalized(quaternion);
+        }
+        let (sr, cr) = (self.roll / 2.0).sin_cos();
+        let (sp, cp) = (self.pitch / 2.0).sin_cos();
+        let (sy, cy) = (self.yaw / 2.0).sin_cos();
+        [
+            cr * cp * cy + sr * sp * sy,
+            sr * cp * cy - cr * sp * sy,
+            cr * sp * cy + sr * cp * sy,
+            cr * cp * sy - sr * sp * cy,
+        ]
+    }
+
+    /// Rotation matrix (row-major) for roll/pitch/yaw applied as Rz * Ry * Rx,
+    /// or for the quaternion if there is one
+    pub fn rotation_matrix(&self) -> [[f64; 3]; 3] {
+        if let Some(quaternion) = self.quaternion {
+            let [w, x, y, z] = normalized(quaternion);
+            return [
+                [1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y - w * z), 2.0 * (x * z + w * y)],
+                [2.0 * (x * y + w * z), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z - w * x)],
+                [2.0 * (x * z - w * y), 2.0 * (y * z + w * x), 1.0 - 2.0 * (x * x + y * y)],
+            ];
+        }
+        let (sr, cr) = self.roll.sin_cos();
+        let (sp, cp) = self.pitch.sin_cos();
+        let (sy, cy) = self.yaw.sin_cos();
+        [
+            [cy * cp, cy * sp * sr - sy * cr, cy * sp * cr + sy * sr],
+            [sy * cp, sy * sp * sr + cy * cr, sy * sp * cr - cy * sr],
+            [-sp, cp * sr, cp * cr],
+        ]
+    }
+
+    /// Pose of `child` (expressed in this pose's frame) in this pose's parent frame
+    ///
+    /// The result has a quaternion if either input does.
+    pub fn compose(&self, child: &Pose) -> Pose {
+        let r = self.rotation_matrix();
+        let offset = [child.x, child.y, child.z];
+        let rotated: Vec<f64> = r.iter().map(|row| (0..3).map(|k| row[k] * offset[k]).sum()).collect();
+        let position = [self.x + rotated[0], self.y + rotated[1], self.z + rotated[2]];
+
+        if self.quaternion.is_some() || child.quaternion.is_some() {
+            let ([aw, ax, ay, az], [bw, bx, by, bz]) = (self.orientation(), child.orientation());
+            let product = [
+                aw * bw - ax * bx - ay * by - az * bz,
+                aw * bx + ax * bw + ay * bz - az * by,
+                aw * by - ax * bz + ay * bw + az * bx,
+                aw * bz + ax * by - ay * bx + az * bw,
+            ];
+            return Self::from_quaternion(position, product);
+        }
+
+        let c = child.rotation_matrix();
+        let mut m = [[0.0; 3]; 3];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..3).map(|k| r[i][k] * c[k][j]).sum();
+            }
+        }
+        Self::from_matrix(&m, position)
+    }
+
+    /// Inverse transform, so that `p.compose(&p.inverse())` is the identity
+    pub fn inverse(&self) -> Pose {
+        let r = self.rotation_matrix();
+        let mut t = [[0.0; 3]; 3];
+        for (i, row) in t.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = r[j][i];
+            }
+        }
+        let offset = [self.x, self.y, self.z];
+        let rotated: Vec<f64> = t.iter().map(|row| (0..3).map(|k| row[k] * offset[k]).sum()).collect();
+        let position = [-rotated[0], -rotated[1], -rotated[2]];
+
+        match self.quaternion {
+            Some(_) => {
+                let [w, x, y, z] = self.orientation();
+                Self::from_quaternion(position, [w, -x, -y, -z])
+            }
+            None => Self::from_matrix(&t, position),
+        }
+    }
+
+    fn from_matrix(m: &[[f64; 3]; 3], position: [f64; 3]) -> Pose {
+        Pose {
+            x: position[0],
+            y: position[1],
+            z: position[2],
+            roll: m[2][1].atan2(m[2][2]),
+            pitch: (-m[2][0]).clamp(-1.0, 1.0).asin(),
+            yaw: m[1][0].atan2(m[0][0]),
+            quaternion: None,
+            degrees: false,
+        }
+    }
+}
+
+/// An angle in degrees, rounded so radians converted back and forth print
+/// as e.g. "90" rather than "90.00000000000001"
+fn rounded_degrees(radians: f64) -> f64 {
+    (radians.to_degrees() * 1e9).round() / 1e9
+}
+
+/// `q` scaled to unit length; parsing rejects zero quaternions
+fn normalized(q: [f64; 4]) -> [f64; 4] {
+    let norm = q.iter().map(|c| c * c).sum::<f64>().sqrt();
+    if norm == 0.0 {
+        return [1.0, 0.0, 0.0, 0.0];
+    }
+    q.map(|c| c / norm)
 }
 
 /// Software running on a device
@@ -205,13 +400,28 @@ pub struct Software {
 }
 
 /// Discovery information embedded in HCDF
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Runtime state rather than part of the canonical document (see
+/// [`crate::runtime`]).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Discovered {
     pub ip: String,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub port: Option<u8>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub last_seen: Option<String>,
+    /// CAN bus address, for devices found on a CAN bus (`ip` is then unspecified)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub can: Option<DiscoveredCan>,
+}
+
+/// `<can bus="can0" node="12"/>` inside `<discovered>`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiscoveredCan {
+    #[serde(rename = "@bus")]
+    pub bus: String,
+    #[serde(rename = "@node")]
+    pub node: u8,
 }
 
 /// Network interface configuration
@@ -248,10 +458,25 @@ pub struct Mcu {
     pub name: String,
     #[serde(rename = "@hwid", default, skip_serializing_if = "Option::is_none")]
     pub hwid: Option<String>,
+    /// pose_cg was placed by the layout heuristic and hasn't been edited
+    #[serde(rename = "@provisional", default, skip_serializing_if = "Option::is_none")]
+    pub provisional: Option<bool>,
+    /// Pose edits keep the bounding box above the ground plane
+    #[serde(rename = "@keep_above_ground", default, skip_serializing_if = "Option::is_none")]
+    pub keep_above_ground: Option<bool>,
+    /// Hardware ID of the device this one rests on while its pose is edited
+    #[serde(rename = "@stack_on", default, skip_serializing_if = "Option::is_none")]
+    pub stack_on: Option<String>,
+    /// Switched off by a user; only `false` is written
+    #[serde(rename = "@enabled", default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub pose_cg: Option<String>,
+    /// Extents checked against keep-out volumes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bbox: Option<BoundingBox>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub mass: Option<f64>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -271,8 +496,35 @@ pub struct Mcu {
     pub frame: Vec<Frame>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub network: Option<Network>,
+    /// Port pose overrides edited locally; everything else about the
+    /// device's ports comes from its own HCDF
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub port: Vec<Port>,
 }
 
+impl Mcu {
+    /// Placement limits for pose edits, from `keep_above_ground` and `stack_on`
+    pub fn pose_constraint(&self) -> PoseConstraint {
+        PoseConstraint {
+            keep_above_ground: self.keep_above_ground == Some(true),
+            stack_on: self.stack_on.as_deref().map(DeviceId::from_hwid),
+        }
+    }
+
+    /// Store placement limits, leaving out the attributes of ones not set
+    pub fn set_pose_constraint(&mut self, constraint: &PoseConstraint) {
+        self.keep_above_ground = constraint.keep_above_ground.then_some(true);
+        self.stack_on = constraint.stack_on.as_ref().map(|id| id.as_str().to_string());
+    }
+}
+
+/// Element names of the `CompChild` variants; anything else inside a
+/// `<comp>` is set aside before parsing (see [`crate::extensions`])
+pub(crate) const COMP_CHILD_ELEMENTS: &[&str] = &[
+    "description", "pose_cg", "bbox", "mass", "board", "software", "discovered", "model", "visual", "frame", "network",
+    "port", "antenna", "sensor",
+];
+
 /// Child element types that can be interleaved in a Comp/Mcu
 /// Using $value enum pattern to handle non-consecutive XML elements
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -281,6 +533,7 @@ enum CompChild {
     Description(String),
     #[serde(rename = "pose_cg")]
     PoseCg(String),
+    Bbox(BoundingBox),
     Mass(f64),
     Board(String),
     Software(Software),
@@ -303,6 +556,8 @@ struct CompRaw {
     role: Option<String>,
     #[serde(rename = "@hwid", default)]
     hwid: Option<String>,
+    #[serde(rename = "@enabled", default)]
+    enabled: Option<bool>,
     #[serde(rename = "$value", default)]
     children: Vec<CompChild>,
 }
@@ -313,8 +568,10 @@ impl From<CompRaw> for Comp {
             name: raw.name,
             role: raw.role,
             hwid: raw.hwid,
+            enabled: raw.enabled,
             description: None,
             pose_cg: None,
+            bbox: None,
             mass: None,
             board: None,
             software: None,
@@ -332,6 +589,7 @@ impl From<CompRaw> for Comp {
             match child {
                 CompChild::Description(v) => comp.description = Some(v),
                 CompChild::PoseCg(v) => comp.pose_cg = Some(v),
+                CompChild::Bbox(v) => comp.bbox = Some(v),
                 CompChild::Mass(v) => comp.mass = Some(v),
                 CompChild::Board(v) => comp.board = Some(v),
                 CompChild::Software(v) => comp.software = Some(v),
@@ -359,10 +617,16 @@ pub struct Comp {
     pub role: Option<String>,
     #[serde(rename = "@hwid", default, skip_serializing_if = "Option::is_none")]
     pub hwid: Option<String>,
+    /// Switched off by a user; only `false` is written
+    #[serde(rename = "@enabled", default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub pose_cg: Option<String>,
+    /// Extents checked against keep-out volumes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bbox: Option<BoundingBox>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub mass: Option<f64>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -421,6 +685,9 @@ pub struct Visual {
     /// Toggle group name for visibility control (e.g., "case")
     #[serde(rename = "@toggle", default, skip_serializing_if = "Option::is_none")]
     pub toggle: Option<String>,
+    /// Initial hidden state of the toggle group when the file is loaded
+    #[serde(rename = "@default_hidden", default, skip_serializing_if = "Option::is_none")]
+    pub default_hidden: Option<bool>,
     /// Pose offset: "x y z roll pitch yaw" (meters, radians)
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub pose: Option<String>,
@@ -434,6 +701,21 @@ impl Visual {
     pub fn parse_pose(&self) -> Option<Pose> {
         self.pose.as_ref().and_then(|s| parse_pose_string(s))
     }
+
+    /// Whether this visual starts hidden (absent attribute means visible)
+    pub fn is_default_hidden(&self) -> bool {
+        self.default_hidden.unwrap_or(false)
+    }
+
+    /// Record the current hidden state of this visual's toggle group.
+    ///
+    /// The attribute is only written when the state differs from the loaded
+    /// default, so untouched visuals round-trip unchanged.
+    pub fn set_toggle_state(&mut self, hidden: bool) {
+        if hidden != self.is_default_hidden() {
+            self.default_hidden = Some(hidden);
+        }
+    }
 }
 
 /// Reference frame - a named coordinate frame with description
@@ -441,10 +723,14 @@ impl Visual {
 pub struct Frame {
     #[serde(rename = "@name")]
     pub name: String,
+    /// Another frame in the same device this frame is positioned relative to
+    #[serde(rename = "@parent", default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
     /// Human-readable description of this frame
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
-    /// Pose offset: "x y z roll pitch yaw" (meters, radians)
+    /// Pose offset: "x y z roll pitch yaw" (meters, radians), relative to
+    /// `parent` if set, otherwise to the device origin
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub pose: Option<String>,
 }
@@ -456,23 +742,83 @@ impl Frame {
     }
 }
 
-/// Parse a pose string "x y z roll pitch yaw" into a Pose struct
+/// Parse a pose string "x y z roll pitch yaw" or "x y z qw qx qy qz" into a
+/// Pose struct, or None if it doesn't parse (see [`parse_pose_result`] for why)
 pub fn parse_pose_string(s: &str) -> Option<Pose> {
-    let parts: Vec<f64> = s.split_whitespace()
-        .filter_map(|p| p.parse().ok())
-        .collect();
-    if parts.len() == 6 {
-        Some(Pose {
-            x: parts[0],
-            y: parts[1],
-            z: parts[2],
-            roll: parts[3],
-            pitch: parts[4],
-            yaw: parts[5],
-        })
-    } else {
-        None
+    parse_pose_result(s).ok()
+}
+
+/// Names of the pose fields, in order
+const POSE_FIELDS: [&str; 6] = ["x", "y", "z", "roll", "pitch", "yaw"];
+
+/// Names of the fields of a quaternion pose, in order
+const QUATERNION_POSE_FIELDS: [&str; 7] = ["x", "y", "z", "qw", "qx", "qy", "qz"];
+
+/// Why a pose string didn't parse
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum PoseParseError {
+    #[error("expected six values (x y z roll pitch yaw) or seven (x y z qw qx qy qz), found {0}")]
+    WrongCount(usize),
+    #[error("{field} \"{token}\" is not a number")]
+    NotANumber { field: &'static str, token: String },
+    #[error("{field} of {value} rad is outside ±2π (degrees need a \"deg\" suffix)")]
+    AngleOutOfRange { field: &'static str, value: f64 },
+    #[error("quaternion has zero length")]
+    ZeroQuaternion,
+}
+
+/// Parse a pose string "x y z roll pitch yaw" or "x y z qw qx qy qz"
+///
+/// Values are separated by whitespace, commas or both. Angles are in
+/// radians; one ending in `deg` or `°` is converted from degrees, and a
+/// `rad` suffix is accepted too. Angles must lie within ±2π, which catches
+/// degrees written without a suffix; a pose with any angle in degrees is
+/// written back in degrees (see [`Pose::degrees`]). A quaternion is kept as
+/// written (see [`Pose::quaternion`]) and only needs a non-zero length.
+pub fn parse_pose_result(s: &str) -> Result<Pose, PoseParseError> {
+    let tokens: Vec<&str> = s.split(|c: char| c == ',' || c.is_whitespace()).filter(|t| !t.is_empty()).collect();
+    let quaternion = tokens.len() == QUATERNION_POSE_FIELDS.len();
+    let fields: &[&str] = if quaternion { &QUATERNION_POSE_FIELDS } else { &POSE_FIELDS };
+
+    let mut values = Vec::with_capacity(tokens.len());
+    let mut degrees = false;
+    for (i, &token) in tokens.iter().enumerate() {
+        let field = fields.get(i).copied().unwrap_or("value");
+        let (number, scale) = if i >= 3 && !quaternion {
+            if let Some(number) = token.strip_suffix("deg").or_else(|| token.strip_suffix('°')) {
+                degrees = true;
+                (number, std::f64::consts::PI / 180.0)
+            } else {
+                (token.strip_suffix("rad").unwrap_or(token), 1.0)
+            }
+        } else {
+            (token, 1.0)
+        };
+        match number.parse::<f64>() {
+            Ok(value) if value.is_finite() => values.push(value * scale),
+            _ => return Err(PoseParseError::NotANumber { field, token: token.to_string() }),
+        }
+    }
+    if quaternion {
+        let q = [values[3], values[4], values[5], values[6]];
+        if q.iter().all(|&c| c == 0.0) {
+            return Err(PoseParseError::ZeroQuaternion);
+        }
+        return Ok(Pose::from_quaternion([values[0], values[1], values[2]], q));
     }
+    if values.len() != 6 {
+        return Err(PoseParseError::WrongCount(values.len()));
+    }
+
+    // A hair of slack so a rounded 2π still counts as in range
+    const ANGLE_LIMIT: f64 = std::f64::consts::TAU + 1e-6;
+    for (&field, &value) in POSE_FIELDS[3..].iter().zip(&values[3..]) {
+        if value.abs() > ANGLE_LIMIT {
+            return Err(PoseParseError::AngleOutOfRange { field, value });
+        }
+    }
+
+    Ok(Pose { degrees, ..Pose::from_array([values[0], values[1], values[2], values[3], values[4], values[5]]) })
 }
 
 /// Wired connection details
@@ -558,6 +904,29 @@ impl BoxGeometry {
     }
 }
 
+/// Device bounding box: a box of `size` centered at `pose`, relative to the
+/// device's pose_cg
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BoundingBox {
+    /// Pose offset: "x y z roll pitch yaw" (meters, radians)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pose: Option<String>,
+    /// Size as "x y z" in meters
+    pub size: String,
+}
+
+impl BoundingBox {
+    /// Parse size string into [x, y, z]
+    pub fn parse_size(&self) -> Option<[f64; 3]> {
+        BoxGeometry { size: self.size.clone() }.parse_size()
+    }
+
+    /// Parse the pose string into a Pose struct
+    pub fn parse_pose(&self) -> Option<Pose> {
+        self.pose.as_ref().and_then(|s| parse_pose_string(s))
+    }
+}
+
 /// Cylinder geometry
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CylinderGeometry {
@@ -653,124 +1022,52 @@ impl Geometry {
 
 // ============ PORTS ============
 
-/// Value with optional unit attribute
-/// Used for capability values like speed, bitrate, etc.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ValueWithUnit {
-    #[serde(rename = "@unit", default, skip_serializing_if = "Option::is_none")]
-    pub unit: Option<String>,
-    #[serde(rename = "$value")]
-    pub value: String,
+// Default units for capability values written without one
+fn in_mbps<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Quantity>, D::Error> {
+    quantity::deserialize_in(d, "Mbps")
 }
 
-impl ValueWithUnit {
-    /// Parse the value as f64
-    pub fn parse_value(&self) -> Option<f64> {
-        self.value.parse().ok()
-    }
+fn in_bps<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Quantity>, D::Error> {
+    quantity::deserialize_in(d, "bps")
+}
 
-    /// Parse the value as u64
-    pub fn parse_value_u64(&self) -> Option<u64> {
-        self.value.parse().ok()
-    }
+fn in_baud<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Quantity>, D::Error> {
+    quantity::deserialize_in(d, "baud")
 }
 
-/// Voltage capability with range (min/max) and nominal value
-/// Example: `<voltage unit="V" min="7" max="28">12</voltage>`
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct VoltageCapability {
-    #[serde(rename = "@unit", default, skip_serializing_if = "Option::is_none")]
-    pub unit: Option<String>,
-    #[serde(rename = "@min", default, skip_serializing_if = "Option::is_none")]
-    pub min: Option<f64>,
-    #[serde(rename = "@max", default, skip_serializing_if = "Option::is_none")]
-    pub max: Option<f64>,
-    /// Nominal voltage value
-    #[serde(rename = "$value", default)]
-    pub value: Option<String>,
-}
-
-impl VoltageCapability {
-    /// Format as display string (e.g., "12V (7-28V)")
-    pub fn to_display_string(&self) -> String {
-        let unit = self.unit.as_deref().unwrap_or("V");
-        let nominal = self.value.as_deref().unwrap_or("");
-        match (self.min, self.max, nominal.is_empty()) {
-            (Some(min), Some(max), false) => format!("{}{} ({}-{}{})", nominal, unit, min, max, unit),
-            (Some(min), Some(max), true) => format!("{}-{}{}", min, max, unit),
-            (None, Some(max), false) => format!("{}{} (max {}{})", nominal, unit, max, unit),
-            (Some(min), None, false) => format!("{}{} (min {}{})", nominal, unit, min, unit),
-            _ if !nominal.is_empty() => format!("{}{}", nominal, unit),
-            _ => String::new(),
-        }
-    }
+fn in_wh<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Quantity>, D::Error> {
+    quantity::deserialize_in(d, "Wh")
 }
 
-/// Current capability with max value
-/// Example: `<current unit="A" max="3"/>`
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CurrentCapability {
-    #[serde(rename = "@unit", default, skip_serializing_if = "Option::is_none")]
-    pub unit: Option<String>,
-    #[serde(rename = "@max", default, skip_serializing_if = "Option::is_none")]
-    pub max: Option<f64>,
-    /// Optional nominal value
-    #[serde(rename = "$value", default)]
-    pub value: Option<String>,
-}
-
-impl CurrentCapability {
-    /// Format as display string (e.g., "3A max")
-    pub fn to_display_string(&self) -> String {
-        let unit = self.unit.as_deref().unwrap_or("A");
-        match (&self.value, self.max) {
-            (Some(v), Some(max)) if !v.is_empty() => format!("{}{} (max {}{})", v, unit, max, unit),
-            (_, Some(max)) => format!("{}{} max", max, unit),
-            (Some(v), None) if !v.is_empty() => format!("{}{}", v, unit),
-            _ => String::new(),
-        }
-    }
+fn in_dbi<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Quantity>, D::Error> {
+    quantity::deserialize_in(d, "dBi")
 }
 
-/// Power capability with max value (in watts)
-/// Example: `<power unit="W" max="36"/>`
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PowerCapability {
-    #[serde(rename = "@unit", default, skip_serializing_if = "Option::is_none")]
-    pub unit: Option<String>,
-    #[serde(rename = "@max", default, skip_serializing_if = "Option::is_none")]
-    pub max: Option<f64>,
-    /// Optional nominal value
-    #[serde(rename = "$value", default)]
-    pub value: Option<String>,
-}
-
-impl PowerCapability {
-    /// Format as display string (e.g., "36W max")
-    pub fn to_display_string(&self) -> String {
-        let unit = self.unit.as_deref().unwrap_or("W");
-        match (&self.value, self.max) {
-            (Some(v), Some(max)) if !v.is_empty() => format!("{}{} (max {}{})", v, unit, max, unit),
-            (_, Some(max)) => format!("{}{} max", max, unit),
-            (Some(v), None) if !v.is_empty() => format!("{}{}", v, unit),
-            _ => String::new(),
-        }
-    }
+fn in_volts<'de, D: Deserializer<'de>>(d: D) -> Result<Option<RatedQuantity>, D::Error> {
+    quantity::deserialize_rated_in(d, "V")
+}
+
+fn in_amps<'de, D: Deserializer<'de>>(d: D) -> Result<Option<RatedQuantity>, D::Error> {
+    quantity::deserialize_rated_in(d, "A")
+}
+
+fn in_watts<'de, D: Deserializer<'de>>(d: D) -> Result<Option<RatedQuantity>, D::Error> {
+    quantity::deserialize_rated_in(d, "W")
 }
 
 /// Port capabilities - type-specific properties
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct PortCapabilities {
     // === Data Capabilities ===
-    /// Network speed (e.g., for ethernet) - typically in Mbps
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub speed: Option<ValueWithUnit>,
-    /// Bitrate (e.g., for CAN, serial) - typically in bps
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub bitrate: Option<ValueWithUnit>,
-    /// Baud rate (e.g., for UART/serial) - typically in baud
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub baud: Option<ValueWithUnit>,
+    /// Network speed (e.g., for ethernet) - Mbps if no unit is given
+    #[serde(default, deserialize_with = "in_mbps", skip_serializing_if = "Option::is_none")]
+    pub speed: Option<Quantity>,
+    /// Bitrate (e.g., for CAN, serial) - bps if no unit is given
+    #[serde(default, deserialize_with = "in_bps", skip_serializing_if = "Option::is_none")]
+    pub bitrate: Option<Quantity>,
+    /// Baud rate (e.g., for UART/serial) - baud if no unit is given
+    #[serde(default, deserialize_with = "in_baud", skip_serializing_if = "Option::is_none")]
+    pub baud: Option<Quantity>,
     /// Physical layer standard (e.g., "1000BASE-T", "1000BASE-T1", "100BASE-TX")
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub standard: Option<String>,
@@ -780,17 +1077,18 @@ pub struct PortCapabilities {
 
     // === Power Capabilities (available on any port type) ===
     /// Voltage with range (min/max) and nominal value
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub voltage: Option<VoltageCapability>,
-    /// Maximum current
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub current: Option<CurrentCapability>,
-    /// Maximum power in watts
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub power: Option<PowerCapability>,
-    /// Energy capacity for batteries
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub capacity: Option<ValueWithUnit>,
+    /// Example: `<voltage unit="V" min="7" max="28">12</voltage>`
+    #[serde(default, deserialize_with = "in_volts", skip_serializing_if = "Option::is_none")]
+    pub voltage: Option<RatedQuantity>,
+    /// Maximum current, e.g. `<current unit="A" max="3"/>`
+    #[serde(default, deserialize_with = "in_amps", skip_serializing_if = "Option::is_none")]
+    pub current: Option<RatedQuantity>,
+    /// Maximum power, e.g. `<power unit="W" max="36"/>`
+    #[serde(default, deserialize_with = "in_watts", skip_serializing_if = "Option::is_none")]
+    pub power: Option<RatedQuantity>,
+    /// Capacity for batteries (Wh if no unit is given, or charge such as mAh)
+    #[serde(default, deserialize_with = "in_wh", skip_serializing_if = "Option::is_none")]
+    pub capacity: Option<Quantity>,
     /// Physical connector type (e.g., "XT60", "RJ45", "USB-C", "JST-GH")
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub connector: Option<String>,
@@ -869,6 +1167,14 @@ impl Port {
         self.geometry.first()
     }
 
+    /// Set the pose, writing to the field this port's schema variant uses:
+    /// the legacy `pose` for ports without a fallback visual that already
+    /// carry a legacy pose or geometry, `fallback_visual` otherwise
+    pub fn set_pose(&mut self, pose: &Pose) {
+        let legacy = self.fallback_visual.is_none() && (self.pose.is_some() || !self.geometry.is_empty());
+        set_fallback_pose(&mut self.fallback_visual, &mut self.pose, legacy, pose);
+    }
+
     /// Check if this port uses a mesh reference (vs fallback visual)
     pub fn has_mesh_reference(&self) -> bool {
         self.visual.is_some() && self.mesh.is_some()
@@ -886,10 +1192,10 @@ pub struct AntennaCapabilities {
     /// Legacy: frequency with unit (deprecated, use band instead)
     /// Example: `<frequency unit="GHz">5.5</frequency>` -> "5.5 GHz"
     #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub frequency: Option<ValueWithUnit>,
-    /// Antenna gain in dBi
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub gain: Option<ValueWithUnit>,
+    pub frequency: Option<Quantity>,
+    /// Antenna gain - dBi if no unit is given
+    #[serde(default, deserialize_with = "in_dbi", skip_serializing_if = "Option::is_none")]
+    pub gain: Option<Quantity>,
     /// PHY/MAC standards (e.g., "802.11ax", "802.15.4", "Bluetooth 5.4")
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub standard: Vec<String>,
@@ -905,15 +1211,10 @@ impl AntennaCapabilities {
     /// Get all frequency bands, combining new `band` elements with legacy `frequency`
     pub fn get_bands(&self) -> Vec<String> {
         let mut bands = self.band.clone();
-        // Add legacy frequency if present
+        // Add legacy frequency if no band already names it ("5 GHz" vs "5000 MHz")
         if let Some(ref freq) = self.frequency {
-            let freq_str = if let Some(ref unit) = freq.unit {
-                format!("{} {}", freq.value, unit)
-            } else {
-                freq.value.clone()
-            };
-            if !bands.contains(&freq_str) {
-                bands.push(freq_str);
+            if !bands.iter().any(|b| b.parse::<Quantity>().is_ok_and(|q| q == *freq)) {
+                bands.push(freq.to_string());
             }
         }
         bands
@@ -974,12 +1275,37 @@ impl Antenna {
         self.geometry.as_ref()
     }
 
+    /// Set the pose, writing to the field this antenna's schema variant uses
+    /// (see `Port::set_pose`)
+    pub fn set_pose(&mut self, pose: &Pose) {
+        let legacy = self.fallback_visual.is_none() && (self.pose.is_some() || self.geometry.is_some());
+        set_fallback_pose(&mut self.fallback_visual, &mut self.pose, legacy, pose);
+    }
+
     /// Check if this antenna uses a mesh reference (vs fallback visual)
     pub fn has_mesh_reference(&self) -> bool {
         self.visual.is_some() && self.mesh.is_some()
     }
 }
 
+/// Write a port/antenna pose to either the legacy field or the fallback visual
+fn set_fallback_pose(
+    fallback_visual: &mut Option<FallbackVisual>,
+    legacy_pose: &mut Option<String>,
+    legacy: bool,
+    pose: &Pose,
+) {
+    let original = if legacy { legacy_pose.as_deref() } else { fallback_visual.as_ref().and_then(|v| v.pose.as_deref()) };
+    let pose = Some(pose.to_pose_string_like(original));
+    if legacy {
+        *legacy_pose = pose;
+    } else {
+        fallback_visual
+            .get_or_insert(FallbackVisual { pose: None, geometry: None })
+            .pose = pose;
+    }
+}
+
 // ============ AXIS ALIGNMENT ============
 
 /// Axis alignment for sensor driver transforms
@@ -1290,6 +1616,59 @@ pub struct Power {
     pub tank: Option<NamedElement>,
 }
 
+/// Keep-out volume: space no component may intrude into (prop disks,
+/// landing gear travel). Box, cylinder and sphere geometry are supported.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Keepout {
+    #[serde(rename = "@name")]
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Pose of the volume center: "x y z roll pitch yaw" (meters, radians)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pose: Option<String>,
+    pub geometry: Geometry,
+}
+
+impl Keepout {
+    /// Parse the pose string into a Pose struct
+    pub fn parse_pose(&self) -> Option<Pose> {
+        self.pose.as_ref().and_then(|s| parse_pose_string(s))
+    }
+}
+
+/// Named group of devices that move as one rigid unit (a camera pod)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Assembly {
+    #[serde(rename = "@name")]
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Pose of the assembly origin: "x y z roll pitch yaw" (meters, radians).
+    /// Without one the origin is the centroid of the members.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pose: Option<String>,
+    #[serde(default)]
+    pub member: Vec<AssemblyMember>,
+}
+
+/// Device belonging to an assembly, by hwid (or element name)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AssemblyMember {
+    #[serde(rename = "@device")]
+    pub device: String,
+}
+
+impl Assembly {
+    /// Parse the pose string into a Pose struct
+    pub fn parse_pose(&self) -> Option<Pose> {
+        self.pose.as_ref().and_then(|s| parse_pose_string(s))
+    }
+}
+
+/// HCDF schema versions this crate reads and writes
+pub const SUPPORTED_HCDF_VERSIONS: &[&str] = &["1.2", "2.0", "2.1"];
+
 /// Root HCDF document
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename = "hcdf")]
@@ -1314,6 +1693,17 @@ pub struct Hcdf {
 
     #[serde(default)]
     pub power: Vec<Power>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keepout: Vec<Keepout>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub assembly: Vec<Assembly>,
+
+    /// Elements and attributes outside the schema (vendor extensions),
+    /// written back by `to_xml`
+    #[serde(skip)]
+    pub extensions: HcdfExtensions,
 }
 
 impl Hcdf {
@@ -1327,12 +1717,22 @@ impl Hcdf {
             sensor: Vec::new(),
             motor: Vec::new(),
             power: Vec::new(),
+            keepout: Vec::new(),
+            assembly: Vec::new(),
+            extensions: HcdfExtensions::default(),
         }
     }
 
     /// Parse HCDF from XML string
+    ///
+    /// Elements and attributes the schema doesn't cover are kept in
+    /// [`Hcdf::extensions`] rather than dropped.
     pub fn from_xml(xml: &str) -> Result<Self, HcdfError> {
-        from_str(xml).map_err(|e| HcdfError::ParseError(e.to_string()))
+        // Malformed XML is left for the parser to report as it always has
+        let known = extensions::strip_unknown(xml).unwrap_or(Cow::Borrowed(xml));
+        let mut hcdf: Self = from_str(&known).map_err(|e| HcdfError::ParseError(e.to_string()))?;
+        hcdf.extensions = HcdfExtensions::collect(xml, &hcdf.serialize_known()?)?;
+        Ok(hcdf)
     }
 
     /// Parse HCDF from file
@@ -1342,13 +1742,27 @@ impl Hcdf {
     }
 
     /// Serialize to XML string with proper indentation for readability
+    ///
+    /// The inverse of [`Hcdf::from_xml`]: the output parses back into an
+    /// equal document. Attributes are written in the order the schema lists
+    /// them, and ports and antennas keep whichever of `<fallback_visual>` or
+    /// the legacy pose/geometry fields they were read with. Extensions are
+    /// written back verbatim after the known sibling they followed. Comments,
+    /// whitespace and the order of a device's known child elements are not
+    /// kept.
     pub fn to_xml(&self) -> Result<String, HcdfError> {
+        let known = self.serialize_known()?;
+        Ok(format!("<?xml version='1.0'?>\n{}", self.extensions.apply(&known)?))
+    }
+
+    /// The schema's part of the document
+    fn serialize_known(&self) -> Result<String, HcdfError> {
         let mut buffer = String::new();
         let mut ser = Serializer::new(&mut buffer);
         ser.indent(' ', 2);
         self.serialize(ser)
             .map_err(|e| HcdfError::SerializeError(e.to_string()))?;
-        Ok(format!("<?xml version='1.0'?>\n{}", buffer))
+        Ok(buffer)
     }
 
     /// Write to file
@@ -1386,23 +1800,30 @@ impl Hcdf {
                 sw.hash = device.firmware.image_hash.clone();
             }
             mcu.discovered = Some(Discovered {
-                ip: device.discovery.ip.to_string(),
+                ip: device.discovery.scoped_ip(),
                 port: device.discovery.switch_port,
                 last_seen: Some(device.discovery.last_seen.to_rfc3339()),
+                can: device.discovery.can.as_ref().map(|can| DiscoveredCan { bus: can.bus.clone(), node: can.node_id }),
             });
             // Update pose_cg from device pose (preserves position edits)
             if let Some(pose) = device.pose {
-                mcu.pose_cg = Some(format!("{} {} {} {} {} {}", pose[0], pose[1], pose[2], pose[3], pose[4], pose[5]));
+                mcu.pose_cg = Some(Pose::from_array(pose).to_pose_string_like(mcu.pose_cg.as_deref()));
+                mcu.provisional = device.pose_provisional.then_some(true);
             }
         } else {
             // Create new MCU
             let mcu = Mcu {
                 name: device.name.clone(),
                 hwid: Some(hwid),
+                provisional: device.pose_provisional.then_some(true),
+                keep_above_ground: device.constraint.keep_above_ground.then_some(true),
+                stack_on: device.constraint.stack_on.as_ref().map(|id| id.as_str().to_string()),
+                enabled: (!device.enabled).then_some(false),
                 description: None,
                 pose_cg: device.pose.map(|p| {
                     format!("{} {} {} {} {} {}", p[0], p[1], p[2], p[3], p[4], p[5])
                 }),
+                bbox: None,
                 mass: None,
                 board: device.info.board.clone(),
                 software: device.firmware.name.as_ref().map(|name| Software {
@@ -1413,14 +1834,16 @@ impl Hcdf {
                     params: None,
                 }),
                 discovered: Some(Discovered {
-                    ip: device.discovery.ip.to_string(),
+                    ip: device.discovery.scoped_ip(),
                     port: device.discovery.switch_port,
                     last_seen: Some(device.discovery.last_seen.to_rfc3339()),
+                    can: device.discovery.can.as_ref().map(|can| DiscoveredCan { bus: can.bus.clone(), node: can.node_id }),
                 }),
                 model: device.model_path.as_ref().map(|p| ModelRef { href: p.clone(), sha: None }),
                 visual: Vec::new(),
                 frame: Vec::new(),
                 network: None,
+                port: Vec::new(),
             };
             self.mcu.push(mcu);
 
@@ -1444,6 +1867,40 @@ impl Hcdf {
         }
     }
 
+    /// Merge a device's self-described HCDF fragment into its MCU entry.
+    ///
+    /// Description, mass, board, model, bounding box, visuals and frames come
+    /// from the fragment. Daemon-side state (name, pose_cg, software, discovered,
+    /// network, port pose overrides) and links are left alone, and toggle states recorded on
+    /// existing visuals carry over to visuals in the same toggle group.
+    /// Returns false if no MCU with `hwid` exists.
+    pub fn merge_device_fragment(&mut self, hwid: &str, fragment: &Comp) -> bool {
+        let Some(mcu) = self.mcu.iter_mut().find(|m| m.hwid.as_deref() == Some(hwid)) else {
+            return false;
+        };
+
+        let toggle_states: HashMap<String, bool> = mcu
+            .visual
+            .iter()
+            .filter_map(|v| Some((v.toggle.clone()?, v.default_hidden?)))
+            .collect();
+
+        mcu.description = fragment.description.clone().or(mcu.description.take());
+        mcu.mass = fragment.mass.or(mcu.mass);
+        mcu.board = fragment.board.clone().or(mcu.board.take());
+        mcu.model = fragment.model.clone();
+        mcu.bbox = fragment.bbox.clone().or(mcu.bbox.take());
+        mcu.visual = fragment.visual.clone();
+        mcu.frame = fragment.frame.clone();
+
+        for visual in &mut mcu.visual {
+            if let Some(hidden) = visual.toggle.as_ref().and_then(|t| toggle_states.get(t)) {
+                visual.set_toggle_state(*hidden);
+            }
+        }
+        true
+    }
+
     /// Remove stale devices (not seen within timeout)
     pub fn remove_stale_devices(&mut self, timeout_secs: i64) {
         let now = chrono::Utc::now();
@@ -1459,6 +1916,133 @@ impl Hcdf {
             true // Keep devices without discovery info
         });
     }
+
+    /// Remove the MCU or comp entries for a device (see `set_toggle_state` for ID matching).
+    /// Returns true if anything was removed.
+    pub fn remove_device(&mut self, device_id: &str) -> bool {
+        let before = self.mcu.len() + self.comp.len();
+        self.mcu
+            .retain(|m| !(m.hwid.as_deref() == Some(device_id) || m.name == device_id));
+        self.comp.retain(|c| {
+            !(c.hwid.as_deref() == Some(device_id)
+                || c.name == device_id
+                || format!("comp-{}", c.name) == device_id)
+        });
+        for assembly in &mut self.assembly {
+            assembly.member.retain(|m| m.device != device_id);
+        }
+        self.mcu.len() + self.comp.len() != before
+    }
+
+    /// Record the current hidden state of a device's toggle group on its visuals.
+    ///
+    /// `device_id` matches an MCU by hwid or name, or a comp by hwid, name, or the
+    /// synthetic `comp-<name>` scene-object ID. Returns the number of visuals touched.
+    pub fn set_toggle_state(&mut self, device_id: &str, toggle: &str, hidden: bool) -> usize {
+        let comp_id = |name: &str| format!("comp-{}", name);
+        let mcu_visuals = self
+            .mcu
+            .iter_mut()
+            .filter(|m| m.hwid.as_deref() == Some(device_id) || m.name == device_id)
+            .flat_map(|m| m.visual.iter_mut());
+        let comp_visuals = self
+            .comp
+            .iter_mut()
+            .filter(|c| {
+                c.hwid.as_deref() == Some(device_id) || c.name == device_id || comp_id(&c.name) == device_id
+            })
+            .flat_map(|c| c.visual.iter_mut());
+
+        let mut count = 0;
+        for visual in mcu_visuals.chain(comp_visuals) {
+            if visual.toggle.as_deref() == Some(toggle) {
+                visual.set_toggle_state(hidden);
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// Set the pose of a device's port (see `set_toggle_state` for ID matching).
+    ///
+    /// Comp ports are edited in place, keeping their schema variant. MCU ports
+    /// live in the device's own HCDF, so the pose is recorded as an override
+    /// entry on the MCU. Returns false if the device (or a comp's port) isn't found.
+    pub fn set_port_pose(&mut self, device_id: &str, port_name: &str, port_type: &str, pose: &Pose) -> bool {
+        if let Some(mcu) = self.mcu_mut(device_id) {
+            match mcu.port.iter_mut().find(|p| p.name == port_name) {
+                Some(port) => port.set_pose(pose),
+                None => {
+                    let mut port = Port {
+                        name: port_name.to_string(),
+                        port_type: port_type.to_string(),
+                        visual: None,
+                        mesh: None,
+                        capabilities: None,
+                        fallback_visual: None,
+                        pose: None,
+                        geometry: Vec::new(),
+                    };
+                    port.set_pose(pose);
+                    mcu.port.push(port);
+                }
+            }
+            return true;
+        }
+        self.comp_port_mut(device_id, port_name).map(|port| port.set_pose(pose)).is_some()
+    }
+
+    /// Undo a port pose edit. An MCU's override entry is removed; a comp port
+    /// has `original` written back. Returns false if nothing matched.
+    pub fn reset_port_pose(&mut self, device_id: &str, port_name: &str, original: &Pose) -> bool {
+        if let Some(mcu) = self.mcu_mut(device_id) {
+            let before = mcu.port.len();
+            mcu.port.retain(|p| p.name != port_name);
+            return mcu.port.len() != before;
+        }
+        self.comp_port_mut(device_id, port_name).map(|port| port.set_pose(original)).is_some()
+    }
+
+    /// Pose overrides recorded for an MCU, by port name
+    pub fn port_overrides(&self, hwid: &str) -> HashMap<String, Pose> {
+        self.mcu
+            .iter()
+            .filter(|m| m.hwid.as_deref() == Some(hwid))
+            .flat_map(|m| m.port.iter())
+            .filter_map(|p| Some((p.name.clone(), p.parse_pose()?)))
+            .collect()
+    }
+
+    /// Check every MCU and comp for broken frame parent references
+    pub fn validate_frames(&self) -> Result<(), HcdfError> {
+        let owners = self
+            .mcu
+            .iter()
+            .map(|m| ("mcu", &m.name, &m.frame))
+            .chain(self.comp.iter().map(|c| ("comp", &c.name, &c.frame)));
+        for (element, name, frames) in owners {
+            crate::frames::validate_frames(frames)
+                .map_err(|e| HcdfError::ValidationError(format!("{} '{}': {}", element, name, e)))?;
+        }
+        Ok(())
+    }
+
+    fn mcu_mut(&mut self, device_id: &str) -> Option<&mut Mcu> {
+        self.mcu
+            .iter_mut()
+            .find(|m| m.hwid.as_deref() == Some(device_id) || m.name == device_id)
+    }
+
+    fn comp_port_mut(&mut self, device_id: &str, port_name: &str) -> Option<&mut Port> {
+        let comp_id = |name: &str| format!("comp-{}", name);
+        self.comp
+            .iter_mut()
+            .filter(|c| {
+                c.hwid.as_deref() == Some(device_id) || c.name == device_id || comp_id(&c.name) == device_id
+            })
+            .flat_map(|c| c.port.iter_mut())
+            .find(|p| p.name == port_name)
+    }
 }
 
 impl Default for Hcdf {
@@ -1493,8 +2077,13 @@ mod tests {
         hcdf.mcu.push(Mcu {
             name: "test-mcu".to_string(),
             hwid: Some("0xaabbccdd".to_string()),
+            provisional: None,
+            keep_above_ground: None,
+            stack_on: None,
+            enabled: None,
             description: None,
             pose_cg: None,
+            bbox: None,
             mass: None,
             board: Some("test-board".to_string()),
             software: None,
@@ -1503,6 +2092,7 @@ mod tests {
             visual: Vec::new(),
             frame: Vec::new(),
             network: None,
+            port: Vec::new(),
         });
 
         let xml = hcdf.to_xml().unwrap();
@@ -1510,6 +2100,21 @@ mod tests {
         assert!(xml.contains("0xaabbccdd"));
     }
 
+    #[test]
+    fn test_mcu_pose_constraint_attributes() {
+        let xml = r#"<hcdf version="2.1"><mcu name="flow" hwid="0x2" keep_above_ground="true" stack_on="0x1"/></hcdf>"#;
+        let mut hcdf = Hcdf::from_xml(xml).unwrap();
+        let constraint = hcdf.mcu[0].pose_constraint();
+        assert!(constraint.keep_above_ground);
+        assert_eq!(constraint.stack_on, Some(DeviceId::from_hwid("0x1")));
+        assert!(hcdf.to_xml().unwrap().contains(r#"keep_above_ground="true" stack_on="0x1""#));
+
+        // Clearing a constraint drops its attribute
+        hcdf.mcu[0].set_pose_constraint(&PoseConstraint { keep_above_ground: true, stack_on: None });
+        let xml = hcdf.to_xml().unwrap();
+        assert!(xml.contains("keep_above_ground") && !xml.contains("stack_on"));
+    }
+
     #[test]
     fn test_parse_visual_and_frame() {
         let xml = r#"<?xml version='1.0'?>
@@ -1789,9 +2394,9 @@ mod tests {
         // Check ETH0 capabilities
         let eth_caps = eth0.capabilities.as_ref().unwrap();
         let speed = eth_caps.speed.as_ref().unwrap();
-        assert_eq!(speed.value, "1000");
+        assert_eq!(speed.value, 1000.0);
         assert_eq!(speed.unit, Some("Mbps".to_string()));
-        assert_eq!(speed.parse_value_u64(), Some(1000));
+        assert_eq!(speed, "1 Gbps".parse::<Quantity>().unwrap());
 
         // Check CAN0 - fallback visual with capabilities
         let can0 = &comp.port[1];
@@ -1802,7 +2407,7 @@ mod tests {
         // Check CAN0 capabilities
         let can_caps = can0.capabilities.as_ref().unwrap();
         let bitrate = can_caps.bitrate.as_ref().unwrap();
-        assert_eq!(bitrate.value, "500000");
+        assert_eq!(bitrate.value, 500000.0);
         assert_eq!(bitrate.unit, Some("bps".to_string()));
         assert_eq!(can_caps.protocol, vec!["CAN-FD".to_string()]);
 
@@ -1828,7 +2433,7 @@ mod tests {
         assert_eq!(uart0.port_type, "serial");
         let uart_caps = uart0.capabilities.as_ref().unwrap();
         let baud = uart_caps.baud.as_ref().unwrap();
-        assert_eq!(baud.value, "115200");
+        assert_eq!(baud.value, 115200.0);
         assert_eq!(baud.unit, Some("baud".to_string()));
         assert_eq!(uart_caps.protocol, vec!["RS-232".to_string()]);
     }
@@ -1919,20 +2524,20 @@ mod tests {
         assert_eq!(voltage.unit, Some("V".to_string()));
         assert_eq!(voltage.min, Some(7.0));
         assert_eq!(voltage.max, Some(28.0));
-        assert_eq!(voltage.value, Some("12".to_string()));
-        assert_eq!(voltage.to_display_string(), "12V (7-28V)");
+        assert_eq!(voltage.value, Some(12.0));
+        assert_eq!(voltage.to_display_string(), "12 V (7-28 V)");
 
         // Check current
         let current = caps.current.as_ref().unwrap();
         assert_eq!(current.unit, Some("A".to_string()));
         assert_eq!(current.max, Some(3.0));
-        assert_eq!(current.to_display_string(), "3A max");
+        assert_eq!(current.to_display_string(), "3 A max");
 
         // Check power
         let power = caps.power.as_ref().unwrap();
         assert_eq!(power.unit, Some("W".to_string()));
         assert_eq!(power.max, Some(36.0));
-        assert_eq!(power.to_display_string(), "36W max");
+        assert_eq!(power.to_display_string(), "36 W max");
 
         // Check connector
         assert_eq!(caps.connector, Some("XT30".to_string()));
@@ -1942,18 +2547,18 @@ mod tests {
         assert_eq!(eth_podl.name, "eth_podl");
         let eth_caps = eth_podl.capabilities.as_ref().unwrap();
         // Data capabilities
-        assert_eq!(eth_caps.speed.as_ref().unwrap().value, "1000");
+        assert_eq!(eth_caps.speed.as_ref().unwrap().value, 1000.0);
         assert_eq!(eth_caps.standard, Some("1000BASE-T1".to_string()));
         assert_eq!(eth_caps.protocol, vec!["PoDL".to_string()]);
         // Power capabilities
         let eth_voltage = eth_caps.voltage.as_ref().unwrap();
-        assert_eq!(eth_voltage.to_display_string(), "24V (12-48V)");
+        assert_eq!(eth_voltage.to_display_string(), "24 V (12-48 V)");
 
         // Check battery port with capacity
         let bat_out = &comp.port[2];
         let bat_caps = bat_out.capabilities.as_ref().unwrap();
         let capacity = bat_caps.capacity.as_ref().unwrap();
-        assert_eq!(capacity.value, "55.5");
+        assert_eq!(capacity.value, 55.5);
         assert_eq!(capacity.unit, Some("Wh".to_string()));
         assert_eq!(bat_caps.connector, Some("XT60".to_string()));
     }
@@ -2034,7 +2639,7 @@ mod tests {
         let gnss_caps = gnss0.capabilities.as_ref().unwrap();
         assert_eq!(gnss_caps.band, vec!["L1".to_string(), "L2".to_string(), "L5".to_string()]);
         let gain = gnss_caps.gain.as_ref().unwrap();
-        assert_eq!(gain.value, "3.5");
+        assert_eq!(gain.value, 3.5);
         assert_eq!(gain.unit, Some("dBi".to_string()));
         assert_eq!(gnss_caps.polarization, Some("RHCP".to_string()));
 
@@ -2080,6 +2685,55 @@ mod tests {
         assert!((size[0] - 0.003).abs() < 0.0001);
     }
 
+    #[test]
+    fn test_capability_quantities_keep_written_units() {
+        let xml = r#"<?xml version='1.0'?>
+<hcdf version="2.1">
+  <comp name="test" role="sensor">
+    <port name="pwr" type="POWER">
+      <capabilities>
+        <speed>100</speed>
+        <voltage min="4.5" max="5.5">5</voltage>
+        <current unit="mA" max="500"/>
+        <capacity unit="mAh">5000</capacity>
+      </capabilities>
+    </port>
+    <antenna name="WIFI0" type="wifi">
+      <capabilities>
+        <band>5 GHz</band>
+        <frequency unit="MHz">5000</frequency>
+        <gain>2</gain>
+      </capabilities>
+    </antenna>
+  </comp>
+</hcdf>"#;
+
+        let hcdf = Hcdf::from_xml(xml).unwrap();
+        let caps = hcdf.comp[0].port[0].capabilities.as_ref().unwrap();
+        // Fields without a unit take their default unit
+        assert_eq!(caps.speed.as_ref().unwrap().to_string(), "100 Mbps");
+        assert_eq!(caps.voltage.as_ref().unwrap().to_display_string(), "5 V (4.5-5.5 V)");
+        let current = caps.current.as_ref().unwrap();
+        assert_eq!(current.max_quantity(), Some("0.5 A".parse().unwrap()));
+        assert_eq!(current.to_display_string(), "500 mA max");
+        assert_eq!(caps.capacity.as_ref().unwrap().to_string(), "5 Ah");
+
+        let antenna_caps = hcdf.comp[0].antenna[0].capabilities.as_ref().unwrap();
+        assert_eq!(antenna_caps.gain.as_ref().unwrap().to_string(), "2 dBi");
+        // The legacy frequency is the band already listed
+        assert_eq!(antenna_caps.get_bands(), vec!["5 GHz".to_string()]);
+
+        // Export writes values and units as they were read
+        let out = hcdf.to_xml().unwrap();
+        assert!(out.contains("<speed>100</speed>"), "{out}");
+        assert!(out.contains(r#"<current unit="mA" max="500"/>"#) || out.contains(r#"<current unit="mA" max="500"></current>"#), "{out}");
+        assert!(out.contains(r#"<capacity unit="mAh">5000</capacity>"#), "{out}");
+        assert!(out.contains(r#"<frequency unit="MHz">5000</frequency>"#), "{out}");
+        let reparsed = Hcdf::from_xml(&out).unwrap();
+        let caps = reparsed.comp[0].port[0].capabilities.as_ref().unwrap();
+        assert_eq!(caps.voltage.as_ref().unwrap().to_display_string(), "5 V (4.5-5.5 V)");
+    }
+
     #[test]
     fn test_antenna_legacy_compatibility() {
         // Test that legacy antenna format still works (backwards compatibility)
@@ -2110,6 +2764,371 @@ mod tests {
         // No fallback_visual in legacy format
         assert!(antenna.fallback_visual.is_none());
     }
+
+    #[test]
+    fn test_visual_default_hidden() {
+        let xml = r#"<?xml version='1.0'?>
+<hcdf version="1.2">
+  <comp name="assembly" role="sensor">
+    <visual name="case" toggle="case" default_hidden="true">
+      <model href="models/case.glb"/>
+    </visual>
+    <visual name="lid" toggle="lid">
+      <model href="models/lid.glb"/>
+    </visual>
+  </comp>
+</hcdf>"#;
+
+        let hcdf = Hcdf::from_xml(xml).unwrap();
+        let visuals = &hcdf.comp[0].visual;
+        assert_eq!(visuals[0].default_hidden, Some(true));
+        assert!(visuals[0].is_default_hidden());
+        assert_eq!(visuals[1].default_hidden, None);
+        assert!(!visuals[1].is_default_hidden());
+
+        // Files without the attribute must not gain it on round-trip
+        let out = hcdf.to_xml().unwrap();
+        assert!(out.contains(r#"default_hidden="true""#));
+        assert_eq!(out.matches("default_hidden").count(), 1);
+    }
+
+    #[test]
+    fn test_merge_device_fragment_preserves_daemon_state() {
+        let xml = r#"<?xml version='1.0'?>
+<hcdf version="1.2">
+    <mcu name="spinali-001" hwid="0x1234">
+        <pose_cg>1 2 3 0 0 0</pose_cg>
+        <board>spinali</board>
+        <visual name="case" toggle="case" default_hidden="true">
+            <model href="old.glb"/>
+        </visual>
+    </mcu>
+    <link name="parent_to_spinali-001"/>
+</hcdf>"#;
+        let mut hcdf = Hcdf::from_xml(xml).unwrap();
+
+        let fragment_xml = r#"<?xml version='1.0'?>
+<hcdf version="1.2">
+    <comp name="spinali">
+        <description>SpinalI board</description>
+        <pose_cg>0 0 0 0 0 0</pose_cg>
+        <visual name="board"><model href="board.glb"/></visual>
+        <visual name="case" toggle="case"><model href="case.glb"/></visual>
+        <frame name="imu"><pose>0 0 0.01 0 0 0</pose></frame>
+    </comp>
+</hcdf>"#;
+        let fragment = Hcdf::from_xml(fragment_xml).unwrap().comp.remove(0);
+
+        assert!(hcdf.merge_device_fragment("0x1234", &fragment));
+        assert!(!hcdf.merge_device_fragment("0xdead", &fragment));
+
+        let mcu = &hcdf.mcu[0];
+        assert_eq!(mcu.name, "spinali-001");
+        assert_eq!(mcu.pose_cg.as_deref(), Some("1 2 3 0 0 0"));
+        assert_eq!(mcu.description.as_deref(), Some("SpinalI board"));
+        assert_eq!(mcu.visual.len(), 2);
+        assert_eq!(mcu.visual[1].model.as_ref().unwrap().href, "case.glb");
+        assert_eq!(mcu.visual[1].default_hidden, Some(true));
+        assert_eq!(mcu.frame.len(), 1);
+        assert_eq!(hcdf.link.len(), 1);
+    }
+
+    #[test]
+    fn test_remove_device() {
+        let xml = r#"<?xml version='1.0'?>
+<hcdf version="1.2">
+  <mcu name="spinali-001" hwid="0x12345678"/>
+  <mcu name="spinali-002" hwid="0x87654321"/>
+  <comp name="assembly" role="sensor"/>
+</hcdf>"#;
+
+        let mut hcdf = Hcdf::from_xml(xml).unwrap();
+        assert!(hcdf.remove_device("0x12345678"));
+        assert!(hcdf.remove_device("comp-assembly"));
+        assert!(!hcdf.remove_device("missing"));
+        assert_eq!(hcdf.mcu.len(), 1);
+        assert_eq!(hcdf.mcu[0].name, "spinali-002");
+        assert!(hcdf.comp.is_empty());
+    }
+
+    #[test]
+    fn test_set_toggle_state_only_writes_changes() {
+        let xml = r#"<?xml version='1.0'?>
+<hcdf version="1.2">
+  <comp name="assembly" role="sensor">
+    <visual name="case" toggle="case" default_hidden="true"/>
+    <visual name="lid" toggle="lid"/>
+    <visual name="board" toggle="board"/>
+  </comp>
+</hcdf>"#;
+
+        let mut hcdf = Hcdf::from_xml(xml).unwrap();
+        // Unchanged states leave the attribute alone
+        assert_eq!(hcdf.set_toggle_state("comp-assembly", "case", true), 1);
+        assert_eq!(hcdf.set_toggle_state("comp-assembly", "board", false), 1);
+        // Changed states are recorded
+        assert_eq!(hcdf.set_toggle_state("assembly", "lid", true), 1);
+        assert_eq!(hcdf.set_toggle_state("unknown", "lid", true), 0);
+
+        let visuals = &hcdf.comp[0].visual;
+        assert_eq!(visuals[0].default_hidden, Some(true));
+        assert_eq!(visuals[1].default_hidden, Some(true));
+        assert_eq!(visuals[2].default_hidden, None);
+
+        hcdf.set_toggle_state("assembly", "case", false);
+        assert_eq!(hcdf.comp[0].visual[0].default_hidden, Some(false));
+
+        let reparsed = Hcdf::from_xml(&hcdf.to_xml().unwrap()).unwrap();
+        assert_eq!(reparsed.comp[0].visual[0].default_hidden, Some(false));
+        assert_eq!(reparsed.comp[0].visual[1].default_hidden, Some(true));
+        assert_eq!(reparsed.comp[0].visual[2].default_hidden, None);
+    }
+
+    #[test]
+    fn test_frame_parent_round_trip_and_validation() {
+        let xml = r#"<?xml version='1.0'?>
+<hcdf version="2.1">
+  <comp name="camera">
+    <frame name="mount"><pose>0.1 0 0 0 0 0</pose></frame>
+    <frame name="sensor" parent="mount"><pose>0.01 0 0 0 0 0</pose></frame>
+    <frame name="lens" parent="sensor"><pose>0 0 0.005 0 0 0</pose></frame>
+  </comp>
+</hcdf>"#;
+        let hcdf = Hcdf::from_xml(xml).unwrap();
+        assert_eq!(hcdf.comp[0].frame[2].parent.as_deref(), Some("sensor"));
+        assert!(hcdf.validate_frames().is_ok());
+
+        let exported = Hcdf::from_xml(&hcdf.to_xml().unwrap()).unwrap();
+        let parents: Vec<_> = exported.comp[0].frame.iter().map(|f| f.parent.as_deref()).collect();
+        assert_eq!(parents, vec![None, Some("mount"), Some("sensor")]);
+
+        let mut broken = hcdf.clone();
+        broken.comp[0].frame[0].parent = Some("lens".to_string());
+        let err = broken.validate_frames().unwrap_err().to_string();
+        assert!(err.contains("comp 'camera'"), "{}", err);
+        assert!(err.contains("cycle"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_pose_result() {
+        let pose = parse_pose_result("  1 2\t3  0 0 0.5 ").unwrap();
+        assert_eq!(pose.to_array(), [1.0, 2.0, 3.0, 0.0, 0.0, 0.5]);
+        let pose = parse_pose_result("1, 2, 3, 0, 0, 0.5").unwrap();
+        assert_eq!(pose.to_array(), [1.0, 2.0, 3.0, 0.0, 0.0, 0.5]);
+        let pose = parse_pose_result("0,0,0,0rad,-90deg,180°").unwrap();
+        assert!((pose.pitch + std::f64::consts::FRAC_PI_2).abs() < 1e-12);
+        assert!((pose.yaw - std::f64::consts::PI).abs() < 1e-12);
+
+        assert_eq!(parse_pose_result("1 2 3 4 5").unwrap_err(), PoseParseError::WrongCount(5));
+        assert_eq!(parse_pose_result("").unwrap_err(), PoseParseError::WrongCount(0));
+        assert_eq!(parse_pose_result("0 0 0 1 0 0 0 0").unwrap_err(), PoseParseError::WrongCount(8));
+        assert_eq!(parse_pose_result("0 0 0 0 0 0 0").unwrap_err(), PoseParseError::ZeroQuaternion);
+        // Quaternion components take no units
+        assert_eq!(
+            parse_pose_result("0 0 0 1deg 0 0 0").unwrap_err(),
+            PoseParseError::NotANumber { field: "qw", token: "1deg".to_string() }
+        );
+        assert_eq!(
+            parse_pose_result("0 0 up 0 0 0").unwrap_err(),
+            PoseParseError::NotANumber { field: "z", token: "up".to_string() }
+        );
+        // Units only make sense on angles
+        assert_eq!(
+            parse_pose_result("1deg 0 0 0 0 0").unwrap_err(),
+            PoseParseError::NotANumber { field: "x", token: "1deg".to_string() }
+        );
+        assert_eq!(
+            parse_pose_result("0 0 0 0 0 NaN").unwrap_err(),
+            PoseParseError::NotANumber { field: "yaw", token: "NaN".to_string() }
+        );
+        assert_eq!(
+            parse_pose_result("0 0 0 0 0 90").unwrap_err(),
+            PoseParseError::AngleOutOfRange { field: "yaw", value: 90.0 }
+        );
+        assert!(parse_pose_result("0 0 0 0 0 6.283185").is_ok());
+
+        // The Option wrapper agrees
+        assert!(parse_pose_string("1 2 3 4 5").is_none());
+        assert!(parse_pose_string("1,2,3,0,0,0").is_some());
+    }
+
+    #[test]
+    fn test_quaternion_pose_keeps_steep_pitch() {
+        use std::f64::consts::{FRAC_1_SQRT_2, FRAC_PI_2};
+
+        // Pitched 90° up, where roll and yaw are degenerate
+        let text = format!("0.1 0 0.2 {} 0 {} 0", FRAC_1_SQRT_2, FRAC_1_SQRT_2);
+        let pose = parse_pose_result(&text).unwrap();
+        assert_eq!(pose.quaternion, Some([FRAC_1_SQRT_2, 0.0, FRAC_1_SQRT_2, 0.0]));
+        assert_eq!(pose.to_array(), [0.1, 0.0, 0.2, 0.0, FRAC_PI_2, 0.0]);
+
+        // Written back as given, so repeated round trips change nothing
+        assert_eq!(pose.to_pose_string(), text);
+        let xml = format!(r#"<hcdf version="2.0"><mcu name="imu"><pose_cg>{}</pose_cg></mcu></hcdf>"#, text);
+        let exported = Hcdf::from_xml(&xml).unwrap().to_xml().unwrap();
+        let reparsed = Hcdf::from_xml(&exported).unwrap();
+        let again = parse_pose_result(reparsed.mcu[0].pose_cg.as_deref().unwrap()).unwrap();
+        assert_eq!(again.quaternion, pose.quaternion);
+        assert_eq!(again.pitch, FRAC_PI_2);
+
+        // Mounting it keeps the quaternion and the sign of the pitch
+        let mounted = Pose { z: 0.5, ..Default::default() }.compose(&pose);
+        assert!(mounted.quaternion.is_some());
+        assert!((mounted.pitch - FRAC_PI_2).abs() < 1e-9);
+        assert!((mounted.z - 0.7).abs() < 1e-12);
+        let identity = pose.compose(&pose.inverse());
+        assert!(identity.to_array().iter().all(|v| v.abs() < 1e-9), "{:?}", identity);
+
+        // Straight down, and a quaternion that isn't unit length
+        let down = parse_pose_result("0 0 0 2 0 -2 0").unwrap();
+        assert_eq!(down.pitch, -FRAC_PI_2);
+        assert_eq!(down.quaternion, Some([2.0, 0.0, -2.0, 0.0]));
+
+        // Away from the poles the derived angles match the rotation
+        let tilted = Pose::from_array([0.0, 0.0, 0.0, 0.3, -0.4, 1.2]);
+        let [w, x, y, z] = tilted.orientation();
+        let derived = Pose::from_quaternion([0.0; 3], [w, x, y, z]);
+        for (a, b) in derived.to_array().iter().zip(tilted.to_array()) {
+            assert!((a - b).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_random_quaternions_survive_round_trip() {
+        // xorshift64, so failures reproduce
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 11) as f64 / (1u64 << 53) as f64 * 2.0 - 1.0
+        };
+        for _ in 0..1000 {
+            let q = [next(), next(), next(), next()];
+            if q.iter().map(|c| c * c).sum::<f64>() < 1e-6 {
+                continue;
+            }
+            let text = format!("{} {} {} {} {} {} {}", next(), next(), next(), q[0], q[1], q[2], q[3]);
+            let pose = parse_pose_result(&text).unwrap();
+            let again = parse_pose_result(&pose.to_pose_string()).unwrap();
+
+            // q and -q are the same rotation
+            let dot: f64 = pose.orientation().iter().zip(again.orientation()).map(|(a, b)| a * b).sum();
+            assert!(1.0 - dot.abs() < 1e-6, "{} -> {}", text, again.to_pose_string());
+            for (a, b) in pose.to_array().iter().zip(again.to_array()) {
+                assert!((a - b).abs() < 1e-6, "{} -> {}", text, again.to_pose_string());
+            }
+
+            // A move edited through roll/pitch/yaw is written back as a quaternion
+            let edited = Pose { x: again.x + 1.0, quaternion: None, ..again.clone() }.to_pose_string_like(Some(&text));
+            let edited = parse_pose_result(&edited).unwrap();
+            let dot: f64 = pose.orientation().iter().zip(edited.orientation()).map(|(a, b)| a * b).sum();
+            assert!(edited.quaternion.is_some() && 1.0 - dot.abs() < 1e-6, "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_degree_poses_are_written_in_degrees() {
+        let pose = parse_pose_result("1 0 0 0 -90deg 45°").unwrap();
+        assert!(pose.degrees);
+        assert!((pose.pitch + std::f64::consts::FRAC_PI_2).abs() < 1e-12);
+        assert_eq!(pose.to_pose_string(), "1 0 0 0deg -90deg 45deg");
+        assert!(!parse_pose_result("1 0 0 0 0 0.5").unwrap().degrees);
+
+        // Rewriting keeps the original's form, or the original itself if unchanged
+        let original = "1 0 0 0 -90deg 45°";
+        assert_eq!(pose.to_pose_string_like(Some(original)), original);
+        let moved = Pose { x: 2.0, degrees: false, ..pose.clone() };
+        assert_eq!(moved.to_pose_string_like(Some(original)), "2 0 0 0deg -90deg 45deg");
+        assert_eq!(moved.to_pose_string_like(None), moved.to_pose_string());
+        assert_eq!(moved.to_pose_string_like(Some("not a pose")), moved.to_pose_string());
+
+        let xml = r#"<hcdf version="2.0"><comp name="board"><port name="CAN0" type="CAN"><pose>0 0 0 0 0 90deg</pose></port></comp></hcdf>"#;
+        let mut hcdf = Hcdf::from_xml(xml).unwrap();
+        hcdf.comp[0].port[0].set_pose(&Pose::from_array([0.5, 0.0, 0.0, 0.0, 0.0, std::f64::consts::FRAC_PI_2]));
+        assert_eq!(hcdf.comp[0].port[0].pose.as_deref(), Some("0.5 0 0 0deg 0deg 90deg"));
+    }
+
+    #[test]
+    fn test_set_pose_keeps_schema_variant() {
+        let xml = r#"<?xml version='1.0'?>
+<hcdf version="2.0">
+  <comp name="board" role="compute">
+    <port name="ETH0" type="ethernet">
+      <fallback_visual>
+        <pose>0.01 0 0 0 0 0</pose>
+        <geometry><box><size>0.008 0.006 0.003</size></box></geometry>
+      </fallback_visual>
+    </port>
+    <port name="CAN0" type="CAN">
+      <pose>0.02 0 0 0 0 0</pose>
+      <geometry><box><size>0.008 0.006 0.003</size></box></geometry>
+    </port>
+    <antenna name="gnss" type="gnss">
+      <pose>0 0 0.01 0 0 0</pose>
+    </antenna>
+  </comp>
+</hcdf>"#;
+
+        let mut hcdf = Hcdf::from_xml(xml).unwrap();
+        let nudged = Pose { x: 0.012, ..Default::default() };
+        let comp = &mut hcdf.comp[0];
+        comp.port[0].set_pose(&nudged);
+        comp.port[1].set_pose(&nudged);
+        comp.antenna[0].set_pose(&nudged);
+
+        let reparsed = Hcdf::from_xml(&hcdf.to_xml().unwrap()).unwrap();
+        let comp = &reparsed.comp[0];
+        // Current schema: written to fallback_visual, geometry untouched
+        let fv = comp.port[0].fallback_visual.as_ref().unwrap();
+        assert_eq!(fv.pose.as_deref(), Some("0.012 0 0 0 0 0"));
+        assert!(fv.geometry.is_some());
+        assert!(comp.port[0].pose.is_none());
+        // Legacy schema: written to the legacy pose field
+        assert_eq!(comp.port[1].pose.as_deref(), Some("0.012 0 0 0 0 0"));
+        assert!(comp.port[1].fallback_visual.is_none());
+        assert_eq!(comp.antenna[0].pose.as_deref(), Some("0.012 0 0 0 0 0"));
+        assert!(comp.antenna[0].fallback_visual.is_none());
+    }
+
+    #[test]
+    fn test_port_pose_overrides() {
+        let xml = r#"<?xml version='1.0'?>
+<hcdf version="1.2">
+  <mcu name="spinali-001" hwid="0x1234">
+    <board>spinali</board>
+  </mcu>
+  <comp name="assembly" role="sensor">
+    <port name="ETH0" type="ethernet">
+      <pose>0.02 0 0 0 0 0</pose>
+    </port>
+  </comp>
+</hcdf>"#;
+
+        let mut hcdf = Hcdf::from_xml(xml).unwrap();
+        let pose = Pose { x: 0.025, ..Default::default() };
+
+        // MCU ports become override entries
+        assert!(hcdf.set_port_pose("0x1234", "ETH0", "ethernet", &pose));
+        assert!(hcdf.set_port_pose("0x1234", "ETH0", "ethernet", &pose));
+        assert_eq!(hcdf.mcu[0].port.len(), 1);
+        let reparsed = Hcdf::from_xml(&hcdf.to_xml().unwrap()).unwrap();
+        assert!((reparsed.port_overrides("0x1234")["ETH0"].x - 0.025).abs() < 1e-9);
+
+        // Comp ports are edited in place
+        assert!(hcdf.set_port_pose("comp-assembly", "ETH0", "ethernet", &pose));
+        assert_eq!(hcdf.comp[0].port[0].pose.as_deref(), Some("0.025 0 0 0 0 0"));
+        assert!(!hcdf.set_port_pose("comp-assembly", "CAN0", "CAN", &pose));
+        assert!(!hcdf.set_port_pose("unknown", "ETH0", "ethernet", &pose));
+
+        // Reset drops the MCU override and restores the comp pose
+        let original = Pose { x: 0.02, ..Default::default() };
+        assert!(hcdf.reset_port_pose("0x1234", "ETH0", &original));
+        assert!(!hcdf.reset_port_pose("0x1234", "ETH0", &original));
+        assert!(hcdf.port_overrides("0x1234").is_empty());
+        assert!(hcdf.reset_port_pose("assembly", "ETH0", &original));
+        assert_eq!(hcdf.comp[0].port[0].pose.as_deref(), Some("0.02 0 0 0 0 0"));
+    }
 }
 
     #[test]
@@ -2154,3 +3173,178 @@ mod tests {
         assert_eq!(comp.sensor.len(), 1);
         assert_eq!(comp.visual.len(), 1);
     }
+
+    /// Every `.hcdf` file in the repository, plus the synthetic fixtures
+    fn round_trip_fixtures() -> Vec<(String, String)> {
+        fn walk(dir: &Path, found: &mut Vec<(String, String)>) {
+            let Ok(entries) = std::fs::read_dir(dir) else { return };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let name = entry.file_name().to_string_lossy().to_string();
+                if path.is_dir() {
+                    if !name.starts_with('.') && name != "target" && name != "node_modules" {
+                        walk(&path, found);
+                    }
+                } else if path.extension().is_some_and(|ext| ext == "hcdf") {
+                    found.push((path.display().to_string(), std::fs::read_to_string(&path).unwrap()));
+                }
+            }
+        }
+
+        let mut fixtures = Vec::new();
+        walk(&Path::new(env!("CARGO_MANIFEST_DIR")).join("../.."), &mut fixtures);
+        assert!(fixtures.len() >= 2, "expected the repository's .hcdf files, found {}", fixtures.len());
+        for size in dendrite_test_support::FixtureSize::ALL {
+            fixtures.push((format!("synthetic {}", size.name()), size.xml()));
+        }
+        fixtures.push(("device fragment".to_string(), dendrite_test_support::device_fragment(0)));
+        fixtures.push(("builder".to_string(), every_sensor_fixture()));
+        fixtures
+    }
+
+    /// A comp with one sensor of every category and ports in both schemas
+    fn every_sensor_fixture() -> String {
+        use dendrite_test_support::{FixtureBuilder, SensorCategory, SensorFixture};
+
+        let mut comp = FixtureBuilder::device()
+            .with_name("everything")
+            .with_role("sensor")
+            .with_visual("board", "models/everything.glb")
+            .with_frame("origin", "0 0 0 0 0 0")
+            .with_port("ETH0", "ethernet")
+            .with_legacy_port("CAN0", "CAN")
+            .with_antenna("ANT0", "gnss");
+        for category in SensorCategory::ALL {
+            let sensor = SensorFixture::new(category.element(), category, "generic")
+                .with_pose("0 0 0.01 0 0 0")
+                .with_driver("generic");
+            comp = comp.with_sensor(if category == SensorCategory::Optical {
+                sensor.with_fov("imager", 1.0, 0.8)
+            } else {
+                sensor
+            });
+        }
+        comp.hcdf()
+    }
+
+    /// Element counts per kind and schema variant, in the shape of the
+    /// fixtures' expected-parse snapshots
+    fn parse_summary(hcdf: &Hcdf) -> serde_json::Value {
+        let mut ports = serde_json::Map::new();
+        let mut sensors = serde_json::Map::new();
+        let (mut legacy_ports, mut antennas, mut legacy_antennas, mut fovs, mut legacy_fov_geometries) = (0, 0, 0, 0, 0);
+        let count = |map: &mut serde_json::Map<String, serde_json::Value>, key: &str, n: usize| {
+            let total = map.get(key).and_then(|v| v.as_u64()).unwrap_or(0) + n as u64;
+            map.insert(key.to_string(), total.into());
+        };
+        for comp in &hcdf.comp {
+            for port in &comp.port {
+                count(&mut ports, &port.port_type, 1);
+                legacy_ports += usize::from(port.fallback_visual.is_none());
+            }
+            antennas += comp.antenna.len();
+            legacy_antennas += comp.antenna.iter().filter(|a| a.fallback_visual.is_none()).count();
+            for sensor in &comp.sensor {
+                count(&mut sensors, "inertial", sensor.inertial.len());
+                count(&mut sensors, "em", sensor.em.len());
+                count(&mut sensors, "optical", sensor.optical.len());
+                count(&mut sensors, "rf", sensor.rf.len());
+                count(&mut sensors, "chemical", sensor.chemical.len());
+                count(&mut sensors, "force", sensor.force.len());
+                fovs += sensor.optical.iter().map(|o| o.fov.len()).sum::<usize>();
+                legacy_fov_geometries += sensor.optical.iter().filter(|o| o.geometry.is_some()).count();
+            }
+        }
+        serde_json::json!({
+            "version": hcdf.version,
+            "mcus": hcdf.mcu.len(),
+            "comps": hcdf.comp.len(),
+            "links": hcdf.link.len(),
+            "visuals": hcdf.mcu.iter().map(|m| m.visual.len()).sum::<usize>()
+                + hcdf.comp.iter().map(|c| c.visual.len()).sum::<usize>(),
+            "legacy_models": hcdf.mcu.iter().filter(|m| m.model.is_some()).count()
+                + hcdf.comp.iter().filter(|c| c.model.is_some()).count(),
+            "frames": hcdf.mcu.iter().map(|m| m.frame.len()).sum::<usize>()
+                + hcdf.comp.iter().map(|c| c.frame.len()).sum::<usize>(),
+            "ports": ports,
+            "legacy_ports": legacy_ports,
+            "antennas": antennas,
+            "legacy_antennas": legacy_antennas,
+            "sensors": sensors,
+            "fovs": fovs,
+            "legacy_fov_geometries": legacy_fov_geometries,
+        })
+    }
+
+    #[test]
+    fn test_fixtures_match_expected_parse() {
+        for size in dendrite_test_support::FixtureSize::ALL {
+            let hcdf = Hcdf::from_xml(&size.xml()).unwrap();
+            let expected: serde_json::Value = serde_json::from_str(size.expected_summary()).unwrap();
+            assert_eq!(parse_summary(&hcdf), expected, "{}", size.name());
+        }
+    }
+
+    /// Attribute names of each element, in document order
+    fn attribute_orders(xml: &str) -> Vec<(String, Vec<String>)> {
+        use quick_xml::events::Event;
+
+        let mut reader = quick_xml::Reader::from_str(xml);
+        let mut elements = Vec::new();
+        loop {
+            match reader.read_event().unwrap() {
+                Event::Start(e) | Event::Empty(e) => {
+                    let name = String::from_utf8_lossy(e.name().as_ref()).to_string();
+                    let attributes = e
+                        .attributes()
+                        .map(|a| String::from_utf8_lossy(a.unwrap().key.as_ref()).to_string())
+                        .collect();
+                    elements.push((name, attributes));
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+        elements
+    }
+
+    #[test]
+    fn test_round_trip_every_fixture() {
+        for (name, xml) in round_trip_fixtures() {
+            let parsed = Hcdf::from_xml(&xml).unwrap_or_else(|e| panic!("{}: {}", name, e));
+            let exported = parsed.to_xml().unwrap_or_else(|e| panic!("{}: {}", name, e));
+            let reparsed = Hcdf::from_xml(&exported).unwrap_or_else(|e| panic!("{}: re-parse failed: {}", name, e));
+
+            assert_eq!(
+                serde_json::to_value(&parsed).unwrap(),
+                serde_json::to_value(&reparsed).unwrap(),
+                "{}: structure changed",
+                name
+            );
+            assert_eq!(reparsed.to_xml().unwrap(), exported, "{}: export is not stable", name);
+
+            // Schema variant: no port or antenna moves between fallback_visual and legacy fields
+            assert_eq!(
+                xml.matches("<fallback_visual>").count(),
+                exported.matches("<fallback_visual>").count(),
+                "{}: fallback_visual count changed",
+                name
+            );
+
+            // Every exported element's attributes appear in the order some
+            // element of the same name had them in the original
+            let original = attribute_orders(&xml);
+            for (element, attributes) in attribute_orders(&exported) {
+                assert!(
+                    original.iter().any(|(e, a)| {
+                        let mut written = a.iter();
+                        *e == element && attributes.iter().all(|attr| written.any(|w| w == attr))
+                    }),
+                    "{}: <{}> attributes reordered to {:?}",
+                    name,
+                    element,
+                    attributes
+                );
+            }
+        }
+    }
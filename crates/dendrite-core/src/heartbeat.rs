@@ -0,0 +1,198 @@
+//! Heartbeat method selection and latency history
+//!
+//! Each device can override how its connectivity is checked. Without an
+//! override, probe-verified devices use an SMP echo and everything else uses
+//! ICMP so devices behind a router hop still report correctly.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+use crate::device::{Device, DiscoveryMethod};
+
+/// Number of latency samples kept per device
+pub const LATENCY_HISTORY_LEN: usize = 60;
+
+/// Error parsing a heartbeat method string
+#[derive(Debug, Error, PartialEq)]
+pub enum HeartbeatMethodError {
+    #[error("Unknown heartbeat method: {0}")]
+    Unknown(String),
+    #[error("Invalid TCP port: {0}")]
+    InvalidPort(String),
+}
+
+/// How a device's connectivity is checked
+///
+/// Serialized as `arp`, `icmp`, `smp-echo` or `tcp-connect:<port>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum HeartbeatMethod {
+    /// ARP cache lookup with ping fallback (L2-adjacent devices only)
+    Arp,
+    /// ICMP echo request
+    Icmp,
+    /// MCUmgr SMP echo on the device's MCUmgr port
+    SmpEcho,
+    /// TCP connect to the given port
+    TcpConnect(u16),
+}
+
+impl HeartbeatMethod {
+    /// Pick a method for a device without an override
+    pub fn auto_for(device: &Device) -> Self {
+        match device.discovery.discovery_method {
+            DiscoveryMethod::Probe => Self::SmpEcho,
+            _ => Self::Icmp,
+        }
+    }
+
+    /// Method actually used for a device (override or auto-selected)
+    pub fn effective_for(device: &Device) -> Self {
+        device
+            .discovery
+            .heartbeat_method
+            .unwrap_or_else(|| Self::auto_for(device))
+    }
+}
+
+impl fmt::Display for HeartbeatMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Arp => write!(f, "arp"),
+            Self::Icmp => write!(f, "icmp"),
+            Self::SmpEcho => write!(f, "smp-echo"),
+            Self::TcpConnect(port) => write!(f, "tcp-connect:{}", port),
+        }
+    }
+}
+
+impl FromStr for HeartbeatMethod {
+    type Err = HeartbeatMethodError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "arp" => Ok(Self::Arp),
+            "icmp" => Ok(Self::Icmp),
+            "smp-echo" => Ok(Self::SmpEcho),
+            other => match other.strip_prefix("tcp-connect:") {
+                Some(port) => port
+                    .parse::<u16>()
+                    .ok()
+                    .filter(|p| *p != 0)
+                    .map(Self::TcpConnect)
+                    .ok_or_else(|| HeartbeatMethodError::InvalidPort(port.to_string())),
+                None => Err(HeartbeatMethodError::Unknown(other.to_string())),
+            },
+        }
+    }
+}
+
+impl TryFrom<String> for HeartbeatMethod {
+    type Error = HeartbeatMethodError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<HeartbeatMethod> for String {
+    fn from(method: HeartbeatMethod) -> Self {
+        method.to_string()
+    }
+}
+
+/// A single heartbeat result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatencySample {
+    /// When the check ran
+    pub timestamp: DateTime<Utc>,
+    /// Round-trip latency in milliseconds (None if the check failed)
+    pub latency_ms: Option<f64>,
+}
+
+/// Rolling history of the last `LATENCY_HISTORY_LEN` samples
+#[derive(Debug, Clone, Default)]
+pub struct LatencyHistory {
+    samples: VecDeque<LatencySample>,
+}
+
+impl LatencyHistory {
+    /// Record a sample, dropping the oldest once full
+    pub fn record(&mut self, latency_ms: Option<f64>) {
+        if self.samples.len() >= LATENCY_HISTORY_LEN {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(LatencySample {
+            timestamp: Utc::now(),
+            latency_ms,
+        });
+    }
+
+    /// Samples from oldest to newest
+    pub fn samples(&self) -> Vec<LatencySample> {
+        self.samples.iter().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::DeviceId;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    #[test]
+    fn test_method_round_trip() {
+        for s in ["arp", "icmp", "smp-echo", "tcp-connect:22"] {
+            let method: HeartbeatMethod = s.parse().unwrap();
+            assert_eq!(method.to_string(), s);
+        }
+        assert_eq!(
+            "tcp-connect:0".parse::<HeartbeatMethod>(),
+            Err(HeartbeatMethodError::InvalidPort("0".to_string()))
+        );
+        assert!("udp".parse::<HeartbeatMethod>().is_err());
+
+        let json = serde_json::to_string(&HeartbeatMethod::TcpConnect(8080)).unwrap();
+        assert_eq!(json, "\"tcp-connect:8080\"");
+    }
+
+    #[test]
+    fn test_auto_selection() {
+        let mut device = Device::new(
+            DeviceId::from_hwid("test"),
+            "test".to_string(),
+            IpAddr::V4(Ipv4Addr::new(192, 168, 1, 10)),
+            1337,
+        );
+        assert_eq!(HeartbeatMethod::effective_for(&device), HeartbeatMethod::SmpEcho);
+
+        device.discovery.discovery_method = DiscoveryMethod::Manual;
+        assert_eq!(HeartbeatMethod::effective_for(&device), HeartbeatMethod::Icmp);
+
+        device.discovery.heartbeat_method = Some(HeartbeatMethod::TcpConnect(22));
+        assert_eq!(HeartbeatMethod::effective_for(&device), HeartbeatMethod::TcpConnect(22));
+    }
+
+    #[test]
+    fn test_history_is_bounded() {
+        let mut history = LatencyHistory::default();
+        for i in 0..(LATENCY_HISTORY_LEN + 10) {
+            history.record(Some(i as f64));
+        }
+        let samples = history.samples();
+        assert_eq!(samples.len(), LATENCY_HISTORY_LEN);
+        assert_eq!(samples[0].latency_ms, Some(10.0));
+    }
+}
@@ -0,0 +1,212 @@
+//! Fleet inventory rollup
+//!
+//! Counts boards, sensor drivers, port types and firmware versions across a
+//! set of devices. Every row is also split by firmware version, so "how many
+//! BMI088s are deployed, and on which firmware" takes a single lookup.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::device::Device;
+
+/// Key used when a board, driver or firmware version isn't known
+pub const UNKNOWN: &str = "unknown";
+
+/// Counts across a set of devices
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FleetInventory {
+    /// Devices counted
+    pub devices: usize,
+    /// Devices per board
+    pub boards: Vec<InventoryRow>,
+    /// Sensors per driver
+    pub sensor_drivers: Vec<InventoryRow>,
+    /// Ports per type and speed (e.g. "ethernet 100 Mbps")
+    pub ports: Vec<InventoryRow>,
+    /// Devices per firmware version
+    pub firmware: Vec<InventoryRow>,
+}
+
+/// One board, driver, port kind or firmware version
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InventoryRow {
+    pub key: String,
+    /// Devices, sensors or ports counted under this key
+    pub count: usize,
+    /// Distinct devices they are on
+    pub devices: usize,
+    /// `count` split by the firmware version of the device each one is on
+    pub by_firmware: BTreeMap<String, usize>,
+}
+
+/// Rows being counted, by key
+#[derive(Default)]
+struct Tally(BTreeMap<String, (usize, BTreeSet<String>, BTreeMap<String, usize>)>);
+
+impl Tally {
+    fn add(&mut self, key: String, device: &Device, firmware: &str) {
+        let (count, devices, by_firmware) = self.0.entry(key).or_default();
+        *count += 1;
+        devices.insert(device.id.0.clone());
+        *by_firmware.entry(firmware.to_string()).or_insert(0) += 1;
+    }
+
+    /// Largest count first, then by key
+    fn into_rows(self) -> Vec<InventoryRow> {
+        let mut rows: Vec<InventoryRow> = self
+            .0
+            .into_iter()
+            .map(|(key, (count, devices, by_firmware))| InventoryRow { key, count, devices: devices.len(), by_firmware })
+            .collect();
+        rows.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.key.cmp(&b.key)));
+        rows
+    }
+}
+
+impl FleetInventory {
+    /// Count boards, sensor drivers, ports and firmware versions
+    pub fn from_devices<'a>(devices: impl IntoIterator<Item = &'a Device>) -> Self {
+        let mut count = 0;
+        let (mut boards, mut drivers, mut ports, mut firmware) =
+            (Tally::default(), Tally::default(), Tally::default(), Tally::default());
+
+        for device in devices {
+            count += 1;
+            let version = device.firmware.version.as_deref().unwrap_or(UNKNOWN);
+            boards.add(device.info.board.clone().unwrap_or_else(|| UNKNOWN.to_string()), device, version);
+            firmware.add(version.to_string(), device, version);
+            for sensor in &device.sensors {
+                drivers.add(sensor.driver.clone().unwrap_or_else(|| UNKNOWN.to_string()), device, version);
+            }
+            for port in &device.ports {
+                let key = match &port.speed {
+                    Some(speed) => format!("{} {}", port.port_type, speed),
+                    None => port.port_type.clone(),
+                };
+                ports.add(key, device, version);
+            }
+        }
+
+        Self {
+            devices: count,
+            boards: boards.into_rows(),
+            sensor_drivers: drivers.into_rows(),
+            ports: ports.into_rows(),
+            firmware: firmware.into_rows(),
+        }
+    }
+
+    /// One line per row: `category,key,count,devices,by_firmware`, with the
+    /// firmware split written as `1.4.2=3;1.5.0=1`
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("category,key,count,devices,by_firmware\n");
+        let categories = [
+            ("board", &self.boards),
+            ("sensor_driver", &self.sensor_drivers),
+            ("port", &self.ports),
+            ("firmware", &self.firmware),
+        ];
+        for (category, rows) in categories {
+            for row in rows {
+                let by_firmware = row
+                    .by_firmware
+                    .iter()
+                    .map(|(version, count)| format!("{}={}", version, count))
+                    .collect::<Vec<_>>()
+                    .join(";");
+                csv.push_str(&format!(
+                    "{},{},{},{},{}\n",
+                    category,
+                    csv_field(&row.key),
+                    row.count,
+                    row.devices,
+                    csv_field(&by_firmware)
+                ));
+            }
+        }
+        csv
+    }
+}
+
+/// Quote a CSV field if it needs it
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::{DeviceId, DevicePort, DeviceSensor};
+
+    fn device(id: &str, board: &str, version: Option<&str>, drivers: &[&str]) -> Device {
+        let mut device = Device::new(DeviceId::from_hwid(id), id.to_string(), "10.0.0.1".parse().unwrap(), 1337);
+        device.info.board = Some(board.to_string());
+        device.firmware.version = version.map(str::to_string);
+        device.sensors = drivers
+            .iter()
+            .map(|driver| DeviceSensor {
+                name: format!("{}_hub", driver),
+                category: "inertial".to_string(),
+                sensor_type: "accel_gyro".to_string(),
+                driver: Some(driver.to_string()),
+                driver_label: None,
+                datasheet: None,
+                pose: None,
+                axis_align: None,
+                geometry: None,
+                fovs: Vec::new(),
+            })
+            .collect();
+        device.ports = vec![DevicePort {
+            name: "ETH0".to_string(),
+            port_type: "ethernet".to_string(),
+            speed: Some("100 Mbps".to_string()),
+            pose: None,
+            geometry: Vec::new(),
+            visual_name: None,
+            mesh_name: None,
+            original_pose: None,
+        }];
+        device
+    }
+
+    #[test]
+    fn test_rollup_counts_and_firmware_split() {
+        let devices = [
+            device("a", "mr_mcxn_t1", Some("1.4.2"), &["bmi088", "bmm350"]),
+            device("b", "mr_mcxn_t1", Some("1.5.0"), &["bmi088"]),
+            device("c", "navq95", None, &["bmi088", "bmi088"]),
+        ];
+        let inventory = FleetInventory::from_devices(&devices);
+
+        assert_eq!(inventory.devices, 3);
+        assert_eq!(inventory.boards[0].key, "mr_mcxn_t1");
+        assert_eq!(inventory.boards[0].count, 2);
+
+        let bmi088 = &inventory.sensor_drivers[0];
+        assert_eq!((bmi088.key.as_str(), bmi088.count, bmi088.devices), ("bmi088", 4, 3));
+        assert_eq!(
+            bmi088.by_firmware,
+            BTreeMap::from([("1.4.2".to_string(), 1), ("1.5.0".to_string(), 1), (UNKNOWN.to_string(), 2)])
+        );
+
+        assert_eq!(inventory.ports[0].key, "ethernet 100 Mbps");
+        assert_eq!(inventory.ports[0].count, 3);
+        assert_eq!(inventory.firmware.len(), 3);
+    }
+
+    #[test]
+    fn test_csv() {
+        let devices = [device("a", "board,with comma", Some("1.4.2"), &["bmi088"])];
+        let csv = FleetInventory::from_devices(&devices).to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "category,key,count,devices,by_firmware");
+        assert_eq!(lines[1], "board,\"board,with comma\",1,1,1.4.2=1");
+        assert_eq!(lines[2], "sensor_driver,bmi088,1,1,1.4.2=1");
+        assert_eq!(lines.len(), 5);
+    }
+}
@@ -0,0 +1,286 @@
+//! Keep-out volume checks
+//!
+//! `<keepout>` volumes at the HCDF root mark space no component may intrude
+//! into. Every `<mcu>`/`<comp>` with a `<bbox>` is placed at its `pose_cg`
+//! and tested as an oriented box against each volume with a separating-axis
+//! test. Overlaps are reported with their penetration depth: how far the
+//! box would have to move along the best axis to clear the volume.
+//!
+//! Box and sphere volumes are tested exactly. Cylinders use the cylinder's
+//! own support function over the box axes, the cylinder axis and their cross
+//! products, which can report a shallow overlap near a rim edge the exact
+//! shapes just miss, but never misses a real intrusion.
+
+use serde::{Deserialize, Serialize};
+
+use crate::hcdf::{BoundingBox, Hcdf, Keepout, Pose};
+
+/// Axes shorter than this (parallel cross products) are skipped
+const AXIS_EPSILON: f64 = 1e-9;
+
+/// Shape of a keep-out volume, centered on its pose
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum KeepoutShape {
+    /// Box with full size (x, y, z)
+    Box { size: [f64; 3] },
+    /// Cylinder along the local Z axis
+    Cylinder { radius: f64, length: f64 },
+    Sphere { radius: f64 },
+}
+
+impl Keepout {
+    /// Volume shape, or None if the geometry is missing or unsupported
+    pub fn shape(&self) -> Option<KeepoutShape> {
+        let geometry = &self.geometry;
+        if let Some(size) = geometry.get_box().and_then(|b| b.parse_size()) {
+            Some(KeepoutShape::Box { size })
+        } else if let Some(c) = &geometry.cylinder {
+            Some(KeepoutShape::Cylinder { radius: c.radius, length: c.length })
+        } else {
+            geometry.sphere.as_ref().map(|s| KeepoutShape::Sphere { radius: s.radius })
+        }
+    }
+}
+
+/// A device intruding into a keep-out volume
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeepoutViolation {
+    /// Device element name
+    pub device: String,
+    /// Device hardware ID, if the element has one
+    pub hwid: Option<String>,
+    /// Keep-out volume name
+    pub keepout: String,
+    /// Penetration depth in meters
+    pub depth: f64,
+}
+
+impl Hcdf {
+    /// Test every device bounding box against every keep-out volume
+    ///
+    /// Devices without a `<bbox>` and volumes without a supported geometry
+    /// are skipped.
+    pub fn check_keepouts(&self) -> Vec<KeepoutViolation> {
+        let volumes: Vec<(&Keepout, KeepoutShape, Pose)> = self
+            .keepout
+            .iter()
+            .filter_map(|k| Some((k, k.shape()?, k.parse_pose().unwrap_or_default())))
+            .collect();
+        if volumes.is_empty() {
+            return Vec::new();
+        }
+
+        let devices = self
+            .mcu
+            .iter()
+            .map(|m| (&m.name, &m.hwid, &m.pose_cg, &m.bbox))
+            .chain(self.comp.iter().map(|c| (&c.name, &c.hwid, &c.pose_cg, &c.bbox)));
+
+        let mut violations = Vec::new();
+        for (name, hwid, pose_cg, bbox) in devices {
+            let Some(device_box) = bbox.as_ref().and_then(|b| Obb::from_bbox(pose_cg.as_deref(), b)) else {
+                continue;
+            };
+            for (keepout, shape, pose) in &volumes {
+                if let Some(depth) = device_box.penetration(*shape, pose) {
+                    violations.push(KeepoutViolation {
+                        device: name.clone(),
+                        hwid: hwid.clone(),
+                        keepout: keepout.name.clone(),
+                        depth,
+                    });
+                }
+            }
+        }
+        violations
+    }
+}
+
+/// Oriented box in the HCDF root frame
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Obb {
+    pub(crate) center: [f64; 3],
+    /// Unit local axes expressed in the root frame
+    pub(crate) axes: [[f64; 3]; 3],
+    pub(crate) half: [f64; 3],
+}
+
+impl Obb {
+    pub(crate) fn from_pose(pose: &Pose, size: [f64; 3]) -> Self {
+        let r = pose.rotation_matrix();
+        Self {
+            center: [pose.x, pose.y, pose.z],
+            axes: [0, 1, 2].map(|j| [r[0][j], r[1][j], r[2][j]]),
+            half: size.map(|s| s.abs() / 2.0),
+        }
+    }
+
+    fn from_bbox(pose_cg: Option<&str>, bbox: &BoundingBox) -> Option<Self> {
+        let origin = pose_cg.and_then(crate::hcdf::parse_pose_string).unwrap_or_default();
+        let pose = origin.compose(&bbox.parse_pose().unwrap_or_default());
+        Some(Self::from_pose(&pose, bbox.parse_size()?))
+    }
+
+    /// Half-length of this box projected onto a unit axis
+    fn radius_along(&self, axis: [f64; 3]) -> f64 {
+        (0..3).map(|i| self.half[i] * dot(self.axes[i], axis).abs()).sum()
+    }
+
+    /// Penetration depth into a keep-out volume at `pose`, or None if clear
+    fn penetration(&self, shape: KeepoutShape, pose: &Pose) -> Option<f64> {
+        match shape {
+            KeepoutShape::Box { size } => {
+                let other = Obb::from_pose(pose, size);
+                let mut axes = self.axes.to_vec();
+                axes.extend(other.axes);
+                for a in self.axes {
+                    for b in other.axes {
+                        axes.push(cross(a, b));
+                    }
+                }
+                separating_axis_depth(self, other.center, &axes, |axis| other.radius_along(axis))
+            }
+            KeepoutShape::Cylinder { radius, length } => {
+                let r = pose.rotation_matrix();
+                let cylinder_axis = [r[0][2], r[1][2], r[2][2]];
+                let mut axes = self.axes.to_vec();
+                axes.push(cylinder_axis);
+                for a in self.axes {
+                    axes.push(cross(a, cylinder_axis));
+                }
+                separating_axis_depth(self, [pose.x, pose.y, pose.z], &axes, |axis| {
+                    let along = dot(cylinder_axis, axis).abs().min(1.0);
+                    length.abs() / 2.0 * along + radius * (1.0 - along * along).sqrt()
+                })
+            }
+            KeepoutShape::Sphere { radius } => {
+                // Sphere center in box coordinates, and the closest point of the box to it
+                let offset = sub([pose.x, pose.y, pose.z], self.center);
+                let local = self.axes.map(|axis| dot(offset, axis));
+                let closest = [0, 1, 2].map(|i| local[i].clamp(-self.half[i], self.half[i]));
+                let distance = norm(sub(local, closest));
+                let depth = if distance > 0.0 {
+                    radius - distance
+                } else {
+                    // Center inside the box: clear it through the nearest face
+                    let to_face = (0..3).map(|i| self.half[i] - local[i].abs()).fold(f64::MAX, f64::min);
+                    radius + to_face
+                };
+                (depth > 0.0).then_some(depth)
+            }
+        }
+    }
+}
+
+/// Smallest overlap of `obb` and a convex shape centered at `center` over
+/// the candidate axes, or None if any axis separates them
+fn separating_axis_depth(
+    obb: &Obb,
+    center: [f64; 3],
+    axes: &[[f64; 3]],
+    other_radius: impl Fn([f64; 3]) -> f64,
+) -> Option<f64> {
+    let offset = sub(center, obb.center);
+    let mut depth = f64::MAX;
+    for &axis in axes {
+        let length = norm(axis);
+        if length < AXIS_EPSILON {
+            continue;
+        }
+        let axis = axis.map(|v| v / length);
+        let overlap = obb.radius_along(axis) + other_radius(axis) - dot(offset, axis).abs();
+        if overlap <= 0.0 {
+            return None;
+        }
+        depth = depth.min(overlap);
+    }
+    Some(depth)
+}
+
+pub(crate) fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub(crate) fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+pub(crate) fn sub(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+pub(crate) fn norm(a: [f64; 3]) -> f64 {
+    dot(a, a).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame_hcdf(gps_pose: &str) -> Hcdf {
+        let xml = format!(
+            r#"<?xml version='1.0'?>
+<hcdf version="2.1">
+  <mcu name="gps" hwid="0x1">
+    <pose_cg>{gps_pose}</pose_cg>
+    <bbox><size>0.04 0.04 0.02</size></bbox>
+  </mcu>
+  <comp name="radio">
+    <pose_cg>0 0 0.1 0 0 0</pose_cg>
+  </comp>
+  <keepout name="prop_fl">
+    <pose>0.2 0.2 0.05 0 0 0</pose>
+    <geometry><cylinder><radius>0.13</radius><length>0.02</length></cylinder></geometry>
+  </keepout>
+  <keepout name="gear">
+    <pose>0 0 -0.1 0 0 0</pose>
+    <geometry><box><size>0.3 0.05 0.1</size></box></geometry>
+  </keepout>
+  <keepout name="antenna_clearance">
+    <pose>-0.2 0 0 0 0 0</pose>
+    <geometry><sphere><radius>0.05</radius></sphere></geometry>
+  </keepout>
+</hcdf>"#
+        );
+        Hcdf::from_xml(&xml).unwrap()
+    }
+
+    #[test]
+    fn test_clear_assembly_has_no_violations() {
+        let hcdf = frame_hcdf("0 0 0.05 0 0 0");
+        assert_eq!(hcdf.keepout.len(), 3);
+        assert!(hcdf.check_keepouts().is_empty());
+    }
+
+    #[test]
+    fn test_violations_report_penetration_depth() {
+        // Half the box sits inside the 2 cm thick prop disk; dropping 1 cm
+        // clears it, which beats any sideways move
+        let violations = frame_hcdf("0.2 0.2 0.04 0 0 0.3").check_keepouts();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].keepout, "prop_fl");
+        assert_eq!(violations[0].hwid.as_deref(), Some("0x1"));
+        assert!((violations[0].depth - 0.01).abs() < 1e-9, "depth {}", violations[0].depth);
+
+        // Box bottom 1 cm into the top of the landing gear volume
+        let violations = frame_hcdf("0 0 -0.05 0 0 0").check_keepouts();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].keepout, "gear");
+        assert!((violations[0].depth - 0.01).abs() < 1e-9, "depth {}", violations[0].depth);
+
+        // Box face 2 cm into the sphere
+        let violations = frame_hcdf("-0.15 0 0 0 0 0").check_keepouts();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].keepout, "antenna_clearance");
+        assert!((violations[0].depth - 0.02).abs() < 1e-9, "depth {}", violations[0].depth);
+    }
+
+    #[test]
+    fn test_rotated_box_clears_volume() {
+        // A 45 degree yaw swings a corner 2.8 cm out from the box center,
+        // still 1.7 mm short of the sphere
+        let hcdf = frame_hcdf("-0.12 0 0 0 0 0.7853981633974483");
+        assert!(hcdf.check_keepouts().is_empty());
+    }
+}
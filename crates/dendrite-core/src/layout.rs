@@ -0,0 +1,297 @@
+//! Provisional placement for devices without a pose
+//!
+//! Freshly discovered devices have no `pose_cg` and would all spawn at the
+//! origin. Until someone places them, they are laid out on a square spiral
+//! around the parent device: in hwid order, each goes to the first spot
+//! where its footprint (the XY extent of its `<bbox>`, or a default size)
+//! clears every device already placed. The layout depends only on the set
+//! of devices, so the same devices always land in the same spots.
+//!
+//! Placed poses are flagged `provisional="true"` on the `<mcu>`; editing the
+//! pose clears the flag and the device becomes a fixed obstacle.
+
+use crate::hcdf::{parse_pose_string, BoundingBox, Hcdf, Pose};
+
+/// Size assumed for devices without a `<bbox>` (meters)
+pub const DEFAULT_DEVICE_SIZE: [f64; 3] = [0.05, 0.05, 0.02];
+
+/// Spacing of the candidate grid (meters)
+const GRID_STEP: f64 = 0.01;
+
+/// Clearance kept between neighbouring footprints (meters)
+const MARGIN: f64 = 0.01;
+
+/// Spiral rings searched before giving up on a device
+const MAX_RINGS: i64 = 500;
+
+/// Axis-aligned XY extent of a device
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Footprint {
+    pub center: [f64; 2],
+    pub half: [f64; 2],
+}
+
+impl Footprint {
+    /// Footprint of a box of `size` centered at `pose`
+    pub fn from_pose(pose: &Pose, size: [f64; 3]) -> Self {
+        let r = pose.rotation_matrix();
+        let half = size.map(|s| s.abs() / 2.0);
+        let extent = |row: usize| (0..3).map(|j| r[row][j].abs() * half[j]).sum();
+        Self {
+            center: [pose.x, pose.y],
+            half: [extent(0), extent(1)],
+        }
+    }
+
+    /// Footprint of a device at `pose_cg` with an optional bounding box
+    pub fn from_device(pose_cg: &Pose, bbox: Option<&BoundingBox>) -> Self {
+        let offset = bbox.and_then(|b| b.parse_pose()).unwrap_or_default();
+        let size = bbox.and_then(|b| b.parse_size()).unwrap_or(DEFAULT_DEVICE_SIZE);
+        Self::from_pose(&pose_cg.compose(&offset), size)
+    }
+
+    /// Whether two footprints come closer than `margin`
+    pub fn overlaps(&self, other: &Footprint, margin: f64) -> bool {
+        (0..2).all(|i| (self.center[i] - other.center[i]).abs() < self.half[i] + other.half[i] + margin)
+    }
+}
+
+/// Grid cells of one spiral ring, nearest first, counter-clockwise from
+/// East among equally near cells
+fn spiral_ring(ring: i64) -> Vec<(i64, i64)> {
+    if ring == 0 {
+        return vec![(0, 0)];
+    }
+    let mut cells: Vec<(i64, i64)> = (-ring..=ring)
+        .flat_map(|i| (-ring..=ring).map(move |j| (i, j)))
+        .filter(|(i, j)| i.abs().max(j.abs()) == ring)
+        .collect();
+    let angle = |(i, j): (i64, i64)| (j as f64).atan2(i as f64).rem_euclid(std::f64::consts::TAU);
+    cells.sort_by(|a, b| {
+        (a.0 * a.0 + a.1 * a.1)
+            .cmp(&(b.0 * b.0 + b.1 * b.1))
+            .then(angle(*a).total_cmp(&angle(*b)))
+    });
+    cells
+}
+
+/// Place `pending` devices (id, bounding box) around `origin`, clear of
+/// `fixed` footprints and of each other
+///
+/// Devices are placed in id order regardless of input order. Returns the
+/// pose_cg for each device that found a spot.
+pub fn layout_provisional(
+    origin: [f64; 3],
+    fixed: &[Footprint],
+    pending: &[(String, Option<BoundingBox>)],
+) -> Vec<(String, Pose)> {
+    let mut order: Vec<&(String, Option<BoundingBox>)> = pending.iter().collect();
+    order.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut placed: Vec<Footprint> = fixed.to_vec();
+    let mut poses = Vec::new();
+    'devices: for (id, bbox) in order {
+        for ring in 0..MAX_RINGS {
+            for (i, j) in spiral_ring(ring) {
+                let pose = Pose {
+                    x: origin[0] + i as f64 * GRID_STEP,
+                    y: origin[1] + j as f64 * GRID_STEP,
+                    z: origin[2],
+                    ..Default::default()
+                };
+                let footprint = Footprint::from_device(&pose, bbox.as_ref());
+                if placed.iter().all(|p| !p.overlaps(&footprint, MARGIN)) {
+                    placed.push(footprint);
+                    poses.push((id.clone(), pose));
+                    continue 'devices;
+                }
+            }
+        }
+    }
+    poses
+}
+
+impl Hcdf {
+    /// Lay out every MCU without a pose, or with a provisional one, around
+    /// the parent MCU (or the origin)
+    ///
+    /// Devices with a user-placed pose stay put and are avoided. Poses are
+    /// written to `pose_cg` with the provisional flag set; returns the
+    /// (hwid, pose) of every device whose pose changed.
+    pub fn layout_provisional_poses(&mut self, parent_hwid: Option<&str>) -> Vec<(String, Pose)> {
+        let is_parent = |hwid: Option<&str>| parent_hwid.is_some() && hwid == parent_hwid;
+        let origin = self
+            .mcu
+            .iter()
+            .find(|m| is_parent(m.hwid.as_deref()))
+            .and_then(|m| m.pose_cg.as_deref().and_then(parse_pose_string))
+            .map(|p| [p.x, p.y, p.z])
+            .unwrap_or([0.0; 3]);
+
+        let mut fixed = Vec::new();
+        let mut pending = Vec::new();
+        for mcu in &self.mcu {
+            let pose = mcu.pose_cg.as_deref().and_then(parse_pose_string);
+            match (&mcu.hwid, pose) {
+                (Some(hwid), None) if !is_parent(Some(hwid.as_str())) => pending.push((hwid.clone(), mcu.bbox.clone())),
+                (Some(hwid), Some(_)) if mcu.provisional == Some(true) => pending.push((hwid.clone(), mcu.bbox.clone())),
+                // An unplaced parent sits at the origin
+                (_, None) if is_parent(mcu.hwid.as_deref()) => {
+                    fixed.push(Footprint::from_device(&Pose::default(), mcu.bbox.as_ref()));
+                }
+                (_, Some(pose)) => fixed.push(Footprint::from_device(&pose, mcu.bbox.as_ref())),
+                _ => {}
+            }
+        }
+        for comp in &self.comp {
+            if let Some(pose) = comp.pose_cg.as_deref().and_then(parse_pose_string) {
+                fixed.push(Footprint::from_device(&pose, comp.bbox.as_ref()));
+            }
+        }
+        if pending.is_empty() {
+            return Vec::new();
+        }
+
+        let mut changed = Vec::new();
+        for (hwid, pose) in layout_provisional(origin, &fixed, &pending) {
+            let Some(mcu) = self.mcu.iter_mut().find(|m| m.hwid.as_deref() == Some(&hwid)) else {
+                continue;
+            };
+            let pose_cg = pose.to_pose_string_like(mcu.pose_cg.as_deref());
+            if mcu.pose_cg.as_deref() != Some(&pose_cg) {
+                mcu.pose_cg = Some(pose_cg);
+                changed.push((hwid, pose));
+            }
+            mcu.provisional = Some(true);
+        }
+        changed
+    }
+
+    /// Drop provisional poses, e.g. before exporting a layout that should
+    /// only contain placements someone made
+    pub fn strip_provisional_poses(&mut self) {
+        for mcu in &mut self.mcu {
+            if mcu.provisional == Some(true) {
+                mcu.pose_cg = None;
+                mcu.provisional = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bbox(size: [f64; 3]) -> Option<BoundingBox> {
+        Some(BoundingBox {
+            pose: None,
+            size: format!("{} {} {}", size[0], size[1], size[2]),
+        })
+    }
+
+    fn footprints(pending: &[(String, Option<BoundingBox>)], poses: &[(String, Pose)]) -> Vec<Footprint> {
+        poses
+            .iter()
+            .map(|(id, pose)| {
+                let (_, bbox) = pending.iter().find(|(p, _)| p == id).unwrap();
+                Footprint::from_device(pose, bbox.as_ref())
+            })
+            .collect()
+    }
+
+    fn assert_no_overlap(footprints: &[Footprint]) {
+        for (i, a) in footprints.iter().enumerate() {
+            for b in &footprints[i + 1..] {
+                assert!(!a.overlaps(b, 0.0), "{a:?} overlaps {b:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_layout_avoids_overlap_for_mixed_sizes() {
+        let pending: Vec<(String, Option<BoundingBox>)> = vec![
+            ("0x01".to_string(), bbox([0.02, 0.02, 0.01])),
+            ("0x02".to_string(), bbox([0.3, 0.05, 0.05])),
+            ("0x03".to_string(), None),
+            ("0x04".to_string(), bbox([0.1, 0.1, 0.1])),
+            ("0x05".to_string(), bbox([0.005, 0.12, 0.01])),
+            ("0x06".to_string(), bbox([0.02, 0.02, 0.01])),
+        ];
+        // The parent at the origin
+        let parent = Footprint::from_device(&Pose::default(), bbox([0.08, 0.06, 0.02]).as_ref());
+
+        let poses = layout_provisional([0.0; 3], &[parent], &pending);
+        assert_eq!(poses.len(), pending.len());
+
+        let mut all = footprints(&pending, &poses);
+        all.push(parent);
+        assert_no_overlap(&all);
+    }
+
+    #[test]
+    fn test_layout_is_deterministic() {
+        let pending: Vec<(String, Option<BoundingBox>)> = vec![
+            ("b".to_string(), bbox([0.04, 0.02, 0.01])),
+            ("a".to_string(), None),
+            ("c".to_string(), bbox([0.15, 0.15, 0.02])),
+        ];
+        let mut reversed = pending.clone();
+        reversed.reverse();
+
+        let first = layout_provisional([0.1, -0.2, 0.0], &[], &pending);
+        let second = layout_provisional([0.1, -0.2, 0.0], &[], &reversed);
+        let as_arrays = |poses: &[(String, Pose)]| -> Vec<(String, [f64; 6])> {
+            poses.iter().map(|(id, p)| (id.clone(), p.to_array())).collect()
+        };
+        assert_eq!(as_arrays(&first), as_arrays(&second));
+        // The first device in id order takes the spiral center
+        assert_eq!(first[0].0, "a");
+        assert_eq!(first[0].1.to_array(), [0.1, -0.2, 0.0, 0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_hcdf_layout_keeps_placed_devices_and_flags_provisional() {
+        let mut hcdf = Hcdf::from_xml(
+            r#"<?xml version='1.0'?>
+<hcdf version="2.1">
+  <mcu name="parent" hwid="hub">
+    <pose_cg>0 0 0.02 0 0 0</pose_cg>
+    <bbox><size>0.08 0.06 0.02</size></bbox>
+  </mcu>
+  <mcu name="placed" hwid="0x1">
+    <pose_cg>0.1 0 0.02 0 0 0</pose_cg>
+  </mcu>
+  <mcu name="new-a" hwid="0x2"/>
+  <mcu name="new-b" hwid="0x3">
+    <bbox><size>0.2 0.02 0.02</size></bbox>
+  </mcu>
+</hcdf>"#,
+        )
+        .unwrap();
+
+        let changed = hcdf.layout_provisional_poses(Some("hub"));
+        assert_eq!(changed.len(), 2);
+        assert_eq!(hcdf.mcu[1].pose_cg.as_deref(), Some("0.1 0 0.02 0 0 0"));
+        assert_eq!(hcdf.mcu[1].provisional, None);
+        for mcu in &hcdf.mcu[2..] {
+            assert_eq!(mcu.provisional, Some(true));
+            // Laid out at the parent's height
+            assert_eq!(parse_pose_string(mcu.pose_cg.as_deref().unwrap()).unwrap().z, 0.02);
+        }
+
+        let all: Vec<Footprint> = hcdf
+            .mcu
+            .iter()
+            .map(|m| Footprint::from_device(&parse_pose_string(m.pose_cg.as_deref().unwrap()).unwrap(), m.bbox.as_ref()))
+            .collect();
+        assert_no_overlap(&all);
+
+        // Same devices, same layout: nothing moves on a second pass
+        assert!(hcdf.layout_provisional_poses(Some("hub")).is_empty());
+
+        hcdf.strip_provisional_poses();
+        assert!(hcdf.mcu[2].pose_cg.is_none());
+        assert_eq!(hcdf.mcu[1].pose_cg.as_deref(), Some("0.1 0 0.02 0 0 0"));
+    }
+}
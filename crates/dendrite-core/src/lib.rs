@@ -2,21 +2,80 @@
 //!
 //! This crate provides the foundational types for the Dendrite system:
 //! - HCDF (Hardware Configuration Descriptive Format) parsing and serialization
+//! - HCDF validation with line/column diagnostics
+//! - Vendor extensions (unknown XML) kept across an HCDF round trip
+//! - Structural diffs between HCDF documents, with device-level pose, model, sensor and port changes
+//! - Element provenance for annotated exports
+//! - URDF export for simulation stacks
 //! - Device registry types for tracking discovered hardware
-//! - Topology graph for parent/child device relationships
+//! - Registry handle applying discovery events, for embedding discovery
+//! - Fleet inventory rollups (boards, sensor drivers, ports, firmware)
+//! - Per-device metrics (latency, uptime, firmware age) for heatmap coloring
+//! - Reference frame hierarchy resolution
+//! - Keep-out volume checks for assembled vehicles
+//! - Named sub-assemblies of devices that move as one rigid unit
+//! - Sensor FOV occlusion by other devices' geometry
+//! - Unit-aware capability quantities (voltage, data rate, capacity...)
+//! - Provisional layout for devices that haven't been placed yet
+//! - Wire protocol version shared by the daemon and frontends
+//! - Runtime discovery fields kept apart from the canonical HCDF document
+//! - Topology graph for parent/child device relationships and shared buses
 //! - Fragment database for board/app to model mapping
+//! - Sensor driver registry for labelling and linting HCDF sensors
 //! - Cache management for remote HCDF files and models
+//! - Offline bundles of an HCDF document and its models
 
+pub mod assembly;
+pub mod bundle;
 pub mod cache;
 pub mod device;
+pub mod diff;
+pub mod drivers;
+pub mod extensions;
 pub mod firmware;
 pub mod fragment;
+pub mod frames;
 pub mod hcdf;
+pub mod heartbeat;
+pub mod inventory;
+pub mod keepout;
+pub mod layout;
+pub mod metrics;
+pub mod occlusion;
+pub mod protocol;
+pub mod provenance;
+pub mod quantity;
+pub mod registry;
+pub mod runtime;
 pub mod topology;
+pub mod urdf;
+pub mod validate;
 
-pub use cache::{CacheError, CacheManifest, CachedHcdf, CachedModel, FragmentCache, sha256_hex};
-pub use device::{Device, DeviceAxisAlign, DeviceFov, DeviceFrame, DeviceGeometry, DeviceId, DeviceInfo, DevicePort, DeviceSensor, DeviceStatus, DeviceVisual, FirmwareInfo};
+pub use assembly::{AssemblyError, MemberPose, rigid_transform};
+pub use bundle::{BundleError, BundleIssue, BundleManifest, BundledModel, ModelBundle, bundle_model_path, read_bundle, write_bundle, BUNDLE_MANIFEST};
+pub use cache::{
+    CacheError, CacheManifest, CachedHcdf, CachedModel, FragmentCache, HttpValidators, ModelOverride,
+    MODEL_OVERRIDE_VISUAL, sha256_hex,
+};
+pub use device::{CanAddress, Device, DeviceAxisAlign, DeviceFov, DeviceFrame, DeviceGeometry, DeviceId, DeviceInfo, DevicePort, DeviceSensor, DeviceStatus, DeviceSummary, DeviceVisual, DiscoveryInfo, DiscoveryMethod, FirmwareInfo, IndicatorStatus, PoseConstraint};
+pub use diff::{ChangeKind, FieldChange, HcdfChange, HcdfDiff, PoseTolerance};
+pub use drivers::{DriverInfo, DriverLint, DriverRegistry};
+pub use extensions::{ElementExtensions, ExtensionAttribute, ExtensionElement, HcdfExtensions};
 pub use firmware::{FirmwareManifest, FirmwareRelease, FirmwareStatus, UpdateState, compare_versions, verify_image_hash};
-pub use fragment::{Fragment, FragmentDatabase, FragmentError, FragmentIndex, FragmentIndexEntry};
-pub use hcdf::{Comp, Frame, Hcdf, HcdfError, ModelRef, Pose, Visual, parse_pose_string};
-pub use topology::{Topology, TopologyNode};
+pub use fragment::{Fragment, FragmentDatabase, FragmentError, FragmentIndex, FragmentIndexEntry, FragmentReload};
+pub use frames::{FrameError, FrameNode, frame_tree_order, resolve_frame_poses, validate_frames};
+pub use heartbeat::{HeartbeatMethod, HeartbeatMethodError, LatencyHistory, LatencySample, LATENCY_HISTORY_LEN};
+pub use hcdf::{Assembly, AssemblyMember, BoundingBox, Comp, Fov, Frame, Geometry, Hcdf, HcdfError, Keepout, Mcu, ModelRef, Port, Pose, Sensor, PoseParseError, Visual, parse_pose_result, parse_pose_string, SUPPORTED_HCDF_VERSIONS};
+pub use inventory::{FleetInventory, InventoryRow};
+pub use keepout::{KeepoutShape, KeepoutViolation};
+pub use layout::{Footprint, layout_provisional};
+pub use metrics::{DeviceMetrics, MetricInfo};
+pub use occlusion::{FovShape, OcclusionHit, OcclusionIntruder, OcclusionReport, OcclusionSample, Occluder, check_occlusion};
+pub use protocol::WS_PROTOCOL_VERSION;
+pub use provenance::{Provenance, ProvenanceRegistry, ProvenanceSource};
+pub use quantity::{Dimension, Quantity, QuantityError, RatedQuantity};
+pub use registry::{DiscoveryEvent, RegistryHandle};
+pub use runtime::{DiscoveredState, RuntimeFields};
+pub use topology::{BusSegment, Topology, TopologyGraph, TopologyNode};
+pub use urdf::{export_urdf, URDF_BASE_LINK};
+pub use validate::{Severity, ValidationIssue, ValidationReport};
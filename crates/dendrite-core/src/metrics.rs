@@ -0,0 +1,134 @@
+//! Per-device metrics for coloring devices in the 3D view
+//!
+//! A [`DeviceMetrics`] report lists the metrics the daemon can provide and
+//! each device's value for them. Devices a metric doesn't apply to (no
+//! heartbeat samples yet, no firmware build date) are left out of that
+//! metric, so frontends can show them as missing rather than as zero.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::device::Device;
+use crate::heartbeat::LatencySample;
+
+/// Most recent successful heartbeat round trip
+pub const LATENCY_MS: &str = "latency_ms";
+/// Share of heartbeats in the latency history that were answered
+pub const UPTIME_PERCENT: &str = "uptime_percent";
+/// Time since the running firmware was built
+pub const FIRMWARE_AGE_DAYS: &str = "firmware_age_days";
+/// Time since the device was last seen
+pub const LAST_SEEN_SECS: &str = "last_seen_secs";
+
+/// A metric devices can be colored by
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricInfo {
+    pub key: String,
+    /// Human-readable name (e.g. "Latency")
+    pub label: String,
+    /// Unit shown next to values (e.g. "ms")
+    pub unit: String,
+    /// Fixed [min, max] for the color ramp; None to use the observed range
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub range: Option<[f64; 2]>,
+}
+
+impl MetricInfo {
+    fn new(key: &str, label: &str, unit: &str, range: Option<[f64; 2]>) -> Self {
+        Self { key: key.to_string(), label: label.to_string(), unit: unit.to_string(), range }
+    }
+}
+
+/// Metric values across a set of devices
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DeviceMetrics {
+    /// Metrics available, in display order
+    pub metrics: Vec<MetricInfo>,
+    /// Values by metric key, then device id
+    pub values: BTreeMap<String, BTreeMap<String, f64>>,
+}
+
+impl DeviceMetrics {
+    /// Latency, uptime, firmware age and last-seen for each device, with its
+    /// latency history from oldest to newest
+    pub fn from_devices<'a>(
+        devices: impl IntoIterator<Item = (&'a Device, &'a [LatencySample])>,
+        now: DateTime<Utc>,
+    ) -> Self {
+        let mut report = Self {
+            metrics: vec![
+                MetricInfo::new(LATENCY_MS, "Latency", "ms", None),
+                MetricInfo::new(UPTIME_PERCENT, "Uptime", "%", Some([0.0, 100.0])),
+                MetricInfo::new(FIRMWARE_AGE_DAYS, "Firmware age", "days", None),
+                MetricInfo::new(LAST_SEEN_SECS, "Last seen", "s", None),
+            ],
+            values: BTreeMap::new(),
+        };
+
+        for (device, samples) in devices {
+            let id = &device.id.0;
+            if let Some(latency) = samples.iter().rev().find_map(|s| s.latency_ms) {
+                report.insert(LATENCY_MS, id, latency);
+            }
+            if !samples.is_empty() {
+                let answered = samples.iter().filter(|s| s.latency_ms.is_some()).count();
+                report.insert(UPTIME_PERCENT, id, 100.0 * answered as f64 / samples.len() as f64);
+            }
+            if let Some(built) = device.firmware.build_date {
+                report.insert(FIRMWARE_AGE_DAYS, id, (now - built).num_seconds() as f64 / 86_400.0);
+            }
+            report.insert(LAST_SEEN_SECS, id, (now - device.discovery.last_seen).num_milliseconds() as f64 / 1000.0);
+        }
+        report
+    }
+
+    /// A device's value for a metric
+    pub fn value(&self, metric: &str, device_id: &str) -> Option<f64> {
+        self.values.get(metric)?.get(device_id).copied()
+    }
+
+    fn insert(&mut self, metric: &str, device_id: &str, value: f64) {
+        self.values.entry(metric.to_string()).or_default().insert(device_id.to_string(), value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::DeviceId;
+    use chrono::Duration;
+
+    fn sample(now: DateTime<Utc>, latency_ms: Option<f64>) -> LatencySample {
+        LatencySample { timestamp: now, latency_ms }
+    }
+
+    #[test]
+    fn test_metrics_from_devices() {
+        let now = Utc::now();
+        let mut flow = Device::new(DeviceId::from_hwid("0x01"), "flow".to_string(), "10.0.0.5".parse().unwrap(), 1337);
+        flow.discovery.last_seen = now - Duration::seconds(30);
+        flow.firmware.build_date = Some(now - Duration::days(10));
+        let gps = Device::new(DeviceId::from_hwid("0x02"), "gps".to_string(), "10.0.0.6".parse().unwrap(), 1337);
+
+        let flow_samples = [sample(now, Some(2.0)), sample(now, Some(4.5)), sample(now, None), sample(now, None)];
+        let report = DeviceMetrics::from_devices([(&flow, &flow_samples[..]), (&gps, &[][..])], now);
+
+        let flow_id = flow.id.as_str();
+        // Latest answered heartbeat, not the latest sample
+        assert_eq!(report.value(LATENCY_MS, flow_id), Some(4.5));
+        assert_eq!(report.value(UPTIME_PERCENT, flow_id), Some(50.0));
+        assert_eq!(report.value(FIRMWARE_AGE_DAYS, flow_id), Some(10.0));
+        assert_eq!(report.value(LAST_SEEN_SECS, flow_id), Some(30.0));
+
+        // No heartbeats or build date: missing, not zero
+        let gps_id = gps.id.as_str();
+        assert_eq!(report.value(LATENCY_MS, gps_id), None);
+        assert_eq!(report.value(UPTIME_PERCENT, gps_id), None);
+        assert_eq!(report.value(FIRMWARE_AGE_DAYS, gps_id), None);
+        assert!(report.value(LAST_SEEN_SECS, gps_id).is_some());
+
+        let uptime = report.metrics.iter().find(|m| m.key == UPTIME_PERCENT).unwrap();
+        assert_eq!(uptime.range, Some([0.0, 100.0]));
+    }
+}
@@ -0,0 +1,393 @@
+//! Sensor FOV occlusion
+//!
+//! Casts a grid of rays through a sensor's field of view and reports how
+//! much of it other geometry blocks. Rays are spaced evenly on the image
+//! plane (in tangent space), so each sample covers the same area of the
+//! far plane, the way pixels do. Each ray runs from the near plane to the
+//! far plane and stops at the closest occluder.
+//!
+//! Occluders are tested coarse to fine: an oriented bounding box first,
+//! then the occluder's triangles for rays that enter the box. An occluder
+//! without triangles is treated as its solid bounding box.
+
+use serde::{Deserialize, Serialize};
+
+use crate::device::DeviceGeometry;
+use crate::hcdf::Pose;
+use crate::keepout::{cross, dot, norm, sub, Obb};
+
+/// Determinants smaller than this mean a ray parallel to a triangle
+const PARALLEL_EPSILON: f64 = 1e-12;
+
+/// Field of view volume along the local +Z axis
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FovShape {
+    /// Rectangular cross-section with full horizontal/vertical angles
+    Pyramid { near: f64, far: f64, hfov: f64, vfov: f64 },
+    /// Circular cross-section with the angle from the axis to the edge
+    Cone { near: f64, far: f64, half_angle: f64 },
+}
+
+impl FovShape {
+    /// FOV volume of a sensor geometry, or None for solid shapes
+    pub fn from_geometry(geometry: &DeviceGeometry) -> Option<Self> {
+        match *geometry {
+            DeviceGeometry::Frustum { near, far, hfov, vfov }
+            | DeviceGeometry::PyramidalFrustum { near, far, hfov, vfov } => {
+                Some(Self::Pyramid { near, far, hfov, vfov })
+            }
+            DeviceGeometry::ConicalFrustum { near, far, fov } => Some(Self::Cone { near, far, half_angle: fov }),
+            DeviceGeometry::Cone { radius, length } if length > 0.0 => Some(Self::Cone {
+                near: 0.0,
+                far: length,
+                half_angle: (radius / length).atan(),
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn near(&self) -> f64 {
+        match *self {
+            Self::Pyramid { near, .. } | Self::Cone { near, .. } => near.max(0.0),
+        }
+    }
+
+    pub fn far(&self) -> f64 {
+        match *self {
+            Self::Pyramid { far, .. } | Self::Cone { far, .. } => far,
+        }
+    }
+
+    /// Tangents of the half-angles along X and Y
+    fn extent(&self) -> (f64, f64) {
+        match *self {
+            Self::Pyramid { hfov, vfov, .. } => ((hfov / 2.0).tan(), (vfov / 2.0).tan()),
+            Self::Cone { half_angle, .. } => (half_angle.tan(), half_angle.tan()),
+        }
+    }
+
+    /// Image-plane sample points in [-1, 1]², centered in the cells of a
+    /// `resolution` × `resolution` grid; cones keep the cells inside the circle
+    pub fn grid(&self, resolution: usize) -> Vec<[f64; 2]> {
+        let cell = 2.0 / resolution as f64;
+        let mut points = Vec::with_capacity(resolution * resolution);
+        for j in 0..resolution {
+            for i in 0..resolution {
+                let uv = [-1.0 + cell * (i as f64 + 0.5), -1.0 + cell * (j as f64 + 0.5)];
+                if matches!(self, Self::Cone { .. }) && uv[0] * uv[0] + uv[1] * uv[1] > 1.0 {
+                    continue;
+                }
+                points.push(uv);
+            }
+        }
+        points
+    }
+
+    /// Unit ray direction in the FOV frame through image point `uv`
+    pub fn direction(&self, uv: [f64; 2]) -> [f64; 3] {
+        let (tx, ty) = self.extent();
+        let d = [uv[0] * tx, uv[1] * ty, 1.0];
+        let length = norm(d);
+        d.map(|v| v / length)
+    }
+
+    /// Corners of the grid cell around `uv` on the far plane, in the FOV frame
+    pub fn cell_corners(&self, uv: [f64; 2], resolution: usize) -> [[f64; 3]; 4] {
+        let (tx, ty) = self.extent();
+        let far = self.far();
+        let h = 1.0 / resolution as f64;
+        [(-h, -h), (h, -h), (h, h), (-h, h)].map(|(du, dv)| [(uv[0] + du) * tx * far, (uv[1] + dv) * ty * far, far])
+    }
+}
+
+/// Geometry that may block a sensor, in the same frame as the sensor pose
+#[derive(Debug, Clone)]
+pub struct Occluder {
+    /// Device ID reported for rays this occluder blocks
+    pub id: String,
+    /// Bounding box center and orientation
+    pub bbox_pose: Pose,
+    /// Bounding box full size (x, y, z)
+    pub bbox_size: [f64; 3],
+    /// Surface triangles; empty means the bounding box is solid
+    pub triangles: Vec<[[f64; 3]; 3]>,
+}
+
+impl Occluder {
+    /// Occluder from triangles, bounded by their axis-aligned box
+    ///
+    /// Returns None if there are no triangles.
+    pub fn from_triangles(id: impl Into<String>, triangles: Vec<[[f64; 3]; 3]>) -> Option<Self> {
+        let mut min = [f64::MAX; 3];
+        let mut max = [f64::MIN; 3];
+        for vertex in triangles.iter().flatten() {
+            min = [0, 1, 2].map(|i| min[i].min(vertex[i]));
+            max = [0, 1, 2].map(|i| max[i].max(vertex[i]));
+        }
+        if triangles.is_empty() {
+            return None;
+        }
+        Some(Self {
+            id: id.into(),
+            bbox_pose: Pose {
+                x: (min[0] + max[0]) / 2.0,
+                y: (min[1] + max[1]) / 2.0,
+                z: (min[2] + max[2]) / 2.0,
+                ..Default::default()
+            },
+            bbox_size: [0, 1, 2].map(|i| max[i] - min[i]),
+            triangles,
+        })
+    }
+
+    /// Distance along the ray to the first hit within [t_min, t_max]
+    fn hit(&self, obb: &Obb, origin: [f64; 3], direction: [f64; 3], t_min: f64, t_max: f64) -> Option<f64> {
+        let (enter, exit) = slab(obb, origin, direction, t_min, t_max)?;
+        if self.triangles.is_empty() {
+            return Some(enter);
+        }
+        self.triangles
+            .iter()
+            .filter_map(|triangle| intersect_triangle(triangle, origin, direction))
+            .filter(|&t| t >= enter && t <= exit)
+            .min_by(f64::total_cmp)
+    }
+}
+
+/// The closest occluder a ray hit
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OcclusionHit {
+    pub occluder: String,
+    /// Distance from the sensor origin in meters
+    pub distance: f64,
+}
+
+/// One ray of the grid
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OcclusionSample {
+    /// Image-plane point in [-1, 1]²
+    pub uv: [f64; 2],
+    pub hit: Option<OcclusionHit>,
+}
+
+/// An occluder blocking part of the FOV
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OcclusionIntruder {
+    pub id: String,
+    /// Number of rays it blocks first
+    pub rays: usize,
+    /// Share of the FOV it blocks (0-100)
+    pub percent: f64,
+}
+
+/// Result of an occlusion check
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OcclusionReport {
+    /// Grid cells per side
+    pub resolution: usize,
+    pub samples: Vec<OcclusionSample>,
+    /// Occluders that block any ray, most rays first
+    pub intruders: Vec<OcclusionIntruder>,
+}
+
+impl OcclusionReport {
+    /// Number of blocked rays
+    pub fn blocked(&self) -> usize {
+        self.samples.iter().filter(|s| s.hit.is_some()).count()
+    }
+
+    /// Share of the FOV blocked by anything (0-100)
+    pub fn blocked_percent(&self) -> f64 {
+        percent(self.blocked(), self.samples.len())
+    }
+}
+
+/// Cast `resolution`² rays through a FOV at `pose` against the occluders
+pub fn check_occlusion(pose: &Pose, shape: &FovShape, occluders: &[Occluder], resolution: usize) -> OcclusionReport {
+    let r = pose.rotation_matrix();
+    let origin = [pose.x, pose.y, pose.z];
+    let boxes: Vec<Obb> = occluders.iter().map(|o| Obb::from_pose(&o.bbox_pose, o.bbox_size)).collect();
+
+    let samples: Vec<OcclusionSample> = shape
+        .grid(resolution.max(1))
+        .into_iter()
+        .map(|uv| {
+            let local = shape.direction(uv);
+            let direction = [0, 1, 2].map(|i| dot(r[i], local));
+            // The near and far planes are perpendicular to the FOV axis
+            let t_min = shape.near() / local[2];
+            let t_max = shape.far() / local[2];
+            let hit = occluders
+                .iter()
+                .zip(&boxes)
+                .filter_map(|(occluder, obb)| Some((occluder, occluder.hit(obb, origin, direction, t_min, t_max)?)))
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(occluder, distance)| OcclusionHit { occluder: occluder.id.clone(), distance });
+            OcclusionSample { uv, hit }
+        })
+        .collect();
+
+    let mut intruders: Vec<OcclusionIntruder> = Vec::new();
+    for hit in samples.iter().filter_map(|s| s.hit.as_ref()) {
+        match intruders.iter_mut().find(|i| i.id == hit.occluder) {
+            Some(intruder) => intruder.rays += 1,
+            None => intruders.push(OcclusionIntruder { id: hit.occluder.clone(), rays: 1, percent: 0.0 }),
+        }
+    }
+    for intruder in &mut intruders {
+        intruder.percent = percent(intruder.rays, samples.len());
+    }
+    intruders.sort_by(|a, b| b.rays.cmp(&a.rays).then_with(|| a.id.cmp(&b.id)));
+
+    OcclusionReport { resolution: resolution.max(1), samples, intruders }
+}
+
+fn percent(count: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        count as f64 * 100.0 / total as f64
+    }
+}
+
+/// Part of [t_min, t_max] the ray spends inside the box, or None if it misses
+fn slab(obb: &Obb, origin: [f64; 3], direction: [f64; 3], t_min: f64, t_max: f64) -> Option<(f64, f64)> {
+    let offset = sub(origin, obb.center);
+    let (mut enter, mut exit) = (t_min, t_max);
+    for (&axis, &half) in obb.axes.iter().zip(&obb.half) {
+        let o = dot(offset, axis);
+        let d = dot(direction, axis);
+        if d.abs() < PARALLEL_EPSILON {
+            if o.abs() > half {
+                return None;
+            }
+            continue;
+        }
+        let t0 = (-half - o) / d;
+        let t1 = (half - o) / d;
+        enter = enter.max(t0.min(t1));
+        exit = exit.min(t0.max(t1));
+        if enter > exit {
+            return None;
+        }
+    }
+    Some((enter, exit))
+}
+
+/// Möller–Trumbore ray/triangle intersection, either winding
+fn intersect_triangle(triangle: &[[f64; 3]; 3], origin: [f64; 3], direction: [f64; 3]) -> Option<f64> {
+    let edge1 = sub(triangle[1], triangle[0]);
+    let edge2 = sub(triangle[2], triangle[0]);
+    let p = cross(direction, edge2);
+    let det = dot(edge1, p);
+    if det.abs() < PARALLEL_EPSILON {
+        return None;
+    }
+    let s = sub(origin, triangle[0]);
+    let u = dot(s, p) / det;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = cross(s, edge1);
+    let v = dot(direction, q) / det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    Some(dot(edge2, q) / det)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RESOLUTION: usize = 16;
+
+    /// 90° × 90° camera at the origin looking along +X
+    fn camera() -> (Pose, FovShape) {
+        let pose = Pose { pitch: std::f64::consts::FRAC_PI_2, ..Default::default() };
+        let shape = FovShape::Pyramid {
+            near: 0.01,
+            far: 1.0,
+            hfov: std::f64::consts::FRAC_PI_2,
+            vfov: std::f64::consts::FRAC_PI_2,
+        };
+        (pose, shape)
+    }
+
+    fn solid(id: &str, center: [f64; 3], size: [f64; 3]) -> Occluder {
+        Occluder {
+            id: id.to_string(),
+            bbox_pose: Pose { x: center[0], y: center[1], z: center[2], ..Default::default() },
+            bbox_size: size,
+            triangles: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_clear_fov() {
+        let (pose, shape) = camera();
+        // Behind the camera, and beyond the far plane
+        let occluders = [solid("behind", [-0.5, 0.0, 0.0], [0.2; 3]), solid("far", [2.0, 0.0, 0.0], [1.0; 3])];
+        let report = check_occlusion(&pose, &shape, &occluders, RESOLUTION);
+        assert_eq!(report.samples.len(), RESOLUTION * RESOLUTION);
+        assert_eq!(report.blocked(), 0);
+        assert_eq!(report.blocked_percent(), 0.0);
+        assert!(report.intruders.is_empty());
+    }
+
+    #[test]
+    fn test_box_blocks_half_the_fov() {
+        let (pose, shape) = camera();
+        // Fills the +Y half of the view from 0.5 m out
+        let report = check_occlusion(&pose, &shape, &[solid("gps", [0.7, 0.5, 0.0], [0.2, 1.0, 2.0])], RESOLUTION);
+        assert!((report.blocked_percent() - 50.0).abs() < 1e-9, "{}", report.blocked_percent());
+        assert_eq!(report.intruders.len(), 1);
+        assert_eq!(report.intruders[0].id, "gps");
+        assert_eq!(report.intruders[0].rays, report.blocked());
+        for sample in report.samples.iter().filter_map(|s| s.hit.as_ref()) {
+            assert!(sample.distance >= 0.6);
+        }
+    }
+
+    #[test]
+    fn test_triangles_refine_the_bounding_box() {
+        let (pose, shape) = camera();
+        // One triangle spanning the lower-left half of a plane at x = 0.5:
+        // its bounding box covers the whole view, the triangle only half
+        let triangle = [[0.5, -1.0, -1.0], [0.5, 1.0, -1.0], [0.5, -1.0, 1.0]];
+        let plate = Occluder::from_triangles("plate", vec![triangle]).unwrap();
+        let report = check_occlusion(&pose, &shape, &[plate], RESOLUTION);
+        let blocked = report.blocked_percent();
+        assert!(blocked > 40.0 && blocked < 60.0, "{blocked}");
+        assert!(Occluder::from_triangles("empty", Vec::new()).is_none());
+    }
+
+    #[test]
+    fn test_nearest_occluder_wins() {
+        let (pose, shape) = camera();
+        let occluders = [solid("back", [0.8, 0.0, 0.0], [0.1, 2.0, 2.0]), solid("front", [0.3, 0.0, 0.0], [0.1, 0.2, 0.2])];
+        let report = check_occlusion(&pose, &shape, &occluders, RESOLUTION);
+        assert_eq!(report.blocked(), report.samples.len());
+        let ids: Vec<&str> = report.intruders.iter().map(|i| i.id.as_str()).collect();
+        assert_eq!(ids, ["back", "front"]);
+        let center = report.samples.iter().find(|s| s.uv[0].abs() < 0.1 && s.uv[1].abs() < 0.1).unwrap();
+        assert_eq!(center.hit.as_ref().unwrap().occluder, "front");
+        let total: f64 = report.intruders.iter().map(|i| i.percent).sum();
+        assert!((total - 100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_cone_grid_drops_corners() {
+        let shape = FovShape::from_geometry(&DeviceGeometry::ConicalFrustum { near: 0.0, far: 1.0, fov: 0.3 }).unwrap();
+        let grid = shape.grid(RESOLUTION);
+        assert!(grid.len() < RESOLUTION * RESOLUTION);
+        assert!(grid.len() > RESOLUTION * RESOLUTION * 3 / 4);
+        assert!(grid.iter().all(|uv| uv[0] * uv[0] + uv[1] * uv[1] <= 1.0));
+
+        // Edge rays leave the axis at the half-angle
+        let edge = shape.direction([1.0, 0.0]);
+        assert!((edge[0].atan2(edge[2]) - 0.3).abs() < 1e-12);
+        assert!(FovShape::from_geometry(&DeviceGeometry::Sphere { radius: 1.0 }).is_none());
+    }
+}
@@ -0,0 +1,13 @@
+//! Wire protocol versioning
+//!
+//! The daemon reports `WS_PROTOCOL_VERSION` from `GET /api/version`. A
+//! frontend built against a newer version may expect WebSocket messages or
+//! REST fields the daemon doesn't send, so it warns the user instead of
+//! failing in confusing ways. Bump the version whenever those payloads
+//! change incompatibly.
+
+/// WebSocket/REST protocol version spoken by this build
+///
+/// - 1: one full device message per device event
+/// - 2: device deltas for clients connecting with `?protocol=2`
+pub const WS_PROTOCOL_VERSION: u32 = 2;
@@ -0,0 +1,279 @@
+//! Element provenance
+//!
+//! The daemon assembles its HCDF from several sources: devices describing
+//! themselves, the local fragment database, imported files and edits made in
+//! the UI. `ProvenanceRegistry` remembers where each `<mcu>`/`<comp>` came
+//! from so an export can say so in a comment above the element:
+//!
+//! ```xml
+//! <!-- source: device mr-001 firmware 1.4.2, merged 2025-06-01 -->
+//! <mcu name="mr-001" hwid="0x1">
+//! ```
+//!
+//! Comments are ignored when the file is parsed again.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::hcdf::{Comp, Hcdf, HcdfError, Mcu};
+
+/// Where an element came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProvenanceSource {
+    /// Discovered on the network, described by the device's own HCDF
+    Device,
+    /// Matched from the local fragment database
+    Fragment,
+    /// Loaded from an imported HCDF file
+    Import,
+    /// Created from the UI
+    Manual,
+}
+
+/// Where an element came from and when it was merged
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Provenance {
+    pub source: ProvenanceSource,
+    /// Device or fragment name
+    pub name: Option<String>,
+    /// Firmware version the device reported
+    pub firmware: Option<String>,
+    pub merged_at: DateTime<Utc>,
+    /// Last edit made from the UI after merging
+    pub edited_at: Option<DateTime<Utc>>,
+}
+
+impl Provenance {
+    pub fn new(source: ProvenanceSource, name: Option<String>, firmware: Option<String>) -> Self {
+        Self {
+            source,
+            name,
+            firmware,
+            merged_at: Utc::now(),
+            edited_at: None,
+        }
+    }
+
+    /// Comment text, e.g. "source: device mr-001 firmware 1.4.2, merged 2025-06-01"
+    pub fn comment(&self) -> String {
+        let source = match self.source {
+            ProvenanceSource::Device => "device",
+            ProvenanceSource::Fragment => "fragment",
+            ProvenanceSource::Import => "import",
+            ProvenanceSource::Manual => "manual edit",
+        };
+        let mut text = format!("source: {}", source);
+        if let Some(name) = &self.name {
+            text.push_str(&format!(" {}", name));
+        }
+        if let Some(firmware) = &self.firmware {
+            text.push_str(&format!(" firmware {}", firmware));
+        }
+        text.push_str(&format!(", merged {}", self.merged_at.format("%Y-%m-%d")));
+        if let Some(edited_at) = self.edited_at {
+            text.push_str(&format!(", edited {}", edited_at.format("%Y-%m-%d")));
+        }
+        // "--" may not appear inside an XML comment
+        while text.contains("--") {
+            text = text.replace("--", "- -");
+        }
+        text
+    }
+}
+
+/// Provenance records for HCDF elements
+///
+/// MCUs are keyed by hwid, falling back to name; comps by name (the same
+/// keys `Hcdf::diff` matches on).
+#[derive(Debug, Clone, Default)]
+pub struct ProvenanceRegistry {
+    records: HashMap<String, Provenance>,
+}
+
+impl ProvenanceRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn mcu_key(key: &str) -> String {
+        format!("mcu:{}", key)
+    }
+
+    fn comp_key(name: &str) -> String {
+        format!("comp:{}", name)
+    }
+
+    /// Record where an MCU came from, keeping the date of any earlier UI edit
+    pub fn record_mcu(&mut self, key: &str, provenance: Provenance) {
+        self.record(Self::mcu_key(key), provenance);
+    }
+
+    /// Record where a comp came from, keeping the date of any earlier UI edit
+    pub fn record_comp(&mut self, name: &str, provenance: Provenance) {
+        self.record(Self::comp_key(name), provenance);
+    }
+
+    fn record(&mut self, key: String, mut provenance: Provenance) {
+        if let Some(existing) = self.records.get(&key) {
+            provenance.edited_at = provenance.edited_at.or(existing.edited_at);
+        }
+        self.records.insert(key, provenance);
+    }
+
+    /// Note a UI edit to an MCU; one without a record becomes a manual entry
+    pub fn mark_mcu_edited(&mut self, key: &str) {
+        let now = Utc::now();
+        self.records
+            .entry(Self::mcu_key(key))
+            .or_insert_with(|| Provenance::new(ProvenanceSource::Manual, None, None))
+            .edited_at = Some(now);
+    }
+
+    pub fn mcu(&self, mcu: &Mcu) -> Option<&Provenance> {
+        let key = mcu.hwid.as_deref().unwrap_or(&mcu.name);
+        self.records.get(&Self::mcu_key(key))
+    }
+
+    pub fn comp(&self, comp: &Comp) -> Option<&Provenance> {
+        self.records.get(&Self::comp_key(&comp.name))
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+}
+
+impl Hcdf {
+    /// Serialize like `to_xml`, with a provenance comment above each
+    /// top-level `<mcu>`/`<comp>` that has a record
+    pub fn to_xml_annotated(&self, provenance: &ProvenanceRegistry) -> Result<String, HcdfError> {
+        let xml = self.to_xml()?;
+        let mut mcus = self.mcu.iter();
+        let mut comps = self.comp.iter();
+
+        let mut out = String::with_capacity(xml.len());
+        for line in xml.split_inclusive('\n') {
+            // Root children are indented by exactly two spaces
+            let record = if is_element_start(line, "mcu") {
+                mcus.next().and_then(|m| provenance.mcu(m))
+            } else if is_element_start(line, "comp") {
+                comps.next().and_then(|c| provenance.comp(c))
+            } else {
+                None
+            };
+            if let Some(record) = record {
+                out.push_str(&format!("  <!-- {} -->\n", record.comment()));
+            }
+            out.push_str(line);
+        }
+        Ok(out)
+    }
+}
+
+fn is_element_start(line: &str, tag: &str) -> bool {
+    line.strip_prefix("  <")
+        .and_then(|rest| rest.strip_prefix(tag))
+        .is_some_and(|rest| rest.starts_with([' ', '>', '/']))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn assembly() -> Hcdf {
+        Hcdf::from_xml(
+            r#"<?xml version='1.0'?>
+<hcdf version="2.1">
+  <mcu name="mr-001" hwid="0x1">
+    <board>mr_mcxn_t1</board>
+  </mcu>
+  <mcu name="spare">
+    <board>mr_mcxn_t1</board>
+  </mcu>
+  <comp name="frame" role="parent">
+    <description>Airframe</description>
+  </comp>
+  <comp name="gnss">
+    <description>GNSS module</description>
+  </comp>
+</hcdf>"#,
+        )
+        .unwrap()
+    }
+
+    fn at(source: ProvenanceSource, name: Option<&str>, firmware: Option<&str>, day: u32) -> Provenance {
+        Provenance {
+            source,
+            name: name.map(String::from),
+            firmware: firmware.map(String::from),
+            merged_at: Utc.with_ymd_and_hms(2025, 6, day, 12, 0, 0).unwrap(),
+            edited_at: None,
+        }
+    }
+
+    #[test]
+    fn test_annotated_export_places_comments_above_elements() {
+        let hcdf = assembly();
+        let mut registry = ProvenanceRegistry::new();
+        registry.record_mcu("0x1", at(ProvenanceSource::Device, Some("mr-001"), Some("1.4.2"), 1));
+        registry.record_comp("gnss", at(ProvenanceSource::Import, None, None, 2));
+
+        let plain = hcdf.to_xml().unwrap();
+        let expected = plain
+            .replacen(
+                "  <mcu name=\"mr-001\"",
+                "  <!-- source: device mr-001 firmware 1.4.2, merged 2025-06-01 -->\n  <mcu name=\"mr-001\"",
+                1,
+            )
+            .replacen(
+                "  <comp name=\"gnss\"",
+                "  <!-- source: import, merged 2025-06-02 -->\n  <comp name=\"gnss\"",
+                1,
+            );
+        assert_ne!(expected, plain);
+        assert_eq!(hcdf.to_xml_annotated(&registry).unwrap(), expected);
+
+        // Elements without a record, and documents without any, are untouched
+        assert_eq!(hcdf.to_xml_annotated(&ProvenanceRegistry::new()).unwrap(), plain);
+    }
+
+    #[test]
+    fn test_annotated_export_reimports_cleanly() {
+        let hcdf = assembly();
+        let mut registry = ProvenanceRegistry::new();
+        registry.record_mcu("0x1", at(ProvenanceSource::Device, Some("mr-001"), Some("1.4.2"), 1));
+        registry.record_mcu("spare", at(ProvenanceSource::Fragment, Some("mr_mcxn_t1"), None, 1));
+        registry.record_comp("frame", at(ProvenanceSource::Manual, None, None, 3));
+
+        let annotated = hcdf.to_xml_annotated(&registry).unwrap();
+        assert_eq!(annotated.matches("<!-- source:").count(), 3);
+        let reimported = Hcdf::from_xml(&annotated).unwrap();
+        assert!(hcdf.diff(&reimported).is_empty());
+        assert_eq!(reimported.to_xml().unwrap(), hcdf.to_xml().unwrap());
+    }
+
+    #[test]
+    fn test_edits_survive_rediscovery() {
+        let mut registry = ProvenanceRegistry::new();
+        registry.record_mcu("0x1", at(ProvenanceSource::Device, Some("mr-001"), Some("1.4.2"), 1));
+        registry.mark_mcu_edited("0x1");
+        registry.record_mcu("0x1", at(ProvenanceSource::Device, Some("mr-001"), Some("1.5.0"), 4));
+
+        let hcdf = assembly();
+        let record = registry.mcu(&hcdf.mcu[0]).unwrap();
+        assert_eq!(record.firmware.as_deref(), Some("1.5.0"));
+        assert!(record.edited_at.is_some());
+        assert!(record.comment().starts_with("source: device mr-001 firmware 1.5.0, merged 2025-06-04, edited "));
+
+        // Names can't break out of the comment
+        let odd = at(ProvenanceSource::Fragment, Some("a--->b"), None, 1);
+        assert!(!odd.comment().contains("--"));
+    }
+}
@@ -0,0 +1,634 @@
+//! Unit-aware capability quantities
+//!
+//! Port and antenna capabilities are written as a number plus a unit, e.g.
+//! `<current unit="mA">100</current>`. A `Quantity` keeps the number and unit
+//! as written, so re-exported HCDF reads the same, and also knows its
+//! dimension and SI value, so "0.1 A" and "100 mA" compare equal and display
+//! the same way with an automatically chosen prefix.
+//!
+//! Units that aren't recognised are kept verbatim and only compare equal to
+//! the same unit.
+
+use serde::{Deserialize, Deserializer, Serialize};
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Error parsing a quantity
+#[derive(Debug, Error, PartialEq)]
+pub enum QuantityError {
+    #[error("Invalid number: {0}")]
+    InvalidNumber(String),
+}
+
+/// What a quantity measures
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Dimension {
+    /// Bare number without a unit
+    Dimensionless,
+    /// Volts
+    Voltage,
+    /// Amperes
+    Current,
+    /// Watts
+    Power,
+    /// Hertz
+    Frequency,
+    /// Bits per second
+    DataRate,
+    /// Symbols per second (baud)
+    SymbolRate,
+    /// Joules; displayed in watt-hours
+    Energy,
+    /// Coulombs; displayed in ampere-hours
+    Charge,
+    /// Logarithmic gain (dB, dBi); never prefixed
+    Gain,
+    /// Unit not recognised; kept as written
+    Other,
+}
+
+impl Dimension {
+    /// Display unit, its size in SI units, and the prefixes chosen from
+    fn display(self) -> Option<(&'static str, f64, &'static [Prefix])> {
+        use Prefix::*;
+        let display: (&'static str, f64, &'static [Prefix]) = match self {
+            Self::Voltage => ("V", 1.0, &[Milli, One, Kilo]),
+            Self::Current => ("A", 1.0, &[Micro, Milli, One]),
+            Self::Power => ("W", 1.0, &[Milli, One, Kilo, Mega]),
+            Self::Frequency => ("Hz", 1.0, &[One, Kilo, Mega, Giga]),
+            Self::DataRate => ("bps", 1.0, &[One, Kilo, Mega, Giga, Tera]),
+            // Baud rates are conventionally written in full
+            Self::SymbolRate => ("baud", 1.0, &[One]),
+            Self::Energy => ("Wh", 3600.0, &[Milli, One, Kilo, Mega]),
+            Self::Charge => ("Ah", 3600.0, &[Milli, One]),
+            Self::Dimensionless | Self::Gain | Self::Other => return None,
+        };
+        Some(display)
+    }
+
+    /// Rates below one per second don't occur in hardware specs, so a
+    /// lowercase "m" on these means mega ("mbps", "mhz")
+    fn lowercase_m_is_mega(self) -> bool {
+        matches!(self, Self::Frequency | Self::DataRate | Self::SymbolRate)
+    }
+}
+
+/// SI prefixes used for display
+#[derive(Debug, Clone, Copy)]
+enum Prefix {
+    Micro,
+    Milli,
+    One,
+    Kilo,
+    Mega,
+    Giga,
+    Tera,
+}
+
+impl Prefix {
+    fn symbol(self) -> &'static str {
+        match self {
+            Self::Micro => "µ",
+            Self::Milli => "m",
+            Self::One => "",
+            Self::Kilo => "k",
+            Self::Mega => "M",
+            Self::Giga => "G",
+            Self::Tera => "T",
+        }
+    }
+
+    fn factor(self) -> f64 {
+        match self {
+            Self::Micro => 1e-6,
+            Self::Milli => 1e-3,
+            Self::One => 1.0,
+            Self::Kilo => 1e3,
+            Self::Mega => 1e6,
+            Self::Giga => 1e9,
+            Self::Tera => 1e12,
+        }
+    }
+}
+
+/// Unprefixed unit spellings: (spelling, dimension, size in SI units, case-sensitive)
+///
+/// Bits and bytes differ only in case, so those spellings are matched exactly.
+const UNITS: &[(&str, Dimension, f64, bool)] = &[
+    ("V", Dimension::Voltage, 1.0, false),
+    ("volt", Dimension::Voltage, 1.0, false),
+    ("volts", Dimension::Voltage, 1.0, false),
+    ("A", Dimension::Current, 1.0, false),
+    ("amp", Dimension::Current, 1.0, false),
+    ("amps", Dimension::Current, 1.0, false),
+    ("ampere", Dimension::Current, 1.0, false),
+    ("amperes", Dimension::Current, 1.0, false),
+    ("W", Dimension::Power, 1.0, false),
+    ("watt", Dimension::Power, 1.0, false),
+    ("watts", Dimension::Power, 1.0, false),
+    ("Hz", Dimension::Frequency, 1.0, false),
+    ("hertz", Dimension::Frequency, 1.0, false),
+    ("bps", Dimension::DataRate, 1.0, true),
+    ("b/s", Dimension::DataRate, 1.0, true),
+    ("bit/s", Dimension::DataRate, 1.0, false),
+    ("bits/s", Dimension::DataRate, 1.0, false),
+    ("bit/sec", Dimension::DataRate, 1.0, false),
+    ("Bps", Dimension::DataRate, 8.0, true),
+    ("B/s", Dimension::DataRate, 8.0, true),
+    ("byte/s", Dimension::DataRate, 8.0, false),
+    ("bytes/s", Dimension::DataRate, 8.0, false),
+    ("baud", Dimension::SymbolRate, 1.0, false),
+    ("Bd", Dimension::SymbolRate, 1.0, false),
+    ("Wh", Dimension::Energy, 3600.0, false),
+    ("J", Dimension::Energy, 1.0, false),
+    ("Ah", Dimension::Charge, 3600.0, false),
+    ("C", Dimension::Charge, 1.0, false),
+    ("dB", Dimension::Gain, 1.0, false),
+    ("dBi", Dimension::Gain, 1.0, false),
+];
+
+fn lookup(spelling: &str) -> Option<(Dimension, f64)> {
+    UNITS
+        .iter()
+        .find(|(s, _, _, exact)| if *exact { *s == spelling } else { s.eq_ignore_ascii_case(spelling) })
+        .map(|(_, dimension, scale, _)| (*dimension, *scale))
+}
+
+/// Dimension and size in SI units of a unit spelling, e.g. "mAh" or "MBit/s"
+pub fn parse_unit(unit: &str) -> Option<(Dimension, f64)> {
+    let unit = unit.trim();
+    if unit.is_empty() {
+        return Some((Dimension::Dimensionless, 1.0));
+    }
+    if let Some(found) = lookup(unit) {
+        return Some(found);
+    }
+    let mut chars = unit.chars();
+    let prefix = chars.next()?;
+    let (dimension, scale) = lookup(chars.as_str())?;
+    let factor = match prefix {
+        'p' => 1e-12,
+        'n' => 1e-9,
+        'u' | 'µ' | 'μ' => 1e-6,
+        'm' if dimension.lowercase_m_is_mega() => 1e6,
+        'm' => 1e-3,
+        'k' | 'K' => 1e3,
+        'M' => 1e6,
+        'G' | 'g' => 1e9,
+        'T' => 1e12,
+        _ => return None,
+    };
+    // Gains are logarithmic and can't take a prefix
+    (dimension != Dimension::Gain).then_some((dimension, scale * factor))
+}
+
+/// Split "100 mA" or "1.5GHz" into the number and the unit after it
+fn split_number(s: &str) -> Result<(f64, &str), QuantityError> {
+    let s = s.trim();
+    let mut end = s
+        .find(|c: char| !(c.is_ascii_digit() || matches!(c, '.' | '+' | '-' | 'e' | 'E')))
+        .unwrap_or(s.len());
+    // An "e" that isn't an exponent belongs to the unit
+    while end > 0 && s[..end].parse::<f64>().is_err() {
+        end -= 1;
+    }
+    let number = s[..end]
+        .parse::<f64>()
+        .map_err(|_| QuantityError::InvalidNumber(s.to_string()))?;
+    Ok((number, s[end..].trim()))
+}
+
+/// Format a number with at most three decimals and no trailing zeros
+fn format_number(value: f64) -> String {
+    let s = format!("{:.3}", value);
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    if s == "-0" { "0".to_string() } else { s.to_string() }
+}
+
+/// Resolved unit of a quantity
+#[derive(Debug, Clone, PartialEq)]
+struct Unit {
+    /// Unit the number is in (written or default), "" for bare numbers
+    symbol: String,
+    dimension: Dimension,
+    /// Size of one `symbol` in SI units
+    scale: f64,
+}
+
+impl Unit {
+    fn resolve(written: Option<&str>, default: &str) -> Self {
+        let symbol = written.unwrap_or(default).trim().to_string();
+        let (dimension, scale) = parse_unit(&symbol).unwrap_or((Dimension::Other, 1.0));
+        Self { symbol, dimension, scale }
+    }
+}
+
+/// A number with a unit, e.g. a port's bitrate or maximum current
+#[derive(Debug, Clone, Serialize)]
+#[serde(into = "ValueWithUnit")]
+pub struct Quantity {
+    /// Number as written, in the quantity's unit
+    pub value: f64,
+    /// Unit as written; None if the field's default unit applies
+    pub unit: Option<String>,
+    resolved: Unit,
+}
+
+impl Quantity {
+    /// A quantity in the given unit, e.g. `Quantity::new(100.0, "mA")`
+    pub fn new(value: f64, unit: &str) -> Self {
+        let unit = Some(unit.to_string()).filter(|u| !u.trim().is_empty());
+        Self { value, resolved: Unit::resolve(unit.as_deref(), ""), unit }
+    }
+
+    /// Build from the XML value and unit attribute, falling back to
+    /// `default_unit` when neither the attribute nor the value names a unit
+    pub fn from_parts(value: &str, unit: Option<&str>, default_unit: &str) -> Result<Self, QuantityError> {
+        let (value, inline) = split_number(value)?;
+        let unit = unit
+            .or(Some(inline))
+            .map(str::trim)
+            .filter(|u| !u.is_empty())
+            .map(str::to_string);
+        Ok(Self { value, resolved: Unit::resolve(unit.as_deref(), default_unit), unit })
+    }
+
+    pub fn dimension(&self) -> Dimension {
+        self.resolved.dimension
+    }
+
+    /// Value in the SI unit of the dimension (volts, amperes, bits/s, joules...)
+    pub fn si(&self) -> f64 {
+        self.value * self.resolved.scale
+    }
+
+    /// Whether two quantities measure the same thing and can be compared
+    pub fn is_comparable(&self, other: &Self) -> bool {
+        self.dimension() == other.dimension()
+            && (self.dimension().display().is_some()
+                || self.resolved.symbol.eq_ignore_ascii_case(&other.resolved.symbol))
+    }
+}
+
+impl PartialEq for Quantity {
+    fn eq(&self, other: &Self) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for Quantity {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        if !self.is_comparable(other) {
+            return None;
+        }
+        let (a, b) = (self.si(), other.si());
+        // Unit conversion isn't exact in floating point
+        if (a - b).abs() <= 1e-9 * a.abs().max(b.abs()) {
+            Some(Ordering::Equal)
+        } else {
+            a.partial_cmp(&b)
+        }
+    }
+}
+
+impl fmt::Display for Quantity {
+    /// Format with the largest prefix that keeps the number at least 1,
+    /// e.g. 0.1 A as "100 mA" and 1000 Mbps as "1 Gbps"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some((symbol, size, prefixes)) = self.dimension().display() else {
+            return match self.resolved.symbol.as_str() {
+                "" => write!(f, "{}", format_number(self.value)),
+                symbol => write!(f, "{} {}", format_number(self.value), symbol),
+            };
+        };
+        let value = self.si() / size;
+        let prefix = prefixes
+            .iter()
+            .rev()
+            .find(|p| value.abs() >= p.factor())
+            .or(if value == 0.0 { prefixes.iter().find(|p| p.factor() == 1.0) } else { None })
+            .unwrap_or(&prefixes[0]);
+        write!(f, "{} {}{}", format_number(value / prefix.factor()), prefix.symbol(), symbol)
+    }
+}
+
+impl FromStr for Quantity {
+    type Err = QuantityError;
+
+    /// Parse "100 mA", "1.5GHz" or a bare number
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_parts(s, None, "")
+    }
+}
+
+/// XML form of a quantity: `<speed unit="Mbps">1000</speed>`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValueWithUnit {
+    #[serde(rename = "@unit", default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    #[serde(rename = "$value")]
+    pub value: String,
+}
+
+impl TryFrom<ValueWithUnit> for Quantity {
+    type Error = QuantityError;
+
+    fn try_from(raw: ValueWithUnit) -> Result<Self, Self::Error> {
+        Self::from_parts(&raw.value, raw.unit.as_deref(), "")
+    }
+}
+
+impl From<Quantity> for ValueWithUnit {
+    fn from(quantity: Quantity) -> Self {
+        Self { unit: quantity.unit, value: quantity.value.to_string() }
+    }
+}
+
+impl<'de> Deserialize<'de> for Quantity {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        ValueWithUnit::deserialize(deserializer)?
+            .try_into()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// A rating with optional nominal value and range sharing one unit
+/// Example: `<voltage unit="V" min="7" max="28">12</voltage>`
+#[derive(Debug, Clone, Serialize)]
+#[serde(into = "RawRated")]
+pub struct RatedQuantity {
+    /// Unit as written; None if the field's default unit applies
+    pub unit: Option<String>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    /// Nominal value
+    pub value: Option<f64>,
+    resolved: Unit,
+}
+
+/// XML form of a `RatedQuantity`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RawRated {
+    #[serde(rename = "@unit", default, skip_serializing_if = "Option::is_none")]
+    unit: Option<String>,
+    #[serde(rename = "@min", default, skip_serializing_if = "Option::is_none")]
+    min: Option<f64>,
+    #[serde(rename = "@max", default, skip_serializing_if = "Option::is_none")]
+    max: Option<f64>,
+    #[serde(rename = "$value", default)]
+    value: Option<String>,
+}
+
+impl RawRated {
+    fn resolve(self, default_unit: &str) -> Result<RatedQuantity, QuantityError> {
+        let value = match self.value.as_deref().map(str::trim) {
+            Some(v) if !v.is_empty() => Some(v.parse().map_err(|_| QuantityError::InvalidNumber(v.to_string()))?),
+            _ => None,
+        };
+        Ok(RatedQuantity {
+            resolved: Unit::resolve(self.unit.as_deref(), default_unit),
+            unit: self.unit,
+            min: self.min,
+            max: self.max,
+            value,
+        })
+    }
+}
+
+impl From<RatedQuantity> for RawRated {
+    fn from(rated: RatedQuantity) -> Self {
+        Self {
+            unit: rated.unit,
+            min: rated.min,
+            max: rated.max,
+            value: rated.value.map(|v| v.to_string()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for RatedQuantity {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        RawRated::deserialize(deserializer)?
+            .resolve("")
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl RatedQuantity {
+    fn quantity(&self, value: Option<f64>) -> Option<Quantity> {
+        value.map(|value| Quantity { value, unit: self.unit.clone(), resolved: self.resolved.clone() })
+    }
+
+    pub fn nominal(&self) -> Option<Quantity> {
+        self.quantity(self.value)
+    }
+
+    pub fn min_quantity(&self) -> Option<Quantity> {
+        self.quantity(self.min)
+    }
+
+    pub fn max_quantity(&self) -> Option<Quantity> {
+        self.quantity(self.max)
+    }
+
+    /// Format as display string (e.g., "12 V (7-28 V)", "3 A max")
+    pub fn to_display_string(&self) -> String {
+        let range = || match (self.min_quantity(), self.max_quantity()) {
+            (Some(min), Some(max)) => Some(format_range(&min, &max)),
+            (None, Some(max)) => Some(format!("max {}", max)),
+            (Some(min), None) => Some(format!("min {}", min)),
+            (None, None) => None,
+        };
+        match (self.nominal(), self.min, self.max) {
+            (Some(nominal), None, None) => nominal.to_string(),
+            (Some(nominal), _, _) => format!("{} ({})", nominal, range().unwrap_or_default()),
+            (None, Some(_), Some(_)) => range().unwrap_or_default(),
+            (None, None, Some(_)) => format!("{} max", self.max_quantity().map(|q| q.to_string()).unwrap_or_default()),
+            (None, Some(_), None) => format!("{} min", self.min_quantity().map(|q| q.to_string()).unwrap_or_default()),
+            (None, None, None) => String::new(),
+        }
+    }
+}
+
+/// "7-28 V" when both ends share a unit, "100 mA-3 A" otherwise
+fn format_range(min: &Quantity, max: &Quantity) -> String {
+    let (min, max) = (min.to_string(), max.to_string());
+    match (min.split_once(' '), max.split_once(' ')) {
+        (Some((number, unit)), Some((_, max_unit))) if unit == max_unit => format!("{}-{}", number, max),
+        _ => format!("{}-{}", min, max),
+    }
+}
+
+/// Deserialize an optional quantity, assuming `default_unit` if none is written
+pub fn deserialize_in<'de, D: Deserializer<'de>>(deserializer: D, default_unit: &str) -> Result<Option<Quantity>, D::Error> {
+    let raw = ValueWithUnit::deserialize(deserializer)?;
+    Quantity::from_parts(&raw.value, raw.unit.as_deref(), default_unit)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+/// Deserialize an optional rating, assuming `default_unit` if none is written
+pub fn deserialize_rated_in<'de, D: Deserializer<'de>>(deserializer: D, default_unit: &str) -> Result<Option<RatedQuantity>, D::Error> {
+    RawRated::deserialize(deserializer)?
+        .resolve(default_unit)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn q(s: &str) -> Quantity {
+        s.parse().unwrap_or_else(|e| panic!("{s}: {e}"))
+    }
+
+    #[test]
+    fn test_unit_spellings() {
+        let cases = [
+            ("12 V", Dimension::Voltage, 12.0),
+            ("3.3v", Dimension::Voltage, 3.3),
+            ("100 mA", Dimension::Current, 0.1),
+            ("250uA", Dimension::Current, 250e-6),
+            ("250 µA", Dimension::Current, 250e-6),
+            ("2 amps", Dimension::Current, 2.0),
+            ("36W", Dimension::Power, 36.0),
+            ("1.5 kW", Dimension::Power, 1500.0),
+            ("2.4 GHz", Dimension::Frequency, 2.4e9),
+            ("433 MHz", Dimension::Frequency, 433e6),
+            ("433 mhz", Dimension::Frequency, 433e6),
+            ("32.768 kHz", Dimension::Frequency, 32768.0),
+            ("1000 Mbps", Dimension::DataRate, 1e9),
+            ("100 MBit/s", Dimension::DataRate, 1e8),
+            ("100 Mbit/s", Dimension::DataRate, 1e8),
+            ("100 mbps", Dimension::DataRate, 1e8),
+            ("10 Mb/s", Dimension::DataRate, 1e7),
+            ("500 kbps", Dimension::DataRate, 5e5),
+            ("500 Kbps", Dimension::DataRate, 5e5),
+            ("1 Gbps", Dimension::DataRate, 1e9),
+            ("12.5 MBps", Dimension::DataRate, 1e8),
+            ("1 MB/s", Dimension::DataRate, 8e6),
+            ("115200 baud", Dimension::SymbolRate, 115200.0),
+            ("1 MBd", Dimension::SymbolRate, 1e6),
+            ("55.5 Wh", Dimension::Energy, 55.5 * 3600.0),
+            ("1 kWh", Dimension::Energy, 3.6e6),
+            ("3600 J", Dimension::Energy, 3600.0),
+            ("5000 mAh", Dimension::Charge, 18000.0),
+            ("2.2 Ah", Dimension::Charge, 2.2 * 3600.0),
+            ("3.5 dBi", Dimension::Gain, 3.5),
+            ("42", Dimension::Dimensionless, 42.0),
+            ("1e3 Hz", Dimension::Frequency, 1000.0),
+            ("-5 V", Dimension::Voltage, -5.0),
+        ];
+        for (input, dimension, si) in cases {
+            let quantity = q(input);
+            assert_eq!(quantity.dimension(), dimension, "{input}");
+            assert!((quantity.si() - si).abs() <= 1e-9 * si.abs(), "{input}: {} != {si}", quantity.si());
+        }
+    }
+
+    #[test]
+    fn test_unknown_units_are_kept() {
+        let quantity = q("7 furlongs");
+        assert_eq!(quantity.dimension(), Dimension::Other);
+        assert_eq!(quantity.to_string(), "7 furlongs");
+        assert_eq!(quantity, q("7 furlongs"));
+        assert_ne!(quantity, q("7 leagues"));
+        // Gains can't be prefixed, so "kdB" is not a gain
+        assert_eq!(q("3 kdB").dimension(), Dimension::Other);
+        assert!("mA".parse::<Quantity>().is_err());
+        assert!("".parse::<Quantity>().is_err());
+    }
+
+    #[test]
+    fn test_equal_across_prefixes() {
+        assert_eq!(q("0.1 A"), q("100 mA"));
+        assert_eq!(q("1000 Mbps"), q("1 Gbps"));
+        assert_eq!(q("100 MBit/s"), q("100 Mbps"));
+        assert_eq!(q("1 MBps"), q("8 Mbps"));
+        assert_eq!(q("5 GHz"), q("5000 MHz"));
+        assert_eq!(q("3.6 kJ"), q("1 Wh"));
+        assert!(q("500 mA") < q("1 A"));
+        assert!(q("2.4 GHz") > q("900 MHz"));
+        // Different dimensions don't compare
+        assert_ne!(q("1 A"), q("1 V"));
+        assert_eq!(q("1 A").partial_cmp(&q("1 V")), None);
+        assert_ne!(q("3 dB"), q("3 dBi"));
+    }
+
+    #[test]
+    fn test_display_picks_prefix() {
+        let cases = [
+            ("0.1 A", "100 mA"),
+            ("100 mA", "100 mA"),
+            ("0.00025 A", "250 µA"),
+            ("12 V", "12 V"),
+            ("3300 mV", "3.3 V"),
+            ("1000 Mbps", "1 Gbps"),
+            ("100 MBit/s", "100 Mbps"),
+            ("500000 bps", "500 kbps"),
+            ("1 MBps", "8 Mbps"),
+            ("115200 baud", "115200 baud"),
+            ("2400 MHz", "2.4 GHz"),
+            ("55.5 Wh", "55.5 Wh"),
+            ("3600 J", "1 Wh"),
+            ("5000 mAh", "5 Ah"),
+            ("500 mAh", "500 mAh"),
+            ("3.5 dBi", "3.5 dBi"),
+            ("0 V", "0 V"),
+            ("1.23456 V", "1.235 V"),
+            ("42", "42"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(q(input).to_string(), expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_format_parse_round_trip() {
+        for input in ["100 mA", "2.4 GHz", "1 Gbps", "500 kbps", "55.5 Wh", "250 µA", "1.5 kW", "3.5 dBi", "115200 baud", "5 Ah"] {
+            let quantity = q(input);
+            let shown = quantity.to_string();
+            assert_eq!(shown, input);
+            assert_eq!(q(&shown), quantity, "{input}");
+        }
+        // Odd spellings come back in canonical form and compare equal
+        for input in ["100 MBit/s", "0.1A", "5000mAh", "2400 mhz", "12.5 MBps"] {
+            let quantity = q(input);
+            assert_eq!(q(&quantity.to_string()), quantity, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_default_unit_and_written_form() {
+        let quantity = Quantity::from_parts("1000", None, "Mbps").unwrap();
+        assert_eq!(quantity.unit, None);
+        assert_eq!(quantity.to_string(), "1 Gbps");
+        // Written form is kept for re-export
+        let raw = ValueWithUnit::from(quantity);
+        assert_eq!((raw.unit, raw.value.as_str()), (None, "1000"));
+
+        let inline = Quantity::from_parts("100 mA", None, "A").unwrap();
+        assert_eq!(inline.unit.as_deref(), Some("mA"));
+        assert_eq!(inline.value, 100.0);
+    }
+
+    #[test]
+    fn test_rated_display() {
+        let rated = |unit: Option<&str>, min: Option<f64>, max: Option<f64>, value: Option<&str>| {
+            RawRated { unit: unit.map(str::to_string), min, max, value: value.map(str::to_string) }
+                .resolve("V")
+                .unwrap()
+                .to_display_string()
+        };
+        assert_eq!(rated(Some("V"), Some(7.0), Some(28.0), Some("12")), "12 V (7-28 V)");
+        assert_eq!(rated(None, Some(7.0), Some(28.0), None), "7-28 V");
+        assert_eq!(rated(Some("V"), None, Some(28.0), Some("12")), "12 V (max 28 V)");
+        assert_eq!(rated(Some("mV"), Some(500.0), Some(5000.0), None), "500 mV-5 V");
+        assert_eq!(rated(Some("A"), None, Some(3.0), None), "3 A max");
+        assert_eq!(rated(Some("mA"), None, None, Some("100")), "100 mA");
+        assert_eq!(rated(None, None, None, None), "");
+    }
+}
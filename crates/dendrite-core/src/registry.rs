@@ -0,0 +1,220 @@
+//! Device registry fed by discovery events
+//!
+//! Discovery publishes what it finds as [`DiscoveryEvent`]s. A
+//! [`RegistryHandle`] applies them in order and answers queries about the
+//! devices currently known, so tools embedding discovery don't have to keep
+//! their own bookkeeping. Handles are cheap to clone and share one registry.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::device::{Device, DeviceId, DeviceStatus};
+use crate::topology::Topology;
+
+/// Discovery event for real-time updates
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum DiscoveryEvent {
+    /// New device discovered
+    DeviceDiscovered(Device),
+    /// Device went offline
+    DeviceOffline(DeviceId),
+    /// Device information updated
+    DeviceUpdated(Device),
+    /// Device removed from registry (forgotten)
+    DeviceRemoved(DeviceId),
+    /// Device archived (hidden from lists until seen again)
+    DeviceArchived(DeviceId),
+    /// Archived device was seen again and restored to the registry
+    DeviceUnarchived(Device),
+    /// Scan started; `total_hosts` is the number of addresses in the IPv4
+    /// subnet (0 for IPv6, where hosts are only known once NDP answers)
+    ScanStarted { total_hosts: usize },
+    /// `done` of the `total` candidate hosts have been probed for MCUmgr
+    ScanProgress { done: usize, total: usize },
+    /// Scan completed with `found` devices, `total` tracked
+    ScanCompleted { found: usize, total: usize },
+    /// An LLDP neighbor's advertised TTL ran out without a refresh
+    ///
+    /// `port` is the switch port parsed from the neighbor's port ID, when numeric.
+    /// A device matching the chassis ID is also reported with `DeviceOffline`.
+    NeighborExpired { port: Option<u8>, chassis_id: String },
+    /// An LLDP neighbor appeared on `interface`, or moved to another port or
+    /// system name
+    LldpNeighborChanged { interface: String, chassis_id: String, port: Option<u8> },
+    /// The fragment database was reloaded
+    ///
+    /// `devices` were matched again and sent as `DeviceUpdated` first;
+    /// `failed` describes fragments that didn't load.
+    FragmentsReloaded { devices: Vec<DeviceId>, failed: Vec<String> },
+    /// A heartbeat check of the device went unanswered, `failures` times in
+    /// a row; the next one is in `retry_in_ms`
+    HeartbeatBackoff { id: DeviceId, failures: u32, retry_in_ms: u64 },
+}
+
+#[derive(Debug, Default)]
+struct Registry {
+    /// Known devices by id
+    devices: BTreeMap<String, Device>,
+    scanning: bool,
+}
+
+/// Shared handle to a device registry
+#[derive(Debug, Clone, Default)]
+pub struct RegistryHandle {
+    inner: Arc<RwLock<Registry>>,
+}
+
+impl RegistryHandle {
+    /// An empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A registry holding `devices`
+    pub fn from_devices(devices: impl IntoIterator<Item = Device>) -> Self {
+        let handle = Self::new();
+        handle.replace(devices);
+        handle
+    }
+
+    /// Apply one event
+    ///
+    /// Discovered, updated and restored devices are stored as given;
+    /// archived and removed ones are dropped. Going offline only changes the
+    /// status of a device already known.
+    pub fn apply(&self, event: &DiscoveryEvent) {
+        let mut registry = self.write();
+        match event {
+            DiscoveryEvent::DeviceDiscovered(device)
+            | DiscoveryEvent::DeviceUpdated(device)
+            | DiscoveryEvent::DeviceUnarchived(device) => {
+                registry.devices.insert(device.id.0.clone(), device.clone());
+            }
+            DiscoveryEvent::DeviceOffline(id) => {
+                if let Some(device) = registry.devices.get_mut(id.as_str()) {
+                    device.status = DeviceStatus::Offline;
+                }
+            }
+            DiscoveryEvent::DeviceRemoved(id) | DiscoveryEvent::DeviceArchived(id) => {
+                registry.devices.remove(id.as_str());
+            }
+            DiscoveryEvent::ScanStarted { .. } => registry.scanning = true,
+            DiscoveryEvent::ScanProgress { .. } => {}
+            DiscoveryEvent::ScanCompleted { .. } => registry.scanning = false,
+            // The matching device's status comes in its own DeviceOffline
+            DiscoveryEvent::NeighborExpired { .. } => {}
+            DiscoveryEvent::LldpNeighborChanged { .. } => {}
+            DiscoveryEvent::FragmentsReloaded { .. } => {}
+            DiscoveryEvent::HeartbeatBackoff { .. } => {}
+        }
+    }
+
+    /// Replace every device at once (e.g. from a snapshot)
+    pub fn replace(&self, devices: impl IntoIterator<Item = Device>) {
+        self.write().devices = devices.into_iter().map(|d| (d.id.0.clone(), d)).collect();
+    }
+
+    /// Every known device, by id
+    pub fn devices(&self) -> Vec<Device> {
+        self.read().devices.values().cloned().collect()
+    }
+
+    /// Known devices that are online
+    pub fn online(&self) -> Vec<Device> {
+        self.read()
+            .devices
+            .values()
+            .filter(|d| d.status == DeviceStatus::Online)
+            .cloned()
+            .collect()
+    }
+
+    pub fn get(&self, id: &DeviceId) -> Option<Device> {
+        self.read().devices.get(id.as_str()).cloned()
+    }
+
+    pub fn len(&self) -> usize {
+        self.read().devices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.read().devices.is_empty()
+    }
+
+    /// Whether a scan has started and not yet completed
+    pub fn is_scanning(&self) -> bool {
+        self.read().scanning
+    }
+
+    /// Topology of the known devices, rooted at `parent_id` if given
+    pub fn topology(&self, parent_id: Option<&DeviceId>) -> Topology {
+        Topology::from_devices(&self.devices(), parent_id.map(std::slice::from_ref).unwrap_or_default())
+    }
+
+    // A panic while holding the lock can't leave the map half-updated, so a
+    // poisoned lock is still safe to use
+    fn read(&self) -> RwLockReadGuard<'_, Registry> {
+        self.inner.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, Registry> {
+        self.inner.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    fn device(hwid: &str, name: &str) -> Device {
+        let mut device = Device::new(
+            DeviceId::from_hwid(hwid),
+            name.to_string(),
+            IpAddr::V4(Ipv4Addr::new(192, 168, 186, 10)),
+            1337,
+        );
+        device.status = DeviceStatus::Online;
+        device
+    }
+
+    #[test]
+    fn test_apply_events() {
+        let registry = RegistryHandle::new();
+        let shared = registry.clone();
+
+        registry.apply(&DiscoveryEvent::ScanStarted { total_hosts: 254 });
+        registry.apply(&DiscoveryEvent::DeviceDiscovered(device("0x02", "gps")));
+        registry.apply(&DiscoveryEvent::DeviceDiscovered(device("0x01", "flow")));
+        registry.apply(&DiscoveryEvent::ScanProgress { done: 100, total: 254 });
+        assert!(shared.is_scanning());
+        registry.apply(&DiscoveryEvent::ScanCompleted { found: 2, total: 2 });
+        assert!(!shared.is_scanning());
+
+        let names: Vec<String> = shared.devices().into_iter().map(|d| d.name).collect();
+        assert_eq!(names, vec!["flow", "gps"]);
+
+        registry.apply(&DiscoveryEvent::DeviceUpdated(device("0x01", "optical-flow")));
+        registry.apply(&DiscoveryEvent::DeviceOffline(DeviceId::from_hwid("0x02")));
+        assert_eq!(shared.get(&DeviceId::from_hwid("0x01")).unwrap().name, "optical-flow");
+        assert_eq!(shared.online().len(), 1);
+
+        // Offline for an unknown device doesn't add it
+        registry.apply(&DiscoveryEvent::DeviceOffline(DeviceId::from_hwid("0x09")));
+        registry.apply(&DiscoveryEvent::DeviceArchived(DeviceId::from_hwid("0x02")));
+        assert_eq!(shared.len(), 1);
+
+        registry.apply(&DiscoveryEvent::DeviceUnarchived(device("0x02", "gps")));
+        registry.apply(&DiscoveryEvent::DeviceRemoved(DeviceId::from_hwid("0x01")));
+        let ids: Vec<String> = shared.devices().into_iter().map(|d| d.id.0).collect();
+        assert_eq!(ids, vec![DeviceId::from_hwid("0x02").0]);
+    }
+
+    #[test]
+    fn test_topology_of_known_devices() {
+        let registry = RegistryHandle::from_devices([device("0x01", "flow"), device("0x02", "gps")]);
+        let topology = registry.topology(None);
+        assert_eq!(topology.nodes().count(), 2);
+    }
+}
@@ -0,0 +1,181 @@
+//! Runtime fields of an HCDF document
+//!
+//! `<discovered>` blocks record where a device was last seen (IP, switch
+//! port, timestamp). They change on every scan, so a document that carries
+//! them differs each time it is written even when nothing about the vehicle
+//! changed. The canonical document leaves them out: `from_xml_split` parses
+//! them into a `DiscoveredState` beside the document, `to_xml_with` writes
+//! with or without them, and `merge_discovered` puts them back for views
+//! that want the live state.
+//!
+//! MCUs are keyed by hwid (or name when they have none) and comps by name,
+//! as in `ProvenanceRegistry`.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::hcdf::{Comp, Discovered, Hcdf, HcdfError, Mcu};
+
+/// Whether serialization writes `<discovered>` blocks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RuntimeFields {
+    /// Write them (API responses, scan reports)
+    #[default]
+    Include,
+    /// Leave them out (the persisted, canonical document)
+    Omit,
+}
+
+/// `<discovered>` blocks taken out of a document
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DiscoveredState {
+    mcu: BTreeMap<String, Discovered>,
+    comp: BTreeMap<String, Discovered>,
+}
+
+impl DiscoveredState {
+    pub fn mcu(&self, mcu: &Mcu) -> Option<&Discovered> {
+        self.mcu.get(mcu.hwid.as_deref().unwrap_or(&mcu.name))
+    }
+
+    pub fn comp(&self, comp: &Comp) -> Option<&Discovered> {
+        self.comp.get(&comp.name)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mcu.is_empty() && self.comp.is_empty()
+    }
+}
+
+impl Hcdf {
+    /// Parse into the canonical document and its runtime fields
+    pub fn from_xml_split(xml: &str) -> Result<(Self, DiscoveredState), HcdfError> {
+        let mut hcdf = Self::from_xml(xml)?;
+        let discovered = hcdf.take_discovered();
+        Ok((hcdf, discovered))
+    }
+
+    /// Move every `<discovered>` block out of the document
+    pub fn take_discovered(&mut self) -> DiscoveredState {
+        let mut state = DiscoveredState::default();
+        for mcu in &mut self.mcu {
+            if let Some(discovered) = mcu.discovered.take() {
+                let key = mcu.hwid.clone().unwrap_or_else(|| mcu.name.clone());
+                state.mcu.insert(key, discovered);
+            }
+        }
+        for comp in &mut self.comp {
+            if let Some(discovered) = comp.discovered.take() {
+                state.comp.insert(comp.name.clone(), discovered);
+            }
+        }
+        state
+    }
+
+    /// Put runtime fields back on the elements they belong to; elements
+    /// without an entry keep what they have
+    pub fn merge_discovered(&mut self, state: &DiscoveredState) {
+        for mcu in &mut self.mcu {
+            if let Some(discovered) = state.mcu(mcu) {
+                mcu.discovered = Some(discovered.clone());
+            }
+        }
+        for comp in &mut self.comp {
+            if let Some(discovered) = state.comp(comp) {
+                comp.discovered = Some(discovered.clone());
+            }
+        }
+    }
+
+    /// Serialize like `to_xml`, optionally leaving out runtime fields
+    pub fn to_xml_with(&self, runtime: RuntimeFields) -> Result<String, HcdfError> {
+        match runtime {
+            RuntimeFields::Include => self.to_xml(),
+            RuntimeFields::Omit => {
+                let mut canonical = self.clone();
+                canonical.take_discovered();
+                canonical.to_xml()
+            }
+        }
+    }
+
+    /// Write to file, optionally leaving out runtime fields
+    pub fn to_file_with(&self, path: &Path, runtime: RuntimeFields) -> Result<(), HcdfError> {
+        std::fs::write(path, self.to_xml_with(runtime)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::{Device, DeviceId};
+    use chrono::{Duration, Utc};
+    use std::net::{IpAddr, Ipv4Addr};
+
+    fn fleet() -> Vec<Device> {
+        (1..=3u8)
+            .map(|i| {
+                let mut device = Device::new(
+                    DeviceId::from_hwid(&format!("0x{:02x}", i)),
+                    format!("node-{}", i),
+                    IpAddr::V4(Ipv4Addr::new(192, 168, 186, i)),
+                    1337,
+                );
+                device.discovery.switch_port = Some(i);
+                device.info.board = Some("mr_mcxn_t1".to_string());
+                device.pose = Some([0.1 * i as f64, 0.0, 0.0, 0.0, 0.0, 0.0]);
+                device
+            })
+            .collect()
+    }
+
+    /// One scan: every device seen again, later than last time
+    fn scan(hcdf: &mut Hcdf, fleet: &mut [Device], minutes: i64) {
+        for device in fleet.iter_mut() {
+            device.discovery.last_seen = Utc::now() + Duration::minutes(minutes);
+            hcdf.upsert_device(device, Some("parent"));
+        }
+    }
+
+    #[test]
+    fn test_canonical_document_is_stable_across_scans() {
+        let mut fleet = fleet();
+        let mut hcdf = Hcdf::new();
+        scan(&mut hcdf, &mut fleet, 0);
+        let first = hcdf.to_xml_with(RuntimeFields::Omit).unwrap();
+        let live = hcdf.to_xml_with(RuntimeFields::Include).unwrap();
+        assert!(!first.contains("<discovered>"));
+        assert!(live.contains("<discovered>"));
+
+        for minutes in 1..5 {
+            scan(&mut hcdf, &mut fleet, minutes);
+            assert_eq!(hcdf.to_xml_with(RuntimeFields::Omit).unwrap(), first);
+        }
+        // The live view does follow the scans
+        assert_ne!(hcdf.to_xml_with(RuntimeFields::Include).unwrap(), live);
+
+        // Reading the canonical file back and writing it again changes nothing
+        let (reread, discovered) = Hcdf::from_xml_split(&first).unwrap();
+        assert!(discovered.is_empty());
+        assert_eq!(reread.to_xml_with(RuntimeFields::Omit).unwrap(), first);
+    }
+
+    #[test]
+    fn test_split_and_merge_round_trip() {
+        let mut fleet = fleet();
+        let mut hcdf = Hcdf::new();
+        scan(&mut hcdf, &mut fleet, 0);
+        let live = hcdf.to_xml().unwrap();
+
+        let (mut canonical, discovered) = Hcdf::from_xml_split(&live).unwrap();
+        assert!(canonical.mcu.iter().all(|m| m.discovered.is_none()));
+        let node = &canonical.mcu[1];
+        assert_eq!(discovered.mcu(node).unwrap().ip, "192.168.186.2");
+        assert_eq!(discovered.mcu(node).unwrap().port, Some(2));
+        assert_eq!(canonical.to_xml().unwrap(), hcdf.to_xml_with(RuntimeFields::Omit).unwrap());
+
+        canonical.merge_discovered(&discovered);
+        assert_eq!(canonical.to_xml().unwrap(), live);
+    }
+}
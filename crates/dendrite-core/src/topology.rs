@@ -1,10 +1,14 @@
 //! Topology graph for parent/child device relationships
+//!
+//! Ethernet devices hang off a port of their parent (point-to-point links).
+//! Devices on a CAN bus all share one wire instead, so they are grouped into
+//! a [`BusSegment`] attached to the host the bus interface is on.
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 use crate::device::{Device, DeviceId};
-use crate::hcdf::Hcdf;
+use crate::hcdf::{Discovered, Hcdf};
 
 /// A node in the topology graph
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,6 +29,19 @@ pub struct TopologyNode {
     pub position: Option<[f64; 3]>,
 }
 
+/// A bus shared by several devices (an edge every member is on)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BusSegment {
+    /// Bus name (the interface on the host, e.g. "can0")
+    pub name: String,
+    /// Bus kind, e.g. "can"
+    pub kind: String,
+    /// Device the bus interface is on
+    pub host: Option<DeviceId>,
+    /// Devices on the bus, by node id
+    pub members: Vec<DeviceId>,
+}
+
 /// Device topology representing the parent/child network structure
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Topology {
@@ -32,6 +49,9 @@ pub struct Topology {
     nodes: HashMap<String, TopologyNode>,
     /// Root/parent device ID
     root: Option<DeviceId>,
+    /// Shared buses, by name
+    #[serde(default)]
+    buses: Vec<BusSegment>,
 }
 
 impl Topology {
@@ -40,56 +60,111 @@ impl Topology {
         Self {
             nodes: HashMap::new(),
             root: None,
+            buses: Vec::new(),
         }
     }
 
-    /// Build topology from HCDF document
+    /// Build topology from an HCDF document
+    ///
+    /// Every `<mcu>` and `<comp>` becomes a node, keyed by hwid or else by
+    /// name. A wired link from `<parent>/<iface>:<port>` to `<child>/<iface>`
+    /// makes `child` a child of `parent` on that port. MCUs and discovered
+    /// comps without such a link hang off the `role="parent"` comp, on their
+    /// discovered switch port; CAN devices share their bus instead. Anything
+    /// left without a parent is a root of its own (see [`Topology::roots`]).
+    ///
+    /// A device gets at most one parent: a second link to it is ignored, and
+    /// a link that would make a device its own ancestor is dropped. Both are
+    /// logged.
     pub fn from_hcdf(hcdf: &Hcdf) -> Self {
         let mut topology = Self::new();
 
-        // Find and add parent device
-        if let Some(parent) = hcdf.find_parent() {
-            let parent_id = parent
-                .hwid
-                .clone()
-                .map(DeviceId)
-                .unwrap_or_else(|| DeviceId(parent.name.clone()));
-
-            topology.add_node(TopologyNode {
-                id: parent_id.clone(),
-                name: parent.name.clone(),
-                board: parent.board.clone(),
-                is_parent: true,
-                port: None,
-                children: Vec::new(),
-                position: Some([0.0, 0.0, 0.0]),
-            });
-            topology.root = Some(parent_id);
+        struct Entry<'a> {
+            id: DeviceId,
+            name: &'a str,
+            board: Option<String>,
+            discovered: Option<&'a Discovered>,
+            is_mcu: bool,
+        }
+        let id_of = |hwid: &Option<String>, name: &str| DeviceId(hwid.clone().unwrap_or_else(|| name.to_string()));
+        let entries: Vec<Entry> = hcdf
+            .comp
+            .iter()
+            .map(|c| Entry {
+                id: id_of(&c.hwid, &c.name),
+                name: &c.name,
+                board: c.board.clone(),
+                discovered: c.discovered.as_ref(),
+                is_mcu: false,
+            })
+            .chain(hcdf.mcu.iter().map(|m| Entry {
+                id: id_of(&m.hwid, &m.name),
+                name: &m.name,
+                board: m.board.clone(),
+                discovered: m.discovered.as_ref(),
+                is_mcu: true,
+            }))
+            .collect();
+        let by_name: HashMap<&str, &DeviceId> = entries.iter().map(|e| (e.name, &e.id)).collect();
+        topology.root = hcdf.find_parent().map(|p| id_of(&p.hwid, &p.name));
+
+        // Child id -> (parent, port on the parent)
+        let mut parent_of: HashMap<DeviceId, (DeviceId, Option<u8>)> = HashMap::new();
+        for link in &hcdf.link {
+            let Some(wired) = link.digital.as_ref().and_then(|d| d.wired.as_ref()) else {
+                continue;
+            };
+            let (Some(from), Some(to)) = (wired.from.as_deref(), wired.to.as_deref()) else {
+                continue;
+            };
+            let ((parent, port), (child, _)) = (link_end(from), link_end(to));
+            let (Some(&parent), Some(&child)) = (by_name.get(parent), by_name.get(child)) else {
+                continue;
+            };
+            if parent == child {
+                continue;
+            }
+            if let Some((existing, _)) = parent_of.get(child) {
+                if existing != parent {
+                    tracing::warn!(link = %link.name, child = %child, "ignoring second parent {} (already under {})", parent, existing);
+                }
+                continue;
+            }
+            parent_of.insert(child.clone(), (parent.clone(), port));
         }
 
-        // Add MCU devices
-        for mcu in &hcdf.mcu {
-            let device_id = mcu
-                .hwid
-                .clone()
-                .map(DeviceId)
-                .unwrap_or_else(|| DeviceId(mcu.name.clone()));
-
-            let port = mcu.discovered.as_ref().and_then(|d| d.port);
+        for entry in &entries {
+            let discovered = entry.discovered;
+            if let Some(can) = discovered.and_then(|d| d.can.as_ref()) {
+                // CAN nodes share their bus rather than linking to the parent
+                topology.add_to_bus(&can.bus, "can", topology.root.clone(), &entry.id);
+                continue;
+            }
+            let Some(root) = topology.root.clone() else {
+                continue;
+            };
+            if entry.id != root && !parent_of.contains_key(&entry.id) && (entry.is_mcu || discovered.is_some()) {
+                parent_of.insert(entry.id.clone(), (root, discovered.and_then(|d| d.port)));
+            }
+        }
+        break_cycles(&mut parent_of);
 
+        for entry in &entries {
+            let is_root = topology.root.as_ref() == Some(&entry.id);
             topology.add_node(TopologyNode {
-                id: device_id.clone(),
-                name: mcu.name.clone(),
-                board: mcu.board.clone(),
-                is_parent: false,
-                port,
+                id: entry.id.clone(),
+                name: entry.name.to_string(),
+                board: entry.board.clone(),
+                is_parent: is_root || parent_of.values().any(|(parent, _)| parent == &entry.id),
+                port: parent_of.get(&entry.id).and_then(|(_, port)| *port),
                 children: Vec::new(),
-                position: None,
+                position: is_root.then_some([0.0, 0.0, 0.0]),
             });
-
-            // Link to parent
-            if let Some(root_id) = topology.root.clone() {
-                topology.add_child(&root_id, &device_id);
+        }
+        // In document order, so children keep the order they were written in
+        for entry in &entries {
+            if let Some((parent, _)) = parent_of.get(&entry.id) {
+                topology.add_child(parent, &entry.id);
             }
         }
 
@@ -98,12 +173,20 @@ impl Topology {
     }
 
     /// Build topology from device registry
-    pub fn from_devices(devices: &[Device], parent_id: Option<&DeviceId>) -> Self {
+    ///
+    /// Each of `parent_ids` in the registry is a root. Devices hang off the
+    /// parent in their `parent_id`, or else the first parent, which is the
+    /// one [`Topology::root`] returns.
+    pub fn from_devices(devices: &[Device], parent_ids: &[DeviceId]) -> Self {
         let mut topology = Self::new();
 
-        // Add parent if specified
-        if let Some(pid) = parent_id {
+        // Add parents; the first one found sits at the origin
+        for pid in parent_ids {
             if let Some(parent) = devices.iter().find(|d| &d.id == pid) {
+                let position = match topology.root {
+                    None => Some([0.0, 0.0, 0.0]),
+                    Some(_) => parent.pose.map(|p| [p[0], p[1], p[2]]),
+                };
                 topology.add_node(TopologyNode {
                     id: parent.id.clone(),
                     name: parent.name.clone(),
@@ -111,16 +194,16 @@ impl Topology {
                     is_parent: true,
                     port: None,
                     children: Vec::new(),
-                    position: Some([0.0, 0.0, 0.0]),
+                    position,
                 });
-                topology.root = Some(parent.id.clone());
+                topology.root.get_or_insert_with(|| parent.id.clone());
             }
         }
 
         // Add all other devices
         for device in devices {
-            if Some(&device.id) == parent_id {
-                continue; // Skip parent, already added
+            if parent_ids.contains(&device.id) {
+                continue; // Skip parents, already added
             }
 
             topology.add_node(TopologyNode {
@@ -133,6 +216,13 @@ impl Topology {
                 position: device.pose.map(|p| [p[0], p[1], p[2]]),
             });
 
+            // CAN nodes share their bus rather than linking to the parent
+            if let Some(can) = &device.discovery.can {
+                let host = device.parent_id.clone().or_else(|| topology.root.clone());
+                topology.add_to_bus(&can.bus, "can", host, &device.id);
+                continue;
+            }
+
             // Link to parent
             if let Some(pid) = device.parent_id.clone() {
                 topology.add_child(&pid, &device.id);
@@ -141,10 +231,40 @@ impl Topology {
             }
         }
 
+        let node_id = |id: &DeviceId| {
+            devices
+                .iter()
+                .find(|d| &d.id == id)
+                .and_then(|d| d.discovery.can.as_ref())
+                .map(|c| c.node_id)
+        };
+        for bus in &mut topology.buses {
+            bus.members.sort_by_key(node_id);
+        }
+        topology.buses.sort_by(|a, b| a.name.cmp(&b.name));
         topology.auto_arrange();
         topology
     }
 
+    /// Add a device to a shared bus, creating the bus on first use
+    pub fn add_to_bus(&mut self, name: &str, kind: &str, host: Option<DeviceId>, id: &DeviceId) {
+        let bus = match self.buses.iter().position(|b| b.name == name) {
+            Some(index) => &mut self.buses[index],
+            None => {
+                self.buses.push(BusSegment {
+                    name: name.to_string(),
+                    kind: kind.to_string(),
+                    host,
+                    members: Vec::new(),
+                });
+                self.buses.last_mut().unwrap()
+            }
+        };
+        if !bus.members.contains(id) {
+            bus.members.push(id.clone());
+        }
+    }
+
     /// Add a node to the topology
     pub fn add_node(&mut self, node: TopologyNode) {
         self.nodes.insert(node.id.0.clone(), node);
@@ -166,6 +286,9 @@ impl Topology {
         for node in self.nodes.values_mut() {
             node.children.retain(|c| c != id);
         }
+        for bus in &mut self.buses {
+            bus.members.retain(|m| m != id);
+        }
     }
 
     /// Get a node by ID
@@ -183,6 +306,30 @@ impl Topology {
         self.root.as_ref().and_then(|id| self.nodes.get(&id.0))
     }
 
+    /// Nodes nobody lists as a child, by name
+    ///
+    /// The root is one of them; devices on separate parents, or with no
+    /// parent at all, add more.
+    pub fn roots(&self) -> Vec<&TopologyNode> {
+        let mut roots: Vec<&TopologyNode> = self
+            .nodes
+            .values()
+            .filter(|n| !self.nodes.values().any(|p| p.children.contains(&n.id)))
+            .collect();
+        roots.sort_by(|a, b| a.name.cmp(&b.name));
+        roots
+    }
+
+    /// Get all shared buses
+    pub fn buses(&self) -> &[BusSegment] {
+        &self.buses
+    }
+
+    /// The bus a device is on, if any
+    pub fn bus_of(&self, id: &DeviceId) -> Option<&BusSegment> {
+        self.buses.iter().find(|b| b.members.contains(id))
+    }
+
     /// Get children of a node
     pub fn children(&self, id: &DeviceId) -> Vec<&TopologyNode> {
         self.nodes
@@ -232,6 +379,39 @@ impl Topology {
         TopologyGraph {
             nodes: self.nodes.values().cloned().collect(),
             root: self.root.clone(),
+            buses: self.buses.clone(),
+        }
+    }
+}
+
+/// Device name and port of one end of a wired link (`navq95/eth0:2`)
+fn link_end(end: &str) -> (&str, Option<u8>) {
+    let (device, interface) = end.split_once('/').unwrap_or((end, ""));
+    let port = interface.rsplit_once(':').and_then(|(_, port)| port.parse().ok());
+    (device, port)
+}
+
+/// Drop parent links that close a loop, logging each
+///
+/// Children are visited in id order so the same document always loses the
+/// same link.
+fn break_cycles(parent_of: &mut HashMap<DeviceId, (DeviceId, Option<u8>)>) {
+    let mut children: Vec<DeviceId> = parent_of.keys().cloned().collect();
+    children.sort_by(|a, b| a.0.cmp(&b.0));
+    for child in children {
+        let mut current = parent_of.get(&child).map(|(parent, _)| parent.clone());
+        // A chain longer than the number of links must repeat
+        for _ in 0..=parent_of.len() {
+            let Some(ancestor) = current else {
+                break;
+            };
+            if ancestor == child {
+                if let Some((parent, _)) = parent_of.remove(&child) {
+                    tracing::warn!(device = %child, "dropping link to parent {}: it closes a cycle", parent);
+                }
+                break;
+            }
+            current = parent_of.get(&ancestor).map(|(parent, _)| parent.clone());
         }
     }
 }
@@ -241,64 +421,148 @@ impl Topology {
 pub struct TopologyGraph {
     pub nodes: Vec<TopologyNode>,
     pub root: Option<DeviceId>,
+    #[serde(default)]
+    pub buses: Vec<BusSegment>,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::device::{Device, DiscoveryInfo, DiscoveryMethod};
+    use crate::device::{Device, DiscoveryMethod};
+    use dendrite_test_support::synthetic_registry;
     use std::net::{IpAddr, Ipv4Addr};
 
     #[test]
     fn test_topology_from_devices() {
-        let parent_id = DeviceId::from_hwid("parent-001");
-        let child_id = DeviceId::from_hwid("child-001");
-
-        let now = chrono::Utc::now();
-        let parent = Device {
-            id: parent_id.clone(),
-            name: "navq95".to_string(),
-            status: crate::device::DeviceStatus::Online,
-            discovery: DiscoveryInfo {
-                ip: IpAddr::V4(Ipv4Addr::new(192, 168, 186, 1)),
-                port: 1337,
-                switch_port: None,
-                mac: None,
-                first_seen: now,
-                last_seen: now,
-                discovery_method: DiscoveryMethod::Manual,
-            },
-            info: Default::default(),
-            firmware: Default::default(),
-            firmware_status: Default::default(),
-            firmware_manifest_uri: None,
-            parent_id: None,
-            model_path: None,
-            pose: None,
-            visuals: Vec::new(),
-            frames: Vec::new(),
-            ports: Vec::new(),
-            sensors: Vec::new(),
+        // Registry device 5 is a child board mounted on device 4
+        let devices: Vec<Device> = serde_json::from_str(&synthetic_registry(6)).unwrap();
+        let (parent, child) = (&devices[4], &devices[5]);
+        assert_eq!(child.parent_id.as_ref(), Some(&parent.id));
+
+        let topology = Topology::from_devices(&devices[4..], std::slice::from_ref(&parent.id));
+
+        assert!(topology.root().is_some());
+        assert_eq!(topology.root().unwrap().name, parent.name);
+
+        let children = topology.children(&parent.id);
+        assert_eq!(children.len(), 1);
+        assert_eq!(children[0].name, child.name);
+        assert_eq!(children[0].port, Some(6));
+    }
+
+    #[test]
+    fn test_topology_with_two_parents() {
+        let device = |name: &str, last_octet: u8, parent: Option<&str>| {
+            let mut device = Device::new(DeviceId::from_hwid(name), name.to_string(), IpAddr::V4(Ipv4Addr::new(192, 168, 186, last_octet)), 1337);
+            device.parent_id = parent.map(DeviceId::from_hwid);
+            device
         };
+        let devices = vec![
+            device("switch-a", 1, None),
+            device("switch-b", 129, None),
+            device("flow", 10, Some("switch-a")),
+            device("gps", 11, Some("switch-a")),
+            device("radar", 130, Some("switch-b")),
+            device("stray", 20, None),
+        ];
+        let parents = [DeviceId::from_hwid("switch-a"), DeviceId::from_hwid("switch-b")];
+
+        let topology = Topology::from_devices(&devices, &parents);
+        let names = |nodes: Vec<&TopologyNode>| nodes.iter().map(|n| n.name.clone()).collect::<Vec<_>>();
+
+        assert_eq!(names(topology.roots()), vec!["switch-a", "switch-b"]);
+        assert_eq!(topology.root().unwrap().name, "switch-a");
+        // Devices without a parent go to the first one
+        assert_eq!(names(topology.children(&parents[0])), vec!["flow", "gps", "stray"]);
+        assert_eq!(names(topology.children(&parents[1])), vec!["radar"]);
+        assert!(topology.get_node(&parents[1]).unwrap().is_parent);
+    }
 
-        let mut child = Device::new(
-            child_id.clone(),
-            "spinali-001".to_string(),
-            IpAddr::V4(Ipv4Addr::new(192, 168, 186, 10)),
-            1337,
+    #[test]
+    fn test_topology_from_hcdf() {
+        let wired = |name: &str, from: &str, to: &str| {
+            format!(
+                r#"<link name="{}"><digital name="t1_eth"><wired name="100base-t1"><from>{}</from><to>{}</to></wired></digital></link>"#,
+                name, from, to
+            )
+        };
+        let xml = format!(
+            r#"<hcdf version="2.0">
+  <comp name="navq95" role="parent" hwid="0xaa"/>
+  <comp name="switch" hwid="0xbb"><discovered><ip>192.168.186.2</ip></discovered></comp>
+  <comp name="battery"/>
+  <comp name="companion" hwid="0xcc"/>
+  <mcu name="spinali" hwid="0x01"><discovered><ip>192.168.186.10</ip><port>3</port></discovered></mcu>
+  <mcu name="flow" hwid="0x02"/>
+  <mcu name="radar" hwid="0x03"/>
+  <mcu name="esc" hwid="0x04"><discovered><ip>0.0.0.0</ip><can bus="can0" node="7"/></discovered></mcu>
+  <mcu name="loop_a" hwid="0x05"/>
+  <mcu name="loop_b" hwid="0x06"/>
+  {}{}{}{}{}{}
+</hcdf>"#,
+            wired("parent_to_switch", "navq95/eth0:1", "switch/eth0"),
+            wired("switch_to_flow", "switch/eth0:4", "flow/eth0"),
+            wired("flow_again", "navq95/eth0:5", "flow/eth0"),
+            wired("companion_to_radar", "companion/eth0:2", "radar/eth0"),
+            wired("a_to_b", "loop_a/eth0:1", "loop_b/eth0"),
+            wired("b_to_a", "loop_b/eth0:1", "loop_a/eth0"),
         );
-        child.parent_id = Some(parent_id.clone());
-        child.discovery.switch_port = Some(2);
+        let topology = Topology::from_hcdf(&Hcdf::from_xml(&xml).unwrap());
+        let id = |hwid: &str| DeviceId(hwid.to_string());
+        let names = |nodes: Vec<&TopologyNode>| nodes.iter().map(|n| n.name.clone()).collect::<Vec<_>>();
 
-        let devices = vec![parent, child];
-        let topology = Topology::from_devices(&devices, Some(&parent_id));
-
-        assert!(topology.root().is_some());
+        // Explicit links, then the parent comp for the rest of the network
         assert_eq!(topology.root().unwrap().name, "navq95");
+        assert_eq!(names(topology.children(&id("0xaa"))), vec!["switch", "spinali"]);
+        assert_eq!(names(topology.children(&id("0xbb"))), vec!["flow"]);
+        assert_eq!(topology.get_node(&id("0x02")).unwrap().port, Some(4));
+        assert_eq!(topology.get_node(&id("0x01")).unwrap().port, Some(3));
+        assert!(topology.get_node(&id("0xbb")).unwrap().is_parent);
+        assert!(!topology.get_node(&id("0x01")).unwrap().is_parent);
+
+        // A second root with its own child, and a comp nothing connects to
+        assert_eq!(names(topology.children(&id("0xcc"))), vec!["radar"]);
+        assert!(topology.get_node(&id("0xcc")).unwrap().is_parent);
+        assert!(topology.get_node(&DeviceId("battery".to_string())).is_some());
+        let roots = names(topology.roots());
+        assert!(roots.contains(&"companion".to_string()) && roots.contains(&"battery".to_string()));
+
+        // The CAN node is on its bus, not under the parent
+        assert_eq!(topology.bus_of(&id("0x04")).unwrap().host, Some(id("0xaa")));
+
+        // loop_a -> loop_b -> loop_a loses one link; both stay in the graph
+        let looped = [id("0x05"), id("0x06")].map(|i| topology.get_node(&i).unwrap().children.len());
+        assert_eq!(looped.iter().sum::<usize>(), 1);
+        assert!(roots.contains(&"loop_a".to_string()) || roots.contains(&"loop_b".to_string()));
+    }
 
-        let children = topology.children(&parent_id);
-        assert_eq!(children.len(), 1);
-        assert_eq!(children[0].name, "spinali-001");
-        assert_eq!(children[0].port, Some(2));
+    #[test]
+    fn test_can_nodes_share_a_bus() {
+        use crate::device::CanAddress;
+
+        let parent_id = DeviceId::from_hwid("navq95");
+        let mut parent = Device::new(parent_id.clone(), "navq95".to_string(), IpAddr::V4(Ipv4Addr::new(192, 168, 186, 1)), 1337);
+        parent.discovery.discovery_method = DiscoveryMethod::Manual;
+        let mut eth = Device::new(DeviceId::from_hwid("spinali"), "spinali".to_string(), IpAddr::V4(Ipv4Addr::new(192, 168, 186, 10)), 1337);
+        eth.discovery.switch_port = Some(2);
+        let node = |bus: &str, node_id| Device::new_can(CanAddress { bus: bus.to_string(), node_id }, format!("{}/{}", bus, node_id));
+        let devices = vec![parent, eth, node("can0", 12), node("can0", 3), node("can1", 5)];
+
+        let topology = Topology::from_devices(&devices, std::slice::from_ref(&parent_id));
+
+        // Only the ethernet device is a point-to-point child
+        assert_eq!(topology.children(&parent_id).len(), 1);
+        let buses = topology.buses();
+        assert_eq!(buses.len(), 2);
+        assert_eq!(buses[0].name, "can0");
+        assert_eq!(buses[0].kind, "can");
+        assert_eq!(buses[0].host, Some(parent_id.clone()));
+        assert_eq!(buses[0].members, vec![DeviceId::from_hwid("can:can0:3"), DeviceId::from_hwid("can:can0:12")]);
+        assert_eq!(topology.bus_of(&DeviceId::from_hwid("can:can1:5")).unwrap().name, "can1");
+        assert_eq!(topology.to_graph().buses.len(), 2);
+
+        let mut topology = topology;
+        topology.remove_node(&DeviceId::from_hwid("can:can0:3"));
+        assert_eq!(topology.buses()[0].members, vec![DeviceId::from_hwid("can:can0:12")]);
     }
 }
@@ -0,0 +1,310 @@
+//! URDF export for simulation stacks
+//!
+//! Every MCU and comp becomes a link fixed to `base_link` at its `pose_cg`
+//! (the origin when it has none), with its visuals as meshes whose filename
+//! is the model `href` as written. Frames become links on fixed joints to
+//! their parent frame or the device, and sensors become links with a
+//! `<gazebo>` sensor extension for the sensor types Gazebo has. Port and
+//! antenna fallback visuals are exported as box, cylinder or sphere; other
+//! geometry, which URDF can't express, is left as a comment.
+//!
+//! Link names are the element names (frames and sensors prefixed with their
+//! device's link), with `_2`, `_3`... appended to repeats.
+
+use quick_xml::escape::escape;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+use crate::hcdf::{Frame, Geometry, Hcdf, HcdfError, ModelRef, Pose, Sensor, Visual, parse_pose_string};
+
+/// Root link everything is fixed to
+pub const URDF_BASE_LINK: &str = "base_link";
+
+/// Convert a document to URDF
+///
+/// Fails only if a device's frame hierarchy is broken (see
+/// [`Hcdf::validate_frames`]), since its frames can't be jointed.
+pub fn export_urdf(hcdf: &Hcdf) -> Result<String, HcdfError> {
+    hcdf.validate_frames()?;
+
+    let mut urdf = UrdfWriter::default();
+    urdf.links.insert(URDF_BASE_LINK.to_string());
+    urdf.line(1, &format!("<link name=\"{}\"/>", URDF_BASE_LINK));
+
+    for mcu in &hcdf.mcu {
+        let fallbacks = mcu.port.iter().map(|p| (p.name.as_str(), p.parse_pose(), p.get_geometry()));
+        urdf.device(&DeviceParts {
+            name: &mcu.name,
+            pose_cg: mcu.pose_cg.as_deref(),
+            model: mcu.model.as_ref(),
+            visuals: &mcu.visual,
+            frames: &mcu.frame,
+            fallbacks: fallbacks.collect(),
+            sensors: &[],
+        });
+    }
+    for comp in &hcdf.comp {
+        let ports = comp.port.iter().map(|p| (p.name.as_str(), p.parse_pose(), p.get_geometry()));
+        let antennas = comp.antenna.iter().map(|a| (a.name.as_str(), a.parse_pose(), a.get_geometry()));
+        urdf.device(&DeviceParts {
+            name: &comp.name,
+            pose_cg: comp.pose_cg.as_deref(),
+            model: comp.model.as_ref(),
+            visuals: &comp.visual,
+            frames: &comp.frame,
+            fallbacks: ports.chain(antennas).collect(),
+            sensors: &comp.sensor,
+        });
+    }
+    for sensor in &hcdf.sensor {
+        urdf.sensor(URDF_BASE_LINK, sensor);
+    }
+
+    Ok(format!("<?xml version=\"1.0\"?>\n<robot name=\"dendrite\">\n{}</robot>\n", urdf.out))
+}
+
+/// What an MCU or comp contributes to the URDF
+struct DeviceParts<'a> {
+    name: &'a str,
+    pose_cg: Option<&'a str>,
+    /// Legacy single model
+    model: Option<&'a ModelRef>,
+    visuals: &'a [Visual],
+    frames: &'a [Frame],
+    /// Ports and antennas: (name, pose, geometry)
+    fallbacks: Vec<(&'a str, Option<Pose>, Option<&'a Geometry>)>,
+    sensors: &'a [Sensor],
+}
+
+#[derive(Default)]
+struct UrdfWriter {
+    out: String,
+    /// Link names handed out so far
+    links: HashSet<String>,
+}
+
+impl UrdfWriter {
+    fn line(&mut self, depth: usize, text: &str) {
+        let _ = writeln!(self.out, "{}{}", "  ".repeat(depth), text);
+    }
+
+    fn comment(&mut self, depth: usize, text: &str) {
+        // "--" may not appear inside an XML comment
+        self.line(depth, &format!("<!-- {} -->", text.replace("--", "- -")));
+    }
+
+    /// `name`, or `name_2`, `name_3`... if a link already has it
+    fn link_name(&mut self, name: &str) -> String {
+        let mut unique = name.to_string();
+        let mut n = 1;
+        while self.links.contains(&unique) {
+            n += 1;
+            unique = format!("{}_{}", name, n);
+        }
+        self.links.insert(unique.clone());
+        unique
+    }
+
+    fn origin(&mut self, depth: usize, pose: &Pose) {
+        let [x, y, z, roll, pitch, yaw] = pose.to_array();
+        self.line(depth, &format!("<origin xyz=\"{} {} {}\" rpy=\"{} {} {}\"/>", x, y, z, roll, pitch, yaw));
+    }
+
+    fn fixed_joint(&mut self, parent: &str, child: &str, pose: &Pose) {
+        self.line(1, &format!("<joint name=\"{}_joint\" type=\"fixed\">", escape(child)));
+        self.line(2, &format!("<parent link=\"{}\"/>", escape(parent)));
+        self.line(2, &format!("<child link=\"{}\"/>", escape(child)));
+        self.origin(2, pose);
+        self.line(1, "</joint>");
+    }
+
+    fn device(&mut self, device: &DeviceParts) {
+        let link = self.link_name(device.name);
+        let pose = device.pose_cg.and_then(parse_pose_string).unwrap_or_default();
+
+        self.line(1, &format!("<link name=\"{}\">", escape(&link)));
+        if let Some(model) = device.model {
+            self.mesh(device.name, &Pose::default(), model);
+        }
+        for visual in device.visuals {
+            match &visual.model {
+                Some(model) => self.mesh(&visual.name, &visual.parse_pose().unwrap_or_default(), model),
+                None => self.comment(2, &format!("visual '{}' has no model", visual.name)),
+            }
+        }
+        for (name, pose, geometry) in &device.fallbacks {
+            if let Some(geometry) = geometry {
+                self.primitive(name, &pose.clone().unwrap_or_default(), geometry);
+            }
+        }
+        self.line(1, "</link>");
+        self.fixed_joint(URDF_BASE_LINK, &link, &pose);
+
+        // Frame links are named up front so children can join parents listed after them
+        let frame_links: HashMap<&str, String> = device
+            .frames
+            .iter()
+            .map(|frame| (frame.name.as_str(), self.link_name(&format!("{}_{}", link, frame.name))))
+            .collect();
+        for frame in device.frames {
+            let frame_link = &frame_links[frame.name.as_str()];
+            let parent = frame.parent.as_deref().map_or(&link, |parent| &frame_links[parent]);
+            if let Some(description) = &frame.description {
+                self.comment(1, description);
+            }
+            self.line(1, &format!("<link name=\"{}\"/>", escape(frame_link)));
+            self.fixed_joint(parent, frame_link, &frame.parse_pose().unwrap_or_default());
+        }
+
+        for sensor in device.sensors {
+            self.sensor(&link, sensor);
+        }
+    }
+
+    fn mesh(&mut self, name: &str, pose: &Pose, model: &ModelRef) {
+        self.line(2, &format!("<visual name=\"{}\">", escape(name)));
+        self.origin(3, pose);
+        self.line(3, "<geometry>");
+        self.line(4, &format!("<mesh filename=\"{}\"/>", escape(&model.href)));
+        self.line(3, "</geometry>");
+        self.line(2, "</visual>");
+    }
+
+    fn primitive(&mut self, name: &str, pose: &Pose, geometry: &Geometry) {
+        let shape = if let Some(size) = geometry.get_box().and_then(|b| b.parse_size()) {
+            format!("<box size=\"{} {} {}\"/>", size[0], size[1], size[2])
+        } else if let Some(cylinder) = &geometry.cylinder {
+            format!("<cylinder radius=\"{}\" length=\"{}\"/>", cylinder.radius, cylinder.length)
+        } else if let Some(sphere) = &geometry.sphere {
+            format!("<sphere radius=\"{}\"/>", sphere.radius)
+        } else {
+            self.comment(2, &format!("'{}': geometry has no URDF equivalent", name));
+            return;
+        };
+        self.line(2, &format!("<visual name=\"{}\">", escape(name)));
+        self.origin(3, pose);
+        self.line(3, "<geometry>");
+        self.line(4, &shape);
+        self.line(3, "</geometry>");
+        self.line(2, "</visual>");
+    }
+
+    /// A sensor's link, fixed to `parent` at its `pose_cg`, and a Gazebo
+    /// sensor for each of its sub-sensors that has one
+    fn sensor(&mut self, parent: &str, sensor: &Sensor) {
+        let link = match parent {
+            URDF_BASE_LINK => self.link_name(&sensor.name),
+            device => self.link_name(&format!("{}_{}", device, sensor.name)),
+        };
+        let pose = sensor.pose_cg.as_deref().and_then(parse_pose_string).unwrap_or_default();
+        self.line(1, &format!("<link name=\"{}\"/>", escape(&link)));
+        self.fixed_joint(parent, &link, &pose);
+
+        let sub_sensors = sensor
+            .inertial
+            .iter()
+            .map(|s| (&s.sensor_type, s.parse_pose()))
+            .chain(sensor.em.iter().map(|s| (&s.sensor_type, s.parse_pose())))
+            .chain(sensor.optical.iter().map(|s| (&s.sensor_type, s.parse_pose())))
+            .chain(sensor.rf.iter().map(|s| (&s.sensor_type, s.parse_pose())))
+            .chain(sensor.chemical.iter().map(|s| (&s.sensor_type, s.parse_pose())))
+            .chain(sensor.force.iter().map(|s| (&s.sensor_type, s.parse_pose())));
+        for (sensor_type, pose) in sub_sensors {
+            let Some(gazebo_type) = gazebo_sensor_type(sensor_type) else {
+                self.comment(1, &format!("sensor '{}' ({}) has no Gazebo equivalent", sensor.name, sensor_type));
+                continue;
+            };
+            let [x, y, z, roll, pitch, yaw] = pose.unwrap_or_default().to_array();
+            self.line(1, &format!("<gazebo reference=\"{}\">", escape(&link)));
+            self.line(
+                2,
+                &format!("<sensor name=\"{}_{}\" type=\"{}\">", escape(&sensor.name), escape(sensor_type), gazebo_type),
+            );
+            self.line(3, &format!("<pose>{} {} {} {} {} {}</pose>", x, y, z, roll, pitch, yaw));
+            self.line(2, "</sensor>");
+            self.line(1, "</gazebo>");
+        }
+    }
+}
+
+/// Gazebo sensor type for an HCDF sensor type
+fn gazebo_sensor_type(sensor_type: &str) -> Option<&'static str> {
+    match sensor_type {
+        "accel" | "gyro" | "accel_gyro" => Some("imu"),
+        "mag" => Some("magnetometer"),
+        "camera" => Some("camera"),
+        "lidar" => Some("gpu_lidar"),
+        "tof" => Some("depth_camera"),
+        "gnss" => Some("navsat"),
+        "pressure" => Some("air_pressure"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOC: &str = r#"<?xml version='1.0'?>
+<hcdf version="2.1">
+  <mcu name="spinali" hwid="0x01">
+    <visual name="board"><pose>0 0 0.01 0 0 0</pose><model href="models/spinali.glb"/></visual>
+    <port name="CAN0" type="CAN">
+      <fallback_visual>
+        <pose>0.01 0 0 0 0 0</pose>
+        <geometry><box><size>0.005 0.004 0.003</size></box></geometry>
+      </fallback_visual>
+    </port>
+  </mcu>
+  <comp name="camera">
+    <pose_cg>0.1 0 0.05 0 0 1.57</pose_cg>
+    <frame name="lens" parent="mount"><pose>0 0 0.002 0 0 0</pose></frame>
+    <frame name="mount"><pose>0.02 0 0 0 0 0</pose></frame>
+    <antenna name="wifi" type="wifi">
+      <fallback_visual>
+        <geometry><conical_frustum><near>0</near><far>0.01</far><fov>0.5</fov></conical_frustum></geometry>
+      </fallback_visual>
+    </antenna>
+    <sensor name="imu">
+      <inertial type="accel_gyro"><pose>0 0 0.001 0 0 0</pose></inertial>
+      <chemical type="gas"/>
+    </sensor>
+  </comp>
+  <comp name="camera"/>
+</hcdf>"#;
+
+    #[test]
+    fn test_devices_become_fixed_links() {
+        let urdf = export_urdf(&Hcdf::from_xml(DOC).unwrap()).unwrap();
+
+        // Devices without a pose sit at the origin; the mesh keeps its href
+        assert!(urdf.contains("<child link=\"spinali\"/>\n    <origin xyz=\"0 0 0\" rpy=\"0 0 0\"/>"));
+        assert!(urdf.contains("<mesh filename=\"models/spinali.glb\"/>"));
+        assert!(urdf.contains("<box size=\"0.005 0.004 0.003\"/>"));
+        assert!(urdf.contains("<origin xyz=\"0.1 0 0.05\" rpy=\"0 0 1.57\"/>"));
+
+        // Repeated names get a suffix, frames join their parent frame
+        assert!(urdf.contains("<link name=\"camera_2\">"));
+        assert!(urdf.contains("<parent link=\"camera_mount\"/>\n    <child link=\"camera_lens\"/>"));
+
+        // Unsupported geometry and sensors are comments, not errors
+        assert!(urdf.contains("<!-- 'wifi': geometry has no URDF equivalent -->"));
+        assert!(urdf.contains("<!-- sensor 'imu' (gas) has no Gazebo equivalent -->"));
+        assert!(urdf.contains("<gazebo reference=\"camera_imu\">\n    <sensor name=\"imu_accel_gyro\" type=\"imu\">"));
+
+        let mut reader = quick_xml::Reader::from_str(&urdf);
+        loop {
+            match reader.read_event() {
+                Ok(quick_xml::events::Event::Eof) => break,
+                Ok(_) => {}
+                Err(e) => panic!("invalid URDF: {}", e),
+            }
+        }
+    }
+
+    #[test]
+    fn test_broken_frame_hierarchy_fails() {
+        let doc = r#"<hcdf version="2.1"><comp name="c"><frame name="a" parent="missing"/></comp></hcdf>"#;
+        assert!(matches!(export_urdf(&Hcdf::from_xml(doc).unwrap()), Err(HcdfError::ValidationError(_))));
+    }
+}
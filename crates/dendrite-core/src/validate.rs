@@ -0,0 +1,496 @@
+//! HCDF validation with positions
+//!
+//! `Hcdf::from_xml` stops at the first problem and doesn't say where it is,
+//! and some problems (a pose with five numbers, an axis-align of "W") only
+//! show up later when the field is used. `Hcdf::validate` walks the document
+//! itself and reports everything it knows to look for, each with its
+//! position, element path such as `comp[2]/sensor[0]/optical[1]/fov[0]` and
+//! attribute, so a hand-written file can be fixed in one pass.
+//!
+//! Errors are problems that make the document unusable (missing required
+//! attributes, unknown geometry types, non-numeric poses); warnings are
+//! things that parse but are probably wrong (poses with the wrong number of
+//! fields or angles beyond ±2π, unknown axis-align values, models without a
+//! sha). If no errors
+//! are found but `from_xml` still rejects the document, that is reported as
+//! an error at the root element.
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::hcdf::{parse_pose_result, AxisMap, Hcdf, HcdfError, PoseParseError};
+
+/// How bad an issue is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The document can't be used as is
+    Error,
+    /// Parses, but is probably not what was meant
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// One problem in an HCDF document
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ValidationIssue {
+    pub severity: Severity,
+    /// Byte offset of the element
+    pub offset: usize,
+    /// 1-based line
+    pub line: usize,
+    /// 1-based column, in characters
+    pub column: usize,
+    /// Element path below the root, e.g. `comp[2]/port[0]/capabilities`
+    pub path: String,
+    /// Attribute the issue is about, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attribute: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}: ", self.line, self.column, self.severity)?;
+        match (self.path.as_str(), &self.attribute) {
+            ("", None) => {}
+            (path, None) => write!(f, "{}: ", path)?,
+            (path, Some(attribute)) => write!(f, "{}@{}: ", path, attribute)?,
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Everything `Hcdf::validate` found, in document order
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ValidationReport {
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    /// Whether any issue makes the document unusable
+    pub fn has_errors(&self) -> bool {
+        self.errors().next().is_some()
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &ValidationIssue> {
+        self.issues.iter().filter(|i| i.severity == Severity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &ValidationIssue> {
+        self.issues.iter().filter(|i| i.severity == Severity::Warning)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Attributes an element can't do without
+const REQUIRED_ATTRIBUTES: &[(&str, &[&str])] = &[
+    ("hcdf", &["version"]),
+    ("mcu", &["name"]),
+    ("comp", &["name"]),
+    ("link", &["name"]),
+    ("motor", &["name"]),
+    ("power", &["name"]),
+    ("model", &["href"]),
+    ("visual", &["name"]),
+    ("frame", &["name"]),
+    ("port", &["name", "type"]),
+    ("antenna", &["name", "type"]),
+    ("sensor", &["name"]),
+    ("inertial", &["type"]),
+    ("em", &["type"]),
+    ("optical", &["type"]),
+    ("rf", &["type"]),
+    ("chemical", &["type"]),
+    ("force", &["type"]),
+    ("driver", &["name"]),
+    ("fov", &["name"]),
+    ("keepout", &["name"]),
+    ("assembly", &["name"]),
+    ("member", &["device"]),
+];
+
+/// Elements that may repeat, indexed in paths
+const REPEATED: &[&str] = &[
+    "mcu", "comp", "link", "motor", "power", "keepout", "assembly", "member", "visual", "frame", "port", "antenna",
+    "sensor", "inertial", "em", "optical", "rf", "chemical", "force", "fov", "geometry", "interface",
+];
+
+/// Children `<geometry>` accepts
+const GEOMETRY_TYPES: &[&str] = &["box", "cylinder", "sphere", "cone", "frustum", "conical_frustum", "pyramidal_frustum"];
+
+/// Elements holding "x y z roll pitch yaw"
+const POSE_ELEMENTS: &[&str] = &["pose", "pose_cg"];
+
+/// An element whose end tag hasn't been read yet
+struct OpenElement {
+    name: String,
+    /// Path segment (`port[0]`)
+    segment: String,
+    offset: usize,
+    /// Text content, kept for pose elements
+    text: String,
+    /// Children seen so far, by name
+    children: HashMap<String, usize>,
+}
+
+struct Validator<'a> {
+    xml: &'a str,
+    stack: Vec<OpenElement>,
+    root_offset: usize,
+    issues: Vec<ValidationIssue>,
+}
+
+impl<'a> Validator<'a> {
+    fn new(xml: &'a str) -> Self {
+        Self { xml, stack: Vec::new(), root_offset: 0, issues: Vec::new() }
+    }
+
+    /// Path of the innermost open element (the root itself is left out)
+    fn path(&self) -> String {
+        self.stack.iter().skip(1).map(|e| e.segment.as_str()).collect::<Vec<_>>().join("/")
+    }
+
+    /// 1-based line and column of a byte offset
+    fn position(&self, offset: usize) -> (usize, usize) {
+        let before = &self.xml.as_bytes()[..offset.min(self.xml.len())];
+        let line_start = before.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+        (
+            before.iter().filter(|&&b| b == b'\n').count() + 1,
+            String::from_utf8_lossy(&before[line_start..]).chars().count() + 1,
+        )
+    }
+
+    fn issue(&mut self, severity: Severity, offset: usize, path: &str, attribute: Option<&str>, message: String) {
+        let (line, column) = self.position(offset);
+        self.issues.push(ValidationIssue {
+            severity,
+            offset,
+            line,
+            column,
+            path: path.to_string(),
+            attribute: attribute.map(str::to_string),
+            message,
+        });
+    }
+
+    fn open(&mut self, element: &BytesStart, offset: usize) {
+        let name = String::from_utf8_lossy(element.name().as_ref()).into_owned();
+        let parent = self.stack.last_mut().map(|parent| {
+            let count = parent.children.entry(name.clone()).or_insert(0);
+            let index = *count;
+            *count += 1;
+            (parent.name.clone(), index)
+        });
+        let segment = match parent {
+            Some((_, index)) if REPEATED.contains(&name.as_str()) => format!("{}[{}]", name, index),
+            _ => name.clone(),
+        };
+        self.stack.push(OpenElement {
+            name: name.clone(),
+            segment,
+            offset,
+            text: String::new(),
+            children: HashMap::new(),
+        });
+        let path = self.path();
+
+        match parent {
+            None => {
+                self.root_offset = offset;
+                if name != "hcdf" {
+                    self.issue(Severity::Error, offset, &path, None, format!("root element is <{}>, expected <hcdf>", name));
+                }
+            }
+            Some((parent, _)) if parent == "geometry" && !GEOMETRY_TYPES.contains(&name.as_str()) => {
+                self.issue(
+                    Severity::Error,
+                    offset,
+                    &path,
+                    None,
+                    format!("unknown geometry type <{}> (expected one of {})", name, GEOMETRY_TYPES.join(", ")),
+                );
+            }
+            Some(_) => {}
+        }
+
+        let mut attributes = HashMap::new();
+        for attribute in element.attributes() {
+            match attribute {
+                Ok(attribute) => {
+                    let key = String::from_utf8_lossy(attribute.key.as_ref()).into_owned();
+                    let value = attribute
+                        .unescape_value()
+                        .map(|v| v.into_owned())
+                        .unwrap_or_else(|_| String::from_utf8_lossy(&attribute.value).into_owned());
+                    attributes.insert(key, value);
+                }
+                Err(e) => self.issue(Severity::Error, offset, &path, None, format!("malformed attribute: {}", e)),
+            }
+        }
+        if let Some((_, required)) = REQUIRED_ATTRIBUTES.iter().find(|(element, _)| *element == name) {
+            for attribute in required.iter().filter(|a| !attributes.contains_key(**a)) {
+                self.issue(
+                    Severity::Error,
+                    offset,
+                    &path,
+                    Some(*attribute),
+                    format!("<{}> is missing required attribute \"{}\"", name, attribute),
+                );
+            }
+        }
+
+        match name.as_str() {
+            "axis-align" => {
+                for axis in ["x", "y", "z"] {
+                    if let Some(value) = attributes.get(axis).filter(|v| AxisMap::parse(v).is_none()) {
+                        self.issue(
+                            Severity::Warning,
+                            offset,
+                            &path,
+                            Some(axis),
+                            format!("unknown axis \"{}\" (expected X, -X, Y, -Y, Z or -Z)", value),
+                        );
+                    }
+                }
+            }
+            "model" if attributes.contains_key("href") && !attributes.contains_key("sha") => {
+                self.issue(
+                    Severity::Warning,
+                    offset,
+                    &path,
+                    Some("sha"),
+                    "model has no sha, so cached copies can't be checked".to_string(),
+                );
+            }
+            _ => {}
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        if let Some(open) = self.stack.last_mut().filter(|e| POSE_ELEMENTS.contains(&e.name.as_str())) {
+            open.text.push_str(text);
+        }
+    }
+
+    fn close(&mut self) {
+        let path = self.path();
+        let Some(open) = self.stack.pop() else {
+            return;
+        };
+        if POSE_ELEMENTS.contains(&open.name.as_str()) {
+            match parse_pose_result(&open.text) {
+                Ok(_) => {}
+                Err(e @ PoseParseError::NotANumber { .. }) => self.issue(
+                    Severity::Error,
+                    open.offset,
+                    &path,
+                    None,
+                    format!("<{}> \"{}\": {}", open.name, open.text.trim(), e),
+                ),
+                Err(e) => self.issue(
+                    Severity::Warning,
+                    open.offset,
+                    &path,
+                    None,
+                    format!("<{}> \"{}\": {}; it will be ignored", open.name, open.text.trim(), e),
+                ),
+            }
+        } else if open.name == "geometry" && open.children.is_empty() {
+            self.issue(Severity::Error, open.offset, &path, None, "<geometry> has no shape".to_string());
+        }
+    }
+}
+
+impl Hcdf {
+    /// Check a document, reporting every issue instead of stopping at the
+    /// first
+    ///
+    /// Fails only if the XML itself is malformed, since nothing after a
+    /// syntax error can be located reliably.
+    pub fn validate(xml: &str) -> Result<ValidationReport, HcdfError> {
+        let mut validator = Validator::new(xml);
+        let mut reader = Reader::from_str(xml);
+        loop {
+            let offset = reader.buffer_position() as usize;
+            match reader.read_event() {
+                Ok(Event::Start(element)) => validator.open(&element, offset),
+                Ok(Event::Empty(element)) => {
+                    validator.open(&element, offset);
+                    validator.close();
+                }
+                Ok(Event::End(_)) => validator.close(),
+                Ok(Event::Text(text)) => match text.unescape() {
+                    Ok(text) => validator.text(&text),
+                    Err(e) => {
+                        let path = validator.path();
+                        validator.issue(Severity::Error, offset, &path, None, format!("malformed text: {}", e));
+                    }
+                },
+                Ok(Event::Eof) => break,
+                Ok(_) => {}
+                Err(e) => {
+                    let (line, column) = validator.position(reader.error_position() as usize);
+                    return Err(HcdfError::ParseError(format!("{}:{}: malformed XML: {}", line, column, e)));
+                }
+            }
+        }
+
+        if !validator.issues.iter().any(|i| i.severity == Severity::Error) {
+            if let Err(e) = Hcdf::from_xml(xml) {
+                let offset = validator.root_offset;
+                validator.issue(Severity::Error, offset, "", None, e.to_string());
+            }
+        }
+        Ok(ValidationReport { issues: validator.issues })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validate(xml: &str) -> ValidationReport {
+        Hcdf::validate(xml).unwrap()
+    }
+
+    /// (severity, path, attribute) of every issue
+    fn summary(report: &ValidationReport) -> Vec<(Severity, String, Option<String>)> {
+        report.issues.iter().map(|i| (i.severity, i.path.clone(), i.attribute.clone())).collect()
+    }
+
+    /// A document with one comp holding `body`
+    fn comp(body: &str) -> String {
+        format!(r#"<hcdf version="2.0"><comp name="fmu">{}</comp></hcdf>"#, body)
+    }
+
+    #[test]
+    fn test_valid_document() {
+        let xml = r#"<?xml version='1.0'?>
+<hcdf version="2.0">
+  <comp name="fmu">
+    <pose_cg>0 0 0.05 0 0 0</pose_cg>
+    <port name="CAN0" type="CAN">
+      <geometry><box><size>0.005 0.004 0.003</size></box></geometry>
+    </port>
+  </comp>
+</hcdf>"#;
+        assert!(validate(xml).is_empty());
+    }
+
+    #[test]
+    fn test_malformed_fixtures() {
+        use Severity::{Error, Warning};
+        let some = |s: &str| Some(s.to_string());
+        let cases: Vec<(String, Vec<(Severity, String, Option<String>)>)> = vec![
+            // 1. Root without a version
+            (r#"<hcdf><comp name="a"/></hcdf>"#.to_string(), vec![(Error, String::new(), some("version"))]),
+            // 2. Wrong root element
+            (r#"<robot version="2.0"/>"#.to_string(), vec![(Error, String::new(), None)]),
+            // 3. Comp without a name
+            (r#"<hcdf version="2.0"><comp/></hcdf>"#.to_string(), vec![(Error, "comp[0]".into(), some("name"))]),
+            // 4. Port without name or type
+            (comp("<port/>"), vec![(Error, "comp[0]/port[0]".into(), some("name")), (Error, "comp[0]/port[0]".into(), some("type"))]),
+            // 5. Unknown geometry
+            (
+                comp(r#"<port name="P" type="CAN"><geometry><capsule/></geometry></port>"#),
+                vec![(Error, "comp[0]/port[0]/geometry[0]/capsule".into(), None)],
+            ),
+            // 6. Geometry with nothing in it
+            (comp(r#"<port name="P" type="CAN"><geometry/></port>"#), vec![(Error, "comp[0]/port[0]/geometry[0]".into(), None)]),
+            // 7. Pose that isn't numbers
+            (comp("<pose_cg>0 0 up 0 0 0</pose_cg>"), vec![(Error, "comp[0]/pose_cg".into(), None)]),
+            // 8. Pose with five fields
+            (comp("<pose_cg>0 0 0.05 0 0</pose_cg>"), vec![(Warning, "comp[0]/pose_cg".into(), None)]),
+            // 9. Unknown axis-align value
+            (
+                comp(r#"<sensor name="imu"><inertial type="accel_gyro"><driver name="bmi088"><axis-align x="Y" y="W" z="Z"/></driver></inertial></sensor>"#),
+                vec![(Warning, "comp[0]/sensor[0]/inertial[0]/driver/axis-align".into(), some("y"))],
+            ),
+            // 10. Model without a sha
+            (comp(r#"<visual name="board"><model href="board.glb"/></visual>"#), vec![(Warning, "comp[0]/visual[0]/model".into(), some("sha"))]),
+            // 11. Model without an href
+            (comp(r#"<visual name="board"><model sha="abc"/></visual>"#), vec![(Error, "comp[0]/visual[0]/model".into(), some("href"))]),
+            // 12. FOV without a name, deep in a sensor
+            (
+                comp(r#"<sensor name="cam"><optical type="camera"/><optical type="flow"><fov><geometry><sphere><radius>1</radius></sphere></geometry></fov></optical></sensor>"#),
+                vec![(Error, "comp[0]/sensor[0]/optical[1]/fov[0]".into(), some("name"))],
+            ),
+            // 13. Assembly member without a device
+            (
+                r#"<hcdf version="2.0"><assembly name="arm"><member/></assembly></hcdf>"#.to_string(),
+                vec![(Error, "assembly[0]/member[0]".into(), some("device"))],
+            ),
+            // 14. Yaw in degrees without a suffix
+            (comp("<pose_cg>0 0 0 0 0 90</pose_cg>"), vec![(Warning, "comp[0]/pose_cg".into(), None)]),
+            // 15. Commas and a degree suffix are fine
+            (comp("<pose_cg>0, 0, 0.05, 0, 0, 90deg</pose_cg>"), vec![]),
+            // 16. A quaternion orientation, and one with no length
+            (comp("<pose_cg>0 0 0.05 0.7071068 0 0.7071068 0</pose_cg>"), vec![]),
+            (comp("<pose_cg>0 0 0.05 0 0 0 0</pose_cg>"), vec![(Warning, "comp[0]/pose_cg".into(), None)]),
+        ];
+        for (xml, expected) in cases {
+            assert_eq!(summary(&validate(&xml)), expected, "{}", xml);
+        }
+    }
+
+    #[test]
+    fn test_collects_every_issue_with_position() {
+        let xml = r#"<hcdf version="2.0">
+  <comp name="a"/>
+  <comp name="b"/>
+  <comp name="fmu">
+    <pose_cg>0 0 0.05 0 0</pose_cg>
+    <port type="CAN">
+      <capabilities/>
+    </port>
+    <port name="ETH0" type="ethernet">
+      <geometry><capsule/></geometry>
+    </port>
+  </comp>
+</hcdf>"#;
+        let report = validate(xml);
+        let found: Vec<(usize, usize, &str)> = report.issues.iter().map(|i| (i.line, i.column, i.path.as_str())).collect();
+        assert_eq!(
+            found,
+            vec![(5, 5, "comp[2]/pose_cg"), (6, 5, "comp[2]/port[0]"), (10, 17, "comp[2]/port[1]/geometry[0]/capsule")]
+        );
+        assert_eq!(report.warnings().count(), 1);
+        assert_eq!(report.errors().count(), 2);
+        assert_eq!(&xml[report.issues[1].offset..report.issues[1].offset + 5], "<port");
+        assert_eq!(
+            report.issues[1].to_string(),
+            "6:5: error: comp[2]/port[0]@name: <port> is missing required attribute \"name\""
+        );
+    }
+
+    #[test]
+    fn test_warnings_alone_still_parse() {
+        let report = validate(&comp("<pose_cg>0 0 0</pose_cg>"));
+        assert!(!report.has_errors());
+        assert_eq!(report.warnings().count(), 1);
+    }
+
+    #[test]
+    fn test_malformed_xml_is_an_error() {
+        let xml = "<hcdf version=\"2.0\">\n  <comp name=\"fmu\">\n</hcdf>";
+        let Err(HcdfError::ParseError(message)) = Hcdf::validate(xml) else {
+            panic!("malformed XML should fail");
+        };
+        assert!(message.starts_with("3:"), "{}", message);
+    }
+}
@@ -0,0 +1,50 @@
+//! Parse smoke test for the large synthetic fixture
+//!
+//! Kept in its own test binary so the counting allocator sees only this
+//! test. The budget is generous; a failure means parsing got several times
+//! slower or more allocation-heavy, not that CI was busy.
+
+use dendrite_core::Hcdf;
+use dendrite_test_support::{CountingAllocator, FixtureSize, LARGE_PARSE_BUDGET};
+use std::time::Instant;
+
+#[global_allocator]
+static ALLOC: CountingAllocator = CountingAllocator::new();
+
+#[test]
+fn large_fixture_parses_within_budget() {
+    let xml = FixtureSize::Large.xml();
+    let (mcus, comps) = FixtureSize::Large.counts();
+
+    // Warm up lazily initialised state so it isn't charged to the parse
+    Hcdf::from_xml(&FixtureSize::Small.xml()).unwrap();
+
+    let start = Instant::now();
+    let (hcdf, stats) = ALLOC.measure(|| Hcdf::from_xml(&xml).unwrap());
+    let elapsed = start.elapsed();
+
+    assert_eq!(hcdf.mcu.len(), mcus);
+    assert_eq!(hcdf.comp.len(), comps);
+    assert!(hcdf.comp.iter().all(|c| c.port.len() == 6 && c.antenna.len() == 2 && c.sensor.len() == 7));
+
+    let budget = LARGE_PARSE_BUDGET;
+    assert!(
+        elapsed <= budget.max_duration,
+        "parsing {} bytes took {:?}, budget {:?}",
+        xml.len(),
+        elapsed,
+        budget.max_duration
+    );
+    assert!(
+        stats.allocations <= budget.max_allocations,
+        "parsing made {} allocations, budget {}",
+        stats.allocations,
+        budget.max_allocations
+    );
+    assert!(
+        stats.peak_bytes <= budget.max_peak_bytes,
+        "parsing peaked at {} live bytes, budget {}",
+        stats.peak_bytes,
+        budget.max_peak_bytes
+    );
+}
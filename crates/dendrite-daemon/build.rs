@@ -1,4 +1,10 @@
+use std::process::Command;
+
 fn main() {
+    // Embed build information reported by /api/version
+    embed_git_hash();
+    embed_features();
+
     // Embed Windows icon and metadata
     #[cfg(target_os = "windows")]
     {
@@ -18,3 +24,32 @@ fn main() {
         }
     }
 }
+
+/// Short commit hash of the checkout, or "unknown" when building from a
+/// source tarball or without git installed
+fn embed_git_hash() {
+    let hash = Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|hash| hash.trim().to_string())
+        .filter(|hash| !hash.is_empty())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    println!("cargo:rustc-env=DENDRITE_GIT_HASH={}", hash);
+    println!("cargo:rerun-if-changed=../../.git/HEAD");
+    println!("cargo:rerun-if-changed=../../.git/refs");
+}
+
+/// Cargo features enabled for this build, comma-separated
+fn embed_features() {
+    let mut features: Vec<String> = std::env::vars()
+        .filter_map(|(key, _)| key.strip_prefix("CARGO_FEATURE_").map(|f| f.to_lowercase().replace('_', "-")))
+        .filter(|f| f != "default")
+        .collect();
+    features.sort();
+
+    println!("cargo:rustc-env=DENDRITE_FEATURES={}", features.join(","));
+}
@@ -1,18 +1,31 @@
 //! REST API handlers
 
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
+    body::Bytes,
+    extract::{Extension, Path, Query, State},
+    http::{header, StatusCode},
     response::IntoResponse,
     Json,
 };
-use dendrite_core::DeviceId;
+use chrono::{DateTime, Utc};
+use dendrite_api_types::{
+    DeviceImagesResponse, FirmwareCheckResponse, FragmentReloadResponse, ImageConfirmRequest, LldpNeighborJson, OtaBatchRequest, OtaProgressResponse, OtaReadiness, OtaStartQuery, OtaStartResponse,
+    ScanResponse, ShellRequest, ShellResponse,
+};
+use dendrite_core::{Assembly, AssemblyMember, Device, DeviceId, DevicePort, DeviceMetrics, DeviceStatus, DeviceSummary, FleetInventory, HeartbeatMethod, KeepoutShape, KeepoutViolation, LatencySample, Pose, PoseConstraint, Provenance, ProvenanceSource, RuntimeFields};
+use dendrite_discovery::lldp::parse_port_number;
+use dendrite_discovery::{IpNet, RemoveMode};
 use dendrite_mcumgr::query_device as mcumgr_query;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
-use crate::state::AppState;
+use crate::auth::{AuthState, SessionRole};
+use crate::ota::OtaTarget;
+use crate::setup::{self, ConfigWriteError, SetupState};
+use crate::state::{convert_frames, convert_port, AppState};
+use crate::trace::TraceFormat;
+use crate::version::VersionInfo;
 
 /// API error response
 #[derive(Serialize)]
@@ -29,9 +42,96 @@ impl ApiError {
 /// List all discovered devices
 pub async fn list_devices(
     State(state): State<Arc<AppState>>,
+    Query(query): Query<DeviceListQuery>,
 ) -> impl IntoResponse {
-    let devices = state.devices().await;
-    Json(devices)
+    let devices = match query.as_of {
+        Some(as_of) => {
+            let Some(journal) = &state.journal else {
+                return (
+                    StatusCode::NOT_FOUND,
+                    Json(ApiError::new("Registry journal is disabled")),
+                )
+                    .into_response();
+            };
+            match journal.devices_at(as_of).await {
+                Some(devices) => devices,
+                None => {
+                    return (
+                        StatusCode::NOT_FOUND,
+                        Json(ApiError::new(format!("Journal has no state at or before {}", as_of.to_rfc3339()))),
+                    )
+                        .into_response()
+                }
+            }
+        }
+        None => state.devices().await,
+    };
+    if query.lite {
+        let summaries: Vec<DeviceSummary> = devices.iter().map(DeviceSummary::from).collect();
+        return Json(summaries).into_response();
+    }
+    Json(devices).into_response()
+}
+
+/// Query parameters for the device list
+#[derive(Deserialize)]
+pub struct DeviceListQuery {
+    /// Leave out visuals, frames, ports and sensors
+    #[serde(default)]
+    pub lite: bool,
+    /// Reconstruct the registry at this past instant from the journal
+    #[serde(default)]
+    pub as_of: Option<DateTime<Utc>>,
+}
+
+/// Time span the registry journal covers, for the History view
+///
+/// GET /api/journal
+pub async fn get_journal(
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    let range = match &state.journal {
+        Some(journal) => journal.range().await,
+        None => None,
+    };
+    Json(serde_json::json!({
+        "enabled": state.journal.is_some(),
+        "start": range.map(|r| r.start),
+        "end": range.map(|r| r.end),
+    }))
+}
+
+/// Findings from the analyzers, open first
+///
+/// GET /api/attention
+pub async fn get_attention(
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    let (findings, open) = match &state.attention {
+        Some(inbox) => (inbox.list().await, inbox.open_count().await),
+        None => (Vec::new(), 0),
+    };
+    Json(serde_json::json!({
+        "enabled": state.attention.is_some(),
+        "open": open,
+        "findings": findings,
+    }))
+}
+
+/// Acknowledge a finding
+///
+/// POST /api/attention/{id}/ack
+pub async fn acknowledge_finding(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let Some(inbox) = &state.attention else {
+        return (StatusCode::NOT_FOUND, Json(ApiError::new("Attention inbox is disabled"))).into_response();
+    };
+    match inbox.acknowledge(&id).await {
+        Some(finding) => Json(finding).into_response(),
+        None => (StatusCode::NOT_FOUND, Json(ApiError::new("Finding not found"))).into_response(),
+    }
 }
 
 /// Get a specific device by ID
@@ -74,15 +174,25 @@ pub async fn query_device(
         }
     };
 
+    if device.is_can() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ApiError::new("CAN devices can't be queried over MCUmgr")),
+        )
+            .into_response();
+    }
+
     info!(device = %id, "Manual device query requested");
 
-    match mcumgr_query(device.discovery.ip, device.discovery.port).await {
+    match mcumgr_query(device.discovery.socket_addr(), dendrite_mcumgr::QueryOptions::default()).await {
         Ok(result) => {
-            let updated = dendrite_mcumgr::query_result_to_device(
+            let mut updated = dendrite_mcumgr::query_result_to_device(
                 device.discovery.ip,
                 device.discovery.port,
+                device.discovery.mac.as_deref(),
                 result,
             );
+            updated.discovery.scope_id = device.discovery.scope_id;
             Json(updated).into_response()
         }
         Err(e) => (
@@ -97,15 +207,357 @@ pub async fn query_device(
 pub async fn get_topology(
     State(state): State<Arc<AppState>>,
 ) -> impl IntoResponse {
-    let topology = state.get_topology().await;
+    let topology = state.get_topology();
     Json(topology.to_graph())
 }
 
+/// LLDP neighbors the scanner knows, each with the device whose MAC is its
+/// chassis ID
+///
+/// GET /api/lldp/neighbors
+pub async fn list_lldp_neighbors(
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    let devices = state.devices().await;
+    let now = Utc::now();
+    let neighbors: Vec<LldpNeighborJson> = state
+        .scanner
+        .lldp_neighbors()
+        .await
+        .into_iter()
+        .map(|entry| {
+            let neighbor = entry.neighbor;
+            let device_id = devices
+                .iter()
+                .find(|d| d.discovery.mac.as_deref().is_some_and(|mac| neighbor.has_chassis_mac(mac)))
+                .map(|d| d.id.0.clone());
+            let age = chrono::Duration::from_std(entry.age).unwrap_or_default();
+            LldpNeighborJson {
+                port: parse_port_number(&neighbor.port_id),
+                interface: neighbor.local_interface,
+                chassis_id: neighbor.chassis_id,
+                port_id: neighbor.port_id,
+                system_name: neighbor.system_name,
+                ttl_secs: neighbor.ttl.map(|ttl| ttl.as_secs()),
+                last_refresh: (now - age).to_rfc3339(),
+                device_id,
+            }
+        })
+        .collect();
+    Json(neighbors)
+}
+
+/// Device counts and assembly warnings, for status displays
+///
+/// GET /api/summary
+pub async fn get_summary(
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    let devices = state.devices().await;
+    let online = devices.iter().filter(|d| d.status == DeviceStatus::Online).count();
+    let offline = devices.iter().filter(|d| d.status == DeviceStatus::Offline).count();
+    let snapshot = state.snapshot();
+    Json(serde_json::json!({
+        "devices": devices.len(),
+        "online": online,
+        "offline": offline,
+        "keepout_count": snapshot.hcdf.keepout.len(),
+        "keepout_violations": snapshot.keepout_violations,
+    }))
+}
+
+/// Counts per board, sensor driver, port type/speed and firmware version
+///
+/// GET /api/fleet/inventory
+pub async fn get_fleet_inventory(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<InventoryQuery>,
+) -> impl IntoResponse {
+    let mut devices = state.devices().await;
+    if query.include_archived {
+        devices.extend(state.scanner.archived_devices().await);
+    }
+    let inventory = FleetInventory::from_devices(&devices);
+    match query.format {
+        InventoryFormat::Json => Json(inventory).into_response(),
+        InventoryFormat::Csv => (
+            [(header::CONTENT_TYPE, "text/csv; charset=utf-8")],
+            inventory.to_csv(),
+        )
+            .into_response(),
+    }
+}
+
+/// Latency, uptime, firmware age and last-seen for every device, for
+/// coloring devices by a metric in the 3D view
+///
+/// GET /api/metrics
+pub async fn get_metrics(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let devices = state.devices().await;
+    let mut histories = Vec::with_capacity(devices.len());
+    for device in &devices {
+        histories.push(state.scanner.latency_history(&device.id.0).await);
+    }
+    let report = DeviceMetrics::from_devices(
+        devices.iter().zip(histories.iter().map(Vec::as_slice)),
+        Utc::now(),
+    );
+    Json(report)
+}
+
+/// Query parameters for the fleet inventory
+#[derive(Deserialize)]
+pub struct InventoryQuery {
+    /// Count archived devices too
+    #[serde(default)]
+    pub include_archived: bool,
+    #[serde(default)]
+    pub format: InventoryFormat,
+}
+
+/// Response format for the fleet inventory
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum InventoryFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+/// A keep-out volume placed in the HCDF root frame
+#[derive(Serialize)]
+struct KeepoutVolume {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    /// [x, y, z, roll, pitch, yaw] (meters, radians)
+    pose: [f64; 6],
+    shape: KeepoutShape,
+}
+
+/// A violation with the intruding device's bounding box, so clients can
+/// outline it
+#[derive(Serialize)]
+struct KeepoutIntrusion {
+    #[serde(flatten)]
+    violation: KeepoutViolation,
+    /// Bounding box pose relative to the device origin
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bbox_pose: Option<[f64; 6]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bbox_size: Option<[f64; 3]>,
+}
+
+/// Keep-out volumes and the devices currently intruding into them
+///
+/// GET /api/keepouts
+pub async fn get_keepouts(
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    let snapshot = state.snapshot();
+    let hcdf = &snapshot.hcdf;
+
+    let volumes: Vec<KeepoutVolume> = hcdf
+        .keepout
+        .iter()
+        .filter_map(|k| {
+            Some(KeepoutVolume {
+                name: k.name.clone(),
+                description: k.description.clone(),
+                pose: k.parse_pose().unwrap_or_default().to_array(),
+                shape: k.shape()?,
+            })
+        })
+        .collect();
+
+    let intrusions: Vec<KeepoutIntrusion> = snapshot
+        .keepout_violations
+        .iter()
+        .cloned()
+        .map(|violation| {
+            let bbox = hcdf
+                .mcu
+                .iter()
+                .find(|m| m.name == violation.device && m.hwid == violation.hwid)
+                .and_then(|m| m.bbox.as_ref())
+                .or_else(|| {
+                    hcdf.comp
+                        .iter()
+                        .find(|c| c.name == violation.device && c.hwid == violation.hwid)
+                        .and_then(|c| c.bbox.as_ref())
+                });
+            KeepoutIntrusion {
+                bbox_pose: bbox.map(|b| b.parse_pose().unwrap_or_default().to_array()),
+                bbox_size: bbox.and_then(|b| b.parse_size()),
+                violation,
+            }
+        })
+        .collect();
+
+    Json(serde_json::json!({
+        "volumes": volumes,
+        "violations": intrusions,
+    }))
+}
+
+/// An assembly as listed by the API
+#[derive(Serialize)]
+pub struct AssemblyView {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Pose written in the HCDF, if any
+    pub pose: Option<[f64; 6]>,
+    /// Effective origin (the pose, or the member centroid)
+    pub origin: [f64; 6],
+    pub members: Vec<String>,
+}
+
+/// Named sub-assemblies of devices
+///
+/// GET /api/assemblies
+pub async fn list_assemblies(
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    let snapshot = state.snapshot();
+    let hcdf = &snapshot.hcdf;
+    let assemblies: Vec<AssemblyView> = hcdf
+        .assembly
+        .iter()
+        .map(|a| AssemblyView {
+            name: a.name.clone(),
+            description: a.description.clone(),
+            pose: a.parse_pose().map(|p| p.to_array()),
+            origin: hcdf.assembly_origin(&a.name).unwrap_or_default().to_array(),
+            members: a.member.iter().map(|m| m.device.clone()).collect(),
+        })
+        .collect();
+    Json(assemblies)
+}
+
+/// Request to create or replace an assembly
+#[derive(Deserialize)]
+pub struct AssemblyRequest {
+    /// Member device IDs
+    pub members: Vec<String>,
+    /// Origin pose: [x, y, z, roll, pitch, yaw]; the member centroid if omitted
+    #[serde(default)]
+    pub pose: Option<[f64; 6]>,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// Create or replace an assembly
+///
+/// PUT /api/assemblies/:name
+///
+/// Fails with 409 if a member already belongs to another assembly.
+pub async fn set_assembly(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    Json(req): Json<AssemblyRequest>,
+) -> impl IntoResponse {
+    let assembly = Assembly {
+        name: name.clone(),
+        description: req.description,
+        pose: req.pose.map(|p| Pose::from_array(p).to_pose_string()),
+        member: req.members.into_iter().map(|device| AssemblyMember { device }).collect(),
+    };
+    let members = assembly.member.len();
+
+    match state.store.update(move |data| data.hcdf.set_assembly(assembly)).await {
+        Ok(()) => {
+            info!(assembly = %name, members, "Assembly saved");
+            state.schedule_save_hcdf();
+            Json(serde_json::json!({
+                "status": "updated",
+                "assembly": name,
+            }))
+            .into_response()
+        }
+        Err(e) => (StatusCode::CONFLICT, Json(ApiError::new(e.to_string()))).into_response(),
+    }
+}
+
+/// Remove an assembly (its members stay where they are)
+///
+/// DELETE /api/assemblies/:name
+pub async fn remove_assembly(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    let assembly = name.clone();
+    if state.store.update(move |data| data.hcdf.remove_assembly(&assembly)).await {
+        state.schedule_save_hcdf();
+        Json(serde_json::json!({
+            "status": "removed",
+            "assembly": name,
+        }))
+        .into_response()
+    } else {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ApiError::new("Assembly not found")),
+        )
+            .into_response()
+    }
+}
+
+/// Request to move an assembly
+#[derive(Deserialize)]
+pub struct AssemblyPoseRequest {
+    /// New origin pose: [x, y, z, roll, pitch, yaw]
+    pub pose: [f64; 6],
+}
+
+/// Move an assembly, rigidly carrying its members along
+///
+/// PUT /api/assemblies/:name/pose
+pub async fn move_assembly(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    Json(req): Json<AssemblyPoseRequest>,
+) -> impl IntoResponse {
+    let assembly = name.clone();
+    let moved = state
+        .store
+        .update(move |data| {
+            let moved = data.hcdf.move_assembly(&assembly, &Pose::from_array(req.pose))?;
+            for member in &moved {
+                data.provenance.mark_mcu_edited(&member.device);
+            }
+            Ok::<_, dendrite_core::AssemblyError>(moved)
+        })
+        .await;
+    let moved = match moved {
+        Ok(moved) => moved,
+        Err(e) => return (StatusCode::NOT_FOUND, Json(ApiError::new(e.to_string()))).into_response(),
+    };
+
+    // Keep the registry in step so the next scan doesn't put members back
+    for member in &moved {
+        set_registry_pose(&state, &member.device, member.pose.to_array()).await;
+    }
+    state.schedule_save_hcdf();
+
+    let members: Vec<_> = moved
+        .iter()
+        .map(|m| serde_json::json!({ "device_id": m.device, "pose": m.pose.to_array() }))
+        .collect();
+    Json(serde_json::json!({
+        "status": "updated",
+        "assembly": name,
+        "pose": req.pose,
+        "members": members,
+    }))
+    .into_response()
+}
+
 /// Get current HCDF document
 pub async fn get_hcdf(
     State(state): State<Arc<AppState>>,
 ) -> impl IntoResponse {
-    let hcdf = state.get_hcdf().await;
+    let hcdf = state.get_hcdf();
     match hcdf.to_xml() {
         Ok(xml) => (
             StatusCode::OK,
@@ -142,10 +594,11 @@ pub async fn trigger_scan(
     info!("Manual scan triggered");
 
     match state.scanner.scan_once().await {
-        Ok(devices) => Json(serde_json::json!({
-            "status": "completed",
-            "devices_found": devices.len()
-        }))
+        Ok(devices) => Json(ScanResponse {
+            status: "completed".to_string(),
+            devices_found: devices.len(),
+            methods: state.scanner.last_scan_stats().await,
+        })
         .into_response(),
         Err(e) => (
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -155,16 +608,65 @@ pub async fn trigger_scan(
     }
 }
 
+/// Reload the fragment database and match affected devices again
+///
+/// Fragments that fail to load are listed in the response while the rest
+/// are used; an unreadable index is rejected and the previous one kept.
+pub async fn reload_fragments(
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    info!("Fragment reload requested");
+
+    match state.reload_fragments().await {
+        Ok((reload, devices)) => {
+            let entries = |keys: &[(String, String)]| keys.iter().map(|(board, app)| format!("{}/{}", board, app)).collect();
+            Json(FragmentReloadResponse {
+                added: entries(&reload.added),
+                changed: entries(&reload.changed),
+                removed: entries(&reload.removed),
+                failed: reload.failed.iter().map(ToString::to_string).collect(),
+                fragments: reload.total,
+                devices: devices.into_iter().map(|id| id.0).collect(),
+            })
+            .into_response()
+        }
+        Err(e) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(ApiError::new(format!("Fragment reload failed: {}", e))),
+        )
+            .into_response(),
+    }
+}
+
+/// Query parameters for device removal
+#[derive(Deserialize)]
+pub struct RemoveDeviceQuery {
+    /// `archive` (default) hides the device until it is seen again,
+    /// `forget` also deletes its saved pose and HCDF entry
+    #[serde(default)]
+    pub mode: RemoveMode,
+}
+
 /// Remove a device from the registry
+///
+/// DELETE /api/devices/:id?mode=archive|forget
 pub async fn remove_device(
     State(state): State<Arc<AppState>>,
     Path(id): Path<String>,
+    Query(query): Query<RemoveDeviceQuery>,
 ) -> impl IntoResponse {
-    info!(device = %id, "Remove device requested");
+    info!(device = %id, mode = ?query.mode, "Remove device requested");
+
+    if state.scanner.remove_device(&id, query.mode).await {
+        if query.mode == RemoveMode::Forget {
+            state.forget_device(&id).await;
+        }
 
-    if state.scanner.remove_device(&id).await {
         Json(serde_json::json!({
-            "status": "removed",
+            "status": match query.mode {
+                RemoveMode::Archive => "archived",
+                RemoveMode::Forget => "removed",
+            },
             "device_id": id
         }))
         .into_response()
@@ -177,70 +679,286 @@ pub async fn remove_device(
     }
 }
 
-/// Get current configuration
+/// Fields of a device to change; omitted ones are left as they are
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DevicePatch {
+    /// Pose relative to the parent: [x, y, z, roll, pitch, yaw] (meters, radians)
+    #[serde(default)]
+    pub pose: Option<[f64; 6]>,
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Board name, used for fragment matching in place of the reported one
+    #[serde(default)]
+    pub board: Option<String>,
+    #[serde(default)]
+    pub enabled: Option<bool>,
+}
+
+/// Change some fields of a device
+///
+/// PATCH /api/devices/:id
+///
+/// Body: `{"pose": [0.1, 0, 0, 0, 0, 1.57], "name": "flow", "board":
+/// "mr_mcxn_t1", "enabled": false}`, any subset. The fields are applied to
+/// the registry entry in one edit and to the device's MCU (or scene
+/// object's comp) in the HCDF, so patches to other devices, or to other
+/// fields of this one, aren't overwritten. Returns the updated device.
+pub async fn patch_device(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Json(patch): Json<DevicePatch>,
+) -> impl IntoResponse {
+    debug!(device = %id, patch = ?patch, "Patching device");
+
+    if patch.name.as_deref().is_some_and(|name| name.trim().is_empty()) {
+        return (StatusCode::BAD_REQUEST, Json(ApiError::new("Device name can't be empty"))).into_response();
+    }
+
+    let edit = patch.clone();
+    let Some(device) = state.scanner.modify_device(&id, move |device| {
+        if let Some(pose) = edit.pose {
+            device.pose = Some(pose);
+            device.pose_provisional = false;
+        }
+        if let Some(name) = edit.name {
+            device.name = name;
+        }
+        if let Some(board) = edit.board {
+            device.info.board = Some(board);
+        }
+        if let Some(enabled) = edit.enabled {
+            device.enabled = enabled;
+        }
+    }).await else {
+        return (StatusCode::NOT_FOUND, Json(ApiError::new("Device not found"))).into_response();
+    };
+
+    let registered = device.clone();
+    state.store.update(move |data| {
+        let hcdf = &mut data.hcdf;
+        let pose = patch.pose.map(Pose::from_array);
+        if let Some(mcu) = hcdf.mcu.iter_mut().find(|m| m.hwid.as_deref() == Some(id.as_str())) {
+            if let Some(pose) = pose {
+                mcu.pose_cg = Some(pose.to_pose_string_like(mcu.pose_cg.as_deref()));
+                mcu.provisional = None;
+            }
+            if let Some(name) = patch.name {
+                mcu.name = name;
+            }
+            if let Some(board) = patch.board {
+                mcu.board = Some(board);
+            }
+            if let Some(enabled) = patch.enabled {
+                mcu.enabled = (!enabled).then_some(false);
+            }
+            data.provenance.mark_mcu_edited(&id);
+        } else if let Some(comp) = hcdf.comp.iter_mut().find(|c| {
+            c.hwid.as_deref() == Some(id.as_str()) || format!("comp-{}", c.name) == id
+        }) {
+            if let Some(pose) = pose {
+                comp.pose_cg = Some(pose.to_pose_string_like(comp.pose_cg.as_deref()));
+            }
+            if let Some(name) = patch.name {
+                // Scene objects without a hwid are identified by name; keep
+                // the ID they were imported with
+                comp.hwid.get_or_insert_with(|| id.clone());
+                comp.name = name;
+            }
+            if let Some(board) = patch.board {
+                comp.board = Some(board);
+            }
+            if let Some(enabled) = patch.enabled {
+                comp.enabled = (!enabled).then_some(false);
+            }
+        } else {
+            // Not in the HCDF yet; the patched registry entry has everything
+            hcdf.upsert_device(&registered, None);
+            data.provenance.mark_mcu_edited(&id);
+        }
+    }).await;
+    state.schedule_save_hcdf();
+
+    Json(device).into_response()
+}
+
+/// Get the effective configuration (defaults applied, secrets redacted)
 pub async fn get_config(
     State(state): State<Arc<AppState>>,
 ) -> impl IntoResponse {
-    Json(state.config.clone())
+    Json(state.config.redacted())
 }
 
-/// Network interface info for the UI
-#[derive(Serialize)]
-pub struct NetworkInterface {
-    pub name: String,
-    pub ip: String,
+/// First-run setup status, with interfaces and subnets to offer
+///
+/// GET /api/setup
+pub async fn get_setup(
+    State(state): State<Arc<AppState>>,
+    Extension(setup): Extension<Arc<SetupState>>,
+) -> impl IntoResponse {
+    let interfaces = setup::network_interfaces();
+    let suggested_subnets = setup::suggest_subnets(&interfaces);
+    Json(serde_json::json!({
+        "required": setup.is_required(),
+        "config_path": setup.path().display().to_string(),
+        "interfaces": interfaces,
+        "suggested_subnets": suggested_subnets,
+        "config": state.config.redacted(),
+    }))
+}
+
+/// Request for a setup test scan
+#[derive(Deserialize)]
+pub struct TestScanRequest {
     pub subnet: String,
     pub prefix_len: u8,
 }
 
+/// Scan a candidate subnet and return what was found
+///
+/// POST /api/setup/scan
+///
+/// Devices also arrive over the WebSocket as they are discovered, so the
+/// wizard can show results before the scan completes.
+pub async fn setup_test_scan(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<TestScanRequest>,
+) -> impl IntoResponse {
+    let subnet = match parse_subnet(&req.subnet, req.prefix_len) {
+        Ok(subnet) => subnet,
+        Err(message) => return (StatusCode::BAD_REQUEST, Json(ApiError::new(message))).into_response(),
+    };
+
+    info!(subnet = %subnet, "Setup test scan");
+    state.scanner.update_subnet(subnet).await;
+
+    match state.scanner.scan_once().await {
+        Ok(devices) => {
+            let summaries: Vec<DeviceSummary> = devices.iter().map(DeviceSummary::from).collect();
+            Json(serde_json::json!({
+                "status": "completed",
+                "devices": summaries,
+            }))
+            .into_response()
+        }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiError::new(format!("Scan failed: {}", e))),
+        )
+            .into_response(),
+    }
+}
+
+/// Dismiss the setup wizard
+///
+/// POST /api/setup/skip
+///
+/// Writes the defaults if there is still no configuration file, so the
+/// wizard is not offered again on the next start.
+pub async fn skip_setup(
+    Extension(setup): Extension<Arc<SetupState>>,
+) -> impl IntoResponse {
+    if !setup.path().exists() {
+        let defaults = serde_json::to_value(crate::config::Config::default()).unwrap_or_default();
+        if let Err(e) = setup::write_config(setup.path(), &defaults) {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiError::new(e.to_string())),
+            )
+                .into_response();
+        }
+        info!(path = %setup.path().display(), "Setup skipped, wrote default configuration");
+    }
+    setup.complete();
+    Json(serde_json::json!({ "status": "skipped" })).into_response()
+}
+
+/// Validate, persist and apply a new configuration
+///
+/// PUT /api/config
+///
+/// Open to anyone while the setup wizard is offered; afterwards only an
+/// operator token may change the configuration (403 otherwise).
+/// Redacted placeholders keep their running values. Every problem is
+/// returned at once (422, with `issues`). Discovery settings and the static
+/// API token are applied immediately and a scan is started; `restart_required` reports
+/// whether anything else changed that is only read at startup.
+pub async fn put_config(
+    State(state): State<Arc<AppState>>,
+    Extension(setup): Extension<Arc<SetupState>>,
+    Extension(auth): Extension<Arc<AuthState>>,
+    role: Option<Extension<SessionRole>>,
+    Json(body): Json<serde_json::Value>,
+) -> impl IntoResponse {
+    let role = role.map(|Extension(role)| role);
+    if !setup.allows_config_write(role) {
+        warn!(target: "audit", role = ?role, "Refused configuration change after setup");
+        return (
+            StatusCode::FORBIDDEN,
+            Json(ApiError::new("Setup is complete; changing the configuration needs an operator token")),
+        )
+            .into_response();
+    }
+    let body = setup::restore_redacted(&body, &state.config);
+    let config = match setup::write_config(setup.path(), &body) {
+        Ok(config) => config,
+        Err(ConfigWriteError::Invalid(errors)) => {
+            // Line numbers refer to the generated TOML, not the request
+            let issues: Vec<_> = errors
+                .0
+                .iter()
+                .map(|issue| serde_json::json!({ "key": issue.key, "message": issue.message }))
+                .collect();
+            return (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(serde_json::json!({
+                    "error": "Invalid configuration",
+                    "issues": issues,
+                })),
+            )
+                .into_response();
+        }
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiError::new(e.to_string())),
+            )
+                .into_response();
+        }
+    };
+
+    info!(path = %setup.path().display(), "Configuration written");
+    setup.complete();
+
+    // Restart discovery with the new settings
+    state.scanner.set_config(config.to_scanner_config()).await;
+    auth.set_api_token(config.daemon.auth_token.clone(), config.daemon.auth_token_reads).await;
+    let scanner = state.scanner.clone();
+    tokio::spawn(async move {
+        if let Err(e) = scanner.scan_once().await {
+            warn!(error = %e, "Scan after configuration change failed");
+        }
+    });
+
+    Json(serde_json::json!({
+        "status": "saved",
+        "path": setup.path().display().to_string(),
+        "restart_required": setup::restart_required(&state.config, &config),
+    }))
+    .into_response()
+}
+
 /// List available network interfaces
 pub async fn list_interfaces() -> impl IntoResponse {
-    use network_interface::{NetworkInterface as NI, NetworkInterfaceConfig};
-
-    let interfaces: Vec<NetworkInterface> = NI::show()
-        .unwrap_or_default()
-        .into_iter()
-        .filter_map(|iface| {
-            // Find IPv4 address
-            iface.addr.iter().find_map(|addr| {
-                if let network_interface::Addr::V4(v4) = addr {
-                    let ip = v4.ip;
-                    let prefix = v4.netmask.map(|m| {
-                        // Count bits in netmask
-                        u32::from(m).count_ones() as u8
-                    }).unwrap_or(24);
-
-                    // Calculate subnet base address
-                    let ip_u32 = u32::from(ip);
-                    let mask = if prefix == 0 { 0 } else { !0u32 << (32 - prefix) };
-                    let subnet_u32 = ip_u32 & mask;
-                    let subnet = std::net::Ipv4Addr::from(subnet_u32);
-
-                    Some(NetworkInterface {
-                        name: iface.name.clone(),
-                        ip: ip.to_string(),
-                        subnet: subnet.to_string(),
-                        prefix_len: prefix,
-                    })
-                } else {
-                    None
-                }
-            })
-        })
-        .filter(|iface| {
-            // Filter out loopback and docker
-            !iface.name.starts_with("lo")
-                && !iface.name.starts_with("docker")
-                && !iface.name.starts_with("br-")
-                && !iface.name.starts_with("veth")
-                && iface.ip != "127.0.0.1"
-        })
-        .collect();
+    Json(setup::network_interfaces())
+}
 
-    Json(interfaces)
+/// A subnet from a request's address and prefix length, or why it is invalid
+fn parse_subnet(subnet: &str, prefix_len: u8) -> Result<IpNet, String> {
+    let addr: std::net::IpAddr = subnet.parse().map_err(|_| "Invalid subnet address".to_string())?;
+    IpNet::new(addr, prefix_len).map_err(|e| format!("Invalid subnet: {}", e))
 }
 
-/// Request to update scan subnet
+/// Request to update scan subnet (IPv4, or IPv6 for neighbor discovery)
 #[derive(Deserialize)]
 pub struct UpdateSubnetRequest {
     pub subnet: String,
@@ -252,61 +970,355 @@ pub async fn update_subnet(
     State(state): State<Arc<AppState>>,
     Json(req): Json<UpdateSubnetRequest>,
 ) -> impl IntoResponse {
-    use std::net::Ipv4Addr;
-
     // Parse the subnet
-    let subnet: Ipv4Addr = match req.subnet.parse() {
-        Ok(ip) => ip,
-        Err(_) => {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(ApiError::new("Invalid subnet address")),
-            )
-                .into_response();
-        }
+    let subnet = match parse_subnet(&req.subnet, req.prefix_len) {
+        Ok(subnet) => subnet,
+        Err(message) => return (StatusCode::BAD_REQUEST, Json(ApiError::new(message))).into_response(),
     };
 
-    info!(subnet = %subnet, prefix = req.prefix_len, "Updating scan subnet");
+    info!(subnet = %subnet, "Updating scan subnet");
 
     // Update scanner config
-    state.scanner.update_subnet(subnet, req.prefix_len).await;
+    state.scanner.update_subnet(subnet).await;
 
     Json(serde_json::json!({
         "status": "updated",
-        "subnet": subnet.to_string(),
+        "subnet": subnet.addr().to_string(),
         "prefix_len": req.prefix_len
     }))
     .into_response()
 }
 
-/// Request to toggle heartbeat (connection checking)
-#[derive(Deserialize)]
-pub struct HeartbeatRequest {
-    pub enabled: bool,
+/// Request to toggle heartbeat (connection checking)
+#[derive(Deserialize)]
+pub struct HeartbeatRequest {
+    pub enabled: bool,
+}
+
+/// Enable or disable heartbeat connection checking
+pub async fn set_heartbeat(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<HeartbeatRequest>,
+) -> impl IntoResponse {
+    info!(enabled = req.enabled, "Setting heartbeat checking");
+    state.scanner.set_heartbeat_enabled(req.enabled).await;
+
+    Json(serde_json::json!({
+        "status": "updated",
+        "heartbeat_enabled": req.enabled
+    }))
+}
+
+/// Get heartbeat status
+pub async fn get_heartbeat(
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    let enabled = state.scanner.is_heartbeat_enabled().await;
+    Json(serde_json::json!({
+        "heartbeat_enabled": enabled
+    }))
+}
+
+/// Role of the calling session
+///
+/// Requests without a valid token are operators; the web UI's
+/// `?spectator=1` mode is client-side only. `auth_required` is set when
+/// session tokens are required or `daemon.auth_token` guards the API.
+pub async fn get_session(
+    Extension(auth): Extension<Arc<AuthState>>,
+    role: Option<Extension<SessionRole>>,
+) -> impl IntoResponse {
+    let role = role.map(|Extension(role)| role).unwrap_or_default();
+    Json(serde_json::json!({
+        "role": role,
+        "read_only": role.is_read_only(),
+        "auth_required": auth.guards_api().await,
+    }))
+}
+
+/// Daemon version, build and protocol information
+///
+/// GET /api/version
+pub async fn get_version() -> impl IntoResponse {
+    Json(VersionInfo::current())
+}
+
+/// Request to override a device's heartbeat method
+#[derive(Deserialize)]
+pub struct HeartbeatMethodRequest {
+    /// "arp", "icmp", "smp-echo" or "tcp-connect:<port>"; null to auto-select
+    #[serde(default)]
+    pub method: Option<String>,
+}
+
+/// Set the heartbeat method for a device
+///
+/// PUT /api/devices/:id/heartbeat
+pub async fn set_device_heartbeat_method(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Json(req): Json<HeartbeatMethodRequest>,
+) -> impl IntoResponse {
+    let method = match req.method.as_deref().map(str::parse::<HeartbeatMethod>).transpose() {
+        Ok(method) => method,
+        Err(e) => {
+            return (StatusCode::BAD_REQUEST, Json(ApiError::new(e.to_string()))).into_response();
+        }
+    };
+
+    match state.scanner.set_heartbeat_method(&id, method).await {
+        Some(device) => Json(serde_json::json!({
+            "status": "updated",
+            "device_id": id,
+            "method": HeartbeatMethod::effective_for(&device),
+            "auto": method.is_none()
+        }))
+        .into_response(),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(ApiError::new("Device not found")),
+        )
+            .into_response(),
+    }
+}
+
+/// Latency history response
+#[derive(Serialize)]
+pub struct LatencyResponse {
+    pub device_id: String,
+    /// Heartbeat method in effect
+    pub method: HeartbeatMethod,
+    /// Whether the method was auto-selected
+    pub auto: bool,
+    /// Samples from oldest to newest
+    pub samples: Vec<LatencySample>,
+}
+
+/// Get the rolling heartbeat latency history for a device
+///
+/// GET /api/devices/:id/latency
+pub async fn get_device_latency(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let Some(device) = state.get_device(&id).await else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(ApiError::new("Device not found")),
+        )
+            .into_response();
+    };
+
+    Json(LatencyResponse {
+        device_id: id.clone(),
+        method: HeartbeatMethod::effective_for(&device),
+        auto: device.discovery.heartbeat_method.is_none(),
+        samples: state.scanner.latency_history(&id).await,
+    })
+    .into_response()
+}
+
+/// List a device's firmware image slots
+///
+/// GET /api/devices/:id/images
+///
+/// Devices that don't answer or have no image management group give an
+/// empty list with the reason in `error`.
+pub async fn get_device_images(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let Some(device) = state.get_device(&id).await else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(ApiError::new("Device not found")),
+        )
+            .into_response();
+    };
+    if device.is_can() {
+        return Json(DeviceImagesResponse {
+            device_id: id,
+            images: Vec::new(),
+            error: Some("CAN devices have no MCUmgr image slots".to_string()),
+        })
+        .into_response();
+    }
+
+    Json(crate::ota::device_images(&id, device.discovery.socket_addr()).await).into_response()
+}
+
+/// Confirm a device's image so MCUboot keeps it across resets
+///
+/// POST /api/devices/:id/images/confirm
+///
+/// Without a hash (`{}`) the running image is confirmed, if it booted for
+/// test. Answers with the image slots after confirming.
+pub async fn confirm_device_image(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Json(req): Json<ImageConfirmRequest>,
+) -> impl IntoResponse {
+    use dendrite_mcumgr::QueryError;
+
+    let Some(device) = state.get_device(&id).await else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(ApiError::new("Device not found")),
+        )
+            .into_response();
+    };
+    if device.is_can() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ApiError::new("CAN devices have no MCUmgr image slots")),
+        )
+            .into_response();
+    }
+
+    let addr = device.discovery.socket_addr();
+    let hash = match req.hash {
+        Some(hash) => hash,
+        None => match crate::ota::device_images(&id, addr).await.unconfirmed_active() {
+            Some(active) => active.hash.clone(),
+            None => {
+                return (
+                    StatusCode::CONFLICT,
+                    Json(ApiError::new("No unconfirmed image to confirm")),
+                )
+                    .into_response()
+            }
+        },
+    };
+
+    info!(device = %id, hash = %hash, "Confirming image");
+    match dendrite_mcumgr::confirm_image(addr, &hash).await {
+        Ok(()) => Json(crate::ota::device_images(&id, addr).await).into_response(),
+        Err(e) => {
+            let status = match e {
+                QueryError::Unsupported { .. } => StatusCode::NOT_IMPLEMENTED,
+                QueryError::Rejected { .. } | QueryError::QueryFailed(_) => StatusCode::CONFLICT,
+                _ => StatusCode::SERVICE_UNAVAILABLE,
+            };
+            (status, Json(ApiError::new(format!("Confirm failed: {}", e)))).into_response()
+        }
+    }
+}
+
+/// Run a Zephyr shell command on a device, for debugging bring-up
+///
+/// POST /api/devices/:id/shell
+///
+/// Only commands listed in `[shell] allowed_commands` are run; anything else
+/// is refused with 403 before the device is contacted. A command the shell
+/// itself rejects still answers 200, with its return code and message.
+pub async fn run_device_shell(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Json(req): Json<ShellRequest>,
+) -> impl IntoResponse {
+    use dendrite_mcumgr::QueryError;
+
+    let command = req.command.split_whitespace().collect::<Vec<_>>().join(" ");
+    if !state.config.shell.allows(&command) {
+        warn!(device = %id, command = %command, "Refused shell command not on the allowlist");
+        return (
+            StatusCode::FORBIDDEN,
+            Json(ApiError::new(format!(
+                "Shell command '{}' is not allowed; permitted commands are listed in [shell] allowed_commands",
+                command
+            ))),
+        )
+            .into_response();
+    }
+
+    let Some(device) = state.get_device(&id).await else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(ApiError::new("Device not found")),
+        )
+            .into_response();
+    };
+    if device.is_can() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ApiError::new("CAN devices have no MCUmgr shell")),
+        )
+            .into_response();
+    }
+
+    info!(device = %id, command = %command, "Running shell command");
+    let timeout = std::time::Duration::from_millis(state.config.shell.timeout_ms);
+    match dendrite_mcumgr::exec_shell(device.discovery.socket_addr(), &command, timeout).await {
+        Ok(output) => Json(ShellResponse {
+            device_id: id,
+            command,
+            ret: output.ret,
+            output: output.output,
+        })
+        .into_response(),
+        Err(e) => {
+            let status = match e {
+                QueryError::Unsupported { .. } => StatusCode::NOT_IMPLEMENTED,
+                QueryError::Rejected { .. } | QueryError::QueryFailed(_) => StatusCode::CONFLICT,
+                _ => StatusCode::SERVICE_UNAVAILABLE,
+            };
+            (status, Json(ApiError::new(format!("Shell command failed: {}", e)))).into_response()
+        }
+    }
+}
+
+/// Query parameters for toggling SMP tracing
+#[derive(Deserialize)]
+pub struct TraceQuery {
+    #[serde(default = "default_trace_enable")]
+    pub enable: bool,
+    /// `log` (default) or `pcap`
+    #[serde(default)]
+    pub format: TraceFormat,
+}
+
+fn default_trace_enable() -> bool {
+    true
 }
 
-/// Enable or disable heartbeat connection checking
-pub async fn set_heartbeat(
+/// Enable or disable SMP frame tracing for a device
+///
+/// POST /api/devices/:id/trace?enable=true&format=pcap
+pub async fn set_device_trace(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<HeartbeatRequest>,
+    Path(id): Path<String>,
+    Query(query): Query<TraceQuery>,
 ) -> impl IntoResponse {
-    info!(enabled = req.enabled, "Setting heartbeat checking");
-    state.scanner.set_heartbeat_enabled(req.enabled).await;
+    let Some(device) = state.get_device(&id).await else {
+        return (StatusCode::NOT_FOUND, Json(ApiError::new("Device not found"))).into_response();
+    };
 
-    Json(serde_json::json!({
-        "status": "updated",
-        "heartbeat_enabled": req.enabled
-    }))
-}
+    if !query.enable {
+        let session = state.tracer.disable(&id).await;
+        return Json(serde_json::json!({
+            "status": if session.is_some() { "disabled" } else { "not_tracing" },
+            "device_id": id,
+            "session": session
+        }))
+        .into_response();
+    }
 
-/// Get heartbeat status
-pub async fn get_heartbeat(
-    State(state): State<Arc<AppState>>,
-) -> impl IntoResponse {
-    let enabled = state.scanner.is_heartbeat_enabled().await;
-    Json(serde_json::json!({
-        "heartbeat_enabled": enabled
-    }))
+    match state.tracer.enable(&id, device.discovery.ip, query.format).await {
+        Ok(session) => Json(serde_json::json!({
+            "status": "enabled",
+            "device_id": id,
+            "session": session
+        }))
+        .into_response(),
+        Err(e) => {
+            warn!(device = %id, error = %e, "Failed to start SMP trace");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiError::new(format!("Failed to start trace: {}", e))),
+            )
+                .into_response()
+        }
+    }
 }
 
 // ============================================================================
@@ -321,6 +1333,22 @@ pub struct UpdatePositionRequest {
     /// Optional orientation in radians: [roll, pitch, yaw]
     #[serde(default)]
     pub orientation: Option<[f64; 3]>,
+    /// Client sequence number; a pose older than the last one the same client
+    /// applied to the device is discarded, so requests arriving out of order
+    /// can't undo a drag
+    #[serde(default)]
+    pub seq: Option<u64>,
+    /// Client session `seq` counts in; other sessions' numbers are unrelated
+    #[serde(default)]
+    pub client: Option<String>,
+}
+
+/// Why a pose update wasn't applied
+#[derive(Debug)]
+enum PoseSkipped {
+    NotFound,
+    /// Its sequence number is not newer than the last one its client applied
+    Stale { latest: u64 },
 }
 
 /// Update device position and orientation
@@ -331,116 +1359,463 @@ pub async fn update_device_position(
     Path(id): Path<String>,
     Json(req): Json<UpdatePositionRequest>,
 ) -> impl IntoResponse {
-    tracing::warn!(device = %id, position = ?req.position, orientation = ?req.orientation, "Updating device position");
-
-    // Build pose array: [x, y, z, roll, pitch, yaw]
-    let pose = match req.orientation {
-        Some([roll, pitch, yaw]) => [req.position[0], req.position[1], req.position[2], roll, pitch, yaw],
-        None => [req.position[0], req.position[1], req.position[2], 0.0, 0.0, 0.0],
-    };
+    debug!(device = %id, position = ?req.position, orientation = ?req.orientation, seq = ?req.seq, client = ?req.client, "Updating device position");
 
-    // Get the device from scanner
-    let device_id = DeviceId::from_hwid(&id);
-    let device = match state.scanner.get_device(&device_id).await {
-        Some(d) => d,
-        None => {
+    let pose = match apply_device_pose(&state, &id, &req).await {
+        Ok(pose) => pose,
+        Err(PoseSkipped::NotFound) => {
             return (
                 StatusCode::NOT_FOUND,
                 Json(ApiError::new("Device not found")),
             )
                 .into_response();
         }
+        Err(PoseSkipped::Stale { latest }) => {
+            debug!(device = %id, seq = ?req.seq, latest, "Discarding stale position update");
+            return Json(serde_json::json!({
+                "status": "stale",
+                "device_id": id,
+                "seq": req.seq,
+                "latest_seq": latest
+            }))
+            .into_response();
+        }
     };
 
-    // Update device pose and push back to scanner
-    let mut updated_device = device;
-    updated_device.pose = Some(pose);
-    state.scanner.update_device_silent(updated_device.clone()).await;
+    // Persist position changes once the edits settle
+    state.schedule_save_hcdf();
 
-    // Update pose_cg in the HCDF MCU element
-    {
-        let mut hcdf = state.hcdf.write().await;
-        let mcu_count = hcdf.mcu.len();
-        tracing::warn!(device_id = %id, mcu_count = mcu_count, "Looking for MCU in HCDF");
+    Json(serde_json::json!({
+        "status": "updated",
+        "device_id": id,
+        "pose": pose,
+        "seq": req.seq
+    }))
+    .into_response()
+}
 
-        // Find MCU by hwid matching device id
-        let mut found = false;
-        for mcu in &mut hcdf.mcu {
-            if let Some(hwid) = &mcu.hwid {
-                tracing::warn!(hwid = %hwid, device_id = %id, "Checking MCU hwid");
-                if hwid == &id {
-                    // Format pose_cg as "x y z roll pitch yaw"
-                    mcu.pose_cg = Some(format!(
-                        "{} {} {} {} {} {}",
-                        pose[0], pose[1], pose[2], pose[3], pose[4], pose[5]
-                    ));
-                    tracing::warn!(mcu = mcu.name, pose_cg = ?mcu.pose_cg, "Updated MCU pose_cg in HCDF");
-                    found = true;
-                    break;
-                }
-            }
+/// A single entry in a batch position update
+#[derive(Deserialize)]
+pub struct DevicePositionUpdate {
+    /// Device ID
+    pub id: String,
+    #[serde(flatten)]
+    pub pose: UpdatePositionRequest,
+}
+
+/// Request to update positions of several devices at once (e.g., group moves)
+#[derive(Deserialize)]
+pub struct BatchPositionRequest {
+    pub updates: Vec<DevicePositionUpdate>,
+}
+
+/// Update positions of multiple devices in one request
+///
+/// PUT /api/devices/positions
+pub async fn update_device_positions(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<BatchPositionRequest>,
+) -> impl IntoResponse {
+    let mut updated = Vec::new();
+    let mut stale = Vec::new();
+    let mut not_found = Vec::new();
+
+    for update in &req.updates {
+        match apply_device_pose(&state, &update.id, &update.pose).await {
+            Ok(_) => updated.push(update.id.clone()),
+            Err(PoseSkipped::Stale { .. }) => stale.push(update.id.clone()),
+            Err(PoseSkipped::NotFound) => not_found.push(update.id.clone()),
+        }
+    }
+
+    if !updated.is_empty() {
+        state.schedule_save_hcdf();
+    }
+
+    debug!(updated = updated.len(), stale = stale.len(), not_found = not_found.len(), "Applied batch position update");
+
+    // Clients stamp a whole batch with one sequence number
+    let seq = req.updates.iter().filter_map(|u| u.pose.seq).max();
+
+    Json(serde_json::json!({
+        "status": "updated",
+        "updated": updated,
+        "stale": stale,
+        "not_found": not_found,
+        "seq": seq
+    }))
+    .into_response()
+}
+
+/// Apply a pose to the scanner registry and the in-memory HCDF, then broadcast it.
+/// Returns the applied pose, or why it was skipped.
+async fn apply_device_pose(
+    state: &AppState,
+    id: &str,
+    req: &UpdatePositionRequest,
+) -> Result<[f64; 6], PoseSkipped> {
+    // Build pose array: [x, y, z, roll, pitch, yaw]
+    let pose = match req.orientation {
+        Some([roll, pitch, yaw]) => [req.position[0], req.position[1], req.position[2], roll, pitch, yaw],
+        None => [req.position[0], req.position[1], req.position[2], 0.0, 0.0, 0.0],
+    };
+
+    // Get the device from scanner
+    let device_id = DeviceId::from_hwid(id);
+    let mut updated_device = state.scanner.get_device(&device_id).await.ok_or(PoseSkipped::NotFound)?;
+
+    // A user-set pose is no longer provisional
+    updated_device.pose = Some(pose);
+    updated_device.pose_provisional = false;
+
+    // Check the sequence number and update pose_cg in the HCDF MCU element
+    // in one mutation, so a newer pose can't land in between
+    let hwid = id.to_string();
+    let seq = req.seq.map(|seq| (req.client.clone().unwrap_or_default(), seq));
+    let name = updated_device.name.clone();
+    let board = updated_device.info.board.clone();
+    let keep_above_ground = updated_device.constraint.keep_above_ground.then_some(true);
+    let stack_on = updated_device.constraint.stack_on.as_ref().map(|id| id.as_str().to_string());
+    let enabled = (!updated_device.enabled).then_some(false);
+    let applied: Result<(), u64> = state.store.update(move |data| {
+        let id = hwid.as_str();
+        if let Some((client, seq)) = &seq {
+            data.pose_seqs.accept(id, client, *seq)?;
         }
+        let hcdf = &mut data.hcdf;
+        let hcdf_pose = Pose::from_array(pose);
 
-        if !found {
+        // Find MCU by hwid matching device id
+        if let Some(mcu) = hcdf.mcu.iter_mut().find(|m| m.hwid.as_deref() == Some(id)) {
+            mcu.pose_cg = Some(hcdf_pose.to_pose_string_like(mcu.pose_cg.as_deref()));
+            mcu.provisional = None;
+        } else {
             // MCU doesn't exist in HCDF yet - create a minimal entry
             // This ensures position is persisted even before full device discovery completes
-            use dendrite_core::hcdf::Mcu;
+            use dendrite_core::Mcu;
             let new_mcu = Mcu {
-                name: updated_device.name.clone(),
-                hwid: Some(id.clone()),
+                name,
+                hwid: Some(id.to_string()),
+                provisional: None,
+                keep_above_ground,
+                stack_on,
+                enabled,
                 description: None,
-                pose_cg: Some(format!(
-                    "{} {} {} {} {} {}",
-                    pose[0], pose[1], pose[2], pose[3], pose[4], pose[5]
-                )),
+                pose_cg: Some(hcdf_pose.to_pose_string()),
+                bbox: None,
                 mass: None,
-                board: updated_device.info.board.clone(),
+                board,
                 software: None,
                 discovered: None,
                 model: None,
                 visual: Vec::new(),
                 frame: Vec::new(),
                 network: None,
+                port: Vec::new(),
             };
             hcdf.mcu.push(new_mcu);
-            tracing::warn!(device_id = %id, mcu_count = mcu_count, "Created new MCU in HCDF with position");
+            debug!(device_id = %id, "Created new MCU in HCDF with position");
+        }
+        data.provenance.mark_mcu_edited(id);
+        Ok(())
+    }).await;
+    applied.map_err(|latest| PoseSkipped::Stale { latest })?;
+
+    // Push back to scanner and broadcast the device update via WebSocket
+    state.scanner.update_device_silent(updated_device.clone()).await;
+    state.scanner.broadcast_device_update(updated_device).await;
+
+    Ok(pose)
+}
+
+/// Set a pose on the scanner registry only (the HCDF is already updated) and
+/// broadcast it. Returns `None` for elements the scanner doesn't track.
+async fn set_registry_pose(state: &AppState, id: &str, pose: [f64; 6]) -> Option<Device> {
+    let mut device = state.scanner.get_device(&DeviceId::from_hwid(id)).await?;
+    device.pose = Some(pose);
+    device.pose_provisional = false;
+    state.scanner.update_device_silent(device.clone()).await;
+    state.scanner.broadcast_device_update(device.clone()).await;
+    Some(device)
+}
+
+/// Set the placement limits applied while a device's pose is edited
+///
+/// PUT /api/devices/:id/constraint
+///
+/// Body: `{"keep_above_ground": true, "stack_on": "0x1234"}`; omitted fields
+/// are cleared. The limits are stored on the device's MCU element and
+/// enforced by the frontend, which only reports a saved pose that breaks them.
+pub async fn update_device_constraint(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Json(constraint): Json<PoseConstraint>,
+) -> impl IntoResponse {
+    debug!(device = %id, constraint = ?constraint, "Updating device pose constraint");
+
+    let device_id = DeviceId::from_hwid(&id);
+    if constraint.stack_on.as_ref() == Some(&device_id) {
+        return (StatusCode::BAD_REQUEST, Json(ApiError::new("A device can't be stacked on itself"))).into_response();
+    }
+    let Some(mut device) = state.scanner.get_device(&device_id).await else {
+        return (StatusCode::NOT_FOUND, Json(ApiError::new("Device not found"))).into_response();
+    };
+
+    device.constraint = constraint.clone();
+    state.scanner.update_device_silent(device.clone()).await;
+
+    let stored = constraint.clone();
+    state.store.update(move |data| {
+        if let Some(mcu) = data.hcdf.mcu.iter_mut().find(|m| m.hwid.as_deref() == Some(id.as_str())) {
+            mcu.set_pose_constraint(&stored);
+        }
+    }).await;
+    state.schedule_save_hcdf();
+    state.scanner.broadcast_device_update(device.clone()).await;
+
+    Json(serde_json::json!({
+        "status": "updated",
+        "device_id": device.id,
+        "constraint": constraint
+    }))
+    .into_response()
+}
+
+/// Request to update a port's pose
+#[derive(Deserialize)]
+pub struct UpdatePortPoseRequest {
+    /// Pose relative to the device: [x, y, z, roll, pitch, yaw] (meters, radians)
+    pub pose: [f64; 6],
+}
+
+/// Update the pose of one of a device's ports
+///
+/// PUT /api/devices/:id/ports/:name/pose
+///
+/// The pose from the device's HCDF is kept as `original_pose` so the edit can be reset.
+pub async fn update_port_pose(
+    State(state): State<Arc<AppState>>,
+    Path((id, name)): Path<(String, String)>,
+    Json(req): Json<UpdatePortPoseRequest>,
+) -> impl IntoResponse {
+    debug!(device = %id, port = %name, pose = ?req.pose, "Updating port pose");
+    port_pose_response(&state, &id, &name, Some(req.pose)).await
+}
+
+/// Reset a port's pose to the one from the device's HCDF
+///
+/// DELETE /api/devices/:id/ports/:name/pose
+pub async fn reset_port_pose(
+    State(state): State<Arc<AppState>>,
+    Path((id, name)): Path<(String, String)>,
+) -> impl IntoResponse {
+    debug!(device = %id, port = %name, "Resetting port pose");
+    port_pose_response(&state, &id, &name, None).await
+}
+
+async fn port_pose_response(
+    state: &AppState,
+    id: &str,
+    name: &str,
+    pose: Option<[f64; 6]>,
+) -> axum::response::Response {
+    match apply_port_pose(state, id, name, pose).await {
+        Ok(port) => {
+            state.schedule_save_hcdf();
+            let status = if pose.is_some() { "updated" } else { "reset" };
+            Json(serde_json::json!({
+                "status": status,
+                "device_id": id,
+                "port": port
+            }))
+            .into_response()
+        }
+        Err((status, msg)) => (status, Json(ApiError::new(msg))).into_response(),
+    }
+}
+
+/// Set (`Some`) or reset (`None`) a port pose in the scanner registry and the
+/// in-memory HCDF, then broadcast the device. Returns the updated port.
+async fn apply_port_pose(
+    state: &AppState,
+    id: &str,
+    name: &str,
+    pose: Option<[f64; 6]>,
+) -> Result<DevicePort, (StatusCode, &'static str)> {
+    let device_id = DeviceId::from_hwid(id);
+    let mut device = state
+        .scanner
+        .get_device(&device_id)
+        .await
+        .ok_or((StatusCode::NOT_FOUND, "Device not found"))?;
+    let port = device
+        .ports
+        .iter_mut()
+        .find(|p| p.name == name)
+        .ok_or((StatusCode::NOT_FOUND, "Port not found"))?;
+    if port.mesh_name.is_some() {
+        return Err((StatusCode::BAD_REQUEST, "Port is placed by its mesh and has no pose to edit"));
+    }
+
+    let original = port.original_pose.or(port.pose).unwrap_or_default();
+    match pose {
+        Some(pose) => {
+            port.original_pose = Some(original);
+            port.pose = Some(pose);
+        }
+        None => {
+            port.original_pose = None;
+            port.pose = Some(original);
         }
     }
+    let port = port.clone();
 
-    // Auto-save HCDF to persist position changes
-    if let Err(e) = state.save_hcdf().await {
-        tracing::warn!(error = %e, "Failed to auto-save HCDF after position update");
+    let (hwid, port_name, port_type) = (id.to_string(), name.to_string(), port.port_type.clone());
+    let written = state.store.update(move |data| {
+        let written = match pose {
+            Some(pose) => data.hcdf.set_port_pose(&hwid, &port_name, &port_type, &Pose::from_array(pose)),
+            None => data.hcdf.reset_port_pose(&hwid, &port_name, &Pose::from_array(original)),
+        };
+        if written {
+            data.provenance.mark_mcu_edited(&hwid);
+        }
+        written
+    }).await;
+    if !written {
+        debug!(device = %id, port = %name, "No HCDF entry for port, pose not persisted");
     }
 
-    // Broadcast device update via WebSocket
-    state.scanner.broadcast_device_update(updated_device).await;
+    state.scanner.update_device_silent(device.clone()).await;
+    state.scanner.broadcast_device_update(device).await;
+
+    Ok(port)
+}
+
+/// Query parameters for model override upload and removal
+#[derive(Deserialize)]
+pub struct ModelOverrideQuery {
+    /// Also apply to (or remove from) every device of this device's board
+    #[serde(default)]
+    pub board: bool,
+    /// Uploaded file name, used in the cached file name
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+/// Upload a .glb model to use instead of the one the device's fragment names
+///
+/// PUT /api/devices/:id/model?board=true&name=board.glb
+///
+/// The body is the raw file, limited to `models.max_override_bytes`.
+pub async fn set_model_override(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Query(query): Query<ModelOverrideQuery>,
+    body: Bytes,
+) -> impl IntoResponse {
+    let device = match override_target(&state, &id).await {
+        Ok(device) => device,
+        Err((status, msg)) => return (status, Json(ApiError::new(msg))).into_response(),
+    };
+    // Binary glTF starts with the ASCII magic "glTF"
+    if !body.starts_with(b"glTF") {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ApiError::new("Not a binary glTF (.glb) file")),
+        )
+            .into_response();
+    }
+
+    let board = device.info.board.as_deref().filter(|_| query.board);
+    let name = query.name.as_deref().unwrap_or("model.glb");
+    let model = match state.hcdf_fetcher.set_model_override(&id, board, name, &body).await {
+        Ok(model) => model,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiError::new(format!("{:#}", e))),
+            )
+                .into_response()
+        }
+    };
 
+    let hwid = id.clone();
+    state.store.update(move |data| data.provenance.mark_mcu_edited(&hwid)).await;
+    let refreshed = state.refresh_device_models(&id, board).await;
     Json(serde_json::json!({
         "status": "updated",
         "device_id": id,
-        "pose": pose
+        "board": board,
+        "model": model,
+        "refreshed": refreshed,
+    }))
+    .into_response()
+}
+
+/// Remove a device's model override, and its board's with `?board=true`
+///
+/// DELETE /api/devices/:id/model
+pub async fn remove_model_override(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Query(query): Query<ModelOverrideQuery>,
+) -> impl IntoResponse {
+    let device = match override_target(&state, &id).await {
+        Ok(device) => device,
+        Err((status, msg)) => return (status, Json(ApiError::new(msg))).into_response(),
+    };
+
+    let board = device.info.board.as_deref().filter(|_| query.board);
+    match state.hcdf_fetcher.remove_model_override(&id, board).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(ApiError::new("No model override set")),
+            )
+                .into_response()
+        }
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiError::new(format!("{:#}", e))),
+            )
+                .into_response()
+        }
+    }
+
+    let refreshed = state.refresh_device_models(&id, board).await;
+    Json(serde_json::json!({
+        "status": "removed",
+        "device_id": id,
+        "board": board,
+        "refreshed": refreshed,
     }))
     .into_response()
 }
 
+/// Device whose model override is being changed
+///
+/// Overrides replace fragment models, so the device must have reported the
+/// board and firmware its fragment is resolved from.
+async fn override_target(
+    state: &AppState,
+    id: &str,
+) -> Result<Device, (StatusCode, &'static str)> {
+    let device = state
+        .get_device(id)
+        .await
+        .ok_or((StatusCode::NOT_FOUND, "Device not found"))?;
+    if device.info.board.is_none() || device.firmware.name.is_none() {
+        return Err((StatusCode::BAD_REQUEST, "Device has not reported its board and firmware"));
+    }
+    Ok(device)
+}
+
 // ============================================================================
 // Firmware API Endpoints
 // ============================================================================
 
-/// Firmware check response
-#[derive(Serialize)]
-pub struct FirmwareCheckResponse {
-    pub device_id: String,
-    pub current_version: Option<String>,
-    /// MCUboot image hash from the device (what MCUmgr reports)
-    pub current_mcuboot_hash: Option<String>,
-    pub latest_version: Option<String>,
-    /// MCUboot image hash for the latest release (for verification after OTA)
-    pub latest_mcuboot_hash: Option<String>,
-    pub status: dendrite_core::FirmwareStatus,
-    pub changelog: Option<String>,
-}
-
 /// Check firmware status for a specific device
 ///
 /// GET /api/firmware/:id/check
@@ -485,8 +1860,9 @@ pub async fn check_firmware(
 
     // Get firmware_manifest_uri from HCDF software element
     let firmware_manifest_uri = {
-        let hcdf = state.hcdf.read().await;
-        hcdf.mcu
+        state.snapshot()
+            .hcdf
+            .mcu
             .iter()
             .find(|m| m.hwid.as_deref() == Some(&id))
             .and_then(|m| m.software.as_ref())
@@ -499,6 +1875,7 @@ pub async fn check_firmware(
     let manifest = match state.firmware_fetcher.get_manifest(&board, &app, firmware_manifest_uri.as_deref()).await {
         Ok(Some(m)) => m,
         Ok(None) => {
+            state.scanner.set_firmware_status(&id, dendrite_core::FirmwareStatus::Unknown).await;
             return Json(FirmwareCheckResponse {
                 device_id: id,
                 current_version: device.firmware.version.clone(),
@@ -525,6 +1902,7 @@ pub async fn check_firmware(
         device.firmware.build_date,
         &manifest,
     );
+    state.scanner.set_firmware_status(&id, status.clone()).await;
 
     Json(FirmwareCheckResponse {
         device_id: id,
@@ -569,8 +1947,9 @@ pub async fn check_all_firmware(
 
         // Get firmware_manifest_uri from HCDF software element
         let firmware_manifest_uri = {
-            let hcdf = state.hcdf.read().await;
-            hcdf.mcu
+            state.snapshot()
+                .hcdf
+                .mcu
                 .iter()
                 .find(|m| m.hwid.as_deref() == Some(&id))
                 .and_then(|m| m.software.as_ref())
@@ -595,6 +1974,7 @@ pub async fn check_all_firmware(
                 }
                 _ => (None, None, dendrite_core::FirmwareStatus::Unknown, None),
             };
+        state.scanner.set_firmware_status(&id, status.clone()).await;
 
         results.push(FirmwareCheckResponse {
             device_id: id,
@@ -610,89 +1990,75 @@ pub async fn check_all_firmware(
     Json(results)
 }
 
-// ============================================================================
-// OTA (Over-The-Air) Update API Endpoints
-// ============================================================================
-
-use crate::ota::UpdateState;
-
-/// OTA update start response
-#[derive(Serialize)]
-pub struct OtaStartResponse {
-    pub device_id: String,
-    pub status: String,
-}
-
-/// OTA update progress response
-#[derive(Serialize)]
-pub struct OtaProgressResponse {
-    pub device_id: String,
-    pub state: Option<UpdateState>,
-}
-
-/// Start an OTA firmware update for a device
-///
-/// POST /api/ota/:id/start
-pub async fn start_ota_update(
-    State(state): State<Arc<AppState>>,
-    Path(id): Path<String>,
-) -> impl IntoResponse {
-    // Get device
-    let device = match state.get_device(&id).await {
-        Some(d) => d,
-        None => {
-            return (
-                StatusCode::NOT_FOUND,
-                Json(ApiError::new("Device not found")),
-            )
-                .into_response()
-        }
-    };
-
-    // Need board and app name for firmware fetching
-    let board = match &device.info.board {
-        Some(b) => b.clone(),
-        None => {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(ApiError::new("Device has no board info")),
-            )
-                .into_response()
-        }
-    };
+// ============================================================================
+// OTA (Over-The-Air) Update API Endpoints
+// ============================================================================
 
-    let app = match &device.firmware.name {
-        Some(a) => a.clone(),
-        None => {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(ApiError::new("Device has no app name")),
-            )
-                .into_response()
-        }
-    };
+/// What an OTA update of device `id` needs: its address, board, app and
+/// the manifest URI from its HCDF software element
+async fn ota_target(state: &AppState, id: &str) -> Result<OtaTarget, (StatusCode, String)> {
+    let device = state
+        .get_device(id)
+        .await
+        .ok_or((StatusCode::NOT_FOUND, "Device not found".to_string()))?;
 
-    // Get firmware_manifest_uri from HCDF software element
-    let firmware_manifest_uri = {
-        let hcdf = state.hcdf.read().await;
-        hcdf.mcu
-            .iter()
-            .find(|m| m.hwid.as_deref() == Some(&id))
-            .and_then(|m| m.software.as_ref())
-            .and_then(|s| s.firmware_manifest_uri.clone())
+    // Need board and app name for firmware fetching
+    let board = device
+        .info
+        .board
+        .clone()
+        .ok_or((StatusCode::BAD_REQUEST, "Device has no board info".to_string()))?;
+    let app = device
+        .firmware
+        .name
+        .clone()
+        .ok_or((StatusCode::BAD_REQUEST, "Device has no app name".to_string()))?;
+
+    let firmware_manifest_uri = state
+        .snapshot()
+        .hcdf
+        .mcu
+        .iter()
+        .find(|m| m.hwid.as_deref() == Some(id))
+        .and_then(|m| m.software.as_ref())
+        .and_then(|s| s.firmware_manifest_uri.clone());
+
+    let addr = device.discovery.scoped_addr(dendrite_mcumgr::MCUMGR_PORT);
+    Ok(OtaTarget::new(id.to_string(), addr, board, app, firmware_manifest_uri))
+}
+
+/// Start an OTA firmware update for a device
+///
+/// POST /api/ota/:id/start
+///
+/// With `?dry_run=true` nothing is uploaded: every step before the upload
+/// runs and the device's readiness is returned instead.
+pub async fn start_ota_update(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Query(query): Query<OtaStartQuery>,
+) -> impl IntoResponse {
+    let target = match ota_target(&state, &id).await {
+        Ok(target) => target,
+        Err((status, error)) => return (status, Json(ApiError::new(error))).into_response(),
     };
 
-    info!(device = %id, board = %board, app = %app, uri = ?firmware_manifest_uri, "Starting OTA update");
+    if query.dry_run {
+        info!(device = %id, board = %target.board, app = %target.app, "Checking OTA readiness");
+        return Json(state.ota_service.dry_run(&target).await).into_response();
+    }
+
+    info!(
+        device = %id, board = %target.board, app = %target.app, uri = ?target.firmware_manifest_uri,
+        "Starting OTA update"
+    );
 
     // Start the update (requires explicit firmware_manifest_uri)
-    match state
-        .ota_service
-        .start_update(id.clone(), device.discovery.ip.to_string(), board, app, firmware_manifest_uri)
-        .await
-    {
+    match state.ota_service.start_update(target).await {
         Ok(()) => Json(OtaStartResponse {
             device_id: id,
             status: "started".to_string(),
+            error: None,
         })
         .into_response(),
         Err(e) => (
@@ -703,6 +2069,61 @@ pub async fn start_ota_update(
     }
 }
 
+/// Start OTA updates for several devices
+///
+/// POST /api/ota/batch
+///
+/// Devices are answered in the order given, and one that can't be updated
+/// doesn't hold up the others. With `?dry_run=true` each device's readiness
+/// is returned instead and nothing is uploaded.
+pub async fn start_ota_batch(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<OtaStartQuery>,
+    Json(req): Json<OtaBatchRequest>,
+) -> impl IntoResponse {
+    let mut targets = Vec::with_capacity(req.device_ids.len());
+    for id in req.device_ids {
+        let target = ota_target(&state, &id).await.map_err(|(_, error)| error);
+        targets.push((id, target));
+    }
+
+    if query.dry_run {
+        info!(devices = targets.len(), "Checking OTA readiness of batch");
+        let found: Vec<OtaTarget> = targets.iter().filter_map(|(_, t)| t.as_ref().ok().cloned()).collect();
+        let mut checked = state.ota_service.dry_run_batch(&found).await.into_iter();
+        let readiness: Vec<OtaReadiness> = targets
+            .into_iter()
+            .map(|(id, target)| match target {
+                Ok(_) => checked.next().unwrap_or_else(|| OtaReadiness::would_fail(id, "Not checked")),
+                Err(error) => OtaReadiness::would_fail(id, error),
+            })
+            .collect();
+        return Json(readiness).into_response();
+    }
+
+    info!(devices = targets.len(), "Starting OTA batch update");
+    let mut responses = Vec::with_capacity(targets.len());
+    for (id, target) in targets {
+        let started = match target {
+            Ok(target) => state.ota_service.start_update(target).await.map_err(|e| e.to_string()),
+            Err(error) => Err(error),
+        };
+        responses.push(match started {
+            Ok(()) => OtaStartResponse {
+                device_id: id,
+                status: "started".to_string(),
+                error: None,
+            },
+            Err(error) => OtaStartResponse {
+                device_id: id,
+                status: "failed".to_string(),
+                error: Some(error),
+            },
+        });
+    }
+    Json(responses).into_response()
+}
+
 /// Get OTA update progress for a device
 ///
 /// GET /api/ota/:id/progress
@@ -812,12 +2233,13 @@ pub async fn upload_local_firmware(
     // Start the upload
     match state
         .ota_service
-        .upload_local_firmware(id.clone(), device.discovery.ip.to_string(), firmware_data)
+        .upload_local_firmware(id.clone(), device.discovery.scoped_ip(), firmware_data)
         .await
     {
         Ok(()) => Json(OtaStartResponse {
             device_id: id,
             status: "started".to_string(),
+            error: None,
         })
         .into_response(),
         Err(e) => (
@@ -834,13 +2256,105 @@ pub async fn upload_local_firmware(
 
 /// Export the current HCDF as XML
 ///
-/// GET /api/hcdf/export
+/// GET /api/hcdf/export?exclude_provisional=true&exclude_model_overrides=true
 pub async fn export_hcdf(
     State(state): State<Arc<AppState>>,
+    Query(query): Query<HcdfExportQuery>,
 ) -> impl IntoResponse {
-    let hcdf = state.hcdf.read().await;
+    let snapshot = state.snapshot();
+    let mut hcdf = snapshot.hcdf.clone();
+    hcdf.pin_assembly_origins();
+    if query.exclude_provisional {
+        hcdf.strip_provisional_poses();
+    }
+    if !query.exclude_model_overrides {
+        state.apply_model_overrides(&mut hcdf).await;
+    }
 
-    match hcdf.to_xml() {
+    let xml = if query.annotate {
+        hcdf.to_xml_annotated(&snapshot.provenance)
+    } else {
+        hcdf.to_xml()
+    };
+    match xml {
+        Ok(xml) => (
+            StatusCode::OK,
+            Json(serde_json::json!({ "xml": xml })),
+        )
+            .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiError::new(format!("Failed to export HCDF: {}", e))),
+        )
+            .into_response(),
+    }
+}
+
+/// Query parameters for HCDF export
+#[derive(Deserialize)]
+pub struct HcdfExportQuery {
+    /// Leave out poses suggested by the layout heuristic
+    #[serde(default)]
+    pub exclude_provisional: bool,
+    /// Keep fragment models instead of uploaded model overrides
+    #[serde(default)]
+    pub exclude_model_overrides: bool,
+    /// Add a comment above each element saying where it came from
+    #[serde(default)]
+    pub annotate: bool,
+}
+
+/// Current visibility of a device toggle group in the frontend
+#[derive(Deserialize)]
+pub struct ToggleState {
+    pub device_id: String,
+    pub toggle: String,
+    pub hidden: bool,
+}
+
+/// Request body for HCDF export with frontend view state
+#[derive(Deserialize)]
+pub struct HcdfExportRequest {
+    /// Toggle groups whose state differs from the loaded default
+    #[serde(default)]
+    pub toggles: Vec<ToggleState>,
+    /// Leave out poses suggested by the layout heuristic
+    #[serde(default)]
+    pub exclude_provisional: bool,
+    /// Keep fragment models instead of uploaded model overrides
+    #[serde(default)]
+    pub exclude_model_overrides: bool,
+    /// Add a comment above each element saying where it came from
+    #[serde(default)]
+    pub annotate: bool,
+}
+
+/// Export the current HCDF as XML, recording the frontend's toggle state
+///
+/// POST /api/hcdf/export
+pub async fn export_hcdf_with_view(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<HcdfExportRequest>,
+) -> impl IntoResponse {
+    let snapshot = state.snapshot();
+    let mut hcdf = snapshot.hcdf.clone();
+    for t in &req.toggles {
+        hcdf.set_toggle_state(&t.device_id, &t.toggle, t.hidden);
+    }
+    hcdf.pin_assembly_origins();
+    if req.exclude_provisional {
+        hcdf.strip_provisional_poses();
+    }
+    if !req.exclude_model_overrides {
+        state.apply_model_overrides(&mut hcdf).await;
+    }
+
+    let xml = if req.annotate {
+        hcdf.to_xml_annotated(&snapshot.provenance)
+    } else {
+        hcdf.to_xml()
+    };
+    match xml {
         Ok(xml) => (
             StatusCode::OK,
             Json(serde_json::json!({ "xml": xml })),
@@ -854,6 +2368,36 @@ pub async fn export_hcdf(
     }
 }
 
+/// Download the current HCDF with every model it references as one zip, for
+/// viewing without internet access (see `crate::bundle`)
+///
+/// GET /api/hcdf/bundle
+pub async fn export_hcdf_bundle(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let mut hcdf = state.get_hcdf();
+    hcdf.pin_assembly_origins();
+    state.apply_model_overrides(&mut hcdf).await;
+
+    let static_models = std::path::Path::new(&state.config.models.path);
+    match crate::bundle::build_bundle(&hcdf, &state.hcdf_fetcher, static_models).await {
+        Ok(bundle) => {
+            info!(models = bundle.bundled, missing = bundle.missing.len(), "Exported HCDF bundle");
+            (
+                [
+                    (header::CONTENT_TYPE, "application/zip"),
+                    (header::CONTENT_DISPOSITION, "attachment; filename=\"dendrite.dzip\""),
+                ],
+                bundle.data,
+            )
+                .into_response()
+        }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiError::new(format!("Failed to export bundle: {:#}", e))),
+        )
+            .into_response(),
+    }
+}
+
 /// Request body for HCDF import
 #[derive(Deserialize)]
 pub struct HcdfImportRequest {
@@ -864,111 +2408,304 @@ pub struct HcdfImportRequest {
     pub merge: bool,
 }
 
-/// Import HCDF from XML
-///
-/// POST /api/hcdf/import
-pub async fn import_hcdf(
-    State(state): State<Arc<AppState>>,
-    Json(req): Json<HcdfImportRequest>,
-) -> impl IntoResponse {
-    use dendrite_core::{Hcdf, Device, DeviceId, DeviceStatus, DeviceInfo, FirmwareInfo, parse_pose_string};
-    use dendrite_core::device::{DiscoveryInfo, DiscoveryMethod, DeviceVisual, DeviceFrame};
-    use chrono::{DateTime, Utc};
-    use std::net::IpAddr;
+/// Validate and parse a document for import, answering `400` for one that
+/// can't be imported; returns it with its validation warnings
+fn parse_import(
+    state: &AppState,
+    xml: &str,
+) -> Result<(dendrite_core::Hcdf, Vec<dendrite_core::ValidationIssue>), axum::response::Response> {
+    use dendrite_core::Hcdf;
+
+    // Report every problem in the document at once, with positions
+    let report = match Hcdf::validate(xml) {
+        Ok(report) => report,
+        Err(e) => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ApiError::new(format!("Invalid HCDF XML: {}", e))),
+            )
+                .into_response())
+        }
+    };
+    if report.has_errors() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": format!("Invalid HCDF XML: {} error(s)", report.errors().count()),
+                "issues": report.issues,
+            })),
+        )
+            .into_response());
+    }
+    let validation_warnings: Vec<_> = report.warnings().cloned().collect();
 
     // Parse the incoming HCDF
-    let imported_hcdf = match Hcdf::from_xml(&req.xml) {
+    let imported_hcdf = match Hcdf::from_xml(xml) {
         Ok(h) => h,
         Err(e) => {
-            return (
+            return Err((
                 StatusCode::BAD_REQUEST,
                 Json(ApiError::new(format!("Invalid HCDF XML: {}", e))),
             )
-                .into_response()
+                .into_response())
         }
     };
 
-    // Collect MCUs and Comps to convert to devices
-    let mcus_to_import: Vec<_> = imported_hcdf.mcu.clone();
-    let comps_to_import: Vec<_> = imported_hcdf.comp.clone();
-    let mcu_count = mcus_to_import.len();
-    let comp_count = comps_to_import.len();
+    // Frame parent chains must resolve before anything is merged
+    if let Err(e) = imported_hcdf.validate_frames() {
+        return Err((StatusCode::BAD_REQUEST, Json(ApiError::new(e.to_string()))).into_response());
+    }
 
-    // Update HCDF state - always merge to preserve existing devices
-    {
-        let mut hcdf = state.hcdf.write().await;
+    // Imported assemblies replace same-named ones but may not claim a device
+    // that another assembly already holds
+    let mut merged_assemblies = state.snapshot().hcdf.clone();
+    let assemblies_valid = imported_hcdf.validate_assemblies().and_then(|()| {
+        imported_hcdf
+            .assembly
+            .iter()
+            .try_for_each(|a| merged_assemblies.set_assembly(a.clone()))
+    });
+    if let Err(e) = assemblies_valid {
+        return Err((StatusCode::BAD_REQUEST, Json(ApiError::new(e.to_string()))).into_response());
+    }
 
-        // Merge MCUs by hwid (update if exists, add if new)
-        for mcu in &mcus_to_import {
-            if let Some(hwid) = &mcu.hwid {
-                if let Some(existing) = hcdf.mcu.iter_mut().find(|m| m.hwid.as_deref() == Some(hwid)) {
-                    // Update existing MCU
-                    *existing = mcu.clone();
-                    debug!("Updated existing MCU '{}' (hwid: {})", mcu.name, hwid);
-                } else {
-                    // Add new MCU
-                    hcdf.mcu.push(mcu.clone());
-                    debug!("Added new MCU '{}' (hwid: {})", mcu.name, hwid);
-                }
+    Ok((imported_hcdf, validation_warnings))
+}
+
+/// Merge an imported document into `hcdf` the way `/api/hcdf/import` does:
+/// MCUs and comps replace those with the same hwid (or name, without one),
+/// links, sensors, motors and power sources are added if new, and keep-outs
+/// and assemblies replace same-named ones
+fn merge_imported_hcdf(hcdf: &mut dendrite_core::Hcdf, imported: &dendrite_core::Hcdf) {
+    // Merge MCUs by hwid (update if exists, add if new)
+    for mcu in &imported.mcu {
+        if let Some(hwid) = &mcu.hwid {
+            if let Some(existing) = hcdf.mcu.iter_mut().find(|m| m.hwid.as_deref() == Some(hwid)) {
+                // Update existing MCU
+                *existing = mcu.clone();
+                debug!("Updated existing MCU '{}' (hwid: {})", mcu.name, hwid);
             } else {
-                // MCU without hwid - add by name match or append
-                if let Some(existing) = hcdf.mcu.iter_mut().find(|m| m.name == mcu.name && m.hwid.is_none()) {
-                    *existing = mcu.clone();
-                    debug!("Updated existing MCU '{}' (no hwid)", mcu.name);
-                } else {
-                    hcdf.mcu.push(mcu.clone());
-                    debug!("Added new MCU '{}' (no hwid)", mcu.name);
-                }
+                // Add new MCU
+                hcdf.mcu.push(mcu.clone());
+                debug!("Added new MCU '{}' (hwid: {})", mcu.name, hwid);
+            }
+        } else {
+            // MCU without hwid - add by name match or append
+            if let Some(existing) = hcdf.mcu.iter_mut().find(|m| m.name == mcu.name && m.hwid.is_none()) {
+                *existing = mcu.clone();
+                debug!("Updated existing MCU '{}' (no hwid)", mcu.name);
+            } else {
+                hcdf.mcu.push(mcu.clone());
+                debug!("Added new MCU '{}' (no hwid)", mcu.name);
             }
         }
+    }
 
-        // Merge Comps by hwid or name
-        for comp in &comps_to_import {
-            let comp_key = comp.hwid.as_ref()
-                .map(|h| format!("hwid:{}", h))
-                .unwrap_or_else(|| format!("name:{}", comp.name));
+    // Merge Comps by hwid or name
+    for comp in &imported.comp {
+        let comp_key = comp.hwid.as_ref()
+            .map(|h| format!("hwid:{}", h))
+            .unwrap_or_else(|| format!("name:{}", comp.name));
 
-            let existing = if let Some(hwid) = &comp.hwid {
-                hcdf.comp.iter_mut().find(|c| c.hwid.as_deref() == Some(hwid))
-            } else {
-                hcdf.comp.iter_mut().find(|c| c.name == comp.name && c.hwid.is_none())
-            };
+        let existing = if let Some(hwid) = &comp.hwid {
+            hcdf.comp.iter_mut().find(|c| c.hwid.as_deref() == Some(hwid))
+        } else {
+            hcdf.comp.iter_mut().find(|c| c.name == comp.name && c.hwid.is_none())
+        };
 
-            if let Some(existing) = existing {
-                *existing = comp.clone();
-                debug!("Updated existing comp '{}'", comp_key);
-            } else {
-                hcdf.comp.push(comp.clone());
-                debug!("Added new comp '{}'", comp_key);
-            }
+        if let Some(existing) = existing {
+            *existing = comp.clone();
+            debug!("Updated existing comp '{}'", comp_key);
+        } else {
+            hcdf.comp.push(comp.clone());
+            debug!("Added new comp '{}'", comp_key);
         }
+    }
 
-        // Merge links, sensors, motors, power from imported HCDF
-        for link in &imported_hcdf.link {
-            if !hcdf.link.iter().any(|l| l.name == link.name) {
-                hcdf.link.push(link.clone());
-            }
+    // Merge links, sensors, motors, power from imported HCDF
+    for link in &imported.link {
+        if !hcdf.link.iter().any(|l| l.name == link.name) {
+            hcdf.link.push(link.clone());
         }
-        for sensor in &imported_hcdf.sensor {
-            if !hcdf.sensor.iter().any(|s| s.name == sensor.name) {
-                hcdf.sensor.push(sensor.clone());
-            }
+    }
+    for sensor in &imported.sensor {
+        if !hcdf.sensor.iter().any(|s| s.name == sensor.name) {
+            hcdf.sensor.push(sensor.clone());
         }
-        for motor in &imported_hcdf.motor {
-            if !hcdf.motor.iter().any(|m| m.name == motor.name) {
-                hcdf.motor.push(motor.clone());
-            }
+    }
+    for motor in &imported.motor {
+        if !hcdf.motor.iter().any(|m| m.name == motor.name) {
+            hcdf.motor.push(motor.clone());
+        }
+    }
+    for power in &imported.power {
+        if !hcdf.power.iter().any(|p| p.name == power.name) {
+            hcdf.power.push(power.clone());
+        }
+    }
+    // Keep-out volumes describe the vehicle, so imported ones replace same-named volumes
+    for keepout in &imported.keepout {
+        if let Some(existing) = hcdf.keepout.iter_mut().find(|k| k.name == keepout.name) {
+            *existing = keepout.clone();
+        } else {
+            hcdf.keepout.push(keepout.clone());
+        }
+    }
+    for assembly in &imported.assembly {
+        if let Err(e) = hcdf.set_assembly(assembly.clone()) {
+            warn!("Skipping imported assembly '{}': {}", assembly.name, e);
         }
-        for power in &imported_hcdf.power {
-            if !hcdf.power.iter().any(|p| p.name == power.name) {
-                hcdf.power.push(power.clone());
+    }
+}
+
+/// Result of an import preview
+#[derive(Serialize)]
+pub struct HcdfDiffResponse {
+    /// Changes the import would make to the current HCDF
+    #[serde(flatten)]
+    pub diff: dendrite_core::HcdfDiff,
+    pub validation_warnings: Vec<dendrite_core::ValidationIssue>,
+}
+
+/// Preview an import: diff an uploaded HCDF against the current one
+/// without applying it
+///
+/// POST /api/hcdf/diff
+pub async fn diff_hcdf(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<HcdfImportRequest>,
+) -> impl IntoResponse {
+    let (imported_hcdf, validation_warnings) = match parse_import(&state, &req.xml) {
+        Ok(parsed) => parsed,
+        Err(response) => return response,
+    };
+
+    let current = state.get_hcdf();
+    let mut candidate = current.clone();
+    merge_imported_hcdf(&mut candidate, &imported_hcdf);
+    let diff = current.diff_with(&candidate, &state.config.hcdf.diff_tolerance());
+
+    Json(HcdfDiffResponse { diff, validation_warnings }).into_response()
+}
+
+/// Query parameters for diffing the live HCDF
+#[derive(Deserialize)]
+pub struct HcdfDiffQuery {
+    /// What to compare with; only `saved`, the `[hcdf] path` file, for now
+    pub against: String,
+}
+
+/// Changes between the saved and the live HCDF
+#[derive(Serialize)]
+pub struct HcdfSavedDiffResponse {
+    pub against: String,
+    /// File the live HCDF was compared with
+    pub path: String,
+    #[serde(flatten)]
+    pub diff: dendrite_core::HcdfDiff,
+}
+
+/// Show what changed in the live HCDF since it was last saved
+///
+/// GET /api/hcdf/diff?against=saved
+///
+/// Pose changes within `[hcdf] diff_position_tolerance_mm` and
+/// `diff_angle_tolerance_deg` are left out. With no saved file yet every
+/// device is reported as added.
+pub async fn diff_hcdf_against(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<HcdfDiffQuery>,
+) -> impl IntoResponse {
+    if query.against != "saved" {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ApiError::new(format!("Unknown diff target '{}' (expected 'saved')", query.against))),
+        )
+            .into_response();
+    }
+
+    let path = std::path::Path::new(&state.config.hcdf.path);
+    let saved = if path.exists() {
+        match dendrite_core::Hcdf::from_file(path) {
+            Ok(hcdf) => hcdf,
+            Err(e) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ApiError::new(format!("Failed to read saved HCDF {}: {}", path.display(), e))),
+                )
+                    .into_response();
             }
         }
+    } else {
+        dendrite_core::Hcdf::new()
+    };
 
-        info!("Merged HCDF data ({} MCUs, {} Comps imported, now {} MCUs, {} Comps total)",
-              mcu_count, comp_count, hcdf.mcu.len(), hcdf.comp.len());
+    let diff = saved.diff_with(&state.get_hcdf(), &state.config.hcdf.diff_tolerance());
+    Json(HcdfSavedDiffResponse {
+        against: query.against,
+        path: path.display().to_string(),
+        diff,
+    })
+    .into_response()
+}
+
+/// Import HCDF from XML
+///
+/// POST /api/hcdf/import
+pub async fn import_hcdf(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<HcdfImportRequest>,
+) -> impl IntoResponse {
+    use dendrite_core::{Device, DeviceId, DeviceInfo, FirmwareInfo, parse_pose_string};
+    use dendrite_core::{CanAddress, DiscoveryInfo, DiscoveryMethod, DeviceVisual, DeviceFrame};
+
+    let (imported_hcdf, validation_warnings) = match parse_import(&state, &req.xml) {
+        Ok(parsed) => parsed,
+        Err(response) => return response,
+    };
+    for warning in &validation_warnings {
+        warn!("Imported HCDF: {}", warning);
+    }
+
+    // Flag sensors whose declared driver doesn't support their type
+    let driver_warnings: Vec<String> = state
+        .fragments
+        .read()
+        .await
+        .drivers()
+        .lint_hcdf(&imported_hcdf)
+        .iter()
+        .map(|lint| lint.to_string())
+        .collect();
+    for warning in &driver_warnings {
+        warn!("Imported HCDF: {}", warning);
     }
 
+    // Collect MCUs and Comps to convert to devices
+    let mcus_to_import: Vec<_> = imported_hcdf.mcu.clone();
+    let comps_to_import: Vec<_> = imported_hcdf.comp.clone();
+    let mcu_count = mcus_to_import.len();
+    let comp_count = comps_to_import.len();
+
+    // Update HCDF state - always merge to preserve existing devices
+    let (mcus, comps) = (mcus_to_import.clone(), comps_to_import.clone());
+    state.store.update(move |data| {
+        let (mcus_to_import, comps_to_import) = (mcus, comps);
+        merge_imported_hcdf(&mut data.hcdf, &imported_hcdf);
+
+        let provenance = &mut data.provenance;
+        for mcu in &mcus_to_import {
+            let key = mcu.hwid.as_deref().unwrap_or(&mcu.name);
+            provenance.record_mcu(key, Provenance::new(ProvenanceSource::Import, None, None));
+        }
+        for comp in &comps_to_import {
+            provenance.record_comp(&comp.name, Provenance::new(ProvenanceSource::Import, None, None));
+        }
+
+        info!("Merged HCDF data ({} MCUs, {} Comps imported, now {} MCUs, {} Comps total)",
+              mcu_count, comp_count, data.hcdf.mcu.len(), data.hcdf.comp.len());
+    }).await;
+
     let mut devices_imported = 0;
 
     // Convert MCUs to Devices and add to scanner (which broadcasts events)
@@ -983,11 +2720,11 @@ pub async fn import_hcdf(
         };
 
         // Parse IP from discovered info
-        let (ip, port, last_seen) = match &mcu.discovered {
+        let (ip, scope_id, port, last_seen) = match &mcu.discovered {
             Some(disc) => {
-                let ip: IpAddr = match disc.ip.parse() {
-                    Ok(ip) => ip,
-                    Err(_) => {
+                let (ip, scope_id) = match DiscoveryInfo::parse_scoped_ip(&disc.ip) {
+                    Some(parsed) => parsed,
+                    None => {
                         info!("Skipping MCU '{}' - invalid IP '{}'", mcu.name, disc.ip);
                         continue;
                     }
@@ -998,7 +2735,7 @@ pub async fn import_hcdf(
                     .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
                     .map(|dt| dt.with_timezone(&Utc))
                     .unwrap_or_else(Utc::now);
-                (ip, port, last_seen)
+                (ip, scope_id, port, last_seen)
             }
             None => {
                 info!("Skipping MCU '{}' - no discovery info", mcu.name);
@@ -1038,12 +2775,20 @@ pub async fn import_hcdf(
             status: DeviceStatus::Unknown, // Will be checked by heartbeat
             discovery: DiscoveryInfo {
                 ip,
+                scope_id,
                 port,
                 switch_port: mcu.discovered.as_ref().and_then(|d| d.port),
                 mac: None,
                 first_seen: last_seen,
                 last_seen,
                 discovery_method: DiscoveryMethod::Manual,
+                heartbeat_method: None,
+                can: mcu
+                    .discovered
+                    .as_ref()
+                    .and_then(|d| d.can.as_ref())
+                    .map(|c| CanAddress { bus: c.bus.clone(), node_id: c.node }),
+                hostname: None,
             },
             info,
             firmware,
@@ -1052,6 +2797,9 @@ pub async fn import_hcdf(
             parent_id: None,
             model_path: mcu.model.as_ref().map(|m| m.href.clone()),
             pose,
+            pose_provisional: pose.is_some() && mcu.provisional == Some(true),
+            constraint: mcu.pose_constraint(),
+            enabled: mcu.enabled != Some(false),
             visuals: Vec::new(),
             frames: Vec::new(),
             ports: Vec::new(),
@@ -1096,6 +2844,7 @@ pub async fn import_hcdf(
             DeviceVisual {
                 name: v.name.clone(),
                 toggle: v.toggle.clone(),
+                default_hidden: v.is_default_hidden(),
                 pose: v.pose.as_ref().and_then(|p| parse_pose_string(p)).map(|p| [p.x, p.y, p.z, p.roll, p.pitch, p.yaw]),
                 model_path,
                 model_sha: v.model.as_ref().and_then(|m| m.sha.clone()),
@@ -1103,13 +2852,7 @@ pub async fn import_hcdf(
         }).collect();
 
         // Convert comp frames to device frames
-        let frames: Vec<DeviceFrame> = comp.frame.iter().map(|f| {
-            DeviceFrame {
-                name: f.name.clone(),
-                description: f.description.clone(),
-                pose: f.pose.as_ref().and_then(|p| parse_pose_string(p)).map(|p| [p.x, p.y, p.z, p.roll, p.pitch, p.yaw]),
-            }
-        }).collect();
+        let frames: Vec<DeviceFrame> = convert_frames(&comp.frame);
 
         // Parse pose from pose_cg string
         let pose: Option<[f64; 6]> = comp.pose_cg.as_ref().and_then(|s| {
@@ -1123,12 +2866,16 @@ pub async fn import_hcdf(
             status: DeviceStatus::Offline, // Static scene object - use Offline so it can be deleted
             discovery: DiscoveryInfo {
                 ip: "127.0.0.1".parse().unwrap(), // Placeholder - not a real device
+                scope_id: None,
                 port: 0,
                 switch_port: None,
                 mac: None,
                 first_seen: now,
                 last_seen: now,
                 discovery_method: DiscoveryMethod::Manual,
+                heartbeat_method: None,
+                can: None,
+                hostname: None,
             },
             info: DeviceInfo {
                 os_name: None,
@@ -1143,9 +2890,12 @@ pub async fn import_hcdf(
             parent_id: None,
             model_path: comp.model.as_ref().map(|m| m.href.clone()),
             pose,
+            pose_provisional: false,
+            constraint: PoseConstraint::default(),
+            enabled: comp.enabled != Some(false),
             visuals,
             frames,
-            ports: Vec::new(), // TODO: Convert comp.port if needed
+            ports: comp.port.iter().map(convert_port).collect(),
             sensors: Vec::new(), // TODO: Convert comp.sensor if needed
         };
 
@@ -1160,7 +2910,9 @@ pub async fn import_hcdf(
         "merge": req.merge,
         "mcu_count": mcu_count,
         "comp_count": comp_count,
-        "devices_imported": devices_imported
+        "devices_imported": devices_imported,
+        "driver_warnings": driver_warnings,
+        "validation_warnings": validation_warnings,
     }))
     .into_response()
 }
@@ -1183,9 +2935,7 @@ pub async fn save_hcdf_to_server(
     use std::path::PathBuf;
     use tokio::fs;
 
-    let hcdf = state.hcdf.read().await;
-
-    let xml = match hcdf.to_xml() {
+    let xml = match state.snapshot().hcdf.to_xml_with(RuntimeFields::Omit) {
         Ok(xml) => xml,
         Err(e) => {
             return (
@@ -1238,3 +2988,46 @@ pub async fn save_hcdf_to_server(
         }
     }
 }
+
+// ============================================================================
+// HCDF Regeneration API Endpoints
+// ============================================================================
+
+use crate::regenerate::RegenerationError;
+
+/// Request body for applying a regeneration preview
+#[derive(Deserialize)]
+pub struct RegenerateApplyRequest {
+    /// Token returned by the preview
+    pub token: String,
+}
+
+/// Re-query every online device for its HCDF and preview the merged result
+///
+/// POST /api/hcdf/regenerate
+///
+/// Nothing is changed until the preview is applied. Offline devices keep
+/// their entries and are listed as stale.
+pub async fn regenerate_hcdf(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    Json(state.preview_regeneration().await)
+}
+
+/// Apply a regeneration preview
+///
+/// POST /api/hcdf/regenerate/apply
+pub async fn apply_hcdf_regeneration(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<RegenerateApplyRequest>,
+) -> impl IntoResponse {
+    match state.apply_regeneration(&req.token).await {
+        Ok(updated) => Json(serde_json::json!({
+            "status": "applied",
+            "devices": updated,
+        }))
+        .into_response(),
+        Err(e @ RegenerationError::NoPending) => {
+            (StatusCode::NOT_FOUND, Json(ApiError::new(e.to_string()))).into_response()
+        }
+        Err(e) => (StatusCode::CONFLICT, Json(ApiError::new(e.to_string()))).into_response(),
+    }
+}
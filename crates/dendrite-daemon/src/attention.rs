@@ -0,0 +1,626 @@
+//! Attention inbox
+//!
+//! Analyzers look at the registry and the published HCDF state and report
+//! what needs a person: outdated firmware, devices sharing an IP address,
+//! keep-out intrusions, devices that went offline. Each finding has a stable
+//! key (kind, device and subject), so a problem that is detected again
+//! updates its existing entry instead of adding another. Findings the
+//! analyzers stop reporting are resolved automatically; one that comes back
+//! reopens with its occurrence count bumped.
+//!
+//! Acknowledging a finding keeps it listed but out of the open count, until
+//! it clears or gets more severe. The inbox is saved to `attention.path` so
+//! acknowledgements survive restarts, and every change is broadcast for the
+//! WebSocket stream.
+
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use dendrite_core::{Device, DeviceStatus, FirmwareStatus, KeepoutViolation};
+use dendrite_discovery::{DiscoveryEvent, DiscoveryScanner, SocketKind, SocketStatus};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, Mutex};
+use tracing::{debug, info, warn};
+
+use crate::config::AttentionConfig;
+use crate::store::StateStore;
+
+/// Changes are analyzed at most this often
+const ANALYZE_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Info,
+    Warning,
+    Critical,
+}
+
+/// Which analyzer raised a finding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingKind {
+    FirmwareOutdated,
+    IpConflict,
+    KeepoutViolation,
+    DeviceOffline,
+    /// A discovery method runs degraded without CAP_NET_RAW
+    RawSocketUnavailable,
+}
+
+impl FindingKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::FirmwareOutdated => "firmware_outdated",
+            Self::IpConflict => "ip_conflict",
+            Self::KeepoutViolation => "keepout_violation",
+            Self::DeviceOffline => "device_offline",
+            Self::RawSocketUnavailable => "raw_socket_unavailable",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingState {
+    Open,
+    Acknowledged,
+    /// No longer detected
+    Resolved,
+}
+
+/// What an analyzer reports on one pass
+#[derive(Debug, Clone, PartialEq)]
+pub struct Detection {
+    pub kind: FindingKind,
+    pub severity: Severity,
+    /// Affected device ID
+    pub device: Option<String>,
+    /// What distinguishes findings of the same kind on one device (the
+    /// keep-out volume, the shared address)
+    pub subject: String,
+    pub message: String,
+}
+
+impl Detection {
+    /// Coalescing key
+    fn key(&self) -> String {
+        format!("{}:{}:{}", self.kind.as_str(), self.device.as_deref().unwrap_or(""), self.subject)
+    }
+}
+
+/// An inbox entry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Finding {
+    /// Stable key; the same problem always gets the same ID
+    pub id: String,
+    pub kind: FindingKind,
+    pub severity: Severity,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+    pub message: String,
+    pub state: FindingState,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    /// Times the problem has been raised, counting reopenings
+    pub occurrences: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved_at: Option<DateTime<Utc>>,
+}
+
+/// Findings keyed by ID
+#[derive(Debug, Default)]
+pub struct Inbox {
+    findings: BTreeMap<String, Finding>,
+}
+
+impl Inbox {
+    pub fn from_findings(findings: Vec<Finding>) -> Self {
+        Self { findings: findings.into_iter().map(|f| (f.id.clone(), f)).collect() }
+    }
+
+    /// Merge one analysis pass, returning the findings that changed
+    ///
+    /// Detections matching an existing finding update it; unresolved
+    /// findings that weren't detected are resolved.
+    pub fn apply(&mut self, detections: Vec<Detection>, now: DateTime<Utc>) -> Vec<Finding> {
+        let mut changed = Vec::new();
+        let mut detected = Vec::with_capacity(detections.len());
+
+        for detection in detections {
+            let id = detection.key();
+            detected.push(id.clone());
+            if let Some(finding) = self.findings.get_mut(&id) {
+                let mut updated = finding.message != detection.message || finding.severity != detection.severity;
+                if finding.state == FindingState::Resolved {
+                    finding.state = FindingState::Open;
+                    finding.occurrences += 1;
+                    finding.resolved_at = None;
+                    updated = true;
+                } else if finding.state == FindingState::Acknowledged && detection.severity > finding.severity {
+                    // Got worse since it was acknowledged
+                    finding.state = FindingState::Open;
+                }
+                finding.severity = detection.severity;
+                finding.message = detection.message;
+                finding.last_seen = now;
+                if updated {
+                    changed.push(finding.clone());
+                }
+                continue;
+            }
+
+            let finding = Finding {
+                id: id.clone(),
+                kind: detection.kind,
+                severity: detection.severity,
+                device: detection.device,
+                message: detection.message,
+                state: FindingState::Open,
+                first_seen: now,
+                last_seen: now,
+                occurrences: 1,
+                resolved_at: None,
+            };
+            changed.push(finding.clone());
+            self.findings.insert(id, finding);
+        }
+
+        for (id, finding) in self.findings.iter_mut() {
+            if finding.state != FindingState::Resolved && !detected.contains(id) {
+                finding.state = FindingState::Resolved;
+                finding.resolved_at = Some(now);
+                changed.push(finding.clone());
+            }
+        }
+        changed
+    }
+
+    /// Acknowledge an open finding; None if there is no such finding
+    pub fn acknowledge(&mut self, id: &str) -> Option<Finding> {
+        let finding = self.findings.get_mut(id)?;
+        if finding.state == FindingState::Open {
+            finding.state = FindingState::Acknowledged;
+        }
+        Some(finding.clone())
+    }
+
+    /// Drop findings resolved before `cutoff`, returning whether any were
+    pub fn prune(&mut self, cutoff: DateTime<Utc>) -> bool {
+        let before = self.findings.len();
+        self.findings.retain(|_, f| f.resolved_at.is_none_or(|at| at >= cutoff));
+        self.findings.len() != before
+    }
+
+    /// Open findings first, then acknowledged, then resolved; most severe
+    /// and most recent first within each
+    pub fn list(&self) -> Vec<Finding> {
+        let rank = |state: FindingState| match state {
+            FindingState::Open => 0,
+            FindingState::Acknowledged => 1,
+            FindingState::Resolved => 2,
+        };
+        let mut findings: Vec<Finding> = self.findings.values().cloned().collect();
+        findings.sort_by(|a, b| {
+            rank(a.state)
+                .cmp(&rank(b.state))
+                .then(b.severity.cmp(&a.severity))
+                .then(b.last_seen.cmp(&a.last_seen))
+        });
+        findings
+    }
+
+    pub fn open_count(&self) -> usize {
+        self.findings.values().filter(|f| f.state == FindingState::Open).count()
+    }
+}
+
+/// Run every analyzer over the current registry and keep-out state
+pub fn analyze(devices: &[Device], keepout_violations: &[KeepoutViolation]) -> Vec<Detection> {
+    let mut detections = Vec::new();
+    let names: HashMap<&str, &str> = devices.iter().map(|d| (d.id.as_str(), d.name.as_str())).collect();
+
+    for device in devices {
+        if device.status == DeviceStatus::Offline {
+            detections.push(Detection {
+                kind: FindingKind::DeviceOffline,
+                severity: Severity::Warning,
+                device: Some(device.id.0.clone()),
+                subject: String::new(),
+                message: format!("{} is offline", device.name),
+            });
+        }
+        if let FirmwareStatus::UpdateAvailable { latest_version, .. } = &device.firmware_status {
+            let current = device.firmware.version.as_deref().unwrap_or("unknown");
+            detections.push(Detection {
+                kind: FindingKind::FirmwareOutdated,
+                severity: Severity::Info,
+                device: Some(device.id.0.clone()),
+                subject: String::new(),
+                message: format!("{} runs firmware {}, {} is available", device.name, current, latest_version),
+            });
+        }
+    }
+
+    // Devices sharing an address, one finding per device
+    let mut by_ip: BTreeMap<std::net::IpAddr, Vec<&Device>> = BTreeMap::new();
+    for device in devices.iter().filter(|d| d.status == DeviceStatus::Online) {
+        by_ip.entry(device.discovery.ip).or_default().push(device);
+    }
+    for (ip, sharing) in by_ip.into_iter().filter(|(_, d)| d.len() > 1) {
+        for device in &sharing {
+            let others: Vec<&str> = sharing.iter().filter(|d| d.id != device.id).map(|d| d.name.as_str()).collect();
+            detections.push(Detection {
+                kind: FindingKind::IpConflict,
+                severity: Severity::Critical,
+                device: Some(device.id.0.clone()),
+                subject: ip.to_string(),
+                message: format!("{} shares {} with {}", device.name, ip, others.join(", ")),
+            });
+        }
+    }
+
+    for violation in keepout_violations {
+        // Keep-out checks work on HCDF elements; hardware IDs are device IDs
+        let name = violation
+            .hwid
+            .as_deref()
+            .and_then(|hwid| names.get(hwid).copied())
+            .unwrap_or(&violation.device);
+        detections.push(Detection {
+            kind: FindingKind::KeepoutViolation,
+            severity: Severity::Critical,
+            device: violation.hwid.clone(),
+            subject: format!("{}/{}", violation.device, violation.keepout),
+            message: format!(
+                "{} intrudes {:.0} mm into keep-out \"{}\"",
+                name,
+                violation.depth * 1000.0,
+                violation.keepout
+            ),
+        });
+    }
+
+    detections
+}
+
+/// Report discovery methods that lost their raw sockets at startup
+pub fn analyze_sockets(status: &SocketStatus) -> Vec<Detection> {
+    const GRANT: &str = "grant the daemon CAP_NET_RAW (setcap or systemd AmbientCapabilities)";
+    let mut detections = Vec::new();
+
+    if let Some(error) = &status.icmp_error {
+        let (severity, message) = match status.icmp {
+            Some(SocketKind::Datagram) => (
+                Severity::Info,
+                format!("ICMP sweeps use the unprivileged ICMP socket (raw socket: {}); {}", error, GRANT),
+            ),
+            _ => (Severity::Warning, format!("ICMP sweeps are disabled ({}); {}", error, GRANT)),
+        };
+        detections.push(Detection {
+            kind: FindingKind::RawSocketUnavailable,
+            severity,
+            device: None,
+            subject: "icmp".to_string(),
+            message,
+        });
+    }
+
+    if let Some(error) = status.lldp_errors.values().next() {
+        let interfaces: Vec<&str> = status.lldp_errors.keys().map(String::as_str).collect();
+        detections.push(Detection {
+            kind: FindingKind::RawSocketUnavailable,
+            severity: Severity::Warning,
+            device: None,
+            subject: "lldp".to_string(),
+            message: format!("LLDP transmit disabled on {} ({}); {}", interfaces.join(", "), error, GRANT),
+        });
+    }
+
+    if let Some(error) = status.ndp_errors.values().next() {
+        let interfaces: Vec<&str> = status.ndp_errors.keys().map(String::as_str).collect();
+        detections.push(Detection {
+            kind: FindingKind::RawSocketUnavailable,
+            severity: Severity::Warning,
+            device: None,
+            subject: "ndp".to_string(),
+            message: format!("IPv6 neighbor discovery can't send on {} ({}); {}", interfaces.join(", "), error, GRANT),
+        });
+    }
+
+    detections
+}
+
+/// The inbox kept on disk, fed by registry and state changes
+pub struct AttentionInbox {
+    path: PathBuf,
+    retention: ChronoDuration,
+    inbox: Mutex<Inbox>,
+    events: broadcast::Sender<Finding>,
+}
+
+impl AttentionInbox {
+    /// Open the inbox file, keeping the states a previous run left
+    pub fn open(config: &AttentionConfig) -> io::Result<Self> {
+        let path = PathBuf::from(&config.path);
+        let findings = match std::fs::read_to_string(&path) {
+            Ok(text) => serde_json::from_str(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        let (events, _) = broadcast::channel(100);
+        Ok(Self {
+            path,
+            retention: ChronoDuration::hours(config.resolved_retention_hours as i64),
+            inbox: Mutex::new(Inbox::from_findings(findings)),
+            events,
+        })
+    }
+
+    /// Subscribe to finding changes
+    pub fn subscribe(&self) -> broadcast::Receiver<Finding> {
+        self.events.subscribe()
+    }
+
+    /// Re-analyze whenever the registry or the HCDF state changes; `sockets`
+    /// is what discovery could open at startup
+    pub fn spawn(
+        self: Arc<Self>,
+        scanner: Arc<DiscoveryScanner>,
+        store: StateStore,
+        mut events: broadcast::Receiver<DiscoveryEvent>,
+        sockets: SocketStatus,
+    ) {
+        info!(path = %self.path.display(), "Attention inbox enabled");
+        let mut snapshots = store.subscribe();
+        tokio::spawn(async move {
+            let mut dirty = true;
+            let mut ticker = tokio::time::interval(ANALYZE_INTERVAL);
+            loop {
+                tokio::select! {
+                    event = events.recv() => match event {
+                        Ok(
+                            DiscoveryEvent::ScanStarted { .. }
+                            | DiscoveryEvent::ScanProgress { .. }
+                            | DiscoveryEvent::HeartbeatBackoff { .. },
+                        ) => {}
+                        Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => dirty = true,
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                    changed = snapshots.changed() => match changed {
+                        Ok(()) => dirty = true,
+                        Err(_) => break,
+                    },
+                    _ = ticker.tick() => {
+                        if dirty {
+                            dirty = false;
+                            let devices = scanner.devices().await;
+                            let violations = store.snapshot().keepout_violations.clone();
+                            let mut detections = analyze(&devices, &violations);
+                            detections.extend(analyze_sockets(&sockets));
+                            self.update(detections, Utc::now()).await;
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    /// Merge an analysis pass, broadcasting and saving what changed
+    pub async fn update(&self, detections: Vec<Detection>, now: DateTime<Utc>) {
+        let mut inbox = self.inbox.lock().await;
+        let changed = inbox.apply(detections, now);
+        let pruned = inbox.prune(now - self.retention);
+        if changed.is_empty() && !pruned {
+            return;
+        }
+        debug!(changed = changed.len(), open = inbox.open_count(), "Attention inbox updated");
+        self.save(&inbox);
+        for finding in changed {
+            let _ = self.events.send(finding);
+        }
+    }
+
+    /// Acknowledge a finding by ID
+    pub async fn acknowledge(&self, id: &str) -> Option<Finding> {
+        let mut inbox = self.inbox.lock().await;
+        let finding = inbox.acknowledge(id)?;
+        self.save(&inbox);
+        let _ = self.events.send(finding.clone());
+        Some(finding)
+    }
+
+    pub async fn list(&self) -> Vec<Finding> {
+        self.inbox.lock().await.list()
+    }
+
+    pub async fn open_count(&self) -> usize {
+        self.inbox.lock().await.open_count()
+    }
+
+    fn save(&self, inbox: &Inbox) {
+        let result = serde_json::to_string_pretty(&inbox.list())
+            .map_err(io::Error::other)
+            .and_then(|json| {
+                let tmp = self.path.with_extension("tmp");
+                std::fs::write(&tmp, json)?;
+                std::fs::rename(&tmp, &self.path)
+            });
+        if let Err(e) = result {
+            warn!(path = %self.path.display(), error = %e, "Failed to save attention inbox");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dendrite_core::DeviceId;
+
+    fn t(minutes: i64) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2025-06-01T12:00:00Z").unwrap().with_timezone(&Utc) + ChronoDuration::minutes(minutes)
+    }
+
+    fn device(id: &str, ip: &str, status: DeviceStatus) -> Device {
+        let mut device = Device::new(DeviceId::from_hwid(id), format!("node-{}", id), ip.parse().unwrap(), 1337);
+        device.status = status;
+        device
+    }
+
+    fn states(inbox: &Inbox) -> Vec<(String, FindingState, u32)> {
+        inbox.list().into_iter().map(|f| (f.id, f.state, f.occurrences)).collect()
+    }
+
+    #[test]
+    fn test_analyzers() {
+        let mut outdated = device("0x1", "10.0.0.5", DeviceStatus::Online);
+        outdated.firmware.version = Some("1.0.0".to_string());
+        outdated.firmware_status = FirmwareStatus::UpdateAvailable { latest_version: "1.1.0".to_string(), changelog: None };
+        let devices = vec![
+            outdated,
+            device("0x2", "10.0.0.5", DeviceStatus::Online),
+            device("0x3", "10.0.0.7", DeviceStatus::Offline),
+        ];
+        let violations = vec![KeepoutViolation {
+            device: "node-0x2".to_string(),
+            hwid: Some("0x2".to_string()),
+            keepout: "prop_arc".to_string(),
+            depth: 0.012,
+        }];
+
+        let mut found: Vec<(String, Severity, String)> = analyze(&devices, &violations)
+            .into_iter()
+            .map(|d| (d.key(), d.severity, d.message))
+            .collect();
+        found.sort();
+        assert_eq!(
+            found,
+            vec![
+                ("device_offline:0x3:".to_string(), Severity::Warning, "node-0x3 is offline".to_string()),
+                (
+                    "firmware_outdated:0x1:".to_string(),
+                    Severity::Info,
+                    "node-0x1 runs firmware 1.0.0, 1.1.0 is available".to_string()
+                ),
+                (
+                    "ip_conflict:0x1:10.0.0.5".to_string(),
+                    Severity::Critical,
+                    "node-0x1 shares 10.0.0.5 with node-0x2".to_string()
+                ),
+                (
+                    "ip_conflict:0x2:10.0.0.5".to_string(),
+                    Severity::Critical,
+                    "node-0x2 shares 10.0.0.5 with node-0x1".to_string()
+                ),
+                (
+                    "keepout_violation:0x2:node-0x2/prop_arc".to_string(),
+                    Severity::Critical,
+                    "node-0x2 intrudes 12 mm into keep-out \"prop_arc\"".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_socket_findings() {
+        assert!(analyze_sockets(&SocketStatus::default()).is_empty());
+
+        let mut status = SocketStatus {
+            icmp: Some(SocketKind::Datagram),
+            icmp_error: Some("Operation not permitted".to_string()),
+            ..Default::default()
+        };
+        status.lldp_errors.insert("eth0".to_string(), "Operation not permitted".to_string());
+        status.ndp_errors.insert("eth1".to_string(), "Operation not permitted".to_string());
+        assert!(status.is_degraded());
+        let found: Vec<(String, Severity)> = analyze_sockets(&status).into_iter().map(|d| (d.key(), d.severity)).collect();
+        assert_eq!(
+            found,
+            vec![
+                ("raw_socket_unavailable::icmp".to_string(), Severity::Info),
+                ("raw_socket_unavailable::lldp".to_string(), Severity::Warning),
+                ("raw_socket_unavailable::ndp".to_string(), Severity::Warning),
+            ]
+        );
+
+        // No ICMP socket at all means no sweeps
+        status.icmp = None;
+        assert_eq!(analyze_sockets(&status)[0].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn test_duplicates_coalesce_and_resolved_findings_reopen() {
+        let offline = || analyze(&[device("0x3", "10.0.0.7", DeviceStatus::Offline)], &[]);
+        let mut inbox = Inbox::default();
+
+        assert_eq!(inbox.apply(offline(), t(0)).len(), 1);
+        // Detected again: same entry, nothing to broadcast
+        assert!(inbox.apply(offline(), t(1)).is_empty());
+        assert!(inbox.apply(offline(), t(2)).is_empty());
+        assert_eq!(states(&inbox), vec![("device_offline:0x3:".to_string(), FindingState::Open, 1)]);
+        assert_eq!(inbox.list()[0].last_seen, t(2));
+
+        let acked = inbox.acknowledge("device_offline:0x3:").unwrap();
+        assert_eq!(acked.state, FindingState::Acknowledged);
+        assert_eq!(inbox.open_count(), 0);
+        assert!(inbox.acknowledge("missing").is_none());
+
+        // Back online: resolved, then offline again reopens the same entry
+        let resolved = inbox.apply(Vec::new(), t(3));
+        assert_eq!((resolved[0].state, resolved[0].resolved_at), (FindingState::Resolved, Some(t(3))));
+        inbox.apply(offline(), t(4));
+        assert_eq!(states(&inbox), vec![("device_offline:0x3:".to_string(), FindingState::Open, 2)]);
+        assert_eq!(inbox.list()[0].first_seen, t(0));
+
+        // Resolved findings are kept for the retention period only
+        inbox.apply(Vec::new(), t(5));
+        assert!(!inbox.prune(t(5)));
+        assert!(inbox.prune(t(6)));
+        assert!(inbox.list().is_empty());
+    }
+
+    #[test]
+    fn test_acknowledged_finding_reopens_when_worse() {
+        let detection = |severity| Detection {
+            kind: FindingKind::KeepoutViolation,
+            severity,
+            device: Some("0x2".to_string()),
+            subject: "node-0x2/prop_arc".to_string(),
+            message: "intrudes".to_string(),
+        };
+        let mut inbox = Inbox::default();
+        inbox.apply(vec![detection(Severity::Warning)], t(0));
+        inbox.acknowledge("keepout_violation:0x2:node-0x2/prop_arc");
+
+        let changed = inbox.apply(vec![detection(Severity::Critical)], t(1));
+        assert_eq!((changed[0].state, changed[0].severity), (FindingState::Open, Severity::Critical));
+    }
+
+    #[tokio::test]
+    async fn test_acknowledgement_survives_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = AttentionConfig {
+            path: dir.path().join("attention.json").to_string_lossy().into_owned(),
+            ..Default::default()
+        };
+        let offline = analyze(&[device("0x3", "10.0.0.7", DeviceStatus::Offline)], &[]);
+
+        let inbox = AttentionInbox::open(&config).unwrap();
+        let mut events = inbox.subscribe();
+        inbox.update(offline.clone(), Utc::now()).await;
+        assert_eq!(events.recv().await.unwrap().state, FindingState::Open);
+        inbox.acknowledge("device_offline:0x3:").await.unwrap();
+        assert_eq!(events.recv().await.unwrap().state, FindingState::Acknowledged);
+
+        let reopened = AttentionInbox::open(&config).unwrap();
+        assert_eq!(reopened.list().await[0].state, FindingState::Acknowledged);
+        // Still detected after the restart: stays acknowledged, no event
+        let mut events = reopened.subscribe();
+        reopened.update(offline, Utc::now()).await;
+        assert!(events.try_recv().is_err());
+        assert_eq!(reopened.open_count().await, 0);
+    }
+}
@@ -2,10 +2,22 @@
 //!
 //! This module provides middleware for validating session tokens issued by
 //! dendrite-se051d after NFC authentication with the SE051C2 secure element.
+//!
+//! Sessions carry a role. Viewer sessions are read-only: the middleware
+//! rejects any request that would change daemon or device state, so
+//! spectators in a shared review can't move or update devices.
+//!
+//! A static token from `daemon.auth_token` can guard the API on its own: with
+//! it set, mutating requests must carry it (or a valid session token) even
+//! when `auth.require_token` is off, and read-only requests may stay open.
+//!
+//! Browsers can't set headers on a WebSocket, so the `/ws` upgrade also
+//! accepts the token as a `token` query parameter. Tokens are compared in
+//! constant time.
 
 use axum::{
     extract::Request,
-    http::{header, StatusCode},
+    http::{header, HeaderMap, Method, StatusCode},
     middleware::Next,
     response::{IntoResponse, Response},
     Json,
@@ -14,6 +26,7 @@ use serde::{Deserialize, Serialize};
 use std::path::Path;
 use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use subtle::ConstantTimeEq;
 use tokio::sync::RwLock;
 use tracing::{debug, trace, warn};
 
@@ -30,6 +43,23 @@ pub struct SharedTokenStore {
     pub sessions: Vec<SharedSession>,
 }
 
+/// What a session is allowed to do
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionRole {
+    /// Full access
+    #[default]
+    Operator,
+    /// Read-only access for spectators
+    Viewer,
+}
+
+impl SessionRole {
+    pub fn is_read_only(&self) -> bool {
+        *self == SessionRole::Viewer
+    }
+}
+
 /// Session info from token store
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SharedSession {
@@ -43,6 +73,9 @@ pub struct SharedSession {
     pub expires_at: u64,
     /// Whether connected via AP mode
     pub via_ap: bool,
+    /// Access level (older stores without a role grant full access)
+    #[serde(default)]
+    pub role: SessionRole,
 }
 
 impl SharedTokenStore {
@@ -66,13 +99,13 @@ impl SharedTokenStore {
     /// Check if a token is valid (exists and not expired)
     pub fn is_token_valid(&self, token_hex: &str) -> bool {
         let now = current_unix_time();
-        self.sessions.iter().any(|s| s.token == token_hex && s.expires_at > now)
+        self.sessions.iter().any(|s| tokens_match(&s.token, token_hex) && s.expires_at > now)
     }
 
     /// Get session info for a token
     pub fn get_session(&self, token_hex: &str) -> Option<&SharedSession> {
         let now = current_unix_time();
-        self.sessions.iter().find(|s| s.token == token_hex && s.expires_at > now)
+        self.sessions.iter().find(|s| tokens_match(&s.token, token_hex) && s.expires_at > now)
     }
 }
 
@@ -82,6 +115,11 @@ impl Default for SharedTokenStore {
     }
 }
 
+/// Compare tokens without the timing revealing how much of a guess matched
+fn tokens_match(expected: &str, given: &str) -> bool {
+    bool::from(expected.as_bytes().ct_eq(given.as_bytes()))
+}
+
 /// Get current Unix timestamp
 fn current_unix_time() -> u64 {
     SystemTime::now()
@@ -90,9 +128,26 @@ fn current_unix_time() -> u64 {
         .as_secs()
 }
 
+/// Static token from `daemon.auth_token`, granting full access
+#[derive(Debug, Default)]
+struct ApiToken {
+    token: Option<String>,
+    /// Whether the token guards read-only requests as well
+    reads: bool,
+}
+
+impl ApiToken {
+    /// Empty tokens are ignored
+    fn new(token: Option<String>, reads: bool) -> Self {
+        Self { token: token.filter(|t| !t.is_empty()), reads }
+    }
+}
+
 /// Authentication state that watches the token store file
 pub struct AuthState {
     config: AuthConfig,
+    /// Static token from `daemon.auth_token`; reloadable, so behind a lock
+    api_token: RwLock<ApiToken>,
     store: RwLock<SharedTokenStore>,
     last_load: RwLock<SystemTime>,
 }
@@ -102,16 +157,40 @@ impl AuthState {
     pub fn new(config: AuthConfig) -> Self {
         Self {
             config,
+            api_token: RwLock::new(ApiToken::default()),
             store: RwLock::new(SharedTokenStore::new()),
             last_load: RwLock::new(SystemTime::UNIX_EPOCH),
         }
     }
 
+    /// Guard the API with a static token (empty tokens are ignored)
+    pub fn with_api_token(mut self, token: Option<String>, reads: bool) -> Self {
+        self.api_token = RwLock::new(ApiToken::new(token, reads));
+        self
+    }
+
+    /// Replace the static token, e.g. after a configuration reload
+    pub async fn set_api_token(&self, token: Option<String>, reads: bool) {
+        *self.api_token.write().await = ApiToken::new(token, reads);
+    }
+
     /// Check if authentication is required
     pub fn is_required(&self) -> bool {
         self.config.require_token
     }
 
+    /// Whether some requests must carry a token: session tokens are required,
+    /// or the static token guards at least the mutating ones
+    pub async fn guards_api(&self) -> bool {
+        self.is_required() || self.api_token.read().await.token.is_some()
+    }
+
+    /// Whether a request must carry a token
+    async fn needs_token(&self, request: &Request) -> bool {
+        let api_token = self.api_token.read().await;
+        self.is_required() || (api_token.token.is_some() && (api_token.reads || is_mutation(request)))
+    }
+
     /// Reload token store if file has changed (checks every 2 seconds)
     async fn maybe_reload(&self) {
         let now = SystemTime::now();
@@ -153,17 +232,20 @@ impl AuthState {
         *self.last_load.write().await = now;
     }
 
-    /// Validate a token
-    pub async fn validate_token(&self, token: &str) -> bool {
-        self.maybe_reload().await;
-        self.store.read().await.is_token_valid(token)
-    }
-
     /// Get session info for a token
     pub async fn get_session(&self, token: &str) -> Option<SharedSession> {
         self.maybe_reload().await;
         self.store.read().await.get_session(token).cloned()
     }
+
+    /// Role granted by a token: the static token is an operator, anything
+    /// else must be a live session
+    async fn role_for(&self, token: &str) -> Option<SessionRole> {
+        if self.api_token.read().await.token.as_deref().is_some_and(|expected| tokens_match(expected, token)) {
+            return Some(SessionRole::Operator);
+        }
+        self.get_session(token).await.map(|session| session.role)
+    }
 }
 
 /// Error response for authentication failures
@@ -173,70 +255,147 @@ struct AuthError {
     code: &'static str,
 }
 
-/// Authentication middleware
+fn auth_error(status: StatusCode, error: &str, code: &'static str) -> Response {
+    (status, Json(AuthError { error: error.to_string(), code })).into_response()
+}
+
+/// Whether a request changes daemon or device state
 ///
-/// Validates Bearer tokens from the Authorization header when auth is required.
-/// Passes through all requests when auth is disabled (development mode).
-pub async fn auth_middleware(
-    axum::extract::State(state): axum::extract::State<Arc<AuthState>>,
-    request: Request,
-    next: Next,
-) -> Response {
-    // If auth not required, pass through
-    if !state.is_required() {
-        return next.run(request).await;
+/// Safe methods never do. `POST /hcdf/export` only renders the current
+/// scene, so spectators may download it too.
+fn is_mutation(request: &Request) -> bool {
+    if request.method().is_safe() {
+        return false;
     }
+    // Exports and import previews read the document without changing it
+    !(request.method() == Method::POST && matches!(request.uri().path(), "/hcdf/export" | "/hcdf/diff"))
+}
 
-    // Extract Authorization header
-    let auth_header = request
-        .headers()
-        .get(header::AUTHORIZATION)
-        .and_then(|v| v.to_str().ok());
-
-    let token = match auth_header {
-        Some(header) if header.starts_with("Bearer ") => &header[7..],
+/// Token from the Authorization header, Err when the header isn't a Bearer token
+fn bearer_token(headers: &HeaderMap) -> Result<Option<String>, Response> {
+    match headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok()) {
+        Some(header) if header.starts_with("Bearer ") => Ok(Some(header[7..].to_string())),
         Some(_) => {
             warn!("Invalid authorization header format");
-            return (
+            Err(auth_error(
                 StatusCode::UNAUTHORIZED,
-                Json(AuthError {
-                    error: "Invalid authorization header format. Use: Bearer <token>".to_string(),
-                    code: "INVALID_AUTH_FORMAT",
-                }),
-            )
-                .into_response();
+                "Invalid authorization header format. Use: Bearer <token>",
+                "INVALID_AUTH_FORMAT",
+            ))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Token from a `token` query parameter
+fn query_token(request: &Request) -> Option<String> {
+    request.uri().query()?.split('&').find_map(|pair| {
+        let value = pair.strip_prefix("token=")?;
+        Some(percent_decode(value))
+    })
+}
+
+/// Decode `%XX` escapes; malformed ones are kept as they are
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| bytes.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
         }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Check a request's token and add its role to the request extensions
+///
+/// Requests that don't need a token pass without one, but a valid session
+/// token still sets their role, so viewer sessions stay read-only on an
+/// open API.
+async fn authorize(state: &AuthState, mut request: Request, token: Option<String>, next: Next) -> Response {
+    let required = state.needs_token(&request).await;
+
+    let token = match token {
+        Some(token) => token,
+        None if !required => return next.run(request).await,
         None => {
             debug!(path = %request.uri().path(), "Missing authorization header");
-            return (
+            return auth_error(
                 StatusCode::UNAUTHORIZED,
-                Json(AuthError {
-                    error: "Authorization required. Include header: Authorization: Bearer <token>".to_string(),
-                    code: "AUTH_REQUIRED",
-                }),
-            )
-                .into_response();
+                "Authorization required. Include header: Authorization: Bearer <token>",
+                "AUTH_REQUIRED",
+            );
         }
     };
 
     // Validate token
-    if !state.validate_token(token).await {
-        warn!("Invalid or expired token");
-        return (
-            StatusCode::UNAUTHORIZED,
-            Json(AuthError {
-                error: "Invalid or expired token".to_string(),
-                code: "INVALID_TOKEN",
-            }),
-        )
-            .into_response();
+    let role = match state.role_for(&token).await {
+        Some(role) => role,
+        None if !required => return next.run(request).await,
+        None => {
+            warn!("Invalid or expired token");
+            return auth_error(StatusCode::UNAUTHORIZED, "Invalid or expired token", "INVALID_TOKEN");
+        }
+    };
+
+    if role.is_read_only() && is_mutation(&request) {
+        debug!(path = %request.uri().path(), method = %request.method(), "Rejected mutation from viewer session");
+        return auth_error(StatusCode::FORBIDDEN, "This session is read-only", "READ_ONLY");
     }
 
     // Token valid, proceed
     debug!("Token validated successfully");
+    request.extensions_mut().insert(role);
     next.run(request).await
 }
 
+/// Authentication middleware
+///
+/// Validates Bearer tokens from the Authorization header when auth is required,
+/// or when the request is guarded by `daemon.auth_token`, and rejects
+/// mutations from viewer sessions. The session role is added to the request
+/// extensions for handlers.
+/// Passes through all requests when auth is disabled (development mode).
+pub async fn auth_middleware(
+    axum::extract::State(state): axum::extract::State<Arc<AuthState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    match bearer_token(request.headers()) {
+        Ok(token) => authorize(&state, request, token, next).await,
+        Err(response) => response,
+    }
+}
+
+/// Authentication middleware for the WebSocket upgrade
+///
+/// Like [`auth_middleware`], but also takes the token from a `token` query
+/// parameter, since browsers can't send headers with a WebSocket.
+pub async fn websocket_auth_middleware(
+    axum::extract::State(state): axum::extract::State<Arc<AuthState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let token = match bearer_token(request.headers()) {
+        Ok(Some(token)) => Some(token),
+        Ok(None) => query_token(&request),
+        Err(response) => return response,
+    };
+    authorize(&state, request, token, next).await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -252,6 +411,7 @@ mod tests {
             phone_name: "Test Phone".to_string(),
             expires_at: future_time,
             via_ap: false,
+            role: SessionRole::Operator,
         });
 
         assert!(store.is_token_valid("abc123"));
@@ -269,8 +429,175 @@ mod tests {
             phone_name: "Test Phone".to_string(),
             expires_at: past_time,
             via_ap: false,
+            role: SessionRole::Operator,
         });
 
         assert!(!store.is_token_valid("expired123"));
     }
+
+    #[test]
+    fn test_session_role_defaults_to_operator() {
+        let store: SharedTokenStore = serde_json::from_str(
+            r#"{"version":1,"updated_at":0,"sessions":[
+                {"token":"a","phone_id":"p","phone_name":"n","expires_at":0,"via_ap":false},
+                {"token":"b","phone_id":"p","phone_name":"n","expires_at":0,"via_ap":false,"role":"viewer"}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(store.sessions[0].role, SessionRole::Operator);
+        assert_eq!(store.sessions[1].role, SessionRole::Viewer);
+    }
+
+    #[test]
+    fn test_mutation_classification() {
+        let request = |method: &str, path: &str| {
+            Request::builder()
+                .method(method)
+                .uri(path)
+                .body(axum::body::Body::empty())
+                .unwrap()
+        };
+        assert!(!is_mutation(&request("GET", "/devices")));
+        assert!(!is_mutation(&request("POST", "/hcdf/export")));
+        assert!(!is_mutation(&request("POST", "/hcdf/diff")));
+        assert!(is_mutation(&request("PUT", "/devices/abc/position")));
+        assert!(is_mutation(&request("DELETE", "/devices/abc")));
+        assert!(is_mutation(&request("POST", "/hcdf/import")));
+    }
+
+    async fn status(auth: AuthState, method: &str, path: &str, token: Option<&str>) -> StatusCode {
+        use axum::routing::{get, post};
+        use tower::ServiceExt;
+
+        let router = axum::Router::new()
+            .route("/devices", get(|| async { "ok" }))
+            .route("/scan", post(|| async { "ok" }))
+            .layer(axum::middleware::from_fn_with_state(Arc::new(auth), auth_middleware));
+        let mut request = Request::builder().method(method).uri(path);
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        router.oneshot(request.body(axum::body::Body::empty()).unwrap()).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_api_token_guards_mutations() {
+        let auth = || AuthState::new(AuthConfig::default()).with_api_token(Some("s3cret".to_string()), false);
+        assert_eq!(status(auth(), "GET", "/devices", None).await, StatusCode::OK);
+        assert_eq!(status(auth(), "POST", "/scan", None).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(auth(), "POST", "/scan", Some("wrong")).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(auth(), "POST", "/scan", Some("s3cret")).await, StatusCode::OK);
+
+        // Empty tokens leave the API open
+        let open = AuthState::new(AuthConfig::default()).with_api_token(Some(String::new()), true);
+        assert_eq!(status(open, "POST", "/scan", None).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_api_token_can_guard_reads() {
+        let auth = || AuthState::new(AuthConfig::default()).with_api_token(Some("s3cret".to_string()), true);
+        assert_eq!(status(auth(), "GET", "/devices", None).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(auth(), "GET", "/devices", Some("s3cret")).await, StatusCode::OK);
+        assert_eq!(status(auth(), "POST", "/scan", Some("s3cret")).await, StatusCode::OK);
+    }
+
+    #[test]
+    fn test_tokens_match() {
+        assert!(tokens_match("s3cret", "s3cret"));
+        assert!(!tokens_match("s3cret", "s3cres"));
+        assert!(!tokens_match("s3cret", "s3cret2"));
+        assert!(!tokens_match("s3cret", ""));
+    }
+
+    #[tokio::test]
+    async fn test_websocket_takes_token_from_query() {
+        use axum::routing::get;
+        use tower::ServiceExt;
+
+        let auth = Arc::new(AuthState::new(AuthConfig::default()).with_api_token(Some("s3/cret".to_string()), true));
+        let router = axum::Router::new()
+            .route("/ws", get(|| async { "ok" }))
+            .layer(axum::middleware::from_fn_with_state(auth, websocket_auth_middleware));
+        let status = |uri: &str| {
+            let request = Request::builder().uri(uri).body(axum::body::Body::empty()).unwrap();
+            let router = router.clone();
+            async move { router.oneshot(request).await.unwrap().status() }
+        };
+
+        assert_eq!(status("/ws?compact=true").await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status("/ws?compact=true&token=wrong").await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status("/ws?compact=true&token=s3%2Fcret").await, StatusCode::OK);
+    }
+
+    /// `GET /session` through the auth middleware, as the web UI asks it
+    async fn session(auth: AuthState, token: Option<&str>) -> serde_json::Value {
+        use axum::routing::get;
+        use tower::ServiceExt;
+
+        let auth = Arc::new(auth);
+        let router = axum::Router::new()
+            .route("/session", get(crate::api::get_session))
+            .layer(axum::Extension(auth.clone()))
+            .layer(axum::middleware::from_fn_with_state(auth, auth_middleware));
+        let mut request = Request::builder().uri("/session");
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let response = router.oneshot(request.body(axum::body::Body::empty()).unwrap()).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_session_reports_auth_required_for_api_token() {
+        assert_eq!(session(AuthState::new(AuthConfig::default()), None).await["auth_required"], false);
+        let guarded = AuthState::new(AuthConfig::default()).with_api_token(Some("s3cret".to_string()), false);
+        assert_eq!(session(guarded, None).await["auth_required"], true);
+    }
+
+    /// Auth state holding a live viewer session with token `v1ewer`
+    fn with_viewer_session(require_token: bool) -> AuthState {
+        // No store file, so the session below is never reloaded away
+        let mut auth = AuthState::new(AuthConfig { require_token, token_store_path: String::new() });
+        let mut store = SharedTokenStore::new();
+        store.sessions.push(SharedSession {
+            token: "v1ewer".to_string(),
+            phone_id: "phone1".to_string(),
+            phone_name: "Spectator".to_string(),
+            expires_at: current_unix_time() + 3600,
+            via_ap: false,
+            role: SessionRole::Viewer,
+        });
+        auth.store = RwLock::new(store);
+        auth
+    }
+
+    #[tokio::test]
+    async fn test_viewer_token_session_is_read_only() {
+        // The session endpoint is open to reads, but the viewer's token still counts
+        for require_token in [false, true] {
+            let viewer = session(with_viewer_session(require_token), Some("v1ewer")).await;
+            assert_eq!(viewer["role"], "viewer", "require_token = {}", require_token);
+            assert_eq!(viewer["read_only"], true);
+        }
+        let guarded = with_viewer_session(false).with_api_token(Some("s3cret".to_string()), false);
+        assert_eq!(session(guarded, Some("s3cret")).await["read_only"], false);
+
+        // And its mutations are refused even where anonymous ones would pass
+        assert_eq!(status(with_viewer_session(false), "POST", "/scan", None).await, StatusCode::OK);
+        assert_eq!(status(with_viewer_session(false), "POST", "/scan", Some("v1ewer")).await, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_api_token_can_be_replaced() {
+        let auth = AuthState::new(AuthConfig::default());
+        let scan = Request::builder().method("POST").uri("/scan").body(axum::body::Body::empty()).unwrap();
+        assert!(!auth.needs_token(&scan).await);
+
+        auth.set_api_token(Some("rotated".to_string()), false).await;
+        assert!(auth.needs_token(&scan).await);
+        assert_eq!(auth.role_for("rotated").await, Some(SessionRole::Operator));
+        auth.set_api_token(None, false).await;
+        assert_eq!(auth.role_for("rotated").await, None);
+    }
 }
@@ -0,0 +1,120 @@
+//! Offline bundles of the HCDF and its models
+//!
+//! `dendrite bundle` and `GET /api/hcdf/bundle` write the document with every
+//! model it references into one zip (see `dendrite_core::bundle`), so the
+//! viewer can show it on a machine without internet access. Models come from
+//! the fragment cache, or from the static models directory for hrefs served
+//! under `/models`. Models found in neither are left out and reported.
+
+use anyhow::{Context, Result};
+use dendrite_core::{bundle_model_path, sha256_hex, write_bundle, BundledModel, Hcdf};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use tracing::warn;
+
+use crate::hcdf_fetch::HcdfFetcher;
+
+/// A bundle ready to be written or served
+pub struct Bundle {
+    /// Zip content
+    pub data: Vec<u8>,
+    /// Number of model hrefs bundled
+    pub bundled: usize,
+    /// Hrefs whose model couldn't be found
+    pub missing: Vec<String>,
+}
+
+/// Bundle `hcdf` with the models the cache or `static_models` hold
+pub async fn build_bundle(hcdf: &Hcdf, fetcher: &HcdfFetcher, static_models: &Path) -> Result<Bundle> {
+    let xml = hcdf.to_xml().context("Failed to serialize HCDF")?;
+
+    let mut models: Vec<(BundledModel, Vec<u8>)> = Vec::new();
+    // Index in `models` of each file already read, for hrefs sharing a file
+    let mut files: HashMap<PathBuf, usize> = HashMap::new();
+    let mut missing = Vec::new();
+    for model_ref in hcdf.model_refs() {
+        let href = &model_ref.href;
+        if models.iter().any(|(m, _)| &m.href == href) || missing.contains(href) {
+            continue;
+        }
+        let file = match fetcher.model_file(href, model_ref.sha.as_deref()).await {
+            Some(file) => file,
+            None => match static_model_file(href, static_models) {
+                Some(file) => file,
+                None => {
+                    warn!(href = %href, "Model not cached, leaving it out of the bundle");
+                    missing.push(href.clone());
+                    continue;
+                }
+            },
+        };
+
+        if let Some(&index) = files.get(&file) {
+            let (shared, content) = models[index].clone();
+            models.push((BundledModel { href: href.clone(), ..shared }, content));
+            continue;
+        }
+        let content = tokio::fs::read(&file)
+            .await
+            .with_context(|| format!("Failed to read {}", file.display()))?;
+        let file_name = file.file_name().and_then(|n| n.to_str()).unwrap_or("model.glb");
+        let model = BundledModel {
+            href: href.clone(),
+            path: bundle_model_path(file_name, &models.iter().map(|(m, _)| m.clone()).collect::<Vec<_>>()),
+            sha: sha256_hex(&content),
+        };
+        files.insert(file, models.len());
+        models.push((model, content));
+    }
+
+    let data = write_bundle(&xml, &models).context("Failed to write bundle")?;
+    Ok(Bundle { data, bundled: models.len(), missing })
+}
+
+/// File in the static models directory for an href served under `/models`
+fn static_model_file(href: &str, static_models: &Path) -> Option<PathBuf> {
+    let relative = href.trim_start_matches("./").trim_start_matches('/').strip_prefix("models/")?;
+    let relative = Path::new(relative);
+    if !relative.components().all(|c| matches!(c, Component::Normal(_))) {
+        return None;
+    }
+    Some(static_models.join(relative)).filter(|p| p.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dendrite_core::read_bundle;
+
+    #[tokio::test]
+    async fn test_bundle_cached_and_static_models() {
+        let dir = tempfile::tempdir().unwrap();
+        let fetcher = HcdfFetcher::new(dir.path().join("cache")).unwrap();
+        let upload = fetcher.set_model_override("0x1", None, "fixed.glb", b"glTF-fixed").await.unwrap();
+        let static_models = dir.path().join("assets");
+        std::fs::create_dir_all(&static_models).unwrap();
+        std::fs::write(static_models.join("frame.glb"), b"glTF-frame").unwrap();
+
+        let mut hcdf = Hcdf::from_xml(
+            r#"<hcdf version="2.1">
+  <mcu name="flow" hwid="0x1"/>
+  <comp name="frame"><model href="/models/frame.glb"/><visual name="body"><model href="models/frame.glb"/></visual></comp>
+  <comp name="gps"><model href="https://hcdf.cognipilot.org/models/gps.glb"/></comp>
+</hcdf>"#,
+        )
+        .unwrap();
+        fetcher.apply_model_overrides(&mut hcdf).await;
+
+        let bundle = build_bundle(&hcdf, &fetcher, &static_models).await.unwrap();
+        assert_eq!(bundle.bundled, 3);
+        assert_eq!(bundle.missing, ["https://hcdf.cognipilot.org/models/gps.glb"]);
+
+        let read = read_bundle(&bundle.data).unwrap();
+        assert!(read.issues.is_empty());
+        let content = |href: &str| read.models.iter().find(|(m, _)| m.href == href).map(|(_, c)| c.as_slice());
+        assert_eq!(content(&upload.path), Some(&b"glTF-fixed"[..]));
+        assert_eq!(content("/models/frame.glb"), Some(&b"glTF-frame"[..]));
+        assert_eq!(content("models/frame.glb"), Some(&b"glTF-frame"[..]));
+        assert!(static_model_file("models/../secret.glb", &static_models).is_none());
+    }
+}
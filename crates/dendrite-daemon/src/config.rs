@@ -1,21 +1,28 @@
 //! Configuration loading and validation
 
 use anyhow::Result;
-use dendrite_discovery::{ScannerConfig, ParentConfig, DeviceOverride};
+use dendrite_core::Device;
+use dendrite_discovery::{arp, mdns, CanDiscoveryConfig, CanIdentify, DeviceOverride, DiscoveryMethods, IcmpSweep, IpNet, LldpTxConfig, ParentConfig, ScannerConfig};
+use dendrite_mcumgr::SerialPortConfig;
+use serde::de::{self, DeserializeOwned, Deserializer, Visitor};
 use serde::{Deserialize, Serialize};
-use std::net::Ipv4Addr;
-use std::path::Path;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::{Path, PathBuf};
+use toml::Value;
 use tracing::info;
 
 /// Main configuration structure
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
     #[serde(default)]
     pub daemon: DaemonConfig,
     #[serde(default)]
     pub discovery: DiscoveryConfig,
-    #[serde(default)]
-    pub parent: Option<ParentDeviceConfig>,
+    /// Parent devices (switches); `[parent]` for one, `[[parent]]` for several
+    #[serde(default, deserialize_with = "one_or_many", skip_serializing_if = "Vec::is_empty")]
+    pub parent: Vec<ParentDeviceConfig>,
     #[serde(default)]
     pub models: ModelsConfig,
     #[serde(default)]
@@ -26,10 +33,19 @@ pub struct Config {
     pub device_overrides: Vec<DeviceOverrideConfig>,
     #[serde(default)]
     pub auth: AuthConfig,
+    #[serde(default)]
+    pub status_led: StatusLedConfig,
+    #[serde(default)]
+    pub journal: JournalConfig,
+    #[serde(default)]
+    pub attention: AttentionConfig,
+    #[serde(default)]
+    pub shell: ShellConfig,
 }
 
 /// Authentication configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct AuthConfig {
     /// Whether token authentication is required for API access
     /// When false (default), API is accessible without authentication (development mode)
@@ -54,7 +70,198 @@ fn default_token_store_path() -> String {
     "/run/dendrite-se051d/tokens.json".to_string()
 }
 
+/// Registry journal for `?as_of=` queries
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JournalConfig {
+    /// Record device events so past registry states can be viewed
+    #[serde(default)]
+    pub enabled: bool,
+    /// Path to the journal file (JSON lines)
+    #[serde(default = "default_journal_path")]
+    pub path: String,
+    /// Size the journal is compacted back under
+    #[serde(default = "default_journal_max_bytes")]
+    pub max_bytes: u64,
+    /// Snapshot periods that ended longer ago than this are dropped
+    #[serde(default = "default_journal_max_age_hours")]
+    pub max_age_hours: u64,
+    /// Seconds between full registry snapshots that replays start from
+    #[serde(default = "default_journal_snapshot_interval")]
+    pub snapshot_interval_secs: u64,
+}
+
+impl Default for JournalConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: default_journal_path(),
+            max_bytes: default_journal_max_bytes(),
+            max_age_hours: default_journal_max_age_hours(),
+            snapshot_interval_secs: default_journal_snapshot_interval(),
+        }
+    }
+}
+
+fn default_journal_path() -> String {
+    "./dendrite-journal.jsonl".to_string()
+}
+
+fn default_journal_max_bytes() -> u64 {
+    16 * 1024 * 1024
+}
+
+fn default_journal_max_age_hours() -> u64 {
+    7 * 24
+}
+
+fn default_journal_snapshot_interval() -> u64 {
+    3600
+}
+
+/// Attention inbox
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AttentionConfig {
+    /// Collect findings from the analyzers for `/api/attention`
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Where findings and their acknowledgements are kept
+    #[serde(default = "default_attention_path")]
+    pub path: String,
+    /// Hours a resolved finding stays listed
+    #[serde(default = "default_attention_retention")]
+    pub resolved_retention_hours: u64,
+}
+
+impl Default for AttentionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            path: default_attention_path(),
+            resolved_retention_hours: default_attention_retention(),
+        }
+    }
+}
+
+fn default_attention_path() -> String {
+    "./dendrite-attention.json".to_string()
+}
+
+fn default_attention_retention() -> u64 {
+    24
+}
+
+/// Zephyr shell commands `POST /api/devices/:id/shell` may run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ShellConfig {
+    /// Commands allowed, arguments included; anything else is refused.
+    /// Empty turns the endpoint off
+    #[serde(default = "default_shell_commands")]
+    pub allowed_commands: Vec<String>,
+    /// Wait for a command's answer, in milliseconds
+    #[serde(default = "default_shell_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+impl Default for ShellConfig {
+    fn default() -> Self {
+        Self {
+            allowed_commands: default_shell_commands(),
+            timeout_ms: default_shell_timeout_ms(),
+        }
+    }
+}
+
+impl ShellConfig {
+    /// Whether `command` is on the allowlist; only the spacing between
+    /// words may differ
+    pub fn allows(&self, command: &str) -> bool {
+        let words = |command: &str| command.split_whitespace().collect::<Vec<_>>().join(" ");
+        let command = words(command);
+        !command.is_empty() && self.allowed_commands.iter().any(|allowed| words(allowed) == command)
+    }
+}
+
+fn default_shell_commands() -> Vec<String> {
+    // Read-only commands useful during bring-up
+    ["device list", "net iface", "kernel version", "kernel uptime"].map(String::from).to_vec()
+}
+
+fn default_shell_timeout_ms() -> u64 {
+    5000
+}
+
+/// Status LED sync configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StatusLedConfig {
+    /// Push each device's status to its RGB status LED over the vendor SMP group
+    #[serde(default)]
+    pub enabled: bool,
+    /// Minimum seconds between color changes sent to one device
+    #[serde(default = "default_status_led_interval")]
+    pub min_interval_secs: u64,
+    /// Hardware IDs of devices whose LEDs are left alone
+    #[serde(default)]
+    pub opt_out: Vec<String>,
+    /// RGB color for each status
+    #[serde(default)]
+    pub colors: StatusLedColors,
+}
+
+impl Default for StatusLedConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_interval_secs: default_status_led_interval(),
+            opt_out: Vec::new(),
+            colors: StatusLedColors::default(),
+        }
+    }
+}
+
+/// RGB colors pushed for each indicator status
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StatusLedColors {
+    #[serde(default = "default_up_to_date_color")]
+    pub up_to_date: [u8; 3],
+    #[serde(default = "default_update_available_color")]
+    pub update_available: [u8; 3],
+    #[serde(default = "default_attention_color")]
+    pub attention: [u8; 3],
+}
+
+impl Default for StatusLedColors {
+    fn default() -> Self {
+        Self {
+            up_to_date: default_up_to_date_color(),
+            update_available: default_update_available_color(),
+            attention: default_attention_color(),
+        }
+    }
+}
+
+fn default_status_led_interval() -> u64 {
+    5
+}
+
+fn default_up_to_date_color() -> [u8; 3] {
+    [0, 255, 0]
+}
+
+fn default_update_available_color() -> [u8; 3] {
+    [255, 160, 0]
+}
+
+fn default_attention_color() -> [u8; 3] {
+    [255, 0, 0]
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct DaemonConfig {
     /// Bind address for web server
     #[serde(default = "default_bind")]
@@ -68,9 +275,44 @@ pub struct DaemonConfig {
     /// Whether heartbeat checking is enabled (sends ARP/ping to check connectivity)
     #[serde(default)]
     pub heartbeat_enabled: bool,
+    /// First retry of a device that stopped answering heartbeats, in
+    /// seconds; each further retry waits twice as long
+    #[serde(default = "default_offline_retry_min")]
+    pub offline_retry_min_secs: u64,
+    /// Longest wait between retries of an offline device, in seconds
+    #[serde(default = "default_offline_retry_max")]
+    pub offline_retry_max_secs: u64,
+    /// Fraction each heartbeat delay is randomly spread by (0.1 = ±10%)
+    #[serde(default = "default_heartbeat_jitter")]
+    pub heartbeat_jitter: f64,
     /// TLS configuration (optional - enables HTTPS when present)
     #[serde(default)]
     pub tls: Option<TlsConfig>,
+    /// Give up all capabilities (CAP_NET_RAW included) once discovery's raw
+    /// sockets are open
+    #[serde(default = "default_true")]
+    pub drop_capabilities: bool,
+    /// Save the device registry to the data directory and restore it at
+    /// startup (`--no-persist` turns this off)
+    #[serde(default = "default_true")]
+    pub persist_registry: bool,
+    /// Shared secret for the API: when set, mutating requests (scans, OTA,
+    /// HCDF saves, device removal, ...) need `Authorization: Bearer <token>`
+    #[serde(default)]
+    pub auth_token: Option<String>,
+    /// Require `auth_token` for read-only requests too
+    #[serde(default)]
+    pub auth_token_reads: bool,
+    /// Origins allowed to call the API from a browser (empty = any origin)
+    #[serde(default)]
+    pub cors_origins: Vec<String>,
+    /// Longest wait on shutdown for running OTA updates to finish, in
+    /// seconds (0 = exit straight away)
+    #[serde(default = "default_shutdown_timeout")]
+    pub shutdown_timeout_secs: u64,
+    /// Log output format (`--log-format` overrides it)
+    #[serde(default)]
+    pub log_format: LogFormat,
 }
 
 impl Default for DaemonConfig {
@@ -80,13 +322,49 @@ impl Default for DaemonConfig {
             discovery_interval_secs: default_interval(),
             heartbeat_interval_secs: default_heartbeat_interval(),
             heartbeat_enabled: false, // Disabled by default
+            offline_retry_min_secs: default_offline_retry_min(),
+            offline_retry_max_secs: default_offline_retry_max(),
+            heartbeat_jitter: default_heartbeat_jitter(),
             tls: None,
+            drop_capabilities: true,
+            persist_registry: true,
+            auth_token: None,
+            auth_token_reads: false,
+            cors_origins: Vec::new(),
+            shutdown_timeout_secs: default_shutdown_timeout(),
+            log_format: LogFormat::default(),
         }
     }
 }
 
+/// How the daemon writes its log
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human-readable lines
+    #[default]
+    Text,
+    /// One JSON object per line, with the fields of the spans it happened in,
+    /// for log aggregators
+    Json,
+}
+
+/// `daemon.log_format` from a configuration file, read before logging is
+/// set up
+///
+/// The file isn't validated here; [`load_config`] reports its problems
+/// once logging is running. A missing or unreadable setting is the default.
+pub fn peek_log_format(path: &Path) -> LogFormat {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| content.parse::<toml::Table>().ok())
+        .and_then(|table| table.get("daemon")?.get("log_format")?.clone().try_into().ok())
+        .unwrap_or_default()
+}
+
 /// TLS/HTTPS configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct TlsConfig {
     /// Path to certificate file (PEM format)
     pub cert: String,
@@ -106,23 +384,84 @@ fn default_heartbeat_interval() -> u64 {
     2  // Lightweight ARP/ping check every 2 seconds (when enabled)
 }
 
+fn default_offline_retry_min() -> u64 {
+    2
+}
+
+fn default_offline_retry_max() -> u64 {
+    60
+}
+
+fn default_heartbeat_jitter() -> f64 {
+    0.1
+}
+
+fn default_shutdown_timeout() -> u64 {
+    300  // Long enough for a slow upload, reboot and verify
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct DiscoveryConfig {
-    /// Subnet to scan
+    /// Subnet to scan: IPv4, or IPv6 (e.g., "fe80::") for neighbor discovery
     #[serde(default = "default_subnet")]
-    pub subnet: Ipv4Addr,
+    pub subnet: IpAddr,
     /// Subnet prefix length
     #[serde(default = "default_prefix")]
     pub prefix_len: u8,
+    /// Interface for IPv6 neighbor discovery (unset = every interface with
+    /// an address in the subnet)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interface: Option<String>,
     /// MCUmgr port
     #[serde(default = "default_mcumgr_port")]
     pub mcumgr_port: u16,
+    /// Devices queried at once after probing
+    #[serde(default = "default_query_concurrency")]
+    pub query_concurrency: usize,
+    /// Time allowed for each device's query
+    #[serde(default = "default_query_timeout_ms")]
+    pub query_timeout_ms: u64,
     /// Use LLDP for port detection
     #[serde(default = "default_true")]
     pub use_lldp: bool,
     /// Use ARP scanning
+    ///
+    /// Without it no ARP requests are sent and every subnet address is probed
+    /// for MCUmgr directly; before `use_probe` existed, turning this off still
+    /// swept the subnet with ARP.
     #[serde(default = "default_true")]
     pub use_arp: bool,
+    /// Probe candidates for MCUmgr and query the devices that answer
+    #[serde(default = "default_true")]
+    pub use_probe: bool,
+    /// Cap on active ARP scan requests per second, at most 1000 (unset = unlimited)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arp_rate_limit: Option<u32>,
+    /// Announce this host via LLDP
+    #[serde(default)]
+    pub lldp_transmit: bool,
+    /// Seconds between LLDP announcements
+    #[serde(default = "default_lldp_tx_interval")]
+    pub lldp_tx_interval_secs: u64,
+    /// Interfaces to announce on (empty = all active non-loopback interfaces)
+    #[serde(default)]
+    pub lldp_tx_interfaces: Vec<String>,
+    /// Addresses or CIDR ranges no discovery method may touch
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Routed subnets to find with an ICMP echo sweep (`[[discovery.icmp_sweep]]`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub icmp_sweep: Vec<IcmpSweepConfig>,
+    /// Browse for hosts advertising `mdns_service_types` over mDNS
+    #[serde(default)]
+    pub enable_mdns: bool,
+    /// DNS-SD service types to browse for
+    #[serde(default = "default_mdns_service_types")]
+    pub mdns_service_types: Vec<String>,
+    /// CAN bus discovery (`[discovery.can]`, disabled when absent)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub can: Option<CanConfig>,
 }
 
 impl Default for DiscoveryConfig {
@@ -130,15 +469,119 @@ impl Default for DiscoveryConfig {
         Self {
             subnet: default_subnet(),
             prefix_len: default_prefix(),
+            interface: None,
             mcumgr_port: default_mcumgr_port(),
+            query_concurrency: default_query_concurrency(),
+            query_timeout_ms: default_query_timeout_ms(),
             use_lldp: true,
             use_arp: true,
+            use_probe: true,
+            arp_rate_limit: None,
+            lldp_transmit: false,
+            lldp_tx_interval_secs: default_lldp_tx_interval(),
+            lldp_tx_interfaces: Vec::new(),
+            exclude: Vec::new(),
+            icmp_sweep: Vec::new(),
+            enable_mdns: false,
+            mdns_service_types: default_mdns_service_types(),
+            can: None,
         }
     }
 }
 
-fn default_subnet() -> Ipv4Addr {
-    Ipv4Addr::new(192, 168, 186, 0)
+impl DiscoveryConfig {
+    /// Discovery phases the `use_*` switches leave enabled
+    pub fn methods(&self) -> DiscoveryMethods {
+        let mut methods = DiscoveryMethods::empty();
+        methods.set(DiscoveryMethods::LLDP, self.use_lldp);
+        methods.set(DiscoveryMethods::ARP, self.use_arp);
+        methods.set(DiscoveryMethods::PROBE, self.use_probe);
+        methods
+    }
+
+    /// Whether a method is enabled that finds devices without a local
+    /// interface on `subnet`: an ICMP sweep of a routed subnet, mDNS or CAN
+    pub fn scans_without_interface(&self) -> bool {
+        !self.icmp_sweep.is_empty() || self.enable_mdns || self.can.is_some()
+    }
+}
+
+/// CAN buses to discover nodes on
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CanConfig {
+    /// Interfaces to scan (empty = every CAN interface)
+    #[serde(default)]
+    pub interfaces: Vec<String>,
+    /// Identification request (`[discovery.can.identify]`); without one only
+    /// nodes declared by a `[[device_override]]` with hwid
+    /// `can:<bus>:<node id>` are registered
+    #[serde(default)]
+    pub identify: Option<CanIdentifyConfig>,
+}
+
+/// Application-defined CAN identification request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CanIdentifyConfig {
+    /// CAN ID of the request frame
+    pub request_id: u32,
+    /// Request payload (at most 8 bytes)
+    #[serde(default)]
+    pub request_data: Vec<u8>,
+    /// Node `n` answers with CAN ID `response_base + n`
+    pub response_base: u32,
+    /// Highest node id answers are accepted from
+    #[serde(default = "default_can_max_node_id")]
+    pub max_node_id: u8,
+    /// Use 29-bit (extended) CAN IDs
+    #[serde(default)]
+    pub extended: bool,
+    /// Milliseconds to collect answers after the request
+    #[serde(default = "default_can_listen_ms")]
+    pub listen_ms: u64,
+}
+
+fn default_can_max_node_id() -> u8 {
+    127
+}
+
+fn default_can_listen_ms() -> u64 {
+    200
+}
+
+/// A subnet behind a router, found by pinging every host
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IcmpSweepConfig {
+    pub subnet: Ipv4Addr,
+    #[serde(default = "default_prefix")]
+    pub prefix_len: u8,
+    /// IP time-to-live (must cover the hops to the subnet)
+    #[serde(default = "default_icmp_ttl")]
+    pub ttl: u8,
+    /// Echo requests per second
+    #[serde(default = "default_icmp_rate")]
+    pub rate_pps: u32,
+    /// Milliseconds to wait for replies after the last request
+    #[serde(default = "default_icmp_timeout")]
+    pub timeout_ms: u64,
+}
+
+fn default_icmp_ttl() -> u8 {
+    64
+}
+
+fn default_icmp_rate() -> u32 {
+    200
+}
+
+fn default_icmp_timeout() -> u64 {
+    1000
+}
+
+fn default_subnet() -> IpAddr {
+    IpAddr::V4(Ipv4Addr::new(192, 168, 186, 0))
 }
 
 fn default_prefix() -> u8 {
@@ -149,11 +592,28 @@ fn default_mcumgr_port() -> u16 {
     1337
 }
 
+fn default_query_concurrency() -> usize {
+    16
+}
+
+fn default_query_timeout_ms() -> u64 {
+    5000
+}
+
+fn default_lldp_tx_interval() -> u64 {
+    30
+}
+
+fn default_mdns_service_types() -> Vec<String> {
+    mdns::DEFAULT_SERVICE_TYPES.iter().map(|t| t.to_string()).collect()
+}
+
 fn default_true() -> bool {
     true
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct ParentDeviceConfig {
     /// Parent device name
     pub name: String,
@@ -164,23 +624,54 @@ pub struct ParentDeviceConfig {
     pub ports: u8,
     /// Parent IP address (optional)
     pub ip: Option<Ipv4Addr>,
+    /// Devices with an address in this network hang off this parent
+    #[serde(default)]
+    pub subnet: Option<IpNet>,
+    /// Devices heard on this local interface (ARP entry, LLDP neighbor or
+    /// CAN bus) hang off this parent
+    #[serde(default)]
+    pub interface: Option<String>,
 }
 
 fn default_ports() -> u8 {
     6
 }
 
+/// A single table or an array of them
+fn one_or_many<'de, D, T>(deserializer: D) -> std::result::Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany<T> {
+        One(T),
+        Many(Vec<T>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(item) => vec![item],
+        OneOrMany::Many(items) => items,
+    })
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct ModelsConfig {
     /// Path to 3D model files
     #[serde(default = "default_models_path")]
     pub path: String,
+    /// Largest accepted model override upload, in bytes
+    #[serde(default = "default_max_override_bytes")]
+    pub max_override_bytes: usize,
 }
 
 impl Default for ModelsConfig {
     fn default() -> Self {
         Self {
             path: default_models_path(),
+            max_override_bytes: default_max_override_bytes(),
         }
     }
 }
@@ -189,7 +680,12 @@ fn default_models_path() -> String {
     "./assets/models".to_string()
 }
 
+fn default_max_override_bytes() -> usize {
+    32 * 1024 * 1024
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct HcdfConfig {
     /// Path to HCDF file
     #[serde(default = "default_hcdf_path")]
@@ -197,6 +693,18 @@ pub struct HcdfConfig {
     /// Auto-save interval in seconds (0 to disable)
     #[serde(default)]
     pub autosave_interval_secs: u64,
+    /// Quiet period before writing the file after edits such as position drags
+    #[serde(default = "default_save_debounce_ms")]
+    pub save_debounce_ms: u64,
+    /// Longest the file waits for that quiet period while edits keep coming
+    #[serde(default = "default_save_max_delay_ms")]
+    pub save_max_delay_ms: u64,
+    /// Device moves shorter than this are left out of HCDF diffs (millimeters)
+    #[serde(default = "default_diff_position_tolerance_mm")]
+    pub diff_position_tolerance_mm: f64,
+    /// Device turns smaller than this are left out of HCDF diffs (degrees)
+    #[serde(default = "default_diff_angle_tolerance_deg")]
+    pub diff_angle_tolerance_deg: f64,
 }
 
 impl Default for HcdfConfig {
@@ -204,6 +712,20 @@ impl Default for HcdfConfig {
         Self {
             path: default_hcdf_path(),
             autosave_interval_secs: 0,
+            save_debounce_ms: default_save_debounce_ms(),
+            save_max_delay_ms: default_save_max_delay_ms(),
+            diff_position_tolerance_mm: default_diff_position_tolerance_mm(),
+            diff_angle_tolerance_deg: default_diff_angle_tolerance_deg(),
+        }
+    }
+}
+
+impl HcdfConfig {
+    /// Pose changes HCDF diffs take as noise
+    pub fn diff_tolerance(&self) -> dendrite_core::PoseTolerance {
+        dendrite_core::PoseTolerance {
+            position: self.diff_position_tolerance_mm / 1000.0,
+            angle: self.diff_angle_tolerance_deg.to_radians(),
         }
     }
 }
@@ -212,17 +734,38 @@ fn default_hcdf_path() -> String {
     "./dendrite.hcdf".to_string()
 }
 
+fn default_save_debounce_ms() -> u64 {
+    500
+}
+
+fn default_save_max_delay_ms() -> u64 {
+    5000
+}
+
+fn default_diff_position_tolerance_mm() -> f64 {
+    1.0
+}
+
+fn default_diff_angle_tolerance_deg() -> f64 {
+    0.1
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct FragmentsConfig {
     /// Path to fragments index file
     #[serde(default = "default_fragments_path")]
     pub path: String,
+    /// Reload when the index or one of its HCDF files changes on disk
+    #[serde(default)]
+    pub watch: bool,
 }
 
 impl Default for FragmentsConfig {
     fn default() -> Self {
         Self {
             path: default_fragments_path(),
+            watch: false,
         }
     }
 }
@@ -232,54 +775,158 @@ fn default_fragments_path() -> String {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct DeviceOverrideConfig {
     /// Hardware ID to match
+    #[serde(default)]
     pub hwid: String,
+    /// Hostname to match instead, for devices found over mDNS
+    #[serde(default)]
+    pub hostname: Option<String>,
     /// Override name
     pub name: Option<String>,
     /// Override port number
     pub port: Option<u8>,
     /// Override model path
     pub model_path: Option<String>,
+    /// Board to match fragments by, for devices that can't report it (CAN nodes)
+    #[serde(default)]
+    pub board: Option<String>,
+    /// Application to match fragments by, for devices that can't report it
+    #[serde(default)]
+    pub app: Option<String>,
+    /// Address of a static device, added even when discovery never finds it
+    #[serde(default)]
+    pub ip: Option<IpAddr>,
+    /// Initial pose of a static device, [x, y, z, roll, pitch, yaw]
+    #[serde(default)]
+    pub pose: Option<[f64; 6]>,
+    /// Serial port the device's console is on (e.g. "/dev/ttyACM0"), for
+    /// `/api/devices/{id}/console`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub serial_port: Option<PathBuf>,
+    /// Baud rate of `serial_port` (default 115200)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub serial_baud_rate: Option<u32>,
 }
 
 impl Config {
+    /// Directory next to the fragments index holding the fragment cache and traces
+    pub fn data_dir(&self) -> &Path {
+        Path::new(&self.fragments.path).parent().unwrap_or(Path::new("."))
+    }
+
+    /// Name of the parent `device` hangs off: the one the scanner assigned it
+    /// to, else the first one configured
+    pub fn parent_name_for(&self, device: &Device) -> Option<&str> {
+        device
+            .parent_id
+            .as_ref()
+            .and_then(|id| self.parent.iter().find(|p| p.name == id.as_str()))
+            .or(self.parent.first())
+            .map(|p| p.name.as_str())
+    }
+
+    /// Serial port an override gives the device with hardware ID `hwid`
+    pub fn serial_port(&self, hwid: &str) -> Option<SerialPortConfig> {
+        let device = self.device_overrides.iter().find(|o| !o.hwid.is_empty() && o.hwid == hwid)?;
+        let port = SerialPortConfig::new(device.serial_port.as_ref()?);
+        Some(match device.serial_baud_rate {
+            Some(baud_rate) => port.with_baud_rate(baud_rate),
+            None => port,
+        })
+    }
+
     /// Convert to ScannerConfig
     pub fn to_scanner_config(&self) -> ScannerConfig {
         ScannerConfig {
-            subnet: self.discovery.subnet,
-            prefix_len: self.discovery.prefix_len,
+            subnet: IpNet::from((self.discovery.subnet, self.discovery.prefix_len)),
+            interface: self.discovery.interface.clone(),
             mcumgr_port: self.discovery.mcumgr_port,
+            query_concurrency: self.discovery.query_concurrency,
+            query_timeout_ms: self.discovery.query_timeout_ms,
             interval_secs: self.daemon.discovery_interval_secs,
             heartbeat_interval_secs: self.daemon.heartbeat_interval_secs,
             heartbeat_enabled: self.daemon.heartbeat_enabled,
-            use_lldp: self.discovery.use_lldp,
-            use_arp: self.discovery.use_arp,
-            parent: self.parent.as_ref().map(|p| ParentConfig {
-                name: p.name.clone(),
-                board: p.board.clone(),
-                ports: p.ports,
-                ip: p.ip,
-            }),
+            offline_retry_min_secs: self.daemon.offline_retry_min_secs,
+            offline_retry_max_secs: self.daemon.offline_retry_max_secs,
+            heartbeat_jitter: self.daemon.heartbeat_jitter,
+            methods: self.discovery.methods(),
+            arp_rate_limit: self.discovery.arp_rate_limit,
+            icmp_sweeps: self
+                .discovery
+                .icmp_sweep
+                .iter()
+                .map(|s| IcmpSweep {
+                    subnet: s.subnet,
+                    prefix_len: s.prefix_len,
+                    ttl: s.ttl,
+                    rate_pps: s.rate_pps,
+                    timeout_ms: s.timeout_ms,
+                })
+                .collect(),
+            enable_mdns: self.discovery.enable_mdns,
+            mdns_service_types: self.discovery.mdns_service_types.clone(),
+            exclude: self.discovery.exclude.clone(),
+            parents: self
+                .parent
+                .iter()
+                .map(|p| ParentConfig {
+                    name: p.name.clone(),
+                    board: p.board.clone(),
+                    ports: p.ports,
+                    ip: p.ip,
+                    subnet: p.subnet,
+                    interface: p.interface.clone(),
+                })
+                .collect(),
             overrides: self
                 .device_overrides
                 .iter()
                 .map(|o| DeviceOverride {
                     hwid: o.hwid.clone(),
+                    hostname: o.hostname.clone(),
                     name: o.name.clone(),
                     port: o.port,
                     model_path: o.model_path.clone(),
+                    board: o.board.clone(),
+                    app: o.app.clone(),
+                    ip: o.ip,
+                    pose: o.pose,
                 })
                 .collect(),
+            can: self.discovery.can.as_ref().map(|can| CanDiscoveryConfig {
+                interfaces: can.interfaces.clone(),
+                identify: can.identify.as_ref().map(|i| CanIdentify {
+                    request_id: i.request_id,
+                    request_data: i.request_data.clone(),
+                    response_base: i.response_base,
+                    max_node_id: i.max_node_id,
+                    extended: i.extended,
+                    listen_ms: i.listen_ms,
+                }),
+            }),
         }
     }
+
+    /// Convert to LldpTxConfig (None when LLDP transmission is disabled)
+    pub fn to_lldp_tx_config(&self) -> Option<LldpTxConfig> {
+        self.discovery.lldp_transmit.then(|| LldpTxConfig {
+            interval_secs: self.discovery.lldp_tx_interval_secs,
+            interfaces: self.discovery.lldp_tx_interfaces.clone(),
+            system_name: None,
+        })
+    }
 }
 
 /// Load configuration from file
+///
+/// Every problem in the file is reported at once (see [`check_config`]).
 pub fn load_config(path: &Path) -> Result<Config> {
     if path.exists() {
         let content = std::fs::read_to_string(path)?;
-        let config: Config = toml::from_str(&content)?;
+        let config = check_config(&content)
+            .map_err(|errors| anyhow::anyhow!("Invalid configuration {}:\n{}", path.display(), errors))?;
         info!(path = %path.display(), "Loaded configuration");
         Ok(config)
     } else {
@@ -287,43 +934,895 @@ pub fn load_config(path: &Path) -> Result<Config> {
             path = %path.display(),
             "Configuration file not found, using defaults"
         );
-        Ok(Config {
-            daemon: DaemonConfig::default(),
-            discovery: DiscoveryConfig::default(),
-            parent: None,
-            models: ModelsConfig::default(),
-            hcdf: HcdfConfig::default(),
-            fragments: FragmentsConfig::default(),
-            device_overrides: Vec::new(),
-            auth: AuthConfig::default(),
-        })
+        Ok(Config::default())
+    }
+}
+
+// ============================================================================
+// Validation
+// ============================================================================
+
+/// Placeholder for redacted values in the effective configuration
+pub(crate) const REDACTED: &str = "<redacted>";
+
+/// A single problem found in a configuration file
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfigIssue {
+    /// Dotted TOML key path (e.g., "discovery.prefix_len", "device_override[1].hwid")
+    pub key: String,
+    /// 1-based line number of the key, when it could be located
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(line) = self.line {
+            write!(f, "line {}: ", line)?;
+        }
+        if self.key.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.key, self.message)
+        }
+    }
+}
+
+/// All problems found in a configuration file
+#[derive(Debug)]
+pub struct ConfigErrors(pub Vec<ConfigIssue>);
+
+impl std::error::Error for ConfigErrors {}
+
+impl fmt::Display for ConfigErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, issue) in self.0.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "  {}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse and validate a configuration file
+///
+/// Unknown keys (with a did-you-mean suggestion), type mismatches, missing
+/// required keys and semantic constraints are all collected before returning.
+pub fn check_config(content: &str) -> std::result::Result<Config, ConfigErrors> {
+    let root: toml::Table = toml::from_str(content).map_err(|e| {
+        let line = e.span().map(|span| line_of_offset(content, span.start));
+        ConfigErrors(vec![ConfigIssue {
+            key: String::new(),
+            line,
+            message: e.message().trim().to_string(),
+        }])
+    })?;
+
+    let mut checker = Checker { source: content, issues: Vec::new() };
+    let cleaned = checker.table::<Config>("", &root, &|c, path, key, value| match (key, value) {
+        ("daemon", Value::Table(t)) => Some(c.table::<DaemonConfig>(path, t, &|c, path, key, value| {
+            match (key, value) {
+                ("tls", Value::Table(t)) => Some(c.table::<TlsConfig>(path, t, &no_nested)),
+                _ => None,
+            }
+        })),
+        ("discovery", Value::Table(t)) => Some(c.table::<DiscoveryConfig>(path, t, &|c, path, key, value| {
+            match (key, value) {
+                ("can", Value::Table(t)) => Some(c.table::<CanConfig>(path, t, &|c, path, key, value| {
+                    match (key, value) {
+                        ("identify", Value::Table(t)) => Some(c.table::<CanIdentifyConfig>(path, t, &no_nested)),
+                        _ => None,
+                    }
+                })),
+                ("icmp_sweep", Value::Array(items)) if items.iter().all(Value::is_table) => {
+                    let items = items
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(i, item)| {
+                            let path = format!("{}[{}]", path, i);
+                            item.as_table().map(|t| c.table::<IcmpSweepConfig>(&path, t, &no_nested))
+                        })
+                        .collect();
+                    Some(Value::Array(items))
+                }
+                _ => None,
+            }
+        })),
+        ("parent", Value::Table(t)) => Some(c.table::<ParentDeviceConfig>(path, t, &no_nested)),
+        ("parent", Value::Array(items)) if items.iter().all(Value::is_table) => {
+            let items = items
+                .iter()
+                .enumerate()
+                .filter_map(|(i, item)| {
+                    let path = format!("{}[{}]", path, i);
+                    item.as_table().map(|t| c.table::<ParentDeviceConfig>(&path, t, &no_nested))
+                })
+                .collect();
+            Some(Value::Array(items))
+        }
+        ("models", Value::Table(t)) => Some(c.table::<ModelsConfig>(path, t, &no_nested)),
+        ("hcdf", Value::Table(t)) => Some(c.table::<HcdfConfig>(path, t, &no_nested)),
+        ("fragments", Value::Table(t)) => Some(c.table::<FragmentsConfig>(path, t, &no_nested)),
+        ("auth", Value::Table(t)) => Some(c.table::<AuthConfig>(path, t, &no_nested)),
+        ("journal", Value::Table(t)) => Some(c.table::<JournalConfig>(path, t, &no_nested)),
+        ("attention", Value::Table(t)) => Some(c.table::<AttentionConfig>(path, t, &no_nested)),
+        ("shell", Value::Table(t)) => Some(c.table::<ShellConfig>(path, t, &no_nested)),
+        ("status_led", Value::Table(t)) => Some(c.table::<StatusLedConfig>(path, t, &|c, path, key, value| {
+            match (key, value) {
+                ("colors", Value::Table(t)) => Some(c.table::<StatusLedColors>(path, t, &no_nested)),
+                _ => None,
+            }
+        })),
+        ("device_override", Value::Array(items)) if items.iter().all(Value::is_table) => {
+            let items = items
+                .iter()
+                .enumerate()
+                .filter_map(|(i, item)| {
+                    let path = format!("{}[{}]", path, i);
+                    item.as_table().map(|t| c.table::<DeviceOverrideConfig>(&path, t, &no_nested))
+                })
+                .collect();
+            Some(Value::Array(items))
+        }
+        _ => None,
+    });
+
+    // Semantic checks run on what remains once bad keys are dropped, unless a
+    // required key is missing and the sections can't be built at all
+    let config: Config = match cleaned.try_into() {
+        Ok(config) => config,
+        Err(e) => {
+            if checker.issues.is_empty() {
+                checker.push("", e.message().trim());
+            }
+            return Err(ConfigErrors(checker.issues));
+        }
+    };
+
+    for (key, message) in config.validate() {
+        checker.push(&key, &message);
+    }
+    if checker.issues.is_empty() {
+        Ok(config)
+    } else {
+        Err(ConfigErrors(checker.issues))
+    }
+}
+
+impl Config {
+    /// Semantic checks on a well-typed configuration, as (key, message) pairs
+    fn validate(&self) -> Vec<(String, String)> {
+        let mut problems = Vec::new();
+        let mut check = |ok: bool, key: &str, message: String| {
+            if !ok {
+                problems.push((key.to_string(), message));
+            }
+        };
+
+        let max_prefix = if self.discovery.subnet.is_ipv4() { 32 } else { 128 };
+        check(
+            self.discovery.prefix_len <= max_prefix,
+            "discovery.prefix_len",
+            format!("must be at most {} (got {})", max_prefix, self.discovery.prefix_len),
+        );
+        check(
+            self.discovery.query_concurrency >= 1,
+            "discovery.query_concurrency",
+            "must be at least 1".to_string(),
+        );
+        check(
+            self.discovery.query_timeout_ms >= 1,
+            "discovery.query_timeout_ms",
+            "must be at least 1".to_string(),
+        );
+        if let Some(rate) = self.discovery.arp_rate_limit {
+            check(
+                (1..=arp::MAX_ARP_RATE).contains(&rate),
+                "discovery.arp_rate_limit",
+                format!("must be between 1 and {} (got {})", arp::MAX_ARP_RATE, rate),
+            );
+        }
+        for (i, entry) in self.discovery.exclude.iter().enumerate() {
+            check(
+                entry.trim().parse::<IpNet>().is_ok(),
+                "discovery.exclude",
+                format!("entry {} is not an address or CIDR range: {:?}", i, entry),
+            );
+        }
+        for (i, sweep) in self.discovery.icmp_sweep.iter().enumerate() {
+            let key = format!("discovery.icmp_sweep[{}]", i);
+            check(
+                sweep.prefix_len <= 32,
+                &format!("{}.prefix_len", key),
+                format!("must be at most 32 (got {})", sweep.prefix_len),
+            );
+            check(sweep.ttl >= 1, &format!("{}.ttl", key), "must be at least 1".to_string());
+            check(sweep.rate_pps >= 1, &format!("{}.rate_pps", key), "must be at least 1".to_string());
+        }
+        for (i, service_type) in self.discovery.mdns_service_types.iter().enumerate() {
+            check(
+                mdns::is_service_type(service_type),
+                "discovery.mdns_service_types",
+                format!("entry {} is not a service type like \"_dendrite._udp\": {:?}", i, service_type),
+            );
+        }
+        for (i, parent) in self.parent.iter().enumerate() {
+            check(
+                !self.parent[..i].iter().any(|p| p.name == parent.name),
+                &format!("parent[{}].name", i),
+                format!("another parent is already named {:?}", parent.name),
+            );
+            if i > 0 {
+                check(
+                    !(parent.subnet.is_none() && parent.interface.is_none()),
+                    &format!("parent[{}]", i),
+                    "needs a subnet or an interface to tell its devices from the first parent's".to_string(),
+                );
+            }
+        }
+        for (i, device) in self.device_overrides.iter().enumerate() {
+            check(
+                !device.hwid.is_empty() || device.hostname.is_some() || device.ip.is_some(),
+                &format!("device_override[{}]", i),
+                "needs a hwid or a hostname to match, or an ip for a static device".to_string(),
+            );
+            if device.serial_port.is_some() {
+                check(
+                    !device.hwid.is_empty(),
+                    &format!("device_override[{}].serial_port", i),
+                    "needs the device's hwid".to_string(),
+                );
+            }
+            if let Some(baud_rate) = device.serial_baud_rate {
+                check(baud_rate >= 1, &format!("device_override[{}].serial_baud_rate", i), "must be at least 1".to_string());
+            }
+        }
+        if let Some(identify) = self.discovery.can.as_ref().and_then(|can| can.identify.as_ref()) {
+            let max_id: u32 = if identify.extended { 0x1fff_ffff } else { 0x7ff };
+            check(
+                identify.request_id <= max_id,
+                "discovery.can.identify.request_id",
+                format!("must fit in {} bits (got {:#x})", if identify.extended { 29 } else { 11 }, identify.request_id),
+            );
+            check(
+                identify.response_base.saturating_add(identify.max_node_id.into()) <= max_id,
+                "discovery.can.identify.response_base",
+                format!("answers up to node {} don't fit in a CAN ID", identify.max_node_id),
+            );
+            check(
+                identify.request_data.len() <= 8,
+                "discovery.can.identify.request_data",
+                format!("at most 8 bytes fit in a CAN frame (got {})", identify.request_data.len()),
+            );
+        }
+        for (key, secs) in [
+            ("daemon.discovery_interval_secs", self.daemon.discovery_interval_secs),
+            ("daemon.heartbeat_interval_secs", self.daemon.heartbeat_interval_secs),
+            ("daemon.offline_retry_min_secs", self.daemon.offline_retry_min_secs),
+            ("discovery.lldp_tx_interval_secs", self.discovery.lldp_tx_interval_secs),
+            ("status_led.min_interval_secs", self.status_led.min_interval_secs),
+            ("journal.snapshot_interval_secs", self.journal.snapshot_interval_secs),
+        ] {
+            check(secs >= 1, key, format!("must be at least 1 second (got {})", secs));
+        }
+        check(
+            self.daemon.offline_retry_max_secs >= self.daemon.offline_retry_min_secs,
+            "daemon.offline_retry_max_secs",
+            format!(
+                "must be at least offline_retry_min_secs ({}, got {})",
+                self.daemon.offline_retry_min_secs, self.daemon.offline_retry_max_secs
+            ),
+        );
+        for (key, tolerance) in [
+            ("hcdf.diff_position_tolerance_mm", self.hcdf.diff_position_tolerance_mm),
+            ("hcdf.diff_angle_tolerance_deg", self.hcdf.diff_angle_tolerance_deg),
+        ] {
+            check(tolerance >= 0.0, key, format!("must not be negative (got {})", tolerance));
+        }
+        check(self.shell.timeout_ms >= 1, "shell.timeout_ms", "must be at least 1 ms".to_string());
+        for (i, command) in self.shell.allowed_commands.iter().enumerate() {
+            check(
+                !command.trim().is_empty(),
+                &format!("shell.allowed_commands[{}]", i),
+                "must not be empty".to_string(),
+            );
+        }
+        check(
+            (0.0..=1.0).contains(&self.daemon.heartbeat_jitter),
+            "daemon.heartbeat_jitter",
+            format!("must be between 0 and 1 (got {})", self.daemon.heartbeat_jitter),
+        );
+        if let Some(tls) = &self.daemon.tls {
+            for (key, path) in [("daemon.tls.cert", &tls.cert), ("daemon.tls.key", &tls.key)] {
+                check(Path::new(path).exists(), key, format!("file not found: {}", path));
+            }
+        }
+        problems
+    }
+
+    /// Effective configuration with secrets and key material paths redacted
+    pub fn redacted(&self) -> Self {
+        let mut config = self.clone();
+        if let Some(tls) = config.daemon.tls.as_mut() {
+            tls.key = REDACTED.to_string();
+        }
+        if let Some(token) = config.daemon.auth_token.as_mut() {
+            *token = REDACTED.to_string();
+        }
+        config.auth.token_store_path = REDACTED.to_string();
+        config
+    }
+}
+
+/// Handler for tables without nested sections
+fn no_nested(_: &mut Checker, _: &str, _: &str, _: &Value) -> Option<Value> {
+    None
+}
+
+/// Walks a parsed config table section by section, collecting every problem
+struct Checker<'a> {
+    source: &'a str,
+    issues: Vec<ConfigIssue>,
+}
+
+impl Checker<'_> {
+    fn push(&mut self, key: &str, message: &str) {
+        self.issues.push(ConfigIssue {
+            key: key.to_string(),
+            line: locate_key(self.source, key),
+            message: message.to_string(),
+        });
+    }
+
+    /// Check a table against the fields of `T`, returning it with bad keys removed
+    ///
+    /// `nested` checks keys that are themselves sections and returns their
+    /// cleaned value, or None to check the key as a plain value of `T`.
+    fn table<T: DeserializeOwned>(
+        &mut self,
+        path: &str,
+        table: &toml::Table,
+        nested: &dyn Fn(&mut Self, &str, &str, &Value) -> Option<Value>,
+    ) -> Value {
+        let fields = field_names::<T>();
+        let mut cleaned = toml::Table::new();
+        let mut plain = toml::Table::new();
+
+        for (key, value) in table {
+            let key_path = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+
+            if !fields.contains(&key.as_str()) {
+                let message = match did_you_mean(key, fields) {
+                    Some(suggestion) => format!("unknown key (did you mean `{}`?)", suggestion),
+                    None => format!("unknown key (expected one of: {})", fields.join(", ")),
+                };
+                self.push(&key_path, &message);
+                continue;
+            }
+
+            if let Some(value) = nested(self, &key_path, key, value) {
+                cleaned.insert(key.clone(), value);
+                continue;
+            }
+
+            let mut single = toml::Table::new();
+            single.insert(key.clone(), value.clone());
+            match Value::Table(single).try_into::<T>() {
+                Err(e) if !is_missing_field(&e) => self.push(&key_path, e.message().trim()),
+                _ => {
+                    cleaned.insert(key.clone(), value.clone());
+                    plain.insert(key.clone(), value.clone());
+                }
+            }
+        }
+
+        // Required keys can only be checked with the whole section present
+        if let Err(e) = Value::Table(plain).try_into::<T>() {
+            if is_missing_field(&e) {
+                self.push(path, e.message().trim());
+            }
+        }
+
+        Value::Table(cleaned)
+    }
+}
+
+fn is_missing_field(e: &toml::de::Error) -> bool {
+    e.message().starts_with("missing field")
+}
+
+/// Serialized field names of a struct, taken from its `Deserialize` impl
+fn field_names<T: DeserializeOwned>() -> &'static [&'static str] {
+    struct Introspect<'a>(&'a mut &'static [&'static str]);
+
+    impl<'de> Deserializer<'de> for Introspect<'_> {
+        type Error = de::value::Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, _: V) -> std::result::Result<V::Value, Self::Error> {
+            Err(de::Error::custom("not a struct"))
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            fields: &'static [&'static str],
+            _visitor: V,
+        ) -> std::result::Result<V::Value, Self::Error> {
+            *self.0 = fields;
+            Err(de::Error::custom("introspected"))
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf option unit unit_struct newtype_struct seq tuple
+            tuple_struct map enum identifier ignored_any
+        }
+    }
+
+    let mut fields: &'static [&'static str] = &[];
+    let _ = T::deserialize(Introspect(&mut fields));
+    fields
+}
+
+/// Closest known key by edit distance, if it is plausibly a typo
+fn did_you_mean<'a>(key: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    let max_distance = (key.len() / 3).max(2);
+    candidates
+        .iter()
+        .map(|c| (edit_distance(key, c), *c))
+        .filter(|(d, _)| *d <= max_distance)
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, c)| c)
+}
+
+/// Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut row = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = prev[j] + usize::from(ca != *cb);
+            row[j + 1] = substitution.min(prev[j + 1] + 1).min(row[j] + 1);
+        }
+        prev = row;
+    }
+    prev[b.len()]
+}
+
+fn line_of_offset(source: &str, offset: usize) -> usize {
+    source[..offset.min(source.len())].matches('\n').count() + 1
+}
+
+/// Find the line defining a dotted key path, falling back to its closest parent
+///
+/// Handles `[table]` and `[[array]]` headers and dotted keys; keys inside
+/// inline tables resolve to the line of the enclosing key.
+fn locate_key(source: &str, path: &str) -> Option<usize> {
+    let mut lines: Vec<(String, usize)> = Vec::new();
+    let mut header = String::new();
+    let mut array_counts: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+
+    for (i, line) in source.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix("[[").and_then(|l| l.split("]]").next()) {
+            let name = normalize_key(name);
+            let index = array_counts.entry(name.clone()).or_insert(0);
+            header = format!("{}[{}]", name, index);
+            *index += 1;
+            lines.push((header.clone(), i + 1));
+        } else if let Some(name) = line.strip_prefix('[').and_then(|l| l.split(']').next()) {
+            header = normalize_key(name);
+            lines.push((header.clone(), i + 1));
+        } else if let Some((key, _)) = line.split_once('=') {
+            let key = normalize_key(key);
+            let full = if header.is_empty() { key } else { format!("{}.{}", header, key) };
+            lines.push((full, i + 1));
+        }
+    }
+
+    let mut path = path;
+    loop {
+        if let Some((_, line)) = lines.iter().find(|(key, _)| key == path) {
+            return Some(*line);
+        }
+        path = &path[..path.rfind(['.', '['])?];
     }
 }
 
+/// Strip whitespace and quotes from each segment of a dotted key
+fn normalize_key(key: &str) -> String {
+    key.split('.')
+        .map(|segment| segment.trim().trim_matches('"').trim_matches('\''))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
 /// Save default configuration to file
 pub fn save_default_config(path: &Path) -> Result<()> {
     let config = Config {
         daemon: DaemonConfig::default(),
         discovery: DiscoveryConfig::default(),
-        parent: Some(ParentDeviceConfig {
+        parent: vec![ParentDeviceConfig {
             name: "navq95".to_string(),
             board: "imx95-navq".to_string(),
             ports: 6,
             ip: None,
-        }),
+            subnet: None,
+            interface: None,
+        }],
         models: ModelsConfig::default(),
         hcdf: HcdfConfig::default(),
         fragments: FragmentsConfig::default(),
         device_overrides: vec![DeviceOverrideConfig {
             hwid: "0x12345678".to_string(),
+            hostname: None,
             name: Some("spinali-front-left".to_string()),
             port: Some(2),
             model_path: Some("models/spinali.glb".to_string()),
+            board: None,
+            app: None,
+            ip: None,
+            pose: None,
+            serial_port: None,
+            serial_baud_rate: None,
         }],
         auth: AuthConfig::default(),
+        status_led: StatusLedConfig::default(),
+        journal: JournalConfig::default(),
+        attention: AttentionConfig::default(),
     };
 
     let content = toml::to_string_pretty(&config)?;
     std::fs::write(path, content)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dendrite_core::DeviceId;
+
+    #[test]
+    fn test_defaults_and_repo_config_are_valid() {
+        assert!(check_config("").is_ok());
+
+        let config = check_config(include_str!("../../../dendrite.toml")).unwrap();
+        assert_eq!(config.discovery.prefix_len, 24);
+    }
+
+    #[test]
+    fn test_unknown_key_suggests_closest() {
+        let errors = check_config("[discovery]\nprefix_len = 24\nsubnett = \"10.0.0.0\"\n").unwrap_err();
+        assert_eq!(errors.0.len(), 1);
+
+        let issue = &errors.0[0];
+        assert_eq!(issue.key, "discovery.subnett");
+        assert_eq!(issue.line, Some(3));
+        assert!(issue.message.contains("did you mean `subnet`"), "{}", issue.message);
+    }
+
+    #[test]
+    fn test_type_mismatch_names_key() {
+        let errors = check_config("[daemon]\nbind = \"0.0.0.0:8080\"\nheartbeat_enabled = \"yes\"\n").unwrap_err();
+        assert_eq!(errors.0.len(), 1);
+        assert_eq!(errors.0[0].key, "daemon.heartbeat_enabled");
+        assert_eq!(errors.0[0].line, Some(3));
+        assert!(errors.0[0].message.contains("invalid type"), "{}", errors.0[0].message);
+    }
+
+    #[test]
+    fn test_reports_every_problem() {
+        let content = r#"
+[daemon]
+heartbeat_interval_secs = "2"
+tls = { cert = "/nonexistent/cert.pem", key = "/nonexistent/key.pem", pasword = "x" }
+
+[discovery]
+prefix_len = 24
+use_lldpp = true
+
+[[device_override]]
+hwid = "0x1"
+
+[parent]
+name = "missing-board"
+
+[colour]
+theme = "dark"
+"#;
+        let errors = check_config(content).unwrap_err();
+        let keys: Vec<_> = errors.0.iter().map(|i| i.key.as_str()).collect();
+        assert_eq!(
+            keys,
+            vec![
+                "colour",
+                "daemon.heartbeat_interval_secs",
+                "daemon.tls.pasword",
+                "discovery.use_lldpp",
+                "parent",
+            ]
+        );
+
+        let missing = &errors.0[4];
+        assert_eq!(missing.line, Some(13));
+        assert!(missing.message.contains("board"), "{}", missing.message);
+
+        // Keys inside inline tables resolve to the enclosing key
+        assert_eq!(errors.0[2].line, Some(4));
+    }
+
+    #[test]
+    fn test_semantic_checks_run_alongside_structural_errors() {
+        let content = "[daemon]\nheartbeat_interval_secs = 0\n\n[discovery]\nprefix_lenn = 24\n";
+        let errors = check_config(content).unwrap_err();
+        let keys: Vec<_> = errors.0.iter().map(|i| i.key.as_str()).collect();
+        assert_eq!(keys, vec!["discovery.prefix_lenn", "daemon.heartbeat_interval_secs"]);
+    }
+
+    #[test]
+    fn test_semantic_constraints() {
+        let content = "[daemon]\ndiscovery_interval_secs = 0\n\n[discovery]\nprefix_len = 40\n";
+        let errors = check_config(content).unwrap_err();
+        let found: Vec<_> = errors.0.iter().map(|i| (i.key.as_str(), i.line)).collect();
+        assert_eq!(
+            found,
+            vec![("discovery.prefix_len", Some(5)), ("daemon.discovery_interval_secs", Some(2))]
+        );
+
+        let errors = check_config("[discovery]\nquery_concurrency = 0\n").unwrap_err();
+        assert_eq!(errors.0[0].key, "discovery.query_concurrency");
+
+        // fping can't pace faster than one target per millisecond
+        let errors = check_config("[discovery]\narp_rate_limit = 5000\n").unwrap_err();
+        assert_eq!(errors.0[0].key, "discovery.arp_rate_limit");
+        assert!(check_config("[discovery]\narp_rate_limit = 1000\n").is_ok());
+    }
+
+    #[test]
+    fn test_mdns_and_hostname_overrides() {
+        let content = r#"
+[discovery]
+enable_mdns = true
+mdns_service_types = ["_dendrite._udp", "mcumgr"]
+
+[[device_override]]
+hostname = "jetson-01.local"
+name = "companion"
+
+[[device_override]]
+name = "matches-nothing"
+"#;
+        let errors = check_config(content).unwrap_err();
+        let found: Vec<_> = errors.0.iter().map(|i| (i.key.as_str(), i.line)).collect();
+        assert_eq!(found, vec![("discovery.mdns_service_types", Some(4)), ("device_override[1]", Some(10))]);
+
+        let config = check_config(
+            "[discovery]\nenable_mdns = true\n\n[[device_override]]\nhostname = \"jetson-01\"\nname = \"companion\"\n",
+        )
+        .unwrap();
+        let scanner = config.to_scanner_config();
+        assert!(scanner.enable_mdns);
+        assert_eq!(scanner.mdns_service_types, vec!["_dendrite._udp", "_mcumgr._udp"]);
+        assert_eq!(scanner.overrides[0].hostname.as_deref(), Some("jetson-01"));
+        assert!(scanner.overrides[0].hwid.is_empty());
+    }
+
+    #[test]
+    fn test_discovery_method_switches() {
+        assert_eq!(Config::default().to_scanner_config().methods, DiscoveryMethods::all());
+
+        let config = check_config("[discovery]\nuse_lldp = false\nuse_arp = false\n").unwrap();
+        assert_eq!(config.to_scanner_config().methods, DiscoveryMethods::PROBE);
+    }
+
+    #[test]
+    fn test_icmp_sweep_entries() {
+        let content = r#"
+[discovery]
+exclude = ["10.41.0.1", "10.41.0.0/40"]
+
+[[discovery.icmp_sweep]]
+subnet = "10.41.0.0"
+ttl = 4
+
+[[discovery.icmp_sweep]]
+subnet = "10.42.0.0"
+rate_pps = 0
+tll = 8
+"#;
+        let errors = check_config(content).unwrap_err();
+        let found: Vec<_> = errors.0.iter().map(|i| (i.key.as_str(), i.line)).collect();
+        assert_eq!(
+            found,
+            vec![
+                ("discovery.icmp_sweep[1].tll", Some(12)),
+                ("discovery.exclude", Some(3)),
+                ("discovery.icmp_sweep[1].rate_pps", Some(11)),
+            ]
+        );
+
+        let config = check_config("[[discovery.icmp_sweep]]
+subnet = \"10.41.0.0\"
+ttl = 4
+").unwrap();
+        let scanner = config.to_scanner_config();
+        assert_eq!(scanner.icmp_sweeps.len(), 1);
+        assert_eq!((scanner.icmp_sweeps[0].prefix_len, scanner.icmp_sweeps[0].ttl), (24, 4));
+        assert_eq!(scanner.icmp_sweeps[0].rate_pps, 200);
+    }
+
+    #[test]
+    fn test_serial_console_overrides() {
+        let config = check_config(
+            r#"
+[[device_override]]
+hwid = "a1b2c3"
+serial_port = "/dev/ttyACM0"
+
+[[device_override]]
+hwid = "d4e5f6"
+serial_port = "/dev/ttyUSB1"
+serial_baud_rate = 921600
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.serial_port("a1b2c3"), Some(SerialPortConfig::new("/dev/ttyACM0")));
+        assert_eq!(config.serial_port("d4e5f6").unwrap().baud_rate, 921600);
+        assert_eq!(config.serial_port("0xfeed"), None);
+
+        let errors = check_config("[[device_override]]\nip = \"10.0.0.2\"\nserial_port = \"/dev/ttyACM0\"\n").unwrap_err();
+        assert_eq!(errors.0[0].key, "device_override[0].serial_port");
+    }
+
+    #[test]
+    fn test_routed_icmp_sweep_scans_without_interface() {
+        // The bench subnet is behind a router; only the sweep reaches it
+        let routed = check_config("[discovery]
+subnet = \"10.41.0.0\"
+use_lldp = false
+use_arp = false
+
+[[discovery.icmp_sweep]]
+subnet = \"10.41.0.0\"
+ttl = 4
+").unwrap();
+        assert!(routed.discovery.scans_without_interface());
+
+        let lan_only = check_config("[discovery]\nsubnet = \"10.41.0.0\"\n").unwrap();
+        assert!(!lan_only.discovery.scans_without_interface());
+        let mdns = check_config("[discovery]\nsubnet = \"10.41.0.0\"\nenable_mdns = true\n").unwrap();
+        assert!(mdns.discovery.scans_without_interface());
+    }
+
+    #[test]
+    fn test_several_parents() {
+        let single = check_config("[parent]\nname = \"navq95\"\nboard = \"imx95-navq\"\n").unwrap();
+        assert_eq!(single.to_scanner_config().parents.len(), 1);
+
+        let content = r#"
+[[parent]]
+name = "switch-a"
+board = "t1_switch"
+subnet = "192.168.186.0/25"
+
+[[parent]]
+name = "switch-b"
+board = "t1_switch"
+interface = "eth1"
+"#;
+        let config = check_config(content).unwrap();
+        let parents = config.to_scanner_config().parents;
+        assert_eq!(parents.len(), 2);
+        assert_eq!(parents[0].subnet, Some("192.168.186.0/25".parse().unwrap()));
+        assert_eq!(parents[1].interface.as_deref(), Some("eth1"));
+
+        let mut device = Device::new(DeviceId::from_hwid("0x1"), "flow".to_string(), IpAddr::V4(Ipv4Addr::LOCALHOST), 1337);
+        assert_eq!(config.parent_name_for(&device), Some("switch-a"));
+        device.parent_id = Some(DeviceId::from_hwid("switch-b"));
+        assert_eq!(config.parent_name_for(&device), Some("switch-b"));
+
+        let content = r#"
+[[parent]]
+name = "switch-a"
+board = "t1_switch"
+
+[[parent]]
+name = "switch-a"
+board = "t1_switch"
+sbunet = "192.168.186.128/25"
+"#;
+        let errors = check_config(content).unwrap_err();
+        let keys: Vec<_> = errors.0.iter().map(|i| i.key.as_str()).collect();
+        assert_eq!(keys, vec!["parent[1].sbunet", "parent[1].name", "parent[1]"]);
+    }
+
+    #[test]
+    fn test_can_identify_config() {
+        let content = r#"
+[discovery.can]
+interfaces = ["can0"]
+
+[discovery.can.identify]
+request_id = 0x800
+response_base = 0x580
+listen = 100
+"#;
+        let errors = check_config(content).unwrap_err();
+        let keys: Vec<_> = errors.0.iter().map(|i| i.key.as_str()).collect();
+        assert_eq!(keys, vec!["discovery.can.identify.listen", "discovery.can.identify.request_id"]);
+
+        let config = check_config("[discovery.can.identify]
+request_id = 0x700
+response_base = 0x580
+").unwrap();
+        let can = config.to_scanner_config().can.unwrap();
+        assert!(can.interfaces.is_empty());
+        let identify = can.identify.unwrap();
+        assert_eq!((identify.max_node_id, identify.listen_ms, identify.extended), (127, 200, false));
+    }
+
+    #[test]
+    fn test_redacted_hides_key_material() {
+        let mut config = check_config("").unwrap();
+        config.daemon.tls = Some(TlsConfig {
+            cert: "/etc/dendrite/cert.pem".to_string(),
+            key: "/etc/dendrite/key.pem".to_string(),
+        });
+        config.daemon.auth_token = Some("s3cret".to_string());
+        let redacted = config.redacted();
+        assert_eq!(redacted.daemon.auth_token.as_deref(), Some(REDACTED));
+        let tls = redacted.daemon.tls.unwrap();
+        assert_eq!(tls.cert, "/etc/dendrite/cert.pem");
+        assert_eq!(tls.key, REDACTED);
+        assert_eq!(redacted.auth.token_store_path, REDACTED);
+    }
+
+    #[test]
+    fn test_log_format() {
+        let config = check_config("[daemon]\nlog_format = \"json\"\n").unwrap();
+        assert_eq!(config.daemon.log_format, LogFormat::Json);
+        assert_eq!(Config::default().daemon.log_format, LogFormat::Text);
+        assert!(check_config("[daemon]\nlog_format = \"xml\"\n").is_err());
+
+        // Read ahead of validation, falling back to text for anything odd
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dendrite.toml");
+        std::fs::write(&path, "[daemon]\nlog_format = \"json\"\nbogus = 1\n").unwrap();
+        assert_eq!(peek_log_format(&path), LogFormat::Json);
+        std::fs::write(&path, "[daemon]\nlog_format = \"xml\"\n").unwrap();
+        assert_eq!(peek_log_format(&path), LogFormat::Text);
+        assert_eq!(peek_log_format(&dir.path().join("missing.toml")), LogFormat::Text);
+    }
+
+    #[test]
+    fn test_shell_allowlist() {
+        let config = check_config("[shell]\nallowed_commands = [\"device list\", \"net  ping 10.0.0.1\"]\n").unwrap();
+        assert!(config.shell.allows("device list"));
+        assert!(config.shell.allows("  device   list "));
+        assert!(config.shell.allows("net ping 10.0.0.1"));
+        // Exact commands only: no extra arguments, no prefixes
+        assert!(!config.shell.allows("device"));
+        assert!(!config.shell.allows("net ping 10.0.0.2"));
+        assert!(!config.shell.allows("device list; reboot"));
+        assert!(!config.shell.allows(""));
+        assert!(Config::default().shell.allows("net iface"));
+
+        let errors = check_config("[shell]\nallowed_commands = [\" \"]\ntimeout_ms = 0\n").unwrap_err();
+        let keys: Vec<_> = errors.0.iter().map(|i| i.key.as_str()).collect();
+        assert_eq!(keys, ["shell.timeout_ms", "shell.allowed_commands[0]"]);
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("subnet", "subnet"), 0);
+        assert_eq!(edit_distance("subnett", "subnet"), 1);
+        assert_eq!(edit_distance("prfix_len", "prefix_len"), 1);
+        assert_eq!(did_you_mean("zzzzzz", &["subnet", "prefix_len"]), None);
+    }
+}
@@ -0,0 +1,336 @@
+//! Serial console of devices attached over a serial port
+//!
+//! `GET /api/devices/{id}/console` upgrades to a WebSocket carrying the
+//! console of a device whose `[[device_override]]` names a `serial_port`:
+//! everything on the port but SMP packets, which is the Zephyr shell and its
+//! log output. Text frames from the client are written to the port as they
+//! are; the daemon sends [`ConsoleMessage`]s.
+//!
+//! The console is a full shell, past `[shell] allowed_commands`, so only an
+//! operator token opens it (like `/ws`, browsers pass it as a `token` query
+//! parameter), and each session and every input is logged under the `audit`
+//! target. One session holds a device's port at a time.
+//!
+//! The port is read into a queue of [`OUTPUT_QUEUE_READS`] reads. When the
+//! client doesn't keep up, reads that don't fit are dropped rather than
+//! holding up the port, and each `output` message carries the number of
+//! bytes dropped so far.
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path, State,
+    },
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use dendrite_api_types::ConsoleMessage;
+use dendrite_mcumgr::{SerialConsole, SerialPortConfig};
+use futures_util::{SinkExt, StreamExt};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tracing::{info, warn};
+
+use crate::auth::SessionRole;
+use crate::config::Config;
+use crate::state::AppState;
+
+/// Port reads queued for a client before further ones are dropped
+pub const OUTPUT_QUEUE_READS: usize = 64;
+
+/// Client inputs queued for the port
+const INPUT_QUEUE: usize = 16;
+
+/// Devices with an open console session
+#[derive(Debug, Clone, Default)]
+pub struct ConsoleSessions(Arc<Mutex<HashSet<String>>>);
+
+impl ConsoleSessions {
+    /// Claim a device's console, None while another session has it
+    pub fn claim(&self, id: &str) -> Option<ConsoleClaim> {
+        self.0.lock().unwrap().insert(id.to_string()).then(|| ConsoleClaim { sessions: self.clone(), id: id.to_string() })
+    }
+}
+
+/// A device's console, free again once dropped
+#[derive(Debug)]
+pub struct ConsoleClaim {
+    sessions: ConsoleSessions,
+    id: String,
+}
+
+impl Drop for ConsoleClaim {
+    fn drop(&mut self) {
+        self.sessions.0.lock().unwrap().remove(&self.id);
+    }
+}
+
+/// Serial port of a device's console, if `role` may open it
+fn console_port(config: &Config, id: &str, role: Option<SessionRole>) -> Result<SerialPortConfig, (StatusCode, String)> {
+    if role != Some(SessionRole::Operator) {
+        return Err((StatusCode::FORBIDDEN, "The console needs an operator token".to_string()));
+    }
+    config.serial_port(id).ok_or_else(|| {
+        (
+            StatusCode::BAD_REQUEST,
+            "Device has no serial console; set serial_port in its [[device_override]]".to_string(),
+        )
+    })
+}
+
+/// Open a device's serial console
+///
+/// GET /api/devices/:id/console
+pub async fn console_handler(
+    ws: WebSocketUpgrade,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    role: Option<Extension<SessionRole>>,
+) -> Response {
+    let role = role.map(|Extension(role)| role);
+    let refuse = |status: StatusCode, error: String| {
+        warn!(target: "audit", device = %id, role = ?role, error = %error, "Refused console session");
+        (status, Json(serde_json::json!({ "error": error }))).into_response()
+    };
+
+    let port = match console_port(&state.config, &id, role) {
+        Ok(port) => port,
+        Err((status, error)) => return refuse(status, error),
+    };
+    if state.get_device(&id).await.is_none() {
+        return refuse(StatusCode::NOT_FOUND, "Device not found".to_string());
+    }
+    let Some(claim) = state.consoles.claim(&id) else {
+        return refuse(StatusCode::CONFLICT, "Another console session has this device".to_string());
+    };
+
+    ws.on_upgrade(move |socket| run_session(socket, id, port, claim))
+}
+
+type Sender = futures_util::stream::SplitSink<WebSocket, Message>;
+
+/// Send one message, false once the client is gone
+async fn send_message(sender: &mut Sender, msg: &ConsoleMessage) -> bool {
+    match serde_json::to_string(msg) {
+        Ok(json) => sender.send(Message::Text(json.into())).await.is_ok(),
+        Err(_) => true,
+    }
+}
+
+async fn run_session(socket: WebSocket, id: String, port: SerialPortConfig, _claim: ConsoleClaim) {
+    let (mut sender, mut receiver) = socket.split();
+    let path = port.path.display().to_string();
+
+    let console = match SerialConsole::open(&port).await {
+        Ok(console) => console,
+        Err(e) => {
+            warn!(target: "audit", device = %id, port = %path, error = %e, "Console port failed to open");
+            send_message(&mut sender, &ConsoleMessage::Closed { error: Some(e.to_string()) }).await;
+            return;
+        }
+    };
+    info!(target: "audit", device = %id, port = %path, baud_rate = port.baud_rate, "Console session opened");
+
+    let (output_tx, mut output_rx) = mpsc::channel(OUTPUT_QUEUE_READS);
+    let (input_tx, input_rx) = mpsc::channel(INPUT_QUEUE);
+    let dropped = Arc::new(AtomicU64::new(0));
+    let mut pump = tokio::spawn(pump(console, output_tx, input_rx, dropped.clone()));
+    let mut text = TextDecoder::default();
+
+    let error = loop {
+        tokio::select! {
+            output = output_rx.recv() => {
+                let Some(output) = output else {
+                    // The port failed or closed
+                    break match (&mut pump).await {
+                        Ok(Ok(())) => None,
+                        Ok(Err(e)) => Some(e.to_string()),
+                        Err(e) => Some(e.to_string()),
+                    };
+                };
+                let data = text.push(&output);
+                if data.is_empty() {
+                    continue;
+                }
+                let msg = ConsoleMessage::Output { data, dropped: dropped.load(Ordering::Relaxed) };
+                if !send_message(&mut sender, &msg).await {
+                    break None;
+                }
+            }
+
+            msg = receiver.next() => {
+                let input = match msg {
+                    Some(Ok(Message::Text(input))) => input.as_str().as_bytes().to_vec(),
+                    Some(Ok(Message::Binary(input))) => input.to_vec(),
+                    Some(Ok(Message::Ping(data))) => {
+                        if sender.send(Message::Pong(data)).await.is_err() {
+                            break None;
+                        }
+                        continue;
+                    }
+                    Some(Ok(Message::Pong(_))) => continue,
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break None,
+                };
+                info!(target: "audit", device = %id, input = ?String::from_utf8_lossy(&input), "Console input");
+                // A closed port shows up as the end of its output
+                let _ = input_tx.send(input).await;
+            }
+        }
+    };
+    pump.abort();
+
+    let dropped = dropped.load(Ordering::Relaxed);
+    info!(target: "audit", device = %id, port = %path, dropped, error = ?error, "Console session closed");
+    if error.is_some() {
+        send_message(&mut sender, &ConsoleMessage::Closed { error }).await;
+    }
+}
+
+/// Move console output into `output` and `input` onto the port until either
+/// side closes
+///
+/// Output that doesn't fit in `output` is dropped and counted in `dropped`,
+/// so a slow client never holds up reading the port.
+async fn pump<R, W>(
+    mut console: SerialConsole<R, W>,
+    output: mpsc::Sender<Vec<u8>>,
+    mut input: mpsc::Receiver<Vec<u8>>,
+    dropped: Arc<AtomicU64>,
+) -> anyhow::Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    loop {
+        tokio::select! {
+            read = console.read() => {
+                let bytes = read?;
+                if bytes.is_empty() {
+                    continue;
+                }
+                match output.try_send(bytes) {
+                    Ok(()) => {}
+                    Err(TrySendError::Full(bytes)) => {
+                        dropped.fetch_add(bytes.len() as u64, Ordering::Relaxed);
+                    }
+                    Err(TrySendError::Closed(_)) => return Ok(()),
+                }
+            }
+            bytes = input.recv() => match bytes {
+                Some(bytes) => console.write(&bytes).await?,
+                None => return Ok(()),
+            },
+        }
+    }
+}
+
+/// Console bytes as text, holding back a character split across reads
+#[derive(Debug, Default)]
+struct TextDecoder {
+    partial: Vec<u8>,
+}
+
+impl TextDecoder {
+    fn push(&mut self, bytes: &[u8]) -> String {
+        self.partial.extend_from_slice(bytes);
+        let complete = match std::str::from_utf8(&self.partial) {
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            _ => self.partial.len(),
+        };
+        let rest = self.partial.split_off(complete);
+        let text = String::from_utf8_lossy(&self.partial).into_owned();
+        self.partial = rest;
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn test_console_needs_an_operator_and_a_serial_port() {
+        let config: Config = toml::from_str(
+            r#"
+[[device_override]]
+hwid = "a1b2c3"
+serial_port = "/dev/ttyACM0"
+
+[[device_override]]
+hwid = "d4e5f6"
+name = "no-console"
+"#,
+        )
+        .unwrap();
+
+        let port = console_port(&config, "a1b2c3", Some(SessionRole::Operator)).unwrap();
+        assert_eq!(port, SerialPortConfig::new("/dev/ttyACM0"));
+        // An open API (no role) or a viewer gets no shell
+        assert_eq!(console_port(&config, "a1b2c3", None).unwrap_err().0, StatusCode::FORBIDDEN);
+        assert_eq!(console_port(&config, "a1b2c3", Some(SessionRole::Viewer)).unwrap_err().0, StatusCode::FORBIDDEN);
+        assert_eq!(console_port(&config, "d4e5f6", Some(SessionRole::Operator)).unwrap_err().0, StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_one_session_per_console() {
+        let sessions = ConsoleSessions::default();
+        let claim = sessions.claim("a1b2c3").unwrap();
+        assert!(sessions.claim("a1b2c3").is_none());
+        assert!(sessions.claim("d4e5f6").is_some());
+        drop(claim);
+        assert!(sessions.claim("a1b2c3").is_some());
+    }
+
+    #[test]
+    fn test_text_decoder_keeps_split_characters() {
+        let mut text = TextDecoder::default();
+        let degrees = "25.0 °C\r\n".as_bytes();
+        let split = degrees.len() - 3;
+        assert_eq!(text.push(&degrees[..split]), "25.0 ");
+        assert_eq!(text.push(&degrees[split..]), "°C\r\n");
+        assert_eq!(text.push(b"\xffok"), "\u{fffd}ok");
+    }
+
+    #[tokio::test]
+    async fn test_pump_drops_output_a_slow_client_cant_take() {
+        let (port, mut device) = tokio::io::duplex(4096);
+        let (reader, writer) = tokio::io::split(port);
+        let (output_tx, mut output_rx) = mpsc::channel(2);
+        let (input_tx, input_rx) = mpsc::channel(INPUT_QUEUE);
+        let dropped = Arc::new(AtomicU64::new(0));
+        let pump = tokio::spawn(pump(SerialConsole::new(reader, writer), output_tx, input_rx, dropped.clone()));
+
+        // Input reaches the port while nobody reads the output
+        input_tx.send(b"log list\r".to_vec()).await.unwrap();
+        let mut buf = [0u8; 16];
+        let len = device.read(&mut buf).await.unwrap();
+        assert_eq!(&buf[..len], b"log list\r");
+
+        let line = b"[00:00:01.000] <inf> imu: sample\r\n";
+        for _ in 0..200 {
+            device.write_all(line).await.unwrap();
+            tokio::task::yield_now().await;
+        }
+        let started = tokio::time::Instant::now();
+        while dropped.load(Ordering::Relaxed) == 0 {
+            assert!(started.elapsed() < std::time::Duration::from_secs(5), "nothing dropped");
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        // What was queued still arrives, and the pump stops with the client
+        let mut received = 0;
+        while let Ok(output) = output_rx.try_recv() {
+            received += output.len();
+        }
+        assert!(received > 0);
+        assert!(received as u64 + dropped.load(Ordering::Relaxed) <= (line.len() * 200) as u64);
+        drop(output_rx);
+        device.write_all(line).await.unwrap();
+        tokio::time::timeout(std::time::Duration::from_secs(5), pump).await.unwrap().unwrap().unwrap();
+    }
+}
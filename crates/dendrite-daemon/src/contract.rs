@@ -0,0 +1,107 @@
+//! Contract tests between daemon responses and the shared API types
+//!
+//! The fixtures in dendrite-api-types are what the frontend's tests parse;
+//! these check that the daemon produces exactly them.
+
+use dendrite_api_types::{fixtures, DeviceJson, FirmwareCheckResponse, FirmwareStatus, MethodStats, ScanResponse, ScanStats};
+use dendrite_core::{Device, DeviceSummary};
+use serde_json::Value;
+
+fn fixture(text: &str) -> Value {
+    serde_json::from_str(text).unwrap()
+}
+
+/// Every field of `shared` must be in `daemon` with the same value
+fn assert_subset(shared: &Value, daemon: &Value, path: &str) {
+    match shared {
+        Value::Object(fields) => {
+            for (key, value) in fields {
+                let path = format!("{}.{}", path, key);
+                let Some(daemon_value) = daemon.get(key) else {
+                    panic!("{} is not in the daemon's response", path);
+                };
+                assert_subset(value, daemon_value, &path);
+            }
+        }
+        Value::Array(items) => {
+            let daemon_items = daemon.as_array().unwrap_or_else(|| panic!("{} is not an array", path));
+            assert_eq!(items.len(), daemon_items.len(), "{}", path);
+            for (i, (item, daemon_item)) in items.iter().zip(daemon_items).enumerate() {
+                assert_subset(item, daemon_item, &format!("{}[{}]", path, i));
+            }
+        }
+        leaf => assert_eq!(leaf, daemon, "{}", path),
+    }
+}
+
+#[test]
+fn test_device_fixture_is_daemon_output() {
+    let device: Device = serde_json::from_str(fixtures::DEVICE).unwrap();
+    assert_eq!(serde_json::to_value(&device).unwrap(), fixture(fixtures::DEVICE));
+}
+
+#[test]
+fn test_device_json_matches_daemon_device() {
+    let device: Device = serde_json::from_str(fixtures::DEVICE).unwrap();
+
+    let daemon = serde_json::to_value(&device).unwrap();
+    let shared: DeviceJson = serde_json::from_value(daemon.clone()).unwrap();
+    assert_subset(&serde_json::to_value(&shared).unwrap(), &daemon, "device");
+    assert_eq!(shared.ports.len(), 2);
+    assert_eq!(shared.sensors[0].fovs.len(), 1);
+
+    // Lite clients get summaries without visuals, frames, ports or sensors
+    let daemon = serde_json::to_value(DeviceSummary::from(&device)).unwrap();
+    let shared: DeviceJson = serde_json::from_value(daemon.clone()).unwrap();
+    assert!(shared.visuals.is_empty() && shared.sensors.is_empty());
+    let mut shared = serde_json::to_value(&shared).unwrap();
+    for key in ["visuals", "frames", "ports", "sensors"] {
+        shared.as_object_mut().unwrap().remove(key);
+    }
+    assert_subset(&shared, &daemon, "summary");
+}
+
+#[test]
+fn test_firmware_check_response_matches_fixture() {
+    let response = FirmwareCheckResponse {
+        device_id: "0x1a2b3c4d".to_string(),
+        current_version: Some("1.2.0".to_string()),
+        current_mcuboot_hash: Some("5d41402abc4b2a76b9719d911017c592".to_string()),
+        latest_version: Some("1.3.0".to_string()),
+        latest_mcuboot_hash: Some("7d793037a0760186574b0282f2f435e7".to_string()),
+        status: FirmwareStatus::UpdateAvailable {
+            latest_version: "1.3.0".to_string(),
+            changelog: Some("Faster flow estimation".to_string()),
+        },
+        changelog: Some("Faster flow estimation".to_string()),
+    };
+    assert_eq!(serde_json::to_value(&response).unwrap(), fixture(fixtures::FIRMWARE_CHECK));
+}
+
+#[test]
+fn test_scan_response_matches_fixture() {
+    let response = ScanResponse {
+        status: "completed".to_string(),
+        devices_found: 3,
+        methods: ScanStats {
+            arp: Some(MethodStats {
+                responders: 5,
+                devices: 2,
+                excluded: 1,
+                duration_ms: 1250,
+                errors: Vec::new(),
+            }),
+            icmp: Some(MethodStats {
+                responders: 2,
+                devices: 1,
+                excluded: 0,
+                duration_ms: 840,
+                errors: vec!["sendto 10.0.3.255: permission denied".to_string()],
+            }),
+            mdns: None,
+            can: None,
+            ndp: None,
+        },
+    };
+    assert_eq!(serde_json::to_value(&response).unwrap(), fixture(fixtures::SCAN));
+}
@@ -230,7 +230,7 @@ impl FirmwareFetcher {
 /// - Image payload (img_size bytes)
 ///
 /// This excludes the trailing TLV area with signature.
-fn compute_mcuboot_hash(data: &[u8]) -> Result<String> {
+pub(crate) fn compute_mcuboot_hash(data: &[u8]) -> Result<String> {
     use sha2::{Sha256, Digest};
 
     // MCUboot image header structure (first 32 bytes):
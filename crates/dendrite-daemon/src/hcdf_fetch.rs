@@ -6,9 +6,13 @@
 //! 3. Fetching and caching remote HCDF files
 //! 4. Fetching and caching GLB model files with SHA verification
 //! 5. SHA verification to avoid re-downloading unchanged files
+//! 6. Conditional requests (`If-None-Match` / `If-Modified-Since`) for cached files
+//! 7. Storing locally uploaded model overrides
 
 use anyhow::{Context, Result};
-use dendrite_core::{FragmentCache, sha256_hex};
+use dendrite_core::{FragmentCache, Hcdf, HttpValidators, ModelOverride, sha256_hex};
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::StatusCode;
 use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::RwLock;
@@ -49,6 +53,20 @@ impl HcdfFetcher {
         format!("{}/{}/{}/{}.hcdf", HCDF_BASE_URL, board, app, app)
     }
 
+    /// GET `url`, conditional on the validators of a cached copy
+    fn conditional_get(&self, url: &str, validators: Option<&HttpValidators>) -> reqwest::RequestBuilder {
+        let mut request = self.client.get(url);
+        if let Some(validators) = validators {
+            if let Some(etag) = &validators.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &validators.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+        request
+    }
+
     /// Get the cache directory path
     pub async fn cache_dir(&self) -> PathBuf {
         self.cache.read().await.base_dir.clone()
@@ -101,8 +119,10 @@ impl HcdfFetcher {
 
         info!(url = %url, board = %board, app = %app, "Fetching remote HCDF");
 
-        // Fetch the HCDF file
-        let response = match self.client.get(&url).send().await {
+        // Fetch the HCDF file, conditional on the copy cached for this URL
+        let cached = self.cache.read().await.hcdf_for_url(&url).cloned();
+        let request = self.conditional_get(&url, cached.as_ref().map(|e| &e.validators));
+        let response = match request.send().await {
             Ok(resp) => resp,
             Err(e) => {
                 warn!(url = %url, error = %e, "Failed to fetch HCDF, trying cache fallback");
@@ -116,6 +136,23 @@ impl HcdfFetcher {
             }
         };
 
+        if let (StatusCode::NOT_MODIFIED, Some(entry)) = (response.status(), &cached) {
+            let mut cache = self.cache.write().await;
+            if let Err(e) = cache.mark_checked(&url) {
+                warn!(error = %e, "Failed to update cache manifest");
+            }
+            match cache.read_hcdf(&entry.sha) {
+                Ok(content) => {
+                    info!(url = %url, sha = %FragmentCache::short_sha(&entry.sha), "HCDF not modified, using cache");
+                    return Ok(Some(content));
+                }
+                Err(e) => {
+                    warn!(url = %url, error = %e, "Failed to read cached HCDF");
+                    return Ok(None);
+                }
+            }
+        }
+
         if !response.status().is_success() {
             warn!(
                 url = %url,
@@ -131,6 +168,7 @@ impl HcdfFetcher {
             return Ok(None);
         }
 
+        let validators = response_validators(&response);
         let content = response.text().await
             .context("Failed to read HCDF response body")?;
 
@@ -153,7 +191,7 @@ impl HcdfFetcher {
         // Cache the content
         {
             let mut cache = self.cache.write().await;
-            match cache.store_hcdf(&url, &computed_sha, board, app, content.as_bytes()) {
+            match cache.store_hcdf(&url, &computed_sha, board, app, content.as_bytes(), validators) {
                 Ok(path) => {
                     info!(
                         url = %url,
@@ -211,8 +249,10 @@ impl HcdfFetcher {
 
         info!(url = %model_url, model = %model_name, "Fetching remote model");
 
-        // Fetch the model file
-        let response = match self.client.get(model_url).send().await {
+        // Fetch the model file, conditional on the copy cached for this URL
+        let cached = self.cache.read().await.model_for_href(model_url).cloned();
+        let request = self.conditional_get(model_url, cached.as_ref().map(|m| &m.validators));
+        let response = match request.send().await {
             Ok(resp) => resp,
             Err(e) => {
                 warn!(url = %model_url, error = %e, "Failed to fetch model");
@@ -220,6 +260,14 @@ impl HcdfFetcher {
             }
         };
 
+        if let (StatusCode::NOT_MODIFIED, Some(model)) = (response.status(), cached) {
+            if let Err(e) = self.cache.write().await.mark_checked(model_url) {
+                warn!(error = %e, "Failed to update cache manifest");
+            }
+            info!(model = %model_name, sha = %model.short_sha, "Model not modified, using cache");
+            return Ok(Some(model.path));
+        }
+
         if !response.status().is_success() {
             warn!(
                 url = %model_url,
@@ -229,6 +277,7 @@ impl HcdfFetcher {
             return Ok(None);
         }
 
+        let validators = response_validators(&response);
         let content = response.bytes().await
             .context("Failed to read model response body")?;
 
@@ -267,7 +316,7 @@ impl HcdfFetcher {
                 }
             }
 
-            match cache.store_model(hcdf_sha, model_name, &computed_sha, model_url, &content) {
+            match cache.store_model(hcdf_sha, model_name, &computed_sha, model_url, &content, validators) {
                 Ok(path) => {
                     // Get the actual filename that was stored
                     let cached_name = path.file_name()
@@ -301,6 +350,66 @@ impl HcdfFetcher {
         self.fetch_hcdf(board, app, None, None).await
     }
 
+    /// Store an uploaded model as the override for a device (and its board)
+    pub async fn set_model_override(
+        &self,
+        hwid: &str,
+        board: Option<&str>,
+        file_name: &str,
+        content: &[u8],
+    ) -> Result<ModelOverride> {
+        let model = self.cache.write().await
+            .store_model_override(hwid, board, file_name, content)
+            .context("Failed to store model override")?;
+        info!(
+            device = %hwid,
+            board = ?board,
+            sha = %FragmentCache::short_sha(&model.sha),
+            path = %model.path,
+            "Stored model override"
+        );
+        Ok(model)
+    }
+
+    /// Remove a device's model override (and its board's); false if none was set
+    pub async fn remove_model_override(&self, hwid: &str, board: Option<&str>) -> Result<bool> {
+        self.cache.write().await
+            .remove_model_override(hwid, board)
+            .context("Failed to remove model override")
+    }
+
+    /// Model override for a device, falling back to its board's
+    pub async fn model_override(&self, hwid: &str, board: Option<&str>) -> Option<ModelOverride> {
+        self.cache.read().await.model_override(hwid, board).cloned()
+    }
+
+    /// Point MCUs that have a model override at the local copy
+    pub async fn apply_model_overrides(&self, hcdf: &mut Hcdf) {
+        let cache = self.cache.read().await;
+        for mcu in &mut hcdf.mcu {
+            let Some(hwid) = mcu.hwid.as_deref() else {
+                continue;
+            };
+            if let Some(model) = cache.model_override(hwid, mcu.board.as_deref()) {
+                mcu.model = None;
+                mcu.visual = vec![model.visual()];
+            }
+        }
+    }
+
+    /// Cached file for a model reference, if the cache holds it
+    pub async fn model_file(&self, href: &str, sha: Option<&str>) -> Option<PathBuf> {
+        self.cache.read().await.model_file(href, sha)
+    }
+
+    /// Refetch the HCDF of `board`/`app` (`*` for every app) next time
+    /// instead of using the cached copy
+    pub async fn forget_board_app(&self, board: &str, app: &str) -> Result<bool> {
+        self.cache.write().await
+            .forget_board_app(board, app)
+            .context("Failed to update cache manifest")
+    }
+
     /// Get cache statistics
     pub async fn cache_stats(&self) -> (usize, usize, PathBuf) {
         let cache = self.cache.read().await;
@@ -310,9 +419,66 @@ impl HcdfFetcher {
     }
 }
 
+/// `ETag` and `Last-Modified` of a response, to make the next fetch conditional
+fn response_validators(response: &reqwest::Response) -> HttpValidators {
+    let header = |name: reqwest::header::HeaderName| {
+        response.headers().get(name).and_then(|v| v.to_str().ok()).map(str::to_string)
+    };
+    HttpValidators {
+        etag: header(ETAG),
+        last_modified: header(LAST_MODIFIED),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use axum::http::HeaderMap;
+    use axum::response::IntoResponse;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    /// Server for a single file whose ETag and body the test can change;
+    /// counts the responses that carried a body
+    struct MockFile {
+        current: Arc<Mutex<(String, Vec<u8>)>>,
+        bodies: Arc<AtomicUsize>,
+        url: String,
+    }
+
+    impl MockFile {
+        async fn serve(path: &str, etag: &str, body: &[u8]) -> Self {
+            let current = Arc::new(Mutex::new((etag.to_string(), body.to_vec())));
+            let bodies = Arc::new(AtomicUsize::new(0));
+            let (state, count) = (current.clone(), bodies.clone());
+            let app = axum::Router::new().route(
+                path,
+                axum::routing::get(move |headers: HeaderMap| {
+                    let (etag, body) = state.lock().unwrap().clone();
+                    let matches = headers.get(IF_NONE_MATCH).is_some_and(|v| v.as_bytes() == etag.as_bytes());
+                    let response = if matches {
+                        (StatusCode::NOT_MODIFIED, [(ETAG, etag)]).into_response()
+                    } else {
+                        count.fetch_add(1, Ordering::SeqCst);
+                        (StatusCode::OK, [(ETAG, etag)], body).into_response()
+                    };
+                    std::future::ready(response)
+                }),
+            );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("http://{}{}", listener.local_addr().unwrap(), path);
+            tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+            Self { current, bodies, url }
+        }
+
+        fn update(&self, etag: &str, body: &[u8]) {
+            *self.current.lock().unwrap() = (etag.to_string(), body.to_vec());
+        }
+
+        fn bodies(&self) -> usize {
+            self.bodies.load(Ordering::SeqCst)
+        }
+    }
 
     #[test]
     fn test_construct_url() {
@@ -321,4 +487,44 @@ mod tests {
             "https://hcdf.cognipilot.org/mr_mcxn_t1/optical-flow/optical-flow.hcdf"
         );
     }
+
+    #[tokio::test]
+    async fn test_not_modified_hcdf_skips_download() {
+        let dir = tempfile::tempdir().unwrap();
+        let fetcher = HcdfFetcher::new(dir.path().to_path_buf()).unwrap();
+        let server = MockFile::serve("/spinali.hcdf", "\"v1\"", b"<hcdf>v1</hcdf>").await;
+
+        let first = fetcher.fetch_hcdf("spinali", "default", Some(&server.url), None).await.unwrap();
+        assert_eq!(first.as_deref(), Some("<hcdf>v1</hcdf>"));
+        let second = fetcher.fetch_hcdf("spinali", "default", Some(&server.url), None).await.unwrap();
+        assert_eq!(second.as_deref(), Some("<hcdf>v1</hcdf>"));
+        assert_eq!(server.bodies(), 1, "304 must not download the body again");
+
+        // A new ETag replaces the cached entry for the URL
+        server.update("\"v2\"", b"<hcdf>v2</hcdf>");
+        let third = fetcher.fetch_hcdf("spinali", "default", Some(&server.url), None).await.unwrap();
+        assert_eq!(third.as_deref(), Some("<hcdf>v2</hcdf>"));
+        assert_eq!(server.bodies(), 2);
+        let cache = fetcher.cache.read().await;
+        assert_eq!(cache.manifest.hcdf.len(), 1);
+        assert!(!cache.has_hcdf(&sha256_hex(b"<hcdf>v1</hcdf>")));
+    }
+
+    #[tokio::test]
+    async fn test_not_modified_model_skips_download() {
+        let dir = tempfile::tempdir().unwrap();
+        let fetcher = HcdfFetcher::new(dir.path().to_path_buf()).unwrap();
+        let hcdf_sha = sha256_hex(b"<hcdf/>");
+        fetcher.cache.write().await
+            .store_hcdf("https://example.com/x.hcdf", &hcdf_sha, "x", "default", b"<hcdf/>", HttpValidators::default())
+            .unwrap();
+        let server = MockFile::serve("/models/board.glb", "\"m1\"", b"glTF-v1").await;
+
+        let first = fetcher.fetch_model(&server.url, None, &hcdf_sha).await.unwrap().unwrap();
+        let second = fetcher.fetch_model(&server.url, None, &hcdf_sha).await.unwrap().unwrap();
+        assert_eq!(first, second);
+        assert_eq!(server.bodies(), 1, "304 must not download the body again");
+        let cache = fetcher.cache.read().await;
+        assert!(cache.model_for_href(&server.url).unwrap().last_checked.is_some());
+    }
 }
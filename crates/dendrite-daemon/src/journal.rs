@@ -0,0 +1,416 @@
+//! Registry journal for time-travel queries
+//!
+//! With `[journal] enabled = true` every device event is appended to a JSON
+//! lines file, with a full snapshot of the registry every
+//! `snapshot_interval_secs`. `GET /api/devices?as_of=` rebuilds the registry
+//! at a past instant by replaying the events after the last snapshot taken
+//! before it. Once the file grows past `max_bytes`, or its oldest snapshot
+//! period ends before `max_age_hours` ago, whole periods are dropped from the
+//! front so the journal always starts with a snapshot.
+
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use dendrite_core::{Device, DeviceId, RegistryHandle};
+use dendrite_discovery::{DiscoveryEvent, DiscoveryScanner};
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, Mutex};
+use tracing::{debug, info, warn};
+
+use crate::config::JournalConfig;
+
+/// How often the snapshot cadence and size bounds are checked
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// One line of the journal
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JournalEntry {
+    /// The whole registry
+    Snapshot { at: DateTime<Utc>, devices: Vec<Device> },
+    /// A device was discovered, updated or restored
+    Upsert { at: DateTime<Utc>, device: Device },
+    /// A device stopped responding
+    Offline { at: DateTime<Utc>, id: String },
+    /// A device was archived or forgotten
+    Remove { at: DateTime<Utc>, id: String },
+}
+
+impl JournalEntry {
+    pub fn at(&self) -> DateTime<Utc> {
+        match self {
+            Self::Snapshot { at, .. } | Self::Upsert { at, .. } | Self::Offline { at, .. } | Self::Remove { at, .. } => *at,
+        }
+    }
+
+    /// The entry recording a registry event, if it changes the registry
+    pub fn from_event(event: &DiscoveryEvent, at: DateTime<Utc>) -> Option<Self> {
+        match event {
+            DiscoveryEvent::DeviceDiscovered(device)
+            | DiscoveryEvent::DeviceUpdated(device)
+            | DiscoveryEvent::DeviceUnarchived(device) => Some(Self::Upsert { at, device: device.clone() }),
+            DiscoveryEvent::DeviceOffline(id) => Some(Self::Offline { at, id: id.0.clone() }),
+            DiscoveryEvent::DeviceArchived(id) | DiscoveryEvent::DeviceRemoved(id) => {
+                Some(Self::Remove { at, id: id.0.clone() })
+            }
+            // Scan progress doesn't change the registry
+            _ => None,
+        }
+    }
+
+    fn is_snapshot(&self) -> bool {
+        matches!(self, Self::Snapshot { .. })
+    }
+}
+
+/// Time span the journal can answer for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct JournalRange {
+    /// First snapshot; nothing earlier can be reconstructed
+    pub start: DateTime<Utc>,
+    /// Latest entry
+    pub end: DateTime<Utc>,
+}
+
+/// Journal entries with their encoded sizes, oldest first
+#[derive(Debug)]
+pub struct Journal {
+    config: JournalConfig,
+    entries: Vec<(JournalEntry, usize)>,
+    bytes: usize,
+}
+
+impl Journal {
+    pub fn new(config: JournalConfig) -> Self {
+        Self { config, entries: Vec::new(), bytes: 0 }
+    }
+
+    /// Load a journal file's contents, skipping unreadable lines and
+    /// anything before the first snapshot
+    pub fn from_lines(config: JournalConfig, text: &str) -> Self {
+        let mut journal = Self::new(config);
+        for line in text.lines().filter(|l| !l.trim().is_empty()) {
+            match serde_json::from_str::<JournalEntry>(line) {
+                Ok(entry) if journal.entries.is_empty() && !entry.is_snapshot() => {}
+                Ok(entry) => journal.push(entry, line.len() + 1),
+                Err(e) => warn!(error = %e, "Skipping unreadable journal line"),
+            }
+        }
+        journal
+    }
+
+    /// Append an entry, returning the line to write to the file
+    ///
+    /// Events before the first snapshot are dropped (and return None) since
+    /// they can't be replayed onto anything.
+    pub fn append(&mut self, entry: JournalEntry) -> Option<String> {
+        if self.entries.is_empty() && !entry.is_snapshot() {
+            return None;
+        }
+        let line = match serde_json::to_string(&entry) {
+            Ok(line) => line,
+            Err(e) => {
+                warn!(error = %e, "Failed to encode journal entry");
+                return None;
+            }
+        };
+        self.push(entry, line.len() + 1);
+        Some(line)
+    }
+
+    fn push(&mut self, entry: JournalEntry, size: usize) {
+        self.bytes += size;
+        self.entries.push((entry, size));
+    }
+
+    /// Whether a snapshot is due: none yet, the cadence has elapsed, or the
+    /// journal is over its size budget and compaction needs a newer snapshot
+    pub fn needs_snapshot(&self, now: DateTime<Utc>) -> bool {
+        let Some(last) = self.entries.iter().rposition(|(e, _)| e.is_snapshot()) else {
+            return true;
+        };
+        let interval = ChronoDuration::seconds(self.config.snapshot_interval_secs as i64);
+        let events_since = self.entries.len() - last - 1;
+        (events_since > 0 && now - self.entries[last].0.at() >= interval)
+            || (events_since > 0 && self.over_budget() && self.snapshot_count() == 1)
+    }
+
+    fn over_budget(&self) -> bool {
+        self.bytes as u64 > self.config.max_bytes
+    }
+
+    fn snapshot_count(&self) -> usize {
+        self.entries.iter().filter(|(e, _)| e.is_snapshot()).count()
+    }
+
+    /// Drop whole snapshot periods from the front while the journal is over
+    /// its size budget or a period ended before the age limit. Returns
+    /// whether anything was dropped (the file then needs rewriting).
+    pub fn compact(&mut self, now: DateTime<Utc>) -> bool {
+        let cutoff = now - ChronoDuration::hours(self.config.max_age_hours as i64);
+        let mut dropped = false;
+        while let Some(next) = self.entries.iter().skip(1).position(|(e, _)| e.is_snapshot()).map(|i| i + 1) {
+            if !self.over_budget() && self.entries[next].0.at() > cutoff {
+                break;
+            }
+            let removed: usize = self.entries.drain(..next).map(|(_, size)| size).sum();
+            self.bytes -= removed;
+            dropped = true;
+        }
+        dropped
+    }
+
+    /// The registry as it was at `as_of`, or None if that is before the
+    /// journal starts
+    pub fn devices_at(&self, as_of: DateTime<Utc>) -> Option<Vec<Device>> {
+        let start = self
+            .entries
+            .iter()
+            .rposition(|(e, _)| e.is_snapshot() && e.at() <= as_of)?;
+
+        let registry = RegistryHandle::new();
+        for (entry, _) in self.entries[start..].iter().take_while(|(e, _)| e.at() <= as_of) {
+            match entry {
+                JournalEntry::Snapshot { devices, .. } => registry.replace(devices.iter().cloned()),
+                JournalEntry::Upsert { device, .. } => registry.apply(&DiscoveryEvent::DeviceUpdated(device.clone())),
+                JournalEntry::Offline { id, .. } => registry.apply(&DiscoveryEvent::DeviceOffline(DeviceId::from_hwid(id))),
+                JournalEntry::Remove { id, .. } => registry.apply(&DiscoveryEvent::DeviceRemoved(DeviceId::from_hwid(id))),
+            }
+        }
+        Some(registry.devices())
+    }
+
+    pub fn range(&self) -> Option<JournalRange> {
+        Some(JournalRange {
+            start: self.entries.first()?.0.at(),
+            end: self.entries.last()?.0.at(),
+        })
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// The whole journal as file contents
+    pub fn to_lines(&self) -> String {
+        let mut text = String::with_capacity(self.bytes);
+        for (entry, _) in &self.entries {
+            if let Ok(line) = serde_json::to_string(entry) {
+                text.push_str(&line);
+                text.push('\n');
+            }
+        }
+        text
+    }
+}
+
+/// A journal kept on disk, fed by registry events
+pub struct DeviceJournal {
+    path: PathBuf,
+    journal: Mutex<Journal>,
+}
+
+impl DeviceJournal {
+    /// Open the journal file, picking up where a previous run left off
+    pub fn open(config: JournalConfig) -> io::Result<Self> {
+        let path = PathBuf::from(&config.path);
+        let journal = match std::fs::read_to_string(&path) {
+            Ok(text) => Journal::from_lines(config, &text),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Journal::new(config),
+            Err(e) => return Err(e),
+        };
+        Ok(Self { path, journal: Mutex::new(journal) })
+    }
+
+    /// Snapshot the registry now and record events until the channel closes
+    pub fn spawn(self: Arc<Self>, scanner: Arc<DiscoveryScanner>, mut events: broadcast::Receiver<DiscoveryEvent>) {
+        info!(path = %self.path.display(), "Registry journal enabled");
+        tokio::spawn(async move {
+            self.snapshot(&scanner).await;
+            let mut ticker = tokio::time::interval(CHECK_INTERVAL);
+            loop {
+                tokio::select! {
+                    event = events.recv() => match event {
+                        Ok(event) => {
+                            if let Some(entry) = JournalEntry::from_event(&event, Utc::now()) {
+                                self.record(entry).await;
+                            }
+                        }
+                        // Missed events are covered by a fresh snapshot
+                        Err(broadcast::error::RecvError::Lagged(_)) => self.snapshot(&scanner).await,
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                    _ = ticker.tick() => {
+                        if self.journal.lock().await.needs_snapshot(Utc::now()) {
+                            self.snapshot(&scanner).await;
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    async fn snapshot(&self, scanner: &DiscoveryScanner) {
+        let devices = scanner.devices().await;
+        self.record(JournalEntry::Snapshot { at: Utc::now(), devices }).await;
+
+        let mut journal = self.journal.lock().await;
+        if journal.compact(Utc::now()) {
+            debug!(bytes = journal.bytes(), "Compacted registry journal");
+            if let Err(e) = self.rewrite(&journal) {
+                warn!(path = %self.path.display(), error = %e, "Failed to rewrite journal");
+            }
+        }
+    }
+
+    async fn record(&self, entry: JournalEntry) {
+        let Some(line) = self.journal.lock().await.append(entry) else {
+            return;
+        };
+        let result = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| writeln!(file, "{}", line));
+        if let Err(e) = result {
+            warn!(path = %self.path.display(), error = %e, "Failed to append to journal");
+        }
+    }
+
+    fn rewrite(&self, journal: &Journal) -> io::Result<()> {
+        let tmp = self.path.with_extension("tmp");
+        std::fs::write(&tmp, journal.to_lines())?;
+        std::fs::rename(&tmp, &self.path)
+    }
+
+    pub async fn devices_at(&self, as_of: DateTime<Utc>) -> Option<Vec<Device>> {
+        self.journal.lock().await.devices_at(as_of)
+    }
+
+    pub async fn range(&self) -> Option<JournalRange> {
+        self.journal.lock().await.range()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dendrite_core::DeviceStatus;
+
+    fn t(minutes: i64) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2025-06-01T12:00:00Z").unwrap().with_timezone(&Utc) + ChronoDuration::minutes(minutes)
+    }
+
+    fn device(id: &str, name: &str) -> Device {
+        Device::new(DeviceId::from_hwid(id), name.to_string(), "192.168.186.10".parse().unwrap(), 1337)
+    }
+
+    fn names(devices: &[Device]) -> Vec<(&str, &str, DeviceStatus)> {
+        devices.iter().map(|d| (d.id.0.as_str(), d.name.as_str(), d.status)).collect()
+    }
+
+    fn config() -> JournalConfig {
+        JournalConfig { enabled: true, snapshot_interval_secs: 3600, ..Default::default() }
+    }
+
+    /// A rig retrofit: a flow sensor is renamed, a GPS goes offline and is
+    /// removed, and a new IMU appears
+    fn retrofit() -> Journal {
+        let mut flow = device("0x1", "flow");
+        flow.status = DeviceStatus::Online;
+        let mut gps = device("0x2", "gps");
+        gps.status = DeviceStatus::Online;
+
+        let mut journal = Journal::new(config());
+        assert!(journal.append(JournalEntry::Upsert { at: t(-1), device: device("0x9", "early") }).is_none());
+        journal.append(JournalEntry::Snapshot { at: t(0), devices: vec![flow.clone(), gps] });
+        journal.append(JournalEntry::Offline { at: t(10), id: "0x2".to_string() });
+        flow.name = "flow-front".to_string();
+        journal.append(JournalEntry::Upsert { at: t(20), device: flow.clone() });
+        journal.append(JournalEntry::Remove { at: t(30), id: "0x2".to_string() });
+        journal.append(JournalEntry::Snapshot { at: t(60), devices: vec![flow.clone()] });
+        journal.append(JournalEntry::Upsert { at: t(70), device: device("0x3", "imu") });
+        journal
+    }
+
+    #[test]
+    fn test_reconstructs_registry_at_several_instants() {
+        let journal = retrofit();
+
+        assert!(journal.devices_at(t(-5)).is_none());
+        assert_eq!(
+            names(&journal.devices_at(t(5)).unwrap()),
+            vec![("0x1", "flow", DeviceStatus::Online), ("0x2", "gps", DeviceStatus::Online)]
+        );
+        // Entries at exactly the requested instant are included
+        assert_eq!(
+            names(&journal.devices_at(t(10)).unwrap()),
+            vec![("0x1", "flow", DeviceStatus::Online), ("0x2", "gps", DeviceStatus::Offline)]
+        );
+        assert_eq!(
+            names(&journal.devices_at(t(25)).unwrap()),
+            vec![("0x1", "flow-front", DeviceStatus::Online), ("0x2", "gps", DeviceStatus::Offline)]
+        );
+        assert_eq!(names(&journal.devices_at(t(45)).unwrap()), vec![("0x1", "flow-front", DeviceStatus::Online)]);
+        assert_eq!(
+            names(&journal.devices_at(t(90)).unwrap()),
+            vec![("0x1", "flow-front", DeviceStatus::Online), ("0x3", "imu", DeviceStatus::Unknown)]
+        );
+        assert_eq!(journal.range(), Some(JournalRange { start: t(0), end: t(70) }));
+    }
+
+    #[test]
+    fn test_reload_from_file_contents() {
+        let journal = retrofit();
+        let reloaded = Journal::from_lines(config(), &journal.to_lines());
+        assert_eq!(reloaded.bytes(), journal.bytes());
+        assert_eq!(names(&reloaded.devices_at(t(25)).unwrap()), names(&journal.devices_at(t(25)).unwrap()));
+
+        // A truncated last line (crash mid-write) is skipped
+        let mut text = journal.to_lines();
+        text.push_str("{\"kind\":\"upsert\",\"at\":");
+        let reloaded = Journal::from_lines(config(), &text);
+        assert_eq!(reloaded.range(), journal.range());
+    }
+
+    #[test]
+    fn test_compaction_drops_whole_periods() {
+        // Young enough and small enough: nothing to do
+        let mut journal = retrofit();
+        assert!(!journal.compact(t(90)));
+        assert_eq!(journal.range().unwrap().start, t(0));
+
+        // The first period ended more than max_age ago
+        let mut journal = retrofit();
+        assert!(journal.compact(t(61) + ChronoDuration::hours(config().max_age_hours as i64)));
+        assert_eq!(journal.range().unwrap().start, t(60));
+        assert!(journal.devices_at(t(30)).is_none());
+        assert_eq!(journal.devices_at(t(90)).unwrap().len(), 2);
+
+        // Over budget: the newest period is always kept
+        let mut journal = retrofit();
+        journal.config.max_bytes = 1;
+        assert!(journal.compact(t(90)));
+        assert_eq!(journal.range().unwrap().start, t(60));
+        assert!(!journal.compact(t(90)));
+    }
+
+    #[test]
+    fn test_snapshot_cadence() {
+        let mut journal = Journal::new(config());
+        assert!(journal.needs_snapshot(t(0)));
+        journal.append(JournalEntry::Snapshot { at: t(0), devices: Vec::new() });
+        // Nothing happened since the snapshot, so there's nothing to capture
+        assert!(!journal.needs_snapshot(t(120)));
+
+        journal.append(JournalEntry::Upsert { at: t(5), device: device("0x1", "flow") });
+        assert!(!journal.needs_snapshot(t(30)));
+        assert!(journal.needs_snapshot(t(60)));
+
+        // A single oversized period needs a new snapshot before it can be compacted
+        journal.config.max_bytes = 1;
+        assert!(journal.needs_snapshot(t(30)));
+    }
+}
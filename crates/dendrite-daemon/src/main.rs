@@ -3,19 +3,40 @@
 //! This is the main daemon that runs discovery and serves the web UI.
 
 mod api;
+mod attention;
 mod auth;
+mod bundle;
 mod config;
+mod console;
+#[cfg(test)]
+mod contract;
 mod firmware_fetch;
 mod hcdf_fetch;
+mod journal;
 mod ota;
+mod persist;
+mod privileges;
+mod regenerate;
+mod registry_file;
+mod scan_report;
 mod server;
+mod setup;
 mod state;
+mod status_led;
+mod store;
+mod trace;
+mod version;
 mod ws;
 
-use anyhow::Result;
-use clap::Parser;
-use std::path::PathBuf;
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
+use dendrite_discovery::{IpNet, NdpSockets, PreopenedSockets};
+use scan_report::{ScanFormat, ScanOutcome, ScanReport, ScanSummary};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tracing::{info, Level};
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
 use tracing_subscriber::FmtSubscriber;
 
 #[derive(Parser, Debug)]
@@ -35,19 +56,93 @@ struct Args {
     #[arg(short, long, default_value = "info")]
     log_level: String,
 
+    /// Log format; overrides `daemon.log_format` in the configuration file
+    #[arg(long, value_enum)]
+    log_format: Option<config::LogFormat>,
+
     /// Run a single scan and exit
     #[arg(long)]
     scan_once: bool,
 
+    /// Scan-once output format (`table` is the summary)
+    #[arg(long, visible_alias = "output", value_enum, default_value_t = ScanFormat::Summary, requires = "scan_once")]
+    format: ScanFormat,
+
+    /// Write scan-once output to a file instead of stdout
+    #[arg(long, requires = "scan_once")]
+    out: Option<PathBuf>,
+
+    /// Compare the scan against an expected HCDF or JSON scan report and
+    /// exit non-zero on mismatch
+    #[arg(long, requires = "scan_once")]
+    expect: Option<PathBuf>,
+
+    /// Exit with code 3 if the scan finds fewer devices (0 accepts an empty
+    /// scan); ignored when `--expect-count` is given
+    #[arg(long, value_name = "N", default_value_t = 1, requires = "scan_once")]
+    require_devices: usize,
+
+    /// Exit with code 1 unless the scan finds exactly this many devices; the
+    /// exact count replaces the `--require-devices` minimum, so
+    /// `--expect-count 0` accepts only an empty scan
+    #[arg(long, value_name = "N", requires = "scan_once")]
+    expect_count: Option<usize>,
+
     /// Open browser automatically when server starts
     #[arg(short, long)]
     open: bool,
+
+    /// Offer the dendrite-web setup wizard even if the configuration file
+    /// exists (it is always offered when the file is missing)
+    #[arg(long, conflicts_with = "scan_once")]
+    setup: bool,
+
+    /// Don't save the device registry or restore it at startup
+    #[arg(long)]
+    no_persist: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Configuration file utilities
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+    /// Write the HCDF and every model it references to one zip, for viewing
+    /// without internet access
+    Bundle {
+        /// Output file
+        #[arg(long, default_value = "dendrite.dzip")]
+        out: PathBuf,
+        /// HCDF file to bundle instead of the configured `[hcdf] path`
+        #[arg(long)]
+        hcdf: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigCommand {
+    /// Validate a configuration file and report every problem found
+    Check {
+        /// Path to configuration file
+        file: PathBuf,
+    },
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
+fn main() -> Result<()> {
     let args = Args::parse();
 
+    if let Some(Command::Config { action: ConfigCommand::Check { file } }) = &args.command {
+        return check_config_file(file);
+    }
+    if let Some(Command::Bundle { out, hcdf }) = &args.command {
+        return runtime()?.block_on(write_bundle_file(&args.config, hcdf.as_deref(), out));
+    }
+
     // Initialize logging
     let level = match args.log_level.to_lowercase().as_str() {
         "trace" => Level::TRACE,
@@ -58,21 +153,39 @@ async fn main() -> Result<()> {
         _ => Level::INFO,
     };
 
-    let subscriber = FmtSubscriber::builder()
+    // Scan-once output goes to stdout, so keep logs out of the way
+    let writer = if args.scan_once {
+        BoxMakeWriter::new(std::io::stderr)
+    } else {
+        BoxMakeWriter::new(std::io::stdout)
+    };
+
+    let builder = FmtSubscriber::builder()
         .with_max_level(level)
         .with_target(true)
-        .finish();
-
-    tracing::subscriber::set_global_default(subscriber)?;
+        .with_writer(writer);
+    let log_format = args.log_format.unwrap_or_else(|| config::peek_log_format(&args.config));
+    match log_format {
+        config::LogFormat::Text => tracing::subscriber::set_global_default(builder.finish())?,
+        // Span fields (subnet, device id...) ride along in "span" and "spans"
+        config::LogFormat::Json => tracing::subscriber::set_global_default(
+            builder.json().with_current_span(true).with_span_list(true).finish(),
+        )?,
+    }
 
-    info!("Dendrite v{}", env!("CARGO_PKG_VERSION"));
+    version::VersionInfo::current().log();
 
     // Load configuration
+    let setup = Arc::new(setup::SetupState::new(&args.config, args.setup || !args.config.exists()));
     let mut config = config::load_config(&args.config)?;
 
     // Override bind address if specified
-    if let Some(bind) = args.bind {
-        config.daemon.bind = bind;
+    if let Some(bind) = &args.bind {
+        config.daemon.bind = bind.clone();
+    }
+    // A single scan reports what it finds, not what the last run knew
+    if args.no_persist || args.scan_once {
+        config.daemon.persist_registry = false;
     }
 
     info!(
@@ -81,29 +194,47 @@ async fn main() -> Result<()> {
         "Configuration loaded"
     );
 
+    // Open discovery's raw sockets while CAP_NET_RAW is still held, then
+    // give up the capabilities before serving anything
+    let subnet = IpNet::from((config.discovery.subnet, config.discovery.prefix_len));
+    let ndp = subnet.is_ipv6().then(|| NdpSockets { subnet: &subnet, interface: config.discovery.interface.as_deref() });
+    let sockets = Arc::new(PreopenedSockets::open(
+        !config.discovery.icmp_sweep.is_empty(),
+        config.to_lldp_tx_config().as_ref(),
+        ndp,
+    ));
+    if config.daemon.drop_capabilities {
+        privileges::drop_capabilities(&config.daemon.bind).context("Failed to drop capabilities")?;
+    }
+
+    // Capabilities belong to each thread, so the runtime only starts now:
+    // its worker and blocking threads are created with the reduced sets
+    runtime()?.block_on(run(args, config, setup, sockets, log_format))
+}
+
+/// Multi-threaded runtime for everything after startup
+fn runtime() -> Result<tokio::runtime::Runtime> {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .context("Failed to start the async runtime")
+}
+
+/// Run the daemon, or a single scan, once the sockets are open
+async fn run(
+    args: Args,
+    config: config::Config,
+    setup: Arc<setup::SetupState>,
+    sockets: Arc<PreopenedSockets>,
+    log_format: config::LogFormat,
+) -> Result<()> {
     // Create application state
-    let state = state::AppState::new(config.clone()).await?;
+    let state = state::AppState::new(config.clone(), sockets).await?;
 
     if args.scan_once {
         // Single scan mode
         info!("Running single discovery scan");
-        let devices = state.scanner.scan_once().await?;
-        println!("Discovered {} devices:", devices.len());
-        for device in devices {
-            println!(
-                "  - {} ({}) at {}:{}",
-                device.name,
-                device.id,
-                device.discovery.ip,
-                device.discovery.port
-            );
-            if let Some(board) = &device.info.board {
-                println!("    Board: {}", board);
-            }
-            if let Some(version) = &device.firmware.version {
-                println!("    Firmware: {}", version);
-            }
-        }
+        run_scan_once(&state, &config, &args, log_format).await?;
     } else {
         // Daemon mode - run web server and discovery
 
@@ -123,8 +254,165 @@ async fn main() -> Result<()> {
             });
         }
 
-        server::run(state, &config.daemon.bind, config.daemon.tls.as_ref()).await?;
+        if setup.is_required() {
+            info!(path = %args.config.display(), "Setup required, the web UI will offer the setup wizard");
+        }
+
+        server::run(state, setup, &config.daemon.bind, config.daemon.tls.as_ref()).await?;
     }
 
     Ok(())
 }
+
+/// `dendrite --scan-once`: scan, write the report, and exit with the
+/// [`ScanOutcome`] code if it doesn't match `--expect`, `--require-devices`
+/// or `--expect-count`, or nothing was found on a subnet no interface is on
+async fn run_scan_once(
+    state: &state::AppState,
+    config: &config::Config,
+    args: &Args,
+    log_format: config::LogFormat,
+) -> Result<()> {
+    let started_at = chrono::Utc::now();
+    let started = std::time::Instant::now();
+    let subnet = IpNet::from((config.discovery.subnet, config.discovery.prefix_len));
+    let mut summary = ScanSummary {
+        outcome: ScanOutcome::Ok,
+        exit_code: 0,
+        subnet: subnet.to_string(),
+        interface: None,
+        found: 0,
+        required: args.require_devices,
+        expected_count: args.expect_count,
+        differences: None,
+        duration_ms: 0,
+    };
+
+    // ARP and probes of a subnet no interface is on can't go anywhere, but an
+    // ICMP sweep of a routed subnet, mDNS and CAN still find devices
+    let mut interface_missing = false;
+    if let IpAddr::V4(addr) = config.discovery.subnet {
+        summary.interface = setup::subnet_interface(addr, config.discovery.prefix_len);
+        interface_missing = summary.interface.is_none();
+    }
+    if interface_missing && !config.discovery.scans_without_interface() {
+        eprintln!("no network interface on {}", subnet);
+        return finish_scan_once(summary, ScanOutcome::NoInterface, started, log_format);
+    }
+
+    let discovered = state.scanner.scan_once().await?;
+
+    // Apply fragments and HCDF merging now rather than waiting on the event loop
+    let mut devices = Vec::with_capacity(discovered.len());
+    for device in &discovered {
+        devices.push(state.update_device(device).await);
+    }
+
+    let report = ScanReport::new(
+        subnet,
+        config.discovery.mcumgr_port,
+        started_at,
+        started.elapsed(),
+        state.scanner.last_scan_stats().await,
+        devices,
+    );
+    // Devices are already in the state's HCDF under their own parents
+    let parent_name = config.parent.first().map(|p| p.name.as_str());
+    let merged = report.to_hcdf(state.get_hcdf(), parent_name);
+
+    let output = report.render(args.format, &merged)?;
+    match &args.out {
+        Some(path) => std::fs::write(path, output)
+            .with_context(|| format!("Failed to write {}", path.display()))?,
+        None => print!("{}", output),
+    }
+
+    let mut outcome = ScanOutcome::Ok;
+    if let Some(path) = &args.expect {
+        let expected = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let changes = scan_report::diff_expected(&expected, &report, &merged, parent_name)?;
+        if changes.is_empty() {
+            eprintln!("{}: matches", path.display());
+        } else {
+            eprintln!("{}: {} difference(s) from expected", path.display(), changes.len());
+            eprint!("{}", scan_report::format_changes(&changes));
+            outcome = ScanOutcome::Mismatch;
+        }
+        summary.differences = Some(changes.len());
+    }
+    summary.found = report.found;
+    // A short count is the more telling failure for CI
+    if let Some((count_outcome, problem)) = scan_report::check_count(report.found, args.require_devices, args.expect_count) {
+        eprintln!("{}", problem);
+        outcome = count_outcome;
+    }
+    // Say why an empty scan was empty rather than reporting an empty bench
+    if interface_missing && report.found == 0 {
+        eprintln!("no network interface on {}", subnet);
+        outcome = ScanOutcome::NoInterface;
+    }
+
+    finish_scan_once(summary, outcome, started, log_format)
+}
+
+/// Print the summary line for JSON logs and exit with the outcome's code
+fn finish_scan_once(
+    mut summary: ScanSummary,
+    outcome: ScanOutcome,
+    started: std::time::Instant,
+    log_format: config::LogFormat,
+) -> Result<()> {
+    summary.outcome = outcome;
+    summary.exit_code = outcome.exit_code();
+    summary.duration_ms = started.elapsed().as_millis() as u64;
+    if log_format == config::LogFormat::Json {
+        eprintln!("{}", serde_json::to_string(&summary)?);
+    }
+    if outcome != ScanOutcome::Ok {
+        std::process::exit(summary.exit_code);
+    }
+    Ok(())
+}
+
+/// `dendrite bundle`: bundle the HCDF with the models in the fragment cache
+/// and the static models directory, listing models that couldn't be found
+async fn write_bundle_file(config_path: &Path, hcdf_path: Option<&Path>, out: &Path) -> Result<()> {
+    let config = config::load_config(config_path)?;
+    let hcdf_path = hcdf_path.map(Path::to_path_buf).unwrap_or_else(|| PathBuf::from(&config.hcdf.path));
+    let mut hcdf = dendrite_core::Hcdf::from_file(&hcdf_path)
+        .with_context(|| format!("Failed to read {}", hcdf_path.display()))?;
+    hcdf.pin_assembly_origins();
+
+    let fetcher = hcdf_fetch::HcdfFetcher::new(config.data_dir().join("cache"))?;
+    fetcher.apply_model_overrides(&mut hcdf).await;
+    let bundle = bundle::build_bundle(&hcdf, &fetcher, Path::new(&config.models.path)).await?;
+    std::fs::write(out, &bundle.data).with_context(|| format!("Failed to write {}", out.display()))?;
+
+    println!("{}: {} model(s) bundled", out.display(), bundle.bundled);
+    if !bundle.missing.is_empty() {
+        eprintln!("{} model(s) not cached, left out:", bundle.missing.len());
+        for href in &bundle.missing {
+            eprintln!("  {}", href);
+        }
+    }
+    Ok(())
+}
+
+/// `dendrite config check <file>`: print every problem and exit non-zero if any
+fn check_config_file(path: &Path) -> Result<()> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+
+    match config::check_config(&content) {
+        Ok(_) => {
+            println!("{}: OK", path.display());
+            Ok(())
+        }
+        Err(errors) => {
+            eprintln!("{}: {} problem(s) found", path.display(), errors.0.len());
+            eprintln!("{}", errors);
+            std::process::exit(1);
+        }
+    }
+}
@@ -2,59 +2,61 @@
 //!
 //! This module handles firmware updates via MCUmgr image upload.
 //! The update process:
-//! 1. Download firmware binary from upstream
-//! 2. Upload to device via MCUmgr
-//! 3. Mark image as pending test
-//! 4. Reset device
-//! 5. Verify update succeeded
+//! 1. Download firmware binary from upstream, checking its size and hash
+//! 2. Check the device answers and its secondary slot can take the image
+//! 3. Upload to device via MCUmgr, resuming if the connection drops, and
+//!    check the uploaded image's hash
+//! 4. Mark image as pending test
+//! 5. Reset device
+//! 6. Verify the device booted the new image and confirm it, so MCUboot
+//!    keeps it rather than reverting on the next reset
+//!
+//! A dry run goes through the same pipeline with a stage that uploads
+//! nothing in place of steps 3-6, and reports whether the device is ready.
+//!
+//! On shutdown the daemon stops accepting updates and waits for running ones
+//! (see [`OtaService::wait_for_updates`]), since a device reset halfway
+//! through an upload may not boot.
 
 use anyhow::{anyhow, Result};
-use serde::{Deserialize, Serialize};
+use dendrite_api_types::{DeviceImagesResponse, ImageSlotInfo};
+use dendrite_core::{DiscoveryInfo, FirmwareRelease};
+use dendrite_mcumgr::{
+    confirm_image, list_images, negotiate_chunk_size, probe_device, query_device, upload_image, verify_uploaded_image,
+    ImageSlot, QueryOptions, UploadOptions, DEFAULT_QUERY_CONCURRENCY, DEFAULT_TIMEOUT_MS,
+};
+use futures_util::StreamExt;
 use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::{broadcast, RwLock};
 use tracing::{debug, error, info, warn};
 
 use crate::firmware_fetch::FirmwareFetcher;
 
+pub use dendrite_api_types::{OtaEvent, OtaReadiness, Readiness, UpdateState};
+
 /// MCUmgr port for device communication
 const MCUMGR_PORT: u16 = 1337;
 
-/// Update state for a device
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(tag = "state", rename_all = "snake_case")]
-pub enum UpdateState {
-    /// Downloading firmware binary from upstream
-    Downloading { progress: f32 },
-    /// Uploading firmware to device via MCUmgr
-    Uploading { progress: f32 },
-    /// Confirming (marking image as pending test)
-    Confirming,
-    /// Rebooting device
-    Rebooting,
-    /// Verifying update was successful
-    Verifying,
-    /// Update completed successfully
-    Complete,
-    /// Update failed
-    Failed { error: String },
-    /// Update was cancelled
-    Cancelled,
-}
+/// Wait after the reset before checking on the device
+const REBOOT_WAIT: Duration = Duration::from_secs(5);
 
-impl UpdateState {
-    pub fn is_terminal(&self) -> bool {
-        matches!(self, UpdateState::Complete | UpdateState::Failed { .. } | UpdateState::Cancelled)
-    }
-}
+/// Wait between checks that the device came back with the new image
+const VERIFY_INTERVAL: Duration = Duration::from_secs(2);
 
-/// OTA update event sent via WebSocket
-#[derive(Debug, Clone, Serialize)]
-pub struct OtaEvent {
-    pub device_id: String,
-    pub state: UpdateState,
-}
+/// Reachability and slot checks before the upload; short, so a silent
+/// device doesn't hold up a batch
+const CHECK_OPTIONS: QueryOptions = QueryOptions {
+    timeout: Duration::from_secs(2),
+    retries: 1,
+    retry_backoff: Duration::from_millis(100),
+};
+
+/// How often shutdown logs the updates it is still waiting for
+const SHUTDOWN_LOG_INTERVAL: Duration = Duration::from_secs(10);
 
 /// Information about a device being updated
 #[derive(Debug, Clone)]
@@ -66,6 +68,291 @@ struct UpdateInfo {
     pub state: UpdateState,
 }
 
+/// A device to update and where its firmware comes from
+#[derive(Debug, Clone)]
+pub struct OtaTarget {
+    pub device_id: String,
+    /// MCUmgr address of the device
+    pub addr: SocketAddr,
+    pub board: String,
+    pub app: String,
+    /// Required: there is no default manifest location
+    pub firmware_manifest_uri: Option<String>,
+}
+
+impl OtaTarget {
+    /// A device answering MCUmgr at `addr` (usually the standard port)
+    pub fn new(
+        device_id: String,
+        addr: SocketAddr,
+        board: String,
+        app: String,
+        firmware_manifest_uri: Option<String>,
+    ) -> Self {
+        Self {
+            device_id,
+            addr,
+            board,
+            app,
+            firmware_manifest_uri,
+        }
+    }
+}
+
+/// Where a run records its state and looks for cancellation
+///
+/// Dry runs aren't tracked: there is nothing to show and nothing to cancel.
+struct Run<'a> {
+    device_id: &'a str,
+    tracking: Option<(&'a RwLock<HashMap<String, UpdateInfo>>, &'a broadcast::Sender<OtaEvent>)>,
+}
+
+impl Run<'_> {
+    async fn is_cancelled(&self) -> bool {
+        let Some((active_updates, _)) = self.tracking else {
+            return false;
+        };
+        let updates = active_updates.read().await;
+        updates
+            .get(self.device_id)
+            .map(|u| matches!(u.state, UpdateState::Cancelled))
+            .unwrap_or(false)
+    }
+
+    async fn set_state(&self, state: UpdateState) {
+        let Some((active_updates, event_tx)) = self.tracking else {
+            return;
+        };
+        let mut updates = active_updates.write().await;
+        if let Some(info) = updates.get_mut(self.device_id) {
+            info.state = state.clone();
+        }
+        let _ = event_tx.send(OtaEvent {
+            device_id: self.device_id.to_string(),
+            state,
+        });
+    }
+}
+
+/// What the steps before the upload found
+struct Prepared {
+    release: FirmwareRelease,
+    firmware: Vec<u8>,
+    /// Echo round trip to the device
+    round_trip: Duration,
+}
+
+impl Prepared {
+    /// One round trip per upload chunk, then the reboot and the first verification
+    fn estimated_duration(&self) -> Duration {
+        let chunks = self.firmware.len().div_ceil(UploadOptions::default().chunk_size).max(1);
+        self.round_trip * chunks as u32 + REBOOT_WAIT + VERIFY_INTERVAL
+    }
+}
+
+/// What a run does once the firmware is downloaded and the device checked
+///
+/// Updates flash it ([`Flash`]); dry runs stop there ([`NoUpload`]). Both go
+/// through [`OtaService::run_pipeline`], so a dry run checks exactly what an
+/// update would.
+trait UploadStage {
+    type Output;
+
+    async fn run(&self, run: &Run<'_>, target: &OtaTarget, prepared: Prepared) -> Result<Self::Output>;
+}
+
+/// Upload, mark for test, reset and verify
+struct Flash<'a> {
+    event_tx: &'a broadcast::Sender<OtaEvent>,
+}
+
+impl UploadStage for Flash<'_> {
+    type Output = ();
+
+    async fn run(&self, run: &Run<'_>, target: &OtaTarget, prepared: Prepared) -> Result<()> {
+        let device_id = &target.device_id;
+        let addr = target.addr;
+
+        // 3. Upload to device via MCUmgr
+        run.set_state(UpdateState::Uploading { progress: 0.0, retries: 0, resumed_at: None }).await;
+        info!("Uploading firmware to device at {}", addr);
+
+        let image_hash = OtaService::upload_and_verify(self.event_tx, device_id, addr, &prepared.firmware).await?;
+
+        if run.is_cancelled().await {
+            return Ok(());
+        }
+
+        // 4. Mark image as pending test and reset
+        run.set_state(UpdateState::Confirming).await;
+        info!("Confirming firmware image");
+
+        tokio::task::spawn_blocking(move || {
+            use mcumgr_client::{UdpTransport, UdpSpecs, test_transport};
+
+            let specs = UdpSpecs {
+                host: addr.ip().to_string(),
+                port: addr.port(),
+                timeout_s: 5,
+                mtu: 1024,
+            };
+            let mut transport = UdpTransport::new(&specs)
+                .map_err(|e| anyhow!("Failed to create transport: {}", e))?;
+
+            // Mark the verified image as pending test
+            test_transport(&mut transport, image_hash, Some(false))?;
+
+            Ok::<_, anyhow::Error>(())
+        })
+        .await??;
+
+        if run.is_cancelled().await {
+            return Ok(());
+        }
+
+        // 5. Reset device
+        run.set_state(UpdateState::Rebooting).await;
+        info!("Resetting device");
+
+        tokio::task::spawn_blocking(move || {
+            use mcumgr_client::{UdpTransport, UdpSpecs, reset_transport};
+
+            let specs = UdpSpecs {
+                host: addr.ip().to_string(),
+                port: addr.port(),
+                timeout_s: 5,
+                mtu: 1024,
+            };
+            let mut transport = UdpTransport::new(&specs)
+                .map_err(|e| anyhow!("Failed to create transport: {}", e))?;
+
+            reset_transport(&mut transport)?;
+
+            Ok::<_, anyhow::Error>(())
+        })
+        .await??;
+
+        // 6. Wait for device to come back and verify
+        run.set_state(UpdateState::Verifying).await;
+        info!("Waiting for device to reboot...");
+
+        // Wait a bit for the device to reboot
+        tokio::time::sleep(REBOOT_WAIT).await;
+
+        if run.is_cancelled().await {
+            return Ok(());
+        }
+
+        // Verify the device came back with the new firmware and confirm it
+        // Give it a few retries since reboot takes time
+        let expected_mcuboot_hash = prepared.release.mcuboot_hash.clone();
+        let mut verified = false;
+
+        for attempt in 0..10 {
+            tokio::time::sleep(VERIFY_INTERVAL).await;
+
+            if run.is_cancelled().await {
+                return Ok(());
+            }
+
+            match confirm_booted_image(addr, &expected_mcuboot_hash).await {
+                Ok(true) => {
+                    info!("Device rebooted with correct firmware (hash verified, image confirmed)");
+                    verified = true;
+                    break;
+                }
+                Ok(false) => {
+                    debug!("Device rebooted but not yet running the new firmware (attempt {})", attempt + 1);
+                }
+                Err(e) => {
+                    debug!("Verification attempt {} failed: {}", attempt + 1, e);
+                }
+            }
+        }
+
+        if !verified {
+            warn!("Could not verify firmware update, but device may still be running new image");
+        }
+
+        // 7. Mark as complete
+        run.set_state(UpdateState::Complete).await;
+        info!("Firmware update completed for device {}", device_id);
+
+        Ok(())
+    }
+}
+
+/// Stops short of the upload and reports what the update would do
+struct NoUpload;
+
+impl UploadStage for NoUpload {
+    type Output = OtaReadiness;
+
+    async fn run(&self, _run: &Run<'_>, target: &OtaTarget, prepared: Prepared) -> Result<OtaReadiness> {
+        Ok(OtaReadiness {
+            device_id: target.device_id.clone(),
+            readiness: Readiness::Ok,
+            version: Some(prepared.release.version.clone()),
+            image_bytes: Some(prepared.firmware.len() as u64),
+            estimated_secs: Some(prepared.estimated_duration().as_secs_f32()),
+        })
+    }
+}
+
+/// Why the secondary slot can't take a new image, if it can't
+fn slot_problem(images: &[ImageSlot]) -> Option<String> {
+    let secondary = images.iter().find(|img| img.slot == 1)?;
+    if secondary.pending {
+        Some(format!("Slot 1 holds image {} pending test; reboot the device first", secondary.version))
+    } else if secondary.active {
+        Some("Device is running from slot 1".to_string())
+    } else {
+        None
+    }
+}
+
+/// Whether the device at `addr` booted the image with `expected_hash`,
+/// confirming it if it is still running for test
+async fn confirm_booted_image(addr: SocketAddr, expected_hash: &str) -> Result<bool> {
+    let images = list_images(addr).await?;
+    let active = images.iter().find(|img| img.active).ok_or_else(|| anyhow!("No active image found"))?;
+    if !active.hash.eq_ignore_ascii_case(expected_hash) {
+        debug!(expected = %expected_hash, running = %active.hash, "Device is not running the new image");
+        return Ok(false);
+    }
+    if !active.confirmed {
+        confirm_image(addr, &active.hash).await?;
+    }
+    Ok(true)
+}
+
+/// API view of an image slot
+fn slot_info(image: ImageSlot) -> ImageSlotInfo {
+    ImageSlotInfo {
+        slot: image.slot,
+        version: image.version,
+        hash: image.hash,
+        active: image.active,
+        confirmed: image.confirmed,
+        pending: image.pending,
+    }
+}
+
+/// A device's image slots for `GET /api/devices/:id/images`
+///
+/// A device that times out or has no image management group gives an empty
+/// list, with the reason in `error`.
+pub async fn device_images(device_id: &str, addr: SocketAddr) -> DeviceImagesResponse {
+    let (images, error) = match list_images(addr).await {
+        Ok(images) => (images.into_iter().map(slot_info).collect(), None),
+        Err(e) => {
+            debug!(device = %device_id, error = %e, "Image slots unavailable");
+            (Vec::new(), Some(e.to_string()))
+        }
+    };
+    DeviceImagesResponse { device_id: device_id.to_string(), images, error }
+}
+
 /// OTA update service
 pub struct OtaService {
     /// Firmware fetcher for downloading binaries
@@ -74,6 +361,8 @@ pub struct OtaService {
     active_updates: Arc<RwLock<HashMap<String, UpdateInfo>>>,
     /// Event sender for update progress
     event_tx: broadcast::Sender<OtaEvent>,
+    /// Set once the daemon is shutting down; no new updates start
+    shutting_down: AtomicBool,
 }
 
 impl OtaService {
@@ -84,6 +373,7 @@ impl OtaService {
             firmware_fetcher,
             active_updates: Arc::new(RwLock::new(HashMap::new())),
             event_tx,
+            shutting_down: AtomicBool::new(false),
         }
     }
 
@@ -120,44 +410,98 @@ impl OtaService {
         Ok(())
     }
 
+    /// Error if an update for the device is still running
+    async fn check_not_updating(&self, device_id: &str) -> Result<()> {
+        let updates = self.active_updates.read().await;
+        match updates.get(device_id) {
+            Some(info) if !info.state.is_terminal() => {
+                Err(anyhow!("Update already in progress for device {}", device_id))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Devices with an update still running
+    pub async fn in_progress(&self) -> Vec<String> {
+        let updates = self.active_updates.read().await;
+        let mut ids: Vec<String> =
+            updates.iter().filter(|(_, info)| !info.state.is_terminal()).map(|(id, _)| id.clone()).collect();
+        ids.sort();
+        ids
+    }
+
+    /// Refuse new updates, then wait up to `timeout` for running ones to end
+    ///
+    /// Returns the devices still updating when the wait gave up.
+    pub async fn wait_for_updates(&self, timeout: Duration) -> Vec<String> {
+        // Subscribe first so an update ending in between isn't missed
+        let mut events = self.event_tx.subscribe();
+        self.shutting_down.store(true, Ordering::SeqCst);
+
+        let deadline = Instant::now() + timeout;
+        let mut next_log = Instant::now();
+        loop {
+            let pending = self.in_progress().await;
+            let now = Instant::now();
+            if pending.is_empty() {
+                return pending;
+            }
+            if now >= deadline {
+                warn!(devices = ?pending, "Gave up waiting for OTA updates; these devices may need recovery");
+                return pending;
+            }
+            if now >= next_log {
+                warn!(
+                    devices = ?pending,
+                    remaining_secs = (deadline - now).as_secs(),
+                    "Waiting for OTA updates to finish before exiting"
+                );
+                next_log = now + SHUTDOWN_LOG_INTERVAL;
+            }
+            // Any event may be an update ending; the sleep bounds the wait
+            tokio::select! {
+                _ = events.recv() => {}
+                _ = tokio::time::sleep_until(deadline.min(next_log).into()) => {}
+            }
+        }
+    }
+
+    /// Error once the daemon is shutting down
+    ///
+    /// Checked under the `active_updates` write lock, so an update either
+    /// starts before shutdown looks for running ones or not at all.
+    fn check_accepting(&self) -> Result<()> {
+        if self.shutting_down.load(Ordering::SeqCst) {
+            return Err(anyhow!("Daemon is shutting down"));
+        }
+        Ok(())
+    }
+
     /// Start a firmware update for a device
     ///
     /// This spawns an async task to handle the update process.
     /// Requires firmware_manifest_uri to be set (no default fallback).
-    pub async fn start_update(
-        &self,
-        device_id: String,
-        ip: String,
-        board: String,
-        app: String,
-        firmware_manifest_uri: Option<String>,
-    ) -> Result<()> {
+    pub async fn start_update(&self, target: OtaTarget) -> Result<()> {
         // Check if already updating
-        {
-            let updates = self.active_updates.read().await;
-            if let Some(info) = updates.get(&device_id) {
-                if !info.state.is_terminal() {
-                    return Err(anyhow!("Update already in progress for device {}", device_id));
-                }
-            }
-        }
+        self.check_not_updating(&target.device_id).await?;
 
         // Initialize update state
         {
             let mut updates = self.active_updates.write().await;
+            self.check_accepting()?;
             updates.insert(
-                device_id.clone(),
+                target.device_id.clone(),
                 UpdateInfo {
-                    device_id: device_id.clone(),
-                    ip: ip.clone(),
-                    board: board.clone(),
-                    app: app.clone(),
+                    device_id: target.device_id.clone(),
+                    ip: target.addr.ip().to_string(),
+                    board: target.board.clone(),
+                    app: target.app.clone(),
                     state: UpdateState::Downloading { progress: 0.0 },
                 },
             );
         }
 
-        self.send_event(&device_id, UpdateState::Downloading { progress: 0.0 });
+        self.send_event(&target.device_id, UpdateState::Downloading { progress: 0.0 });
 
         // Clone what we need for the spawned task
         let firmware_fetcher = self.firmware_fetcher.clone();
@@ -166,17 +510,17 @@ impl OtaService {
 
         // Spawn the update task
         tokio::spawn(async move {
-            let result = Self::run_update(
-                &firmware_fetcher,
-                &active_updates,
-                &event_tx,
-                device_id.clone(),
-                ip,
-                board,
-                app,
-                firmware_manifest_uri,
-            )
-            .await;
+            let device_id = target.device_id.clone();
+            info!(
+                "Starting firmware update for device {} ({}/{})",
+                device_id, target.board, target.app
+            );
+
+            let run = Run {
+                device_id: &device_id,
+                tracking: Some((&*active_updates, &event_tx)),
+            };
+            let result = Self::run_pipeline(&firmware_fetcher, &run, &target, &Flash { event_tx: &event_tx }).await;
 
             if let Err(e) = result {
                 error!("Update failed for device {}: {}", device_id, e);
@@ -198,275 +542,135 @@ impl OtaService {
         Ok(())
     }
 
-    /// Run the actual update process
-    async fn run_update(
-        firmware_fetcher: &FirmwareFetcher,
-        active_updates: &RwLock<HashMap<String, UpdateInfo>>,
-        event_tx: &broadcast::Sender<OtaEvent>,
-        device_id: String,
-        ip: String,
-        board: String,
-        app: String,
-        firmware_manifest_uri: Option<String>,
-    ) -> Result<()> {
-        info!(
-            "Starting firmware update for device {} ({}/{})",
-            device_id, board, app
-        );
+    /// Check whether an update would go through, without uploading anything
+    ///
+    /// Runs the update pipeline up to the upload, untracked: no events are
+    /// sent and the device's update state is left alone. An update already
+    /// running on the device is a failure, as it would be for a real one.
+    pub async fn dry_run(&self, target: &OtaTarget) -> OtaReadiness {
+        if let Err(e) = self.check_not_updating(&target.device_id).await {
+            return OtaReadiness::would_fail(&target.device_id, e.to_string());
+        }
 
-        // Helper to check if cancelled
-        let is_cancelled = || async {
-            let updates = active_updates.read().await;
-            updates
-                .get(&device_id)
-                .map(|u| matches!(u.state, UpdateState::Cancelled))
-                .unwrap_or(false)
+        let run = Run {
+            device_id: &target.device_id,
+            tracking: None,
         };
+        match Self::run_pipeline(&self.firmware_fetcher, &run, target, &NoUpload).await {
+            Ok(Some(readiness)) => readiness,
+            // Untracked runs can't be cancelled, but say so if one was
+            Ok(None) => OtaReadiness::would_fail(&target.device_id, "Cancelled"),
+            Err(e) => OtaReadiness::would_fail(&target.device_id, e.to_string()),
+        }
+    }
 
-        // Helper to update state
-        let set_state = |state: UpdateState| async {
-            let mut updates = active_updates.write().await;
-            if let Some(info) = updates.get_mut(&device_id) {
-                info.state = state.clone();
-            }
-            let _ = event_tx.send(OtaEvent {
-                device_id: device_id.clone(),
-                state,
-            });
-        };
+    /// Dry run several devices, [`DEFAULT_QUERY_CONCURRENCY`] at a time;
+    /// readiness comes back in the order given
+    pub async fn dry_run_batch(&self, targets: &[OtaTarget]) -> Vec<OtaReadiness> {
+        futures_util::stream::iter(targets)
+            .map(|target| self.dry_run(target))
+            .buffered(DEFAULT_QUERY_CONCURRENCY)
+            .collect()
+            .await
+    }
+
+    /// Resolve, download and check, then hand over to `stage`
+    ///
+    /// Returns None if the run was cancelled before getting to `stage`.
+    async fn run_pipeline<S: UploadStage>(
+        firmware_fetcher: &FirmwareFetcher,
+        run: &Run<'_>,
+        target: &OtaTarget,
+        stage: &S,
+    ) -> Result<Option<S::Output>> {
+        let (board, app) = (&target.board, &target.app);
 
         // 1. Fetch manifest to get download URL (requires explicit firmware_manifest_uri)
         let manifest = firmware_fetcher
-            .get_manifest(&board, &app, firmware_manifest_uri.as_deref())
+            .get_manifest(board, app, target.firmware_manifest_uri.as_deref())
             .await?
             .ok_or_else(|| anyhow!("No firmware manifest found for {}/{} (firmware_manifest_uri not configured)", board, app))?;
 
-        if is_cancelled().await {
-            return Ok(());
+        if run.is_cancelled().await {
+            return Ok(None);
         }
 
-        // 2. Download firmware binary
-        set_state(UpdateState::Downloading { progress: 0.0 }).await;
+        // 2. Download firmware binary; size and hash are checked
+        run.set_state(UpdateState::Downloading { progress: 0.0 }).await;
         info!(
             "Downloading firmware v{} from {}",
             manifest.latest.version, manifest.latest.url
         );
 
-        let firmware_data = firmware_fetcher.download_firmware(&manifest.latest).await?;
-        info!("Downloaded {} bytes", firmware_data.len());
+        let firmware = firmware_fetcher.download_firmware(&manifest.latest).await?;
+        info!("Downloaded {} bytes", firmware.len());
 
-        if is_cancelled().await {
-            return Ok(());
+        if run.is_cancelled().await {
+            return Ok(None);
         }
 
-        // 3. Upload to device via MCUmgr
-        set_state(UpdateState::Uploading { progress: 0.0 }).await;
-        info!("Uploading firmware to device at {}:{}", ip, MCUMGR_PORT);
-
-        // Create a temporary file for the firmware
-        // The mcumgr-client upload functions expect a file path
-        let temp_dir = std::env::temp_dir();
-        let temp_file = temp_dir.join(format!("firmware_{}.bin", device_id));
-        tokio::fs::write(&temp_file, &firmware_data).await?;
-
-        // Upload using mcumgr-client
-        // Note: This is blocking, so we run it in a blocking task
-        let temp_file_clone = temp_file.clone();
-        let device_id_clone = device_id.clone();
-        let event_tx_clone = event_tx.clone();
-        let active_updates_clone = active_updates.clone();
+        // 3. The device answers and its secondary slot is free
+        let round_trip = Self::check_device(target.addr).await?;
 
-        let ip_clone = ip.clone();
-        let upload_result = tokio::task::spawn_blocking(move || {
-            use mcumgr_client::{UdpTransport, UdpSpecs, upload_image_transport};
-
-            // Create UDP transport
-            let specs = UdpSpecs {
-                host: ip_clone,
-                port: MCUMGR_PORT,
-                timeout_s: 10,
-                mtu: 512,
-            };
-            let mut transport = UdpTransport::new(&specs)
-                .map_err(|e| anyhow!("Failed to create transport: {}", e))?;
-
-            // Upload with progress callback
-            upload_image_transport(
-                &mut transport,
-                &temp_file_clone,
-                0, // slot 0
-                Some(|uploaded: u64, total: u64| {
-                    let progress = uploaded as f32 / total as f32;
-                    // Send progress update (best effort)
-                    let _ = event_tx_clone.send(OtaEvent {
-                        device_id: device_id_clone.clone(),
-                        state: UpdateState::Uploading { progress },
-                    });
-                }),
-            )?;
-
-            Ok::<_, anyhow::Error>(())
-        })
-        .await??;
-
-        // Clean up temp file
-        let _ = tokio::fs::remove_file(&temp_file).await;
-
-        if is_cancelled().await {
-            return Ok(());
+        if run.is_cancelled().await {
+            return Ok(None);
         }
 
-        // 4. Mark image as pending test and reset
-        set_state(UpdateState::Confirming).await;
-        info!("Confirming firmware image");
-
-        let ip_clone = ip.clone();
-        let confirm_result = tokio::task::spawn_blocking(move || {
-            use mcumgr_client::{UdpTransport, UdpSpecs, list_transport, test_transport};
-
-            let specs = UdpSpecs {
-                host: ip_clone,
-                port: MCUMGR_PORT,
-                timeout_s: 5,
-                mtu: 1024,
-            };
-            let mut transport = UdpTransport::new(&specs)
-                .map_err(|e| anyhow!("Failed to create transport: {}", e))?;
-
-            // Get the hash of the uploaded image
-            let image_list = list_transport(&mut transport)?;
-
-            // Find the pending image (slot 1 typically)
-            let pending_hash = image_list
-                .images
-                .iter()
-                .find(|img| !img.confirmed && !img.active)
-                .map(|img| img.hash.clone())
-                .ok_or_else(|| anyhow!("No pending image found after upload"))?;
-
-            // Mark as pending test
-            test_transport(&mut transport, pending_hash, Some(false))?;
-
-            Ok::<_, anyhow::Error>(())
-        })
-        .await??;
-
-        if is_cancelled().await {
-            return Ok(());
-        }
-
-        // 5. Reset device
-        set_state(UpdateState::Rebooting).await;
-        info!("Resetting device");
-
-        let ip_clone = ip.clone();
-        let reset_result = tokio::task::spawn_blocking(move || {
-            use mcumgr_client::{UdpTransport, UdpSpecs, reset_transport};
-
-            let specs = UdpSpecs {
-                host: ip_clone,
-                port: MCUMGR_PORT,
-                timeout_s: 5,
-                mtu: 1024,
-            };
-            let mut transport = UdpTransport::new(&specs)
-                .map_err(|e| anyhow!("Failed to create transport: {}", e))?;
-
-            reset_transport(&mut transport)?;
-
-            Ok::<_, anyhow::Error>(())
-        })
-        .await??;
-
-        // 6. Wait for device to come back and verify
-        set_state(UpdateState::Verifying).await;
-        info!("Waiting for device to reboot...");
-
-        // Wait a bit for the device to reboot
-        tokio::time::sleep(Duration::from_secs(5)).await;
+        let prepared = Prepared {
+            release: manifest.latest,
+            firmware,
+            round_trip,
+        };
+        stage.run(run, target, prepared).await.map(Some)
+    }
 
-        if is_cancelled().await {
-            return Ok(());
+    /// Check the device answers and can take an image; returns the echo round trip time
+    async fn check_device(addr: SocketAddr) -> Result<Duration> {
+        let started = Instant::now();
+        if !probe_device(addr, CHECK_OPTIONS).await {
+            return Err(anyhow!("Device not reachable at {}", addr));
         }
+        let round_trip = started.elapsed();
 
-        // Try to verify the device came back with new firmware
-        // Give it a few retries since reboot takes time
-        let expected_mcuboot_hash = manifest.latest.mcuboot_hash.clone();
-        let mut verified = false;
-
-        for attempt in 0..10 {
-            tokio::time::sleep(Duration::from_secs(2)).await;
-
-            if is_cancelled().await {
-                return Ok(());
-            }
-
-            let ip_clone = ip.clone();
-            let expected_hash_clone = expected_mcuboot_hash.clone();
-
-            let verify_result = tokio::task::spawn_blocking(move || {
-                use mcumgr_client::{UdpTransport, UdpSpecs, list_transport};
-
-                let specs = UdpSpecs {
-                    host: ip_clone,
-                    port: MCUMGR_PORT,
-                    timeout_s: 2,
-                    mtu: 1024,
-                };
-                let mut transport = UdpTransport::new(&specs)
-                    .map_err(|e| anyhow!("Failed to create transport: {}", e))?;
-
-                let image_list = list_transport(&mut transport)?;
-
-                // Check if the active image is now the one we uploaded
-                let active_image = image_list
-                    .images
-                    .iter()
-                    .find(|img| img.active)
-                    .ok_or_else(|| anyhow!("No active image found"))?;
-
-                // Return both confirmed status and hash/version for verification
-                let hash_hex = hex::encode(&active_image.hash);
-                Ok::<_, anyhow::Error>((active_image.confirmed, hash_hex, active_image.version.clone()))
-            })
-            .await;
-
-            match verify_result {
-                Ok(Ok((confirmed, device_hash, _device_version))) => {
-                    if confirmed {
-                        // Verify by MCUboot hash
-                        if device_hash.eq_ignore_ascii_case(&expected_hash_clone) {
-                            info!("Device rebooted with correct firmware (hash verified)");
-                            verified = true;
-                            break;
-                        } else {
-                            warn!(
-                                "Hash mismatch after update! Expected: {}, Got: {}",
-                                &expected_hash_clone[..16], &device_hash[..16.min(device_hash.len())]
-                            );
-                        }
-                    } else {
-                        debug!("Device rebooted but firmware not yet confirmed (attempt {})", attempt + 1);
-                    }
-                }
-                Ok(Err(e)) => {
-                    debug!("Verification attempt {} failed: {}", attempt + 1, e);
-                }
-                Err(e) => {
-                    debug!("Verification task failed: {}", e);
-                }
-            }
+        let device = query_device(addr, CHECK_OPTIONS).await?;
+        if let Some(problem) = slot_problem(&device.images) {
+            return Err(anyhow!(problem));
         }
+        Ok(round_trip)
+    }
 
-        if !verified {
-            warn!("Could not verify firmware update, but device may still be running new image");
+    /// Upload an image, resuming after dropped connections, and check the
+    /// device's secondary slot holds it
+    ///
+    /// Chunks are sized to the device's SMP buffer. Progress events carry
+    /// the retry count and where the last retry resumed. Returns the image
+    /// hash to mark for test.
+    async fn upload_and_verify(
+        event_tx: &broadcast::Sender<OtaEvent>,
+        device_id: &str,
+        addr: SocketAddr,
+        firmware_data: &[u8],
+    ) -> Result<Vec<u8>> {
+        let defaults = UploadOptions::default();
+        let options = UploadOptions { chunk_size: negotiate_chunk_size(addr, &defaults).await, ..defaults };
+        let report = upload_image(addr.ip(), addr.port(), firmware_data, &options, |progress| {
+            // Best effort, like the other progress events
+            let _ = event_tx.send(OtaEvent {
+                device_id: device_id.to_string(),
+                state: UpdateState::Uploading {
+                    progress: progress.fraction(),
+                    retries: progress.retries,
+                    resumed_at: progress.resumed_fraction(),
+                },
+            });
+        })
+        .await?;
+        if report.retries > 0 {
+            info!(device = %device_id, retries = report.retries, restarted = report.restarted, "Firmware upload recovered");
         }
 
-        // 7. Mark as complete
-        set_state(UpdateState::Complete).await;
-        info!("Firmware update completed for device {}", device_id);
-
-        Ok(())
+        let hash = verify_uploaded_image(addr.ip(), addr.port(), DEFAULT_TIMEOUT_MS, firmware_data).await?;
+        Ok(hex::decode(hash)?)
     }
 
     fn send_event(&self, device_id: &str, state: UpdateState) {
@@ -487,18 +691,12 @@ impl OtaService {
         firmware_data: Vec<u8>,
     ) -> Result<()> {
         // Check if already updating
-        {
-            let updates = self.active_updates.read().await;
-            if let Some(info) = updates.get(&device_id) {
-                if !info.state.is_terminal() {
-                    return Err(anyhow!("Update already in progress for device {}", device_id));
-                }
-            }
-        }
+        self.check_not_updating(&device_id).await?;
 
         // Initialize update state (skip downloading since we have the binary)
         {
             let mut updates = self.active_updates.write().await;
+            self.check_accepting()?;
             updates.insert(
                 device_id.clone(),
                 UpdateInfo {
@@ -506,12 +704,12 @@ impl OtaService {
                     ip: ip.clone(),
                     board: "local".to_string(),
                     app: "local".to_string(),
-                    state: UpdateState::Uploading { progress: 0.0 },
+                    state: UpdateState::Uploading { progress: 0.0, retries: 0, resumed_at: None },
                 },
             );
         }
 
-        self.send_event(&device_id, UpdateState::Uploading { progress: 0.0 });
+        self.send_event(&device_id, UpdateState::Uploading { progress: 0.0, retries: 0, resumed_at: None });
 
         // Clone what we need for the spawned task
         let active_updates = self.active_updates.clone();
@@ -605,60 +803,27 @@ impl OtaService {
             return Ok(());
         }
 
-        // 1. Write firmware to temp file for mcumgr-client
-        let temp_dir = std::env::temp_dir();
-        let temp_file = temp_dir.join(format!("firmware_{}.bin", device_id));
-        tokio::fs::write(&temp_file, &firmware_data).await?;
-
-        // 2. Upload using mcumgr-client
-        let temp_file_clone = temp_file.clone();
-        let device_id_clone = device_id.clone();
-        let event_tx_clone = event_tx.clone();
-        let ip_clone = ip.clone();
-
-        let _upload_result = tokio::task::spawn_blocking(move || {
-            use mcumgr_client::{upload_image_transport, UdpSpecs, UdpTransport};
-
-            let specs = UdpSpecs {
-                host: ip_clone,
-                port: MCUMGR_PORT,
-                timeout_s: 10,
-                mtu: 512,
-            };
-            let mut transport =
-                UdpTransport::new(&specs).map_err(|e| anyhow!("Failed to create transport: {}", e))?;
-
-            upload_image_transport(
-                &mut transport,
-                &temp_file_clone,
-                0, // slot 0
-                Some(|uploaded: u64, total: u64| {
-                    let progress = uploaded as f32 / total as f32;
-                    let _ = event_tx_clone.send(OtaEvent {
-                        device_id: device_id_clone.clone(),
-                        state: UpdateState::Uploading { progress },
-                    });
-                }),
-            )?;
-
-            Ok::<_, anyhow::Error>(())
-        })
-        .await??;
-
-        // Clean up temp file
-        let _ = tokio::fs::remove_file(&temp_file).await;
+        // 1. Upload, resuming after dropped connections, and check the slot
+        let (addr, scope_id) =
+            DiscoveryInfo::parse_scoped_ip(&ip).ok_or_else(|| anyhow!("Invalid device address {}", ip))?;
+        let mut device_addr = SocketAddr::new(addr, MCUMGR_PORT);
+        if let (SocketAddr::V6(v6), Some(scope_id)) = (&mut device_addr, scope_id) {
+            v6.set_scope_id(scope_id);
+        }
+        let image_hash = Self::upload_and_verify(event_tx, &device_id, device_addr, &firmware_data).await?;
+        let expected_hash = hex::encode(&image_hash);
 
         if is_cancelled().await {
             return Ok(());
         }
 
-        // 3. Confirm and reboot
+        // 2. Confirm and reboot
         set_state(UpdateState::Confirming).await;
         info!("Confirming firmware image");
 
         let ip_clone = ip.clone();
         let _confirm_result = tokio::task::spawn_blocking(move || {
-            use mcumgr_client::{list_transport, test_transport, UdpSpecs, UdpTransport};
+            use mcumgr_client::{test_transport, UdpSpecs, UdpTransport};
 
             let specs = UdpSpecs {
                 host: ip_clone,
@@ -669,15 +834,7 @@ impl OtaService {
             let mut transport =
                 UdpTransport::new(&specs).map_err(|e| anyhow!("Failed to create transport: {}", e))?;
 
-            let image_list = list_transport(&mut transport)?;
-            let pending_hash = image_list
-                .images
-                .iter()
-                .find(|img| !img.confirmed && !img.active)
-                .map(|img| img.hash.clone())
-                .ok_or_else(|| anyhow!("No pending image found after upload"))?;
-
-            test_transport(&mut transport, pending_hash, Some(false))?;
+            test_transport(&mut transport, image_hash, Some(false))?;
 
             Ok::<_, anyhow::Error>(())
         })
@@ -687,7 +844,7 @@ impl OtaService {
             return Ok(());
         }
 
-        // 4. Reset device
+        // 3. Reset device
         set_state(UpdateState::Rebooting).await;
         info!("Resetting device");
 
@@ -710,67 +867,38 @@ impl OtaService {
         })
         .await??;
 
-        // 5. Wait for device to come back
+        // 4. Wait for device to come back
         set_state(UpdateState::Verifying).await;
         info!("Waiting for device to reboot...");
-        tokio::time::sleep(Duration::from_secs(5)).await;
+        tokio::time::sleep(REBOOT_WAIT).await;
 
         if is_cancelled().await {
             return Ok(());
         }
 
-        // For local uploads, we just verify the device comes back online
-        // (we don't have an expected hash to compare against)
+        // Verify the device booted the uploaded image and confirm it
         let mut verified = false;
         for attempt in 0..10 {
-            tokio::time::sleep(Duration::from_secs(2)).await;
+            tokio::time::sleep(VERIFY_INTERVAL).await;
 
             if is_cancelled().await {
                 return Ok(());
             }
 
-            let ip_clone = ip.clone();
-            let verify_result = tokio::task::spawn_blocking(move || {
-                use mcumgr_client::{list_transport, UdpSpecs, UdpTransport};
-
-                let specs = UdpSpecs {
-                    host: ip_clone,
-                    port: MCUMGR_PORT,
-                    timeout_s: 2,
-                    mtu: 1024,
-                };
-                let mut transport =
-                    UdpTransport::new(&specs).map_err(|e| anyhow!("Failed to create transport: {}", e))?;
-
-                let image_list = list_transport(&mut transport)?;
-                let active_image = image_list
-                    .images
-                    .iter()
-                    .find(|img| img.active)
-                    .ok_or_else(|| anyhow!("No active image found"))?;
-
-                Ok::<_, anyhow::Error>(active_image.confirmed)
-            })
-            .await;
-
-            match verify_result {
-                Ok(Ok(confirmed)) => {
-                    if confirmed {
-                        info!("Device rebooted with new firmware (confirmed)");
-                        verified = true;
-                        break;
-                    } else {
-                        debug!(
-                            "Device rebooted but firmware not yet confirmed (attempt {})",
-                            attempt + 1
-                        );
-                    }
+            match confirm_booted_image(device_addr, &expected_hash).await {
+                Ok(true) => {
+                    info!("Device rebooted with new firmware (confirmed)");
+                    verified = true;
+                    break;
                 }
-                Ok(Err(e)) => {
-                    debug!("Verification attempt {} failed: {}", attempt + 1, e);
+                Ok(false) => {
+                    debug!(
+                        "Device rebooted but not yet running the new firmware (attempt {})",
+                        attempt + 1
+                    );
                 }
                 Err(e) => {
-                    debug!("Verification task failed: {}", e);
+                    debug!("Verification attempt {} failed: {}", attempt + 1, e);
                 }
             }
         }
@@ -779,7 +907,7 @@ impl OtaService {
             warn!("Could not verify local firmware update, but device may still be running new image");
         }
 
-        // 6. Mark as complete
+        // 5. Mark as complete
         set_state(UpdateState::Complete).await;
         info!("Local firmware upload completed for device {}", device_id);
 
@@ -790,16 +918,196 @@ impl OtaService {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::firmware_fetch::compute_mcuboot_hash;
+    use axum::routing::get;
+    use axum::{Json, Router};
+    use dendrite_test_support::{mcuboot_image, MockHcdfInfo, MockSmpDevice};
+
+    /// Serve a manifest for `image` and the image itself; returns the manifest URI
+    async fn serve_firmware(image: Vec<u8>) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri = format!("http://{}/spinali/cerebri", listener.local_addr().unwrap());
+        let manifest = serde_json::json!({
+            "board": "spinali",
+            "app": "cerebri",
+            "latest": {
+                "version": "1.2.0",
+                "date": "2026-10-01T00:00:00Z",
+                "mcuboot_hash": compute_mcuboot_hash(&image).unwrap(),
+                "size": image.len(),
+                "url": format!("{}/cerebri.bin", uri),
+            }
+        });
+        let app = Router::new()
+            .route("/spinali/cerebri/latest.json", get(move || std::future::ready(Json(manifest.clone()))))
+            .route("/spinali/cerebri/cerebri.bin", get(move || std::future::ready(image.clone())));
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        uri
+    }
+
+    fn target(device_id: &str, addr: SocketAddr, firmware_manifest_uri: Option<&str>) -> OtaTarget {
+        OtaTarget {
+            device_id: device_id.to_string(),
+            addr,
+            board: "spinali".to_string(),
+            app: "cerebri".to_string(),
+            firmware_manifest_uri: firmware_manifest_uri.map(str::to_string),
+        }
+    }
+
+    fn reason(readiness: &OtaReadiness) -> &str {
+        match &readiness.readiness {
+            Readiness::WouldFail { reason } => reason,
+            Readiness::Ok => panic!("{} should not be ready", readiness.device_id),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_batch_against_simulator() {
+        let image = mcuboot_image(1000, 0x5a);
+        let uri = serve_firmware(image.clone()).await;
+
+        let ready = MockSmpDevice::spawn(MockHcdfInfo::default()).await.unwrap();
+        let pending = MockSmpDevice::spawn(MockHcdfInfo::default()).await.unwrap();
+        pending.set_pending_image(mcuboot_image(64, 0x11));
+        // Nothing listens on a port that was just released
+        let unreachable = std::net::UdpSocket::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+
+        let service = OtaService::new(Arc::new(FirmwareFetcher::new().unwrap()));
+        let mut events = service.subscribe();
+        let report = service
+            .dry_run_batch(&[
+                target("ready", ready.addr(), Some(&uri)),
+                target("unreachable", unreachable, Some(&uri)),
+                target("pending", pending.addr(), Some(&uri)),
+                target("no-manifest", ready.addr(), None),
+            ])
+            .await;
+
+        let ids: Vec<&str> = report.iter().map(|r| r.device_id.as_str()).collect();
+        assert_eq!(ids, ["ready", "unreachable", "pending", "no-manifest"]);
+
+        assert!(report[0].is_ok(), "{:?}", report[0]);
+        assert_eq!(report[0].version.as_deref(), Some("1.2.0"));
+        assert_eq!(report[0].image_bytes, Some(image.len() as u64));
+        let floor = (REBOOT_WAIT + VERIFY_INTERVAL).as_secs_f32();
+        assert!(report[0].estimated_secs.unwrap() >= floor);
+
+        assert!(reason(&report[1]).contains("not reachable"), "{}", reason(&report[1]));
+        assert!(reason(&report[2]).contains("pending test"), "{}", reason(&report[2]));
+        assert!(reason(&report[3]).contains("No firmware manifest"), "{}", reason(&report[3]));
+
+        // Nothing was uploaded, tracked or announced
+        assert!(ready.requests_for(1, 1).is_empty());
+        assert!(pending.requests_for(1, 1).is_empty());
+        assert!(service.get_all_updates().await.is_empty());
+        assert!(events.try_recv().is_err());
+    }
+
+    /// Track an update for `device_id` as if it were uploading
+    async fn track_upload(service: &OtaService, device_id: &str) {
+        service.active_updates.write().await.insert(
+            device_id.to_string(),
+            UpdateInfo {
+                device_id: device_id.to_string(),
+                ip: "127.0.0.1".to_string(),
+                board: "spinali".to_string(),
+                app: "cerebri".to_string(),
+                state: UpdateState::Uploading { progress: 0.4, retries: 0, resumed_at: None },
+            },
+        );
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_waits_for_running_update() {
+        let service = Arc::new(OtaService::new(Arc::new(FirmwareFetcher::new().unwrap())));
+        track_upload(&service, "flow").await;
+
+        // The upload finishes a little after shutdown starts
+        let finishing = service.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            finishing.active_updates.write().await.get_mut("flow").unwrap().state = UpdateState::Complete;
+            finishing.send_event("flow", UpdateState::Complete);
+        });
+
+        let started = Instant::now();
+        assert!(service.wait_for_updates(Duration::from_secs(10)).await.is_empty());
+        let waited = started.elapsed();
+        assert!(waited >= Duration::from_millis(200) && waited < Duration::from_secs(5), "{:?}", waited);
+
+        // No new updates once shutdown began
+        let addr = "127.0.0.1:1337".parse().unwrap();
+        let error = service.start_update(target("other", addr, None)).await.unwrap_err();
+        assert!(error.to_string().contains("shutting down"), "{}", error);
+        let error = service.upload_local_firmware("other".into(), "127.0.0.1".into(), vec![0; 16]).await.unwrap_err();
+        assert!(error.to_string().contains("shutting down"), "{}", error);
+        assert!(service.get_state("other").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_gives_up_after_timeout() {
+        let service = OtaService::new(Arc::new(FirmwareFetcher::new().unwrap()));
+        track_upload(&service, "flow").await;
+        track_upload(&service, "gps").await;
+        service.cancel_update("gps").await.unwrap();
+
+        let started = Instant::now();
+        assert_eq!(service.wait_for_updates(Duration::from_millis(300)).await, ["flow"]);
+        assert!(started.elapsed() >= Duration::from_millis(300));
+    }
+
+    #[tokio::test]
+    async fn test_device_images_degrade_to_empty() {
+        let device = MockSmpDevice::spawn(MockHcdfInfo::default()).await.unwrap();
+        device.set_pending_image(mcuboot_image(64, 0x11));
+        let response = device_images("abc", device.addr()).await;
+        assert_eq!(response.images.len(), 2);
+        assert!(response.has_pending() && response.error.is_none());
+
+        device.disable_image_group();
+        let response = device_images("abc", device.addr()).await;
+        assert!(response.images.is_empty());
+        assert_eq!(response.error.as_deref(), Some("image list not supported by the device"));
+    }
+
+    #[tokio::test]
+    async fn test_confirm_booted_image() {
+        let device = MockSmpDevice::spawn(MockHcdfInfo::default()).await.unwrap();
+        device.set_unconfirmed_active();
+        let running = "00".repeat(32);
+
+        // Still on some other image: nothing is confirmed
+        assert!(!confirm_booted_image(device.addr(), &"ab".repeat(32)).await.unwrap());
+        assert!(!list_images(device.addr()).await.unwrap()[0].confirmed);
+
+        assert!(confirm_booted_image(device.addr(), &running).await.unwrap());
+        assert!(list_images(device.addr()).await.unwrap()[0].confirmed);
+        let confirms = device.requests_for(1, 0).into_iter().filter(|r| r.op == 2).count();
+        assert_eq!(confirms, 1);
+
+        // Already confirmed images aren't confirmed again
+        assert!(confirm_booted_image(device.addr(), &running).await.unwrap());
+        assert_eq!(device.requests_for(1, 0).into_iter().filter(|r| r.op == 2).count(), 1);
+    }
 
     #[test]
-    fn test_update_state_is_terminal() {
-        assert!(!UpdateState::Downloading { progress: 0.5 }.is_terminal());
-        assert!(!UpdateState::Uploading { progress: 0.5 }.is_terminal());
-        assert!(!UpdateState::Confirming.is_terminal());
-        assert!(!UpdateState::Rebooting.is_terminal());
-        assert!(!UpdateState::Verifying.is_terminal());
-        assert!(UpdateState::Complete.is_terminal());
-        assert!(UpdateState::Failed { error: "test".to_string() }.is_terminal());
-        assert!(UpdateState::Cancelled.is_terminal());
+    fn test_slot_problem() {
+        let image = |slot: u32, pending: bool, active: bool| ImageSlot {
+            slot,
+            version: "1.0.0".to_string(),
+            hash: String::new(),
+            bootable: true,
+            pending,
+            confirmed: active,
+            active,
+        };
+        assert_eq!(slot_problem(&[image(0, false, true)]), None);
+        assert_eq!(slot_problem(&[image(0, false, true), image(1, false, false)]), None);
+        assert!(slot_problem(&[image(0, false, true), image(1, true, false)]).unwrap().contains("pending test"));
+        assert_eq!(
+            slot_problem(&[image(0, false, false), image(1, false, true)]).as_deref(),
+            Some("Device is running from slot 1")
+        );
     }
 }
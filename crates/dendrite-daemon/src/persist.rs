@@ -0,0 +1,140 @@
+//! Debounced persistence
+//!
+//! Position drags and other rapid edits update the in-memory HCDF on every
+//! request. Writing the file each time is expensive, so callers request a save
+//! and the actual write happens once the requests go quiet, or once the
+//! first of them has waited the maximum delay if they never do.
+
+use std::future::Future;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Coalesces save requests and runs the save once no new request arrives
+/// within the debounce delay, or at the latest `max_delay` after the first
+/// request of a burst
+#[derive(Clone)]
+pub struct DebouncedSaver {
+    tx: mpsc::UnboundedSender<()>,
+}
+
+impl DebouncedSaver {
+    /// Spawn the background task that performs the debounced save
+    pub fn spawn<F, Fut>(delay: Duration, max_delay: Duration, save: F) -> Self
+    where
+        F: Fn() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send,
+    {
+        let (tx, mut rx) = mpsc::unbounded_channel::<()>();
+
+        tokio::spawn(async move {
+            // Wait for the first request of a burst
+            while rx.recv().await.is_some() {
+                // Keep extending the quiet period while requests keep coming,
+                // but not past the burst's deadline
+                let deadline = tokio::time::Instant::now() + max_delay;
+                loop {
+                    tokio::select! {
+                        msg = rx.recv() => {
+                            if msg.is_none() {
+                                // Sender dropped - flush what we have and stop
+                                save().await;
+                                return;
+                            }
+                        }
+                        _ = tokio::time::sleep(delay) => break,
+                        _ = tokio::time::sleep_until(deadline) => break,
+                    }
+                }
+                save().await;
+            }
+        });
+
+        Self { tx }
+    }
+
+    /// Request a save; the write happens after the debounce delay, or the
+    /// maximum delay if requests keep coming
+    pub fn request(&self) {
+        let _ = self.tx.send(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    #[tokio::test]
+    async fn test_burst_persists_once_with_final_value() {
+        let current = Arc::new(Mutex::new(0u32));
+        let persisted = Arc::new(Mutex::new(Vec::new()));
+        let writes = Arc::new(AtomicUsize::new(0));
+
+        let saver = {
+            let current = current.clone();
+            let persisted = persisted.clone();
+            let writes = writes.clone();
+            DebouncedSaver::spawn(Duration::from_millis(100), Duration::from_secs(5), move || {
+                let value = *current.lock().unwrap();
+                persisted.lock().unwrap().push(value);
+                writes.fetch_add(1, Ordering::SeqCst);
+                async {}
+            })
+        };
+
+        for i in 1..=100 {
+            *current.lock().unwrap() = i;
+            saver.request();
+        }
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+
+        assert_eq!(writes.load(Ordering::SeqCst), 1);
+        assert_eq!(*persisted.lock().unwrap(), vec![100]);
+    }
+
+    #[tokio::test]
+    async fn test_separate_bursts_persist_separately() {
+        let writes = Arc::new(AtomicUsize::new(0));
+        let saver = {
+            let writes = writes.clone();
+            DebouncedSaver::spawn(Duration::from_millis(50), Duration::from_secs(5), move || {
+                writes.fetch_add(1, Ordering::SeqCst);
+                async {}
+            })
+        };
+
+        saver.request();
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        saver.request();
+        saver.request();
+        tokio::time::sleep(Duration::from_millis(150)).await;
+
+        assert_eq!(writes.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_steady_requests_save_by_the_max_delay() {
+        let start = tokio::time::Instant::now();
+        let saved_at = Arc::new(Mutex::new(Vec::new()));
+        let saver = {
+            let saved_at = saved_at.clone();
+            DebouncedSaver::spawn(Duration::from_millis(100), Duration::from_secs(1), move || {
+                saved_at.lock().unwrap().push(start.elapsed());
+                async {}
+            })
+        };
+
+        // A request every 50 ms never leaves the 100 ms quiet period
+        for _ in 0..50 {
+            saver.request();
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+
+        let saved_at = saved_at.lock().unwrap().clone();
+        assert_eq!(saved_at.len(), 2, "{:?}", saved_at);
+        assert_eq!(saved_at[0], Duration::from_secs(1));
+        assert!(saved_at[1] <= Duration::from_millis(2050), "{:?}", saved_at);
+    }
+}
@@ -0,0 +1,68 @@
+//! Dropping capabilities once discovery's raw sockets are open
+//!
+//! The daemon needs CAP_NET_RAW (granted with `setcap cap_net_raw+ep` or
+//! systemd's `AmbientCapabilities=CAP_NET_RAW`) only to open the LLDP and ICMP
+//! sockets, which `PreopenedSockets::open` does at startup. After that every
+//! capability is cleared, so the web server and everything it runs do so
+//! unprivileged. CAP_NET_BIND_SERVICE is kept when the web server binds a
+//! port below 1024, since that happens later.
+//!
+//! capset(2) changes only the calling thread, so this runs on the main
+//! thread before the tokio runtime is built; its worker and blocking threads
+//! start from the reduced sets (`tests/capabilities.rs` checks every thread).
+//!
+//! Only the process's own sets are cleared; the bounding set is left alone so
+//! `ping`, `fping` and `lldpcli` (setuid or file capabilities) keep working.
+
+use anyhow::Result;
+
+/// Clear the effective, permitted, inheritable and ambient capability sets
+///
+/// Call before any other thread is started: threads that already exist keep
+/// their capabilities.
+#[cfg(target_os = "linux")]
+pub fn drop_capabilities(bind: &str) -> Result<()> {
+    use caps::{CapSet, Capability, CapsHashSet};
+    use tracing::info;
+
+    let held = caps::read(None, CapSet::Permitted)?;
+    let mut keep = CapsHashSet::new();
+    if binds_privileged_port(bind) && held.contains(&Capability::CAP_NET_BIND_SERVICE) {
+        keep.insert(Capability::CAP_NET_BIND_SERVICE);
+    }
+
+    caps::clear(None, CapSet::Ambient)?;
+    caps::clear(None, CapSet::Inheritable)?;
+    caps::set(None, CapSet::Effective, &keep)?;
+    caps::set(None, CapSet::Permitted, &keep)?;
+
+    info!(dropped = held.len() - keep.len(), kept = ?keep, "Dropped capabilities");
+    Ok(())
+}
+
+/// Capabilities are Linux-only; elsewhere there is nothing to drop
+#[cfg(not(target_os = "linux"))]
+pub fn drop_capabilities(_bind: &str) -> Result<()> {
+    Ok(())
+}
+
+/// Whether a bind address has a port below 1024
+#[cfg(any(target_os = "linux", test))]
+fn binds_privileged_port(bind: &str) -> bool {
+    bind.rsplit_once(':')
+        .and_then(|(_, port)| port.parse::<u16>().ok())
+        .is_some_and(|port| port < 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binds_privileged_port() {
+        assert!(binds_privileged_port("0.0.0.0:443"));
+        assert!(binds_privileged_port("[::]:80"));
+        assert!(!binds_privileged_port("0.0.0.0:8080"));
+        assert!(!binds_privileged_port("localhost"));
+    }
+}
@@ -0,0 +1,280 @@
+//! Bulk HCDF regeneration from live devices
+//!
+//! A preview re-queries every online device for its embedded HCDF, merges the
+//! results into a copy of the current document and reports the diff. Nothing
+//! changes until the preview is applied with its token. Applying re-merges the
+//! fetched fragments onto the document as it is at that point, so pose edits
+//! made while the preview was open are kept.
+
+use chrono::Utc;
+use dendrite_core::{Comp, Device, DeviceStatus, Hcdf, HcdfChange, Provenance, ProvenanceSource};
+use futures_util::stream::{self, StreamExt};
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tracing::{debug, info, warn};
+
+use crate::state::{apply_port_overrides, resolve_model_url, AppState, HcdfFragmentData};
+
+/// Maximum number of devices queried at once
+const MAX_CONCURRENT_QUERIES: usize = 4;
+
+/// Previews older than this can no longer be applied
+const PREVIEW_TTL: Duration = Duration::from_secs(300);
+
+/// Error applying a regeneration preview
+#[derive(Debug, Error)]
+pub enum RegenerationError {
+    #[error("No regeneration preview is pending")]
+    NoPending,
+    #[error("Regeneration preview token does not match the pending preview")]
+    TokenMismatch,
+    #[error("Regeneration preview has expired, request a new one")]
+    Expired,
+}
+
+/// A device included in a regeneration report
+#[derive(Debug, Clone, Serialize)]
+pub struct RegenerationDevice {
+    pub id: String,
+    pub name: String,
+}
+
+/// An online device whose HCDF could not be refreshed
+#[derive(Debug, Clone, Serialize)]
+pub struct RegenerationFailure {
+    pub id: String,
+    pub name: String,
+    pub reason: String,
+}
+
+/// Result of a regeneration preview
+#[derive(Debug, Clone, Serialize)]
+pub struct RegenerationPreview {
+    /// Token to pass to apply
+    pub token: String,
+    /// Changes the regeneration would make to the current HCDF
+    pub changes: Vec<HcdfChange>,
+    /// Devices whose HCDF was fetched
+    pub refreshed: Vec<RegenerationDevice>,
+    /// Offline devices whose entries are kept unchanged
+    pub stale: Vec<RegenerationDevice>,
+    /// Online devices that could not be refreshed (entries kept unchanged)
+    pub failed: Vec<RegenerationFailure>,
+}
+
+/// Fragment fetched from one device during a preview
+struct FetchedFragment {
+    device: Device,
+    /// Comp with model references resolved to absolute URLs
+    comp: Comp,
+    data: HcdfFragmentData,
+}
+
+/// Preview awaiting confirmation
+pub(crate) struct PendingRegeneration {
+    token: String,
+    created: Instant,
+    fetched: Vec<FetchedFragment>,
+}
+
+impl AppState {
+    /// Query every online device for its HCDF and preview the merged result
+    pub async fn preview_regeneration(&self) -> RegenerationPreview {
+        let devices = self.scanner.devices().await;
+
+        let mut stale = Vec::new();
+        let mut online = Vec::new();
+        for device in devices {
+            // Comp-derived scene objects have no device to query
+            if device.id.as_str().starts_with("comp-") || device.id.as_str().starts_with("hwid:comp-") {
+                continue;
+            }
+            if device.status == DeviceStatus::Online {
+                online.push(device);
+            } else {
+                stale.push(report_device(&device));
+            }
+        }
+
+        info!(online = online.len(), stale = stale.len(), "Regenerating HCDF from devices");
+
+        let results: Vec<Result<FetchedFragment, RegenerationFailure>> = stream::iter(online)
+            .map(|device| self.fetch_fragment(device))
+            .buffer_unordered(MAX_CONCURRENT_QUERIES)
+            .collect()
+            .await;
+
+        let mut fetched = Vec::new();
+        let mut failed = Vec::new();
+        for result in results {
+            match result {
+                Ok(fragment) => fetched.push(fragment),
+                Err(failure) => failed.push(failure),
+            }
+        }
+
+        let current = self.get_hcdf();
+        let mut candidate = current.clone();
+        let parent_name = self.config.parent.first().map(|p| p.name.as_str());
+        merge_fragments(&mut candidate, &fetched, parent_name);
+        let changes = current.diff(&candidate).changes;
+
+        let token = format!("{:x}", Utc::now().timestamp_micros());
+        let refreshed = fetched.iter().map(|f| report_device(&f.device)).collect();
+
+        info!(
+            refreshed = fetched.len(),
+            failed = failed.len(),
+            changes = changes.len(),
+            "HCDF regeneration preview ready"
+        );
+
+        *self.pending_regeneration.lock().await = Some(PendingRegeneration {
+            token: token.clone(),
+            created: Instant::now(),
+            fetched,
+        });
+
+        RegenerationPreview { token, changes, refreshed, stale, failed }
+    }
+
+    /// Apply a pending regeneration preview, returning the refreshed device IDs
+    pub async fn apply_regeneration(&self, token: &str) -> Result<Vec<String>, RegenerationError> {
+        let pending = {
+            let mut slot = self.pending_regeneration.lock().await;
+            let Some(pending) = slot.as_ref() else {
+                return Err(RegenerationError::NoPending);
+            };
+            if pending.token != token {
+                return Err(RegenerationError::TokenMismatch);
+            }
+            if pending.created.elapsed() > PREVIEW_TTL {
+                *slot = None;
+                return Err(RegenerationError::Expired);
+            }
+            slot.take().expect("checked above")
+        };
+
+        let parent_name = self.config.parent.first().map(|p| p.name.clone());
+        let fetched = self.store.update(move |data| {
+            merge_fragments(&mut data.hcdf, &pending.fetched, parent_name.as_deref());
+            for fragment in &pending.fetched {
+                let device = &fragment.device;
+                data.provenance.record_mcu(
+                    device.id.as_str(),
+                    Provenance::new(ProvenanceSource::Device, Some(device.name.clone()), device.firmware.version.clone()),
+                );
+            }
+            pending.fetched
+        }).await;
+        self.schedule_save_hcdf();
+
+        let drivers = self.fragments.read().await.drivers().clone();
+        let mut updated = Vec::new();
+        for fragment in fetched {
+            // The device may have been removed since the preview
+            let Some(mut device) = self.scanner.get_device(&fragment.device.id).await else {
+                continue;
+            };
+            device.visuals = fragment.data.visuals;
+            device.frames = fragment.data.frames;
+            device.ports = fragment.data.ports;
+            let overrides = self.snapshot().hcdf.port_overrides(device.id.as_str());
+            apply_port_overrides(&mut device.ports, &overrides);
+            device.sensors = fragment.data.sensors;
+            for lint in drivers.enrich_sensors(&mut device.sensors) {
+                warn!(device = %device.id, "{}", lint);
+            }
+            if let Some(path) = device.visuals.first().and_then(|v| v.model_path.clone()) {
+                device.model_path = Some(path);
+            }
+            self.apply_model_override(&mut device).await;
+
+            updated.push(device.id.as_str().to_string());
+            self.scanner.update_device_silent(device.clone()).await;
+            self.scanner.broadcast_device_update(device).await;
+        }
+
+        info!(devices = updated.len(), "Applied HCDF regeneration");
+        Ok(updated)
+    }
+
+    /// Fetch one device's HCDF fragment
+    async fn fetch_fragment(&self, device: Device) -> Result<FetchedFragment, RegenerationFailure> {
+        let fail = |reason: &str| RegenerationFailure {
+            id: device.id.as_str().to_string(),
+            name: device.name.clone(),
+            reason: reason.to_string(),
+        };
+
+        if device.is_can() {
+            return Err(fail("CAN devices are described by local fragments only"));
+        }
+        let (Some(board), Some(app)) = (device.info.board.clone(), device.firmware.name.clone()) else {
+            return Err(fail("Device has not reported its board and firmware"));
+        };
+        let Some(remote) = self.fetch_remote_comp(&device, &board, &app).await else {
+            return Err(fail("No HCDF available from device"));
+        };
+        let data = self.convert_remote_comp(&remote).await.unwrap_or_default();
+        debug!(device = %device.id, visuals = data.visuals.len(), "Fetched HCDF for regeneration");
+
+        let mut comp = remote.comp;
+        resolve_comp_models(&mut comp, &remote.root_url);
+
+        Ok(FetchedFragment { device, comp, data })
+    }
+}
+
+/// Merge fetched fragments into `hcdf`, adding MCUs for devices it lacks
+fn merge_fragments(hcdf: &mut Hcdf, fetched: &[FetchedFragment], parent_name: Option<&str>) {
+    for fragment in fetched {
+        let hwid = fragment.device.id.as_str();
+        if !hcdf.merge_device_fragment(hwid, &fragment.comp) {
+            hcdf.upsert_device(&fragment.device, parent_name);
+            hcdf.merge_device_fragment(hwid, &fragment.comp);
+        }
+    }
+}
+
+fn report_device(device: &Device) -> RegenerationDevice {
+    RegenerationDevice {
+        id: device.id.as_str().to_string(),
+        name: device.name.clone(),
+    }
+}
+
+/// Rewrite model references to absolute URLs so the merged HCDF stays loadable
+fn resolve_comp_models(comp: &mut Comp, root_url: &str) {
+    if let Some(model) = comp.model.as_mut() {
+        model.href = resolve_model_url(&model.href, root_url);
+    }
+    for model in comp.visual.iter_mut().filter_map(|v| v.model.as_mut()) {
+        model.href = resolve_model_url(&model.href, root_url);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_comp_models() {
+        let xml = r#"<?xml version='1.0'?>
+<hcdf version="1.2">
+    <comp name="spinali">
+        <visual name="board"><model href="./models/spinali.glb"/></visual>
+        <visual name="case"><model href="https://example.com/case.glb"/></visual>
+    </comp>
+</hcdf>"#;
+        let mut comp = Hcdf::from_xml(xml).unwrap().comp.remove(0);
+        resolve_comp_models(&mut comp, "https://hcdf.cognipilot.org/");
+
+        let hrefs: Vec<_> = comp.visual.iter().map(|v| v.model.as_ref().unwrap().href.as_str()).collect();
+        assert_eq!(
+            hrefs,
+            vec!["https://hcdf.cognipilot.org/models/spinali.glb", "https://example.com/case.glb"]
+        );
+    }
+}
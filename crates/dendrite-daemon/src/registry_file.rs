@@ -0,0 +1,174 @@
+//! Device registry persistence
+//!
+//! The registry is written to `registry.json` in the data directory whenever
+//! it changes, at most once a second, and on shutdown. It is read back at
+//! startup so the scene isn't empty until the first scan completes; the
+//! topology is rebuilt from the restored devices' parents. Restored devices
+//! start with status Unknown until a scan or heartbeat sees them again.
+//!
+//! The file records its format version; older formats are migrated on load.
+//! A file that can't be read is renamed to `registry.json.corrupt` and the
+//! daemon starts with an empty registry.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use dendrite_core::{Device, DeviceStatus};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// File name inside the data directory
+pub const REGISTRY_FILE_NAME: &str = "registry.json";
+
+/// Current format version
+pub const REGISTRY_FORMAT_VERSION: u64 = 1;
+
+/// Changes are saved at most this often
+pub const SAVE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A registry that never stops changing is still saved this long after the
+/// first unsaved change
+pub const SAVE_MAX_DELAY: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredRegistry {
+    version: u64,
+    saved_at: DateTime<Utc>,
+    devices: Vec<Device>,
+}
+
+/// The registry file on disk
+#[derive(Debug, Clone)]
+pub struct RegistryFile {
+    path: PathBuf,
+}
+
+impl RegistryFile {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Devices saved by the previous run, with their status reset to Unknown
+    ///
+    /// A missing file is an empty registry; an unreadable one is moved aside.
+    pub fn load(&self) -> Vec<Device> {
+        let content = match std::fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Vec::new(),
+            Err(e) => {
+                warn!(path = %self.path.display(), error = %e, "Failed to read device registry");
+                return Vec::new();
+            }
+        };
+
+        match parse(&content) {
+            Ok(mut devices) => {
+                for device in &mut devices {
+                    device.status = DeviceStatus::Unknown;
+                }
+                info!(path = %self.path.display(), devices = devices.len(), "Restored device registry");
+                devices
+            }
+            Err(e) => {
+                let aside = self.corrupt_path();
+                match std::fs::rename(&self.path, &aside) {
+                    Ok(()) => warn!(
+                        path = %self.path.display(),
+                        moved_to = %aside.display(),
+                        error = %e,
+                        "Device registry is unreadable, moved aside"
+                    ),
+                    Err(rename_error) => warn!(
+                        path = %self.path.display(),
+                        error = %e,
+                        rename_error = %rename_error,
+                        "Device registry is unreadable and could not be moved aside"
+                    ),
+                }
+                Vec::new()
+            }
+        }
+    }
+
+    /// Write the registry, replacing the previous file atomically
+    pub fn save(&self, devices: &[Device]) -> io::Result<()> {
+        let stored = StoredRegistry {
+            version: REGISTRY_FORMAT_VERSION,
+            saved_at: Utc::now(),
+            devices: devices.to_vec(),
+        };
+        let json = serde_json::to_string_pretty(&stored).map_err(io::Error::other)?;
+        let tmp = self.path.with_extension("tmp");
+        std::fs::write(&tmp, json)?;
+        std::fs::rename(&tmp, &self.path)
+    }
+
+    fn corrupt_path(&self) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(".corrupt");
+        PathBuf::from(path)
+    }
+}
+
+fn parse(content: &str) -> Result<Vec<Device>> {
+    let value: Value = serde_json::from_str(content).context("invalid JSON")?;
+    let version = value.get("version").and_then(Value::as_u64).context("missing format version")?;
+    let value = migrate(version, value)?;
+    let stored: StoredRegistry = serde_json::from_value(value).context("invalid registry")?;
+    Ok(stored.devices)
+}
+
+/// Bring an older format up to the current version
+fn migrate(version: u64, value: Value) -> Result<Value> {
+    match version {
+        REGISTRY_FORMAT_VERSION => Ok(value),
+        // Upgrades from older versions go here as the format changes
+        _ => bail!("unsupported format version {}", version),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dendrite_test_support::{synthetic_registry, REGISTRY_DEVICES};
+
+    #[test]
+    fn test_round_trip_resets_status() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = RegistryFile::new(dir.path().join(REGISTRY_FILE_NAME));
+        assert!(file.load().is_empty());
+
+        let saved: Vec<Device> = serde_json::from_str(&synthetic_registry(REGISTRY_DEVICES)).unwrap();
+        file.save(&saved).unwrap();
+        let devices = file.load();
+
+        assert_eq!(devices.len(), REGISTRY_DEVICES);
+        assert!(devices.iter().all(|d| d.status == DeviceStatus::Unknown));
+        for (loaded, saved) in devices.iter().zip(&saved) {
+            let mut saved = saved.clone();
+            saved.status = DeviceStatus::Unknown;
+            assert_eq!(serde_json::to_value(loaded).unwrap(), serde_json::to_value(&saved).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_unreadable_file_is_moved_aside() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(REGISTRY_FILE_NAME);
+        let file = RegistryFile::new(&path);
+
+        for content in ["{\"version\": 1, \"devi", r#"{"version": 99, "saved_at": "2025-06-01T12:00:00Z", "devices": []}"#] {
+            std::fs::write(&path, content).unwrap();
+            assert!(file.load().is_empty());
+            assert!(!path.exists());
+            assert_eq!(std::fs::read_to_string(dir.path().join("registry.json.corrupt")).unwrap(), content);
+        }
+    }
+}
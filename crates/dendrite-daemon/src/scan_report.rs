@@ -0,0 +1,527 @@
+//! Scan-once output
+//!
+//! `dendrite --scan-once` prints a human summary by default. CI pipelines
+//! ask for the full discovered state instead: `--format json` emits the scan
+//! report with complete device structures (schema `SCHEMA_VERSION`),
+//! `--format csv` one row per device with nested fields flattened into
+//! dotted columns, and `--format hcdf` the HCDF document with the discovered
+//! devices merged in. `--expect <file>` compares the result against a
+//! reference in either format using the core HCDF diff, and
+//! `--require-devices` / `--expect-count` check the device count. The exit
+//! code tells those failures apart ([`ScanOutcome`]), and with
+//! `--log-format json` a [`ScanSummary`] line follows the logs on stderr.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use dendrite_core::{ChangeKind, Device, Hcdf, HcdfDiff};
+use dendrite_discovery::{IpNet, MethodStats, ScanStats};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::Duration;
+
+/// Version of the JSON scan report layout; bumped when a field is renamed or
+/// removed, not when one is added
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Output format for `--scan-once`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ScanFormat {
+    /// Human-readable device list
+    #[default]
+    #[value(alias = "table")]
+    Summary,
+    /// Scan report with full device structures
+    Json,
+    /// One row per device, nested fields in dotted columns (`firmware.version`)
+    Csv,
+    /// HCDF document with the discovered devices merged in
+    Hcdf,
+}
+
+/// Result of a single discovery scan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanReport {
+    /// Report layout version (`SCHEMA_VERSION`)
+    #[serde(default = "default_schema")]
+    pub schema: u32,
+    /// Scanned subnet in CIDR notation
+    pub subnet: String,
+    pub mcumgr_port: u16,
+    pub started_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    /// Number of devices found
+    pub found: usize,
+    /// What each discovery method contributed
+    #[serde(default)]
+    pub methods: ScanStats,
+    pub devices: Vec<Device>,
+}
+
+impl ScanReport {
+    pub fn new(
+        subnet: IpNet,
+        mcumgr_port: u16,
+        started_at: DateTime<Utc>,
+        duration: Duration,
+        methods: ScanStats,
+        devices: Vec<Device>,
+    ) -> Self {
+        Self {
+            schema: SCHEMA_VERSION,
+            subnet: subnet.to_string(),
+            mcumgr_port,
+            started_at,
+            duration_ms: duration.as_millis() as u64,
+            found: devices.len(),
+            methods,
+            devices,
+        }
+    }
+
+    /// Merge the discovered devices into `base`
+    pub fn to_hcdf(&self, mut base: Hcdf, parent_name: Option<&str>) -> Hcdf {
+        for device in &self.devices {
+            base.upsert_device(device, parent_name);
+        }
+        base
+    }
+
+    /// Render the report; `merged` is the HCDF emitted for `ScanFormat::Hcdf`
+    pub fn render(&self, format: ScanFormat, merged: &Hcdf) -> Result<String> {
+        match format {
+            ScanFormat::Summary => Ok(self.summary()),
+            ScanFormat::Json => {
+                let json = serde_json::to_string_pretty(self).context("Failed to serialize scan report")?;
+                Ok(format!("{}\n", json))
+            }
+            ScanFormat::Csv => self.csv(),
+            ScanFormat::Hcdf => {
+                let xml = merged.to_xml().context("Failed to serialize HCDF")?;
+                Ok(format!("{}\n", xml))
+            }
+        }
+    }
+
+    /// Devices as CSV: columns are every dotted field path any device has,
+    /// sorted, so a device set always gives the same header
+    fn csv(&self) -> Result<String> {
+        let mut rows = Vec::with_capacity(self.devices.len());
+        for device in &self.devices {
+            let value = serde_json::to_value(device).context("Failed to serialize device")?;
+            let mut row = BTreeMap::new();
+            flatten("", &value, &mut row);
+            rows.push(row);
+        }
+        let mut columns: Vec<&String> = rows.iter().flat_map(|row| row.keys()).collect();
+        columns.sort();
+        columns.dedup();
+
+        let mut out = String::new();
+        let header: Vec<String> = columns.iter().map(|c| csv_field(c)).collect();
+        let _ = writeln!(out, "{}", header.join(","));
+        for row in &rows {
+            let fields: Vec<String> =
+                columns.iter().map(|c| row.get(*c).map(|v| csv_field(v)).unwrap_or_default()).collect();
+            let _ = writeln!(out, "{}", fields.join(","));
+        }
+        Ok(out)
+    }
+
+    fn summary(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "Discovered {} devices:", self.devices.len());
+        for device in &self.devices {
+            match &device.discovery.can {
+                Some(can) => {
+                    let _ = writeln!(out, "  - {} ({}) on {} node {}", device.name, device.id, can.bus, can.node_id);
+                }
+                None => {
+                    let _ = writeln!(out, "  - {} ({}) at {}", device.name, device.id, device.discovery.socket_addr());
+                }
+            }
+            if let Some(board) = &device.info.board {
+                let _ = writeln!(out, "    Board: {}", board);
+            }
+            if let Some(version) = &device.firmware.version {
+                let _ = writeln!(out, "    Firmware: {}", version);
+            }
+        }
+        let methods = [
+            ("ARP", &self.methods.arp),
+            ("ICMP", &self.methods.icmp),
+            ("mDNS", &self.methods.mdns),
+            ("NDP", &self.methods.ndp),
+            ("CAN", &self.methods.can),
+        ];
+        for (name, stats) in methods {
+            if let Some(stats) = stats {
+                let _ = writeln!(out, "{}", method_line(name, stats));
+            }
+        }
+        out
+    }
+}
+
+fn default_schema() -> u32 {
+    SCHEMA_VERSION
+}
+
+/// Flatten objects into `prefix.key` entries; arrays stay JSON, null is left empty
+fn flatten(prefix: &str, value: &Value, out: &mut BTreeMap<String, String>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let path = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+                flatten(&path, value, out);
+            }
+        }
+        Value::Null => {
+            out.insert(prefix.to_string(), String::new());
+        }
+        Value::String(text) => {
+            out.insert(prefix.to_string(), text.clone());
+        }
+        other => {
+            out.insert(prefix.to_string(), other.to_string());
+        }
+    }
+}
+
+/// Quote a CSV field if it holds a separator, quote or line break (RFC 4180)
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// How a `--scan-once` run ended; each has its own exit code (clap exits
+/// with 2 on bad arguments)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanOutcome {
+    Ok,
+    /// `--expect` found differences or `--expect-count` didn't match
+    Mismatch,
+    /// Fewer devices than `--require-devices`
+    TooFewDevices,
+    /// No local interface is on the configured subnet, and either no method
+    /// that works without one is enabled or the scan found nothing
+    NoInterface,
+}
+
+impl ScanOutcome {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Self::Ok => 0,
+            Self::Mismatch => 1,
+            Self::TooFewDevices => 3,
+            Self::NoInterface => 4,
+        }
+    }
+}
+
+/// Why the device count fails `--require-devices` / `--expect-count`, if it
+/// does; an exact count replaces the minimum
+pub fn check_count(found: usize, require_devices: usize, expect_count: Option<usize>) -> Option<(ScanOutcome, String)> {
+    match expect_count {
+        Some(expected) if found != expected => {
+            Some((ScanOutcome::Mismatch, format!("expected {} devices, found {}", expected, found)))
+        }
+        Some(_) => None,
+        None if found == 0 && require_devices > 0 => Some((ScanOutcome::TooFewDevices, "no devices found".to_string())),
+        None if found < require_devices => Some((
+            ScanOutcome::TooFewDevices,
+            format!("found {} devices, at least {} required", found, require_devices),
+        )),
+        None => None,
+    }
+}
+
+/// Machine-readable outcome of a scan-once run, one JSON line
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename = "scan_summary")]
+pub struct ScanSummary {
+    pub outcome: ScanOutcome,
+    pub exit_code: i32,
+    pub subnet: String,
+    /// Interface on the subnet (None when there was none)
+    pub interface: Option<String>,
+    pub found: usize,
+    pub required: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_count: Option<usize>,
+    /// Differences from `--expect`, when given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub differences: Option<usize>,
+    pub duration_ms: u64,
+}
+
+/// "ICMP: 4 responders, 1 device, 2 excluded in 1300 ms"
+fn method_line(name: &str, stats: &MethodStats) -> String {
+    let mut line = format!(
+        "{}: {} responders, {} device{}, {} excluded in {} ms",
+        name,
+        stats.responders,
+        stats.devices,
+        if stats.devices == 1 { "" } else { "s" },
+        stats.excluded,
+        stats.duration_ms
+    );
+    for error in &stats.errors {
+        let _ = write!(line, "\n  error: {}", error);
+    }
+    line
+}
+
+/// Compare a scan against an expected manifest
+///
+/// `expected` is either an HCDF document, compared with `merged`, or a JSON
+/// scan report, whose devices are compared with this scan's devices on an
+/// empty document. Per-scan state (last seen timestamps) is ignored. Returns
+/// the changes that turn the expected document into the actual one.
+pub fn diff_expected(
+    expected: &str,
+    report: &ScanReport,
+    merged: &Hcdf,
+    parent_name: Option<&str>,
+) -> Result<HcdfDiff> {
+    let (mut expected, mut actual) = if expected.trim_start().starts_with('<') {
+        let expected = Hcdf::from_xml(expected).context("Failed to parse expected HCDF")?;
+        (expected, merged.clone())
+    } else {
+        let expected: ScanReport = serde_json::from_str(expected).context("Failed to parse expected scan report")?;
+        (
+            expected.to_hcdf(Hcdf::new(), parent_name),
+            report.to_hcdf(Hcdf::new(), parent_name),
+        )
+    };
+    strip_scan_state(&mut expected);
+    strip_scan_state(&mut actual);
+    Ok(expected.diff(&actual))
+}
+
+/// Clear fields that change on every scan
+fn strip_scan_state(hcdf: &mut Hcdf) {
+    for mcu in &mut hcdf.mcu {
+        if let Some(discovered) = &mut mcu.discovered {
+            discovered.last_seen = None;
+        }
+    }
+}
+
+/// One line per change: `+` added, `-` removed, `~` modified with its fields
+pub fn format_changes(diff: &HcdfDiff) -> String {
+    let mut out = String::new();
+    for change in &diff.changes {
+        let _ = match change.kind {
+            ChangeKind::Added => writeln!(out, "+ {} {} ({})", change.element, change.name, change.key),
+            ChangeKind::Removed => writeln!(out, "- {} {} ({})", change.element, change.name, change.key),
+            ChangeKind::Modified => writeln!(
+                out,
+                "~ {} {} ({}): {}",
+                change.element,
+                change.name,
+                change.key,
+                change.fields().join(", ")
+            ),
+        };
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dendrite_mcumgr::{query_device, query_result_to_device, QueryOptions};
+    use dendrite_test_support::{MockHcdfInfo, MockIdentity, MockSmpDevice};
+    use std::net::Ipv4Addr;
+
+    const OS_INFO: &str =
+        "Zephyr optical-flow 4ad28d86da70 4.3.0 Sun Jan  4 02:34:48 2026 arm cortex-m33 mr_mcxn_t1/mcxn947/cpu0 Zephyr";
+
+    async fn spawn_device(hwid: &str) -> MockSmpDevice {
+        MockSmpDevice::spawn_with_identity(
+            MockHcdfInfo::default(),
+            MockIdentity {
+                hwid: Some(hwid.to_string()),
+                os_info: Some(OS_INFO.to_string()),
+            },
+        )
+        .await
+        .unwrap()
+    }
+
+    /// Query the simulated devices the way a scan does
+    async fn scan(mocks: &[&MockSmpDevice]) -> ScanReport {
+        let mut devices = Vec::new();
+        for mock in mocks {
+            let ip = mock.addr().ip();
+            let result = query_device(mock.addr(), QueryOptions::default()).await.unwrap();
+            devices.push(query_result_to_device(ip, mock.port(), None, result));
+        }
+        let methods = ScanStats {
+            arp: Some(MethodStats {
+                responders: mocks.len(),
+                devices: mocks.len(),
+                duration_ms: 3,
+                ..Default::default()
+            }),
+            icmp: None,
+            mdns: None,
+            can: None,
+            ndp: None,
+        };
+        let subnet = IpNet::from((Ipv4Addr::LOCALHOST, 32));
+        ScanReport::new(subnet, 1337, Utc::now(), Duration::from_millis(5), methods, devices)
+    }
+
+    #[tokio::test]
+    async fn test_scan_once_formats() {
+        let mock = spawn_device("a1b2c3").await;
+        let report = scan(&[&mock]).await;
+        let merged = report.to_hcdf(Hcdf::new(), None);
+
+        let summary = report.render(ScanFormat::Summary, &merged).unwrap();
+        assert!(summary.starts_with("Discovered 1 devices:\n"));
+        assert!(summary.contains("  - optical-flow (a1b2c3) at 127.0.0.1:"));
+        assert!(summary.contains("    Board: mr_mcxn_t1\n"));
+        assert!(summary.ends_with("ARP: 1 responders, 1 device, 0 excluded in 3 ms\n"));
+
+        let json = report.render(ScanFormat::Json, &merged).unwrap();
+        let parsed: ScanReport = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.subnet, "127.0.0.1/32");
+        assert_eq!(parsed.found, 1);
+        assert_eq!(parsed.methods, report.methods);
+        assert_eq!(parsed.devices[0].id.as_str(), "a1b2c3");
+        assert_eq!(parsed.devices[0].discovery.port, mock.port());
+
+        let xml = report.render(ScanFormat::Hcdf, &merged).unwrap();
+        let hcdf = Hcdf::from_xml(&xml).unwrap();
+        assert_eq!(hcdf.mcu.len(), 1);
+        assert_eq!(hcdf.mcu[0].hwid.as_deref(), Some("a1b2c3"));
+        assert_eq!(hcdf.mcu[0].board.as_deref(), Some("mr_mcxn_t1"));
+        assert_eq!(hcdf.mcu[0].discovered.as_ref().unwrap().ip, "127.0.0.1");
+    }
+
+    #[tokio::test]
+    async fn test_scan_once_csv_and_schema() {
+        let first = spawn_device("a1b2c3").await;
+        let second = spawn_device("d4e5f6").await;
+        let report = scan(&[&first, &second]).await;
+        let merged = report.to_hcdf(Hcdf::new(), None);
+
+        let json: Value = serde_json::from_str(&report.render(ScanFormat::Json, &merged).unwrap()).unwrap();
+        assert_eq!(json["schema"], SCHEMA_VERSION);
+        assert_eq!(json["devices"].as_array().unwrap().len(), 2);
+        // What bring-up scripts pick out with jq
+        let device = &json["devices"][0];
+        assert_eq!(device["info"]["board"], "mr_mcxn_t1");
+        assert!(device["firmware"]["version"].is_string());
+        assert_eq!(device["discovery"]["ip"], "127.0.0.1");
+        assert_eq!(device["discovery"]["port"], first.port());
+        assert!(device["discovery"]["last_seen"].is_string());
+
+        let csv = report.render(ScanFormat::Csv, &merged).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        let header: Vec<&str> = lines[0].split(',').collect();
+        let column = |name: &str| header.iter().position(|h| *h == name).unwrap_or_else(|| panic!("{name}: {csv}"));
+        assert!(header.windows(2).all(|w| w[0] < w[1]), "{csv}");
+        let row = split_csv_line(lines[1]);
+        assert_eq!(row.len(), header.len());
+        assert_eq!(row[column("id")], "a1b2c3");
+        assert_eq!(row[column("info.board")], "mr_mcxn_t1");
+        assert_eq!(row[column("discovery.ip")], "127.0.0.1");
+        assert_eq!(row[column("discovery.port")], first.port().to_string());
+        assert!(!row[column("discovery.last_seen")].is_empty());
+        assert_eq!(row[column("discovery.switch_port")], "");
+        assert_eq!(split_csv_line(lines[2])[column("id")], "d4e5f6");
+    }
+
+    /// Split one CSV record, undoing `csv_field` quoting
+    fn split_csv_line(line: &str) -> Vec<String> {
+        let mut fields = vec![String::new()];
+        let mut quoted = false;
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' if quoted && chars.peek() == Some(&'"') => {
+                    chars.next();
+                    fields.last_mut().unwrap().push('"');
+                }
+                '"' => quoted = !quoted,
+                ',' if !quoted => fields.push(String::new()),
+                c => fields.last_mut().unwrap().push(c),
+            }
+        }
+        fields
+    }
+
+    #[test]
+    fn test_csv_quoting_and_count_checks() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(split_csv_line("x,\"a,b\",\"say \"\"hi\"\"\""), vec!["x", "a,b", "say \"hi\""]);
+
+        assert_eq!(check_count(0, 0, None), None);
+        assert_eq!(check_count(0, 1, None), Some((ScanOutcome::TooFewDevices, "no devices found".to_string())));
+        assert_eq!(check_count(2, 3, None), Some((ScanOutcome::TooFewDevices, "found 2 devices, at least 3 required".to_string())));
+        // An exact count replaces the minimum
+        assert_eq!(check_count(0, 1, Some(0)), None);
+        assert_eq!(check_count(2, 1, Some(3)), Some((ScanOutcome::Mismatch, "expected 3 devices, found 2".to_string())));
+
+        let summary = ScanSummary {
+            outcome: ScanOutcome::NoInterface,
+            exit_code: ScanOutcome::NoInterface.exit_code(),
+            subnet: "192.168.186.0/24".to_string(),
+            interface: None,
+            found: 0,
+            required: 1,
+            expected_count: None,
+            differences: None,
+            duration_ms: 0,
+        };
+        let line = serde_json::to_value(&summary).unwrap();
+        assert_eq!(line["type"], "scan_summary");
+        assert_eq!(line["outcome"], "no_interface");
+        assert_eq!(line["exit_code"], 4);
+    }
+
+    #[tokio::test]
+    async fn test_expect_matches_previous_scan() {
+        let mock = spawn_device("a1b2c3").await;
+        let first = scan(&[&mock]).await;
+        let first_hcdf = first.to_hcdf(Hcdf::new(), None);
+        let expected_xml = first.render(ScanFormat::Hcdf, &first_hcdf).unwrap();
+        let expected_json = first.render(ScanFormat::Json, &first_hcdf).unwrap();
+
+        // A later scan differs only in timestamps
+        let second = scan(&[&mock]).await;
+        let second_hcdf = second.to_hcdf(Hcdf::new(), None);
+        assert!(diff_expected(&expected_xml, &second, &second_hcdf, None).unwrap().is_empty());
+        assert!(diff_expected(&expected_json, &second, &second_hcdf, None).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_expect_reports_missing_and_extra_devices() {
+        let expected_mock = spawn_device("a1b2c3").await;
+        let expected = scan(&[&expected_mock]).await;
+        let expected_xml = expected.render(ScanFormat::Hcdf, &expected.to_hcdf(Hcdf::new(), None)).unwrap();
+
+        let other_mock = spawn_device("d4e5f6").await;
+        let actual = scan(&[&other_mock]).await;
+        let merged = actual.to_hcdf(Hcdf::new(), None);
+        let changes = diff_expected(&expected_xml, &actual, &merged, None).unwrap();
+        assert_eq!(changes.len(), 2);
+
+        let text = format_changes(&changes);
+        assert!(text.contains("- mcu optical-flow (a1b2c3)"), "{text}");
+        assert!(text.contains("+ mcu optical-flow (d4e5f6)"), "{text}");
+    }
+}
@@ -2,23 +2,61 @@
 
 use anyhow::Result;
 use axum::{
+    extract::DefaultBodyLimit,
     middleware,
     routing::{delete, get, post, put},
-    Router,
+    Extension, Router,
 };
+use dendrite_discovery::LldpTransmitter;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tower_http::cors::{Any, CorsLayer};
+use std::time::Duration;
+use tower_http::compression::predicate::{DefaultPredicate, Predicate, SizeAbove};
+use tower_http::compression::CompressionLayer;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
 use tower_http::services::ServeDir;
-use tracing::info;
+use tracing::{error, info, warn};
 
 use crate::api;
 use crate::auth::{self, AuthState};
-use crate::config::TlsConfig;
+use crate::config::{self, TlsConfig};
+use crate::console;
+use crate::setup::{self, SetupState};
 use crate::state::AppState;
+use crate::status_led::StatusLedSync;
 use crate::ws;
 
+/// REST responses at least this large are gzip/brotli compressed when the
+/// client accepts it; smaller ones aren't worth the CPU
+const COMPRESS_MIN_BYTES: u16 = 1024;
+
+/// CORS for the configured origins, or any origin when none are configured
+fn cors_layer(origins: &[String]) -> CorsLayer {
+    let layer = CorsLayer::new().allow_methods(Any).allow_headers(Any);
+    if origins.is_empty() {
+        return layer.allow_origin(Any);
+    }
+    let origins: Vec<_> = origins
+        .iter()
+        .filter_map(|origin| match origin.trim_end_matches('/').parse() {
+            Ok(value) => Some(value),
+            Err(_) => {
+                warn!(origin = %origin, "Ignoring invalid CORS origin");
+                None
+            }
+        })
+        .collect();
+    info!(origins = ?origins, "CORS restricted to configured origins");
+    layer.allow_origin(AllowOrigin::list(origins))
+}
+
 /// Run the web server (HTTP or HTTPS depending on config)
-pub async fn run(state: Arc<AppState>, bind: &str, tls: Option<&TlsConfig>) -> Result<()> {
+pub async fn run(
+    state: Arc<AppState>,
+    setup: Arc<SetupState>,
+    bind: &str,
+    tls: Option<&TlsConfig>,
+) -> Result<()> {
     // Get the cached models directory from the HCDF fetcher
     let cached_models_dir = state.hcdf_fetcher.models_dir().await;
     info!(
@@ -28,9 +66,13 @@ pub async fn run(state: Arc<AppState>, bind: &str, tls: Option<&TlsConfig>) -> R
     );
 
     // Initialize authentication state
-    let auth_state = Arc::new(AuthState::new(state.config.auth.clone()));
+    let auth_state = Arc::new(
+        AuthState::new(state.config.auth.clone())
+            .with_api_token(state.config.daemon.auth_token.clone(), state.config.daemon.auth_token_reads),
+    );
     info!(
         require_token = state.config.auth.require_token,
+        api_token = state.config.daemon.auth_token.is_some(),
         token_store = %state.config.auth.token_store_path,
         "Authentication configured"
     );
@@ -38,46 +80,103 @@ pub async fn run(state: Arc<AppState>, bind: &str, tls: Option<&TlsConfig>) -> R
     // Build API router with optional auth middleware
     let api_router = Router::new()
         .route("/devices", get(api::list_devices))
+        .route("/journal", get(api::get_journal))
+        .route("/attention", get(api::get_attention))
+        .route("/attention/{id}/ack", post(api::acknowledge_finding))
         .route("/devices/{id}", get(api::get_device))
         .route("/devices/{id}/query", post(api::query_device))
         .route("/topology", get(api::get_topology))
+        .route("/lldp/neighbors", get(api::list_lldp_neighbors))
+        .route("/summary", get(api::get_summary))
+        .route("/fleet/inventory", get(api::get_fleet_inventory))
+        .route("/metrics", get(api::get_metrics))
+        .route("/keepouts", get(api::get_keepouts))
+        .route("/assemblies", get(api::list_assemblies))
+        .route("/assemblies/{name}", put(api::set_assembly).delete(api::remove_assembly))
+        .route("/assemblies/{name}/pose", put(api::move_assembly))
         .route("/hcdf", get(api::get_hcdf))
         .route("/hcdf", post(api::save_hcdf))
         .route("/scan", post(api::trigger_scan))
-        .route("/devices/{id}", delete(api::remove_device))
-        .route("/config", get(api::get_config))
+        .route("/fragments/reload", post(api::reload_fragments))
+        .route("/devices/{id}", delete(api::remove_device).patch(api::patch_device))
+        .route("/config", get(api::get_config).put(api::put_config))
+        .route("/session", get(api::get_session))
+        .route("/version", get(api::get_version))
         .route("/interfaces", get(api::list_interfaces))
         .route("/subnet", post(api::update_subnet))
+        // First-run setup wizard
+        .route("/setup", get(api::get_setup))
+        .route("/setup/scan", post(api::setup_test_scan))
+        .route("/setup/skip", post(api::skip_setup))
         .route("/heartbeat", get(api::get_heartbeat))
         .route("/heartbeat", post(api::set_heartbeat))
+        .route("/devices/{id}/heartbeat", put(api::set_device_heartbeat_method))
+        .route("/devices/{id}/latency", get(api::get_device_latency))
+        .route("/devices/{id}/images", get(api::get_device_images))
+        .route("/devices/{id}/images/confirm", post(api::confirm_device_image))
+        .route("/devices/{id}/trace", post(api::set_device_trace))
+        .route("/devices/{id}/shell", post(api::run_device_shell))
         // Device position updates
         .route("/devices/{id}/position", put(api::update_device_position))
+        .route("/devices/positions", put(api::update_device_positions))
+        .route("/devices/{id}/constraint", put(api::update_device_constraint))
+        .route("/devices/{id}/ports/{name}/pose", put(api::update_port_pose))
+        .route("/devices/{id}/ports/{name}/pose", delete(api::reset_port_pose))
+        // Uploaded models replacing fragment models
+        .route(
+            "/devices/{id}/model",
+            put(api::set_model_override)
+                .delete(api::remove_model_override)
+                .layer(DefaultBodyLimit::max(state.config.models.max_override_bytes)),
+        )
         // Firmware checking
         .route("/firmware/check", get(api::check_all_firmware))
         .route("/firmware/{id}/check", get(api::check_firmware))
         // OTA firmware updates
         .route("/ota", get(api::get_all_ota_updates))
+        .route("/ota/batch", post(api::start_ota_batch))
         .route("/ota/{id}/start", post(api::start_ota_update))
         .route("/ota/{id}/progress", get(api::get_ota_progress))
         .route("/ota/{id}/cancel", post(api::cancel_ota_update))
         .route("/ota/{id}/upload-local", post(api::upload_local_firmware))
         // HCDF import/export (for file picker)
-        .route("/hcdf/export", get(api::export_hcdf))
+        .route("/hcdf/export", get(api::export_hcdf).post(api::export_hcdf_with_view))
+        .route("/hcdf/bundle", get(api::export_hcdf_bundle))
         .route("/hcdf/import", post(api::import_hcdf))
+        .route("/hcdf/diff", get(api::diff_hcdf_against).post(api::diff_hcdf))
         .route("/hcdf/save", post(api::save_hcdf_to_server))
+        .route("/hcdf/regenerate", post(api::regenerate_hcdf))
+        .route("/hcdf/regenerate/apply", post(api::apply_hcdf_regeneration))
         .with_state(state.clone())
+        .layer(Extension(setup.clone()))
+        .layer(Extension(auth_state.clone()))
         // Apply auth middleware to all API routes
         .layer(middleware::from_fn_with_state(
             auth_state.clone(),
             auth::auth_middleware,
-        ));
+        ))
+        // Device lists and HCDF exports are large and compress well
+        .layer(
+            CompressionLayer::new()
+                .compress_when(DefaultPredicate::new().and(SizeAbove::new(COMPRESS_MIN_BYTES))),
+        );
 
     // Build main router
     let app = Router::new()
         // Nest API routes under /api
         .nest("/api", api_router)
-        // WebSocket for real-time updates (no auth - uses token in message)
-        .route("/ws", get(ws::websocket_handler))
+        // WebSocket for real-time updates, guarded like a read-only API request
+        .route(
+            "/ws",
+            get(ws::websocket_handler)
+                .layer(middleware::from_fn_with_state(auth_state.clone(), auth::websocket_auth_middleware)),
+        )
+        // Serial console of a device; the handler only admits operators
+        .route(
+            "/api/devices/{id}/console",
+            get(console::console_handler)
+                .layer(middleware::from_fn_with_state(auth_state.clone(), auth::websocket_auth_middleware)),
+        )
         .with_state(state.clone())
         // Serve cached models (from remote HCDF fetch) - takes precedence
         .nest_service("/models", ServeDir::new(&cached_models_dir)
@@ -85,12 +184,7 @@ pub async fn run(state: Arc<AppState>, bind: &str, tls: Option<&TlsConfig>) -> R
         // Static files (WASM frontend) - must be fallback for root
         .fallback_service(ServeDir::new("web"))
         // CORS
-        .layer(
-            CorsLayer::new()
-                .allow_origin(Any)
-                .allow_methods(Any)
-                .allow_headers(Any),
-        );
+        .layer(cors_layer(&state.config.daemon.cors_origins));
 
     // Start discovery in background
     let scanner = state.scanner.clone();
@@ -100,12 +194,152 @@ pub async fn run(state: Arc<AppState>, bind: &str, tls: Option<&TlsConfig>) -> R
         }
     });
 
-    // Start server with or without TLS
-    if let Some(tls_config) = tls {
-        run_https(app, bind, tls_config).await
-    } else {
-        run_http(app, bind).await
+    // Push device status to status LEDs if enabled
+    if state.config.status_led.enabled {
+        Arc::new(StatusLedSync::new(state.config.status_led.clone())).spawn(state.subscribe());
+    }
+
+    // Journal registry events for past-state queries if enabled
+    if let Some(journal) = &state.journal {
+        journal.clone().spawn(state.scanner.clone(), state.subscribe());
     }
+
+    // Collect findings for the attention inbox if enabled
+    if let Some(attention) = &state.attention {
+        let sockets = state.sockets.status().cloned().unwrap_or_default();
+        attention.clone().spawn(state.scanner.clone(), state.store.clone(), state.subscribe(), sockets);
+    }
+
+    // Announce this host via LLDP if enabled. Without CAP_NET_RAW no channel
+    // could be opened at startup, and the attention inbox says so
+    if let Some(lldp_tx_config) = state.config.to_lldp_tx_config() {
+        let status = state.sockets.status();
+        if status.is_some_and(|s| s.lldp_interfaces.is_empty() && !s.lldp_errors.is_empty()) {
+            warn!("LLDP transmit disabled: no interface could be opened for raw frames");
+        } else {
+            let transmitter = LldpTransmitter::with_sockets(lldp_tx_config, state.sockets.clone());
+            tokio::spawn(async move {
+                if let Err(e) = transmitter.run().await {
+                    tracing::error!(error = %e, "LLDP transmitter failed");
+                }
+            });
+        }
+    }
+
+    // Apply safe settings from the configuration file on SIGHUP
+    #[cfg(unix)]
+    tokio::spawn(reload_on_hangup(state.clone(), auth_state.clone(), setup.path().to_path_buf()));
+
+    // Start server with or without TLS, until a shutdown signal
+    let serve = async {
+        if let Some(tls_config) = tls {
+            run_https(app, bind, tls_config).await
+        } else {
+            run_http(app, bind).await
+        }
+    };
+    // Keep serving while running OTA updates finish, so their progress
+    // stays visible and new ones are refused rather than cut off
+    let shutdown = async {
+        shutdown_signal().await;
+        let timeout = Duration::from_secs(state.config.daemon.shutdown_timeout_secs);
+        tokio::select! {
+            _ = state.ota_service.wait_for_updates(timeout) => {}
+            _ = shutdown_signal() => warn!("Second shutdown signal, exiting without waiting for OTA updates"),
+        }
+    };
+    let served = tokio::select! {
+        served = serve => served,
+        _ = shutdown => Ok(()),
+    };
+
+    // Keep what changed since the last debounced save
+    state.save_registry().await;
+    served
+}
+
+/// Resolves on Ctrl-C or SIGTERM
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!(error = %e, "Failed to listen for Ctrl-C");
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                warn!(error = %e, "Failed to listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+    info!("Shutting down");
+}
+
+/// Reload the configuration file on every SIGHUP
+#[cfg(unix)]
+async fn reload_on_hangup(state: Arc<AppState>, auth: Arc<AuthState>, path: PathBuf) {
+    let mut hangup = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()) {
+        Ok(signal) => signal,
+        Err(e) => {
+            warn!(error = %e, "Failed to listen for SIGHUP; configuration reload disabled");
+            return;
+        }
+    };
+    while hangup.recv().await.is_some() {
+        info!(path = %path.display(), "SIGHUP received, reloading configuration");
+        reload_config(&state, &auth, &path).await;
+    }
+}
+
+/// Apply the settings that are safe to change at runtime from the file at
+/// `path`; an invalid file is logged and the running configuration kept
+async fn reload_config(state: &AppState, auth: &AuthState, path: &Path) {
+    let reloaded = match config::load_config(path) {
+        Ok(reloaded) => reloaded,
+        Err(e) => {
+            error!(error = %e, "Rejected reloaded configuration, keeping the running one");
+            return;
+        }
+    };
+    let live = match setup::with_live_settings(&state.config, &reloaded) {
+        Ok(live) => live,
+        Err(e) => {
+            error!(error = %e, "Failed to merge reloaded configuration, keeping the running one");
+            return;
+        }
+    };
+    if setup::restart_required(&state.config, &reloaded) {
+        warn!("Some changed settings only take effect after a restart");
+    }
+
+    state.scanner.set_config(live.to_scanner_config()).await;
+    auth.set_api_token(live.daemon.auth_token.clone(), live.daemon.auth_token_reads).await;
+    let scanner = state.scanner.clone();
+    tokio::spawn(async move {
+        if let Err(e) = scanner.scan_once().await {
+            warn!(error = %e, "Scan after configuration reload failed");
+        }
+    });
+    info!(
+        subnet = %live.discovery.subnet,
+        prefix = live.discovery.prefix_len,
+        interval_secs = live.daemon.discovery_interval_secs,
+        api_token = live.daemon.auth_token.is_some(),
+        "Configuration reloaded"
+    );
 }
 
 /// Run plain HTTP server
@@ -119,7 +353,6 @@ async fn run_http(app: Router, bind: &str) -> Result<()> {
 /// Run HTTPS server with TLS
 async fn run_https(app: Router, bind: &str, tls: &TlsConfig) -> Result<()> {
     use axum_server::tls_rustls::RustlsConfig;
-    use std::path::PathBuf;
 
     let cert_path = PathBuf::from(&tls.cert);
     let key_path = PathBuf::from(&tls.key);
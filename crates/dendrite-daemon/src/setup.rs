@@ -0,0 +1,504 @@
+//! First-run setup
+//!
+//! A daemon started without a configuration file (or with `--setup`) reports
+//! itself as unconfigured on `GET /api/setup`, and dendrite-web walks the
+//! user through picking interfaces, a scan interval and TLS/auth before the
+//! result is written with `PUT /api/config`.
+
+use serde::Serialize;
+use std::net::Ipv4Addr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use thiserror::Error;
+
+use crate::auth::SessionRole;
+use crate::config::{check_config, Config, ConfigErrors, REDACTED};
+
+/// Largest subnet suggested for scanning; wider interface networks are
+/// narrowed to the /24 around the interface address
+const MIN_SUGGESTED_PREFIX: u8 = 24;
+
+/// Whether the setup wizard should be offered, and where the result goes
+#[derive(Debug)]
+pub struct SetupState {
+    path: PathBuf,
+    required: AtomicBool,
+}
+
+impl SetupState {
+    pub fn new(path: impl Into<PathBuf>, required: bool) -> Self {
+        Self {
+            path: path.into(),
+            required: AtomicBool::new(required),
+        }
+    }
+
+    /// Configuration file written by the wizard
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn is_required(&self) -> bool {
+        self.required.load(Ordering::Relaxed)
+    }
+
+    /// Whether a caller with `role` may replace the configuration: anyone
+    /// while the wizard is offered, only operator tokens once it is done
+    pub fn allows_config_write(&self, role: Option<SessionRole>) -> bool {
+        self.is_required() || role == Some(SessionRole::Operator)
+    }
+
+    /// Stop offering the wizard (config written or setup skipped)
+    pub fn complete(&self) {
+        self.required.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Network interface info for the UI
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NetworkInterface {
+    pub name: String,
+    pub ip: String,
+    pub subnet: String,
+    pub prefix_len: u8,
+}
+
+/// A subnet worth scanning, derived from an interface
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SubnetSuggestion {
+    pub interface: String,
+    pub subnet: String,
+    pub prefix_len: u8,
+    /// Number of host addresses a scan will probe
+    pub hosts: u32,
+}
+
+/// List IPv4 interfaces that devices could be attached to
+///
+/// Loopback, docker and virtual ethernet interfaces are left out.
+pub fn network_interfaces() -> Vec<NetworkInterface> {
+    use network_interface::{Addr, NetworkInterface as NI, NetworkInterfaceConfig};
+
+    NI::show()
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|iface| {
+            // First IPv4 address of each interface
+            iface.addr.iter().find_map(|addr| match addr {
+                Addr::V4(v4) => interface_info(&iface.name, v4.ip, v4.netmask),
+                Addr::V6(_) => None,
+            })
+        })
+        .collect()
+}
+
+/// Interface info for one IPv4 address, or None for interfaces to hide
+fn interface_info(name: &str, ip: Ipv4Addr, netmask: Option<Ipv4Addr>) -> Option<NetworkInterface> {
+    let hidden = ["lo", "docker", "br-", "veth"];
+    if ip.is_loopback() || hidden.iter().any(|prefix| name.starts_with(prefix)) {
+        return None;
+    }
+
+    // Count bits in netmask
+    let prefix_len = netmask.map(|m| u32::from(m).count_ones() as u8).unwrap_or(24);
+    Some(NetworkInterface {
+        name: name.to_string(),
+        ip: ip.to_string(),
+        subnet: network_address(ip, prefix_len).to_string(),
+        prefix_len,
+    })
+}
+
+/// Name of a local interface whose IPv4 network overlaps `subnet`, or None
+/// if the subnet isn't on any interface and a scan of it can't reach anything
+///
+/// Unlike [`network_interfaces`] nothing is hidden: a bench on a bridge or a
+/// loopback alias counts.
+pub fn subnet_interface(subnet: Ipv4Addr, prefix_len: u8) -> Option<String> {
+    use network_interface::{Addr, NetworkInterface as NI, NetworkInterfaceConfig};
+
+    NI::show().unwrap_or_default().into_iter().find_map(|iface| {
+        let on_subnet = iface.addr.iter().any(|addr| match addr {
+            Addr::V4(v4) => {
+                let iface_prefix = v4.netmask.map(|m| u32::from(m).count_ones() as u8).unwrap_or(32);
+                networks_overlap(v4.ip, iface_prefix, subnet, prefix_len)
+            }
+            Addr::V6(_) => false,
+        });
+        on_subnet.then_some(iface.name)
+    })
+}
+
+/// Whether two IPv4 networks share addresses: the wider one contains the other
+fn networks_overlap(a: Ipv4Addr, a_prefix: u8, b: Ipv4Addr, b_prefix: u8) -> bool {
+    let prefix_len = a_prefix.min(b_prefix);
+    network_address(a, prefix_len) == network_address(b, prefix_len)
+}
+
+fn network_address(ip: Ipv4Addr, prefix_len: u8) -> Ipv4Addr {
+    let mask = if prefix_len == 0 { 0 } else { !0u32 << (32 - prefix_len.min(32)) };
+    Ipv4Addr::from(u32::from(ip) & mask)
+}
+
+/// Subnets to offer for scanning, one per distinct interface network
+pub fn suggest_subnets(interfaces: &[NetworkInterface]) -> Vec<SubnetSuggestion> {
+    let mut suggestions: Vec<SubnetSuggestion> = Vec::new();
+    for iface in interfaces {
+        let Ok(ip) = iface.ip.parse::<Ipv4Addr>() else {
+            continue;
+        };
+        let prefix_len = iface.prefix_len.max(MIN_SUGGESTED_PREFIX);
+        let subnet = network_address(ip, prefix_len).to_string();
+        if suggestions.iter().any(|s| s.subnet == subnet && s.prefix_len == prefix_len) {
+            continue;
+        }
+        suggestions.push(SubnetSuggestion {
+            interface: iface.name.clone(),
+            subnet,
+            prefix_len,
+            hosts: host_count(prefix_len),
+        });
+    }
+    suggestions
+}
+
+fn host_count(prefix_len: u8) -> u32 {
+    match prefix_len {
+        32 => 1,
+        31 => 2,
+        p => (1u32 << (32 - p)) - 2,
+    }
+}
+
+/// Errors writing a configuration file
+#[derive(Debug, Error)]
+pub enum ConfigWriteError {
+    #[error("configuration cannot be written as TOML: {0}")]
+    Encode(#[from] toml::ser::Error),
+
+    #[error("invalid configuration:\n{0}")]
+    Invalid(ConfigErrors),
+
+    #[error("failed to write {path}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+/// Validate a configuration submitted as JSON and write it as TOML
+///
+/// The file is only replaced once every check passes; nulls are treated as
+/// absent keys.
+pub fn write_config(path: &Path, config: &serde_json::Value) -> Result<Config, ConfigWriteError> {
+    let content = toml::to_string_pretty(&without_nulls(config))?;
+    let parsed = check_config(&content).map_err(ConfigWriteError::Invalid)?;
+
+    // Write alongside and rename so a failed write never leaves half a file
+    let tmp = path.with_extension("toml.tmp");
+    let io_error = |source| ConfigWriteError::Io { path: path.to_path_buf(), source };
+    std::fs::write(&tmp, &content).map_err(io_error)?;
+    std::fs::rename(&tmp, path).map_err(io_error)?;
+    Ok(parsed)
+}
+
+fn without_nulls(value: &serde_json::Value) -> serde_json::Value {
+    use serde_json::Value;
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| (k.clone(), without_nulls(v)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(without_nulls).collect()),
+        other => other.clone(),
+    }
+}
+
+/// Put the running values back where a submitted config still holds the
+/// placeholders from [`Config::redacted`], so a config read from
+/// `GET /api/config` can be edited and written back
+pub fn restore_redacted(submitted: &serde_json::Value, running: &Config) -> serde_json::Value {
+    fn restore(value: &serde_json::Value, running: Option<&serde_json::Value>) -> serde_json::Value {
+        use serde_json::Value;
+        match value {
+            Value::String(s) if s == REDACTED => running.cloned().unwrap_or(Value::Null),
+            Value::Object(map) => Value::Object(
+                map.iter()
+                    .map(|(k, v)| (k.clone(), restore(v, running.and_then(|r| r.get(k)))))
+                    .collect(),
+            ),
+            other => other.clone(),
+        }
+    }
+    let running = serde_json::to_value(running).unwrap_or_default();
+    restore(submitted, Some(&running))
+}
+
+/// Keys the running daemon applies without a restart
+const LIVE_KEYS: &[(&str, Option<&str>)] = &[
+    ("daemon", Some("discovery_interval_secs")),
+    ("daemon", Some("heartbeat_interval_secs")),
+    ("daemon", Some("heartbeat_enabled")),
+    ("daemon", Some("offline_retry_min_secs")),
+    ("daemon", Some("offline_retry_max_secs")),
+    ("daemon", Some("heartbeat_jitter")),
+    ("daemon", Some("auth_token")),
+    ("daemon", Some("auth_token_reads")),
+    ("discovery", Some("subnet")),
+    ("discovery", Some("prefix_len")),
+    ("discovery", Some("mcumgr_port")),
+    ("discovery", Some("use_lldp")),
+    ("discovery", Some("use_arp")),
+    ("discovery", Some("use_probe")),
+    ("discovery", Some("arp_rate_limit")),
+    ("parent", None),
+    ("device_override", None),
+];
+
+/// Whether switching from `running` to `new` needs a daemon restart
+///
+/// Discovery settings go straight to the scanner and the static API token
+/// to the auth layer; the bind address, TLS, the token store, paths and
+/// background services are only read at startup.
+pub fn restart_required(running: &Config, new: &Config) -> bool {
+    let startup_only = |config: &Config| {
+        let mut value = serde_json::to_value(config).unwrap_or_default();
+        for (section, key) in LIVE_KEYS {
+            match (key, value.as_object_mut()) {
+                (Some(key), Some(root)) => {
+                    if let Some(table) = root.get_mut(*section).and_then(|t| t.as_object_mut()) {
+                        table.remove(*key);
+                    }
+                }
+                (None, Some(root)) => {
+                    root.remove(*section);
+                }
+                _ => {}
+            }
+        }
+        value
+    };
+    startup_only(running) != startup_only(new)
+}
+
+/// `running` with the settings the daemon applies without a restart taken
+/// from `new`, for a configuration reloaded while running
+///
+/// Everything else keeps its running value, so the bound socket, TLS and
+/// paths stay as they were started.
+pub fn with_live_settings(running: &Config, new: &Config) -> Result<Config, serde_json::Error> {
+    let mut merged = serde_json::to_value(running)?;
+    let new = serde_json::to_value(new)?;
+    for (section, key) in LIVE_KEYS {
+        let (Some(root), Some(new_root)) = (merged.as_object_mut(), new.as_object()) else {
+            continue;
+        };
+        match key {
+            Some(key) => {
+                let value = new_root.get(*section).and_then(|t| t.get(*key)).cloned();
+                if let Some(table) = root.get_mut(*section).and_then(|t| t.as_object_mut()) {
+                    match value {
+                        Some(value) => table.insert(key.to_string(), value),
+                        None => table.remove(*key),
+                    };
+                }
+            }
+            None => {
+                match new_root.get(*section).cloned() {
+                    Some(value) => root.insert(section.to_string(), value),
+                    None => root.remove(*section),
+                };
+            }
+        }
+    }
+    serde_json::from_value(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_config_writes_after_setup_need_an_operator() {
+        let setup = SetupState::new("dendrite.toml", true);
+        assert!(setup.allows_config_write(None));
+        assert!(setup.allows_config_write(Some(SessionRole::Viewer)));
+
+        setup.complete();
+        assert!(!setup.allows_config_write(None));
+        assert!(!setup.allows_config_write(Some(SessionRole::Viewer)));
+        assert!(setup.allows_config_write(Some(SessionRole::Operator)));
+    }
+
+    #[test]
+    fn test_interface_filtering_and_subnets() {
+        let iface = interface_info("eth0", Ipv4Addr::new(192, 168, 186, 17), Some(Ipv4Addr::new(255, 255, 255, 0))).unwrap();
+        assert_eq!(iface.subnet, "192.168.186.0");
+        assert_eq!(iface.prefix_len, 24);
+
+        assert!(interface_info("lo", Ipv4Addr::LOCALHOST, None).is_none());
+        assert!(interface_info("docker0", Ipv4Addr::new(172, 17, 0, 1), None).is_none());
+        assert!(interface_info("veth1234", Ipv4Addr::new(10, 1, 0, 1), None).is_none());
+        // Missing netmask assumes /24
+        assert_eq!(interface_info("wlan0", Ipv4Addr::new(10, 0, 3, 9), None).unwrap().subnet, "10.0.3.0");
+    }
+
+    #[test]
+    fn test_networks_overlap() {
+        let ip = |s: &str| s.parse::<Ipv4Addr>().unwrap();
+        assert!(networks_overlap(ip("192.168.186.17"), 24, ip("192.168.186.0"), 24));
+        // A /24 scan inside an interface's /16, and the other way around
+        assert!(networks_overlap(ip("10.20.30.40"), 16, ip("10.20.99.0"), 24));
+        assert!(networks_overlap(ip("10.20.30.40"), 28, ip("10.20.0.0"), 16));
+        assert!(!networks_overlap(ip("192.168.7.2"), 30, ip("192.168.186.0"), 24));
+    }
+
+    #[test]
+    fn test_suggestions_narrow_wide_networks_and_dedupe() {
+        let iface = |name: &str, ip: &str, prefix_len: u8| NetworkInterface {
+            name: name.to_string(),
+            ip: ip.to_string(),
+            subnet: String::new(),
+            prefix_len,
+        };
+        let suggestions = suggest_subnets(&[
+            iface("eth0", "192.168.186.10", 24),
+            iface("eth1", "192.168.186.11", 24),
+            iface("wlan0", "10.20.30.40", 16),
+            iface("usb0", "192.168.7.2", 30),
+        ]);
+
+        let found: Vec<_> = suggestions.iter().map(|s| (s.interface.as_str(), s.subnet.as_str(), s.prefix_len, s.hosts)).collect();
+        assert_eq!(
+            found,
+            vec![
+                ("eth0", "192.168.186.0", 24, 254),
+                ("wlan0", "10.20.30.0", 24, 254),
+                ("usb0", "192.168.7.0", 30, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_write_config_validates_before_writing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dendrite.toml");
+
+        let invalid = json!({ "discovery": { "prefix_len": 40, "subnett": "10.0.0.0" } });
+        let Err(ConfigWriteError::Invalid(errors)) = write_config(&path, &invalid) else {
+            panic!("expected validation errors");
+        };
+        let keys: Vec<_> = errors.0.iter().map(|i| i.key.as_str()).collect();
+        assert_eq!(keys, vec!["discovery.subnett", "discovery.prefix_len"]);
+        assert!(!path.exists());
+
+        let valid = json!({
+            "daemon": { "discovery_interval_secs": 30, "tls": null },
+            "discovery": { "subnet": "10.0.3.0", "prefix_len": 24, "lldp_transmit": true, "lldp_tx_interfaces": ["eth0"] },
+            "auth": { "require_token": true },
+        });
+        let config = write_config(&path, &valid).unwrap();
+        assert_eq!(config.daemon.discovery_interval_secs, 30);
+        assert!(config.auth.require_token);
+
+        // The written file round-trips through the normal loader
+        let loaded = check_config(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(loaded.discovery.subnet, Ipv4Addr::new(10, 0, 3, 0));
+        assert_eq!(loaded.discovery.lldp_tx_interfaces, vec!["eth0".to_string()]);
+        assert!(loaded.daemon.tls.is_none());
+    }
+
+    #[test]
+    fn test_redacted_values_are_restored() {
+        let mut running = Config::default();
+        running.daemon.tls = Some(crate::config::TlsConfig {
+            cert: "/etc/dendrite/cert.pem".to_string(),
+            key: "/etc/dendrite/key.pem".to_string(),
+        });
+        let mut submitted = serde_json::to_value(running.redacted()).unwrap();
+        submitted["daemon"]["discovery_interval_secs"] = json!(10);
+
+        let restored = restore_redacted(&submitted, &running);
+        assert_eq!(restored["daemon"]["tls"]["key"], "/etc/dendrite/key.pem");
+        assert_eq!(restored["auth"]["token_store_path"], running.auth.token_store_path.as_str());
+        assert_eq!(restored["daemon"]["discovery_interval_secs"], 10);
+
+        // A placeholder with nothing behind it is dropped rather than written
+        let enabled_tls = json!({ "daemon": { "tls": { "cert": "/c.pem", "key": REDACTED } } });
+        let restored = restore_redacted(&enabled_tls, &Config::default());
+        assert!(restored["daemon"]["tls"]["key"].is_null());
+    }
+
+    #[test]
+    fn test_restart_required_only_for_startup_settings() {
+        let running = Config::default();
+
+        let mut live = running.clone();
+        live.discovery.subnet = Ipv4Addr::new(10, 0, 3, 0).into();
+        live.daemon.discovery_interval_secs = 15;
+        live.daemon.heartbeat_enabled = true;
+        assert!(!restart_required(&running, &live));
+
+        let mut auth = running.clone();
+        auth.auth.require_token = true;
+        assert!(restart_required(&running, &auth));
+
+        let mut lldp = running.clone();
+        lldp.discovery.lldp_transmit = true;
+        assert!(restart_required(&running, &lldp));
+    }
+
+    #[test]
+    fn test_reload_takes_only_live_settings() {
+        let mut running = Config::default();
+        running.daemon.bind = "127.0.0.1:9000".to_string();
+        running.daemon.auth_token = Some("old".to_string());
+
+        let mut reloaded = running.clone();
+        reloaded.daemon.discovery_interval_secs = 15;
+        reloaded.discovery.subnet = Ipv4Addr::new(10, 0, 3, 0).into();
+        reloaded.discovery.arp_rate_limit = Some(50);
+        reloaded.daemon.auth_token = Some("new".to_string());
+        reloaded.daemon.auth_token_reads = true;
+        // Needs a restart, so not applied
+        reloaded.daemon.bind = "0.0.0.0:8080".to_string();
+        reloaded.auth.require_token = true;
+        reloaded.hcdf.path = "/tmp/other.hcdf".to_string();
+        reloaded.discovery.query_concurrency = 2;
+
+        let merged = with_live_settings(&running, &reloaded).unwrap();
+        assert_eq!(merged.daemon.discovery_interval_secs, 15);
+        assert_eq!(merged.discovery.subnet, reloaded.discovery.subnet);
+        assert_eq!(merged.discovery.arp_rate_limit, Some(50));
+        assert_eq!(merged.daemon.auth_token.as_deref(), Some("new"));
+        assert!(merged.daemon.auth_token_reads);
+
+        assert_eq!(merged.daemon.bind, "127.0.0.1:9000");
+        assert!(!merged.auth.require_token);
+        assert_eq!(merged.hcdf.path, running.hcdf.path);
+        assert_eq!(merged.discovery.query_concurrency, running.discovery.query_concurrency);
+        // Only the startup settings differ from the file, and they stay put
+        assert!(!restart_required(&running, &merged));
+        assert!(restart_required(&running, &reloaded));
+
+        // Removing a live setting from the file turns it off
+        let mut cleared = reloaded.clone();
+        cleared.daemon.auth_token = None;
+        cleared.discovery.arp_rate_limit = None;
+        let merged = with_live_settings(&running, &cleared).unwrap();
+        assert_eq!(merged.daemon.auth_token, None);
+        assert_eq!(merged.discovery.arp_rate_limit, None);
+    }
+
+    #[test]
+    fn test_setup_state_completes() {
+        let setup = SetupState::new("dendrite.toml", true);
+        assert!(setup.is_required());
+        setup.complete();
+        assert!(!setup.is_required());
+    }
+}
@@ -1,37 +1,58 @@
 //! Application state management
 
 use anyhow::Result;
-use dendrite_core::{Comp, Device, DeviceAxisAlign, DeviceFrame, DeviceFov, DeviceGeometry, DeviceId, DevicePort, DeviceSensor, DeviceVisual, FragmentDatabase, Hcdf, Topology, parse_pose_string, sha256_hex};
-use dendrite_core::hcdf::{Geometry, Sensor, Fov};
-use dendrite_discovery::{DiscoveryEvent, DiscoveryScanner};
-use dendrite_mcumgr::query::query_hcdf_info;
-use std::path::Path;
+use dendrite_core::{Comp, Device, DeviceAxisAlign, DeviceFrame, DeviceFov, DeviceGeometry, DeviceId, DevicePort, DeviceSensor, DeviceVisual, Fov, FragmentDatabase, FragmentError, FragmentReload, Frame, Geometry, Hcdf, Port, Pose, Provenance, ProvenanceSource, RuntimeFields, Sensor, Topology, parse_pose_string, sha256_hex, validate_frames};
+use dendrite_discovery::{DiscoveryEvent, DiscoveryScanner, Registry, SocketProvider};
+use dendrite_mcumgr::query_hcdf_info;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tokio::sync::{broadcast, RwLock};
-use tracing::{debug, info, warn};
+use std::time::{Duration, SystemTime};
+use tokio::sync::{broadcast, Mutex, RwLock};
+use tracing::{debug, info, info_span, warn, Instrument};
 
 use crate::config::Config;
+use crate::console::ConsoleSessions;
 use crate::firmware_fetch::FirmwareFetcher;
 use crate::hcdf_fetch::HcdfFetcher;
+use crate::attention::AttentionInbox;
+use crate::journal::DeviceJournal;
 use crate::ota::OtaService;
+use crate::persist::DebouncedSaver;
+use crate::regenerate::PendingRegeneration;
+use crate::registry_file::{RegistryFile, REGISTRY_FILE_NAME, SAVE_INTERVAL, SAVE_MAX_DELAY};
+use crate::store::{StateData, StateSnapshot, StateStore};
+use crate::trace::DeviceTracer;
+
+/// How often a watched fragment index and its files are checked for changes
+const FRAGMENT_WATCH_INTERVAL: Duration = Duration::from_secs(2);
 
 /// Result of fetching and parsing an HCDF fragment
 #[derive(Debug, Default)]
-struct HcdfFragmentData {
-    visuals: Vec<DeviceVisual>,
-    frames: Vec<DeviceFrame>,
-    ports: Vec<DevicePort>,
-    sensors: Vec<DeviceSensor>,
+pub(crate) struct HcdfFragmentData {
+    pub visuals: Vec<DeviceVisual>,
+    pub frames: Vec<DeviceFrame>,
+    pub ports: Vec<DevicePort>,
+    pub sensors: Vec<DeviceSensor>,
+}
+
+/// A device's own HCDF description, as fetched from its reported URL
+#[derive(Debug, Clone)]
+pub(crate) struct RemoteHcdf {
+    /// First comp (or MCU converted to a comp) in the document
+    pub comp: Comp,
+    /// Base URL for resolving relative model paths
+    pub root_url: String,
+    /// SHA256 of the fetched document, used to link cached models
+    pub hcdf_sha: String,
 }
 
 /// Shared application state
 pub struct AppState {
     /// Discovery scanner
     pub scanner: Arc<DiscoveryScanner>,
-    /// HCDF document
-    pub hcdf: Arc<RwLock<Hcdf>>,
-    /// Device topology
-    pub topology: Arc<RwLock<Topology>>,
+    /// Device registry, HCDF document, topology and provenance, owned by the state task
+    pub store: StateStore,
     /// Fragment database for board/app to model mapping
     pub fragments: Arc<RwLock<FragmentDatabase>>,
     /// Remote HCDF fetcher with caching
@@ -44,11 +65,28 @@ pub struct AppState {
     pub config: Config,
     /// Event broadcast for WebSocket clients
     pub events: broadcast::Sender<DiscoveryEvent>,
+    /// Debounced HCDF file writer for high-frequency edits
+    hcdf_saver: DebouncedSaver,
+    /// Device registry file and its debounced writer, unless persistence is disabled
+    registry_file: Option<RegistryFile>,
+    registry_saver: Option<DebouncedSaver>,
+    /// Regeneration preview awaiting confirmation
+    pub(crate) pending_regeneration: Mutex<Option<PendingRegeneration>>,
+    /// Per-device SMP protocol tracing
+    pub tracer: DeviceTracer,
+    /// Registry journal for past-state queries, when enabled
+    pub journal: Option<Arc<DeviceJournal>>,
+    /// Attention inbox, when enabled
+    pub attention: Option<Arc<AttentionInbox>>,
+    /// Raw sockets for discovery, opened before capabilities were dropped
+    pub sockets: Arc<dyn SocketProvider>,
+    /// Devices whose serial console is open
+    pub consoles: ConsoleSessions,
 }
 
 impl AppState {
     /// Create new application state
-    pub async fn new(config: Config) -> Result<Arc<Self>> {
+    pub async fn new(config: Config, sockets: Arc<dyn SocketProvider>) -> Result<Arc<Self>> {
         // Load or create HCDF document
         let hcdf = load_or_create_hcdf(&config.hcdf.path)?;
 
@@ -59,10 +97,9 @@ impl AppState {
         let fragments = load_fragments(&config.fragments.path);
 
         // Create HCDF fetcher with cache in fragments directory
-        let cache_dir = Path::new(&config.fragments.path)
-            .parent()
-            .unwrap_or(Path::new("."))
-            .join("cache");
+        let data_dir = config.data_dir();
+        let cache_dir = data_dir.join("cache");
+        let tracer = DeviceTracer::new(data_dir.join("traces"));
         let hcdf_fetcher = Arc::new(HcdfFetcher::new(cache_dir)?);
 
         // Create firmware fetcher
@@ -71,25 +108,123 @@ impl AppState {
         // Create OTA service
         let ota_service = Arc::new(OtaService::new(firmware_fetcher.clone()));
 
-        // Create discovery scanner
+        // Bring back the previous run's devices so the scene isn't empty
+        // until the first scan completes
+        let registry_file = config.daemon.persist_registry.then(|| RegistryFile::new(data_dir.join(REGISTRY_FILE_NAME)));
+        let mut registry = Registry::default();
+        if let Some(file) = &registry_file {
+            for device in file.load() {
+                registry.devices.insert(device.id.0.clone(), device);
+            }
+        }
+
+        // One task owns the registry and the document
+        let store = StateStore::spawn(StateData::new(hcdf, topology, registry));
+
+        // Create discovery scanner, keeping its devices in the store
         let scanner_config = config.to_scanner_config();
-        let scanner = Arc::new(DiscoveryScanner::new(scanner_config));
+        let scanner = Arc::new(DiscoveryScanner::with_registry(scanner_config, sockets.clone(), Arc::new(store.clone())));
+
+        let registry_saver = registry_file.clone().map(|file| {
+            let scanner = scanner.clone();
+            DebouncedSaver::spawn(SAVE_INTERVAL, SAVE_MAX_DELAY, move || {
+                let scanner = scanner.clone();
+                let file = file.clone();
+                async move { write_registry(&file, &scanner).await }
+            })
+        });
 
         // Create event channel
         let (events, _) = broadcast::channel(100);
 
+        // Debounced writer saves whatever snapshot is current when it fires.
+        // The file holds the canonical document without discovery state, so
+        // a scan of an unchanged fleet leaves it untouched
+        let hcdf_saver = {
+            let store = store.clone();
+            let path = config.hcdf.path.clone();
+            let last_saved = Arc::new(std::sync::Mutex::new(None::<String>));
+            let delay = Duration::from_millis(config.hcdf.save_debounce_ms);
+            let max_delay = Duration::from_millis(config.hcdf.save_max_delay_ms);
+            DebouncedSaver::spawn(delay, max_delay, move || {
+                let snapshot = store.snapshot();
+                let path = path.clone();
+                let last_saved = last_saved.clone();
+                async move {
+                    let xml = match snapshot.hcdf.to_xml_with(RuntimeFields::Omit) {
+                        Ok(xml) => xml,
+                        Err(e) => {
+                            warn!(path = %path, error = %e, "Failed to save HCDF");
+                            return;
+                        }
+                    };
+                    let mut last_saved = last_saved.lock().unwrap();
+                    if last_saved.as_ref() == Some(&xml) {
+                        debug!(path = %path, "HCDF unchanged, not saving");
+                        return;
+                    }
+                    match std::fs::write(&path, &xml) {
+                        Ok(()) => {
+                            debug!(path = %path, "Saved HCDF (debounced)");
+                            *last_saved = Some(xml);
+                        }
+                        Err(e) => warn!(path = %path, error = %e, "Failed to save HCDF"),
+                    }
+                }
+            })
+        };
+
+        let journal = if config.journal.enabled {
+            match DeviceJournal::open(config.journal.clone()) {
+                Ok(journal) => Some(Arc::new(journal)),
+                Err(e) => {
+                    warn!(path = %config.journal.path, error = %e, "Failed to open registry journal");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let attention = if config.attention.enabled {
+            match AttentionInbox::open(&config.attention) {
+                Ok(inbox) => Some(Arc::new(inbox)),
+                Err(e) => {
+                    warn!(path = %config.attention.path, error = %e, "Failed to open attention inbox");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
         let state = Arc::new(Self {
             scanner,
-            hcdf: Arc::new(RwLock::new(hcdf)),
-            topology: Arc::new(RwLock::new(topology)),
+            store,
             fragments: Arc::new(RwLock::new(fragments)),
             hcdf_fetcher,
             firmware_fetcher,
             ota_service,
             config,
             events,
+            hcdf_saver,
+            registry_file,
+            registry_saver,
+            pending_regeneration: Mutex::new(None),
+            tracer,
+            journal,
+            attention,
+            sockets,
+            consoles: ConsoleSessions::default(),
         });
 
+        // Restored devices are in the registry but not yet in the topology
+        state.rebuild_topology().await;
+
+        if state.config.fragments.watch {
+            state.spawn_fragment_watcher().await;
+        }
+
         // Start forwarding scanner events
         let state_clone = state.clone();
         let mut rx = state.scanner.subscribe();
@@ -105,12 +240,33 @@ impl AppState {
                         let updated = state_clone.update_device(device).await;
                         DiscoveryEvent::DeviceUpdated(updated)
                     }
+                    DiscoveryEvent::DeviceUnarchived(device) => {
+                        let updated = state_clone.update_device(device).await;
+                        DiscoveryEvent::DeviceUnarchived(updated)
+                    }
+                    DiscoveryEvent::DeviceArchived(id) | DiscoveryEvent::DeviceRemoved(id) => {
+                        let hwid = id.as_str().to_string();
+                        state_clone.store.update(move |data| data.pose_seqs.remove(&hwid)).await;
+                        state_clone.rebuild_topology().await;
+                        event.clone()
+                    }
                     DiscoveryEvent::DeviceOffline(_id) => {
                         // Could mark device as offline in HCDF
                         event.clone()
                     }
                     _ => event.clone(),
                 };
+                if !matches!(
+                    event,
+                    DiscoveryEvent::ScanStarted { .. }
+                        | DiscoveryEvent::ScanProgress { .. }
+                        | DiscoveryEvent::ScanCompleted { .. }
+                        | DiscoveryEvent::NeighborExpired { .. }
+                        | DiscoveryEvent::LldpNeighborChanged { .. }
+                        | DiscoveryEvent::HeartbeatBackoff { .. }
+                ) {
+                    state_clone.schedule_save_registry();
+                }
 
                 // Forward updated event to WebSocket clients
                 let _ = state_clone.events.send(updated_event);
@@ -123,28 +279,45 @@ impl AppState {
     /// Update device in HCDF and topology, returns the (potentially modified) device
     /// This applies fragment matching, fetches remote HCDF data, and updates topology
     pub async fn update_device(&self, device: &Device) -> Device {
-        let parent_name = self.config.parent.as_ref().map(|p| p.name.as_str());
+        // Everything logged on the way carries the device id
+        let span = info_span!("device", device = %device.id);
+        self.merge_device(device).instrument(span).await
+    }
+
+    async fn merge_device(&self, device: &Device) -> Device {
+        let parent_name = self.config.parent_name_for(device);
 
         // Apply fragment matching if device doesn't have visuals
         let mut device = device.clone();
 
         // Preserve existing pose from HCDF if device doesn't have one
         // This ensures positions are restored on page refresh
-        if device.pose.is_none() {
-            let hcdf = self.hcdf.read().await;
-            if let Some(mcu) = hcdf.mcu.iter().find(|m| m.hwid.as_deref() == Some(device.id.as_str())) {
-                if let Some(pose_str) = &mcu.pose_cg {
-                    if let Some(pose) = parse_pose_string(pose_str) {
-                        device.pose = Some(pose.to_array());
-                        debug!(device = %device.id, pose = ?pose_str, "Restored pose from HCDF");
-                    }
+        let pose_from_hcdf = device.pose.is_none();
+        if pose_from_hcdf {
+            restore_pose(&self.snapshot().hcdf, &mut device);
+        }
+        // Scans don't know about placement limits or a device being switched
+        // off; they live in the HCDF
+        if device.constraint.is_none() || device.enabled {
+            let snapshot = self.snapshot();
+            if let Some(mcu) = snapshot.hcdf.mcu.iter().find(|m| m.hwid.as_deref() == Some(device.id.as_str())) {
+                if device.constraint.is_none() {
+                    device.constraint = mcu.pose_constraint();
                 }
+                device.enabled = device.enabled && mcu.enabled != Some(false);
             }
         }
+        // Where the device's description came from, if one was applied this time
+        let mut described_by = None;
         if device.visuals.is_empty() {
             if let (Some(board), Some(app)) = (&device.info.board, &device.firmware.name) {
-                // Try to fetch remote HCDF first (MCUmgr query + remote fetch)
-                let remote_fragment = self.try_fetch_remote_hcdf(&device, board, app).await;
+                // Try to fetch remote HCDF first (MCUmgr query + remote fetch);
+                // CAN nodes only have local fragments
+                let remote_fragment = if device.is_can() {
+                    None
+                } else {
+                    self.try_fetch_remote_hcdf(&device, board, app).await
+                };
 
                 if let Some(fragment_data) = remote_fragment {
                     info!(
@@ -161,6 +334,11 @@ impl AppState {
                     device.frames = fragment_data.frames;
                     device.ports = fragment_data.ports;
                     device.sensors = fragment_data.sensors;
+                    described_by = Some(Provenance::new(
+                        ProvenanceSource::Device,
+                        Some(device.name.clone()),
+                        device.firmware.version.clone(),
+                    ));
                 } else {
                     // Fall back to local fragment database
                     let mut fragments = self.fragments.write().await;
@@ -179,6 +357,7 @@ impl AppState {
                             DeviceVisual {
                                 name: v.name.clone(),
                                 toggle: v.toggle.clone(),
+                                default_hidden: v.is_default_hidden(),
                                 pose: v.pose.as_ref().and_then(|p| parse_pose_string(p)).map(|p| p.to_array()),
                                 model_path: v.model.as_ref().map(|m| m.href.clone()),
                                 model_sha: v.model.as_ref().and_then(|m| m.sha.clone()),
@@ -186,33 +365,32 @@ impl AppState {
                         }).collect();
 
                         // Convert fragment frames to device frames
-                        device.frames = fragment.frames.iter().map(|f| {
-                            DeviceFrame {
-                                name: f.name.clone(),
-                                description: f.description.clone(),
-                                pose: f.pose.as_ref().and_then(|p| parse_pose_string(p)).map(|p| p.to_array()),
-                            }
-                        }).collect();
+                        device.frames = convert_frames(&fragment.frames);
 
                         // Convert fragment ports to device ports
-                        device.ports = fragment.ports.iter().map(|p| {
-                            DevicePort {
-                                name: p.name.clone(),
-                                port_type: p.port_type.clone(),
-                                pose: p.pose.as_ref().and_then(|pose| parse_pose_string(pose)).map(|pose| pose.to_array()),
-                                geometry: p.geometry.iter().filter_map(convert_geometry).collect(),
-                                visual_name: p.visual.clone(),
-                                mesh_name: p.mesh.clone(),
-                            }
-                        }).collect();
+                        device.ports = fragment.ports.iter().map(convert_port).collect();
 
                         // Convert fragment sensors to device sensors
                         device.sensors = fragment.sensors.iter()
                             .flat_map(convert_sensor)
                             .collect();
+                        described_by = Some(Provenance::new(ProvenanceSource::Fragment, Some(fragment.name.clone()), None));
                     }
                 }
 
+                // Re-apply port poses edited from the UI
+                let overrides = self.snapshot().hcdf.port_overrides(device.id.as_str());
+                apply_port_overrides(&mut device.ports, &overrides);
+
+                // Label known sensor drivers and flag driver/type mismatches
+                let lints = self.fragments.read().await.drivers().enrich_sensors(&mut device.sensors);
+                for lint in lints {
+                    warn!(device = %device.id, "{}", lint);
+                }
+
+                // A model uploaded from the UI takes precedence over the fragment's
+                self.apply_model_override(&mut device).await;
+
                 // Also set legacy model_path for backward compatibility
                 if device.model_path.is_none() {
                     device.model_path = device.visuals.first()
@@ -231,53 +409,270 @@ impl AppState {
         let is_comp_derived = device.id.as_str().starts_with("comp-")
             || device.id.as_str().starts_with("hwid:comp-");
         if !is_comp_derived {
-            let mut hcdf = self.hcdf.write().await;
-            hcdf.upsert_device(&device, parent_name);
+            let parent_name = parent_name.map(String::from);
+            let (updated, laid_out) = self.store.update(move |data| {
+                // Re-read a restored pose here rather than writing back the
+                // copy from above, which a position edit may have replaced
+                if pose_from_hcdf {
+                    device.pose = None;
+                }
+                data.hcdf.upsert_device(&device, parent_name.as_deref());
+                record_device_provenance(data, &device, described_by);
+                if pose_from_hcdf {
+                    restore_pose(&data.hcdf, &mut device);
+                }
+                let laid_out = if device.pose.is_none() {
+                    data.hcdf.layout_provisional_poses(parent_name.as_deref())
+                } else {
+                    Vec::new()
+                };
+                (device, laid_out)
+            }).await;
+            device = updated;
+            self.apply_provisional_poses(&mut device, laid_out).await;
         } else {
             debug!(device = %device.id, "Skipping HCDF upsert for comp-derived device");
         }
 
-        // Rebuild topology
-        {
-            let devices = self.scanner.devices().await;
-            let parent_id = self.config.parent.as_ref().map(|p| DeviceId::from_hwid(&p.name));
-            let new_topology = Topology::from_devices(&devices, parent_id.as_ref());
-            *self.topology.write().await = new_topology;
-        }
+        self.rebuild_topology().await;
 
         debug!(device = %device.id, "Updated device in state");
         device
     }
 
+    /// Push poses from the layout heuristic to the device registry
+    ///
+    /// `device` is the device being updated and is changed in place; other
+    /// provisionally placed devices that moved to make room are updated and
+    /// broadcast.
+    async fn apply_provisional_poses(&self, device: &mut Device, laid_out: Vec<(String, Pose)>) {
+        for (hwid, pose) in laid_out {
+            if hwid == device.id.as_str() {
+                device.pose = Some(pose.to_array());
+                device.pose_provisional = true;
+                self.scanner.update_device_silent(device.clone()).await;
+                debug!(device = %hwid, pose = ?device.pose, "Assigned provisional pose");
+            } else if let Some(mut other) = self.scanner.get_device(&DeviceId::from_hwid(&hwid)).await {
+                other.pose = Some(pose.to_array());
+                other.pose_provisional = true;
+                self.scanner.update_device_silent(other.clone()).await;
+                self.scanner.broadcast_device_update(other).await;
+                debug!(device = %hwid, "Moved provisional pose to make room");
+            }
+        }
+    }
+
+    /// Rebuild the topology from the current (non-archived) device registry
+    pub async fn rebuild_topology(&self) {
+        let devices = self.scanner.devices().await;
+        let parent_ids: Vec<DeviceId> = self.config.parent.iter().map(|p| DeviceId::from_hwid(&p.name)).collect();
+        let new_topology = Topology::from_devices(&devices, &parent_ids);
+        self.store.update(move |data| *data.topology = new_topology).await;
+    }
+
+    /// Reload the fragment database and match affected devices again
+    ///
+    /// Devices whose board/app entry was added, changed or removed lose
+    /// their visuals, frames, ports and sensors and go through
+    /// [`AppState::update_device`] again, and their cached remote HCDFs are
+    /// forgotten so they're fetched afresh. Clients get a `DeviceUpdated` for
+    /// each, then `FragmentsReloaded`. Returns the reload and the devices
+    /// matched again.
+    pub async fn reload_fragments(&self) -> Result<(FragmentReload, Vec<DeviceId>), FragmentError> {
+        let reload = self.fragments.write().await.reload(Path::new(&self.config.fragments.path))?;
+        for failure in &reload.failed {
+            warn!(error = %failure, "Fragment not loaded");
+        }
+        info!(
+            added = reload.added.len(),
+            changed = reload.changed.len(),
+            removed = reload.removed.len(),
+            failed = reload.failed.len(),
+            "Reloaded fragment database"
+        );
+
+        for (board, app) in reload.added.iter().chain(&reload.changed).chain(&reload.removed) {
+            if let Err(e) = self.hcdf_fetcher.forget_board_app(board, app).await {
+                warn!(board = %board, app = %app, error = %e, "Failed to invalidate cached HCDF");
+            }
+        }
+
+        let mut matched = Vec::new();
+        for mut device in self.scanner.devices().await {
+            let affected = match (&device.info.board, &device.firmware.name) {
+                (Some(board), Some(app)) => reload.affects(board, app),
+                _ => false,
+            };
+            if !affected {
+                continue;
+            }
+            // Keep a configured model path, drop one taken from the old visuals
+            if device.model_path == device.visuals.first().and_then(|v| v.model_path.clone()) {
+                device.model_path = None;
+            }
+            device.visuals.clear();
+            device.frames.clear();
+            device.ports.clear();
+            device.sensors.clear();
+            self.scanner.update_device_silent(device.clone()).await;
+            let updated = self.update_device(&device).await;
+            matched.push(updated.id.clone());
+            let _ = self.events.send(DiscoveryEvent::DeviceUpdated(updated));
+        }
+        if !matched.is_empty() {
+            self.schedule_save_registry();
+        }
+
+        let _ = self.events.send(DiscoveryEvent::FragmentsReloaded {
+            devices: matched.clone(),
+            failed: reload.failed.iter().map(ToString::to_string).collect(),
+        });
+        Ok((reload, matched))
+    }
+
+    /// Modification times of the fragment index and the files it lists
+    async fn fragment_mtimes(&self) -> Vec<(PathBuf, Option<SystemTime>)> {
+        let mut files = vec![PathBuf::from(&self.config.fragments.path)];
+        files.extend(self.fragments.read().await.source_files());
+        files
+            .into_iter()
+            .map(|path| {
+                let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+                (path, modified)
+            })
+            .collect()
+    }
+
+    /// Reload the fragments whenever the index or one of its files changes
+    async fn spawn_fragment_watcher(self: &Arc<Self>) {
+        let state = self.clone();
+        let mut seen = state.fragment_mtimes().await;
+        info!(path = %state.config.fragments.path, "Watching fragment database for changes");
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(FRAGMENT_WATCH_INTERVAL);
+            loop {
+                ticker.tick().await;
+                if state.fragment_mtimes().await == seen {
+                    continue;
+                }
+                if let Err(e) = state.reload_fragments().await {
+                    warn!(path = %state.config.fragments.path, error = %e, "Failed to reload fragments");
+                }
+                // The index may list other files now
+                seen = state.fragment_mtimes().await;
+            }
+        });
+    }
+
+    /// Drop everything remembered about a device (saved pose, HCDF entry)
+    pub async fn forget_device(&self, id: &str) {
+        let hwid = id.to_string();
+        let removed = self.store.update(move |data| data.hcdf.remove_device(&hwid)).await;
+        if removed {
+            info!(device = %id, "Removed device from HCDF");
+            self.schedule_save_hcdf();
+        }
+    }
+
+    /// Replace a device's visuals with its uploaded model override, if any
+    pub(crate) async fn apply_model_override(&self, device: &mut Device) -> bool {
+        let hwid = device.id.as_str();
+        let Some(model) = self.hcdf_fetcher.model_override(hwid, device.info.board.as_deref()).await else {
+            return false;
+        };
+        debug!(device = %hwid, path = %model.path, "Applied model override");
+        device.visuals = vec![model.device_visual()];
+        device.model_path = Some(model.path);
+        true
+    }
+
+    /// Resolve models again for the device `hwid`, and for every device of
+    /// `board` when given, after a model override changed
+    ///
+    /// Returns the number of devices refreshed.
+    pub async fn refresh_device_models(&self, hwid: &str, board: Option<&str>) -> usize {
+        let mut refreshed = 0;
+        for mut device in self.scanner.devices().await {
+            let matches = device.id.as_str() == hwid
+                || (board.is_some() && device.info.board.as_deref() == board);
+            if !matches {
+                continue;
+            }
+            // Cleared visuals make update_device resolve the fragment again
+            device.visuals.clear();
+            device.model_path = None;
+            let device = self.update_device(&device).await;
+            self.scanner.update_device_silent(device.clone()).await;
+            self.scanner.broadcast_device_update(device).await;
+            refreshed += 1;
+        }
+        refreshed
+    }
+
+    /// Point exported MCUs that have a model override at the local copy
+    pub async fn apply_model_overrides(&self, hcdf: &mut Hcdf) {
+        self.hcdf_fetcher.apply_model_overrides(hcdf).await;
+    }
+
     /// Get all devices
     pub async fn devices(&self) -> Vec<Device> {
         self.scanner.devices().await
     }
 
-    /// Get device by ID
+    /// Get device by ID, trimmed as discovery trims hardware IDs (see
+    /// [`DeviceId::derive`])
     pub async fn get_device(&self, id: &str) -> Option<Device> {
-        self.scanner.get_device(&DeviceId(id.to_string())).await
+        self.scanner.get_device(&DeviceId::from_hwid(id.trim())).await
+    }
+
+    /// Latest published HCDF, topology and provenance
+    pub fn snapshot(&self) -> Arc<StateSnapshot> {
+        self.store.snapshot()
     }
 
     /// Get current topology
-    pub async fn get_topology(&self) -> Topology {
-        self.topology.read().await.clone()
+    pub fn get_topology(&self) -> Topology {
+        self.snapshot().topology.clone()
     }
 
     /// Get current HCDF document
-    pub async fn get_hcdf(&self) -> Hcdf {
-        self.hcdf.read().await.clone()
+    pub fn get_hcdf(&self) -> Hcdf {
+        self.snapshot().hcdf.clone()
     }
 
     /// Save HCDF to file
     pub async fn save_hcdf(&self) -> Result<()> {
-        let hcdf = self.hcdf.read().await;
+        let snapshot = self.snapshot();
         let path = Path::new(&self.config.hcdf.path);
-        hcdf.to_file(path)?;
+        snapshot.hcdf.to_file_with(path, RuntimeFields::Omit)?;
         info!(path = %path.display(), "Saved HCDF");
         Ok(())
     }
 
+    /// Schedule a debounced save of the HCDF file
+    ///
+    /// Use this for rapid edits; the in-memory HCDF is already up to date.
+    pub fn schedule_save_hcdf(&self) {
+        self.hcdf_saver.request();
+    }
+
+    /// Schedule a debounced save of the device registry, if it is persisted
+    pub fn schedule_save_registry(&self) {
+        if let Some(saver) = &self.registry_saver {
+            saver.request();
+        }
+    }
+
+    /// Write the device registry now, if it is persisted
+    ///
+    /// Called on shutdown, so changes still waiting for the debounced save
+    /// aren't lost.
+    pub async fn save_registry(&self) {
+        if let Some(file) = &self.registry_file {
+            write_registry(file, &self.scanner).await;
+        }
+    }
+
     /// Subscribe to events
     pub fn subscribe(&self) -> broadcast::Receiver<DiscoveryEvent> {
         self.events.subscribe()
@@ -295,8 +690,20 @@ impl AppState {
         board: &str,
         app: &str,
     ) -> Option<HcdfFragmentData> {
+        let remote = self.fetch_remote_comp(device, board, app).await?;
+        self.convert_remote_comp(&remote).await
+    }
+
+    /// Query a device for its HCDF and return the comp it describes
+    pub(crate) async fn fetch_remote_comp(
+        &self,
+        device: &Device,
+        board: &str,
+        app: &str,
+    ) -> Option<RemoteHcdf> {
         // Try to query HCDF info from device via MCUmgr
-        let (device_url, device_sha) = match query_hcdf_info(device.discovery.ip, device.discovery.port).await {
+        let addr = device.discovery.socket_addr();
+        let (device_url, device_sha) = match query_hcdf_info(addr).await {
             Ok(Some(info)) => {
                 info!(
                     device = %device.id,
@@ -358,8 +765,10 @@ impl AppState {
                     name: m.name,
                     role: None,
                     hwid: m.hwid,
+                    enabled: m.enabled,
                     description: m.description,
                     pose_cg: m.pose_cg,
+                    bbox: m.bbox,
                     mass: m.mass,
                     board: m.board,
                     software: m.software,
@@ -374,12 +783,20 @@ impl AppState {
                 })
             })?;
 
+        Some(RemoteHcdf { comp, root_url, hcdf_sha })
+    }
+
+    /// Convert a fetched comp to device visuals, frames, ports and sensors,
+    /// fetching and caching the models it references
+    pub(crate) async fn convert_remote_comp(&self, remote: &RemoteHcdf) -> Option<HcdfFragmentData> {
+        let RemoteHcdf { comp, root_url, hcdf_sha } = remote;
+
         // Convert to DeviceVisual/DeviceFrame, fetching and caching models
         let mut visuals: Vec<DeviceVisual> = Vec::new();
         for v in comp.visual.iter() {
             let model_path = if let Some(model_ref) = &v.model {
                 // Resolve the model URL
-                let model_url = resolve_model_url(&model_ref.href, &root_url);
+                let model_url = resolve_model_url(&model_ref.href, root_url);
 
                 // Fetch and cache the model (for local serving)
                 // But always return the remote URL for frontend to load directly
@@ -387,7 +804,7 @@ impl AppState {
                 let _ = self.hcdf_fetcher.fetch_model(
                     &model_url,
                     model_ref.sha.as_deref(),
-                    &hcdf_sha,
+                    hcdf_sha,
                 ).await;
 
                 Some(model_url)
@@ -398,31 +815,17 @@ impl AppState {
             visuals.push(DeviceVisual {
                 name: v.name.clone(),
                 toggle: v.toggle.clone(),
+                default_hidden: v.is_default_hidden(),
                 pose: v.pose.as_ref().and_then(|p| parse_pose_string(p)).map(|p| p.to_array()),
                 model_path,
                 model_sha: v.model.as_ref().and_then(|m| m.sha.clone()),
             });
         }
 
-        let frames: Vec<DeviceFrame> = comp.frame.iter().map(|f| {
-            DeviceFrame {
-                name: f.name.clone(),
-                description: f.description.clone(),
-                pose: f.pose.as_ref().and_then(|p| parse_pose_string(p)).map(|p| p.to_array()),
-            }
-        }).collect();
+        let frames: Vec<DeviceFrame> = convert_frames(&comp.frame);
 
         // Convert ports
-        let ports: Vec<DevicePort> = comp.port.iter().map(|p| {
-            DevicePort {
-                name: p.name.clone(),
-                port_type: p.port_type.clone(),
-                pose: p.pose.as_ref().and_then(|pose| parse_pose_string(pose)).map(|pose| pose.to_array()),
-                geometry: p.geometry.iter().filter_map(convert_geometry).collect(),
-                visual_name: p.visual.clone(),
-                mesh_name: p.mesh.clone(),
-            }
-        }).collect();
+        let ports: Vec<DevicePort> = comp.port.iter().map(convert_port).collect();
 
         // Convert sensors
         let sensors: Vec<DeviceSensor> = comp.sensor.iter()
@@ -437,6 +840,49 @@ impl AppState {
     }
 }
 
+/// Write the scanner's devices to the registry file
+async fn write_registry(file: &RegistryFile, scanner: &DiscoveryScanner) {
+    let devices = scanner.devices().await;
+    match file.save(&devices) {
+        Ok(()) => debug!(path = %file.path().display(), devices = devices.len(), "Saved device registry"),
+        Err(e) => warn!(path = %file.path().display(), error = %e, "Failed to save device registry"),
+    }
+}
+
+/// Set a device's pose from its MCU element, if that has one
+fn restore_pose(hcdf: &Hcdf, device: &mut Device) {
+    let Some(mcu) = hcdf.mcu.iter().find(|m| m.hwid.as_deref() == Some(device.id.as_str())) else {
+        return;
+    };
+    if let Some(pose) = mcu.pose_cg.as_deref().and_then(parse_pose_string) {
+        device.pose = Some(pose.to_array());
+        device.pose_provisional = mcu.provisional == Some(true);
+        debug!(device = %device.id, pose = ?mcu.pose_cg, "Restored pose from HCDF");
+    }
+}
+
+/// Record where a device's MCU element came from
+///
+/// A description applied on this update wins; otherwise the device itself
+/// is the source, unless the element was already described by a local
+/// fragment or an imported file.
+fn record_device_provenance(data: &mut StateData, device: &Device, described_by: Option<Provenance>) {
+    let described_elsewhere = data.hcdf.mcu.iter()
+        .find(|m| m.hwid.as_deref() == Some(device.id.as_str()))
+        .and_then(|m| data.provenance.mcu(m))
+        .is_some_and(|p| matches!(p.source, ProvenanceSource::Fragment | ProvenanceSource::Import));
+    let record = match described_by {
+        Some(record) => record,
+        None if described_elsewhere => return,
+        None => Provenance::new(
+            ProvenanceSource::Device,
+            Some(device.name.clone()),
+            device.firmware.version.clone(),
+        ),
+    };
+    data.provenance.record_mcu(device.id.as_str(), record);
+}
+
 /// Get the root URL from an HCDF URL (domain root for absolute paths like "models/")
 fn get_root_url(hcdf_url: &str) -> String {
     // Extract scheme + host from URL
@@ -452,7 +898,7 @@ fn get_root_url(hcdf_url: &str) -> String {
 }
 
 /// Resolve a model path to absolute URL
-fn resolve_model_url(model_path: &str, root_url: &str) -> String {
+pub(crate) fn resolve_model_url(model_path: &str, root_url: &str) -> String {
     // If already absolute URL, return as-is
     if model_path.starts_with("http://") || model_path.starts_with("https://") {
         return model_path.to_string();
@@ -465,6 +911,57 @@ fn resolve_model_url(model_path: &str, root_url: &str) -> String {
     format!("{}{}", root_url, path)
 }
 
+/// Convert HCDF frames to device frames, warning about broken parent chains
+///
+/// Poses stay relative to their parent frame; clients resolve the chain.
+pub(crate) fn convert_frames(frames: &[Frame]) -> Vec<DeviceFrame> {
+    if let Err(e) = validate_frames(frames) {
+        warn!("Invalid frame hierarchy: {}", e);
+    }
+    frames
+        .iter()
+        .map(|f| DeviceFrame {
+            name: f.name.clone(),
+            description: f.description.clone(),
+            pose: f.parse_pose().map(|p| p.to_array()),
+            parent: f.parent.clone(),
+        })
+        .collect()
+}
+
+/// Convert an HCDF port to a device port, reading whichever pose/geometry
+/// fields its schema variant uses
+pub(crate) fn convert_port(port: &Port) -> DevicePort {
+    let geometry = match port.fallback_visual.as_ref().and_then(|fv| fv.geometry.as_ref()) {
+        Some(geom) => convert_geometry(geom).into_iter().collect(),
+        None => port.geometry.iter().filter_map(convert_geometry).collect(),
+    };
+    DevicePort {
+        name: port.name.clone(),
+        port_type: port.port_type.clone(),
+        speed: port
+            .capabilities
+            .as_ref()
+            .and_then(|c| c.speed.as_ref().or(c.bitrate.as_ref()).or(c.baud.as_ref()))
+            .map(|rate| rate.to_string()),
+        pose: port.parse_pose().map(|pose| pose.to_array()),
+        geometry,
+        visual_name: port.visual.clone(),
+        mesh_name: port.mesh.clone(),
+        original_pose: None,
+    }
+}
+
+/// Replace device port poses with locally edited ones, remembering the originals
+pub(crate) fn apply_port_overrides(ports: &mut [DevicePort], overrides: &HashMap<String, Pose>) {
+    for port in ports.iter_mut() {
+        if let Some(pose) = overrides.get(&port.name) {
+            port.original_pose = Some(port.pose.unwrap_or_default());
+            port.pose = Some(pose.to_array());
+        }
+    }
+}
+
 /// Convert HCDF Geometry to DeviceGeometry
 fn convert_geometry(geom: &Geometry) -> Option<DeviceGeometry> {
     if let Some(ref box_geom) = geom.box_geom {
@@ -525,6 +1022,8 @@ fn convert_sensor(sensor: &Sensor) -> Vec<DeviceSensor> {
             category: "inertial".to_string(),
             sensor_type: inertial.sensor_type.clone(),
             driver: inertial.driver.as_ref().map(|d| d.name.clone()),
+            driver_label: None,
+            datasheet: None,
             pose: inertial.pose.as_ref().and_then(|p| parse_pose_string(p)).map(|p| p.to_array()),
             axis_align: inertial.driver.as_ref()
                 .and_then(|d| d.axis_align.as_ref())
@@ -545,6 +1044,8 @@ fn convert_sensor(sensor: &Sensor) -> Vec<DeviceSensor> {
             category: "em".to_string(),
             sensor_type: em.sensor_type.clone(),
             driver: em.driver.as_ref().map(|d| d.name.clone()),
+            driver_label: None,
+            datasheet: None,
             pose: em.pose.as_ref().and_then(|p| parse_pose_string(p)).map(|p| p.to_array()),
             axis_align: em.driver.as_ref()
                 .and_then(|d| d.axis_align.as_ref())
@@ -568,6 +1069,8 @@ fn convert_sensor(sensor: &Sensor) -> Vec<DeviceSensor> {
             category: "optical".to_string(),
             sensor_type: optical.sensor_type.clone(),
             driver: optical.driver.as_ref().map(|d| d.name.clone()),
+            driver_label: None,
+            datasheet: None,
             pose: optical.pose.as_ref().and_then(|p| parse_pose_string(p)).map(|p| p.to_array()),
             axis_align: optical.driver.as_ref()
                 .and_then(|d| d.axis_align.as_ref())
@@ -588,6 +1091,8 @@ fn convert_sensor(sensor: &Sensor) -> Vec<DeviceSensor> {
             category: "rf".to_string(),
             sensor_type: rf.sensor_type.clone(),
             driver: rf.driver.as_ref().map(|d| d.name.clone()),
+            driver_label: None,
+            datasheet: None,
             pose: rf.pose.as_ref().and_then(|p| parse_pose_string(p)).map(|p| p.to_array()),
             axis_align: rf.driver.as_ref()
                 .and_then(|d| d.axis_align.as_ref())
@@ -608,6 +1113,8 @@ fn convert_sensor(sensor: &Sensor) -> Vec<DeviceSensor> {
             category: "chemical".to_string(),
             sensor_type: chem.sensor_type.clone(),
             driver: chem.driver.as_ref().map(|d| d.name.clone()),
+            driver_label: None,
+            datasheet: None,
             pose: chem.pose.as_ref().and_then(|p| parse_pose_string(p)).map(|p| p.to_array()),
             axis_align: None, // Chemical sensors don't have axis alignment
             geometry: chem.geometry.as_ref().and_then(convert_geometry),
@@ -622,6 +1129,8 @@ fn convert_sensor(sensor: &Sensor) -> Vec<DeviceSensor> {
             category: "force".to_string(),
             sensor_type: force.sensor_type.clone(),
             driver: force.driver.as_ref().map(|d| d.name.clone()),
+            driver_label: None,
+            datasheet: None,
             pose: force.pose.as_ref().and_then(|p| parse_pose_string(p)).map(|p| p.to_array()),
             axis_align: None, // Force sensors typically don't have axis alignment
             geometry: force.geometry.as_ref().and_then(convert_geometry),
@@ -649,6 +1158,9 @@ fn load_or_create_hcdf(path: &str) -> Result<Hcdf> {
         match Hcdf::from_file(path) {
             Ok(hcdf) => {
                 info!(path = %path.display(), "Loaded HCDF");
+                if let Err(e) = hcdf.validate_frames() {
+                    warn!(path = %path.display(), error = %e, "HCDF has an invalid frame hierarchy");
+                }
                 return Ok(hcdf);
             }
             Err(e) => {
@@ -682,3 +1194,637 @@ fn load_fragments(path: &str) -> FragmentDatabase {
     FragmentDatabase::empty()
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{diff_hcdf, diff_hcdf_against, get_device, get_summary, import_hcdf, list_lldp_neighbors, patch_device, reload_fragments, update_device_constraint, update_device_position, update_device_positions, HcdfDiffQuery, HcdfImportRequest, UpdatePositionRequest};
+    use axum::extract::{Path as UrlPath, Query, State};
+    use axum::response::IntoResponse;
+    use axum::Json;
+    use dendrite_api_types::FragmentReloadResponse;
+    use dendrite_core::{CanAddress, Mcu, PoseConstraint};
+    use dendrite_discovery::{RemoveMode, SystemSockets};
+    use dendrite_test_support::FixtureSize;
+    use std::net::{IpAddr, Ipv4Addr};
+    use tokio::task::JoinSet;
+
+    const DEVICES: usize = 6;
+    const ROUNDS: usize = 25;
+
+    fn device(i: usize) -> Device {
+        Device::new(
+            DeviceId::from_hwid(&format!("dev-{}", i)),
+            format!("dev-{}", i),
+            IpAddr::V4(Ipv4Addr::new(10, 0, 0, i as u8 + 1)),
+            1337,
+        )
+    }
+
+    async fn test_state(dir: &Path) -> Arc<AppState> {
+        let mut config: Config = toml::from_str("").unwrap();
+        config.hcdf.path = dir.join("dendrite.hcdf").display().to_string();
+        config.fragments.path = dir.join("fragments/index.toml").display().to_string();
+        AppState::new(config, Arc::new(SystemSockets)).await.unwrap()
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_imports_scans_and_moves() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = test_state(dir.path()).await;
+        for i in 0..DEVICES {
+            state.scanner.add_device(device(i)).await;
+        }
+
+        let mut tasks = JoinSet::new();
+        // Scan results for every device, as the scan task applies them
+        for _ in 0..ROUNDS {
+            let state = state.clone();
+            tasks.spawn(async move {
+                for i in 0..DEVICES {
+                    state.update_device(&device(i)).await;
+                }
+            });
+        }
+        // Imports adding one comp each
+        for round in 0..ROUNDS {
+            let state = state.clone();
+            tasks.spawn(async move {
+                let xml = format!(r#"<hcdf version="2.1"><comp name="payload-{}"/></hcdf>"#, round);
+                let response = import_hcdf(State(state), Json(HcdfImportRequest { xml, merge: true }))
+                    .await
+                    .into_response();
+                assert!(response.status().is_success());
+            });
+        }
+        // Drags: each device moves through its own sequence of poses
+        for i in 0..DEVICES {
+            let state = state.clone();
+            tasks.spawn(async move {
+                for step in 0..ROUNDS {
+                    let request = UpdatePositionRequest { position: [i as f64, step as f64, 0.0], orientation: None, seq: None, client: None };
+                    let response = update_device_position(State(state.clone()), UrlPath(format!("dev-{}", i)), Json(request))
+                        .await
+                        .into_response();
+                    assert!(response.status().is_success());
+                }
+            });
+        }
+
+        tokio::time::timeout(Duration::from_secs(30), async {
+            while let Some(result) = tasks.join_next().await {
+                result.unwrap();
+            }
+        })
+        .await
+        .expect("state operations deadlocked");
+
+        // Every device ends on its last drag, even with scans interleaved
+        let final_pose = |i: usize| Some([i as f64, (ROUNDS - 1) as f64, 0.0, 0.0, 0.0, 0.0]);
+        let settled = |snapshot: &StateSnapshot| {
+            (0..DEVICES).all(|i| {
+                snapshot.hcdf.mcu.iter()
+                    .find(|m| m.hwid.as_deref() == Some(&format!("dev-{}", i)))
+                    .and_then(|m| m.pose_cg.as_deref())
+                    .and_then(parse_pose_string)
+                    .map(|p| p.to_array())
+                    == final_pose(i)
+            })
+        };
+        // Position broadcasts are re-applied by the event forwarder; let it drain
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while !settled(&state.snapshot()) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("device poses did not settle on the last drag");
+
+        // The registry is published with the document it was changed alongside
+        let snapshot = state.snapshot();
+        assert_eq!(snapshot.hcdf.mcu.len(), DEVICES);
+        assert_eq!(snapshot.registry.devices.len(), DEVICES);
+        for round in 0..ROUNDS {
+            assert!(snapshot.hcdf.comp.iter().any(|c| c.name == format!("payload-{}", round)));
+        }
+        for mcu in &snapshot.hcdf.mcu {
+            assert_eq!(mcu.provisional, None, "{} still provisional", mcu.name);
+            assert!(snapshot.provenance.mcu(mcu).unwrap().edited_at.is_some());
+        }
+    }
+
+    async fn json_body(response: axum::response::Response) -> serde_json::Value {
+        assert!(response.status().is_success(), "{}", response.status());
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_import_large_fixture_and_summarize() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = test_state(dir.path()).await;
+        let (mcus, comps) = FixtureSize::Large.counts();
+
+        let request = HcdfImportRequest { xml: FixtureSize::Large.xml(), merge: true };
+        let imported = json_body(import_hcdf(State(state.clone()), Json(request)).await.into_response()).await;
+        assert_eq!(imported["mcu_count"], mcus);
+        assert_eq!(imported["comp_count"], comps);
+        // Every MCU has a hwid and an IP; every comp has a visual to render
+        assert_eq!(imported["devices_imported"], mcus + comps);
+
+        let summary = json_body(get_summary(State(state.clone())).await.into_response()).await;
+        assert_eq!(summary["devices"], mcus + comps);
+        assert_eq!(summary["offline"], comps);
+        assert_eq!(summary["keepout_count"], 0);
+
+        let snapshot = state.snapshot();
+        assert_eq!((snapshot.hcdf.mcu.len(), snapshot.hcdf.comp.len()), (mcus, comps));
+        assert_eq!(snapshot.hcdf.link.len(), mcus.min(comps));
+    }
+
+    #[tokio::test]
+    async fn test_diff_import_leaves_state_unchanged() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = test_state(dir.path()).await;
+        for i in 0..2 {
+            state.update_device(&device(i)).await;
+        }
+        let before = state.get_hcdf();
+
+        // Move and describe one board, and add another
+        let mut upload = before.clone();
+        upload.mcu[0].pose_cg = Some("1 2 3 0 0 0".to_string());
+        upload.mcu[0].description = Some("IMU board".to_string());
+        let mut added = upload.mcu[1].clone();
+        added.hwid = Some("dev-9".to_string());
+        added.name = "dev-9".to_string();
+        upload.mcu.push(added);
+
+        let request = HcdfImportRequest { xml: upload.to_xml().unwrap(), merge: true };
+        let diff = json_body(diff_hcdf(State(state.clone()), Json(request)).await.into_response()).await;
+        let changes = diff["changes"].as_array().unwrap();
+        assert_eq!(changes.len(), 2, "{:?}", changes);
+        let change = |key: &str| changes.iter().find(|c| c["key"] == key).unwrap();
+        let moved = change(upload.mcu[0].hwid.as_deref().unwrap());
+        assert_eq!(moved["kind"], "modified");
+        assert_eq!(
+            moved["changes"],
+            serde_json::json!([
+                {
+                    "change": "pose",
+                    "from": before.mcu[0].pose_cg,
+                    "to": "1 2 3 0 0 0",
+                    "offset": moved["changes"][0]["offset"],
+                    "rotation": moved["changes"][0]["rotation"],
+                },
+                {"change": "field", "name": "description", "from": null, "to": "IMU board"},
+            ])
+        );
+        assert_eq!(change("dev-9")["kind"], "added");
+
+        assert!(state.get_hcdf().diff(&before).is_empty());
+
+        let request = HcdfImportRequest { xml: "<hcdf>".to_string(), merge: true };
+        let response = diff_hcdf(State(state.clone()), Json(request)).await.into_response();
+        assert_eq!(response.status(), axum::http::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_diff_against_saved_hcdf() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = test_state(dir.path()).await;
+        for i in 0..2 {
+            state.update_device(&device(i)).await;
+        }
+        let saved = || Query(HcdfDiffQuery { against: "saved".to_string() });
+
+        // Nothing saved yet: every device is new
+        let diff = json_body(diff_hcdf_against(State(state.clone()), saved()).await.into_response()).await;
+        assert_eq!(diff["changes"].as_array().unwrap().len(), 2);
+        assert!(diff["changes"].as_array().unwrap().iter().all(|d| d["kind"] == "added"));
+
+        state.save_hcdf().await.unwrap();
+        let diff = json_body(diff_hcdf_against(State(state.clone()), saved()).await.into_response()).await;
+        assert_eq!(diff["changes"], serde_json::json!([]));
+
+        // A drag that ends half a millimeter away is noise; a real move is not
+        let pose = state.get_hcdf().mcu[0].pose_cg.as_deref().and_then(parse_pose_string).unwrap_or_default();
+        let moved_by = |dx: f64| {
+            let mut moved = pose.clone();
+            moved.x += dx;
+            let pose_cg = Some(moved.to_pose_string());
+            state.store.update(move |data| data.hcdf.mcu[0].pose_cg = pose_cg)
+        };
+        moved_by(0.0005).await;
+        let diff = json_body(diff_hcdf_against(State(state.clone()), saved()).await.into_response()).await;
+        assert_eq!(diff["changes"], serde_json::json!([]));
+        moved_by(0.25).await;
+        let diff = json_body(diff_hcdf_against(State(state.clone()), saved()).await.into_response()).await;
+        let devices = diff["changes"].as_array().unwrap();
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0]["kind"], "modified");
+        assert_eq!(devices[0]["changes"][0]["change"], "pose");
+        assert!((devices[0]["changes"][0]["offset"][0].as_f64().unwrap() - 0.25).abs() < 1e-9);
+
+        let query = Query(HcdfDiffQuery { against: "head".to_string() });
+        let response = diff_hcdf_against(State(state.clone()), query).await.into_response();
+        assert_eq!(response.status(), axum::http::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_rapid_edits_from_two_clients_persist_only_the_final_pose() {
+        use tower::ServiceExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let state = test_state(dir.path()).await;
+        for i in 0..2 {
+            state.scanner.add_device(device(i)).await;
+        }
+        let router = axum::Router::new()
+            .route("/devices/positions", axum::routing::put(update_device_positions))
+            .route("/devices/{id}/position", axum::routing::put(update_device_position))
+            .with_state(state.clone());
+        let put = |uri: String, body: serde_json::Value| {
+            let request = axum::http::Request::builder()
+                .method("PUT")
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(axum::body::Body::from(body.to_string()))
+                .unwrap();
+            let router = router.clone();
+            async move { json_body(router.oneshot(request).await.unwrap()).await }
+        };
+        let move_to = |client: &str, id: &str, x: f64, seq: u64| {
+            let body = serde_json::json!({ "position": [x, 0.0, 0.0], "seq": seq, "client": client });
+            put(format!("/devices/{}/position", id), body)
+        };
+        let pose_x = |id: &str| {
+            state.snapshot().hcdf.mcu.iter()
+                .find(|m| m.hwid.as_deref() == Some(id))
+                .and_then(|m| m.pose_cg.as_deref())
+                .and_then(parse_pose_string)
+                .map(|p| p.to_array()[0])
+        };
+
+        // Client "a" drags both devices; its batches arrive in reverse
+        let batches: Vec<_> = (1..=10u64)
+            .map(|seq| {
+                let updates: Vec<_> = (0..2)
+                    .map(|i| serde_json::json!({ "id": format!("dev-{}", i), "position": [seq as f64, i as f64, 0.0], "seq": seq, "client": "a" }))
+                    .collect();
+                serde_json::json!({ "updates": updates })
+            })
+            .collect();
+        for batch in batches.into_iter().rev() {
+            put("/devices/positions".to_string(), batch).await;
+        }
+        assert_eq!(pose_x("dev-0"), Some(10.0));
+        assert_eq!(pose_x("dev-1"), Some(10.0));
+        assert_eq!(state.scanner.get_device(&DeviceId::from_hwid("dev-1")).await.unwrap().pose.unwrap()[0], 10.0);
+
+        // Client "b" numbers its edits from 1 and isn't taken for a stale "a"
+        let body = move_to("b", "dev-0", 20.0, 1).await;
+        assert_eq!(body["status"], "updated");
+        assert_eq!(body["seq"], 1);
+        assert_eq!(pose_x("dev-0"), Some(20.0));
+
+        // A late request from "a" is still dropped, and says so
+        let body = move_to("a", "dev-0", 9.5, 9).await;
+        assert_eq!(body["status"], "stale");
+        assert_eq!(body["latest_seq"], 10);
+        assert_eq!(pose_x("dev-0"), Some(20.0));
+
+        // Removing the device forgets its sequence numbers
+        state.scanner.remove_device("dev-0", RemoveMode::Forget).await;
+        state.scanner.add_device(device(0)).await;
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while move_to("a", "dev-0", 1.0, 1).await["status"] != "updated" {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("sequence numbers outlived the device");
+    }
+
+    #[tokio::test]
+    async fn test_pose_constraint_survives_rescans() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = test_state(dir.path()).await;
+        for i in 0..2 {
+            state.scanner.add_device(state.update_device(&device(i)).await).await;
+        }
+
+        let constraint = PoseConstraint { keep_above_ground: true, stack_on: Some(DeviceId::from_hwid("dev-0")) };
+        let response = update_device_constraint(State(state.clone()), UrlPath("dev-1".to_string()), Json(constraint.clone()))
+            .await
+            .into_response();
+        assert_eq!(json_body(response).await["constraint"]["stack_on"], "dev-0");
+        let mcu = state.snapshot().hcdf.mcu.iter().find(|m| m.hwid.as_deref() == Some("dev-1")).cloned().unwrap();
+        assert_eq!(mcu.pose_constraint(), constraint);
+
+        // A scan knows nothing of the constraint; the HCDF fills it in
+        assert_eq!(state.update_device(&device(1)).await.constraint, constraint);
+
+        let own = PoseConstraint { keep_above_ground: false, stack_on: Some(DeviceId::from_hwid("dev-1")) };
+        let response = update_device_constraint(State(state.clone()), UrlPath("dev-1".to_string()), Json(own))
+            .await
+            .into_response();
+        assert_eq!(response.status(), axum::http::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_model_overrides_in_export() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = test_state(dir.path()).await;
+        for i in 0..2 {
+            let mut device = device(i);
+            device.info.board = Some(if i == 0 { "mr_mcxn_t1" } else { "spinali" }.to_string());
+            state.update_device(&device).await;
+        }
+        let model = state.hcdf_fetcher
+            .set_model_override("dev-9", Some("mr_mcxn_t1"), "fixed.glb", b"glTF-fixed")
+            .await
+            .unwrap();
+
+        // The stored document keeps the fragment models; exports point at the upload
+        let mut hcdf = state.get_hcdf();
+        state.apply_model_overrides(&mut hcdf).await;
+        assert!(state.get_hcdf().mcu.iter().all(|m| m.visual.is_empty()));
+        let visual_of = |hwid: &str| hcdf.mcu.iter().find(|m| m.hwid.as_deref() == Some(hwid)).unwrap().visual.clone();
+        let overridden = visual_of("dev-0");
+        assert_eq!(overridden.len(), 1);
+        let href = overridden[0].model.as_ref().unwrap();
+        assert_eq!(href.href, model.path);
+        assert_eq!(href.sha.as_deref(), Some(model.sha.as_str()));
+        assert!(visual_of("dev-1").is_empty());
+    }
+
+    /// Router with just the device patch route, driven through tower
+    fn patch_router(state: Arc<AppState>) -> axum::Router {
+        axum::Router::new()
+            .route("/devices/{id}", axum::routing::patch(patch_device))
+            .with_state(state)
+    }
+
+    async fn send_patch(router: axum::Router, id: &str, body: serde_json::Value) -> axum::response::Response {
+        use tower::ServiceExt;
+
+        let request = axum::http::Request::builder()
+            .method("PATCH")
+            .uri(format!("/devices/{}", id))
+            .header("content-type", "application/json")
+            .body(axum::body::Body::from(body.to_string()))
+            .unwrap();
+        router.oneshot(request).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_get_single_device() {
+        use tower::ServiceExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let state = test_state(dir.path()).await;
+        let can = Device::new_can(CanAddress { bus: "vcan0".to_string(), node_id: 12 }, "esc".to_string());
+        state.scanner.add_device(state.update_device(&can).await).await;
+        let router = axum::Router::new()
+            .route("/devices/{id}", axum::routing::get(get_device))
+            .with_state(state);
+        let get = |uri: &str| {
+            let request = axum::http::Request::builder().uri(uri).body(axum::body::Body::empty()).unwrap();
+            router.clone().oneshot(request)
+        };
+
+        // Percent-encoded, as the web UI sends it
+        let device = json_body(get("/devices/can%3Avcan0%3A12").await.unwrap()).await;
+        assert_eq!(device["id"], "can:vcan0:12");
+        assert_eq!(device["name"], "esc");
+        let device = json_body(get("/devices/%20can:vcan0:12%20").await.unwrap()).await;
+        assert_eq!(device["id"], "can:vcan0:12");
+
+        let response = get("/devices/can%3Avcan0%3A13").await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::NOT_FOUND);
+    }
+
+    fn hcdf_mcu(state: &AppState, hwid: &str) -> Mcu {
+        state.snapshot().hcdf.mcu.iter().find(|m| m.hwid.as_deref() == Some(hwid)).cloned().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_patch_device_fields() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = test_state(dir.path()).await;
+        state.scanner.add_device(state.update_device(&device(0)).await).await;
+        let router = patch_router(state.clone());
+
+        // Pose only: the name stays
+        let pose = serde_json::json!([0.1, 0.2, 0.0, 0.0, 0.0, 1.5]);
+        let patched = json_body(send_patch(router.clone(), "dev-0", serde_json::json!({ "pose": pose })).await).await;
+        assert_eq!(patched["pose"], pose);
+        assert_eq!(patched["name"], "dev-0");
+        let mcu = hcdf_mcu(&state, "dev-0");
+        assert_eq!(mcu.pose_cg.as_deref(), Some("0.1 0.2 0 0 0 1.5"));
+        assert_eq!(mcu.name, "dev-0");
+
+        // Name only: the pose stays
+        let patched = json_body(send_patch(router.clone(), "dev-0", serde_json::json!({ "name": "flow" })).await).await;
+        assert_eq!(patched["name"], "flow");
+        assert_eq!(patched["pose"], pose);
+        let mcu = hcdf_mcu(&state, "dev-0");
+        assert_eq!(mcu.name, "flow");
+        assert_eq!(mcu.pose_cg.as_deref(), Some("0.1 0.2 0 0 0 1.5"));
+
+        // Switching off is kept in the HCDF, so a scan doesn't undo it
+        let patched = json_body(send_patch(router.clone(), "dev-0", serde_json::json!({ "enabled": false })).await).await;
+        assert_eq!(patched["enabled"], false);
+        assert_eq!(hcdf_mcu(&state, "dev-0").enabled, Some(false));
+        assert!(!state.update_device(&device(0)).await.enabled);
+
+        let response = send_patch(router.clone(), "dev-9", serde_json::json!({ "name": "ghost" })).await;
+        assert_eq!(response.status(), axum::http::StatusCode::NOT_FOUND);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "Device not found");
+
+        let response = send_patch(router, "dev-0", serde_json::json!({ "name": " " })).await;
+        assert_eq!(response.status(), axum::http::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_patches_keep_every_field() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = test_state(dir.path()).await;
+        for i in 0..DEVICES {
+            state.scanner.add_device(state.update_device(&device(i)).await).await;
+        }
+        let router = patch_router(state.clone());
+
+        // Each device gets a pose, a name and a board from separate requests at once
+        let mut tasks = JoinSet::new();
+        for i in 0..DEVICES {
+            let bodies = [
+                serde_json::json!({ "pose": [i as f64, 0.0, 0.0, 0.0, 0.0, 0.0] }),
+                serde_json::json!({ "name": format!("renamed-{}", i) }),
+                serde_json::json!({ "board": "mr_mcxn_t1" }),
+            ];
+            for body in bodies {
+                let router = router.clone();
+                tasks.spawn(async move {
+                    let response = send_patch(router, &format!("dev-{}", i), body).await;
+                    assert!(response.status().is_success());
+                });
+            }
+        }
+        while let Some(result) = tasks.join_next().await {
+            result.unwrap();
+        }
+
+        for i in 0..DEVICES {
+            let device = state.scanner.get_device(&DeviceId::from_hwid(&format!("dev-{}", i))).await.unwrap();
+            assert_eq!(device.pose, Some([i as f64, 0.0, 0.0, 0.0, 0.0, 0.0]));
+            assert_eq!(device.name, format!("renamed-{}", i));
+            assert_eq!(device.info.board.as_deref(), Some("mr_mcxn_t1"));
+        }
+
+        // Update broadcasts are re-applied by the event forwarder; let it drain
+        let settled = |state: &AppState| {
+            (0..DEVICES).all(|i| {
+                let mcu = hcdf_mcu(state, &format!("dev-{}", i));
+                mcu.pose_cg == Some(format!("{} 0 0 0 0 0", i))
+                    && mcu.name == format!("renamed-{}", i)
+                    && mcu.board.as_deref() == Some("mr_mcxn_t1")
+            })
+        };
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while !settled(&state) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("patched fields did not all reach the HCDF");
+    }
+
+    #[tokio::test]
+    async fn test_lldp_neighbors_are_mapped_to_devices() {
+        use dendrite_discovery::lldp::parse_frame;
+        use dendrite_discovery::lldp_tx::{encode_frame, LldpAnnouncement};
+
+        let dir = tempfile::tempdir().unwrap();
+        let state = test_state(dir.path()).await;
+        let mut known = device(0);
+        known.discovery.mac = Some("02:00:00:00:00:01".to_string());
+        state.scanner.add_device(known).await;
+
+        let neighbor = |mac: [u8; 6], port: &str| {
+            let announcement = LldpAnnouncement {
+                chassis_mac: mac,
+                port_id: port.to_string(),
+                ttl: 120,
+                system_name: "node".to_string(),
+                system_description: "dendrite v0.1".to_string(),
+                management_address: None,
+            };
+            parse_frame("eth0", &encode_frame(mac, &announcement)).unwrap()
+        };
+        let neighbors = [neighbor([0x02, 0, 0, 0, 0, 0x01], "swp3"), neighbor([0x02, 0, 0, 0, 0, 0x02], "swp4")];
+        state.scanner.observe_lldp_neighbors(neighbors, std::time::Instant::now()).await;
+
+        let body = json_body(list_lldp_neighbors(State(state)).await.into_response()).await;
+        let neighbors: Vec<dendrite_api_types::LldpNeighborJson> = serde_json::from_value(body).unwrap();
+        assert_eq!(neighbors.len(), 2);
+        assert_eq!(neighbors[0].port, Some(3));
+        assert_eq!(neighbors[0].device_id.as_deref(), Some("dev-0"));
+        assert_eq!(neighbors[0].ttl_secs, Some(120));
+        assert!(chrono::DateTime::parse_from_rfc3339(&neighbors[0].last_refresh).is_ok());
+        // Heard over LLDP but not (yet) a registered device
+        assert_eq!(neighbors[1].port, Some(4));
+        assert_eq!(neighbors[1].device_id, None);
+    }
+
+    #[tokio::test]
+    async fn test_registry_survives_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("fragments")).unwrap();
+        let pose = [0.5, -0.25, 0.1, 0.0, 0.0, 1.0];
+
+        let state = test_state(dir.path()).await;
+        let mut placed = device(0);
+        placed.pose = Some(pose);
+        placed.status = DeviceStatus::Online;
+        state.scanner.add_device(placed).await;
+        state.scanner.add_device(device(1)).await;
+        state.save_registry().await;
+        drop(state);
+
+        let restarted = test_state(dir.path()).await;
+        assert_eq!(restarted.devices().await.len(), 2);
+        let restored = restarted.get_device("dev-0").await.unwrap();
+        assert_eq!(restored.pose, Some(pose));
+        assert_eq!(restored.status, DeviceStatus::Unknown);
+        assert!(restarted.get_topology().get_node(&restored.id).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_fragment_reload_rematches_devices() {
+        let dir = tempfile::tempdir().unwrap();
+        let fragments = dir.path().join("fragments");
+        std::fs::create_dir_all(&fragments).unwrap();
+        let index = fragments.join("index.toml");
+        std::fs::write(&index, "").unwrap();
+        let state = test_state(dir.path()).await;
+        let mut events = state.subscribe();
+
+        // A CAN node, so matching doesn't try to fetch a remote HCDF
+        let mut node = Device::new_can(CanAddress { bus: "can0".to_string(), node_id: 5 }, "esc-5".to_string());
+        node.info.board = Some("esc_board".to_string());
+        node.firmware.name = Some("esc".to_string());
+        state.scanner.add_device(node.clone()).await;
+        state.scanner.add_device(device(0)).await;
+        assert!(state.update_device(&node).await.visuals.is_empty());
+
+        std::fs::write(
+            fragments.join("esc.hcdf"),
+            r#"<hcdf version="2.1"><comp name="esc"><visual name="body"><model href="models/esc.glb"/></visual></comp></hcdf>"#,
+        )
+        .unwrap();
+        std::fs::write(fragments.join("broken.hcdf"), "<hcdf").unwrap();
+        std::fs::write(&index, r#"
+[[fragment]]
+board = "esc_board"
+hcdf = "esc.hcdf"
+
+[[fragment]]
+board = "other_board"
+hcdf = "broken.hcdf"
+"#).unwrap();
+
+        let response = reload_fragments(State(state.clone())).await.into_response();
+        let reload: FragmentReloadResponse = serde_json::from_value(json_body(response).await).unwrap();
+        assert_eq!(reload.added, ["esc_board/*", "other_board/*"]);
+        assert_eq!(reload.failed.len(), 1);
+        assert_eq!(reload.fragments, 2);
+        assert_eq!(reload.devices, [node.id.0.clone()]);
+        let matched = state.get_device(node.id.as_str()).await.unwrap();
+        assert_eq!(matched.visuals[0].model_path.as_deref(), Some("models/esc.glb"));
+
+        // Clients hear about the device before the reload itself
+        let mut updated = false;
+        loop {
+            match tokio::time::timeout(Duration::from_secs(5), events.recv()).await.unwrap().unwrap() {
+                DiscoveryEvent::DeviceUpdated(device) if device.id == node.id => updated = !device.visuals.is_empty(),
+                DiscoveryEvent::FragmentsReloaded { devices, failed } => {
+                    assert_eq!(devices, [node.id.clone()]);
+                    assert_eq!(failed.len(), 1);
+                    break;
+                }
+                _ => {}
+            }
+        }
+        assert!(updated);
+
+        // An unreadable index is rejected and the loaded fragments kept
+        std::fs::write(&index, "[[fragment").unwrap();
+        let response = reload_fragments(State(state.clone())).await.into_response();
+        assert_eq!(response.status(), axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(state.fragments.write().await.find_fragment("esc_board", "esc").is_some());
+    }
+}
@@ -0,0 +1,222 @@
+//! Status LED sync
+//!
+//! Pushes each device's indicator status (the same rule the UI uses to color
+//! devices: offline, outdated firmware, up to date) to its RGB status LED
+//! over the CogniPilot status LED SMP group. Colors are sent only when the
+//! status changes, and at most once per `min_interval_secs` per device; a
+//! status that changes again inside the interval is sent once it expires.
+
+use dendrite_core::{Device, IndicatorStatus};
+use dendrite_discovery::DiscoveryEvent;
+use dendrite_mcumgr::set_status_led;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, Mutex};
+use tracing::{debug, info};
+
+use crate::config::StatusLedConfig;
+
+/// How often pending colors are checked against the rate limit
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What we last knew and last sent for one device
+struct LedState {
+    ip: IpAddr,
+    port: u16,
+    desired: IndicatorStatus,
+    sent: Option<IndicatorStatus>,
+    last_attempt: Option<Instant>,
+}
+
+/// Keeps device status LEDs in step with daemon state
+pub struct StatusLedSync {
+    config: StatusLedConfig,
+    devices: Mutex<HashMap<String, LedState>>,
+}
+
+impl StatusLedSync {
+    pub fn new(config: StatusLedConfig) -> Self {
+        Self {
+            config,
+            devices: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Follow device events and push colors until the channel closes
+    pub fn spawn(self: Arc<Self>, mut events: broadcast::Receiver<DiscoveryEvent>) {
+        info!(
+            min_interval_secs = self.config.min_interval_secs,
+            opt_out = self.config.opt_out.len(),
+            "Status LED sync enabled"
+        );
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(FLUSH_INTERVAL);
+            loop {
+                tokio::select! {
+                    event = events.recv() => match event {
+                        Ok(DiscoveryEvent::DeviceDiscovered(device))
+                        | Ok(DiscoveryEvent::DeviceUpdated(device))
+                        | Ok(DiscoveryEvent::DeviceUnarchived(device)) => self.observe(&device).await,
+                        Ok(DiscoveryEvent::DeviceOffline(id)) => self.mark_offline(&id.0).await,
+                        Ok(DiscoveryEvent::DeviceArchived(id)) | Ok(DiscoveryEvent::DeviceRemoved(id)) => {
+                            self.devices.lock().await.remove(&id.0);
+                        }
+                        Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                    _ = ticker.tick() => {
+                        self.flush(Instant::now()).await;
+                    }
+                }
+            }
+        });
+    }
+
+    /// Record a device's current status
+    pub async fn observe(&self, device: &Device) {
+        if self.config.opt_out.iter().any(|id| *id == device.id.0) {
+            return;
+        }
+        let desired = device.indicator_status();
+        let mut devices = self.devices.lock().await;
+        let state = devices.entry(device.id.0.clone()).or_insert(LedState {
+            ip: device.discovery.ip,
+            port: device.discovery.port,
+            desired,
+            sent: None,
+            last_attempt: None,
+        });
+        state.ip = device.discovery.ip;
+        state.port = device.discovery.port;
+        state.desired = desired;
+    }
+
+    /// Record that a device stopped responding
+    async fn mark_offline(&self, id: &str) {
+        if let Some(state) = self.devices.lock().await.get_mut(id) {
+            state.desired = IndicatorStatus::Attention;
+        }
+    }
+
+    /// Send colors whose status changed and whose rate limit has expired,
+    /// returning how many commands were sent successfully
+    pub async fn flush(&self, now: Instant) -> usize {
+        let min_interval = Duration::from_secs(self.config.min_interval_secs);
+        let due: Vec<(String, IpAddr, u16, IndicatorStatus)> = {
+            let mut devices = self.devices.lock().await;
+            devices
+                .iter_mut()
+                .filter(|(_, s)| s.desired != IndicatorStatus::Unknown && s.sent != Some(s.desired))
+                .filter(|(_, s)| s.last_attempt.is_none_or(|t| now.duration_since(t) >= min_interval))
+                .map(|(id, s)| {
+                    s.last_attempt = Some(now);
+                    (id.clone(), s.ip, s.port, s.desired)
+                })
+                .collect()
+        };
+
+        let mut sent = 0;
+        for (id, ip, port, status) in due {
+            let Some(color) = self.color_for(status) else {
+                continue;
+            };
+            match set_status_led(ip, port, color.into()).await {
+                Ok(()) => {
+                    debug!(device = %id, status = ?status, "Status LED updated");
+                    if let Some(state) = self.devices.lock().await.get_mut(&id) {
+                        state.sent = Some(status);
+                    }
+                    sent += 1;
+                }
+                // Retried after the interval; devices without the group keep failing quietly
+                Err(e) => debug!(device = %id, error = %e, "Status LED update failed"),
+            }
+        }
+        sent
+    }
+
+    fn color_for(&self, status: IndicatorStatus) -> Option<[u8; 3]> {
+        let colors = &self.config.colors;
+        match status {
+            IndicatorStatus::UpToDate => Some(colors.up_to_date),
+            IndicatorStatus::UpdateAvailable => Some(colors.update_available),
+            IndicatorStatus::Attention => Some(colors.attention),
+            IndicatorStatus::Unknown => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dendrite_core::{DeviceId, DeviceStatus, FirmwareStatus};
+    use dendrite_mcumgr::{status_led_group, StatusLedColor};
+    use dendrite_test_support::{MockHcdfInfo, MockSmpDevice};
+
+    fn device(id: &str, mock: &MockSmpDevice, status: DeviceStatus, firmware: FirmwareStatus) -> Device {
+        let mut device = Device::new(DeviceId::from_hwid(id), id.to_string(), mock.addr().ip(), mock.port());
+        device.status = status;
+        device.firmware_status = firmware;
+        device
+    }
+
+    fn sent_colors(mock: &MockSmpDevice) -> Vec<StatusLedColor> {
+        mock.requests_for(status_led_group::GROUP_STATUS_LED, status_led_group::ID_SET_COLOR)
+            .iter()
+            .map(|r| r.decode().unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_pushes_color_on_each_transition() {
+        let mock = MockSmpDevice::spawn(MockHcdfInfo::default()).await.unwrap();
+        let config = StatusLedConfig { enabled: true, ..Default::default() };
+        let colors = config.colors.clone();
+        let interval = Duration::from_secs(config.min_interval_secs);
+        let sync = StatusLedSync::new(config);
+        let start = Instant::now();
+
+        // Unknown firmware status leaves the LED alone
+        sync.observe(&device("0x1", &mock, DeviceStatus::Online, FirmwareStatus::CheckDisabled)).await;
+        assert_eq!(sync.flush(start).await, 0);
+
+        sync.observe(&device("0x1", &mock, DeviceStatus::Online, FirmwareStatus::UpToDate)).await;
+        assert_eq!(sync.flush(start).await, 1);
+        // Unchanged status is not resent
+        assert_eq!(sync.flush(start + interval).await, 0);
+
+        // A change inside the interval waits for it to expire
+        let outdated = FirmwareStatus::UpdateAvailable { latest_version: "2.0.0".to_string(), changelog: None };
+        sync.observe(&device("0x1", &mock, DeviceStatus::Online, outdated.clone())).await;
+        assert_eq!(sync.flush(start + interval / 2).await, 0);
+        assert_eq!(sync.flush(start + interval * 2).await, 1);
+
+        sync.observe(&device("0x1", &mock, DeviceStatus::Offline, outdated)).await;
+        assert_eq!(sync.flush(start + interval * 3).await, 1);
+
+        assert_eq!(
+            sent_colors(&mock),
+            vec![
+                StatusLedColor::from(colors.up_to_date),
+                StatusLedColor::from(colors.update_available),
+                StatusLedColor::from(colors.attention),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_opted_out_device_is_left_alone() {
+        let mock = MockSmpDevice::spawn(MockHcdfInfo::default()).await.unwrap();
+        let sync = StatusLedSync::new(StatusLedConfig {
+            enabled: true,
+            opt_out: vec!["0x2".to_string()],
+            ..Default::default()
+        });
+
+        sync.observe(&device("0x2", &mock, DeviceStatus::Offline, FirmwareStatus::UpToDate)).await;
+        assert_eq!(sync.flush(Instant::now()).await, 0);
+        assert!(mock.requests().is_empty());
+    }
+}
@@ -0,0 +1,442 @@
+//! State actor
+//!
+//! The device registry, the HCDF document and what is derived from it
+//! (topology, provenance records, keep-out violations) are owned by a single
+//! task. Mutations are closures sent over a command channel; the task runs
+//! them one at a time and returns each result on a oneshot. The closures are
+//! synchronous, so no caller can hold the document across an await, and
+//! there is no lock order for the scan task and API handlers to disagree on.
+//! The discovery scanner sends its registry edits here too, through
+//! [`RegistryStore`].
+//!
+//! A mutation that panics is rolled back to the state before it. Each part
+//! of the state keeps a copy of itself the first time a mutation changes
+//! it, so only the parts a mutation touches are copied. After each batch of
+//! mutations the task re-runs the keep-out check and publishes an
+//! `Arc<StateSnapshot>` on a watch channel. Readers clone the `Arc` and never
+//! wait on a writer.
+
+use dendrite_core::{Hcdf, KeepoutViolation, ProvenanceRegistry, Topology};
+use dendrite_discovery::{Registry, RegistryEdit, RegistrySnapshot, RegistryStore};
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot, watch};
+use tracing::{error, info, warn};
+
+/// State owned by the actor task, handed to mutations
+#[derive(Debug, Default)]
+pub struct StateData {
+    pub hcdf: Tracked<Hcdf>,
+    pub topology: Tracked<Topology>,
+    /// Where each HCDF element came from, for annotated exports
+    pub provenance: Tracked<ProvenanceRegistry>,
+    /// Sequence numbers of the poses applied through the position API
+    pub pose_seqs: Tracked<PoseSeqs>,
+    /// Known and archived devices
+    pub registry: Tracked<Registry>,
+}
+
+impl StateData {
+    pub fn new(hcdf: Hcdf, topology: Topology, registry: Registry) -> Self {
+        Self {
+            hcdf: Tracked::new(hcdf),
+            topology: Tracked::new(topology),
+            registry: Tracked::new(registry),
+            ..Default::default()
+        }
+    }
+
+    /// Keep what the last mutation changed
+    fn commit(&mut self) {
+        self.hcdf.commit();
+        self.topology.commit();
+        self.provenance.commit();
+        self.pose_seqs.commit();
+        self.registry.commit();
+    }
+
+    /// Undo what the last mutation changed
+    fn roll_back(&mut self) {
+        self.hcdf.roll_back();
+        self.topology.roll_back();
+        self.provenance.roll_back();
+        self.pose_seqs.roll_back();
+        self.registry.roll_back();
+    }
+}
+
+/// A part of the state that copies itself the first time a mutation changes
+/// it, to roll back to if the mutation panics
+///
+/// Reads go through `Deref` and copy nothing.
+#[derive(Debug, Default)]
+pub struct Tracked<T: Clone> {
+    value: T,
+    /// The value before the running mutation first changed it
+    before: Option<T>,
+    /// Changed since the last published snapshot
+    changed: bool,
+}
+
+impl<T: Clone> Tracked<T> {
+    fn new(value: T) -> Self {
+        Self { value, before: None, changed: false }
+    }
+
+    fn commit(&mut self) {
+        self.before = None;
+    }
+
+    fn roll_back(&mut self) {
+        if let Some(before) = self.before.take() {
+            self.value = before;
+        }
+    }
+
+    /// Whether the value changed since this was last called
+    fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+}
+
+impl<T: Clone> Deref for Tracked<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T: Clone> DerefMut for Tracked<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        if self.before.is_none() {
+            self.before = Some(self.value.clone());
+        }
+        self.changed = true;
+        &mut self.value
+    }
+}
+
+/// Newest pose sequence number each client session applied to a device
+///
+/// Sequence numbers only order one client's edits, so each session is
+/// checked against its own; two clients moving a device don't lock each
+/// other out.
+#[derive(Debug, Clone, Default)]
+pub struct PoseSeqs(HashMap<String, Vec<(String, u64)>>);
+
+impl PoseSeqs {
+    /// Sessions remembered per device; the one that moved it least recently
+    /// is forgotten beyond this
+    const CLIENTS_PER_DEVICE: usize = 16;
+
+    /// Record `client`'s `seq` for a device, or return the newer one it
+    /// already applied
+    pub fn accept(&mut self, device: &str, client: &str, seq: u64) -> Result<(), u64> {
+        let clients = self.0.entry(device.to_string()).or_default();
+        if let Some(i) = clients.iter().position(|(c, _)| c == client) {
+            let latest = clients[i].1;
+            if seq <= latest {
+                return Err(latest);
+            }
+            clients.remove(i);
+        }
+        clients.push((client.to_string(), seq));
+        if clients.len() > Self::CLIENTS_PER_DEVICE {
+            clients.remove(0);
+        }
+        Ok(())
+    }
+
+    /// Forget a device that left the registry
+    pub fn remove(&mut self, device: &str) {
+        self.0.remove(device);
+    }
+}
+
+/// Published view of the state
+#[derive(Debug, Clone, Default)]
+pub struct StateSnapshot {
+    /// Known and archived devices
+    pub registry: Arc<RegistrySnapshot>,
+    pub hcdf: Hcdf,
+    pub topology: Topology,
+    pub provenance: ProvenanceRegistry,
+    /// Devices intruding into keep-out volumes
+    pub keepout_violations: Vec<KeepoutViolation>,
+    /// Number of mutations applied so far
+    pub revision: u64,
+}
+
+/// Sends a mutation's result, once the snapshot including it is published
+type Reply = Box<dyn FnOnce() + Send>;
+type Command = Box<dyn FnOnce(&mut StateData) -> Reply + Send>;
+
+/// Handle to the state task
+#[derive(Clone)]
+pub struct StateStore {
+    commands: mpsc::UnboundedSender<Command>,
+    snapshot: watch::Receiver<Arc<StateSnapshot>>,
+}
+
+impl StateStore {
+    /// Spawn the task owning `data`
+    pub fn spawn(data: StateData) -> Self {
+        let (commands, rx) = mpsc::unbounded_channel();
+        let keepout_violations = check_keepouts(&data.hcdf, &[]);
+        let (publisher, snapshot) = watch::channel(Arc::new(StateSnapshot {
+            registry: Arc::new((*data.registry).clone()),
+            hcdf: (*data.hcdf).clone(),
+            topology: (*data.topology).clone(),
+            provenance: (*data.provenance).clone(),
+            keepout_violations,
+            revision: 0,
+        }));
+        tokio::spawn(run(data, rx, publisher));
+        Self { commands, snapshot }
+    }
+
+    /// Latest published state
+    pub fn snapshot(&self) -> Arc<StateSnapshot> {
+        self.snapshot.borrow().clone()
+    }
+
+    /// Receiver notified whenever a new snapshot is published
+    pub fn subscribe(&self) -> watch::Receiver<Arc<StateSnapshot>> {
+        self.snapshot.clone()
+    }
+
+    /// Run a mutation on the state task and return its result
+    ///
+    /// The new snapshot is published before this returns. Panics if the
+    /// mutation panicked, like a poisoned lock would.
+    pub async fn update<R, F>(&self, mutation: F) -> R
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut StateData) -> R + Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        let command: Command = Box::new(move |data| {
+            let result = mutation(data);
+            Box::new(move || {
+                let _ = tx.send(result);
+            })
+        });
+        self.commands.send(command).expect("state task stopped");
+        rx.await.expect("state mutation panicked")
+    }
+}
+
+impl RegistryStore for StateStore {
+    fn edit(&self, edit: RegistryEdit) -> oneshot::Receiver<()> {
+        let (tx, rx) = oneshot::channel();
+        let command: Command = Box::new(move |data| {
+            edit(&mut *data.registry);
+            Box::new(move || {
+                let _ = tx.send(());
+            })
+        });
+        self.commands.send(command).expect("state task stopped");
+        rx
+    }
+
+    fn snapshot(&self) -> Arc<RegistrySnapshot> {
+        self.snapshot.borrow().registry.clone()
+    }
+}
+
+async fn run(
+    mut data: StateData,
+    mut commands: mpsc::UnboundedReceiver<Command>,
+    publisher: watch::Sender<Arc<StateSnapshot>>,
+) {
+    let mut revision = 0;
+    let (mut registry, mut keepout_violations) = {
+        let snapshot = publisher.borrow();
+        (snapshot.registry.clone(), snapshot.keepout_violations.clone())
+    };
+    while let Some(command) = commands.recv().await {
+        // Apply everything queued before publishing once
+        let mut batch = vec![command];
+        while let Ok(command) = commands.try_recv() {
+            batch.push(command);
+        }
+        let mut replies = Vec::with_capacity(batch.len());
+        for command in batch {
+            // A panicking mutation drops its reply, failing only its caller,
+            // and whatever it changed before panicking is rolled back so
+            // half-applied state is never checked or published
+            match catch_unwind(AssertUnwindSafe(|| command(&mut data))) {
+                Ok(reply) => {
+                    data.commit();
+                    revision += 1;
+                    replies.push(reply);
+                }
+                Err(_) => {
+                    error!(revision = revision + 1, "State mutation panicked, rolled back");
+                    data.roll_back();
+                }
+            }
+        }
+
+        // Parts no mutation of the batch changed keep their published copy
+        if data.registry.take_changed() {
+            registry = Arc::new((*data.registry).clone());
+        }
+        if data.hcdf.take_changed() {
+            keepout_violations = check_keepouts(&data.hcdf, &keepout_violations);
+        }
+        publisher.send_replace(Arc::new(StateSnapshot {
+            registry: registry.clone(),
+            hcdf: (*data.hcdf).clone(),
+            topology: (*data.topology).clone(),
+            provenance: (*data.provenance).clone(),
+            keepout_violations: keepout_violations.clone(),
+            revision,
+        }));
+        for reply in replies {
+            reply();
+        }
+    }
+}
+
+/// Re-run keep-out checks, logging violations that appeared or cleared
+fn check_keepouts(hcdf: &Hcdf, previous: &[KeepoutViolation]) -> Vec<KeepoutViolation> {
+    let violations = hcdf.check_keepouts();
+    for v in &violations {
+        if !previous.iter().any(|p| p.device == v.device && p.keepout == v.keepout) {
+            warn!(device = %v.device, keepout = %v.keepout, depth_m = v.depth, "Device intrudes into keep-out volume");
+        }
+    }
+    for p in previous {
+        if !violations.iter().any(|v| v.device == p.device && v.keepout == p.keepout) {
+            info!(device = %p.device, keepout = %p.keepout, "Device cleared keep-out volume");
+        }
+    }
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dendrite_core::{Device, DeviceId, Mcu};
+
+    fn mcu(name: &str) -> Mcu {
+        Hcdf::from_xml(&format!(r#"<hcdf version="2.1"><mcu name="{name}" hwid="{name}"/></hcdf>"#))
+            .unwrap()
+            .mcu
+            .remove(0)
+    }
+
+    #[tokio::test]
+    async fn test_updates_are_published_in_order() {
+        let store = StateStore::spawn(StateData::default());
+        assert_eq!(store.snapshot().revision, 0);
+
+        let tasks: Vec<_> = (0..50)
+            .map(|i| {
+                let store = store.clone();
+                tokio::spawn(async move {
+                    store.update(move |data| {
+                        data.hcdf.mcu.push(mcu(&format!("mcu-{}", i)));
+                        data.hcdf.mcu.len()
+                    }).await
+                })
+            })
+            .collect();
+        let mut lengths = Vec::new();
+        for task in tasks {
+            lengths.push(task.await.unwrap());
+        }
+
+        // Each mutation saw the ones before it
+        lengths.sort();
+        assert_eq!(lengths, (1..=50).collect::<Vec<_>>());
+        let snapshot = store.snapshot();
+        assert_eq!(snapshot.hcdf.mcu.len(), 50);
+        assert_eq!(snapshot.revision, 50);
+    }
+
+    #[test]
+    fn test_pose_seqs_are_per_client() {
+        let mut seqs = PoseSeqs::default();
+        assert_eq!(seqs.accept("dev", "a", 7), Ok(()));
+        assert_eq!(seqs.accept("dev", "a", 6), Err(7));
+        assert_eq!(seqs.accept("dev", "b", 1), Ok(()));
+        assert_eq!(seqs.accept("dev", "a", 8), Ok(()));
+
+        // Past the cap the least recent session is forgotten
+        for client in 0..PoseSeqs::CLIENTS_PER_DEVICE {
+            seqs.accept("dev", &client.to_string(), 1).unwrap();
+        }
+        assert_eq!(seqs.accept("dev", "b", 1), Ok(()));
+        assert_eq!(seqs.accept("dev", "0", 1), Err(1));
+
+        seqs.remove("dev");
+        assert_eq!(seqs.accept("dev", "a", 1), Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_panicking_mutation_leaves_state_usable() {
+        let store = StateStore::spawn(StateData::default());
+        let failed = {
+            let store = store.clone();
+            tokio::spawn(async move {
+                store.update(|data| {
+                    data.hcdf.mcu.push(mcu("before-panic"));
+                    panic!("bad mutation");
+                }).await
+            })
+            .await
+        };
+        assert!(failed.is_err());
+
+        let count = store.update(|data| {
+            data.hcdf.mcu.push(mcu("after"));
+            data.hcdf.mcu.len()
+        }).await;
+        // The panicking mutation's push was rolled back
+        assert_eq!(count, 1);
+        let snapshot = store.snapshot();
+        assert_eq!(snapshot.hcdf.mcu.len(), 1);
+        assert_eq!(snapshot.hcdf.mcu[0].name, "after");
+        assert_eq!(snapshot.revision, 1);
+    }
+
+    #[test]
+    fn test_only_changed_parts_are_copied() {
+        let mut data = StateData::default();
+        assert!(data.hcdf.mcu.is_empty());
+        assert!(data.hcdf.before.is_none());
+
+        data.hcdf.mcu.push(mcu("a"));
+        assert!(data.hcdf.before.is_some());
+        assert!(data.registry.before.is_none());
+        data.roll_back();
+        assert!(data.hcdf.mcu.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_registry_edits_are_published_and_rolled_back() {
+        let store = StateStore::spawn(StateData::default());
+        let device = Device::new(DeviceId::from_hwid("abc"), "abc".to_string(), "10.0.0.2".parse().unwrap(), 1337);
+        RegistryStore::edit(&store, Box::new(move |registry| {
+            registry.devices.insert("abc".to_string(), device);
+        }))
+        .await
+        .unwrap();
+        assert_eq!(RegistryStore::snapshot(&store).devices.len(), 1);
+
+        let failed = RegistryStore::edit(&store, Box::new(|registry| {
+            registry.devices.clear();
+            panic!("bad edit");
+        }))
+        .await;
+        assert!(failed.is_err());
+        // Once a later mutation is published, the panicking one is known undone
+        store.update(|_| ()).await;
+        assert_eq!(store.snapshot().registry.devices.len(), 1);
+    }
+}
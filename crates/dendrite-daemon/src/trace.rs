@@ -0,0 +1,130 @@
+//! Per-device SMP protocol tracing
+//!
+//! Registers a transport observer for a device's IP so every MCUmgr frame
+//! exchanged with it is either logged at TRACE level or captured to a
+//! rotating pcapng file under the traces directory.
+
+use chrono::{DateTime, Utc};
+use dendrite_mcumgr::{clear_observer, set_observer, PcapObserver, PcapRotation, TracingObserver, TransportObserver};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::info;
+
+/// Maximum size of one capture file
+const MAX_TRACE_FILE_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Capture files kept per device, including the current one
+const MAX_TRACE_FILES: usize = 5;
+
+/// Where traced frames go
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TraceFormat {
+    /// Decoded frames in the daemon log at TRACE level
+    #[default]
+    Log,
+    /// Rotating pcapng capture
+    Pcap,
+}
+
+/// An active trace
+#[derive(Debug, Clone, Serialize)]
+pub struct TraceSession {
+    pub device_id: String,
+    pub ip: IpAddr,
+    pub format: TraceFormat,
+    /// Current capture file (pcap only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+    pub started: DateTime<Utc>,
+}
+
+/// Tracks which devices are being traced
+pub struct DeviceTracer {
+    dir: PathBuf,
+    sessions: Mutex<HashMap<String, TraceSession>>,
+}
+
+impl DeviceTracer {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Start tracing a device, replacing any trace already running for it
+    pub async fn enable(&self, device_id: &str, ip: IpAddr, format: TraceFormat) -> io::Result<TraceSession> {
+        let mut sessions = self.sessions.lock().await;
+        if let Some(previous) = sessions.remove(device_id) {
+            clear_observer(previous.ip);
+        }
+
+        let (observer, path): (Arc<dyn TransportObserver>, _) = match format {
+            TraceFormat::Log => (Arc::new(TracingObserver), None),
+            TraceFormat::Pcap => {
+                let path = self.dir.join(format!("{}.pcapng", sanitize(device_id)));
+                let rotation = PcapRotation {
+                    max_file_bytes: MAX_TRACE_FILE_BYTES,
+                    max_files: MAX_TRACE_FILES,
+                };
+                (Arc::new(PcapObserver::create(&path, rotation)?), Some(path))
+            }
+        };
+        set_observer(ip, observer);
+
+        let session = TraceSession {
+            device_id: device_id.to_string(),
+            ip,
+            format,
+            path,
+            started: Utc::now(),
+        };
+        info!(device = %device_id, ip = %ip, format = ?format, "SMP tracing enabled");
+        sessions.insert(device_id.to_string(), session.clone());
+        Ok(session)
+    }
+
+    /// Stop tracing a device, returning the session that was running
+    pub async fn disable(&self, device_id: &str) -> Option<TraceSession> {
+        let session = self.sessions.lock().await.remove(device_id)?;
+        clear_observer(session.ip);
+        info!(device = %device_id, "SMP tracing disabled");
+        Some(session)
+    }
+}
+
+/// Make a device ID safe to use as a file name
+fn sanitize(device_id: &str) -> String {
+    device_id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_enable_and_disable_pcap_trace() {
+        let dir = tempfile::tempdir().unwrap();
+        let tracer = DeviceTracer::new(dir.path().to_path_buf());
+        let ip: IpAddr = "192.0.2.10".parse().unwrap();
+
+        let session = tracer.enable("hwid:0x1234", ip, TraceFormat::Pcap).await.unwrap();
+        let path = session.path.unwrap();
+        assert_eq!(path, dir.path().join("hwid_0x1234.pcapng"));
+        assert!(path.exists());
+        assert!(dendrite_mcumgr::observer_for(ip).is_some());
+
+        assert!(tracer.disable("hwid:0x1234").await.is_some());
+        assert!(dendrite_mcumgr::observer_for(ip).is_none());
+        assert!(tracer.disable("hwid:0x1234").await.is_none());
+    }
+}
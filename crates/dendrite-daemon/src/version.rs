@@ -0,0 +1,68 @@
+//! Daemon build information
+//!
+//! Served from `GET /api/version` and logged as one structured line at
+//! startup, so integrations can detect capabilities and bug reports say
+//! exactly which build was running.
+
+use dendrite_core::{SUPPORTED_HCDF_VERSIONS, WS_PROTOCOL_VERSION};
+use serde::Serialize;
+use tracing::info;
+
+/// Commit the daemon was built from (embedded by build.rs)
+const GIT_HASH: &str = env!("DENDRITE_GIT_HASH");
+
+/// Comma-separated cargo features enabled at build time (embedded by build.rs)
+const FEATURES: &str = env!("DENDRITE_FEATURES");
+
+/// Version, build and protocol information
+#[derive(Debug, Clone, Serialize)]
+pub struct VersionInfo {
+    pub version: &'static str,
+    pub git_hash: &'static str,
+    pub features: Vec<&'static str>,
+    pub hcdf_versions: &'static [&'static str],
+    pub ws_protocol: u32,
+}
+
+impl VersionInfo {
+    /// Information for the running build
+    pub fn current() -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            git_hash: GIT_HASH,
+            features: FEATURES.split(',').filter(|f| !f.is_empty()).collect(),
+            hcdf_versions: SUPPORTED_HCDF_VERSIONS,
+            ws_protocol: WS_PROTOCOL_VERSION,
+        }
+    }
+
+    /// Log the startup banner
+    pub fn log(&self) {
+        info!(
+            version = self.version,
+            git_hash = self.git_hash,
+            features = ?self.features,
+            hcdf_versions = ?self.hcdf_versions,
+            ws_protocol = self.ws_protocol,
+            "Dendrite v{}",
+            self.version
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_current_version_info() {
+        let info = VersionInfo::current();
+        assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+        assert!(!info.git_hash.is_empty());
+        assert_eq!(info.ws_protocol, WS_PROTOCOL_VERSION);
+
+        let json = serde_json::to_value(&info).unwrap();
+        assert!(json["features"].is_array());
+        assert!(json["hcdf_versions"].as_array().unwrap().contains(&"2.1".into()));
+    }
+}
@@ -1,19 +1,52 @@
 //! WebSocket handler for real-time updates
+//!
+//! Clients get every device event by default. A client on a slow link can
+//! subscribe in lite mode, by connecting with `?lite=true` or by sending
+//! `{"type": "subscribe", "lite": true, "selected": "<device id>"}`: events
+//! for devices other than the selected one then carry a `device_summary`
+//! without visuals, frames, ports or sensors. Switching back to full sends
+//! the complete device list again.
+//!
+//! Attention inbox changes are sent as `attention` messages carrying the
+//! finding's current state.
+//!
+//! A client connecting with `?compact=true` gets the device list (on connect
+//! and when leaving lite mode) as a single `device_snapshot` message holding a
+//! [`CompactSnapshot`] of the per-device messages, about a third smaller.
+//! axum's WebSocket doesn't negotiate permessage-deflate, so this is where
+//! the bulk of the savings on slow links comes from.
+//!
+//! A client connecting with `?protocol=2` gets device deltas instead (see
+//! [`dendrite_api_types::delta`]): one `full_sync` on connect or when it
+//! sends `{"type": "sync"}`, then only the fields that changed. The
+//! connection remembers what it last sent for each device; in lite mode
+//! that is the summary, and a subscription change sends whatever the client
+//! is now missing. Clients that don't ask for protocol 2 keep getting a full
+//! device record per event.
+//!
+//! The upgrade is guarded like a read-only API request; browsers pass the
+//! token as a `token` query parameter.
 
 use axum::{
     extract::{
         ws::{Message, WebSocket, WebSocketUpgrade},
-        State,
+        Query, State,
     },
     response::IntoResponse,
 };
+use dendrite_api_types::{
+    CompactSnapshot, DeviceDelta, DeviceRecords, FullSync, Record, RecordChange, StatusChange, DELTA_PROTOCOL_VERSION,
+};
+use dendrite_core::{Device, DeviceStatus, DeviceSummary, WS_PROTOCOL_VERSION};
 use dendrite_discovery::DiscoveryEvent;
 use futures_util::{SinkExt, StreamExt};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::sync::Arc;
 use tracing::{debug, info, warn};
 
-use crate::ota::{OtaEvent, UpdateState};
+use crate::attention::Finding;
+use crate::ota::OtaEvent;
 use crate::state::AppState;
 
 /// WebSocket message types
@@ -28,40 +61,276 @@ enum WsMessage {
     DeviceUpdated(dendrite_core::Device),
     #[serde(rename = "device_removed")]
     DeviceRemoved { id: String },
+    #[serde(rename = "device_archived")]
+    DeviceArchived { id: String },
+    #[serde(rename = "device_unarchived")]
+    DeviceUnarchived(dendrite_core::Device),
+    /// Discovered, updated or unarchived device outside a lite subscription
+    #[serde(rename = "device_summary")]
+    DeviceSummary(DeviceSummary),
+    /// The device list in one message, for compact clients
+    #[serde(rename = "device_snapshot")]
+    DeviceSnapshot(CompactSnapshot),
+    /// Every device, for delta clients
+    #[serde(rename = "full_sync")]
+    FullSync(FullSync),
+    /// Device a delta client hasn't been sent yet
+    #[serde(rename = "device_added")]
+    DeviceAdded(Record),
+    #[serde(rename = "device_delta")]
+    DeviceDelta(DeviceDelta),
+    #[serde(rename = "status_changed")]
+    StatusChanged(StatusChange),
     #[serde(rename = "scan_started")]
-    ScanStarted,
+    ScanStarted { total_hosts: usize },
+    #[serde(rename = "scan_progress")]
+    ScanProgress { done: usize, total: usize },
     #[serde(rename = "scan_completed")]
     ScanCompleted { found: usize, total: usize },
+    /// An LLDP neighbor appeared or changed port; `GET /api/lldp/neighbors` has the details
+    #[serde(rename = "lldp_neighbor_changed")]
+    LldpNeighborChanged { interface: String, chassis_id: String, port: Option<u8> },
+    /// An LLDP neighbor's TTL ran out without a refresh
+    #[serde(rename = "lldp_neighbor_expired")]
+    LldpNeighborExpired { chassis_id: String, port: Option<u8> },
+    /// An offline device's heartbeat check failed; the next is in `retry_in_ms`
+    #[serde(rename = "heartbeat_backoff")]
+    HeartbeatBackoff { id: String, failures: u32, retry_in_ms: u64 },
+    /// The fragment database was reloaded; `devices` were matched again
+    #[serde(rename = "fragments_reloaded")]
+    FragmentsReloaded { devices: Vec<String>, failed: Vec<String> },
     #[serde(rename = "ota_progress")]
-    OtaProgress { device_id: String, state: UpdateState },
+    OtaProgress(OtaEvent),
+    /// A finding was raised, changed, acknowledged or resolved
+    #[serde(rename = "attention")]
+    Attention(Finding),
     #[serde(rename = "pong")]
     Pong,
 }
 
+/// Messages from the client (besides the plain-text "ping")
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type")]
+enum ClientMessage {
+    #[serde(rename = "subscribe")]
+    Subscribe(Subscription),
+    /// Resend every device (delta clients)
+    #[serde(rename = "sync")]
+    Sync,
+}
+
+/// What a client wants to receive
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+struct Subscription {
+    /// Only send full device records for the selected device
+    #[serde(default)]
+    lite: bool,
+    /// Device the client is looking at
+    #[serde(default)]
+    selected: Option<String>,
+}
+
+impl Subscription {
+    /// Message for a device event, reduced to a summary if the client doesn't need it in full
+    fn device_message(&self, device: Device, full: fn(Device) -> WsMessage) -> WsMessage {
+        if self.lite && self.selected.as_deref() != Some(device.id.as_str()) {
+            WsMessage::DeviceSummary(DeviceSummary::from(&device))
+        } else {
+            full(device)
+        }
+    }
+
+    /// Device as the client should see it: the full record or a summary
+    fn device_record(&self, device: &Device) -> Option<Record> {
+        let value = if self.lite && self.selected.as_deref() != Some(device.id.as_str()) {
+            serde_json::to_value(DeviceSummary::from(device))
+        } else {
+            serde_json::to_value(device)
+        };
+        match value {
+            Ok(Value::Object(record)) => Some(record),
+            _ => None,
+        }
+    }
+
+    /// Message for a discovery event, None for events clients aren't sent
+    fn event_message(&self, event: DiscoveryEvent) -> Option<WsMessage> {
+        Some(match event {
+            DiscoveryEvent::DeviceDiscovered(device) => self.device_message(device, WsMessage::DeviceDiscovered),
+            DiscoveryEvent::DeviceOffline(id) => WsMessage::DeviceOffline { id: id.0 },
+            DiscoveryEvent::DeviceUpdated(device) => self.device_message(device, WsMessage::DeviceUpdated),
+            DiscoveryEvent::DeviceRemoved(id) => WsMessage::DeviceRemoved { id: id.0 },
+            DiscoveryEvent::DeviceArchived(id) => WsMessage::DeviceArchived { id: id.0 },
+            DiscoveryEvent::DeviceUnarchived(device) => self.device_message(device, WsMessage::DeviceUnarchived),
+            DiscoveryEvent::ScanStarted { total_hosts } => WsMessage::ScanStarted { total_hosts },
+            DiscoveryEvent::ScanProgress { done, total } => WsMessage::ScanProgress { done, total },
+            DiscoveryEvent::ScanCompleted { found, total } => WsMessage::ScanCompleted { found, total },
+            DiscoveryEvent::LldpNeighborChanged { interface, chassis_id, port } => {
+                WsMessage::LldpNeighborChanged { interface, chassis_id, port }
+            }
+            DiscoveryEvent::NeighborExpired { port, chassis_id } => WsMessage::LldpNeighborExpired { chassis_id, port },
+            DiscoveryEvent::HeartbeatBackoff { id, failures, retry_in_ms } => {
+                WsMessage::HeartbeatBackoff { id: id.0, failures, retry_in_ms }
+            }
+            DiscoveryEvent::FragmentsReloaded { devices, failed } => WsMessage::FragmentsReloaded {
+                devices: devices.into_iter().map(|id| id.0).collect(),
+                failed,
+            },
+            _ => return None,
+        })
+    }
+}
+
+/// What a delta client has been sent
+#[derive(Default)]
+struct Deltas {
+    records: DeviceRecords,
+}
+
+impl Deltas {
+    /// Every device, forgetting what was sent before
+    fn full_sync(&mut self, devices: &[Device], subscription: &Subscription) -> WsMessage {
+        self.records.clear();
+        let records: Vec<Value> = devices
+            .iter()
+            .filter_map(|device| {
+                let record = subscription.device_record(device)?;
+                self.records.insert(device.id.as_str(), record.clone());
+                Some(Value::Object(record))
+            })
+            .collect();
+        WsMessage::FullSync(FullSync { protocol: WS_PROTOCOL_VERSION, devices: CompactSnapshot::compact(records) })
+    }
+
+    /// Message bringing the client's copy of a device up to date, None if it already is
+    fn device_message(&mut self, device: &Device, subscription: &Subscription) -> Option<WsMessage> {
+        let record = subscription.device_record(device)?;
+        let id = device.id.as_str();
+        match self.records.update(id, record) {
+            RecordChange::Added => self.records.get(id).cloned().map(WsMessage::DeviceAdded),
+            RecordChange::Changed(changed) => Some(match changed.get("status").and_then(Value::as_str) {
+                Some(status) if changed.len() == 1 => {
+                    WsMessage::StatusChanged(StatusChange { id: id.to_string(), status: status.to_string() })
+                }
+                _ => WsMessage::DeviceDelta(DeviceDelta { id: id.to_string(), changed_fields: changed }),
+            }),
+            RecordChange::Unchanged => None,
+        }
+    }
+
+    /// Message for a discovery event, None for events clients aren't sent
+    fn event_message(&mut self, event: DiscoveryEvent, subscription: &Subscription) -> Option<WsMessage> {
+        match event {
+            DiscoveryEvent::DeviceDiscovered(device) | DiscoveryEvent::DeviceUpdated(device) => {
+                self.device_message(&device, subscription)
+            }
+            DiscoveryEvent::DeviceOffline(id) => {
+                let status = serde_json::to_value(DeviceStatus::Offline).ok()?;
+                self.records.apply(id.as_str(), Record::from_iter([("status".to_string(), status.clone())]));
+                Some(WsMessage::StatusChanged(StatusChange { id: id.0, status: status.as_str()?.to_string() }))
+            }
+            DiscoveryEvent::DeviceRemoved(ref id) | DiscoveryEvent::DeviceArchived(ref id) => {
+                self.records.remove(id.as_str());
+                subscription.event_message(event)
+            }
+            DiscoveryEvent::DeviceUnarchived(device) => {
+                // Always in full, so the client has a record to apply later deltas to
+                if let Ok(Value::Object(record)) = serde_json::to_value(&device) {
+                    self.records.insert(device.id.as_str(), record);
+                }
+                Some(WsMessage::DeviceUnarchived(device))
+            }
+            event => subscription.event_message(event),
+        }
+    }
+}
+
+/// Query parameters for the WebSocket upgrade
+#[derive(Deserialize)]
+pub struct WsQuery {
+    /// Start in a lite subscription
+    #[serde(default)]
+    lite: bool,
+    /// Send the device list as one compact snapshot
+    #[serde(default)]
+    compact: bool,
+    /// Protocol version the client speaks; 2 and up get device deltas
+    #[serde(default)]
+    protocol: Option<u32>,
+}
+
 /// WebSocket upgrade handler
 pub async fn websocket_handler(
     ws: WebSocketUpgrade,
     State(state): State<Arc<AppState>>,
+    Query(query): Query<WsQuery>,
 ) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| handle_socket(socket, state))
+    let subscription = Subscription { lite: query.lite, selected: None };
+    let compact = query.compact;
+    let deltas = query.protocol.is_some_and(|p| p >= DELTA_PROTOCOL_VERSION).then(Deltas::default);
+    ws.on_upgrade(move |socket| handle_socket(socket, state, subscription, compact, deltas))
+}
+
+/// Messages carrying a device list as seen through `subscription`
+fn device_list_messages(devices: Vec<Device>, subscription: &Subscription, compact: bool) -> Vec<WsMessage> {
+    let messages = devices
+        .into_iter()
+        .map(|device| subscription.device_message(device, WsMessage::DeviceDiscovered));
+    if compact {
+        let values = messages.filter_map(|msg| serde_json::to_value(&msg).ok());
+        vec![WsMessage::DeviceSnapshot(CompactSnapshot::compact(values))]
+    } else {
+        messages.collect()
+    }
+}
+
+type Sender = futures_util::stream::SplitSink<WebSocket, Message>;
+
+/// Send one message, false once the client is gone
+async fn send_message(sender: &mut Sender, msg: &WsMessage) -> bool {
+    match serde_json::to_string(msg) {
+        Ok(json) => sender.send(Message::Text(json.into())).await.is_ok(),
+        Err(_) => true,
+    }
+}
+
+/// Send the current device list as seen through `subscription`
+async fn send_devices(
+    sender: &mut Sender,
+    state: &AppState,
+    subscription: &Subscription,
+    compact: bool,
+    deltas: Option<&mut Deltas>,
+) -> bool {
+    let devices = state.devices().await;
+    if let Some(deltas) = deltas {
+        return send_message(sender, &deltas.full_sync(&devices, subscription)).await;
+    }
+    for msg in device_list_messages(devices, subscription, compact) {
+        if !send_message(sender, &msg).await {
+            return false;
+        }
+    }
+    true
 }
 
-async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
+async fn handle_socket(
+    socket: WebSocket,
+    state: Arc<AppState>,
+    mut subscription: Subscription,
+    compact: bool,
+    mut deltas: Option<Deltas>,
+) {
     let (mut sender, mut receiver) = socket.split();
     let mut discovery_events = state.subscribe();
     let mut ota_events = state.ota_service.subscribe();
+    let mut attention_events = state.attention.as_ref().map(|inbox| inbox.subscribe());
 
-    info!("WebSocket client connected");
+    info!(lite = subscription.lite, compact, deltas = deltas.is_some(), "WebSocket client connected");
 
     // Send current device list on connect
-    let devices = state.devices().await;
-    for device in devices {
-        let msg = WsMessage::DeviceDiscovered(device);
-        if let Ok(json) = serde_json::to_string(&msg) {
-            if sender.send(Message::Text(json.into())).await.is_err() {
-                return;
-            }
-        }
+    if !send_devices(&mut sender, &state, &subscription, compact, deltas.as_mut()).await {
+        return;
     }
 
     // Handle incoming messages and forward events
@@ -71,29 +340,16 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
             event = discovery_events.recv() => {
                 match event {
                     Ok(event) => {
-                        let msg = match event {
-                            DiscoveryEvent::DeviceDiscovered(device) => {
-                                WsMessage::DeviceDiscovered(device)
-                            }
-                            DiscoveryEvent::DeviceOffline(id) => {
-                                WsMessage::DeviceOffline { id: id.0 }
-                            }
-                            DiscoveryEvent::DeviceUpdated(device) => {
-                                WsMessage::DeviceUpdated(device)
-                            }
-                            DiscoveryEvent::DeviceRemoved(id) => {
-                                WsMessage::DeviceRemoved { id: id.0 }
-                            }
-                            DiscoveryEvent::ScanStarted => WsMessage::ScanStarted,
-                            DiscoveryEvent::ScanCompleted { found, total } => {
-                                WsMessage::ScanCompleted { found, total }
-                            }
+                        let msg = match &mut deltas {
+                            Some(deltas) => deltas.event_message(event, &subscription),
+                            None => subscription.event_message(event),
+                        };
+                        let Some(msg) = msg else {
+                            continue;
                         };
 
-                        if let Ok(json) = serde_json::to_string(&msg) {
-                            if sender.send(Message::Text(json.into())).await.is_err() {
-                                break;
-                            }
+                        if !send_message(&mut sender, &msg).await {
+                            break;
                         }
                     }
                     Err(e) => {
@@ -106,12 +362,9 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
             // Forward OTA events to client
             event = ota_events.recv() => {
                 match event {
-                    Ok(OtaEvent { device_id, state }) => {
-                        let msg = WsMessage::OtaProgress { device_id, state };
-                        if let Ok(json) = serde_json::to_string(&msg) {
-                            if sender.send(Message::Text(json.into())).await.is_err() {
-                                break;
-                            }
+                    Ok(event) => {
+                        if !send_message(&mut sender, &WsMessage::OtaProgress(event)).await {
+                            break;
                         }
                     }
                     Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) => {
@@ -125,6 +378,19 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
                 }
             }
 
+            // Forward attention inbox changes to client
+            finding = next_finding(&mut attention_events) => {
+                match finding {
+                    Some(finding) => {
+                        if !send_message(&mut sender, &WsMessage::Attention(finding)).await {
+                            break;
+                        }
+                    }
+                    // Inbox gone - keep serving device events
+                    None => attention_events = None,
+                }
+            }
+
             // Handle incoming messages from client
             msg = receiver.next() => {
                 match msg {
@@ -136,10 +402,30 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
                     Some(Ok(Message::Text(text))) => {
                         // Handle ping/pong for keepalive
                         if text.as_str() == "ping" {
-                            let pong = serde_json::to_string(&WsMessage::Pong).unwrap();
-                            if sender.send(Message::Text(pong.into())).await.is_err() {
+                            if !send_message(&mut sender, &WsMessage::Pong).await {
                                 break;
                             }
+                            continue;
+                        }
+                        match serde_json::from_str(text.as_str()) {
+                            Ok(ClientMessage::Subscribe(next)) => {
+                                debug!(lite = next.lite, selected = ?next.selected, "WebSocket subscription changed");
+                                let previous = std::mem::replace(&mut subscription, next);
+                                let sent = send_subscription_change(
+                                    &mut sender, &state, &previous, &subscription, compact, deltas.as_mut(),
+                                )
+                                .await;
+                                if !sent {
+                                    break;
+                                }
+                            }
+                            Ok(ClientMessage::Sync) => {
+                                debug!("WebSocket client requested a full sync");
+                                if !send_devices(&mut sender, &state, &subscription, compact, deltas.as_mut()).await {
+                                    break;
+                                }
+                            }
+                            Err(_) => {}
                         }
                     }
                     Some(Ok(Message::Close(_))) | None => {
@@ -157,3 +443,218 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
 
     info!("WebSocket client disconnected");
 }
+
+/// Send what a client is missing after changing its subscription
+async fn send_subscription_change(
+    sender: &mut Sender,
+    state: &AppState,
+    previous: &Subscription,
+    subscription: &Subscription,
+    compact: bool,
+    deltas: Option<&mut Deltas>,
+) -> bool {
+    if let Some(deltas) = deltas {
+        // Whatever the summaries left out shows up as changed fields
+        for device in state.devices().await {
+            if let Some(msg) = deltas.device_message(&device, subscription) {
+                if !send_message(sender, &msg).await {
+                    return false;
+                }
+            }
+        }
+        return true;
+    }
+
+    if previous.lite && !subscription.lite {
+        // Back to full fidelity: resend everything the summaries left out
+        return send_devices(sender, state, subscription, compact, None).await;
+    }
+    if subscription.lite && subscription.selected != previous.selected {
+        // Newly selected device needs its full record
+        let selected = match &subscription.selected {
+            Some(id) => state.get_device(id).await,
+            None => None,
+        };
+        if let Some(device) = selected {
+            return send_message(sender, &WsMessage::DeviceUpdated(device)).await;
+        }
+    }
+    true
+}
+
+/// Next attention change, or never if the inbox is disabled
+///
+/// Findings missed while lagging are picked up by the client's next
+/// `GET /api/attention`.
+async fn next_finding(events: &mut Option<tokio::sync::broadcast::Receiver<Finding>>) -> Option<Finding> {
+    let Some(events) = events else {
+        return std::future::pending().await;
+    };
+    loop {
+        match events.recv().await {
+            Ok(finding) => return Some(finding),
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) => {
+                debug!(skipped = n, "Attention event channel lagged");
+            }
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => return None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dendrite_core::DeviceId;
+    use dendrite_test_support::{synthetic_registry, REGISTRY_DEVICES};
+    use std::net::{IpAddr, Ipv4Addr};
+
+    fn device(id: &str) -> Device {
+        Device::new(DeviceId::from_hwid(id), id.to_string(), IpAddr::V4(Ipv4Addr::LOCALHOST), 1337)
+    }
+
+    fn message_type(msg: &WsMessage) -> String {
+        serde_json::to_value(msg).unwrap()["type"].as_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_lite_subscription_summarizes_unselected_devices() {
+        let full = Subscription::default();
+        let msg = full.event_message(DiscoveryEvent::DeviceUpdated(device("a"))).unwrap();
+        assert_eq!(message_type(&msg), "device_updated");
+
+        let lite: Subscription = serde_json::from_str::<ClientMessage>(
+            r#"{"type": "subscribe", "lite": true, "selected": "a"}"#,
+        )
+        .map(|msg| match msg {
+            ClientMessage::Subscribe(s) => s,
+            other => panic!("expected a subscription, got {:?}", other),
+        })
+        .unwrap();
+        let msg = lite.event_message(DiscoveryEvent::DeviceUpdated(device("a"))).unwrap();
+        assert_eq!(message_type(&msg), "device_updated");
+        let msg = lite.event_message(DiscoveryEvent::DeviceDiscovered(device("b"))).unwrap();
+        assert_eq!(message_type(&msg), "device_summary");
+        let json = serde_json::to_value(&msg).unwrap();
+        assert_eq!(json["data"]["id"], "b");
+        assert!(json["data"].get("visuals").is_none());
+
+        // Status-only events pass through unchanged
+        let msg = lite.event_message(DiscoveryEvent::DeviceOffline(DeviceId::from_hwid("b"))).unwrap();
+        assert_eq!(message_type(&msg), "device_offline");
+        let backoff = DiscoveryEvent::HeartbeatBackoff { id: DeviceId::from_hwid("b"), failures: 3, retry_in_ms: 8000 };
+        let msg = lite.event_message(backoff).unwrap();
+        assert_eq!(message_type(&msg), "heartbeat_backoff");
+    }
+
+    #[test]
+    fn test_scan_lifecycle_messages() {
+        let subscription = Subscription::default();
+        let msg = subscription.event_message(DiscoveryEvent::ScanStarted { total_hosts: 254 }).unwrap();
+        assert_eq!(serde_json::to_value(&msg).unwrap(), serde_json::json!({"type": "scan_started", "total_hosts": 254}));
+        let msg = subscription.event_message(DiscoveryEvent::ScanProgress { done: 45, total: 254 }).unwrap();
+        assert_eq!(
+            serde_json::to_value(&msg).unwrap(),
+            serde_json::json!({"type": "scan_progress", "done": 45, "total": 254})
+        );
+    }
+
+    #[test]
+    fn test_ota_progress_matches_fixture() {
+        let msg = WsMessage::OtaProgress(OtaEvent {
+            device_id: "0x1a2b3c4d".to_string(),
+            state: crate::ota::UpdateState::Uploading { progress: 0.5, retries: 0, resumed_at: None },
+        });
+        let fixture: serde_json::Value =
+            serde_json::from_str(dendrite_api_types::fixtures::OTA_PROGRESS).unwrap();
+        assert_eq!(serde_json::to_value(&msg).unwrap(), fixture);
+    }
+
+    #[test]
+    fn test_compact_snapshot_is_smaller_and_lossless() {
+        let devices: Vec<Device> = serde_json::from_str(&synthetic_registry(REGISTRY_DEVICES)).unwrap();
+        let size = |messages: &[WsMessage]| -> usize {
+            messages.iter().map(|m| serde_json::to_string(m).unwrap().len()).sum()
+        };
+
+        let full = device_list_messages(devices.clone(), &Subscription::default(), false);
+        let compact = device_list_messages(devices.clone(), &Subscription::default(), true);
+        assert_eq!((full.len(), compact.len()), (REGISTRY_DEVICES, 1));
+        let (full_bytes, compact_bytes) = (size(&full), size(&compact));
+        assert!(
+            compact_bytes * 4 < full_bytes * 3,
+            "compact snapshot is {} bytes, per-device messages {} bytes",
+            compact_bytes,
+            full_bytes
+        );
+
+        let WsMessage::DeviceSnapshot(snapshot) = compact.into_iter().next().unwrap() else {
+            panic!("expected a snapshot");
+        };
+        let expanded = snapshot.expand().unwrap();
+        for (value, device) in expanded.into_iter().zip(&devices) {
+            assert_eq!(value["type"], "device_discovered");
+            let parsed: Device = serde_json::from_value(value["data"].clone()).unwrap();
+            assert_eq!(serde_json::to_value(&parsed).unwrap(), serde_json::to_value(device).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_delta_client_gets_changed_fields_only() {
+        let mut deltas = Deltas::default();
+        let full = Subscription::default();
+        let mut fixture: Device = serde_json::from_str(dendrite_api_types::fixtures::DEVICE).unwrap();
+
+        let WsMessage::FullSync(sync) = deltas.full_sync(std::slice::from_ref(&fixture), &full) else {
+            panic!("expected a full sync");
+        };
+        assert_eq!(sync.protocol, WS_PROTOCOL_VERSION);
+        assert_eq!(sync.devices.expand().unwrap().len(), 1);
+
+        // Nothing changed, nothing sent
+        assert!(deltas.event_message(DiscoveryEvent::DeviceUpdated(fixture.clone()), &full).is_none());
+
+        fixture.pose = Some([1.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+        let msg = deltas.event_message(DiscoveryEvent::DeviceUpdated(fixture.clone()), &full).unwrap();
+        let json = serde_json::to_value(&msg).unwrap();
+        assert_eq!(json["type"], "device_delta");
+        assert_eq!(json["data"]["id"], fixture.id.as_str());
+        let changed = json["data"]["changed_fields"].as_object().unwrap();
+        assert_eq!(changed.keys().collect::<Vec<_>>(), ["pose"]);
+        assert!(serde_json::to_string(&msg).unwrap().len() < 200);
+
+        let msg = deltas.event_message(DiscoveryEvent::DeviceOffline(fixture.id.clone()), &full).unwrap();
+        assert_eq!(serde_json::to_value(&msg).unwrap(), serde_json::json!({
+            "type": "status_changed",
+            "data": { "id": fixture.id.as_str(), "status": "offline" },
+        }));
+
+        let msg = deltas.event_message(DiscoveryEvent::DeviceDiscovered(device("new")), &full).unwrap();
+        assert_eq!(message_type(&msg), "device_added");
+        let msg = deltas.event_message(DiscoveryEvent::DeviceRemoved(DeviceId::from_hwid("new")), &full).unwrap();
+        assert_eq!(message_type(&msg), "device_removed");
+        let msg = deltas.event_message(DiscoveryEvent::DeviceDiscovered(device("new")), &full).unwrap();
+        assert_eq!(message_type(&msg), "device_added");
+    }
+
+    #[test]
+    fn test_delta_client_leaving_lite_gets_missing_fields() {
+        let mut deltas = Deltas::default();
+        let lite = Subscription { lite: true, selected: None };
+        let device: Device = serde_json::from_str(dendrite_api_types::fixtures::DEVICE).unwrap();
+        deltas.full_sync(std::slice::from_ref(&device), &lite);
+
+        let WsMessage::DeviceDelta(delta) = deltas.device_message(&device, &Subscription::default()).unwrap() else {
+            panic!("expected a delta");
+        };
+        assert!(delta.changed_fields.contains_key("visuals"));
+        assert!(!delta.changed_fields.contains_key("name"));
+
+        // Back in lite mode the summary leaves the full record alone
+        assert!(deltas.device_message(&device, &lite).is_none());
+    }
+
+    #[test]
+    fn test_sync_request_parses() {
+        assert!(matches!(serde_json::from_str(r#"{"type": "sync"}"#), Ok(ClientMessage::Sync)));
+    }
+}
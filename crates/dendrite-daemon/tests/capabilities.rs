@@ -0,0 +1,95 @@
+//! Capabilities are dropped on every thread of the running daemon
+//!
+//! capset(2) only changes the calling thread, so this starts the real binary
+//! and reads `CapEff` from each of its threads once the web server is up.
+//! Run as an unprivileged user without file capabilities, the daemon never
+//! held any and the test only shows nothing was gained.
+
+#![cfg(target_os = "linux")]
+
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// Kills the daemon when the test ends, passing or not
+struct Daemon(Child);
+
+impl Drop for Daemon {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+fn free_port() -> u16 {
+    TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap().local_addr().unwrap().port()
+}
+
+/// `CapEff` of every thread of a process, by thread id
+fn effective_capabilities(pid: u32) -> Vec<(String, u64)> {
+    let mut threads = Vec::new();
+    for entry in std::fs::read_dir(format!("/proc/{}/task", pid)).unwrap() {
+        let entry = entry.unwrap();
+        let Ok(status) = std::fs::read_to_string(entry.path().join("status")) else {
+            // The thread exited while listing
+            continue;
+        };
+        let cap_eff = status
+            .lines()
+            .find_map(|line| line.strip_prefix("CapEff:"))
+            .map(|hex| u64::from_str_radix(hex.trim(), 16).unwrap())
+            .unwrap();
+        threads.push((entry.file_name().to_string_lossy().into_owned(), cap_eff));
+    }
+    threads
+}
+
+#[test]
+fn test_capabilities_dropped_on_every_thread() {
+    let dir = tempfile::tempdir().unwrap();
+    let port = free_port();
+    let config = format!(
+        r#"
+[daemon]
+bind = "127.0.0.1:{port}"
+discovery_interval_secs = 3600
+drop_capabilities = true
+
+[discovery]
+subnet = "127.0.0.1"
+prefix_len = 32
+use_lldp = false
+use_arp = false
+
+[hcdf]
+path = "dendrite.hcdf"
+
+[fragments]
+path = "fragments/index.toml"
+"#
+    );
+    std::fs::write(dir.path().join("dendrite.toml"), config).unwrap();
+
+    let daemon = Daemon(
+        Command::new(env!("CARGO_BIN_EXE_dendrite"))
+            .args(["--config", "dendrite.toml", "--no-persist", "--log-level", "warn"])
+            .current_dir(dir.path())
+            .stdout(Stdio::null())
+            .spawn()
+            .unwrap(),
+    );
+
+    // Every runtime thread exists once the web server answers
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    let started = Instant::now();
+    while TcpStream::connect_timeout(&addr, Duration::from_millis(100)).is_err() {
+        assert!(started.elapsed() < Duration::from_secs(30), "daemon didn't start listening");
+        std::thread::sleep(Duration::from_millis(100));
+    }
+
+    let threads = effective_capabilities(daemon.0.id());
+    assert!(threads.len() > 1, "expected runtime threads: {:?}", threads);
+    for (tid, cap_eff) in &threads {
+        assert_eq!(*cap_eff, 0, "thread {} still holds capabilities {:#x}", tid, cap_eff);
+    }
+}
@@ -0,0 +1,59 @@
+//! Scan a subnet once and print the device topology
+//!
+//! Discovery embedded without the daemon: a scanner built from a plain
+//! `ScannerConfig`, and a `RegistryHandle` kept up to date from its events.
+//!
+//! ```bash
+//! cargo run -p dendrite-discovery --example scan_topology -- 192.168.186.0/24
+//! cargo run -p dendrite-discovery --example scan_topology -- fe80::/64
+//! ```
+//!
+//! ARP scanning and IPv6 neighbor discovery need raw sockets (root or CAP_NET_RAW).
+
+use anyhow::Context;
+use dendrite_core::{DeviceId, RegistryHandle, Topology};
+use dendrite_discovery::{DiscoveryScanner, IpNet, ScannerConfig};
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let cidr = std::env::args().nth(1).unwrap_or_else(|| "192.168.186.0/24".to_string());
+    let subnet: IpNet = cidr.parse().with_context(|| format!("not a CIDR range: {}", cidr))?;
+
+    let scanner = DiscoveryScanner::new(ScannerConfig { subnet, ..ScannerConfig::default() });
+    let registry = RegistryHandle::new();
+
+    // Events are buffered while the scan runs; apply them once it's done
+    let mut events = scanner.subscribe();
+    scanner.scan_once().await?;
+    while let Ok(event) = events.try_recv() {
+        registry.apply(&event);
+    }
+
+    println!("{} device(s) on {}", registry.len(), cidr);
+    print_tree(&registry.topology(None), &registry);
+    Ok(())
+}
+
+/// Each device with the ones connected below it, indented
+fn print_tree(topology: &Topology, registry: &RegistryHandle) {
+    let is_child = |id: &DeviceId| topology.nodes().any(|n| n.children.contains(id));
+    let mut roots: Vec<_> = topology.nodes().filter(|n| !is_child(&n.id)).collect();
+    roots.sort_by(|a, b| a.name.cmp(&b.name));
+    for root in roots {
+        print_node(topology, registry, &root.id, 0);
+    }
+    for bus in topology.buses() {
+        println!("bus {} ({}): {} node(s)", bus.name, bus.kind, bus.members.len());
+    }
+}
+
+fn print_node(topology: &Topology, registry: &RegistryHandle, id: &DeviceId, depth: usize) {
+    let Some(device) = registry.get(id) else {
+        return;
+    };
+    let board = device.info.board.as_deref().unwrap_or("unknown board");
+    println!("{}{} [{}] {} {:?}", "  ".repeat(depth), device.name, board, device.discovery.scoped_ip(), device.status);
+    for child in topology.children(id) {
+        print_node(topology, registry, &child.id, depth + 1);
+    }
+}
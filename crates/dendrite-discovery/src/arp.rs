@@ -1,9 +1,17 @@
 //! ARP-based network scanning for device discovery
+//!
+//! The active scan pings every host in the subnet, and each ping to an
+//! unresolved address starts with a broadcast ARP request. On a wide subnet
+//! that is thousands of broadcasts at once, which some managed switches take
+//! for a broadcast storm. A rate limit paces the pings with a token bucket,
+//! and the `ping` fallback keeps at most [`MAX_PINGS_IN_FLIGHT`] running.
 
 use anyhow::Result;
+use std::future::Future;
 use std::net::{IpAddr, Ipv4Addr};
 use std::process::Command;
 use std::str::FromStr;
+use tokio::time::{Duration, Instant};
 use tracing::{debug, trace, warn};
 
 /// ARP table entry
@@ -99,23 +107,50 @@ fn parse_arp_state(s: &str) -> ArpState {
     }
 }
 
-/// Scan a subnet for reachable hosts using ping
-pub async fn scan_subnet(subnet: Ipv4Addr, prefix_len: u8) -> Result<Vec<Ipv4Addr>> {
-    let subnet_u32 = u32::from(subnet);
-    let mask = if prefix_len >= 32 {
-        0xFFFFFFFF
-    } else {
-        !((1u32 << (32 - prefix_len)) - 1)
-    };
-    let network = subnet_u32 & mask;
-    let broadcast = network | !mask;
+/// Token bucket pacing scan requests
+///
+/// Holds at most one token, refilled at `rate` per second, so requests go
+/// out evenly spaced rather than in bursts.
+#[derive(Debug)]
+pub struct TokenBucket {
+    rate: f64,
+    burst: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    /// A bucket allowing `rate` requests per second (at least one)
+    pub fn new(rate: u32) -> Self {
+        Self { rate: f64::from(rate.max(1)), burst: 1.0, tokens: 1.0, last: Instant::now() }
+    }
 
-    let mut hosts = Vec::new();
+    /// Wait for a token and take it
+    pub async fn acquire(&mut self) {
+        self.refill();
+        if self.tokens < 1.0 {
+            tokio::time::sleep(Duration::from_secs_f64((1.0 - self.tokens) / self.rate)).await;
+            self.refill();
+        }
+        self.tokens -= 1.0;
+    }
 
-    // Skip network and broadcast addresses
-    for host in (network + 1)..broadcast {
-        hosts.push(Ipv4Addr::from(host));
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.burst);
+        self.last = now;
     }
+}
+
+/// Scan a subnet for reachable hosts using ping
+///
+/// `rate_limit` caps requests per second (None = as fast as possible). The
+/// scan covers every host the prefix allows, so at a fixed rate its duration
+/// grows with the subnet: a /24 (254 hosts) at 100/s takes about 2.5 s, a
+/// /20 (4094 hosts) about 41 s.
+pub async fn scan_subnet(subnet: Ipv4Addr, prefix_len: u8, rate_limit: Option<u32>) -> Result<Vec<Ipv4Addr>> {
+    let hosts = subnet_hosts(subnet, prefix_len);
 
     debug!(
         "Scanning {} hosts in {}/{}",
@@ -123,30 +158,105 @@ pub async fn scan_subnet(subnet: Ipv4Addr, prefix_len: u8) -> Result<Vec<Ipv4Add
         subnet,
         prefix_len
     );
+    if let Some(duration) = paced_scan_duration(hosts.len(), rate_limit) {
+        debug!(rate = rate_limit, seconds = duration.as_secs(), "ARP scan rate limited");
+    }
 
     // Use fping if available (much faster), otherwise fall back to sequential ping
-    if is_fping_available() {
-        scan_with_fping(&hosts).await
+    if is_fping_available().await {
+        scan_with_fping(&hosts, rate_limit).await
     } else {
-        scan_with_ping(&hosts).await
+        scan_with_ping(&hosts, rate_limit).await
     }
 }
 
-fn is_fping_available() -> bool {
-    Command::new("which")
+/// Every host address in a subnet, without the network and broadcast addresses
+pub fn subnet_hosts(subnet: Ipv4Addr, prefix_len: u8) -> Vec<Ipv4Addr> {
+    let mask = if prefix_len >= 32 {
+        0xFFFFFFFF
+    } else {
+        !((1u32 << (32 - prefix_len)) - 1)
+    };
+    let network = u32::from(subnet) & mask;
+    let broadcast = network | !mask;
+
+    ((network + 1)..broadcast).map(Ipv4Addr::from).collect()
+}
+
+/// Number of host addresses `subnet_hosts` gives for a prefix
+pub fn subnet_host_count(prefix_len: u8) -> usize {
+    if prefix_len >= 31 {
+        0
+    } else {
+        (1usize << (32 - prefix_len)) - 2
+    }
+}
+
+/// Time a full scan of `hosts` takes at `rate_limit`, None when unlimited
+pub fn paced_scan_duration(hosts: usize, rate_limit: Option<u32>) -> Option<Duration> {
+    rate_limit.map(|rate| Duration::from_secs_f64(hosts as f64 / f64::from(rate.max(1))))
+}
+
+async fn is_fping_available() -> bool {
+    tokio::process::Command::new("which")
         .arg("fping")
         .output()
+        .await
         .map(|o| o.status.success())
         .unwrap_or(false)
 }
 
-async fn scan_with_fping(hosts: &[Ipv4Addr]) -> Result<Vec<Ipv4Addr>> {
+/// fping's interval between targets when not rate limited
+const FPING_DEFAULT_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Highest rate limit a scan honours, requests per second: fping can't
+/// space targets less than 1 ms apart
+pub const MAX_ARP_RATE: u32 = 1000;
+
+/// Most `ping` processes the fallback scan runs at once
+pub const MAX_PINGS_IN_FLIGHT: usize = 64;
+
+/// fping's interval between targets: the whole milliseconds that keep it
+/// at or below `rate_limit`, capped at [`MAX_ARP_RATE`]
+fn fping_interval(rate_limit: Option<u32>) -> Duration {
+    match rate_limit {
+        Some(rate) => Duration::from_millis(1000_u32.div_ceil(rate.clamp(1, MAX_ARP_RATE)).into()),
+        None => FPING_DEFAULT_INTERVAL,
+    }
+}
+
+/// Time allowed beyond the expected duration for replies, the retry and process startup
+const FPING_SLACK: Duration = Duration::from_secs(10);
+
+/// How long an fping run over `hosts` may take before it is killed: twice the
+/// time its pacing needs, plus slack
+fn fping_timeout(hosts: usize, rate_limit: Option<u32>) -> Duration {
+    fping_interval(rate_limit) * hosts as u32 * 2 + FPING_SLACK
+}
+
+async fn scan_with_fping(hosts: &[Ipv4Addr], rate_limit: Option<u32>) -> Result<Vec<Ipv4Addr>> {
     let host_list: Vec<String> = hosts.iter().map(|h| h.to_string()).collect();
 
-    let output = Command::new("fping")
-        .args(["-a", "-q", "-r", "1", "-t", "100"])
-        .args(&host_list)
-        .output()?;
+    let mut command = tokio::process::Command::new("fping");
+    command.args(["-a", "-q", "-r", "1", "-t", "100"]);
+    // fping paces itself; its interval between targets can't go below 1 ms
+    if let Some(rate) = rate_limit {
+        if rate > MAX_ARP_RATE {
+            warn!(rate, max = MAX_ARP_RATE, "ARP rate limit above what fping can pace; using the maximum");
+        }
+        command.args(["-i", &fping_interval(rate_limit).as_millis().to_string()]);
+    }
+    // A paced scan of a wide subnet runs for a while; don't leave it running
+    // if it hangs or the scan is dropped
+    command.args(&host_list).kill_on_drop(true);
+    let limit = fping_timeout(hosts.len(), rate_limit);
+    let output = match tokio::time::timeout(limit, command.output()).await {
+        Ok(output) => output?,
+        Err(_) => {
+            warn!(hosts = hosts.len(), timeout_secs = limit.as_secs(), "fping timed out");
+            anyhow::bail!("fping didn't finish within {} s", limit.as_secs());
+        }
+    };
 
     let stdout = String::from_utf8_lossy(&output.stdout);
     let mut reachable = Vec::new();
@@ -161,22 +271,47 @@ async fn scan_with_fping(hosts: &[Ipv4Addr]) -> Result<Vec<Ipv4Addr>> {
     Ok(reachable)
 }
 
-async fn scan_with_ping(hosts: &[Ipv4Addr]) -> Result<Vec<Ipv4Addr>> {
+async fn scan_with_ping(hosts: &[Ipv4Addr], rate_limit: Option<u32>) -> Result<Vec<Ipv4Addr>> {
+    let reachable = ping_paced(hosts, rate_limit, |host| async move {
+        let result = tokio::process::Command::new("ping")
+            .args(["-c", "1", "-W", "1", &host.to_string()])
+            .output()
+            .await;
+        matches!(result, Ok(output) if output.status.success())
+    })
+    .await;
+
+    debug!("ping found {} reachable hosts", reachable.len());
+    Ok(reachable)
+}
+
+/// Run `ping` against every host concurrently, starting them no faster than
+/// `rate_limit` and with at most [`MAX_PINGS_IN_FLIGHT`] running
+async fn ping_paced<F, Fut>(hosts: &[Ipv4Addr], rate_limit: Option<u32>, ping: F) -> Vec<Ipv4Addr>
+where
+    F: Fn(Ipv4Addr) -> Fut,
+    Fut: Future<Output = bool> + Send + 'static,
+{
+    use std::sync::Arc;
+    use tokio::sync::Semaphore;
     use tokio::task::JoinSet;
 
+    let mut bucket = rate_limit.map(TokenBucket::new);
+    let in_flight = Arc::new(Semaphore::new(MAX_PINGS_IN_FLIGHT));
     let mut tasks = JoinSet::new();
 
     for &host in hosts {
+        let Ok(permit) = in_flight.clone().acquire_owned().await else {
+            break;
+        };
+        if let Some(bucket) = bucket.as_mut() {
+            bucket.acquire().await;
+        }
+        let reply = ping(host);
         tasks.spawn(async move {
-            let result = tokio::process::Command::new("ping")
-                .args(["-c", "1", "-W", "1", &host.to_string()])
-                .output()
-                .await;
-
-            match result {
-                Ok(output) if output.status.success() => Some(host),
-                _ => None,
-            }
+            let reachable = reply.await;
+            drop(permit);
+            reachable.then_some(host)
         });
     }
 
@@ -186,9 +321,7 @@ async fn scan_with_ping(hosts: &[Ipv4Addr]) -> Result<Vec<Ipv4Addr>> {
             reachable.push(ip);
         }
     }
-
-    debug!("ping found {} reachable hosts", reachable.len());
-    Ok(reachable)
+    reachable
 }
 
 /// Get hosts from ARP table for a specific interface
@@ -297,6 +430,79 @@ pub async fn check_hosts_reachable(hosts: &[Ipv4Addr]) -> Vec<Ipv4Addr> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rate_limit_spreads_sends() {
+        let hosts: Vec<Ipv4Addr> = (1..=1000u32).map(|i| Ipv4Addr::from(0x0a00_0000 + i)).collect();
+        let sent = Arc::new(Mutex::new(Vec::new()));
+
+        let ping = |host| {
+            sent.lock().unwrap().push(Instant::now());
+            async move { host == Ipv4Addr::new(10, 0, 0, 7) }
+        };
+        let reachable = ping_paced(&hosts, Some(100), ping).await;
+
+        assert_eq!(reachable, vec![Ipv4Addr::new(10, 0, 0, 7)]);
+        let sent = sent.lock().unwrap();
+        assert_eq!(sent.len(), 1000);
+        let spread = *sent.last().unwrap() - sent[0];
+        assert!(spread >= Duration::from_millis(9900), "{:?}", spread);
+        assert!(spread < Duration::from_secs(11), "{:?}", spread);
+        assert_eq!(paced_scan_duration(1000, Some(100)), Some(Duration::from_secs(10)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_no_rate_limit_sends_at_once() {
+        let hosts: Vec<Ipv4Addr> = (1..=1000u32).map(|i| Ipv4Addr::from(0x0a00_0000 + i)).collect();
+        let start = Instant::now();
+        ping_paced(&hosts, None, |_| async { false }).await;
+        assert_eq!(start.elapsed(), Duration::ZERO);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_ping_fallback_caps_pings_in_flight() {
+        let hosts: Vec<Ipv4Addr> = (1..=1000u32).map(|i| Ipv4Addr::from(0x0a00_0000 + i)).collect();
+        let running = Arc::new(Mutex::new((0usize, 0usize)));
+
+        let ping = |_| {
+            let running = running.clone();
+            async move {
+                {
+                    let mut running = running.lock().unwrap();
+                    running.0 += 1;
+                    running.1 = running.1.max(running.0);
+                }
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                running.lock().unwrap().0 -= 1;
+                false
+            }
+        };
+        ping_paced(&hosts, None, ping).await;
+
+        assert_eq!(*running.lock().unwrap(), (0, MAX_PINGS_IN_FLIGHT));
+    }
+
+    #[test]
+    fn test_fping_interval_never_exceeds_the_rate() {
+        assert_eq!(fping_interval(Some(100)), Duration::from_millis(10));
+        // 1000 / 300 rounds up to 4 ms (250/s) rather than down to 3 ms (333/s)
+        assert_eq!(fping_interval(Some(300)), Duration::from_millis(4));
+        assert_eq!(fping_interval(Some(MAX_ARP_RATE)), Duration::from_millis(1));
+        // fping can't go faster than 1 ms
+        assert_eq!(fping_interval(Some(5000)), Duration::from_millis(1));
+        assert_eq!(fping_interval(Some(0)), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_fping_timeout_covers_pacing() {
+        // 4000 hosts at 100/s pace for 40 s
+        assert_eq!(fping_timeout(4000, Some(100)), Duration::from_secs(80) + FPING_SLACK);
+        // At 300/s fping spaces targets 4 ms apart, so 4000 hosts take 16 s
+        assert_eq!(fping_timeout(4000, Some(300)), Duration::from_secs(32) + FPING_SLACK);
+        // Unlimited runs use fping's own 10 ms interval
+        assert_eq!(fping_timeout(254, None), Duration::from_millis(5080) + FPING_SLACK);
+    }
 
     #[test]
     fn test_parse_ip_neigh_line_reachable() {
@@ -0,0 +1,235 @@
+//! CAN bus discovery over Linux SocketCAN
+//!
+//! Buses are the interfaces the kernel reports as CAN (`type` 280 in
+//! `/sys/class/net`), virtual `vcan` ones included. CAN has no standard way
+//! to ask who is on a bus, so finding nodes is application-defined: when an
+//! identification request is configured, a frame with the request ID is sent
+//! on each bus and every frame arriving on `response_base + node_id` within
+//! the listen window counts as a node. Without one, only nodes declared in
+//! the override rules (`can:<bus>:<node id>`) are registered, on buses that
+//! are present.
+//!
+//! Sockets need the `socketcan` feature; without it (or off Linux) buses are
+//! still listed but identification fails.
+
+use anyhow::Result;
+use dendrite_core::CanAddress;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// `ARPHRD_CAN`, the link type of CAN interfaces
+const ARPHRD_CAN: &str = "280";
+
+/// CAN discovery configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CanDiscoveryConfig {
+    /// Interfaces to scan (empty = every CAN interface)
+    #[serde(default)]
+    pub interfaces: Vec<String>,
+    /// Identification request; None registers only declared nodes
+    #[serde(default)]
+    pub identify: Option<CanIdentify>,
+}
+
+/// Application-defined identification request and where answers arrive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CanIdentify {
+    /// CAN ID of the request frame
+    pub request_id: u32,
+    /// Request payload (at most 8 bytes)
+    #[serde(default)]
+    pub request_data: Vec<u8>,
+    /// Node `n` answers with CAN ID `response_base + n`
+    pub response_base: u32,
+    /// Highest node id answers are accepted from
+    #[serde(default = "default_max_node_id")]
+    pub max_node_id: u8,
+    /// Use 29-bit (extended) CAN IDs
+    #[serde(default)]
+    pub extended: bool,
+    /// How long to collect answers after sending the request
+    #[serde(default = "default_listen_ms")]
+    pub listen_ms: u64,
+}
+
+fn default_max_node_id() -> u8 {
+    127
+}
+
+fn default_listen_ms() -> u64 {
+    200
+}
+
+/// A node that answered an identification request
+#[derive(Debug, Clone, PartialEq)]
+pub struct CanNode {
+    pub address: CanAddress,
+    /// Payload of its answer
+    pub data: Vec<u8>,
+}
+
+impl CanIdentify {
+    /// Node id of an answer with the given CAN ID, if it is one
+    pub fn node_for(&self, can_id: u32, extended: bool) -> Option<u8> {
+        if extended != self.extended {
+            return None;
+        }
+        let node_id = can_id.checked_sub(self.response_base)?;
+        u8::try_from(node_id).ok().filter(|&id| id <= self.max_node_id)
+    }
+}
+
+/// CAN interfaces on this host, sorted by name
+pub fn can_interfaces() -> Vec<String> {
+    can_interfaces_in(Path::new("/sys/class/net"))
+}
+
+fn can_interfaces_in(sysfs: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(sysfs) else {
+        return Vec::new();
+    };
+    let mut interfaces: Vec<String> = entries
+        .flatten()
+        .filter(|entry| {
+            std::fs::read_to_string(entry.path().join("type")).is_ok_and(|t| t.trim() == ARPHRD_CAN)
+        })
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect();
+    interfaces.sort();
+    interfaces
+}
+
+/// Send the identification request on `bus` and collect the answers
+pub async fn identify_bus(bus: &str, identify: &CanIdentify) -> Result<Vec<CanNode>> {
+    let (bus, identify) = (bus.to_string(), identify.clone());
+    tokio::task::spawn_blocking(move || identify_blocking(&bus, &identify)).await?
+}
+
+#[cfg(all(feature = "socketcan", target_os = "linux"))]
+fn identify_blocking(bus: &str, identify: &CanIdentify) -> Result<Vec<CanNode>> {
+    use anyhow::Context;
+    use socketcan::{CanFrame, CanSocket, EmbeddedFrame, ExtendedId, Id, Socket, StandardId};
+    use std::collections::BTreeMap;
+    use std::io::ErrorKind;
+    use std::time::{Duration, Instant};
+
+    let id = if identify.extended {
+        ExtendedId::new(identify.request_id).map(Id::Extended)
+    } else {
+        u16::try_from(identify.request_id).ok().and_then(StandardId::new).map(Id::Standard)
+    }
+    .with_context(|| format!("invalid request CAN ID {:#x}", identify.request_id))?;
+    let request = CanFrame::new(id, &identify.request_data)
+        .with_context(|| format!("request payload is {} bytes, at most 8 fit", identify.request_data.len()))?;
+
+    let socket = CanSocket::open(bus).with_context(|| format!("failed to open {}", bus))?;
+    socket.write_frame(&request).with_context(|| format!("failed to send on {}", bus))?;
+
+    let deadline = Instant::now() + Duration::from_millis(identify.listen_ms);
+    let mut answers = BTreeMap::new();
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        socket.set_read_timeout(remaining)?;
+        let frame = match socket.read_frame() {
+            Ok(frame) => frame,
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => break,
+            Err(e) => return Err(e).with_context(|| format!("failed to read from {}", bus)),
+        };
+        let (can_id, extended) = match frame.id() {
+            Id::Standard(id) => (u32::from(id.as_raw()), false),
+            Id::Extended(id) => (id.as_raw(), true),
+        };
+        if let Some(node_id) = identify.node_for(can_id, extended) {
+            answers.entry(node_id).or_insert_with(|| frame.data().to_vec());
+        }
+    }
+
+    Ok(answers
+        .into_iter()
+        .map(|(node_id, data)| CanNode { address: CanAddress { bus: bus.to_string(), node_id }, data })
+        .collect())
+}
+
+#[cfg(not(all(feature = "socketcan", target_os = "linux")))]
+fn identify_blocking(bus: &str, _identify: &CanIdentify) -> Result<Vec<CanNode>> {
+    anyhow::bail!("cannot identify nodes on {}: built without the socketcan feature", bus)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identify() -> CanIdentify {
+        CanIdentify {
+            request_id: 0x700,
+            request_data: Vec::new(),
+            response_base: 0x580,
+            max_node_id: 127,
+            extended: false,
+            listen_ms: 200,
+        }
+    }
+
+    #[test]
+    fn test_node_for_answer_ids() {
+        let identify = identify();
+        assert_eq!(identify.node_for(0x580, false), Some(0));
+        assert_eq!(identify.node_for(0x58c, false), Some(12));
+        assert_eq!(identify.node_for(0x5ff, false), Some(127));
+        // Outside the answer range, or the wrong ID width
+        assert_eq!(identify.node_for(0x600, false), None);
+        assert_eq!(identify.node_for(0x57f, false), None);
+        assert_eq!(identify.node_for(0x58c, true), None);
+    }
+
+    #[test]
+    fn test_can_interfaces_from_sysfs() {
+        let sysfs = std::env::temp_dir().join(format!("dendrite-can-sysfs-{}", std::process::id()));
+        for (name, link_type) in [("vcan0", "280\n"), ("eth0", "1\n"), ("can1", "280\n")] {
+            std::fs::create_dir_all(sysfs.join(name)).unwrap();
+            std::fs::write(sysfs.join(name).join("type"), link_type).unwrap();
+        }
+        let interfaces = can_interfaces_in(&sysfs);
+        std::fs::remove_dir_all(&sysfs).unwrap();
+
+        assert_eq!(interfaces, vec!["can1".to_string(), "vcan0".to_string()]);
+    }
+
+    /// Needs a vcan interface: `ip link add dev vcan0 type vcan && ip link set up vcan0`
+    #[cfg(all(feature = "socketcan", target_os = "linux"))]
+    #[tokio::test]
+    async fn test_identify_on_vcan() {
+        use socketcan::{CanFrame, CanSocket, EmbeddedFrame, Id, Socket, StandardId};
+        use std::time::Duration;
+
+        if !can_interfaces().contains(&"vcan0".to_string()) {
+            eprintln!("skipping: no vcan0 interface");
+            return;
+        }
+
+        // Two nodes answer the request
+        let responder = CanSocket::open("vcan0").unwrap();
+        responder.set_read_timeout(Duration::from_secs(2)).unwrap();
+        let nodes = std::thread::spawn(move || loop {
+            let frame = responder.read_frame().unwrap();
+            if matches!(frame.id(), Id::Standard(id) if id.as_raw() == 0x700) {
+                for (node_id, data) in [(12u16, [0xaa]), (3, [0xbb])] {
+                    let id = StandardId::new(0x580 + node_id).unwrap();
+                    responder.write_frame(&CanFrame::new(id, &data).unwrap()).unwrap();
+                }
+                break;
+            }
+        });
+
+        let found = identify_bus("vcan0", &identify()).await.unwrap();
+        nodes.join().unwrap();
+
+        let ids: Vec<u8> = found.iter().map(|n| n.address.node_id).collect();
+        assert_eq!(ids, vec![3, 12]);
+        assert_eq!(found[1].address.bus, "vcan0");
+        assert_eq!(found[1].data, vec![0xaa]);
+    }
+}
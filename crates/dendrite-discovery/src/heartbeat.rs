@@ -0,0 +1,133 @@
+//! Per-device connectivity checks used by the heartbeat loop
+//!
+//! Each check returns the measured round-trip time, or None if the device
+//! did not respond within `CHECK_TIMEOUT_MS`.
+
+use dendrite_core::HeartbeatMethod;
+use dendrite_mcumgr::{probe_device, QueryOptions};
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tracing::trace;
+
+use crate::arp::is_host_reachable;
+
+/// Timeout for a single heartbeat check
+const CHECK_TIMEOUT_MS: u64 = 1000;
+
+/// Run a heartbeat check against a device's MCUmgr address and return its latency
+///
+/// Link-local IPv6 addresses must carry their scope id.
+pub async fn check_device(addr: SocketAddr, method: HeartbeatMethod) -> Option<Duration> {
+    let result = match method {
+        HeartbeatMethod::Arp => check_arp(addr).await,
+        HeartbeatMethod::Icmp => check_icmp(addr).await,
+        HeartbeatMethod::SmpEcho => check_smp_echo(addr).await,
+        HeartbeatMethod::TcpConnect(port) => check_tcp_connect(addr, port).await,
+    };
+    trace!(addr = %addr, method = %method, latency = ?result, "Heartbeat check");
+    result
+}
+
+/// ARP cache lookup with ping fallback (IPv4 only, falls back to ICMP otherwise)
+async fn check_arp(addr: SocketAddr) -> Option<Duration> {
+    let IpAddr::V4(v4) = addr.ip() else {
+        return check_icmp(addr).await;
+    };
+    let start = Instant::now();
+    is_host_reachable(v4).await.then(|| start.elapsed())
+}
+
+/// Host argument for `ping`, with the scope of a link-local address ("fe80::1%3")
+fn ping_target(addr: SocketAddr) -> String {
+    match addr {
+        SocketAddr::V6(v6) if v6.scope_id() != 0 => format!("{}%{}", v6.ip(), v6.scope_id()),
+        _ => addr.ip().to_string(),
+    }
+}
+
+/// ICMP echo via the system `ping` binary (no raw socket privileges needed)
+async fn check_icmp(addr: SocketAddr) -> Option<Duration> {
+    let start = Instant::now();
+    let output = tokio::process::Command::new("ping")
+        .args(["-c", "1", "-W", &(CHECK_TIMEOUT_MS / 1000).max(1).to_string(), &ping_target(addr)])
+        .output()
+        .await
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    // Prefer the RTT reported by ping over our wall-clock measurement
+    let rtt = parse_ping_time(&String::from_utf8_lossy(&output.stdout));
+    Some(rtt.unwrap_or_else(|| start.elapsed()))
+}
+
+/// MCUmgr SMP echo on the device's MCUmgr port
+async fn check_smp_echo(addr: SocketAddr) -> Option<Duration> {
+    let start = Instant::now();
+    // A dropped packet on a flaky link is retried rather than read as offline
+    let options = QueryOptions { timeout: Duration::from_millis(CHECK_TIMEOUT_MS), ..QueryOptions::default() };
+    probe_device(addr, options)
+        .await
+        .then(|| start.elapsed())
+}
+
+/// TCP connect to a specific port
+async fn check_tcp_connect(mut addr: SocketAddr, port: u16) -> Option<Duration> {
+    let start = Instant::now();
+    addr.set_port(port);
+    let connect = TcpStream::connect(addr);
+    match tokio::time::timeout(Duration::from_millis(CHECK_TIMEOUT_MS), connect).await {
+        Ok(Ok(_stream)) => Some(start.elapsed()),
+        _ => None,
+    }
+}
+
+/// Extract the round-trip time from `ping` output ("... time=0.421 ms")
+fn parse_ping_time(output: &str) -> Option<Duration> {
+    let start = output.find("time=")? + "time=".len();
+    let rest = &output[start..];
+    let end = rest
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(rest.len());
+    let ms: f64 = rest[..end].parse().ok()?;
+    Some(Duration::from_secs_f64(ms / 1000.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, Ipv6Addr, SocketAddrV6};
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_parse_ping_time() {
+        let output = "PING 192.168.1.1 (192.168.1.1) 56(84) bytes of data.\n\
+                      64 bytes from 192.168.1.1: icmp_seq=1 ttl=64 time=0.421 ms\n";
+        assert_eq!(parse_ping_time(output), Some(Duration::from_secs_f64(0.000421)));
+        assert_eq!(parse_ping_time("time=12 ms"), Some(Duration::from_millis(12)));
+        assert_eq!(parse_ping_time("no reply"), None);
+    }
+
+    #[tokio::test]
+    async fn test_tcp_connect_check() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let localhost = SocketAddr::from((Ipv4Addr::LOCALHOST, 0));
+
+        assert!(check_device(localhost, HeartbeatMethod::TcpConnect(port)).await.is_some());
+
+        drop(listener);
+        assert!(check_device(localhost, HeartbeatMethod::TcpConnect(port)).await.is_none());
+    }
+
+    #[test]
+    fn test_ping_target_keeps_scope() {
+        let link_local = SocketAddr::V6(SocketAddrV6::new("fe80::2".parse().unwrap(), 1337, 0, 3));
+        assert_eq!(ping_target(link_local), "fe80::2%3");
+        assert_eq!(ping_target(SocketAddr::from((Ipv6Addr::LOCALHOST, 1337))), "::1");
+        assert_eq!(ping_target(SocketAddr::from((Ipv4Addr::LOCALHOST, 1337))), "127.0.0.1");
+    }
+}
@@ -0,0 +1,416 @@
+//! ICMP echo sweep for subnets ARP can't reach
+//!
+//! ARP only resolves hosts on directly attached links, so devices behind a
+//! router (e.g. on a companion computer's subnet) never appear in the ARP
+//! table. A sweep sends an echo request to every host of a subnet at a fixed
+//! rate and collects the replies; responders are then probed for MCUmgr like
+//! ARP hits.
+//!
+//! A raw ICMP socket is used when the process may open one. Otherwise the
+//! unprivileged ICMP datagram socket is tried (Linux with a matching
+//! `net.ipv4.ping_group_range`, macOS).
+
+use serde::{Deserialize, Serialize};
+use socket2::{Domain, Protocol, Socket, Type};
+use std::collections::HashSet;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, trace};
+
+use crate::sockets::SocketProvider;
+
+const ECHO_REPLY: u8 = 0;
+const ECHO_REQUEST: u8 = 8;
+
+/// Echo request payload
+const PAYLOAD: &[u8] = b"dendrite";
+
+/// One subnet to sweep
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IcmpSweep {
+    pub subnet: Ipv4Addr,
+    pub prefix_len: u8,
+    /// IP time-to-live of the echo requests (must cover the routed hops)
+    pub ttl: u8,
+    /// Echo requests sent per second
+    pub rate_pps: u32,
+    /// How long to wait for replies after the last request
+    pub timeout_ms: u64,
+}
+
+impl IcmpSweep {
+    /// Sweep a subnet with the default TTL and rate
+    pub fn new(subnet: Ipv4Addr, prefix_len: u8) -> Self {
+        Self {
+            subnet,
+            prefix_len,
+            ttl: 64,
+            rate_pps: 200,
+            timeout_ms: 1000,
+        }
+    }
+}
+
+/// Which kind of ICMP socket a sweep ran on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SocketKind {
+    /// `SOCK_RAW`, needs CAP_NET_RAW or root
+    Raw,
+    /// Unprivileged `SOCK_DGRAM` ICMP
+    Datagram,
+}
+
+/// Socket operations used by a sweep, so tests can stand in for the network
+pub trait IcmpSocket {
+    fn kind(&self) -> SocketKind;
+
+    /// Send an ICMP message to `dst`
+    fn send_to(&self, packet: &[u8], dst: Ipv4Addr) -> io::Result<()>;
+
+    /// Wait up to `timeout` for a packet, returning its length and source,
+    /// or None on timeout
+    fn recv_from(&self, buf: &mut [u8], timeout: Duration) -> io::Result<Option<(usize, Ipv4Addr)>>;
+}
+
+/// An ICMP socket opened from the OS
+struct SystemSocket {
+    socket: UdpSocket,
+    kind: SocketKind,
+}
+
+impl IcmpSocket for SystemSocket {
+    fn kind(&self) -> SocketKind {
+        self.kind
+    }
+
+    fn send_to(&self, packet: &[u8], dst: Ipv4Addr) -> io::Result<()> {
+        self.socket.send_to(packet, SocketAddrV4::new(dst, 0)).map(|_| ())
+    }
+
+    fn recv_from(&self, buf: &mut [u8], timeout: Duration) -> io::Result<Option<(usize, Ipv4Addr)>> {
+        // A zero read timeout means "block forever"
+        self.socket.set_read_timeout(Some(timeout.max(Duration::from_millis(1))))?;
+        match self.socket.recv_from(buf) {
+            Ok((len, SocketAddr::V4(src))) => Ok(Some((len, *src.ip()))),
+            Ok((_, SocketAddr::V6(_))) => Ok(Some((0, Ipv4Addr::UNSPECIFIED))),
+            Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Open a raw ICMP socket (needs CAP_NET_RAW or root)
+pub(crate) fn open_raw_socket() -> io::Result<UdpSocket> {
+    Socket::new(Domain::IPV4, Type::RAW, Some(Protocol::ICMPV4)).map(UdpSocket::from)
+}
+
+/// Wrap an opened ICMP socket for a sweep with the given TTL
+pub(crate) fn system_socket(socket: UdpSocket, kind: SocketKind, ttl: u8) -> io::Result<Box<dyn IcmpSocket + Send>> {
+    socket.set_ttl(u32::from(ttl))?;
+    Ok(Box::new(SystemSocket { socket, kind }))
+}
+
+/// Open an ICMP socket, preferring a raw socket and falling back to the
+/// unprivileged datagram socket
+pub fn open_socket(ttl: u8) -> io::Result<Box<dyn IcmpSocket + Send>> {
+    let (socket, kind) = match open_raw_socket() {
+        Ok(socket) => (socket, SocketKind::Raw),
+        Err(raw_err) => match Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::ICMPV4)) {
+            Ok(socket) => {
+                debug!(error = %raw_err, "Raw ICMP socket unavailable, using unprivileged ICMP");
+                (socket.into(), SocketKind::Datagram)
+            }
+            Err(e) => {
+                return Err(io::Error::new(
+                    e.kind(),
+                    format!("raw ICMP socket: {}; unprivileged ICMP socket: {}", raw_err, e),
+                ))
+            }
+        },
+    };
+    system_socket(socket, kind, ttl)
+}
+
+/// Outcome of sweeping a list of hosts
+#[derive(Debug, Clone, Default)]
+pub struct SweepResult {
+    /// Hosts that answered, in reply order
+    pub responders: Vec<Ipv4Addr>,
+    /// Echo requests sent
+    pub sent: usize,
+    /// Echo requests the OS refused to send (e.g. no route)
+    pub send_errors: usize,
+    /// Hosts skipped by the exclusion list
+    pub excluded: usize,
+}
+
+/// Send one echo request to each target at `rate_pps`, then wait `timeout`
+/// for stragglers
+///
+/// Replies from hosts outside `targets` are ignored, as are replies to other
+/// processes' pings on a raw socket.
+pub fn sweep(socket: &dyn IcmpSocket, targets: &[Ipv4Addr], rate_pps: u32, timeout: Duration) -> SweepResult {
+    let ident = std::process::id() as u16;
+    let interval = Duration::from_secs_f64(1.0 / f64::from(rate_pps.max(1)));
+    let wanted: HashSet<Ipv4Addr> = targets.iter().copied().collect();
+    let mut seen = HashSet::new();
+    let mut result = SweepResult::default();
+    let mut buf = [0u8; 1500];
+
+    let mut collect = |until: Instant, result: &mut SweepResult| loop {
+        let Some(remaining) = until.checked_duration_since(Instant::now()).filter(|d| !d.is_zero()) else {
+            break;
+        };
+        match socket.recv_from(&mut buf, remaining) {
+            Ok(Some((len, src))) => {
+                let Some((reply_ident, seq)) = parse_echo_reply(&buf[..len]) else {
+                    continue;
+                };
+                // The kernel rewrites the identifier on datagram sockets and
+                // only delivers our own replies there
+                let ours = socket.kind() == SocketKind::Datagram || reply_ident == ident;
+                if ours && wanted.contains(&src) && seen.insert(src) {
+                    trace!(ip = %src, seq, "Echo reply");
+                    result.responders.push(src);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => {
+                debug!(error = %e, "ICMP receive failed");
+                break;
+            }
+        }
+    };
+
+    for (seq, &target) in targets.iter().enumerate() {
+        let next = Instant::now() + interval;
+        match socket.send_to(&echo_request(ident, seq as u16), target) {
+            Ok(()) => result.sent += 1,
+            Err(e) => {
+                trace!(ip = %target, error = %e, "Echo request not sent");
+                result.send_errors += 1;
+            }
+        }
+        collect(next, &mut result);
+    }
+    collect(Instant::now() + timeout, &mut result);
+
+    debug!(
+        sent = result.sent,
+        responders = result.responders.len(),
+        send_errors = result.send_errors,
+        "ICMP sweep finished"
+    );
+    result
+}
+
+/// Sweep a subnet on a socket from `sockets`, skipping `excluded` hosts
+///
+/// Runs on the blocking pool; fails only if no ICMP socket can be opened.
+pub async fn sweep_subnet(
+    config: &IcmpSweep,
+    sockets: Arc<dyn SocketProvider>,
+    excluded: impl Fn(Ipv4Addr) -> bool,
+) -> io::Result<(SweepResult, SocketKind)> {
+    let hosts = subnet_hosts(config.subnet, config.prefix_len);
+    let total = hosts.len();
+    let targets: Vec<Ipv4Addr> = hosts.into_iter().filter(|&ip| !excluded(ip)).collect();
+    let skipped = total - targets.len();
+    let config = config.clone();
+
+    tokio::task::spawn_blocking(move || {
+        let socket = sockets.icmp_socket(config.ttl)?;
+        debug!(
+            subnet = %config.subnet,
+            prefix = config.prefix_len,
+            hosts = targets.len(),
+            socket = ?socket.kind(),
+            "Starting ICMP sweep"
+        );
+        let mut result = sweep(&*socket, &targets, config.rate_pps, Duration::from_millis(config.timeout_ms));
+        result.excluded = skipped;
+        Ok((result, socket.kind()))
+    })
+    .await
+    .map_err(io::Error::other)?
+}
+
+/// Host addresses of a subnet (network and broadcast skipped below /31)
+pub fn subnet_hosts(subnet: Ipv4Addr, prefix_len: u8) -> Vec<Ipv4Addr> {
+    let mask = if prefix_len >= 32 { u32::MAX } else { !(u32::MAX >> prefix_len) };
+    let network = u32::from(subnet) & mask;
+    let broadcast = network | !mask;
+    if prefix_len >= 31 {
+        (network..=broadcast).map(Ipv4Addr::from).collect()
+    } else {
+        ((network + 1)..broadcast).map(Ipv4Addr::from).collect()
+    }
+}
+
+/// Build an ICMP echo request
+fn echo_request(ident: u16, seq: u16) -> Vec<u8> {
+    let mut packet = vec![ECHO_REQUEST, 0, 0, 0];
+    packet.extend_from_slice(&ident.to_be_bytes());
+    packet.extend_from_slice(&seq.to_be_bytes());
+    packet.extend_from_slice(PAYLOAD);
+    let sum = checksum(&packet);
+    packet[2..4].copy_from_slice(&sum.to_be_bytes());
+    packet
+}
+
+/// Identifier and sequence number of an echo reply
+///
+/// Raw sockets (and datagram sockets on macOS) deliver the IPv4 header too.
+fn parse_echo_reply(packet: &[u8]) -> Option<(u16, u16)> {
+    let icmp = if packet.first()? >> 4 == 4 {
+        let header_len = usize::from(packet[0] & 0x0f) * 4;
+        packet.get(header_len..)?
+    } else {
+        packet
+    };
+    if icmp.len() < 8 || icmp[0] != ECHO_REPLY {
+        return None;
+    }
+    Some((u16::from_be_bytes([icmp[4], icmp[5]]), u16::from_be_bytes([icmp[6], icmp[7]])))
+}
+
+/// RFC 1071 Internet checksum
+pub(crate) fn checksum(data: &[u8]) -> u16 {
+    let mut sum: u32 = data
+        .chunks(2)
+        .map(|pair| u32::from(u16::from_be_bytes([pair[0], pair.get(1).copied().unwrap_or(0)])))
+        .sum();
+    while sum >> 16 != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sockets::SystemSockets;
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+
+    /// Privileged socket stand-in: answers echo requests to `alive` hosts
+    /// with an IPv4 header in front, like a raw socket delivers them
+    struct MockRawSocket {
+        alive: HashSet<Ipv4Addr>,
+        sent: Mutex<Vec<(Ipv4Addr, Instant)>>,
+        replies: Mutex<VecDeque<(Vec<u8>, Ipv4Addr)>>,
+    }
+
+    impl MockRawSocket {
+        fn new(alive: &[Ipv4Addr]) -> Self {
+            Self {
+                alive: alive.iter().copied().collect(),
+                sent: Mutex::new(Vec::new()),
+                replies: Mutex::new(VecDeque::new()),
+            }
+        }
+
+        fn push_reply(&self, src: Ipv4Addr, ident: u16, seq: u16) {
+            let mut packet = vec![0x45, 0, 0, 36, 0, 0, 0, 0, 64, 1, 0, 0];
+            packet.extend_from_slice(&src.octets());
+            packet.extend_from_slice(&[10, 0, 0, 1]);
+            packet.extend_from_slice(&[ECHO_REPLY, 0, 0, 0]);
+            packet.extend_from_slice(&ident.to_be_bytes());
+            packet.extend_from_slice(&seq.to_be_bytes());
+            self.replies.lock().unwrap().push_back((packet, src));
+        }
+    }
+
+    impl IcmpSocket for MockRawSocket {
+        fn kind(&self) -> SocketKind {
+            SocketKind::Raw
+        }
+
+        fn send_to(&self, packet: &[u8], dst: Ipv4Addr) -> io::Result<()> {
+            assert_eq!(checksum(packet), 0, "bad checksum");
+            self.sent.lock().unwrap().push((dst, Instant::now()));
+            if self.alive.contains(&dst) {
+                let ident = u16::from_be_bytes([packet[4], packet[5]]);
+                let seq = u16::from_be_bytes([packet[6], packet[7]]);
+                self.push_reply(dst, ident, seq);
+            }
+            Ok(())
+        }
+
+        fn recv_from(&self, buf: &mut [u8], timeout: Duration) -> io::Result<Option<(usize, Ipv4Addr)>> {
+            match self.replies.lock().unwrap().pop_front() {
+                Some((packet, src)) => {
+                    buf[..packet.len()].copy_from_slice(&packet);
+                    Ok(Some((packet.len(), src)))
+                }
+                None => {
+                    std::thread::sleep(timeout);
+                    Ok(None)
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_echo_request_round_trip() {
+        let packet = echo_request(0x1234, 7);
+        assert_eq!(packet[0], ECHO_REQUEST);
+        assert_eq!(checksum(&packet), 0);
+
+        let mut reply = packet.clone();
+        reply[0] = ECHO_REPLY;
+        assert_eq!(parse_echo_reply(&reply), Some((0x1234, 7)));
+        // Our own request echoed back on a raw socket is not a reply
+        assert_eq!(parse_echo_reply(&packet), None);
+    }
+
+    #[test]
+    fn test_subnet_hosts() {
+        let hosts = subnet_hosts(Ipv4Addr::new(10, 41, 0, 77), 29);
+        assert_eq!(hosts.first(), Some(&Ipv4Addr::new(10, 41, 0, 73)));
+        assert_eq!(hosts.len(), 6);
+        assert_eq!(subnet_hosts(Ipv4Addr::new(10, 41, 0, 9), 32), vec![Ipv4Addr::new(10, 41, 0, 9)]);
+    }
+
+    #[test]
+    fn test_sweep_collects_responders_at_rate() {
+        let alive = [Ipv4Addr::new(10, 41, 0, 2), Ipv4Addr::new(10, 41, 0, 5)];
+        let socket = MockRawSocket::new(&alive);
+        let targets: Vec<_> = (1..=5).map(|i| Ipv4Addr::new(10, 41, 0, i)).collect();
+
+        // Replies that aren't ours: another process's ping, and a host we
+        // didn't ask
+        socket.push_reply(Ipv4Addr::new(10, 41, 0, 3), std::process::id() as u16 ^ 0xffff, 0);
+        socket.push_reply(Ipv4Addr::new(10, 41, 0, 9), std::process::id() as u16, 0);
+
+        let result = sweep(&socket, &targets, 50, Duration::from_millis(20));
+        assert_eq!(result.sent, 5);
+        assert_eq!(result.responders, alive);
+
+        let sent = socket.sent.lock().unwrap();
+        assert_eq!(sent.iter().map(|(ip, _)| *ip).collect::<Vec<_>>(), targets);
+        // 50 per second: at least 20 ms between requests
+        let span = sent[4].1 - sent[0].1;
+        assert!(span >= Duration::from_millis(75), "sent too fast: {:?}", span);
+    }
+
+    #[tokio::test]
+    async fn test_sweep_loopback() {
+        // Needs CAP_NET_RAW or unprivileged ICMP; skip where neither is allowed
+        let Ok(socket) = open_socket(1) else {
+            return;
+        };
+        let result = sweep(&*socket, &[Ipv4Addr::LOCALHOST], 100, Duration::from_millis(500));
+        assert_eq!(result.responders, vec![Ipv4Addr::LOCALHOST]);
+
+        let config = IcmpSweep::new(Ipv4Addr::LOCALHOST, 32);
+        let sockets: Arc<dyn SocketProvider> = Arc::new(SystemSockets);
+        let (result, _) = sweep_subnet(&config, sockets.clone(), |_| false).await.unwrap();
+        assert_eq!(result.responders, vec![Ipv4Addr::LOCALHOST]);
+        let (result, _) = sweep_subnet(&config, sockets, |ip| ip.is_loopback()).await.unwrap();
+        assert_eq!((result.sent, result.excluded), (0, 1));
+    }
+}
@@ -2,14 +2,43 @@
 //!
 //! This crate provides multiple discovery methods:
 //! - LLDP (Link Layer Discovery Protocol) for physical port detection
+//! - LLDP transmission so switches can identify the dendrite host
 //! - ARP scanning for subnet enumeration
+//! - ICMP echo sweeps for routed subnets ARP can't reach
+//! - IPv6 neighbor discovery (NDP) for IPv6-only links
+//! - mDNS/DNS-SD browsing for hosts that advertise a service
 //! - MCUmgr port probing for device verification
+//! - Per-device heartbeat checks (ARP, ICMP, SMP echo, TCP connect), with
+//!   backoff for devices that stop answering
+//! - Raw sockets opened up front so the daemon can drop CAP_NET_RAW
+//! - A device registry kept by the scanner or handed to the daemon's state task
+//! - CAN bus node identification over SocketCAN (`socketcan` feature)
 
 pub mod arp;
+pub mod can;
+pub mod heartbeat;
+pub mod icmp;
 pub mod lldp;
+pub mod lldp_tx;
+pub mod mdns;
+pub mod ndp;
+pub mod net;
 pub mod probe;
+pub mod registry;
 pub mod scanner;
+pub mod schedule;
+pub mod sockets;
 
+pub use can::{CanDiscoveryConfig, CanIdentify, CanNode};
+pub use icmp::{IcmpSweep, SocketKind};
+pub use lldp_tx::{LldpTransmitter, LldpTxConfig};
+pub use mdns::MdnsService;
+pub use ndp::Ipv6Neighbor;
+pub use net::{IpNet, IpNetError};
+pub use registry::{LocalRegistry, Registry, RegistryEdit, RegistrySnapshot, RegistryStore};
 pub use scanner::{
-    DeviceOverride, DiscoveryEvent, DiscoveryScanner, ParentConfig, ScannerConfig,
+    parse_cidr, DeviceOverride, DiscoveryEvent, DiscoveryMethods, DiscoveryScanner, MethodStats, ParentConfig,
+    RemoveMode, ScanStats, ScannerConfig,
 };
+pub use schedule::{Backoff, HeartbeatSchedule, ScheduleTiming};
+pub use sockets::{NdpSockets, PreopenedSockets, SocketProvider, SocketStatus, SystemSockets};
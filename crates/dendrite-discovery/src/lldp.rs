@@ -1,12 +1,24 @@
 //! LLDP (Link Layer Discovery Protocol) parsing for physical port detection
 //!
 //! LLDP allows discovery of which physical switch port a device is connected to.
+//!
+//! Every LLDPDU carries a time-to-live. [`NeighborTable`] keeps each
+//! neighbor until its TTL runs out without a refresh, so an unplugged device
+//! is noticed within one TTL rather than at the next full scan.
 
 use anyhow::Result;
 use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::process::Command;
+use std::time::{Duration, Instant};
 use tracing::{debug, info, warn};
 
+use crate::lldp_tx::{
+    ADDRESS_FAMILY_IPV4, ADDRESS_FAMILY_IPV6, CHASSIS_ID_SUBTYPE_MAC, LLDP_ETHERTYPE, PORT_ID_SUBTYPE_MAC,
+    TLV_CHASSIS_ID, TLV_END, TLV_MANAGEMENT_ADDRESS, TLV_PORT_DESCRIPTION, TLV_PORT_ID, TLV_SYSTEM_DESCRIPTION,
+    TLV_SYSTEM_NAME, TLV_TTL,
+};
+
 /// LLDP neighbor information
 #[derive(Debug, Clone)]
 pub struct LldpNeighbor {
@@ -24,6 +36,8 @@ pub struct LldpNeighbor {
     pub system_desc: Option<String>,
     /// Management addresses
     pub mgmt_addresses: Vec<String>,
+    /// How long the neighbor's information stays valid (None if not reported)
+    pub ttl: Option<Duration>,
 }
 
 /// Check if lldpd is running
@@ -120,9 +134,181 @@ fn build_neighbor(interface: &str, data: &HashMap<String, String>) -> Option<Lld
             .filter(|(k, _)| k.starts_with("chassis.mgmt-ip"))
             .map(|(_, v)| v.clone())
             .collect(),
+        // lldpd reports the TTL under the port or, in older versions, the chassis
+        ttl: ["port.ttl", "ttl.ttl", "chassis.ttl"]
+            .iter()
+            .find_map(|key| data.get(*key)?.trim().parse().ok())
+            .map(Duration::from_secs),
     })
 }
 
+/// Parse a received LLDP Ethernet frame
+pub fn parse_frame(local_interface: &str, frame: &[u8]) -> Option<LldpNeighbor> {
+    let ethertype = u16::from_be_bytes([*frame.get(12)?, *frame.get(13)?]);
+    if ethertype != LLDP_ETHERTYPE {
+        return None;
+    }
+    parse_lldpdu(local_interface, &frame[14..])
+}
+
+/// Parse an LLDPDU (TLVs only, no Ethernet header)
+///
+/// Returns None unless the mandatory chassis ID, port ID and TTL TLVs are present.
+pub fn parse_lldpdu(local_interface: &str, lldpdu: &[u8]) -> Option<LldpNeighbor> {
+    let mut chassis_id = None;
+    let mut port_id = None;
+    let mut ttl = None;
+    let mut neighbor = LldpNeighbor {
+        local_interface: local_interface.to_string(),
+        chassis_id: String::new(),
+        port_id: String::new(),
+        port_desc: None,
+        system_name: None,
+        system_desc: None,
+        mgmt_addresses: Vec::new(),
+        ttl: None,
+    };
+
+    let mut rest = lldpdu;
+    while rest.len() >= 2 {
+        let header = u16::from_be_bytes([rest[0], rest[1]]);
+        let (tlv_type, len) = ((header >> 9) as u8, (header & 0x01ff) as usize);
+        let value = rest.get(2..2 + len)?;
+        rest = &rest[2 + len..];
+
+        match tlv_type {
+            TLV_END => break,
+            TLV_CHASSIS_ID => chassis_id = id_value(value, CHASSIS_ID_SUBTYPE_MAC),
+            TLV_PORT_ID => port_id = id_value(value, PORT_ID_SUBTYPE_MAC),
+            TLV_TTL if len >= 2 => ttl = Some(u16::from_be_bytes([value[0], value[1]])),
+            TLV_PORT_DESCRIPTION => neighbor.port_desc = Some(String::from_utf8_lossy(value).into_owned()),
+            TLV_SYSTEM_NAME => neighbor.system_name = Some(String::from_utf8_lossy(value).into_owned()),
+            TLV_SYSTEM_DESCRIPTION => neighbor.system_desc = Some(String::from_utf8_lossy(value).into_owned()),
+            TLV_MANAGEMENT_ADDRESS => neighbor.mgmt_addresses.extend(management_address(value)),
+            _ => {}
+        }
+    }
+
+    neighbor.chassis_id = chassis_id?;
+    neighbor.port_id = port_id?;
+    neighbor.ttl = Some(Duration::from_secs(ttl?.into()));
+    Some(neighbor)
+}
+
+/// Chassis or port ID: a MAC when the subtype says so, text otherwise
+fn id_value(value: &[u8], mac_subtype: u8) -> Option<String> {
+    let (&subtype, id) = value.split_first()?;
+    if subtype == mac_subtype && id.len() == 6 {
+        Some(id.iter().map(|b| format!("{b:02x}")).collect::<Vec<_>>().join(":"))
+    } else {
+        Some(String::from_utf8_lossy(id).into_owned())
+    }
+}
+
+fn management_address(value: &[u8]) -> Option<String> {
+    let len = *value.first()? as usize;
+    let (&family, octets) = value.get(1..len + 1)?.split_first()?;
+    let addr = match family {
+        ADDRESS_FAMILY_IPV4 => IpAddr::V4(Ipv4Addr::from(<[u8; 4]>::try_from(octets).ok()?)),
+        ADDRESS_FAMILY_IPV6 => IpAddr::V6(Ipv6Addr::from(<[u8; 16]>::try_from(octets).ok()?)),
+        _ => return None,
+    };
+    Some(addr.to_string())
+}
+
+/// LLDP neighbors with the time each one's advertised TTL runs out
+///
+/// Neighbors are keyed by local interface and chassis ID. Neighbors that
+/// didn't report a TTL never expire; a TTL of zero (a shutdown LLDPDU)
+/// expires the neighbor at once.
+#[derive(Debug, Default)]
+pub struct NeighborTable {
+    neighbors: HashMap<(String, String), TableEntry>,
+}
+
+#[derive(Debug)]
+struct TableEntry {
+    neighbor: LldpNeighbor,
+    refreshed: Instant,
+    expires: Option<Instant>,
+}
+
+/// A neighbor in the table, as of some instant
+#[derive(Debug, Clone)]
+pub struct NeighborEntry {
+    pub neighbor: LldpNeighbor,
+    /// Time since the neighbor was last seen
+    pub age: Duration,
+    /// Time left until its TTL runs out (None if it never expires)
+    pub expires_in: Option<Duration>,
+}
+
+impl NeighborTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a neighbor seen at `now`, refreshing its expiry
+    ///
+    /// Returns whether the neighbor is new or moved to another port or
+    /// system name; a plain refresh returns false.
+    pub fn observe(&mut self, neighbor: LldpNeighbor, now: Instant) -> bool {
+        let expires = neighbor.ttl.map(|ttl| now + ttl);
+        let key = (neighbor.local_interface.clone(), neighbor.chassis_id.clone());
+        let changed = self.neighbors.get(&key).is_none_or(|entry| {
+            entry.neighbor.port_id != neighbor.port_id || entry.neighbor.system_name != neighbor.system_name
+        });
+        self.neighbors.insert(key, TableEntry { neighbor, refreshed: now, expires });
+        changed
+    }
+
+    /// Remove and return the neighbors whose TTL has run out by `now`
+    pub fn expire(&mut self, now: Instant) -> Vec<LldpNeighbor> {
+        let expired: Vec<_> = self
+            .neighbors
+            .iter()
+            .filter(|(_, entry)| entry.expires.is_some_and(|at| at <= now))
+            .map(|(key, _)| key.clone())
+            .collect();
+        expired
+            .into_iter()
+            .filter_map(|key| self.neighbors.remove(&key))
+            .map(|entry| entry.neighbor)
+            .collect()
+    }
+
+    /// Neighbors currently known
+    pub fn neighbors(&self) -> Vec<LldpNeighbor> {
+        self.neighbors.values().map(|entry| entry.neighbor.clone()).collect()
+    }
+
+    /// Neighbors currently known with their age and time left at `now`,
+    /// by local interface then port ID
+    pub fn entries(&self, now: Instant) -> Vec<NeighborEntry> {
+        let mut entries: Vec<_> = self
+            .neighbors
+            .values()
+            .map(|entry| NeighborEntry {
+                neighbor: entry.neighbor.clone(),
+                age: now.saturating_duration_since(entry.refreshed),
+                expires_in: entry.expires.map(|at| at.saturating_duration_since(now)),
+            })
+            .collect();
+        entries.sort_by(|a, b| {
+            (&a.neighbor.local_interface, &a.neighbor.port_id).cmp(&(&b.neighbor.local_interface, &b.neighbor.port_id))
+        });
+        entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.neighbors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.neighbors.is_empty()
+    }
+}
+
 /// Extract port number from port ID (if numeric)
 pub fn parse_port_number(port_id: &str) -> Option<u8> {
     // Port ID might be "1", "port1", "eth1", "swp1", etc.
@@ -134,16 +320,8 @@ pub fn parse_port_number(port_id: &str) -> Option<u8> {
 pub fn find_port_for_mac(neighbors: &[LldpNeighbor], mac: &str) -> Option<u8> {
     // This would require the neighbor to advertise its MAC in chassis ID
     // Common format: chassis ID is MAC address
-    let mac_normalized = mac.to_lowercase().replace(':', "").replace('-', "");
-
     for neighbor in neighbors {
-        let chassis_normalized = neighbor
-            .chassis_id
-            .to_lowercase()
-            .replace(':', "")
-            .replace('-', "");
-
-        if chassis_normalized == mac_normalized {
+        if neighbor.has_chassis_mac(mac) {
             return parse_port_number(&neighbor.port_id);
         }
     }
@@ -151,6 +329,14 @@ pub fn find_port_for_mac(neighbors: &[LldpNeighbor], mac: &str) -> Option<u8> {
     None
 }
 
+impl LldpNeighbor {
+    /// Whether the chassis ID is `mac`, whatever the separators and case
+    pub fn has_chassis_mac(&self, mac: &str) -> bool {
+        let normalize = |s: &str| s.to_lowercase().replace([':', '-'], "");
+        normalize(&self.chassis_id) == normalize(mac)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -181,4 +367,66 @@ lldp.eth1.port.id=2
         assert_eq!(neighbors[1].local_interface, "eth1");
         assert_eq!(neighbors[1].port_id, "2");
     }
+
+    #[test]
+    fn test_parse_lldpcli_ttl() {
+        let output = "lldp.eth0.chassis.id=aa:bb:cc:dd:ee:ff\nlldp.eth0.port.id=1\nlldp.eth0.port.ttl=120\n";
+        let neighbors = parse_lldpcli_keyvalue(output).unwrap();
+        assert_eq!(neighbors[0].ttl, Some(Duration::from_secs(120)));
+    }
+
+    fn frame(ttl: u16) -> Vec<u8> {
+        crate::lldp_tx::encode_frame(
+            [0x02, 0x00, 0x00, 0x00, 0x00, 0x01],
+            &crate::lldp_tx::LldpAnnouncement {
+                chassis_mac: [0x02, 0x00, 0x00, 0x00, 0x00, 0x01],
+                port_id: "port3".to_string(),
+                ttl,
+                system_name: "node".to_string(),
+                system_description: "dendrite v0.1".to_string(),
+                management_address: Some((IpAddr::V4(Ipv4Addr::new(192, 0, 2, 7)), 2)),
+            },
+        )
+    }
+
+    #[test]
+    fn test_parse_frame_round_trip() {
+        let neighbor = parse_frame("eth0", &frame(120)).unwrap();
+        assert_eq!(neighbor.chassis_id, "02:00:00:00:00:01");
+        assert_eq!(neighbor.port_id, "port3");
+        assert_eq!(neighbor.ttl, Some(Duration::from_secs(120)));
+        assert_eq!(neighbor.system_name.as_deref(), Some("node"));
+        assert_eq!(neighbor.mgmt_addresses, vec!["192.0.2.7".to_string()]);
+        assert_eq!(parse_port_number(&neighbor.port_id), Some(3));
+
+        let mut not_lldp = frame(120);
+        not_lldp[13] = 0x00;
+        assert!(parse_frame("eth0", &not_lldp).is_none());
+        assert!(parse_frame("eth0", &frame(120)[..20]).is_none());
+    }
+
+    #[test]
+    fn test_neighbor_expires_after_ttl_without_refresh() {
+        let mut table = NeighborTable::new();
+        let start = Instant::now();
+
+        // Two frames with a 2 s TTL, one second apart; only the first is news
+        assert!(table.observe(parse_frame("eth0", &frame(2)).unwrap(), start));
+        assert!(!table.observe(parse_frame("eth0", &frame(2)).unwrap(), start + Duration::from_secs(1)));
+        assert_eq!(table.len(), 1);
+        let entries = table.entries(start + Duration::from_millis(1500));
+        assert_eq!(entries[0].age, Duration::from_millis(500));
+        assert_eq!(entries[0].expires_in, Some(Duration::from_millis(1500)));
+
+        // The refresh pushed the expiry out to 3 s
+        assert!(table.expire(start + Duration::from_millis(2500)).is_empty());
+        let expired = table.expire(start + Duration::from_secs(3));
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].chassis_id, "02:00:00:00:00:01");
+        assert!(table.is_empty());
+
+        // A shutdown LLDPDU expires the neighbor straight away
+        assert!(table.observe(parse_frame("eth0", &frame(0)).unwrap(), start));
+        assert_eq!(table.expire(start).len(), 1);
+    }
 }
@@ -0,0 +1,332 @@
+//! LLDP transmitter so managed switches can see the dendrite host
+//!
+//! Periodically sends LLDPDU frames (IEEE 802.1AB) on the configured interfaces
+//! using a raw datalink channel from a `SocketProvider`. The receive side is
+//! handled by `lldp`.
+
+use anyhow::Result;
+use pnet::datalink::{self, NetworkInterface};
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::sync::Arc;
+use tokio::time::{interval, Duration};
+use tracing::{debug, info, warn};
+
+use crate::sockets::{SocketProvider, SystemSockets};
+
+/// LLDP nearest-bridge multicast address
+pub const LLDP_MULTICAST: [u8; 6] = [0x01, 0x80, 0xc2, 0x00, 0x00, 0x0e];
+/// LLDP EtherType
+pub const LLDP_ETHERTYPE: u16 = 0x88cc;
+
+/// Minimum Ethernet frame length without FCS
+const MIN_FRAME_LEN: usize = 60;
+
+// TLV types
+pub(crate) const TLV_END: u8 = 0;
+pub(crate) const TLV_CHASSIS_ID: u8 = 1;
+pub(crate) const TLV_PORT_ID: u8 = 2;
+pub(crate) const TLV_TTL: u8 = 3;
+pub(crate) const TLV_PORT_DESCRIPTION: u8 = 4;
+pub(crate) const TLV_SYSTEM_NAME: u8 = 5;
+pub(crate) const TLV_SYSTEM_DESCRIPTION: u8 = 6;
+pub(crate) const TLV_MANAGEMENT_ADDRESS: u8 = 8;
+
+// TLV subtypes
+pub(crate) const CHASSIS_ID_SUBTYPE_MAC: u8 = 4;
+pub(crate) const PORT_ID_SUBTYPE_MAC: u8 = 3;
+const PORT_ID_SUBTYPE_INTERFACE_NAME: u8 = 5;
+pub(crate) const ADDRESS_FAMILY_IPV4: u8 = 1;
+pub(crate) const ADDRESS_FAMILY_IPV6: u8 = 2;
+const INTERFACE_SUBTYPE_IFINDEX: u8 = 2;
+
+/// LLDP transmitter configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LldpTxConfig {
+    /// Seconds between announcements
+    pub interval_secs: u64,
+    /// Interfaces to announce on (empty = all active non-loopback interfaces)
+    pub interfaces: Vec<String>,
+    /// System name to announce (defaults to the hostname)
+    pub system_name: Option<String>,
+}
+
+impl Default for LldpTxConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: 30,
+            interfaces: Vec::new(),
+            system_name: None,
+        }
+    }
+}
+
+/// Contents of a single LLDPDU
+#[derive(Debug, Clone, PartialEq)]
+pub struct LldpAnnouncement {
+    /// Chassis ID (MAC address of the host)
+    pub chassis_mac: [u8; 6],
+    /// Port ID (interface name)
+    pub port_id: String,
+    /// Time-to-live in seconds
+    pub ttl: u16,
+    /// System name
+    pub system_name: String,
+    /// System description (e.g., "dendrite v0.1")
+    pub system_description: String,
+    /// Management address and the ifIndex it belongs to
+    pub management_address: Option<(IpAddr, u32)>,
+}
+
+/// System description announced by this host
+pub fn system_description() -> String {
+    format!("dendrite v{}", env!("CARGO_PKG_VERSION"))
+}
+
+/// Append a TLV with the 7-bit type / 9-bit length header
+fn push_tlv(buf: &mut Vec<u8>, tlv_type: u8, value: &[u8]) {
+    let len = value.len().min(511) as u16;
+    let header = ((tlv_type as u16) << 9) | len;
+    buf.extend_from_slice(&header.to_be_bytes());
+    buf.extend_from_slice(&value[..len as usize]);
+}
+
+/// Encode the LLDPDU (TLVs only, no Ethernet header)
+pub fn encode_lldpdu(announcement: &LldpAnnouncement) -> Vec<u8> {
+    let mut buf = Vec::new();
+
+    let mut chassis = vec![CHASSIS_ID_SUBTYPE_MAC];
+    chassis.extend_from_slice(&announcement.chassis_mac);
+    push_tlv(&mut buf, TLV_CHASSIS_ID, &chassis);
+
+    let mut port = vec![PORT_ID_SUBTYPE_INTERFACE_NAME];
+    port.extend_from_slice(announcement.port_id.as_bytes());
+    push_tlv(&mut buf, TLV_PORT_ID, &port);
+
+    push_tlv(&mut buf, TLV_TTL, &announcement.ttl.to_be_bytes());
+    push_tlv(&mut buf, TLV_SYSTEM_NAME, announcement.system_name.as_bytes());
+    push_tlv(&mut buf, TLV_SYSTEM_DESCRIPTION, announcement.system_description.as_bytes());
+
+    if let Some((addr, if_index)) = announcement.management_address {
+        let (family, octets) = match addr {
+            IpAddr::V4(v4) => (ADDRESS_FAMILY_IPV4, v4.octets().to_vec()),
+            IpAddr::V6(v6) => (ADDRESS_FAMILY_IPV6, v6.octets().to_vec()),
+        };
+        let mut mgmt = vec![(octets.len() + 1) as u8, family];
+        mgmt.extend_from_slice(&octets);
+        mgmt.push(INTERFACE_SUBTYPE_IFINDEX);
+        mgmt.extend_from_slice(&if_index.to_be_bytes());
+        mgmt.push(0); // No OID
+        push_tlv(&mut buf, TLV_MANAGEMENT_ADDRESS, &mgmt);
+    }
+
+    push_tlv(&mut buf, TLV_END, &[]);
+    buf
+}
+
+/// Encode a complete Ethernet frame carrying the LLDPDU (padded to minimum length)
+pub fn encode_frame(src_mac: [u8; 6], announcement: &LldpAnnouncement) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(MIN_FRAME_LEN);
+    frame.extend_from_slice(&LLDP_MULTICAST);
+    frame.extend_from_slice(&src_mac);
+    frame.extend_from_slice(&LLDP_ETHERTYPE.to_be_bytes());
+    frame.extend_from_slice(&encode_lldpdu(announcement));
+    if frame.len() < MIN_FRAME_LEN {
+        frame.resize(MIN_FRAME_LEN, 0);
+    }
+    frame
+}
+
+/// Read the host name, falling back to "dendrite"
+fn hostname() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .map(|s| s.trim().to_string())
+        .ok()
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "dendrite".to_string())
+}
+
+/// Interfaces selected for announcements
+pub(crate) fn select_interfaces(config: &LldpTxConfig) -> Vec<NetworkInterface> {
+    let mut selected: Vec<NetworkInterface> = datalink::interfaces()
+        .into_iter()
+        .filter(|iface| {
+            if config.interfaces.is_empty() {
+                iface.is_up() && !iface.is_loopback() && iface.mac.is_some()
+            } else {
+                config.interfaces.contains(&iface.name)
+            }
+        })
+        .collect();
+    selected.sort_by(|a, b| a.name.cmp(&b.name));
+    selected
+}
+
+/// Send one announcement on an interface
+fn send_on_interface(
+    sockets: &dyn SocketProvider,
+    iface: &NetworkInterface,
+    chassis_mac: [u8; 6],
+    config: &LldpTxConfig,
+    system_name: &str,
+) -> Result<()> {
+    let Some(mac) = iface.mac else {
+        anyhow::bail!("interface {} has no MAC address", iface.name);
+    };
+
+    let announcement = LldpAnnouncement {
+        chassis_mac,
+        port_id: iface.name.clone(),
+        // Standard recommendation: TTL = 4 x transmit interval
+        ttl: (config.interval_secs.saturating_mul(4)).min(u16::MAX as u64) as u16,
+        system_name: system_name.to_string(),
+        system_description: system_description(),
+        management_address: iface
+            .ips
+            .iter()
+            .find(|ip| ip.is_ipv4())
+            .or_else(|| iface.ips.first())
+            .map(|ip| (ip.ip(), iface.index)),
+    };
+    let frame = encode_frame(mac.octets(), &announcement);
+
+    sockets.send_frame(iface, &frame)?;
+    Ok(())
+}
+
+/// Periodic LLDP announcer
+pub struct LldpTransmitter {
+    config: LldpTxConfig,
+    sockets: Arc<dyn SocketProvider>,
+}
+
+impl LldpTransmitter {
+    /// Announcer that opens its datalink channels when it sends
+    pub fn new(config: LldpTxConfig) -> Self {
+        Self::with_sockets(config, Arc::new(SystemSockets))
+    }
+
+    /// Announcer that sends through `sockets` (e.g. channels opened before
+    /// privileges were dropped)
+    pub fn with_sockets(config: LldpTxConfig, sockets: Arc<dyn SocketProvider>) -> Self {
+        Self { config, sockets }
+    }
+
+    /// Send one round of announcements on all selected interfaces
+    pub async fn announce_once(&self) -> Result<usize> {
+        let config = self.config.clone();
+        let sockets = self.sockets.clone();
+        tokio::task::spawn_blocking(move || {
+            let interfaces = select_interfaces(&config);
+            let Some(chassis_mac) = interfaces.iter().find_map(|i| i.mac).map(|m| m.octets()) else {
+                debug!("No interfaces with a MAC address for LLDP announcements");
+                return Ok(0);
+            };
+            let system_name = config.system_name.clone().unwrap_or_else(hostname);
+
+            let mut sent = 0;
+            for iface in &interfaces {
+                match send_on_interface(&*sockets, iface, chassis_mac, &config, &system_name) {
+                    Ok(()) => sent += 1,
+                    Err(e) => warn!(interface = %iface.name, error = %e, "LLDP transmit failed"),
+                }
+            }
+            Ok(sent)
+        })
+        .await?
+    }
+
+    /// Announce forever at the configured interval
+    pub async fn run(&self) -> Result<()> {
+        info!(
+            interval = self.config.interval_secs,
+            interfaces = ?self.config.interfaces,
+            "LLDP transmitter started"
+        );
+
+        let mut ticker = interval(Duration::from_secs(self.config.interval_secs.max(1)));
+        loop {
+            ticker.tick().await;
+            match self.announce_once().await {
+                Ok(sent) => debug!(interfaces = sent, "Sent LLDP announcements"),
+                Err(e) => warn!(error = %e, "LLDP announcement round failed"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    fn reference_announcement() -> LldpAnnouncement {
+        LldpAnnouncement {
+            chassis_mac: [0x02, 0x00, 0x00, 0x00, 0x00, 0x01],
+            port_id: "eth0".to_string(),
+            ttl: 120,
+            system_name: "host".to_string(),
+            system_description: "dendrite v0.1".to_string(),
+            management_address: Some((IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)), 2)),
+        }
+    }
+
+    #[test]
+    fn test_encode_frame_matches_reference_dump() {
+        #[rustfmt::skip]
+        let expected: [u8; 71] = [
+            // Ethernet header: dst, src, ethertype
+            0x01, 0x80, 0xc2, 0x00, 0x00, 0x0e,
+            0x02, 0x00, 0x00, 0x00, 0x00, 0x01,
+            0x88, 0xcc,
+            // Chassis ID (MAC)
+            0x02, 0x07, 0x04, 0x02, 0x00, 0x00, 0x00, 0x00, 0x01,
+            // Port ID (interface name "eth0")
+            0x04, 0x05, 0x05, 0x65, 0x74, 0x68, 0x30,
+            // TTL 120
+            0x06, 0x02, 0x00, 0x78,
+            // System name "host"
+            0x0a, 0x04, 0x68, 0x6f, 0x73, 0x74,
+            // System description "dendrite v0.1"
+            0x0c, 0x0d, 0x64, 0x65, 0x6e, 0x64, 0x72, 0x69, 0x74, 0x65, 0x20, 0x76, 0x30, 0x2e, 0x31,
+            // Management address 192.0.2.1, ifIndex 2, no OID
+            0x10, 0x0c, 0x05, 0x01, 0xc0, 0x00, 0x02, 0x01, 0x02, 0x00, 0x00, 0x00, 0x02, 0x00,
+            // End of LLDPDU
+            0x00, 0x00,
+        ];
+
+        let frame = encode_frame([0x02, 0x00, 0x00, 0x00, 0x00, 0x01], &reference_announcement());
+        assert_eq!(frame, expected.to_vec());
+    }
+
+    #[test]
+    fn test_short_frame_is_padded() {
+        let announcement = LldpAnnouncement {
+            chassis_mac: [0; 6],
+            port_id: "e".to_string(),
+            ttl: 0,
+            system_name: String::new(),
+            system_description: String::new(),
+            management_address: None,
+        };
+        let frame = encode_frame([0; 6], &announcement);
+        assert_eq!(frame.len(), MIN_FRAME_LEN);
+        // End TLV precedes padding
+        let pdu = encode_lldpdu(&announcement);
+        assert_eq!(&pdu[pdu.len() - 2..], &[0x00, 0x00]);
+    }
+
+    #[test]
+    fn test_tlv_header_packs_type_and_length() {
+        let mut buf = Vec::new();
+        push_tlv(&mut buf, TLV_SYSTEM_DESCRIPTION, &[0u8; 300]);
+        // type 6 << 9 | 300 = 0x0d2c
+        assert_eq!(&buf[..2], &[0x0d, 0x2c]);
+        assert_eq!(buf.len(), 302);
+    }
+
+    #[test]
+    fn test_system_description_format() {
+        assert!(system_description().starts_with("dendrite v"));
+    }
+}
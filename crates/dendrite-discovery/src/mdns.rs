@@ -0,0 +1,432 @@
+//! mDNS / DNS-SD browsing for hosts that advertise themselves
+//!
+//! Companion computers (Jetson, Raspberry Pi) running avahi can announce a
+//! `_dendrite._udp` or `_mcumgr._udp` service. A browse sends one query for
+//! the PTR records of each service type from an ephemeral port, so
+//! responders answer by unicast (RFC 6762 section 6.7) and nothing has to
+//! bind port 5353 next to avahi. The SRV, TXT and A records responders attach
+//! resolve each instance to a hostname, address and port.
+//!
+//! A TXT record with `hwid=` (and optionally `board=`, `name=`) carries the
+//! device's identity, so it can be registered without an MCUmgr probe.
+
+use anyhow::Result;
+use dendrite_core::{Device, DeviceId, DeviceStatus, DiscoveryMethod};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr};
+use tokio::net::UdpSocket;
+use tokio::time::{timeout, Duration, Instant};
+use tracing::debug;
+
+/// mDNS multicast group
+pub const MDNS_ADDR: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+/// mDNS port
+pub const MDNS_PORT: u16 = 5353;
+
+/// Service types browsed for unless configured otherwise
+pub const DEFAULT_SERVICE_TYPES: [&str; 2] = ["_dendrite._udp", "_mcumgr._udp"];
+
+/// How long a browse collects answers
+pub const BROWSE_WINDOW: Duration = Duration::from_millis(1500);
+
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_TXT: u16 = 16;
+const TYPE_SRV: u16 = 33;
+const CLASS_IN: u16 = 1;
+
+/// A resolved service instance
+#[derive(Debug, Clone, PartialEq)]
+pub struct MdnsService {
+    /// Instance name without the service type (e.g., "jetson-01")
+    pub instance: String,
+    /// Service type it was found under, as configured (e.g., "_dendrite._udp")
+    pub service_type: String,
+    /// Host the service runs on (e.g., "jetson-01.local")
+    pub hostname: String,
+    pub ip: Ipv4Addr,
+    pub port: u16,
+    /// TXT record entries; keys are lowercase
+    pub txt: BTreeMap<String, String>,
+}
+
+impl MdnsService {
+    /// Hardware ID from the TXT record
+    pub fn hwid(&self) -> Option<&str> {
+        self.txt_value("hwid")
+    }
+
+    /// Board from the TXT record
+    pub fn board(&self) -> Option<&str> {
+        self.txt_value("board")
+    }
+
+    fn txt_value(&self, key: &str) -> Option<&str> {
+        self.txt.get(key).map(String::as_str).filter(|v| !v.is_empty())
+    }
+
+    /// Device for an advertisement that carries its hwid (None otherwise,
+    /// and the host is probed for MCUmgr instead)
+    pub fn to_device(&self) -> Option<Device> {
+        let hwid = self.hwid()?;
+        let name = self.txt_value("name").unwrap_or(self.instance.as_str()).to_string();
+        let mut device = Device::new(DeviceId::from_hwid(hwid), name, IpAddr::V4(self.ip), self.port);
+        device.status = DeviceStatus::Online;
+        device.discovery.discovery_method = DiscoveryMethod::Mdns;
+        device.discovery.hostname = Some(self.hostname.clone());
+        device.info.board = self.board().map(str::to_string);
+        Some(device)
+    }
+}
+
+/// Whether `service_type` looks like a DNS-SD service type (`_name._udp`
+/// or `_name._tcp`, optionally followed by `.local`)
+pub fn is_service_type(service_type: &str) -> bool {
+    let domain = service_domain(service_type);
+    let labels: Vec<&str> = domain.split('.').collect();
+    matches!(labels.as_slice(), [name, proto, "local"]
+        if name.len() > 1 && name.len() <= 63 && name.starts_with('_') && matches!(*proto, "_udp" | "_tcp"))
+}
+
+/// Whether two hostnames name the same host, ignoring case, a trailing dot
+/// and the `.local` domain
+pub fn same_hostname(a: &str, b: &str) -> bool {
+    let normalize = |host: &str| {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        host.strip_suffix(".local").map(str::to_string).unwrap_or(host)
+    };
+    normalize(a) == normalize(b)
+}
+
+/// Browse for instances of `service_types` for `window`
+pub async fn browse(service_types: &[String], window: Duration) -> Result<Vec<MdnsService>> {
+    if service_types.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+    socket.set_multicast_ttl_v4(255)?;
+    socket.send_to(&build_query(service_types), (MDNS_ADDR, MDNS_PORT)).await?;
+
+    let deadline = Instant::now() + window;
+    let mut records = Vec::new();
+    let mut buf = [0u8; 9000];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        match timeout(remaining, socket.recv_from(&mut buf)).await {
+            Ok(Ok((len, from))) => match parse_records(&buf[..len]) {
+                Some(parsed) => records.extend(parsed),
+                None => debug!(from = %from, "Ignoring malformed mDNS response"),
+            },
+            Ok(Err(e)) => return Err(e.into()),
+            Err(_) => break,
+        }
+    }
+
+    let services = collect_services(&records, service_types);
+    debug!("Found {} mDNS services", services.len());
+    Ok(services)
+}
+
+/// `_dendrite._udp` -> `_dendrite._udp.local`
+fn service_domain(service_type: &str) -> String {
+    let service_type = service_type.trim().trim_end_matches('.');
+    if service_type.to_ascii_lowercase().ends_with(".local") {
+        service_type.to_string()
+    } else {
+        format!("{}.local", service_type)
+    }
+}
+
+/// DNS query for the PTR records of each service type
+fn build_query(service_types: &[String]) -> Vec<u8> {
+    let mut packet = Vec::new();
+    packet.extend_from_slice(&0u16.to_be_bytes()); // ID, echoed in unicast answers
+    packet.extend_from_slice(&0u16.to_be_bytes()); // Standard query
+    packet.extend_from_slice(&(service_types.len() as u16).to_be_bytes());
+    packet.extend_from_slice(&[0; 6]); // No answer, authority or additional records
+    for service_type in service_types {
+        for label in service_domain(service_type).split('.').filter(|l| !l.is_empty()) {
+            let label = &label.as_bytes()[..label.len().min(63)];
+            packet.push(label.len() as u8);
+            packet.extend_from_slice(label);
+        }
+        packet.push(0);
+        packet.extend_from_slice(&TYPE_PTR.to_be_bytes());
+        packet.extend_from_slice(&CLASS_IN.to_be_bytes());
+    }
+    packet
+}
+
+/// Resource record we care about
+#[derive(Debug, Clone, PartialEq)]
+enum Record {
+    Ptr { name: String, target: String },
+    Srv { name: String, port: u16, target: String },
+    Txt { name: String, entries: Vec<(String, String)> },
+    A { name: String, ip: Ipv4Addr },
+}
+
+/// Domain name at `start`, following compression pointers, and the offset
+/// just past it
+fn read_name(packet: &[u8], start: usize) -> Option<(String, usize)> {
+    let mut labels: Vec<String> = Vec::new();
+    let mut pos = start;
+    let mut end = None;
+    // Bounds pointer loops
+    for _ in 0..128 {
+        let len = *packet.get(pos)? as usize;
+        if len == 0 {
+            return Some((labels.join("."), end.unwrap_or(pos + 1)));
+        }
+        match len & 0xc0 {
+            0xc0 => {
+                if end.is_none() {
+                    end = Some(pos + 2);
+                }
+                pos = ((len & 0x3f) << 8) | *packet.get(pos + 1)? as usize;
+            }
+            0x00 => {
+                let label = packet.get(pos + 1..pos + 1 + len)?;
+                labels.push(String::from_utf8_lossy(label).into_owned());
+                pos += 1 + len;
+            }
+            _ => return None,
+        }
+    }
+    None
+}
+
+/// `key=value` strings of a TXT record; keys are lowercased
+fn parse_txt(data: &[u8]) -> Vec<(String, String)> {
+    let mut entries = Vec::new();
+    let mut pos = 0;
+    while let Some(&len) = data.get(pos) {
+        let Some(entry) = data.get(pos + 1..pos + 1 + len as usize) else {
+            break;
+        };
+        let entry = String::from_utf8_lossy(entry);
+        match entry.split_once('=') {
+            Some((key, value)) => entries.push((key.to_ascii_lowercase(), value.to_string())),
+            None if !entry.is_empty() => entries.push((entry.to_ascii_lowercase(), String::new())),
+            None => {}
+        }
+        pos += 1 + len as usize;
+    }
+    entries
+}
+
+/// PTR, SRV, TXT and A records of a response (None if it is malformed)
+fn parse_records(packet: &[u8]) -> Option<Vec<Record>> {
+    let u16_at = |pos: usize| packet.get(pos..pos + 2).map(|b| u16::from_be_bytes([b[0], b[1]]));
+    // Queries from other hosts arrive too
+    if u16_at(2)? & 0x8000 == 0 {
+        return Some(Vec::new());
+    }
+    let questions = u16_at(4)?;
+    let count = u16_at(6)? as usize + u16_at(8)? as usize + u16_at(10)? as usize;
+
+    let mut pos = 12;
+    for _ in 0..questions {
+        pos = read_name(packet, pos)?.1 + 4;
+    }
+    let mut records = Vec::new();
+    for _ in 0..count {
+        let (name, next) = read_name(packet, pos)?;
+        let record_type = u16_at(next)?;
+        let len = u16_at(next + 8)? as usize;
+        let data = next + 10;
+        let rdata = packet.get(data..data + len)?;
+        pos = data + len;
+        // The class is not checked: mDNS sets its top bit for cache flushes
+        let record = match record_type {
+            TYPE_PTR => Record::Ptr { name, target: read_name(packet, data)?.0 },
+            TYPE_SRV if len >= 7 => Record::Srv { name, port: u16_at(data + 4)?, target: read_name(packet, data + 6)?.0 },
+            TYPE_TXT => Record::Txt { name, entries: parse_txt(rdata) },
+            TYPE_A if len == 4 => Record::A { name, ip: Ipv4Addr::new(rdata[0], rdata[1], rdata[2], rdata[3]) },
+            _ => continue,
+        };
+        records.push(record);
+    }
+    Some(records)
+}
+
+/// Resolve the instances of each service type from records of any number
+/// of responses
+///
+/// Instances without an SRV record or an address for its host are left
+/// out. The first TXT value of a key wins (RFC 6763 section 6.4).
+fn collect_services(records: &[Record], service_types: &[String]) -> Vec<MdnsService> {
+    let key = |name: &str| name.trim_end_matches('.').to_ascii_lowercase();
+    let mut srv: HashMap<String, (u16, &str)> = HashMap::new();
+    let mut txt: HashMap<String, BTreeMap<String, String>> = HashMap::new();
+    let mut addresses: HashMap<String, Ipv4Addr> = HashMap::new();
+    for record in records {
+        match record {
+            Record::Srv { name, port, target } => {
+                srv.entry(key(name)).or_insert((*port, target.as_str()));
+            }
+            Record::Txt { name, entries } => {
+                let values = txt.entry(key(name)).or_default();
+                for (k, v) in entries {
+                    values.entry(k.clone()).or_insert_with(|| v.clone());
+                }
+            }
+            Record::A { name, ip } => {
+                addresses.entry(key(name)).or_insert(*ip);
+            }
+            Record::Ptr { .. } => {}
+        }
+    }
+
+    let mut seen = HashSet::new();
+    let mut services = Vec::new();
+    for service_type in service_types {
+        let domain = key(&service_domain(service_type));
+        for record in records {
+            let Record::Ptr { name, target } = record else {
+                continue;
+            };
+            let instance_key = key(target);
+            if key(name) != domain || !seen.insert(instance_key.clone()) {
+                continue;
+            }
+            let Some(&(port, host)) = srv.get(&instance_key) else {
+                debug!(instance = %target, "mDNS instance without SRV record");
+                continue;
+            };
+            let Some(&ip) = addresses.get(&key(host)) else {
+                debug!(instance = %target, host = %host, "mDNS instance without address");
+                continue;
+            };
+            let instance = match instance_key.strip_suffix(&format!(".{}", domain)) {
+                Some(stripped) => &target[..stripped.len()],
+                None => target.as_str(),
+            };
+            services.push(MdnsService {
+                instance: instance.to_string(),
+                service_type: service_type.trim().to_string(),
+                hostname: host.trim_end_matches('.').to_string(),
+                ip,
+                port,
+                txt: txt.get(&instance_key).cloned().unwrap_or_default(),
+            });
+        }
+    }
+    services
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name(packet: &mut Vec<u8>, name: &str) {
+        for label in name.split('.') {
+            packet.push(label.len() as u8);
+            packet.extend_from_slice(label.as_bytes());
+        }
+        packet.push(0);
+    }
+
+    fn record(packet: &mut Vec<u8>, owner: &[u8], record_type: u16, rdata: &[u8]) {
+        packet.extend_from_slice(owner);
+        packet.extend_from_slice(&record_type.to_be_bytes());
+        packet.extend_from_slice(&0x8001u16.to_be_bytes()); // IN, cache flush
+        packet.extend_from_slice(&120u32.to_be_bytes());
+        packet.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        packet.extend_from_slice(rdata);
+    }
+
+    /// Response the way avahi writes it, with compressed names
+    fn response() -> Vec<u8> {
+        let mut packet = vec![0x12, 0x34, 0x84, 0x00, 0, 0, 0, 4, 0, 0, 0, 0];
+        // PTR _dendrite._udp.local -> jetson-01._dendrite._udp.local
+        let service = packet.len() as u16;
+        let mut owner = Vec::new();
+        name(&mut owner, "_dendrite._udp.local");
+        let target_at = service + owner.len() as u16 + 10;
+        let mut target = vec![9];
+        target.extend_from_slice(b"jetson-01");
+        target.extend_from_slice(&(0xc000 | service).to_be_bytes());
+        record(&mut packet, &owner, TYPE_PTR, &target);
+
+        let instance = (0xc000 | target_at).to_be_bytes();
+        let mut srv = vec![0, 0, 0, 0, 0x05, 0x39];
+        name(&mut srv, "Jetson-01.local");
+        record(&mut packet, &instance, TYPE_SRV, &srv);
+
+        let mut txt = Vec::new();
+        for entry in ["hwid=0xdeadbeef", "Board=jetson_orin", "hwid=ignored", "flag"] {
+            txt.push(entry.len() as u8);
+            txt.extend_from_slice(entry.as_bytes());
+        }
+        record(&mut packet, &instance, TYPE_TXT, &txt);
+
+        let mut host = Vec::new();
+        name(&mut host, "jetson-01.local");
+        record(&mut packet, &host, TYPE_A, &[192, 168, 186, 40]);
+        packet
+    }
+
+    #[test]
+    fn test_build_query() {
+        let query = build_query(&["_dendrite._udp".to_string(), "_mcumgr._udp.local.".to_string()]);
+        assert_eq!(&query[..12], &[0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(read_name(&query, 12), Some(("_dendrite._udp.local".to_string(), 34)));
+        assert_eq!(&query[34..38], &[0, 12, 0, 1]);
+        assert_eq!(read_name(&query, 38).unwrap().0, "_mcumgr._udp.local");
+    }
+
+    #[test]
+    fn test_resolve_services() {
+        let records = parse_records(&response()).unwrap();
+        assert_eq!(records.len(), 4);
+
+        let types = vec!["_dendrite._udp".to_string(), "_mcumgr._udp".to_string()];
+        let services = collect_services(&records, &types);
+        assert_eq!(services.len(), 1);
+        let jetson = &services[0];
+        assert_eq!(jetson.instance, "jetson-01");
+        assert_eq!(jetson.service_type, "_dendrite._udp");
+        // The SRV target's case, and the A record matched regardless
+        assert_eq!(jetson.hostname, "Jetson-01.local");
+        assert_eq!(jetson.ip, Ipv4Addr::new(192, 168, 186, 40));
+        assert_eq!(jetson.port, 1337);
+        assert_eq!(jetson.hwid(), Some("0xdeadbeef"));
+        assert_eq!(jetson.board(), Some("jetson_orin"));
+        assert_eq!(jetson.txt["flag"], "");
+
+        let device = jetson.to_device().unwrap();
+        assert_eq!(device.id, DeviceId::from_hwid("0xdeadbeef"));
+        assert_eq!(device.name, "jetson-01");
+        assert_eq!(device.discovery.discovery_method, DiscoveryMethod::Mdns);
+        assert_eq!(device.info.board.as_deref(), Some("jetson_orin"));
+
+        // Without the A record the instance can't be reached
+        let unresolved: Vec<Record> = records.into_iter().filter(|r| !matches!(r, Record::A { .. })).collect();
+        assert!(collect_services(&unresolved, &types).is_empty());
+
+        // Truncated packets and pointer loops are rejected, queries skipped
+        let packet = response();
+        assert!(parse_records(&packet[..packet.len() - 2]).is_none());
+        assert_eq!(read_name(&[0xc0, 0x00], 0), None);
+        assert_eq!(parse_records(&build_query(&types)), Some(Vec::new()));
+    }
+
+    #[test]
+    fn test_service_types_and_hostnames() {
+        assert!(is_service_type("_dendrite._udp"));
+        assert!(is_service_type("_mcumgr._udp.local."));
+        assert!(is_service_type("_ssh._tcp"));
+        assert!(!is_service_type("dendrite._udp"));
+        assert!(!is_service_type("_dendrite"));
+        assert!(!is_service_type("_dendrite._sctp"));
+
+        assert!(same_hostname("jetson-01", "Jetson-01.local."));
+        assert!(same_hostname("pi.local", "pi"));
+        assert!(!same_hostname("pi", "pi2.local"));
+    }
+}
@@ -0,0 +1,316 @@
+//! IPv6 neighbor discovery (NDP) for links without IPv4
+//!
+//! There is no ARP on IPv6 and a /64 can't be swept, so hosts are found from
+//! the kernel's neighbor cache instead. Before reading it, an ICMPv6 echo
+//! request to all-nodes (ff02::1) is sent on the interface: every host that
+//! answers has to resolve our address first, which leaves an entry for it in
+//! the cache. Entries already cached get a neighbor solicitation so stale ones
+//! are confirmed or dropped.
+//!
+//! Frames go out through [`SocketProvider::send_frame`], the datalink channel
+//! LLDP uses, so this needs the same privileges (or pre-opened sockets).
+//! Link-local neighbors carry the interface index as their scope id; it has to
+//! stay with the address for anything sent to them afterwards.
+
+use anyhow::Result;
+use pnet::datalink::{self, NetworkInterface};
+use std::net::{IpAddr, Ipv6Addr, SocketAddr, SocketAddrV6};
+use std::process::Command;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+use crate::icmp::checksum;
+use crate::net::IpNet;
+use crate::sockets::SocketProvider;
+
+const ETHERTYPE_IPV6: u16 = 0x86dd;
+const NEXT_HEADER_ICMPV6: u8 = 58;
+/// Neighbor discovery messages must arrive with the hop limit they were sent
+/// with (RFC 4861 section 7.1.1)
+const NDP_HOP_LIMIT: u8 = 255;
+
+const ECHO_REQUEST: u8 = 128;
+const NEIGHBOR_SOLICITATION: u8 = 135;
+const OPTION_SOURCE_LINK_LAYER: u8 = 1;
+
+/// Echo identifier, so our requests are recognizable in a capture
+const ECHO_IDENT: u16 = 0x6464;
+
+/// How long hosts get to answer before the neighbor cache is read
+pub const DEFAULT_WAIT: Duration = Duration::from_millis(1500);
+
+/// A host in the IPv6 neighbor cache
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ipv6Neighbor {
+    pub ip: Ipv6Addr,
+    pub mac: String,
+    pub interface: String,
+    /// Index of `interface`, the scope of link-local addresses
+    pub scope_id: u32,
+}
+
+impl Ipv6Neighbor {
+    /// Address to reach the neighbor on `port`, scoped if it is link-local
+    pub fn socket_addr(&self, port: u16) -> SocketAddr {
+        let scope_id = if is_link_local(&self.ip) { self.scope_id } else { 0 };
+        SocketAddr::V6(SocketAddrV6::new(self.ip, port, 0, scope_id))
+    }
+}
+
+/// Result of neighbor discovery on the selected interfaces
+#[derive(Debug, Default)]
+pub struct NdpScan {
+    pub neighbors: Vec<Ipv6Neighbor>,
+    /// Interfaces that couldn't be used, with why
+    pub errors: Vec<String>,
+}
+
+/// Whether `ip` is in fe80::/10
+pub fn is_link_local(ip: &Ipv6Addr) -> bool {
+    ip.segments()[0] & 0xffc0 == 0xfe80
+}
+
+/// Solicited-node multicast address of `target` (ff02::1:ffXX:XXXX)
+pub fn solicited_node(target: Ipv6Addr) -> Ipv6Addr {
+    let t = target.octets();
+    Ipv6Addr::from([0xff, 0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x01, 0xff, t[13], t[14], t[15]])
+}
+
+/// Ethernet address an IPv6 multicast group is delivered to (33:33 + low 32 bits)
+pub fn multicast_mac(group: Ipv6Addr) -> [u8; 6] {
+    let g = group.octets();
+    [0x33, 0x33, g[12], g[13], g[14], g[15]]
+}
+
+/// Neighbor solicitation for `target`, sent to its solicited-node group
+pub fn neighbor_solicitation(src_mac: [u8; 6], src_ip: Ipv6Addr, target: Ipv6Addr) -> Vec<u8> {
+    let mut icmp = vec![NEIGHBOR_SOLICITATION, 0, 0, 0, 0, 0, 0, 0];
+    icmp.extend_from_slice(&target.octets());
+    icmp.extend_from_slice(&[OPTION_SOURCE_LINK_LAYER, 1]);
+    icmp.extend_from_slice(&src_mac);
+    let group = solicited_node(target);
+    ipv6_frame(src_mac, src_ip, group, NDP_HOP_LIMIT, icmp)
+}
+
+/// Echo request to all nodes on the link (ff02::1)
+pub fn all_nodes_echo(src_mac: [u8; 6], src_ip: Ipv6Addr) -> Vec<u8> {
+    let mut icmp = vec![ECHO_REQUEST, 0, 0, 0];
+    icmp.extend_from_slice(&ECHO_IDENT.to_be_bytes());
+    icmp.extend_from_slice(&1u16.to_be_bytes());
+    icmp.extend_from_slice(b"dendrite");
+    ipv6_frame(src_mac, src_ip, Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 1), 1, icmp)
+}
+
+/// Ethernet + IPv6 frame around an ICMPv6 message, filling in its checksum
+fn ipv6_frame(src_mac: [u8; 6], src: Ipv6Addr, dst: Ipv6Addr, hop_limit: u8, mut icmp: Vec<u8>) -> Vec<u8> {
+    let sum = icmpv6_checksum(src, dst, &icmp);
+    icmp[2..4].copy_from_slice(&sum.to_be_bytes());
+
+    let mut frame = Vec::with_capacity(14 + 40 + icmp.len());
+    frame.extend_from_slice(&multicast_mac(dst));
+    frame.extend_from_slice(&src_mac);
+    frame.extend_from_slice(&ETHERTYPE_IPV6.to_be_bytes());
+    frame.extend_from_slice(&[0x60, 0, 0, 0]);
+    frame.extend_from_slice(&(icmp.len() as u16).to_be_bytes());
+    frame.extend_from_slice(&[NEXT_HEADER_ICMPV6, hop_limit]);
+    frame.extend_from_slice(&src.octets());
+    frame.extend_from_slice(&dst.octets());
+    frame.extend_from_slice(&icmp);
+    frame
+}
+
+/// ICMPv6 checksum, which covers an IPv6 pseudo-header (RFC 8200 section 8.1)
+fn icmpv6_checksum(src: Ipv6Addr, dst: Ipv6Addr, icmp: &[u8]) -> u16 {
+    let mut data = Vec::with_capacity(40 + icmp.len());
+    data.extend_from_slice(&src.octets());
+    data.extend_from_slice(&dst.octets());
+    data.extend_from_slice(&(icmp.len() as u32).to_be_bytes());
+    data.extend_from_slice(&[0, 0, 0, NEXT_HEADER_ICMPV6]);
+    data.extend_from_slice(icmp);
+    checksum(&data)
+}
+
+/// Interfaces to run discovery on: `name` if given, otherwise every interface
+/// that is up, has a MAC and an IPv6 address in `subnet`
+pub fn select_interfaces(subnet: &IpNet, name: Option<&str>) -> Vec<NetworkInterface> {
+    let mut selected: Vec<NetworkInterface> = datalink::interfaces()
+        .into_iter()
+        .filter(|iface| match name {
+            Some(name) => iface.name == name,
+            None => {
+                iface.is_up()
+                    && !iface.is_loopback()
+                    && iface.mac.is_some()
+                    && iface.ips.iter().any(|ip| subnet.contains(ip.ip()))
+            }
+        })
+        .collect();
+    selected.sort_by(|a, b| a.name.cmp(&b.name));
+    selected
+}
+
+/// Source address for frames on an interface: its link-local address, which
+/// every IPv6 interface has and which all neighbors can answer
+fn source_address(iface: &NetworkInterface) -> Option<Ipv6Addr> {
+    iface.ips.iter().find_map(|ip| match ip.ip() {
+        IpAddr::V6(v6) if is_link_local(&v6) => Some(v6),
+        _ => None,
+    })
+}
+
+/// Find the hosts of `subnet` on the selected interfaces
+///
+/// Sends the echo and solicitations, waits `wait` for answers, then reads
+/// the neighbor cache. Interfaces frames can't be sent on are reported in
+/// [`NdpScan::errors`]; whatever the cache already knew is still returned.
+pub async fn discover(
+    subnet: &IpNet,
+    interface: Option<&str>,
+    sockets: Arc<dyn SocketProvider>,
+    wait: Duration,
+) -> Result<NdpScan> {
+    let interfaces = select_interfaces(subnet, interface);
+    let mut scan = NdpScan::default();
+    if interfaces.is_empty() {
+        scan.errors.push(match interface {
+            Some(name) => format!("{}: no such interface", name),
+            None => format!("no interface with an address in {}", subnet),
+        });
+        return Ok(scan);
+    }
+
+    let cached = get_neighbor_table()?;
+    for iface in &interfaces {
+        let (Some(mac), Some(src_ip)) = (iface.mac, source_address(iface)) else {
+            scan.errors.push(format!("{}: no MAC or link-local address", iface.name));
+            continue;
+        };
+        let mac = mac.octets();
+        let mut frames = vec![all_nodes_echo(mac, src_ip)];
+        frames.extend(
+            cached
+                .iter()
+                .filter(|n| n.interface == iface.name && subnet.contains(IpAddr::V6(n.ip)))
+                .map(|n| neighbor_solicitation(mac, src_ip, n.ip)),
+        );
+        debug!(interface = %iface.name, frames = frames.len(), "Sending neighbor discovery");
+        for frame in frames {
+            if let Err(e) = sockets.send_frame(iface, &frame) {
+                warn!(interface = %iface.name, error = %e, "Neighbor discovery send failed");
+                scan.errors.push(format!("{}: {}", iface.name, e));
+                break;
+            }
+        }
+    }
+
+    tokio::time::sleep(wait).await;
+
+    scan.neighbors = get_neighbor_table()?
+        .into_iter()
+        .filter(|n| interfaces.iter().any(|iface| iface.name == n.interface))
+        .filter(|n| subnet.contains(IpAddr::V6(n.ip)))
+        .map(|mut n| {
+            n.scope_id = interfaces.iter().find(|iface| iface.name == n.interface).map_or(0, |iface| iface.index);
+            n
+        })
+        .collect();
+    debug!(neighbors = scan.neighbors.len(), "Neighbor discovery complete");
+    Ok(scan)
+}
+
+/// Resolved entries of the IPv6 neighbor cache (scope ids not filled in)
+pub fn get_neighbor_table() -> Result<Vec<Ipv6Neighbor>> {
+    let output = Command::new("ip").args(["-6", "neigh", "show"]).output()?;
+    if !output.status.success() {
+        anyhow::bail!("Failed to get neighbor table: {}", String::from_utf8_lossy(&output.stderr));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).lines().filter_map(parse_neigh_line).collect())
+}
+
+/// Parse a line of `ip -6 neigh show`, skipping entries without a MAC
+/// (INCOMPLETE, FAILED)
+fn parse_neigh_line(line: &str) -> Option<Ipv6Neighbor> {
+    // Format: "fe80::1 dev eth0 lladdr aa:bb:cc:dd:ee:ff router STALE"
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    let ip = Ipv6Addr::from_str(parts.first()?).ok()?;
+    let value = |key: &str| parts.iter().position(|&p| p == key).and_then(|i| parts.get(i + 1));
+    if parts.last() == Some(&"FAILED") {
+        return None;
+    }
+    Some(Ipv6Neighbor {
+        ip,
+        mac: value("lladdr")?.to_string(),
+        interface: value("dev")?.to_string(),
+        scope_id: 0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAC: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x01];
+
+    fn addr(text: &str) -> Ipv6Addr {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn test_neighbor_solicitation_frame() {
+        let src = addr("fe80::1");
+        let target = addr("fe80::1c2:3ff:fe04:506");
+        let frame = neighbor_solicitation(MAC, src, target);
+
+        assert_eq!(frame.len(), 14 + 40 + 32);
+        assert_eq!(&frame[0..6], &[0x33, 0x33, 0xff, 0x04, 0x05, 0x06]);
+        assert_eq!(&frame[6..12], &MAC);
+        assert_eq!(&frame[12..14], &[0x86, 0xdd]);
+
+        let ip = &frame[14..54];
+        assert_eq!(ip[0] >> 4, 6);
+        assert_eq!(u16::from_be_bytes([ip[4], ip[5]]), 32);
+        assert_eq!((ip[6], ip[7]), (NEXT_HEADER_ICMPV6, 255));
+        assert_eq!(&ip[24..40], &addr("ff02::1:ff04:506").octets());
+
+        let icmp = &frame[54..];
+        assert_eq!(icmp[0], NEIGHBOR_SOLICITATION);
+        assert_eq!(&icmp[8..24], &target.octets());
+        assert_eq!(&icmp[24..26], &[OPTION_SOURCE_LINK_LAYER, 1]);
+        assert_eq!(&icmp[26..32], &MAC);
+        // A correct checksum sums (with the pseudo-header) to all ones
+        assert_eq!(icmpv6_checksum(src, solicited_node(target), icmp), 0);
+    }
+
+    #[test]
+    fn test_all_nodes_echo_frame() {
+        let src = addr("fe80::1");
+        let frame = all_nodes_echo(MAC, src);
+        assert_eq!(&frame[0..6], &[0x33, 0x33, 0, 0, 0, 1]);
+        assert_eq!(&frame[38..54], &addr("ff02::1").octets());
+        assert_eq!(frame[54], ECHO_REQUEST);
+        assert_eq!(icmpv6_checksum(src, addr("ff02::1"), &frame[54..]), 0);
+    }
+
+    #[test]
+    fn test_parse_neigh_line() {
+        let neighbor = parse_neigh_line("fe80::1c2:3ff:fe04:506 dev eth1 lladdr 02:c2:03:04:05:06 router STALE").unwrap();
+        assert_eq!(neighbor.ip, addr("fe80::1c2:3ff:fe04:506"));
+        assert_eq!(neighbor.mac, "02:c2:03:04:05:06");
+        assert_eq!(neighbor.interface, "eth1");
+
+        assert!(parse_neigh_line("fe80::9 dev eth1 INCOMPLETE").is_none());
+        assert!(parse_neigh_line("fe80::9 dev eth1 lladdr 02:00:00:00:00:09 FAILED").is_none());
+        assert!(parse_neigh_line("192.168.1.1 dev eth0 lladdr aa:bb:cc:dd:ee:ff REACHABLE").is_none());
+    }
+
+    #[test]
+    fn test_link_local_neighbors_are_scoped() {
+        let mut neighbor = parse_neigh_line("fe80::2 dev eth1 lladdr 02:00:00:00:00:02 REACHABLE").unwrap();
+        neighbor.scope_id = 3;
+        assert_eq!(neighbor.socket_addr(1337).to_string(), "[fe80::2%3]:1337");
+        neighbor.ip = addr("fd00::2");
+        assert_eq!(neighbor.socket_addr(1337).to_string(), "[fd00::2]:1337");
+    }
+}
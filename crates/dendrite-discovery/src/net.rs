@@ -0,0 +1,186 @@
+//! IPv4 and IPv6 networks in CIDR notation
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// A network such as "192.168.186.0/24" or "fe80::/64"
+///
+/// The address is kept as given; [`IpNet::network`] masks off the host bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct IpNet {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum IpNetError {
+    #[error("invalid address in {0:?}")]
+    Address(String),
+    #[error("prefix length {prefix_len} is longer than {max}")]
+    PrefixTooLong { prefix_len: u8, max: u8 },
+    #[error("invalid prefix length in {0:?}")]
+    Prefix(String),
+}
+
+impl IpNet {
+    pub fn new(addr: IpAddr, prefix_len: u8) -> Result<Self, IpNetError> {
+        let max = max_prefix(addr);
+        if prefix_len > max {
+            return Err(IpNetError::PrefixTooLong { prefix_len, max });
+        }
+        Ok(Self { addr, prefix_len })
+    }
+
+    /// The address as given, host bits included
+    pub fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    pub fn is_ipv6(&self) -> bool {
+        self.addr.is_ipv6()
+    }
+
+    /// The IPv4 address and prefix, for the methods that only sweep IPv4
+    pub fn as_v4(&self) -> Option<(Ipv4Addr, u8)> {
+        match self.addr {
+            IpAddr::V4(addr) => Some((addr, self.prefix_len)),
+            IpAddr::V6(_) => None,
+        }
+    }
+
+    /// The network address (host bits cleared)
+    pub fn network(&self) -> IpAddr {
+        match self.addr {
+            IpAddr::V4(addr) => IpAddr::V4((u32::from(addr) & mask32(self.prefix_len)).into()),
+            IpAddr::V6(addr) => IpAddr::V6((u128::from(addr) & mask128(self.prefix_len)).into()),
+        }
+    }
+
+    /// Whether `ip` is in this network; addresses of the other family never are
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = mask32(self.prefix_len);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = mask128(self.prefix_len);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+fn max_prefix(addr: IpAddr) -> u8 {
+    if addr.is_ipv4() {
+        32
+    } else {
+        128
+    }
+}
+
+fn mask32(prefix_len: u8) -> u32 {
+    u32::MAX.checked_shl(32 - u32::from(prefix_len.min(32))).unwrap_or(0)
+}
+
+fn mask128(prefix_len: u8) -> u128 {
+    u128::MAX.checked_shl(128 - u32::from(prefix_len.min(128))).unwrap_or(0)
+}
+
+impl FromStr for IpNet {
+    type Err = IpNetError;
+
+    /// "addr/len", or a bare address as a single host
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (addr, len) = match text.split_once('/') {
+            Some((addr, len)) => (addr, Some(len)),
+            None => (text, None),
+        };
+        let addr: IpAddr = addr.parse().map_err(|_| IpNetError::Address(text.to_string()))?;
+        let prefix_len = match len {
+            Some(len) => len.parse().map_err(|_| IpNetError::Prefix(text.to_string()))?,
+            None => max_prefix(addr),
+        };
+        Self::new(addr, prefix_len)
+    }
+}
+
+impl fmt::Display for IpNet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+impl TryFrom<String> for IpNet {
+    type Error = IpNetError;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        text.parse()
+    }
+}
+
+impl From<IpNet> for String {
+    fn from(net: IpNet) -> Self {
+        net.to_string()
+    }
+}
+
+impl From<(IpAddr, u8)> for IpNet {
+    /// Prefixes longer than the address are clamped
+    fn from((addr, prefix_len): (IpAddr, u8)) -> Self {
+        Self { addr, prefix_len: prefix_len.min(max_prefix(addr)) }
+    }
+}
+
+impl From<(Ipv4Addr, u8)> for IpNet {
+    fn from((addr, prefix_len): (Ipv4Addr, u8)) -> Self {
+        Self::from((IpAddr::V4(addr), prefix_len))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_contains() {
+        let v4: IpNet = "192.168.186.77/24".parse().unwrap();
+        assert_eq!(v4.network(), "192.168.186.0".parse::<IpAddr>().unwrap());
+        assert!(v4.contains("192.168.186.255".parse().unwrap()));
+        assert!(!v4.contains("192.168.187.1".parse().unwrap()));
+        assert!(!v4.contains("fe80::1".parse().unwrap()));
+        assert_eq!(v4.to_string(), "192.168.186.77/24");
+
+        let v6: IpNet = "fe80::/64".parse().unwrap();
+        assert!(v6.is_ipv6());
+        assert!(v6.contains("fe80::1c2:3ff:fe04:506".parse().unwrap()));
+        assert!(!v6.contains("fd00::1".parse().unwrap()));
+        assert!(!v6.contains("10.0.0.1".parse().unwrap()));
+
+        assert_eq!("10.0.0.1".parse::<IpNet>().unwrap().prefix_len(), 32);
+        assert_eq!("::1".parse::<IpNet>().unwrap().prefix_len(), 128);
+        assert!("0.0.0.0/0".parse::<IpNet>().unwrap().contains("8.8.8.8".parse().unwrap()));
+        assert_eq!(
+            "10.0.0.0/33".parse::<IpNet>(),
+            Err(IpNetError::PrefixTooLong { prefix_len: 33, max: 32 })
+        );
+        assert!("fe80::/abc".parse::<IpNet>().is_err());
+        assert!("router/24".parse::<IpNet>().is_err());
+    }
+
+    #[test]
+    fn test_serde_as_string() {
+        let net: IpNet = serde_json::from_str("\"fe80::/64\"").unwrap();
+        assert_eq!(serde_json::to_string(&net).unwrap(), "\"fe80::/64\"");
+        assert!(serde_json::from_str::<IpNet>("\"fe80::/129\"").is_err());
+    }
+}
@@ -1,8 +1,8 @@
 //! MCUmgr port probing for device verification
 
 use anyhow::Result;
-use dendrite_mcumgr::{probe_device, query_device, DeviceQueryResult, MCUMGR_PORT};
-use std::net::{IpAddr, Ipv4Addr};
+use dendrite_mcumgr::{probe_device, query_devices_parallel, DeviceQueryResult, QueryOptions};
+use std::net::SocketAddr;
 use std::time::Duration;
 use tokio::task::JoinSet;
 use tracing::{debug, info, trace};
@@ -10,26 +10,47 @@ use tracing::{debug, info, trace};
 /// Probe timeout in milliseconds
 const PROBE_TIMEOUT_MS: u64 = 1000;
 
-/// Probe multiple IP addresses for MCUmgr devices
-pub async fn probe_hosts(hosts: &[Ipv4Addr], port: u16) -> Vec<Ipv4Addr> {
-    let mut tasks = JoinSet::new();
+/// Hosts probed at once, so a busy subnet doesn't open a socket per host
+const PROBE_CONCURRENCY: usize = 64;
 
-    for &host in hosts {
-        tasks.spawn(async move {
-            let ip = IpAddr::V4(host);
-            if probe_device(ip, port, PROBE_TIMEOUT_MS).await {
-                Some(host)
-            } else {
-                None
-            }
-        });
-    }
+/// A single echo per probe: most swept hosts have no device, and retrying
+/// each of them would multiply the sweep's time
+fn probe_options() -> QueryOptions {
+    QueryOptions { timeout: Duration::from_millis(PROBE_TIMEOUT_MS), retries: 0, ..QueryOptions::default() }
+}
+
+/// Probe multiple addresses for MCUmgr devices, `PROBE_CONCURRENCY` at a time
+///
+/// Link-local IPv6 addresses must carry their scope id.
+pub async fn probe_hosts(hosts: &[SocketAddr]) -> Vec<SocketAddr> {
+    probe_hosts_with_progress(hosts, |_, _| {}).await
+}
 
+/// Like [`probe_hosts`], calling `progress(done, total)` as each probe finishes
+pub async fn probe_hosts_with_progress(
+    hosts: &[SocketAddr],
+    mut progress: impl FnMut(usize, usize),
+) -> Vec<SocketAddr> {
+    let mut done = 0;
+    let mut pending = hosts.iter().copied();
+    let mut tasks = JoinSet::new();
     let mut mcumgr_hosts = Vec::new();
-    while let Some(result) = tasks.join_next().await {
-        if let Ok(Some(ip)) = result {
-            info!(ip = %ip, "Found MCUmgr device");
-            mcumgr_hosts.push(ip);
+
+    loop {
+        while tasks.len() < PROBE_CONCURRENCY {
+            let Some(addr) = pending.next() else {
+                break;
+            };
+            tasks.spawn(async move { probe_device(addr, probe_options()).await.then_some(addr) });
+        }
+        let Some(result) = tasks.join_next().await else {
+            break;
+        };
+        done += 1;
+        progress(done, hosts.len());
+        if let Ok(Some(addr)) = result {
+            info!(addr = %addr, "Found MCUmgr device");
+            mcumgr_hosts.push(addr);
         }
     }
 
@@ -41,40 +62,31 @@ pub async fn probe_hosts(hosts: &[Ipv4Addr], port: u16) -> Vec<Ipv4Addr> {
     mcumgr_hosts
 }
 
-/// Query multiple devices for full information
+/// Query multiple devices for full information, `concurrency` at a time
+///
+/// Devices that fail or take longer than `timeout_ms` are left out.
 pub async fn query_hosts(
-    hosts: &[Ipv4Addr],
-    port: u16,
-) -> Vec<(Ipv4Addr, DeviceQueryResult)> {
-    let mut tasks = JoinSet::new();
-
-    for &host in hosts {
-        tasks.spawn(async move {
-            let ip = IpAddr::V4(host);
-            match query_device(ip, port).await {
-                Ok(result) => Some((host, result)),
-                Err(e) => {
-                    debug!(ip = %host, error = %e, "Failed to query device");
-                    None
-                }
+    hosts: &[SocketAddr],
+    concurrency: usize,
+    timeout_ms: u64,
+) -> Vec<(SocketAddr, DeviceQueryResult)> {
+    query_devices_parallel(hosts, concurrency, Duration::from_millis(timeout_ms))
+        .await
+        .into_iter()
+        .filter_map(|(addr, result)| match result {
+            Ok(result) => Some((addr, result)),
+            Err(e) => {
+                debug!(addr = %addr, error = %e, "Failed to query device");
+                None
             }
-        });
-    }
-
-    let mut results = Vec::new();
-    while let Some(result) = tasks.join_next().await {
-        if let Ok(Some((ip, query_result))) = result {
-            results.push((ip, query_result));
-        }
-    }
-
-    results
+        })
+        .collect()
 }
 
 /// Probe a single host with retries
-pub async fn probe_with_retry(ip: Ipv4Addr, port: u16, retries: u32) -> bool {
+pub async fn probe_with_retry(addr: SocketAddr, retries: u32) -> bool {
     for attempt in 0..retries {
-        if probe_device(IpAddr::V4(ip), port, PROBE_TIMEOUT_MS).await {
+        if probe_device(addr, probe_options()).await {
             return true;
         }
         if attempt < retries - 1 {
@@ -83,3 +95,27 @@ pub async fn probe_with_retry(ip: Ipv4Addr, port: u16, retries: u32) -> bool {
     }
     false
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dendrite_test_support::{MockHcdfInfo, MockIdentity, MockSmpDevice};
+
+    #[tokio::test]
+    async fn test_probe_and_query_over_ipv6() {
+        let identity = MockIdentity { hwid: Some("0x1c2a".to_string()), os_info: None };
+        let bind = SocketAddr::from((std::net::Ipv6Addr::LOCALHOST, 0));
+        let Ok(device) = MockSmpDevice::spawn_at(bind, MockHcdfInfo::default(), identity).await else {
+            // No IPv6 loopback on this host
+            return;
+        };
+
+        let silent = std::net::UdpSocket::bind("[::1]:0").unwrap().local_addr().unwrap();
+        assert_eq!(probe_hosts(&[device.addr(), silent]).await, vec![device.addr()]);
+
+        let results = query_hosts(&[device.addr()], 4, 1000).await;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0, device.addr());
+        assert_eq!(results[0].1.hwid.as_deref(), Some("0x1c2a"));
+    }
+}
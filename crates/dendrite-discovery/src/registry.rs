@@ -0,0 +1,89 @@
+//! Device registry storage
+//!
+//! The scanner doesn't own its registry; it sends edits to a
+//! [`RegistryStore`] and reads the published [`RegistrySnapshot`]. The
+//! daemon's state task implements the store, so the registry and the HCDF
+//! document are changed by one task and no lock order spans them.
+//! [`LocalRegistry`] keeps the registry in the scanner's process for tools
+//! and tests that have no state task.
+
+use dendrite_core::Device;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+
+/// Devices known to the scanner
+#[derive(Debug, Clone, Default)]
+pub struct Registry {
+    pub devices: HashMap<String, Device>,
+    /// Devices hidden by the user, keyed like `devices`
+    pub archived: HashMap<String, Device>,
+}
+
+/// The registry as of the last published edit
+pub type RegistrySnapshot = Registry;
+
+/// A synchronous change to the registry
+pub type RegistryEdit = Box<dyn FnOnce(&mut Registry) + Send>;
+
+/// Where the scanner keeps its registry
+pub trait RegistryStore: Send + Sync {
+    /// Queue `edit` after every edit queued before it
+    ///
+    /// The receiver resolves once the edit is applied and published; it is
+    /// dropped without a value if the edit panicked.
+    fn edit(&self, edit: RegistryEdit) -> oneshot::Receiver<()>;
+
+    /// The registry as of the last published edit
+    fn snapshot(&self) -> Arc<RegistrySnapshot>;
+}
+
+/// Registry kept by the scanner itself
+///
+/// Edits copy the registry only while a snapshot of it is still held.
+#[derive(Debug, Default)]
+pub struct LocalRegistry(Mutex<Arc<Registry>>);
+
+impl LocalRegistry {
+    /// A store starting out with `registry`
+    pub fn new(registry: Registry) -> Self {
+        Self(Mutex::new(Arc::new(registry)))
+    }
+}
+
+impl RegistryStore for LocalRegistry {
+    fn edit(&self, edit: RegistryEdit) -> oneshot::Receiver<()> {
+        let (tx, rx) = oneshot::channel();
+        let mut registry = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        edit(Arc::make_mut(&mut registry));
+        let _ = tx.send(());
+        rx
+    }
+
+    fn snapshot(&self) -> Arc<RegistrySnapshot> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dendrite_core::DeviceId;
+
+    #[tokio::test]
+    async fn test_local_snapshots_are_unaffected_by_later_edits() {
+        let store = LocalRegistry::default();
+        let device = Device::new(DeviceId::from_hwid("abc"), "abc".to_string(), "10.0.0.2".parse().unwrap(), 1337);
+        store
+            .edit(Box::new(move |registry| {
+                registry.devices.insert("abc".to_string(), device);
+            }))
+            .await
+            .unwrap();
+        let before = store.snapshot();
+
+        store.edit(Box::new(|registry| registry.devices.clear())).await.unwrap();
+        assert_eq!(before.devices.len(), 1);
+        assert!(store.snapshot().devices.is_empty());
+    }
+}
@@ -1,120 +1,369 @@
 //! Discovery scanner that combines all discovery methods
 
 use anyhow::Result;
-use dendrite_core::device::DiscoveryMethod;
-use dendrite_core::{Device, DeviceId, DeviceStatus};
-use dendrite_mcumgr::{query_result_to_device, MCUMGR_PORT};
+pub use dendrite_api_types::{MethodStats, ScanStats};
+pub use dendrite_core::DiscoveryEvent;
+use dendrite_core::{CanAddress, DiscoveryMethod, Device, DeviceId, DeviceStatus, FirmwareStatus, HeartbeatMethod, LatencyHistory, LatencySample};
+use dendrite_mcumgr::{query_result_to_device, DEFAULT_QUERY_CONCURRENCY, DEFAULT_TIMEOUT_MS, MCUMGR_PORT};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::net::{IpAddr, Ipv4Addr};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::str::FromStr;
 use std::sync::Arc;
-use tokio::sync::{broadcast, RwLock};
-use tokio::time::{interval, Duration};
-use tracing::{debug, info, warn};
-
-use crate::arp::{get_arp_table, scan_subnet};
-use crate::lldp::{get_lldp_neighbors, LldpNeighbor};
-use crate::probe::{probe_hosts, query_hosts};
+use tokio::sync::{broadcast, oneshot, RwLock};
+use tokio::task::JoinSet;
+use tokio::time::{interval, Duration, Instant};
+use tracing::{debug, info, info_span, warn, Instrument};
+
+use crate::arp::{get_arp_table, scan_subnet, subnet_host_count, subnet_hosts, ArpEntry};
+use crate::can::{self, CanDiscoveryConfig, CanNode};
+use crate::heartbeat::check_device;
+use crate::icmp::{sweep_subnet, IcmpSweep};
+use crate::lldp::{get_lldp_neighbors, parse_port_number, LldpNeighbor, NeighborEntry, NeighborTable};
+use crate::mdns;
+use crate::ndp::{self, Ipv6Neighbor};
+use crate::net::IpNet;
+use crate::probe::{probe_hosts_with_progress, query_hosts};
+use crate::registry::{LocalRegistry, Registry, RegistryStore};
+use crate::schedule::{HeartbeatSchedule, ScheduleTiming};
+use crate::sockets::{SocketProvider, SystemSockets};
+
+/// How often known LLDP neighbors are refreshed from lldpd between scans
+const LLDP_REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Progress events sent over the MCUmgr probe of one scan, at most
+const PROGRESS_EVENTS: usize = 20;
+
+/// How often the heartbeat loop looks for devices due a check
+const HEARTBEAT_TICK: Duration = Duration::from_secs(1);
 
 /// Scanner configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScannerConfig {
-    /// Subnet to scan (e.g., "192.168.186.0")
-    pub subnet: Ipv4Addr,
-    /// Subnet prefix length (e.g., 24 for /24)
-    pub prefix_len: u8,
+    /// Subnet to scan: IPv4 is swept with ARP (e.g., "192.168.186.0/24"),
+    /// IPv6 is found with neighbor discovery (e.g., "fe80::/64")
+    pub subnet: IpNet,
+    /// Interface for neighbor discovery (None = every interface with an
+    /// address in an IPv6 `subnet`)
+    #[serde(default)]
+    pub interface: Option<String>,
     /// MCUmgr port
     pub mcumgr_port: u16,
+    /// Devices queried at once after probing
+    #[serde(default = "default_query_concurrency")]
+    pub query_concurrency: usize,
+    /// Time allowed for each device's query
+    #[serde(default = "default_query_timeout_ms")]
+    pub query_timeout_ms: u64,
     /// Full scan interval in seconds (discovers new devices)
     pub interval_secs: u64,
-    /// Heartbeat interval in seconds (lightweight status check)
+    /// Heartbeat interval in seconds for online devices (lightweight status check)
     pub heartbeat_interval_secs: u64,
     /// Whether heartbeat checking is enabled (sends ARP/ping to check connectivity)
     pub heartbeat_enabled: bool,
-    /// Use LLDP for port detection
-    pub use_lldp: bool,
-    /// Use ARP scanning
-    pub use_arp: bool,
-    /// Parent device configuration
-    pub parent: Option<ParentConfig>,
+    /// First retry of a device that stopped answering, in seconds; later
+    /// retries double the wait
+    #[serde(default = "default_offline_retry_min_secs")]
+    pub offline_retry_min_secs: u64,
+    /// Longest wait between retries of an offline device, in seconds
+    #[serde(default = "default_offline_retry_max_secs")]
+    pub offline_retry_max_secs: u64,
+    /// Fraction each heartbeat delay is randomly spread by (0.1 = ±10%)
+    #[serde(default = "default_heartbeat_jitter")]
+    pub heartbeat_jitter: f64,
+    /// Discovery phases to run; on networks without LLDP, leave it out
+    #[serde(default)]
+    pub methods: DiscoveryMethods,
+    /// Cap on active ARP scan requests per second (None = unlimited)
+    ///
+    /// Every host the subnet's prefix allows is probed, so a scan takes about
+    /// hosts / rate seconds: 2.5 s for a /24 at 100/s, 41 s for a /20.
+    #[serde(default)]
+    pub arp_rate_limit: Option<u32>,
+    /// Routed subnets to find with an ICMP echo sweep
+    pub icmp_sweeps: Vec<IcmpSweep>,
+    /// Browse for hosts advertising `mdns_service_types` over mDNS
+    #[serde(default)]
+    pub enable_mdns: bool,
+    /// DNS-SD service types to browse for (e.g., "_dendrite._udp")
+    #[serde(default = "default_mdns_service_types")]
+    pub mdns_service_types: Vec<String>,
+    /// Addresses or CIDR ranges no discovery method may touch
+    pub exclude: Vec<String>,
+    /// Parent devices (switches) the discovered devices hang off
+    #[serde(default)]
+    pub parents: Vec<ParentConfig>,
     /// Manual device overrides
     pub overrides: Vec<DeviceOverride>,
+    /// CAN bus discovery (None = disabled)
+    #[serde(default)]
+    pub can: Option<CanDiscoveryConfig>,
+}
+
+bitflags::bitflags! {
+    /// Set of discovery phases a scan runs, combined with `|`
+    ///
+    /// - `LLDP` maps devices to switch ports through lldpd
+    /// - `ARP` reads the ARP table and sweeps the IPv4 subnet for live hosts
+    /// - `PROBE` checks candidates for MCUmgr and queries the ones that answer
+    ///
+    /// Without `ARP`, `PROBE` tries every address in the IPv4 subnet directly
+    /// and no ARP traffic is sent. Without `PROBE`, only mDNS advertisements
+    /// with a hwid and CAN nodes are registered. Serialized as the flag names
+    /// joined by `|`, e.g. `"ARP | PROBE"`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    #[serde(transparent)]
+    pub struct DiscoveryMethods: u8 {
+        const LLDP = 1 << 0;
+        const ARP = 1 << 1;
+        const PROBE = 1 << 2;
+    }
+}
+
+impl Default for DiscoveryMethods {
+    fn default() -> Self {
+        Self::all()
+    }
 }
 
+/// A parent device (switch) and the devices it claims
+///
+/// With several parents, a device goes to the one configured for the local
+/// interface its ARP entry or LLDP advertisement arrived on, else to the one
+/// whose subnet holds its address, else to the first that sets neither.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ParentConfig {
     pub name: String,
     pub board: String,
     pub ports: u8,
     pub ip: Option<Ipv4Addr>,
+    /// Devices with an address in this network hang off this parent
+    #[serde(default)]
+    pub subnet: Option<IpNet>,
+    /// Devices heard on this local interface (e.g., "eth1", or a CAN bus)
+    /// hang off this parent
+    #[serde(default)]
+    pub interface: Option<String>,
+}
+
+impl ParentConfig {
+    /// Whether this parent takes devices no other parent claims
+    fn is_catch_all(&self) -> bool {
+        self.subnet.is_none() && self.interface.is_none()
+    }
+}
+
+/// The parent a device at `ip`, heard on the local `interface`, hangs off
+fn parent_for<'a>(parents: &'a [ParentConfig], ip: IpAddr, interface: Option<&str>) -> Option<&'a ParentConfig> {
+    interface
+        .and_then(|interface| parents.iter().find(|p| p.interface.as_deref() == Some(interface)))
+        .or_else(|| parents.iter().find(|p| p.subnet.is_some_and(|net| net.contains(ip))))
+        .or_else(|| parents.iter().find(|p| p.is_catch_all()))
+}
+
+/// Local interface a device was heard on: where its LLDP advertisement
+/// arrived, else the interface of its ARP entry
+fn heard_on(arp: &[ArpEntry], lldp: &[LldpNeighbor], ip: IpAddr, mac: Option<&str>) -> Option<String> {
+    mac.and_then(|mac| lldp.iter().find(|n| n.has_chassis_mac(mac)))
+        .map(|n| n.local_interface.clone())
+        .or_else(|| arp.iter().find(|e| IpAddr::V4(e.ip) == ip).map(|e| e.interface.clone()))
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DeviceOverride {
+    /// Hardware ID to match (empty to match by hostname only)
+    #[serde(default)]
     pub hwid: String,
+    /// Hostname to match, for devices found over mDNS (e.g., "jetson-01" or
+    /// "jetson-01.local")
+    #[serde(default)]
+    pub hostname: Option<String>,
     pub name: Option<String>,
     pub port: Option<u8>,
     pub model_path: Option<String>,
+    /// Board and application to look the device's fragment up by, for
+    /// devices that can't report them (CAN nodes)
+    #[serde(default)]
+    pub board: Option<String>,
+    #[serde(default)]
+    pub app: Option<String>,
+    /// Address of a device to add even when discovery never finds it
+    #[serde(default)]
+    pub ip: Option<IpAddr>,
+    /// Initial pose of a static device, [x, y, z, roll, pitch, yaw]
+    #[serde(default)]
+    pub pose: Option<[f64; 6]>,
+}
+
+impl DeviceOverride {
+    /// Whether the override is for this device, by hwid or by the hostname
+    /// it advertised
+    pub fn matches(&self, device: &Device) -> bool {
+        if !self.hwid.is_empty() && self.hwid == device.id.0 {
+            return true;
+        }
+        match (&self.hostname, &device.discovery.hostname) {
+            (Some(wanted), Some(hostname)) => mdns::same_hostname(wanted, hostname),
+            _ => false,
+        }
+    }
+
+    /// Apply the override to a device it matches
+    pub fn apply(&self, device: &mut Device) {
+        if let Some(ref name) = self.name {
+            device.name = name.clone();
+        }
+        if let Some(port) = self.port {
+            device.discovery.switch_port = Some(port);
+        }
+        if let Some(ref model) = self.model_path {
+            device.model_path = Some(model.clone());
+        }
+        if let Some(ref board) = self.board {
+            device.info.board = Some(board.clone());
+        }
+        if let Some(ref app) = self.app {
+            device.firmware.name = Some(app.clone());
+        }
+    }
+
+    /// The static device this override describes, if it gives an address.
+    /// Its ID is the hwid, or derived from the address when none is set.
+    pub fn static_device(&self) -> Option<Device> {
+        let ip = self.ip?;
+        let id = if self.hwid.is_empty() {
+            DeviceId(format!("static-{}", ip))
+        } else {
+            DeviceId::from_hwid(&self.hwid)
+        };
+        let mut device = Device::new(id, ip.to_string(), ip, 0);
+        device.discovery.discovery_method = DiscoveryMethod::Manual;
+        device.discovery.hostname = self.hostname.clone();
+        device.pose = self.pose;
+        self.apply(&mut device);
+        Some(device)
+    }
+}
+
+fn default_query_concurrency() -> usize {
+    DEFAULT_QUERY_CONCURRENCY
+}
+
+fn default_query_timeout_ms() -> u64 {
+    DEFAULT_TIMEOUT_MS
+}
+
+fn default_offline_retry_min_secs() -> u64 {
+    2
+}
+
+fn default_offline_retry_max_secs() -> u64 {
+    60
+}
+
+fn default_heartbeat_jitter() -> f64 {
+    0.1
+}
+
+fn default_mdns_service_types() -> Vec<String> {
+    mdns::DEFAULT_SERVICE_TYPES.iter().map(|t| t.to_string()).collect()
 }
 
 impl Default for ScannerConfig {
     fn default() -> Self {
         Self {
-            subnet: Ipv4Addr::new(192, 168, 186, 0),
-            prefix_len: 24,
+            subnet: IpNet::from((Ipv4Addr::new(192, 168, 186, 0), 24)),
+            interface: None,
             mcumgr_port: MCUMGR_PORT,
+            query_concurrency: DEFAULT_QUERY_CONCURRENCY,
+            query_timeout_ms: DEFAULT_TIMEOUT_MS,
             interval_secs: 60,          // Full scan every 60 seconds
             heartbeat_interval_secs: 2, // Lightweight ARP/ping check every 2 seconds
             heartbeat_enabled: false,   // Disabled by default (no network traffic until user enables)
-            use_lldp: true,
-            use_arp: true,
-            parent: None,
+            offline_retry_min_secs: default_offline_retry_min_secs(),
+            offline_retry_max_secs: default_offline_retry_max_secs(),
+            heartbeat_jitter: default_heartbeat_jitter(),
+            methods: DiscoveryMethods::all(),
+            arp_rate_limit: None,
+            icmp_sweeps: Vec::new(),
+            enable_mdns: false,
+            mdns_service_types: default_mdns_service_types(),
+            exclude: Vec::new(),
+            parents: Vec::new(),
             overrides: Vec::new(),
+            can: None,
         }
     }
 }
 
-/// Discovery event for real-time updates
-#[derive(Debug, Clone)]
-pub enum DiscoveryEvent {
-    /// New device discovered
-    DeviceDiscovered(Device),
-    /// Device went offline
-    DeviceOffline(DeviceId),
-    /// Device information updated
-    DeviceUpdated(Device),
-    /// Device removed from registry
-    DeviceRemoved(DeviceId),
-    /// Scan started
-    ScanStarted,
-    /// Scan completed
-    ScanCompleted { found: usize, total: usize },
+/// How a device is removed from the registry
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RemoveMode {
+    /// Hide the device; it is restored (with a notification) if seen again
+    #[default]
+    Archive,
+    /// Delete the device and everything remembered about it
+    Forget,
 }
 
 /// Discovery scanner service
 pub struct DiscoveryScanner {
     config: Arc<RwLock<ScannerConfig>>,
-    devices: Arc<RwLock<HashMap<String, Device>>>,
+    /// Known and archived devices
+    registry: Arc<dyn RegistryStore>,
+    /// Rolling heartbeat latency per device
+    latency: Arc<RwLock<HashMap<String, LatencyHistory>>>,
+    /// When each device is checked next
+    schedule: Arc<RwLock<HeartbeatSchedule>>,
+    /// Per-method statistics of the last scan
+    last_stats: Arc<RwLock<ScanStats>>,
+    /// Where ICMP sweeps get their sockets
+    sockets: Arc<dyn SocketProvider>,
+    /// LLDP neighbors and when their TTLs run out
+    lldp: Arc<RwLock<NeighborTable>>,
     event_tx: broadcast::Sender<DiscoveryEvent>,
 }
 
 impl DiscoveryScanner {
     /// Create a new scanner with the given configuration
     pub fn new(config: ScannerConfig) -> Self {
+        Self::with_sockets(config, Arc::new(SystemSockets))
+    }
+
+    /// Create a scanner that gets its ICMP sockets from `sockets`
+    pub fn with_sockets(config: ScannerConfig, sockets: Arc<dyn SocketProvider>) -> Self {
+        Self::with_registry(config, sockets, Arc::new(LocalRegistry::default()))
+    }
+
+    /// Create a scanner that keeps its devices in `registry`
+    pub fn with_registry(config: ScannerConfig, sockets: Arc<dyn SocketProvider>, registry: Arc<dyn RegistryStore>) -> Self {
         let (event_tx, _) = broadcast::channel(100);
         Self {
             config: Arc::new(RwLock::new(config)),
-            devices: Arc::new(RwLock::new(HashMap::new())),
+            registry,
+            latency: Arc::new(RwLock::new(HashMap::new())),
+            schedule: Arc::new(RwLock::new(HeartbeatSchedule::new())),
+            last_stats: Arc::new(RwLock::new(ScanStats::default())),
+            sockets,
+            lldp: Arc::new(RwLock::new(NeighborTable::new())),
             event_tx,
         }
     }
 
     /// Update the scan subnet at runtime
-    pub async fn update_subnet(&self, subnet: Ipv4Addr, prefix_len: u8) {
+    pub async fn update_subnet(&self, subnet: IpNet) {
         let mut config = self.config.write().await;
         config.subnet = subnet;
-        config.prefix_len = prefix_len;
-        info!(subnet = %subnet, prefix = prefix_len, "Scan subnet updated");
+        info!(subnet = %subnet, "Scan subnet updated");
+    }
+
+    /// Replace the whole configuration at runtime (e.g., after first-run setup)
+    ///
+    /// Takes effect from the next scan and heartbeat tick; known devices are kept.
+    pub async fn set_config(&self, config: ScannerConfig) {
+        info!(subnet = %config.subnet, "Scanner configuration replaced");
+        *self.config.write().await = config;
     }
 
     /// Get current config
@@ -141,84 +390,298 @@ impl DiscoveryScanner {
 
     /// Get current device list
     pub async fn devices(&self) -> Vec<Device> {
-        self.devices.read().await.values().cloned().collect()
+        self.registry.snapshot().devices.values().cloned().collect()
     }
 
     /// Get a specific device
     pub async fn get_device(&self, id: &DeviceId) -> Option<Device> {
-        self.devices.read().await.get(&id.0).cloned()
+        self.registry.snapshot().devices.get(&id.0).cloned()
+    }
+
+    /// Get archived (hidden) devices
+    pub async fn archived_devices(&self) -> Vec<Device> {
+        self.registry.snapshot().archived.values().cloned().collect()
+    }
+
+    /// Per-method statistics of the last scan
+    pub async fn last_scan_stats(&self) -> ScanStats {
+        self.last_stats.read().await.clone()
     }
 
     /// Run a single discovery scan
     pub async fn scan_once(&self) -> Result<Vec<Device>> {
-        let _ = self.event_tx.send(DiscoveryEvent::ScanStarted);
-
         // Get a snapshot of config for this scan
         let config = self.config.read().await.clone();
+        // Everything logged during the scan carries the subnet
+        let span = info_span!("scan", subnet = %config.subnet);
+        self.scan_with(config).instrument(span).await
+    }
 
-        info!(
-            subnet = %config.subnet,
-            prefix = config.prefix_len,
-            "Starting discovery scan"
-        );
+    async fn scan_with(&self, config: ScannerConfig) -> Result<Vec<Device>> {
+
+        let total_hosts = config.subnet.as_v4().map_or(0, |(_, prefix_len)| subnet_host_count(prefix_len));
+        let _ = self.event_tx.send(DiscoveryEvent::ScanStarted { total_hosts });
+
+        info!(subnet = %config.subnet, "Starting discovery scan");
+
+        let exclusions = parse_exclusions(&config.exclude);
+        let excluded_ip = |ip: IpAddr| exclusions.iter().any(|net| net.contains(ip));
+        let excluded = |ip: Ipv4Addr| excluded_ip(IpAddr::V4(ip));
 
-        // Step 1: Get list of potential hosts
+        // Step 1: Get list of potential hosts (IPv4 subnets)
         let mut candidates: Vec<Ipv4Addr> = Vec::new();
+        let mut arp_stats = None;
+
+        if let Some((subnet, prefix_len)) = config.subnet.as_v4() {
+            if config.methods.contains(DiscoveryMethods::ARP) {
+                let arp_started = Instant::now();
+
+                // Check ARP table first (instant)
+                if let Ok(entries) = get_arp_table() {
+                    for entry in entries {
+                        if is_in_subnet(entry.ip, subnet, prefix_len) {
+                            candidates.push(entry.ip);
+                        }
+                    }
+                }
+
+                // Also do active scan for hosts not in ARP table
+                if let Ok(hosts) = scan_subnet(subnet, prefix_len, config.arp_rate_limit).await {
+                    for host in hosts {
+                        if !candidates.contains(&host) {
+                            candidates.push(host);
+                        }
+                    }
+                }
+
+                let before = candidates.len();
+                candidates.retain(|&ip| !excluded(ip));
+                arp_stats = Some(MethodStats {
+                    responders: candidates.len(),
+                    excluded: before - candidates.len(),
+                    duration_ms: arp_started.elapsed().as_millis() as u64,
+                    ..Default::default()
+                });
+            } else if config.methods.contains(DiscoveryMethods::PROBE) {
+                // Without ARP, probe every address in the subnet directly
+                candidates = subnet_hosts(subnet, prefix_len);
+                candidates.retain(|&ip| !excluded(ip));
+            }
+        }
+        let arp_hits = if arp_stats.is_some() { candidates.clone() } else { Vec::new() };
+
+        // Step 1a: Neighbor discovery for IPv6 subnets, which are too large to sweep
+        let mut ndp_stats = None;
+        let mut ndp_neighbors: Vec<Ipv6Neighbor> = Vec::new();
+        if config.subnet.is_ipv6() {
+            let started = Instant::now();
+            let mut stats = MethodStats::default();
+            let interface = config.interface.as_deref();
+            match ndp::discover(&config.subnet, interface, self.sockets.clone(), ndp::DEFAULT_WAIT).await {
+                Ok(scan) => {
+                    stats.errors = scan.errors;
+                    for neighbor in scan.neighbors {
+                        if excluded_ip(IpAddr::V6(neighbor.ip)) {
+                            stats.excluded += 1;
+                        } else {
+                            ndp_neighbors.push(neighbor);
+                        }
+                    }
+                }
+                Err(e) => {
+                    warn!(error = %e, "Neighbor discovery failed");
+                    stats.errors.push(e.to_string());
+                }
+            }
+            stats.responders = ndp_neighbors.len();
+            stats.duration_ms = started.elapsed().as_millis() as u64;
+            ndp_stats = Some(stats);
+        }
 
-        if config.use_arp {
-            // Check ARP table first (instant)
-            if let Ok(entries) = get_arp_table() {
-                for entry in entries {
-                    if is_in_subnet(entry.ip, config.subnet, config.prefix_len) {
-                        candidates.push(entry.ip);
+        // Step 1b: ICMP echo sweeps for routed subnets ARP can't reach
+        let mut icmp_stats = None;
+        let mut icmp_hits: Vec<Ipv4Addr> = Vec::new();
+        if !config.icmp_sweeps.is_empty() {
+            let started = Instant::now();
+            let mut stats = MethodStats::default();
+            for sweep in &config.icmp_sweeps {
+                match sweep_subnet(sweep, self.sockets.clone(), excluded).await {
+                    Ok((result, socket)) => {
+                        debug!(
+                            subnet = %sweep.subnet,
+                            prefix = sweep.prefix_len,
+                            socket = ?socket,
+                            responders = result.responders.len(),
+                            "ICMP sweep complete"
+                        );
+                        stats.excluded += result.excluded;
+                        for host in result.responders {
+                            if !icmp_hits.contains(&host) {
+                                icmp_hits.push(host);
+                            }
+                        }
                     }
+                    Err(e) => {
+                        warn!(subnet = %sweep.subnet, error = %e, "ICMP sweep failed");
+                        stats.errors.push(format!("{}/{}: {}", sweep.subnet, sweep.prefix_len, e));
+                    }
+                }
+            }
+            stats.responders = icmp_hits.len();
+            stats.duration_ms = started.elapsed().as_millis() as u64;
+            icmp_stats = Some(stats);
+
+            for &host in &icmp_hits {
+                if !candidates.contains(&host) {
+                    candidates.push(host);
                 }
             }
+        }
 
-            // Also do active scan for hosts not in ARP table
-            if let Ok(hosts) = scan_subnet(config.subnet, config.prefix_len).await {
-                for host in hosts {
-                    if !candidates.contains(&host) {
-                        candidates.push(host);
+        // Step 1c: Hosts advertising themselves over mDNS. Advertisements
+        // that carry a hwid are registered as they are; the rest are probed
+        // for MCUmgr like ARP hits, keeping the hostname they advertised.
+        let mut mdns_stats = None;
+        let mut mdns_devices: Vec<Device> = Vec::new();
+        let mut mdns_hostnames: HashMap<Ipv4Addr, String> = HashMap::new();
+        if config.enable_mdns {
+            let started = Instant::now();
+            let mut stats = MethodStats::default();
+            match mdns::browse(&config.mdns_service_types, mdns::BROWSE_WINDOW).await {
+                Ok(services) => {
+                    for service in services {
+                        if excluded(service.ip) {
+                            stats.excluded += 1;
+                            continue;
+                        }
+                        stats.responders += 1;
+                        match service.to_device() {
+                            Some(device) => mdns_devices.push(device),
+                            None => {
+                                mdns_hostnames.insert(service.ip, service.hostname);
+                            }
+                        }
                     }
                 }
+                Err(e) => {
+                    warn!(error = %e, "mDNS browse failed");
+                    stats.errors.push(e.to_string());
+                }
+            }
+            stats.duration_ms = started.elapsed().as_millis() as u64;
+            mdns_stats = Some(stats);
+
+            candidates.retain(|&ip| !mdns_devices.iter().any(|d| d.discovery.ip == IpAddr::V4(ip)));
+            for &host in mdns_hostnames.keys() {
+                if !candidates.contains(&host) {
+                    candidates.push(host);
+                }
             }
-        } else {
-            // Just scan the subnet
-            candidates = scan_subnet(config.subnet, config.prefix_len).await?;
         }
 
-        debug!("Found {} candidate hosts", candidates.len());
+        // Link-local neighbors keep the scope of the interface they were found on
+        let mut targets: Vec<SocketAddr> =
+            candidates.iter().map(|&ip| SocketAddr::from((ip, config.mcumgr_port))).collect();
+        targets.extend(ndp_neighbors.iter().map(|n| n.socket_addr(config.mcumgr_port)));
+
+        debug!("Found {} candidate hosts", targets.len());
 
         // Step 2: Probe for MCUmgr devices
-        let mcumgr_hosts = probe_hosts(&candidates, config.mcumgr_port).await;
+        let mcumgr_hosts = if config.methods.contains(DiscoveryMethods::PROBE) {
+            let step = (targets.len() / PROGRESS_EVENTS).max(1);
+            probe_hosts_with_progress(&targets, |done, total| {
+                if done % step == 0 || done == total {
+                    let _ = self.event_tx.send(DiscoveryEvent::ScanProgress { done, total });
+                }
+            })
+            .await
+        } else {
+            Vec::new()
+        };
 
         debug!("Found {} MCUmgr devices", mcumgr_hosts.len());
 
+        let found_v4 = |hit: &dyn Fn(&Ipv4Addr) -> bool| {
+            mcumgr_hosts.iter().filter(|addr| matches!(addr.ip(), IpAddr::V4(ip) if hit(&ip))).count()
+        };
+        if let Some(stats) = arp_stats.as_mut() {
+            stats.devices = found_v4(&|ip| arp_hits.contains(ip));
+        }
+        if let Some(stats) = icmp_stats.as_mut() {
+            stats.devices = found_v4(&|ip| icmp_hits.contains(ip));
+        }
+        if let Some(stats) = mdns_stats.as_mut() {
+            stats.devices = mdns_devices.len() + found_v4(&|ip| mdns_hostnames.contains_key(ip));
+        }
+        if let Some(stats) = ndp_stats.as_mut() {
+            stats.devices = mcumgr_hosts.iter().filter(|addr| addr.is_ipv6()).count();
+        }
+        *self.last_stats.write().await = ScanStats {
+            arp: arp_stats,
+            icmp: icmp_stats,
+            mdns: mdns_stats,
+            ndp: ndp_stats,
+            can: None,
+        };
+
         // Step 3: Query device information
-        let query_results = query_hosts(&mcumgr_hosts, config.mcumgr_port).await;
+        let query_results = query_hosts(
+            &mcumgr_hosts,
+            config.query_concurrency,
+            config.query_timeout_ms,
+        )
+        .await;
 
         // Step 4: Get LLDP info for port mapping
-        let lldp_neighbors = if config.use_lldp {
-            get_lldp_neighbors().unwrap_or_default()
+        let lldp_neighbors = if config.methods.contains(DiscoveryMethods::LLDP) {
+            let neighbors = get_lldp_neighbors().unwrap_or_default();
+            self.observe_lldp_neighbors(neighbors.clone(), std::time::Instant::now()).await;
+            neighbors
         } else {
             Vec::new()
         };
 
-        // Step 5: Build/update device registry
-        let mut discovered = Vec::new();
-        let mut devices = self.devices.write().await;
-        let existing_ids: Vec<String> = devices.keys().cloned().collect();
+        // Step 4b: Nodes on CAN buses
+        let can_nodes = match &config.can {
+            Some(can) => {
+                let started = Instant::now();
+                let (nodes, mut stats) = scan_can(can, &config.overrides).await;
+                stats.duration_ms = started.elapsed().as_millis() as u64;
+                self.last_stats.write().await.can = Some(stats);
+                nodes
+            }
+            None => Vec::new(),
+        };
 
-        for (ip, result) in query_results {
-            let mut device =
-                query_result_to_device(IpAddr::V4(ip), config.mcumgr_port, result);
+        // Step 5: Build/update device registry
+        // Parents picked by interface need to know where each device was heard
+        let arp_table = if config.parents.iter().any(|p| p.interface.is_some()) {
+            get_arp_table().unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        // Everything about the found devices that doesn't depend on the
+        // registry is worked out before editing it
+        let mut found = Vec::new();
+        for (addr, result) in query_results {
+            // The MAC identifies devices that don't report a hardware ID
+            let mac = match addr.ip() {
+                IpAddr::V4(ip) => get_mac_for_ip(ip),
+                IpAddr::V6(ip) => ndp_neighbors.iter().find(|n| n.ip == ip).map(|n| n.mac.clone()),
+            };
+            let mut device = query_result_to_device(addr.ip(), addr.port(), mac.as_deref(), result);
+            if let SocketAddr::V6(v6) = addr {
+                device.discovery.scope_id = (v6.scope_id() != 0).then_some(v6.scope_id());
+            }
 
             // Apply LLDP port mapping
-            if let Some(mac) = get_mac_for_ip(ip) {
-                device.discovery.mac = Some(mac.clone());
-                if let Some(port) = find_port_for_mac(&lldp_neighbors, &mac) {
-                    device.discovery.switch_port = Some(port);
+            if let Some(port) = mac.as_deref().and_then(|mac| find_port_for_mac(&lldp_neighbors, mac)) {
+                device.discovery.switch_port = Some(port);
+            }
+
+            if let IpAddr::V4(ip) = addr.ip() {
+                if let Some(hostname) = mdns_hostnames.get(&ip) {
+                    device.discovery.hostname = Some(hostname.clone());
                 }
             }
 
@@ -226,98 +689,212 @@ impl DiscoveryScanner {
             if let Some(override_cfg) = config
                 .overrides
                 .iter()
-                .find(|o| o.hwid == device.id.0)
+                .find(|o| o.matches(&device))
             {
-                if let Some(ref name) = override_cfg.name {
-                    device.name = name.clone();
-                }
-                if let Some(port) = override_cfg.port {
-                    device.discovery.switch_port = Some(port);
-                }
-                if let Some(ref model) = override_cfg.model_path {
-                    device.model_path = Some(model.clone());
-                }
+                override_cfg.apply(&mut device);
             }
 
             // Set parent ID if configured
-            if let Some(ref parent) = config.parent {
+            let interface = match addr {
+                SocketAddr::V6(v6) => ndp_neighbors.iter().find(|n| n.ip == *v6.ip()).map(|n| n.interface.clone()),
+                SocketAddr::V4(_) => heard_on(&arp_table, &lldp_neighbors, addr.ip(), mac.as_deref()),
+            };
+            if let Some(parent) = parent_for(&config.parents, addr.ip(), interface.as_deref()) {
                 device.parent_id = Some(DeviceId::from_hwid(&parent.name));
             }
+            found.push(device);
+        }
 
-            // Check for IP address conflicts - find any existing device with same IP
-            let device_ip = device.discovery.ip;
-            let conflicting_id = devices.iter()
-                .find(|(id, d)| d.discovery.ip == device_ip && *id != &device.id.0)
-                .map(|(id, _)| id.clone());
-
-            if let Some(old_id) = conflicting_id {
-                let new_has_real_id = !device.id.0.starts_with("temp-");
-                let old_has_temp_id = old_id.starts_with("temp-");
-
-                if new_has_real_id && old_has_temp_id {
-                    // New device has real hwid, old had temp - remove old entry
-                    debug!(
-                        old_id = %old_id,
-                        new_id = %device.id,
-                        ip = %device_ip,
-                        "Replacing temp device ID with real hardware ID"
-                    );
-                    devices.remove(&old_id);
-                    let _ = self.event_tx.send(DiscoveryEvent::DeviceOffline(DeviceId::from_hwid(&old_id)));
-                } else if !new_has_real_id && !old_has_temp_id {
-                    // New device has temp ID but old has real ID - skip the temp one
-                    debug!(
-                        old_id = %old_id,
-                        temp_id = %device.id,
-                        ip = %device_ip,
-                        "Ignoring temp ID, device already registered with real hardware ID"
-                    );
-                    // Update the existing device instead
-                    if let Some(existing) = devices.get_mut(&old_id) {
-                        existing.status = DeviceStatus::Online;
-                        let _ = self.event_tx.send(DiscoveryEvent::DeviceUpdated(existing.clone()));
-                        discovered.push(existing.clone());
+        for device in &mut mdns_devices {
+            if let IpAddr::V4(ip) = device.discovery.ip {
+                if let Some(mac) = get_mac_for_ip(ip) {
+                    if let Some(port) = find_port_for_mac(&lldp_neighbors, &mac) {
+                        device.discovery.switch_port = Some(port);
                     }
-                    continue;
-                } else if new_has_real_id && !old_has_temp_id && device.id.0 != old_id {
-                    // Both have real IDs but different - IP conflict warning
-                    tracing::warn!(
-                        old_id = %old_id,
-                        new_id = %device.id,
-                        ip = %device_ip,
-                        "IP address conflict: two different devices claim same IP"
-                    );
+                    device.discovery.mac = Some(mac);
                 }
             }
+            if let Some(override_cfg) = config.overrides.iter().find(|o| o.matches(device)) {
+                override_cfg.apply(device);
+            }
+            let interface = heard_on(&arp_table, &lldp_neighbors, device.discovery.ip, device.discovery.mac.as_deref());
+            if let Some(parent) = parent_for(&config.parents, device.discovery.ip, interface.as_deref()) {
+                device.parent_id = Some(DeviceId::from_hwid(&parent.name));
+            }
+        }
+
+        let can_devices: Vec<Device> = can_nodes
+            .into_iter()
+            .map(|node| {
+                let mut device = Device::new_can(
+                    node.address.clone(),
+                    format!("{}-node{}", node.address.bus, node.address.node_id),
+                );
+                device.status = DeviceStatus::Online;
+                if let Some(override_cfg) = config.overrides.iter().find(|o| o.matches(&device)) {
+                    override_cfg.apply(&mut device);
+                }
+                // A CAN bus is the interface its nodes are heard on
+                if let Some(parent) = parent_for(&config.parents, device.discovery.ip, Some(node.address.bus.as_str())) {
+                    device.parent_id = Some(DeviceId::from_hwid(&parent.name));
+                }
+                device
+            })
+            .collect();
+
+        let static_devices: Vec<Device> = config
+            .overrides
+            .iter()
+            .filter_map(DeviceOverride::static_device)
+            .map(|mut device| {
+                if let Some(parent) = parent_for(&config.parents, device.discovery.ip, None) {
+                    device.parent_id = Some(DeviceId::from_hwid(&parent.name));
+                }
+                device
+            })
+            .collect();
 
-            // Check if new or updated
-            let is_new = !devices.contains_key(&device.id.0);
-            devices.insert(device.id.0.clone(), device.clone());
+        // The registry is updated in one edit
+        let (discovered, total, events) = self
+            .edit_registry(move |registry| {
+                let mut events = Vec::new();
+                let mut discovered: Vec<Device> = Vec::new();
+                let existing_ids: Vec<String> = registry.devices.keys().cloned().collect();
+
+                for device in found {
+                    // Check for IP address conflicts - find any existing device with same IP
+                    let device_ip = device.discovery.ip;
+                    let conflicting_id = registry.devices.iter()
+                        .find(|(id, d)| d.discovery.ip == device_ip && *id != &device.id.0)
+                        .map(|(id, _)| id.clone());
+
+                    if let Some(old_id) = conflicting_id {
+                        let new_has_real_id = !device.id.is_derived();
+                        let old_has_temp_id = DeviceId(old_id.clone()).is_derived();
+
+                        if new_has_real_id && old_has_temp_id {
+                            // New device has real hwid, old had temp - remove old entry
+                            debug!(
+                                old_id = %old_id,
+                                new_id = %device.id,
+                                ip = %device_ip,
+                                "Replacing temp device ID with real hardware ID"
+                            );
+                            registry.devices.remove(&old_id);
+                            events.push(DiscoveryEvent::DeviceOffline(DeviceId::from_hwid(&old_id)));
+                        } else if !new_has_real_id && !old_has_temp_id {
+                            // New device has temp ID but old has real ID - skip the temp one
+                            debug!(
+                                old_id = %old_id,
+                                temp_id = %device.id,
+                                ip = %device_ip,
+                                "Ignoring temp ID, device already registered with real hardware ID"
+                            );
+                            // Update the existing device instead
+                            if let Some(existing) = registry.devices.get_mut(&old_id) {
+                                existing.status = DeviceStatus::Online;
+                                events.push(DiscoveryEvent::DeviceUpdated(existing.clone()));
+                                discovered.push(existing.clone());
+                            }
+                            continue;
+                        } else if new_has_real_id && !old_has_temp_id && device.id.0 != old_id {
+                            // Both have real IDs but different - IP conflict warning
+                            tracing::warn!(
+                                old_id = %old_id,
+                                new_id = %device.id,
+                                ip = %device_ip,
+                                "IP address conflict: two different devices claim same IP"
+                            );
+                        }
+                    }
 
-            if is_new {
-                let _ = self.event_tx.send(DiscoveryEvent::DeviceDiscovered(device.clone()));
-            } else {
-                let _ = self.event_tx.send(DiscoveryEvent::DeviceUpdated(device.clone()));
-            }
+                    events.push(register_device(registry, device.clone()));
+                    discovered.push(device);
+                }
 
-            discovered.push(device);
-        }
+                for mut device in mdns_devices {
+                    // Keep what was learned about the device (pose, firmware) across scans
+                    if let Some(existing) = registry.devices.get(&device.id.0) {
+                        let mut updated = existing.clone();
+                        updated.status = DeviceStatus::Online;
+                        updated.name = device.name;
+                        updated.discovery.ip = device.discovery.ip;
+                        updated.discovery.port = device.discovery.port;
+                        updated.discovery.hostname = device.discovery.hostname;
+                        updated.discovery.last_seen = device.discovery.last_seen;
+                        updated.discovery.switch_port = device.discovery.switch_port.or(updated.discovery.switch_port);
+                        updated.discovery.mac = device.discovery.mac.or(updated.discovery.mac);
+                        updated.info.board = device.info.board.or(updated.info.board);
+                        device = updated;
+                    }
+
+                    events.push(register_device(registry, device.clone()));
+                    discovered.push(device);
+                }
 
-        // Mark missing devices as offline
-        for id in existing_ids {
-            if !discovered.iter().any(|d| d.id.0 == id) {
-                if let Some(device) = devices.get_mut(&id) {
-                    if device.status == DeviceStatus::Online {
-                        device.status = DeviceStatus::Offline;
-                        let _ = self
-                            .event_tx
-                            .send(DiscoveryEvent::DeviceOffline(device.id.clone()));
+                for mut device in can_devices {
+                    // Keep what was learned about the node (pose, fragment data) across scans
+                    if let Some(existing) = registry.devices.get(&device.id.0) {
+                        let mut updated = existing.clone();
+                        updated.status = DeviceStatus::Online;
+                        updated.discovery.last_seen = device.discovery.last_seen;
+                        updated.name = device.name;
+                        updated.info.board = device.info.board.or(updated.info.board);
+                        updated.firmware.name = device.firmware.name.or(updated.firmware.name);
+                        device = updated;
                     }
+
+                    events.push(register_device(registry, device.clone()));
+                    discovered.push(device);
                 }
-            }
-        }
 
-        let total = devices.len();
+                // Static devices from overrides go in after live discovery, so a
+                // device found at the same address takes their place
+                let mut pinned = Vec::new();
+                for mut device in static_devices {
+                    let ip = device.discovery.ip;
+                    if discovered.iter().any(|d| d.discovery.ip == ip) {
+                        if !discovered.iter().any(|d| d.id == device.id) && registry.devices.remove(&device.id.0).is_some() {
+                            events.push(DiscoveryEvent::DeviceRemoved(device.id.clone()));
+                        }
+                        continue;
+                    }
+                    // Keep a pose placed by the user over the configured one
+                    if let Some(existing) = registry.devices.get(&device.id.0) {
+                        let mut updated = existing.clone();
+                        updated.name = device.name;
+                        updated.discovery.ip = ip;
+                        updated.discovery.discovery_method = DiscoveryMethod::Manual;
+                        updated.model_path = device.model_path.or(updated.model_path);
+                        updated.info.board = device.info.board.or(updated.info.board);
+                        updated.firmware.name = device.firmware.name.or(updated.firmware.name);
+                        updated.parent_id = device.parent_id.or(updated.parent_id);
+                        device = updated;
+                    }
+
+                    pinned.push(device.id.0.clone());
+                    events.push(register_device(registry, device));
+                }
+
+                // Mark missing devices as offline; static devices are never pruned
+                for id in existing_ids {
+                    if !discovered.iter().any(|d| d.id.0 == id) && !pinned.contains(&id) {
+                        if let Some(device) = registry.devices.get_mut(&id) {
+                            if device.status == DeviceStatus::Online {
+                                device.status = DeviceStatus::Offline;
+                                events.push(DiscoveryEvent::DeviceOffline(device.id.clone()));
+                            }
+                        }
+                    }
+                }
+
+                (discovered, registry.devices.len(), events)
+            })
+            .await;
+
+        for event in events {
+            let _ = self.event_tx.send(event);
+        }
         let _ = self.event_tx.send(DiscoveryEvent::ScanCompleted {
             found: discovered.len(),
             total,
@@ -332,70 +909,353 @@ impl DiscoveryScanner {
         Ok(discovered)
     }
 
-    /// Lightweight heartbeat check for known devices
-    /// Checks if IPs are still reachable and marks devices online/offline accordingly
+    /// Run `edit` on the registry, returning its result once the edit is
+    /// published
+    ///
+    /// Edits are synchronous, so nothing awaits while the registry is being
+    /// changed. Panics if `edit` panicked, like a poisoned lock would.
+    async fn edit_registry<R, F>(&self, edit: F) -> R
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut Registry) -> R + Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        let published = self.registry.edit(Box::new(move |registry| {
+            let _ = tx.send(edit(registry));
+        }));
+        let _ = published.await;
+        rx.await.expect("registry edit panicked")
+    }
+
+    /// Lightweight heartbeat check for the known devices that are due one
+    ///
+    /// Checks each device with its heartbeat method, records latency and
+    /// marks devices online/offline accordingly. Online devices are checked
+    /// every heartbeat interval; unanswered checks are retried with backoff
+    /// (see [`crate::schedule`]), each retry reported as `HeartbeatBackoff`.
     pub async fn heartbeat(&self) -> Result<()> {
-        let devices = self.devices.read().await;
-
-        // Collect all known devices (both online and offline) with their IPs
-        let device_ips: Vec<(String, Ipv4Addr, DeviceStatus)> = devices
-            .values()
-            .filter_map(|d| {
-                if let IpAddr::V4(ip) = d.discovery.ip {
-                    Some((d.id.0.clone(), ip, d.status.clone()))
-                } else {
-                    None
-                }
-            })
+        let timing = ScheduleTiming::from(&*self.config.read().await);
+        let registry = self.registry.snapshot();
+        let devices = &registry.devices;
+
+        // CAN nodes have no IP; scans keep their status
+        let ids = devices.values().filter(|d| !d.is_can()).map(|d| d.id.0.as_str());
+        let due = self.schedule.write().await.due(ids, std::time::Instant::now());
+
+        // Collect the due devices (both online and offline) with how to check them
+        let targets: Vec<(String, SocketAddr, HeartbeatMethod, DeviceStatus)> = due
+            .into_iter()
+            .filter_map(|id| devices.get(&id))
+            .map(|d| (d.id.0.clone(), d.discovery.socket_addr(), HeartbeatMethod::effective_for(d), d.status))
             .collect();
 
-        if device_ips.is_empty() {
+        if targets.is_empty() {
             return Ok(());
         }
 
-        let online_count = device_ips.iter().filter(|(_, _, s)| *s == DeviceStatus::Online).count();
-        let offline_count = device_ips.iter().filter(|(_, _, s)| *s == DeviceStatus::Offline).count();
+        let online_count = targets.iter().filter(|t| t.3 == DeviceStatus::Online).count();
+        let offline_count = targets.iter().filter(|t| t.3 == DeviceStatus::Offline).count();
+
+
+        debug!(online = online_count, offline = offline_count, "Heartbeat check");
+
+        // Check all devices in parallel
+        let mut tasks = JoinSet::new();
+        for (id, addr, method, old_status) in targets {
+            tasks.spawn(async move {
+                let latency = check_device(addr, method).await;
+                (id, addr.ip(), old_status, latency)
+            });
+        }
 
-        drop(devices); // Release read lock before async operation
+        let mut results = Vec::new();
+        while let Some(result) = tasks.join_next().await {
+            if let Ok(result) = result {
+                results.push(result);
+            }
+        }
 
-        info!(online = online_count, offline = offline_count, "Heartbeat check");
+        // Record latency history
+        {
+            let mut latency = self.latency.write().await;
+            for (id, _, _, rtt) in &results {
+                latency
+                    .entry(id.clone())
+                    .or_default()
+                    .record(rtt.map(|d| d.as_secs_f64() * 1000.0));
+            }
+        }
 
-        // Ping all known device IPs
-        let all_ips: Vec<Ipv4Addr> = device_ips.iter().map(|(_, ip, _)| *ip).collect();
-        let reachable = ping_hosts(&all_ips).await;
-        let reachable_set: std::collections::HashSet<_> = reachable.into_iter().collect();
+        // Schedule the next checks
+        let now = std::time::Instant::now();
+        let results: Vec<_> = {
+            let mut schedule = self.schedule.write().await;
+            results
+                .into_iter()
+                .map(|(id, ip, old_status, rtt)| {
+                    let is_reachable = rtt.is_some();
+                    let backoff = if is_reachable {
+                        schedule.succeeded(&id, now, &timing);
+                        None
+                    } else {
+                        Some(schedule.failed(&id, now, &timing))
+                    };
+                    (id, ip, old_status, is_reachable, backoff)
+                })
+                .collect()
+        };
 
         // Update device statuses
-        let mut devices = self.devices.write().await;
-        for (id, ip, old_status) in device_ips {
-            let is_reachable = reachable_set.contains(&ip);
-
-            if let Some(device) = devices.get_mut(&id) {
-                match (old_status, is_reachable) {
-                    (DeviceStatus::Online, false) => {
-                        // Was online, now unreachable -> mark offline
-                        info!(device = %id, ip = %ip, "Device went offline");
-                        device.status = DeviceStatus::Offline;
-                        let _ = self.event_tx.send(DiscoveryEvent::DeviceOffline(device.id.clone()));
+        let events = self
+            .edit_registry(move |registry| {
+                let mut events = Vec::new();
+                for (id, ip, old_status, is_reachable, backoff) in results {
+                    let Some(device) = registry.devices.get_mut(&id) else {
+                        continue;
+                    };
+                    match (old_status, is_reachable) {
+                        (DeviceStatus::Online, false) => {
+                            // Was online, now unreachable -> mark offline
+                            info!(device = %id, ip = %ip, "Device went offline");
+                            device.status = DeviceStatus::Offline;
+                            events.push(DiscoveryEvent::DeviceOffline(device.id.clone()));
+                        }
+                        (DeviceStatus::Offline, true) => {
+                            // Was offline, now reachable -> mark online
+                            info!(device = %id, ip = %ip, "Device came back online");
+                            device.status = DeviceStatus::Online;
+                            events.push(DiscoveryEvent::DeviceUpdated(device.clone()));
+                        }
+                        _ => {
+                            // No change
+                        }
                     }
-                    (DeviceStatus::Offline, true) => {
-                        // Was offline, now reachable -> mark online
-                        info!(device = %id, ip = %ip, "Device came back online");
-                        device.status = DeviceStatus::Online;
-                        let _ = self.event_tx.send(DiscoveryEvent::DeviceUpdated(device.clone()));
+                    if let Some(backoff) = backoff {
+                        debug!(device = %id, failures = backoff.failures, retry_in = ?backoff.retry_in, "Heartbeat retry scheduled");
+                        events.push(DiscoveryEvent::HeartbeatBackoff {
+                            id: device.id.clone(),
+                            failures: backoff.failures,
+                            retry_in_ms: backoff.retry_in.as_millis() as u64,
+                        });
                     }
-                    _ => {
-                        // No change
+                }
+                events
+            })
+            .await;
+        for event in events {
+            let _ = self.event_tx.send(event);
+        }
+
+        Ok(())
+    }
+
+    /// Probe addresses that just showed up in the ARP table and register the
+    /// MCUmgr devices among them, without waiting for the next scan
+    async fn probe_new_hosts(&self, hosts: &[Ipv4Addr]) {
+        let config = self.config.read().await.clone();
+        let targets: Vec<SocketAddr> = hosts.iter().map(|&ip| SocketAddr::from((ip, config.mcumgr_port))).collect();
+        let found = probe_hosts_with_progress(&targets, |_, _| {}).await;
+        if found.is_empty() {
+            return;
+        }
+        let results = query_hosts(&found, config.query_concurrency, config.query_timeout_ms).await;
+
+        let arp_table = if config.parents.iter().any(|p| p.interface.is_some()) {
+            get_arp_table().unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        let mut found = Vec::new();
+        for (addr, result) in results {
+            let IpAddr::V4(ip) = addr.ip() else {
+                continue;
+            };
+            let mac = get_mac_for_ip(ip);
+            let mut device = query_result_to_device(addr.ip(), addr.port(), mac.as_deref(), result);
+            if let Some(override_cfg) = config.overrides.iter().find(|o| o.matches(&device)) {
+                override_cfg.apply(&mut device);
+            }
+            let interface = heard_on(&arp_table, &[], addr.ip(), mac.as_deref());
+            if let Some(parent) = parent_for(&config.parents, addr.ip(), interface.as_deref()) {
+                device.parent_id = Some(DeviceId::from_hwid(&parent.name));
+            }
+            found.push(device);
+        }
+
+        let events = self
+            .edit_registry(move |registry| {
+                let mut events = Vec::new();
+                for device in found {
+                    // Leave address conflicts to a full scan
+                    if registry.devices.values().any(|d| d.discovery.ip == device.discovery.ip && d.id != device.id) {
+                        continue;
                     }
+                    info!(device = %device.id, ip = %device.discovery.ip, "New host in ARP table is an MCUmgr device");
+                    events.push(register_device(registry, device));
                 }
+                events
+            })
+            .await;
+        for event in events {
+            let _ = self.event_tx.send(event);
+        }
+    }
+
+    /// Latency samples recorded by the heartbeat for a device (oldest first)
+    pub async fn latency_history(&self, id: &str) -> Vec<LatencySample> {
+        self.latency
+            .read()
+            .await
+            .get(id)
+            .map(|h| h.samples())
+            .unwrap_or_default()
+    }
+
+    /// Override the heartbeat method for a device (None = auto-select)
+    /// Returns the updated device, or None if it is not in the registry
+    pub async fn set_heartbeat_method(&self, id: &str, method: Option<HeartbeatMethod>) -> Option<Device> {
+        let key = id.to_string();
+        let (device, changed) = self
+            .edit_registry(move |registry| {
+                let device = registry.devices.get_mut(&key)?;
+                let changed = device.discovery.heartbeat_method != method;
+                device.discovery.heartbeat_method = method;
+                Some((device.clone(), changed))
+            })
+            .await?;
+        if changed {
+            // Samples from the previous method aren't comparable
+            self.latency.write().await.remove(id);
+        }
+
+        info!(device = %id, method = ?method, "Heartbeat method updated");
+        let _ = self.event_tx.send(DiscoveryEvent::DeviceUpdated(device.clone()));
+        Some(device)
+    }
+
+    /// Record the result of a firmware check
+    /// Sends an update event only when the status changed
+    pub async fn set_firmware_status(&self, id: &str, status: FirmwareStatus) -> Option<Device> {
+        let id = id.to_string();
+        let (device, changed) = self
+            .edit_registry(move |registry| {
+                let device = registry.devices.get_mut(&id)?;
+                let changed = device.firmware_status != status;
+                device.firmware_status = status;
+                Some((device.clone(), changed))
+            })
+            .await?;
+        if !changed {
+            return Some(device);
+        }
+
+        let _ = self.event_tx.send(DiscoveryEvent::DeviceUpdated(device.clone()));
+        Some(device)
+    }
+
+    /// Apply `edit` to a device in one registry edit, so edits to different
+    /// fields of the same device don't overwrite each other
+    /// Returns the updated device, or None if it is not in the registry
+    pub async fn modify_device(&self, id: &str, edit: impl FnOnce(&mut Device) + Send + 'static) -> Option<Device> {
+        let id = id.to_string();
+        let device = self
+            .edit_registry(move |registry| {
+                let device = registry.devices.get_mut(&id)?;
+                edit(device);
+                Some(device.clone())
+            })
+            .await?;
+
+        let _ = self.event_tx.send(DiscoveryEvent::DeviceUpdated(device.clone()));
+        Some(device)
+    }
+
+    /// Record LLDP neighbors seen at `now` (from lldpd or received frames),
+    /// refreshing their TTLs
+    ///
+    /// Sends `LldpNeighborChanged` for neighbors that are new or changed port.
+    pub async fn observe_lldp_neighbors(
+        &self,
+        neighbors: impl IntoIterator<Item = LldpNeighbor>,
+        now: std::time::Instant,
+    ) {
+        let mut table = self.lldp.write().await;
+        for neighbor in neighbors {
+            let event = DiscoveryEvent::LldpNeighborChanged {
+                interface: neighbor.local_interface.clone(),
+                chassis_id: neighbor.chassis_id.clone(),
+                port: parse_port_number(&neighbor.port_id),
+            };
+            if table.observe(neighbor, now) {
+                let _ = self.event_tx.send(event);
             }
         }
+    }
+
+    /// LLDP neighbors currently known, with how long ago each was refreshed
+    pub async fn lldp_neighbors(&self) -> Vec<NeighborEntry> {
+        self.lldp.read().await.entries(std::time::Instant::now())
+    }
+
+    /// Drop LLDP neighbors whose TTL ran out by `now`, returning how many
+    ///
+    /// Each sends `NeighborExpired`; a device whose MAC is the
+    /// neighbor's chassis ID is marked offline straight away rather than at
+    /// the next heartbeat or scan.
+    pub async fn expire_lldp_neighbors(&self, now: std::time::Instant) -> usize {
+        let expired = self.lldp.write().await.expire(now);
+        if expired.is_empty() {
+            return 0;
+        }
 
-        Ok(())
+        let count = expired.len();
+        let events = self
+            .edit_registry(move |registry| {
+                let mut events = Vec::new();
+                for neighbor in &expired {
+                    let port = parse_port_number(&neighbor.port_id);
+                    info!(
+                        interface = %neighbor.local_interface,
+                        chassis_id = %neighbor.chassis_id,
+                        port = %neighbor.port_id,
+                        "LLDP neighbor expired"
+                    );
+                    events.push(DiscoveryEvent::NeighborExpired {
+                        port,
+                        chassis_id: neighbor.chassis_id.clone(),
+                    });
+
+                    for device in registry.devices.values_mut() {
+                        let matches = device.discovery.mac.as_deref().is_some_and(|mac| neighbor.has_chassis_mac(mac));
+                        if matches && device.status != DeviceStatus::Offline {
+                            info!(device = %device.id, "Device went offline (LLDP neighbor expired)");
+                            device.status = DeviceStatus::Offline;
+                            events.push(DiscoveryEvent::DeviceOffline(device.id.clone()));
+                        }
+                    }
+                }
+                events
+            })
+            .await;
+        for event in events {
+            let _ = self.event_tx.send(event);
+        }
+        count
+    }
+
+    /// Refresh LLDP neighbors from lldpd and expire the ones that went quiet
+    async fn refresh_lldp(&self) {
+        match tokio::task::spawn_blocking(get_lldp_neighbors).await {
+            Ok(Ok(neighbors)) => self.observe_lldp_neighbors(neighbors, std::time::Instant::now()).await,
+            Ok(Err(e)) => debug!(error = %e, "LLDP neighbor refresh failed"),
+            Err(e) => warn!(error = %e, "LLDP neighbor refresh panicked"),
+        }
+        self.expire_lldp_neighbors(std::time::Instant::now()).await;
     }
 
     /// Run continuous discovery in background
-    /// Only runs heartbeat checks - full MCUmgr scans are manual only
+    /// Only runs heartbeat checks, and probes hosts that newly appear in the
+    /// ARP table while heartbeat is on - full MCUmgr scans are manual only
     /// NOTE: No initial scan on startup - user must manually trigger scan
     /// This allows HCDF to be loaded from file without competing with auto-discovery
     pub async fn run(&self) -> Result<()> {
@@ -405,23 +1265,62 @@ impl DiscoveryScanner {
         // This prevents auto-discovery from competing with imported HCDF configurations
         info!("Scanner ready (no auto-scan on startup - use UI to trigger discovery)");
 
-        // Use a fixed 2-second interval, but check config each time to see if heartbeat is enabled
-        let mut heartbeat_interval = interval(Duration::from_secs(2));
+        // Tick often and check the devices that are due; check config each
+        // time to see if heartbeat is enabled
+        let mut heartbeat_interval = interval(HEARTBEAT_TICK);
 
         info!("Heartbeat scheduler started (MCUmgr scans are manual only)");
+        let mut last_lldp_refresh = Instant::now();
+        // Addresses in the ARP table at the last tick (None until the first
+        // heartbeat, so hosts already there aren't probed all at once)
+        let mut arp_seen: Option<std::collections::HashSet<Ipv4Addr>> = None;
 
         loop {
             heartbeat_interval.tick().await;
 
+            // Age out LLDP neighbors once a scan has found some
+            let use_lldp = self.config.read().await.methods.contains(DiscoveryMethods::LLDP);
+            if use_lldp && last_lldp_refresh.elapsed() >= LLDP_REFRESH_INTERVAL && !self.lldp.read().await.is_empty() {
+                last_lldp_refresh = Instant::now();
+                self.refresh_lldp().await;
+            }
+
             // Check if heartbeat is enabled (config may have changed at runtime)
-            let config = self.config.read().await;
+            let config = self.config.read().await.clone();
             if !config.heartbeat_enabled {
                 // Heartbeat is disabled, skip this iteration
+                arp_seen = None;
                 continue;
             }
-            drop(config);
 
-            debug!("Running heartbeat check");
+            // Probe hosts that appeared in the ARP table right away
+            if let Some((subnet, prefix_len)) = config.subnet.as_v4() {
+                if config.methods.contains(DiscoveryMethods::ARP | DiscoveryMethods::PROBE) {
+                    let exclusions = parse_exclusions(&config.exclude);
+                    let current: std::collections::HashSet<Ipv4Addr> = get_arp_table()
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|entry| entry.ip)
+                        .filter(|&ip| is_in_subnet(ip, subnet, prefix_len))
+                        .filter(|&ip| !exclusions.iter().any(|net| net.contains(IpAddr::V4(ip))))
+                        .collect();
+                    if let Some(seen) = &arp_seen {
+                        let known = self.registry.snapshot();
+                        let new: Vec<Ipv4Addr> = current
+                            .iter()
+                            .filter(|ip| !seen.contains(ip))
+                            .filter(|&&ip| !known.devices.values().any(|d| d.discovery.ip == IpAddr::V4(ip)))
+                            .copied()
+                            .collect();
+                        if !new.is_empty() {
+                            debug!(hosts = ?new, "New hosts in ARP table");
+                            self.probe_new_hosts(&new).await;
+                        }
+                    }
+                    arp_seen = Some(current);
+                }
+            }
+
             if let Err(e) = self.heartbeat().await {
                 warn!(error = %e, "Heartbeat check failed");
             }
@@ -429,17 +1328,24 @@ impl DiscoveryScanner {
     }
 
     /// Manually add a device (sends DeviceDiscovered event)
+    /// An explicit add (e.g., HCDF import) also clears any archived entry
     pub async fn add_device(&self, device: Device) {
-        let mut devices = self.devices.write().await;
-        devices.insert(device.id.0.clone(), device.clone());
+        let added = device.clone();
+        self.edit_registry(move |registry| {
+            registry.archived.remove(&added.id.0);
+            registry.devices.insert(added.id.0.clone(), added);
+        })
+        .await;
         let _ = self.event_tx.send(DiscoveryEvent::DeviceDiscovered(device));
     }
 
     /// Update a device in the registry without sending events
     /// Used for internal updates like fragment matching
     pub async fn update_device_silent(&self, device: Device) {
-        let mut devices = self.devices.write().await;
-        devices.insert(device.id.0.clone(), device);
+        self.edit_registry(move |registry| {
+            registry.devices.insert(device.id.0.clone(), device);
+        })
+        .await;
     }
 
     /// Broadcast a device update event (for position/orientation changes, etc.)
@@ -448,16 +1354,79 @@ impl DiscoveryScanner {
     }
 
     /// Remove a device by ID string, returns true if device was found and removed
-    pub async fn remove_device(&self, id: &str) -> bool {
-        let mut devices = self.devices.write().await;
-        if let Some(device) = devices.remove(id) {
-            info!(device = %id, "Device removed from registry");
-            let _ = self.event_tx.send(DiscoveryEvent::DeviceRemoved(device.id.clone()));
-            true
-        } else {
-            false
+    ///
+    /// `Archive` moves the device out of the active registry so it can be restored
+    /// when seen again; `Forget` drops it (including any archived copy).
+    pub async fn remove_device(&self, id: &str, mode: RemoveMode) -> bool {
+        let key = id.to_string();
+        let removed = self
+            .edit_registry(move |registry| {
+                let removed = registry.devices.remove(&key);
+                match mode {
+                    RemoveMode::Archive => {
+                        if let Some(device) = &removed {
+                            registry.archived.insert(key, device.clone());
+                        }
+                        removed
+                    }
+                    RemoveMode::Forget => removed.or(registry.archived.remove(&key)),
+                }
+            })
+            .await;
+
+        match mode {
+            RemoveMode::Archive => {
+                let Some(device) = removed else {
+                    return false;
+                };
+                info!(device = %id, "Device archived");
+                let _ = self.event_tx.send(DiscoveryEvent::DeviceArchived(device.id));
+                true
+            }
+            RemoveMode::Forget => {
+                self.latency.write().await.remove(id);
+                self.schedule.write().await.reset(id);
+                match removed {
+                    Some(device) => {
+                        info!(device = %id, "Device removed from registry");
+                        let _ = self.event_tx.send(DiscoveryEvent::DeviceRemoved(device.id.clone()));
+                        true
+                    }
+                    None => false,
+                }
+            }
+        }
+    }
+}
+
+/// Insert a freshly discovered device into the registry and return the event to emit.
+/// Archived devices are restored with `DeviceUnarchived` rather than reappearing silently.
+fn register_device(registry: &mut Registry, mut device: Device) -> DiscoveryEvent {
+    let archived = registry.archived.remove(&device.id.0);
+    let was_archived = archived.is_some();
+    let is_new = !registry.devices.contains_key(&device.id.0);
+
+    // Keep a user-selected heartbeat method, and a pose placed before
+    // (or restored from the registry file), across rediscovery
+    if let Some(known) = registry.devices.get(&device.id.0).or(archived.as_ref()) {
+        if device.discovery.heartbeat_method.is_none() {
+            device.discovery.heartbeat_method = known.discovery.heartbeat_method;
+        }
+        if device.pose.is_none() {
+            device.pose = known.pose;
+            device.pose_provisional = known.pose_provisional;
         }
     }
+    registry.devices.insert(device.id.0.clone(), device.clone());
+
+    if was_archived {
+        info!(device = %device.id, "Archived device seen again, restoring");
+        DiscoveryEvent::DeviceUnarchived(device)
+    } else if is_new {
+        DiscoveryEvent::DeviceDiscovered(device)
+    } else {
+        DiscoveryEvent::DeviceUpdated(device)
+    }
 }
 
 /// Check if IP is in subnet
@@ -467,11 +1436,74 @@ fn is_in_subnet(ip: Ipv4Addr, subnet: Ipv4Addr, prefix_len: u8) -> bool {
     let mask = if prefix_len >= 32 {
         0xFFFFFFFF
     } else {
-        !((1u32 << (32 - prefix_len)) - 1)
+        !(u32::MAX >> prefix_len)
     };
     (ip_u32 & mask) == (subnet_u32 & mask)
 }
 
+/// Nodes on the configured CAN buses: those answering the identification
+/// request, or without one the nodes declared in the overrides
+async fn scan_can(config: &CanDiscoveryConfig, overrides: &[DeviceOverride]) -> (Vec<CanNode>, MethodStats) {
+    let mut stats = MethodStats::default();
+    let present = can::can_interfaces();
+    let buses = if config.interfaces.is_empty() { present.clone() } else { config.interfaces.clone() };
+
+    let mut nodes = Vec::new();
+    for bus in buses {
+        if !present.contains(&bus) {
+            stats.errors.push(format!("{}: no such CAN interface", bus));
+            continue;
+        }
+        match &config.identify {
+            Some(identify) => match can::identify_bus(&bus, identify).await {
+                Ok(found) => {
+                    debug!(bus = %bus, nodes = found.len(), "CAN identification complete");
+                    nodes.extend(found);
+                }
+                Err(e) => {
+                    warn!(bus = %bus, error = %e, "CAN identification failed");
+                    stats.errors.push(format!("{}: {:#}", bus, e));
+                }
+            },
+            None => nodes.extend(
+                overrides
+                    .iter()
+                    .filter_map(|o| CanAddress::from_device_id(&o.hwid))
+                    .filter(|address| address.bus == bus)
+                    .map(|address| CanNode { address, data: Vec::new() }),
+            ),
+        }
+    }
+    stats.responders = nodes.len();
+    stats.devices = nodes.len();
+    (nodes, stats)
+}
+
+/// Parse an exclusion entry: an address ("10.41.0.1") or a CIDR range ("10.41.0.0/28")
+pub fn parse_cidr(text: &str) -> Option<(Ipv4Addr, u8)> {
+    match text.split_once('/') {
+        Some((addr, len)) => {
+            let len: u8 = len.parse().ok()?;
+            (len <= 32).then_some((Ipv4Addr::from_str(addr).ok()?, len))
+        }
+        None => Some((Ipv4Addr::from_str(text).ok()?, 32)),
+    }
+}
+
+/// Parse the exclusion list (IPv4 or IPv6), skipping (and logging) malformed entries
+fn parse_exclusions(entries: &[String]) -> Vec<IpNet> {
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let parsed = entry.trim().parse().ok();
+            if parsed.is_none() {
+                warn!(entry = %entry, "Ignoring malformed exclusion");
+            }
+            parsed
+        })
+        .collect()
+}
+
 /// Get MAC address for an IP from ARP table
 fn get_mac_for_ip(ip: Ipv4Addr) -> Option<String> {
     if let Ok(entries) = get_arp_table() {
@@ -489,39 +1521,439 @@ fn find_port_for_mac(neighbors: &[LldpNeighbor], mac: &str) -> Option<u8> {
     crate::lldp::find_port_for_mac(neighbors, mac)
 }
 
-/// Ping multiple hosts in parallel, return list of reachable IPs
-async fn ping_hosts(hosts: &[Ipv4Addr]) -> Vec<Ipv4Addr> {
-    use tokio::task::JoinSet;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::icmp::{IcmpSocket, SocketKind};
+    use dendrite_test_support::{MockHcdfInfo, MockIdentity, MockSmpDevice};
+    use pnet::datalink::NetworkInterface;
+    use std::io;
+    use std::sync::Mutex;
+
+    fn test_device(id: &str) -> Device {
+        Device::new(
+            DeviceId::from_hwid(id),
+            id.to_string(),
+            IpAddr::V4(Ipv4Addr::new(192, 168, 186, 10)),
+            MCUMGR_PORT,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_rediscovered_archived_device_is_unarchived() {
+        let scanner = DiscoveryScanner::new(ScannerConfig::default());
+        let mut rx = scanner.subscribe();
+
+        scanner.add_device(test_device("abc")).await;
+        assert!(matches!(rx.recv().await.unwrap(), DiscoveryEvent::DeviceDiscovered(_)));
+
+        assert!(scanner.remove_device("abc", RemoveMode::Archive).await);
+        assert!(matches!(rx.recv().await.unwrap(), DiscoveryEvent::DeviceArchived(id) if id.0 == "abc"));
+        assert!(scanner.devices().await.is_empty());
+        assert_eq!(scanner.archived_devices().await.len(), 1);
+
+        // Seen again by a scan: restored with a notification, not a plain discovery
+        let event = scanner.edit_registry(|registry| register_device(registry, test_device("abc"))).await;
+        assert!(matches!(event, DiscoveryEvent::DeviceUnarchived(ref d) if d.id.0 == "abc"));
+        assert_eq!(scanner.devices().await.len(), 1);
+        assert!(scanner.archived_devices().await.is_empty());
+
+        // A second sighting is an ordinary update
+        let event = scanner.edit_registry(|registry| register_device(registry, test_device("abc"))).await;
+        assert!(matches!(event, DiscoveryEvent::DeviceUpdated(_)));
+    }
+
+    #[tokio::test]
+    async fn test_expired_lldp_neighbor_marks_device_offline() {
+        let scanner = DiscoveryScanner::new(ScannerConfig::default());
+        let mut device = test_device("abc");
+        device.status = DeviceStatus::Online;
+        device.discovery.mac = Some("02-00-00-00-00-01".to_string());
+        scanner.add_device(device).await;
+        let mut rx = scanner.subscribe();
+
+        let frame = |ttl| {
+            crate::lldp_tx::encode_frame(
+                [0x02, 0x00, 0x00, 0x00, 0x00, 0x01],
+                &crate::lldp_tx::LldpAnnouncement {
+                    chassis_mac: [0x02, 0x00, 0x00, 0x00, 0x00, 0x01],
+                    port_id: "swp4".to_string(),
+                    ttl,
+                    system_name: "abc".to_string(),
+                    system_description: "dendrite v0.1".to_string(),
+                    management_address: None,
+                },
+            )
+        };
+        let start = std::time::Instant::now();
+        let neighbor = |ttl| crate::lldp::parse_frame("eth0", &frame(ttl)).unwrap();
+        scanner.observe_lldp_neighbors([neighbor(1)], start).await;
+        scanner.observe_lldp_neighbors([neighbor(1)], start + Duration::from_millis(500)).await;
+
+        assert_eq!(scanner.expire_lldp_neighbors(start + Duration::from_millis(1200)).await, 0);
+        assert_eq!(scanner.lldp_neighbors().await.len(), 1);
+        assert_eq!(scanner.expire_lldp_neighbors(start + Duration::from_millis(1500)).await, 1);
+        assert!(scanner.lldp_neighbors().await.is_empty());
+
+        // Only the first sighting is a change
+        assert!(matches!(
+            rx.recv().await.unwrap(),
+            DiscoveryEvent::LldpNeighborChanged { port: Some(4), ref interface, .. } if interface == "eth0"
+        ));
+        assert!(matches!(
+            rx.recv().await.unwrap(),
+            DiscoveryEvent::NeighborExpired { port: Some(4), ref chassis_id } if chassis_id == "02:00:00:00:00:01"
+        ));
+        assert!(matches!(rx.recv().await.unwrap(), DiscoveryEvent::DeviceOffline(id) if id.0 == "abc"));
+        assert_eq!(scanner.get_device(&DeviceId::from_hwid("abc")).await.unwrap().status, DeviceStatus::Offline);
+
+        // Expired neighbors are gone, so nothing fires twice
+        assert_eq!(scanner.expire_lldp_neighbors(start + Duration::from_secs(10)).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_forget_removes_archived_device() {
+        let scanner = DiscoveryScanner::new(ScannerConfig::default());
+        scanner.add_device(test_device("abc")).await;
+        assert!(scanner.remove_device("abc", RemoveMode::Archive).await);
+
+        let mut rx = scanner.subscribe();
+        assert!(scanner.remove_device("abc", RemoveMode::Forget).await);
+        assert!(matches!(rx.recv().await.unwrap(), DiscoveryEvent::DeviceRemoved(id) if id.0 == "abc"));
+        assert!(scanner.archived_devices().await.is_empty());
+        assert!(!scanner.remove_device("abc", RemoveMode::Forget).await);
+
+        // Forgotten devices come back as brand new
+        let event = scanner.edit_registry(|registry| register_device(registry, test_device("abc"))).await;
+        assert!(matches!(event, DiscoveryEvent::DeviceDiscovered(_)));
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_method_and_pose_survive_rediscovery() {
+        let scanner = DiscoveryScanner::new(ScannerConfig::default());
+        let mut placed = test_device("abc");
+        placed.pose = Some([0.5, 0.0, 0.1, 0.0, 0.0, 1.0]);
+        scanner.add_device(placed).await;
+        assert!(scanner.set_heartbeat_method("abc", Some(HeartbeatMethod::TcpConnect(22))).await.is_some());
+        assert!(scanner.set_heartbeat_method("missing", Some(HeartbeatMethod::Icmp)).await.is_none());
+
+        scanner.edit_registry(|registry| register_device(registry, test_device("abc"))).await;
+        let device = scanner.get_device(&DeviceId::from_hwid("abc")).await.unwrap();
+        assert_eq!(device.discovery.heartbeat_method, Some(HeartbeatMethod::TcpConnect(22)));
+        assert_eq!(device.pose, Some([0.5, 0.0, 0.1, 0.0, 0.0, 1.0]));
+    }
+
+    /// Sockets handed in by the test: echo replies from `alive` hosts, or
+    /// no ICMP at all (as without CAP_NET_RAW or ping_group_range)
+    struct FakeSockets {
+        alive: Vec<Ipv4Addr>,
+        icmp_available: bool,
+        opened: Mutex<Vec<u8>>,
+    }
+
+    struct FakeIcmp {
+        alive: Vec<Ipv4Addr>,
+        replies: Mutex<Vec<(Vec<u8>, Ipv4Addr)>>,
+    }
 
-    let mut tasks = JoinSet::new();
+    impl IcmpSocket for FakeIcmp {
+        fn kind(&self) -> SocketKind {
+            SocketKind::Datagram
+        }
 
-    for &ip in hosts {
-        tasks.spawn(async move {
-            let result = tokio::process::Command::new("ping")
-                .args(["-c", "1", "-W", "1", &ip.to_string()])
-                .output()
-                .await;
+        fn send_to(&self, packet: &[u8], dst: Ipv4Addr) -> io::Result<()> {
+            if self.alive.contains(&dst) {
+                let mut reply = packet.to_vec();
+                reply[0] = 0;
+                self.replies.lock().unwrap().push((reply, dst));
+            }
+            Ok(())
+        }
+
+        fn recv_from(&self, buf: &mut [u8], timeout: Duration) -> io::Result<Option<(usize, Ipv4Addr)>> {
+            match self.replies.lock().unwrap().pop() {
+                Some((reply, src)) => {
+                    buf[..reply.len()].copy_from_slice(&reply);
+                    Ok(Some((reply.len(), src)))
+                }
+                None => {
+                    std::thread::sleep(timeout);
+                    Ok(None)
+                }
+            }
+        }
+    }
 
-            match result {
-                Ok(output) if output.status.success() => Some(ip),
-                _ => None,
+    impl SocketProvider for FakeSockets {
+        fn icmp_socket(&self, ttl: u8) -> io::Result<Box<dyn IcmpSocket + Send>> {
+            if !self.icmp_available {
+                return Err(io::Error::from(io::ErrorKind::PermissionDenied));
             }
+            self.opened.lock().unwrap().push(ttl);
+            Ok(Box::new(FakeIcmp { alive: self.alive.clone(), replies: Default::default() }))
+        }
+
+        fn send_frame(&self, _: &NetworkInterface, _: &[u8]) -> io::Result<()> {
+            Err(io::Error::from(io::ErrorKind::PermissionDenied))
+        }
+    }
+
+    fn sweep_only_config() -> ScannerConfig {
+        let mut sweep = IcmpSweep::new(Ipv4Addr::new(192, 0, 2, 0), 29);
+        sweep.ttl = 5;
+        sweep.rate_pps = 1000;
+        sweep.timeout_ms = 10;
+        ScannerConfig {
+            // A /32 scan leaves no ARP candidates
+            subnet: "192.0.2.255/32".parse().unwrap(),
+            methods: DiscoveryMethods::PROBE,
+            icmp_sweeps: vec![sweep],
+            ..ScannerConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scan_sweeps_on_handed_in_sockets() {
+        let sockets = Arc::new(FakeSockets {
+            alive: vec![Ipv4Addr::new(192, 0, 2, 3), Ipv4Addr::new(192, 0, 2, 5)],
+            icmp_available: true,
+            opened: Default::default(),
         });
+        let scanner = DiscoveryScanner::with_sockets(sweep_only_config(), sockets.clone());
+        scanner.scan_once().await.unwrap();
+
+        assert_eq!(*sockets.opened.lock().unwrap(), vec![5]);
+        let icmp = scanner.last_scan_stats().await.icmp.unwrap();
+        assert_eq!(icmp.responders, 2);
+        assert!(icmp.errors.is_empty());
+
+        // Without ICMP the scan still completes, with the sweep's error
+        let sockets = Arc::new(FakeSockets { alive: Vec::new(), icmp_available: false, opened: Default::default() });
+        let scanner = DiscoveryScanner::with_sockets(sweep_only_config(), sockets);
+        scanner.scan_once().await.unwrap();
+        let icmp = scanner.last_scan_stats().await.icmp.unwrap();
+        assert_eq!(icmp.responders, 0);
+        assert_eq!(icmp.errors.len(), 1);
     }
 
-    let mut reachable = Vec::new();
-    while let Some(result) = tasks.join_next().await {
-        if let Ok(Some(ip)) = result {
-            reachable.push(ip);
+    #[tokio::test]
+    async fn test_probe_only_scan_finds_known_device() {
+        let identity = MockIdentity { hwid: Some("0x5e11".to_string()), os_info: None };
+        let mock = MockSmpDevice::spawn_with_identity(MockHcdfInfo::default(), identity).await.unwrap();
+        let config = ScannerConfig {
+            // 127.0.0.1 and 127.0.0.2, probed directly without an ARP sweep
+            subnet: "127.0.0.0/30".parse().unwrap(),
+            mcumgr_port: mock.port(),
+            methods: DiscoveryMethods::PROBE,
+            ..ScannerConfig::default()
+        };
+        let scanner = DiscoveryScanner::new(config);
+        let mut events = scanner.subscribe();
+
+        let found = scanner.scan_once().await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id.0, "0x5e11");
+        assert_eq!(found[0].discovery.ip, IpAddr::V4(Ipv4Addr::LOCALHOST));
+        assert_eq!(found[0].discovery.switch_port, None);
+        assert!(scanner.last_scan_stats().await.arp.is_none());
+
+        // Lifecycle events frame the per-device ones
+        let mut lifecycle = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            lifecycle.push(match event {
+                DiscoveryEvent::ScanStarted { total_hosts } => format!("started {total_hosts}"),
+                DiscoveryEvent::ScanProgress { done, total } => format!("progress {done}/{total}"),
+                DiscoveryEvent::DeviceDiscovered(device) => format!("discovered {}", device.id),
+                DiscoveryEvent::ScanCompleted { found, .. } => format!("completed {found}"),
+                other => format!("{other:?}"),
+            });
         }
+        assert_eq!(lifecycle, ["started 2", "progress 1/2", "progress 2/2", "discovered 0x5e11", "completed 1"]);
     }
 
-    reachable
-}
+    #[tokio::test]
+    async fn test_subnet_walk_needs_probe_without_arp() {
+        let identity = MockIdentity { hwid: Some("0x5e11".to_string()), os_info: None };
+        let mock = MockSmpDevice::spawn_with_identity(MockHcdfInfo::default(), identity).await.unwrap();
+        let config = ScannerConfig {
+            subnet: "127.0.0.0/30".parse().unwrap(),
+            mcumgr_port: mock.port(),
+            methods: DiscoveryMethods::LLDP,
+            ..ScannerConfig::default()
+        };
+        let scanner = DiscoveryScanner::new(config);
+        let mut events = scanner.subscribe();
+
+        // Neither an ARP sweep nor a direct probe of the subnet runs
+        assert!(scanner.scan_once().await.unwrap().is_empty());
+        assert!(scanner.last_scan_stats().await.arp.is_none());
+        while let Ok(event) = events.try_recv() {
+            assert!(!matches!(event, DiscoveryEvent::ScanProgress { .. }), "probed a host: {event:?}");
+        }
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_discovery_methods_serde() {
+        let methods = DiscoveryMethods::ARP | DiscoveryMethods::PROBE;
+        assert!(methods.contains(DiscoveryMethods::PROBE));
+        assert!(!methods.contains(DiscoveryMethods::LLDP));
+        assert_eq!(serde_json::to_string(&methods).unwrap(), r#""ARP | PROBE""#);
+        assert_eq!(serde_json::from_str::<DiscoveryMethods>(r#""PROBE | ARP""#).unwrap(), methods);
+        assert!(serde_json::from_str::<DiscoveryMethods>(r#""SNMP""#).is_err());
+        assert!(serde_json::from_str::<DiscoveryMethods>(r#""""#).unwrap().is_empty());
+
+        let config: ScannerConfig = serde_json::from_value(
+            serde_json::to_value(ScannerConfig { methods: DiscoveryMethods::empty(), ..Default::default() }).unwrap(),
+        )
+        .unwrap();
+        assert!(config.methods.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_missing_can_interface_is_reported() {
+        let config = ScannerConfig {
+            icmp_sweeps: Vec::new(),
+            can: Some(CanDiscoveryConfig { interfaces: vec!["dendrite-nocan0".to_string()], identify: None }),
+            overrides: vec![DeviceOverride {
+                hwid: "can:dendrite-nocan0:12".to_string(),
+                hostname: None,
+                name: Some("rear-esc".to_string()),
+                port: None,
+                model_path: None,
+                board: Some("esc".to_string()),
+                app: None,
+                ip: None,
+                pose: None,
+            }],
+            ..sweep_only_config()
+        };
+        let sockets = Arc::new(FakeSockets { alive: Vec::new(), icmp_available: false, opened: Default::default() });
+        let scanner = DiscoveryScanner::with_sockets(config, sockets);
+        scanner.scan_once().await.unwrap();
+
+        let can = scanner.last_scan_stats().await.can.unwrap();
+        assert_eq!(can.errors, vec!["dendrite-nocan0: no such CAN interface".to_string()]);
+        assert_eq!(can.devices, 0);
+        assert!(scanner.devices().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_static_override_is_kept_without_discovery() {
+        let ip = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 40));
+        let config = ScannerConfig {
+            icmp_sweeps: Vec::new(),
+            overrides: vec![DeviceOverride {
+                hwid: String::new(),
+                hostname: None,
+                name: Some("lidar".to_string()),
+                port: None,
+                model_path: None,
+                board: None,
+                app: None,
+                ip: Some(ip),
+                pose: Some([0.1, 0.0, 0.2, 0.0, 0.0, 0.0]),
+            }],
+            ..sweep_only_config()
+        };
+        let sockets = Arc::new(FakeSockets { alive: Vec::new(), icmp_available: false, opened: Default::default() });
+        let scanner = DiscoveryScanner::with_sockets(config, sockets);
+
+        // Nothing answers, yet the static device is tracked, and stays so
+        for _ in 0..2 {
+            assert!(scanner.scan_once().await.unwrap().is_empty());
+            let devices = scanner.devices().await;
+            assert_eq!(devices.len(), 1);
+            assert_eq!(devices[0].id.0, "static-192.0.2.40");
+            assert_eq!(devices[0].name, "lidar");
+            assert_eq!(devices[0].status, DeviceStatus::Unknown);
+            assert_eq!(devices[0].discovery.discovery_method, DiscoveryMethod::Manual);
+            assert_eq!(devices[0].pose, Some([0.1, 0.0, 0.2, 0.0, 0.0, 0.0]));
+        }
+    }
+
+    #[test]
+    fn test_override_matches_hwid_or_hostname() {
+        let by_hostname = DeviceOverride {
+            hwid: String::new(),
+            hostname: Some("jetson-01".to_string()),
+            name: Some("companion".to_string()),
+            port: None,
+            model_path: None,
+            board: None,
+            app: None,
+            ip: None,
+            pose: None,
+        };
+        let by_hwid = DeviceOverride { hwid: "0xabc".to_string(), hostname: None, ..by_hostname.clone() };
+
+        let ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 4));
+        let mut device = Device::new(DeviceId::from_hwid("0xabc"), "jetson".to_string(), ip, 1337);
+        assert!(by_hwid.matches(&device));
+        assert!(!by_hostname.matches(&device));
+
+        device.discovery.hostname = Some("Jetson-01.local".to_string());
+        assert!(by_hostname.matches(&device));
+        by_hostname.apply(&mut device);
+        assert_eq!(device.name, "companion");
+
+        // An empty hwid never matches by itself
+        let other = Device::new(DeviceId::from_hwid(""), String::new(), IpAddr::V4(Ipv4Addr::LOCALHOST), 1337);
+        assert!(!by_hostname.matches(&other));
+    }
+
+    #[test]
+    fn test_devices_are_partitioned_between_parents() {
+        use crate::arp::ArpState;
+
+        let parent = |name: &str, subnet: Option<&str>, interface: Option<&str>| ParentConfig {
+            name: name.to_string(),
+            board: "t1_switch".to_string(),
+            ports: 6,
+            ip: None,
+            subnet: subnet.map(|s| s.parse().unwrap()),
+            interface: interface.map(String::from),
+        };
+        let parents = [
+            parent("switch-a", Some("192.168.186.0/25"), Some("eth0")),
+            parent("switch-b", Some("192.168.186.128/25"), Some("eth1")),
+        ];
+        let arp = [ArpEntry {
+            ip: Ipv4Addr::new(192, 168, 186, 10),
+            mac: "00:11:22:33:44:0a".to_string(),
+            interface: "eth1".to_string(),
+            state: ArpState::Reachable,
+        }];
+        let lldp = [LldpNeighbor {
+            local_interface: "eth0".to_string(),
+            chassis_id: "00:11:22:33:44:82".to_string(),
+            port_id: "3".to_string(),
+            port_desc: None,
+            system_name: None,
+            system_desc: None,
+            mgmt_addresses: Vec::new(),
+            ttl: None,
+        }];
+        let parent_of = |ip: [u8; 4], mac: Option<&str>| {
+            let ip = IpAddr::V4(Ipv4Addr::from(ip));
+            let interface = heard_on(&arp, &lldp, ip, mac);
+            parent_for(&parents, ip, interface.as_deref()).map(|p| p.name.as_str())
+        };
+
+        // By subnet when nothing says where the device was heard
+        assert_eq!(parent_of([192, 168, 186, 20], None), Some("switch-a"));
+        assert_eq!(parent_of([192, 168, 186, 200], None), Some("switch-b"));
+        // The interface of the ARP entry or LLDP advertisement wins
+        assert_eq!(parent_of([192, 168, 186, 10], Some("00:11:22:33:44:0a")), Some("switch-b"));
+        assert_eq!(parent_of([192, 168, 186, 130], Some("00-11-22-33-44-82")), Some("switch-a"));
+        // Neither parent claims the rest
+        assert_eq!(parent_of([10, 0, 0, 1], None), None);
+
+        // A parent with no subnet or interface takes what nobody claims
+        let catch_all = [parents[0].clone(), parent("navq95", None, None)];
+        let ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        assert_eq!(parent_for(&catch_all, ip, None).unwrap().name, "navq95");
+        assert_eq!(parent_for(&catch_all, ip, Some("eth0")).unwrap().name, "switch-a");
+    }
 
     #[test]
     fn test_is_in_subnet() {
@@ -530,5 +1962,18 @@ mod tests {
         assert!(is_in_subnet(Ipv4Addr::new(192, 168, 186, 255), subnet, 24));
         assert!(!is_in_subnet(Ipv4Addr::new(192, 168, 187, 1), subnet, 24));
         assert!(!is_in_subnet(Ipv4Addr::new(10, 0, 0, 1), subnet, 24));
+        assert!(is_in_subnet(Ipv4Addr::new(10, 0, 0, 1), subnet, 0));
+    }
+
+    #[test]
+    fn test_parse_exclusions() {
+        assert_eq!(parse_cidr("10.41.0.1"), Some((Ipv4Addr::new(10, 41, 0, 1), 32)));
+        assert_eq!(parse_cidr("10.41.0.0/28"), Some((Ipv4Addr::new(10, 41, 0, 0), 28)));
+        assert_eq!(parse_cidr("10.41.0.0/33"), None);
+        assert_eq!(parse_cidr("router"), None);
+
+        let entries = ["10.41.0.1", "bogus", " 10.41.0.16/30 ", "fe80::1c2:3ff:fe04:506"].map(String::from);
+        let parsed: Vec<String> = parse_exclusions(&entries).iter().map(ToString::to_string).collect();
+        assert_eq!(parsed, ["10.41.0.1/32", "10.41.0.16/30", "fe80::1c2:3ff:fe04:506/128"]);
     }
 }
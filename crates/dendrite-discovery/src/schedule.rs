@@ -0,0 +1,216 @@
+//! Per-device heartbeat schedule with backoff for offline devices
+//!
+//! Online devices are checked every `heartbeat_interval_secs`. A device that
+//! stops answering is retried quickly, backing off exponentially from
+//! `offline_retry_min_secs` up to `offline_retry_max_secs`, so it is noticed
+//! soon after it comes back without being polled hard while it stays away.
+//! Devices the schedule hasn't seen yet are due at once. Every delay is
+//! spread by `heartbeat_jitter` so checks of many devices don't line up.
+//!
+//! Callers pass the current time in, so tests can drive the schedule with a
+//! made-up clock.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::scanner::ScannerConfig;
+
+/// Delays the schedule works with, taken from [`ScannerConfig`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScheduleTiming {
+    /// Between checks of an online device
+    pub base: Duration,
+    /// First retry after a failed check
+    pub retry_min: Duration,
+    /// Longest wait between retries
+    pub retry_max: Duration,
+    /// Fraction each delay is spread by (0.1 = ±10%)
+    pub jitter: f64,
+}
+
+impl From<&ScannerConfig> for ScheduleTiming {
+    fn from(config: &ScannerConfig) -> Self {
+        let retry_min = Duration::from_secs(config.offline_retry_min_secs.max(1));
+        Self {
+            base: Duration::from_secs(config.heartbeat_interval_secs.max(1)),
+            retry_min,
+            retry_max: Duration::from_secs(config.offline_retry_max_secs).max(retry_min),
+            jitter: config.heartbeat_jitter.clamp(0.0, 1.0),
+        }
+    }
+}
+
+impl ScheduleTiming {
+    /// Wait before the next retry after `failures` failed checks in a row,
+    /// before jitter
+    pub fn backoff(&self, failures: u32) -> Duration {
+        let doublings = failures.saturating_sub(1).min(31);
+        self.retry_min.saturating_mul(1 << doublings).min(self.retry_max)
+    }
+}
+
+/// Where a device stands after a failed check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Backoff {
+    /// Failed checks in a row
+    pub failures: u32,
+    /// Until the next check
+    pub retry_in: Duration,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    next: Instant,
+    failures: u32,
+}
+
+/// When each device is checked next
+#[derive(Debug)]
+pub struct HeartbeatSchedule {
+    entries: HashMap<String, Entry>,
+    /// xorshift state for jitter
+    rng: u64,
+}
+
+impl Default for HeartbeatSchedule {
+    fn default() -> Self {
+        let seed = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64);
+        Self::with_seed(seed)
+    }
+}
+
+impl HeartbeatSchedule {
+    /// Create a schedule with randomly seeded jitter
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a schedule whose jitter follows `seed`
+    pub fn with_seed(seed: u64) -> Self {
+        Self { entries: HashMap::new(), rng: seed | 1 }
+    }
+
+    /// Devices among `ids` due for a check at `now`
+    ///
+    /// Devices not scheduled yet are due at once; devices missing from
+    /// `ids` are dropped from the schedule.
+    pub fn due<'a>(&mut self, ids: impl IntoIterator<Item = &'a str>, now: Instant) -> Vec<String> {
+        let ids: Vec<&str> = ids.into_iter().collect();
+        self.entries.retain(|id, _| ids.contains(&id.as_str()));
+        ids.into_iter()
+            .filter(|id| self.entries.get(*id).is_none_or(|entry| entry.next <= now))
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Record a check that got an answer: the next one is a base interval away
+    pub fn succeeded(&mut self, id: &str, now: Instant, timing: &ScheduleTiming) {
+        let delay = self.jittered(timing.base, timing.jitter);
+        self.entries.insert(id.to_string(), Entry { next: now + delay, failures: 0 });
+    }
+
+    /// Record a check that went unanswered and schedule the retry
+    pub fn failed(&mut self, id: &str, now: Instant, timing: &ScheduleTiming) -> Backoff {
+        let failures = self.entries.get(id).map_or(0, |entry| entry.failures).saturating_add(1);
+        let retry_in = self.jittered(timing.backoff(failures), timing.jitter).min(timing.retry_max);
+        self.entries.insert(id.to_string(), Entry { next: now + retry_in, failures });
+        Backoff { failures, retry_in }
+    }
+
+    /// Check a device at the next opportunity
+    pub fn reset(&mut self, id: &str) {
+        self.entries.remove(id);
+    }
+
+    /// `delay` moved by up to `jitter` of itself either way
+    fn jittered(&mut self, delay: Duration, jitter: f64) -> Duration {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        // Uniform in [-1, 1]
+        let spread = (self.rng >> 11) as f64 / (1u64 << 53) as f64 * 2.0 - 1.0;
+        delay.mul_f64(1.0 + jitter * spread)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timing(jitter: f64) -> ScheduleTiming {
+        ScheduleTiming {
+            base: Duration::from_secs(30),
+            retry_min: Duration::from_secs(2),
+            retry_max: Duration::from_secs(60),
+            jitter,
+        }
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let timing = timing(0.0);
+        let mut schedule = HeartbeatSchedule::with_seed(7);
+        let start = Instant::now();
+
+        let retries: Vec<u64> =
+            (0..7).map(|_| schedule.failed("dev", start, &timing).retry_in.as_secs()).collect();
+        assert_eq!(retries, [2, 4, 8, 16, 32, 60, 60]);
+        assert_eq!(schedule.failed("dev", start, &timing).failures, 8);
+
+        // An answer goes back to the base interval and starts over
+        schedule.succeeded("dev", start, &timing);
+        assert_eq!(schedule.failed("dev", start, &timing), Backoff { failures: 1, retry_in: Duration::from_secs(2) });
+    }
+
+    #[test]
+    fn test_due_follows_the_clock() {
+        let timing = timing(0.0);
+        let mut schedule = HeartbeatSchedule::with_seed(7);
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+
+        // New devices are due straight away
+        assert_eq!(schedule.due(["up", "down"], start), ["up", "down"]);
+        schedule.succeeded("up", start, &timing);
+        schedule.failed("down", start, &timing);
+
+        assert!(schedule.due(["up", "down"], at(1)).is_empty());
+        assert_eq!(schedule.due(["up", "down"], at(2)), ["down"]);
+        schedule.failed("down", at(2), &timing);
+        assert!(schedule.due(["up", "down"], at(5)).is_empty());
+        assert_eq!(schedule.due(["up", "down"], at(6)), ["down"]);
+        assert_eq!(schedule.due(["up", "down"], at(30)), ["up", "down"]);
+
+        // Devices that left the registry are forgotten, and come back due
+        schedule.due(["down"], at(30));
+        assert_eq!(schedule.due(["up"], at(31)), ["up"]);
+        schedule.reset("up");
+        assert_eq!(schedule.due(["up"], start), ["up"]);
+    }
+
+    #[test]
+    fn test_jitter_stays_in_bounds() {
+        let timing = timing(0.2);
+        let mut schedule = HeartbeatSchedule::with_seed(42);
+        let start = Instant::now();
+
+        let mut delays = Vec::new();
+        for i in 0..500 {
+            let id = format!("dev-{}", i);
+            schedule.succeeded(&id, start, &timing);
+            delays.push(schedule.entries[&id].next - start);
+            // First retry: 2s ± 20%
+            let retry = schedule.failed(&id, start, &timing).retry_in;
+            assert!(retry >= Duration::from_millis(1600) && retry <= Duration::from_millis(2400), "{:?}", retry);
+            // Capped retries never exceed the maximum
+            for _ in 0..6 {
+                schedule.failed(&id, start, &timing);
+            }
+            assert!(schedule.failed(&id, start, &timing).retry_in <= Duration::from_secs(60));
+        }
+        assert!(delays.iter().all(|d| *d >= Duration::from_secs(24) && *d <= Duration::from_secs(36)));
+        // The spread is actually used
+        let (min, max) = (delays.iter().min().unwrap(), delays.iter().max().unwrap());
+        assert!(*max - *min > Duration::from_secs(8));
+    }
+}
@@ -0,0 +1,214 @@
+//! Privileged sockets for discovery
+//!
+//! Sending LLDP and IPv6 neighbor discovery frames and raw ICMP echo requests
+//! needs CAP_NET_RAW, and nothing else the daemon does. Rather than keep the capability (or run as
+//! root) for its whole life, the daemon opens these sockets at startup with
+//! [`PreopenedSockets::open`], drops its capabilities, and discovery gets its
+//! sockets from the provider from then on.
+//!
+//! Without the capability, ICMP sweeps fall back to the unprivileged datagram
+//! socket, and LLDP and neighbor discovery frames can't be sent;
+//! [`SocketStatus`] records what could not be opened so it can be reported.
+
+use pnet::datalink::{self, Channel, DataLinkSender, NetworkInterface};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::net::UdpSocket;
+use std::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::icmp::{self, IcmpSocket, SocketKind};
+use crate::lldp_tx::{select_interfaces, LldpTxConfig};
+use crate::ndp;
+use crate::net::IpNet;
+
+/// Where discovery gets sockets that may need privileges
+pub trait SocketProvider: Send + Sync {
+    /// An ICMP socket for one sweep
+    fn icmp_socket(&self, ttl: u8) -> io::Result<Box<dyn IcmpSocket + Send>>;
+
+    /// Send a complete Ethernet frame on an interface
+    fn send_frame(&self, interface: &NetworkInterface, frame: &[u8]) -> io::Result<()>;
+
+    /// What was opened ahead of time (None for providers that open on demand)
+    fn status(&self) -> Option<&SocketStatus> {
+        None
+    }
+}
+
+/// Opens sockets when asked, with the privileges the process has then
+#[derive(Debug, Default)]
+pub struct SystemSockets;
+
+impl SocketProvider for SystemSockets {
+    fn icmp_socket(&self, ttl: u8) -> io::Result<Box<dyn IcmpSocket + Send>> {
+        icmp::open_socket(ttl)
+    }
+
+    fn send_frame(&self, interface: &NetworkInterface, frame: &[u8]) -> io::Result<()> {
+        send(&mut *open_sender(interface)?, interface, frame)
+    }
+}
+
+/// What [`PreopenedSockets::open`] managed to open
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SocketStatus {
+    /// Socket ICMP sweeps run on (None when sweeps are off or no ICMP
+    /// socket can be opened at all)
+    pub icmp: Option<SocketKind>,
+    /// Why the raw ICMP socket couldn't be opened
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icmp_error: Option<String>,
+    /// Interfaces LLDP frames can be sent on
+    pub lldp_interfaces: Vec<String>,
+    /// Interfaces whose datalink channel couldn't be opened, with the error
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub lldp_errors: BTreeMap<String, String>,
+    /// Interfaces neighbor discovery frames can be sent on
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ndp_interfaces: Vec<String>,
+    /// Neighbor discovery interfaces whose channel couldn't be opened, with the error
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub ndp_errors: BTreeMap<String, String>,
+}
+
+impl SocketStatus {
+    /// Whether something asked for couldn't be opened with privileges
+    pub fn is_degraded(&self) -> bool {
+        self.icmp_error.is_some() || !self.lldp_errors.is_empty() || !self.ndp_errors.is_empty()
+    }
+}
+
+/// Interfaces IPv6 neighbor discovery will send on, for [`PreopenedSockets::open`]
+#[derive(Debug, Clone, Copy)]
+pub struct NdpSockets<'a> {
+    /// The IPv6 subnet being scanned
+    pub subnet: &'a IpNet,
+    /// Interface to use (None = every interface with an address in the subnet)
+    pub interface: Option<&'a str>,
+}
+
+/// Sockets opened once, while the process still had CAP_NET_RAW
+///
+/// Anything not opened up front (an interface that appeared later, sweeps
+/// configured after startup) is opened on demand like [`SystemSockets`].
+pub struct PreopenedSockets {
+    icmp: Option<UdpSocket>,
+    senders: Mutex<HashMap<String, Box<dyn DataLinkSender>>>,
+    status: SocketStatus,
+}
+
+impl PreopenedSockets {
+    /// Open a raw ICMP socket if `icmp` is set, and a datalink channel per
+    /// interface LLDP will announce on or neighbor discovery will send on
+    pub fn open(icmp: bool, lldp: Option<&LldpTxConfig>, ndp: Option<NdpSockets>) -> Self {
+        let mut status = SocketStatus::default();
+
+        let raw = if icmp {
+            match icmp::open_raw_socket() {
+                Ok(socket) => {
+                    status.icmp = Some(SocketKind::Raw);
+                    Some(socket)
+                }
+                Err(e) => {
+                    status.icmp_error = Some(e.to_string());
+                    status.icmp = icmp::open_socket(64).ok().map(|socket| socket.kind());
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let mut senders = HashMap::new();
+        if let Some(config) = lldp {
+            for interface in select_interfaces(config) {
+                match open_sender(&interface) {
+                    Ok(sender) => {
+                        status.lldp_interfaces.push(interface.name.clone());
+                        senders.insert(interface.name, sender);
+                    }
+                    Err(e) => {
+                        status.lldp_errors.insert(interface.name, e.to_string());
+                    }
+                }
+            }
+        }
+        if let Some(ndp) = ndp {
+            for interface in ndp::select_interfaces(ndp.subnet, ndp.interface) {
+                // LLDP may already have a channel on it
+                if senders.contains_key(&interface.name) {
+                    status.ndp_interfaces.push(interface.name);
+                    continue;
+                }
+                match open_sender(&interface) {
+                    Ok(sender) => {
+                        status.ndp_interfaces.push(interface.name.clone());
+                        senders.insert(interface.name, sender);
+                    }
+                    Err(e) => {
+                        status.ndp_errors.insert(interface.name, e.to_string());
+                    }
+                }
+            }
+        }
+
+        if status.is_degraded() {
+            warn!(
+                icmp = ?status.icmp,
+                icmp_error = status.icmp_error.as_deref().unwrap_or(""),
+                lldp_failed = ?status.lldp_errors.keys().collect::<Vec<_>>(),
+                ndp_failed = ?status.ndp_errors.keys().collect::<Vec<_>>(),
+                "Raw sockets unavailable (CAP_NET_RAW not granted?), discovery is degraded"
+            );
+        } else {
+            info!(icmp = ?status.icmp, lldp = ?status.lldp_interfaces, ndp = ?status.ndp_interfaces, "Opened raw sockets");
+        }
+
+        Self { icmp: raw, senders: Mutex::new(senders), status }
+    }
+}
+
+impl SocketProvider for PreopenedSockets {
+    fn icmp_socket(&self, ttl: u8) -> io::Result<Box<dyn IcmpSocket + Send>> {
+        let Some(raw) = &self.icmp else {
+            return icmp::open_socket(ttl);
+        };
+        let socket = raw.try_clone()?;
+        // The socket sees every ICMP packet between sweeps; drop those so
+        // late replies to the last sweep don't count for this one
+        socket.set_nonblocking(true)?;
+        let mut buf = [0u8; 1500];
+        while socket.recv_from(&mut buf).is_ok() {}
+        socket.set_nonblocking(false)?;
+        icmp::system_socket(socket, SocketKind::Raw, ttl)
+    }
+
+    fn send_frame(&self, interface: &NetworkInterface, frame: &[u8]) -> io::Result<()> {
+        let mut senders = self.senders.lock().unwrap();
+        match senders.get_mut(&interface.name) {
+            Some(sender) => send(&mut **sender, interface, frame),
+            None => send(&mut *open_sender(interface)?, interface, frame),
+        }
+    }
+
+    fn status(&self) -> Option<&SocketStatus> {
+        Some(&self.status)
+    }
+}
+
+/// Open the send side of a datalink channel
+fn open_sender(interface: &NetworkInterface) -> io::Result<Box<dyn DataLinkSender>> {
+    match datalink::channel(interface, Default::default())? {
+        Channel::Ethernet(tx, _rx) => Ok(tx),
+        _ => Err(io::Error::other(format!("unsupported channel type on {}", interface.name))),
+    }
+}
+
+fn send(sender: &mut dyn DataLinkSender, interface: &NetworkInterface, frame: &[u8]) -> io::Result<()> {
+    match sender.send_to(frame, None) {
+        Some(result) => result,
+        None => Err(io::Error::other(format!("failed to send frame on {}", interface.name))),
+    }
+}
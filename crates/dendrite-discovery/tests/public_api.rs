@@ -0,0 +1,110 @@
+//! Public API for embedding discovery in other tools
+//!
+//! Compiling this file is most of the test: every binding spells out a
+//! signature the embedding example and partner tools rely on, so changing
+//! one fails here before it breaks someone downstream. Adding items is fine;
+//! a change that needs this file edited is a breaking change.
+
+use dendrite_core::{Device, DeviceId, DeviceStatus, DiscoveryEvent, RegistryHandle, Topology};
+use dendrite_discovery::{
+    CanDiscoveryConfig, DeviceOverride, DiscoveryMethods, DiscoveryScanner, IcmpSweep, IpNet, ParentConfig, RegistryStore, RemoveMode,
+    ScanStats, ScannerConfig, SocketProvider,
+};
+use dendrite_mcumgr::{query_devices, query_devices_parallel, DeviceQueryResult, QueryError};
+use dendrite_test_support::{hwid, registry_device};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+#[allow(dead_code)]
+fn constructors() {
+    let _: fn(ScannerConfig) -> DiscoveryScanner = DiscoveryScanner::new;
+    let _: fn(ScannerConfig, Arc<dyn SocketProvider>) -> DiscoveryScanner = DiscoveryScanner::with_sockets;
+    let _: fn(ScannerConfig, Arc<dyn SocketProvider>, Arc<dyn RegistryStore>) -> DiscoveryScanner = DiscoveryScanner::with_registry;
+    let _: fn() -> RegistryHandle = RegistryHandle::new;
+    let _: fn(Ipv4Addr, u8) -> IcmpSweep = IcmpSweep::new;
+}
+
+#[allow(dead_code)]
+fn config_fields(config: ScannerConfig) {
+    let ScannerConfig {
+        subnet: _,
+        interface: _,
+        mcumgr_port: _,
+        query_concurrency: _,
+        query_timeout_ms: _,
+        interval_secs: _,
+        heartbeat_interval_secs: _,
+        heartbeat_enabled: _,
+        offline_retry_min_secs: _,
+        offline_retry_max_secs: _,
+        heartbeat_jitter: _,
+        methods: _,
+        arp_rate_limit: _,
+        icmp_sweeps: _,
+        enable_mdns: _,
+        mdns_service_types: _,
+        exclude: _,
+        parents: _,
+        overrides: _,
+        can: _,
+    } = config;
+    let _: Vec<ParentConfig> = Vec::new();
+    let _: Vec<DeviceOverride> = Vec::new();
+    let _: Option<CanDiscoveryConfig> = None;
+    let _: DiscoveryMethods = DiscoveryMethods::LLDP | DiscoveryMethods::ARP | DiscoveryMethods::PROBE;
+}
+
+#[allow(dead_code)]
+async fn scanner_methods(scanner: &DiscoveryScanner) -> anyhow::Result<()> {
+    let _: broadcast::Receiver<DiscoveryEvent> = scanner.subscribe();
+    let _: Vec<Device> = scanner.scan_once().await?;
+    let _: Vec<Device> = scanner.devices().await;
+    let _: Option<Device> = scanner.get_device(&DeviceId::from_hwid("0x1")).await;
+    let _: ScanStats = scanner.last_scan_stats().await;
+    let _: ScannerConfig = scanner.get_config().await;
+    scanner.set_config(ScannerConfig::default()).await;
+    scanner.update_subnet("fe80::/64".parse::<IpNet>()?).await;
+    let _: bool = scanner.remove_device("0x1", RemoveMode::Archive).await;
+    scanner.run().await
+}
+
+#[allow(dead_code)]
+fn registry_methods(registry: &RegistryHandle) {
+    registry.apply(&DiscoveryEvent::ScanStarted { total_hosts: 254 });
+    registry.replace(Vec::<Device>::new());
+    let _: Vec<Device> = registry.devices();
+    let _: Vec<Device> = registry.online();
+    let _: Option<Device> = registry.get(&DeviceId::from_hwid("0x1"));
+    let _: (usize, bool, bool) = (registry.len(), registry.is_empty(), registry.is_scanning());
+    let _: Topology = registry.topology(None);
+}
+
+#[allow(dead_code)]
+async fn query_functions(addrs: &[SocketAddr]) {
+    let _: Vec<(SocketAddr, Result<DeviceQueryResult, QueryError>)> =
+        query_devices_parallel(addrs, 4, Duration::from_secs(1)).await;
+    let _: Vec<(SocketAddr, Result<DeviceQueryResult, QueryError>)> = query_devices(addrs, 4).await;
+}
+
+#[tokio::test]
+async fn registry_follows_scanner_events() {
+    let scanner = DiscoveryScanner::new(ScannerConfig {
+        subnet: IpNet::from((Ipv4Addr::new(10, 0, 0, 0), 24)),
+        methods: DiscoveryMethods::ARP | DiscoveryMethods::PROBE,
+        ..ScannerConfig::default()
+    });
+    let registry = RegistryHandle::new();
+    let mut events = scanner.subscribe();
+
+    let device: Device = serde_json::from_str(&registry_device(0)).unwrap();
+    assert_eq!(device.status, DeviceStatus::Online);
+    scanner.add_device(device).await;
+    scanner.remove_device(&hwid(0), RemoveMode::Archive).await;
+
+    registry.apply(&events.recv().await.unwrap());
+    assert_eq!(registry.online().len(), 1);
+    registry.apply(&events.recv().await.unwrap());
+    assert!(registry.is_empty());
+}
@@ -0,0 +1,78 @@
+//! MCUmgr response decoding and query throughput benchmarks
+//!
+//! Run with `cargo bench -p dendrite-mcumgr`. Queries go to a mock device on
+//! localhost, so throughput reflects transport and decoding overhead rather
+//! than network latency. Compare across commits with `--save-baseline main`
+//! and `--baseline main`.
+
+use criterion::{BenchmarkId, Criterion, Throughput};
+use dendrite_mcumgr::{query_hcdf_info, HcdfInfoResponse};
+use dendrite_test_support::{FixtureSize, MockHcdfInfo, MockSmpDevice};
+use serde::Serialize;
+use std::hint::black_box;
+use tokio::task::JoinSet;
+
+/// HCDF info response carrying the document inline, as firmware with an
+/// embedded HCDF may send; decoding has to skip the unknown field
+#[derive(Serialize)]
+struct InlineHcdfResponse {
+    url: String,
+    sha: String,
+    hcdf: String,
+}
+
+fn bench_decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode_hcdf_info");
+    for size in FixtureSize::ALL {
+        let body = serde_cbor::to_vec(&InlineHcdfResponse {
+            url: "https://hcdf.cognipilot.org/fleet/fleet.hcdf".to_string(),
+            sha: format!("{:064x}", 0),
+            hcdf: size.xml(),
+        })
+        .unwrap();
+
+        println!("decode_hcdf_info/{}: {} byte CBOR body", size.name(), body.len());
+        group.throughput(Throughput::Bytes(body.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size.name()), &body, |b, body| {
+            b.iter(|| serde_cbor::from_slice::<HcdfInfoResponse>(black_box(body)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_concurrent_queries(c: &mut Criterion) {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let device = rt
+        .block_on(MockSmpDevice::spawn(MockHcdfInfo {
+            url: Some("https://hcdf.cognipilot.org/spinali/spinali.hcdf".to_string()),
+            sha: Some(format!("{:064x}", 0)),
+            ..Default::default()
+        }))
+        .unwrap();
+    let addr = device.addr();
+
+    let mut group = c.benchmark_group("concurrent_hcdf_info_queries");
+    for concurrency in [1, 8, 32] {
+        group.throughput(Throughput::Elements(concurrency as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(concurrency), &concurrency, |b, &n| {
+            b.to_async(&rt).iter(|| async move {
+                let mut queries = JoinSet::new();
+                for _ in 0..n {
+                    queries.spawn(query_hcdf_info(addr));
+                }
+                while let Some(result) = queries.join_next().await {
+                    result.unwrap().unwrap();
+                }
+            })
+        });
+    }
+    group.finish();
+    drop(device);
+}
+
+fn main() {
+    let mut criterion = Criterion::default().configure_from_args();
+    bench_decode(&mut criterion);
+    bench_concurrent_queries(&mut criterion);
+    criterion.final_summary();
+}
@@ -0,0 +1,486 @@
+//! Firmware image upload with resume
+//!
+//! Images are sent in chunks through the SMP image group, and every upload
+//! response carries the offset the device expects next. When the link drops
+//! mid-transfer the uploader reconnects and sends the chunk again; the
+//! device's answer says where it actually got to, so the upload carries on
+//! from there rather than from byte zero. A device that lost the transfer
+//! (it rebooted, say) answers with offset 0 and the upload starts over.
+//!
+//! If a run of retries makes no progress, the upload restarts from zero
+//! once without the image SHA, so the device can't match it to the stale
+//! transfer. Before an image is marked for test, [`verify_uploaded_image`]
+//! checks the secondary slot holds the image's MCUboot hash.
+//!
+//! [`upload`] sizes its chunks from the SMP buffer the device reports
+//! (MCUmgr parameters) and reports progress as bytes acknowledged.
+
+use dendrite_core::sha256_hex;
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+use thiserror::Error;
+use tracing::{debug, info, warn};
+
+use crate::query::{hex_encode, query_image_state, query_mcumgr_params, QueryOptions};
+use crate::transport::{Transport, UdpTransportAsync};
+
+pub use crate::query::image_group::{GROUP_IMAGE, ID_IMAGE_UPLOAD};
+
+const OP_WRITE: u8 = 2;
+
+/// Data bytes per upload request, leaving room for the SMP header and CBOR
+/// keys in a 384 byte device buffer
+pub const DEFAULT_CHUNK_SIZE: usize = 256;
+
+/// Room left in each packet for the SMP header and the CBOR keys, offset,
+/// length and SHA around the data
+const UPLOAD_OVERHEAD: usize = 128;
+
+/// Largest packet the UDP transport can receive, so the largest worth sending
+const MAX_PACKET_SIZE: usize = 1024;
+
+/// Slot an upload lands in
+const SECONDARY_SLOT: u32 = 1;
+
+const MCUBOOT_MAGIC: u32 = 0x96f3_b83d;
+const TLV_INFO_MAGIC: u16 = 0x6907;
+const TLV_PROTECTED_INFO_MAGIC: u16 = 0x6908;
+const TLV_SHA256: u16 = 0x10;
+
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum ImageError {
+    #[error("Not an MCUboot image: {0}")]
+    InvalidImage(String),
+    #[error("Device rejected the upload at offset {offset} (rc {rc})")]
+    Rejected { offset: u64, rc: i64 },
+    #[error("Upload failed after {retries} retries: {last_error}")]
+    RetriesExhausted { retries: u32, last_error: String },
+    #[error("No image in slot {0} after upload")]
+    MissingImage(u32),
+    #[error("Uploaded image hash {actual} does not match {expected}")]
+    HashMismatch { expected: String, actual: String },
+    #[error("Transport error: {0}")]
+    TransportError(#[from] anyhow::Error),
+}
+
+/// Upload tuning
+#[derive(Debug, Clone)]
+pub struct UploadOptions {
+    /// Image number, for devices with more than one updatable image
+    pub image: u32,
+    pub chunk_size: usize,
+    /// Time to wait for each response
+    pub timeout_ms: u64,
+    /// Retries allowed over the whole upload, restart included
+    pub max_retries: u32,
+    /// Retries in a row without progress before starting over from zero
+    pub max_stalled_retries: u32,
+    /// Pause before reconnecting, giving a rebooting device time to come up
+    pub retry_delay: Duration,
+}
+
+impl Default for UploadOptions {
+    fn default() -> Self {
+        Self {
+            image: 0,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            timeout_ms: 5000,
+            max_retries: 10,
+            max_stalled_retries: 3,
+            retry_delay: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Upload progress, reported after every acknowledged chunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UploadProgress {
+    /// Bytes the device has acknowledged
+    pub offset: u64,
+    pub total: u64,
+    /// Retries so far
+    pub retries: u32,
+    /// Offset the most recent retry resumed from
+    pub resumed_at: Option<u64>,
+}
+
+impl UploadProgress {
+    pub fn fraction(&self) -> f32 {
+        fraction(self.offset, self.total)
+    }
+
+    pub fn resumed_fraction(&self) -> Option<f32> {
+        self.resumed_at.map(|offset| fraction(offset, self.total))
+    }
+}
+
+fn fraction(offset: u64, total: u64) -> f32 {
+    if total == 0 {
+        1.0
+    } else {
+        offset as f32 / total as f32
+    }
+}
+
+/// How an upload went
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UploadReport {
+    pub retries: u32,
+    /// Resuming failed and the upload started over from zero
+    pub restarted: bool,
+}
+
+#[derive(Serialize)]
+struct UploadReq<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    len: Option<u64>,
+    off: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sha: Option<&'a serde_bytes::Bytes>,
+    data: &'a serde_bytes::Bytes,
+}
+
+#[derive(Deserialize)]
+struct UploadRsp {
+    #[serde(default)]
+    rc: i64,
+    #[serde(default)]
+    off: Option<u64>,
+}
+
+/// Data bytes per upload request for a device with `buf_size` byte SMP buffers
+pub fn chunk_size_for(buf_size: usize) -> usize {
+    buf_size.min(MAX_PACKET_SIZE).saturating_sub(UPLOAD_OVERHEAD).max(1)
+}
+
+/// Chunk size for uploads to `target`, from the buffer size it reports
+///
+/// Falls back to `options.chunk_size` when the device doesn't answer the
+/// MCUmgr parameters command.
+pub async fn negotiate_chunk_size(target: SocketAddr, options: &UploadOptions) -> usize {
+    match query_mcumgr_params(target).await {
+        Ok(Some(params)) => {
+            let chunk_size = chunk_size_for(params.buf_size as usize);
+            debug!(device = %target, buf_size = params.buf_size, chunk_size, "Negotiated upload chunk size");
+            chunk_size
+        }
+        Ok(None) => options.chunk_size,
+        Err(e) => {
+            debug!(device = %target, error = %e, "MCUmgr parameters unavailable, using default chunk size");
+            options.chunk_size
+        }
+    }
+}
+
+/// Upload an image with chunks sized to the device's SMP buffer
+///
+/// `progress` is called with the bytes uploaded and the image size after
+/// every chunk the device acknowledges. Link-local IPv6 targets must carry
+/// their scope id.
+pub async fn upload(
+    target: SocketAddr,
+    image: &[u8],
+    options: &UploadOptions,
+    mut progress: impl FnMut(usize, usize),
+) -> Result<UploadReport, ImageError> {
+    let options = UploadOptions { chunk_size: negotiate_chunk_size(target, options).await, ..options.clone() };
+    upload_to(target, image, &options, |p| progress(p.offset as usize, p.total as usize)).await
+}
+
+/// Upload an image to a device's secondary slot, resuming after dropped
+/// connections
+///
+/// `progress` is called after every chunk the device acknowledges.
+pub async fn upload_image(
+    ip: IpAddr,
+    port: u16,
+    image: &[u8],
+    options: &UploadOptions,
+    progress: impl FnMut(UploadProgress),
+) -> Result<UploadReport, ImageError> {
+    upload_to(SocketAddr::new(ip, port), image, options, progress).await
+}
+
+async fn upload_to(
+    target: SocketAddr,
+    image: &[u8],
+    options: &UploadOptions,
+    mut progress: impl FnMut(UploadProgress),
+) -> Result<UploadReport, ImageError> {
+    let sha = sha256(image);
+    let total = image.len() as u64;
+    let chunk_size = options.chunk_size.max(1) as u64;
+
+    let mut transport = UdpTransportAsync::new(target, options.timeout_ms).await?;
+    let mut offset = 0u64;
+    let mut retries = 0u32;
+    let mut stalled = 0u32;
+    let mut restarted = false;
+    let mut retrying = false;
+    let mut resumed_at = None;
+
+    info!(device = %target, bytes = total, "Uploading image");
+
+    while offset < total {
+        let end = (offset + chunk_size).min(total);
+        let first = offset == 0;
+        // The restart leaves the SHA out so the device erases the slot
+        let request = UploadReq {
+            image: first.then_some(options.image),
+            len: first.then_some(total),
+            off: offset,
+            sha: (first && !restarted).then_some(serde_bytes::Bytes::new(&sha)),
+            data: serde_bytes::Bytes::new(&image[offset as usize..end as usize]),
+        };
+
+        let error = match send_chunk(&mut transport, &request).await {
+            Ok(UploadRsp { rc: 0, off: Some(device_offset) }) => {
+                if retrying {
+                    // The device either took the chunk or said where it is
+                    let resume = if device_offset == end { offset } else { device_offset };
+                    info!(device = %target, offset = resume, retries, "Resumed image upload");
+                    resumed_at = Some(resume);
+                    retrying = false;
+                } else if device_offset != end {
+                    debug!(device = %target, sent = offset, device_offset, "Device asked for a different offset");
+                }
+                offset = device_offset.min(total);
+                stalled = 0;
+                progress(UploadProgress { offset, total, retries, resumed_at });
+                continue;
+            }
+            Ok(UploadRsp { rc: 0, off: None }) => "Upload response without an offset".to_string(),
+            Ok(UploadRsp { rc, .. }) => return Err(ImageError::Rejected { offset, rc }),
+            Err(e) => e.to_string(),
+        };
+
+        retries += 1;
+        stalled += 1;
+        if retries > options.max_retries {
+            return Err(ImageError::RetriesExhausted { retries: options.max_retries, last_error: error });
+        }
+        if stalled >= options.max_stalled_retries && !restarted {
+            warn!(device = %target, offset, error = %error, "Resuming keeps failing, restarting upload from zero");
+            restarted = true;
+            stalled = 0;
+            offset = 0;
+            resumed_at = None;
+            retrying = false;
+        } else {
+            warn!(device = %target, offset, retries, error = %error, "Image upload interrupted, retrying");
+            retrying = true;
+        }
+
+        tokio::time::sleep(options.retry_delay).await;
+        // A fresh socket, so a late answer to the lost request can't be
+        // taken for the next one
+        transport = UdpTransportAsync::new(target, options.timeout_ms).await?;
+    }
+
+    info!(device = %target, retries, restarted, "Image upload complete");
+    Ok(UploadReport { retries, restarted })
+}
+
+async fn send_chunk(transport: &mut UdpTransportAsync, request: &UploadReq<'_>) -> anyhow::Result<UploadRsp> {
+    let body = serde_cbor::to_vec(request)?;
+    let response = transport.transceive(OP_WRITE, GROUP_IMAGE, ID_IMAGE_UPLOAD, &body).await?;
+    Ok(serde_cbor::from_slice(&response)?)
+}
+
+/// Check the device's secondary slot holds `image`, returning its hash
+///
+/// The hash is what to mark for test once this passes.
+pub async fn verify_uploaded_image(ip: IpAddr, port: u16, timeout_ms: u64, image: &[u8]) -> Result<String, ImageError> {
+    let expected = mcuboot_image_hash(image)
+        .map(|hash| hex_encode(&hash))
+        .ok_or_else(|| ImageError::InvalidImage("no SHA256 TLV".to_string()))?;
+
+    let options = QueryOptions { timeout: Duration::from_millis(timeout_ms), ..QueryOptions::default() };
+    let mut transport = Transport::Udp(SocketAddr::new(ip, port)).connect(&options).await?;
+    let images = query_image_state(&mut transport).await?;
+    let uploaded = images
+        .into_iter()
+        .find(|i| i.slot == SECONDARY_SLOT)
+        .ok_or(ImageError::MissingImage(SECONDARY_SLOT))?;
+
+    if !uploaded.hash.eq_ignore_ascii_case(&expected) {
+        return Err(ImageError::HashMismatch { expected, actual: uploaded.hash });
+    }
+    debug!(device = %ip, hash = %expected, "Uploaded image hash verified");
+    Ok(expected)
+}
+
+/// The SHA256 MCUboot records for an image, from its TLV trailer
+pub fn mcuboot_image_hash(image: &[u8]) -> Option<Vec<u8>> {
+    let u16_at = |at: usize| image.get(at..at + 2).map(|b| u16::from_le_bytes([b[0], b[1]]));
+    let u32_at = |at: usize| image.get(at..at + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+
+    if u32_at(0)? != MCUBOOT_MAGIC {
+        return None;
+    }
+    let header_size = u16_at(8)? as usize;
+    let protected_size = u16_at(10)? as usize;
+    let image_size = u32_at(12)? as usize;
+
+    let mut tlv = header_size.checked_add(image_size)?;
+    if u16_at(tlv)? == TLV_PROTECTED_INFO_MAGIC {
+        tlv = tlv.checked_add(protected_size)?;
+    }
+    if u16_at(tlv)? != TLV_INFO_MAGIC {
+        return None;
+    }
+    let end = tlv + u16_at(tlv + 2)? as usize;
+
+    let mut at = tlv + 4;
+    while at + 4 <= end {
+        let (kind, len) = (u16_at(at)?, u16_at(at + 2)? as usize);
+        let value = image.get(at + 4..at + 4 + len)?;
+        if kind == TLV_SHA256 {
+            return Some(value.to_vec());
+        }
+        at += 4 + len;
+    }
+    None
+}
+
+fn sha256(data: &[u8]) -> Vec<u8> {
+    let hex = sha256_hex(data);
+    (0..hex.len())
+        .step_by(2)
+        .filter_map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dendrite_test_support::{mcuboot_image, MockHcdfInfo, MockSmpDevice};
+    use std::net::Ipv4Addr;
+
+    const LOCALHOST: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+    fn options() -> UploadOptions {
+        UploadOptions {
+            timeout_ms: 100,
+            retry_delay: Duration::from_millis(10),
+            ..Default::default()
+        }
+    }
+
+    fn upload_offsets(device: &MockSmpDevice) -> Vec<u64> {
+        device
+            .requests_for(GROUP_IMAGE, ID_IMAGE_UPLOAD)
+            .iter()
+            .map(|r| r.decode::<serde_cbor::Value>().unwrap())
+            .map(|body| match body {
+                serde_cbor::Value::Map(map) => match map.get(&serde_cbor::Value::Text("off".into())) {
+                    Some(serde_cbor::Value::Integer(off)) => *off as u64,
+                    _ => panic!("upload request without an offset"),
+                },
+                _ => panic!("upload request is not a map"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_mcuboot_image_hash() {
+        let image = mcuboot_image(1000, 0x5a);
+        assert_eq!(mcuboot_image_hash(&image), Some(vec![0x5a; 32]));
+        assert_eq!(mcuboot_image_hash(&image[..500]), None);
+        assert_eq!(mcuboot_image_hash(b"not an image at all"), None);
+    }
+
+    #[tokio::test]
+    async fn test_upload_resumes_after_disconnect() {
+        let device = MockSmpDevice::spawn(MockHcdfInfo::default()).await.unwrap();
+        let image = mcuboot_image(4000, 0x11);
+        // The link goes down halfway through, for two requests
+        device.drop_upload_requests(2048, 2);
+
+        let mut updates = Vec::new();
+        let report = upload_image(LOCALHOST, device.port(), &image, &options(), |p| updates.push(p)).await.unwrap();
+
+        assert_eq!(report, UploadReport { retries: 2, restarted: false });
+        assert_eq!(device.uploaded_image(), Some(image.clone()));
+        let last = updates.last().unwrap();
+        assert_eq!((last.offset, last.total, last.retries), (image.len() as u64, image.len() as u64, 2));
+        assert_eq!(last.resumed_at, Some(2048));
+        // Nothing before the drop was sent twice
+        let offsets = upload_offsets(&device);
+        assert_eq!(offsets.iter().filter(|&&off| off == 0).count(), 1);
+        assert_eq!(offsets.iter().filter(|&&off| off == 2048).count(), 3);
+
+        let hash = verify_uploaded_image(LOCALHOST, device.port(), 100, &image).await.unwrap();
+        assert_eq!(hash, "11".repeat(32));
+    }
+
+    #[tokio::test]
+    async fn test_upload_restarts_once_when_resume_stalls() {
+        let device = MockSmpDevice::spawn(MockHcdfInfo::default()).await.unwrap();
+        let image = mcuboot_image(1500, 0x22);
+        device.drop_upload_requests(1024, 3);
+
+        let report = upload_image(LOCALHOST, device.port(), &image, &options(), |_| {}).await.unwrap();
+
+        assert_eq!(report, UploadReport { retries: 3, restarted: true });
+        assert_eq!(device.uploaded_image(), Some(image));
+        let offsets = upload_offsets(&device);
+        assert_eq!(offsets.iter().filter(|&&off| off == 0).count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_upload_gives_up_after_max_retries() {
+        let device = MockSmpDevice::spawn(MockHcdfInfo::default()).await.unwrap();
+        let image = mcuboot_image(1500, 0x33);
+        device.drop_upload_requests(512, u32::MAX);
+
+        let error = upload_image(LOCALHOST, device.port(), &image, &options(), |_| {}).await.unwrap_err();
+
+        assert!(matches!(error, ImageError::RetriesExhausted { retries: 10, .. }), "{}", error);
+        assert_eq!(device.uploaded_image(), None);
+    }
+
+    #[tokio::test]
+    async fn test_verify_rejects_other_image() {
+        let device = MockSmpDevice::spawn(MockHcdfInfo::default()).await.unwrap();
+        let image = mcuboot_image(600, 0x44);
+        upload_image(LOCALHOST, device.port(), &image, &options(), |_| {}).await.unwrap();
+
+        let other = mcuboot_image(600, 0x55);
+        let error = verify_uploaded_image(LOCALHOST, device.port(), 100, &other).await.unwrap_err();
+        assert!(matches!(error, ImageError::HashMismatch { .. }), "{}", error);
+    }
+
+    #[test]
+    fn test_chunk_size_for() {
+        assert_eq!(chunk_size_for(384), DEFAULT_CHUNK_SIZE);
+        assert_eq!(chunk_size_for(640), 512);
+        assert_eq!(chunk_size_for(4096), MAX_PACKET_SIZE - UPLOAD_OVERHEAD);
+        assert_eq!(chunk_size_for(64), 1);
+    }
+
+    #[tokio::test]
+    async fn test_upload_negotiates_chunk_size() {
+        let device = MockSmpDevice::spawn(MockHcdfInfo::default()).await.unwrap();
+        device.set_buffer_size(640);
+        let image = mcuboot_image(1500, 0x66);
+
+        let mut updates = Vec::new();
+        upload(device.addr(), &image, &options(), |uploaded, total| updates.push((uploaded, total))).await.unwrap();
+
+        assert_eq!(device.uploaded_image(), Some(image.clone()));
+        assert_eq!(upload_offsets(&device)[..2], [0, 512]);
+        assert_eq!(updates.first(), Some(&(512, image.len())));
+        assert_eq!(updates.last(), Some(&(image.len(), image.len())));
+    }
+
+    #[tokio::test]
+    async fn test_upload_keeps_chunk_size_without_params() {
+        let device = MockSmpDevice::spawn(MockHcdfInfo::default()).await.unwrap();
+        assert_eq!(negotiate_chunk_size(device.addr(), &options()).await, DEFAULT_CHUNK_SIZE);
+    }
+}
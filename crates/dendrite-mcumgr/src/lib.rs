@@ -1,14 +1,29 @@
 //! Dendrite MCUmgr - MCUmgr integration for device queries
 //!
 //! This crate wraps mcumgr-client to provide async device querying
-//! for the Dendrite system.
+//! for the Dendrite system, over UDP or a serial port (see `transport`).
+//! Transports can be observed frame by frame for
+//! protocol debugging (see `observer`). Firmware images are uploaded with
+//! resume after dropped connections (see `image`). Zephyr shell commands can
+//! be run on a device for debugging (see [`exec_shell`]).
 
+pub mod image;
+pub mod observer;
+pub mod pcap;
 pub mod query;
 pub mod transport;
 
+pub use image::{
+    chunk_size_for, mcuboot_image_hash, negotiate_chunk_size, upload, upload_image, verify_uploaded_image, ImageError,
+    UploadOptions, UploadProgress, UploadReport,
+};
+pub use observer::{clear_observer, observer_for, set_observer, Direction, SmpFrame, TracingObserver, TransportObserver};
+pub use pcap::{PcapObserver, PcapRotation};
+
 pub use query::{
-    probe_device, query_device, query_hcdf_info, query_result_to_device,
-    hcdf_group, DeviceQueryResult, HcdfInfoResponse, QueryError,
-    MCUMGR_PORT,
+    confirm_image, exec_shell, list_images, nmp_error_name, parse_hcdf_info, probe_device, query_device, query_devices,
+    query_devices_parallel, query_hcdf_info, query_mcumgr_params, query_result_to_device, revert_image, set_status_led,
+    hcdf_group, image_group, shell_group, status_led_group, DeviceQueryResult, HcdfInfoResponse, ImageSlot, McumgrParams,
+    QueryError, QueryOptions, Revert, ShellOutput, StatusLedColor, DEFAULT_QUERY_CONCURRENCY, DEFAULT_TIMEOUT_MS, MCUMGR_PORT, NMP_ERR_ENOTSUP,
 };
-pub use transport::UdpTransportAsync;
+pub use transport::{SerialConsole, SerialPortConfig, SerialTransportAsync, Transport, UdpTransportAsync, DEFAULT_BAUD_RATE};
@@ -0,0 +1,218 @@
+//! SMP frame observation for protocol debugging
+//!
+//! A `TransportObserver` sees every frame a `UdpTransportAsync` sends or
+//! receives. Observers are registered per device IP, so tracing can be
+//! switched on at runtime without threading anything through the query
+//! functions: every transport created for that IP picks the observer up.
+//!
+//! Two observers are built in: `TracingObserver` logs decoded frames at TRACE
+//! level, and `PcapObserver` (in `pcap`) writes pcapng files for Wireshark.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::SystemTime;
+use tracing::trace;
+
+/// Which way a frame travelled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Request sent to the device
+    Outbound,
+    /// Response received from the device
+    Inbound,
+}
+
+/// A single SMP frame with its header decoded
+#[derive(Debug, Clone)]
+pub struct SmpFrame<'a> {
+    pub direction: Direction,
+    /// Local socket address
+    pub local: SocketAddr,
+    /// Device address
+    pub peer: SocketAddr,
+    pub timestamp: SystemTime,
+    pub op: u8,
+    pub flags: u8,
+    /// Body length from the header
+    pub len: u16,
+    pub group: u16,
+    pub seq: u8,
+    pub id: u8,
+    /// Raw frame: 8-byte header followed by the CBOR body
+    pub packet: &'a [u8],
+}
+
+impl<'a> SmpFrame<'a> {
+    /// Decode a raw frame, returning None if it is shorter than a header
+    pub fn parse(direction: Direction, local: SocketAddr, peer: SocketAddr, packet: &'a [u8]) -> Option<Self> {
+        if packet.len() < 8 {
+            return None;
+        }
+        Some(Self {
+            direction,
+            local,
+            peer,
+            timestamp: SystemTime::now(),
+            op: packet[0] & 0x07,
+            flags: packet[1],
+            len: u16::from_be_bytes([packet[2], packet[3]]),
+            group: u16::from_be_bytes([packet[4], packet[5]]),
+            seq: packet[6],
+            id: packet[7],
+            packet,
+        })
+    }
+
+    /// CBOR body following the header
+    pub fn payload(&self) -> &'a [u8] {
+        &self.packet[8..]
+    }
+
+    /// Payload as lowercase hex
+    pub fn payload_hex(&self) -> String {
+        self.payload().iter().fold(String::new(), |mut s, b| {
+            let _ = write!(s, "{:02x}", b);
+            s
+        })
+    }
+
+    /// Payload in CBOR diagnostic notation (RFC 8949 section 8), or a note if
+    /// it doesn't decode
+    pub fn payload_diagnostic(&self) -> String {
+        match serde_cbor::from_slice::<serde_cbor::Value>(self.payload()) {
+            Ok(value) => {
+                let mut out = String::new();
+                write_diagnostic(&mut out, &value);
+                out
+            }
+            Err(e) => format!("<invalid CBOR: {}>", e),
+        }
+    }
+}
+
+/// Receives SMP frames from transports
+pub trait TransportObserver: Send + Sync {
+    fn on_frame(&self, frame: &SmpFrame<'_>);
+}
+
+/// Logs every frame at TRACE level
+#[derive(Debug, Default)]
+pub struct TracingObserver;
+
+impl TransportObserver for TracingObserver {
+    fn on_frame(&self, frame: &SmpFrame<'_>) {
+        trace!(
+            direction = ?frame.direction,
+            peer = %frame.peer,
+            op = frame.op,
+            flags = frame.flags,
+            group = frame.group,
+            id = frame.id,
+            seq = frame.seq,
+            len = frame.len,
+            hex = %frame.payload_hex(),
+            cbor = %frame.payload_diagnostic(),
+            "SMP frame"
+        );
+    }
+}
+
+type ObserverMap = RwLock<HashMap<IpAddr, Arc<dyn TransportObserver>>>;
+
+fn observers() -> &'static ObserverMap {
+    static OBSERVERS: OnceLock<ObserverMap> = OnceLock::new();
+    OBSERVERS.get_or_init(Default::default)
+}
+
+/// Observe all transports created for `ip` from now on
+pub fn set_observer(ip: IpAddr, observer: Arc<dyn TransportObserver>) {
+    observers().write().unwrap_or_else(|e| e.into_inner()).insert(ip, observer);
+}
+
+/// Stop observing `ip`, returning whether an observer was registered
+pub fn clear_observer(ip: IpAddr) -> bool {
+    observers().write().unwrap_or_else(|e| e.into_inner()).remove(&ip).is_some()
+}
+
+/// Observer registered for `ip`
+pub fn observer_for(ip: IpAddr) -> Option<Arc<dyn TransportObserver>> {
+    observers().read().unwrap_or_else(|e| e.into_inner()).get(&ip).cloned()
+}
+
+fn write_diagnostic(out: &mut String, value: &serde_cbor::Value) {
+    use serde_cbor::Value;
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => {
+            let _ = write!(out, "{}", b);
+        }
+        Value::Integer(i) => {
+            let _ = write!(out, "{}", i);
+        }
+        Value::Float(f) => {
+            let _ = write!(out, "{:?}", f);
+        }
+        Value::Text(s) => {
+            let _ = write!(out, "{:?}", s);
+        }
+        Value::Bytes(bytes) => {
+            out.push_str("h'");
+            for b in bytes {
+                let _ = write!(out, "{:02x}", b);
+            }
+            out.push('\'');
+        }
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                write_diagnostic(out, item);
+            }
+            out.push(']');
+        }
+        Value::Map(entries) => {
+            out.push('{');
+            for (i, (k, v)) in entries.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                write_diagnostic(out, k);
+                out.push_str(": ");
+                write_diagnostic(out, v);
+            }
+            out.push('}');
+        }
+        Value::Tag(tag, inner) => {
+            let _ = write!(out, "{}(", tag);
+            write_diagnostic(out, inner);
+            out.push(')');
+        }
+        _ => out.push_str("undefined"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_frame_decoding_and_diagnostic() {
+        let body = serde_cbor::to_vec(&BTreeMap::from([("d", "ping")])).unwrap();
+        let mut packet = vec![(1 << 3) | 2, 0, 0, body.len() as u8, 0, 0, 7, 0];
+        packet.extend_from_slice(&body);
+
+        let addr: SocketAddr = "127.0.0.1:1337".parse().unwrap();
+        let frame = SmpFrame::parse(Direction::Outbound, addr, addr, &packet).unwrap();
+        assert_eq!((frame.op, frame.group, frame.id, frame.seq), (2, 0, 0, 7));
+        assert_eq!(frame.len as usize, body.len());
+        assert_eq!(frame.payload_diagnostic(), r#"{"d": "ping"}"#);
+        assert!(frame.payload_hex().starts_with("a1"));
+
+        assert!(SmpFrame::parse(Direction::Inbound, addr, addr, &packet[..4]).is_none());
+    }
+}
@@ -0,0 +1,336 @@
+//! pcapng capture of SMP frames
+//!
+//! Frames are wrapped in synthetic IPv4/UDP headers (link type RAW) so
+//! Wireshark's UDP dissector and its SMP-over-UDP heuristics apply without
+//! any configuration. Files rotate when they reach a size cap:
+//! `trace.pcapng` is the current file, `trace.1.pcapng` the previous one and
+//! so on, with the oldest deleted once `max_files` is reached.
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+use tracing::warn;
+
+use crate::observer::{Direction, SmpFrame, TransportObserver};
+
+/// pcapng link type for raw IP packets
+const LINKTYPE_RAW: u16 = 101;
+
+/// Size of the section header and interface description blocks
+const FILE_HEADER_LEN: u64 = 28 + 20;
+
+/// When to start a new capture file
+#[derive(Debug, Clone, Copy)]
+pub struct PcapRotation {
+    /// Maximum size of one capture file in bytes
+    pub max_file_bytes: u64,
+    /// Number of files kept, including the current one
+    pub max_files: usize,
+}
+
+impl Default for PcapRotation {
+    fn default() -> Self {
+        Self {
+            max_file_bytes: 10 * 1024 * 1024,
+            max_files: 5,
+        }
+    }
+}
+
+struct PcapFile {
+    writer: BufWriter<File>,
+    written: u64,
+}
+
+/// Writes every observed frame to a rotating pcapng capture
+pub struct PcapObserver {
+    path: PathBuf,
+    rotation: PcapRotation,
+    file: Mutex<PcapFile>,
+}
+
+impl PcapObserver {
+    /// Start a capture at `path`, replacing any existing file there
+    pub fn create(path: impl Into<PathBuf>, rotation: PcapRotation) -> io::Result<Self> {
+        let path = path.into();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = open_capture(&path)?;
+        Ok(Self {
+            path,
+            rotation,
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Path of the current capture file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn write_frame(&self, frame: &SmpFrame<'_>) -> io::Result<()> {
+        let block = enhanced_packet_block(frame);
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+
+        if file.written + block.len() as u64 > self.rotation.max_file_bytes && file.written > FILE_HEADER_LEN {
+            file.writer.flush()?;
+            rotate(&self.path, self.rotation.max_files)?;
+            *file = open_capture(&self.path)?;
+        }
+
+        file.writer.write_all(&block)?;
+        file.writer.flush()?;
+        file.written += block.len() as u64;
+        Ok(())
+    }
+}
+
+impl TransportObserver for PcapObserver {
+    fn on_frame(&self, frame: &SmpFrame<'_>) {
+        if let Err(e) = self.write_frame(frame) {
+            warn!(path = %self.path.display(), error = %e, "Failed to write SMP capture");
+        }
+    }
+}
+
+/// Path of the `index`th rotated file (0 is the current file)
+pub fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    if index == 0 {
+        return path.to_path_buf();
+    }
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("trace");
+    let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("pcapng");
+    path.with_file_name(format!("{}.{}.{}", stem, index, ext))
+}
+
+/// Shift `path` -> `path.1` -> `path.2` ..., dropping the oldest
+fn rotate(path: &Path, max_files: usize) -> io::Result<()> {
+    let keep = max_files.max(1);
+    let _ = fs::remove_file(rotated_path(path, keep - 1));
+    for index in (0..keep - 1).rev() {
+        let from = rotated_path(path, index);
+        if from.exists() {
+            fs::rename(&from, rotated_path(path, index + 1))?;
+        }
+    }
+    Ok(())
+}
+
+fn open_capture(path: &Path) -> io::Result<PcapFile> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(&section_header_block())?;
+    writer.write_all(&interface_description_block())?;
+    writer.flush()?;
+    Ok(PcapFile {
+        writer,
+        written: FILE_HEADER_LEN,
+    })
+}
+
+fn section_header_block() -> Vec<u8> {
+    let mut block = Vec::with_capacity(28);
+    block.extend_from_slice(&0x0A0D_0D0Au32.to_le_bytes());
+    block.extend_from_slice(&28u32.to_le_bytes());
+    block.extend_from_slice(&0x1A2B_3C4Du32.to_le_bytes());
+    block.extend_from_slice(&1u16.to_le_bytes());
+    block.extend_from_slice(&0u16.to_le_bytes());
+    // Section length unknown
+    block.extend_from_slice(&(-1i64).to_le_bytes());
+    block.extend_from_slice(&28u32.to_le_bytes());
+    block
+}
+
+fn interface_description_block() -> Vec<u8> {
+    let mut block = Vec::with_capacity(20);
+    block.extend_from_slice(&1u32.to_le_bytes());
+    block.extend_from_slice(&20u32.to_le_bytes());
+    block.extend_from_slice(&LINKTYPE_RAW.to_le_bytes());
+    block.extend_from_slice(&0u16.to_le_bytes());
+    block.extend_from_slice(&65535u32.to_le_bytes());
+    block.extend_from_slice(&20u32.to_le_bytes());
+    block
+}
+
+/// Enhanced packet block holding the frame in IP/UDP headers (microsecond
+/// timestamps, the pcapng default)
+fn enhanced_packet_block(frame: &SmpFrame<'_>) -> Vec<u8> {
+    let (src, dst) = match frame.direction {
+        Direction::Outbound => (frame.local, frame.peer),
+        Direction::Inbound => (frame.peer, frame.local),
+    };
+    let packet = ip_udp_packet(src, dst, frame.packet);
+    let padded = packet.len().div_ceil(4) * 4;
+    let total = (32 + padded) as u32;
+    let micros = frame
+        .timestamp
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_micros() as u64)
+        .unwrap_or(0);
+
+    let mut block = Vec::with_capacity(total as usize);
+    block.extend_from_slice(&6u32.to_le_bytes());
+    block.extend_from_slice(&total.to_le_bytes());
+    block.extend_from_slice(&0u32.to_le_bytes());
+    block.extend_from_slice(&((micros >> 32) as u32).to_le_bytes());
+    block.extend_from_slice(&(micros as u32).to_le_bytes());
+    block.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+    block.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+    block.extend_from_slice(&packet);
+    block.resize(block.len() + padded - packet.len(), 0);
+    block.extend_from_slice(&total.to_le_bytes());
+    block
+}
+
+/// Wrap a UDP payload in IPv4 (or IPv6 if either end is v6) and UDP headers
+fn ip_udp_packet(src: SocketAddr, dst: SocketAddr, payload: &[u8]) -> Vec<u8> {
+    let udp_len = (8 + payload.len()) as u16;
+    let mut udp = Vec::with_capacity(udp_len as usize);
+    udp.extend_from_slice(&src.port().to_be_bytes());
+    udp.extend_from_slice(&dst.port().to_be_bytes());
+    udp.extend_from_slice(&udp_len.to_be_bytes());
+    // Checksum not computed
+    udp.extend_from_slice(&0u16.to_be_bytes());
+    udp.extend_from_slice(payload);
+
+    let mut packet = Vec::with_capacity(40 + udp.len());
+    match (src.ip(), dst.ip()) {
+        (IpAddr::V4(s), IpAddr::V4(d)) => {
+            let total_len = 20 + udp_len;
+            let mut header = [0u8; 20];
+            header[0] = 0x45;
+            header[2..4].copy_from_slice(&total_len.to_be_bytes());
+            // Don't fragment
+            header[6] = 0x40;
+            header[8] = 64;
+            header[9] = 17;
+            header[12..16].copy_from_slice(&s.octets());
+            header[16..20].copy_from_slice(&d.octets());
+            let checksum = ipv4_checksum(&header);
+            header[10..12].copy_from_slice(&checksum.to_be_bytes());
+            packet.extend_from_slice(&header);
+        }
+        (s, d) => {
+            packet.extend_from_slice(&[0x60, 0, 0, 0]);
+            packet.extend_from_slice(&udp_len.to_be_bytes());
+            packet.push(17);
+            packet.push(64);
+            packet.extend_from_slice(&to_v6(s).octets());
+            packet.extend_from_slice(&to_v6(d).octets());
+        }
+    }
+    packet.extend_from_slice(&udp);
+    packet
+}
+
+fn to_v6(ip: IpAddr) -> Ipv6Addr {
+    match ip {
+        IpAddr::V4(v4) => v4.to_ipv6_mapped(),
+        IpAddr::V6(v6) => v6,
+    }
+}
+
+fn ipv4_checksum(header: &[u8; 20]) -> u16 {
+    let mut sum: u32 = header
+        .chunks(2)
+        .map(|w| u32::from(u16::from_be_bytes([w[0], w[1]])))
+        .sum();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pcap_parser::traits::PcapReaderIterator;
+    use pcap_parser::{Block, Linktype, PcapBlockOwned, PcapError, PcapNGReader};
+
+    fn frame(direction: Direction, packet: &[u8]) -> SmpFrame<'_> {
+        let local: SocketAddr = "192.168.186.1:50000".parse().unwrap();
+        let peer: SocketAddr = "192.168.186.10:1337".parse().unwrap();
+        SmpFrame::parse(direction, local, peer, packet).unwrap()
+    }
+
+    /// (link types, packet payloads) read back from a capture
+    fn read_capture(path: &Path) -> (Vec<Linktype>, Vec<Vec<u8>>) {
+        let data = fs::read(path).unwrap();
+        let mut reader = PcapNGReader::new(65536, data.as_slice()).unwrap();
+        let mut linktypes = Vec::new();
+        let mut packets = Vec::new();
+        loop {
+            match reader.next() {
+                Ok((offset, block)) => {
+                    match block {
+                        PcapBlockOwned::NG(Block::InterfaceDescription(idb)) => linktypes.push(idb.linktype),
+                        PcapBlockOwned::NG(Block::EnhancedPacket(epb)) => {
+                            packets.push(epb.data[..epb.caplen as usize].to_vec())
+                        }
+                        _ => {}
+                    }
+                    reader.consume(offset);
+                }
+                Err(PcapError::Eof) => break,
+                Err(PcapError::Incomplete(_)) => reader.refill().unwrap(),
+                Err(e) => panic!("invalid pcapng: {:?}", e),
+            }
+        }
+        (linktypes, packets)
+    }
+
+    #[test]
+    fn test_capture_opens_in_pcap_parser() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("trace.pcapng");
+        let observer = PcapObserver::create(&path, PcapRotation::default()).unwrap();
+
+        let request = [0x0a, 0, 0, 1, 0, 0, 3, 0, 0xa0];
+        let response = [0x0b, 0, 0, 1, 0, 0, 3, 0, 0xa0];
+        observer.on_frame(&frame(Direction::Outbound, &request));
+        observer.on_frame(&frame(Direction::Inbound, &response));
+
+        let (linktypes, packets) = read_capture(&path);
+        assert_eq!(linktypes, vec![Linktype(LINKTYPE_RAW as i32)]);
+        assert_eq!(packets.len(), 2);
+
+        // 20-byte IPv4 header, 8-byte UDP header, then the SMP frame
+        let outbound = &packets[0];
+        assert_eq!(outbound[0], 0x45);
+        assert_eq!(&outbound[16..20], &[192, 168, 186, 10]);
+        assert_eq!(u16::from_be_bytes([outbound[22], outbound[23]]), 1337);
+        assert_eq!(&outbound[28..], &request);
+        assert_eq!(ipv4_checksum(outbound[..20].try_into().unwrap()), 0);
+
+        let inbound = &packets[1];
+        assert_eq!(u16::from_be_bytes([inbound[20], inbound[21]]), 1337);
+        assert_eq!(&inbound[28..], &response);
+    }
+
+    #[test]
+    fn test_capture_rotates_at_size_cap() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("trace.pcapng");
+        let rotation = PcapRotation {
+            max_file_bytes: 256,
+            max_files: 3,
+        };
+        let observer = PcapObserver::create(&path, rotation).unwrap();
+
+        let packet = [0x0a, 0, 0, 1, 0, 0, 0, 0, 0xa0];
+        for _ in 0..20 {
+            observer.on_frame(&frame(Direction::Outbound, &packet));
+        }
+
+        for index in 0..3 {
+            let file = rotated_path(&path, index);
+            assert!(fs::metadata(&file).unwrap().len() <= 256);
+            assert!(!read_capture(&file).1.is_empty());
+        }
+        assert!(!rotated_path(&path, 3).exists());
+        assert_eq!(rotated_path(&path, 2), dir.path().join("trace.2.pcapng"));
+    }
+}
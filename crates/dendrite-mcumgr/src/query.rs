@@ -4,11 +4,14 @@ use anyhow::Result;
 use dendrite_core::{Device, DeviceId, DeviceInfo, DeviceStatus, FirmwareInfo};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::net::IpAddr;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::time::Duration;
 use thiserror::Error;
+use tokio::task::JoinSet;
 use tracing::{debug, info, warn};
 
-use crate::transport::UdpTransportAsync;
+use crate::transport::{Connection, Transport, Unanswered, UdpTransportAsync};
 
 /// MCUmgr port
 pub const MCUMGR_PORT: u16 = 1337;
@@ -16,7 +19,33 @@ pub const MCUMGR_PORT: u16 = 1337;
 /// Default timeout for queries
 pub const DEFAULT_TIMEOUT_MS: u64 = 5000;
 
+/// Default number of devices [`query_devices_parallel`] queries at once
+pub const DEFAULT_QUERY_CONCURRENCY: usize = 16;
+
+/// How long and how often a query waits for a device
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueryOptions {
+    /// Wait for each response
+    pub timeout: Duration,
+    /// Times an unanswered request is sent again
+    pub retries: u8,
+    /// Wait before the first retry, doubling for each one after
+    pub retry_backoff: Duration,
+}
+
+impl Default for QueryOptions {
+    /// [`DEFAULT_TIMEOUT_MS`] per response, two retries starting 100 ms apart
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_millis(DEFAULT_TIMEOUT_MS),
+            retries: 2,
+            retry_backoff: Duration::from_millis(100),
+        }
+    }
+}
+
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum QueryError {
     #[error("Device not reachable at {0}:{1}")]
     NotReachable(IpAddr, u16),
@@ -26,6 +55,65 @@ pub enum QueryError {
     InvalidResponse(String),
     #[error("Transport error: {0}")]
     TransportError(#[from] anyhow::Error),
+    #[error("Query of {0}:{1} timed out")]
+    TimedOut(IpAddr, u16),
+    #[error("Query task failed: {0}")]
+    TaskFailed(String),
+    #[error("Device not reachable on serial port {}", .0.display())]
+    SerialNotReachable(PathBuf),
+    /// No attempt at a request got a response (unlike `TimedOut`, which is a
+    /// whole query running out of time)
+    #[error("No response from {transport} after {attempts} attempts")]
+    Timeout { transport: String, attempts: u32 },
+    /// The device doesn't implement the command (`MGMT_ERR_ENOTSUP`)
+    #[error("{command} not supported by the device")]
+    Unsupported { command: &'static str },
+    /// The device answered with a non-zero NMP return code
+    #[error("{command} rejected: {} (rc {rc})", nmp_error_name(*rc))]
+    Rejected { command: &'static str, rc: i32 },
+}
+
+/// NMP return code for a command the device doesn't implement
+pub const NMP_ERR_ENOTSUP: i32 = 8;
+
+/// Name of an NMP (MCUmgr) return code
+pub fn nmp_error_name(rc: i32) -> &'static str {
+    match rc {
+        0 => "EOK",
+        1 => "EUNKNOWN",
+        2 => "ENOMEM",
+        3 => "EINVAL",
+        4 => "ETIMEOUT",
+        5 => "ENOENT",
+        6 => "EBADSTATE",
+        7 => "EMSGSIZE",
+        NMP_ERR_ENOTSUP => "ENOTSUP",
+        9 => "ECORRUPT",
+        10 => "EBUSY",
+        _ => "unknown error",
+    }
+}
+
+/// `Ok` for a zero `rc`, otherwise the error for the code
+fn check_rc(command: &'static str, rc: i32) -> Result<(), QueryError> {
+    match rc {
+        0 => Ok(()),
+        NMP_ERR_ENOTSUP => Err(QueryError::Unsupported { command }),
+        rc => Err(QueryError::Rejected { command, rc }),
+    }
+}
+
+impl QueryError {
+    /// `error` from pinging over `transport`: a timeout if nothing answered at all
+    fn unreachable(transport: &Transport, error: Option<anyhow::Error>) -> Self {
+        if let Some(Unanswered { attempts }) = error.as_ref().and_then(|e| e.downcast_ref::<Unanswered>()) {
+            return QueryError::Timeout { transport: transport.to_string(), attempts: *attempts };
+        }
+        match transport {
+            Transport::Udp(addr) => QueryError::NotReachable(addr.ip(), addr.port()),
+            Transport::Serial(config) => QueryError::SerialNotReachable(config.path.clone()),
+        }
+    }
 }
 
 /// Result of querying a device
@@ -44,7 +132,9 @@ pub struct DeviceQueryResult {
     /// Bootloader info
     pub bootloader: Option<BootloaderInfo>,
     /// Firmware images
-    pub images: Vec<ImageInfo>,
+    pub images: Vec<ImageSlot>,
+    /// Board metadata and fragment reference from the HCDF group, when the device has it
+    pub hcdf_info: Option<HcdfInfoResponse>,
 }
 
 #[derive(Debug, Clone)]
@@ -54,8 +144,9 @@ pub struct BootloaderInfo {
     pub no_downgrade: bool,
 }
 
+/// A firmware image slot as the SMP image group reports it
 #[derive(Debug, Clone)]
-pub struct ImageInfo {
+pub struct ImageSlot {
     pub slot: u32,
     pub version: String,
     pub hash: String,
@@ -93,6 +184,15 @@ struct BootloaderInfoRsp {
 struct ImageStateRsp {
     #[serde(default)]
     images: Vec<ImageEntry>,
+    #[serde(default)]
+    rc: i32,
+}
+
+#[derive(Serialize)]
+struct ImageConfirmReq {
+    #[serde(with = "serde_bytes")]
+    hash: Vec<u8>,
+    confirm: bool,
 }
 
 #[derive(Deserialize)]
@@ -131,16 +231,31 @@ mod hex_bytes {
 /// MCUmgr groups and commands
 mod nmp {
     pub const GROUP_DEFAULT: u16 = 0;
-    pub const GROUP_IMAGE: u16 = 1;
 
+    pub const ID_RESET: u8 = 5;
+    pub const ID_MCUMGR_PARAMS: u8 = 6;
     pub const ID_OS_INFO: u8 = 7;
     pub const ID_BOOTLOADER_INFO: u8 = 8;
-    pub const ID_IMAGE_STATE: u8 = 0;
 
     pub const OP_READ: u8 = 0;
     pub const OP_WRITE: u8 = 2;
 }
 
+/// SMP image management group for listing, uploading and confirming images
+pub mod image_group {
+    /// MCUmgr group ID for image management
+    pub const GROUP_IMAGE: u16 = 1;
+
+    /// Command ID for reading image state, or marking an image for test or confirming it
+    pub const ID_IMAGE_STATE: u8 = 0;
+
+    /// Command ID for uploading an image chunk
+    pub const ID_IMAGE_UPLOAD: u8 = 1;
+
+    /// Command ID for erasing the secondary slot
+    pub const ID_IMAGE_ERASE: u8 = 5;
+}
+
 /// CogniPilot HCDF MCUmgr group for querying device fragment information
 pub mod hcdf_group {
     /// MCUmgr group ID for HCDF queries (CogniPilot custom group)
@@ -150,11 +265,96 @@ pub mod hcdf_group {
     pub const ID_HCDF_INFO: u8 = 0;
 }
 
+/// CogniPilot status LED MCUmgr group for driving an enclosure's RGB LED
+pub mod status_led_group {
+    /// MCUmgr group ID for the status LED (CogniPilot custom group)
+    pub const GROUP_STATUS_LED: u16 = 101;
+
+    /// Command ID for setting the LED color
+    pub const ID_SET_COLOR: u8 = 0;
+}
+
+/// SMP shell management group for running Zephyr shell commands
+pub mod shell_group {
+    /// MCUmgr group ID for shell management
+    pub const GROUP_SHELL: u16 = 9;
+
+    /// Command ID for executing a shell command
+    pub const ID_SHELL_EXEC: u8 = 0;
+}
+
+/// Body of a status LED set-color request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatusLedColor {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl From<[u8; 3]> for StatusLedColor {
+    fn from([r, g, b]: [u8; 3]) -> Self {
+        Self { r, g, b }
+    }
+}
+
+/// SMP buffer parameters reported by a device
+///
+/// `buf_size` is the largest SMP packet the device accepts, header included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct McumgrParams {
+    pub buf_size: u32,
+    pub buf_count: u32,
+}
+
+#[derive(Deserialize)]
+struct McumgrParamsRsp {
+    #[serde(default)]
+    buf_size: u32,
+    #[serde(default)]
+    buf_count: u32,
+    #[serde(default)]
+    rc: i32,
+}
+
+#[derive(Deserialize)]
+struct RcRsp {
+    #[serde(default)]
+    rc: i32,
+}
+
+#[derive(Serialize)]
+struct ShellExecReq {
+    argv: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct ShellExecRsp {
+    /// Combined output of the command
+    #[serde(default)]
+    o: String,
+    /// The command's own return code
+    #[serde(default)]
+    ret: i32,
+    /// SMP return code, set when the command couldn't be run at all
+    #[serde(default)]
+    rc: i32,
+}
+
+/// Output of a shell command run with [`exec_shell`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShellOutput {
+    /// The command's return code (0 on success, negative errno otherwise)
+    pub ret: i32,
+    /// Everything the command printed, stdout and stderr together
+    pub output: String,
+}
+
 /// Response from HCDF info query
 ///
 /// Devices that support the HCDF group will return their fragment URL and SHA,
 /// allowing the daemon to skip network fetches if the cached version matches.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Newer firmware also reports its board, version and hardware ID there.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct HcdfInfoResponse {
     /// URL to the HCDF fragment file (e.g., "https://hcdf.cognipilot.org/spinali/v1.2.hcdf")
     #[serde(default)]
@@ -162,17 +362,115 @@ pub struct HcdfInfoResponse {
     /// SHA256 hash of the HCDF content (hex string)
     #[serde(default)]
     pub sha: Option<String>,
+    /// Board name without SoC and CPU (e.g., "mr_mcxn_t1")
+    #[serde(default)]
+    pub board: Option<String>,
+    /// Version of the running firmware (e.g., "0.3.1")
+    #[serde(default, rename = "version")]
+    pub firmware_version: Option<String>,
+    /// Hardware ID (chip unique ID)
+    #[serde(default)]
+    pub hwid: Option<String>,
+    /// CBOR body as the device sent it, for the fragment cache
+    #[serde(skip)]
+    pub raw: Vec<u8>,
+}
+
+impl HcdfInfoResponse {
+    /// Whether the device reported nothing
+    pub fn is_empty(&self) -> bool {
+        self.url.is_none()
+            && self.sha.is_none()
+            && self.board.is_none()
+            && self.firmware_version.is_none()
+            && self.hwid.is_none()
+    }
 }
 
 /// Query a device for all available information
-pub async fn query_device(ip: IpAddr, port: u16) -> Result<DeviceQueryResult, QueryError> {
-    info!(ip = %ip, port = port, "Querying device");
+///
+/// `transport` is a UDP address or a [`SerialPortConfig`](crate::SerialPortConfig).
+/// Unanswered requests are sent again as `options` says; if the device
+/// never answers the error is [`QueryError::Timeout`].
+pub async fn query_device(
+    transport: impl Into<Transport>,
+    options: QueryOptions,
+) -> Result<DeviceQueryResult, QueryError> {
+    query_device_with_options(&transport.into(), &options).await
+}
 
-    let mut transport = UdpTransportAsync::new(&ip.to_string(), port, DEFAULT_TIMEOUT_MS).await?;
+/// Query many devices, at most `concurrency` at a time, each within
+/// [`DEFAULT_TIMEOUT_MS`]
+///
+/// Failures are reported per address; one unreachable device doesn't fail
+/// the batch. See [`query_devices_parallel`] to choose the timeout.
+pub async fn query_devices(
+    addrs: &[SocketAddr],
+    concurrency: usize,
+) -> Vec<(SocketAddr, Result<DeviceQueryResult, QueryError>)> {
+    query_devices_parallel(addrs, concurrency, Duration::from_millis(DEFAULT_TIMEOUT_MS)).await
+}
+
+/// Query many devices, at most `concurrency` at a time
+///
+/// `timeout` bounds each device's whole query on its own, so a slow or
+/// silent device only holds up its own slot. Every address gets a result,
+/// in the order given, including ones whose task panicked.
+pub async fn query_devices_parallel(
+    addrs: &[SocketAddr],
+    concurrency: usize,
+    timeout: Duration,
+) -> Vec<(SocketAddr, Result<DeviceQueryResult, QueryError>)> {
+    let mut results: Vec<Option<Result<DeviceQueryResult, QueryError>>> = addrs.iter().map(|_| None).collect();
+    let mut pending = addrs.iter().copied().enumerate();
+    let mut running = HashMap::new();
+    let mut tasks = JoinSet::new();
+    loop {
+        while tasks.len() < concurrency.max(1) {
+            let Some((index, addr)) = pending.next() else {
+                break;
+            };
+            let task = tasks.spawn(query_device_within(addr, timeout));
+            running.insert(task.id(), index);
+        }
+        let (id, result) = match tasks.join_next_with_id().await {
+            Some(Ok((id, result))) => (id, result),
+            Some(Err(e)) => (e.id(), Err(QueryError::TaskFailed(e.to_string()))),
+            None => break,
+        };
+        if let Some(index) = running.remove(&id) {
+            results[index] = Some(result);
+        }
+    }
+
+    addrs
+        .iter()
+        .zip(results)
+        .map(|(&addr, result)| {
+            (addr, result.unwrap_or_else(|| Err(QueryError::TaskFailed("query was not run".to_string()))))
+        })
+        .collect()
+}
+
+/// Query one device, giving up after `timeout`
+async fn query_device_within(addr: SocketAddr, timeout: Duration) -> Result<DeviceQueryResult, QueryError> {
+    let transport = Transport::Udp(addr);
+    let options = QueryOptions { timeout, ..QueryOptions::default() };
+    tokio::time::timeout(timeout, query_device_with_options(&transport, &options))
+        .await
+        .unwrap_or_else(|_| Err(QueryError::TimedOut(addr.ip(), addr.port())))
+}
+
+async fn query_device_with_options(target: &Transport, options: &QueryOptions) -> Result<DeviceQueryResult, QueryError> {
+    info!(transport = %target, "Querying device");
+
+    let mut transport = target.connect(options).await?;
 
     // First check if device is reachable
-    if !transport.ping().await.unwrap_or(false) {
-        return Err(QueryError::NotReachable(ip, port));
+    match transport.ping().await {
+        Ok(true) => {}
+        Ok(false) => return Err(QueryError::unreachable(target, None)),
+        Err(e) => return Err(QueryError::unreachable(target, Some(e))),
     }
 
     debug!("Device is reachable, querying info");
@@ -185,6 +483,7 @@ pub async fn query_device(ip: IpAddr, port: u16) -> Result<DeviceQueryResult, Qu
         processor: None,
         bootloader: None,
         images: Vec::new(),
+        hcdf_info: None,
     };
 
     // Query hardware ID
@@ -218,11 +517,16 @@ pub async fn query_device(ip: IpAddr, port: u16) -> Result<DeviceQueryResult, Qu
         result.images = images;
     }
 
+    // Query HCDF info (only CogniPilot firmware has the group)
+    if let Ok(info) = read_hcdf_info(&mut transport).await {
+        result.hcdf_info = info;
+    }
+
     Ok(result)
 }
 
 /// Query OS info with specific format
-async fn query_os_info(transport: &mut UdpTransportAsync, format: &str) -> Result<String> {
+async fn query_os_info(transport: &mut Connection, format: &str) -> Result<String> {
     let req = OsInfoReq { format };
     let body = serde_cbor::to_vec(&req)?;
 
@@ -239,7 +543,7 @@ async fn query_os_info(transport: &mut UdpTransportAsync, format: &str) -> Resul
 }
 
 /// Query bootloader information
-async fn query_bootloader_info(transport: &mut UdpTransportAsync) -> Result<BootloaderInfo> {
+async fn query_bootloader_info(transport: &mut Connection) -> Result<BootloaderInfo> {
     let body = serde_cbor::to_vec(&HashMap::<String, String>::new())?;
 
     let resp_body = transport
@@ -275,19 +579,21 @@ async fn query_bootloader_info(transport: &mut UdpTransportAsync) -> Result<Boot
 }
 
 /// Query image state (firmware slots)
-async fn query_image_state(transport: &mut UdpTransportAsync) -> Result<Vec<ImageInfo>> {
+pub(crate) async fn query_image_state(transport: &mut Connection) -> Result<Vec<ImageSlot>> {
     let body = serde_cbor::to_vec(&HashMap::<String, String>::new())?;
 
     let resp_body = transport
-        .transceive(nmp::OP_READ, nmp::GROUP_IMAGE, nmp::ID_IMAGE_STATE, &body)
+        .transceive(nmp::OP_READ, image_group::GROUP_IMAGE, image_group::ID_IMAGE_STATE, &body)
         .await?;
 
     let resp: ImageStateRsp = serde_cbor::from_slice(&resp_body)?;
+    Ok(image_slots(resp))
+}
 
-    Ok(resp
-        .images
+fn image_slots(resp: ImageStateRsp) -> Vec<ImageSlot> {
+    resp.images
         .into_iter()
-        .map(|img| ImageInfo {
+        .map(|img| ImageSlot {
             slot: img.slot,
             version: img.version,
             hash: hex_encode(&img.hash),
@@ -296,10 +602,128 @@ async fn query_image_state(transport: &mut UdpTransportAsync) -> Result<Vec<Imag
             confirmed: img.confirmed,
             active: img.active,
         })
-        .collect())
+        .collect()
+}
+
+/// List a device's firmware image slots
+///
+/// `transport` is a UDP address or a [`SerialPortConfig`](crate::SerialPortConfig).
+/// Devices without the image group answer `Unsupported`.
+pub async fn list_images(transport: impl Into<Transport>) -> Result<Vec<ImageSlot>, QueryError> {
+    let target = transport.into();
+    debug!(transport = %target, "Listing images");
+
+    let mut transport = target.connect(&QueryOptions::default()).await?;
+    read_image_slots(&mut transport).await
+}
+
+async fn read_image_slots(transport: &mut Connection) -> Result<Vec<ImageSlot>, QueryError> {
+    let body = serde_cbor::to_vec(&HashMap::<String, String>::new())
+        .map_err(|e| QueryError::QueryFailed(e.to_string()))?;
+    let resp_body = transport
+        .transceive(nmp::OP_READ, image_group::GROUP_IMAGE, image_group::ID_IMAGE_STATE, &body)
+        .await?;
+    let resp: ImageStateRsp =
+        serde_cbor::from_slice(&resp_body).map_err(|e| QueryError::InvalidResponse(e.to_string()))?;
+    check_rc("image list", resp.rc)?;
+    Ok(image_slots(resp))
+}
+
+/// Send a command with an empty body, checking the `rc` of the answer
+async fn empty_command(
+    transport: &mut Connection,
+    command: &'static str,
+    group: u16,
+    id: u8,
+) -> Result<(), QueryError> {
+    let body = serde_cbor::to_vec(&HashMap::<String, String>::new())
+        .map_err(|e| QueryError::QueryFailed(e.to_string()))?;
+    let resp_body = transport.transceive(nmp::OP_WRITE, group, id, &body).await?;
+    let resp: RcRsp = serde_cbor::from_slice(&resp_body).map_err(|e| QueryError::InvalidResponse(e.to_string()))?;
+    check_rc(command, resp.rc)
+}
+
+/// What [`revert_image`] did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Revert {
+    /// The secondary slot was marked for test and has been erased, so the
+    /// next reset keeps the running image
+    PendingErased,
+    /// The running image booted for test without being confirmed; the device
+    /// was reset and MCUboot swaps the previous image back in
+    Reset,
+}
+
+/// Confirm the image with `hash` (hex, as in [`ImageSlot::hash`]) so it stays
+/// after the next reset
+///
+/// After an update boots an image marked for test, confirming the running
+/// image makes it permanent; otherwise MCUboot reverts to the previous one.
+/// A device that doesn't know the hash answers with a non-zero `rc`, which
+/// is returned as `Rejected`.
+pub async fn confirm_image(transport: impl Into<Transport>, hash: &str) -> Result<(), QueryError> {
+    let target = transport.into();
+    debug!(transport = %target, hash = %hash, "Confirming image");
+
+    let hash = hex_decode(hash).ok_or_else(|| QueryError::QueryFailed(format!("invalid image hash {hash:?}")))?;
+    let mut transport = target.connect(&QueryOptions::default()).await?;
+    let body = serde_cbor::to_vec(&ImageConfirmReq { hash, confirm: true })
+        .map_err(|e| QueryError::QueryFailed(e.to_string()))?;
+    let resp_body = transport
+        .transceive(nmp::OP_WRITE, image_group::GROUP_IMAGE, image_group::ID_IMAGE_STATE, &body)
+        .await?;
+    let resp: RcRsp = serde_cbor::from_slice(&resp_body)
+        .map_err(|e| QueryError::InvalidResponse(e.to_string()))?;
+    check_rc("image confirm", resp.rc)
+}
+
+/// Undo an update that hasn't been confirmed
+///
+/// An image still waiting in the secondary slot for its test boot is erased.
+/// Otherwise, if the running image booted for test and isn't confirmed, the
+/// device is reset so MCUboot reverts to the previous image. With nothing
+/// pending or unconfirmed there is nothing to revert and `QueryFailed` is
+/// returned.
+pub async fn revert_image(transport: impl Into<Transport>) -> Result<Revert, QueryError> {
+    let target = transport.into();
+    debug!(transport = %target, "Reverting image");
+
+    let mut transport = target.connect(&QueryOptions::default()).await?;
+    let images = read_image_slots(&mut transport).await?;
+    if images.iter().any(|i| i.pending && !i.active) {
+        empty_command(&mut transport, "image erase", image_group::GROUP_IMAGE, image_group::ID_IMAGE_ERASE).await?;
+        return Ok(Revert::PendingErased);
+    }
+    if images.iter().any(|i| i.active && !i.confirmed) {
+        empty_command(&mut transport, "reset", nmp::GROUP_DEFAULT, nmp::ID_RESET).await?;
+        return Ok(Revert::Reset);
+    }
+    Err(QueryError::QueryFailed("no pending or unconfirmed image to revert".to_string()))
+}
+
+/// Read a device's SMP buffer size and count
+///
+/// Returns `Ok(None)` when the device doesn't implement the command (older
+/// Zephyr builds answer with a non-zero `rc`).
+pub async fn query_mcumgr_params(transport: impl Into<Transport>) -> Result<Option<McumgrParams>, QueryError> {
+    let target = transport.into();
+    debug!(transport = %target, "Querying MCUmgr parameters");
+
+    let mut transport = target.connect(&QueryOptions::default()).await?;
+    let body = serde_cbor::to_vec(&HashMap::<String, String>::new())
+        .map_err(|e| QueryError::QueryFailed(e.to_string()))?;
+    let resp_body = transport
+        .transceive(nmp::OP_READ, nmp::GROUP_DEFAULT, nmp::ID_MCUMGR_PARAMS, &body)
+        .await?;
+    let resp: McumgrParamsRsp =
+        serde_cbor::from_slice(&resp_body).map_err(|e| QueryError::InvalidResponse(e.to_string()))?;
+    if resp.rc != 0 || resp.buf_size == 0 {
+        return Ok(None);
+    }
+    Ok(Some(McumgrParams { buf_size: resp.buf_size, buf_count: resp.buf_count }))
 }
 
-fn hex_encode(bytes: &[u8]) -> String {
+pub(crate) fn hex_encode(bytes: &[u8]) -> String {
     const HEX_CHARS: &[u8; 16] = b"0123456789abcdef";
     let mut s = String::with_capacity(bytes.len() * 2);
     for &b in bytes {
@@ -309,6 +733,16 @@ fn hex_encode(bytes: &[u8]) -> String {
     s
 }
 
+fn hex_decode(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| hex.get(i..i + 2).and_then(|b| u8::from_str_radix(b, 16).ok()))
+        .collect()
+}
+
 /// Parsed info from the os_info string
 struct ParsedOsInfo {
     /// App name (e.g., "optical-flow")
@@ -345,11 +779,9 @@ fn parse_os_info_fields(os_info: &str) -> ParsedOsInfo {
         if part.contains('/') {
             // Verify it's not a version number
             if !part.chars().next().map(|c| c.is_ascii_digit()).unwrap_or(false) {
-                // Extract just the board name (before first /)
-                if let Some(board_name) = part.split('/').next() {
-                    result.board = Some(board_name.to_string());
-                    debug!(full_board = %part, board_name = %board_name, "Parsed board name");
-                }
+                let board_name = board_name(part);
+                result.board = Some(board_name.to_string());
+                debug!(full_board = %part, board_name = %board_name, "Parsed board name");
                 break;
             }
         }
@@ -358,58 +790,81 @@ fn parse_os_info_fields(os_info: &str) -> ParsedOsInfo {
     result
 }
 
-/// Probe an IP address to check if it has an MCUmgr device
-pub async fn probe_device(ip: IpAddr, port: u16, timeout_ms: u64) -> bool {
-    match UdpTransportAsync::new(&ip.to_string(), port, timeout_ms).await {
+/// Board name from a Zephyr board target: "mr_mcxn_t1/mcxn947/cpu0" -> "mr_mcxn_t1"
+fn board_name(target: &str) -> &str {
+    target.split('/').next().unwrap_or(target)
+}
+
+/// Parse the body of an HCDF info response
+///
+/// The board is trimmed to its name like the one parsed from OS info, and the
+/// body is kept in [`HcdfInfoResponse::raw`]. A device reporting no field
+/// gives `None`.
+pub fn parse_hcdf_info(body: &[u8]) -> Result<Option<HcdfInfoResponse>, QueryError> {
+    let mut info: HcdfInfoResponse =
+        serde_cbor::from_slice(body).map_err(|e| QueryError::InvalidResponse(e.to_string()))?;
+    if info.is_empty() {
+        return Ok(None);
+    }
+    info.board = info.board.as_deref().map(board_name).map(str::to_string);
+    info.raw = body.to_vec();
+    Ok(Some(info))
+}
+
+/// Read HCDF info over an open connection; errors if the device lacks the group
+async fn read_hcdf_info(transport: &mut Connection) -> Result<Option<HcdfInfoResponse>, QueryError> {
+    // Send empty request body
+    let body = serde_cbor::to_vec(&HashMap::<String, String>::new())
+        .map_err(|e| QueryError::QueryFailed(e.to_string()))?;
+    let resp_body = transport
+        .transceive(
+            nmp::OP_READ,
+            hcdf_group::GROUP_HCDF,
+            hcdf_group::ID_HCDF_INFO,
+            &body,
+        )
+        .await?;
+    parse_hcdf_info(&resp_body)
+}
+
+/// Probe an address or serial port to check if it has an MCUmgr device
+///
+/// The echo is sent again as `options` says before giving up.
+pub async fn probe_device(transport: impl Into<Transport>, options: QueryOptions) -> bool {
+    match transport.into().connect(&options).await {
         Ok(mut transport) => transport.ping().await.unwrap_or(false),
         Err(_) => false,
     }
 }
 
-/// Query HCDF info from a device (URL and SHA of its fragment)
+/// Query HCDF info from a device (fragment URL and SHA, board metadata)
 ///
 /// This queries the CogniPilot custom MCUmgr group (100) to get the device's
-/// HCDF fragment URL and content hash. If the device doesn't support this group,
-/// None is returned.
+/// HCDF fragment URL and content hash, and the board, firmware version and
+/// hardware ID where the firmware reports them. If the device doesn't support
+/// this group, None is returned.
 ///
 /// # Arguments
-/// * `ip` - Device IP address
-/// * `port` - MCUmgr port (usually 1337)
+/// * `transport` - Device address (MCUmgr port usually 1337) or serial port
 ///
 /// # Returns
 /// * `Ok(Some(response))` - Device returned HCDF info
 /// * `Ok(None)` - Device doesn't support HCDF group or returned empty response
 /// * `Err(e)` - Transport or parse error
-pub async fn query_hcdf_info(ip: IpAddr, port: u16) -> Result<Option<HcdfInfoResponse>, QueryError> {
-    debug!(ip = %ip, port = port, "Querying HCDF info");
-
-    let mut transport = UdpTransportAsync::new(&ip.to_string(), port, DEFAULT_TIMEOUT_MS).await?;
-
-    // Send empty request body
-    let body = serde_cbor::to_vec(&HashMap::<String, String>::new())
-        .map_err(|e| QueryError::QueryFailed(e.to_string()))?;
+pub async fn query_hcdf_info(transport: impl Into<Transport>) -> Result<Option<HcdfInfoResponse>, QueryError> {
+    let target = transport.into();
+    debug!(transport = %target, "Querying HCDF info");
 
-    match transport
-        .transceive(
-            nmp::OP_READ,
-            hcdf_group::GROUP_HCDF,
-            hcdf_group::ID_HCDF_INFO,
-            &body,
-        )
-        .await
-    {
-        Ok(resp_body) => {
-            let resp: HcdfInfoResponse = serde_cbor::from_slice(&resp_body)
-                .map_err(|e| QueryError::InvalidResponse(e.to_string()))?;
+    let mut transport = target.connect(&QueryOptions::default()).await?;
 
-            // Return None if both fields are empty
-            if resp.url.is_none() && resp.sha.is_none() {
-                return Ok(None);
+    match read_hcdf_info(&mut transport).await {
+        Ok(resp) => {
+            if let Some(resp) = &resp {
+                debug!(url = ?resp.url, sha = ?resp.sha, board = ?resp.board, "Got HCDF info");
             }
-
-            debug!(url = ?resp.url, sha = ?resp.sha, "Got HCDF info");
-            Ok(Some(resp))
+            Ok(resp)
         }
+        Err(e @ QueryError::InvalidResponse(_)) => Err(e),
         Err(e) => {
             // If the device doesn't support the group, it will return an error
             // This is expected behavior, not a failure
@@ -419,17 +874,85 @@ pub async fn query_hcdf_info(ip: IpAddr, port: u16) -> Result<Option<HcdfInfoRes
     }
 }
 
+/// Set a device's status LED color
+///
+/// Uses the CogniPilot status LED group (101). Devices without the group
+/// answer with a non-zero `rc`, which is returned as `QueryFailed`.
+pub async fn set_status_led(ip: IpAddr, port: u16, color: StatusLedColor) -> Result<(), QueryError> {
+    debug!(ip = %ip, port = port, color = ?color, "Setting status LED");
+
+    let mut transport = UdpTransportAsync::new(SocketAddr::new(ip, port), DEFAULT_TIMEOUT_MS).await?;
+    let body = serde_cbor::to_vec(&color).map_err(|e| QueryError::QueryFailed(e.to_string()))?;
+
+    let resp_body = transport
+        .transceive(
+            nmp::OP_WRITE,
+            status_led_group::GROUP_STATUS_LED,
+            status_led_group::ID_SET_COLOR,
+            &body,
+        )
+        .await?;
+    let resp: RcRsp = serde_cbor::from_slice(&resp_body)
+        .map_err(|e| QueryError::InvalidResponse(e.to_string()))?;
+    if resp.rc != 0 {
+        return Err(QueryError::QueryFailed(format!("status LED rejected (rc {})", resp.rc)));
+    }
+    Ok(())
+}
+
+/// Run a Zephyr shell command on a device through the SMP shell group
+///
+/// `command` is split on whitespace into the shell's argv; quoting isn't
+/// supported. The request isn't retried, so a command that timed out may
+/// still have run. A command the shell rejects (unknown, bad arguments) is
+/// not an error: its return code and message are in the [`ShellOutput`].
+/// Firmware built without the shell group answers `Unsupported`.
+pub async fn exec_shell(
+    transport: impl Into<Transport>,
+    command: &str,
+    timeout: Duration,
+) -> Result<ShellOutput, QueryError> {
+    let target = transport.into();
+    let argv: Vec<String> = command.split_whitespace().map(str::to_string).collect();
+    if argv.is_empty() {
+        return Err(QueryError::QueryFailed("empty shell command".to_string()));
+    }
+    debug!(transport = %target, command = %command, "Running shell command");
+
+    let options = QueryOptions { timeout, retries: 0, ..QueryOptions::default() };
+    let mut transport = target.connect(&options).await?;
+    let body = serde_cbor::to_vec(&ShellExecReq { argv }).map_err(|e| QueryError::QueryFailed(e.to_string()))?;
+    let resp_body = transport
+        .transceive(nmp::OP_WRITE, shell_group::GROUP_SHELL, shell_group::ID_SHELL_EXEC, &body)
+        .await?;
+    let resp: ShellExecRsp =
+        serde_cbor::from_slice(&resp_body).map_err(|e| QueryError::InvalidResponse(e.to_string()))?;
+    check_rc("shell exec", resp.rc)?;
+    Ok(ShellOutput { ret: resp.ret, output: resp.o })
+}
+
 /// Convert query result to Device struct
+///
+/// The id comes from [`DeviceId::derive`]: the hardware ID, else the MAC
+/// address (when the caller knows it), else the board at `ip` and `port`. A device that
+/// reported none of them gets a temporary id. Board, hardware ID and firmware
+/// version reported through the HCDF group take precedence over OS info and
+/// image state.
 pub fn query_result_to_device(
     ip: IpAddr,
     port: u16,
-    result: DeviceQueryResult,
+    mac: Option<&str>,
+    mut result: DeviceQueryResult,
 ) -> Device {
-    let id = result
-        .hwid
-        .as_ref()
-        .map(|h| DeviceId::from_hwid(h))
-        .unwrap_or_else(DeviceId::temporary);
+    let hcdf_info = result.hcdf_info.take().unwrap_or_default();
+    result.board = hcdf_info.board.or(result.board);
+    result.hwid = hcdf_info.hwid.or(result.hwid);
+
+    let id = if result.hwid.is_some() || mac.is_some() || result.board.is_some() {
+        DeviceId::derive(result.board.as_deref(), mac, result.hwid.as_deref(), SocketAddr::new(ip, port))
+    } else {
+        DeviceId::temporary()
+    };
 
     // Use app name as the device name, falling back to board or IP
     let name = result
@@ -440,6 +963,7 @@ pub fn query_result_to_device(
 
     let mut device = Device::new(id, name, ip, port);
     device.status = DeviceStatus::Online;
+    device.discovery.mac = mac.map(str::to_string);
 
     device.info = DeviceInfo {
         os_name: result.os_info,
@@ -460,7 +984,7 @@ pub fn query_result_to_device(
     if let Some(img) = active_image {
         device.firmware = FirmwareInfo {
             name: result.app_name.clone(),
-            version: Some(img.version.clone()),
+            version: Some(hcdf_info.firmware_version.clone().unwrap_or_else(|| img.version.clone())),
             build_date: None, // Not available from MCUmgr image_state
             image_hash: Some(img.hash.clone()),
             confirmed: img.confirmed,
@@ -471,3 +995,308 @@ pub fn query_result_to_device(
 
     device
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dendrite_test_support::{hwid, MockHcdfInfo, MockIdentity, MockSmpDevice};
+    use std::collections::BTreeMap;
+    use std::time::Instant;
+
+    #[test]
+    fn test_device_id_without_hwid() {
+        let result = DeviceQueryResult {
+            hwid: None,
+            os_info: None,
+            app_name: None,
+            board: Some("spinali".to_string()),
+            processor: None,
+            bootloader: None,
+            images: Vec::new(),
+            hcdf_info: None,
+        };
+        let ip: IpAddr = "10.0.0.5".parse().unwrap();
+
+        // The MAC keeps two boards of the same type apart, across restarts
+        let a = query_result_to_device(ip, 1337, Some("aa:bb:cc:00:11:22"), result.clone());
+        let b = query_result_to_device(ip, 1337, Some("aa:bb:cc:00:11:33"), result.clone());
+        assert_ne!(a.id, b.id);
+        assert_eq!(a.id, query_result_to_device(ip, 1337, Some("aa:bb:cc:00:11:22"), result.clone()).id);
+        assert_eq!(a.discovery.mac.as_deref(), Some("aa:bb:cc:00:11:22"));
+
+        // Without one, two routed boards of the same type are told apart by address
+        let a = query_result_to_device(ip, 1337, None, result.clone());
+        let b = query_result_to_device("10.0.0.6".parse().unwrap(), 1337, None, result.clone());
+        assert_eq!(a.id.as_str(), "board-spinali@10.0.0.5:1337");
+        assert_ne!(a.id, b.id);
+        assert_eq!(a.id, query_result_to_device(ip, 1337, None, result.clone()).id);
+        let unknown = DeviceQueryResult { board: None, ..result };
+        assert!(query_result_to_device(ip, 1337, None, unknown).id.as_str().starts_with("temp-"));
+    }
+
+    #[tokio::test]
+    async fn test_query_hcdf_info_from_mock_device() {
+        let device = MockSmpDevice::spawn(MockHcdfInfo {
+            url: Some("https://hcdf.cognipilot.org/spinali/spinali.hcdf".to_string()),
+            sha: Some("abc123".to_string()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        let info = query_hcdf_info(device.addr()).await.unwrap().unwrap();
+        assert_eq!(info.sha.as_deref(), Some("abc123"));
+
+        // A device reporting neither field has no HCDF info
+        let empty = MockSmpDevice::spawn(MockHcdfInfo::default()).await.unwrap();
+        assert!(query_hcdf_info(empty.addr()).await.unwrap().is_none());
+    }
+
+    /// HCDF info response recorded from an optical-flow board, SMP header included
+    const HCDF_INFO_RESPONSE: &str = include_str!("../fixtures/hcdf_info_response.hex");
+
+    #[test]
+    fn test_parse_recorded_hcdf_info() {
+        let hex: String = HCDF_INFO_RESPONSE.split_whitespace().collect();
+        let packet: Vec<u8> = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect();
+        let body = crate::transport::response_body(&packet, 0x2a).unwrap();
+
+        let info = parse_hcdf_info(&body).unwrap().unwrap();
+        assert_eq!(info.board.as_deref(), Some("mr_mcxn_t1"));
+        assert_eq!(info.firmware_version.as_deref(), Some("0.3.1"));
+        assert_eq!(info.hwid.as_deref(), Some("4d3c2b1a00ff8e7d"));
+        assert_eq!(info.url.as_deref(), Some("https://hcdf.cognipilot.org/optical-flow/optical-flow.hcdf"));
+        assert_eq!(info.sha.as_ref().map(String::len), Some(64));
+        assert_eq!(info.raw, body);
+
+        // Firmware without the group answers with just an rc
+        let unsupported = serde_cbor::to_vec(&HashMap::from([("rc", 8)])).unwrap();
+        assert!(parse_hcdf_info(&unsupported).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_list_and_confirm_images() {
+        let device = MockSmpDevice::spawn(MockHcdfInfo::default()).await.unwrap();
+        device.set_pending_image(dendrite_test_support::mcuboot_image(64, 0xcd));
+        device.set_unconfirmed_active();
+
+        let images = list_images(device.addr()).await.unwrap();
+        assert_eq!(images.len(), 2);
+        let running = &images[0];
+        assert!(running.active && !running.confirmed);
+        assert!(images[1].pending);
+        assert_eq!(images[1].hash, "cd".repeat(32));
+
+        confirm_image(device.addr(), &running.hash).await.unwrap();
+        let images = list_images(device.addr()).await.unwrap();
+        assert!(images[0].active && images[0].confirmed);
+        let request: BTreeMap<String, serde_cbor::Value> =
+            device.requests_for(image_group::GROUP_IMAGE, image_group::ID_IMAGE_STATE)[1].decode().unwrap();
+        assert_eq!(request["confirm"], serde_cbor::Value::Bool(true));
+
+        // A hash the device doesn't have is refused, and one that isn't hex never sent
+        let unknown = confirm_image(device.addr(), &"ab".repeat(32)).await.unwrap_err();
+        assert!(matches!(unknown, QueryError::Rejected { rc: 3, .. }), "{unknown}");
+        assert_eq!(unknown.to_string(), "image confirm rejected: EINVAL (rc 3)");
+        assert!(matches!(confirm_image(device.addr(), "xyz").await, Err(QueryError::QueryFailed(_))));
+    }
+
+    #[tokio::test]
+    async fn test_revert_image() {
+        let device = MockSmpDevice::spawn(MockHcdfInfo::default()).await.unwrap();
+        assert!(matches!(revert_image(device.addr()).await, Err(QueryError::QueryFailed(_))));
+
+        // Marked for test but not booted yet: the slot is erased
+        device.set_pending_image(dendrite_test_support::mcuboot_image(64, 0xcd));
+        assert_eq!(revert_image(device.addr()).await.unwrap(), Revert::PendingErased);
+        assert_eq!(list_images(device.addr()).await.unwrap().len(), 1);
+
+        // Booted for test: a reset brings the previous image back
+        device.set_unconfirmed_active();
+        assert_eq!(revert_image(device.addr()).await.unwrap(), Revert::Reset);
+        assert!(list_images(device.addr()).await.unwrap()[0].confirmed);
+    }
+
+    #[tokio::test]
+    async fn test_image_group_unsupported() {
+        let device = MockSmpDevice::spawn(MockHcdfInfo::default()).await.unwrap();
+        device.disable_image_group();
+        let error = list_images(device.addr()).await.unwrap_err();
+        assert!(matches!(error, QueryError::Unsupported { command: "image list" }), "{error}");
+    }
+
+    #[test]
+    fn test_hex_decode() {
+        assert_eq!(hex_decode("00ff1a"), Some(vec![0x00, 0xff, 0x1a]));
+        assert_eq!(hex_decode(&hex_encode(&[0xde, 0xad])), Some(vec![0xde, 0xad]));
+        assert_eq!(hex_decode("abc"), None);
+        assert_eq!(hex_decode("zz"), None);
+    }
+
+    #[tokio::test]
+    async fn test_exec_shell() {
+        let device = MockSmpDevice::spawn(MockHcdfInfo::default()).await.unwrap();
+        device.set_shell_command("device list", "devices:\n- uart@4000 (READY)\n", 0);
+        let timeout = Duration::from_secs(2);
+
+        let output = exec_shell(device.addr(), "  device   list ", timeout).await.unwrap();
+        assert_eq!(output, ShellOutput { ret: 0, output: "devices:\n- uart@4000 (READY)\n".to_string() });
+        let sent: HashMap<String, Vec<String>> = device.requests_for(9, 0)[0].decode().unwrap();
+        assert_eq!(sent["argv"], ["device", "list"]);
+
+        // The shell's own failures come back as output, not errors
+        let output = exec_shell(device.addr(), "net iface", timeout).await.unwrap();
+        assert_eq!(output.ret, -8);
+        assert!(output.output.contains("command not found"), "{}", output.output);
+
+        assert!(matches!(exec_shell(device.addr(), " ", timeout).await, Err(QueryError::QueryFailed(_))));
+        device.disable_shell_group();
+        assert!(matches!(
+            exec_shell(device.addr(), "device list", timeout).await,
+            Err(QueryError::Unsupported { command: "shell exec" })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_device_takes_board_metadata_from_hcdf_info() {
+        let info = MockHcdfInfo {
+            board: Some("mr_mcxn_t1/mcxn947/cpu0".to_string()),
+            version: Some("0.3.1".to_string()),
+            hwid: Some(hwid(7)),
+            ..Default::default()
+        };
+        let device = MockSmpDevice::spawn(info).await.unwrap();
+        let result = query_device(device.addr(), QueryOptions::default()).await.unwrap();
+        assert_eq!(result.hcdf_info.as_ref().and_then(|i| i.board.as_deref()), Some("mr_mcxn_t1"));
+
+        let device = query_result_to_device(device.addr().ip(), device.port(), None, result);
+        assert_eq!(device.info.board.as_deref(), Some("mr_mcxn_t1"));
+        assert_eq!(device.firmware.version.as_deref(), Some("0.3.1"));
+        assert_eq!(device.id, DeviceId::derive(Some("mr_mcxn_t1"), None, Some(hwid(7).as_str()), device.discovery.socket_addr()));
+    }
+
+    #[tokio::test]
+    async fn test_probe_over_missing_serial_port() {
+        let port = crate::SerialPortConfig::new("/nonexistent/ttyACM9").with_baud_rate(921_600);
+        assert!(!probe_device(port.clone(), QueryOptions::default()).await);
+        assert!(query_device(port, QueryOptions::default()).await.is_err());
+    }
+
+    fn quick(retries: u8) -> QueryOptions {
+        QueryOptions { timeout: Duration::from_millis(100), retries, retry_backoff: Duration::from_millis(10) }
+    }
+
+    #[tokio::test]
+    async fn test_probe_retries_dropped_requests() {
+        let device = MockSmpDevice::spawn(MockHcdfInfo::default()).await.unwrap();
+
+        device.drop_requests(2);
+        assert!(probe_device(device.addr(), quick(2)).await);
+        // Each retry is a new request
+        let echoes = device.requests_for(nmp::GROUP_DEFAULT, 0);
+        assert_eq!(echoes.len(), 3);
+        assert!(echoes[1].at - echoes[0].at >= Duration::from_millis(110));
+        assert!(echoes[2].at - echoes[1].at >= Duration::from_millis(120));
+
+        device.drop_requests(1);
+        assert!(!probe_device(device.addr(), quick(0)).await);
+    }
+
+    #[tokio::test]
+    async fn test_unanswered_query_reports_attempts() {
+        let device = MockSmpDevice::spawn(MockHcdfInfo::default()).await.unwrap();
+        device.drop_requests(u32::MAX);
+
+        let error = query_device(device.addr(), quick(1)).await.unwrap_err();
+        assert!(matches!(error, QueryError::Timeout { attempts: 2, .. }), "{error}");
+        assert!(error.to_string().ends_with("after 2 attempts"));
+    }
+
+    #[tokio::test]
+    async fn test_set_status_led_sends_color() {
+        let device = MockSmpDevice::spawn(MockHcdfInfo::default()).await.unwrap();
+        let color = StatusLedColor::from([255, 160, 0]);
+
+        set_status_led(device.addr().ip(), device.port(), color).await.unwrap();
+
+        let requests = device.requests_for(status_led_group::GROUP_STATUS_LED, status_led_group::ID_SET_COLOR);
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].op, nmp::OP_WRITE);
+        assert_eq!(requests[0].decode::<StatusLedColor>().unwrap(), color);
+    }
+
+    async fn devices_with_hwids(count: usize) -> Vec<MockSmpDevice> {
+        let mut devices = Vec::new();
+        for i in 0..count {
+            let identity = MockIdentity { hwid: Some(hwid(i)), os_info: None };
+            devices.push(MockSmpDevice::spawn_with_identity(MockHcdfInfo::default(), identity).await.unwrap());
+        }
+        devices
+    }
+
+    #[tokio::test]
+    async fn test_query_devices_parallel_is_bounded() {
+        let devices = devices_with_hwids(6).await;
+        // Earlier devices answer slower, so they finish last
+        for (i, device) in devices.iter().enumerate() {
+            device.set_response_delay(Duration::from_millis(10 * (6 - i as u64)));
+        }
+        let addrs: Vec<SocketAddr> = devices.iter().map(MockSmpDevice::addr).collect();
+
+        let results = query_devices_parallel(&addrs, 2, Duration::from_secs(5)).await;
+
+        // Every address, in the order given, with its own device's answer
+        assert_eq!(results.len(), 6);
+        for (i, (addr, result)) in results.iter().enumerate() {
+            assert_eq!(*addr, addrs[i]);
+            assert_eq!(result.as_ref().unwrap().hwid, Some(hwid(i)));
+        }
+
+        // Never more than two devices between their first and last request
+        let spans: Vec<(Instant, Instant)> = devices
+            .iter()
+            .map(|device| {
+                let requests = device.requests();
+                (requests.first().unwrap().at, requests.last().unwrap().at)
+            })
+            .collect();
+        let busiest = spans
+            .iter()
+            .map(|(start, _)| spans.iter().filter(|(s, e)| s <= start && start <= e).count())
+            .max()
+            .unwrap();
+        assert_eq!(busiest, 2);
+    }
+
+    #[tokio::test]
+    async fn test_query_devices_keeps_errors_per_address() {
+        let devices = devices_with_hwids(2).await;
+        // Nothing listens on a port that was just released
+        let closed = std::net::UdpSocket::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let addrs = vec![devices[0].addr(), closed, devices[1].addr()];
+
+        let results = query_devices(&addrs, DEFAULT_QUERY_CONCURRENCY).await;
+
+        assert_eq!(results.iter().map(|(addr, _)| *addr).collect::<Vec<_>>(), addrs);
+        assert_eq!(results[0].1.as_ref().unwrap().hwid, Some(hwid(0)));
+        assert!(results[1].1.is_err());
+        assert_eq!(results[2].1.as_ref().unwrap().hwid, Some(hwid(1)));
+    }
+
+    #[tokio::test]
+    async fn test_slow_device_times_out_alone() {
+        let devices = devices_with_hwids(3).await;
+        devices[0].set_response_delay(Duration::from_secs(10));
+        let addrs: Vec<SocketAddr> = devices.iter().map(MockSmpDevice::addr).collect();
+
+        let started = Instant::now();
+        let results = query_devices_parallel(&addrs, 2, Duration::from_millis(300)).await;
+
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert!(matches!(results[0].1, Err(QueryError::TimedOut(_, port)) if port == devices[0].port()));
+        assert_eq!(results[1].1.as_ref().unwrap().hwid, Some(hwid(1)));
+        assert_eq!(results[2].1.as_ref().unwrap().hwid, Some(hwid(2)));
+    }
+}
@@ -1,12 +1,116 @@
-//! Async UDP transport wrapper for mcumgr-client
+//! Async SMP transports: UDP and serial
+//!
+//! `UdpTransportAsync` talks to devices on the network, over IPv4 or IPv6. `SerialTransportAsync`
+//! talks to boards on a serial port (USB CDC-ACM, usually), framing packets
+//! as the MCUmgr serial protocol does: the packet's length, the packet and
+//! its CRC16 are base64 encoded and split into lines of at most 127 bytes,
+//! the first starting with 0x06 0x09 and the rest with 0x04 0x14. Lines
+//! without either marker (console output sharing the port) are skipped.
+//! [`SerialConsole`] reads that console output instead, leaving out the SMP
+//! lines.
+//!
+//! [`Transport`] says which one to use for a device.
 
 use anyhow::Result;
-use std::net::SocketAddr;
+use std::collections::VecDeque;
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use tokio::net::UdpSocket;
-use tokio::time::timeout;
+use thiserror::Error;
+use tokio::time::error::Elapsed;
+use tokio::time::{timeout_at, Instant};
 use tracing::{debug, trace};
 
+use crate::observer::{observer_for, Direction, SmpFrame, TransportObserver};
+use crate::query::QueryOptions;
+
+/// Encode SMP v2 header
+fn encode_header(op: u8, group: u16, id: u8, body_len: u16, seq: u8) -> [u8; 8] {
+    let mut header = [0u8; 8];
+    // Byte 0: Res(3) | Ver(2) | OP(3) - Version 1 (SMP v2)
+    header[0] = (1 << 3) | (op & 0x07);
+    // Byte 1: Flags
+    header[1] = 0;
+    // Bytes 2-3: Length (big-endian)
+    header[2] = (body_len >> 8) as u8;
+    header[3] = body_len as u8;
+    // Bytes 4-5: Group ID (big-endian)
+    header[4] = (group >> 8) as u8;
+    header[5] = group as u8;
+    // Byte 6: Sequence
+    header[6] = seq;
+    // Byte 7: Command ID
+    header[7] = id;
+    header
+}
+
+/// Decode SMP v2 header
+fn decode_header(data: &[u8]) -> Option<(u8, u16, u8, u16, u8)> {
+    if data.len() < 8 {
+        return None;
+    }
+    let op = data[0] & 0x07;
+    let body_len = ((data[2] as u16) << 8) | (data[3] as u16);
+    let group = ((data[4] as u16) << 8) | (data[5] as u16);
+    let seq = data[6];
+    let id = data[7];
+    Some((op, group, id, body_len, seq))
+}
+
+/// Request packet: header followed by the body
+fn encode_request(op: u8, group: u16, id: u8, body: &[u8], seq: u8) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(8 + body.len());
+    packet.extend_from_slice(&encode_header(op, group, id, body.len() as u16, seq));
+    packet.extend_from_slice(body);
+    packet
+}
+
+/// Body of a response packet, checked against the request's sequence number
+pub(crate) fn response_body(packet: &[u8], seq: u8) -> Result<Vec<u8>> {
+    if packet.len() < 8 {
+        anyhow::bail!("Response too short: {} bytes", packet.len());
+    }
+
+    // Parse response header
+    let (resp_op, resp_group, resp_id, resp_len, resp_seq) =
+        decode_header(packet).ok_or_else(|| anyhow::anyhow!("Invalid response header"))?;
+
+    debug!(
+        op = resp_op,
+        group = resp_group,
+        id = resp_id,
+        seq = resp_seq,
+        body_len = resp_len,
+        "Received MCUmgr response"
+    );
+
+    // Validate response matches request
+    if resp_seq != seq {
+        anyhow::bail!(
+            "Sequence mismatch: expected {}, got {}",
+            seq,
+            resp_seq
+        );
+    }
+
+    // Extract body
+    let body_start = 8;
+    let body_end = body_start + resp_len as usize;
+    if body_end > packet.len() {
+        anyhow::bail!(
+            "Response body truncated: expected {} bytes, got {}",
+            resp_len,
+            packet.len() - body_start
+        );
+    }
+
+    Ok(packet[body_start..body_end].to_vec())
+}
+
 /// Async UDP transport for MCUmgr protocol
 pub struct UdpTransportAsync {
     socket: UdpSocket,
@@ -14,13 +118,28 @@ pub struct UdpTransportAsync {
     timeout_ms: u64,
     mtu: usize,
     seq: u8,
+    /// Receives every frame sent and received, if tracing is enabled
+    observer: Option<Arc<dyn TransportObserver>>,
 }
 
 impl UdpTransportAsync {
-    /// Create a new async UDP transport
-    pub async fn new(host: &str, port: u16, timeout_ms: u64) -> Result<Self> {
-        let socket = UdpSocket::bind("0.0.0.0:0").await?;
-        let target: SocketAddr = format!("{}:{}", host, port).parse()?;
+    /// Create a new async UDP transport to an IPv4 or IPv6 device, sending
+    /// from an ephemeral port
+    ///
+    /// Picks up the observer registered for the target IP, if any.
+    pub async fn new(target: SocketAddr, timeout_ms: u64) -> Result<Self> {
+        Self::with_source_port(target, 0, timeout_ms).await
+    }
+
+    /// Like [`new`](Self::new), but sending from `source_port` so a firewall
+    /// can allow the traffic; 0 picks an ephemeral port
+    pub async fn with_source_port(target: SocketAddr, source_port: u16, timeout_ms: u64) -> Result<Self> {
+        let local = match target {
+            SocketAddr::V4(_) => SocketAddr::from((Ipv4Addr::UNSPECIFIED, source_port)),
+            SocketAddr::V6(_) => SocketAddr::from((Ipv6Addr::UNSPECIFIED, source_port)),
+        };
+        let socket = UdpSocket::bind(local).await?;
+        let observer = observer_for(target.ip());
 
         Ok(Self {
             socket,
@@ -28,9 +147,34 @@ impl UdpTransportAsync {
             timeout_ms,
             mtu: 1024,
             seq: 0,
+            observer,
         })
     }
 
+    /// Local address the socket is bound to
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.socket.local_addr()?)
+    }
+
+    /// Replace the frame observer for this transport
+    pub fn set_observer(&mut self, observer: Option<Arc<dyn TransportObserver>>) {
+        self.observer = observer;
+    }
+
+    /// Pass a raw frame to the observer
+    fn observe(&self, direction: Direction, packet: &[u8]) {
+        let Some(observer) = &self.observer else {
+            return;
+        };
+        let local = self
+            .socket
+            .local_addr()
+            .unwrap_or_else(|_| SocketAddr::from(([0, 0, 0, 0], 0)));
+        if let Some(frame) = SmpFrame::parse(direction, local, self.target, packet) {
+            observer.on_frame(&frame);
+        }
+    }
+
     /// Get next sequence number
     fn next_seq(&mut self) -> u8 {
         let seq = self.seq;
@@ -38,39 +182,6 @@ impl UdpTransportAsync {
         seq
     }
 
-    /// Encode SMP v2 header
-    fn encode_header(&self, op: u8, group: u16, id: u8, body_len: u16, seq: u8) -> [u8; 8] {
-        let mut header = [0u8; 8];
-        // Byte 0: Res(3) | Ver(2) | OP(3) - Version 1 (SMP v2)
-        header[0] = (1 << 3) | (op & 0x07);
-        // Byte 1: Flags
-        header[1] = 0;
-        // Bytes 2-3: Length (big-endian)
-        header[2] = (body_len >> 8) as u8;
-        header[3] = body_len as u8;
-        // Bytes 4-5: Group ID (big-endian)
-        header[4] = (group >> 8) as u8;
-        header[5] = group as u8;
-        // Byte 6: Sequence
-        header[6] = seq;
-        // Byte 7: Command ID
-        header[7] = id;
-        header
-    }
-
-    /// Decode SMP v2 header
-    fn decode_header(&self, data: &[u8]) -> Option<(u8, u16, u8, u16, u8)> {
-        if data.len() < 8 {
-            return None;
-        }
-        let op = data[0] & 0x07;
-        let body_len = ((data[2] as u16) << 8) | (data[3] as u16);
-        let group = ((data[4] as u16) << 8) | (data[5] as u16);
-        let seq = data[6];
-        let id = data[7];
-        Some((op, group, id, body_len, seq))
-    }
-
     /// Send request and receive response
     pub async fn transceive(
         &mut self,
@@ -80,12 +191,7 @@ impl UdpTransportAsync {
         body: &[u8],
     ) -> Result<Vec<u8>> {
         let seq = self.next_seq();
-        let header = self.encode_header(op, group, id, body.len() as u16, seq);
-
-        // Build packet
-        let mut packet = Vec::with_capacity(8 + body.len());
-        packet.extend_from_slice(&header);
-        packet.extend_from_slice(body);
+        let packet = encode_request(op, group, id, body, seq);
 
         trace!(
             target = %self.target,
@@ -98,57 +204,390 @@ impl UdpTransportAsync {
         );
 
         // Send packet
+        self.observe(Direction::Outbound, &packet);
         self.socket.send_to(&packet, self.target).await?;
 
-        // Receive response with timeout
+        // Receive response with timeout; late answers to an earlier request are skipped
         let mut buf = vec![0u8; self.mtu];
-        let duration = Duration::from_millis(self.timeout_ms);
+        let deadline = Instant::now() + Duration::from_millis(self.timeout_ms);
+        loop {
+            let (len, _addr) = timeout_at(deadline, self.socket.recv_from(&mut buf)).await??;
+
+            self.observe(Direction::Inbound, &buf[..len]);
+            match decode_header(&buf[..len]) {
+                Some((_, _, _, _, resp_seq)) if resp_seq != seq => {
+                    debug!(target = %self.target, expected = seq, got = resp_seq, "Skipping stale MCUmgr response");
+                }
+                _ => return response_body(&buf[..len], seq),
+            }
+        }
+    }
+
+    /// Check if device is reachable (echo test)
+    pub async fn ping(&mut self) -> Result<bool> {
+        // Echo request: group=0 (Default), id=0 (Echo), op=2 (Write)
+        let body = serde_cbor::to_vec(&EchoReq { d: "ping" })?;
+
+        match self.transceive(2, 0, 0, &body).await {
+            Ok(resp_body) => {
+                let resp: EchoRsp = serde_cbor::from_slice(&resp_body)?;
+                Ok(resp.r == "ping")
+            }
+            Err(_) => Ok(false),
+        }
+    }
+}
+
+/// Baud rate for serial ports unless configured otherwise
+pub const DEFAULT_BAUD_RATE: u32 = 115_200;
+
+/// Marker starting the first line of a serial packet
+const FRAME_START: [u8; 2] = [0x06, 0x09];
+/// Marker starting each further line of a serial packet
+const FRAME_CONTINUATION: [u8; 2] = [0x04, 0x14];
+/// Base64 characters per line: 127 bytes less the marker and newline, in whole groups of 4
+const FRAME_BASE64_LEN: usize = 124;
+/// A line this long without a newline is noise; it is dropped to resynchronize
+const MAX_LINE_LEN: usize = 4096;
+
+/// Frame an SMP packet as serial lines
+fn encode_serial_frames(packet: &[u8]) -> Vec<u8> {
+    let mut raw = Vec::with_capacity(packet.len() + 4);
+    raw.extend_from_slice(&((packet.len() + 2) as u16).to_be_bytes());
+    raw.extend_from_slice(packet);
+    raw.extend_from_slice(&crc16(packet).to_be_bytes());
+
+    let encoded = base64_encode(&raw);
+    let mut frames = Vec::with_capacity(encoded.len() + encoded.len() / FRAME_BASE64_LEN * 3 + 3);
+    for (i, chunk) in encoded.chunks(FRAME_BASE64_LEN).enumerate() {
+        frames.extend_from_slice(if i == 0 { &FRAME_START } else { &FRAME_CONTINUATION });
+        frames.extend_from_slice(chunk);
+        frames.push(b'\n');
+    }
+    frames
+}
+
+/// Reassembles SMP packets from serial input, however it is split across reads
+#[derive(Debug, Default)]
+struct SerialDecoder {
+    /// Bytes of the current line so far
+    line: Vec<u8>,
+    /// Base64 of the packet being received, None between packets
+    packet: Option<Vec<u8>>,
+    /// Console output not taken yet, None unless it is kept
+    console: Option<Vec<u8>>,
+    /// Bytes of the current line already counted as console output
+    console_len: usize,
+    /// Whether the current line carries an SMP marker
+    in_frame: bool,
+}
+
+impl SerialDecoder {
+    /// A decoder keeping the console output for [`SerialDecoder::take_console`]
+    fn with_console() -> Self {
+        Self { console: Some(Vec::new()), ..Default::default() }
+    }
+
+    /// Feed bytes read from the port, returning the packets they complete
+    fn push(&mut self, bytes: &[u8]) -> Vec<Vec<u8>> {
+        let mut packets = Vec::new();
+        for &byte in bytes {
+            if byte != b'\n' {
+                self.line.push(byte);
+                self.pass_console();
+                if self.line.len() > MAX_LINE_LEN {
+                    self.line.clear();
+                    self.packet = None;
+                    self.console_len = 0;
+                }
+                continue;
+            }
+            if !self.in_frame {
+                self.line.push(byte);
+                self.pass_console();
+                self.line.pop();
+            }
+            self.console_len = 0;
+            self.in_frame = false;
+            let line = std::mem::take(&mut self.line);
+            if let Some(packet) = self.end_line(&line) {
+                packets.push(packet);
+            }
+        }
+        packets
+    }
+
+    /// Move the current line's new bytes to the console output, unless they
+    /// start an SMP marker
+    ///
+    /// Console text is passed on before its newline, so prompts show up. A
+    /// byte that could be the first of a marker waits for the next one.
+    fn pass_console(&mut self) {
+        let Some(console) = self.console.as_mut() else {
+            return;
+        };
+        if self.in_frame {
+            return;
+        }
+        let len = self.line.len();
+        let tail = &self.line[len.saturating_sub(2)..];
+        if tail == FRAME_START || tail == FRAME_CONTINUATION {
+            // The marker's first byte was held back; the rest of the line is SMP
+            self.in_frame = true;
+            self.console_len = len;
+        } else if tail.last().is_some_and(|&b| b == FRAME_START[0] || b == FRAME_CONTINUATION[0]) {
+            console.extend_from_slice(&self.line[self.console_len..len - 1]);
+            self.console_len = len - 1;
+        } else {
+            console.extend_from_slice(&self.line[self.console_len..]);
+            self.console_len = len;
+        }
+    }
+
+    /// Console output received since the last call
+    fn take_console(&mut self) -> Vec<u8> {
+        self.console.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    fn end_line(&mut self, line: &[u8]) -> Option<Vec<u8>> {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        // Markers can follow console output that had no newline of its own
+        let after = |marker: &[u8; 2]| line.windows(2).position(|w| w == marker).map(|at| &line[at + 2..]);
+        if let Some(data) = after(&FRAME_START) {
+            self.packet = Some(data.to_vec());
+        } else if let (Some(data), Some(packet)) = (after(&FRAME_CONTINUATION), self.packet.as_mut()) {
+            packet.extend_from_slice(data);
+        } else {
+            if !line.is_empty() {
+                trace!(len = line.len(), "Skipping non-SMP serial line");
+            }
+            return None;
+        }
+
+        let encoded = self.packet.as_deref()?;
+        let Some(raw) = base64_decode(encoded) else {
+            debug!("Dropping serial packet with invalid base64");
+            self.packet = None;
+            return None;
+        };
+        if raw.len() < 2 {
+            return None;
+        }
+        let len = u16::from_be_bytes([raw[0], raw[1]]) as usize;
+        if raw.len() < 2 + len {
+            // More lines to come
+            return None;
+        }
+        self.packet = None;
+
+        let data = &raw[2..2 + len];
+        // The CRC of a packet followed by its own CRC is zero
+        if len < 2 || crc16(data) != 0 {
+            debug!(len, "Dropping serial packet with a bad CRC");
+            return None;
+        }
+        Some(data[..len - 2].to_vec())
+    }
+}
+
+/// CRC16 of the serial protocol (CRC-16/XMODEM: polynomial 0x1021, initial value 0)
+fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0u16, |crc, &byte| {
+        (0..8).fold(crc ^ ((byte as u16) << 8), |crc, _| {
+            if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
 
-        let (len, _addr) = timeout(duration, self.socket.recv_from(&mut buf)).await??;
+/// Standard base64 with padding
+fn base64_encode(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | ((b as u32) << (16 - 8 * i)));
+        for i in 0..4 {
+            out.push(if i <= chunk.len() { BASE64_ALPHABET[((n >> (18 - 6 * i)) & 0x3f) as usize] } else { b'=' });
+        }
+    }
+    out
+}
 
-        if len < 8 {
-            anyhow::bail!("Response too short: {} bytes", len);
+/// Decode standard base64 with padding, None if it isn't valid
+fn base64_decode(data: &[u8]) -> Option<Vec<u8>> {
+    if data.len() % 4 != 0 {
+        return None;
+    }
+    let mut out = Vec::with_capacity(data.len() / 4 * 3);
+    for (index, group) in data.chunks(4).enumerate() {
+        let last = index == data.len() / 4 - 1;
+        let padding = group.iter().rev().take_while(|&&c| c == b'=').count();
+        if padding > 2 || (padding > 0 && !last) {
+            return None;
         }
+        let mut n = 0u32;
+        for (i, &c) in group[..4 - padding].iter().enumerate() {
+            let value = BASE64_ALPHABET.iter().position(|&a| a == c)? as u32;
+            n |= value << (18 - 6 * i);
+        }
+        out.extend_from_slice(&n.to_be_bytes()[1..4 - padding]);
+    }
+    Some(out)
+}
+
+/// Async serial transport for MCUmgr protocol
+///
+/// Works over any byte stream; [`SerialTransportAsync::new`] opens a serial
+/// port. Responses whose sequence number doesn't match the request (late
+/// answers to a request that timed out) are skipped.
+pub struct SerialTransportAsync<S = tokio::fs::File> {
+    stream: S,
+    /// Port name, for logs
+    name: String,
+    timeout_ms: u64,
+    seq: u8,
+    decoder: SerialDecoder,
+    /// Packets decoded but not yet returned
+    received: VecDeque<Vec<u8>>,
+    /// Receives every frame sent and received, if tracing is enabled
+    observer: Option<Arc<dyn TransportObserver>>,
+}
 
-        // Parse response header
-        let (resp_op, resp_group, resp_id, resp_len, resp_seq) =
-            self.decode_header(&buf).ok_or_else(|| anyhow::anyhow!("Invalid response header"))?;
+impl SerialTransportAsync {
+    /// Open a serial port at `baud_rate`
+    ///
+    /// The port is put in raw mode with `stty`, with reads returning after
+    /// 100 ms without data so a timed out request can't leave one blocked.
+    pub async fn new(path: impl Into<PathBuf>, baud_rate: u32, timeout_ms: u64) -> Result<Self> {
+        let path = path.into();
+        configure_port(&path, baud_rate).await?;
+        let file = tokio::fs::OpenOptions::new().read(true).write(true).open(&path).await?;
+        Ok(Self::from_stream(file, path.display().to_string(), timeout_ms))
+    }
+}
 
-        debug!(
-            op = resp_op,
-            group = resp_group,
-            id = resp_id,
-            seq = resp_seq,
-            body_len = resp_len,
-            "Received MCUmgr response"
+#[cfg(unix)]
+async fn configure_port(path: &std::path::Path, baud_rate: u32) -> Result<()> {
+    let device_flag = if cfg!(target_os = "macos") { "-f" } else { "-F" };
+    let output = tokio::process::Command::new("stty")
+        .arg(device_flag)
+        .arg(path)
+        .args([&baud_rate.to_string(), "raw", "-echo", "min", "0", "time", "1"])
+        .output()
+        .await?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "Failed to configure {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
         );
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+async fn configure_port(path: &std::path::Path, _baud_rate: u32) -> Result<()> {
+    anyhow::bail!("Serial ports are not supported on this platform ({})", path.display())
+}
 
-        // Validate response matches request
-        if resp_seq != seq {
-            anyhow::bail!(
-                "Sequence mismatch: expected {}, got {}",
-                seq,
-                resp_seq
-            );
+impl<S: AsyncRead + AsyncWrite + Unpin> SerialTransportAsync<S> {
+    /// Use an already open stream, `name` identifying it in logs
+    pub fn from_stream(stream: S, name: impl Into<String>, timeout_ms: u64) -> Self {
+        Self {
+            stream,
+            name: name.into(),
+            timeout_ms,
+            seq: 0,
+            decoder: SerialDecoder::default(),
+            received: VecDeque::new(),
+            observer: None,
         }
+    }
+
+    /// Replace the frame observer for this transport
+    pub fn set_observer(&mut self, observer: Option<Arc<dyn TransportObserver>>) {
+        self.observer = observer;
+    }
 
-        // Extract body
-        let body_start = 8;
-        let body_end = body_start + resp_len as usize;
-        if body_end > len {
-            anyhow::bail!(
-                "Response body truncated: expected {} bytes, got {}",
-                resp_len,
-                len - body_start
-            );
+    /// Pass a raw frame to the observer; a serial port has no addresses, so both are unspecified
+    fn observe(&self, direction: Direction, packet: &[u8]) {
+        let Some(observer) = &self.observer else {
+            return;
+        };
+        let unspecified = SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0));
+        if let Some(frame) = SmpFrame::parse(direction, unspecified, unspecified, packet) {
+            observer.on_frame(&frame);
         }
+    }
 
-        Ok(buf[body_start..body_end].to_vec())
+    /// Get next sequence number
+    fn next_seq(&mut self) -> u8 {
+        let seq = self.seq;
+        self.seq = self.seq.wrapping_add(1);
+        seq
+    }
+
+    /// Send request and receive response
+    pub async fn transceive(
+        &mut self,
+        op: u8,
+        group: u16,
+        id: u8,
+        body: &[u8],
+    ) -> Result<Vec<u8>> {
+        let seq = self.next_seq();
+        let packet = encode_request(op, group, id, body, seq);
+
+        trace!(
+            port = %self.name,
+            op = op,
+            group = group,
+            id = id,
+            seq = seq,
+            body_len = body.len(),
+            "Sending MCUmgr request"
+        );
+
+        self.observe(Direction::Outbound, &packet);
+        self.stream.write_all(&encode_serial_frames(&packet)).await?;
+        self.stream.flush().await?;
+
+        let deadline = Instant::now() + Duration::from_millis(self.timeout_ms);
+        loop {
+            let response = timeout_at(deadline, self.read_packet()).await??;
+            self.observe(Direction::Inbound, &response);
+            match decode_header(&response) {
+                Some((_, _, _, _, resp_seq)) if resp_seq != seq => {
+                    debug!(port = %self.name, expected = seq, got = resp_seq, "Skipping stale MCUmgr response");
+                }
+                _ => return response_body(&response, seq),
+            }
+        }
+    }
+
+    /// Next complete packet from the port
+    async fn read_packet(&mut self) -> Result<Vec<u8>> {
+        let mut buf = [0u8; 256];
+        loop {
+            if let Some(packet) = self.received.pop_front() {
+                return Ok(packet);
+            }
+            let len = self.stream.read(&mut buf).await?;
+            if len == 0 {
+                // Nothing within the port's read timeout
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                continue;
+            }
+            self.received.extend(self.decoder.push(&buf[..len]));
+        }
     }
 
     /// Check if device is reachable (echo test)
     pub async fn ping(&mut self) -> Result<bool> {
-        // Echo request: group=0 (Default), id=0 (Echo), op=2 (Write)
         let body = serde_cbor::to_vec(&EchoReq { d: "ping" })?;
 
         match self.transceive(2, 0, 0, &body).await {
@@ -161,6 +600,192 @@ impl UdpTransportAsync {
     }
 }
 
+/// Console of a board on a serial port: everything on it but SMP packets
+///
+/// The Zephyr shell and logs share the port with SMP. [`SerialConsole::read`]
+/// returns what a terminal would show, without the SMP lines, and input is
+/// written to the port as it is.
+///
+/// Reads and writes go through separate handles, so a read waiting on the
+/// port never holds up input.
+pub struct SerialConsole<R = tokio::fs::File, W = tokio::fs::File> {
+    reader: R,
+    writer: W,
+    decoder: SerialDecoder,
+}
+
+impl SerialConsole {
+    /// Open a serial port, configured as [`SerialTransportAsync::new`] does
+    pub async fn open(config: &SerialPortConfig) -> Result<Self> {
+        configure_port(&config.path, config.baud_rate).await?;
+        let reader = tokio::fs::OpenOptions::new().read(true).open(&config.path).await?;
+        let writer = tokio::fs::OpenOptions::new().write(true).open(&config.path).await?;
+        Ok(Self::new(reader, writer))
+    }
+}
+
+impl<R: AsyncRead + Unpin, W: AsyncWrite + Unpin> SerialConsole<R, W> {
+    /// Read from `reader` and write to `writer`, e.g. the halves of [`tokio::io::split`]
+    pub fn new(reader: R, writer: W) -> Self {
+        Self { reader, writer, decoder: SerialDecoder::with_console() }
+    }
+
+    /// Console output from the next read, empty when nothing but SMP
+    /// arrived or the port's read timeout passed without data
+    ///
+    /// Cancel safe: a read cut short leaves its bytes to the next call.
+    pub async fn read(&mut self) -> Result<Vec<u8>> {
+        let mut buf = [0u8; 256];
+        let len = self.reader.read(&mut buf).await?;
+        if len == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            return Ok(Vec::new());
+        }
+        let packets = self.decoder.push(&buf[..len]);
+        if !packets.is_empty() {
+            trace!(packets = packets.len(), "Skipping SMP packets on the console");
+        }
+        Ok(self.decoder.take_console())
+    }
+
+    /// Write console input to the port
+    pub async fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        self.writer.write_all(bytes).await?;
+        self.writer.flush().await?;
+        Ok(())
+    }
+}
+
+/// Serial port settings for [`Transport::Serial`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SerialPortConfig {
+    /// Device path, e.g. `/dev/ttyACM0`
+    pub path: PathBuf,
+    pub baud_rate: u32,
+}
+
+impl SerialPortConfig {
+    /// A port at [`DEFAULT_BAUD_RATE`]
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), baud_rate: DEFAULT_BAUD_RATE }
+    }
+
+    pub fn with_baud_rate(mut self, baud_rate: u32) -> Self {
+        self.baud_rate = baud_rate;
+        self
+    }
+}
+
+/// How to reach a device's SMP server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transport {
+    /// SMP over UDP at this address
+    Udp(SocketAddr),
+    /// SMP over a serial port
+    Serial(SerialPortConfig),
+}
+
+impl From<SocketAddr> for Transport {
+    fn from(addr: SocketAddr) -> Self {
+        Transport::Udp(addr)
+    }
+}
+
+impl From<SerialPortConfig> for Transport {
+    fn from(config: SerialPortConfig) -> Self {
+        Transport::Serial(config)
+    }
+}
+
+impl fmt::Display for Transport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Transport::Udp(addr) => write!(f, "{}", addr),
+            Transport::Serial(config) => write!(f, "{} at {} baud", config.path.display(), config.baud_rate),
+        }
+    }
+}
+
+impl Transport {
+    /// Open the transport, waiting `options.timeout` for each response
+    pub(crate) async fn connect(&self, options: &QueryOptions) -> Result<Connection> {
+        let timeout_ms = u64::try_from(options.timeout.as_millis()).unwrap_or(u64::MAX);
+        let link = match self {
+            Transport::Udp(addr) => Link::Udp(UdpTransportAsync::new(*addr, timeout_ms).await?),
+            Transport::Serial(config) => {
+                Link::Serial(SerialTransportAsync::new(&config.path, config.baud_rate, timeout_ms).await?)
+            }
+        };
+        Ok(Connection { link, retries: options.retries, retry_backoff: options.retry_backoff })
+    }
+}
+
+/// Every attempt at a request timed out
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("No response after {attempts} attempts")]
+pub(crate) struct Unanswered {
+    pub attempts: u32,
+}
+
+enum Link {
+    Udp(UdpTransportAsync),
+    Serial(SerialTransportAsync),
+}
+
+/// An open transport of either kind, sending requests again while they go unanswered
+pub(crate) struct Connection {
+    link: Link,
+    retries: u8,
+    retry_backoff: Duration,
+}
+
+impl Connection {
+    /// Send request and receive response
+    ///
+    /// A request that times out is sent again (with a new sequence number)
+    /// up to `retries` times, the wait before each retry doubling. When none
+    /// is answered the error is an [`Unanswered`]; other errors aren't retried.
+    pub(crate) async fn transceive(&mut self, op: u8, group: u16, id: u8, body: &[u8]) -> Result<Vec<u8>> {
+        let mut backoff = self.retry_backoff;
+        let mut attempts = 1u32;
+        loop {
+            let result = match &mut self.link {
+                Link::Udp(transport) => transport.transceive(op, group, id, body).await,
+                Link::Serial(transport) => transport.transceive(op, group, id, body).await,
+            };
+            match result {
+                Err(e) if e.is::<Elapsed>() => {
+                    if attempts > u32::from(self.retries) {
+                        return Err(Unanswered { attempts }.into());
+                    }
+                    debug!(group, id, attempts, "No MCUmgr response, retrying");
+                    tokio::time::sleep(backoff).await;
+                    backoff = backoff.saturating_mul(2);
+                    attempts += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Check if device is reachable (echo test)
+    ///
+    /// Fails with [`Unanswered`] if no attempt got a response; any other
+    /// failure is `Ok(false)`.
+    pub(crate) async fn ping(&mut self) -> Result<bool> {
+        let body = serde_cbor::to_vec(&EchoReq { d: "ping" })?;
+
+        match self.transceive(2, 0, 0, &body).await {
+            Ok(resp_body) => {
+                let resp: EchoRsp = serde_cbor::from_slice(&resp_body)?;
+                Ok(resp.r == "ping")
+            }
+            Err(e) if e.is::<Unanswered>() => Err(e),
+            Err(_) => Ok(false),
+        }
+    }
+}
+
 #[derive(serde::Serialize)]
 struct EchoReq<'a> {
     d: &'a str,
@@ -174,18 +799,13 @@ struct EchoRsp {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use dendrite_test_support::{MockHcdfInfo, MockSmpDevice};
+    use std::collections::BTreeMap;
+    use tokio::io::DuplexStream;
 
     #[test]
     fn test_header_encoding() {
-        let transport = UdpTransportAsync {
-            socket: unsafe { std::mem::zeroed() }, // Just for testing header encoding
-            target: "127.0.0.1:1337".parse().unwrap(),
-            timeout_ms: 5000,
-            mtu: 1024,
-            seq: 0,
-        };
-
-        let header = transport.encode_header(2, 0, 0, 10, 5);
+        let header = encode_header(2, 0, 0, 10, 5);
         // op=2, version=1: (1 << 3) | 2 = 10
         assert_eq!(header[0], 10);
         // flags=0
@@ -201,4 +821,266 @@ mod tests {
         // id=0
         assert_eq!(header[7], 0);
     }
+
+    #[derive(Default)]
+    struct RecordingObserver {
+        frames: std::sync::Mutex<Vec<(Direction, u8, u16, u8)>>,
+    }
+
+    impl TransportObserver for RecordingObserver {
+        fn on_frame(&self, frame: &SmpFrame<'_>) {
+            self.frames.lock().unwrap().push((frame.direction, frame.op, frame.group, frame.seq));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_observer_sees_both_directions() {
+        let device = MockSmpDevice::spawn(MockHcdfInfo::default()).await.unwrap();
+        let observer = Arc::new(RecordingObserver::default());
+
+        let mut transport = UdpTransportAsync::new(device.addr(), 1000).await.unwrap();
+        transport.set_observer(Some(observer.clone()));
+        assert!(transport.ping().await.unwrap());
+
+        let frames = observer.frames.lock().unwrap().clone();
+        assert_eq!(
+            frames,
+            vec![(Direction::Outbound, 2, 0, 0), (Direction::Inbound, 3, 0, 0)]
+        );
+    }
+
+    /// SMP echo server on `addr`, answering `{"d": text}` with `{"r": text}`;
+    /// returns its address and the source address of each request
+    async fn echo_server(addr: &str) -> (SocketAddr, Arc<std::sync::Mutex<Vec<SocketAddr>>>) {
+        let socket = UdpSocket::bind(addr).await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        let peers = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = peers.clone();
+        tokio::spawn(async move {
+            let mut buf = vec![0u8; 2048];
+            while let Ok((len, peer)) = socket.recv_from(&mut buf).await {
+                seen.lock().unwrap().push(peer);
+                let Some((op, group, id, _, seq)) = decode_header(&buf[..len]) else { continue };
+                let request: BTreeMap<String, String> = serde_cbor::from_slice(&buf[8..len]).unwrap();
+                let body = serde_cbor::to_vec(&BTreeMap::from([("r", &request["d"])])).unwrap();
+                let mut response = encode_header(op + 1, group, id, body.len() as u16, seq).to_vec();
+                response.extend_from_slice(&body);
+                socket.send_to(&response, peer).await.unwrap();
+            }
+        });
+        (addr, peers)
+    }
+
+    #[tokio::test]
+    async fn test_udp_over_ipv6_loopback() {
+        let (addr, peers) = echo_server("[::1]:0").await;
+        let mut transport = UdpTransportAsync::new(addr, 1000).await.unwrap();
+        assert!(transport.ping().await.unwrap());
+
+        // A response close to the MTU comes through whole, as over IPv4
+        let text = "x".repeat(1000);
+        let body = serde_cbor::to_vec(&BTreeMap::from([("d", &text)])).unwrap();
+        let response = transport.transceive(2, 0, 0, &body).await.unwrap();
+        let echoed: BTreeMap<String, String> = serde_cbor::from_slice(&response).unwrap();
+        assert_eq!(echoed["r"], text);
+        assert!(peers.lock().unwrap().iter().all(SocketAddr::is_ipv6));
+    }
+
+    #[tokio::test]
+    async fn test_udp_source_port() {
+        let (addr, peers) = echo_server("127.0.0.1:0").await;
+        // A port that was free a moment ago
+        let source_port = std::net::UdpSocket::bind("0.0.0.0:0").unwrap().local_addr().unwrap().port();
+
+        let mut transport = UdpTransportAsync::with_source_port(addr, source_port, 1000).await.unwrap();
+        assert_eq!(transport.local_addr().unwrap().port(), source_port);
+        assert!(transport.ping().await.unwrap());
+        assert_eq!(peers.lock().unwrap()[0].port(), source_port);
+    }
+
+    #[test]
+    fn test_crc16_and_base64() {
+        // CRC-16/XMODEM check value
+        assert_eq!(crc16(b"123456789"), 0x31c3);
+        assert_eq!(base64_encode(b"hello"), b"aGVsbG8=");
+        assert_eq!(base64_encode(b"hell"), b"aGVsbA==");
+        for len in 0..8 {
+            let data: Vec<u8> = (0..len).map(|i| i * 37).collect();
+            assert_eq!(base64_decode(&base64_encode(&data)), Some(data));
+        }
+        assert_eq!(base64_decode(b"aGVsbG8"), None);
+        assert_eq!(base64_decode(b"aG=sbG8="), None);
+    }
+
+    #[test]
+    fn test_serial_frame_encoding() {
+        let packet = encode_request(0, 0, 0, &[], 1);
+        // Length 10 (packet and CRC), the packet, its CRC
+        let frames = encode_serial_frames(&packet);
+        assert_eq!(frames, b"\x06\x09AAoIAAAAAAABAC3r\n");
+
+        // A larger packet spans lines of at most 127 bytes
+        let packet = encode_request(0, 100, 0, &[0xa5; 300], 2);
+        let frames = encode_serial_frames(&packet);
+        let lines: Vec<&[u8]> = frames.split_inclusive(|&b| b == b'\n').collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with(&FRAME_START));
+        assert!(lines[1..].iter().all(|line| line.starts_with(&FRAME_CONTINUATION)));
+        assert!(lines.iter().all(|line| line.len() <= 127));
+        assert_eq!(SerialDecoder::default().push(&frames), vec![packet]);
+    }
+
+    #[test]
+    fn test_serial_decoder_resynchronizes() {
+        let first = encode_request(1, 0, 0, &[1; 200], 7);
+        let second = encode_request(1, 0, 0, &[2; 20], 8);
+        let mut corrupted = encode_serial_frames(&first);
+        corrupted[40] = if corrupted[40] == b'A' { b'B' } else { b'A' };
+
+        let mut input = b"\x00\xffuart:~$ boot noise\r\n".to_vec();
+        // A line that breaks off mid-packet is abandoned at the next start marker
+        input.extend_from_slice(&encode_serial_frames(&first)[..60]);
+        input.extend_from_slice(b"\r\n");
+        input.extend_from_slice(&corrupted);
+        input.extend_from_slice(b"log without newline ");
+        input.extend_from_slice(&encode_serial_frames(&first));
+        input.extend_from_slice(b"[00:00:01.000] <inf> main: ready\r\n");
+        input.extend_from_slice(&encode_serial_frames(&second));
+
+        // One byte at a time, as the slowest port would deliver it
+        let mut decoder = SerialDecoder::default();
+        let packets: Vec<Vec<u8>> = input.iter().flat_map(|b| decoder.push(std::slice::from_ref(b))).collect();
+        assert_eq!(packets, vec![first, second]);
+    }
+
+    /// Device end of a serial loopback: decodes each request and writes the
+    /// packets `respond` returns, after a console line, a few bytes at a time
+    async fn serial_device(mut stream: DuplexStream, respond: impl Fn(&[u8]) -> Vec<Vec<u8>>) {
+        let mut decoder = SerialDecoder::default();
+        let mut buf = [0u8; 64];
+        while let Ok(len) = stream.read(&mut buf).await {
+            if len == 0 {
+                break;
+            }
+            for request in decoder.push(&buf[..len]) {
+                let mut output = b"[00:00:01.000] <inf> smp: request\r\n".to_vec();
+                for packet in respond(&request) {
+                    output.extend_from_slice(&encode_serial_frames(&packet));
+                }
+                for chunk in output.chunks(7) {
+                    if stream.write_all(chunk).await.is_err() {
+                        return;
+                    }
+                    tokio::task::yield_now().await;
+                }
+            }
+        }
+    }
+
+    /// Response to `request` with sequence number `seq`
+    fn reply(request: &[u8], seq: u8, body: &[u8]) -> Vec<u8> {
+        let group = u16::from_be_bytes([request[4], request[5]]);
+        encode_request((request[0] & 0x07) + 1, group, request[7], body, seq)
+    }
+
+    fn large_body() -> Vec<u8> {
+        (0..600).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[tokio::test]
+    async fn test_serial_loopback_multi_frame_response() {
+        let (client, device) = tokio::io::duplex(64);
+        tokio::spawn(serial_device(device, |request| {
+            let body = match request[4..6] {
+                [0, 0] => serde_cbor::to_vec(&BTreeMap::from([("r", "ping")])).unwrap(),
+                _ => large_body(),
+            };
+            vec![reply(request, request[6], &body)]
+        }));
+        let observer = Arc::new(RecordingObserver::default());
+
+        let mut transport = SerialTransportAsync::from_stream(client, "loopback", 1000);
+        transport.set_observer(Some(observer.clone()));
+        assert!(transport.ping().await.unwrap());
+
+        // The response takes several lines on the wire
+        assert!(encode_serial_frames(&encode_request(1, 100, 0, &large_body(), 1)).len() > 127);
+        let body = transport.transceive(0, 100, 0, &[]).await.unwrap();
+        assert_eq!(body, large_body());
+
+        let frames = observer.frames.lock().unwrap().clone();
+        assert_eq!(frames.len(), 4);
+        assert_eq!(frames[3], (Direction::Inbound, 1, 100, 1));
+    }
+
+    #[tokio::test]
+    async fn test_serial_skips_stale_responses() {
+        let (client, device) = tokio::io::duplex(64);
+        // A late answer to an earlier request arrives first
+        tokio::spawn(serial_device(device, |request| {
+            let seq = request[6];
+            vec![reply(request, seq.wrapping_sub(1), b"stale"), reply(request, seq, b"fresh")]
+        }));
+
+        let mut transport = SerialTransportAsync::from_stream(client, "loopback", 1000);
+        assert_eq!(transport.transceive(0, 100, 0, &[]).await.unwrap(), b"fresh");
+        assert_eq!(transport.transceive(0, 100, 0, &[]).await.unwrap(), b"fresh");
+    }
+
+    #[test]
+    fn test_serial_console_leaves_out_smp_lines() {
+        let packet = encode_request(2, 0, 0, &large_body(), 3);
+        let mut input = b"uart:~$ ".to_vec();
+        input.extend_from_slice(&encode_serial_frames(&packet));
+        input.extend_from_slice(b"\x1b[1;32mok\x1b[0m\r\n\x06x\n");
+
+        let mut decoder = SerialDecoder::with_console();
+        let mut console = Vec::new();
+        let mut packets = Vec::new();
+        for chunk in input.chunks(5) {
+            packets.extend(decoder.push(chunk));
+            console.extend(decoder.take_console());
+        }
+        assert_eq!(packets, vec![packet]);
+        // A lone marker byte is console text after all
+        assert_eq!(console, b"uart:~$ \x1b[1;32mok\x1b[0m\r\n\x06x\n");
+
+        // The prompt shows before any newline
+        let mut decoder = SerialDecoder::with_console();
+        decoder.push(b"uart:~$ ");
+        assert_eq!(decoder.take_console(), b"uart:~$ ");
+        // Decoders for requests don't keep it
+        let mut decoder = SerialDecoder::default();
+        decoder.push(b"uart:~$ ");
+        assert!(decoder.take_console().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_serial_console_reads_and_writes() {
+        let (client, mut device) = tokio::io::duplex(256);
+        let (reader, writer) = tokio::io::split(client);
+        let mut console = SerialConsole::new(reader, writer);
+
+        console.write(b"kernel uptime\r").await.unwrap();
+        let mut buf = [0u8; 32];
+        let len = device.read(&mut buf).await.unwrap();
+        assert_eq!(&buf[..len], b"kernel uptime\r");
+
+        let mut output = encode_serial_frames(&encode_request(3, 0, 0, b"", 0));
+        output.extend_from_slice(b"Uptime: 42 ms\r\n");
+        device.write_all(&output).await.unwrap();
+        let mut text = Vec::new();
+        while text.len() < 15 {
+            text.extend(console.read().await.unwrap());
+        }
+        assert_eq!(text, b"Uptime: 42 ms\r\n");
+    }
+
+    #[tokio::test]
+    async fn test_serial_times_out_without_a_device() {
+        let (client, _device) = tokio::io::duplex(64);
+        let mut transport = SerialTransportAsync::from_stream(client, "loopback", 50);
+        assert!(transport.transceive(0, 0, 0, &[]).await.is_err());
+        assert!(!transport.ping().await.unwrap());
+    }
 }
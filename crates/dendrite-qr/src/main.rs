@@ -2,6 +2,8 @@
 //!
 //! This tool checks if a Dendrite daemon is running and displays a QR code
 //! that can be scanned to open the web UI with the correct daemon address.
+//! When the daemon has an `auth_token`, pass it with `--token` so the URL
+//! carries it as a `token` query parameter.
 
 use clap::Parser;
 use network_interface::{NetworkInterface, NetworkInterfaceConfig};
@@ -36,6 +38,10 @@ struct Args {
     /// Use direct daemon URL instead of remote frontend
     #[arg(long)]
     local: bool,
+
+    /// Daemon API token (`daemon.auth_token`) to embed in the URL
+    #[arg(long)]
+    token: Option<String>,
 }
 
 #[tokio::main]
@@ -74,7 +80,7 @@ async fn main() {
     // Check if daemon is running
     if !args.no_check {
         print!("Checking daemon at {}... ", daemon_url);
-        match check_daemon(&daemon_url).await {
+        match check_daemon(&daemon_url, args.token.as_deref()).await {
             Ok(true) => println!("OK"),
             Ok(false) => {
                 println!("NOT RESPONDING");
@@ -92,13 +98,17 @@ async fn main() {
     }
 
     // Build the connection URL
-    let connect_url = if args.local {
+    let mut connect_url = if args.local {
         // Direct connection to daemon
         daemon_url.clone()
     } else {
         // Remote frontend with daemon parameter
         format!("{}?daemon={}", args.frontend_url.trim_end_matches('/'), daemon_addr)
     };
+    if let Some(token) = args.token.as_deref().filter(|t| !t.is_empty()) {
+        let separator = if connect_url.contains('?') { '&' } else { '?' };
+        connect_url = format!("{}{}token={}", connect_url, separator, percent_encode(token));
+    }
 
     println!();
     println!("=== Dendrite Connection ===");
@@ -163,8 +173,19 @@ fn get_local_ips() -> Vec<Ipv4Addr> {
     ips
 }
 
+/// Percent-encode a query parameter value
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
 /// Check if daemon is responding
-async fn check_daemon(url: &str) -> Result<bool, String> {
+async fn check_daemon(url: &str, token: Option<&str>) -> Result<bool, String> {
     let client = reqwest::Client::builder()
         .timeout(std::time::Duration::from_secs(3))
         .danger_accept_invalid_certs(true) // Allow self-signed certs
@@ -173,7 +194,12 @@ async fn check_daemon(url: &str) -> Result<bool, String> {
 
     let check_url = format!("{}/api/devices", url);
 
-    match client.get(&check_url).send().await {
+    let mut request = client.get(&check_url);
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    }
+
+    match request.send().await {
         Ok(resp) => Ok(resp.status().is_success()),
         Err(e) if e.is_timeout() => Ok(false),
         Err(e) if e.is_connect() => Ok(false),
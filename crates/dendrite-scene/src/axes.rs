@@ -0,0 +1,41 @@
+//! Axis triad geometry
+//!
+//! Frame gizmos and the FLU/ENU overlay draw a coordinate triad as one
+//! Y-aligned cylinder per axis (the mesh default). These helpers place those
+//! cylinders so every triad in the scene is built the same way.
+
+use bevy::prelude::*;
+
+/// Triad axes in drawing order: X (red), Y (green), Z (blue)
+pub const TRIAD_AXES: [Vec3; 3] = [Vec3::X, Vec3::Y, Vec3::Z];
+
+/// Rotation that lays a Y-aligned cylinder along a unit `axis`
+pub fn cylinder_rotation(axis: Vec3) -> Quat {
+    Quat::from_rotation_arc(Vec3::Y, axis)
+}
+
+/// Transform of a `length` cylinder running from the origin of `frame` along
+/// its local `axis`, expressed in the frame's parent space
+pub fn axis_transform(frame: Transform, axis: Vec3, length: f32) -> Transform {
+    Transform::from_translation(frame.translation + frame.rotation * axis * (length / 2.0))
+        .with_rotation(frame.rotation * cylinder_rotation(axis))
+}
+
+/// Length of each dash when an axis of `length` is split into `dashes`
+/// dashes separated by equal gaps
+pub fn dash_length(length: f32, dashes: usize) -> f32 {
+    length / (2 * dashes.max(1) - 1) as f32
+}
+
+/// Transforms of the dashes of a dashed axis (see `axis_transform`); the
+/// first dash starts at the frame origin and the last ends at `length`
+pub fn dashed_axis_transforms(frame: Transform, axis: Vec3, length: f32, dashes: usize) -> Vec<Transform> {
+    let dash = dash_length(length, dashes);
+    (0..dashes.max(1))
+        .map(|i| {
+            let center = (2 * i) as f32 * dash + dash / 2.0;
+            Transform::from_translation(frame.translation + frame.rotation * axis * center)
+                .with_rotation(frame.rotation * cylinder_rotation(axis))
+        })
+        .collect()
+}
@@ -0,0 +1,292 @@
+//! Saved camera viewpoints
+//!
+//! The "Views" section of both viewers' left panel lists the built-in
+//! presets (Top, Front, Right, Isometric) followed by views the user saved
+//! under a name. Recalling a view returns it to the viewer, which eases its
+//! camera there: zoom and focus through `target_distance`/`target_focus`,
+//! the orbit angles through [`ease_orbit`]. Presets only set the direction,
+//! so they keep whatever the camera is centered on.
+//!
+//! Saved views persist in localStorage on wasm and in
+//! [`CAMERA_VIEWS_FILE`] in the working directory (next to
+//! `dendrite.toml`) on native.
+
+use bevy::prelude::*;
+use bevy_egui::egui;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI, TAU};
+
+/// File holding saved views on native
+pub const CAMERA_VIEWS_FILE: &str = "dendrite-views.json";
+
+/// localStorage key holding saved views on wasm
+#[cfg(target_arch = "wasm32")]
+const STORAGE_KEY: &str = "dendrite.camera_views";
+
+/// Highest elevation the camera orbits to; straight down has no up vector
+const MAX_ELEVATION: f32 = 1.5;
+
+/// A named camera viewpoint
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CameraView {
+    pub name: String,
+    /// Radians around Z from +X (ENU)
+    pub azimuth: f32,
+    /// Radians above the ground plane
+    pub elevation: f32,
+    /// Distance from the focus (None keeps the current zoom)
+    #[serde(default)]
+    pub distance: Option<f32>,
+    /// Point the camera looks at (None keeps the current focus)
+    #[serde(default)]
+    pub focus: Option<[f32; 3]>,
+}
+
+impl CameraView {
+    /// A view of the camera as it is now
+    pub fn current(name: &str, azimuth: f32, elevation: f32, distance: f32, focus: Vec3) -> Self {
+        Self {
+            name: name.to_string(),
+            azimuth,
+            elevation,
+            distance: Some(distance),
+            focus: Some(focus.to_array()),
+        }
+    }
+
+    fn preset(name: &str, azimuth: f32, elevation: f32) -> Self {
+        Self { name: name.to_string(), azimuth, elevation, distance: None, focus: None }
+    }
+}
+
+/// Built-in views, always listed first
+pub fn preset_views() -> Vec<CameraView> {
+    vec![
+        // From above, with +X (forward) pointing up the screen
+        CameraView::preset("Top", PI, MAX_ELEVATION),
+        CameraView::preset("Front", 0.0, 0.0),
+        CameraView::preset("Right", -FRAC_PI_2, 0.0),
+        // Equal angles to all three axes
+        CameraView::preset("Isometric", FRAC_PI_4, (1.0 / 2.0_f32.sqrt()).atan()),
+    ]
+}
+
+/// Views the user saved, and the state of the Views section
+#[derive(Debug, Default, Resource)]
+pub struct CameraViews {
+    saved: Vec<CameraView>,
+    /// Name typed for the next saved view
+    new_name: String,
+    /// Saved view being renamed, with the name typed so far
+    renaming: Option<(usize, String)>,
+}
+
+impl CameraViews {
+    /// Views saved in an earlier session
+    pub fn load() -> Self {
+        let saved = load_saved().and_then(|json| serde_json::from_str(&json).ok()).unwrap_or_default();
+        Self { saved, ..Default::default() }
+    }
+
+    pub fn saved(&self) -> &[CameraView] {
+        &self.saved
+    }
+
+    /// Save a view, replacing a saved view of the same name
+    pub fn save(&mut self, view: CameraView) {
+        match self.saved.iter_mut().find(|v| v.name == view.name) {
+            Some(existing) => *existing = view,
+            None => self.saved.push(view),
+        }
+        self.persist();
+    }
+
+    /// Rename a saved view; fails on an empty name or one already taken
+    pub fn rename(&mut self, index: usize, name: &str) -> bool {
+        let name = name.trim();
+        let taken = self.saved.iter().enumerate().any(|(i, v)| i != index && v.name == name);
+        let Some(view) = self.saved.get_mut(index).filter(|_| !name.is_empty() && !taken) else {
+            return false;
+        };
+        view.name = name.to_string();
+        self.persist();
+        true
+    }
+
+    /// Delete a saved view
+    pub fn remove(&mut self, index: usize) -> Option<CameraView> {
+        if index >= self.saved.len() {
+            return None;
+        }
+        let view = self.saved.remove(index);
+        self.persist();
+        Some(view)
+    }
+
+    fn persist(&self) {
+        match serde_json::to_string_pretty(&self.saved) {
+            Ok(json) => store_saved(&json),
+            Err(e) => tracing::warn!("Failed to serialize camera views: {}", e),
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn load_saved() -> Option<String> {
+    let storage = web_sys::window()?.local_storage().ok()??;
+    storage.get_item(STORAGE_KEY).ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+fn store_saved(json: &str) {
+    let storage = web_sys::window().and_then(|w| w.local_storage().ok().flatten());
+    if storage.is_none_or(|s| s.set_item(STORAGE_KEY, json).is_err()) {
+        tracing::warn!("Failed to save camera views to localStorage");
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn load_saved() -> Option<String> {
+    std::fs::read_to_string(CAMERA_VIEWS_FILE).ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn store_saved(json: &str) {
+    if let Err(e) = std::fs::write(CAMERA_VIEWS_FILE, json) {
+        tracing::warn!("Failed to write {}: {}", CAMERA_VIEWS_FILE, e);
+    }
+}
+
+/// Move the orbit angles a `factor` of the way to `target`, turning the
+/// short way around; true once they have arrived
+pub fn ease_orbit(azimuth: &mut f32, elevation: &mut f32, target: (f32, f32), factor: f32) -> bool {
+    let azimuth_delta = (target.0 - *azimuth + PI).rem_euclid(TAU) - PI;
+    let elevation_delta = target.1.clamp(-MAX_ELEVATION, MAX_ELEVATION) - *elevation;
+    if azimuth_delta.abs() < 1e-3 && elevation_delta.abs() < 1e-3 {
+        *azimuth = target.0;
+        *elevation += elevation_delta;
+        return true;
+    }
+    *azimuth += azimuth_delta * factor;
+    *elevation += elevation_delta * factor;
+    false
+}
+
+/// Draw the Views section: presets, saved views with rename and delete, and
+/// a field to save `current` under a name
+///
+/// Returns the view the user picked, for the viewer to move its camera to.
+pub fn views_section(ui: &mut egui::Ui, views: &mut CameraViews, current: CameraView) -> Option<CameraView> {
+    let mut recalled = None;
+
+    ui.horizontal_wrapped(|ui| {
+        for preset in preset_views() {
+            if ui.button(&preset.name).clicked() {
+                recalled = Some(preset);
+            }
+        }
+    });
+
+    // Applied after the loop, which borrows the saved views
+    let mut start_rename = None;
+    let mut finish_rename = None;
+    let mut remove = None;
+    for (i, view) in views.saved.iter().enumerate() {
+        ui.horizontal(|ui| match &mut views.renaming {
+            Some((index, name)) if *index == i => {
+                let response = ui.text_edit_singleline(name);
+                let entered = response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+                if ui.small_button("✔").clicked() || entered {
+                    finish_rename = Some(name.clone());
+                }
+            }
+            _ => {
+                if ui.button(&view.name).on_hover_text("Move the camera to this view").clicked() {
+                    recalled = Some(view.clone());
+                }
+                if ui.small_button("✏").on_hover_text("Rename").clicked() {
+                    start_rename = Some((i, view.name.clone()));
+                }
+                if ui.small_button("🗑").on_hover_text("Delete").clicked() {
+                    remove = Some(i);
+                }
+            }
+        });
+    }
+    if let Some(renaming) = start_rename {
+        views.renaming = Some(renaming);
+    }
+    if let (Some(name), Some((index, _))) = (finish_rename, views.renaming.clone()) {
+        // A taken or empty name leaves the field open
+        if views.rename(index, &name) {
+            views.renaming = None;
+        }
+    }
+    if let Some(i) = remove {
+        views.remove(i);
+        views.renaming = None;
+    }
+
+    ui.horizontal(|ui| {
+        ui.add(egui::TextEdit::singleline(&mut views.new_name).hint_text("View name").desired_width(100.0));
+        let name = views.new_name.trim().to_string();
+        if ui.add_enabled(!name.is_empty(), egui::Button::new("Save")).on_hover_text("Save the current view").clicked() {
+            views.save(CameraView { name, ..current });
+            views.new_name.clear();
+        }
+    });
+
+    recalled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn view(name: &str) -> CameraView {
+        CameraView::current(name, 0.3, 0.4, 1.2, Vec3::new(0.1, 0.2, 0.0))
+    }
+
+    #[test]
+    fn test_presets_are_always_listed() {
+        let names: Vec<_> = preset_views().into_iter().map(|v| v.name).collect();
+        assert_eq!(names, ["Top", "Front", "Right", "Isometric"]);
+        // Presets keep the current focus and zoom
+        assert!(preset_views().iter().all(|v| v.focus.is_none() && v.distance.is_none()));
+    }
+
+    #[test]
+    fn test_rejected_renames_and_file_format() {
+        // Only changes that fail are made here: the others write the file
+        let mut views = CameraViews { saved: vec![view("gps"), view("lidar")], ..Default::default() };
+        assert!(!views.rename(0, "lidar"));
+        assert!(!views.rename(0, "  "));
+        assert!(!views.rename(5, "radar"));
+        assert!(views.remove(5).is_none());
+        assert_eq!(views.saved().iter().map(|v| v.name.as_str()).collect::<Vec<_>>(), ["gps", "lidar"]);
+
+        let json = serde_json::to_string(&views.saved).unwrap();
+        let read: Vec<CameraView> = serde_json::from_str(&json).unwrap();
+        assert_eq!(read, views.saved);
+        // Older or hand-written files may leave out the zoom and focus
+        let read: CameraView = serde_json::from_str(r#"{"name":"top","azimuth":0,"elevation":1.5}"#).unwrap();
+        assert_eq!(read.focus, None);
+    }
+
+    #[test]
+    fn test_ease_orbit_turns_the_short_way() {
+        let (mut azimuth, mut elevation) = (3.0, 0.0);
+        assert!(!ease_orbit(&mut azimuth, &mut elevation, (-3.0, 2.0), 0.5));
+        // Across ±π rather than back through zero
+        assert!(azimuth > 3.0);
+        assert!((elevation - 0.75).abs() < 1e-6);
+
+        for _ in 0..40 {
+            if ease_orbit(&mut azimuth, &mut elevation, (-3.0, 2.0), 0.5) {
+                break;
+            }
+        }
+        assert_eq!(azimuth, -3.0);
+        assert!((elevation - MAX_ELEVATION).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,231 @@
+//! Pose constraints for device placement
+//!
+//! Works out where a device may sit while its pose is edited: with its
+//! bounding box above the ground plane (Z = 0), resting on the upper face of
+//! another device's bounding box, or both. Bounding boxes are device-local
+//! min/max corners, as the selection highlight computes them.
+//!
+//! Edits are corrected with [`constrain`]; a pose loaded from a file is only
+//! checked with [`violation`], so opening a file never moves anything.
+
+use std::fmt;
+
+use bevy::prelude::*;
+
+/// How far a pose may be off before it counts as a violation (meters)
+pub const VIOLATION_TOLERANCE: f32 = 0.001;
+
+/// A device's placement together with its device-local bounding box
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundedPose {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl BoundedPose {
+    pub fn new(translation: Vec3, rotation: Quat, min: Vec3, max: Vec3) -> Self {
+        Self { translation, rotation, min, max }
+    }
+
+    /// Lowest point of the rotated box along `direction`, relative to the device origin
+    fn extent_min(&self, direction: Vec3) -> f32 {
+        let (min, max) = (self.min, self.max);
+        (0..8)
+            .map(|i| {
+                let corner = Vec3::new(
+                    if i & 1 == 0 { min.x } else { max.x },
+                    if i & 2 == 0 { min.y } else { max.y },
+                    if i & 4 == 0 { min.z } else { max.z },
+                );
+                (self.rotation * corner).dot(direction)
+            })
+            .fold(f32::INFINITY, f32::min)
+    }
+
+    /// The box face pointing most nearly up, in world coordinates
+    fn upper_face(&self) -> Face {
+        let axes = [Vec3::X, Vec3::Y, Vec3::Z];
+        let up = |(i, sign): (usize, f32)| (self.rotation * axes[i]).z * sign;
+        let (i, sign) = (0..3)
+            .flat_map(|i| [(i, 1.0), (i, -1.0)])
+            .max_by(|a, b| up(*a).total_cmp(&up(*b)))
+            .unwrap_or((2, 1.0));
+        let (u, v) = ((i + 1) % 3, (i + 2) % 3);
+
+        let center = (self.min + self.max) / 2.0;
+        let half = (self.max - self.min) / 2.0;
+        Face {
+            center: self.translation + self.rotation * (center + axes[i] * sign * half[i]),
+            normal: self.rotation * axes[i] * sign,
+            tangents: [(self.rotation * axes[u], half[u]), (self.rotation * axes[v], half[v])],
+        }
+    }
+}
+
+/// A box face: center, outward normal and the in-plane axes with their half extents
+struct Face {
+    center: Vec3,
+    normal: Vec3,
+    tangents: [(Vec3, f32); 2],
+}
+
+/// Translation lifting `pose` so its bounding box is at or above Z = 0
+pub fn keep_above_ground(pose: &BoundedPose) -> Vec3 {
+    let lowest = pose.translation.z + pose.extent_min(Vec3::Z);
+    let mut translation = pose.translation;
+    if lowest < 0.0 {
+        translation.z -= lowest;
+    }
+    translation
+}
+
+/// Translation resting `pose` on the upper face of `base`
+///
+/// The device slides across the face: its position is projected onto the
+/// face plane and kept within the face, then it is set down so the lowest
+/// point of its box touches the face.
+pub fn stack_on(pose: &BoundedPose, base: &BoundedPose) -> Vec3 {
+    let face = base.upper_face();
+    let offset = pose.translation - face.center;
+    let in_plane: Vec3 = face
+        .tangents
+        .iter()
+        .map(|(tangent, half)| *tangent * offset.dot(*tangent).clamp(-half, *half))
+        .sum();
+    face.center + in_plane - face.normal * pose.extent_min(face.normal)
+}
+
+/// Translation for an edited pose with the limits applied; stacking first, then the ground
+pub fn constrain(pose: &BoundedPose, keep_above: bool, base: Option<&BoundedPose>) -> Vec3 {
+    let mut pose = *pose;
+    if let Some(base) = base {
+        pose.translation = stack_on(&pose, base);
+    }
+    if keep_above {
+        pose.translation = keep_above_ground(&pose);
+    }
+    pose.translation
+}
+
+/// A pose that breaks its constraints
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConstraintViolation {
+    /// The bounding box reaches this far below the ground (meters)
+    BelowGround(f32),
+    /// The device is this far from resting on its base (meters)
+    OffBase(f32),
+}
+
+impl fmt::Display for ConstraintViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConstraintViolation::BelowGround(depth) => write!(f, "{:.0} mm below the ground", depth * 1000.0),
+            ConstraintViolation::OffBase(distance) => write!(f, "{:.0} mm off its base", distance * 1000.0),
+        }
+    }
+}
+
+/// Check a pose against its limits without correcting it
+pub fn violation(pose: &BoundedPose, keep_above: bool, base: Option<&BoundedPose>) -> Option<ConstraintViolation> {
+    if let Some(base) = base {
+        let distance = stack_on(pose, base).distance(pose.translation);
+        if distance > VIOLATION_TOLERANCE {
+            return Some(ConstraintViolation::OffBase(distance));
+        }
+    }
+    if keep_above {
+        let depth = -(pose.translation.z + pose.extent_min(Vec3::Z));
+        if depth > VIOLATION_TOLERANCE {
+            return Some(ConstraintViolation::BelowGround(depth));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    fn cube(translation: Vec3, rotation: Quat, half: f32) -> BoundedPose {
+        BoundedPose::new(translation, rotation, Vec3::splat(-half), Vec3::splat(half))
+    }
+
+    fn assert_near(actual: Vec3, expected: Vec3) {
+        assert!(actual.abs_diff_eq(expected, 1e-5), "{actual} != {expected}");
+    }
+
+    #[test]
+    fn test_keep_above_ground_clamps_to_the_box() {
+        let half = Vec3::new(0.05, 0.03, 0.01);
+        let board = BoundedPose::new(Vec3::new(0.2, 0.1, -0.2), Quat::IDENTITY, -half, half);
+        assert_near(keep_above_ground(&board), Vec3::new(0.2, 0.1, 0.01));
+
+        // Standing on its side, the box's Y extent is its height
+        let standing = BoundedPose { rotation: Quat::from_rotation_x(FRAC_PI_2), ..board };
+        assert_near(keep_above_ground(&standing), Vec3::new(0.2, 0.1, 0.03));
+
+        // Already above the ground: untouched
+        let raised = BoundedPose { translation: Vec3::new(0.0, 0.0, 0.5), ..board };
+        assert_near(keep_above_ground(&raised), raised.translation);
+    }
+
+    #[test]
+    fn test_stack_on_level_base() {
+        let half = Vec3::new(0.1, 0.1, 0.02);
+        let base = BoundedPose::new(Vec3::new(0.0, 0.0, 0.1), Quat::IDENTITY, -half, half);
+        let device = cube(Vec3::new(0.05, -0.3, 0.5), Quat::IDENTITY, 0.01);
+        // X slides freely, Y stays on the face, Z rests on top
+        assert_near(stack_on(&device, &base), Vec3::new(0.05, -0.1, 0.13));
+    }
+
+    #[test]
+    fn test_stack_on_rotated_base() {
+        // Rolled 90° about X, the base's +Y face points up and its Z axis runs along -Y
+        let half = Vec3::new(0.1, 0.05, 0.02);
+        let base = BoundedPose::new(Vec3::new(0.0, 0.0, 0.2), Quat::from_rotation_x(FRAC_PI_2), -half, half);
+        let device = cube(Vec3::new(0.3, 0.5, 0.0), Quat::IDENTITY, 0.01);
+        assert_near(stack_on(&device, &base), Vec3::new(0.1, 0.02, 0.26));
+    }
+
+    #[test]
+    fn test_stack_on_tilted_base_touches_the_face() {
+        let base = cube(Vec3::new(0.1, 0.0, 0.3), Quat::from_rotation_y(0.3), 0.1);
+        let device = cube(Vec3::new(0.12, 0.02, 1.0), Quat::from_rotation_z(0.5), 0.01);
+        let stacked = BoundedPose { translation: stack_on(&device, &base), ..device };
+
+        let face = base.upper_face();
+        let gap = (stacked.translation - face.center).dot(face.normal) + stacked.extent_min(face.normal);
+        assert!(gap.abs() < 1e-5, "gap {gap}");
+        // Stacking a stacked device again leaves it where it is
+        assert_near(stack_on(&stacked, &base), stacked.translation);
+        assert_eq!(violation(&stacked, false, Some(&base)), None);
+    }
+
+    #[test]
+    fn test_constrain_stacks_then_keeps_above_ground() {
+        // A base sunk into the ground would put the device below it
+        let base = cube(Vec3::new(0.0, 0.0, -0.5), Quat::IDENTITY, 0.1);
+        let device = cube(Vec3::ZERO, Quat::IDENTITY, 0.01);
+        assert_near(constrain(&device, false, Some(&base)), Vec3::new(0.0, 0.0, -0.39));
+        assert_near(constrain(&device, true, Some(&base)), Vec3::new(0.0, 0.0, 0.01));
+        assert_near(constrain(&device, false, None), Vec3::ZERO);
+    }
+
+    #[test]
+    fn test_violations_are_reported() {
+        let buried = cube(Vec3::new(0.0, 0.0, -0.02), Quat::IDENTITY, 0.01);
+        let Some(ConstraintViolation::BelowGround(depth)) = violation(&buried, true, None) else {
+            panic!("expected a ground violation");
+        };
+        assert!((depth - 0.03).abs() < 1e-5);
+        assert_eq!(violation(&buried, false, None), None);
+
+        let base = cube(Vec3::ZERO, Quat::IDENTITY, 0.1);
+        let floating = cube(Vec3::new(0.0, 0.0, 0.2), Quat::IDENTITY, 0.01);
+        assert!(matches!(violation(&floating, true, Some(&base)), Some(ConstraintViolation::OffBase(_))));
+        assert_eq!(ConstraintViolation::BelowGround(0.03).to_string(), "30 mm below the ground");
+    }
+}
@@ -0,0 +1,180 @@
+//! Undo/redo of device pose edits
+//!
+//! The details panel of both viewers reports every frame of a pose edit to
+//! [`EditHistory::track`]. A drag over a value (or typing into it) changes
+//! the pose many times; those changes are coalesced into one entry when the
+//! edit ends, so a single undo goes back to the pose before the drag started.
+//!
+//! Undo and redo return the entry; applying its pose to the scene (and, in
+//! dendrite-web, syncing it to the daemon) is up to the viewer.
+
+use bevy::prelude::*;
+use std::collections::VecDeque;
+
+/// Entries kept unless the viewer sets another depth
+pub const DEFAULT_EDIT_HISTORY_DEPTH: usize = 100;
+
+/// A device pose as the details panel edits it
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct EditPose {
+    /// Position (ENU, meters)
+    pub position: Vec3,
+    /// Roll, pitch, yaw (FLU, radians)
+    pub orientation: Vec3,
+}
+
+/// One undoable pose change
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoseEdit {
+    pub device_id: String,
+    pub old_pose: EditPose,
+    pub new_pose: EditPose,
+}
+
+/// Pose changes that can be undone and redone, oldest first
+#[derive(Debug, Resource)]
+pub struct EditHistory {
+    undo: VecDeque<PoseEdit>,
+    redo: Vec<PoseEdit>,
+    /// Device whose edit is in progress, with its pose when the edit began
+    pending: Option<(String, EditPose)>,
+    depth: usize,
+}
+
+impl Default for EditHistory {
+    fn default() -> Self {
+        Self::with_depth(DEFAULT_EDIT_HISTORY_DEPTH)
+    }
+}
+
+impl EditHistory {
+    /// An empty history keeping at most `depth` entries
+    pub fn with_depth(depth: usize) -> Self {
+        Self { undo: VecDeque::new(), redo: Vec::new(), pending: None, depth }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Keep at most `depth` entries, dropping the oldest
+    pub fn set_depth(&mut self, depth: usize) {
+        self.depth = depth;
+        while self.undo.len() > depth {
+            self.undo.pop_front();
+        }
+    }
+
+    /// Follow one frame of the details panel for `device_id`
+    ///
+    /// `before` is the pose when the frame started, `after` when it ended,
+    /// and `editing` whether a value is still being dragged or typed into.
+    /// Changes during an edit are held back and recorded as one entry once
+    /// `editing` turns false.
+    pub fn track(&mut self, device_id: &str, before: EditPose, after: EditPose, editing: bool) {
+        // An edit of another device ended while it wasn't shown
+        if self.pending.as_ref().is_some_and(|(id, _)| id != device_id) {
+            self.pending = None;
+        }
+        if editing {
+            if self.pending.is_none() && before != after {
+                self.pending = Some((device_id.to_string(), before));
+            }
+            return;
+        }
+        let old_pose = self.pending.take().map_or(before, |(_, start)| start);
+        if old_pose != after {
+            self.push(PoseEdit { device_id: device_id.to_string(), old_pose, new_pose: after });
+        }
+    }
+
+    /// Record a finished change, dropping whatever could be redone
+    pub fn push(&mut self, edit: PoseEdit) {
+        self.redo.clear();
+        self.undo.push_back(edit);
+        if self.undo.len() > self.depth {
+            self.undo.pop_front();
+        }
+    }
+
+    /// The latest change, to be reverted to its `old_pose`
+    pub fn undo(&mut self) -> Option<PoseEdit> {
+        self.pending = None;
+        let edit = self.undo.pop_back()?;
+        self.redo.push(edit.clone());
+        Some(edit)
+    }
+
+    /// The latest undone change, to be applied again with its `new_pose`
+    pub fn redo(&mut self) -> Option<PoseEdit> {
+        self.pending = None;
+        let edit = self.redo.pop()?;
+        self.undo.push_back(edit.clone());
+        Some(edit)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Forget everything, e.g. when another HCDF is loaded
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.pending = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(x: f32) -> EditPose {
+        EditPose { position: Vec3::new(x, 0.0, 0.0), orientation: Vec3::ZERO }
+    }
+
+    #[test]
+    fn test_drag_is_one_entry() {
+        let mut history = EditHistory::default();
+        // Hovering and focusing without a change records nothing
+        history.track("a", at(0.0), at(0.0), true);
+        history.track("a", at(0.0), at(0.0), false);
+        assert!(!history.can_undo());
+
+        for step in 0..10 {
+            history.track("a", at(step as f32), at(step as f32 + 1.0), true);
+        }
+        assert!(!history.can_undo());
+        history.track("a", at(10.0), at(10.0), false);
+
+        let edit = history.undo().unwrap();
+        assert_eq!((edit.old_pose, edit.new_pose), (at(0.0), at(10.0)));
+        assert!(!history.can_undo());
+        assert_eq!(history.redo().unwrap().new_pose, at(10.0));
+        assert!(history.redo().is_none());
+    }
+
+    #[test]
+    fn test_new_edit_drops_redo_and_depth_is_capped() {
+        let mut history = EditHistory::with_depth(3);
+        for i in 0..5 {
+            history.track("a", at(i as f32), at(i as f32 + 1.0), false);
+        }
+        history.undo().unwrap();
+        assert!(history.can_redo());
+        history.track("b", at(0.0), at(2.0), false);
+        assert!(!history.can_redo());
+
+        let undone: Vec<_> = std::iter::from_fn(|| history.undo()).map(|e| e.device_id).collect();
+        assert_eq!(undone, ["b", "a", "a"]);
+
+        history.set_depth(1);
+        assert!(history.redo().is_some());
+        history.clear();
+        assert!(!history.can_undo() && !history.can_redo());
+    }
+}
@@ -0,0 +1,233 @@
+//! FLU/ENU frame overlay
+//!
+//! Draws two triads at the selected device's origin: the body FLU triad
+//! (Forward/Left/Up, solid, rotating with the device) and the world ENU
+//! triad (East/North/Up, dashed, fixed). Seeing both at once makes it clear
+//! how an orientation maps body axes onto world axes.
+//!
+//! Apps point `FrameOverlayTarget` at the selected device entity and flip
+//! `ShowFrameOverlay` from their UI; this module does the rest. Axis labels
+//! are painted in screen space with egui so they face the camera and stay
+//! the same size from any viewing angle.
+
+use bevy::prelude::*;
+use bevy::render::alpha::AlphaMode;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::axes::{axis_transform, dash_length, dashed_axis_transforms, TRIAD_AXES};
+
+/// Body triad axis length (meters)
+const FLU_LENGTH: f32 = 0.06;
+
+/// World triad axis length; longer than the body triad so labels of
+/// coinciding axes (e.g. F and E at zero yaw) don't overlap
+const ENU_LENGTH: f32 = 0.075;
+
+/// Radius of the overlay axis cylinders
+const AXIS_THICKNESS: f32 = 0.0012;
+
+/// Dashes per world axis
+const ENU_DASHES: usize = 5;
+
+/// Distance past the axis tip at which its label is placed
+const LABEL_OFFSET: f32 = 0.008;
+
+const FLU_LABELS: [&str; 3] = ["F", "L", "U"];
+const ENU_LABELS: [&str; 3] = ["E", "N", "U"];
+
+/// Axis colors (RGB, matching the world axis and frame gizmos)
+const AXIS_COLORS: [[f32; 3]; 3] = [[0.9, 0.2, 0.2], [0.2, 0.9, 0.2], [0.2, 0.2, 0.9]];
+
+/// Whether the FLU/ENU overlay is shown on the selected device
+#[derive(Debug, Clone, Resource, Default)]
+pub struct ShowFrameOverlay(pub bool);
+
+/// Device entity the overlay is drawn on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Resource, Default)]
+pub struct FrameOverlayTarget(pub Option<Entity>);
+
+/// Root of one overlay triad
+#[derive(Component)]
+pub struct FrameOverlayRoot {
+    /// Body (FLU) triad that rotates with the device, or world (ENU) triad
+    pub body: bool,
+}
+
+/// Device forward (body +X) expressed in world ENU coordinates
+pub fn forward_enu(rotation: Quat) -> Vec3 {
+    rotation * Vec3::X
+}
+
+/// Compass heading of a forward vector in degrees (0 = North, 90 = East),
+/// or None when it points straight up or down
+pub fn heading_degrees(forward: Vec3) -> Option<f32> {
+    if forward.truncate().length() < 1e-4 {
+        return None;
+    }
+    Some(forward.x.atan2(forward.y).to_degrees().rem_euclid(360.0))
+}
+
+/// Plugin for the FLU/ENU overlay
+pub struct FrameOverlayPlugin;
+
+impl Plugin for FrameOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ShowFrameOverlay>()
+            .init_resource::<FrameOverlayTarget>()
+            .add_systems(Update, (
+                sync_frame_overlay,
+                follow_frame_overlay_target.after(sync_frame_overlay),
+                render_frame_overlay_labels,
+            ));
+    }
+}
+
+/// Spawn or despawn the overlay when the toggle or target changes
+fn sync_frame_overlay(
+    mut commands: Commands,
+    show: Res<ShowFrameOverlay>,
+    target: Res<FrameOverlayTarget>,
+    overlay_query: Query<Entity, With<FrameOverlayRoot>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    if !show.is_changed() && !target.is_changed() {
+        return;
+    }
+
+    for entity in overlay_query.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    if !show.0 || target.0.is_none() {
+        return;
+    }
+
+    let flu_mesh = meshes.add(Cylinder::new(AXIS_THICKNESS, FLU_LENGTH));
+    let enu_mesh = meshes.add(Cylinder::new(AXIS_THICKNESS, dash_length(ENU_LENGTH, ENU_DASHES)));
+
+    // Both roots start hidden and are placed by follow_frame_overlay_target
+    commands
+        .spawn((Transform::default(), Visibility::Hidden, FrameOverlayRoot { body: true }))
+        .with_children(|parent| {
+            for (axis, [r, g, b]) in TRIAD_AXES.into_iter().zip(AXIS_COLORS) {
+                parent.spawn((
+                    Mesh3d(flu_mesh.clone()),
+                    MeshMaterial3d(materials.add(StandardMaterial {
+                        base_color: Color::srgb(r, g, b),
+                        unlit: true,
+                        ..default()
+                    })),
+                    axis_transform(Transform::IDENTITY, axis, FLU_LENGTH),
+                ));
+            }
+        });
+
+    commands
+        .spawn((Transform::default(), Visibility::Hidden, FrameOverlayRoot { body: false }))
+        .with_children(|parent| {
+            for (axis, [r, g, b]) in TRIAD_AXES.into_iter().zip(AXIS_COLORS) {
+                let material = materials.add(StandardMaterial {
+                    base_color: Color::srgba(r, g, b, 0.6),
+                    unlit: true,
+                    alpha_mode: AlphaMode::Blend,
+                    ..default()
+                });
+                for transform in dashed_axis_transforms(Transform::IDENTITY, axis, ENU_LENGTH, ENU_DASHES) {
+                    parent.spawn((Mesh3d(enu_mesh.clone()), MeshMaterial3d(material.clone()), transform));
+                }
+            }
+        });
+}
+
+/// Keep both triads at the target's origin; only the body triad rotates
+fn follow_frame_overlay_target(
+    target: Res<FrameOverlayTarget>,
+    target_query: Query<&GlobalTransform, Without<FrameOverlayRoot>>,
+    mut overlay_query: Query<(&FrameOverlayRoot, &mut Transform, &mut Visibility)>,
+) {
+    let target_transform = target.0.and_then(|entity| target_query.get(entity).ok());
+
+    for (root, mut transform, mut visibility) in overlay_query.iter_mut() {
+        let Some(target_transform) = target_transform else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        let (_, rotation, translation) = target_transform.to_scale_rotation_translation();
+        transform.translation = translation;
+        transform.rotation = if root.body { rotation } else { Quat::IDENTITY };
+        *visibility = Visibility::Visible;
+    }
+}
+
+/// Paint F/L/U and E/N/U labels at the axis tips, facing the camera
+fn render_frame_overlay_labels(
+    mut contexts: EguiContexts,
+    show: Res<ShowFrameOverlay>,
+    target: Res<FrameOverlayTarget>,
+    target_query: Query<&GlobalTransform, Without<FrameOverlayRoot>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+) {
+    if !show.0 {
+        return;
+    }
+    let Some(target_transform) = target.0.and_then(|entity| target_query.get(entity).ok()) else {
+        return;
+    };
+    let Ok((camera, camera_transform)) = camera_query.single() else {
+        return;
+    };
+    let Ok(ctx) = contexts.ctx_mut() else { return };
+
+    let (_, rotation, origin) = target_transform.to_scale_rotation_translation();
+    let labels = TRIAD_AXES
+        .iter()
+        .zip(FLU_LABELS)
+        .zip(AXIS_COLORS)
+        .map(|((axis, text), color)| (origin + rotation * *axis * (FLU_LENGTH + LABEL_OFFSET), text, color))
+        .chain(
+            TRIAD_AXES
+                .iter()
+                .zip(ENU_LABELS)
+                .zip(AXIS_COLORS)
+                .map(|((axis, text), color)| (origin + *axis * (ENU_LENGTH + LABEL_OFFSET), text, color)),
+        );
+
+    let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Background, egui::Id::new("frame_overlay_labels")));
+    for (position, text, [r, g, b]) in labels {
+        let Ok(screen) = camera.world_to_viewport(camera_transform, position) else {
+            continue;
+        };
+        let color = egui::Color32::from_rgb((r * 255.0) as u8, (g * 255.0) as u8, (b * 255.0) as u8);
+        let galley = painter.layout_no_wrap(text.to_string(), egui::FontId::proportional(13.0), color);
+        let rect = egui::Align2::CENTER_CENTER.anchor_size(egui::pos2(screen.x, screen.y), galley.size());
+        // Dark backing keeps the letter readable over bright models and the grid
+        painter.rect_filled(rect.expand(2.0), 3.0, egui::Color32::from_black_alpha(160));
+        painter.galley(rect.min, galley, color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    #[test]
+    fn test_forward_enu_and_heading() {
+        // Unrotated device faces East
+        let forward = forward_enu(Quat::IDENTITY);
+        assert!(forward.abs_diff_eq(Vec3::X, 1e-6));
+        assert!((heading_degrees(forward).unwrap() - 90.0).abs() < 1e-3);
+
+        // Yaw +90 (counter-clockwise about Up) turns it to face North
+        let forward = forward_enu(Quat::from_rotation_z(FRAC_PI_2));
+        assert!(forward.abs_diff_eq(Vec3::Y, 1e-6));
+        let heading = heading_degrees(forward).unwrap();
+        assert!(heading < 1e-3 || heading > 360.0 - 1e-3);
+
+        // Pitched nose-up 90 degrees has no heading
+        let forward = forward_enu(Quat::from_rotation_y(-FRAC_PI_2));
+        assert!(forward.abs_diff_eq(Vec3::Z, 1e-6));
+        assert_eq!(heading_degrees(forward), None);
+    }
+}
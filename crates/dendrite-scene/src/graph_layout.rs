@@ -0,0 +1,239 @@
+//! Radial layout for the topology graph
+//!
+//! The root sits in the center with each level of its subtree on a ring
+//! further out. A parent's children share the angle the parent was given,
+//! in proportion to how many leaves each carries, so subtrees don't cross.
+//! Nodes outside the root's subtree go on one more ring outside the tree;
+//! without a root, every node is on a single ring.
+//!
+//! Positions are in ring units (ring `n` is at distance `n` from the root);
+//! the UI scales them by its ring spacing and zoom.
+
+use std::collections::{HashMap, HashSet};
+use std::f32::consts::{FRAC_PI_2, TAU};
+
+/// Where each visible node of the graph goes
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RadialLayout {
+    positions: HashMap<String, [f32; 2]>,
+    /// Hidden descendants of each collapsed parent
+    hidden: HashMap<String, usize>,
+    radius: f32,
+}
+
+impl RadialLayout {
+    /// Position of a node, None if it is inside a collapsed subtree
+    pub fn position(&self, id: &str) -> Option<[f32; 2]> {
+        self.positions.get(id).copied()
+    }
+
+    /// How many nodes a collapsed parent hides (0 if it isn't collapsed)
+    pub fn hidden_under(&self, id: &str) -> usize {
+        self.hidden.get(id).copied().unwrap_or(0)
+    }
+
+    /// Distance of the outermost ring
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+}
+
+/// Lay out a graph given as `(id, children)` pairs
+///
+/// Children of a parent in `collapsed` are hidden along with their own
+/// subtrees. Children that aren't nodes of the graph are ignored, and a
+/// node reached twice keeps its first place.
+pub fn radial_layout<'a>(
+    root: Option<&str>,
+    nodes: impl IntoIterator<Item = (&'a str, &'a [String])>,
+    collapsed: &HashSet<String>,
+) -> RadialLayout {
+    let nodes: Vec<(&str, &[String])> = nodes.into_iter().collect();
+    let children: HashMap<&str, Vec<&str>> = nodes
+        .iter()
+        .map(|(id, children)| {
+            let known = children.iter().map(String::as_str).filter(|c| nodes.iter().any(|(n, _)| n == c)).collect();
+            (*id, known)
+        })
+        .collect();
+
+    let mut layout = RadialLayout::default();
+    let mut placed: HashSet<&str> = HashSet::new();
+
+    // Everything under a collapsed parent is hidden, wherever it would have gone
+    let mut hidden: HashSet<&str> = HashSet::new();
+    for (id, _) in &nodes {
+        if collapsed.contains(*id) {
+            let mut under = HashSet::new();
+            collect_subtree(*id, &children, &mut under);
+            under.remove(id);
+            layout.hidden.insert(id.to_string(), under.len());
+            hidden.extend(under);
+        }
+    }
+    // A collapsed parent inside another collapsed subtree hides nothing more
+    layout.hidden.retain(|id, _| !hidden.contains(id.as_str()));
+
+    if let Some((&root, _)) = root.and_then(|r| children.get_key_value(r)) {
+        let mut weights = HashMap::new();
+        leaf_weight(root, &children, &hidden, &mut HashSet::new(), &mut weights);
+        place(root, 0, -FRAC_PI_2, -FRAC_PI_2 + TAU, &children, &hidden, &weights, &mut placed, &mut layout);
+    }
+
+    // Loose nodes on a ring outside the tree
+    let loose: Vec<&str> = nodes
+        .iter()
+        .map(|(id, _)| *id)
+        .filter(|id| !placed.contains(id) && !hidden.contains(id))
+        .collect();
+    if !loose.is_empty() {
+        let ring = layout.radius.floor() + 1.0;
+        for (i, id) in loose.iter().enumerate() {
+            let angle = -FRAC_PI_2 + i as f32 / loose.len() as f32 * TAU;
+            layout.positions.insert(id.to_string(), [ring * angle.cos(), ring * angle.sin()]);
+        }
+        layout.radius = ring;
+    }
+    layout
+}
+
+fn collect_subtree<'a>(id: &'a str, children: &HashMap<&'a str, Vec<&'a str>>, seen: &mut HashSet<&'a str>) {
+    if !seen.insert(id) {
+        return;
+    }
+    for child in children.get(id).into_iter().flatten() {
+        collect_subtree(*child, children, seen);
+    }
+}
+
+/// Visible leaves under a node (at least 1), memoized in `weights`
+fn leaf_weight<'a>(
+    id: &'a str,
+    children: &HashMap<&'a str, Vec<&'a str>>,
+    hidden: &HashSet<&'a str>,
+    visiting: &mut HashSet<&'a str>,
+    weights: &mut HashMap<&'a str, usize>,
+) -> usize {
+    if let Some(weight) = weights.get(id) {
+        return *weight;
+    }
+    if !visiting.insert(id) {
+        return 0;
+    }
+    let weight = children
+        .get(id)
+        .into_iter()
+        .flatten()
+        .filter(|c| !hidden.contains(*c))
+        .map(|c| leaf_weight(*c, children, hidden, visiting, weights))
+        .sum::<usize>()
+        .max(1);
+    weights.insert(id, weight);
+    weight
+}
+
+/// Place `id` on ring `depth` in the middle of its sector, then its children across the sector
+#[allow(clippy::too_many_arguments)]
+fn place<'a>(
+    id: &'a str,
+    depth: usize,
+    start: f32,
+    end: f32,
+    children: &HashMap<&'a str, Vec<&'a str>>,
+    hidden: &HashSet<&'a str>,
+    weights: &HashMap<&'a str, usize>,
+    placed: &mut HashSet<&'a str>,
+    layout: &mut RadialLayout,
+) {
+    if !placed.insert(id) {
+        return;
+    }
+    let ring = depth as f32;
+    let angle = (start + end) / 2.0;
+    layout.positions.insert(id.to_string(), [ring * angle.cos(), ring * angle.sin()]);
+    layout.radius = layout.radius.max(ring);
+
+    let visible: Vec<&str> = children
+        .get(id)
+        .into_iter()
+        .flatten()
+        .copied()
+        .filter(|c| !hidden.contains(c) && !placed.contains(c))
+        .collect();
+    let total: usize = visible.iter().map(|c| weights.get(c).copied().unwrap_or(1)).sum();
+    let mut from = start;
+    for child in visible {
+        let share = (end - start) * weights.get(child).copied().unwrap_or(1) as f32 / total.max(1) as f32;
+        place(child, depth + 1, from, from + share, children, hidden, weights, placed, layout);
+        from += share;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph(edges: &[(&str, &[&str])]) -> Vec<(String, Vec<String>)> {
+        edges
+            .iter()
+            .map(|(id, children)| (id.to_string(), children.iter().map(|c| c.to_string()).collect()))
+            .collect()
+    }
+
+    fn layout(graph: &[(String, Vec<String>)], root: Option<&str>, collapsed: &[&str]) -> RadialLayout {
+        let collapsed = collapsed.iter().map(|c| c.to_string()).collect();
+        radial_layout(root, graph.iter().map(|(id, c)| (id.as_str(), c.as_slice())), &collapsed)
+    }
+
+    fn distance(layout: &RadialLayout, id: &str) -> f32 {
+        let [x, y] = layout.position(id).unwrap();
+        (x * x + y * y).sqrt()
+    }
+
+    #[test]
+    fn test_root_in_center_with_children_around() {
+        let children = ["a", "b", "c", "d", "e", "f"];
+        let mut edges: Vec<(&str, &[&str])> = vec![("switch", &children[..])];
+        edges.extend(children.iter().map(|c| (*c, &[] as &[&str])));
+        let layout = layout(&graph(&edges), Some("switch"), &[]);
+
+        assert_eq!(layout.position("switch"), Some([0.0, 0.0]));
+        for child in children {
+            assert!((distance(&layout, child) - 1.0).abs() < 1e-5, "{child}");
+        }
+        // Evenly spread: neighbours are a sixth of a turn apart
+        let [ax, ay] = layout.position("a").unwrap();
+        let [bx, by] = layout.position("b").unwrap();
+        assert!(((ax - bx).hypot(ay - by) - 1.0).abs() < 1e-5);
+        assert_eq!(layout.radius(), 1.0);
+    }
+
+    #[test]
+    fn test_collapsed_parent_hides_its_subtree() {
+        let g = graph(&[("root", &["hub", "x"]), ("hub", &["y", "z"]), ("y", &["w"]), ("x", &[]), ("z", &[]), ("w", &[])]);
+        let open = layout(&g, Some("root"), &[]);
+        assert!((distance(&open, "w") - 3.0).abs() < 1e-5);
+
+        let folded = layout(&g, Some("root"), &["hub"]);
+        assert!(folded.position("hub").is_some());
+        for id in ["y", "z", "w"] {
+            assert_eq!(folded.position(id), None, "{id}");
+        }
+        assert_eq!(folded.hidden_under("hub"), 3);
+        assert_eq!(folded.hidden_under("root"), 0);
+        assert_eq!(folded.radius(), 1.0);
+    }
+
+    #[test]
+    fn test_loose_nodes_and_no_root() {
+        let g = graph(&[("root", &["a"]), ("a", &[]), ("loose", &[]), ("cycle", &["cycle"])]);
+        let with_root = layout(&g, Some("root"), &[]);
+        assert!((distance(&with_root, "loose") - 2.0).abs() < 1e-5);
+        assert!((distance(&with_root, "cycle") - 2.0).abs() < 1e-5);
+
+        let flat = layout(&g, None, &[]);
+        for id in ["root", "a", "loose", "cycle"] {
+            assert!((distance(&flat, id) - 1.0).abs() < 1e-5, "{id}");
+        }
+    }
+}
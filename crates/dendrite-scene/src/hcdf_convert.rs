@@ -4,9 +4,10 @@
 //! into dendrite-scene visualization types.
 
 use dendrite_core::hcdf::{
-    Comp, Fov as HcdfFov, Frame, Mcu, Port as HcdfPort, Sensor as HcdfSensor, Visual,
-    parse_hex_color,
+    Comp, Fov as HcdfFov, Frame, Mcu, Port as HcdfPort, Sensor as HcdfSensor, SensorDriver,
+    Visual, parse_hex_color,
 };
+use dendrite_core::DriverRegistry;
 
 use crate::types::{
     AxisAlignData, DeviceData, DeviceStatus, FovData, FrameData, GeometryData, PortData,
@@ -84,6 +85,7 @@ pub fn visual_to_visual_data(v: &Visual) -> VisualData {
     VisualData {
         name: v.name.clone(),
         toggle: v.toggle.clone(),
+        default_hidden: v.is_default_hidden(),
         pose: v.parse_pose().map(|p| p.to_array()),
         model_path: v.model.as_ref().map(|m| m.href.clone()),
         model_sha: v.model.as_ref().and_then(|m| m.sha.clone()),
@@ -96,6 +98,7 @@ pub fn frame_to_frame_data(f: &Frame) -> FrameData {
         name: f.name.clone(),
         description: f.description.clone(),
         pose: f.parse_pose().map(|p| p.to_array()),
+        parent: f.parent.clone(),
     }
 }
 
@@ -135,6 +138,8 @@ pub fn sensors_to_sensor_data_vec(s: &HcdfSensor) -> Vec<SensorData> {
             category: "inertial".to_string(),
             sensor_type: inertial.sensor_type.clone(),
             driver: inertial.driver.as_ref().map(|d| d.name.clone()),
+            driver_label: sensor_driver_label(inertial.driver.as_ref()),
+            datasheet: sensor_driver_datasheet(inertial.driver.as_ref()),
             pose: inertial.parse_pose().map(|p| p.to_array()),
             axis_align: axis_data,
             geometry: None,
@@ -158,6 +163,8 @@ pub fn sensors_to_sensor_data_vec(s: &HcdfSensor) -> Vec<SensorData> {
             category: "em".to_string(),
             sensor_type: em.sensor_type.clone(),
             driver: em.driver.as_ref().map(|d| d.name.clone()),
+            driver_label: sensor_driver_label(em.driver.as_ref()),
+            datasheet: sensor_driver_datasheet(em.driver.as_ref()),
             pose: em.parse_pose().map(|p| p.to_array()),
             axis_align: axis_data,
             geometry: None,
@@ -173,6 +180,8 @@ pub fn sensors_to_sensor_data_vec(s: &HcdfSensor) -> Vec<SensorData> {
             category: "optical".to_string(),
             sensor_type: optical.sensor_type.clone(),
             driver: optical.driver.as_ref().map(|d| d.name.clone()),
+            driver_label: sensor_driver_label(optical.driver.as_ref()),
+            datasheet: sensor_driver_datasheet(optical.driver.as_ref()),
             pose: optical.parse_pose().map(|p| p.to_array()),
             axis_align: None,
             geometry: None,
@@ -187,6 +196,8 @@ pub fn sensors_to_sensor_data_vec(s: &HcdfSensor) -> Vec<SensorData> {
             category: "rf".to_string(),
             sensor_type: rf.sensor_type.clone(),
             driver: rf.driver.as_ref().map(|d| d.name.clone()),
+            driver_label: sensor_driver_label(rf.driver.as_ref()),
+            datasheet: sensor_driver_datasheet(rf.driver.as_ref()),
             pose: rf.parse_pose().map(|p| p.to_array()),
             axis_align: None,
             geometry: None,
@@ -201,6 +212,8 @@ pub fn sensors_to_sensor_data_vec(s: &HcdfSensor) -> Vec<SensorData> {
             category: "force".to_string(),
             sensor_type: force.sensor_type.clone(),
             driver: force.driver.as_ref().map(|d| d.name.clone()),
+            driver_label: sensor_driver_label(force.driver.as_ref()),
+            datasheet: sensor_driver_datasheet(force.driver.as_ref()),
             pose: force.parse_pose().map(|p| p.to_array()),
             axis_align: None,
             geometry: None,
@@ -215,6 +228,8 @@ pub fn sensors_to_sensor_data_vec(s: &HcdfSensor) -> Vec<SensorData> {
             category: "chemical".to_string(),
             sensor_type: chem.sensor_type.clone(),
             driver: chem.driver.as_ref().map(|d| d.name.clone()),
+            driver_label: sensor_driver_label(chem.driver.as_ref()),
+            datasheet: sensor_driver_datasheet(chem.driver.as_ref()),
             pose: chem.parse_pose().map(|p| p.to_array()),
             axis_align: None,
             geometry: None,
@@ -225,6 +240,20 @@ pub fn sensors_to_sensor_data_vec(s: &HcdfSensor) -> Vec<SensorData> {
     result
 }
 
+/// Registry display name for a sensor driver (None for unknown drivers)
+pub fn sensor_driver_label(driver: Option<&SensorDriver>) -> Option<String> {
+    DriverRegistry::builtin()
+        .get(&driver?.name)
+        .map(|info| info.display_name())
+}
+
+/// Registry datasheet URL for a sensor driver
+pub fn sensor_driver_datasheet(driver: Option<&SensorDriver>) -> Option<String> {
+    DriverRegistry::builtin()
+        .get(&driver?.name)
+        .and_then(|info| info.datasheet.clone())
+}
+
 /// Convert FOV to FovData
 pub fn fov_to_fov_data(fov: &HcdfFov) -> FovData {
     let pose = fov.parse_pose();
@@ -277,14 +306,9 @@ pub fn geometry_to_geometry_data(g: &dendrite_core::hcdf::Geometry) -> Option<Ge
 }
 
 /// Parse pose string "x y z roll pitch yaw" to [f64; 6]
+///
+/// Same rules as [`dendrite_core::hcdf::parse_pose_result`] (commas, `deg`
+/// suffixes), so the scene and the daemon agree on which poses are valid.
 pub fn parse_pose_string(s: &str) -> Option<[f64; 6]> {
-    let parts: Vec<f64> = s
-        .split_whitespace()
-        .filter_map(|p| p.parse().ok())
-        .collect();
-    if parts.len() >= 6 {
-        Some([parts[0], parts[1], parts[2], parts[3], parts[4], parts[5]])
-    } else {
-        None
-    }
+    dendrite_core::hcdf::parse_pose_result(s).ok().map(|p| p.to_array())
 }
@@ -0,0 +1,290 @@
+//! Heatmap coloring of devices by a metric
+//!
+//! Apps put the chosen metric's values by device id in `Heatmap` and map
+//! device ids to the entities whose meshes should be tinted in
+//! `HeatmapTargets` (usually the device's visuals). Every mesh below a target
+//! gets a copy of its material in the ramp color for the device's value, or
+//! gray if the device has no value. The original material goes back when the
+//! heatmap is cleared or the mesh is no longer below a target. Unlit
+//! materials (FOVs, axes, gizmos) keep their colors.
+//!
+//! A metric with a `solid` color paints every device that has a value in
+//! that one color instead, for highlights such as the viewer's Compare mode.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+/// Color of devices without a value for the metric
+pub const MISSING_COLOR: Color = Color::srgb(0.45, 0.45, 0.45);
+
+/// Ramp from low to high, evenly spaced
+const RAMP: [[f32; 3]; 5] = [
+    [0.15, 0.25, 0.85], // blue
+    [0.10, 0.75, 0.85], // cyan
+    [0.20, 0.80, 0.30], // green
+    [0.95, 0.80, 0.10], // yellow
+    [0.90, 0.15, 0.10], // red
+];
+
+/// What maps onto the ends of the color ramp
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum MetricRange {
+    /// Lowest and highest value present
+    #[default]
+    Observed,
+    /// Fixed bounds (e.g. 0-100 for a percentage); values outside are clamped
+    Fixed { min: f64, max: f64 },
+}
+
+/// The metric devices are colored by
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HeatmapMetric {
+    /// Shown on the legend (e.g. "Latency")
+    pub label: String,
+    pub unit: String,
+    pub range: MetricRange,
+    /// Values by device id
+    pub values: HashMap<String, f64>,
+    /// One color for every device with a value, instead of the ramp
+    pub solid: Option<Color>,
+}
+
+impl HeatmapMetric {
+    /// Values at the low and high end of the ramp (None if there are none)
+    pub fn bounds(&self) -> Option<(f64, f64)> {
+        metric_bounds(self.values.values().copied(), self.range)
+    }
+
+    /// Ramp color for a device, or `MISSING_COLOR`
+    pub fn color(&self, device_id: &str) -> Color {
+        let value = self.values.get(device_id).copied();
+        if let (Some(color), Some(_)) = (self.solid, value) {
+            return color;
+        }
+        match (value, self.bounds()) {
+            (Some(value), Some(bounds)) => normalize(value, bounds).map_or(MISSING_COLOR, ramp_color),
+            _ => MISSING_COLOR,
+        }
+    }
+}
+
+/// Devices colored by a metric; None leaves materials as they are
+#[derive(Debug, Clone, Default, PartialEq, Resource)]
+pub struct Heatmap(pub Option<HeatmapMetric>);
+
+/// Entities to tint by device id, for finding their meshes
+#[derive(Debug, Clone, Default, PartialEq, Resource)]
+pub struct HeatmapTargets(pub HashMap<String, Vec<Entity>>);
+
+/// A mesh showing a heatmap color instead of its own material
+#[derive(Component)]
+pub struct HeatmapTint {
+    original: Handle<StandardMaterial>,
+    color: Color,
+}
+
+/// Bounds of the ramp for a set of values
+///
+/// Non-finite values are ignored. A fixed range with `max <= min` has no
+/// bounds.
+pub fn metric_bounds(values: impl IntoIterator<Item = f64>, range: MetricRange) -> Option<(f64, f64)> {
+    match range {
+        MetricRange::Fixed { min, max } => (min.is_finite() && max.is_finite() && max > min).then_some((min, max)),
+        MetricRange::Observed => values
+            .into_iter()
+            .filter(|v| v.is_finite())
+            .fold(None, |bounds, v| match bounds {
+                None => Some((v, v)),
+                Some((min, max)) => Some((f64::min(min, v), f64::max(max, v))),
+            }),
+    }
+}
+
+/// Position of `value` between `bounds`, from 0 to 1
+///
+/// Values outside the bounds are clamped. When every value is the same the
+/// bounds are equal and everything lands mid-ramp. Non-finite values have
+/// no position.
+pub fn normalize(value: f64, (min, max): (f64, f64)) -> Option<f32> {
+    if !value.is_finite() {
+        return None;
+    }
+    if max <= min {
+        return Some(0.5);
+    }
+    Some(((value - min) / (max - min)).clamp(0.0, 1.0) as f32)
+}
+
+/// Normalize every device's value for the ramp
+///
+/// Devices with non-finite values are left out, as if they had none.
+pub fn normalize_metric(values: &HashMap<String, f64>, range: MetricRange) -> HashMap<String, f32> {
+    let Some(bounds) = metric_bounds(values.values().copied(), range) else {
+        return HashMap::new();
+    };
+    values
+        .iter()
+        .filter_map(|(id, value)| Some((id.clone(), normalize(*value, bounds)?)))
+        .collect()
+}
+
+/// Color at `t` (0 to 1) along the blue-to-red ramp
+pub fn ramp_color(t: f32) -> Color {
+    let scaled = t.clamp(0.0, 1.0) * (RAMP.len() - 1) as f32;
+    let i = (scaled.floor() as usize).min(RAMP.len() - 2);
+    let f = scaled - i as f32;
+    let [r0, g0, b0] = RAMP[i];
+    let [r1, g1, b1] = RAMP[i + 1];
+    Color::srgb(r0 + (r1 - r0) * f, g0 + (g1 - g0) * f, b0 + (b1 - b0) * f)
+}
+
+/// Plugin for heatmap tinting
+pub struct HeatmapPlugin;
+
+impl Plugin for HeatmapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Heatmap>()
+            .init_resource::<HeatmapTargets>()
+            .add_systems(Update, apply_heatmap);
+    }
+}
+
+/// Tint meshes below the targets and restore the ones that left
+///
+/// Runs every frame while a heatmap is shown, since models finish loading
+/// (and spawn their meshes) after the device entity exists.
+fn apply_heatmap(
+    mut commands: Commands,
+    heatmap: Res<Heatmap>,
+    targets: Res<HeatmapTargets>,
+    children_query: Query<&Children>,
+    mut mesh_query: Query<(Entity, &mut MeshMaterial3d<StandardMaterial>, Option<&mut HeatmapTint>)>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let changed = heatmap.is_changed() || targets.is_changed();
+    if heatmap.0.is_none() && !changed {
+        return;
+    }
+
+    // Color wanted for every entity below a target
+    let mut wanted: HashMap<Entity, Color> = HashMap::new();
+    if let Some(metric) = &heatmap.0 {
+        for (device_id, roots) in &targets.0 {
+            let color = metric.color(device_id);
+            let mut stack = roots.clone();
+            while let Some(entity) = stack.pop() {
+                wanted.insert(entity, color);
+                if let Ok(children) = children_query.get(entity) {
+                    stack.extend_from_slice(children);
+                }
+            }
+        }
+    }
+
+    for (&entity, &color) in &wanted {
+        let Ok((_, mut material, tint)) = mesh_query.get_mut(entity) else {
+            continue;
+        };
+        match tint {
+            Some(mut tint) => {
+                if tint.color != color {
+                    if let Some(tinted) = materials.get_mut(&material.0) {
+                        tinted.base_color = color.with_alpha(tinted.base_color.alpha());
+                    }
+                    tint.color = color;
+                }
+            }
+            None => {
+                let Some(original) = materials.get(&material.0) else {
+                    continue;
+                };
+                if original.unlit {
+                    continue;
+                }
+                let mut tinted = original.clone();
+                tinted.base_color = color.with_alpha(original.base_color.alpha());
+                tinted.base_color_texture = None;
+                let original = std::mem::replace(&mut material.0, materials.add(tinted));
+                commands.entity(entity).insert(HeatmapTint { original, color });
+            }
+        }
+    }
+
+    // Tints whose device is gone, or every tint once the heatmap is cleared
+    if changed {
+        for (entity, mut material, tint) in mesh_query.iter_mut() {
+            let Some(tint) = tint else {
+                continue;
+            };
+            if !wanted.contains_key(&entity) {
+                material.0 = tint.original.clone();
+                commands.entity(entity).remove::<HeatmapTint>();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(pairs: &[(&str, f64)]) -> HashMap<String, f64> {
+        pairs.iter().map(|(id, v)| (id.to_string(), *v)).collect()
+    }
+
+    #[test]
+    fn test_normalize_observed_range() {
+        let latency = values(&[("a", 2.0), ("b", 12.0), ("c", 7.0), ("d", f64::NAN)]);
+        let normalized = normalize_metric(&latency, MetricRange::Observed);
+        assert_eq!(normalized["a"], 0.0);
+        assert_eq!(normalized["b"], 1.0);
+        assert_eq!(normalized["c"], 0.5);
+        // A NaN is missing, and doesn't widen the range
+        assert!(!normalized.contains_key("d"));
+
+        // One value, or all the same: mid-ramp
+        let single = normalize_metric(&values(&[("a", 3.0), ("b", 3.0)]), MetricRange::Observed);
+        assert_eq!(single["a"], 0.5);
+        assert!(normalize_metric(&HashMap::new(), MetricRange::Observed).is_empty());
+    }
+
+    #[test]
+    fn test_normalize_fixed_range() {
+        let uptime = values(&[("a", 25.0), ("b", 100.0), ("c", 140.0), ("d", -5.0)]);
+        let normalized = normalize_metric(&uptime, MetricRange::Fixed { min: 0.0, max: 100.0 });
+        assert_eq!(normalized["a"], 0.25);
+        assert_eq!(normalized["b"], 1.0);
+        // Outside the range clamps to the ends
+        assert_eq!(normalized["c"], 1.0);
+        assert_eq!(normalized["d"], 0.0);
+
+        // An empty fixed range has no bounds
+        assert!(normalize_metric(&uptime, MetricRange::Fixed { min: 5.0, max: 5.0 }).is_empty());
+    }
+
+    #[test]
+    fn test_colors() {
+        assert_eq!(ramp_color(0.0), Color::srgb(0.15, 0.25, 0.85));
+        let red = ramp_color(1.0).to_srgba();
+        assert!((red.red - 0.90).abs() < 1e-6 && (red.green - 0.15).abs() < 1e-6 && (red.blue - 0.10).abs() < 1e-6);
+        assert_eq!(ramp_color(-1.0), ramp_color(0.0));
+        assert_eq!(ramp_color(0.5), Color::srgb(0.20, 0.80, 0.30));
+
+        let metric = HeatmapMetric {
+            label: "Latency".to_string(),
+            unit: "ms".to_string(),
+            range: MetricRange::Observed,
+            values: values(&[("a", 1.0), ("b", 9.0)]),
+            solid: None,
+        };
+        assert_eq!(metric.bounds(), Some((1.0, 9.0)));
+        assert_eq!(metric.color("b"), ramp_color(1.0));
+        assert_eq!(metric.color("missing"), MISSING_COLOR);
+
+        let orange = Color::srgb(1.0, 0.5, 0.0);
+        let solid = HeatmapMetric { solid: Some(orange), ..metric };
+        assert_eq!(solid.color("a"), orange);
+        assert_eq!(solid.color("missing"), MISSING_COLOR);
+    }
+}
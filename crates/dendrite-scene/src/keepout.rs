@@ -0,0 +1,324 @@
+//! Keep-out volume rendering
+//!
+//! Draws HCDF `<keepout>` volumes as red translucent shapes in the world
+//! frame and outlines the bounding box of every device intruding into one.
+//! Apps fill `KeepoutScene` (from the daemon, or from a loaded file) and
+//! map device ids to their entities in `KeepoutTargets`; outlines follow
+//! those entities so they stay on the device while it is dragged.
+
+use std::collections::HashMap;
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::prelude::*;
+use bevy::render::alpha::AlphaMode;
+use dendrite_core::{KeepoutShape, Pose};
+use serde::Deserialize;
+
+/// Radius of the outline edges (meters)
+const OUTLINE_THICKNESS: f32 = 0.0008;
+
+const VOLUME_COLOR: Color = Color::srgba(0.9, 0.1, 0.1, 0.25);
+const OUTLINE_COLOR: Color = Color::srgb(1.0, 0.15, 0.15);
+
+/// A keep-out volume placed in the world frame
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct KeepoutVolume {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// [x, y, z, roll, pitch, yaw] (meters, radians)
+    pub pose: [f64; 6],
+    pub shape: KeepoutShape,
+}
+
+/// A device intruding into a keep-out volume
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct KeepoutIntrusion {
+    pub device: String,
+    #[serde(default)]
+    pub hwid: Option<String>,
+    pub keepout: String,
+    /// Penetration depth in meters
+    pub depth: f64,
+    /// Bounding box pose relative to the device origin
+    #[serde(default)]
+    pub bbox_pose: Option<[f64; 6]>,
+    #[serde(default)]
+    pub bbox_size: Option<[f64; 3]>,
+}
+
+/// Keep-out volumes and current violations to draw
+#[derive(Debug, Clone, Default, PartialEq, Resource, Deserialize)]
+pub struct KeepoutScene {
+    #[serde(default)]
+    pub volumes: Vec<KeepoutVolume>,
+    #[serde(default)]
+    pub violations: Vec<KeepoutIntrusion>,
+}
+
+impl KeepoutScene {
+    /// Number of distinct devices intruding into any volume
+    pub fn violating_devices(&self) -> usize {
+        let mut devices: Vec<(&str, Option<&str>)> = self
+            .violations
+            .iter()
+            .map(|v| (v.device.as_str(), v.hwid.as_deref()))
+            .collect();
+        devices.sort();
+        devices.dedup();
+        devices.len()
+    }
+}
+
+/// Whether keep-out volumes are drawn; outlines are always drawn
+#[derive(Debug, Clone, Resource)]
+pub struct ShowKeepouts(pub bool);
+
+impl Default for ShowKeepouts {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+/// Device entities by hardware id, for placing outlines
+#[derive(Debug, Clone, Default, PartialEq, Resource)]
+pub struct KeepoutTargets(pub HashMap<String, Entity>);
+
+/// Marker for a keep-out volume mesh
+#[derive(Component)]
+pub struct KeepoutVolumeEntity;
+
+/// Root of one violating device's outline, following the device entity
+#[derive(Component)]
+pub struct KeepoutOutline {
+    pub hwid: String,
+}
+
+/// Convert an HCDF pose array to a transform
+pub fn pose_transform(pose: [f64; 6]) -> Transform {
+    Transform::from_xyz(pose[0] as f32, pose[1] as f32, pose[2] as f32)
+        .with_rotation(pose_rotation([pose[3], pose[4], pose[5]], None))
+}
+
+/// Rotation of an HCDF orientation: the document's quaternion [w, x, y, z]
+/// if it gave one, else roll/pitch/yaw, converted by [`Pose::orientation`]
+/// so the scene rotates exactly as dendrite-core does
+pub fn pose_rotation(rpy: [f64; 3], quaternion: Option<[f64; 4]>) -> Quat {
+    let pose = Pose { roll: rpy[0], pitch: rpy[1], yaw: rpy[2], quaternion, ..Pose::default() };
+    let [w, x, y, z] = pose.orientation();
+    Quat::from_xyzw(x as f32, y as f32, z as f32, w as f32)
+}
+
+/// Transforms of the 12 edges of a box of `size` centered on `frame`, as
+/// (transform, edge length) pairs for Y-aligned cylinders
+pub fn box_edge_transforms(frame: Transform, size: Vec3) -> Vec<(Transform, f32)> {
+    let half = size / 2.0;
+    let mut edges = Vec::with_capacity(12);
+    for (axis, length) in [(Vec3::X, size.x), (Vec3::Y, size.y), (Vec3::Z, size.z)] {
+        // The two axes the edge is offset along
+        let (u, v) = (axis.yzx(), axis.zxy());
+        for (su, sv) in [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)] {
+            let offset = u * half * su + v * half * sv;
+            let transform = Transform::from_translation(frame.translation + frame.rotation * offset)
+                .with_rotation(frame.rotation * crate::axes::cylinder_rotation(axis));
+            edges.push((transform, length));
+        }
+    }
+    edges
+}
+
+/// Plugin for keep-out volumes and violation outlines
+pub struct KeepoutPlugin;
+
+impl Plugin for KeepoutPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<KeepoutScene>()
+            .init_resource::<ShowKeepouts>()
+            .init_resource::<KeepoutTargets>()
+            .add_systems(Update, (
+                sync_keepout_volumes,
+                update_keepout_visibility.after(sync_keepout_volumes),
+                sync_keepout_outlines,
+                follow_keepout_targets.after(sync_keepout_outlines),
+            ));
+    }
+}
+
+/// Respawn the volume meshes when the volumes change
+fn sync_keepout_volumes(
+    mut commands: Commands,
+    keepouts: Res<KeepoutScene>,
+    show: Res<ShowKeepouts>,
+    volume_query: Query<Entity, With<KeepoutVolumeEntity>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    if !keepouts.is_changed() {
+        return;
+    }
+
+    for entity in volume_query.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let material = materials.add(StandardMaterial {
+        base_color: VOLUME_COLOR,
+        alpha_mode: AlphaMode::Blend,
+        unlit: true,
+        double_sided: true,
+        cull_mode: None,
+        ..default()
+    });
+    let visibility = if show.0 { Visibility::Visible } else { Visibility::Hidden };
+
+    for volume in &keepouts.volumes {
+        let mut transform = pose_transform(volume.pose);
+        let mesh = match volume.shape {
+            KeepoutShape::Box { size } => meshes.add(Cuboid::new(size[0] as f32, size[1] as f32, size[2] as f32)),
+            KeepoutShape::Cylinder { radius, length } => {
+                // HCDF cylinders run along local Z, Bevy's along Y
+                transform.rotation *= Quat::from_rotation_x(FRAC_PI_2);
+                meshes.add(Cylinder::new(radius as f32, length as f32))
+            }
+            KeepoutShape::Sphere { radius } => meshes.add(Sphere::new(radius as f32)),
+        };
+        commands.spawn((
+            Mesh3d(mesh),
+            MeshMaterial3d(material.clone()),
+            transform,
+            visibility,
+            KeepoutVolumeEntity,
+            Name::new(format!("keepout:{}", volume.name)),
+        ));
+    }
+}
+
+fn update_keepout_visibility(
+    show: Res<ShowKeepouts>,
+    mut volume_query: Query<&mut Visibility, With<KeepoutVolumeEntity>>,
+) {
+    if !show.is_changed() {
+        return;
+    }
+    for mut visibility in volume_query.iter_mut() {
+        *visibility = if show.0 { Visibility::Visible } else { Visibility::Hidden };
+    }
+}
+
+/// Respawn outlines when the violations change
+fn sync_keepout_outlines(
+    mut commands: Commands,
+    keepouts: Res<KeepoutScene>,
+    outline_query: Query<Entity, With<KeepoutOutline>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    if !keepouts.is_changed() {
+        return;
+    }
+
+    for entity in outline_query.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let material = materials.add(StandardMaterial {
+        base_color: OUTLINE_COLOR,
+        unlit: true,
+        ..default()
+    });
+
+    let mut outlined: Vec<&str> = Vec::new();
+    for violation in &keepouts.violations {
+        // One outline per device, however many volumes it intrudes into
+        let (Some(hwid), Some(size)) = (violation.hwid.as_deref(), violation.bbox_size) else {
+            continue;
+        };
+        if outlined.contains(&hwid) {
+            continue;
+        }
+        outlined.push(hwid);
+
+        let frame = pose_transform(violation.bbox_pose.unwrap_or_default());
+        let size = Vec3::new(size[0] as f32, size[1] as f32, size[2] as f32);
+        // Root starts hidden and is placed by follow_keepout_targets
+        commands
+            .spawn((Transform::default(), Visibility::Hidden, KeepoutOutline { hwid: hwid.to_string() }))
+            .with_children(|parent| {
+                for (transform, length) in box_edge_transforms(frame, size) {
+                    parent.spawn((
+                        Mesh3d(meshes.add(Cylinder::new(OUTLINE_THICKNESS, length))),
+                        MeshMaterial3d(material.clone()),
+                        transform,
+                    ));
+                }
+            });
+    }
+}
+
+/// Keep outlines on their device entities
+fn follow_keepout_targets(
+    targets: Res<KeepoutTargets>,
+    target_query: Query<&GlobalTransform, Without<KeepoutOutline>>,
+    mut outline_query: Query<(&KeepoutOutline, &mut Transform, &mut Visibility)>,
+) {
+    for (outline, mut transform, mut visibility) in outline_query.iter_mut() {
+        let Some(target) = targets.0.get(&outline.hwid).and_then(|e| target_query.get(*e).ok()) else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        let (_, rotation, translation) = target.to_scale_rotation_translation();
+        transform.translation = translation;
+        transform.rotation = rotation;
+        *visibility = Visibility::Visible;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keepout_scene_parses_daemon_payload() {
+        let json = r#"{
+            "volumes": [
+                {"name": "prop_fl", "pose": [0.2, 0.2, 0.05, 0, 0, 0],
+                 "shape": {"type": "cylinder", "radius": 0.13, "length": 0.02}}
+            ],
+            "violations": [
+                {"device": "gps", "hwid": "0x1", "keepout": "prop_fl", "depth": 0.01,
+                 "bbox_pose": [0, 0, 0, 0, 0, 0], "bbox_size": [0.04, 0.04, 0.02]},
+                {"device": "gps", "hwid": "0x1", "keepout": "gear", "depth": 0.002}
+            ]
+        }"#;
+        let scene: KeepoutScene = serde_json::from_str(json).unwrap();
+        assert_eq!(scene.volumes[0].shape, KeepoutShape::Cylinder { radius: 0.13, length: 0.02 });
+        assert_eq!(scene.violations.len(), 2);
+        assert_eq!(scene.violating_devices(), 1);
+    }
+
+    #[test]
+    fn test_pose_rotation_matches_euler_and_keeps_quaternion() {
+        let rpy = [0.3, -0.4, 1.2];
+        let euler = Quat::from_euler(EulerRot::ZYX, 1.2, -0.4, 0.3);
+        assert!(pose_rotation(rpy, None).angle_between(euler) < 1e-6);
+
+        // Pitched straight up, where roll/pitch/yaw can't say which way round
+        let half = std::f64::consts::FRAC_1_SQRT_2;
+        let up = pose_rotation([0.0, 0.0, 0.0], Some([half, 0.0, half, 0.0]));
+        assert!(up.angle_between(Quat::from_rotation_y(FRAC_PI_2)) < 1e-6);
+    }
+
+    #[test]
+    fn test_box_edges_cover_every_corner() {
+        let size = Vec3::new(0.04, 0.02, 0.01);
+        let edges = box_edge_transforms(Transform::IDENTITY, size);
+        assert_eq!(edges.len(), 12);
+        for (transform, length) in edges {
+            // Each edge's ends land on box corners
+            let direction = transform.rotation * Vec3::Y * (length / 2.0);
+            for end in [transform.translation + direction, transform.translation - direction] {
+                assert!((end.abs() - size / 2.0).abs().max_element() < 1e-6, "end {end:?}");
+            }
+        }
+    }
+}
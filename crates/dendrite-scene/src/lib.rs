@@ -4,9 +4,18 @@
 //! both the daemon-connected viewer (dendrite-web) and the standalone
 //! HCDF viewer (dendrite-viewer).
 
+pub mod axes;
 pub mod camera;
+pub mod camera_views;
+pub mod constraints;
+pub mod edit_history;
+pub mod frame_overlay;
+pub mod graph_layout;
 pub mod hcdf_convert;
+pub mod heatmap;
+pub mod keepout;
 pub mod models;
+pub mod occlusion;
 pub mod scene;
 pub mod types;
 pub mod ui;
@@ -20,7 +29,10 @@ impl Plugin for DendriteScenePlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(camera::CameraPlugin)
             .add_plugins(scene::SceneSetupPlugin)
-            .add_plugins(models::ModelsPlugin);
+            .add_plugins(models::ModelsPlugin)
+            .add_plugins(frame_overlay::FrameOverlayPlugin)
+            .add_plugins(keepout::KeepoutPlugin)
+            .add_plugins(heatmap::HeatmapPlugin);
     }
 }
 
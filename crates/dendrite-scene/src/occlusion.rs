@@ -0,0 +1,134 @@
+//! Sensor FOV occlusion helpers
+//!
+//! Bridges the ray casting in `dendrite_core::occlusion` and Bevy: world
+//! transforms become core poses, rendered meshes become occluder triangles,
+//! and a report becomes a mesh of red quads over the blocked cells of the
+//! FOV's far plane, to be placed at the FOV's transform.
+
+use bevy::asset::RenderAssetUsages;
+use bevy::mesh::{Indices, VertexAttributeValues};
+use bevy::prelude::*;
+use bevy::render::render_resource::PrimitiveTopology;
+use dendrite_core::{FovShape, OcclusionReport, Pose};
+
+/// Convert a transform to an HCDF pose (inverse of `keepout::pose_transform`)
+pub fn pose_from_transform(transform: &Transform) -> Pose {
+    let (yaw, pitch, roll) = transform.rotation.to_euler(EulerRot::ZYX);
+    Pose {
+        x: transform.translation.x as f64,
+        y: transform.translation.y as f64,
+        z: transform.translation.z as f64,
+        roll: roll as f64,
+        pitch: pitch as f64,
+        yaw: yaw as f64,
+        quaternion: None,
+        degrees: false,
+    }
+}
+
+/// World-space triangles of a triangle-list mesh
+///
+/// Other topologies and meshes without float positions yield nothing.
+pub fn mesh_triangles(mesh: &Mesh, transform: &GlobalTransform) -> Vec<[[f64; 3]; 3]> {
+    if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+        return Vec::new();
+    }
+    let Some(VertexAttributeValues::Float32x3(positions)) = mesh.attribute(Mesh::ATTRIBUTE_POSITION) else {
+        return Vec::new();
+    };
+    let world: Vec<[f64; 3]> = positions
+        .iter()
+        .map(|p| {
+            let v = transform.transform_point(Vec3::from(*p));
+            [v.x as f64, v.y as f64, v.z as f64]
+        })
+        .collect();
+
+    let indices: Vec<usize> = match mesh.indices() {
+        Some(indices) => indices.iter().collect(),
+        None => (0..world.len()).collect(),
+    };
+    indices
+        .chunks_exact(3)
+        .filter_map(|tri| Some([*world.get(tri[0])?, *world.get(tri[1])?, *world.get(tri[2])?]))
+        .collect()
+}
+
+/// Quads over the far-plane cells of blocked rays, in the FOV frame
+///
+/// Returns None if nothing is blocked.
+pub fn blocked_cells_mesh(shape: &FovShape, report: &OcclusionReport) -> Option<Mesh> {
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+    for sample in report.samples.iter().filter(|s| s.hit.is_some()) {
+        let base = positions.len() as u32;
+        for corner in shape.cell_corners(sample.uv, report.resolution) {
+            positions.push(corner.map(|v| v as f32));
+        }
+        indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+    if positions.is_empty() {
+        return None;
+    }
+    // Facing back toward the sensor
+    let normals = vec![[0.0, 0.0, -1.0]; positions.len()];
+    Some(
+        Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+            .with_inserted_indices(Indices::U32(indices)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keepout::pose_transform;
+    use dendrite_core::{check_occlusion, Occluder};
+
+    #[test]
+    fn test_pose_round_trip() {
+        let pose = [0.1, -0.2, 0.3, 0.4, -0.5, 1.2];
+        let back = pose_from_transform(&pose_transform(pose)).to_array();
+        for (a, b) in pose.iter().zip(back) {
+            assert!((a - b).abs() < 1e-5, "{pose:?} != {back:?}");
+        }
+    }
+
+    #[test]
+    fn test_cuboid_triangles_in_world_space() {
+        let mesh = Mesh::from(Cuboid::new(2.0, 2.0, 2.0));
+        let transform = GlobalTransform::from(Transform::from_xyz(0.0, 0.0, 5.0));
+        let triangles = mesh_triangles(&mesh, &transform);
+        assert_eq!(triangles.len(), 12);
+        for vertex in triangles.iter().flatten() {
+            assert!((vertex[2] - 5.0).abs() <= 1.0 + 1e-6 && vertex[0].abs() <= 1.0 + 1e-6);
+        }
+
+        // A narrow FOV at the origin looking down +Z sees only the cube
+        let occluder = Occluder::from_triangles("cube", triangles).unwrap();
+        let shape = FovShape::Pyramid { near: 0.0, far: 10.0, hfov: 0.1, vfov: 0.1 };
+        let report = check_occlusion(&Pose::default(), &shape, &[occluder], 8);
+        assert_eq!(report.blocked(), 64);
+        assert!((report.samples[0].hit.as_ref().unwrap().distance - 4.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_blocked_cells_mesh_has_a_quad_per_blocked_ray() {
+        let shape = FovShape::Pyramid { near: 0.0, far: 1.0, hfov: 1.0, vfov: 1.0 };
+        let clear = check_occlusion(&Pose::default(), &shape, &[], 4);
+        assert!(blocked_cells_mesh(&shape, &clear).is_none());
+
+        let wall = Occluder {
+            id: "wall".to_string(),
+            bbox_pose: Pose { x: 0.5, z: 0.5, ..Default::default() },
+            bbox_size: [1.0, 2.0, 0.1],
+            triangles: Vec::new(),
+        };
+        let report = check_occlusion(&Pose::default(), &shape, &[wall], 4);
+        assert_eq!(report.blocked(), 8);
+        let mesh = blocked_cells_mesh(&shape, &report).unwrap();
+        assert_eq!(mesh.count_vertices(), 32);
+        assert_eq!(mesh.indices().unwrap().len(), 48);
+    }
+}
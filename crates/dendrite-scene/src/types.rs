@@ -1,6 +1,7 @@
 //! Shared types for device data, visualization settings, and UI state
 
 use bevy::prelude::*;
+use dendrite_core::FrameNode;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -10,6 +11,9 @@ pub struct VisualData {
     pub name: String,
     /// Toggle group name for visibility control (e.g., "case")
     pub toggle: Option<String>,
+    /// Whether the toggle group starts hidden when the device is first loaded
+    #[serde(default)]
+    pub default_hidden: bool,
     /// Pose offset: (x, y, z, roll, pitch, yaw) in meters/radians
     pub pose: Option<[f64; 6]>,
     /// Model file path
@@ -23,8 +27,26 @@ pub struct VisualData {
 pub struct FrameData {
     pub name: String,
     pub description: Option<String>,
-    /// Pose offset: (x, y, z, roll, pitch, yaw) in meters/radians
+    /// Pose offset: (x, y, z, roll, pitch, yaw) in meters/radians, relative
+    /// to `parent` if set, otherwise to the device origin
     pub pose: Option<[f64; 6]>,
+    /// Frame this one is positioned relative to
+    #[serde(default)]
+    pub parent: Option<String>,
+}
+
+impl FrameNode for FrameData {
+    fn frame_name(&self) -> &str {
+        &self.name
+    }
+
+    fn parent_frame(&self) -> Option<&str> {
+        self.parent.as_deref()
+    }
+
+    fn local_pose(&self) -> dendrite_core::Pose {
+        self.pose.map(dendrite_core::Pose::from_array).unwrap_or_default()
+    }
 }
 
 /// Axis alignment for sensor driver transforms
@@ -89,6 +111,12 @@ pub struct SensorData {
     pub sensor_type: String,
     /// Driver name (icm45686, bmm350, etc.)
     pub driver: Option<String>,
+    /// Human-readable driver name from the driver registry (e.g., "Bosch BMI088 (IMU)")
+    #[serde(default)]
+    pub driver_label: Option<String>,
+    /// Driver datasheet URL from the driver registry
+    #[serde(default)]
+    pub datasheet: Option<String>,
     /// Pose offset: (x, y, z, roll, pitch, yaw) in meters/radians
     pub pose: Option<[f64; 6]>,
     /// Axis alignment for driver transforms
@@ -3,6 +3,7 @@
 use bevy::prelude::*;
 use bevy_egui::egui;
 
+use crate::heatmap::{ramp_color, HeatmapMetric, MISSING_COLOR};
 use crate::types::*;
 
 /// Render device details panel (shared between apps)
@@ -107,8 +108,10 @@ pub fn render_frame_list(
     }
 
     ui.collapsing("Frames", |ui| {
-        for frame in &device.frames {
+        for (index, depth) in dendrite_core::frame_tree_order(&device.frames) {
+            let frame = &device.frames[index];
             ui.horizontal(|ui| {
+                ui.add_space(depth as f32 * 12.0);
                 ui.label(&frame.name);
                 if let Some(desc) = &frame.description {
                     ui.label(
@@ -121,3 +124,65 @@ pub fn render_frame_list(
         }
     });
 }
+
+/// Color ramp legend for a heatmap, drawn in the bottom-left corner of the
+/// space the side panels leave free
+pub fn render_heatmap_legend(ctx: &egui::Context, metric: &HeatmapMetric, ui_scale: f32) {
+    let to_egui = |color: Color| {
+        let srgba = color.to_srgba();
+        egui::Color32::from_rgb((srgba.red * 255.0) as u8, (srgba.green * 255.0) as u8, (srgba.blue * 255.0) as u8)
+    };
+    let format_value = |value: f64| {
+        if value.abs() >= 100.0 {
+            format!("{:.0}", value)
+        } else {
+            format!("{:.1}", value)
+        }
+    };
+
+    egui::Area::new(egui::Id::new("heatmap_legend"))
+        .pivot(egui::Align2::LEFT_BOTTOM)
+        .fixed_pos(ctx.available_rect().left_bottom() + egui::vec2(10.0, -10.0))
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                let title = if metric.unit.is_empty() {
+                    metric.label.clone()
+                } else {
+                    format!("{} ({})", metric.label, metric.unit)
+                };
+                ui.label(egui::RichText::new(title).size(13.0 * ui_scale).strong());
+
+                // Gradient bar in thin slices
+                let size = egui::vec2(160.0 * ui_scale, 12.0 * ui_scale);
+                let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+                const SLICES: usize = 32;
+                let width = rect.width() / SLICES as f32;
+                for i in 0..SLICES {
+                    let t = (i as f32 + 0.5) / SLICES as f32;
+                    let min = rect.left_top() + egui::vec2(i as f32 * width, 0.0);
+                    let slice = egui::Rect::from_min_size(min, egui::vec2(width + 0.5, rect.height()));
+                    ui.painter().rect_filled(slice, 0.0, to_egui(ramp_color(t)));
+                }
+
+                // Values at the ends of the bar
+                match metric.bounds() {
+                    Some((min, max)) => {
+                        let font = egui::FontId::proportional(11.0 * ui_scale);
+                        let (row, _) = ui.allocate_exact_size(egui::vec2(size.x, 14.0 * ui_scale), egui::Sense::hover());
+                        let color = ui.visuals().text_color();
+                        let painter = ui.painter();
+                        painter.text(row.left_top(), egui::Align2::LEFT_TOP, format_value(min), font.clone(), color);
+                        painter.text(row.right_top(), egui::Align2::RIGHT_TOP, format_value(max), font, color);
+                    }
+                    None => {
+                        ui.label(egui::RichText::new("No values yet").small().color(egui::Color32::GRAY));
+                    }
+                }
+                ui.horizontal(|ui| {
+                    let (swatch, _) = ui.allocate_exact_size(egui::vec2(10.0, 10.0) * ui_scale, egui::Sense::hover());
+                    ui.painter().rect_filled(swatch, 2.0, to_egui(MISSING_COLOR));
+                    ui.label(egui::RichText::new("No data").small());
+                });
+            });
+        });
+}
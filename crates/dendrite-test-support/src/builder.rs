@@ -0,0 +1,341 @@
+//! Builders for one-off HCDF fixtures
+//!
+//! For tests that need a particular device rather than a sized document:
+//!
+//! ```
+//! use dendrite_test_support::{FixtureBuilder, SensorCategory, SensorFixture};
+//!
+//! let xml = FixtureBuilder::device()
+//!     .with_name("flow")
+//!     .with_sensor(SensorFixture::new("imu", SensorCategory::Inertial, "accel_gyro").with_driver("icm45686"))
+//!     .hcdf();
+//! assert!(xml.contains(r#"<inertial type="accel_gyro">"#));
+//! ```
+//!
+//! Output is XML text, written in the element order the HCDF exporter uses,
+//! so a parsed-and-exported builder fixture compares equal to its input.
+
+use std::fmt::Write;
+
+/// Top-level sensor element categories
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SensorCategory {
+    Inertial,
+    Em,
+    Optical,
+    Rf,
+    Chemical,
+    Force,
+}
+
+impl SensorCategory {
+    pub const ALL: [SensorCategory; 6] = [
+        SensorCategory::Inertial,
+        SensorCategory::Em,
+        SensorCategory::Optical,
+        SensorCategory::Rf,
+        SensorCategory::Chemical,
+        SensorCategory::Force,
+    ];
+
+    /// Element name inside `<sensor>`
+    pub fn element(&self) -> &'static str {
+        match self {
+            SensorCategory::Inertial => "inertial",
+            SensorCategory::Em => "em",
+            SensorCategory::Optical => "optical",
+            SensorCategory::Rf => "rf",
+            SensorCategory::Chemical => "chemical",
+            SensorCategory::Force => "force",
+        }
+    }
+}
+
+/// A `<sensor>` with one category element
+#[derive(Debug, Clone)]
+pub struct SensorFixture {
+    name: String,
+    category: SensorCategory,
+    sensor_type: String,
+    pose: Option<String>,
+    driver: Option<String>,
+    fovs: Vec<(String, f64, f64)>,
+}
+
+impl SensorFixture {
+    pub fn new(name: &str, category: SensorCategory, sensor_type: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            category,
+            sensor_type: sensor_type.to_string(),
+            pose: None,
+            driver: None,
+            fovs: Vec::new(),
+        }
+    }
+
+    pub fn with_driver(mut self, driver: &str) -> Self {
+        self.driver = Some(driver.to_string());
+        self
+    }
+
+    /// Pose as written in HCDF, e.g. `"0 0 0.01 0 0 0"`
+    pub fn with_pose(mut self, pose: &str) -> Self {
+        self.pose = Some(pose.to_string());
+        self
+    }
+
+    /// Add a named FOV: a pyramidal frustum with the given angles, in radians
+    pub fn with_fov(mut self, name: &str, hfov: f64, vfov: f64) -> Self {
+        self.fovs.push((name.to_string(), hfov, vfov));
+        self
+    }
+
+    fn write(&self, xml: &mut String) {
+        let element = self.category.element();
+        let _ = writeln!(xml, r#"    <sensor name="{}">"#, self.name);
+        let _ = writeln!(xml, r#"      <{} type="{}">"#, element, self.sensor_type);
+        if let Some(pose) = &self.pose {
+            let _ = writeln!(xml, "        <pose>{}</pose>", pose);
+        }
+        if let Some(driver) = &self.driver {
+            let _ = writeln!(xml, r#"        <driver name="{}"/>"#, driver);
+        }
+        for (name, hfov, vfov) in &self.fovs {
+            let _ = writeln!(
+                xml,
+                r##"        <fov name="{name}" color="#88ff88"><geometry><pyramidal_frustum><near>0.05</near><far>10</far><hfov>{hfov}</hfov><vfov>{vfov}</vfov></pyramidal_frustum></geometry></fov>"##
+            );
+        }
+        let _ = writeln!(xml, "      </{}>", element);
+        xml.push_str("    </sensor>\n");
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Mcu,
+    Comp,
+}
+
+/// A port in either schema variant
+#[derive(Debug, Clone)]
+struct PortFixture {
+    name: String,
+    port_type: String,
+    legacy: bool,
+}
+
+/// Builds a single `<mcu>` or `<comp>` element
+#[derive(Debug, Clone)]
+pub struct FixtureBuilder {
+    kind: Kind,
+    name: String,
+    hwid: Option<String>,
+    role: Option<String>,
+    pose_cg: Option<String>,
+    board: Option<String>,
+    ip: Option<String>,
+    visuals: Vec<(String, String)>,
+    frames: Vec<(String, String)>,
+    ports: Vec<PortFixture>,
+    antennas: Vec<(String, String)>,
+    sensors: Vec<SensorFixture>,
+}
+
+impl FixtureBuilder {
+    /// A `<comp>`: the element devices, sensor boards and fragments use
+    pub fn device() -> Self {
+        Self::new(Kind::Comp, "device")
+    }
+
+    /// An `<mcu>`
+    pub fn mcu() -> Self {
+        Self::new(Kind::Mcu, "mcu")
+    }
+
+    fn new(kind: Kind, name: &str) -> Self {
+        Self {
+            kind,
+            name: name.to_string(),
+            hwid: None,
+            role: None,
+            pose_cg: None,
+            board: None,
+            ip: None,
+            visuals: Vec::new(),
+            frames: Vec::new(),
+            ports: Vec::new(),
+            antennas: Vec::new(),
+            sensors: Vec::new(),
+        }
+    }
+
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    pub fn with_hwid(mut self, hwid: &str) -> Self {
+        self.hwid = Some(hwid.to_string());
+        self
+    }
+
+    /// Panics on an MCU, which has no role
+    pub fn with_role(mut self, role: &str) -> Self {
+        assert_eq!(self.kind, Kind::Comp, "only comps have a role");
+        self.role = Some(role.to_string());
+        self
+    }
+
+    pub fn with_board(mut self, board: &str) -> Self {
+        self.board = Some(board.to_string());
+        self
+    }
+
+    /// Pose (`pose_cg`) as written in HCDF, e.g. `"0.1 0 0.05 0 0 1.57"`
+    pub fn with_pose(mut self, pose: &str) -> Self {
+        self.pose_cg = Some(pose.to_string());
+        self
+    }
+
+    /// Discovered IP address
+    pub fn with_ip(mut self, ip: &str) -> Self {
+        self.ip = Some(ip.to_string());
+        self
+    }
+
+    pub fn with_visual(mut self, name: &str, href: &str) -> Self {
+        self.visuals.push((name.to_string(), href.to_string()));
+        self
+    }
+
+    pub fn with_frame(mut self, name: &str, pose: &str) -> Self {
+        self.frames.push((name.to_string(), pose.to_string()));
+        self
+    }
+
+    /// Add a port in the current schema, with a `fallback_visual`
+    pub fn with_port(mut self, name: &str, port_type: &str) -> Self {
+        self.ports.push(PortFixture { name: name.to_string(), port_type: port_type.to_string(), legacy: false });
+        self
+    }
+
+    /// Add a port in the legacy schema, with pose and geometry on the port
+    pub fn with_legacy_port(mut self, name: &str, port_type: &str) -> Self {
+        self.ports.push(PortFixture { name: name.to_string(), port_type: port_type.to_string(), legacy: true });
+        self
+    }
+
+    /// Panics on an MCU, which has no antennas
+    pub fn with_antenna(mut self, name: &str, antenna_type: &str) -> Self {
+        assert_eq!(self.kind, Kind::Comp, "only comps have antennas");
+        self.antennas.push((name.to_string(), antenna_type.to_string()));
+        self
+    }
+
+    /// Panics on an MCU, which has no sensors
+    pub fn with_sensor(mut self, sensor: SensorFixture) -> Self {
+        assert_eq!(self.kind, Kind::Comp, "only comps have sensors");
+        self.sensors.push(sensor);
+        self
+    }
+
+    /// The element on its own
+    pub fn element(&self) -> String {
+        let tag = match self.kind {
+            Kind::Mcu => "mcu",
+            Kind::Comp => "comp",
+        };
+        let mut xml = format!(r#"  <{} name="{}""#, tag, self.name);
+        if let Some(role) = &self.role {
+            let _ = write!(xml, r#" role="{}""#, role);
+        }
+        if let Some(hwid) = &self.hwid {
+            let _ = write!(xml, r#" hwid="{}""#, hwid);
+        }
+        xml.push_str(">\n");
+        if let Some(pose) = &self.pose_cg {
+            let _ = writeln!(xml, "    <pose_cg>{}</pose_cg>", pose);
+        }
+        if let Some(board) = &self.board {
+            let _ = writeln!(xml, "    <board>{}</board>", board);
+        }
+        if let Some(ip) = &self.ip {
+            let _ = writeln!(xml, "    <discovered><ip>{}</ip></discovered>", ip);
+        }
+        for (name, href) in &self.visuals {
+            let _ = writeln!(xml, r#"    <visual name="{}"><model href="{}"/></visual>"#, name, href);
+        }
+        for (name, pose) in &self.frames {
+            let _ = writeln!(xml, r#"    <frame name="{}"><pose>{}</pose></frame>"#, name, pose);
+        }
+        for port in &self.ports {
+            let geometry = "<geometry><box><size>0.008 0.006 0.003</size></box></geometry>";
+            let body = if port.legacy {
+                format!("<pose>0 0 0 0 0 0</pose>{}", geometry)
+            } else {
+                format!("<fallback_visual><pose>0 0 0 0 0 0</pose>{}</fallback_visual>", geometry)
+            };
+            let _ = writeln!(xml, r#"    <port name="{}" type="{}">{}</port>"#, port.name, port.port_type, body);
+        }
+        for (name, antenna_type) in &self.antennas {
+            let _ = writeln!(
+                xml,
+                r#"    <antenna name="{}" type="{}"><fallback_visual><geometry><cylinder><radius>0.002</radius><length>0.015</length></cylinder></geometry></fallback_visual></antenna>"#,
+                name, antenna_type
+            );
+        }
+        for sensor in &self.sensors {
+            sensor.write(&mut xml);
+        }
+        let _ = writeln!(xml, "  </{}>", tag);
+        xml
+    }
+
+    /// A whole HCDF document holding just this element
+    pub fn hcdf(&self) -> String {
+        hcdf_document(std::slice::from_ref(self))
+    }
+}
+
+/// A whole HCDF document holding the given elements, MCUs first
+pub fn hcdf_document(elements: &[FixtureBuilder]) -> String {
+    let mut xml = String::from("<?xml version='1.0'?>\n<hcdf version=\"2.1\">\n");
+    for kind in [Kind::Mcu, Kind::Comp] {
+        for element in elements.iter().filter(|e| e.kind == kind) {
+            xml.push_str(&element.element());
+        }
+    }
+    xml.push_str("</hcdf>\n");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_writes_every_part() {
+        let xml = hcdf_document(&[
+            FixtureBuilder::device()
+                .with_name("flow")
+                .with_role("sensor")
+                .with_legacy_port("CAN0", "CAN")
+                .with_port("ETH0", "ethernet")
+                .with_antenna("ANT0", "gnss")
+                .with_sensor(SensorFixture::new("cam", SensorCategory::Optical, "camera").with_fov("imager", 1.0, 0.8)),
+            FixtureBuilder::mcu().with_hwid("0x1").with_ip("10.0.0.5").with_visual("board", "models/b.glb"),
+        ]);
+        assert!(xml.find("<mcu ").unwrap() < xml.find("<comp ").unwrap());
+        assert!(xml.contains(r#"<comp name="flow" role="sensor">"#));
+        assert_eq!(xml.matches("<fallback_visual>").count(), 2);
+        assert!(xml.contains(r##"<fov name="imager" color="#88ff88">"##));
+    }
+
+    #[test]
+    #[should_panic(expected = "only comps have sensors")]
+    fn test_mcu_has_no_sensors() {
+        FixtureBuilder::mcu().with_sensor(SensorFixture::new("imu", SensorCategory::Inertial, "accel_gyro"));
+    }
+}
@@ -0,0 +1,417 @@
+//! Synthetic HCDF fixtures
+//!
+//! Documents are generated rather than checked in so the large fixture can be
+//! sized for benchmarks without bloating the repository. Output is
+//! deterministic: the same size always produces the same XML.
+
+use std::fmt::Write;
+use std::time::Duration;
+
+/// Boards cycled through by generated MCUs
+pub(crate) const BOARDS: &[(&str, &str)] = &[
+    ("spinali", "spinali"),
+    ("mr_mcxn_t1", "optical-flow"),
+    ("mr_canhubk3", "rdd2"),
+    ("frdm_mcxn947", "rtk-gnss"),
+];
+
+/// Ports generated on each comp
+const PORTS_PER_COMP: usize = 6;
+
+/// Antennas generated on each comp
+const ANTENNAS_PER_COMP: usize = 2;
+
+/// Every this many MCUs, one uses a legacy `<model>` rather than visuals
+const LEGACY_MODEL_EVERY: usize = 4;
+
+/// Fixture sizes used by benchmarks and the parse smoke test
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixtureSize {
+    /// One MCU and one comp, like a single board on the bench
+    Small,
+    /// A typical vehicle
+    Medium,
+    /// A fleet-sized document for catching regressions
+    Large,
+}
+
+impl FixtureSize {
+    pub const ALL: [FixtureSize; 3] = [FixtureSize::Small, FixtureSize::Medium, FixtureSize::Large];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FixtureSize::Small => "small",
+            FixtureSize::Medium => "medium",
+            FixtureSize::Large => "large",
+        }
+    }
+
+    /// Number of (MCUs, comps) in the generated document
+    pub fn counts(&self) -> (usize, usize) {
+        match self {
+            FixtureSize::Small => (1, 1),
+            FixtureSize::Medium => (16, 8),
+            FixtureSize::Large => (256, 64),
+        }
+    }
+
+    /// Generate the fixture XML
+    pub fn xml(&self) -> String {
+        let (mcus, comps) = self.counts();
+        synthetic_hcdf(mcus, comps)
+    }
+
+    /// Expected-parse snapshot: element counts per kind and schema variant
+    ///
+    /// JSON checked in under `snapshots/`, so a parser change that drops or
+    /// reclassifies elements shows up as a diff against a reviewed file.
+    pub fn expected_summary(&self) -> &'static str {
+        match self {
+            FixtureSize::Small => include_str!("../snapshots/hcdf_small.json"),
+            FixtureSize::Medium => include_str!("../snapshots/hcdf_medium.json"),
+            FixtureSize::Large => include_str!("../snapshots/hcdf_large.json"),
+        }
+    }
+}
+
+/// Limits the large fixture must parse within
+#[derive(Debug, Clone, Copy)]
+pub struct ParseBudget {
+    pub max_duration: Duration,
+    pub max_allocations: u64,
+    pub max_peak_bytes: usize,
+}
+
+/// Budget for parsing `FixtureSize::Large`
+///
+/// Deliberately generous so it only trips on a several-fold regression, not
+/// on a slow CI runner. Debug builds get more time.
+pub const LARGE_PARSE_BUDGET: ParseBudget = ParseBudget {
+    max_duration: if cfg!(debug_assertions) {
+        Duration::from_secs(10)
+    } else {
+        Duration::from_secs(2)
+    },
+    max_allocations: 2_000_000,
+    max_peak_bytes: 64 * 1024 * 1024,
+};
+
+/// Hardware ID of the generated MCU at `index`
+pub fn hwid(index: usize) -> String {
+    format!("0x{:08x}", 0x1000_0000 + index)
+}
+
+/// Generate an HCDF document with `mcus` MCUs and `comps` sensor assemblies
+///
+/// MCUs carry visuals (every fourth a legacy `<model>` instead), frames,
+/// software and discovery info; comps carry ports and antennas in both the
+/// legacy and fallback-visual schemas plus a sensor of every category, with
+/// drivers, legacy FOV geometry and named FOVs. Each comp is wired to the MCU
+/// with the same index.
+pub fn synthetic_hcdf(mcus: usize, comps: usize) -> String {
+    let mut xml = String::with_capacity(mcus * 1024 + comps * 8 * 1024);
+    xml.push_str("<?xml version='1.0'?>\n<hcdf version=\"2.1\">\n");
+    for i in 0..mcus {
+        write_mcu(&mut xml, i);
+    }
+    for i in 0..comps {
+        write_comp(&mut xml, i);
+    }
+    for i in 0..mcus.min(comps) {
+        write_link(&mut xml, i);
+    }
+    xml.push_str("</hcdf>\n");
+    xml
+}
+
+/// Generate a device fragment for the MCU at `index`, as served by the
+/// fragment index or a device's embedded HCDF
+pub fn device_fragment(index: usize) -> String {
+    let (board, app) = BOARDS[index % BOARDS.len()];
+    let mut xml = String::from("<?xml version='1.0'?>\n<hcdf version=\"2.1\">\n");
+    let _ = write!(
+        xml,
+        r#"  <comp name="{board}">
+    <description>{board} running {app} (fragment rev {index})</description>
+    <visual name="board"><pose>0 0 0 0 0 0</pose><model href="models/{board}.glb"/></visual>
+    <visual name="case" toggle="case"><model href="models/{board}_case.glb"/></visual>
+    <frame name="imu"><description>IMU origin</description><pose>0.012 -0.004 0.003 0 0 0</pose></frame>
+    <frame name="mag"><pose>-0.008 0.002 0.003 0 0 1.5708</pose></frame>
+    <frame name="connector"><pose>0 0.02 0 0 0 0</pose></frame>
+"#
+    );
+    write_port(&mut xml, "ETH0", "ethernet", index, 0);
+    write_port(&mut xml, "CAN0", "CAN", index, 1);
+    xml.push_str("  </comp>\n</hcdf>\n");
+    xml
+}
+
+fn write_mcu(xml: &mut String, i: usize) {
+    let (board, app) = BOARDS[i % BOARDS.len()];
+    let (x, y) = grid_position(i);
+    let _ = write!(
+        xml,
+        r#"  <mcu name="{board}-{i:04}" hwid="{hwid}">
+    <description>Synthetic {board} board {i}</description>
+    <pose_cg>{x:.3} {y:.3} 0.05 0 0 {yaw:.4}</pose_cg>
+    <mass>0.012</mass>
+    <board>{board}</board>
+    <software name="{app}">
+      <version>1.{minor}.{patch}</version>
+      <firmware_manifest_uri>https://firmware.cognipilot.org/{board}/{app}</firmware_manifest_uri>
+    </software>
+    <discovered>
+      <ip>10.{a}.{b}.{c}</ip>
+      <last_seen>2026-01-01T00:00:00Z</last_seen>
+    </discovered>
+{visuals}
+    <frame name="imu"><description>IMU origin</description><pose>0.012 -0.004 0.003 0 0 0</pose></frame>
+    <frame name="mag"><pose>-0.008 0.002 0.003 0 0 1.5708</pose></frame>
+    <frame name="connector"><pose>0 0.02 0 0 0 0</pose></frame>
+    <network>
+      <interface name="eth0" type="t1" ports="1"/>
+    </network>
+  </mcu>
+"#,
+        hwid = hwid(i),
+        visuals = if i % LEGACY_MODEL_EVERY == LEGACY_MODEL_EVERY - 1 {
+            format!(r#"    <model href="models/{board}.glb"/>"#)
+        } else {
+            format!(
+                r#"    <visual name="board"><pose>0 0 0 0 0 0</pose><model href="models/{board}.glb" sha="{i:064x}"/></visual>
+    <visual name="case" toggle="case" default_hidden="true"><pose>0 0 0.002 0 0 0</pose><model href="models/{board}_case.glb"/></visual>"#
+            )
+        },
+        yaw = (i % 8) as f64 * 0.785,
+        minor = i % 5,
+        patch = i % 10,
+        a = (i >> 16) & 0xff,
+        b = (i >> 8) & 0xff,
+        c = i & 0xff,
+    );
+}
+
+fn write_comp(xml: &mut String, i: usize) {
+    let _ = write!(
+        xml,
+        r#"  <comp name="assembly-{i:03}" role="sensor">
+    <description>Synthetic sensor assembly {i}</description>
+    <visual name="board"><model href="models/assembly.glb"/></visual>
+    <frame name="origin"><pose>0 0 0 0 0 0</pose></frame>
+"#
+    );
+    for k in 0..PORTS_PER_COMP {
+        let (name, port_type) = match k % 3 {
+            0 => (format!("ETH{}", k / 3), "ethernet"),
+            1 => (format!("CAN{}", k / 3), "CAN"),
+            _ => (format!("UART{}", k / 3), "serial"),
+        };
+        write_port(xml, &name, port_type, i, k);
+    }
+    for k in 0..ANTENNAS_PER_COMP {
+        write_antenna(xml, i, k);
+    }
+    xml.push_str(
+        r#"    <sensor name="imu">
+      <inertial type="accel_gyro">
+        <pose>0.016 -0.001 -0.008 0 0 0</pose>
+        <driver name="icm45686">
+          <axis-align x="Y" y="-X" z="Z"/>
+        </driver>
+      </inertial>
+    </sensor>
+    <sensor name="mag">
+      <em type="mag">
+        <pose>0.021 0.001 -0.010 0 0 0</pose>
+        <driver name="bmm350">
+          <axis-align x="X" y="Y" z="Z"/>
+        </driver>
+      </em>
+    </sensor>
+    <sensor name="tof">
+      <optical type="tof">
+        <pose>-0.008 0 0.003 0 0 0</pose>
+        <driver name="afbr_s50"/>
+        <geometry>
+          <frustum>
+            <near>0.001</near>
+            <far>0.30</far>
+            <hfov>0.1047</hfov>
+            <vfov>0.1047</vfov>
+          </frustum>
+        </geometry>
+      </optical>
+    </sensor>
+    <sensor name="camera">
+      <optical type="camera">
+        <pose>0.030 0 0.004 0 -1.5708 0</pose>
+        <driver name="imx219"/>
+        <fov name="imager" color="#88ff88">
+          <geometry>
+            <pyramidal_frustum>
+              <near>0.05</near>
+              <far>20.0</far>
+              <hfov>1.0856</hfov>
+              <vfov>0.8517</vfov>
+            </pyramidal_frustum>
+          </geometry>
+        </fov>
+        <fov name="illuminator" color="#ff4444">
+          <pose>-0.005 0 0 0 0 0</pose>
+          <geometry>
+            <conical_frustum>
+              <near>0.001</near>
+              <far>5.0</far>
+              <fov>0.5236</fov>
+            </conical_frustum>
+          </geometry>
+        </fov>
+      </optical>
+    </sensor>
+    <sensor name="gnss">
+      <rf type="gnss">
+        <pose>0 0.010 0.006 0 0 0</pose>
+        <driver name="ublox_m10"/>
+      </rf>
+    </sensor>
+    <sensor name="baro">
+      <force type="pressure">
+        <pose>0.015 0.005 -0.0095 0 0 0</pose>
+        <driver name="bmp581"/>
+      </force>
+    </sensor>
+    <sensor name="humidity">
+      <chemical type="humidity">
+        <pose>-0.012 0.006 0.002 0 0 0</pose>
+        <driver name="sht4x"/>
+      </chemical>
+    </sensor>
+  </comp>
+"#,
+    );
+}
+
+/// Write an antenna, alternating between the legacy and fallback-visual schemas
+fn write_antenna(xml: &mut String, owner: usize, k: usize) {
+    let y = 0.01 * k as f64;
+    let _ = write!(
+        xml,
+        r#"    <antenna name="ANT{k}" type="wifi">
+      <capabilities>
+        <band>2.4 GHz</band>
+        <band>5 GHz</band>
+        <gain unit="dBi">2.0</gain>
+        <standard>802.11ax</standard>
+        <protocol>WPA3</protocol>
+      </capabilities>
+"#
+    );
+    let geometry = "<cylinder><radius>0.002</radius><length>0.015</length></cylinder>";
+    if (owner + k) % 2 == 0 {
+        let _ = write!(
+            xml,
+            r#"      <pose>0.01 {y:.3} 0.005 0 0 0</pose>
+      <geometry>
+        {geometry}
+      </geometry>
+    </antenna>
+"#
+        );
+    } else {
+        let _ = write!(
+            xml,
+            r#"      <fallback_visual>
+        <pose>0.01 {y:.3} 0.005 0 0 0</pose>
+        <geometry>
+          {geometry}
+        </geometry>
+      </fallback_visual>
+    </antenna>
+"#
+        );
+    }
+}
+
+/// Wire comp `i` to the MCU with the same index
+fn write_link(xml: &mut String, i: usize) {
+    let (board, _) = BOARDS[i % BOARDS.len()];
+    let _ = write!(
+        xml,
+        r#"  <link name="assembly-{i:03}-uplink">
+    <digital name="ethernet">
+      <wired name="t1">
+        <from>assembly-{i:03}/ETH0</from>
+        <to>{board}-{i:04}/eth0</to>
+      </wired>
+    </digital>
+  </link>
+"#
+    );
+}
+
+/// Write a port, alternating between the legacy and fallback-visual schemas
+fn write_port(xml: &mut String, name: &str, port_type: &str, owner: usize, k: usize) {
+    let x = 0.005 * k as f64 - 0.02;
+    if (owner + k) % 2 == 0 {
+        let _ = write!(
+            xml,
+            r#"    <port name="{name}" type="{port_type}">
+      <pose>{x:.3} -0.015 -0.009 0 0 0</pose>
+      <geometry>
+        <box><size>0.008 0.006 0.003</size></box>
+      </geometry>
+    </port>
+"#
+        );
+    } else {
+        let _ = write!(
+            xml,
+            r#"    <port name="{name}" type="{port_type}">
+      <capabilities>
+        <bitrate unit="bps">500000</bitrate>
+        <protocol>CAN-FD</protocol>
+      </capabilities>
+      <fallback_visual>
+        <pose>{x:.3} -0.0155 -0.0085 0 0 0</pose>
+        <geometry>
+          <box><size>0.005 0.004 0.003</size></box>
+        </geometry>
+      </fallback_visual>
+    </port>
+"#
+        );
+    }
+}
+
+/// Lay MCUs out on a 10 cm grid so poses differ
+fn grid_position(i: usize) -> (f64, f64) {
+    ((i % 16) as f64 * 0.1, (i / 16) as f64 * 0.1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixture_counts() {
+        for size in FixtureSize::ALL {
+            let (mcus, comps) = size.counts();
+            let xml = size.xml();
+            assert_eq!(xml.matches("<mcu ").count(), mcus, "{}", size.name());
+            assert_eq!(xml.matches("<comp ").count(), comps, "{}", size.name());
+            assert_eq!(xml.matches("<port ").count(), comps * PORTS_PER_COMP);
+            assert_eq!(xml.matches("<antenna ").count(), comps * ANTENNAS_PER_COMP);
+            assert_eq!(xml.matches("<link ").count(), mcus.min(comps));
+            for category in ["<inertial ", "<em ", "<rf ", "<chemical ", "<force "] {
+                assert_eq!(xml.matches(category).count(), comps, "{}", category);
+            }
+        }
+    }
+
+    #[test]
+    fn test_fixture_is_deterministic() {
+        assert_eq!(FixtureSize::Medium.xml(), FixtureSize::Medium.xml());
+        assert!(device_fragment(3).contains("frdm_mcxn947 running rtk-gnss"));
+        assert_eq!(hwid(1), "0x10000001");
+    }
+}
@@ -0,0 +1,20 @@
+//! Dendrite Test Support - Shared helpers for tests and benchmarks
+//!
+//! - Synthetic HCDF fixtures in small, medium and large sizes, covering the
+//!   legacy and current schemas, with expected-parse snapshots
+//! - Builders for one-off MCU and comp fixtures
+//! - A synthetic device registry in the daemon's JSON shape
+//! - A counting global allocator for allocation budgets
+//! - A mock MCUmgr device answering SMP requests over local UDP
+
+pub mod alloc;
+pub mod builder;
+pub mod fixtures;
+pub mod registry;
+pub mod smp;
+
+pub use alloc::{AllocStats, CountingAllocator};
+pub use builder::{hcdf_document, FixtureBuilder, SensorCategory, SensorFixture};
+pub use fixtures::{device_fragment, hwid, synthetic_hcdf, FixtureSize, ParseBudget, LARGE_PARSE_BUDGET};
+pub use registry::{registry_device, synthetic_registry, REGISTRY_DEVICES};
+pub use smp::{mcuboot_image, MockHcdfInfo, MockIdentity, MockRequest, MockSmpDevice};
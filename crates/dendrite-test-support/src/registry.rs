@@ -0,0 +1,142 @@
+//! Synthetic device registry
+//!
+//! Devices in the JSON shape of `dendrite_core::Device`, as the daemon keeps
+//! them in its registry and sends them to the frontend. Generated as JSON
+//! text so this crate doesn't depend on the crates it tests; deserialize into
+//! `Device` on the other side. Device `i` is the MCU at index `i` in the
+//! synthetic HCDF: same hardware ID, board and pose.
+
+use crate::fixtures::{hwid, BOARDS};
+use std::fmt::Write;
+
+/// Devices in the registry fixture, a rig with a few switches' worth of boards
+pub const REGISTRY_DEVICES: usize = 60;
+
+/// Discovery methods cycled through, with the heartbeat each one gets
+const METHODS: &[(&str, &str)] = &[("arp", "icmp"), ("lldp", "icmp"), ("probe", "smp-echo"), ("mdns", "icmp")];
+
+/// Generate a JSON array of `count` devices
+pub fn synthetic_registry(count: usize) -> String {
+    let devices: Vec<String> = (0..count).map(registry_device).collect();
+    format!("[\n{}\n]\n", devices.join(",\n"))
+}
+
+/// Generate the registry device at `index` as a JSON object
+///
+/// Every fourth device is offline and every third has an update available.
+/// Every sixth is a child board mounted on the one before it, with no
+/// visuals or ports of its own.
+pub fn registry_device(index: usize) -> String {
+    let (board, app) = BOARDS[index % BOARDS.len()];
+    let (method, heartbeat) = METHODS[index % METHODS.len()];
+    let status = if index % 4 == 3 { "offline" } else { "online" };
+    let firmware_status = if index % 3 == 2 {
+        format!(r#"{{"status": "update_available", "latest_version": "1.{}.0", "changelog": null}}"#, index % 5 + 1)
+    } else {
+        r#"{"status": "up_to_date"}"#.to_string()
+    };
+    let child = index % 6 == 5;
+    let parent_id = if child { format!(r#""{}""#, hwid(index - 1)) } else { "null".to_string() };
+    let (x, y) = ((index % 16) as f64 * 0.1, (index / 16) as f64 * 0.1);
+
+    let mut json = String::with_capacity(4096);
+    let _ = write!(
+        json,
+        r#"  {{
+    "id": "{id}",
+    "name": "{app}-{index}",
+    "status": "{status}",
+    "discovery": {{
+      "ip": "10.0.{subnet}.{host}",
+      "port": 1337,
+      "switch_port": {switch_port},
+      "mac": "02:00:10:00:{subnet:02x}:{host:02x}",
+      "first_seen": "2026-01-05T09:00:00Z",
+      "last_seen": "2026-01-05T09:{minute:02}:00Z",
+      "discovery_method": "{method}",
+      "heartbeat_method": "{heartbeat}"
+    }},
+    "info": {{
+      "os_name": "Zephyr",
+      "board": "{board}",
+      "processor": "cortex-m33",
+      "bootloader": "MCUboot",
+      "mcuboot_mode": null
+    }},
+    "firmware": {{
+      "name": "{app}",
+      "version": "1.{minor}.{patch}",
+      "build_date": "2025-12-20T12:00:00Z",
+      "image_hash": "{index:032x}",
+      "confirmed": true,
+      "pending": false,
+      "slot": 0
+    }},
+    "firmware_status": {firmware_status},
+    "firmware_manifest_uri": "https://firmware.cognipilot.org/{board}/{app}",
+    "parent_id": {parent_id},
+    "model_path": null,
+    "pose": [{x:.3}, {y:.3}, 0.05, 0.0, 0.0, {yaw:.4}],
+    "pose_provisional": {provisional},
+"#,
+        id = hwid(index),
+        subnet = index / 200,
+        host = index % 200 + 10,
+        switch_port = index % 8 + 1,
+        minute = index % 60,
+        minor = index % 5,
+        patch = index % 10,
+        yaw = (index % 8) as f64 * 0.785,
+        provisional = index % 10 == 9,
+    );
+    if child {
+        json.push_str(
+            r#"    "visuals": [],
+    "frames": [],
+    "ports": [],
+"#,
+        );
+    } else {
+        let _ = write!(
+            json,
+            r#"    "visuals": [
+      {{"name": "board", "toggle": null, "default_hidden": false, "pose": [0.0, 0.0, 0.0, 0.0, 0.0, 0.0], "model_path": "models/{board}.glb", "model_sha": "{index:064x}"}},
+      {{"name": "case", "toggle": "case", "default_hidden": true, "pose": null, "model_path": "models/{board}_case.glb", "model_sha": null}}
+    ],
+    "frames": [
+      {{"name": "imu", "description": "IMU origin", "pose": [0.012, -0.004, 0.003, 0.0, 0.0, 0.0]}},
+      {{"name": "connector", "description": null, "pose": [0.0, 0.02, 0.0, 0.0, 0.0, 0.0], "parent": "imu"}}
+    ],
+    "ports": [
+      {{"name": "ETH0", "port_type": "ethernet", "pose": [-0.02, -0.0155, -0.0085, 0.0, 0.0, 0.0], "geometry": [{{"type": "box", "size": [0.005, 0.004, 0.003]}}], "visual_name": null, "mesh_name": null}},
+      {{"name": "CAN0", "port_type": "CAN", "pose": null, "geometry": [], "visual_name": "board", "mesh_name": "CAN0"}}
+    ],
+"#
+        );
+    }
+    json.push_str(
+        r#"    "sensors": [
+      {"name": "imu", "category": "inertial", "sensor_type": "accel_gyro", "driver": "icm45686", "pose": [0.016, -0.001, -0.008, 0.0, 0.0, 0.0], "axis_align": {"x": "Y", "y": "-X", "z": "Z"}, "geometry": null, "fovs": []},
+      {"name": "tof", "category": "optical", "sensor_type": "tof", "driver": "afbr_s50", "pose": [-0.008, 0.0, 0.003, 0.0, 0.0, 0.0], "axis_align": null, "geometry": null, "fovs": [
+        {"name": "collector", "color": [0.25, 0.5, 1.0], "pose": null, "geometry": {"type": "conical_frustum", "near": 0.05, "far": 4.0, "fov": 0.2}}
+      ]}
+    ]
+  }"#,
+    );
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_shape() {
+        let registry = synthetic_registry(REGISTRY_DEVICES);
+        assert!(registry.starts_with("[\n") && registry.ends_with("}\n]\n"));
+        assert_eq!(registry.matches(r#""discovery": {"#).count(), REGISTRY_DEVICES);
+        assert_eq!(registry.matches(r#""status": "offline""#).count(), REGISTRY_DEVICES / 4);
+        assert!(registry_device(5).contains(r#""parent_id": "0x10000004""#));
+        assert_eq!(registry_device(7), registry_device(7));
+    }
+}
@@ -0,0 +1,596 @@
+//! Mock MCUmgr device
+//!
+//! Binds a UDP socket on localhost and answers SMP requests the way a
+//! device running the CogniPilot HCDF group would, so transport and query
+//! code can be exercised without hardware.
+//!
+//! Supported commands:
+//! - Default group echo (group 0, id 0)
+//! - MCUmgr parameters (group 0, id 6), once a buffer size is set; requests
+//!   larger than the buffer are then dropped
+//! - Reset (group 0, id 5), which reverts an unconfirmed running image
+//! - OS info hardware ID and full info (group 0, id 7, formats "h" and "a")
+//! - HCDF info (group 100, id 0)
+//! - Status LED set color (group 101, id 0)
+//! - Image state read and image upload (group 1, ids 0 and 1), resuming a
+//!   transfer whose SHA matches the one in progress as Zephyr does. The
+//!   secondary slot can be given an image pending test up front.
+//! - Image state write (group 1, id 0), marking the secondary image for test
+//!   or confirming the running one. The running image can be left
+//!   unconfirmed as if it had just booted for test.
+//! - Image erase (group 1, id 5), clearing the secondary slot. The whole
+//!   image group can be turned off, as on firmware built without it.
+//! - Shell exec (group 9, id 0), answering commands given output and return
+//!   code up front; others get "command not found" and -8 (`ENOEXEC`), as
+//!   Zephyr's shell does. The group can be turned off too.
+//!
+//! Anything else gets an `rc` of 8 (not supported). Every request is recorded
+//! so tests can assert on what was sent, and responses can be delayed to
+//! stand in for a slow device. Requests, or just upload requests, can be
+//! dropped unanswered to stand in for a lossy link or one going down
+//! mid-transfer.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_cbor::Value;
+use std::collections::BTreeMap;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+use tokio::task::JoinHandle;
+
+const GROUP_DEFAULT: u16 = 0;
+const GROUP_IMAGE: u16 = 1;
+const ID_IMAGE_STATE: u8 = 0;
+const ID_IMAGE_UPLOAD: u8 = 1;
+const ID_IMAGE_ERASE: u8 = 5;
+const ID_RESET: u8 = 5;
+const ID_ECHO: u8 = 0;
+const ID_MCUMGR_PARAMS: u8 = 6;
+const ID_OS_INFO: u8 = 7;
+const GROUP_SHELL: u16 = 9;
+const ID_SHELL_EXEC: u8 = 0;
+const GROUP_HCDF: u16 = 100;
+const ID_HCDF_INFO: u8 = 0;
+const GROUP_STATUS_LED: u16 = 101;
+const ID_SET_COLOR: u8 = 0;
+
+const OP_WRITE: u8 = 2;
+
+/// MCUmgr "invalid value" return code
+const RC_EINVAL: i128 = 3;
+/// MCUmgr "not supported" return code
+const RC_ENOTSUP: u32 = 8;
+
+/// Hash the mock device reports for its running image
+const RUNNING_HASH: [u8; 32] = [0; 32];
+
+/// HCDF info the mock device reports
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MockHcdfInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha: Option<String>,
+    /// Board target, e.g. "mr_mcxn_t1/mcxn947/cpu0"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub board: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hwid: Option<String>,
+}
+
+/// Identity the mock device reports through OS info; unset fields get `rc` 8
+#[derive(Debug, Clone, Default)]
+pub struct MockIdentity {
+    /// Hardware ID (format "h")
+    pub hwid: Option<String>,
+    /// Full OS info string (format "a"), e.g.
+    /// "Zephyr optical-flow 4ad28d86da70 4.3.0 ... mr_mcxn_t1/mcxn947/cpu0"
+    pub os_info: Option<String>,
+}
+
+/// A request received by the mock device
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockRequest {
+    pub op: u8,
+    pub group: u16,
+    pub id: u8,
+    /// CBOR body
+    pub body: Vec<u8>,
+    /// When it arrived
+    pub at: Instant,
+}
+
+impl MockRequest {
+    /// Decode the CBOR body
+    pub fn decode<T: DeserializeOwned>(&self) -> Result<T, serde_cbor::Error> {
+        serde_cbor::from_slice(&self.body)
+    }
+}
+
+/// Image upload state of the mock device
+#[derive(Debug, Default)]
+struct MockImage {
+    /// Transfer in progress: declared length, SHA and the data so far
+    upload: Option<(usize, Option<Vec<u8>>, Vec<u8>)>,
+    /// Completed image in the secondary slot
+    secondary: Option<Vec<u8>>,
+    /// The secondary slot's image is marked for test on the next boot
+    pending: bool,
+    /// The running image booted for test and reverts unless confirmed
+    unconfirmed: bool,
+    /// Answer image group requests with `ENOTSUP`, like firmware without it
+    images_unsupported: bool,
+    /// SMP buffer size reported as the MCUmgr parameters; larger requests
+    /// are dropped, as they wouldn't fit the device's buffer
+    buf_size: Option<usize>,
+    /// Upload requests at or past this offset are dropped while `drops` lasts
+    drop_from: u64,
+    drops: u32,
+    /// Output and return code of each known shell command
+    shell: BTreeMap<String, (String, i32)>,
+    /// Answer shell requests with `ENOTSUP`, like firmware without the group
+    shell_unsupported: bool,
+}
+
+/// A mock device serving SMP over UDP; stops when dropped
+pub struct MockSmpDevice {
+    addr: SocketAddr,
+    requests: Arc<Mutex<Vec<MockRequest>>>,
+    delay: Arc<Mutex<Duration>>,
+    /// Requests still to be dropped
+    drops: Arc<Mutex<u32>>,
+    image: Arc<Mutex<MockImage>>,
+    task: JoinHandle<()>,
+}
+
+impl MockSmpDevice {
+    /// Start a mock device on an ephemeral localhost port
+    pub async fn spawn(info: MockHcdfInfo) -> io::Result<Self> {
+        Self::spawn_with_identity(info, MockIdentity::default()).await
+    }
+
+    /// Start a mock device that also reports a hardware ID and OS info
+    pub async fn spawn_with_identity(info: MockHcdfInfo, identity: MockIdentity) -> io::Result<Self> {
+        Self::spawn_at(SocketAddr::from(([127, 0, 0, 1], 0)), info, identity).await
+    }
+
+    /// Start a mock device bound to `bind`, e.g. `[::1]:0` to serve over IPv6
+    pub async fn spawn_at(bind: SocketAddr, info: MockHcdfInfo, identity: MockIdentity) -> io::Result<Self> {
+        let socket = Arc::new(UdpSocket::bind(bind).await?);
+        let addr = socket.local_addr()?;
+        let hcdf_info = serde_cbor::to_vec(&info).map_err(io::Error::other)?;
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let delay = Arc::new(Mutex::new(Duration::ZERO));
+        let drops = Arc::new(Mutex::new(0));
+        let image = Arc::new(Mutex::new(MockImage::default()));
+
+        let (recorded, response_delay, upload_state) = (requests.clone(), delay.clone(), image.clone());
+        let dropping = drops.clone();
+        let task = tokio::spawn(async move {
+            let mut buf = vec![0u8; 2048];
+            loop {
+                let Ok((len, peer)) = socket.recv_from(&mut buf).await else {
+                    break;
+                };
+                let packet = &buf[..len];
+                if packet.len() >= 8 {
+                    recorded.lock().unwrap_or_else(|e| e.into_inner()).push(MockRequest {
+                        op: packet[0] & 0x07,
+                        group: u16::from_be_bytes([packet[4], packet[5]]),
+                        id: packet[7],
+                        body: packet[8..].to_vec(),
+                        at: Instant::now(),
+                    });
+                }
+                {
+                    let mut drops = dropping.lock().unwrap_or_else(|e| e.into_inner());
+                    if *drops > 0 {
+                        *drops -= 1;
+                        continue;
+                    }
+                }
+                let delay = *response_delay.lock().unwrap_or_else(|e| e.into_inner());
+                if !delay.is_zero() {
+                    tokio::time::sleep(delay).await;
+                }
+                let response = {
+                    let mut image = upload_state.lock().unwrap_or_else(|e| e.into_inner());
+                    respond(packet, &hcdf_info, &identity, &mut image)
+                };
+                if let Some(response) = response {
+                    let _ = socket.send_to(&response, peer).await;
+                }
+            }
+        });
+
+        Ok(Self { addr, requests, delay, drops, image, task })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn port(&self) -> u16 {
+        self.addr.port()
+    }
+
+    /// Wait this long before answering each request
+    pub fn set_response_delay(&self, delay: Duration) {
+        *self.delay.lock().unwrap_or_else(|e| e.into_inner()) = delay;
+    }
+
+    /// Leave the next `count` requests unanswered
+    pub fn drop_requests(&self, count: u32) {
+        *self.drops.lock().unwrap_or_else(|e| e.into_inner()) = count;
+    }
+
+    /// Leave the next `count` upload requests at or past `offset` unanswered
+    pub fn drop_upload_requests(&self, offset: u64, count: u32) {
+        let mut image = self.image.lock().unwrap_or_else(|e| e.into_inner());
+        image.drop_from = offset;
+        image.drops = count;
+    }
+
+    /// Put `image` in the secondary slot, marked pending test as if a
+    /// previous update hadn't rebooted yet
+    pub fn set_pending_image(&self, image: Vec<u8>) {
+        let mut state = self.image.lock().unwrap_or_else(|e| e.into_inner());
+        state.secondary = Some(image);
+        state.pending = true;
+    }
+
+    /// Leave the running image unconfirmed, as after booting an update for test
+    pub fn set_unconfirmed_active(&self) {
+        self.image.lock().unwrap_or_else(|e| e.into_inner()).unconfirmed = true;
+    }
+
+    /// Answer every image group request with `ENOTSUP`
+    pub fn disable_image_group(&self) {
+        self.image.lock().unwrap_or_else(|e| e.into_inner()).images_unsupported = true;
+    }
+
+    /// Answer the shell command `command` (words separated by single spaces)
+    /// with `output` and return code `ret`
+    pub fn set_shell_command(&self, command: &str, output: &str, ret: i32) {
+        let mut state = self.image.lock().unwrap_or_else(|e| e.into_inner());
+        state.shell.insert(command.to_string(), (output.to_string(), ret));
+    }
+
+    /// Answer every shell request with `ENOTSUP`
+    pub fn disable_shell_group(&self) {
+        self.image.lock().unwrap_or_else(|e| e.into_inner()).shell_unsupported = true;
+    }
+
+    /// Report `buf_size` byte SMP buffers (MCUmgr parameters) and drop any
+    /// request that doesn't fit one
+    pub fn set_buffer_size(&self, buf_size: usize) {
+        self.image.lock().unwrap_or_else(|e| e.into_inner()).buf_size = Some(buf_size);
+    }
+
+    /// The image in the secondary slot, once an upload has completed
+    pub fn uploaded_image(&self) -> Option<Vec<u8>> {
+        self.image.lock().unwrap_or_else(|e| e.into_inner()).secondary.clone()
+    }
+
+    /// Requests received so far, oldest first
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.requests.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Requests received for one group and command
+    pub fn requests_for(&self, group: u16, id: u8) -> Vec<MockRequest> {
+        self.requests()
+            .into_iter()
+            .filter(|r| r.group == group && r.id == id)
+            .collect()
+    }
+}
+
+impl Drop for MockSmpDevice {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Build the response packet for one SMP request
+fn respond(packet: &[u8], hcdf_info: &[u8], identity: &MockIdentity, image: &mut MockImage) -> Option<Vec<u8>> {
+    if packet.len() < 8 {
+        return None;
+    }
+    let op = packet[0] & 0x07;
+    let group = u16::from_be_bytes([packet[4], packet[5]]);
+    let seq = packet[6];
+    let id = packet[7];
+    let body = &packet[8..];
+    if image.buf_size.is_some_and(|buf_size| packet.len() > buf_size) {
+        return None;
+    }
+
+    let response_body = match (group, id) {
+        (GROUP_IMAGE, _) if image.images_unsupported => {
+            serde_cbor::to_vec(&BTreeMap::from([("rc", RC_ENOTSUP)])).ok()?
+        }
+        (GROUP_DEFAULT, ID_ECHO) => {
+            let request: BTreeMap<String, String> = serde_cbor::from_slice(body).ok()?;
+            let echo = request.get("d").cloned().unwrap_or_default();
+            serde_cbor::to_vec(&BTreeMap::from([("r", echo)])).ok()?
+        }
+        (GROUP_DEFAULT, ID_OS_INFO) => {
+            let request: BTreeMap<String, String> = serde_cbor::from_slice(body).ok()?;
+            let output = match request.get("format").map(String::as_str) {
+                Some("h") => identity.hwid.clone(),
+                Some("a") => identity.os_info.clone(),
+                _ => None,
+            };
+            match output {
+                Some(output) => serde_cbor::to_vec(&BTreeMap::from([("output", output)])).ok()?,
+                None => serde_cbor::to_vec(&BTreeMap::from([("rc", RC_ENOTSUP)])).ok()?,
+            }
+        }
+        (GROUP_DEFAULT, ID_MCUMGR_PARAMS) => match image.buf_size {
+            Some(buf_size) => serde_cbor::to_vec(&BTreeMap::from([("buf_size", buf_size), ("buf_count", 4)])).ok()?,
+            None => serde_cbor::to_vec(&BTreeMap::from([("rc", RC_ENOTSUP)])).ok()?,
+        },
+        (GROUP_DEFAULT, ID_RESET) => {
+            // MCUboot reverts an image that booted for test without being confirmed
+            image.unconfirmed = false;
+            serde_cbor::to_vec(&BTreeMap::from([("rc", 0u32)])).ok()?
+        }
+        (GROUP_HCDF, ID_HCDF_INFO) => hcdf_info.to_vec(),
+        (GROUP_STATUS_LED, ID_SET_COLOR) => serde_cbor::to_vec(&BTreeMap::from([("rc", 0u32)])).ok()?,
+        (GROUP_SHELL, _) if image.shell_unsupported => serde_cbor::to_vec(&BTreeMap::from([("rc", RC_ENOTSUP)])).ok()?,
+        (GROUP_SHELL, ID_SHELL_EXEC) => {
+            let request: BTreeMap<String, Vec<String>> = serde_cbor::from_slice(body).ok()?;
+            let command = request.get("argv").map(|argv| argv.join(" ")).unwrap_or_default();
+            let (output, ret) = image.shell.get(&command).cloned().unwrap_or_else(|| {
+                let name = command.split(' ').next().unwrap_or_default();
+                (format!("{}: command not found\n", name), -8)
+            });
+            serde_cbor::to_vec(&map([("o", Value::Text(output)), ("ret", Value::Integer(ret.into()))])).ok()?
+        }
+        (GROUP_IMAGE, ID_IMAGE_STATE) if op == OP_WRITE => {
+            serde_cbor::to_vec(&set_image_state(image, serde_cbor::from_slice(body).ok()?)).ok()?
+        }
+        (GROUP_IMAGE, ID_IMAGE_STATE) => serde_cbor::to_vec(&image_state(image)).ok()?,
+        (GROUP_IMAGE, ID_IMAGE_ERASE) => {
+            image.secondary = None;
+            image.pending = false;
+            image.upload = None;
+            serde_cbor::to_vec(&BTreeMap::from([("rc", 0u32)])).ok()?
+        }
+        (GROUP_IMAGE, ID_IMAGE_UPLOAD) => serde_cbor::to_vec(&upload(image, serde_cbor::from_slice(body).ok()?)?).ok()?,
+        _ => serde_cbor::to_vec(&BTreeMap::from([("rc", RC_ENOTSUP)])).ok()?,
+    };
+
+    // Response op is the request op + 1 (read -> read rsp, write -> write rsp)
+    let mut response = Vec::with_capacity(8 + response_body.len());
+    response.push((1 << 3) | ((op + 1) & 0x07));
+    response.push(0);
+    response.extend_from_slice(&(response_body.len() as u16).to_be_bytes());
+    response.extend_from_slice(&group.to_be_bytes());
+    response.push(seq);
+    response.push(id);
+    response.extend_from_slice(&response_body);
+    Some(response)
+}
+
+/// Image state response: the running image in slot 0, an uploaded one in slot 1
+fn image_state(image: &MockImage) -> Value {
+    let entry = |slot: i128, hash: Vec<u8>, active: bool, pending: bool, confirmed: bool| {
+        map([
+            ("image", Value::Integer(0)),
+            ("slot", Value::Integer(slot)),
+            ("version", Value::Text("1.0.0".to_string())),
+            ("hash", Value::Bytes(hash)),
+            ("bootable", Value::Bool(true)),
+            ("pending", Value::Bool(pending)),
+            ("confirmed", Value::Bool(confirmed)),
+            ("active", Value::Bool(active)),
+        ])
+    };
+    let mut images = vec![entry(0, RUNNING_HASH.to_vec(), true, false, !image.unconfirmed)];
+    if let Some(secondary) = &image.secondary {
+        images.push(entry(1, tlv_hash(secondary).unwrap_or_default(), false, image.pending, false));
+    }
+    map([("images", Value::Array(images))])
+}
+
+/// Mark the secondary image for test or confirm the running one, answering
+/// with the new image state; an unknown hash gets `rc` 3
+fn set_image_state(image: &mut MockImage, request: Value) -> Value {
+    let field = |key: &str| match &request {
+        Value::Map(fields) => fields.get(&Value::Text(key.to_string())).cloned(),
+        _ => None,
+    };
+    let confirm = matches!(field("confirm"), Some(Value::Bool(true)));
+    let hash = match field("hash") {
+        Some(Value::Bytes(hash)) => Some(hash),
+        _ => None,
+    };
+    let secondary = image.secondary.as_deref().and_then(tlv_hash);
+    match hash {
+        None if confirm => image.unconfirmed = false,
+        Some(hash) if confirm && hash == RUNNING_HASH => image.unconfirmed = false,
+        Some(hash) if secondary.as_ref() == Some(&hash) => image.pending = true,
+        _ => return map([("rc", Value::Integer(RC_EINVAL))]),
+    }
+    image_state(image)
+}
+
+/// Handle one upload chunk, or drop it; answers with the offset expected next
+fn upload(image: &mut MockImage, request: Value) -> Option<Value> {
+    let field = |key: &str| match &request {
+        Value::Map(fields) => fields.get(&Value::Text(key.to_string())).cloned(),
+        _ => None,
+    };
+    let off = match field("off")? {
+        Value::Integer(off) => off as usize,
+        _ => return None,
+    };
+    if image.drops > 0 && off as u64 >= image.drop_from {
+        image.drops -= 1;
+        return None;
+    }
+    let bytes = |value: Option<Value>| match value {
+        Some(Value::Bytes(bytes)) => Some(bytes),
+        _ => None,
+    };
+    let data = bytes(field("data")).unwrap_or_default();
+    let sha = bytes(field("sha"));
+
+    if off == 0 {
+        let resuming = matches!(
+            (&image.upload, &sha),
+            (Some((_, Some(current), so_far)), Some(sha)) if current == sha && !so_far.is_empty()
+        );
+        if !resuming {
+            let len = match field("len") {
+                Some(Value::Integer(len)) => len as usize,
+                _ => return Some(map([("rc", Value::Integer(3))])),
+            };
+            image.upload = Some((len, sha, Vec::new()));
+            image.secondary = None;
+            image.pending = false;
+        }
+    }
+
+    let Some((len, _, so_far)) = image.upload.as_mut() else {
+        return Some(map([("rc", Value::Integer(0)), ("off", Value::Integer(0))]));
+    };
+    if off == so_far.len() {
+        so_far.extend_from_slice(&data);
+    }
+    let next = so_far.len();
+    if next >= *len {
+        image.secondary = image.upload.take().map(|(_, _, data)| data);
+    }
+    Some(map([("rc", Value::Integer(0)), ("off", Value::Integer(next as i128))]))
+}
+
+fn map<const N: usize>(fields: [(&str, Value); N]) -> Value {
+    Value::Map(fields.into_iter().map(|(k, v)| (Value::Text(k.to_string()), v)).collect())
+}
+
+/// SHA256 TLV of an MCUboot image, which is the hash the device reports
+fn tlv_hash(image: &[u8]) -> Option<Vec<u8>> {
+    let u16_at = |at: usize| image.get(at..at + 2).map(|b| u16::from_le_bytes([b[0], b[1]]) as usize);
+    let header_size = u16_at(8)?;
+    let body_size = image.get(12..16).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize)?;
+    let tlv = header_size + body_size;
+    let end = tlv + u16_at(tlv + 2)?;
+    let mut at = tlv + 4;
+    while at + 4 <= end {
+        let len = u16_at(at + 2)?;
+        if u16_at(at)? == 0x10 {
+            return image.get(at + 4..at + 4 + len).map(<[u8]>::to_vec);
+        }
+        at += 4 + len;
+    }
+    None
+}
+
+/// A minimal MCUboot image: 32 byte header, `body_len` bytes of body and a
+/// TLV trailer whose SHA256 entry is `hash_byte` repeated
+///
+/// The hash isn't computed; the mock device reports whatever the trailer
+/// says, as MCUboot does.
+pub fn mcuboot_image(body_len: usize, hash_byte: u8) -> Vec<u8> {
+    let mut image = Vec::with_capacity(32 + body_len + 40);
+    image.extend_from_slice(&0x96f3_b83du32.to_le_bytes());
+    image.extend_from_slice(&0u32.to_le_bytes());
+    image.extend_from_slice(&32u16.to_le_bytes());
+    image.extend_from_slice(&0u16.to_le_bytes());
+    image.extend_from_slice(&(body_len as u32).to_le_bytes());
+    image.resize(32, 0);
+    image.extend((0..body_len).map(|i| (i % 251) as u8));
+    image.extend_from_slice(&0x6907u16.to_le_bytes());
+    image.extend_from_slice(&40u16.to_le_bytes());
+    image.extend_from_slice(&0x10u16.to_le_bytes());
+    image.extend_from_slice(&32u16.to_le_bytes());
+    image.extend_from_slice(&[hash_byte; 32]);
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_respond_echoes_sequence_and_group() {
+        let body = serde_cbor::to_vec(&BTreeMap::from([("d", "ping")])).unwrap();
+        let mut packet = vec![(1 << 3) | 2, 0, 0, body.len() as u8, 0, 0, 42, 0];
+        packet.extend_from_slice(&body);
+
+        let response = respond(&packet, &[], &MockIdentity::default(), &mut MockImage::default()).unwrap();
+        assert_eq!(response[0] & 0x07, 3);
+        assert_eq!(response[6], 42);
+        let echoed: BTreeMap<String, String> = serde_cbor::from_slice(&response[8..]).unwrap();
+        assert_eq!(echoed["r"], "ping");
+    }
+
+    #[test]
+    fn test_upload_resumes_matching_sha() {
+        let image = mcuboot_image(100, 0xab);
+        let chunk = |off: usize, sha: Option<&[u8]>| {
+            let mut fields = vec![("off", Value::Integer(off as i128)), ("data", Value::Bytes(image[off..(off + 64).min(image.len())].to_vec()))];
+            if off == 0 {
+                fields.push(("len", Value::Integer(image.len() as i128)));
+            }
+            if let Some(sha) = sha {
+                fields.push(("sha", Value::Bytes(sha.to_vec())));
+            }
+            Value::Map(fields.into_iter().map(|(k, v)| (Value::Text(k.to_string()), v)).collect())
+        };
+        let offset = |response: Option<Value>| match response {
+            Some(Value::Map(fields)) => fields.get(&Value::Text("off".to_string())).cloned(),
+            _ => None,
+        };
+        let mut device = MockImage::default();
+
+        assert_eq!(offset(upload(&mut device, chunk(0, Some(b"one")))), Some(Value::Integer(64)));
+        // Same SHA at offset 0: the device says where it got to
+        assert_eq!(offset(upload(&mut device, chunk(0, Some(b"one")))), Some(Value::Integer(64)));
+        // Without it the transfer starts over
+        assert_eq!(offset(upload(&mut device, chunk(0, None))), Some(Value::Integer(64)));
+        assert_eq!(offset(upload(&mut device, chunk(64, None))), Some(Value::Integer(128)));
+        assert_eq!(offset(upload(&mut device, chunk(128, None))), Some(Value::Integer(image.len() as i128)));
+        assert_eq!(device.secondary.as_deref(), Some(image.as_slice()));
+        assert_eq!(tlv_hash(&image), Some(vec![0xab; 32]));
+    }
+
+    #[test]
+    fn test_confirm_running_image() {
+        let request = |hash: &[u8]| {
+            map([("hash", Value::Bytes(hash.to_vec())), ("confirm", Value::Bool(true))])
+        };
+        let mut device = MockImage { unconfirmed: true, ..Default::default() };
+        assert_eq!(set_image_state(&mut device, request(&[0xee; 32])), map([("rc", Value::Integer(RC_EINVAL))]));
+        assert!(device.unconfirmed);
+        set_image_state(&mut device, request(&RUNNING_HASH));
+        assert!(!device.unconfirmed);
+    }
+
+    #[test]
+    fn test_pending_image_is_reported_in_slot_1() {
+        let slot = |state: &Value, index: usize, key: &str| match state {
+            Value::Map(fields) => match fields.get(&Value::Text("images".to_string())) {
+                Some(Value::Array(images)) => match images.get(index) {
+                    Some(Value::Map(image)) => image.get(&Value::Text(key.to_string())).cloned(),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        };
+        let device = MockImage { secondary: Some(mcuboot_image(16, 0xcd)), pending: true, ..Default::default() };
+        let state = image_state(&device);
+        assert_eq!(slot(&state, 0, "pending"), Some(Value::Bool(false)));
+        assert_eq!(slot(&state, 1, "pending"), Some(Value::Bool(true)));
+        assert_eq!(slot(&state, 1, "hash"), Some(Value::Bytes(vec![0xcd; 32])));
+    }
+}
@@ -4,9 +4,11 @@ use bevy::prelude::*;
 use bevy::winit::WinitSettings;
 use bevy_egui::EguiPlugin;
 use bevy_picking::{DefaultPickingPlugins, prelude::MeshPickingPlugin};
+use dendrite_core::{FrameNode, Topology};
 use std::time::Duration;
 
-use crate::file_picker::FilePickerPlugin;
+use crate::compare::ComparePlugin;
+use crate::file_picker::{BundleAssets, FilePickerPlugin, BUNDLE_ASSET_SOURCE};
 use crate::models::ModelsPlugin;
 use crate::scene::ScenePlugin;
 use crate::ui::UiPlugin;
@@ -24,6 +26,8 @@ pub struct VisualData {
     pub name: String,
     /// Toggle group name for visibility control (e.g., "case")
     pub toggle: Option<String>,
+    /// Whether the toggle group starts hidden when the device is first loaded
+    pub default_hidden: bool,
     /// Pose offset: (x, y, z, roll, pitch, yaw) in meters/radians
     pub pose: Option<[f64; 6]>,
     /// Model file path
@@ -37,8 +41,25 @@ pub struct VisualData {
 pub struct FrameData {
     pub name: String,
     pub description: Option<String>,
-    /// Pose offset: (x, y, z, roll, pitch, yaw) in meters/radians
+    /// Pose offset: (x, y, z, roll, pitch, yaw) in meters/radians, relative
+    /// to `parent` if set, otherwise to the device origin
     pub pose: Option<[f64; 6]>,
+    /// Frame this one is positioned relative to
+    pub parent: Option<String>,
+}
+
+impl FrameNode for FrameData {
+    fn frame_name(&self) -> &str {
+        &self.name
+    }
+
+    fn parent_frame(&self) -> Option<&str> {
+        self.parent.as_deref()
+    }
+
+    fn local_pose(&self) -> dendrite_core::Pose {
+        self.pose.map(dendrite_core::Pose::from_array).unwrap_or_default()
+    }
 }
 
 /// Axis alignment for sensor driver transforms
@@ -82,9 +103,9 @@ pub struct FovData {
 #[derive(Debug, Clone, Default)]
 pub struct PortCapabilitiesData {
     // === Data Capabilities ===
-    /// Network speed (e.g., "1000 Mbps" for ethernet)
+    /// Network speed (e.g., "1 Gbps" for ethernet)
     pub speed: Option<String>,
-    /// Bitrate (e.g., "500000 bps" for CAN)
+    /// Bitrate (e.g., "500 kbps" for CAN)
     pub bitrate: Option<String>,
     /// Baud rate (e.g., "115200 baud" for UART)
     pub baud: Option<String>,
@@ -94,11 +115,11 @@ pub struct PortCapabilitiesData {
     pub protocols: Vec<String>,
 
     // === Power Capabilities ===
-    /// Voltage with range (e.g., "12V (7-28V)")
+    /// Voltage with range (e.g., "12 V (7-28 V)")
     pub voltage: Option<String>,
-    /// Maximum current (e.g., "3A max")
+    /// Maximum current (e.g., "3 A max")
     pub current: Option<String>,
-    /// Maximum power in watts (e.g., "36W max")
+    /// Maximum power (e.g., "36 W max")
     pub power_watts: Option<String>,
     /// Energy capacity for batteries (e.g., "55.5 Wh")
     pub capacity: Option<String>,
@@ -121,6 +142,8 @@ pub struct PortData {
     pub mesh_name: Option<String>,
     /// Port capabilities (speed, bitrate, protocol, etc.)
     pub capabilities: Option<PortCapabilitiesData>,
+    /// Pose as loaded from the HCDF before a local edit (None if unedited)
+    pub original_pose: Option<[f64; 6]>,
 }
 
 /// Antenna capabilities data - type-specific properties for wireless interfaces
@@ -153,6 +176,8 @@ pub struct AntennaData {
     pub mesh_name: Option<String>,
     /// Antenna capabilities (frequency, gain, protocol, etc.)
     pub capabilities: Option<AntennaCapabilitiesData>,
+    /// Pose as loaded from the HCDF before a local edit (None if unedited)
+    pub original_pose: Option<[f64; 6]>,
 }
 
 /// Sensor data - sensor with pose, axis alignment, and optional FOV geometry
@@ -165,6 +190,10 @@ pub struct SensorData {
     pub sensor_type: String,
     /// Driver name (icm45686, bmm350, etc.)
     pub driver: Option<String>,
+    /// Human-readable driver name from the driver registry (e.g., "Bosch BMI088 (IMU)")
+    pub driver_label: Option<String>,
+    /// Driver datasheet URL from the driver registry
+    pub datasheet: Option<String>,
     /// Pose offset: (x, y, z, roll, pitch, yaw) in meters/radians
     pub pose: Option<[f64; 6]>,
     /// Axis alignment for driver transforms
@@ -187,6 +216,8 @@ pub struct DeviceData {
     pub position: Option<[f64; 3]>,
     /// Orientation as [roll, pitch, yaw] in radians
     pub orientation: Option<[f64; 3]>,
+    /// Orientation as a [w, x, y, z] quaternion when the HCDF gave one; preferred over `orientation`
+    pub rotation: Option<[f64; 4]>,
     /// Legacy single model path (for backward compatibility)
     pub model_path: Option<String>,
     /// Composite visuals with individual poses
@@ -223,6 +254,7 @@ pub struct CameraSettings {
     pub elevation: f32,
     pub target: Vec3,
     pub target_focus: Vec3, // For smooth re-centering
+    pub target_orbit: Option<(f32, f32)>, // Azimuth and elevation of a recalled view
     pub sensitivity: f32,
     pub zoom_speed: f32,
     pub smooth_factor: f32,
@@ -237,6 +269,7 @@ impl Default for CameraSettings {
             elevation: 0.5, // Slightly elevated view
             target: Vec3::ZERO,
             target_focus: Vec3::ZERO,
+            target_orbit: None,
             sensitivity: 0.005,
             zoom_speed: 0.1,
             smooth_factor: 0.15,
@@ -294,7 +327,8 @@ pub struct FrameVisibility {
     /// Whether the frame hover came from a click/tap (sticky until next click)
     pub hovered_frame_from_click: bool,
     /// Per-device, per-toggle-group hidden state: (device_id, toggle_group) -> is_hidden
-    /// Default is visible (not hidden), so only hidden groups are tracked
+    /// Seeded from the visuals' `default_hidden` when a device is first loaded;
+    /// groups without an entry are visible
     pub hidden_toggles: std::collections::HashMap<(String, String), bool>,
     /// Per-device sensor (FOV) visibility (device_id -> show_sensors)
     pub device_sensors: std::collections::HashMap<String, bool>,
@@ -315,12 +349,16 @@ pub struct FrameVisibility {
     pub hovered_port: Option<String>,
     /// Whether the current port hover came from UI (true) or 3D (false)
     pub hovered_port_from_ui: bool,
+    /// Port selected for pose editing (device_id:port_name)
+    pub selected_port: Option<String>,
     /// Per-device antenna visibility (device_id -> show_antennas)
     pub device_antennas: std::collections::HashMap<String, bool>,
     /// Currently hovered antenna (device_id:antenna_name)
     pub hovered_antenna: Option<String>,
     /// Whether the current antenna hover came from UI (true) or 3D (false)
     pub hovered_antenna_from_ui: bool,
+    /// Antenna selected for pose editing (device_id:antenna_name)
+    pub selected_antenna: Option<String>,
     /// Per-sensor axis alignment mode: (device_id, sensor_name) -> show_aligned
     /// Default is true (show aligned), false shows raw physical axes
     pub sensor_axis_aligned: std::collections::HashMap<(String, String), bool>,
@@ -356,12 +394,41 @@ impl FrameVisibility {
 
     /// Set whether a toggle group is hidden for a specific device
     pub fn set_toggle_hidden(&mut self, device_id: &str, toggle_group: &str, hidden: bool) {
-        let key = (device_id.to_string(), toggle_group.to_string());
-        if hidden {
-            self.hidden_toggles.insert(key, true);
-        } else {
-            self.hidden_toggles.remove(&key);
+        self.hidden_toggles
+            .insert((device_id.to_string(), toggle_group.to_string()), hidden);
+    }
+
+    /// Initial hidden state of a toggle group, as declared by its visuals
+    pub fn default_toggle_hidden(visuals: &[VisualData], toggle_group: &str) -> bool {
+        visuals
+            .iter()
+            .any(|v| v.toggle.as_deref() == Some(toggle_group) && v.default_hidden)
+    }
+
+    /// Seed toggle state for a device's groups that have not been seen yet
+    /// (existing user choices are left untouched)
+    pub fn init_toggle_defaults(&mut self, device_id: &str, visuals: &[VisualData]) {
+        for group in Self::get_toggle_groups(visuals) {
+            let hidden = Self::default_toggle_hidden(visuals, &group);
+            self.hidden_toggles
+                .entry((device_id.to_string(), group))
+                .or_insert(hidden);
+        }
+    }
+
+    /// Toggle groups whose current state differs from the loaded default,
+    /// as (device_id, toggle_group, hidden) for recording on export
+    pub fn changed_toggle_states(&self, devices: &[DeviceData]) -> Vec<(String, String, bool)> {
+        let mut changed = Vec::new();
+        for device in devices {
+            for group in Self::get_toggle_groups(&device.visuals) {
+                let hidden = self.is_toggle_hidden(&device.id, &group);
+                if hidden != Self::default_toggle_hidden(&device.visuals, &group) {
+                    changed.push((device.id.clone(), group, hidden));
+                }
+            }
         }
+        changed
     }
 
     /// Get all unique toggle groups from a device's visuals
@@ -614,6 +681,8 @@ pub struct GraphVisualization {
     pub hovered_node: Option<String>,
     /// Cached topology data
     pub topology: Option<TopologyData>,
+    /// Parents whose subtrees are folded away
+    pub collapsed: std::collections::HashSet<String>,
 }
 
 /// Topology data for graph visualization
@@ -634,6 +703,54 @@ pub struct TopologyNode {
     pub children: Vec<String>,
 }
 
+impl TopologyData {
+    /// Flat graph of the loaded devices
+    pub fn from_devices(devices: &[DeviceData]) -> Self {
+        let nodes = devices
+            .iter()
+            .map(|d| TopologyNode {
+                id: d.id.clone(),
+                name: d.name.clone(),
+                board: d.board.clone(),
+                is_parent: false,
+                port: d.port,
+                children: Vec::new(),
+            })
+            .collect();
+        Self { nodes, root: None }
+    }
+
+    /// Graph of the loaded devices with the hierarchy of an HCDF topology
+    ///
+    /// Nodes without a loaded device (the parent comp, say) are added after
+    /// the devices, by name.
+    pub fn from_topology(topology: &Topology, devices: &[DeviceData]) -> Self {
+        let mut data = Self::from_devices(devices);
+        let mut graph = topology.to_graph();
+        graph.nodes.sort_by(|a, b| a.name.cmp(&b.name));
+
+        for node in graph.nodes {
+            let children = node.children.into_iter().map(|c| c.0).collect();
+            if let Some(existing) = data.nodes.iter_mut().find(|n| n.id == node.id.0) {
+                existing.is_parent = node.is_parent;
+                existing.port = node.port.or(existing.port);
+                existing.children = children;
+                continue;
+            }
+            data.nodes.push(TopologyNode {
+                id: node.id.0,
+                name: node.name,
+                board: node.board,
+                is_parent: node.is_parent,
+                port: node.port,
+                children,
+            });
+        }
+        data.root = graph.root.map(|r| r.0);
+        data
+    }
+}
+
 impl Default for GraphVisualization {
     fn default() -> Self {
         Self {
@@ -642,13 +759,18 @@ impl Default for GraphVisualization {
             zoom: 1.0,
             hovered_node: None,
             topology: None,
+            collapsed: std::collections::HashSet::new(),
         }
     }
 }
 
 /// Run the Bevy application
 pub fn run() {
+    // Bundle models are served from memory; the source must exist before AssetPlugin
+    let bundle_assets = BundleAssets::default();
     App::new()
+        .register_asset_source(BUNDLE_ASSET_SOURCE, bundle_assets.source())
+        .insert_resource(bundle_assets)
         .insert_resource(ClearColor(Color::srgb(0.1, 0.1, 0.15))) // Dark blue-gray background
         // Start with default continuous rendering - mobile will switch to power-saving mode
         .insert_resource(WinitSettings::default())
@@ -694,6 +816,7 @@ pub fn run() {
         .add_plugins(ScenePlugin)
         .add_plugins(ModelsPlugin)
         .add_plugins(UiPlugin)
+        .add_plugins(ComparePlugin)
         .add_systems(Update, (
             adjust_power_settings_for_mobile,
             apply_render_scale,
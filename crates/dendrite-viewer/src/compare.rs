@@ -0,0 +1,208 @@
+//! Compare mode: diff the loaded HCDF against a second file
+//!
+//! "Compare..." in the Compare section picks another `.hcdf`. Its
+//! device-level changes from the loaded document (`Hcdf::diff`) are
+//! listed there, and devices that moved, changed model or changed sensors
+//! are tinted orange in the scene through the `dendrite_scene::heatmap`
+//! machinery. Devices only the other file has aren't in the scene, so they
+//! are only listed. The diff is worked out again whenever the loaded
+//! document changes, and "Stop comparing" restores the normal colors.
+
+use bevy::prelude::*;
+use bevy_egui::egui;
+use dendrite_core::hcdf::Hcdf;
+use dendrite_core::{ChangeKind, FieldChange, HcdfDiff};
+use dendrite_scene::heatmap::{Heatmap, HeatmapMetric, HeatmapPlugin, HeatmapTargets, MetricRange};
+use std::collections::HashMap;
+
+use crate::models::VisualEntity;
+use crate::scene::DeviceEntity;
+use crate::ui::LoadedHcdf;
+
+/// Color of changed devices in the scene
+pub const CHANGED_COLOR: Color = Color::srgb(1.0, 0.55, 0.1);
+
+/// The file the loaded HCDF is compared with, and the changes found
+#[derive(Resource, Default)]
+pub struct HcdfCompare {
+    /// File name of the other document (None when not comparing)
+    pub filename: Option<String>,
+    other: Option<Hcdf>,
+    /// Changes from the loaded document to the other one
+    pub diff: HcdfDiff,
+    /// Why the last picked file couldn't be compared
+    pub error: Option<String>,
+}
+
+impl HcdfCompare {
+    /// Compare with a picked file; the diff follows on the next frame
+    pub fn load(&mut self, filename: &str, xml: &str) {
+        match Hcdf::from_xml(xml) {
+            Ok(hcdf) => {
+                tracing::info!("Comparing with {}", filename);
+                self.filename = Some(filename.to_string());
+                self.other = Some(hcdf);
+                self.error = None;
+            }
+            Err(e) => {
+                tracing::error!("Failed to parse {} for comparison: {:?}", filename, e);
+                self.error = Some(format!("{}: {}", filename, e));
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Device ids to highlight: devices in the scene that the other file changes
+    fn changed_devices(&self) -> impl Iterator<Item = &str> {
+        self.diff
+            .devices()
+            .filter(|d| d.kind != ChangeKind::Added)
+            .map(|d| d.key.as_str())
+    }
+}
+
+/// Plugin for Compare mode
+pub struct ComparePlugin;
+
+impl Plugin for ComparePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(HeatmapPlugin)
+            .init_resource::<HcdfCompare>()
+            .add_systems(Update, (update_compare_diff, update_compare_highlight).chain());
+    }
+}
+
+/// Work the diff out again when either document changes
+fn update_compare_diff(loaded: Res<LoadedHcdf>, mut compare: ResMut<HcdfCompare>) {
+    if !loaded.is_changed() && !compare.is_changed() {
+        return;
+    }
+    let new_diff = match (&loaded.0, &compare.other) {
+        (Some(loaded), Some(other)) => loaded.diff(other),
+        _ => HcdfDiff::default(),
+    };
+    if compare.diff != new_diff {
+        compare.diff = new_diff;
+    }
+}
+
+/// Tint the changed devices, or clear the tint when not comparing
+fn update_compare_highlight(
+    compare: Res<HcdfCompare>,
+    device_query: Query<(Entity, &DeviceEntity)>,
+    visual_query: Query<(Entity, &VisualEntity)>,
+    mut heatmap: ResMut<Heatmap>,
+    mut targets: ResMut<HeatmapTargets>,
+) {
+    let changed: HashMap<String, f64> = compare.changed_devices().map(|id| (id.to_string(), 1.0)).collect();
+    let metric = (!changed.is_empty()).then(|| HeatmapMetric {
+        label: "Changed".to_string(),
+        unit: String::new(),
+        range: MetricRange::Observed,
+        values: changed,
+        solid: Some(CHANGED_COLOR),
+    });
+
+    // Only changed devices are targets, so the rest keep their materials
+    let mut entities: HashMap<String, Vec<Entity>> = HashMap::new();
+    if let Some(metric) = &metric {
+        for (entity, visual) in visual_query.iter() {
+            if metric.values.contains_key(&visual.device_id) {
+                entities.entry(visual.device_id.clone()).or_default().push(entity);
+            }
+        }
+        for (entity, device) in device_query.iter() {
+            if metric.values.contains_key(&device.device_id) {
+                entities.entry(device.device_id.clone()).or_insert_with(|| vec![entity]);
+            }
+        }
+        // Query order isn't stable; keep it from looking like a change
+        for roots in entities.values_mut() {
+            roots.sort();
+        }
+    }
+
+    heatmap.set_if_neq(Heatmap(metric));
+    targets.set_if_neq(HeatmapTargets(entities));
+}
+
+/// One line describing a field change
+pub fn describe_change(change: &FieldChange) -> String {
+    match change {
+        FieldChange::Pose { offset, rotation, .. } => {
+            let distance = offset.iter().map(|d| d * d).sum::<f64>().sqrt();
+            format!("moved {:.1} mm, turned {:.1}°", distance * 1000.0, rotation.to_degrees())
+        }
+        FieldChange::Model { visual, to, .. } => {
+            let target = to.as_deref().unwrap_or("none");
+            match visual {
+                Some(visual) => format!("visual {} model: {}", visual, target),
+                None => format!("model: {}", target),
+            }
+        }
+        FieldChange::SensorAdded { name, .. } => format!("sensor added: {}", name),
+        FieldChange::SensorRemoved { name, .. } => format!("sensor removed: {}", name),
+        FieldChange::SensorModified { name, .. } => format!("sensor changed: {}", name),
+        FieldChange::PortAdded { name, .. } => format!("port added: {}", name),
+        FieldChange::PortRemoved { name, .. } => format!("port removed: {}", name),
+        FieldChange::PortModified { name, .. } => format!("port changed: {}", name),
+        FieldChange::Field { name, .. } => format!("{} changed", name),
+    }
+}
+
+/// Draw the Compare section: the compared file and its changes
+///
+/// Returns the id of a device the user clicked, for selecting it.
+pub fn compare_section(ui: &mut egui::Ui, compare: &mut HcdfCompare, ui_scale: f32) -> Option<String> {
+    let mut clicked = None;
+
+    if let Some(error) = &compare.error {
+        ui.label(egui::RichText::new(error).size(10.0 * ui_scale).color(egui::Color32::from_rgb(220, 100, 100)));
+    }
+    let Some(filename) = compare.filename.clone() else {
+        ui.label(
+            egui::RichText::new("Pick a second file with \"Compare...\" to see what it changes")
+                .size(10.0 * ui_scale)
+                .color(egui::Color32::GRAY),
+        );
+        return None;
+    };
+
+    ui.horizontal(|ui| {
+        ui.label(egui::RichText::new(format!("vs {}", filename)).size(11.0 * ui_scale));
+        if ui.small_button("Stop comparing").clicked() {
+            compare.clear();
+        }
+    });
+    if compare.diff.devices().next().is_none() {
+        ui.label(egui::RichText::new("No device changes").size(10.0 * ui_scale).color(egui::Color32::GRAY));
+        return None;
+    }
+
+    let orange = egui::Color32::from_rgb(255, 140, 25);
+    for device in compare.diff.devices() {
+        let (marker, color) = match device.kind {
+            ChangeKind::Added => ("+", egui::Color32::from_rgb(100, 200, 100)),
+            ChangeKind::Removed => ("-", egui::Color32::from_rgb(220, 100, 100)),
+            ChangeKind::Modified => ("~", orange),
+        };
+        let label = egui::RichText::new(format!("{} {}", marker, device.name)).size(11.0 * ui_scale).color(color);
+        if device.kind == ChangeKind::Added {
+            ui.label(label).on_hover_text("Only in the compared file");
+        } else if ui.add(egui::Label::new(label).sense(egui::Sense::click())).on_hover_text("Select").clicked() {
+            clicked = Some(device.key.clone());
+        }
+        for change in &device.changes {
+            ui.label(
+                egui::RichText::new(format!("    {}", describe_change(change)))
+                    .size(10.0 * ui_scale)
+                    .color(egui::Color32::GRAY),
+            );
+        }
+    }
+
+    clicked
+}
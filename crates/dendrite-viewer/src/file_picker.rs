@@ -3,12 +3,18 @@
 //! Provides a modal-based file picker that can be used for:
 //! - Firmware upload (pick .bin files)
 //! - HCDF import/export (pick/save .hcdf files)
+//! - Offline bundles (`.dzip` from `dendrite bundle`), whose models are
+//!   served from memory through the `bundle://` asset source
 //! - Any future file operations
 //!
 //! Uses JavaScript interop for native file dialogs in the browser.
 
+use bevy::asset::io::memory::{Dir, MemoryAssetReader};
+use bevy::asset::io::AssetSourceBuilder;
 use bevy::prelude::*;
-use std::collections::VecDeque;
+use dendrite_core::{read_bundle, BundleError, BundleIssue};
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
 /// File picker plugin
@@ -38,8 +44,12 @@ pub enum FilePickerContext {
     FirmwareUpload { device_id: String },
     /// Importing an HCDF file
     HcdfImport,
+    /// Picking a second HCDF file to compare the loaded one with
+    HcdfCompare,
     /// Exporting/saving the current HCDF
     HcdfExport,
+    /// Exporting the current HCDF as URDF
+    UrdfExport,
     /// Custom context with a string identifier
     Custom(String),
 }
@@ -68,6 +78,14 @@ impl FileFilter {
         }
     }
 
+    /// HCDF files and offline bundles
+    pub fn hcdf_or_bundle() -> Self {
+        Self {
+            name: "HCDF Files and Bundles".to_string(),
+            extensions: vec!["hcdf".to_string(), "xml".to_string(), "dzip".to_string(), "zip".to_string()],
+        }
+    }
+
     pub fn all() -> Self {
         Self {
             name: "All Files".to_string(),
@@ -89,6 +107,67 @@ impl FileFilter {
     }
 }
 
+/// Asset source serving the models of a loaded bundle
+pub const BUNDLE_ASSET_SOURCE: &str = "bundle";
+
+/// Whether picked file content is a bundle (a zip) rather than an HCDF
+pub fn is_bundle(content: &[u8]) -> bool {
+    content.starts_with(b"PK\x03\x04")
+}
+
+/// Models of the last loaded bundle, kept in memory for the asset server
+#[derive(Resource, Clone, Default)]
+pub struct BundleAssets {
+    /// Files behind the `bundle://` asset source
+    dir: Dir,
+    /// Asset path for each model href of the bundle
+    paths: HashMap<String, String>,
+    /// Files of the bundle in `dir`
+    files: Vec<PathBuf>,
+    /// Bundles loaded so far; each gets its own directory so models cached
+    /// for an earlier bundle aren't reused
+    loads: usize,
+}
+
+impl BundleAssets {
+    /// Asset source to register (before `AssetPlugin`) under [`BUNDLE_ASSET_SOURCE`]
+    pub fn source(&self) -> AssetSourceBuilder {
+        let dir = self.dir.clone();
+        AssetSourceBuilder::new(move || Box::new(MemoryAssetReader { root: dir.clone() }))
+    }
+
+    /// Extract a bundle's models, replacing the previous bundle's, and return
+    /// its HCDF with the models that were missing or didn't match their SHA
+    pub fn load(&mut self, data: &[u8]) -> Result<(String, Vec<BundleIssue>), BundleError> {
+        let bundle = read_bundle(data)?;
+        self.clear();
+        self.loads += 1;
+        for (model, content) in bundle.models {
+            let path = PathBuf::from(self.loads.to_string()).join(&model.path);
+            let asset_path = format!("{}://{}/{}", BUNDLE_ASSET_SOURCE, self.loads, model.path);
+            if !self.files.contains(&path) {
+                self.dir.insert_asset(&path, content);
+                self.files.push(path);
+            }
+            self.paths.insert(model.href, asset_path);
+        }
+        Ok((bundle.hcdf, bundle.issues))
+    }
+
+    /// Forget the loaded bundle, for documents loaded from elsewhere
+    pub fn clear(&mut self) {
+        for path in self.files.drain(..) {
+            self.dir.remove_asset(Path::new(&path));
+        }
+        self.paths.clear();
+    }
+
+    /// Asset path of a model of the loaded bundle
+    pub fn asset_path(&self, href: &str) -> Option<&str> {
+        self.paths.get(href).map(String::as_str)
+    }
+}
+
 /// Request to open the file picker
 #[derive(Debug, Clone)]
 pub struct FilePickerRequest {
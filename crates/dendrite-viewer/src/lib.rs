@@ -4,6 +4,7 @@
 //! Based on dendrite-web but without network scanning and firmware features.
 
 mod app;
+mod compare;
 mod file_picker;
 mod models;
 mod scene;
@@ -11,6 +12,12 @@ mod ui;
 
 use wasm_bindgen::prelude::*;
 
+/// Native entry point (see `main.rs`)
+#[cfg(not(target_arch = "wasm32"))]
+pub fn run_native() {
+    app::run();
+}
+
 /// WASM entry point
 #[wasm_bindgen(start)]
 pub fn main() {
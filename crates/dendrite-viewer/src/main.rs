@@ -0,0 +1,12 @@
+//! Native desktop entry point
+//!
+//! `dendrite-viewer [--hcdf <url>] [--fetch-timeout <secs>]`
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    dendrite_viewer::run_native();
+}
+
+// The browser build starts from the library's `#[wasm_bindgen(start)]`
+#[cfg(target_arch = "wasm32")]
+fn main() {}
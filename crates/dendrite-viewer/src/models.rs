@@ -5,10 +5,12 @@ use bevy::ecs::schedule::ApplyDeferred;
 use bevy::prelude::*;
 use bevy_picking::mesh_picking::ray_cast::RayCastBackfaces;
 use bevy_picking::prelude::Pickable;
+use dendrite_scene::keepout::{pose_rotation, pose_transform};
 use std::collections::HashMap;
 
 use crate::app::{AntennaCapabilitiesData, AntennaData, AxisAlignData, DeviceRegistry, DeviceStatus, FrameVisibility, GeometryData, PortCapabilitiesData, PortData, SensorData, VisualData};
 use crate::scene::DeviceEntity;
+use crate::file_picker::BundleAssets;
 use crate::ui::HcdfBaseUrl;
 
 /// Component marking a visual child entity
@@ -50,7 +52,7 @@ pub struct SensorAxisEntity {
     pub category: String,
     /// Sensor type (optical_flow, tof, etc.)
     pub sensor_type: String,
-    /// Driver name
+    /// Driver display name (registry label, or raw driver name if unknown)
     pub driver: Option<String>,
     /// Axis alignment info for tooltip
     pub axis_align: Option<AxisAlignData>,
@@ -72,7 +74,7 @@ pub struct SensorFovEntity {
     pub category: String,
     /// Sensor type (optical_flow, tof, etc.)
     pub sensor_type: String,
-    /// Driver name
+    /// Driver display name (registry label, or raw driver name if unknown)
     pub driver: Option<String>,
     /// Axis alignment info for tooltip
     pub axis_align: Option<AxisAlignData>,
@@ -153,13 +155,15 @@ impl Plugin for ModelsPlugin {
             .add_systems(Update, sync_device_entities.after(load_models))
             .add_systems(Update, sync_sensor_entities.after(sync_device_entities))
             .add_systems(Update, sync_port_entities.after(sync_device_entities))
+            .add_systems(Update, sync_port_transforms.after(sync_port_entities))
             .add_systems(Update, (
                 tag_gltf_node_names.after(sync_device_entities),
                 ApplyDeferred,
                 link_port_meshes,
                 link_antenna_meshes,
             ).chain())
-            .add_systems(Update, update_visual_visibility.after(sync_device_entities))
+            .add_systems(Update, init_toggle_visibility)
+            .add_systems(Update, update_visual_visibility.after(sync_device_entities).after(init_toggle_visibility))
             .add_systems(Update, update_sensor_axis_visibility.after(sync_sensor_entities))
             .add_systems(Update, update_sensor_axis_hover_alpha.after(update_sensor_axis_visibility))
             .add_systems(Update, update_sensor_fov_visibility.after(sync_sensor_entities))
@@ -297,6 +301,7 @@ fn sync_device_entities(
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     base_url: Res<HcdfBaseUrl>,
+    bundle_assets: Res<BundleAssets>,
 ) {
     // Collect existing device IDs and entities
     let existing_ids: HashMap<String, Entity> = existing_devices
@@ -338,12 +343,8 @@ fn sync_device_entities(
                 }
                 // Update rotation if explicit orientation from HCDF import
                 if let Some(orient) = device.orientation {
-                    let new_rotation = Quat::from_euler(
-                        EulerRot::ZYX,
-                        orient[2] as f32, // yaw (Z)
-                        orient[1] as f32, // pitch (Y)
-                        orient[0] as f32, // roll (X)
-                    );
+                    // A quaternion from the HCDF avoids the Euler singularity at ±90° pitch
+                    let new_rotation = pose_rotation(orient, device.rotation);
                     // Only update if rotation changed significantly
                     if transform.rotation.angle_between(new_rotation) > 0.001 {
                         tracing::info!(
@@ -438,7 +439,7 @@ fn sync_device_entities(
             // Check if all visuals are ready (loaded or failed)
             let all_ready = device.visuals.iter().all(|v| {
                 if let Some(ref model_path) = v.model_path {
-                    let asset_path = normalize_model_path(model_path, &base_url.0);
+                    let asset_path = resolve_model_path(model_path, &base_url.0, &bundle_assets);
                     model_cache.ready.contains_key(&asset_path) || model_cache.models.contains_key(&asset_path)
                 } else {
                     true // No model = ready
@@ -448,7 +449,7 @@ fn sync_device_entities(
             // Start loading any visuals that aren't loading yet
             for visual in &device.visuals {
                 if let Some(ref model_path) = visual.model_path {
-                    let asset_path = normalize_model_path(model_path, &base_url.0);
+                    let asset_path = resolve_model_path(model_path, &base_url.0, &bundle_assets);
                     if !model_cache.loading.contains_key(&asset_path)
                         && !model_cache.models.contains_key(&asset_path)
                         && !model_cache.ready.contains_key(&asset_path)
@@ -479,7 +480,7 @@ fn sync_device_entities(
                 let visual_transform = visual_to_transform(visual);
 
                 if let Some(ref model_path) = visual.model_path {
-                    let asset_path = normalize_model_path(model_path, &base_url.0);
+                    let asset_path = resolve_model_path(model_path, &base_url.0, &bundle_assets);
                     if let Some(scene_handle) = model_cache.models.get(&asset_path) {
                         tracing::info!("Spawning visual {} for device {} from {}", visual.name, device.id, asset_path);
                         let child = commands.spawn((
@@ -502,7 +503,7 @@ fn sync_device_entities(
 
         // Legacy: If device has a single model_path, try to load it from the server
         if let Some(ref model_path) = device.model_path {
-            let asset_path = normalize_model_path(model_path, &base_url.0);
+            let asset_path = resolve_model_path(model_path, &base_url.0, &bundle_assets);
 
             // Start loading if not already loading or loaded
             if !model_cache.loading.contains_key(&asset_path)
@@ -549,6 +550,15 @@ fn sync_device_entities(
     }
 }
 
+/// Asset path for a model: from the loaded bundle when it has the model,
+/// otherwise normalized
+fn resolve_model_path(path: &str, base_url: &Option<String>, bundle_assets: &BundleAssets) -> String {
+    match bundle_assets.asset_path(path) {
+        Some(asset_path) => asset_path.to_string(),
+        None => normalize_model_path(path, base_url),
+    }
+}
+
 /// Normalize model path for asset loading
 /// If base_url is provided and path is relative, prepend the base URL
 /// For local files (base_url is None), use relative path for local loading
@@ -587,18 +597,20 @@ fn normalize_model_path(path: &str, base_url: &Option<String>) -> String {
 /// Convert visual pose to Transform
 /// Pose is [x, y, z, roll, pitch, yaw] in meters/radians
 fn visual_to_transform(visual: &VisualData) -> Transform {
-    if let Some(pose) = visual.pose {
-        let translation = Vec3::new(pose[0] as f32, pose[1] as f32, pose[2] as f32);
-        // SDF convention: roll, pitch, yaw (extrinsic XYZ = intrinsic ZYX)
-        let rotation = Quat::from_euler(
-            EulerRot::ZYX,
-            pose[5] as f32, // yaw (Z)
-            pose[4] as f32, // pitch (Y)
-            pose[3] as f32, // roll (X)
-        );
-        Transform::from_translation(translation).with_rotation(rotation)
-    } else {
-        Transform::IDENTITY
+    visual.pose.map(pose_transform).unwrap_or(Transform::IDENTITY)
+}
+
+/// Seed toggle-group visibility from HCDF `default_hidden` for newly loaded devices
+fn init_toggle_visibility(
+    registry: Res<DeviceRegistry>,
+    mut frame_visibility: ResMut<FrameVisibility>,
+) {
+    if !registry.is_changed() {
+        return;
+    }
+
+    for device in &registry.devices {
+        frame_visibility.init_toggle_defaults(&device.id, &device.visuals);
     }
 }
 
@@ -786,7 +798,7 @@ fn spawn_sensor_axis_frame(
             sensor_name: sensor.name.clone(),
             category: sensor.category.clone(),
             sensor_type: sensor.sensor_type.clone(),
-            driver: sensor.driver.clone(),
+            driver: sensor.driver_label.clone().or_else(|| sensor.driver.clone()),
             axis_align: sensor.axis_align.clone(),
             mode,
         },
@@ -917,7 +929,7 @@ fn spawn_sensor_fov_with_color(
         fov_name: fov_name.map(|s| s.to_string()),
         category: sensor.category.clone(),
         sensor_type: sensor.sensor_type.clone(),
-        driver: sensor.driver.clone(),
+        driver: sensor.driver_label.clone().or_else(|| sensor.driver.clone()),
         axis_align: sensor.axis_align.clone(),
     };
 
@@ -1268,6 +1280,40 @@ fn sync_port_entities(
     }
 }
 
+/// Move port and antenna highlights when their pose changes in the registry
+/// (e.g., a pose edit)
+fn sync_port_transforms(
+    registry: Res<DeviceRegistry>,
+    mut ports: Query<(&PortGeometryEntity, &mut Transform), Without<AntennaGeometryEntity>>,
+    mut antennas: Query<(&AntennaGeometryEntity, &mut Transform), Without<PortGeometryEntity>>,
+) {
+    if !registry.is_changed() {
+        return;
+    }
+
+    let device = |id: &str| registry.devices.iter().find(|d| d.id == id);
+
+    for (port, mut transform) in ports.iter_mut() {
+        let Some(data) = device(&port.device_id).and_then(|d| d.ports.iter().find(|p| p.name == port.port_name)) else {
+            continue;
+        };
+        let target = pose_to_transform(data.pose);
+        if *transform != target {
+            *transform = target;
+        }
+    }
+
+    for (antenna, mut transform) in antennas.iter_mut() {
+        let Some(data) = device(&antenna.device_id).and_then(|d| d.antennas.iter().find(|a| a.name == antenna.antenna_name)) else {
+            continue;
+        };
+        let target = pose_to_transform(data.pose);
+        if *transform != target {
+            *transform = target;
+        }
+    }
+}
+
 /// Spawn a port geometry as a transparent highlight
 fn spawn_port_geometry(
     commands: &mut Commands,
@@ -1393,18 +1439,7 @@ fn spawn_antenna_geometry(
 
 /// Convert pose array to Transform
 fn pose_to_transform(pose: Option<[f64; 6]>) -> Transform {
-    if let Some(p) = pose {
-        let translation = Vec3::new(p[0] as f32, p[1] as f32, p[2] as f32);
-        let rotation = Quat::from_euler(
-            EulerRot::ZYX,
-            p[5] as f32, // yaw
-            p[4] as f32, // pitch
-            p[3] as f32, // roll
-        );
-        Transform::from_translation(translation).with_rotation(rotation)
-    } else {
-        Transform::IDENTITY
-    }
+    pose.map(pose_transform).unwrap_or(Transform::IDENTITY)
 }
 
 /// Update sensor axis frame visibility based on "Show Reference Frames" toggle
@@ -1486,7 +1521,8 @@ fn update_port_visibility(
 
         // Check if this port is hovered
         let port_key = format!("{}:{}", port.device_id, port.port_name);
-        let is_hovered = frame_visibility.hovered_port.as_ref() == Some(&port_key);
+        let is_hovered = frame_visibility.hovered_port.as_ref() == Some(&port_key)
+            || frame_visibility.selected_port.as_ref() == Some(&port_key);
 
         // Update material alpha based on hover state
         if let Some(material) = materials.get_mut(&material_handle.0) {
@@ -1518,7 +1554,8 @@ fn update_antenna_visibility(
 
         // Check if this antenna is hovered
         let antenna_key = format!("{}:{}", antenna.device_id, antenna.antenna_name);
-        let is_hovered = frame_visibility.hovered_antenna.as_ref() == Some(&antenna_key);
+        let is_hovered = frame_visibility.hovered_antenna.as_ref() == Some(&antenna_key)
+            || frame_visibility.selected_antenna.as_ref() == Some(&antenna_key);
 
         // Update material alpha based on hover state
         if let Some(material) = materials.get_mut(&material_handle.0) {
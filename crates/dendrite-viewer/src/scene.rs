@@ -7,6 +7,11 @@ use bevy::render::alpha::AlphaMode;
 use bevy::camera::primitives::MeshAabb;  // Trait for compute_aabb
 use bevy_egui::{egui, EguiContexts};
 use bevy_picking::prelude::{Click, Out, Over, Pointer, PointerButton};
+use dendrite_core::resolve_frame_poses;
+use dendrite_scene::axes::{axis_transform, TRIAD_AXES};
+use dendrite_scene::camera_views::ease_orbit;
+use dendrite_scene::frame_overlay::{FrameOverlayPlugin, FrameOverlayTarget};
+use dendrite_scene::keepout::pose_rotation;
 
 use crate::app::{ActiveRotationAxis, ActiveRotationField, AntennaCapabilitiesData, CameraSettings, DeviceOrientations, DevicePositions, DeviceRegistry, FrameVisibility, PortCapabilitiesData, SelectedDevice, ShowRotationAxis, UiLayout, WorldSettings};
 use crate::models::{AntennaEntity, AntennaMeshTarget, ExcludeFromBounds, PortEntity, PortMeshTarget, SensorAxisEntity, SensorFovEntity};
@@ -19,7 +24,8 @@ pub struct ScenePlugin;
 
 impl Plugin for ScenePlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, setup_scene)
+        app.add_plugins(FrameOverlayPlugin)
+            .add_systems(Startup, setup_scene)
             .add_systems(Update, (
                 update_camera,
                 handle_deselection,
@@ -27,6 +33,7 @@ impl Plugin for ScenePlugin {
                 update_device_orientations,
                 update_selection_highlight,
                 update_effective_rotation_axis,
+                update_frame_overlay_target,
                 update_world_visibility,
                 update_grid_spacing,
                 update_frame_gizmos,
@@ -443,6 +450,9 @@ fn update_camera(
 
     // Orbit with left mouse drag (only when UI doesn't want pointer)
     if mouse_button.pressed(MouseButton::Left) && !egui_wants_pointer {
+        if total_motion != Vec2::ZERO {
+            settings.target_orbit = None;
+        }
         settings.azimuth -= total_motion.x * settings.sensitivity;
         settings.elevation = (settings.elevation - total_motion.y * settings.sensitivity)
             .clamp(-1.5, 1.5);
@@ -482,6 +492,7 @@ fn update_camera(
         if let Some(touch) = touch_input.iter().next() {
             let delta = touch.delta();
             if delta != Vec2::ZERO {
+                settings.target_orbit = None;
                 settings.azimuth -= delta.x * settings.sensitivity;
                 settings.elevation = (settings.elevation - delta.y * settings.sensitivity)
                     .clamp(-1.5, 1.5);
@@ -518,12 +529,18 @@ fn update_camera(
         }
     }
 
-    // Smooth interpolation for zoom and target
+    // Smooth interpolation for zoom, target and recalled view angles
     let dt = time.delta_secs();
     let lerp_factor = 1.0 - (-settings.smooth_factor * 60.0 * dt).exp();
     settings.distance += (settings.target_distance - settings.distance) * lerp_factor;
     let target_delta = (settings.target_focus - settings.target) * lerp_factor;
     settings.target += target_delta;
+    if let Some(target_orbit) = settings.target_orbit {
+        let CameraSettings { azimuth, elevation, .. } = &mut *settings;
+        if ease_orbit(azimuth, elevation, target_orbit, lerp_factor) {
+            settings.target_orbit = None;
+        }
+    }
 
     // Update camera position (ENU: Z is up, spherical coordinates)
     if let Ok(mut transform) = camera_query.single_mut() {
@@ -1220,6 +1237,20 @@ fn update_effective_rotation_axis(
     ));
 }
 
+/// Point the FLU/ENU overlay at the selected device
+fn update_frame_overlay_target(
+    selected: Res<SelectedDevice>,
+    device_query: Query<(Entity, &DeviceEntity)>,
+    mut target: ResMut<FrameOverlayTarget>,
+) {
+    let entity = selected.0.as_ref().and_then(|id| {
+        device_query.iter()
+            .find(|(_, d)| &d.device_id == id)
+            .map(|(e, _)| e)
+    });
+    target.set_if_neq(FrameOverlayTarget(entity));
+}
+
 /// Update visibility of world grid and axis based on settings
 fn update_world_visibility(
     world_settings: Res<WorldSettings>,
@@ -1432,71 +1463,50 @@ fn update_frame_gizmos(
             None => continue, // Device not yet spawned
         };
 
-        for frame in &device.frames {
+        // Resolve parent chains to device-relative poses; a broken hierarchy
+        // falls back to treating every pose as device-relative
+        let resolved_poses = resolve_frame_poses(&device.frames).ok();
+
+        for (index, frame) in device.frames.iter().enumerate() {
             // Skip frames that are individually hidden
             if !frame_visibility.is_frame_visible(&device.id, &frame.name) {
                 continue;
             }
 
-            // Parse frame pose (local to device)
-            let frame_pose = frame.pose.unwrap_or([0.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+            // Frame pose (local to device)
+            let resolved = resolved_poses.as_ref().map(|poses| &poses[index]);
+            let frame_pose = resolved
+                .map(|pose| pose.to_array())
+                .or(frame.pose)
+                .unwrap_or([0.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
             let frame_translation = Vec3::new(
                 frame_pose[0] as f32,
                 frame_pose[1] as f32,
                 frame_pose[2] as f32,
             );
-            let frame_rotation = Quat::from_euler(
-                EulerRot::ZYX,
-                frame_pose[5] as f32, // yaw
-                frame_pose[4] as f32, // pitch
-                frame_pose[3] as f32, // roll
+            // A quaternion from the HCDF avoids the Euler singularity at ±90° pitch
+            let frame_rotation = pose_rotation(
+                [frame_pose[3], frame_pose[4], frame_pose[5]],
+                resolved.and_then(|pose| pose.quaternion),
             );
 
             let description = frame.description.clone().unwrap_or_default();
 
-            // X axis (red) - cylinder rotated to point along X, positioned in local space
-            let x_axis_local_pos = frame_translation + frame_rotation * Vec3::X * (axis_length / 2.0);
-            let x_rotation = frame_rotation * Quat::from_rotation_z(-std::f32::consts::FRAC_PI_2);
-            let x_entity = commands.spawn((
-                Mesh3d(axis_mesh.clone()),
-                MeshMaterial3d(x_material.clone()),
-                Transform::from_translation(x_axis_local_pos).with_rotation(x_rotation),
-                FrameGizmo {
-                    device_id: device.id.clone(),
-                    frame_name: frame.name.clone(),
-                    description: description.clone(),
-                },
-            )).id();
-            commands.entity(device_entity).add_child(x_entity);
-
-            // Y axis (green) - cylinder along Y (default orientation)
-            let y_axis_local_pos = frame_translation + frame_rotation * Vec3::Y * (axis_length / 2.0);
-            let y_entity = commands.spawn((
-                Mesh3d(axis_mesh.clone()),
-                MeshMaterial3d(y_material.clone()),
-                Transform::from_translation(y_axis_local_pos).with_rotation(frame_rotation),
-                FrameGizmo {
-                    device_id: device.id.clone(),
-                    frame_name: frame.name.clone(),
-                    description: description.clone(),
-                },
-            )).id();
-            commands.entity(device_entity).add_child(y_entity);
-
-            // Z axis (blue) - cylinder rotated to point along Z
-            let z_axis_local_pos = frame_translation + frame_rotation * Vec3::Z * (axis_length / 2.0);
-            let z_rotation = frame_rotation * Quat::from_rotation_x(std::f32::consts::FRAC_PI_2);
-            let z_entity = commands.spawn((
-                Mesh3d(axis_mesh.clone()),
-                MeshMaterial3d(z_material.clone()),
-                Transform::from_translation(z_axis_local_pos).with_rotation(z_rotation),
-                FrameGizmo {
-                    device_id: device.id.clone(),
-                    frame_name: frame.name.clone(),
-                    description: description.clone(),
-                },
-            )).id();
-            commands.entity(device_entity).add_child(z_entity);
+            // One cylinder per axis (X red, Y green, Z blue), positioned in device space
+            let frame_transform = Transform::from_translation(frame_translation).with_rotation(frame_rotation);
+            for (axis, material) in TRIAD_AXES.into_iter().zip([&x_material, &y_material, &z_material]) {
+                let axis_entity = commands.spawn((
+                    Mesh3d(axis_mesh.clone()),
+                    MeshMaterial3d(material.clone()),
+                    axis_transform(frame_transform, axis, axis_length),
+                    FrameGizmo {
+                        device_id: device.id.clone(),
+                        frame_name: frame.name.clone(),
+                        description: description.clone(),
+                    },
+                )).id();
+                commands.entity(device_entity).add_child(axis_entity);
+            }
         }
     }
 }
@@ -1966,7 +1976,11 @@ fn on_port_click(
         if frame_visibility.hovered_port.as_ref() == Some(&port_key) && frame_visibility.hovered_port_from_ui {
             frame_visibility.hovered_port = None;
             frame_visibility.hovered_port_from_ui = false;
+            frame_visibility.selected_port = None;
         } else {
+            // Fallback-geometry ports have a pose that can be edited
+            frame_visibility.selected_port = Some(port_key.clone());
+            frame_visibility.selected_antenna = None;
             frame_visibility.hovered_port = Some(port_key);
             frame_visibility.hovered_port_from_ui = true; // Reuse this flag to make it sticky
             // Clear other sticky selections
@@ -2205,7 +2219,11 @@ fn on_antenna_click(
         if frame_visibility.hovered_antenna.as_ref() == Some(&antenna_key) && frame_visibility.hovered_antenna_from_ui {
             frame_visibility.hovered_antenna = None;
             frame_visibility.hovered_antenna_from_ui = false;
+            frame_visibility.selected_antenna = None;
         } else {
+            // Fallback-geometry antennas have a pose that can be edited
+            frame_visibility.selected_antenna = Some(antenna_key.clone());
+            frame_visibility.selected_port = None;
             frame_visibility.hovered_antenna = Some(antenna_key);
             frame_visibility.hovered_antenna_from_ui = true;
             // Clear other sticky selections
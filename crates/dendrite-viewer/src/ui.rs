@@ -4,21 +4,29 @@ use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 use bevy_egui::{egui, EguiContexts, EguiPrimaryContextPass};
 
-use crate::app::{ActiveRotationAxis, ActiveRotationField, AntennaCapabilitiesData, AntennaData, AxisAlignData, CameraSettings, DeviceData, DeviceOrientations, DevicePositions, DeviceRegistry, DeviceStatus, FovData, FrameData, FrameVisibility, GeometryData, GraphVisualization, PortCapabilitiesData, PortData, SelectedDevice, SensorData, ShowRotationAxis, TopologyData, TopologyNode, UiLayout, VisualData, WorldSettings};
-use crate::file_picker::{FileFilter, FilePickerContext, FilePickerState, PendingFileResults, trigger_file_open};
-use dendrite_core::hcdf::Hcdf;
+use crate::app::{ActiveRotationAxis, ActiveRotationField, AntennaCapabilitiesData, AntennaData, AxisAlignData, CameraSettings, DeviceData, DeviceOrientations, DevicePositions, DeviceRegistry, DeviceStatus, FovData, FrameData, FrameVisibility, GeometryData, GraphVisualization, PortCapabilitiesData, PortData, SelectedDevice, SensorData, ShowRotationAxis, TopologyData, UiLayout, VisualData, WorldSettings};
+use crate::compare::{compare_section, HcdfCompare};
+use crate::file_picker::{is_bundle, BundleAssets, FileFilter, FilePickerContext, FilePickerState, PendingFileResults, trigger_file_open, trigger_file_save};
+use dendrite_core::hcdf::{Hcdf, Pose};
+use dendrite_core::{DriverRegistry, Topology};
+use dendrite_scene::camera_views::{views_section, CameraView, CameraViews};
+use dendrite_scene::edit_history::{EditHistory, EditPose, PoseEdit};
+use dendrite_scene::frame_overlay::{forward_enu, heading_degrees, ShowFrameOverlay};
+use dendrite_scene::graph_layout::radial_layout;
+use dendrite_scene::hcdf_convert::{sensor_driver_datasheet, sensor_driver_label};
 
 /// Grouped system parameters for the main UI system to work around Bevy's 16-param limit
 #[derive(SystemParam)]
 pub struct UiParams<'w, 's> {
     pub contexts: EguiContexts<'w, 's>,
-    pub registry: Res<'w, DeviceRegistry>,
+    pub registry: ResMut<'w, DeviceRegistry>,
     pub selected: ResMut<'w, SelectedDevice>,
     pub camera_settings: ResMut<'w, CameraSettings>,
     pub positions: ResMut<'w, DevicePositions>,
     pub orientations: ResMut<'w, DeviceOrientations>,
     pub active_rotation_field: ResMut<'w, ActiveRotationField>,
     pub show_rotation_axis: ResMut<'w, ShowRotationAxis>,
+    pub show_frame_overlay: ResMut<'w, ShowFrameOverlay>,
     pub world_settings: ResMut<'w, WorldSettings>,
     pub frame_visibility: ResMut<'w, FrameVisibility>,
     pub device_query: Query<'w, 's, (&'static crate::scene::DeviceEntity, &'static mut Transform)>,
@@ -26,8 +34,13 @@ pub struct UiParams<'w, 's> {
     pub pending_file_results: Res<'w, PendingFileResults>,
     pub graph_vis: ResMut<'w, GraphVisualization>,
     pub pending_removals: ResMut<'w, PendingDeviceRemovals>,
+    pub archived: ResMut<'w, ArchivedDevices>,
     pub url_input: ResMut<'w, HcdfUrlInput>,
     pub hosted_mode: Res<'w, HostedMode>,
+    pub loaded_hcdf: ResMut<'w, LoadedHcdf>,
+    pub edit_history: ResMut<'w, EditHistory>,
+    pub camera_views: ResMut<'w, CameraViews>,
+    pub compare: ResMut<'w, HcdfCompare>,
 }
 
 pub struct UiPlugin;
@@ -41,7 +54,11 @@ impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
         // Initialize resources
         app.init_resource::<PendingHcdfContent>()
+            .init_resource::<EditHistory>()
+            .insert_resource(CameraViews::load())
+            .init_resource::<LoadedHcdf>()
             .init_resource::<PendingDeviceRemovals>()
+            .init_resource::<ArchivedDevices>()
             .init_resource::<HcdfUrlInput>()
             .init_resource::<HcdfBaseUrl>()
             .init_resource::<HostedMode>()
@@ -54,30 +71,50 @@ impl Plugin for UiPlugin {
     }
 }
 
-/// Process pending device removals
+/// Process pending device removals and restores of archived devices
 fn process_device_removals(
     mut registry: ResMut<DeviceRegistry>,
     mut pending_removals: ResMut<PendingDeviceRemovals>,
+    mut archived: ResMut<ArchivedDevices>,
     mut positions: ResMut<DevicePositions>,
     mut orientations: ResMut<DeviceOrientations>,
     mut frame_visibility: ResMut<FrameVisibility>,
 ) {
-    if pending_removals.0.is_empty() {
+    if pending_removals.0.is_empty() && archived.pending_restore.is_empty() {
         return;
     }
 
-    for device_id in pending_removals.0.drain(..) {
-        // Remove from registry
-        registry.devices.retain(|d| d.id != device_id);
+    for (device_id, mode) in std::mem::take(&mut pending_removals.0) {
+        let Some(index) = registry.devices.iter().position(|d| d.id == device_id) else {
+            continue;
+        };
+        let device = registry.devices.remove(index);
 
-        // Clean up associated state
-        positions.positions.remove(&device_id);
-        orientations.orientations.remove(&device_id);
-        frame_visibility.device_frames.remove(&device_id);
-        frame_visibility.device_sensors.remove(&device_id);
-        frame_visibility.device_ports.remove(&device_id);
+        match mode {
+            RemoveMode::Archive => {
+                // Keep the device (and its pose/visibility state) so it can be restored
+                tracing::info!("Archived device: {}", device_id);
+                archived.devices.push(device);
+            }
+            RemoveMode::Forget => {
+                // Clean up associated state
+                positions.positions.remove(&device_id);
+                orientations.orientations.remove(&device_id);
+                frame_visibility.device_frames.remove(&device_id);
+                frame_visibility.device_sensors.remove(&device_id);
+                frame_visibility.device_ports.remove(&device_id);
+
+                tracing::info!("Removed device: {}", device_id);
+            }
+        }
+    }
 
-        tracing::info!("Removed device: {}", device_id);
+    for device_id in std::mem::take(&mut archived.pending_restore) {
+        if let Some(index) = archived.devices.iter().position(|d| d.id == device_id) {
+            let device = archived.devices.remove(index);
+            tracing::info!("Restored archived device: {}", device_id);
+            registry.devices.push(device);
+        }
     }
 }
 
@@ -85,9 +122,13 @@ fn process_device_removals(
 fn process_pending_hcdf(
     mut pending_hcdf: ResMut<PendingHcdfContent>,
     mut registry: ResMut<DeviceRegistry>,
+    mut archived: ResMut<ArchivedDevices>,
     mut positions: ResMut<DevicePositions>,
     mut orientations: ResMut<DeviceOrientations>,
     mut frame_visibility: ResMut<FrameVisibility>,
+    mut loaded_hcdf: ResMut<LoadedHcdf>,
+    mut url_input: ResMut<HcdfUrlInput>,
+    mut edit_history: ResMut<EditHistory>,
 ) {
     // Take pending content if available
     let Some(xml_content) = pending_hcdf.0.take() else {
@@ -96,6 +137,31 @@ fn process_pending_hcdf(
 
     tracing::info!("Processing HCDF content ({} bytes)", xml_content.len());
 
+    // Report every problem with its line and column, not just the first
+    let report = match Hcdf::validate(&xml_content) {
+        Ok(report) => report,
+        Err(e) => {
+            tracing::error!("Invalid HCDF: {}", e);
+            url_input.error = Some(e.to_string());
+            return;
+        }
+    };
+    for warning in report.warnings() {
+        tracing::warn!("HCDF: {}", warning);
+    }
+    if report.has_errors() {
+        let errors: Vec<String> = report.errors().map(|issue| issue.to_string()).collect();
+        for error in &errors {
+            tracing::error!("Invalid HCDF: {}", error);
+        }
+        let mut error = errors.iter().take(5).cloned().collect::<Vec<_>>().join("\n");
+        if errors.len() > 5 {
+            error.push_str(&format!("\n...and {} more", errors.len() - 5));
+        }
+        url_input.error = Some(error);
+        return;
+    }
+
     // Parse HCDF XML
     let hcdf = match Hcdf::from_xml(&xml_content) {
         Ok(hcdf) => hcdf,
@@ -105,13 +171,23 @@ fn process_pending_hcdf(
         }
     };
 
-    // Clear existing devices and state
+    // Flag sensors whose declared driver doesn't support their type
+    for lint in DriverRegistry::builtin().lint_hcdf(&hcdf) {
+        tracing::warn!("HCDF driver mismatch: {}", lint);
+    }
+
+    // Clear existing devices and state (archived devices belong to the previous file)
+    archived.devices.clear();
+    archived.pending_restore.clear();
     registry.devices.clear();
     positions.positions.clear();
     orientations.orientations.clear();
     frame_visibility.device_frames.clear();
     frame_visibility.device_sensors.clear();
     frame_visibility.device_ports.clear();
+    frame_visibility.selected_port = None;
+    frame_visibility.selected_antenna = None;
+    edit_history.clear();
 
     // Process MCUs
     for mcu in &hcdf.mcu {
@@ -131,11 +207,32 @@ fn process_pending_hcdf(
     registry.connected = true;
 
     tracing::info!("HCDF processing complete: {} devices loaded", registry.devices.len());
+    loaded_hcdf.0 = Some(hcdf);
 }
 
-/// Check URL parameters on startup for ?hcdf=URL
-#[allow(unused_variables, unused_mut)]
+/// Check URL parameters on startup for ?hcdf=URL (`--hcdf <url>` and
+/// `--fetch-timeout <secs>` on native)
 fn check_url_parameters(mut url_input: ResMut<HcdfUrlInput>) {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match (arg.as_str(), args.next()) {
+                ("--hcdf", Some(hcdf_url)) => url_input.url = hcdf_url,
+                ("--fetch-timeout", Some(secs)) => match secs.parse() {
+                    Ok(secs) => url_input.timeout = std::time::Duration::from_secs(secs),
+                    Err(_) => tracing::warn!("Ignoring invalid --fetch-timeout {}", secs),
+                },
+                _ => tracing::warn!("Ignoring argument {}", arg),
+            }
+        }
+        if !url_input.url.is_empty() {
+            tracing::info!("Loading HCDF from command line: {}", url_input.url);
+            fetch_hcdf_from_url(&url_input.url, url_input.timeout, url_input.pending_result.clone());
+            url_input.loading = true;
+        }
+    }
+
     #[cfg(target_arch = "wasm32")]
     {
         let window = match web_sys::window() {
@@ -155,7 +252,7 @@ fn check_url_parameters(mut url_input: ResMut<HcdfUrlInput>) {
                 tracing::info!("Loading HCDF from URL parameter: {}", hcdf_url);
                 url_input.url = hcdf_url.clone();
                 // Trigger fetch
-                fetch_hcdf_from_url(&hcdf_url, url_input.pending_result.clone());
+                fetch_hcdf_from_url(&hcdf_url, url_input.timeout, url_input.pending_result.clone());
                 url_input.loading = true;
             }
         }
@@ -185,6 +282,7 @@ fn process_url_fetch_results(
     mut url_input: ResMut<HcdfUrlInput>,
     mut pending_hcdf: ResMut<PendingHcdfContent>,
     mut base_url: ResMut<HcdfBaseUrl>,
+    mut bundle_assets: ResMut<BundleAssets>,
 ) {
     // Take the result from the mutex (if any) - this drops the lock immediately
     let fetch_result = {
@@ -203,6 +301,7 @@ fn process_url_fetch_results(
                 tracing::info!("HCDF fetched from URL ({} bytes)", content.len());
                 url_input.error = None;
                 pending_hcdf.0 = Some(content);
+                bundle_assets.clear();
 
                 // Extract base URL for resolving relative model paths
                 // e.g., "https://hcdf.cognipilot.org/mr_mcxn_t1/optical-flow/file.hcdf"
@@ -228,9 +327,12 @@ fn process_url_fetch_results(
 }
 
 /// Fetch HCDF content from a URL (async via wasm_bindgen_futures)
+///
+/// The browser applies its own fetch timeout.
 #[cfg(target_arch = "wasm32")]
 pub fn fetch_hcdf_from_url(
     url: &str,
+    _timeout: std::time::Duration,
     pending_result: std::sync::Arc<std::sync::Mutex<Option<Result<String, String>>>>,
 ) {
     use wasm_bindgen::JsCast;
@@ -265,22 +367,59 @@ pub fn fetch_hcdf_from_url(
     });
 }
 
+/// Fetch HCDF content from a URL on a background thread
+///
+/// Redirects are followed; errors read like the wasm path's so the URL box
+/// shows the same messages.
 #[cfg(not(target_arch = "wasm32"))]
 pub fn fetch_hcdf_from_url(
-    _url: &str,
+    url: &str,
+    timeout: std::time::Duration,
     pending_result: std::sync::Arc<std::sync::Mutex<Option<Result<String, String>>>>,
 ) {
-    // Native: not supported yet
-    if let Ok(mut pending) = pending_result.lock() {
-        *pending = Some(Err("URL fetch not supported on native".to_string()));
+    let url = url.to_string();
+    std::thread::spawn(move || {
+        let result = (|| {
+            let client = reqwest::blocking::Client::builder()
+                .timeout(timeout)
+                .redirect(reqwest::redirect::Policy::limited(10))
+                .build()
+                .map_err(|e| format!("Fetch failed: {}", e))?;
+
+            let resp = client.get(&url).send().map_err(|e| format!("Fetch failed: {}", e))?;
+
+            let status = resp.status();
+            if !status.is_success() {
+                return Err(format!("HTTP {}: {}", status.as_u16(), status.canonical_reason().unwrap_or("")));
+            }
+
+            resp.text().map_err(|e| format!("Text extraction failed: {}", e))
+        })();
+
+        if let Ok(mut pending) = pending_result.lock() {
+            *pending = Some(result);
+        }
+    });
+}
+
+/// Parse a device's `pose_cg`, logging why it was ignored if it doesn't parse
+fn parse_device_pose(device: &str, pose_cg: Option<&str>) -> Option<Pose> {
+    let text = pose_cg?;
+    match dendrite_core::hcdf::parse_pose_result(text) {
+        Ok(pose) => Some(pose),
+        Err(e) => {
+            tracing::warn!("Ignoring pose_cg \"{}\" of {}: {}", text, device, e);
+            None
+        }
     }
 }
 
 /// Convert an HCDF MCU to DeviceData
 fn convert_mcu_to_device(mcu: &dendrite_core::hcdf::Mcu) -> DeviceData {
-    let pose = mcu.pose_cg.as_ref().and_then(|s| dendrite_core::hcdf::parse_pose_string(s));
+    let pose = parse_device_pose(&mcu.name, mcu.pose_cg.as_deref());
     let position = pose.as_ref().map(|p| [p.x, p.y, p.z]);
     let orientation = pose.as_ref().map(|p| [p.roll, p.pitch, p.yaw]);
+    let rotation = pose.as_ref().and_then(|p| p.quaternion);
 
     // Convert visuals
     let visuals: Vec<VisualData> = mcu.visual.iter().map(|v| {
@@ -288,6 +427,7 @@ fn convert_mcu_to_device(mcu: &dendrite_core::hcdf::Mcu) -> DeviceData {
         VisualData {
             name: v.name.clone(),
             toggle: v.toggle.clone(),
+            default_hidden: v.is_default_hidden(),
             pose: pose.map(|p| p.to_array()),
             model_path: v.model.as_ref().map(|m| m.href.clone()),
             model_sha: v.model.as_ref().and_then(|m| m.sha.clone()),
@@ -301,6 +441,7 @@ fn convert_mcu_to_device(mcu: &dendrite_core::hcdf::Mcu) -> DeviceData {
             name: f.name.clone(),
             description: f.description.clone(),
             pose: pose.map(|p| p.to_array()),
+            parent: f.parent.clone(),
         }
     }).collect();
 
@@ -317,6 +458,7 @@ fn convert_mcu_to_device(mcu: &dendrite_core::hcdf::Mcu) -> DeviceData {
         version: mcu.software.as_ref().and_then(|s| s.version.clone()),
         position,
         orientation,
+        rotation,
         model_path,
         visuals,
         frames,
@@ -329,9 +471,10 @@ fn convert_mcu_to_device(mcu: &dendrite_core::hcdf::Mcu) -> DeviceData {
 
 /// Convert an HCDF Comp to DeviceData
 fn convert_comp_to_device(comp: &dendrite_core::hcdf::Comp) -> DeviceData {
-    let pose = comp.pose_cg.as_ref().and_then(|s| dendrite_core::hcdf::parse_pose_string(s));
+    let pose = parse_device_pose(&comp.name, comp.pose_cg.as_deref());
     let position = pose.as_ref().map(|p| [p.x, p.y, p.z]);
     let orientation = pose.as_ref().map(|p| [p.roll, p.pitch, p.yaw]);
+    let rotation = pose.as_ref().and_then(|p| p.quaternion);
 
     // Convert visuals
     let visuals: Vec<VisualData> = comp.visual.iter().map(|v| {
@@ -339,6 +482,7 @@ fn convert_comp_to_device(comp: &dendrite_core::hcdf::Comp) -> DeviceData {
         VisualData {
             name: v.name.clone(),
             toggle: v.toggle.clone(),
+            default_hidden: v.is_default_hidden(),
             pose: pose.map(|p| p.to_array()),
             model_path: v.model.as_ref().map(|m| m.href.clone()),
             model_sha: v.model.as_ref().and_then(|m| m.sha.clone()),
@@ -352,6 +496,7 @@ fn convert_comp_to_device(comp: &dendrite_core::hcdf::Comp) -> DeviceData {
             name: f.name.clone(),
             description: f.description.clone(),
             pose: pose.map(|p| p.to_array()),
+            parent: f.parent.clone(),
         }
     }).collect();
 
@@ -376,24 +521,16 @@ fn convert_comp_to_device(comp: &dendrite_core::hcdf::Comp) -> DeviceData {
         let capabilities = p.capabilities.as_ref().map(|caps| {
             PortCapabilitiesData {
                 // Data capabilities
-                speed: caps.speed.as_ref().map(|v| {
-                    format!("{}{}", v.value, v.unit.as_ref().map(|u| format!(" {}", u)).unwrap_or_default())
-                }),
-                bitrate: caps.bitrate.as_ref().map(|v| {
-                    format!("{}{}", v.value, v.unit.as_ref().map(|u| format!(" {}", u)).unwrap_or_default())
-                }),
-                baud: caps.baud.as_ref().map(|v| {
-                    format!("{}{}", v.value, v.unit.as_ref().map(|u| format!(" {}", u)).unwrap_or_default())
-                }),
+                speed: caps.speed.as_ref().map(|v| v.to_string()),
+                bitrate: caps.bitrate.as_ref().map(|v| v.to_string()),
+                baud: caps.baud.as_ref().map(|v| v.to_string()),
                 standard: caps.standard.clone(),
                 protocols: caps.protocol.clone(),
                 // Power capabilities
                 voltage: caps.voltage.as_ref().map(|v| v.to_display_string()).filter(|s| !s.is_empty()),
                 current: caps.current.as_ref().map(|v| v.to_display_string()).filter(|s| !s.is_empty()),
                 power_watts: caps.power.as_ref().map(|v| v.to_display_string()).filter(|s| !s.is_empty()),
-                capacity: caps.capacity.as_ref().map(|v| {
-                    format!("{}{}", v.value, v.unit.as_ref().map(|u| format!(" {}", u)).unwrap_or_default())
-                }),
+                capacity: caps.capacity.as_ref().map(|v| v.to_string()),
                 connector: caps.connector.clone(),
             }
         });
@@ -406,6 +543,7 @@ fn convert_comp_to_device(comp: &dendrite_core::hcdf::Comp) -> DeviceData {
             visual_name: p.visual.clone(),
             mesh_name: p.mesh.clone(),
             capabilities,
+            original_pose: None,
         }
     }).collect();
 
@@ -427,9 +565,7 @@ fn convert_comp_to_device(comp: &dendrite_core::hcdf::Comp) -> DeviceData {
         let capabilities = a.capabilities.as_ref().map(|caps| {
             AntennaCapabilitiesData {
                 bands: caps.get_bands(),
-                gain: caps.gain.as_ref().map(|v| {
-                    format!("{}{}", v.value, v.unit.as_ref().map(|u| format!(" {}", u)).unwrap_or_default())
-                }),
+                gain: caps.gain.as_ref().map(|v| v.to_string()),
                 standards: caps.standard.clone(),
                 protocols: caps.protocol.clone(),
                 polarization: caps.polarization.clone(),
@@ -444,6 +580,7 @@ fn convert_comp_to_device(comp: &dendrite_core::hcdf::Comp) -> DeviceData {
             visual_name: a.visual.clone(),
             mesh_name: a.mesh.clone(),
             capabilities,
+            original_pose: None,
         }
     }).collect();
 
@@ -467,6 +604,8 @@ fn convert_comp_to_device(comp: &dendrite_core::hcdf::Comp) -> DeviceData {
                 category: "inertial".to_string(),
                 sensor_type: inertial.sensor_type.clone(),
                 driver: inertial.driver.as_ref().map(|d| d.name.clone()),
+                driver_label: sensor_driver_label(inertial.driver.as_ref()),
+                datasheet: sensor_driver_datasheet(inertial.driver.as_ref()),
                 pose: pose.map(|p| p.to_array()),
                 axis_align,
                 geometry: inertial.geometry.as_ref().and_then(|g| convert_geometry(g)),
@@ -491,6 +630,8 @@ fn convert_comp_to_device(comp: &dendrite_core::hcdf::Comp) -> DeviceData {
                 category: "em".to_string(),
                 sensor_type: em.sensor_type.clone(),
                 driver: em.driver.as_ref().map(|d| d.name.clone()),
+                driver_label: sensor_driver_label(em.driver.as_ref()),
+                datasheet: sensor_driver_datasheet(em.driver.as_ref()),
                 pose: pose.map(|p| p.to_array()),
                 axis_align,
                 geometry: em.geometry.as_ref().and_then(|g| convert_geometry(g)),
@@ -527,6 +668,8 @@ fn convert_comp_to_device(comp: &dendrite_core::hcdf::Comp) -> DeviceData {
                 category: "optical".to_string(),
                 sensor_type: optical.sensor_type.clone(),
                 driver: optical.driver.as_ref().map(|d| d.name.clone()),
+                driver_label: sensor_driver_label(optical.driver.as_ref()),
+                datasheet: sensor_driver_datasheet(optical.driver.as_ref()),
                 pose: pose.map(|p| p.to_array()),
                 axis_align,
                 geometry: optical.geometry.as_ref().and_then(|g| convert_geometry(g)),
@@ -542,6 +685,8 @@ fn convert_comp_to_device(comp: &dendrite_core::hcdf::Comp) -> DeviceData {
                 category: "rf".to_string(),
                 sensor_type: rf.sensor_type.clone(),
                 driver: rf.driver.as_ref().map(|d| d.name.clone()),
+                driver_label: sensor_driver_label(rf.driver.as_ref()),
+                datasheet: sensor_driver_datasheet(rf.driver.as_ref()),
                 pose: pose.map(|p| p.to_array()),
                 axis_align: None,
                 geometry: rf.geometry.as_ref().and_then(|g| convert_geometry(g)),
@@ -557,6 +702,8 @@ fn convert_comp_to_device(comp: &dendrite_core::hcdf::Comp) -> DeviceData {
                 category: "force".to_string(),
                 sensor_type: force.sensor_type.clone(),
                 driver: force.driver.as_ref().map(|d| d.name.clone()),
+                driver_label: sensor_driver_label(force.driver.as_ref()),
+                datasheet: sensor_driver_datasheet(force.driver.as_ref()),
                 pose: pose.map(|p| p.to_array()),
                 axis_align: None,
                 geometry: force.geometry.as_ref().and_then(|g| convert_geometry(g)),
@@ -572,6 +719,8 @@ fn convert_comp_to_device(comp: &dendrite_core::hcdf::Comp) -> DeviceData {
                 category: "chemical".to_string(),
                 sensor_type: chemical.sensor_type.clone(),
                 driver: chemical.driver.as_ref().map(|d| d.name.clone()),
+                driver_label: sensor_driver_label(chemical.driver.as_ref()),
+                datasheet: sensor_driver_datasheet(chemical.driver.as_ref()),
                 pose: pose.map(|p| p.to_array()),
                 axis_align: None,
                 geometry: chemical.geometry.as_ref().and_then(|g| convert_geometry(g)),
@@ -593,6 +742,7 @@ fn convert_comp_to_device(comp: &dendrite_core::hcdf::Comp) -> DeviceData {
         version: comp.software.as_ref().and_then(|s| s.version.clone()),
         position,
         orientation,
+        rotation,
         model_path,
         visuals,
         frames,
@@ -604,6 +754,16 @@ fn convert_comp_to_device(comp: &dendrite_core::hcdf::Comp) -> DeviceData {
 }
 
 /// Convert AxisMap enum to string representation
+/// The loaded HCDF as exported: a copy with the toggle groups the user
+/// changed recorded as `default_hidden`
+fn hcdf_for_export(loaded: &Hcdf, devices: &[DeviceData], frame_visibility: &FrameVisibility) -> Hcdf {
+    let mut hcdf = loaded.clone();
+    for (device_id, toggle, hidden) in frame_visibility.changed_toggle_states(devices) {
+        hcdf.set_toggle_state(&device_id, &toggle, hidden);
+    }
+    hcdf
+}
+
 fn axis_map_to_string(axis: &dendrite_core::hcdf::AxisMap) -> String {
     use dendrite_core::hcdf::AxisMap;
     match axis {
@@ -667,6 +827,11 @@ fn convert_geometry(g: &dendrite_core::hcdf::Geometry) -> Option<GeometryData> {
 #[derive(Resource, Default)]
 pub struct PendingHcdfContent(pub Option<String>);
 
+/// The loaded HCDF document; port and antenna pose edits are written back to
+/// it so they can be exported
+#[derive(Resource, Default)]
+pub struct LoadedHcdf(pub Option<Hcdf>);
+
 /// URL input state for loading HCDF from web
 #[derive(Resource)]
 pub struct HcdfUrlInput {
@@ -678,8 +843,13 @@ pub struct HcdfUrlInput {
     pub error: Option<String>,
     /// Pending fetch result (set by async callback)
     pub pending_result: std::sync::Arc<std::sync::Mutex<Option<Result<String, String>>>>,
+    /// How long a native fetch may take (`--fetch-timeout <secs>`)
+    pub timeout: std::time::Duration,
 }
 
+/// Default native fetch timeout
+pub const DEFAULT_FETCH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
 impl Default for HcdfUrlInput {
     fn default() -> Self {
         Self {
@@ -687,6 +857,7 @@ impl Default for HcdfUrlInput {
             loading: false,
             error: None,
             pending_result: std::sync::Arc::new(std::sync::Mutex::new(None)),
+            timeout: DEFAULT_FETCH_TIMEOUT,
         }
     }
 }
@@ -695,15 +866,35 @@ impl Default for HcdfUrlInput {
 #[derive(Resource, Default)]
 pub struct HcdfBaseUrl(pub Option<String>);
 
+/// How a device is removed from the scene (mirrors the daemon's remove modes)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoveMode {
+    /// Hide the device; it can be restored from the archived list
+    Archive,
+    /// Drop the device and its pose/visibility state
+    Forget,
+}
+
 /// Pending device removals (device IDs to remove from registry)
 #[derive(Resource, Default)]
-pub struct PendingDeviceRemovals(pub Vec<String>);
+pub struct PendingDeviceRemovals(pub Vec<(String, RemoveMode)>);
+
+/// Devices archived from the scene, kept until the next HCDF load
+#[derive(Resource, Default)]
+pub struct ArchivedDevices {
+    pub devices: Vec<DeviceData>,
+    /// Device IDs to move back into the registry
+    pub pending_restore: Vec<String>,
+}
 
 /// Process completed file picker results and dispatch to appropriate handlers
 fn process_file_picker_results(
     mut file_picker_state: ResMut<FilePickerState>,
     mut pending_hcdf: ResMut<PendingHcdfContent>,
     mut base_url: ResMut<HcdfBaseUrl>,
+    mut bundle_assets: ResMut<BundleAssets>,
+    mut url_input: ResMut<HcdfUrlInput>,
+    mut compare: ResMut<HcdfCompare>,
 ) {
     // Process completed file picker results
     while let Some(result) = file_picker_state.take_result() {
@@ -718,7 +909,28 @@ fn process_file_picker_results(
                 tracing::warn!("Firmware upload not available in viewer mode");
             }
             FilePickerContext::HcdfImport => {
-                if let Some(content) = result.content {
+                if let Some(content) = result.content.as_ref().filter(|content| is_bundle(content)) {
+                    // Models come from the bundle, hrefs are resolved against it
+                    match bundle_assets.load(content) {
+                        Ok((xml, issues)) => {
+                            tracing::warn!("Bundle loaded: {} ({} bytes)", result.filename, content.len());
+                            for issue in &issues {
+                                tracing::warn!("Bundle: {}", issue);
+                            }
+                            url_input.error = (!issues.is_empty()).then(|| {
+                                let lines: Vec<String> = issues.iter().map(|issue| issue.to_string()).collect();
+                                format!("{} model(s) in the bundle have problems:\n{}", issues.len(), lines.join("\n"))
+                            });
+                            pending_hcdf.0 = Some(xml);
+                            base_url.0 = None;
+                        }
+                        Err(e) => {
+                            tracing::error!("Failed to read bundle {}: {}", result.filename, e);
+                            url_input.error = Some(format!("Failed to read bundle: {}", e));
+                        }
+                    }
+                } else if let Some(content) = result.content {
+                    bundle_assets.clear();
                     // Convert bytes to string and store for processing
                     if let Ok(xml) = String::from_utf8(content) {
                         tracing::warn!("HCDF file loaded: {} ({} bytes)", result.filename, xml.len());
@@ -730,10 +942,18 @@ fn process_file_picker_results(
                     }
                 }
             }
+            FilePickerContext::HcdfCompare => match result.content.map(String::from_utf8) {
+                Some(Ok(xml)) => compare.load(&result.filename, &xml),
+                Some(Err(_)) => compare.error = Some(format!("{} is not valid UTF-8", result.filename)),
+                None => {}
+            },
             FilePickerContext::HcdfExport => {
                 // Export was completed (file saved via browser download)
                 tracing::info!("HCDF export completed: {}", result.filename);
             }
+            FilePickerContext::UrdfExport => {
+                tracing::info!("URDF export completed: {}", result.filename);
+            }
             FilePickerContext::Custom(name) => {
                 tracing::info!("Custom file picker result for '{}': {}", name, result.filename);
             }
@@ -759,6 +979,31 @@ fn update_ui_layout(
     }
 }
 
+/// Put a device back to the pose before `edit` (`undo`) or after it (redo)
+fn apply_pose_edit(
+    edit: &PoseEdit,
+    undo: bool,
+    positions: &mut DevicePositions,
+    orientations: &mut DeviceOrientations,
+    device_query: &mut Query<(&crate::scene::DeviceEntity, &mut Transform)>,
+) {
+    let (from, to) = if undo { (edit.new_pose, edit.old_pose) } else { (edit.old_pose, edit.new_pose) };
+    positions.positions.insert(edit.device_id.clone(), to.position);
+    // Only touch the rotation if the edit did, so a position edit keeps it exactly
+    if from.orientation != to.orientation {
+        orientations.orientations.insert(edit.device_id.clone(), to.orientation);
+    }
+    for (device, mut transform) in device_query.iter_mut() {
+        if device.device_id == edit.device_id {
+            transform.translation = to.position;
+            if from.orientation != to.orientation {
+                transform.rotation = Quat::from_euler(EulerRot::XYZ, to.orientation.x, to.orientation.y, to.orientation.z);
+            }
+            break;
+        }
+    }
+}
+
 fn ui_system(mut params: UiParams) {
     let is_mobile = params.ui_layout.is_mobile;
     let panel_width = params.ui_layout.panel_width();
@@ -767,6 +1012,16 @@ fn ui_system(mut params: UiParams) {
     // Get the egui context - early return if not available
     let Ok(ctx) = params.contexts.ctx_mut() else { return };
 
+    // Undo/redo pose edits, unless a text field has the keyboard
+    if !ctx.wants_keyboard_input() {
+        // Checked first: Ctrl+Z alone would also match with Shift held
+        let redo = ctx.input_mut(|i| i.consume_shortcut(&egui::KeyboardShortcut::new(egui::Modifiers::COMMAND | egui::Modifiers::SHIFT, egui::Key::Z)));
+        let undo = !redo && ctx.input_mut(|i| i.consume_shortcut(&egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Z)));
+        if let Some(edit) = if undo { params.edit_history.undo() } else if redo { params.edit_history.redo() } else { None } {
+            apply_pose_edit(&edit, undo, &mut params.positions, &mut params.orientations, &mut params.device_query);
+        }
+    }
+
     // Set up style for mobile - compact but still touch-friendly
     if is_mobile {
         let mut style = (*ctx.style()).clone();
@@ -857,7 +1112,7 @@ fn ui_system(mut params: UiParams) {
                         trigger_file_open(
                             &params.pending_file_results,
                             FilePickerContext::HcdfImport,
-                            FileFilter::hcdf(),
+                            FileFilter::hcdf_or_bundle(),
                         );
                     }
 
@@ -883,7 +1138,7 @@ fn ui_system(mut params: UiParams) {
                                 && (params.url_input.url.starts_with("http://")
                                     || params.url_input.url.starts_with("https://"));
                             if ui.add_enabled(fetch_enabled, egui::Button::new("Go")).clicked() {
-                                fetch_hcdf_from_url(&params.url_input.url, params.url_input.pending_result.clone());
+                                fetch_hcdf_from_url(&params.url_input.url, params.url_input.timeout, params.url_input.pending_result.clone());
                                 params.url_input.loading = true;
                                 params.url_input.error = None;
                             }
@@ -892,7 +1147,7 @@ fn ui_system(mut params: UiParams) {
                         // Also fetch on Enter key
                         if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
                             if !params.url_input.url.is_empty() && !params.url_input.loading {
-                                fetch_hcdf_from_url(&params.url_input.url, params.url_input.pending_result.clone());
+                                fetch_hcdf_from_url(&params.url_input.url, params.url_input.timeout, params.url_input.pending_result.clone());
                                 params.url_input.loading = true;
                                 params.url_input.error = None;
                             }
@@ -976,6 +1231,26 @@ fn ui_system(mut params: UiParams) {
 
                 ui.label(format!("{} devices", params.registry.devices.len()));
 
+                // Archived devices can be brought back into the scene
+                if !params.archived.devices.is_empty() {
+                    egui::CollapsingHeader::new(
+                        egui::RichText::new(format!("Archived ({})", params.archived.devices.len())).size(12.0 * ui_scale)
+                    )
+                        .default_open(false)
+                        .show(ui, |ui| {
+                            let mut restore = Vec::new();
+                            for device in &params.archived.devices {
+                                ui.horizontal(|ui| {
+                                    ui.label(egui::RichText::new(&device.name).color(egui::Color32::GRAY));
+                                    if ui.small_button("Restore").clicked() {
+                                        restore.push(device.id.clone());
+                                    }
+                                });
+                            }
+                            params.archived.pending_restore.extend(restore);
+                        });
+                }
+
                 ui.separator();
 
                 // HCDF Import - collapsible section (only in non-hosted mode)
@@ -996,7 +1271,7 @@ fn ui_system(mut params: UiParams) {
                                     trigger_file_open(
                                         &params.pending_file_results,
                                         FilePickerContext::HcdfImport,
-                                        FileFilter::hcdf(),
+                                        FileFilter::hcdf_or_bundle(),
                                     );
                                 }
                                 ui.label(
@@ -1005,11 +1280,119 @@ fn ui_system(mut params: UiParams) {
                                         .color(egui::Color32::GRAY)
                                 );
                             });
+
+                            // Export button (includes port/antenna pose edits and toggle states)
+                            ui.horizontal(|ui| {
+                                let export_button = if is_mobile {
+                                    egui::Button::new(egui::RichText::new("Export").size(14.0 * ui_scale))
+                                        .min_size(egui::vec2(0.0, 32.0))
+                                } else {
+                                    egui::Button::new("Export")
+                                };
+                                let loaded = params.loaded_hcdf.0.as_ref();
+                                if ui.add_enabled(loaded.is_some(), export_button).clicked() {
+                                    let exported = loaded.map(|hcdf| hcdf_for_export(hcdf, &params.registry.devices, &params.frame_visibility));
+                                    match exported.map(|hcdf| hcdf.to_xml()) {
+                                        Some(Ok(xml)) => trigger_file_save(
+                                            &params.pending_file_results,
+                                            FilePickerContext::HcdfExport,
+                                            "dendrite_viewer.hcdf",
+                                            xml.as_bytes(),
+                                            "application/xml",
+                                        ),
+                                        Some(Err(e)) => tracing::error!("Failed to serialize HCDF: {:?}", e),
+                                        None => {}
+                                    }
+                                }
+                                ui.label(
+                                    egui::RichText::new("Save .hcdf file")
+                                        .size(10.0 * ui_scale)
+                                        .color(egui::Color32::GRAY)
+                                );
+                            });
+
+                            // URDF export for simulation stacks
+                            ui.horizontal(|ui| {
+                                let export_button = if is_mobile {
+                                    egui::Button::new(egui::RichText::new("Export URDF").size(14.0 * ui_scale))
+                                        .min_size(egui::vec2(0.0, 32.0))
+                                } else {
+                                    egui::Button::new("Export URDF")
+                                };
+                                let loaded = params.loaded_hcdf.0.as_ref();
+                                if ui.add_enabled(loaded.is_some(), export_button).clicked() {
+                                    match loaded.map(dendrite_core::export_urdf) {
+                                        Some(Ok(urdf)) => trigger_file_save(
+                                            &params.pending_file_results,
+                                            FilePickerContext::UrdfExport,
+                                            "dendrite_viewer.urdf",
+                                            urdf.as_bytes(),
+                                            "application/xml",
+                                        ),
+                                        Some(Err(e)) => tracing::error!("Failed to export URDF: {:?}", e),
+                                        None => {}
+                                    }
+                                }
+                                ui.label(
+                                    egui::RichText::new("Save .urdf file")
+                                        .size(10.0 * ui_scale)
+                                        .color(egui::Color32::GRAY)
+                                );
+                            });
+                        });
+
+                    ui.separator();
+
+                    // Compare - changes a second file would make to the loaded one
+                    let header = match params.compare.diff.devices().count() {
+                        0 => "Compare".to_string(),
+                        n => format!("Compare ({} changed)", n),
+                    };
+                    egui::CollapsingHeader::new(egui::RichText::new(header).size(14.0 * ui_scale))
+                        .id_salt("compare")
+                        .default_open(false)
+                        .show(ui, |ui| {
+                            let loaded = params.loaded_hcdf.0.is_some();
+                            if ui.add_enabled(loaded, egui::Button::new("Compare..."))
+                                .on_hover_text("Pick a second .hcdf to compare the loaded file with")
+                                .on_disabled_hover_text("Load an HCDF file first")
+                                .clicked()
+                            {
+                                trigger_file_open(
+                                    &params.pending_file_results,
+                                    FilePickerContext::HcdfCompare,
+                                    FileFilter::hcdf(),
+                                );
+                            }
+                            if let Some(device_id) = compare_section(ui, &mut params.compare, ui_scale) {
+                                params.selected.0 = Some(device_id);
+                            }
                         });
 
                     ui.separator();
                 }
 
+                // Views - presets and saved camera viewpoints
+                egui::CollapsingHeader::new(egui::RichText::new("Views").size(14.0 * ui_scale))
+                    .default_open(false)
+                    .show(ui, |ui| {
+                        let camera = &params.camera_settings;
+                        let current = CameraView::current("", camera.azimuth, camera.elevation, camera.target_distance, camera.target_focus);
+                        if let Some(view) = views_section(ui, &mut params.camera_views, current) {
+                            // Only the camera moves: the selection stays as it is
+                            let camera = &mut params.camera_settings;
+                            camera.target_orbit = Some((view.azimuth, view.elevation));
+                            if let Some(distance) = view.distance {
+                                camera.target_distance = distance.clamp(0.05, 5.0);
+                            }
+                            if let Some(focus) = view.focus {
+                                camera.target_focus = Vec3::from_array(focus);
+                            }
+                        }
+                    });
+
+                ui.separator();
+
                 // World Settings - collapsible section
                 egui::CollapsingHeader::new(egui::RichText::new("World Settings").size(14.0 * ui_scale))
                     .default_open(false)
@@ -1062,6 +1445,15 @@ fn ui_system(mut params: UiParams) {
                             egui::Slider::new(&mut params.world_settings.grid_alpha, 0.0..=1.0)
                         );
 
+                        ui.separator();
+
+                        // How many pose edits can be undone
+                        ui.label("Undo Depth:");
+                        let mut depth = params.edit_history.depth();
+                        if ui.add(egui::DragValue::new(&mut depth).range(1..=1000).suffix(" edits")).changed() {
+                            params.edit_history.set_depth(depth);
+                        }
+
                         // NOTE: Render scale feature removed - scale_factor_override doesn't work
                         // correctly in WASM (renders to partial canvas instead of downscaling)
                     });
@@ -1077,20 +1469,10 @@ fn ui_system(mut params: UiParams) {
                 };
                 if ui.add_sized([ui.available_width(), 0.0], graph_button).clicked() {
                     params.graph_vis.show = true;
-                    // Build topology from current device registry
-                    let nodes: Vec<TopologyNode> = params.registry.devices.iter().map(|d| {
-                        TopologyNode {
-                            id: d.id.clone(),
-                            name: d.name.clone(),
-                            board: d.board.clone(),
-                            is_parent: false, // TODO: detect parent from HCDF
-                            port: d.port,
-                            children: Vec::new(),
-                        }
-                    }).collect();
-                    params.graph_vis.topology = Some(TopologyData {
-                        nodes,
-                        root: None,
+                    // Hierarchy from the loaded HCDF's links and switch ports
+                    params.graph_vis.topology = Some(match &params.loaded_hcdf.0 {
+                        Some(hcdf) => TopologyData::from_topology(&Topology::from_hcdf(hcdf), &params.registry.devices),
+                        None => TopologyData::from_devices(&params.registry.devices),
                     });
                     params.graph_vis.pan_offset = [0.0, 0.0];
                     params.graph_vis.zoom = 1.0;
@@ -1114,6 +1496,9 @@ fn ui_system(mut params: UiParams) {
             });
     }
 
+    // Port/antenna pose edits are applied after the details panel releases the registry
+    let mut pose_edit = None;
+
     // Selected device details (right side, only if selected)
     if let Some(id) = params.selected.0.clone() {
         if let Some(device) = params.registry.devices.iter().find(|d| d.id == id) {
@@ -1224,6 +1609,10 @@ fn ui_system(mut params: UiParams) {
                                     ui.end_row();
 
                                     let current_pos = params.positions.positions.get(&id).cloned().unwrap_or(Vec3::ZERO);
+                                    let before = EditPose {
+                                        position: current_pos,
+                                        orientation: params.orientations.orientations.get(&id).cloned().unwrap_or(Vec3::ZERO),
+                                    };
 
                                     // Position labels - shorter on mobile
                                     let (x_label, y_label, z_label) = if is_mobile {
@@ -1285,6 +1674,12 @@ fn ui_system(mut params: UiParams) {
                                     }
                                     ui.end_row();
 
+                                    // Body FLU vs world ENU overlay (unchecked by default)
+                                    ui.label("Show FLU/ENU Axes:");
+                                    ui.checkbox(&mut params.show_frame_overlay.0, "")
+                                        .on_hover_text("Body axes (solid F/L/U) and world axes (dashed E/N/U) at the device origin");
+                                    ui.end_row();
+
                                     // Show orientation from 3D scene
                                     // Get stored Euler angles (these are display values, not used to compute rotation)
                                     let orient = params.orientations.orientations.get(&id).cloned().unwrap_or(Vec3::ZERO);
@@ -1367,8 +1762,45 @@ fn ui_system(mut params: UiParams) {
                                             }
                                         }
                                     }
+
+                                    // A drag or typed value becomes one undo entry once it ends
+                                    let after = EditPose {
+                                        position: params.positions.positions.get(&id).cloned().unwrap_or(Vec3::ZERO),
+                                        orientation: params.orientations.orientations.get(&id).cloned().unwrap_or(Vec3::ZERO),
+                                    };
+                                    let editing = [&x_response, &y_response, &z_response, &roll_response, &pitch_response, &yaw_response]
+                                        .iter()
+                                        .any(|response| response.dragged() || response.has_focus());
+                                    params.edit_history.track(&id, before, after, editing);
+
+                                    // Body forward axis expressed in world coordinates
+                                    let rotation = params.device_query.iter()
+                                        .find(|(device, _)| device.device_id == id)
+                                        .map(|(_, transform)| transform.rotation)
+                                        .unwrap_or(Quat::IDENTITY);
+                                    let forward = forward_enu(rotation);
+                                    ui.label("Forward (ENU):");
+                                    ui.label(format!("E {:.2}  N {:.2}  U {:.2}", forward.x, forward.y, forward.z));
+                                    ui.end_row();
+
+                                    ui.label("  Heading:");
+                                    ui.label(heading_degrees(forward).map_or("-".to_string(), |h| format!("{:.1}°", h)));
+                                    ui.end_row();
                                 });
 
+                            // Undo/redo of pose edits (any device, not just this one)
+                            ui.horizontal(|ui| {
+                                let undo = ui.add_enabled(params.edit_history.can_undo(), egui::Button::new("Undo"))
+                                    .on_hover_text("Undo the last pose edit (Ctrl+Z)")
+                                    .clicked();
+                                let redo = ui.add_enabled(params.edit_history.can_redo(), egui::Button::new("Redo"))
+                                    .on_hover_text("Redo the last undone pose edit (Ctrl+Shift+Z)")
+                                    .clicked();
+                                if let Some(edit) = if undo { params.edit_history.undo() } else if redo { params.edit_history.redo() } else { None } {
+                                    apply_pose_edit(&edit, undo, &mut params.positions, &mut params.orientations, &mut params.device_query);
+                                }
+                            });
+
                             ui.separator();
 
                             // Per-device frame visibility toggle (if device has frames or sensors)
@@ -1405,8 +1837,11 @@ fn ui_system(mut params: UiParams) {
                                                         .size(10.0 * ui_scale)
                                                         .color(egui::Color32::GRAY)
                                                 );
-                                                for frame in &device.frames {
+                                                // Child frames are indented under their parent
+                                                for (index, depth) in dendrite_core::frame_tree_order(&device.frames) {
+                                                    let frame = &device.frames[index];
                                                     ui.horizontal(|ui| {
+                                                        ui.add_space(depth as f32 * 12.0 * ui_scale);
                                                         let mut frame_vis = params.frame_visibility.is_frame_visible(&id, &frame.name);
                                                         if ui.checkbox(&mut frame_vis, "").changed() {
                                                             params.frame_visibility.set_frame_visible(&id, &frame.name, frame_vis);
@@ -1560,11 +1995,18 @@ fn ui_system(mut params: UiParams) {
                                                         .color(egui::Color32::GRAY)
                                                 );
                                                 if let Some(ref driver) = sensor.driver {
-                                                    ui.label(
-                                                        egui::RichText::new(format!("Driver: {}", driver))
-                                                            .size(10.0 * ui_scale)
-                                                            .color(egui::Color32::GRAY)
-                                                    );
+                                                    // Prefer the registry label; unknown drivers show the raw name
+                                                    let text = egui::RichText::new(format!("Driver: {}", sensor.driver_label.as_deref().unwrap_or(driver)))
+                                                        .size(10.0 * ui_scale)
+                                                        .color(egui::Color32::GRAY);
+                                                    match sensor.datasheet {
+                                                        Some(ref url) => {
+                                                            ui.hyperlink_to(text, url).on_hover_text(driver);
+                                                        }
+                                                        None => {
+                                                            ui.label(text);
+                                                        }
+                                                    }
                                                 }
                                                 // Per-sensor FOV visibility toggle (only for sensors with FOV)
                                                 if has_fov {
@@ -1663,6 +2105,7 @@ fn ui_system(mut params: UiParams) {
                                         for port in &device.ports {
                                             let port_key = format!("{}:{}", id, port.name);
                                             let is_hovered = params.frame_visibility.hovered_port.as_ref() == Some(&port_key);
+                                            let is_selected = params.frame_visibility.selected_port.as_ref() == Some(&port_key);
                                             let port_color = match port.port_type.to_lowercase().as_str() {
                                                 "ethernet" => egui::Color32::from_rgb(50, 200, 50),
                                                 "can" => egui::Color32::from_rgb(255, 200, 50),
@@ -1682,16 +2125,28 @@ fn ui_system(mut params: UiParams) {
                                             };
 
                                             // Build port label text
-                                            let label_text = format!("{} ({})", port.name, port.port_type);
+                                            let label_text = if port.original_pose.is_some() {
+                                                format!("{} ({}) - edited", port.name, port.port_type)
+                                            } else {
+                                                format!("{} ({})", port.name, port.port_type)
+                                            };
 
                                             // Use selectable_label for built-in hover detection
                                             let response = ui.selectable_label(
-                                                is_hovered,
+                                                is_hovered || is_selected,
                                                 egui::RichText::new(&label_text)
                                                     .size(12.0 * ui_scale)
                                                     .color(display_color)
                                             );
 
+                                            // Click selects a port for pose editing; mesh-placed
+                                            // ports follow their GLTF node and have no pose
+                                            if response.clicked() && port.mesh_name.is_none() {
+                                                params.frame_visibility.selected_antenna = None;
+                                                params.frame_visibility.selected_port =
+                                                    if is_selected { None } else { Some(port_key.clone()) };
+                                            }
+
                                             // Set hovered_port when hovering over port name in UI
                                             if response.hovered() {
                                                 params.frame_visibility.hovered_port = Some(port_key);
@@ -1699,6 +2154,22 @@ fn ui_system(mut params: UiParams) {
                                                 any_port_hovered = true;
                                             }
                                         }
+
+                                        // Pose editor for the selected port
+                                        let selected = params.frame_visibility.selected_port.clone();
+                                        if let Some(port) = device.ports.iter().find(|p| {
+                                            p.mesh_name.is_none() && selected.as_deref() == Some(format!("{}:{}", id, p.name).as_str())
+                                        }) {
+                                            ui.add_space(4.0);
+                                            if let Some(pose) = sub_element_pose_editor(ui, &port.name, port.pose, port.original_pose, ui_scale) {
+                                                pose_edit = Some(SubElementPoseEdit {
+                                                    device_id: id.clone(),
+                                                    kind: SubElement::Port,
+                                                    name: port.name.clone(),
+                                                    pose,
+                                                });
+                                            }
+                                        }
                                     });
 
                                     // Clear hovered_port only if:
@@ -1736,6 +2207,7 @@ fn ui_system(mut params: UiParams) {
                                         for antenna in &device.antennas {
                                             let antenna_key = format!("{}:{}", id, antenna.name);
                                             let is_hovered = params.frame_visibility.hovered_antenna.as_ref() == Some(&antenna_key);
+                                            let is_selected = params.frame_visibility.selected_antenna.as_ref() == Some(&antenna_key);
                                             let antenna_color = match antenna.antenna_type.to_lowercase().as_str() {
                                                 "wifi" | "wlan" => egui::Color32::from_rgb(50, 150, 255),
                                                 "bluetooth" | "bt" => egui::Color32::from_rgb(100, 100, 255),
@@ -1755,16 +2227,27 @@ fn ui_system(mut params: UiParams) {
                                             };
 
                                             // Build antenna label text
-                                            let label_text = format!("{} ({})", antenna.name, antenna.antenna_type);
+                                            let label_text = if antenna.original_pose.is_some() {
+                                                format!("{} ({}) - edited", antenna.name, antenna.antenna_type)
+                                            } else {
+                                                format!("{} ({})", antenna.name, antenna.antenna_type)
+                                            };
 
                                             // Use selectable_label for built-in hover detection
                                             let response = ui.selectable_label(
-                                                is_hovered,
+                                                is_hovered || is_selected,
                                                 egui::RichText::new(&label_text)
                                                     .size(12.0 * ui_scale)
                                                     .color(display_color)
                                             );
 
+                                            // Click selects an antenna for pose editing (fallback geometry only)
+                                            if response.clicked() && antenna.mesh_name.is_none() {
+                                                params.frame_visibility.selected_port = None;
+                                                params.frame_visibility.selected_antenna =
+                                                    if is_selected { None } else { Some(antenna_key.clone()) };
+                                            }
+
                                             // Set hovered_antenna when hovering over antenna name in UI
                                             if response.hovered() {
                                                 params.frame_visibility.hovered_antenna = Some(antenna_key);
@@ -1772,6 +2255,22 @@ fn ui_system(mut params: UiParams) {
                                                 any_antenna_hovered = true;
                                             }
                                         }
+
+                                        // Pose editor for the selected antenna
+                                        let selected = params.frame_visibility.selected_antenna.clone();
+                                        if let Some(antenna) = device.antennas.iter().find(|a| {
+                                            a.mesh_name.is_none() && selected.as_deref() == Some(format!("{}:{}", id, a.name).as_str())
+                                        }) {
+                                            ui.add_space(4.0);
+                                            if let Some(pose) = sub_element_pose_editor(ui, &antenna.name, antenna.pose, antenna.original_pose, ui_scale) {
+                                                pose_edit = Some(SubElementPoseEdit {
+                                                    device_id: id.clone(),
+                                                    kind: SubElement::Antenna,
+                                                    name: antenna.name.clone(),
+                                                    pose,
+                                                });
+                                            }
+                                        }
                                     });
 
                                     // Clear hovered_antenna only if:
@@ -1813,22 +2312,36 @@ fn ui_system(mut params: UiParams) {
                                 ui.separator();
                             }
 
-                            // Remove device button (available for all devices)
-                            let remove_button = if is_mobile {
-                                egui::Button::new(
-                                    egui::RichText::new("Remove Device")
-                                        .size(16.0 * ui_scale)
-                                        .color(egui::Color32::from_rgb(200, 100, 100))
-                                ).min_size(egui::vec2(0.0, 40.0))
-                            } else {
-                                egui::Button::new(
-                                    egui::RichText::new("Remove Device")
-                                        .color(egui::Color32::from_rgb(200, 100, 100))
-                                )
-                            };
-                            if ui.add(remove_button).clicked() {
+                            // Remove device options (available for all devices)
+                            let mut remove_mode = None;
+                            for (mode, label, help) in [
+                                (RemoveMode::Archive, "Archive Device", "Hide from the scene; restore it from the device list"),
+                                (RemoveMode::Forget, "Forget Device", "Remove it and its position from this session"),
+                            ] {
+                                let remove_button = if is_mobile {
+                                    egui::Button::new(
+                                        egui::RichText::new(label)
+                                            .size(16.0 * ui_scale)
+                                            .color(egui::Color32::from_rgb(200, 100, 100))
+                                    ).min_size(egui::vec2(0.0, 40.0))
+                                } else {
+                                    egui::Button::new(
+                                        egui::RichText::new(label)
+                                            .color(egui::Color32::from_rgb(200, 100, 100))
+                                    )
+                                };
+                                if ui.add(remove_button).clicked() {
+                                    remove_mode = Some(mode);
+                                }
+                                ui.label(
+                                    egui::RichText::new(help)
+                                        .size(10.0 * ui_scale)
+                                        .color(egui::Color32::GRAY)
+                                );
+                            }
+                            if let Some(mode) = remove_mode {
                                 // Mark device for removal (processed by separate system)
-                                params.pending_removals.0.push(id.clone());
+                                params.pending_removals.0.push((id.clone(), mode));
                                 params.selected.0 = None;
                                 params.ui_layout.show_right_panel = false;
                             }
@@ -1850,6 +2363,10 @@ fn ui_system(mut params: UiParams) {
         }
     }
 
+    if let Some(edit) = pose_edit {
+        apply_sub_element_pose_edit(&mut params.registry, params.loaded_hcdf.0.as_mut(), edit);
+    }
+
     // Graph visualization overlay
     if params.graph_vis.show {
         let screen_rect = ctx.screen_rect();
@@ -1920,29 +2437,32 @@ fn ui_system(mut params: UiParams) {
                     let pan = params.graph_vis.pan_offset;
                     let center = rect.center();
 
-                    // Calculate node positions in a radial layout
-                    let node_count = topology.nodes.len();
-                    let radius = 150.0 * zoom;
+                    // Radial layout: the root in the center, each level of its subtree a ring further out
+                    let layout = radial_layout(
+                        topology.root.as_deref(),
+                        topology.nodes.iter().map(|n| (n.id.as_str(), n.children.as_slice())),
+                        &params.graph_vis.collapsed,
+                    );
+                    let ring = 150.0 * zoom;
+                    let position_of = |id: &str| {
+                        layout.position(id).map(|[x, y]| egui::pos2(center.x + pan[0] + ring * x, center.y + pan[1] + ring * y))
+                    };
 
                     // Track hover/click state changes to apply after rendering
                     let mut new_hovered: Option<String> = None;
                     let mut clicked_node: Option<String> = None;
+                    let mut toggled_node: Option<String> = None;
 
                     // Draw connections and nodes
-                    for (i, node) in topology.nodes.iter().enumerate() {
-                        let angle = (i as f32 / node_count.max(1) as f32) * std::f32::consts::TAU;
-                        let node_x = center.x + pan[0] + radius * angle.cos();
-                        let node_y = center.y + pan[1] + radius * angle.sin();
-                        let node_pos = egui::pos2(node_x, node_y);
+                    for node in &topology.nodes {
+                        // Inside a collapsed subtree
+                        let Some(node_pos) = position_of(&node.id) else {
+                            continue;
+                        };
 
                         // Draw connections to children
                         for child_id in &node.children {
-                            if let Some((j, _)) = topology.nodes.iter().enumerate().find(|(_, n)| &n.id == child_id) {
-                                let child_angle = (j as f32 / node_count.max(1) as f32) * std::f32::consts::TAU;
-                                let child_x = center.x + pan[0] + radius * child_angle.cos();
-                                let child_y = center.y + pan[1] + radius * child_angle.sin();
-                                let child_pos = egui::pos2(child_x, child_y);
-
+                            if let Some(child_pos) = position_of(child_id) {
                                 painter.line_segment(
                                     [node_pos, child_pos],
                                     egui::Stroke::new(2.0 * zoom, egui::Color32::from_rgb(100, 150, 200)),
@@ -2000,6 +2520,18 @@ fn ui_system(mut params: UiParams) {
                             );
                         }
 
+                        // Count of the nodes a collapsed parent hides
+                        let hidden = layout.hidden_under(&node.id);
+                        if hidden > 0 {
+                            painter.text(
+                                egui::pos2(node_pos.x + node_radius + 4.0 * zoom, node_pos.y),
+                                egui::Align2::LEFT_CENTER,
+                                format!("+{}", hidden),
+                                egui::FontId::proportional(font_size * 0.9),
+                                egui::Color32::from_rgb(255, 180, 80),
+                            );
+                        }
+
                         // Port number if available
                         if let Some(port) = node.port {
                             painter.text(
@@ -2017,9 +2549,11 @@ fn ui_system(mut params: UiParams) {
                             if node_rect.contains(pointer_pos) {
                                 new_hovered = Some(node.id.clone());
 
-                                // Click to select
+                                // Click to select, right-click a parent to fold its subtree
                                 if response.clicked() {
                                     clicked_node = Some(node.id.clone());
+                                } else if response.secondary_clicked() && !node.children.is_empty() {
+                                    toggled_node = Some(node.id.clone());
                                 }
                             }
                         }
@@ -2027,6 +2561,11 @@ fn ui_system(mut params: UiParams) {
 
                     // Apply state changes after iteration
                     params.graph_vis.hovered_node = new_hovered;
+                    if let Some(node_id) = toggled_node {
+                        if !params.graph_vis.collapsed.remove(&node_id) {
+                            params.graph_vis.collapsed.insert(node_id);
+                        }
+                    }
                     if let Some(node_id) = clicked_node {
                         params.selected.0 = Some(node_id);
                         params.graph_vis.show = false; // Close graph and show device details
@@ -2045,7 +2584,7 @@ fn ui_system(mut params: UiParams) {
                 // Instructions at bottom
                 ui.add_space(4.0);
                 ui.horizontal(|ui| {
-                    ui.label(egui::RichText::new("Drag to pan | Scroll to zoom | Click node to select").small().color(egui::Color32::GRAY));
+                    ui.label(egui::RichText::new("Drag to pan | Scroll to zoom | Click node to select | Right-click parent to collapse").small().color(egui::Color32::GRAY));
                 });
             });
     }
@@ -2079,3 +2618,187 @@ fn capitalize_first(s: &str) -> String {
         Some(c) => c.to_uppercase().collect::<String>() + chars.as_str(),
     }
 }
+
+/// Kind of sub-element targeted by a pose edit
+#[derive(Debug, Clone, Copy)]
+enum SubElement {
+    Port,
+    Antenna,
+}
+
+/// A port/antenna pose edit made in the details panel
+struct SubElementPoseEdit {
+    device_id: String,
+    kind: SubElement,
+    name: String,
+    /// New pose, or `None` to reset to the pose loaded from the HCDF
+    pose: Option<[f64; 6]>,
+}
+
+/// Pose fields for a port/antenna fallback geometry, in millimeters and degrees.
+/// Returns `Some(Some(pose))` for an edit and `Some(None)` for a reset.
+fn sub_element_pose_editor(
+    ui: &mut egui::Ui,
+    name: &str,
+    pose: Option<[f64; 6]>,
+    original_pose: Option<[f64; 6]>,
+    ui_scale: f32,
+) -> Option<Option<[f64; 6]>> {
+    let pose = pose.unwrap_or_default();
+    let mut values = [
+        pose[0] * 1000.0,
+        pose[1] * 1000.0,
+        pose[2] * 1000.0,
+        pose[3].to_degrees(),
+        pose[4].to_degrees(),
+        pose[5].to_degrees(),
+    ];
+    let mut changed = false;
+
+    ui.label(
+        egui::RichText::new(format!("{} pose", name))
+            .size(12.0 * ui_scale)
+            .strong()
+    );
+    egui::Grid::new(("sub_element_pose", name))
+        .num_columns(2)
+        .show(ui, |ui| {
+            for (i, label) in ["  X:", "  Y:", "  Z:", "  Roll:", "  Pitch:", "  Yaw:"].iter().enumerate() {
+                ui.label(*label);
+                let drag = if i < 3 {
+                    egui::DragValue::new(&mut values[i]).speed(0.1).suffix(" mm")
+                } else {
+                    egui::DragValue::new(&mut values[i]).speed(1.0).suffix("°")
+                };
+                changed |= ui.add(drag).changed();
+                ui.end_row();
+            }
+        });
+
+    let mut reset = false;
+    if original_pose.is_some() {
+        ui.horizontal(|ui| {
+            ui.label(
+                egui::RichText::new("edited")
+                    .size(11.0 * ui_scale)
+                    .italics()
+                    .color(egui::Color32::from_rgb(255, 200, 50))
+            );
+            reset = ui
+                .small_button("Reset")
+                .on_hover_text("Restore the pose loaded from the HCDF")
+                .clicked();
+        });
+    }
+
+    if reset {
+        return Some(None);
+    }
+    changed.then_some(Some([
+        values[0] / 1000.0,
+        values[1] / 1000.0,
+        values[2] / 1000.0,
+        values[3].to_radians(),
+        values[4].to_radians(),
+        values[5].to_radians(),
+    ]))
+}
+
+/// Apply a port/antenna pose edit to the registry (the marker follows it) and
+/// write it through to the loaded HCDF document for export
+fn apply_sub_element_pose_edit(registry: &mut DeviceRegistry, hcdf: Option<&mut Hcdf>, edit: SubElementPoseEdit) {
+    let Some(device) = registry.devices.iter_mut().find(|d| d.id == edit.device_id) else {
+        return;
+    };
+    let fields = match edit.kind {
+        SubElement::Port => device
+            .ports
+            .iter_mut()
+            .find(|p| p.name == edit.name)
+            .map(|p| (&mut p.pose, &mut p.original_pose)),
+        SubElement::Antenna => device
+            .antennas
+            .iter_mut()
+            .find(|a| a.name == edit.name)
+            .map(|a| (&mut a.pose, &mut a.original_pose)),
+    };
+    let Some((pose, original_pose)) = fields else {
+        return;
+    };
+
+    let new_pose = match edit.pose {
+        Some(new_pose) => {
+            if original_pose.is_none() {
+                *original_pose = Some(pose.unwrap_or_default());
+            }
+            new_pose
+        }
+        None => match original_pose.take() {
+            Some(original) => original,
+            None => return,
+        },
+    };
+    *pose = Some(new_pose);
+
+    // Comps are the only HCDF elements with ports and antennas
+    let Some(hcdf) = hcdf else {
+        return;
+    };
+    let new_pose = Pose::from_array(new_pose);
+    for comp in hcdf
+        .comp
+        .iter_mut()
+        .filter(|c| c.hwid.as_deref().unwrap_or(&c.name) == edit.device_id)
+    {
+        match edit.kind {
+            SubElement::Port => comp
+                .port
+                .iter_mut()
+                .filter(|p| p.name == edit.name)
+                .for_each(|p| p.set_pose(&new_pose)),
+            SubElement::Antenna => comp
+                .antenna
+                .iter_mut()
+                .filter(|a| a.name == edit.name)
+                .for_each(|a| a.set_pose(&new_pose)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CASED: &str = r#"<hcdf version="1.2">
+    <mcu name="spinali-001" hwid="0x1234">
+        <visual name="board" toggle="board"/>
+        <visual name="case" toggle="case" default_hidden="true"/>
+    </mcu>
+    <comp name="navq95">
+        <visual name="lid" toggle="lid"/>
+    </comp>
+</hcdf>"#;
+
+    #[test]
+    fn test_export_records_toggle_states() {
+        let loaded = Hcdf::from_xml(CASED).unwrap();
+        let devices = vec![convert_mcu_to_device(&loaded.mcu[0]), convert_comp_to_device(&loaded.comp[0])];
+        let mut frame_visibility = FrameVisibility::default();
+        for device in &devices {
+            frame_visibility.init_toggle_defaults(&device.id, &device.visuals);
+        }
+
+        // Untouched toggles export as loaded
+        let untouched = hcdf_for_export(&loaded, &devices, &frame_visibility);
+        assert_eq!(untouched.to_xml().unwrap(), loaded.to_xml().unwrap());
+
+        frame_visibility.set_toggle_hidden("0x1234", "case", false);
+        frame_visibility.set_toggle_hidden("navq95", "lid", true);
+        let exported = Hcdf::from_xml(&hcdf_for_export(&loaded, &devices, &frame_visibility).to_xml().unwrap()).unwrap();
+        assert_eq!(exported.mcu[0].visual[0].default_hidden, None);
+        assert_eq!(exported.mcu[0].visual[1].default_hidden, Some(false));
+        assert_eq!(exported.comp[0].visual[0].default_hidden, Some(true));
+        // The loaded document keeps its own defaults
+        assert_eq!(loaded.mcu[0].visual[1].default_hidden, Some(true));
+    }
+}
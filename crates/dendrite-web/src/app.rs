@@ -4,12 +4,25 @@ use bevy::prelude::*;
 use bevy::winit::WinitSettings;
 use bevy_egui::EguiPlugin;
 use bevy_picking::{DefaultPickingPlugins, prelude::MeshPickingPlugin};
+use dendrite_core::FrameNode;
 use std::time::Duration;
 
+use crate::assemblies::AssembliesPlugin;
+use crate::attention::AttentionPlugin;
+use crate::daemons::DaemonsPlugin;
+use crate::device_filter::DeviceListFilter;
 use crate::file_picker::FilePickerPlugin;
+use crate::fleet::FleetPlugin;
+use crate::heatmap::DeviceHeatmapPlugin;
+use crate::history::HistoryPlugin;
 use crate::models::ModelsPlugin;
 use crate::network::NetworkPlugin;
+use crate::occlusion::OcclusionPlugin;
+use crate::overlap::OverlapPlugin;
+use crate::ota_batch::OtaBatchPlugin;
 use crate::scene::ScenePlugin;
+use crate::setup::SetupPlugin;
+use crate::topology::TopologyGraphPlugin;
 use crate::ui::UiPlugin;
 
 /// Device data from the backend
@@ -17,6 +30,9 @@ use crate::ui::UiPlugin;
 pub struct DeviceRegistry {
     pub devices: Vec<DeviceData>,
     pub connected: bool,
+    /// Offline devices the daemon is retrying, by ID: when the next heartbeat
+    /// check is due, in seconds of `Time::elapsed`
+    pub retry_at: std::collections::HashMap<String, f64>,
 }
 
 /// Visual element data - a 3D model with a pose offset
@@ -25,6 +41,8 @@ pub struct VisualData {
     pub name: String,
     /// Toggle group name for visibility control (e.g., "case")
     pub toggle: Option<String>,
+    /// Whether the toggle group starts hidden when the device is first loaded
+    pub default_hidden: bool,
     /// Pose offset: (x, y, z, roll, pitch, yaw) in meters/radians
     pub pose: Option<[f64; 6]>,
     /// Model file path
@@ -38,8 +56,25 @@ pub struct VisualData {
 pub struct FrameData {
     pub name: String,
     pub description: Option<String>,
-    /// Pose offset: (x, y, z, roll, pitch, yaw) in meters/radians
+    /// Pose offset: (x, y, z, roll, pitch, yaw) in meters/radians, relative
+    /// to `parent` if set, otherwise to the device origin
     pub pose: Option<[f64; 6]>,
+    /// Frame this one is positioned relative to
+    pub parent: Option<String>,
+}
+
+impl FrameNode for FrameData {
+    fn frame_name(&self) -> &str {
+        &self.name
+    }
+
+    fn parent_frame(&self) -> Option<&str> {
+        self.parent.as_deref()
+    }
+
+    fn local_pose(&self) -> dendrite_core::Pose {
+        self.pose.map(dendrite_core::Pose::from_array).unwrap_or_default()
+    }
 }
 
 /// Axis alignment for sensor driver transforms
@@ -92,6 +127,8 @@ pub struct PortData {
     pub visual_name: Option<String>,
     /// GLTF mesh node name within the visual (e.g., "port_eth0")
     pub mesh_name: Option<String>,
+    /// Pose from the device's HCDF before a local edit (None if unedited)
+    pub original_pose: Option<[f64; 6]>,
 }
 
 /// Sensor data - sensor with pose, axis alignment, and optional FOV geometry
@@ -104,6 +141,10 @@ pub struct SensorData {
     pub sensor_type: String,
     /// Driver name (icm45686, bmm350, etc.)
     pub driver: Option<String>,
+    /// Human-readable driver name from the driver registry (e.g., "Bosch BMI088 (IMU)")
+    pub driver_label: Option<String>,
+    /// Driver datasheet URL from the driver registry
+    pub datasheet: Option<String>,
     /// Pose offset: (x, y, z, roll, pitch, yaw) in meters/radians
     pub pose: Option<[f64; 6]>,
     /// Axis alignment for driver transforms
@@ -126,6 +167,12 @@ pub struct DeviceData {
     pub position: Option<[f64; 3]>,
     /// Orientation as [roll, pitch, yaw] in radians
     pub orientation: Option<[f64; 3]>,
+    /// Pose was suggested by the daemon's layout, not placed by a user
+    pub pose_provisional: bool,
+    /// Pose edits keep the bounding box above the ground plane
+    pub keep_above_ground: bool,
+    /// ID of the device this one rests on while its pose is edited
+    pub stack_on: Option<String>,
     /// Legacy single model path (for backward compatibility)
     pub model_path: Option<String>,
     /// Composite visuals with individual poses
@@ -137,6 +184,10 @@ pub struct DeviceData {
     /// Sensors on this device
     pub sensors: Vec<SensorData>,
     pub last_seen: Option<String>,
+    /// Heartbeat method override (None = auto-selected by the daemon)
+    pub heartbeat_method: Option<String>,
+    /// CAN bus and node id, for devices found on a CAN bus
+    pub can: Option<(String, u8)>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -182,6 +233,31 @@ pub struct OtaState {
     pub device_updates: std::collections::HashMap<String, crate::network::OtaUpdateState>,
 }
 
+/// Transient notifications shown as an overlay (e.g., an archived device reappeared)
+#[derive(Debug, Clone, Resource, Default)]
+pub struct Notifications {
+    /// Active messages with their remaining display time in seconds
+    pub messages: Vec<(String, f32)>,
+}
+
+impl Notifications {
+    /// How long a notification stays on screen
+    pub const DISPLAY_SECS: f32 = 6.0;
+
+    /// Show a new notification
+    pub fn push(&mut self, message: impl Into<String>) {
+        self.messages.push((message.into(), Self::DISPLAY_SECS));
+    }
+
+    /// Advance timers and drop expired notifications
+    pub fn tick(&mut self, delta_secs: f32) {
+        for (_, remaining) in &mut self.messages {
+            *remaining -= delta_secs;
+        }
+        self.messages.retain(|(_, remaining)| *remaining > 0.0);
+    }
+}
+
 /// Currently selected device
 #[derive(Debug, Clone, Resource, Default)]
 pub struct SelectedDevice(pub Option<String>);
@@ -195,6 +271,7 @@ pub struct CameraSettings {
     pub elevation: f32,
     pub target: Vec3,
     pub target_focus: Vec3, // For smooth re-centering
+    pub target_orbit: Option<(f32, f32)>, // Azimuth and elevation of a recalled view
     pub sensitivity: f32,
     pub zoom_speed: f32,
     pub smooth_factor: f32,
@@ -209,6 +286,7 @@ impl Default for CameraSettings {
             elevation: 0.5, // Slightly elevated view
             target: Vec3::ZERO,
             target_focus: Vec3::ZERO,
+            target_orbit: None,
             sensitivity: 0.005,
             zoom_speed: 0.1,
             smooth_factor: 0.15,
@@ -266,7 +344,8 @@ pub struct FrameVisibility {
     /// Whether the frame hover came from a click/tap (sticky until next click)
     pub hovered_frame_from_click: bool,
     /// Per-device, per-toggle-group hidden state: (device_id, toggle_group) -> is_hidden
-    /// Default is visible (not hidden), so only hidden groups are tracked
+    /// Seeded from the visuals' `default_hidden` when a device is first loaded;
+    /// groups without an entry are visible
     pub hidden_toggles: std::collections::HashMap<(String, String), bool>,
     /// Per-device sensor (FOV) visibility (device_id -> show_sensors)
     pub device_sensors: std::collections::HashMap<String, bool>,
@@ -287,6 +366,8 @@ pub struct FrameVisibility {
     pub hovered_port: Option<String>,
     /// Whether the current port hover came from UI (true) or 3D (false)
     pub hovered_port_from_ui: bool,
+    /// Port selected for pose editing (device_id:port_name)
+    pub selected_port: Option<String>,
     /// Per-sensor axis alignment mode: (device_id, sensor_name) -> show_aligned
     /// Default is true (show aligned), false shows raw physical axes
     pub sensor_axis_aligned: std::collections::HashMap<(String, String), bool>,
@@ -322,12 +403,41 @@ impl FrameVisibility {
 
     /// Set whether a toggle group is hidden for a specific device
     pub fn set_toggle_hidden(&mut self, device_id: &str, toggle_group: &str, hidden: bool) {
-        let key = (device_id.to_string(), toggle_group.to_string());
-        if hidden {
-            self.hidden_toggles.insert(key, true);
-        } else {
-            self.hidden_toggles.remove(&key);
+        self.hidden_toggles
+            .insert((device_id.to_string(), toggle_group.to_string()), hidden);
+    }
+
+    /// Initial hidden state of a toggle group, as declared by its visuals
+    pub fn default_toggle_hidden(visuals: &[VisualData], toggle_group: &str) -> bool {
+        visuals
+            .iter()
+            .any(|v| v.toggle.as_deref() == Some(toggle_group) && v.default_hidden)
+    }
+
+    /// Seed toggle state for a device's groups that have not been seen yet
+    /// (existing user choices are left untouched)
+    pub fn init_toggle_defaults(&mut self, device_id: &str, visuals: &[VisualData]) {
+        for group in Self::get_toggle_groups(visuals) {
+            let hidden = Self::default_toggle_hidden(visuals, &group);
+            self.hidden_toggles
+                .entry((device_id.to_string(), group))
+                .or_insert(hidden);
+        }
+    }
+
+    /// Toggle groups whose current state differs from the loaded default,
+    /// as (device_id, toggle_group, hidden) for recording on export
+    pub fn changed_toggle_states(&self, devices: &[DeviceData]) -> Vec<(String, String, bool)> {
+        let mut changed = Vec::new();
+        for device in devices {
+            for group in Self::get_toggle_groups(&device.visuals) {
+                let hidden = self.is_toggle_hidden(&device.id, &group);
+                if hidden != Self::default_toggle_hidden(&device.visuals, &group) {
+                    changed.push((device.id.clone(), group, hidden));
+                }
+            }
         }
+        changed
     }
 
     /// Get all unique toggle groups from a device's visuals
@@ -448,6 +558,8 @@ pub struct WorldSettings {
     pub grid_alpha: f32,
     /// Render scale factor (1.0 = native, 0.5 = half resolution for performance)
     pub render_scale: f32,
+    /// Compare device bounding boxes after every move and outline overlaps
+    pub check_overlaps: bool,
     // Track previous values to detect specific changes
     prev_spacing: f32,
     prev_thickness: f32,
@@ -463,6 +575,7 @@ impl Default for WorldSettings {
             grid_line_thickness: 0.0002, // 0.2mm default thickness
             grid_alpha: 0.5, // 50% transparent by default
             render_scale: 1.0, // Native resolution by default
+            check_overlaps: true,
             prev_spacing: 0.1,
             prev_thickness: 0.0002,
             prev_alpha: 0.5,
@@ -591,6 +704,8 @@ pub struct GraphVisualization {
     pub hovered_node: Option<String>,
     /// Cached topology data
     pub topology: Option<TopologyData>,
+    /// Parents whose subtrees are folded away
+    pub collapsed: std::collections::HashSet<String>,
 }
 
 /// Topology data for graph visualization
@@ -598,6 +713,50 @@ pub struct GraphVisualization {
 pub struct TopologyData {
     pub nodes: Vec<TopologyNode>,
     pub root: Option<String>,
+    /// Shared buses, drawn as one bar instead of point-to-point links
+    pub buses: Vec<TopologyBus>,
+}
+
+/// A shared bus segment (a CAN bus) and the nodes on it, by node id
+#[derive(Debug, Clone)]
+pub struct TopologyBus {
+    pub name: String,
+    pub members: Vec<String>,
+}
+
+impl TopologyData {
+    /// Flat graph of the device registry, with its CAN buses
+    pub fn from_devices(devices: &[DeviceData]) -> Self {
+        let nodes = devices
+            .iter()
+            .map(|d| TopologyNode {
+                id: d.id.clone(),
+                name: d.name.clone(),
+                board: d.board.clone(),
+                // Hierarchy comes from the HCDF, see `topology::from_topology`
+                is_parent: false,
+                port: d.port,
+                can_node_id: d.can.as_ref().map(|(_, node_id)| *node_id),
+                children: Vec::new(),
+                unverified: false,
+            })
+            .collect();
+
+        let mut on_bus: Vec<(&str, u8, &str)> = devices
+            .iter()
+            .filter_map(|d| d.can.as_ref().map(|(bus, node_id)| (bus.as_str(), *node_id, d.id.as_str())))
+            .collect();
+        on_bus.sort();
+        let mut buses: Vec<TopologyBus> = Vec::new();
+        for (bus, _, id) in on_bus {
+            match buses.last_mut() {
+                Some(last) if last.name == bus => last.members.push(id.to_string()),
+                _ => buses.push(TopologyBus { name: bus.to_string(), members: vec![id.to_string()] }),
+            }
+        }
+
+        Self { nodes, root: None, buses }
+    }
 }
 
 /// A node in the topology graph
@@ -608,7 +767,11 @@ pub struct TopologyNode {
     pub board: Option<String>,
     pub is_parent: bool,
     pub port: Option<u8>,
+    /// Node id on its CAN bus
+    pub can_node_id: Option<u8>,
     pub children: Vec<String>,
+    /// Heard over LLDP but never answered MCUmgr discovery
+    pub unverified: bool,
 }
 
 impl Default for GraphVisualization {
@@ -619,6 +782,7 @@ impl Default for GraphVisualization {
             zoom: 1.0,
             hovered_node: None,
             topology: None,
+            collapsed: std::collections::HashSet::new(),
         }
     }
 }
@@ -666,15 +830,28 @@ pub fn run() {
         .init_resource::<FrameVisibility>()
         .init_resource::<WorldSettings>()
         .init_resource::<UiLayout>()
+        .init_resource::<DeviceListFilter>()
         .init_resource::<ConnectionDialog>()
         .init_resource::<FirmwareCheckState>()
         .init_resource::<OtaState>()
+        .init_resource::<Notifications>()
         .init_resource::<GraphVisualization>()
         .add_plugins(FilePickerPlugin)
         .add_plugins(NetworkPlugin)
+        .add_plugins(DaemonsPlugin)
         .add_plugins(ScenePlugin)
         .add_plugins(ModelsPlugin)
+        .add_plugins(OcclusionPlugin)
+        .add_plugins(OverlapPlugin)
+        .add_plugins(DeviceHeatmapPlugin)
+        .add_plugins(AssembliesPlugin)
         .add_plugins(UiPlugin)
+        .add_plugins(SetupPlugin)
+        .add_plugins(HistoryPlugin)
+        .add_plugins(FleetPlugin)
+        .add_plugins(OtaBatchPlugin)
+        .add_plugins(TopologyGraphPlugin)
+        .add_plugins(AttentionPlugin)
         .add_systems(Update, (
             adjust_power_settings_for_mobile,
             apply_render_scale,
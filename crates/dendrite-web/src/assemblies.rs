@@ -0,0 +1,304 @@
+//! Device assemblies
+//!
+//! Assemblies are read from the primary daemon's `GET /api/assemblies`. The
+//! device list shows each one as a group with a pose editor for its origin:
+//! members follow the edit locally using the same rigid transform the daemon
+//! applies, and the daemon moves them for real when the drag ends. A
+//! highlighted assembly gets a box around its members in the 3D view.
+
+use bevy::prelude::*;
+use dendrite_core::{rigid_transform, Pose};
+use serde::Deserialize;
+use std::sync::{Arc, Mutex};
+
+use crate::daemons::{split_id, PRIMARY_DAEMON};
+use crate::models::ExcludeFromBounds;
+use crate::network::{DaemonConfig, PeriodicSyncTimer};
+use crate::scene::DeviceEntity;
+
+/// Space left around the members inside the highlight box (meters)
+const HIGHLIGHT_MARGIN: f32 = 0.03;
+
+const HIGHLIGHT_COLOR: Color = Color::srgba(1.0, 0.75, 0.2, 0.15);
+
+/// An assembly as listed by the daemon
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct AssemblyInfo {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Pose written in the HCDF, if any
+    #[serde(default)]
+    pub pose: Option<[f64; 6]>,
+    /// Effective origin (the pose, or the member centroid)
+    pub origin: [f64; 6],
+    pub members: Vec<String>,
+}
+
+/// Poses captured when a group edit starts, so every step of a drag is
+/// applied to the same starting layout
+#[derive(Debug, Clone)]
+struct GroupEdit {
+    name: String,
+    origin: Pose,
+    members: Vec<(String, Pose)>,
+}
+
+#[derive(Resource, Default)]
+pub struct Assemblies {
+    pub list: Vec<AssemblyInfo>,
+    /// Assembly drawn as a unit in the 3D view and topology graph
+    pub highlighted: Option<String>,
+    editing: Option<GroupEdit>,
+}
+
+impl Assemblies {
+    /// The assembly a device (in the frontend's IDs) belongs to
+    pub fn of(&self, device_id: &str) -> Option<&AssemblyInfo> {
+        let (key, raw) = split_id(device_id);
+        if key != PRIMARY_DAEMON {
+            return None;
+        }
+        self.list.iter().find(|a| a.members.iter().any(|m| m == raw))
+    }
+
+    pub fn is_highlighted(&self, device_id: &str) -> bool {
+        self.highlighted.is_some() && self.of(device_id).map(|a| &a.name) == self.highlighted.as_ref()
+    }
+
+    /// Member poses after moving the origin of `name` to `target`
+    ///
+    /// `current` gives a member's pose in the scene; members without one are
+    /// left alone. The assembly's origin is updated locally until the daemon
+    /// confirms the move.
+    pub fn preview(&mut self, name: &str, target: Pose, current: impl Fn(&str) -> Option<Pose>) -> Vec<(String, Pose)> {
+        let Some(assembly) = self.list.iter_mut().find(|a| a.name == name) else {
+            return Vec::new();
+        };
+        if !matches!(&self.editing, Some(edit) if edit.name == name) {
+            self.editing = Some(GroupEdit {
+                name: name.to_string(),
+                origin: Pose::from_array(assembly.origin),
+                members: assembly
+                    .members
+                    .iter()
+                    .filter_map(|m| Some((m.clone(), current(m)?)))
+                    .collect(),
+            });
+        }
+        assembly.origin = target.to_array();
+
+        let Some(edit) = &self.editing else {
+            return Vec::new();
+        };
+        edit.members
+            .iter()
+            .map(|(id, pose)| (id.clone(), rigid_transform(&edit.origin, &target, pose)))
+            .collect()
+    }
+
+    /// End the current group edit, returning the assembly name and the
+    /// origin to send to the daemon
+    pub fn finish(&mut self) -> Option<(String, [f64; 6])> {
+        let edit = self.editing.take()?;
+        let assembly = self.list.iter().find(|a| a.name == edit.name)?;
+        Some((edit.name, assembly.origin))
+    }
+}
+
+#[derive(Resource, Default)]
+struct PendingAssemblies(Arc<Mutex<Option<Vec<AssemblyInfo>>>>);
+
+/// Translucent box around the members of the highlighted assembly
+#[derive(Component)]
+struct AssemblyHighlight;
+
+pub struct AssembliesPlugin;
+
+impl Plugin for AssembliesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Assemblies>()
+            .init_resource::<PendingAssemblies>()
+            .add_systems(Update, (refetch_assemblies, process_assemblies, sync_assembly_highlight));
+    }
+}
+
+/// Fetch on startup, after reconnecting and alongside the periodic device sync
+fn refetch_assemblies(
+    sync_timer: Res<PeriodicSyncTimer>,
+    pending: Res<PendingAssemblies>,
+    daemon_config: Res<DaemonConfig>,
+) {
+    if sync_timer.timer.just_finished() || daemon_config.is_changed() {
+        request_assemblies(&daemon_config.http_url, &pending);
+    }
+}
+
+fn request_assemblies(base_url: &str, pending: &PendingAssemblies) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let pending = pending.0.clone();
+        let url = format!("{}/api/assemblies", base_url);
+        wasm_bindgen_futures::spawn_local(async move {
+            match crate::network::DaemonRequest::get(&url).send().await {
+                Ok(response) if response.ok() => match response.json::<Vec<AssemblyInfo>>().await {
+                    Ok(list) => {
+                        if let Ok(mut data) = pending.lock() {
+                            *data = Some(list);
+                        }
+                    }
+                    Err(e) => tracing::warn!("Invalid assembly list: {:?}", e),
+                },
+                // Older daemons have no assemblies
+                Ok(_) => {}
+                Err(e) => tracing::error!("Failed to fetch assemblies: {:?}", e),
+            }
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = (base_url, pending);
+    }
+}
+
+/// Ask the primary daemon to move an assembly's origin (members follow)
+pub fn send_assembly_pose(base_url: &str, name: &str, pose: [f64; 6]) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let url = format!("{}/api/assemblies/{}/pose", base_url, name);
+        wasm_bindgen_futures::spawn_local(async move {
+            match crate::network::DaemonRequest::put(&url)
+                .header("Content-Type", "application/json")
+                .body(serde_json::json!({ "pose": pose }).to_string())
+                .unwrap()
+                .send()
+                .await
+            {
+                Ok(response) if response.ok() => {}
+                Ok(response) => {
+                    let status = response.status();
+                    let text = response.text().await.unwrap_or_default();
+                    tracing::error!("Failed to move assembly: {} - {}", status, text);
+                }
+                Err(e) => tracing::error!("Failed to move assembly: {:?}", e),
+            }
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = (base_url, name, pose);
+        tracing::warn!("Assembly move not available in native mode");
+    }
+}
+
+/// Take fetched assemblies, keeping local origins while a group edit is open
+fn process_assemblies(pending: Res<PendingAssemblies>, mut assemblies: ResMut<Assemblies>) {
+    let Some(list) = pending.0.lock().ok().and_then(|mut data| data.take()) else {
+        return;
+    };
+    if assemblies.editing.is_some() || assemblies.list == list {
+        return;
+    }
+    if let Some(name) = &assemblies.highlighted {
+        if !list.iter().any(|a| &a.name == name) {
+            assemblies.highlighted = None;
+        }
+    }
+    assemblies.list = list;
+}
+
+/// Keep one box around the highlighted assembly's members
+fn sync_assembly_highlight(
+    mut commands: Commands,
+    assemblies: Res<Assemblies>,
+    moved: Query<(), (With<DeviceEntity>, Changed<Transform>)>,
+    device_query: Query<(&DeviceEntity, &Transform)>,
+    highlight_query: Query<Entity, With<AssemblyHighlight>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    if !assemblies.is_changed() && moved.is_empty() {
+        return;
+    }
+    for entity in highlight_query.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let members: Vec<Vec3> = device_query
+        .iter()
+        .filter(|(device, _)| assemblies.is_highlighted(&device.device_id))
+        .map(|(_, transform)| transform.translation)
+        .collect();
+    let Some(first) = members.first() else {
+        return;
+    };
+    let (min, max) = members.iter().fold((*first, *first), |(min, max), p| (min.min(*p), max.max(*p)));
+    let size = max - min + Vec3::splat(2.0 * HIGHLIGHT_MARGIN);
+
+    commands.spawn((
+        Mesh3d(meshes.add(Cuboid::new(size.x, size.y, size.z))),
+        MeshMaterial3d(materials.add(StandardMaterial {
+            base_color: HIGHLIGHT_COLOR,
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            double_sided: true,
+            cull_mode: None,
+            ..default()
+        })),
+        Transform::from_translation((min + max) / 2.0),
+        ExcludeFromBounds,
+        AssemblyHighlight,
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pod() -> Assemblies {
+        Assemblies {
+            list: vec![AssemblyInfo {
+                name: "front_pod".to_string(),
+                description: None,
+                pose: None,
+                origin: [1.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+                members: vec!["0x01".to_string(), "0x02".to_string()],
+            }],
+            highlighted: None,
+            editing: None,
+        }
+    }
+
+    #[test]
+    fn test_membership_is_primary_only() {
+        let mut assemblies = pod();
+        assert_eq!(assemblies.of("0x01").map(|a| a.name.as_str()), Some("front_pod"));
+        assert!(assemblies.of("d1/0x01").is_none());
+        assert!(!assemblies.is_highlighted("0x01"));
+        assemblies.highlighted = Some("front_pod".to_string());
+        assert!(assemblies.is_highlighted("0x02"));
+        assert!(!assemblies.is_highlighted("0x03"));
+    }
+
+    #[test]
+    fn test_preview_starts_from_the_same_layout() {
+        let mut assemblies = pod();
+        let current = |id: &str| match id {
+            "0x01" => Some(Pose::from_array([1.0, 0.1, 0.0, 0.0, 0.0, 0.0])),
+            _ => None,
+        };
+
+        assemblies.preview("front_pod", Pose::from_array([2.0, 0.0, 0.0, 0.0, 0.0, 0.0]), current);
+        // The second step of a drag is measured from where the edit started,
+        // not from the previewed layout
+        let poses = assemblies.preview("front_pod", Pose::from_array([3.0, 0.0, 0.0, 0.0, 0.0, 0.0]), |_| None);
+        assert_eq!(poses.len(), 1);
+        assert_eq!(poses[0].0, "0x01");
+        assert!((poses[0].1.x - 3.0).abs() < 1e-9 && (poses[0].1.y - 0.1).abs() < 1e-9);
+
+        assert_eq!(assemblies.finish(), Some(("front_pod".to_string(), [3.0, 0.0, 0.0, 0.0, 0.0, 0.0])));
+        assert_eq!(assemblies.finish(), None);
+    }
+}
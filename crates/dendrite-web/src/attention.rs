@@ -0,0 +1,278 @@
+//! Attention inbox
+//!
+//! Findings come from the primary daemon's `GET /api/attention`, refetched
+//! with the periodic device sync, and are kept current between fetches by
+//! `attention` WebSocket messages. The bell button in the device panel shows
+//! how many are open; the Attention window lists everything unresolved, with
+//! a button to focus the affected device and one to acknowledge.
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts, EguiPrimaryContextPass};
+use serde::Deserialize;
+use std::sync::{Arc, Mutex};
+
+use crate::app::{CameraSettings, DeviceRegistry, SelectedDevice};
+use crate::network::{DaemonConfig, PeriodicSyncTimer};
+use crate::scene::DeviceEntity;
+
+/// A finding as reported by the daemon
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Finding {
+    pub id: String,
+    /// "info", "warning" or "critical"
+    pub severity: String,
+    #[serde(default)]
+    pub device: Option<String>,
+    pub message: String,
+    /// "open", "acknowledged" or "resolved"
+    pub state: String,
+    pub occurrences: u32,
+}
+
+impl Finding {
+    fn is_open(&self) -> bool {
+        self.state == "open"
+    }
+
+    fn is_resolved(&self) -> bool {
+        self.state == "resolved"
+    }
+
+    fn color(&self) -> egui::Color32 {
+        match self.severity.as_str() {
+            "critical" => egui::Color32::from_rgb(255, 100, 100),
+            "warning" => egui::Color32::from_rgb(255, 200, 50),
+            _ => egui::Color32::from_rgb(120, 180, 255),
+        }
+    }
+}
+
+/// `GET /api/attention` response
+#[derive(Debug, Clone, Deserialize)]
+struct AttentionList {
+    findings: Vec<Finding>,
+}
+
+#[derive(Resource, Default)]
+pub struct Attention {
+    pub open: bool,
+    /// Unresolved findings, most urgent first
+    findings: Vec<Finding>,
+}
+
+impl Attention {
+    /// Number of findings nobody has acknowledged yet
+    pub fn open_count(&self) -> usize {
+        self.findings.iter().filter(|f| f.is_open()).count()
+    }
+
+    /// Apply a streamed change; the same finding always replaces its entry
+    pub fn upsert(&mut self, finding: Finding) {
+        self.findings.retain(|f| f.id != finding.id);
+        if !finding.is_resolved() {
+            self.findings.push(finding);
+            self.sort();
+        }
+    }
+
+    fn replace(&mut self, findings: Vec<Finding>) {
+        self.findings = findings.into_iter().filter(|f| !f.is_resolved()).collect();
+        self.sort();
+    }
+
+    /// Open before acknowledged, most severe first (stable, so the daemon's
+    /// recency order is kept otherwise)
+    fn sort(&mut self) {
+        let severity = |f: &Finding| match f.severity.as_str() {
+            "critical" => 0,
+            "warning" => 1,
+            _ => 2,
+        };
+        self.findings.sort_by_key(|f| (!f.is_open(), severity(f)));
+    }
+}
+
+#[derive(Resource, Default)]
+struct PendingAttention(Arc<Mutex<Option<Vec<Finding>>>>);
+
+pub struct AttentionPlugin;
+
+impl Plugin for AttentionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Attention>()
+            .init_resource::<PendingAttention>()
+            .add_systems(Update, (refetch_attention, process_attention))
+            .add_systems(EguiPrimaryContextPass, render_attention);
+    }
+}
+
+/// Fetch on startup, after reconnecting and alongside the periodic device sync
+fn refetch_attention(
+    sync_timer: Res<PeriodicSyncTimer>,
+    pending: Res<PendingAttention>,
+    daemon_config: Res<DaemonConfig>,
+) {
+    if !(sync_timer.timer.just_finished() || daemon_config.is_changed()) {
+        return;
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        let pending = pending.0.clone();
+        let url = format!("{}/api/attention", daemon_config.http_url);
+        wasm_bindgen_futures::spawn_local(async move {
+            match crate::network::DaemonRequest::get(&url).send().await {
+                Ok(response) if response.ok() => match response.json::<AttentionList>().await {
+                    Ok(list) => {
+                        if let Ok(mut data) = pending.lock() {
+                            *data = Some(list.findings);
+                        }
+                    }
+                    Err(e) => tracing::warn!("Invalid attention list: {:?}", e),
+                },
+                // Older daemons have no inbox
+                Ok(_) => {}
+                Err(e) => tracing::error!("Failed to fetch attention inbox: {:?}", e),
+            }
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = (&pending, &daemon_config);
+    }
+}
+
+fn process_attention(pending: Res<PendingAttention>, mut attention: ResMut<Attention>) {
+    if let Some(findings) = pending.0.lock().ok().and_then(|mut data| data.take()) {
+        attention.replace(findings);
+    }
+}
+
+/// Ask the primary daemon to acknowledge a finding; the change comes back
+/// over the WebSocket
+fn send_acknowledge(base_url: &str, id: &str) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let id: String = js_sys::encode_uri_component(id).into();
+        let url = format!("{}/api/attention/{}/ack", base_url, id);
+        wasm_bindgen_futures::spawn_local(async move {
+            match crate::network::DaemonRequest::post(&url).send().await {
+                Ok(response) if response.ok() => {}
+                Ok(response) => tracing::error!("Failed to acknowledge finding: {}", response.status()),
+                Err(e) => tracing::error!("Failed to acknowledge finding: {:?}", e),
+            }
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = (base_url, id);
+        tracing::warn!("Acknowledging findings not available in native mode");
+    }
+}
+
+/// The Attention window
+fn render_attention(
+    mut contexts: EguiContexts,
+    mut attention: ResMut<Attention>,
+    daemon_config: Res<DaemonConfig>,
+    registry: Res<DeviceRegistry>,
+    mut selected: ResMut<SelectedDevice>,
+    mut camera_settings: ResMut<CameraSettings>,
+    device_query: Query<(&DeviceEntity, &GlobalTransform)>,
+) {
+    if !attention.open {
+        return;
+    }
+    let Ok(ctx) = contexts.ctx_mut() else { return };
+
+    let mut open = attention.open;
+    let mut focus = None;
+    egui::Window::new("Attention")
+        .open(&mut open)
+        .default_width(380.0)
+        .show(ctx, |ui| {
+            if attention.findings.is_empty() {
+                ui.label("Nothing needs attention.");
+                return;
+            }
+            egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                for finding in &attention.findings {
+                    ui.horizontal(|ui| {
+                        ui.colored_label(finding.color(), "●");
+                        let text = if finding.occurrences > 1 {
+                            format!("{} (×{})", finding.message, finding.occurrences)
+                        } else {
+                            finding.message.clone()
+                        };
+                        if finding.is_open() {
+                            ui.label(text);
+                        } else {
+                            ui.label(egui::RichText::new(text).color(egui::Color32::GRAY));
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        let device = finding
+                            .device
+                            .as_ref()
+                            .filter(|id| registry.devices.iter().any(|d| &d.id == *id));
+                        if let Some(id) = device {
+                            if ui.small_button("Show device").clicked() {
+                                focus = Some(id.clone());
+                            }
+                        }
+                        if finding.is_open() && ui.small_button("Acknowledge").clicked() {
+                            send_acknowledge(&daemon_config.http_url, &finding.id);
+                        }
+                    });
+                    ui.separator();
+                }
+            });
+        });
+    attention.open = open;
+
+    if let Some(id) = focus {
+        if let Some((_, transform)) = device_query.iter().find(|(device, _)| device.device_id == id) {
+            camera_settings.target_focus = transform.translation();
+        }
+        selected.0 = Some(id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn finding(id: &str, severity: &str, state: &str) -> Finding {
+        Finding {
+            id: id.to_string(),
+            severity: severity.to_string(),
+            device: None,
+            message: id.to_string(),
+            state: state.to_string(),
+            occurrences: 1,
+        }
+    }
+
+    #[test]
+    fn test_streamed_changes_replace_entries() {
+        let mut attention = Attention::default();
+        attention.replace(vec![
+            finding("a", "info", "open"),
+            finding("b", "critical", "acknowledged"),
+            finding("c", "warning", "resolved"),
+        ]);
+        assert_eq!(attention.open_count(), 1);
+
+        attention.upsert(finding("d", "critical", "open"));
+        attention.upsert(finding("d", "critical", "open"));
+        let ids: Vec<_> = attention.findings.iter().map(|f| f.id.as_str()).collect();
+        assert_eq!(ids, vec!["d", "a", "b"]);
+
+        attention.upsert(finding("a", "info", "acknowledged"));
+        attention.upsert(finding("d", "critical", "resolved"));
+        assert_eq!(attention.open_count(), 0);
+        assert_eq!(attention.findings.len(), 2);
+    }
+}
@@ -0,0 +1,322 @@
+//! Multi-daemon sessions
+//!
+//! One page can follow several daemons at once, e.g. one per vehicle. The
+//! first is the primary daemon (`DaemonConfig`): scans, heartbeat and
+//! firmware settings, HCDF import/export and keep-outs go to it. Every
+//! daemon gets its own WebSocket and device polling, and its devices join the
+//! shared registry under namespaced IDs (`d<key>/<id>`; the primary's IDs stay
+//! bare) so device requests can be routed back to the daemon that owns them.
+//!
+//! Each daemon's assembly is shifted into its own region of the scene along
+//! +X so vehicles don't overlap. The offset is added to poses received from
+//! a daemon and subtracted from poses sent to it.
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts, EguiPrimaryContextPass};
+use std::sync::{Arc, Mutex};
+
+use crate::network::{DaemonConfig, PendingMessages, PoseUpdate};
+
+/// Key of the primary daemon
+pub const PRIMARY_DAEMON: u32 = 0;
+
+/// Distance between daemon regions along +X (meters)
+pub const REGION_SPACING: f32 = 1.5;
+
+/// WebSocket state of a daemon
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LinkState {
+    #[default]
+    Connecting,
+    Connected,
+    Disconnected,
+}
+
+/// A daemon followed by this page
+#[derive(Clone)]
+pub struct DaemonLink {
+    /// Stable key, used in namespaced device IDs; never reused
+    pub key: u32,
+    /// Address as entered, e.g. "192.168.1.100:8080"
+    pub address: String,
+    pub config: DaemonConfig,
+    /// Written by the WebSocket callbacks
+    pub state: Arc<Mutex<LinkState>>,
+    /// Messages received from this daemon
+    pub messages: PendingMessages,
+}
+
+impl DaemonLink {
+    fn new(key: u32, address: &str, config: DaemonConfig, messages: PendingMessages) -> Self {
+        Self {
+            key,
+            address: address.to_string(),
+            config,
+            state: Arc::default(),
+            messages,
+        }
+    }
+
+    pub fn state(&self) -> LinkState {
+        self.state.lock().map(|s| *s).unwrap_or_default()
+    }
+
+    pub fn set_state(&self, state: LinkState) {
+        if let Ok(mut s) = self.state.lock() {
+            *s = state;
+        }
+    }
+}
+
+/// Daemons followed by this page, primary first
+#[derive(Resource)]
+pub struct Daemons {
+    pub links: Vec<DaemonLink>,
+    next_key: u32,
+}
+
+impl Daemons {
+    /// Start with the primary daemon, which shares the main message queue
+    pub fn new(primary: &DaemonConfig, messages: PendingMessages) -> Self {
+        Self {
+            links: vec![DaemonLink::new(PRIMARY_DAEMON, &display_address(&primary.http_url), primary.clone(), messages)],
+            next_key: PRIMARY_DAEMON + 1,
+        }
+    }
+
+    /// Start with the primary daemon plus any further `?daemon=` addresses
+    #[cfg(target_arch = "wasm32")]
+    pub fn from_browser(primary: &DaemonConfig, messages: PendingMessages) -> Self {
+        let mut daemons = Self::new(primary, messages);
+        if let Some(search) = web_sys::window().and_then(|w| w.location().search().ok()) {
+            // The first address is the primary, already in `DaemonConfig`
+            for address in addresses_from_query(&search).iter().skip(1) {
+                if let Err(e) = daemons.add(address) {
+                    tracing::warn!("Ignoring daemon {}: {}", address, e);
+                }
+            }
+        }
+        daemons
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_browser(primary: &DaemonConfig, messages: PendingMessages) -> Self {
+        Self::new(primary, messages)
+    }
+
+    /// Point the primary entry at a new daemon
+    pub fn set_primary(&mut self, address: &str, config: DaemonConfig) {
+        let primary = &mut self.links[0];
+        primary.address = address.to_string();
+        primary.config = config;
+        primary.set_state(LinkState::Connecting);
+    }
+
+    /// Follow another daemon; fails if the address is already followed
+    pub fn add(&mut self, address: &str) -> Result<u32, String> {
+        let address = address.trim();
+        if address.is_empty() {
+            return Err("Please enter a daemon address".to_string());
+        }
+        let config = DaemonConfig::from_daemon_address(address);
+        if self.links.iter().any(|l| l.config.http_url == config.http_url) {
+            return Err(format!("Already connected to {}", address));
+        }
+        let key = self.next_key;
+        self.next_key += 1;
+        self.links.push(DaemonLink::new(key, address, config, PendingMessages::default()));
+        Ok(key)
+    }
+
+    /// Stop following a daemon; the primary can only be replaced
+    pub fn remove(&mut self, key: u32) -> Option<DaemonLink> {
+        if key == PRIMARY_DAEMON {
+            return None;
+        }
+        let index = self.links.iter().position(|l| l.key == key)?;
+        Some(self.links.remove(index))
+    }
+
+    pub fn get(&self, key: u32) -> Option<&DaemonLink> {
+        self.links.iter().find(|l| l.key == key)
+    }
+
+    /// Whether more than one daemon is followed
+    pub fn is_multi(&self) -> bool {
+        self.links.len() > 1
+    }
+
+    /// Base URL of the daemon owning a device, and the device's own ID there
+    pub fn route<'a>(&self, device_id: &'a str) -> Option<(&str, &'a str)> {
+        let (key, raw) = split_id(device_id);
+        self.get(key).map(|link| (link.config.http_url.as_str(), raw))
+    }
+
+    /// Origin of a daemon's region in the scene
+    pub fn region_offset(&self, key: u32) -> Vec3 {
+        Vec3::X * REGION_SPACING * key as f32
+    }
+
+    /// Group pose updates by owning daemon, in that daemon's IDs and frame
+    pub fn route_poses(&self, updates: Vec<PoseUpdate>) -> Vec<(String, Vec<PoseUpdate>)> {
+        let mut batches: Vec<(String, Vec<PoseUpdate>)> = Vec::new();
+        for mut update in updates {
+            let Some((base_url, raw)) = self.route(&update.id) else {
+                continue;
+            };
+            let offset = self.region_offset(split_id(&update.id).0);
+            for (value, shift) in update.position.iter_mut().zip([offset.x, offset.y, offset.z]) {
+                *value -= shift as f64;
+            }
+            let base_url = base_url.to_string();
+            update.id = raw.to_string();
+            match batches.iter_mut().find(|(url, _)| *url == base_url) {
+                Some((_, batch)) => batch.push(update),
+                None => batches.push((base_url, vec![update])),
+            }
+        }
+        batches
+    }
+}
+
+/// Device ID as stored in the registry
+pub fn namespaced_id(key: u32, id: &str) -> String {
+    if key == PRIMARY_DAEMON {
+        id.to_string()
+    } else {
+        format!("d{}/{}", key, id)
+    }
+}
+
+/// Daemon key and daemon-side ID of a registry device ID
+pub fn split_id(id: &str) -> (u32, &str) {
+    id.strip_prefix('d')
+        .and_then(|rest| rest.split_once('/'))
+        .and_then(|(key, raw)| Some((key.parse().ok()?, raw)))
+        .filter(|(key, _)| *key != PRIMARY_DAEMON)
+        .unwrap_or((PRIMARY_DAEMON, id))
+}
+
+/// Address without the scheme, as shown in the UI
+pub fn display_address(http_url: &str) -> String {
+    http_url
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .to_string()
+}
+
+/// Daemon addresses from `?daemon=` parameters, in order
+pub fn addresses_from_query(search: &str) -> Vec<String> {
+    search
+        .trim_start_matches('?')
+        .split('&')
+        .filter_map(|pair| pair.strip_prefix("daemon="))
+        .filter(|value| !value.is_empty())
+        .map(|value| value.replace("%3A", ":").replace("%2F", "/"))
+        .collect()
+}
+
+pub struct DaemonsPlugin;
+
+impl Plugin for DaemonsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(EguiPrimaryContextPass, render_region_labels);
+    }
+}
+
+/// Name each daemon's region in the scene while several are followed
+fn render_region_labels(
+    mut contexts: EguiContexts,
+    daemons: Res<Daemons>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+) {
+    if !daemons.is_multi() {
+        return;
+    }
+    let Ok((camera, camera_transform)) = camera_query.single() else {
+        return;
+    };
+    let Ok(ctx) = contexts.ctx_mut() else { return };
+
+    let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Background, egui::Id::new("daemon_region_labels")));
+    for link in &daemons.links {
+        // Just south of the region origin, clear of the assembly itself
+        let position = daemons.region_offset(link.key) - Vec3::Y * (REGION_SPACING / 3.0);
+        let Ok(screen) = camera.world_to_viewport(camera_transform, position) else {
+            continue;
+        };
+        let color = match link.state() {
+            LinkState::Connected => egui::Color32::from_rgb(150, 200, 255),
+            LinkState::Connecting => egui::Color32::GRAY,
+            LinkState::Disconnected => egui::Color32::from_rgb(220, 100, 100),
+        };
+        let galley = painter.layout_no_wrap(link.address.clone(), egui::FontId::proportional(14.0), color);
+        let rect = egui::Align2::CENTER_CENTER.anchor_size(egui::pos2(screen.x, screen.y), galley.size());
+        painter.rect_filled(rect.expand(3.0), 3.0, egui::Color32::from_black_alpha(160));
+        painter.galley(rect.min, galley, color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fleet() -> Daemons {
+        let mut daemons = Daemons::new(&DaemonConfig::from_daemon_address("10.0.0.1:8080"), PendingMessages::default());
+        assert_eq!(daemons.add("10.0.0.2:8080"), Ok(1));
+        assert_eq!(daemons.add("10.0.0.3:8080"), Ok(2));
+        daemons
+    }
+
+    #[test]
+    fn test_ids_round_trip() {
+        assert_eq!(namespaced_id(PRIMARY_DAEMON, "0x1a2b"), "0x1a2b");
+        assert_eq!(split_id("0x1a2b"), (PRIMARY_DAEMON, "0x1a2b"));
+        assert_eq!(namespaced_id(3, "0x1a2b"), "d3/0x1a2b");
+        assert_eq!(split_id("d3/0x1a2b"), (3, "0x1a2b"));
+        // Primary IDs that happen to look namespaced stay whole
+        assert_eq!(split_id("d0/x"), (PRIMARY_DAEMON, "d0/x"));
+        assert_eq!(split_id("dx/y"), (PRIMARY_DAEMON, "dx/y"));
+    }
+
+    #[test]
+    fn test_requests_route_to_owning_daemon() {
+        let mut daemons = fleet();
+        assert_eq!(daemons.route("abc"), Some(("http://10.0.0.1:8080", "abc")));
+        assert_eq!(daemons.route("d2/abc"), Some(("http://10.0.0.3:8080", "abc")));
+
+        assert!(daemons.add("10.0.0.2:8080").is_err());
+        assert!(daemons.remove(PRIMARY_DAEMON).is_none());
+        assert!(daemons.remove(1).is_some());
+        assert_eq!(daemons.route("d1/abc"), None);
+        // Keys are not reused, so stale IDs can't reach a different daemon
+        assert_eq!(daemons.add("10.0.0.4:8080"), Ok(3));
+    }
+
+    #[test]
+    fn test_poses_are_sent_in_daemon_frame() {
+        let daemons = fleet();
+        let update = |id: &str, x: f64| PoseUpdate { id: id.to_string(), position: [x, 0.5, 0.0], orientation: None, seq: 0, client: String::new() };
+        let batches = daemons.route_poses(vec![update("a", 0.1), update("d2/b", 3.2), update("d1/c", 1.6), update("d9/x", 0.0)]);
+
+        assert_eq!(batches.len(), 3);
+        assert_eq!(batches[0].0, "http://10.0.0.1:8080");
+        assert_eq!(batches[0].1[0].id, "a");
+        assert!((batches[0].1[0].position[0] - 0.1).abs() < 1e-6);
+        let (url, batch) = &batches[1];
+        assert_eq!(url, "http://10.0.0.3:8080");
+        assert_eq!(batch[0].id, "b");
+        assert!((batch[0].position[0] - 0.2).abs() < 1e-6);
+        assert_eq!(batch[0].position[1], 0.5);
+        assert_eq!(batches[2].1[0].id, "c");
+    }
+
+    #[test]
+    fn test_addresses_from_query() {
+        assert_eq!(
+            addresses_from_query("?spectator=1&daemon=10.0.0.1%3A8080&daemon=10.0.0.2:8080"),
+            ["10.0.0.1:8080", "10.0.0.2:8080"]
+        );
+        assert!(addresses_from_query("?daemon=").is_empty());
+    }
+}
@@ -0,0 +1,184 @@
+//! Device list search and quick filters
+//!
+//! The search text matches name, board, ID and IP as a case-insensitive
+//! substring. Status chips narrow the list to the statuses switched on (none
+//! on shows every status), and "Update available" to devices with newer
+//! firmware while firmware checking is enabled. The selected device always
+//! stays in the list, so picking one in the scene never hides its row.
+
+use bevy::prelude::*;
+use bevy_egui::egui;
+
+use crate::app::{DeviceData, DeviceStatus, FirmwareCheckState, FirmwareStatusData};
+
+/// Filter state of the device list; kept while panels open and close
+#[derive(Debug, Clone, Resource, Default)]
+pub struct DeviceListFilter {
+    pub query: String,
+    pub online: bool,
+    pub offline: bool,
+    pub unknown: bool,
+    /// Only devices with a firmware update available
+    pub update_available: bool,
+}
+
+impl DeviceListFilter {
+    /// Whether anything narrows the list
+    pub fn is_active(&self) -> bool {
+        !self.query.trim().is_empty() || self.online || self.offline || self.unknown || self.update_available
+    }
+
+    /// Whether a device passes the filter; the update chip only counts while firmware checking is on
+    pub fn matches(&self, device: &DeviceData, firmware_state: &FirmwareCheckState) -> bool {
+        let query = self.query.trim().to_lowercase();
+        if !query.is_empty() {
+            let fields = [Some(&device.name), device.board.as_ref(), Some(&device.id), Some(&device.ip)];
+            if !fields.into_iter().flatten().any(|field| field.to_lowercase().contains(&query)) {
+                return false;
+            }
+        }
+
+        if self.online || self.offline || self.unknown {
+            let wanted = match device.status {
+                DeviceStatus::Online => self.online,
+                DeviceStatus::Offline => self.offline,
+                DeviceStatus::Unknown => self.unknown,
+            };
+            if !wanted {
+                return false;
+            }
+        }
+
+        if self.update_available && firmware_state.enabled {
+            let outdated = matches!(
+                firmware_state.device_status.get(&device.id),
+                Some(FirmwareStatusData::UpdateAvailable { .. })
+            );
+            if !outdated {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Whether a device's row is shown: it passes the filter or is selected
+    pub fn shows(&self, device: &DeviceData, firmware_state: &FirmwareCheckState, selected: Option<&str>) -> bool {
+        selected == Some(device.id.as_str()) || self.matches(device, firmware_state)
+    }
+
+    /// Device count for the bottom of the list: "5 of 32 devices" while filtering
+    pub fn count_label(&self, shown: usize, total: usize) -> String {
+        if self.is_active() {
+            format!("{} of {} devices", shown, total)
+        } else {
+            format!("{} devices", total)
+        }
+    }
+}
+
+/// Search box and quick-filter chips
+pub fn filter_bar(ui: &mut egui::Ui, filter: &mut DeviceListFilter, firmware_enabled: bool, ui_scale: f32) {
+    ui.horizontal(|ui| {
+        let clear_width = if filter.is_active() { 24.0 * ui_scale } else { 0.0 };
+        ui.add(
+            egui::TextEdit::singleline(&mut filter.query)
+                .hint_text("Search name, board, ID or IP")
+                .desired_width(ui.available_width() - clear_width),
+        );
+        if filter.is_active() && ui.small_button("✕").on_hover_text("Clear filters").clicked() {
+            *filter = DeviceListFilter::default();
+        }
+    });
+    ui.horizontal_wrapped(|ui| {
+        let chip = |ui: &mut egui::Ui, on: &mut bool, label: &str| {
+            if ui.selectable_label(*on, egui::RichText::new(label).size(12.0 * ui_scale)).clicked() {
+                *on = !*on;
+            }
+        };
+        chip(ui, &mut filter.online, "Online");
+        chip(ui, &mut filter.offline, "Offline");
+        chip(ui, &mut filter.unknown, "Unknown");
+        if firmware_enabled {
+            chip(ui, &mut filter.update_available, "Update available");
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(id: &str, name: &str, board: &str, ip: &str, status: DeviceStatus) -> DeviceData {
+        DeviceData {
+            id: id.to_string(),
+            name: name.to_string(),
+            board: Some(board.to_string()),
+            ip: ip.to_string(),
+            port: None,
+            status,
+            version: None,
+            position: None,
+            orientation: None,
+            pose_provisional: false,
+            keep_above_ground: false,
+            stack_on: None,
+            model_path: None,
+            visuals: Vec::new(),
+            frames: Vec::new(),
+            ports: Vec::new(),
+            sensors: Vec::new(),
+            last_seen: None,
+            heartbeat_method: None,
+            can: None,
+        }
+    }
+
+    #[test]
+    fn test_search_matches_any_field_ignoring_case() {
+        let spinali = device("0x1a2b", "Spinali Front", "mr_mcxn_t1", "192.168.186.10", DeviceStatus::Online);
+        let firmware = FirmwareCheckState::default();
+        let search = |query: &str| DeviceListFilter { query: query.to_string(), ..Default::default() };
+
+        for query in ["front", "MCXN", "0X1A", "186.10", "  spinali "] {
+            assert!(search(query).matches(&spinali, &firmware), "{query}");
+        }
+        assert!(!search("optical").matches(&spinali, &firmware));
+        assert!(!DeviceListFilter::default().is_active());
+        assert!(!search("  ").is_active());
+    }
+
+    #[test]
+    fn test_status_and_update_chips() {
+        let online = device("a", "a", "b", "10.0.0.1", DeviceStatus::Online);
+        let offline = device("b", "b", "b", "10.0.0.2", DeviceStatus::Offline);
+        let mut firmware = FirmwareCheckState::default();
+        firmware.device_status.insert(
+            "b".to_string(),
+            FirmwareStatusData::UpdateAvailable { latest_version: "1.2.0".to_string(), changelog: None },
+        );
+
+        let offline_only = DeviceListFilter { offline: true, ..Default::default() };
+        assert!(!offline_only.matches(&online, &firmware));
+        assert!(offline_only.matches(&offline, &firmware));
+        let either = DeviceListFilter { online: true, offline: true, ..Default::default() };
+        assert!(either.matches(&online, &firmware) && either.matches(&offline, &firmware));
+
+        // The update chip waits for firmware checking to be enabled
+        let outdated = DeviceListFilter { update_available: true, ..Default::default() };
+        assert!(outdated.matches(&online, &firmware));
+        firmware.enabled = true;
+        assert!(!outdated.matches(&online, &firmware));
+        assert!(outdated.matches(&offline, &firmware));
+    }
+
+    #[test]
+    fn test_selected_device_is_always_shown() {
+        let device = device("0x01", "imu", "b", "10.0.0.1", DeviceStatus::Online);
+        let firmware = FirmwareCheckState::default();
+        let filter = DeviceListFilter { query: "camera".to_string(), ..Default::default() };
+        assert!(!filter.shows(&device, &firmware, None));
+        assert!(filter.shows(&device, &firmware, Some("0x01")));
+        assert_eq!(filter.count_label(5, 32), "5 of 32 devices");
+        assert_eq!(DeviceListFilter::default().count_label(32, 32), "32 devices");
+    }
+}
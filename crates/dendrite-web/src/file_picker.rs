@@ -2,6 +2,7 @@
 //!
 //! Provides a modal-based file picker that can be used for:
 //! - Firmware upload (pick .bin files)
+//! - Model overrides (pick .glb files)
 //! - HCDF import/export (pick/save .hcdf files)
 //! - Any future file operations
 //!
@@ -36,10 +37,14 @@ pub enum FileOperation {
 pub enum FilePickerContext {
     /// Uploading firmware to a specific device
     FirmwareUpload { device_id: String },
+    /// Uploading a model to use instead of a device's fragment model
+    ModelOverride { device_id: String, for_board: bool },
     /// Importing an HCDF file
     HcdfImport,
     /// Exporting/saving the current HCDF
     HcdfExport,
+    /// Exporting the current HCDF as URDF
+    UrdfExport,
     /// Custom context with a string identifier
     Custom(String),
 }
@@ -68,6 +73,13 @@ impl FileFilter {
         }
     }
 
+    pub fn model() -> Self {
+        Self {
+            name: "glTF Binary Models".to_string(),
+            extensions: vec!["glb".to_string()],
+        }
+    }
+
     pub fn all() -> Self {
         Self {
             name: "All Files".to_string(),
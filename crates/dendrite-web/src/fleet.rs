@@ -0,0 +1,188 @@
+//! Fleet inventory
+//!
+//! The Fleet window shows the primary daemon's `GET /api/fleet/inventory`:
+//! how many of each board, sensor driver, port type and firmware version are
+//! deployed, each split by firmware version. It is fetched when the window
+//! opens or the archived toggle changes, and "Refresh" fetches it again.
+//! "Update firmware" opens the batch update (see `ota_batch`).
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts, EguiPrimaryContextPass};
+use dendrite_core::{FleetInventory, InventoryRow};
+use std::sync::{Arc, Mutex};
+
+use crate::network::DaemonConfig;
+use crate::ota_batch::OtaBatch;
+
+#[derive(Resource, Default)]
+pub struct Fleet {
+    pub open: bool,
+    /// Count archived devices too
+    include_archived: bool,
+    inventory: Option<FleetInventory>,
+    loading: bool,
+    error: Option<String>,
+}
+
+#[derive(Resource, Default)]
+struct PendingFleet(Arc<Mutex<Option<Result<FleetInventory, String>>>>);
+
+pub struct FleetPlugin;
+
+impl Plugin for FleetPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Fleet>()
+            .init_resource::<PendingFleet>()
+            .add_systems(Update, (fetch_on_open, process_fleet))
+            .add_systems(EguiPrimaryContextPass, render_fleet);
+    }
+}
+
+/// URL of the inventory on the primary daemon
+fn inventory_url(base_url: &str, include_archived: bool, csv: bool) -> String {
+    let mut url = format!("{}/api/fleet/inventory?include_archived={}", base_url, include_archived);
+    if csv {
+        url.push_str("&format=csv");
+    }
+    url
+}
+
+fn fetch_inventory(base_url: &str, include_archived: bool, pending: &PendingFleet) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let pending = pending.0.clone();
+        let url = inventory_url(base_url, include_archived, false);
+        wasm_bindgen_futures::spawn_local(async move {
+            let result = match crate::network::DaemonRequest::get(&url).send().await {
+                Ok(response) if response.ok() => response
+                    .json::<FleetInventory>()
+                    .await
+                    .map_err(|e| format!("Invalid inventory: {:?}", e)),
+                // Older daemons have no inventory
+                Ok(response) => Err(format!("Inventory unavailable ({})", response.status())),
+                Err(e) => Err(format!("{:?}", e)),
+            };
+            if let Ok(mut data) = pending.lock() {
+                *data = Some(result);
+            }
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = (base_url, include_archived, pending);
+    }
+}
+
+/// Fetch each time the window opens
+fn fetch_on_open(
+    mut fleet: ResMut<Fleet>,
+    pending: Res<PendingFleet>,
+    daemon_config: Res<DaemonConfig>,
+    mut was_open: Local<bool>,
+) {
+    if fleet.open && !*was_open {
+        fleet.loading = true;
+        fetch_inventory(&daemon_config.http_url, fleet.include_archived, &pending);
+    }
+    *was_open = fleet.open;
+}
+
+fn process_fleet(pending: Res<PendingFleet>, mut fleet: ResMut<Fleet>) {
+    let Some(result) = pending.0.lock().ok().and_then(|mut data| data.take()) else {
+        return;
+    };
+    fleet.loading = false;
+    match result {
+        Ok(inventory) => {
+            fleet.inventory = Some(inventory);
+            fleet.error = None;
+        }
+        Err(e) => fleet.error = Some(e),
+    }
+}
+
+/// One section of the inventory, with the firmware split on hover
+fn inventory_section(ui: &mut egui::Ui, title: &str, rows: &[InventoryRow]) {
+    egui::CollapsingHeader::new(format!("{} ({})", title, rows.len()))
+        .default_open(true)
+        .show(ui, |ui| {
+            if rows.is_empty() {
+                ui.label(egui::RichText::new("None").color(egui::Color32::GRAY));
+                return;
+            }
+            egui::Grid::new(title).striped(true).num_columns(3).show(ui, |ui| {
+                ui.strong("");
+                ui.strong("Count");
+                ui.strong("Devices");
+                ui.end_row();
+                for row in rows {
+                    let firmware = row
+                        .by_firmware
+                        .iter()
+                        .map(|(version, count)| format!("{}: {}", version, count))
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    ui.label(&row.key).on_hover_text(firmware);
+                    ui.label(row.count.to_string());
+                    ui.label(row.devices.to_string());
+                    ui.end_row();
+                }
+            });
+        });
+}
+
+/// The Fleet window
+fn render_fleet(
+    mut contexts: EguiContexts,
+    mut fleet: ResMut<Fleet>,
+    pending: Res<PendingFleet>,
+    daemon_config: Res<DaemonConfig>,
+    mut ota_batch: ResMut<OtaBatch>,
+) {
+    if !fleet.open {
+        return;
+    }
+    let Ok(ctx) = contexts.ctx_mut() else { return };
+
+    let mut open = fleet.open;
+    let mut refresh = false;
+    egui::Window::new("Fleet")
+        .open(&mut open)
+        .default_width(360.0)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                refresh |= ui.checkbox(&mut fleet.include_archived, "Include archived").changed();
+                refresh |= ui.add_enabled(!fleet.loading, egui::Button::new("Refresh")).clicked();
+                ui.hyperlink_to(
+                    "CSV",
+                    inventory_url(&daemon_config.http_url, fleet.include_archived, true),
+                );
+                if ui.button("Update firmware").clicked() {
+                    ota_batch.open = true;
+                }
+            });
+            if fleet.loading {
+                ui.spinner();
+            }
+            if let Some(error) = &fleet.error {
+                ui.colored_label(egui::Color32::from_rgb(255, 100, 100), error);
+            }
+            let Some(inventory) = &fleet.inventory else {
+                return;
+            };
+            ui.label(format!("{} device(s)", inventory.devices));
+            egui::ScrollArea::vertical().max_height(420.0).show(ui, |ui| {
+                inventory_section(ui, "Boards", &inventory.boards);
+                inventory_section(ui, "Sensor drivers", &inventory.sensor_drivers);
+                inventory_section(ui, "Ports", &inventory.ports);
+                inventory_section(ui, "Firmware", &inventory.firmware);
+            });
+        });
+    fleet.open = open;
+
+    if refresh {
+        fleet.loading = true;
+        fetch_inventory(&daemon_config.http_url, fleet.include_archived, &pending);
+    }
+}
@@ -0,0 +1,220 @@
+//! Heatmap coloring by a device metric
+//!
+//! "Color by" in World Settings picks one of the metrics the primary daemon
+//! lists at `GET /api/metrics` (latency, uptime, firmware age...). While one
+//! is picked the metrics are polled and devices are tinted along a color
+//! ramp by `dendrite_scene::heatmap`, with a legend over the scene; devices
+//! without a value are gray. Picking "None" restores their materials.
+
+use bevy::prelude::*;
+use dendrite_core::{DeviceMetrics, MetricInfo};
+use dendrite_scene::heatmap::{Heatmap, HeatmapMetric, HeatmapPlugin, HeatmapTargets, MetricRange};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::models::VisualEntity;
+use crate::network::{DaemonConfig, LowBandwidthMode};
+use crate::scene::DeviceEntity;
+
+/// How often metrics are refreshed while a metric is picked
+const METRICS_POLL_SECS: f32 = 5.0;
+
+/// Polling interval in low-bandwidth mode
+const LOW_BANDWIDTH_METRICS_POLL_SECS: f32 = 30.0;
+
+/// Metric picked in World Settings and the latest report
+#[derive(Resource, Default)]
+pub struct DeviceHeatmap {
+    /// Key of the metric devices are colored by (None = off)
+    pub metric: Option<String>,
+    pub report: Option<DeviceMetrics>,
+}
+
+impl DeviceHeatmap {
+    /// Metrics the daemon offers (empty until the first report, or for
+    /// daemons without `/api/metrics`)
+    pub fn available(&self) -> &[MetricInfo] {
+        self.report.as_ref().map(|r| r.metrics.as_slice()).unwrap_or_default()
+    }
+
+    /// The picked metric, if the daemon offers it
+    pub fn selected(&self) -> Option<&MetricInfo> {
+        let key = self.metric.as_deref()?;
+        self.available().iter().find(|m| m.key == key)
+    }
+}
+
+#[derive(Resource, Default)]
+struct PendingMetrics(Arc<Mutex<Option<DeviceMetrics>>>);
+
+#[derive(Resource)]
+struct MetricsPollTimer(Timer);
+
+impl Default for MetricsPollTimer {
+    fn default() -> Self {
+        Self(Timer::from_seconds(METRICS_POLL_SECS, TimerMode::Repeating))
+    }
+}
+
+pub struct DeviceHeatmapPlugin;
+
+impl Plugin for DeviceHeatmapPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(HeatmapPlugin)
+            .init_resource::<DeviceHeatmap>()
+            .init_resource::<PendingMetrics>()
+            .init_resource::<MetricsPollTimer>()
+            .add_systems(Startup, fetch_initial_metrics)
+            .add_systems(Update, (poll_metrics, process_metrics, update_heatmap, update_heatmap_targets));
+    }
+}
+
+/// Scene heatmap for a metric in a report
+fn heatmap_metric(info: &MetricInfo, report: &DeviceMetrics) -> HeatmapMetric {
+    HeatmapMetric {
+        label: info.label.clone(),
+        unit: info.unit.clone(),
+        range: match info.range {
+            Some([min, max]) => MetricRange::Fixed { min, max },
+            None => MetricRange::Observed,
+        },
+        values: report
+            .values
+            .get(&info.key)
+            .map(|values| values.iter().map(|(id, v)| (id.clone(), *v)).collect())
+            .unwrap_or_default(),
+        solid: None,
+    }
+}
+
+fn fetch_metrics(base_url: &str, pending: &PendingMetrics) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let pending = pending.0.clone();
+        let url = format!("{}/api/metrics", base_url);
+        wasm_bindgen_futures::spawn_local(async move {
+            match crate::network::DaemonRequest::get(&url).send().await {
+                Ok(response) if response.ok() => match response.json::<DeviceMetrics>().await {
+                    Ok(report) => {
+                        if let Ok(mut data) = pending.lock() {
+                            *data = Some(report);
+                        }
+                    }
+                    Err(e) => tracing::warn!("Invalid metrics: {:?}", e),
+                },
+                // Older daemons have no metrics; "Color by" stays empty
+                Ok(response) => tracing::debug!("Metrics unavailable ({})", response.status()),
+                Err(e) => tracing::warn!("Failed to fetch metrics: {:?}", e),
+            }
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = (base_url, pending);
+    }
+}
+
+/// Fetch once at startup so the dropdown can list the metrics
+fn fetch_initial_metrics(pending: Res<PendingMetrics>, daemon_config: Res<DaemonConfig>) {
+    fetch_metrics(&daemon_config.http_url, &pending);
+}
+
+/// Refresh while a metric is picked, and right away when one is picked
+fn poll_metrics(
+    time: Res<Time>,
+    heatmap: Res<DeviceHeatmap>,
+    mut timer: ResMut<MetricsPollTimer>,
+    low_bandwidth: Res<LowBandwidthMode>,
+    pending: Res<PendingMetrics>,
+    daemon_config: Res<DaemonConfig>,
+    mut picked: Local<Option<String>>,
+) {
+    if low_bandwidth.is_changed() {
+        let secs = if low_bandwidth.enabled { LOW_BANDWIDTH_METRICS_POLL_SECS } else { METRICS_POLL_SECS };
+        timer.0.set_duration(Duration::from_secs_f32(secs));
+    }
+    if heatmap.metric.is_none() {
+        *picked = None;
+        return;
+    }
+
+    timer.0.tick(time.delta());
+    if *picked != heatmap.metric || timer.0.just_finished() {
+        *picked = heatmap.metric.clone();
+        timer.0.reset();
+        fetch_metrics(&daemon_config.http_url, &pending);
+    }
+}
+
+fn process_metrics(pending: Res<PendingMetrics>, mut heatmap: ResMut<DeviceHeatmap>) {
+    if let Some(report) = pending.0.lock().ok().and_then(|mut data| data.take()) {
+        heatmap.report = Some(report);
+    }
+}
+
+/// Hand the picked metric's values to the scene
+fn update_heatmap(heatmap: Res<DeviceHeatmap>, mut scene_heatmap: ResMut<Heatmap>) {
+    if !heatmap.is_changed() {
+        return;
+    }
+    let metric = heatmap
+        .selected()
+        .zip(heatmap.report.as_ref())
+        .map(|(info, report)| heatmap_metric(info, report));
+    scene_heatmap.set_if_neq(Heatmap(metric));
+}
+
+/// Tint each device's visuals, or the device entity itself when it is a
+/// single model or a fallback cube
+fn update_heatmap_targets(
+    device_query: Query<(Entity, &DeviceEntity)>,
+    visual_query: Query<(Entity, &VisualEntity)>,
+    mut targets: ResMut<HeatmapTargets>,
+) {
+    let mut entities: HashMap<String, Vec<Entity>> = HashMap::new();
+    for (entity, visual) in visual_query.iter() {
+        entities.entry(visual.device_id.clone()).or_default().push(entity);
+    }
+    for (entity, device) in device_query.iter() {
+        entities.entry(device.device_id.clone()).or_insert_with(|| vec![entity]);
+    }
+    // Query order isn't stable; keep it from looking like a change
+    for roots in entities.values_mut() {
+        roots.sort();
+    }
+    targets.set_if_neq(HeatmapTargets(entities));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heatmap_metric_from_report() {
+        let report: DeviceMetrics = serde_json::from_str(
+            r#"{
+                "metrics": [
+                    {"key": "latency_ms", "label": "Latency", "unit": "ms"},
+                    {"key": "uptime_percent", "label": "Uptime", "unit": "%", "range": [0.0, 100.0]}
+                ],
+                "values": {"uptime_percent": {"0x01": 95.0}}
+            }"#,
+        )
+        .unwrap();
+        let heatmap = DeviceHeatmap { metric: Some("uptime_percent".to_string()), report: Some(report) };
+
+        let info = heatmap.selected().unwrap();
+        let metric = heatmap_metric(info, heatmap.report.as_ref().unwrap());
+        assert_eq!(metric.range, MetricRange::Fixed { min: 0.0, max: 100.0 });
+        assert_eq!(metric.values["0x01"], 95.0);
+
+        // A metric with no values yet, and one the daemon doesn't offer
+        let latency = heatmap_metric(&heatmap.available()[0], heatmap.report.as_ref().unwrap());
+        assert_eq!(latency.range, MetricRange::Observed);
+        assert!(latency.values.is_empty());
+        let unknown = DeviceHeatmap { metric: Some("temperature".to_string()), ..heatmap };
+        assert!(unknown.selected().is_none());
+    }
+}
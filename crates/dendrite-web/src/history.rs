@@ -0,0 +1,327 @@
+//! History view
+//!
+//! When the daemon keeps a registry journal, the History panel offers a time
+//! slider over the span it covers. Loading a time replaces the primary
+//! daemon's devices with the registry as it was then
+//! (`GET /api/devices?as_of=`) and makes the session read-only until "Back to
+//! live" refetches the current devices.
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts, EguiPrimaryContextPass};
+use serde::Deserialize;
+use std::sync::{Arc, Mutex};
+
+use crate::app::{DeviceData, DeviceRegistry, SelectedDevice};
+use crate::daemons::{split_id, PRIMARY_DAEMON};
+use crate::network::{reload_devices, DaemonConfig, LowBandwidthMode, PendingMessages, ReadOnlyMode};
+use dendrite_api_types::DeviceJson;
+
+/// `GET /api/journal` response
+#[derive(Debug, Clone, Deserialize)]
+struct JournalStatus {
+    enabled: bool,
+    #[serde(default)]
+    start: Option<String>,
+}
+
+#[derive(Resource, Default)]
+pub struct History {
+    pub open: bool,
+    /// Whether the daemon keeps a journal, once known
+    enabled: Option<bool>,
+    /// Earliest time the journal can reconstruct (Unix seconds)
+    start: Option<f64>,
+    /// Slider position (Unix seconds)
+    selected: f64,
+    /// Time whose registry is on show, None when live
+    pub viewing: Option<f64>,
+    loading: bool,
+    error: Option<String>,
+}
+
+impl History {
+    /// Record the journal's span, keeping the slider inside it
+    fn set_status(&mut self, enabled: bool, start: Option<f64>, now: f64) {
+        self.enabled = Some(enabled);
+        self.start = start;
+        let start = start.unwrap_or(now);
+        self.selected = if self.selected == 0.0 { now } else { self.selected.clamp(start, now) };
+    }
+}
+
+enum HistoryReply {
+    Status(JournalStatus),
+    Devices { at: f64, devices: Vec<DeviceJson> },
+    Failed(String),
+}
+
+#[derive(Resource, Default)]
+struct PendingHistory(Arc<Mutex<Vec<HistoryReply>>>);
+
+pub struct HistoryPlugin;
+
+impl Plugin for HistoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<History>()
+            .init_resource::<PendingHistory>()
+            .add_systems(Update, (fetch_journal_status, process_history_replies))
+            .add_systems(EguiPrimaryContextPass, render_history);
+    }
+}
+
+/// Ask for the journal's span each time the panel opens
+fn fetch_journal_status(
+    history: Res<History>,
+    pending: Res<PendingHistory>,
+    daemon_config: Res<DaemonConfig>,
+    mut was_open: Local<bool>,
+) {
+    if history.open && !*was_open {
+        #[cfg(target_arch = "wasm32")]
+        {
+            let pending = pending.0.clone();
+            let url = format!("{}/api/journal", daemon_config.http_url);
+            wasm_bindgen_futures::spawn_local(async move {
+                let reply = match crate::network::DaemonRequest::get(&url).send().await {
+                    Ok(response) if response.ok() => match response.json::<JournalStatus>().await {
+                        Ok(status) => HistoryReply::Status(status),
+                        Err(e) => HistoryReply::Failed(format!("Invalid journal status: {:?}", e)),
+                    },
+                    // Older daemons have no journal
+                    Ok(_) => HistoryReply::Status(JournalStatus { enabled: false, start: None }),
+                    Err(e) => HistoryReply::Failed(format!("{:?}", e)),
+                };
+                if let Ok(mut replies) = pending.lock() {
+                    replies.push(reply);
+                }
+            });
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let _ = (&pending, &daemon_config);
+        }
+    }
+    *was_open = history.open;
+}
+
+fn load_past_state(base_url: &str, at: f64, pending: &PendingHistory) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let pending = pending.0.clone();
+        let url = format!("{}/api/devices?as_of={}", base_url, to_iso(at));
+        wasm_bindgen_futures::spawn_local(async move {
+            let reply = match crate::network::DaemonRequest::get(&url).send().await {
+                Ok(response) if response.ok() => match response.json::<Vec<DeviceJson>>().await {
+                    Ok(devices) => HistoryReply::Devices { at, devices },
+                    Err(e) => HistoryReply::Failed(format!("Invalid device list: {:?}", e)),
+                },
+                Ok(response) => {
+                    let json: serde_json::Value = response.json().await.unwrap_or_default();
+                    HistoryReply::Failed(json["error"].as_str().unwrap_or("No state for that time").to_string())
+                }
+                Err(e) => HistoryReply::Failed(format!("{:?}", e)),
+            };
+            if let Ok(mut replies) = pending.lock() {
+                replies.push(reply);
+            }
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = (base_url, at, pending);
+    }
+}
+
+fn process_history_replies(
+    pending: Res<PendingHistory>,
+    mut history: ResMut<History>,
+    mut registry: ResMut<DeviceRegistry>,
+    mut read_only: ResMut<ReadOnlyMode>,
+    mut selected: ResMut<SelectedDevice>,
+) {
+    let replies: Vec<_> = match pending.0.lock() {
+        Ok(mut replies) => replies.drain(..).collect(),
+        Err(_) => return,
+    };
+    for reply in replies {
+        match reply {
+            HistoryReply::Status(status) => {
+                let start = status.start.as_deref().and_then(parse_iso);
+                history.set_status(status.enabled, start, now_secs());
+            }
+            HistoryReply::Devices { at, devices } => {
+                registry.devices.retain(|d| split_id(&d.id).0 != PRIMARY_DAEMON);
+                registry.devices.extend(devices.into_iter().map(DeviceData::from));
+                selected.0 = None;
+                read_only.history = true;
+                history.viewing = Some(at);
+                history.loading = false;
+                history.error = None;
+            }
+            HistoryReply::Failed(e) => {
+                history.loading = false;
+                history.error = Some(e);
+            }
+        }
+    }
+}
+
+/// The History window, and a badge over the scene while a past state is shown
+fn render_history(
+    mut contexts: EguiContexts,
+    mut history: ResMut<History>,
+    pending: Res<PendingHistory>,
+    daemon_config: Res<DaemonConfig>,
+    mut registry: ResMut<DeviceRegistry>,
+    mut read_only: ResMut<ReadOnlyMode>,
+    messages: Res<PendingMessages>,
+    low_bandwidth: Res<LowBandwidthMode>,
+) {
+    let Ok(ctx) = contexts.ctx_mut() else { return };
+    let mut back_to_live = false;
+
+    if let Some(at) = history.viewing {
+        egui::Area::new(egui::Id::new("history_badge"))
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 8.0))
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.colored_label(
+                            egui::Color32::from_rgb(255, 200, 50),
+                            format!("⏱ Viewing {} (read-only)", format_time(at)),
+                        );
+                        back_to_live |= ui.button("Back to live").clicked();
+                    });
+                });
+            });
+    }
+
+    let mut open = history.open;
+    egui::Window::new("History")
+        .open(&mut open)
+        .resizable(false)
+        .default_width(360.0)
+        .show(ctx, |ui| match history.enabled {
+            None => {
+                ui.spinner();
+            }
+            Some(false) => {
+                ui.label("The daemon is not keeping a registry journal.");
+                ui.label(
+                    egui::RichText::new("Set `enabled = true` under [journal] in its config to record history.")
+                        .size(11.0)
+                        .color(egui::Color32::GRAY),
+                );
+            }
+            Some(true) => {
+                let now = now_secs();
+                let Some(start) = history.start else {
+                    ui.label("Nothing recorded yet.");
+                    return;
+                };
+                ui.label(format!("Recorded since {}", format_time(start)));
+                ui.add(
+                    egui::Slider::new(&mut history.selected, start..=now)
+                        .show_value(false)
+                        .text(format_time(history.selected)),
+                );
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(!history.loading, egui::Button::new("Load")).clicked() {
+                        history.loading = true;
+                        history.error = None;
+                        load_past_state(&daemon_config.http_url, history.selected, &pending);
+                    }
+                    if ui.add_enabled(history.viewing.is_some(), egui::Button::new("Back to live")).clicked() {
+                        back_to_live = true;
+                    }
+                    if history.loading {
+                        ui.spinner();
+                    }
+                });
+                if let Some(error) = &history.error {
+                    ui.colored_label(egui::Color32::from_rgb(255, 100, 100), error);
+                }
+            }
+        });
+    history.open = open;
+
+    if back_to_live {
+        history.viewing = None;
+        read_only.history = false;
+        registry.devices.retain(|d| split_id(&d.id).0 != PRIMARY_DAEMON);
+        reload_devices(&daemon_config, &messages, low_bandwidth.enabled);
+    }
+}
+
+fn now_secs() -> f64 {
+    #[cfg(target_arch = "wasm32")]
+    {
+        js_sys::Date::now() / 1000.0
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0.0, |d| d.as_secs_f64())
+    }
+}
+
+/// Unix seconds from an RFC 3339 timestamp
+fn parse_iso(text: &str) -> Option<f64> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        Some(js_sys::Date::parse(text) / 1000.0).filter(|secs| secs.is_finite())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = text;
+        None
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn to_iso(secs: f64) -> String {
+    js_sys::Date::new(&wasm_bindgen::JsValue::from_f64(secs * 1000.0))
+        .to_iso_string()
+        .into()
+}
+
+/// Local date and time for display
+fn format_time(secs: f64) -> String {
+    #[cfg(target_arch = "wasm32")]
+    {
+        js_sys::Date::new(&wasm_bindgen::JsValue::from_f64(secs * 1000.0))
+            .to_locale_string("default", &wasm_bindgen::JsValue::UNDEFINED)
+            .into()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        format!("{:.0} s", secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slider_stays_inside_journal_span() {
+        let mut history = History::default();
+        history.set_status(true, Some(1000.0), 5000.0);
+        // First opening starts at the present
+        assert_eq!(history.selected, 5000.0);
+
+        history.selected = 200.0;
+        history.set_status(true, Some(1000.0), 6000.0);
+        assert_eq!(history.selected, 1000.0);
+
+        history.set_status(false, None, 7000.0);
+        assert_eq!(history.enabled, Some(false));
+        assert_eq!(history.selected, 7000.0);
+    }
+}
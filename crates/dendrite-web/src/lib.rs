@@ -3,10 +3,22 @@
 //! This crate provides the browser-based visualization using Bevy and WebGPU.
 
 mod app;
+mod assemblies;
+mod attention;
+mod daemons;
+mod device_filter;
 pub mod file_picker;
+mod fleet;
+mod heatmap;
+mod history;
 mod models;
 mod network;
+mod occlusion;
+mod ota_batch;
+mod overlap;
 mod scene;
+mod setup;
+mod topology;
 mod ui;
 
 use wasm_bindgen::prelude::*;
@@ -3,11 +3,22 @@
 use bevy::asset::LoadState;
 use bevy::ecs::schedule::ApplyDeferred;
 use bevy::prelude::*;
+use dendrite_scene::keepout::{pose_rotation, pose_transform};
 use std::collections::HashMap;
 
 use crate::app::{AxisAlignData, DeviceRegistry, DeviceStatus, FrameVisibility, GeometryData, PortData, SensorData, VisualData};
+use crate::daemons::{split_id, Daemons, REGION_SPACING};
+use crate::network::LowBandwidthMode;
 use crate::scene::DeviceEntity;
 
+/// Marks a device drawn as a primitive because low-bandwidth mode skipped its model
+#[derive(Component)]
+pub struct LiteFallback;
+
+/// Model paths a device entity was spawned with, to notice when they change
+#[derive(Component)]
+pub struct SpawnedModels(pub Vec<String>);
+
 /// Component marking a visual child entity
 #[derive(Component)]
 pub struct VisualEntity {
@@ -47,7 +58,7 @@ pub struct SensorAxisEntity {
     pub category: String,
     /// Sensor type (optical_flow, tof, etc.)
     pub sensor_type: String,
-    /// Driver name
+    /// Driver display name (registry label, or raw driver name if unknown)
     pub driver: Option<String>,
     /// Axis alignment info for tooltip
     pub axis_align: Option<AxisAlignData>,
@@ -69,7 +80,7 @@ pub struct SensorFovEntity {
     pub category: String,
     /// Sensor type (optical_flow, tof, etc.)
     pub sensor_type: String,
-    /// Driver name
+    /// Driver display name (registry label, or raw driver name if unknown)
     pub driver: Option<String>,
     /// Axis alignment info for tooltip
     pub axis_align: Option<AxisAlignData>,
@@ -119,22 +130,29 @@ impl Plugin for ModelsPlugin {
         app.init_resource::<ModelCache>()
             .init_resource::<SensorPortCache>()
             .init_resource::<PendingPortMeshes>()
+            .init_resource::<ModelRefresh>()
             .add_systems(Update, load_models)
+            .add_systems(Update, restore_lite_fallbacks.before(sync_device_entities))
+            .add_systems(Update, respawn_changed_models.before(sync_device_entities))
+            .add_systems(Update, refresh_reloaded_models.before(sync_device_entities))
             .add_systems(Update, sync_device_entities.after(load_models))
             .add_systems(Update, sync_sensor_entities.after(sync_device_entities))
             .add_systems(Update, sync_port_entities.after(sync_device_entities))
+            .add_systems(Update, sync_port_transforms.after(sync_port_entities))
             .add_systems(Update, (
                 tag_gltf_node_names.after(sync_device_entities),
                 ApplyDeferred,
                 link_port_meshes,
             ).chain())
-            .add_systems(Update, update_visual_visibility.after(sync_device_entities))
+            .add_systems(Update, init_toggle_visibility)
+            .add_systems(Update, update_visual_visibility.after(sync_device_entities).after(init_toggle_visibility))
             .add_systems(Update, update_sensor_axis_visibility.after(sync_sensor_entities))
             .add_systems(Update, update_sensor_axis_hover_alpha.after(update_sensor_axis_visibility))
             .add_systems(Update, update_sensor_fov_visibility.after(sync_sensor_entities))
             .add_systems(Update, update_sensor_fov_hover_alpha.after(update_sensor_fov_visibility))
             .add_systems(Update, update_port_visibility.after(sync_port_entities))
-            .add_systems(Update, update_port_mesh_highlighting.after(link_port_meshes));
+            .add_systems(Update, update_port_mesh_highlighting.after(link_port_meshes))
+            .add_systems(Update, update_provisional_fade.after(sync_device_entities));
     }
 }
 
@@ -146,6 +164,11 @@ pub struct ModelCache {
     pub ready: HashMap<String, bool>,
 }
 
+/// Devices whose models must be fetched again even though their paths are
+/// unchanged, e.g. after the daemon reloaded its fragments
+#[derive(Resource, Default)]
+pub struct ModelRefresh(pub Vec<String>);
+
 /// Cache to track which sensors/ports have been spawned for each device
 #[derive(Resource, Default)]
 pub struct SensorPortCache {
@@ -215,10 +238,98 @@ fn load_models(
     }
 }
 
+/// Despawn low-bandwidth placeholders once full fidelity is back, so the
+/// devices respawn with their models
+fn restore_lite_fallbacks(
+    mut commands: Commands,
+    low_bandwidth: Res<LowBandwidthMode>,
+    fallbacks: Query<(Entity, &DeviceEntity), With<LiteFallback>>,
+    mut sensor_port_cache: ResMut<SensorPortCache>,
+) {
+    if !low_bandwidth.is_changed() || low_bandwidth.enabled {
+        return;
+    }
+    for (entity, device) in fallbacks.iter() {
+        commands.entity(entity).despawn();
+        // Sensors and ports are children of the device entity
+        sensor_port_cache.spawned_sensors.retain(|(id, _)| id != &device.device_id);
+        sensor_port_cache.spawned_ports.retain(|(id, _)| id != &device.device_id);
+    }
+}
+
+/// Despawn devices whose models changed, e.g. when a model override is
+/// uploaded or removed, so they respawn with the new ones
+fn respawn_changed_models(
+    mut commands: Commands,
+    registry: Res<DeviceRegistry>,
+    spawned: Query<(Entity, &DeviceEntity, &SpawnedModels)>,
+    mut sensor_port_cache: ResMut<SensorPortCache>,
+) {
+    if !registry.is_changed() {
+        return;
+    }
+    for (entity, device, models) in spawned.iter() {
+        let Some(data) = registry.devices.iter().find(|d| d.id == device.device_id) else {
+            continue;
+        };
+        if device_model_paths(&data.visuals, data.model_path.as_deref()) != models.0 {
+            tracing::info!("Models changed for device {}, respawning", device.device_id);
+            commands.entity(entity).despawn();
+            sensor_port_cache.spawned_sensors.retain(|(id, _)| id != &device.device_id);
+            sensor_port_cache.spawned_ports.retain(|(id, _)| id != &device.device_id);
+        }
+    }
+}
+
+/// Despawn devices queued in [`ModelRefresh`] and reload their models, so
+/// they respawn with whatever the daemon now serves at the same paths
+fn refresh_reloaded_models(
+    mut commands: Commands,
+    mut refresh: ResMut<ModelRefresh>,
+    registry: Res<DeviceRegistry>,
+    spawned: Query<(Entity, &DeviceEntity)>,
+    mut model_cache: ResMut<ModelCache>,
+    asset_server: Res<AssetServer>,
+    mut sensor_port_cache: ResMut<SensorPortCache>,
+) {
+    if refresh.0.is_empty() {
+        return;
+    }
+    for device_id in std::mem::take(&mut refresh.0) {
+        if let Some(data) = registry.devices.iter().find(|d| d.id == device_id) {
+            for path in device_model_paths(&data.visuals, data.model_path.as_deref()) {
+                model_cache.models.remove(&path);
+                model_cache.loading.remove(&path);
+                model_cache.ready.remove(&path);
+                asset_server.reload(path);
+            }
+        }
+        for (entity, device) in spawned.iter().filter(|(_, d)| d.device_id == device_id) {
+            tracing::info!("Fragments reloaded for device {}, respawning", device.device_id);
+            commands.entity(entity).despawn();
+        }
+        sensor_port_cache.spawned_sensors.retain(|(id, _)| id != &device_id);
+        sensor_port_cache.spawned_ports.retain(|(id, _)| id != &device_id);
+    }
+}
+
+/// Asset paths of the models a device is drawn with
+fn device_model_paths(visuals: &[VisualData], model_path: Option<&str>) -> Vec<String> {
+    if visuals.is_empty() {
+        return model_path.map(normalize_model_path).into_iter().collect();
+    }
+    visuals
+        .iter()
+        .filter_map(|v| v.model_path.as_deref().map(normalize_model_path))
+        .collect()
+}
+
 /// Sync device entities with the registry
 fn sync_device_entities(
     mut commands: Commands,
     registry: Res<DeviceRegistry>,
+    low_bandwidth: Res<LowBandwidthMode>,
+    daemons: Res<Daemons>,
     mut model_cache: ResMut<ModelCache>,
     asset_server: Res<AssetServer>,
     existing_devices: Query<(Entity, &DeviceEntity)>,
@@ -266,12 +377,7 @@ fn sync_device_entities(
                 }
                 // Update rotation if explicit orientation from HCDF import
                 if let Some(orient) = device.orientation {
-                    let new_rotation = Quat::from_euler(
-                        EulerRot::ZYX,
-                        orient[2] as f32, // yaw (Z)
-                        orient[1] as f32, // pitch (Y)
-                        orient[0] as f32, // roll (X)
-                    );
+                    let new_rotation = pose_rotation(orient, None);
                     // Only update if rotation changed significantly
                     if transform.rotation.angle_between(new_rotation) > 0.001 {
                         tracing::info!(
@@ -290,7 +396,9 @@ fn sync_device_entities(
             .position
             .map(|p| Vec3::new(p[0] as f32, p[1] as f32, p[2] as f32))
             .unwrap_or_else(|| {
-                // Auto-position: origin if no other devices, otherwise radially offset to avoid collisions
+                // Auto-position: region origin of the owning daemon if no other devices there,
+                // otherwise radially offset to avoid collisions
+                let origin = daemons.region_offset(split_id(&device.id).0) + Vec3::new(0.0, 0.0, 0.01);
                 // Collect existing device positions (from already-spawned entities)
                 let mut occupied_positions: Vec<Vec3> = existing_devices
                     .iter()
@@ -315,9 +423,12 @@ fn sync_device_entities(
                 let estimated_bounds = 0.1_f32; // 10cm
                 let padding = 0.02_f32; // 2cm padding
 
+                // Devices of other daemons live in their own regions
+                occupied_positions.retain(|p| (p.x - origin.x).abs() < REGION_SPACING / 2.0);
+
                 if occupied_positions.is_empty() {
                     // First device: place at origin
-                    Vec3::new(0.0, 0.0, 0.01)
+                    origin
                 } else {
                     // Find non-colliding position radially outward from origin
                     // Try positions along +X, +Y, -X, -Y axes, then diagonals, increasing radius
@@ -337,7 +448,7 @@ fn sync_device_entities(
                     for radius_mult in 1..20 {
                         let radius = min_distance * radius_mult as f32;
                         for dir in &directions {
-                            let candidate = *dir * radius + Vec3::new(0.0, 0.0, 0.01);
+                            let candidate = *dir * radius + origin;
                             // Check if this position collides with any existing device
                             let collides = occupied_positions.iter().any(|pos| {
                                 let dist_xy = ((candidate.x - pos.x).powi(2) + (candidate.y - pos.y).powi(2)).sqrt();
@@ -350,7 +461,7 @@ fn sync_device_entities(
                     }
 
                     // Fallback: place far away if all positions taken
-                    Vec3::new(1.0, 0.0, 0.01)
+                    origin + Vec3::new(1.0, 0.0, 0.0)
                 }
             });
 
@@ -361,18 +472,19 @@ fn sync_device_entities(
             DeviceStatus::Unknown => Color::srgb(0.5, 0.5, 0.5),
         };
 
-        // Check if device has composite visuals
-        if !device.visuals.is_empty() {
-            // Check if all visuals are ready (loaded or failed)
-            let all_ready = device.visuals.iter().all(|v| {
-                if let Some(ref model_path) = v.model_path {
-                    let asset_path = normalize_model_path(model_path);
-                    model_cache.ready.contains_key(&asset_path) || model_cache.models.contains_key(&asset_path)
-                } else {
-                    true // No model = ready
-                }
-            });
+        // Low-bandwidth mode only uses models that are already cached
+        let lite = low_bandwidth.enabled;
 
+        // Check if device has composite visuals
+        let all_ready = device.visuals.iter().all(|v| {
+            if let Some(ref model_path) = v.model_path {
+                let asset_path = normalize_model_path(model_path);
+                model_cache.ready.contains_key(&asset_path) || model_cache.models.contains_key(&asset_path)
+            } else {
+                true // No model = ready
+            }
+        });
+        if !device.visuals.is_empty() && (!lite || all_ready) {
             // Start loading any visuals that aren't loading yet
             for visual in &device.visuals {
                 if let Some(ref model_path) = visual.model_path {
@@ -400,6 +512,7 @@ fn sync_device_entities(
                 DeviceEntity {
                     device_id: device.id.clone(),
                 },
+                SpawnedModels(device_model_paths(&device.visuals, device.model_path.as_deref())),
             )).id();
 
             // Spawn child entities for each visual
@@ -429,7 +542,11 @@ fn sync_device_entities(
         }
 
         // Legacy: If device has a single model_path, try to load it from the server
-        if let Some(ref model_path) = device.model_path {
+        let legacy_model = device
+            .model_path
+            .as_deref()
+            .filter(|path| !lite || model_cache.models.contains_key(&normalize_model_path(path)));
+        if let Some(model_path) = legacy_model {
             let asset_path = normalize_model_path(model_path);
 
             // Start loading if not already loading or loaded
@@ -452,6 +569,7 @@ fn sync_device_entities(
                     DeviceEntity {
                         device_id: device.id.clone(),
                     },
+                    SpawnedModels(vec![asset_path.clone()]),
                 ));
                 continue;
             }
@@ -463,7 +581,7 @@ fn sync_device_entities(
         }
 
         // Fallback: spawn a colored cube (or model failed to load)
-        commands.spawn((
+        let mut fallback = commands.spawn((
             Mesh3d(meshes.add(Cuboid::new(0.03, 0.015, 0.02))),
             MeshMaterial3d(materials.add(StandardMaterial {
                 base_color: color,
@@ -474,6 +592,9 @@ fn sync_device_entities(
                 device_id: device.id.clone(),
             },
         ));
+        if lite && (!device.visuals.is_empty() || device.model_path.is_some()) {
+            fallback.insert(LiteFallback);
+        }
     }
 }
 
@@ -498,18 +619,20 @@ fn normalize_model_path(path: &str) -> String {
 /// Convert visual pose to Transform
 /// Pose is [x, y, z, roll, pitch, yaw] in meters/radians
 fn visual_to_transform(visual: &VisualData) -> Transform {
-    if let Some(pose) = visual.pose {
-        let translation = Vec3::new(pose[0] as f32, pose[1] as f32, pose[2] as f32);
-        // SDF convention: roll, pitch, yaw (extrinsic XYZ = intrinsic ZYX)
-        let rotation = Quat::from_euler(
-            EulerRot::ZYX,
-            pose[5] as f32, // yaw (Z)
-            pose[4] as f32, // pitch (Y)
-            pose[3] as f32, // roll (X)
-        );
-        Transform::from_translation(translation).with_rotation(rotation)
-    } else {
-        Transform::IDENTITY
+    visual.pose.map(pose_transform).unwrap_or(Transform::IDENTITY)
+}
+
+/// Seed toggle-group visibility from HCDF `default_hidden` for newly loaded devices
+fn init_toggle_visibility(
+    registry: Res<DeviceRegistry>,
+    mut frame_visibility: ResMut<FrameVisibility>,
+) {
+    if !registry.is_changed() {
+        return;
+    }
+
+    for device in &registry.devices {
+        frame_visibility.init_toggle_defaults(&device.id, &device.visuals);
     }
 }
 
@@ -697,7 +820,7 @@ fn spawn_sensor_axis_frame(
             sensor_name: sensor.name.clone(),
             category: sensor.category.clone(),
             sensor_type: sensor.sensor_type.clone(),
-            driver: sensor.driver.clone(),
+            driver: sensor.driver_label.clone().or_else(|| sensor.driver.clone()),
             axis_align: sensor.axis_align.clone(),
             mode,
         },
@@ -828,7 +951,7 @@ fn spawn_sensor_fov_with_color(
         fov_name: fov_name.map(|s| s.to_string()),
         category: sensor.category.clone(),
         sensor_type: sensor.sensor_type.clone(),
-        driver: sensor.driver.clone(),
+        driver: sensor.driver_label.clone().or_else(|| sensor.driver.clone()),
         axis_align: sensor.axis_align.clone(),
     };
 
@@ -1123,6 +1246,31 @@ fn sync_port_entities(
     }
 }
 
+/// Move port highlights when their pose changes in the registry (e.g., a pose edit)
+fn sync_port_transforms(
+    registry: Res<DeviceRegistry>,
+    mut ports: Query<(&PortGeometryEntity, &mut Transform)>,
+) {
+    if !registry.is_changed() {
+        return;
+    }
+
+    for (port, mut transform) in ports.iter_mut() {
+        let Some(data) = registry
+            .devices
+            .iter()
+            .find(|d| d.id == port.device_id)
+            .and_then(|d| d.ports.iter().find(|p| p.name == port.port_name))
+        else {
+            continue;
+        };
+        let target = pose_to_transform(data.pose);
+        if *transform != target {
+            *transform = target;
+        }
+    }
+}
+
 /// Spawn a port geometry as a transparent highlight
 fn spawn_port_geometry(
     commands: &mut Commands,
@@ -1183,19 +1331,8 @@ fn spawn_port_geometry(
 }
 
 /// Convert pose array to Transform
-fn pose_to_transform(pose: Option<[f64; 6]>) -> Transform {
-    if let Some(p) = pose {
-        let translation = Vec3::new(p[0] as f32, p[1] as f32, p[2] as f32);
-        let rotation = Quat::from_euler(
-            EulerRot::ZYX,
-            p[5] as f32, // yaw
-            p[4] as f32, // pitch
-            p[3] as f32, // roll
-        );
-        Transform::from_translation(translation).with_rotation(rotation)
-    } else {
-        Transform::IDENTITY
-    }
+pub(crate) fn pose_to_transform(pose: Option<[f64; 6]>) -> Transform {
+    pose.map(pose_transform).unwrap_or(Transform::IDENTITY)
 }
 
 /// Update sensor axis frame visibility based on "Show Reference Frames" toggle
@@ -1275,9 +1412,10 @@ fn update_port_visibility(
             Visibility::Hidden
         };
 
-        // Check if this port is hovered
+        // Check if this port is hovered or being edited
         let port_key = format!("{}:{}", port.device_id, port.port_name);
-        let is_hovered = frame_visibility.hovered_port.as_ref() == Some(&port_key);
+        let is_hovered = frame_visibility.hovered_port.as_ref() == Some(&port_key)
+            || frame_visibility.selected_port.as_ref() == Some(&port_key);
 
         // Update material alpha based on hover state
         if let Some(material) = materials.get_mut(&material_handle.0) {
@@ -1675,6 +1813,56 @@ fn update_port_mesh_highlighting(
     }
 }
 
+/// Opacity of visuals on devices whose pose is only a layout suggestion
+const PROVISIONAL_ALPHA: f32 = 0.45;
+
+/// Material a mesh had before it was faded for a provisional pose
+#[derive(Component)]
+struct ProvisionalFade(Handle<StandardMaterial>);
+
+/// Fade visuals of devices the daemon placed provisionally, restoring them
+/// once the pose has been set by a user
+///
+/// Faded meshes get their own material so other devices sharing the glTF
+/// keep theirs. Port meshes are left alone; their highlighting owns their
+/// materials.
+fn update_provisional_fade(
+    mut commands: Commands,
+    registry: Res<DeviceRegistry>,
+    visual_query: Query<(Entity, &VisualEntity)>,
+    children_query: Query<&Children>,
+    mut mesh_query: Query<(&mut MeshMaterial3d<StandardMaterial>, Option<&ProvisionalFade>), Without<PortMeshTarget>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for (visual_entity, visual) in visual_query.iter() {
+        let provisional = registry.devices.iter()
+            .any(|d| d.id == visual.device_id && d.pose_provisional);
+
+        for entity in children_query.iter_descendants(visual_entity) {
+            let Ok((mut material, fade)) = mesh_query.get_mut(entity) else {
+                continue;
+            };
+            match (provisional, fade) {
+                (true, None) => {
+                    let Some(mut faded) = materials.get(&material.0).cloned() else {
+                        continue;
+                    };
+                    let alpha = faded.base_color.alpha() * PROVISIONAL_ALPHA;
+                    faded.base_color.set_alpha(alpha);
+                    faded.alpha_mode = AlphaMode::Blend;
+                    commands.entity(entity).insert(ProvisionalFade(material.0.clone()));
+                    material.0 = materials.add(faded);
+                }
+                (false, Some(fade)) => {
+                    material.0 = fade.0.clone();
+                    commands.entity(entity).remove::<ProvisionalFade>();
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
 /// Get highlight color for port type as (r, g, b)
 fn port_type_to_color(port_type: &str) -> (f32, f32, f32) {
     match port_type.to_lowercase().as_str() {
@@ -2,9 +2,23 @@
 
 use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use crate::app::{AxisAlignData, DeviceData, DeviceRegistry, DeviceStatus, FirmwareCheckState, FirmwareStatusData, FovData, FrameData, GeometryData, PortData, SensorData, VisualData};
+use crate::app::{AxisAlignData, DeviceData, DeviceRegistry, DeviceStatus, FirmwareCheckState, FirmwareStatusData, FovData, FrameData, GeometryData, Notifications, PortData, SelectedDevice, SensorData, VisualData};
+use crate::daemons::{namespaced_id, split_id, Daemons, PRIMARY_DAEMON};
+#[cfg(target_arch = "wasm32")]
+use crate::daemons::{DaemonLink, LinkState};
+use dendrite_api_types::{
+    CompactSnapshot, ConsoleMessage, DeviceDelta, DeviceImagesResponse, DeviceJson, DeviceRecords, FirmwareCheckResponse, FovJson, FragmentReloadResponse, FullSync, GeometryJson,
+    OtaEvent, Record, RecordChange, ShellResponse,
+    StatusChange, DELTA_PROTOCOL_VERSION, SNAPSHOT_MESSAGE_TYPE,
+};
+pub use dendrite_api_types::UpdateState as OtaUpdateState;
+use dendrite_core::{FirmwareStatus, HcdfChange, WS_PROTOCOL_VERSION};
+use dendrite_scene::keepout::KeepoutScene;
 
 pub struct NetworkPlugin;
 
@@ -15,6 +29,8 @@ pub struct DaemonConfig {
     pub http_url: String,
     /// WebSocket URL (e.g., "ws://192.168.1.100:8080/ws")
     pub ws_url: String,
+    /// Access token from the `?token=` URL parameter, sent with every request
+    pub token: Option<String>,
 }
 
 impl Default for DaemonConfig {
@@ -22,6 +38,7 @@ impl Default for DaemonConfig {
         Self {
             http_url: String::new(),
             ws_url: String::new(),
+            token: None,
         }
     }
 }
@@ -33,12 +50,16 @@ impl DaemonConfig {
         let window = web_sys::window().expect("no window");
         let location = window.location();
 
+        // A token from dendrite-qr belongs to the daemon the page was opened for
+        let search = location.search().unwrap_or_default();
+        let token = Self::parse_query_param(&search, "token")
+            .map(|token| percent_decode(&token))
+            .filter(|token| !token.is_empty());
+
         // Check for ?daemon= query parameter
-        if let Ok(search) = location.search() {
-            if let Some(daemon_param) = Self::parse_query_param(&search, "daemon") {
-                tracing::info!("Using daemon from URL parameter: {}", daemon_param);
-                return Self::from_daemon_address(&daemon_param);
-            }
+        if let Some(daemon_param) = Self::parse_query_param(&search, "daemon") {
+            tracing::info!("Using daemon from URL parameter: {}", daemon_param);
+            return Self { token, ..Self::from_daemon_address(&daemon_param) };
         }
 
         // Fall back to same-origin
@@ -48,6 +69,7 @@ impl DaemonConfig {
         Self {
             http_url: format!("{}://{}", if is_https { "https" } else { "http" }, host),
             ws_url: format!("{}://{}/ws", if is_https { "wss" } else { "ws" }, host),
+            token,
         }
     }
 
@@ -66,6 +88,7 @@ impl DaemonConfig {
         Self {
             http_url,
             ws_url,
+            token: None,
         }
     }
 
@@ -90,6 +113,100 @@ impl DaemonConfig {
     }
 }
 
+/// Access tokens by daemon HTTP base URL, for request functions that only
+/// know the URL they call
+static ACCESS_TOKENS: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+
+/// Send `config.token` with every request to that daemon
+pub fn register_access_token(config: &DaemonConfig) {
+    let mut tokens = ACCESS_TOKENS.lock().unwrap();
+    tokens.retain(|(base, _)| *base != config.http_url);
+    if let Some(token) = &config.token {
+        tokens.push((config.http_url.clone(), token.clone()));
+    }
+}
+
+/// Token for a request URL; other daemons never see it
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+fn access_token_for(url: &str) -> Option<String> {
+    ACCESS_TOKENS
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|(base, _)| url.strip_prefix(base.as_str()).is_some_and(|rest| rest.is_empty() || rest.starts_with(['/', '?'])))
+        .map(|(_, token)| token.clone())
+}
+
+/// Requests to a daemon, carrying its access token as a Bearer token
+///
+/// Mirrors `gloo_net::http::Request`.
+#[cfg(target_arch = "wasm32")]
+pub struct DaemonRequest;
+
+#[cfg(target_arch = "wasm32")]
+impl DaemonRequest {
+    pub fn get(url: &str) -> gloo_net::http::RequestBuilder {
+        Self::new(url, gloo_net::http::Method::GET)
+    }
+
+    pub fn post(url: &str) -> gloo_net::http::RequestBuilder {
+        Self::new(url, gloo_net::http::Method::POST)
+    }
+
+    pub fn put(url: &str) -> gloo_net::http::RequestBuilder {
+        Self::new(url, gloo_net::http::Method::PUT)
+    }
+
+    pub fn delete(url: &str) -> gloo_net::http::RequestBuilder {
+        Self::new(url, gloo_net::http::Method::DELETE)
+    }
+
+    fn new(url: &str, method: gloo_net::http::Method) -> gloo_net::http::RequestBuilder {
+        let request = gloo_net::http::RequestBuilder::new(url).method(method);
+        match access_token_for(url) {
+            Some(token) => request.header("Authorization", &format!("Bearer {}", token)),
+            None => request,
+        }
+    }
+}
+
+/// Percent-encode a query parameter value
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Decode `%XX` escapes in a query parameter value; malformed ones are kept
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| bytes.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
 /// Network interface info from the server
 #[derive(Debug, Clone, Deserialize, Default)]
 pub struct NetworkInterfaceInfo {
@@ -106,6 +223,12 @@ pub struct NetworkInterfaces {
     pub selected_index: Option<usize>,
     pub loading: bool,
     pub scan_in_progress: bool,
+    /// Hosts probed and to probe in the running scan (None before probing starts)
+    pub scan_progress: Option<(usize, usize)>,
+    /// Addresses in the scanned subnet, from the scan's start
+    pub scan_total_hosts: usize,
+    /// Devices the last completed scan found
+    pub last_scan_found: Option<usize>,
 }
 
 /// Resource storing heartbeat (connection checking) state
@@ -126,6 +249,278 @@ impl Default for HeartbeatState {
     }
 }
 
+/// Resource tracking whether this client is a read-only spectator
+///
+/// Set by the `?spectator=1` URL parameter, by the daemon reporting a
+/// viewer-role session, or while the History panel shows a past state.
+/// Mutating controls are disabled while any of them holds.
+#[derive(Resource, Default)]
+pub struct ReadOnlyMode {
+    /// Requested via `?spectator=1`
+    pub spectator: bool,
+    /// Daemon reported a viewer session
+    pub viewer_session: bool,
+    /// Primary daemon's devices are a past state from the journal
+    pub history: bool,
+}
+
+impl ReadOnlyMode {
+    pub fn is_read_only(&self) -> bool {
+        self.spectator || self.viewer_session || self.history
+    }
+
+    /// Read the `?spectator=1` URL parameter
+    #[cfg(target_arch = "wasm32")]
+    pub fn from_browser() -> Self {
+        let search = web_sys::window()
+            .and_then(|w| w.location().search().ok())
+            .unwrap_or_default();
+        let spectator = DaemonConfig::parse_query_param(&search, "spectator")
+            .is_some_and(|v| v == "1" || v == "true");
+        if spectator {
+            tracing::info!("Spectator mode: read-only session");
+        }
+        Self { spectator, viewer_session: false, history: false }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_browser() -> Self {
+        Self::default()
+    }
+}
+
+/// Device sync and latency polling interval in low-bandwidth mode
+const LOW_BANDWIDTH_POLL_SECS: f32 = 30.0;
+
+/// Device sync interval at full fidelity
+const DEVICE_SYNC_SECS: f32 = 5.0;
+
+/// Bytes received from the daemon over the WebSocket and REST API
+static RECEIVED_BYTES: AtomicU64 = AtomicU64::new(0);
+
+/// Count a payload received from the daemon toward the data usage estimate
+#[cfg(target_arch = "wasm32")]
+fn record_received(bytes: usize) {
+    RECEIVED_BYTES.fetch_add(bytes as u64, Ordering::Relaxed);
+}
+
+/// Resource for the low-bandwidth mode used over metered links
+///
+/// While enabled, device models are not downloaded, the WebSocket only
+/// carries full records for the selected device and polling slows to
+/// every 30 seconds. Turning it off restores everything without a reload.
+#[derive(Resource, Default)]
+pub struct LowBandwidthMode {
+    pub enabled: bool,
+    /// The browser reports a cellular connection
+    pub suggested: bool,
+    /// The user dismissed the suggestion
+    pub dismissed: bool,
+}
+
+impl LowBandwidthMode {
+    /// Check `navigator.connection` for a cellular link
+    #[cfg(target_arch = "wasm32")]
+    pub fn from_browser() -> Self {
+        use wasm_bindgen::JsValue;
+
+        let connection = web_sys::window()
+            .map(|w| JsValue::from(w.navigator()))
+            .and_then(|n| js_sys::Reflect::get(&n, &JsValue::from_str("connection")).ok())
+            .filter(|c| c.is_object());
+        let field = |name: &str| {
+            connection
+                .as_ref()
+                .and_then(|c| js_sys::Reflect::get(c, &JsValue::from_str(name)).ok())
+                .and_then(|v| v.as_string())
+        };
+        let suggested = field("type").as_deref() == Some("cellular")
+            || matches!(field("effectiveType").as_deref(), Some("slow-2g" | "2g" | "3g"));
+        if suggested {
+            tracing::info!("Cellular connection detected, suggesting low-bandwidth mode");
+        }
+        Self { enabled: false, suggested, dismissed: false }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_browser() -> Self {
+        Self::default()
+    }
+
+    /// Whether the UI should offer to turn the mode on
+    pub fn should_suggest(&self) -> bool {
+        self.suggested && !self.enabled && !self.dismissed
+    }
+}
+
+/// Estimated data received from the daemon since the page loaded
+#[derive(Resource, Default)]
+pub struct DataUsage {
+    pub bytes: u64,
+    pub elapsed_secs: f32,
+}
+
+impl DataUsage {
+    /// Total and hourly rate, e.g. "1.2 MB (~40 MB/h)"
+    pub fn label(&self) -> String {
+        let total = format_bytes(self.bytes);
+        // The rate is noise until a few polls have gone by
+        if self.elapsed_secs < 60.0 {
+            return total;
+        }
+        let per_hour = self.bytes as f64 * 3600.0 / self.elapsed_secs as f64;
+        format!("{} (~{}/h)", total, format_bytes(per_hour as u64))
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    match bytes {
+        b if b < 1024 => format!("{} B", b),
+        b if b < 1024 * 1024 => format!("{:.1} KB", b as f64 / 1024.0),
+        b => format!("{:.1} MB", b as f64 / (1024.0 * 1024.0)),
+    }
+}
+
+fn update_data_usage(time: Res<Time>, mut usage: ResMut<DataUsage>) {
+    let bytes = RECEIVED_BYTES.load(Ordering::Relaxed);
+    if usage.bytes != bytes || time.elapsed_secs() - usage.elapsed_secs >= 1.0 {
+        usage.bytes = bytes;
+        usage.elapsed_secs = time.elapsed_secs();
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+thread_local! {
+    /// Open WebSocket, kept so subscription changes can be sent
+    static SOCKET: std::cell::RefCell<Option<web_sys::WebSocket>> = const { std::cell::RefCell::new(None) };
+    /// Last subscription message, replayed whenever the socket opens
+    static SUBSCRIPTION: std::cell::RefCell<Option<String>> = const { std::cell::RefCell::new(None) };
+    /// WebSockets to the other daemons by key, with their last subscription message
+    static LINK_SOCKETS: std::cell::RefCell<std::collections::HashMap<u32, (web_sys::WebSocket, Option<String>)>> =
+        std::cell::RefCell::new(std::collections::HashMap::new());
+}
+
+/// Send the current subscription if the socket is open
+#[cfg(target_arch = "wasm32")]
+fn send_current_subscription() {
+    SUBSCRIPTION.with(|sub| {
+        let Some(msg) = sub.borrow().clone() else { return };
+        SOCKET.with(|socket| {
+            if let Some(ws) = socket.borrow().as_ref() {
+                if ws.ready_state() == web_sys::WebSocket::OPEN {
+                    let _ = ws.send_with_str(&msg);
+                }
+            }
+        });
+    });
+}
+
+/// Send a daemon's subscription if its socket is open
+#[cfg(target_arch = "wasm32")]
+fn send_link_subscription(key: u32) {
+    LINK_SOCKETS.with(|sockets| {
+        if let Some((ws, Some(msg))) = sockets.borrow().get(&key) {
+            if ws.ready_state() == web_sys::WebSocket::OPEN {
+                let _ = ws.send_with_str(msg);
+            }
+        }
+    });
+}
+
+/// Tell each daemon which device events to send in full
+///
+/// Only the daemon owning the selected device is told about it.
+fn send_subscription(daemons: &Daemons, lite: bool, selected: Option<&str>) {
+    for link in &daemons.links {
+        let selected = selected.map(split_id).filter(|(key, _)| *key == link.key).map(|(_, raw)| raw);
+        let msg = serde_json::json!({ "type": "subscribe", "lite": lite, "selected": selected }).to_string();
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            if link.key == PRIMARY_DAEMON {
+                SUBSCRIPTION.with(|sub| *sub.borrow_mut() = Some(msg));
+                send_current_subscription();
+            } else {
+                LINK_SOCKETS.with(|sockets| {
+                    if let Some((_, sub)) = sockets.borrow_mut().get_mut(&link.key) {
+                        *sub = Some(msg);
+                    }
+                });
+                send_link_subscription(link.key);
+            }
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let _ = msg;
+        }
+    }
+}
+
+/// WebSocket URL for the current mode; device changes come as deltas, or
+/// from daemons without them the device list as one compact snapshot
+/// (daemons that don't know a parameter ignore it)
+///
+/// Browsers can't send headers with a WebSocket, so the access token goes
+/// in the query.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+fn subscription_ws_url(daemon_config: &DaemonConfig, lite: bool) -> String {
+    let mut url = format!("{}?compact=true&protocol={}", daemon_config.ws_url, DELTA_PROTOCOL_VERSION);
+    if lite {
+        url.push_str("&lite=true");
+    }
+    if let Some(token) = &daemon_config.token {
+        url.push_str(&format!("&token={}", percent_encode(token)));
+    }
+    url
+}
+
+/// Apply low-bandwidth mode and selection changes to the subscription and polling
+fn sync_low_bandwidth(
+    mode: Res<LowBandwidthMode>,
+    selected: Res<SelectedDevice>,
+    mut sync_timer: ResMut<PeriodicSyncTimer>,
+    mut latency: ResMut<DeviceLatency>,
+    daemon_config: Res<DaemonConfig>,
+    pending: Res<PendingMessages>,
+    daemons: Res<Daemons>,
+) {
+    if !mode.is_changed() && !selected.is_changed() && !daemons.is_changed() {
+        return;
+    }
+    send_subscription(&daemons, mode.enabled, selected.0.as_deref());
+
+    if mode.is_changed() && !mode.is_added() {
+        let (sync_secs, latency_secs) = if mode.enabled {
+            (LOW_BANDWIDTH_POLL_SECS, LOW_BANDWIDTH_POLL_SECS)
+        } else {
+            (DEVICE_SYNC_SECS, LATENCY_POLL_SECS)
+        };
+        sync_timer.timer.set_duration(Duration::from_secs_f32(sync_secs));
+        sync_timer.timer.reset();
+        latency.timer.set_duration(Duration::from_secs_f32(latency_secs));
+        latency.timer.reset();
+        tracing::info!("Low-bandwidth mode {}", if mode.enabled { "enabled" } else { "disabled" });
+
+        // The daemon resends full records over the WebSocket; fetch them
+        // too in case the socket is down
+        if !mode.enabled {
+            #[cfg(target_arch = "wasm32")]
+            {
+                refetch_devices(&daemon_config, &pending, false);
+                for link in daemons.links.iter().filter(|l| l.key != PRIMARY_DAEMON) {
+                    refetch_devices(&link.config, &link.messages, false);
+                }
+            }
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = (&daemon_config, &pending);
+    }
+}
+
 /// Request to update subnet (used by trigger_scan_on_interface)
 #[derive(Serialize)]
 #[allow(dead_code)]
@@ -138,42 +533,15 @@ struct UpdateSubnetRequest {
 #[derive(Resource, Default)]
 pub struct PendingFirmwareData(pub Arc<Mutex<Vec<FirmwareCheckResponse>>>);
 
-/// Response from firmware check API
-#[derive(Debug, Clone, Deserialize)]
-pub struct FirmwareCheckResponse {
-    pub device_id: String,
-    pub current_version: Option<String>,
-    /// MCUboot image hash from the device (what MCUmgr reports)
-    pub current_mcuboot_hash: Option<String>,
-    pub latest_version: Option<String>,
-    /// MCUboot image hash for the latest release (for verification after OTA)
-    pub latest_mcuboot_hash: Option<String>,
-    pub status: FirmwareStatusJson,
-    pub changelog: Option<String>,
-}
-
-/// Firmware status JSON from backend (matches serde tag format)
-#[derive(Debug, Clone, Deserialize)]
-#[serde(tag = "status", rename_all = "snake_case")]
-pub enum FirmwareStatusJson {
-    UpToDate,
-    UpdateAvailable {
-        latest_version: String,
-        changelog: Option<String>,
-    },
-    Unknown,
-    CheckDisabled,
-}
-
-impl From<FirmwareStatusJson> for FirmwareStatusData {
-    fn from(json: FirmwareStatusJson) -> Self {
-        match json {
-            FirmwareStatusJson::UpToDate => FirmwareStatusData::UpToDate,
-            FirmwareStatusJson::UpdateAvailable { latest_version, changelog } => {
+impl From<FirmwareStatus> for FirmwareStatusData {
+    fn from(status: FirmwareStatus) -> Self {
+        match status {
+            FirmwareStatus::UpToDate => FirmwareStatusData::UpToDate,
+            FirmwareStatus::UpdateAvailable { latest_version, changelog } => {
                 FirmwareStatusData::UpdateAvailable { latest_version, changelog }
             }
-            FirmwareStatusJson::Unknown => FirmwareStatusData::Unknown,
-            FirmwareStatusJson::CheckDisabled => FirmwareStatusData::CheckDisabled,
+            FirmwareStatus::Unknown => FirmwareStatusData::Unknown,
+            FirmwareStatus::CheckDisabled => FirmwareStatusData::CheckDisabled,
         }
     }
 }
@@ -188,8 +556,7 @@ pub struct PeriodicSyncTimer {
 impl Default for PeriodicSyncTimer {
     fn default() -> Self {
         Self {
-            // Sync every 5 seconds
-            timer: Timer::from_seconds(5.0, TimerMode::Repeating),
+            timer: Timer::from_seconds(DEVICE_SYNC_SECS, TimerMode::Repeating),
         }
     }
 }
@@ -198,20 +565,49 @@ impl Plugin for NetworkPlugin {
     fn build(&self, app: &mut App) {
         // Initialize daemon config from browser URL
         let daemon_config = DaemonConfig::from_browser();
+        register_access_token(&daemon_config);
+        // The primary daemon's messages share the main queue
+        let messages = PendingMessages::default();
+        let daemons = Daemons::from_browser(&daemon_config, messages.clone());
 
         app.insert_resource(daemon_config)
+            .insert_resource(messages)
+            .insert_resource(daemons)
+            .insert_resource(ReadOnlyMode::from_browser())
+            .insert_resource(LowBandwidthMode::from_browser())
+            .init_resource::<DataUsage>()
+            .init_resource::<PendingSessionRole>()
+            .init_resource::<PendingDaemonVersion>()
+            .init_resource::<PendingKeepouts>()
             .init_resource::<WebSocketConnection>()
-            .init_resource::<PendingMessages>()
             .init_resource::<NetworkInterfaces>()
             .init_resource::<PendingInterfaceData>()
             .init_resource::<HeartbeatState>()
             .init_resource::<PendingHeartbeatData>()
             .init_resource::<PendingFirmwareData>()
             .init_resource::<PendingHcdfExport>()
+            .init_resource::<PendingUrdfExport>()
+            .init_resource::<HcdfExportOptions>()
             .init_resource::<PeriodicSyncTimer>()
+            .init_resource::<PositionSync>()
+            .init_resource::<DeviceLatency>()
+            .init_resource::<PendingLatencyData>()
+            .init_resource::<DeviceImages>()
+            .init_resource::<PendingDeviceImages>()
+            .init_resource::<HcdfRegeneration>()
+            .init_resource::<PendingRegeneration>()
+            .init_resource::<FragmentReload>()
+            .init_resource::<PendingFragmentReload>()
+            .init_resource::<ImportPreview>()
+            .init_resource::<PendingImportPreview>()
+            .init_resource::<DeviceShell>()
+            .init_resource::<PendingShellOutput>()
+            .init_resource::<DeviceConsole>()
+            .init_resource::<PendingConsole>()
             .add_message::<ReconnectEvent>()
-            .add_systems(Startup, (connect_websocket, fetch_initial_devices, fetch_network_interfaces, fetch_heartbeat_state))
-            .add_systems(Update, (process_messages, process_interface_data, process_heartbeat_data, process_firmware_data, handle_reconnect, periodic_device_sync));
+            .add_systems(Startup, (connect_websocket, fetch_initial_devices, fetch_network_interfaces, fetch_heartbeat_state, fetch_session_role, fetch_daemon_version, fetch_keepouts))
+            .add_systems(Update, (sync_daemon_links, process_messages.after(sync_daemon_links), process_interface_data, process_heartbeat_data, process_session_role, process_daemon_version, process_keepouts, process_firmware_data, handle_reconnect, periodic_device_sync, flush_position_sync, poll_device_latency, process_latency_data, poll_device_images, process_device_images, process_regeneration_data, process_fragment_reload, sync_low_bandwidth.after(sync_daemon_links), update_data_usage))
+            .add_systems(Update, (fetch_selected_device, process_import_preview, process_shell_output, process_console));
     }
 }
 
@@ -222,15 +618,83 @@ fn periodic_device_sync(
     mut sync_timer: ResMut<PeriodicSyncTimer>,
     daemon_config: Res<DaemonConfig>,
     pending: Res<PendingMessages>,
+    pending_keepouts: Res<PendingKeepouts>,
+    low_bandwidth: Res<LowBandwidthMode>,
+    daemons: Res<Daemons>,
+    mut connection: ResMut<WebSocketConnection>,
 ) {
     sync_timer.timer.tick(time.delta());
 
     if sync_timer.timer.just_finished() {
         #[cfg(target_arch = "wasm32")]
         {
-            refetch_devices(&daemon_config, &pending);
+            refetch_devices(&daemon_config, &pending, low_bandwidth.enabled);
+            // Daemons that dropped their socket are retried here; the
+            // others keep updating in the meantime
+            for link in &daemons.links {
+                if link.key == PRIMARY_DAEMON {
+                    if link.state() == LinkState::Disconnected {
+                        reconnect_websocket(&daemon_config, &pending, &mut connection, link, low_bandwidth.enabled);
+                    }
+                } else {
+                    refetch_devices(&link.config, &link.messages, low_bandwidth.enabled);
+                    if link.state() == LinkState::Disconnected {
+                        connect_link_websocket(link, low_bandwidth.enabled);
+                    }
+                }
+            }
             tracing::debug!("Periodic device sync triggered");
         }
+        request_keepouts(&daemon_config.http_url, &pending_keepouts);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = (&pending, &low_bandwidth, &daemons, &mut connection);
+    }
+}
+
+/// Open sockets to newly added daemons and drop the devices of removed ones
+fn sync_daemon_links(
+    daemons: Res<Daemons>,
+    mut known: Local<Vec<u32>>,
+    mut registry: ResMut<DeviceRegistry>,
+    low_bandwidth: Res<LowBandwidthMode>,
+) {
+    if !daemons.is_changed() {
+        return;
+    }
+    for link in daemons.links.iter().filter(|l| l.key != PRIMARY_DAEMON) {
+        if known.contains(&link.key) {
+            continue;
+        }
+        known.push(link.key);
+        tracing::info!("Following daemon {} ({})", link.address, link.config.http_url);
+        #[cfg(target_arch = "wasm32")]
+        {
+            connect_link_websocket(link, low_bandwidth.enabled);
+            refetch_devices(&link.config, &link.messages, low_bandwidth.enabled);
+        }
+    }
+    known.retain(|key| {
+        if daemons.get(*key).is_some() {
+            return true;
+        }
+        tracing::info!("No longer following daemon {}", key);
+        #[cfg(target_arch = "wasm32")]
+        LINK_SOCKETS.with(|sockets| {
+            if let Some((ws, _)) = sockets.borrow_mut().remove(key) {
+                ws.set_onclose(None);
+                let _ = ws.close();
+            }
+        });
+        registry.devices.retain(|d| split_id(&d.id).0 != *key);
+        false
+    });
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = &low_bandwidth;
     }
 }
 
@@ -241,16 +705,22 @@ fn handle_reconnect(
     mut connection: ResMut<WebSocketConnection>,
     pending: Res<PendingMessages>,
     pending_interfaces: Res<PendingInterfaceData>,
+    pending_session: Res<PendingSessionRole>,
+    pending_version: Res<PendingDaemonVersion>,
+    pending_keepouts: Res<PendingKeepouts>,
+    low_bandwidth: Res<LowBandwidthMode>,
     mut registry: ResMut<crate::app::DeviceRegistry>,
+    mut daemons: ResMut<Daemons>,
 ) {
     for event in events.read() {
         tracing::info!("Reconnecting to daemon: {}", event.daemon_address);
 
         // Update daemon config
         *daemon_config = DaemonConfig::from_daemon_address(&event.daemon_address);
+        daemons.set_primary(&event.daemon_address, daemon_config.clone());
 
-        // Clear existing state
-        registry.devices.clear();
+        // Clear the primary daemon's state; other daemons are unaffected
+        registry.devices.retain(|d| split_id(&d.id).0 != PRIMARY_DAEMON);
         registry.connected = false;
         connection.connected = false;
 
@@ -267,10 +737,18 @@ fn handle_reconnect(
         // Reconnect WebSocket and fetch data
         #[cfg(target_arch = "wasm32")]
         {
-            reconnect_websocket(&daemon_config, &pending, &mut connection);
-            refetch_devices(&daemon_config, &pending);
+            reconnect_websocket(&daemon_config, &pending, &mut connection, &daemons.links[0], low_bandwidth.enabled);
+            refetch_devices(&daemon_config, &pending, low_bandwidth.enabled);
             refetch_interfaces(&daemon_config, &pending_interfaces);
         }
+        request_session_role(&daemon_config.http_url, &pending_session);
+        request_daemon_version(&daemon_config.http_url, &pending_version);
+        request_keepouts(&daemon_config.http_url, &pending_keepouts);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = &low_bandwidth;
     }
 }
 
@@ -279,114 +757,388 @@ fn reconnect_websocket(
     daemon_config: &DaemonConfig,
     pending: &PendingMessages,
     connection: &mut WebSocketConnection,
+    link: &DaemonLink,
+    lite: bool,
 ) {
     use wasm_bindgen::prelude::*;
     use web_sys::{MessageEvent, WebSocket};
 
-    let ws_url = daemon_config.ws_url.clone();
+    let ws_url = subscription_ws_url(daemon_config, lite);
     tracing::info!("Reconnecting WebSocket to: {}", ws_url);
 
+    // A replaced socket must not report the new one closed
+    SOCKET.with(|socket| {
+        if let Some(old) = socket.borrow_mut().take() {
+            old.set_onclose(None);
+            let _ = old.close();
+        }
+    });
+    link.set_state(LinkState::Connecting);
+
     match WebSocket::new(&ws_url) {
         Ok(ws) => {
             ws.set_binary_type(web_sys::BinaryType::Arraybuffer);
 
+            let open_link = link.clone();
             let onopen = Closure::wrap(Box::new(move |_| {
                 tracing::info!("WebSocket reconnected");
+                open_link.set_state(LinkState::Connected);
+                send_current_subscription();
             }) as Box<dyn FnMut(JsValue)>);
             ws.set_onopen(Some(onopen.as_ref().unchecked_ref()));
             onopen.forget();
+            set_onclose(&ws, link);
 
             let pending_clone = pending.0.clone();
+            let records = pending.1.clone();
             let onmessage = Closure::wrap(Box::new(move |e: MessageEvent| {
                 if let Ok(text) = e.data().dyn_into::<js_sys::JsString>() {
                     let text: String = text.into();
-                    if let Ok(msg) = serde_json::from_str::<WsMessage>(&text) {
-                        if let Ok(mut queue) = pending_clone.lock() {
-                            queue.push(msg);
-                        }
+                    record_received(text.len());
+                    let messages = match records.lock() {
+                        Ok(mut records) => parse_ws_text(&text, &mut records),
+                        Err(_) => return,
+                    };
+                    if let Ok(mut queue) = pending_clone.lock() {
+                        queue.extend(messages);
                     }
                 }
             }) as Box<dyn FnMut(MessageEvent)>);
             ws.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
             onmessage.forget();
 
+            SOCKET.with(|socket| *socket.borrow_mut() = Some(ws));
             connection.connected = true;
         }
         Err(e) => {
             tracing::error!("Failed to reconnect WebSocket: {:?}", e);
+            link.set_state(LinkState::Disconnected);
         }
     }
 }
 
+/// Mark a daemon disconnected when its socket closes
 #[cfg(target_arch = "wasm32")]
-fn refetch_devices(daemon_config: &DaemonConfig, pending: &PendingMessages) {
-    use wasm_bindgen_futures::spawn_local;
+fn set_onclose(ws: &web_sys::WebSocket, link: &DaemonLink) {
+    use wasm_bindgen::prelude::*;
 
-    let pending_clone = pending.0.clone();
-    let base_url = daemon_config.http_url.clone();
+    let link = link.clone();
+    let onclose = Closure::wrap(Box::new(move |_| {
+        tracing::warn!("WebSocket to {} closed", link.address);
+        link.set_state(LinkState::Disconnected);
+    }) as Box<dyn FnMut(JsValue)>);
+    ws.set_onclose(Some(onclose.as_ref().unchecked_ref()));
+    onclose.forget();
+}
 
-    spawn_local(async move {
-        let url = format!("{}/api/devices", base_url);
-        tracing::info!("Refetching devices from: {}", url);
+/// Open the WebSocket to a daemon other than the primary
+#[cfg(target_arch = "wasm32")]
+fn connect_link_websocket(link: &DaemonLink, lite: bool) {
+    use wasm_bindgen::prelude::*;
+    use web_sys::{MessageEvent, WebSocket};
 
-        match gloo_net::http::Request::get(&url).send().await {
-            Ok(response) => {
-                if let Ok(text) = response.text().await {
-                    if let Ok(devices) = serde_json::from_str::<Vec<DeviceJson>>(&text) {
-                        if let Ok(mut queue) = pending_clone.lock() {
-                            for device in devices {
-                                queue.push(WsMessage::DeviceDiscovered(device));
-                            }
-                        }
+    let ws_url = subscription_ws_url(&link.config, lite);
+    tracing::info!("Connecting WebSocket to {}: {}", link.address, ws_url);
+    link.set_state(LinkState::Connecting);
+
+    match WebSocket::new(&ws_url) {
+        Ok(ws) => {
+            ws.set_binary_type(web_sys::BinaryType::Arraybuffer);
+
+            let open_link = link.clone();
+            let onopen = Closure::wrap(Box::new(move |_| {
+                tracing::info!("WebSocket to {} connected", open_link.address);
+                open_link.set_state(LinkState::Connected);
+                send_link_subscription(open_link.key);
+            }) as Box<dyn FnMut(JsValue)>);
+            ws.set_onopen(Some(onopen.as_ref().unchecked_ref()));
+            onopen.forget();
+            set_onclose(&ws, link);
+
+            let queue = link.messages.0.clone();
+            let records = link.messages.1.clone();
+            let onmessage = Closure::wrap(Box::new(move |e: MessageEvent| {
+                if let Ok(text) = e.data().dyn_into::<js_sys::JsString>() {
+                    let text: String = text.into();
+                    record_received(text.len());
+                    let messages = match records.lock() {
+                        Ok(mut records) => parse_ws_text(&text, &mut records),
+                        Err(_) => return,
+                    };
+                    if let Ok(mut queue) = queue.lock() {
+                        queue.extend(messages);
                     }
                 }
-            }
-            Err(e) => {
-                tracing::error!("Failed to refetch devices: {:?}", e);
-            }
+            }) as Box<dyn FnMut(MessageEvent)>);
+            ws.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
+            onmessage.forget();
+
+            LINK_SOCKETS.with(|sockets| {
+                let mut sockets = sockets.borrow_mut();
+                // Keep the subscription across reconnects
+                let subscription = sockets.remove(&link.key).and_then(|(old, sub)| {
+                    old.set_onclose(None);
+                    let _ = old.close();
+                    sub
+                });
+                sockets.insert(link.key, (ws, subscription));
+            });
         }
-    });
+        Err(e) => {
+            tracing::error!("Failed to connect WebSocket to {}: {:?}", link.address, e);
+            link.set_state(LinkState::Disconnected);
+        }
+    }
 }
 
 #[cfg(target_arch = "wasm32")]
-fn refetch_interfaces(daemon_config: &DaemonConfig, pending: &PendingInterfaceData) {
+fn refetch_devices(daemon_config: &DaemonConfig, pending: &PendingMessages, lite: bool) {
     use wasm_bindgen_futures::spawn_local;
 
     let pending_clone = pending.0.clone();
+    let records = pending.1.clone();
     let base_url = daemon_config.http_url.clone();
 
     spawn_local(async move {
-        let url = format!("{}/api/interfaces", base_url);
-        tracing::info!("Refetching interfaces from: {}", url);
+        // Summaries leave out visuals, frames, ports and sensors
+        let url = if lite {
+            format!("{}/api/devices?lite=true", base_url)
+        } else {
+            format!("{}/api/devices", base_url)
+        };
+        tracing::info!("Refetching devices from: {}", url);
 
-        match gloo_net::http::Request::get(&url).send().await {
+        match DaemonRequest::get(&url).send().await {
             Ok(response) => {
                 if let Ok(text) = response.text().await {
-                    if let Ok(interfaces) = serde_json::from_str::<Vec<NetworkInterfaceInfo>>(&text) {
-                        if let Ok(mut data) = pending_clone.lock() {
-                            *data = Some(interfaces);
+                    record_received(text.len());
+                    if let Ok(devices) = serde_json::from_str::<Vec<Record>>(&text) {
+                        let messages = match records.lock() {
+                            Ok(mut records) => refetched_changes(&mut records, devices),
+                            Err(_) => return,
+                        };
+                        if let Ok(mut queue) = pending_clone.lock() {
+                            queue.extend(messages);
                         }
                     }
                 }
             }
             Err(e) => {
-                tracing::error!("Failed to refetch interfaces: {:?}", e);
+                tracing::error!("Failed to refetch devices: {:?}", e);
             }
         }
     });
 }
 
-/// Pending interface data from async fetch
-#[derive(Resource, Default)]
-pub struct PendingInterfaceData(pub Arc<Mutex<Option<Vec<NetworkInterfaceInfo>>>>);
+/// Compare refetched devices with the records the WebSocket keeps, turning
+/// new and changed ones into messages and devices missing from the refetch
+/// into removals; unchanged devices leave the registry alone
+///
+/// A summary (lite refetch) keeps the visuals, frames, ports and sensors of
+/// the earlier record, as the registry does.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+fn refetched_changes(records: &mut DeviceRecords, devices: Vec<Record>) -> Vec<WsMessage> {
+    let mut messages = Vec::new();
+    let mut refetched = HashSet::new();
+    for record in devices {
+        let Some(id) = record.get("id").and_then(|id| id.as_str()).map(str::to_string) else {
+            continue;
+        };
+        refetched.insert(id.clone());
+        let fields = match records.update(&id, record) {
+            RecordChange::Unchanged => continue,
+            RecordChange::Added => records.get(&id).map(|r| r.keys().cloned().collect()).unwrap_or_default(),
+            RecordChange::Changed(changed) => changed.into_keys().collect(),
+        };
+        if let Some(change) = records.get(&id).and_then(|record| DeviceChange::new(record, fields)) {
+            messages.push(WsMessage::DeviceChanged(change));
+        }
+    }
+    for id in records.ids().filter(|id| !refetched.contains(*id)).map(str::to_string).collect::<Vec<_>>() {
+        records.remove(&id);
+        messages.push(WsMessage::DeviceRemoved { id });
+    }
+    messages
+}
 
-/// Pending heartbeat data from async fetch
-#[derive(Resource, Default)]
-pub struct PendingHeartbeatData(pub Arc<Mutex<Option<bool>>>);
+/// Fetch the selected device on its own when the selection changes, so the
+/// details panel is current without refetching the whole list (and has the
+/// full record in low-bandwidth mode)
+fn fetch_selected_device(selected: Res<SelectedDevice>, daemons: Res<Daemons>) {
+    if !selected.is_changed() {
+        return;
+    }
+    let Some(id) = selected.0.as_deref() else {
+        return;
+    };
+    let (key, device_id) = split_id(id);
+    if let Some(link) = daemons.get(key) {
+        fetch_device(&link.config.http_url, device_id, &link.messages);
+    }
+}
 
-/// Shared message queue between WebSocket callback and Bevy
-#[derive(Resource, Default, Clone)]
-pub struct PendingMessages(pub Arc<Mutex<Vec<WsMessage>>>);
+/// Fetch one device and queue it as an update
+fn fetch_device(base_url: &str, device_id: &str, pending: &PendingMessages) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        use wasm_bindgen_futures::spawn_local;
+
+        let pending_clone = pending.0.clone();
+        let id: String = js_sys::encode_uri_component(device_id).into();
+        let url = format!("{}/api/devices/{}", base_url, id);
+
+        spawn_local(async move {
+            match DaemonRequest::get(&url).send().await {
+                Ok(response) if response.ok() => {
+                    if let Ok(text) = response.text().await {
+                        record_received(text.len());
+                        if let Ok(device) = serde_json::from_str::<DeviceJson>(&text) {
+                            if let Ok(mut queue) = pending_clone.lock() {
+                                queue.push(WsMessage::DeviceUpdated(device));
+                            }
+                        }
+                    }
+                }
+                Ok(response) => {
+                    tracing::debug!("Device fetch returned {}", response.status());
+                }
+                Err(e) => {
+                    tracing::error!("Failed to fetch device: {:?}", e);
+                }
+            }
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = (base_url, device_id, pending);
+    }
+}
+
+/// Load the primary daemon's current devices again, e.g. when leaving the
+/// History view
+pub fn reload_devices(daemon_config: &DaemonConfig, pending: &PendingMessages, lite: bool) {
+    // The registry held another state meanwhile; send every device again
+    if let Ok(mut records) = pending.1.lock() {
+        records.clear();
+    }
+    #[cfg(target_arch = "wasm32")]
+    refetch_devices(daemon_config, pending, lite);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = (daemon_config, pending, lite);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn refetch_interfaces(daemon_config: &DaemonConfig, pending: &PendingInterfaceData) {
+    use wasm_bindgen_futures::spawn_local;
+
+    let pending_clone = pending.0.clone();
+    let base_url = daemon_config.http_url.clone();
+
+    spawn_local(async move {
+        let url = format!("{}/api/interfaces", base_url);
+        tracing::info!("Refetching interfaces from: {}", url);
+
+        match DaemonRequest::get(&url).send().await {
+            Ok(response) => {
+                if let Ok(text) = response.text().await {
+                    if let Ok(interfaces) = serde_json::from_str::<Vec<NetworkInterfaceInfo>>(&text) {
+                        if let Ok(mut data) = pending_clone.lock() {
+                            *data = Some(interfaces);
+                        }
+                    }
+                }
+            }
+            Err(e) => {
+                tracing::error!("Failed to refetch interfaces: {:?}", e);
+            }
+        }
+    });
+}
+
+/// Pending interface data from async fetch
+#[derive(Resource, Default)]
+pub struct PendingInterfaceData(pub Arc<Mutex<Option<Vec<NetworkInterfaceInfo>>>>);
+
+/// Pending heartbeat data from async fetch
+#[derive(Resource, Default)]
+pub struct PendingHeartbeatData(pub Arc<Mutex<Option<bool>>>);
+
+/// Pending session read-only flag from async fetch
+#[derive(Resource, Default)]
+pub struct PendingSessionRole(pub Arc<Mutex<Option<bool>>>);
+
+/// Pending keep-out volumes and violations from async fetch
+#[derive(Resource, Default)]
+pub struct PendingKeepouts(pub Arc<Mutex<Option<KeepoutScene>>>);
+
+/// Pending daemon version from async fetch; the inner None means the
+/// daemon has no version endpoint
+#[derive(Resource, Default)]
+pub struct PendingDaemonVersion(pub Arc<Mutex<Option<Option<DaemonVersion>>>>);
+
+/// Daemon build and protocol information from GET /api/version
+#[derive(Debug, Clone, Deserialize)]
+pub struct DaemonVersion {
+    pub version: String,
+    #[serde(default)]
+    pub git_hash: String,
+    #[serde(default)]
+    pub features: Vec<String>,
+    #[serde(default)]
+    pub hcdf_versions: Vec<String>,
+    pub ws_protocol: u32,
+}
+
+/// How the daemon's protocol compares to the one this frontend was built for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProtocolCompatibility {
+    Compatible,
+    /// The frontend expects messages the daemon may not send; `daemon` is
+    /// None when the daemon predates the version endpoint
+    FrontendNewer { frontend: u32, daemon: Option<u32> },
+    /// The daemon is newer; everything this frontend uses still works
+    DaemonNewer { frontend: u32, daemon: u32 },
+}
+
+impl ProtocolCompatibility {
+    /// Compare a frontend protocol version with what the daemon reported
+    pub fn check(frontend: u32, daemon: Option<&DaemonVersion>) -> Self {
+        match daemon.map(|d| d.ws_protocol) {
+            None => Self::FrontendNewer { frontend, daemon: None },
+            Some(daemon) if daemon < frontend => Self::FrontendNewer { frontend, daemon: Some(daemon) },
+            Some(daemon) if daemon > frontend => Self::DaemonNewer { frontend, daemon },
+            Some(_) => Self::Compatible,
+        }
+    }
+
+    /// Warning to show the user, if the mismatch can break the UI
+    pub fn warning(&self) -> Option<String> {
+        match self {
+            Self::FrontendNewer { frontend, daemon: Some(daemon) } => Some(format!(
+                "Daemon speaks protocol v{} but this UI expects v{}; update the daemon for full functionality",
+                daemon, frontend
+            )),
+            Self::FrontendNewer { frontend, daemon: None } => Some(format!(
+                "Daemon does not report a protocol version (this UI expects v{}); update the daemon for full functionality",
+                frontend
+            )),
+            Self::Compatible | Self::DaemonNewer { .. } => None,
+        }
+    }
+}
+
+/// Shared message queue between WebSocket callback and Bevy
+#[derive(Resource, Default, Clone)]
+pub struct PendingMessages(
+    pub Arc<Mutex<Vec<WsMessage>>>,
+    /// Every device as last received from the daemon, kept by the WebSocket
+    /// and corrected by each HTTP refetch
+    pub Arc<Mutex<DeviceRecords>>,
+);
 
 /// WebSocket connection state
 #[derive(Resource, Default)]
@@ -400,6 +1152,128 @@ pub struct ReconnectEvent {
     pub daemon_address: String,
 }
 
+/// Device deltas from a daemon speaking protocol 2
+#[derive(Deserialize)]
+#[serde(tag = "type", content = "data")]
+enum DeltaMessage {
+    #[serde(rename = "full_sync")]
+    FullSync(FullSync),
+    #[serde(rename = "device_added")]
+    DeviceAdded(Record),
+    #[serde(rename = "device_delta")]
+    DeviceDelta(DeviceDelta),
+    #[serde(rename = "status_changed")]
+    StatusChanged(StatusChange),
+}
+
+/// A device's record after a delta, and the fields the delta changed
+#[derive(Debug, Clone)]
+pub struct DeviceChange {
+    pub device: DeviceJson,
+    pub changed: Vec<String>,
+}
+
+impl DeviceChange {
+    fn new(record: &Record, changed: Vec<String>) -> Option<Self> {
+        match serde_json::from_value(serde_json::Value::Object(record.clone())) {
+            Ok(device) => Some(Self { device, changed }),
+            Err(e) => {
+                tracing::warn!("Invalid device record: {}", e);
+                None
+            }
+        }
+    }
+}
+
+/// Apply device deltas to the connection's `records`, turning them into
+/// messages carrying the whole record; None for any other message
+fn apply_delta(value: &serde_json::Value, records: &mut DeviceRecords) -> Option<Vec<WsMessage>> {
+    let Ok(delta) = DeltaMessage::deserialize(value) else {
+        return None;
+    };
+    let (id, changed) = match delta {
+        DeltaMessage::FullSync(sync) => {
+            records.clear();
+            let devices = match sync.devices.expand() {
+                Ok(devices) => devices,
+                Err(e) => {
+                    tracing::warn!("Invalid full sync: {}", e);
+                    return Some(Vec::new());
+                }
+            };
+            let changes = devices
+                .into_iter()
+                .filter_map(|device| match device {
+                    serde_json::Value::Object(record) => Some(record),
+                    _ => None,
+                })
+                .filter_map(|record| {
+                    let change = DeviceChange::new(&record, record.keys().cloned().collect())?;
+                    records.insert(&change.device.id.0, record);
+                    Some(change)
+                })
+                .collect();
+            return Some(vec![WsMessage::DeviceSync(changes)]);
+        }
+        DeltaMessage::DeviceAdded(record) => {
+            let Some(id) = record.get("id").and_then(|id| id.as_str()).map(str::to_string) else {
+                return Some(Vec::new());
+            };
+            // Replaces any earlier record; every field counts as changed
+            records.insert(&id, Record::new());
+            (id, record)
+        }
+        DeltaMessage::DeviceDelta(DeviceDelta { id, changed_fields }) => (id, changed_fields),
+        DeltaMessage::StatusChanged(StatusChange { id, status }) => {
+            (id, Record::from_iter([("status".to_string(), status.into())]))
+        }
+    };
+    let fields = changed.keys().cloned().collect();
+    let Some(record) = records.apply(&id, changed) else {
+        // Deltas always follow the device's full record; the next full sync catches up
+        tracing::warn!("Delta for unknown device {}", id);
+        return Some(Vec::new());
+    };
+    Some(DeviceChange::new(record, fields).map(WsMessage::DeviceChanged).into_iter().collect())
+}
+
+/// Messages in one WebSocket text frame: a compact device snapshot expands
+/// into one message per device, device deltas are applied to `records`,
+/// anything unparseable turns into no messages
+fn parse_ws_text(text: &str, records: &mut DeviceRecords) -> Vec<WsMessage> {
+    let Ok(mut value) = serde_json::from_str::<serde_json::Value>(text) else {
+        return Vec::new();
+    };
+    if let Some(messages) = apply_delta(&value, records) {
+        return messages;
+    }
+    match value["type"].as_str() {
+        Some("device_removed" | "device_archived") => {
+            if let Some(id) = value["data"]["id"].as_str() {
+                records.remove(id);
+            }
+        }
+        Some("device_unarchived") => {
+            if let (Some(id), serde_json::Value::Object(record)) = (value["data"]["id"].as_str(), &value["data"]) {
+                records.insert(id, record.clone());
+            }
+        }
+        _ => {}
+    }
+    let values = if value["type"] == SNAPSHOT_MESSAGE_TYPE {
+        match serde_json::from_value::<CompactSnapshot>(value["data"].take()).map_err(|e| e.to_string()).and_then(CompactSnapshot::expand) {
+            Ok(values) => values,
+            Err(e) => {
+                tracing::warn!("Invalid device snapshot: {}", e);
+                return Vec::new();
+            }
+        }
+    } else {
+        vec![value]
+    };
+    values.into_iter().filter_map(|v| serde_json::from_value(v).ok()).collect()
+}
+
 /// Messages from the server
 #[derive(Debug, Clone, Deserialize)]
 #[serde(tag = "type", content = "data")]
@@ -412,161 +1286,123 @@ pub enum WsMessage {
     DeviceUpdated(DeviceJson),
     #[serde(rename = "device_removed")]
     DeviceRemoved { id: String },
+    #[serde(rename = "device_archived")]
+    DeviceArchived { id: String },
+    #[serde(rename = "device_unarchived")]
+    DeviceUnarchived(DeviceJson),
+    /// Device without visuals, frames, ports or sensors (lite subscription)
+    #[serde(rename = "device_summary")]
+    DeviceSummary(DeviceJson),
+    /// A device was added or changed (applied from a delta by `parse_ws_text`)
+    #[serde(skip)]
+    DeviceChanged(DeviceChange),
+    /// Every device of one daemon (applied from a full sync by `parse_ws_text`)
+    #[serde(skip)]
+    DeviceSync(Vec<DeviceChange>),
     #[serde(rename = "scan_started")]
-    ScanStarted,
+    ScanStarted {
+        #[serde(default)]
+        total_hosts: usize,
+    },
+    /// Candidate hosts probed for MCUmgr so far
+    #[serde(rename = "scan_progress")]
+    ScanProgress { done: usize, total: usize },
     #[serde(rename = "scan_completed")]
     ScanCompleted {
-        #[allow(dead_code)]
         found: usize,
         #[allow(dead_code)]
         total: usize,
     },
-    #[serde(rename = "ota_progress")]
-    OtaProgress {
-        device_id: String,
-        state: OtaUpdateState,
+    /// An LLDP neighbor appeared or changed port
+    #[serde(rename = "lldp_neighbor_changed")]
+    LldpNeighborChanged {
+        #[allow(dead_code)]
+        chassis_id: String,
     },
+    /// An LLDP neighbor's TTL ran out
+    #[serde(rename = "lldp_neighbor_expired")]
+    LldpNeighborExpired {
+        #[allow(dead_code)]
+        chassis_id: String,
+    },
+    /// An offline device's heartbeat check failed; the daemon retries it in
+    /// `retry_in_ms`
+    #[serde(rename = "heartbeat_backoff")]
+    HeartbeatBackoff {
+        id: String,
+        #[allow(dead_code)]
+        failures: u32,
+        retry_in_ms: u64,
+    },
+    /// The daemon reloaded its fragment database; the listed devices were
+    /// matched again and their models may have new contents
+    #[serde(rename = "fragments_reloaded")]
+    FragmentsReloaded {
+        devices: Vec<String>,
+        #[serde(default)]
+        failed: Vec<String>,
+    },
+    #[serde(rename = "ota_progress")]
+    OtaProgress(OtaEvent),
+    /// A finding in the daemon's attention inbox changed
+    #[serde(rename = "attention")]
+    Attention(crate::attention::Finding),
     #[serde(rename = "pong")]
     Pong,
 }
 
-#[derive(Debug, Clone, Deserialize)]
-pub struct DeviceJson {
-    pub id: IdJson,
-    pub name: String,
-    pub status: String,
-    pub discovery: DiscoveryJson,
-    pub info: InfoJson,
-    pub firmware: FirmwareJson,
-    pub model_path: Option<String>,
-    pub pose: Option<[f64; 6]>,
-    /// Composite visuals with individual poses
-    #[serde(default)]
-    pub visuals: Vec<VisualJson>,
-    /// Reference frames for this device
-    #[serde(default)]
-    pub frames: Vec<FrameJson>,
-    /// Ports on this device
-    #[serde(default)]
-    pub ports: Vec<PortJson>,
-    /// Sensors on this device
-    #[serde(default)]
-    pub sensors: Vec<SensorJson>,
-}
-
-#[derive(Debug, Clone, Deserialize)]
-pub struct IdJson(pub String);
-
-#[derive(Debug, Clone, Deserialize)]
-pub struct DiscoveryJson {
-    pub ip: String,
-    #[allow(dead_code)]
-    pub port: u16,
-    pub switch_port: Option<u8>,
-    pub last_seen: Option<String>,
-}
-
-#[derive(Debug, Clone, Deserialize)]
-pub struct InfoJson {
-    pub board: Option<String>,
-}
-
-#[derive(Debug, Clone, Deserialize)]
-pub struct FirmwareJson {
-    pub version: Option<String>,
-}
-
-/// Visual element JSON from the backend
-#[derive(Debug, Clone, Deserialize)]
-pub struct VisualJson {
-    pub name: String,
-    #[serde(default)]
-    pub toggle: Option<String>,
-    #[serde(default)]
-    pub pose: Option<[f64; 6]>,
-    #[serde(default)]
-    pub model_path: Option<String>,
-    #[serde(default)]
-    pub model_sha: Option<String>,
-}
-
-/// Reference frame JSON from the backend
-#[derive(Debug, Clone, Deserialize)]
-pub struct FrameJson {
-    pub name: String,
-    #[serde(default)]
-    pub description: Option<String>,
-    #[serde(default)]
-    pub pose: Option<[f64; 6]>,
-}
-
-/// Port JSON from the backend
-#[derive(Debug, Clone, Deserialize)]
-pub struct PortJson {
-    pub name: String,
-    pub port_type: String,
-    #[serde(default)]
-    pub pose: Option<[f64; 6]>,
-    #[serde(default)]
-    pub geometry: Vec<GeometryJson>,
-    /// Reference to visual containing the mesh (e.g., "board")
-    #[serde(default)]
-    pub visual_name: Option<String>,
-    /// GLTF mesh node name within the visual (e.g., "port_eth0")
-    #[serde(default)]
-    pub mesh_name: Option<String>,
-}
-
-/// Axis alignment JSON from the backend
-#[derive(Debug, Clone, Deserialize)]
-pub struct AxisAlignJson {
-    pub x: String,
-    pub y: String,
-    pub z: String,
+impl WsMessage {
+    /// Move a message from a daemon into the shared registry: namespace its
+    /// device IDs and shift poses into the daemon's region
+    fn localize(self, key: u32, offset: Vec3) -> Self {
+        if key == PRIMARY_DAEMON {
+            return self;
+        }
+        match self {
+            Self::DeviceDiscovered(device) => Self::DeviceDiscovered(localize_device(device, key, offset)),
+            Self::DeviceUpdated(device) => Self::DeviceUpdated(localize_device(device, key, offset)),
+            Self::DeviceUnarchived(device) => Self::DeviceUnarchived(localize_device(device, key, offset)),
+            Self::DeviceSummary(device) => Self::DeviceSummary(localize_device(device, key, offset)),
+            Self::DeviceChanged(change) => Self::DeviceChanged(change.localize(key, offset)),
+            Self::DeviceSync(changes) => {
+                Self::DeviceSync(changes.into_iter().map(|change| change.localize(key, offset)).collect())
+            }
+            Self::DeviceOffline { id } => Self::DeviceOffline { id: namespaced_id(key, &id) },
+            Self::DeviceRemoved { id } => Self::DeviceRemoved { id: namespaced_id(key, &id) },
+            Self::DeviceArchived { id } => Self::DeviceArchived { id: namespaced_id(key, &id) },
+            Self::HeartbeatBackoff { id, failures, retry_in_ms } => {
+                Self::HeartbeatBackoff { id: namespaced_id(key, &id), failures, retry_in_ms }
+            }
+            Self::OtaProgress(OtaEvent { device_id, state }) => {
+                Self::OtaProgress(OtaEvent { device_id: namespaced_id(key, &device_id), state })
+            }
+            Self::FragmentsReloaded { devices, failed } => Self::FragmentsReloaded {
+                devices: devices.iter().map(|id| namespaced_id(key, id)).collect(),
+                failed,
+            },
+            other => other,
+        }
+    }
 }
 
-/// Geometry JSON from the backend (tagged enum)
-#[derive(Debug, Clone, Deserialize)]
-#[serde(tag = "type", rename_all = "snake_case")]
-pub enum GeometryJson {
-    #[serde(rename = "box")]
-    Box { size: [f64; 3] },
-    Cylinder { radius: f64, length: f64 },
-    Sphere { radius: f64 },
-    Cone { radius: f64, length: f64 },
-    Frustum { near: f64, far: f64, hfov: f64, vfov: f64 },
-    ConicalFrustum { near: f64, far: f64, fov: f64 },
-    PyramidalFrustum { near: f64, far: f64, hfov: f64, vfov: f64 },
-}
-
-/// FOV JSON from the backend
-#[derive(Debug, Clone, Deserialize)]
-pub struct FovJson {
-    pub name: String,
-    #[serde(default)]
-    pub color: Option<[f32; 3]>,
-    #[serde(default)]
-    pub pose: Option<[f64; 6]>,
-    #[serde(default)]
-    pub geometry: Option<GeometryJson>,
+impl DeviceChange {
+    fn localize(self, key: u32, offset: Vec3) -> Self {
+        Self { device: localize_device(self.device, key, offset), changed: self.changed }
+    }
 }
 
-/// Sensor JSON from the backend
-#[derive(Debug, Clone, Deserialize)]
-pub struct SensorJson {
-    pub name: String,
-    pub category: String,
-    pub sensor_type: String,
-    #[serde(default)]
-    pub driver: Option<String>,
-    #[serde(default)]
-    pub pose: Option<[f64; 6]>,
-    #[serde(default)]
-    pub axis_align: Option<AxisAlignJson>,
-    #[serde(default)]
-    pub geometry: Option<GeometryJson>,
-    #[serde(default)]
-    pub fovs: Vec<FovJson>,
+/// Namespace a daemon's device ID and shift its pose into the daemon's region
+fn localize_device(mut device: DeviceJson, key: u32, offset: Vec3) -> DeviceJson {
+    device.id.0 = namespaced_id(key, &device.id.0);
+    if let Some(base) = &mut device.constraint.stack_on {
+        *base = namespaced_id(key, base);
+    }
+    if let Some(pose) = &mut device.pose {
+        for (value, shift) in pose.iter_mut().zip([offset.x, offset.y, offset.z]) {
+            *value += shift as f64;
+        }
+    }
+    device
 }
 
 /// Convert GeometryJson to GeometryData
@@ -598,7 +1434,11 @@ impl From<DeviceJson> for DeviceData {
             id: json.id.0,
             name: json.name,
             board: json.info.board,
-            ip: json.discovery.ip,
+            // Link-local IPv6 addresses are only usable with their zone
+            ip: match json.discovery.scope_id {
+                Some(scope) if scope != 0 => format!("{}%{}", json.discovery.ip, scope),
+                _ => json.discovery.ip,
+            },
             port: json.discovery.switch_port,
             status: match json.status.as_str() {
                 "online" => DeviceStatus::Online,
@@ -608,10 +1448,14 @@ impl From<DeviceJson> for DeviceData {
             version: json.firmware.version,
             position: json.pose.map(|p| [p[0], p[1], p[2]]),
             orientation: json.pose.map(|p| [p[3], p[4], p[5]]),
+            pose_provisional: json.pose_provisional,
+            keep_above_ground: json.constraint.keep_above_ground,
+            stack_on: json.constraint.stack_on,
             model_path: json.model_path,
             visuals: json.visuals.into_iter().map(|v| VisualData {
                 name: v.name,
                 toggle: v.toggle,
+                default_hidden: v.default_hidden,
                 pose: v.pose,
                 model_path: v.model_path,
                 model_sha: v.model_sha,
@@ -620,6 +1464,7 @@ impl From<DeviceJson> for DeviceData {
                 name: f.name,
                 description: f.description,
                 pose: f.pose,
+                parent: f.parent,
             }).collect(),
             ports: json.ports.into_iter().map(|p| PortData {
                 name: p.name,
@@ -628,12 +1473,15 @@ impl From<DeviceJson> for DeviceData {
                 geometry: p.geometry.into_iter().map(convert_geometry).collect(),
                 visual_name: p.visual_name,
                 mesh_name: p.mesh_name,
+                original_pose: p.original_pose,
             }).collect(),
             sensors: json.sensors.into_iter().map(|s| SensorData {
                 name: s.name,
                 category: s.category,
                 sensor_type: s.sensor_type,
                 driver: s.driver,
+                driver_label: s.driver_label,
+                datasheet: s.datasheet,
                 pose: s.pose,
                 axis_align: s.axis_align.map(|a| AxisAlignData {
                     x: a.x,
@@ -644,6 +1492,8 @@ impl From<DeviceJson> for DeviceData {
                 fovs: s.fovs.into_iter().map(convert_fov).collect(),
             }).collect(),
             last_seen: json.discovery.last_seen,
+            heartbeat_method: json.discovery.heartbeat_method,
+            can: json.discovery.can.map(|can| (can.bus, can.node_id)),
         }
     }
 }
@@ -652,6 +1502,8 @@ fn connect_websocket(
     mut connection: ResMut<WebSocketConnection>,
     pending: Res<PendingMessages>,
     daemon_config: Res<DaemonConfig>,
+    low_bandwidth: Res<LowBandwidthMode>,
+    daemons: Res<Daemons>,
 ) {
     // In WASM, we use web_sys WebSocket
     #[cfg(target_arch = "wasm32")]
@@ -659,45 +1511,57 @@ fn connect_websocket(
         use wasm_bindgen::prelude::*;
         use web_sys::{MessageEvent, WebSocket};
 
-        let ws_url = daemon_config.ws_url.clone();
+        let ws_url = subscription_ws_url(&daemon_config, low_bandwidth.enabled);
         tracing::info!("Connecting to WebSocket: {}", ws_url);
 
         match WebSocket::new(&ws_url) {
             Ok(ws) => {
                 ws.set_binary_type(web_sys::BinaryType::Arraybuffer);
 
+                let link = daemons.links[0].clone();
                 let onopen = Closure::wrap(Box::new(move |_| {
                     tracing::info!("WebSocket connected");
+                    link.set_state(LinkState::Connected);
+                    send_current_subscription();
                 }) as Box<dyn FnMut(JsValue)>);
                 ws.set_onopen(Some(onopen.as_ref().unchecked_ref()));
                 onopen.forget();
+                set_onclose(&ws, &daemons.links[0]);
 
                 // Clone pending for the callback
                 let pending_clone = pending.0.clone();
+                // Device records this connection's deltas apply to
+                let records = pending.1.clone();
                 let onmessage = Closure::wrap(Box::new(move |e: MessageEvent| {
                     if let Ok(text) = e.data().dyn_into::<js_sys::JsString>() {
                         let text: String = text.into();
+                        record_received(text.len());
                         tracing::debug!("WS message: {}", text);
-                        if let Ok(msg) = serde_json::from_str::<WsMessage>(&text) {
-                            if let Ok(mut queue) = pending_clone.lock() {
-                                queue.push(msg);
-                            }
+                        let messages = match records.lock() {
+                            Ok(mut records) => parse_ws_text(&text, &mut records),
+                            Err(_) => return,
+                        };
+                        if let Ok(mut queue) = pending_clone.lock() {
+                            queue.extend(messages);
                         }
                     }
                 }) as Box<dyn FnMut(MessageEvent)>);
                 ws.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
                 onmessage.forget();
 
+                SOCKET.with(|socket| *socket.borrow_mut() = Some(ws));
                 connection.connected = true;
             }
             Err(e) => {
                 tracing::error!("Failed to create WebSocket: {:?}", e);
+                daemons.links[0].set_state(LinkState::Disconnected);
             }
         }
     }
 
     #[cfg(not(target_arch = "wasm32"))]
     {
+        let _ = (&pending, &daemon_config, &low_bandwidth, &daemons);
         tracing::info!("WebSocket not available in native mode");
     }
 }
@@ -716,9 +1580,10 @@ fn fetch_initial_devices(pending: Res<PendingMessages>, daemon_config: Res<Daemo
 
             tracing::info!("Fetching devices from: {}", url);
 
-            match gloo_net::http::Request::get(&url).send().await {
+            match DaemonRequest::get(&url).send().await {
                 Ok(response) => {
                     if let Ok(text) = response.text().await {
+                        record_received(text.len());
                         tracing::debug!("Devices response: {}", text);
                         if let Ok(devices) = serde_json::from_str::<Vec<DeviceJson>>(&text) {
                             if let Ok(mut queue) = pending_clone.lock() {
@@ -742,17 +1607,39 @@ fn process_messages(
     pending: Res<PendingMessages>,
     mut registry: ResMut<DeviceRegistry>,
     mut ota_state: ResMut<crate::app::OtaState>,
+    mut notifications: ResMut<crate::app::Notifications>,
+    daemons: Res<Daemons>,
+    read_only: Res<ReadOnlyMode>,
+    mut attention: ResMut<crate::attention::Attention>,
+    position_sync: Res<PositionSync>,
+    mut interfaces: ResMut<NetworkInterfaces>,
+    mut lldp: ResMut<crate::topology::LldpNeighbors>,
+    mut model_refresh: ResMut<crate::models::ModelRefresh>,
+    time: Res<Time>,
 ) {
-    // Process queued messages from the shared queue
-    let messages = {
-        if let Ok(mut queue) = pending.0.lock() {
-            std::mem::take(&mut *queue)
-        } else {
-            Vec::new()
+    // Process queued messages from the shared queue and every other daemon
+    let queues = std::iter::once((PRIMARY_DAEMON, &*pending))
+        .chain(daemons.links.iter().filter(|l| l.key != PRIMARY_DAEMON).map(|l| (l.key, &l.messages)));
+    let mut messages = Vec::new();
+    for (key, queue) in queues {
+        if let Ok(mut queue) = queue.0.lock() {
+            // A past state is on show; live devices are refetched on return
+            if key == PRIMARY_DAEMON && read_only.history {
+                queue.clear();
+                continue;
+            }
+            let offset = daemons.region_offset(key);
+            messages.extend(
+                std::mem::take(&mut *queue)
+                    .into_iter()
+                    // The attention inbox follows the primary daemon only
+                    .filter(|msg| key == PRIMARY_DAEMON || !matches!(msg, WsMessage::Attention(_)))
+                    .map(|msg| (key, msg.localize(key, offset))),
+            );
         }
-    };
+    }
 
-    for msg in messages {
+    for (key, msg) in messages {
         match msg {
             WsMessage::DeviceDiscovered(device) => {
                 let data: DeviceData = device.into();
@@ -766,20 +1653,57 @@ fn process_messages(
                 }
             }
             WsMessage::DeviceUpdated(device) => {
-                let data: DeviceData = device.into();
+                let mut data: DeviceData = device.into();
                 if let Some(existing) = registry.devices.iter_mut().find(|d| d.id == data.id) {
+                    // An edit not sent yet is newer than the daemon's pose
+                    if position_sync.is_pending(&data.id) {
+                        data.position = existing.position;
+                        data.orientation = existing.orientation;
+                    }
                     *existing = data;
                 }
             }
+            WsMessage::DeviceSummary(device) => {
+                let data: DeviceData = device.into();
+                if let Some(existing) = registry.devices.iter_mut().find(|d| d.id == data.id) {
+                    merge_summary(existing, data);
+                } else {
+                    registry.devices.push(data);
+                }
+            }
+            WsMessage::DeviceChanged(change) => {
+                apply_device_change(&mut registry, change, &position_sync);
+            }
+            WsMessage::DeviceSync(changes) => {
+                // Whatever the daemon no longer has went away while disconnected
+                let ids: HashSet<&str> = changes.iter().map(|c| c.device.id.0.as_str()).collect();
+                registry.devices.retain(|d| split_id(&d.id).0 != key || ids.contains(d.id.as_str()));
+                for change in changes {
+                    apply_device_change(&mut registry, change, &position_sync);
+                }
+            }
             WsMessage::DeviceOffline { id } => {
                 if let Some(device) = registry.devices.iter_mut().find(|d| d.id == id) {
                     device.status = DeviceStatus::Offline;
                 }
             }
-            WsMessage::DeviceRemoved { id } => {
+            WsMessage::HeartbeatBackoff { id, retry_in_ms, .. } => {
+                registry.retry_at.insert(id, time.elapsed_secs_f64() + retry_in_ms as f64 / 1000.0);
+            }
+            WsMessage::DeviceRemoved { id } | WsMessage::DeviceArchived { id } => {
                 registry.devices.retain(|d| d.id != id);
             }
-            WsMessage::OtaProgress { device_id, state } => {
+            WsMessage::DeviceUnarchived(device) => {
+                let data: DeviceData = device.into();
+                tracing::info!("Archived device seen again: {} - {}", data.id, data.name);
+                notifications.push(format!("Archived device \"{}\" was seen again and has been restored", data.name));
+                if let Some(existing) = registry.devices.iter_mut().find(|d| d.id == data.id) {
+                    *existing = data;
+                } else {
+                    registry.devices.push(data);
+                }
+            }
+            WsMessage::OtaProgress(OtaEvent { device_id, state }) => {
                 tracing::info!("OTA progress for {}: {:?}", device_id, state);
                 // Store the state, or remove if terminal
                 if state.is_terminal() {
@@ -789,6 +1713,35 @@ fn process_messages(
                     ota_state.device_updates.insert(device_id, state);
                 }
             }
+            WsMessage::Attention(finding) => {
+                attention.upsert(finding);
+            }
+            WsMessage::FragmentsReloaded { devices, failed } => {
+                tracing::info!("Fragments reloaded, {} device(s) rematched", devices.len());
+                if !failed.is_empty() {
+                    notifications.push(format!("{} fragment(s) failed to load: {}", failed.len(), failed.join("; ")));
+                }
+                model_refresh.0.extend(devices);
+            }
+            // Scans run on the primary daemon, which the scan button drives
+            WsMessage::ScanStarted { total_hosts } if key == PRIMARY_DAEMON => {
+                interfaces.scan_in_progress = true;
+                interfaces.scan_progress = None;
+                interfaces.scan_total_hosts = total_hosts;
+            }
+            WsMessage::ScanProgress { done, total } if key == PRIMARY_DAEMON => {
+                interfaces.scan_in_progress = true;
+                interfaces.scan_progress = Some((done, total));
+            }
+            WsMessage::ScanCompleted { found, .. } if key == PRIMARY_DAEMON => {
+                interfaces.scan_in_progress = false;
+                interfaces.scan_progress = None;
+                interfaces.last_scan_found = Some(found);
+            }
+            // The topology graph refetches the primary daemon's neighbors
+            WsMessage::LldpNeighborChanged { .. } | WsMessage::LldpNeighborExpired { .. } if key == PRIMARY_DAEMON => {
+                lldp.stale = true;
+            }
             _ => {}
         }
     }
@@ -796,6 +1749,58 @@ fn process_messages(
     registry.connected = connection.connected;
 }
 
+/// Add or update a device from a delta
+///
+/// Only the fields the delta changed are taken over, and a pose the user
+/// is still editing (queued in `PositionSync`) is never overwritten.
+fn apply_device_change(registry: &mut DeviceRegistry, change: DeviceChange, position_sync: &PositionSync) {
+    let DeviceChange { device, changed } = change;
+    let update: DeviceData = device.into();
+    let Some(existing) = registry.devices.iter_mut().find(|d| d.id == update.id) else {
+        registry.devices.push(update);
+        return;
+    };
+    let keep_pose = position_sync.is_pending(&update.id);
+    merge_changed(existing, update, &changed, keep_pose);
+}
+
+/// Update a device from its latest record, keeping the local pose unless the
+/// daemon changed it (and `keep_pose` is false), and the visuals, frames,
+/// ports and sensors unless they were sent
+fn merge_changed(existing: &mut DeviceData, update: DeviceData, changed: &[String], keep_pose: bool) {
+    let changed = |field: &str| changed.iter().any(|c| c == field);
+    let previous = std::mem::replace(existing, update);
+    if keep_pose || !changed("pose") {
+        existing.position = previous.position;
+        existing.orientation = previous.orientation;
+    }
+    if keep_pose || !changed("pose_provisional") {
+        existing.pose_provisional = previous.pose_provisional;
+    }
+    if !changed("visuals") {
+        existing.visuals = previous.visuals;
+    }
+    if !changed("frames") {
+        existing.frames = previous.frames;
+    }
+    if !changed("ports") {
+        existing.ports = previous.ports;
+    }
+    if !changed("sensors") {
+        existing.sensors = previous.sensors;
+    }
+}
+
+/// Update a device from a summary, keeping the visuals, frames, ports and
+/// sensors of the last full record
+fn merge_summary(existing: &mut DeviceData, summary: DeviceData) {
+    let DeviceData { visuals, frames, ports, sensors, .. } = std::mem::replace(existing, summary);
+    existing.visuals = visuals;
+    existing.frames = frames;
+    existing.ports = ports;
+    existing.sensors = sensors;
+}
+
 /// Fetch network interfaces from backend
 fn fetch_network_interfaces(pending: Res<PendingInterfaceData>, daemon_config: Res<DaemonConfig>) {
     #[cfg(target_arch = "wasm32")]
@@ -810,7 +1815,7 @@ fn fetch_network_interfaces(pending: Res<PendingInterfaceData>, daemon_config: R
 
             tracing::info!("Fetching network interfaces from: {}", url);
 
-            match gloo_net::http::Request::get(&url).send().await {
+            match DaemonRequest::get(&url).send().await {
                 Ok(response) => {
                     if let Ok(text) = response.text().await {
                         tracing::debug!("Interfaces response: {}", text);
@@ -856,7 +1861,7 @@ fn fetch_heartbeat_state(pending: Res<PendingHeartbeatData>, daemon_config: Res<
 
             tracing::info!("Fetching heartbeat state from: {}", url);
 
-            match gloo_net::http::Request::get(&url).send().await {
+            match DaemonRequest::get(&url).send().await {
                 Ok(response) => {
                     if let Ok(text) = response.text().await {
                         tracing::debug!("Heartbeat response: {}", text);
@@ -890,158 +1895,210 @@ fn process_heartbeat_data(
     }
 }
 
-/// Toggle heartbeat checking (called from UI)
-pub fn toggle_heartbeat(enabled: bool, base_url: &str) {
-    #[cfg(target_arch = "wasm32")]
+/// Ask the daemon whether this session is read-only
+///
+/// The request carries the page's access token, so a viewer session's token
+/// comes back read-only.
+fn fetch_session_role(pending: Res<PendingSessionRole>, daemon_config: Res<DaemonConfig>) {
+    request_session_role(&daemon_config.http_url, &pending);
+}
+
+fn request_session_role(base_url: &str, pending: &PendingSessionRole) {
+    #[cfg(target_arch = "wasm32")]
     {
         use wasm_bindgen_futures::spawn_local;
 
-        let base_url = base_url.to_string();
+        let pending_clone = pending.0.clone();
+        let url = format!("{}/api/session", base_url);
 
         spawn_local(async move {
-            let url = format!("{}/api/heartbeat", base_url);
-            let body = serde_json::json!({ "enabled": enabled });
-
-            tracing::info!("Setting heartbeat to: {}", enabled);
-
-            match gloo_net::http::Request::post(&url)
-                .header("Content-Type", "application/json")
-                .body(body.to_string())
-                .unwrap()
-                .send()
-                .await
-            {
-                Ok(_) => {
-                    tracing::info!("Heartbeat set to: {}", enabled);
+            match DaemonRequest::get(&url).send().await {
+                Ok(response) => {
+                    if let Ok(text) = response.text().await {
+                        if let Ok(json) = serde_json::from_str::<serde_json::Value>(&text) {
+                            if let Some(read_only) = json.get("read_only").and_then(|v| v.as_bool()) {
+                                if let Ok(mut data) = pending_clone.lock() {
+                                    *data = Some(read_only);
+                                }
+                            }
+                        }
+                    }
                 }
                 Err(e) => {
-                    tracing::error!("Failed to set heartbeat: {:?}", e);
+                    tracing::error!("Failed to fetch session role: {:?}", e);
                 }
             }
         });
     }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = (base_url, pending);
+    }
 }
 
-/// Trigger a scan on the selected interface (called from UI)
-pub fn trigger_scan_on_interface(subnet: &str, prefix_len: u8, base_url: &str) {
+/// Process pending session role
+fn process_session_role(
+    pending: Res<PendingSessionRole>,
+    mut read_only: ResMut<ReadOnlyMode>,
+) {
+    if let Ok(mut data) = pending.0.lock() {
+        if let Some(viewer) = data.take() {
+            read_only.viewer_session = viewer;
+        }
+    }
+}
+
+/// Ask the daemon for its version and protocol
+fn fetch_daemon_version(pending: Res<PendingDaemonVersion>, daemon_config: Res<DaemonConfig>) {
+    request_daemon_version(&daemon_config.http_url, &pending);
+}
+
+fn request_daemon_version(base_url: &str, pending: &PendingDaemonVersion) {
     #[cfg(target_arch = "wasm32")]
     {
         use wasm_bindgen_futures::spawn_local;
 
-        let subnet = subnet.to_string();
-        let base_url = base_url.to_string();
+        let pending_clone = pending.0.clone();
+        let url = format!("{}/api/version", base_url);
 
         spawn_local(async move {
-            // First update the subnet
-            let update_url = format!("{}/api/subnet", base_url);
-            let body = serde_json::json!({
-                "subnet": subnet,
-                "prefix_len": prefix_len
-            });
-
-            tracing::info!("Updating subnet to: {}/{}", subnet, prefix_len);
-
-            match gloo_net::http::Request::post(&update_url)
-                .header("Content-Type", "application/json")
-                .body(body.to_string())
-                .unwrap()
-                .send()
-                .await
-            {
-                Ok(_) => {
-                    tracing::info!("Subnet updated, triggering scan");
-
-                    // Now trigger a scan
-                    let scan_url = format!("{}/api/scan", base_url);
-                    match gloo_net::http::Request::post(&scan_url).send().await {
-                        Ok(_) => {
-                            tracing::info!("Scan triggered successfully");
-                        }
-                        Err(e) => {
-                            tracing::error!("Failed to trigger scan: {:?}", e);
+            match DaemonRequest::get(&url).send().await {
+                Ok(response) if response.status() == 404 => {
+                    if let Ok(mut data) = pending_clone.lock() {
+                        *data = Some(None);
+                    }
+                }
+                Ok(response) => {
+                    if let Ok(text) = response.text().await {
+                        if let Ok(version) = serde_json::from_str::<DaemonVersion>(&text) {
+                            if let Ok(mut data) = pending_clone.lock() {
+                                *data = Some(Some(version));
+                            }
                         }
                     }
                 }
                 Err(e) => {
-                    tracing::error!("Failed to update subnet: {:?}", e);
+                    tracing::error!("Failed to fetch daemon version: {:?}", e);
                 }
             }
         });
     }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = (base_url, pending);
+    }
 }
 
-/// Remove a device from the backend (called from UI)
-pub fn remove_device(device_id: &str, base_url: &str) {
+/// Process pending daemon version, warning on protocol mismatch
+fn process_daemon_version(
+    pending: Res<PendingDaemonVersion>,
+    mut notifications: ResMut<Notifications>,
+) {
+    let Some(version) = pending.0.lock().ok().and_then(|mut data| data.take()) else {
+        return;
+    };
+
+    if let Some(v) = &version {
+        tracing::info!(
+            version = %v.version,
+            git_hash = %v.git_hash,
+            features = ?v.features,
+            hcdf_versions = ?v.hcdf_versions,
+            ws_protocol = v.ws_protocol,
+            "Connected to daemon"
+        );
+    }
+
+    let compatibility = ProtocolCompatibility::check(WS_PROTOCOL_VERSION, version.as_ref());
+    if let Some(warning) = compatibility.warning() {
+        tracing::warn!("{}", warning);
+        notifications.push(warning);
+    }
+}
+
+/// Fetch keep-out volumes and violations
+fn fetch_keepouts(pending: Res<PendingKeepouts>, daemon_config: Res<DaemonConfig>) {
+    request_keepouts(&daemon_config.http_url, &pending);
+}
+
+fn request_keepouts(base_url: &str, pending: &PendingKeepouts) {
     #[cfg(target_arch = "wasm32")]
     {
         use wasm_bindgen_futures::spawn_local;
 
-        let device_id = device_id.to_string();
-        let base_url = base_url.to_string();
+        let pending_clone = pending.0.clone();
+        let url = format!("{}/api/keepouts", base_url);
 
         spawn_local(async move {
-            let url = format!("{}/api/devices/{}", base_url, device_id);
-
-            tracing::info!("Removing device: {}", device_id);
-
-            match gloo_net::http::Request::delete(&url).send().await {
+            match DaemonRequest::get(&url).send().await {
+                // Older daemons have no keep-out support
+                Ok(response) if response.status() == 404 => {}
                 Ok(response) => {
-                    if response.ok() {
-                        tracing::info!("Device removed successfully: {}", device_id);
-                    } else {
-                        tracing::error!("Failed to remove device: {}", response.status());
+                    if let Ok(text) = response.text().await {
+                        record_received(text.len());
+                        if let Ok(keepouts) = serde_json::from_str::<KeepoutScene>(&text) {
+                            if let Ok(mut data) = pending_clone.lock() {
+                                *data = Some(keepouts);
+                            }
+                        }
                     }
                 }
                 Err(e) => {
-                    tracing::error!("Failed to remove device: {:?}", e);
+                    tracing::error!("Failed to fetch keep-out volumes: {:?}", e);
                 }
             }
         });
     }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = (base_url, pending);
+    }
 }
 
-/// Process pending firmware check data
-fn process_firmware_data(
-    pending: Res<PendingFirmwareData>,
-    mut firmware_state: ResMut<FirmwareCheckState>,
+/// Process pending keep-out data, only touching the scene when it changed
+fn process_keepouts(
+    pending: Res<PendingKeepouts>,
+    mut keepouts: ResMut<KeepoutScene>,
 ) {
-    if let Ok(mut data) = pending.0.lock() {
-        for response in data.drain(..) {
-            // Convert JSON status to our enum
-            let status: FirmwareStatusData = response.status.into();
-            firmware_state.device_status.insert(response.device_id.clone(), status);
-            firmware_state.loading.remove(&response.device_id);
+    if let Some(data) = pending.0.lock().ok().and_then(|mut data| data.take()) {
+        if *keepouts != data {
+            if data.violations.len() > keepouts.violations.len() {
+                tracing::warn!("{} keep-out violation(s)", data.violations.len());
+            }
+            *keepouts = data;
         }
     }
 }
 
-/// Trigger firmware check for all devices (called from UI)
-pub fn check_all_firmware(base_url: &str, pending: &PendingFirmwareData) {
+/// Toggle heartbeat checking (called from UI)
+pub fn toggle_heartbeat(enabled: bool, base_url: &str) {
     #[cfg(target_arch = "wasm32")]
     {
         use wasm_bindgen_futures::spawn_local;
 
         let base_url = base_url.to_string();
-        let pending_clone = pending.0.clone();
 
         spawn_local(async move {
-            let url = format!("{}/api/firmware/check", base_url);
+            let url = format!("{}/api/heartbeat", base_url);
+            let body = serde_json::json!({ "enabled": enabled });
 
-            tracing::info!("Checking firmware for all devices");
+            tracing::info!("Setting heartbeat to: {}", enabled);
 
-            match gloo_net::http::Request::get(&url).send().await {
-                Ok(response) => {
-                    if let Ok(text) = response.text().await {
-                        tracing::debug!("Firmware check response: {}", text);
-                        if let Ok(results) = serde_json::from_str::<Vec<FirmwareCheckResponse>>(&text) {
-                            if let Ok(mut data) = pending_clone.lock() {
-                                data.extend(results);
-                            }
-                        }
-                    }
+            match DaemonRequest::post(&url)
+                .header("Content-Type", "application/json")
+                .body(body.to_string())
+                .unwrap()
+                .send()
+                .await
+            {
+                Ok(_) => {
+                    tracing::info!("Heartbeat set to: {}", enabled);
                 }
                 Err(e) => {
-                    tracing::error!("Failed to check firmware: {:?}", e);
+                    tracing::error!("Failed to set heartbeat: {:?}", e);
                 }
             }
         });
@@ -1049,168 +2106,273 @@ pub fn check_all_firmware(base_url: &str, pending: &PendingFirmwareData) {
 }
 
 // ============================================================================
-// OTA Update Functions
+// Heartbeat Method / Latency Functions
 // ============================================================================
 
-/// Pending OTA update events from WebSocket
-#[derive(Resource, Default)]
-pub struct PendingOtaEvents(pub Arc<Mutex<Vec<OtaProgressEvent>>>);
+/// Heartbeat methods selectable in the UI (besides auto and tcp-connect:<port>)
+pub const HEARTBEAT_METHODS: [&str; 3] = ["arp", "icmp", "smp-echo"];
+
+/// How often the selected device's latency history is refreshed
+const LATENCY_POLL_SECS: f32 = 2.0;
 
-/// OTA progress event from WebSocket
+/// Samples kept per device by the daemon (sparkline width)
+pub const LATENCY_SPARKLINE_LEN: usize = 60;
+
+/// Latency history response from the daemon
 #[derive(Debug, Clone, Deserialize)]
-pub struct OtaProgressEvent {
+pub struct LatencyJson {
     pub device_id: String,
-    pub state: OtaUpdateState,
+    pub method: String,
+    pub auto: bool,
+    pub samples: Vec<LatencySampleJson>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LatencySampleJson {
+    pub latency_ms: Option<f64>,
 }
 
-/// OTA update state (mirrors backend UpdateState)
-#[derive(Debug, Clone, Deserialize, PartialEq)]
-#[serde(tag = "state", rename_all = "snake_case")]
-pub enum OtaUpdateState {
-    Downloading { progress: f32 },
-    Uploading { progress: f32 },
-    Confirming,
-    Rebooting,
-    Verifying,
-    Complete,
-    Failed { error: String },
-    Cancelled,
+/// Pending latency data from async fetch
+#[derive(Resource, Default)]
+pub struct PendingLatencyData(pub Arc<Mutex<Option<LatencyJson>>>);
+
+/// Latency history for the selected device
+#[derive(Resource)]
+pub struct DeviceLatency {
+    /// Device the samples belong to
+    pub device_id: Option<String>,
+    /// Heartbeat method in effect
+    pub method: Option<String>,
+    /// Whether the method was auto-selected
+    pub auto: bool,
+    /// Latency samples in ms, oldest first (None = no response)
+    pub samples: Vec<Option<f64>>,
+    /// Port used when selecting tcp-connect
+    pub tcp_port: u16,
+    pub timer: Timer,
 }
 
-impl OtaUpdateState {
-    pub fn is_terminal(&self) -> bool {
-        matches!(self, OtaUpdateState::Complete | OtaUpdateState::Failed { .. } | OtaUpdateState::Cancelled)
+impl Default for DeviceLatency {
+    fn default() -> Self {
+        Self {
+            device_id: None,
+            method: None,
+            auto: true,
+            samples: Vec::new(),
+            tcp_port: 22,
+            timer: Timer::from_seconds(LATENCY_POLL_SECS, TimerMode::Repeating),
+        }
     }
+}
 
-    pub fn progress_text(&self) -> String {
-        match self {
-            OtaUpdateState::Downloading { progress } => format!("Downloading... {:.0}%", progress * 100.0),
-            OtaUpdateState::Uploading { progress } => format!("Uploading... {:.0}%", progress * 100.0),
-            OtaUpdateState::Confirming => "Confirming image...".to_string(),
-            OtaUpdateState::Rebooting => "Rebooting device...".to_string(),
-            OtaUpdateState::Verifying => "Verifying update...".to_string(),
-            OtaUpdateState::Complete => "Update complete!".to_string(),
-            OtaUpdateState::Failed { error } => format!("Failed: {}", error),
-            OtaUpdateState::Cancelled => "Cancelled".to_string(),
+/// Periodically fetch latency history for the selected device while heartbeat is enabled
+fn poll_device_latency(
+    time: Res<Time>,
+    selected: Res<SelectedDevice>,
+    heartbeat_state: Res<HeartbeatState>,
+    mut latency: ResMut<DeviceLatency>,
+    pending: Res<PendingLatencyData>,
+    daemons: Res<Daemons>,
+) {
+    // Drop stale history when the selection changes and fetch immediately
+    if latency.device_id != selected.0 {
+        latency.device_id = selected.0.clone();
+        latency.method = None;
+        latency.samples.clear();
+        if let Some((base_url, id)) = selected.0.as_deref().and_then(|id| daemons.route(id)) {
+            fetch_device_latency(id, base_url, &pending);
         }
+        return;
     }
 
-    pub fn progress_value(&self) -> Option<f32> {
-        match self {
-            OtaUpdateState::Downloading { progress } => Some(*progress),
-            OtaUpdateState::Uploading { progress } => Some(*progress),
-            _ => None,
+    latency.timer.tick(time.delta());
+    if !latency.timer.just_finished() || !heartbeat_state.enabled {
+        return;
+    }
+
+    if let Some((base_url, id)) = selected.0.as_deref().and_then(|id| daemons.route(id)) {
+        fetch_device_latency(id, base_url, &pending);
+    }
+}
+
+/// Process pending latency data
+fn process_latency_data(
+    pending: Res<PendingLatencyData>,
+    mut latency: ResMut<DeviceLatency>,
+) {
+    if let Ok(mut data) = pending.0.lock() {
+        if let Some(json) = data.take() {
+            // Ignore responses for a device that is no longer selected
+            if latency.device_id.as_deref().map(|id| split_id(id).1) == Some(json.device_id.as_str()) {
+                if let Some(port) = json.method.strip_prefix("tcp-connect:").and_then(|p| p.parse().ok()) {
+                    latency.tcp_port = port;
+                }
+                latency.method = Some(json.method);
+                latency.auto = json.auto;
+                latency.samples = json.samples.into_iter().map(|s| s.latency_ms).collect();
+            }
         }
     }
 }
 
-/// Start an OTA firmware update for a device (called from UI)
-pub fn start_ota_update(device_id: &str, base_url: &str) {
+/// Fetch latency history for a device
+fn fetch_device_latency(device_id: &str, base_url: &str, pending: &PendingLatencyData) {
     #[cfg(target_arch = "wasm32")]
     {
         use wasm_bindgen_futures::spawn_local;
 
-        let device_id = device_id.to_string();
-        let base_url = base_url.to_string();
+        let pending_clone = pending.0.clone();
+        let url = format!("{}/api/devices/{}/latency", base_url, device_id);
 
         spawn_local(async move {
-            let url = format!("{}/api/ota/{}/start", base_url, device_id);
-
-            tracing::info!("Starting OTA update for device: {}", device_id);
-
-            match gloo_net::http::Request::post(&url).send().await {
-                Ok(response) => {
-                    if response.ok() {
-                        tracing::info!("OTA update started for device: {}", device_id);
-                    } else {
-                        let status = response.status();
-                        let text = response.text().await.unwrap_or_default();
-                        tracing::error!("Failed to start OTA update: {} - {}", status, text);
+            match DaemonRequest::get(&url).send().await {
+                Ok(response) if response.ok() => {
+                    if let Ok(text) = response.text().await {
+                        record_received(text.len());
+                        if let Ok(json) = serde_json::from_str::<LatencyJson>(&text) {
+                            if let Ok(mut data) = pending_clone.lock() {
+                                *data = Some(json);
+                            }
+                        }
                     }
                 }
+                Ok(response) => {
+                    tracing::debug!("Latency fetch returned {}", response.status());
+                }
                 Err(e) => {
-                    tracing::error!("Failed to start OTA update: {:?}", e);
+                    tracing::error!("Failed to fetch latency history: {:?}", e);
                 }
             }
         });
     }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = (device_id, base_url, pending);
+    }
 }
 
-/// Cancel an OTA firmware update for a device (called from UI)
-pub fn cancel_ota_update(device_id: &str, base_url: &str) {
+// ============================================================================
+// Firmware Image Slots
+// ============================================================================
+
+/// Pending image slot response from async fetch or confirm
+#[derive(Resource, Default)]
+pub struct PendingDeviceImages(pub Arc<Mutex<Option<DeviceImagesResponse>>>);
+
+/// Firmware image slots of the selected device
+#[derive(Resource, Default)]
+pub struct DeviceImages {
+    /// Device the slots belong to
+    pub device_id: Option<String>,
+    /// Last answer from the daemon (None until it arrives)
+    pub response: Option<DeviceImagesResponse>,
+    /// A fetch or confirm is in flight
+    pub loading: bool,
+}
+
+/// Fetch the selected device's image slots whenever the selection changes
+fn poll_device_images(
+    selected: Res<SelectedDevice>,
+    mut images: ResMut<DeviceImages>,
+    pending: Res<PendingDeviceImages>,
+    daemons: Res<Daemons>,
+) {
+    if images.device_id == selected.0 {
+        return;
+    }
+    images.device_id = selected.0.clone();
+    images.response = None;
+    images.loading = false;
+    if let Some((base_url, id)) = selected.0.as_deref().and_then(|id| daemons.route(id)) {
+        images.loading = true;
+        fetch_device_images(id, base_url, &pending);
+    }
+}
+
+/// Process pending image slot data
+fn process_device_images(pending: Res<PendingDeviceImages>, mut images: ResMut<DeviceImages>) {
+    if let Ok(mut data) = pending.0.lock() {
+        if let Some(response) = data.take() {
+            // Ignore responses for a device that is no longer selected
+            if images.device_id.as_deref().map(|id| split_id(id).1) == Some(response.device_id.as_str()) {
+                images.response = Some(response);
+                images.loading = false;
+            }
+        }
+    }
+}
+
+/// Fetch a device's image slots
+pub fn fetch_device_images(device_id: &str, base_url: &str, pending: &PendingDeviceImages) {
     #[cfg(target_arch = "wasm32")]
     {
         use wasm_bindgen_futures::spawn_local;
 
-        let device_id = device_id.to_string();
-        let base_url = base_url.to_string();
+        let pending_clone = pending.0.clone();
+        let url = format!("{}/api/devices/{}/images", base_url, device_id);
 
         spawn_local(async move {
-            let url = format!("{}/api/ota/{}/cancel", base_url, device_id);
-
-            tracing::info!("Cancelling OTA update for device: {}", device_id);
-
-            match gloo_net::http::Request::post(&url).send().await {
-                Ok(response) => {
-                    if response.ok() {
-                        tracing::info!("OTA update cancelled for device: {}", device_id);
-                    } else {
-                        let status = response.status();
-                        let text = response.text().await.unwrap_or_default();
-                        tracing::error!("Failed to cancel OTA update: {} - {}", status, text);
+            match DaemonRequest::get(&url).send().await {
+                Ok(response) if response.ok() => {
+                    if let Ok(text) = response.text().await {
+                        record_received(text.len());
+                        if let Ok(json) = serde_json::from_str::<DeviceImagesResponse>(&text) {
+                            if let Ok(mut data) = pending_clone.lock() {
+                                *data = Some(json);
+                            }
+                        }
                     }
                 }
+                Ok(response) => {
+                    tracing::debug!("Image slot fetch returned {}", response.status());
+                }
                 Err(e) => {
-                    tracing::error!("Failed to cancel OTA update: {:?}", e);
+                    tracing::error!("Failed to fetch image slots: {:?}", e);
                 }
             }
         });
     }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = (device_id, base_url, pending);
+    }
 }
 
-// ============================================================================
-// Local Firmware Upload (for development images)
-// ============================================================================
-
-/// Upload local firmware file to a device (called from UI after file picker)
-pub fn upload_local_firmware(device_id: &str, firmware_data: Vec<u8>, base_url: &str) {
+/// Confirm a device's image by hash, called from UI; the daemon answers with the new slots
+pub fn confirm_device_image(device_id: &str, hash: &str, base_url: &str, pending: &PendingDeviceImages) {
     #[cfg(target_arch = "wasm32")]
     {
         use wasm_bindgen_futures::spawn_local;
-        use base64::Engine;
 
-        let device_id = device_id.to_string();
-        let base_url = base_url.to_string();
+        let pending_clone = pending.0.clone();
+        let url = format!("{}/api/devices/{}/images/confirm", base_url, device_id);
+        let body = serde_json::json!({ "hash": hash });
 
         spawn_local(async move {
-            let url = format!("{}/api/ota/{}/upload-local", base_url, device_id);
-
-            tracing::info!("Uploading local firmware for device: {} ({} bytes)", device_id, firmware_data.len());
-
-            // Encode firmware as base64
-            let firmware_base64 = base64::engine::general_purpose::STANDARD.encode(&firmware_data);
-            let body = serde_json::json!({
-                "firmware_base64": firmware_base64
-            });
-
-            match gloo_net::http::Request::post(&url)
+            match DaemonRequest::post(&url)
                 .header("Content-Type", "application/json")
                 .body(body.to_string())
                 .unwrap()
                 .send()
                 .await
             {
-                Ok(response) => {
-                    if response.ok() {
-                        tracing::info!("Local firmware upload started for device: {}", device_id);
-                    } else {
-                        let status = response.status();
-                        let text = response.text().await.unwrap_or_default();
-                        tracing::error!("Failed to upload local firmware: {} - {}", status, text);
+                Ok(response) if response.ok() => {
+                    if let Ok(text) = response.text().await {
+                        record_received(text.len());
+                        if let Ok(json) = serde_json::from_str::<DeviceImagesResponse>(&text) {
+                            if let Ok(mut data) = pending_clone.lock() {
+                                *data = Some(json);
+                            }
+                        }
                     }
                 }
+                Ok(response) => {
+                    tracing::error!("Failed to confirm image: {}", response.status());
+                }
                 Err(e) => {
-                    tracing::error!("Failed to upload local firmware: {:?}", e);
+                    tracing::error!("Failed to confirm image: {:?}", e);
                 }
             }
         });
@@ -1218,53 +2380,42 @@ pub fn upload_local_firmware(device_id: &str, firmware_data: Vec<u8>, base_url:
 
     #[cfg(not(target_arch = "wasm32"))]
     {
-        let _ = (device_id, firmware_data, base_url);
-        tracing::warn!("Local firmware upload not available in native mode");
+        let _ = (device_id, hash, base_url, pending);
     }
 }
 
-// ============================================================================
-// HCDF Import/Export (for file picker)
-// ============================================================================
-
-/// Pending HCDF export data (used when fetching HCDF for file save)
-#[derive(Resource, Default)]
-pub struct PendingHcdfExport(pub Arc<Mutex<Option<Vec<u8>>>>);
-
-/// Export HCDF (fetch from backend for file save)
-pub fn export_hcdf(base_url: &str, pending: &PendingHcdfExport) {
+/// Set the heartbeat method for a device (None = auto-select), called from UI
+pub fn set_heartbeat_method(device_id: &str, method: Option<String>, base_url: &str, pending: &PendingLatencyData) {
     #[cfg(target_arch = "wasm32")]
     {
         use wasm_bindgen_futures::spawn_local;
 
+        let device_id = device_id.to_string();
         let base_url = base_url.to_string();
-        let pending_clone = pending.0.clone();
+        let pending = PendingLatencyData(pending.0.clone());
 
         spawn_local(async move {
-            let url = format!("{}/api/hcdf/export", base_url);
+            let url = format!("{}/api/devices/{}/heartbeat", base_url, device_id);
+            let body = serde_json::json!({ "method": method });
 
-            tracing::info!("Fetching HCDF for export");
+            tracing::info!("Setting heartbeat method for {} to {:?}", device_id, method);
 
-            match gloo_net::http::Request::get(&url).send().await {
+            match DaemonRequest::put(&url)
+                .header("Content-Type", "application/json")
+                .body(body.to_string())
+                .unwrap()
+                .send()
+                .await
+            {
+                Ok(response) if response.ok() => {
+                    // History is reset on the daemon when the method changes
+                    fetch_device_latency(&device_id, &base_url, &pending);
+                }
                 Ok(response) => {
-                    if response.ok() {
-                        if let Ok(text) = response.text().await {
-                            // Parse as JSON to extract the XML content
-                            if let Ok(json) = serde_json::from_str::<serde_json::Value>(&text) {
-                                if let Some(xml) = json.get("xml").and_then(|v| v.as_str()) {
-                                    if let Ok(mut data) = pending_clone.lock() {
-                                        *data = Some(xml.as_bytes().to_vec());
-                                    }
-                                    tracing::info!("HCDF export fetched successfully");
-                                }
-                            }
-                        }
-                    } else {
-                        tracing::error!("Failed to export HCDF: {}", response.status());
-                    }
+                    tracing::error!("Failed to set heartbeat method: {}", response.status());
                 }
                 Err(e) => {
-                    tracing::error!("Failed to export HCDF: {:?}", e);
+                    tracing::error!("Failed to set heartbeat method: {:?}", e);
                 }
             }
         });
@@ -1272,47 +2423,40 @@ pub fn export_hcdf(base_url: &str, pending: &PendingHcdfExport) {
 
     #[cfg(not(target_arch = "wasm32"))]
     {
-        let _ = (base_url, pending);
-        tracing::warn!("HCDF export not available in native mode");
+        let _ = (device_id, method, base_url, pending);
+        tracing::warn!("Heartbeat method selection not available in native mode");
     }
 }
 
-/// Import HCDF (send to backend from file picker)
-pub fn import_hcdf(xml_content: String, merge: bool, base_url: &str) {
+/// Set a device's pose edit limits on the daemon, which stores them in the HCDF
+///
+/// `stack_on` is the base device's ID on the same daemon.
+pub fn set_pose_constraint(device_id: &str, keep_above_ground: bool, stack_on: Option<&str>, base_url: &str) {
     #[cfg(target_arch = "wasm32")]
     {
         use wasm_bindgen_futures::spawn_local;
 
+        let device_id = device_id.to_string();
         let base_url = base_url.to_string();
+        let body = serde_json::json!({ "keep_above_ground": keep_above_ground, "stack_on": stack_on });
 
         spawn_local(async move {
-            let url = format!("{}/api/hcdf/import", base_url);
-
-            tracing::info!("Importing HCDF ({} bytes, merge={})", xml_content.len(), merge);
-
-            let body = serde_json::json!({
-                "xml": xml_content,
-                "merge": merge
-            });
-
-            match gloo_net::http::Request::post(&url)
+            let url = format!("{}/api/devices/{}/constraint", base_url, device_id);
+            match DaemonRequest::put(&url)
                 .header("Content-Type", "application/json")
                 .body(body.to_string())
                 .unwrap()
                 .send()
                 .await
             {
+                Ok(response) if response.ok() => {
+                    tracing::info!("Pose constraint set for device: {}", device_id);
+                }
                 Ok(response) => {
-                    if response.ok() {
-                        tracing::info!("HCDF imported successfully");
-                    } else {
-                        let status = response.status();
-                        let text = response.text().await.unwrap_or_default();
-                        tracing::error!("Failed to import HCDF: {} - {}", status, text);
-                    }
+                    tracing::error!("Failed to set pose constraint: {}", response.status());
                 }
                 Err(e) => {
-                    tracing::error!("Failed to import HCDF: {:?}", e);
+                    tracing::error!("Failed to set pose constraint: {:?}", e);
                 }
             }
         });
@@ -1320,49 +2464,139 @@ pub fn import_hcdf(xml_content: String, merge: bool, base_url: &str) {
 
     #[cfg(not(target_arch = "wasm32"))]
     {
-        let _ = (xml_content, merge, base_url);
-        tracing::warn!("HCDF import not available in native mode");
+        let _ = (device_id, keep_above_ground, stack_on, base_url);
+        tracing::warn!("Pose constraints not available in native mode");
     }
 }
 
-/// Save HCDF to server filesystem (not browser download)
-pub fn save_hcdf_to_server(base_url: &str, filename: Option<&str>) {
+/// Persist a port pose edit on the daemon (`None` resets it to the HCDF pose)
+pub fn sync_port_pose(device_id: &str, port_name: &str, pose: Option<[f64; 6]>, base_url: &str) {
     #[cfg(target_arch = "wasm32")]
     {
         use wasm_bindgen_futures::spawn_local;
 
-        let base_url = base_url.to_string();
-        let filename = filename.map(|s| s.to_string());
+        let url = format!("{}/api/devices/{}/ports/{}/pose", base_url, device_id, port_name);
 
         spawn_local(async move {
-            let url = format!("{}/api/hcdf/save", base_url);
+            let request = match pose {
+                Some(pose) => DaemonRequest::put(&url)
+                    .header("Content-Type", "application/json")
+                    .body(serde_json::json!({ "pose": pose }).to_string())
+                    .unwrap(),
+                None => DaemonRequest::delete(&url).build().unwrap(),
+            };
+
+            match request.send().await {
+                Ok(response) if response.ok() => {}
+                Ok(response) => {
+                    let status = response.status();
+                    let text = response.text().await.unwrap_or_default();
+                    tracing::error!("Failed to update port pose: {} - {}", status, text);
+                }
+                Err(e) => {
+                    tracing::error!("Failed to update port pose: {:?}", e);
+                }
+            }
+        });
+    }
 
-            tracing::info!("Saving HCDF to server");
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = (device_id, port_name, pose, base_url);
+        tracing::warn!("Port pose update not available in native mode");
+    }
+}
+
+/// Trigger a scan on the selected interface (called from UI)
+pub fn trigger_scan_on_interface(subnet: &str, prefix_len: u8, base_url: &str) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        use wasm_bindgen_futures::spawn_local;
+
+        let subnet = subnet.to_string();
+        let base_url = base_url.to_string();
 
+        spawn_local(async move {
+            // First update the subnet
+            let update_url = format!("{}/api/subnet", base_url);
             let body = serde_json::json!({
-                "filename": filename
+                "subnet": subnet,
+                "prefix_len": prefix_len
             });
 
-            match gloo_net::http::Request::post(&url)
+            tracing::info!("Updating subnet to: {}/{}", subnet, prefix_len);
+
+            match DaemonRequest::post(&update_url)
                 .header("Content-Type", "application/json")
                 .body(body.to_string())
                 .unwrap()
                 .send()
                 .await
             {
+                Ok(_) => {
+                    tracing::info!("Subnet updated, triggering scan");
+
+                    // Now trigger a scan
+                    let scan_url = format!("{}/api/scan", base_url);
+                    match DaemonRequest::post(&scan_url).send().await {
+                        Ok(_) => {
+                            tracing::info!("Scan triggered successfully");
+                        }
+                        Err(e) => {
+                            tracing::error!("Failed to trigger scan: {:?}", e);
+                        }
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("Failed to update subnet: {:?}", e);
+                }
+            }
+        });
+    }
+}
+
+/// How the daemon should remove a device (mirrors backend RemoveMode)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoveMode {
+    /// Hide until the device is seen again
+    Archive,
+    /// Delete the device and its saved pose/HCDF entry
+    Forget,
+}
+
+impl RemoveMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RemoveMode::Archive => "archive",
+            RemoveMode::Forget => "forget",
+        }
+    }
+}
+
+/// Remove a device from the backend (called from UI)
+pub fn remove_device(device_id: &str, mode: RemoveMode, base_url: &str) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        use wasm_bindgen_futures::spawn_local;
+
+        let device_id = device_id.to_string();
+        let base_url = base_url.to_string();
+
+        spawn_local(async move {
+            let url = format!("{}/api/devices/{}?mode={}", base_url, device_id, mode.as_str());
+
+            tracing::info!("Removing device ({}): {}", mode.as_str(), device_id);
+
+            match DaemonRequest::delete(&url).build().unwrap().send().await {
                 Ok(response) => {
                     if response.ok() {
-                        if let Ok(text) = response.text().await {
-                            tracing::info!("HCDF saved to server: {}", text);
-                        }
+                        tracing::info!("Device removed successfully: {}", device_id);
                     } else {
-                        let status = response.status();
-                        let text = response.text().await.unwrap_or_default();
-                        tracing::error!("Failed to save HCDF to server: {} - {}", status, text);
+                        tracing::error!("Failed to remove device: {}", response.status());
                     }
                 }
                 Err(e) => {
-                    tracing::error!("Failed to save HCDF to server: {:?}", e);
+                    tracing::error!("Failed to remove device: {:?}", e);
                 }
             }
         });
@@ -1370,71 +2604,1843 @@ pub fn save_hcdf_to_server(base_url: &str, filename: Option<&str>) {
 
     #[cfg(not(target_arch = "wasm32"))]
     {
-        let _ = (base_url, filename);
-        tracing::warn!("HCDF save to server not available in native mode");
+        let _ = (device_id, mode, base_url);
+        tracing::warn!("Device removal not available in native mode");
     }
 }
 
-/// Update device position and orientation on the backend
-/// This syncs position changes to the HCDF so they're persisted on export
-pub fn update_device_position(
-    device_id: &str,
-    position: [f32; 3],
-    orientation: Option<[f32; 3]>,
-    base_url: &str,
+/// Process pending firmware check data
+fn process_firmware_data(
+    pending: Res<PendingFirmwareData>,
+    mut firmware_state: ResMut<FirmwareCheckState>,
 ) {
+    if let Ok(mut data) = pending.0.lock() {
+        for response in data.drain(..) {
+            // Convert JSON status to our enum
+            let status: FirmwareStatusData = response.status.into();
+            firmware_state.device_status.insert(response.device_id.clone(), status);
+            firmware_state.loading.remove(&response.device_id);
+        }
+    }
+}
+
+/// Trigger firmware check for all devices (called from UI)
+pub fn check_all_firmware(base_url: &str, pending: &PendingFirmwareData) {
     #[cfg(target_arch = "wasm32")]
     {
         use wasm_bindgen_futures::spawn_local;
 
-        let device_id = device_id.to_string();
         let base_url = base_url.to_string();
-        // Convert f32 to f64 for the API
-        let position = [position[0] as f64, position[1] as f64, position[2] as f64];
-        let orientation = orientation.map(|o| [o[0] as f64, o[1] as f64, o[2] as f64]);
+        let pending_clone = pending.0.clone();
 
         spawn_local(async move {
-            let url = format!("{}/api/devices/{}/position", base_url, device_id);
+            let url = format!("{}/api/firmware/check", base_url);
 
-            let body = serde_json::json!({
-                "position": position,
-                "orientation": orientation
-            });
+            tracing::info!("Checking firmware for all devices");
 
-            tracing::warn!(
-                "Updating device {} position to {:?}, orientation {:?}",
-                device_id, position, orientation
-            );
+            match DaemonRequest::get(&url).send().await {
+                Ok(response) => {
+                    if let Ok(text) = response.text().await {
+                        tracing::debug!("Firmware check response: {}", text);
+                        if let Ok(results) = serde_json::from_str::<Vec<FirmwareCheckResponse>>(&text) {
+                            if let Ok(mut data) = pending_clone.lock() {
+                                data.extend(results);
+                            }
+                        }
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("Failed to check firmware: {:?}", e);
+                }
+            }
+        });
+    }
+}
 
-            match gloo_net::http::Request::put(&url)
-                .header("Content-Type", "application/json")
-                .body(body.to_string())
-                .unwrap()
-                .send()
-                .await
-            {
+// ============================================================================
+// OTA Update Functions
+// ============================================================================
+
+/// Pending OTA update events from WebSocket
+#[derive(Resource, Default)]
+pub struct PendingOtaEvents(pub Arc<Mutex<Vec<OtaEvent>>>);
+
+/// Start an OTA firmware update for a device (called from UI)
+pub fn start_ota_update(device_id: &str, base_url: &str) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        use wasm_bindgen_futures::spawn_local;
+
+        let device_id = device_id.to_string();
+        let base_url = base_url.to_string();
+
+        spawn_local(async move {
+            let url = format!("{}/api/ota/{}/start", base_url, device_id);
+
+            tracing::info!("Starting OTA update for device: {}", device_id);
+
+            match DaemonRequest::post(&url).send().await {
                 Ok(response) => {
                     if response.ok() {
-                        tracing::warn!("Device {} position updated successfully", device_id);
+                        tracing::info!("OTA update started for device: {}", device_id);
                     } else {
                         let status = response.status();
                         let text = response.text().await.unwrap_or_default();
-                        tracing::error!(
-                            "Failed to update device {} position: {} - {}",
-                            device_id, status, text
-                        );
+                        tracing::error!("Failed to start OTA update: {} - {}", status, text);
                     }
                 }
                 Err(e) => {
-                    tracing::error!("Failed to update device {} position: {:?}", device_id, e);
+                    tracing::error!("Failed to start OTA update: {:?}", e);
                 }
             }
         });
     }
+}
 
-    #[cfg(not(target_arch = "wasm32"))]
+/// Cancel an OTA firmware update for a device (called from UI)
+pub fn cancel_ota_update(device_id: &str, base_url: &str) {
+    #[cfg(target_arch = "wasm32")]
     {
-        let _ = (device_id, position, orientation, base_url);
-        tracing::warn!("Device position update not available in native mode");
+        use wasm_bindgen_futures::spawn_local;
+
+        let device_id = device_id.to_string();
+        let base_url = base_url.to_string();
+
+        spawn_local(async move {
+            let url = format!("{}/api/ota/{}/cancel", base_url, device_id);
+
+            tracing::info!("Cancelling OTA update for device: {}", device_id);
+
+            match DaemonRequest::post(&url).send().await {
+                Ok(response) => {
+                    if response.ok() {
+                        tracing::info!("OTA update cancelled for device: {}", device_id);
+                    } else {
+                        let status = response.status();
+                        let text = response.text().await.unwrap_or_default();
+                        tracing::error!("Failed to cancel OTA update: {} - {}", status, text);
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("Failed to cancel OTA update: {:?}", e);
+                }
+            }
+        });
+    }
+}
+
+// ============================================================================
+// Local Firmware Upload (for development images)
+// ============================================================================
+
+/// Upload local firmware file to a device (called from UI after file picker)
+pub fn upload_local_firmware(device_id: &str, firmware_data: Vec<u8>, base_url: &str) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        use wasm_bindgen_futures::spawn_local;
+        use base64::Engine;
+
+        let device_id = device_id.to_string();
+        let base_url = base_url.to_string();
+
+        spawn_local(async move {
+            let url = format!("{}/api/ota/{}/upload-local", base_url, device_id);
+
+            tracing::info!("Uploading local firmware for device: {} ({} bytes)", device_id, firmware_data.len());
+
+            // Encode firmware as base64
+            let firmware_base64 = base64::engine::general_purpose::STANDARD.encode(&firmware_data);
+            let body = serde_json::json!({
+                "firmware_base64": firmware_base64
+            });
+
+            match DaemonRequest::post(&url)
+                .header("Content-Type", "application/json")
+                .body(body.to_string())
+                .unwrap()
+                .send()
+                .await
+            {
+                Ok(response) => {
+                    if response.ok() {
+                        tracing::info!("Local firmware upload started for device: {}", device_id);
+                    } else {
+                        let status = response.status();
+                        let text = response.text().await.unwrap_or_default();
+                        tracing::error!("Failed to upload local firmware: {} - {}", status, text);
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("Failed to upload local firmware: {:?}", e);
+                }
+            }
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = (device_id, firmware_data, base_url);
+        tracing::warn!("Local firmware upload not available in native mode");
+    }
+}
+
+/// Upload a .glb to use instead of a device's fragment model
+///
+/// With `for_board`, every device of the same board uses it too.
+pub fn upload_model_override(device_id: &str, filename: &str, model_data: Vec<u8>, for_board: bool, base_url: &str) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        use wasm_bindgen_futures::spawn_local;
+
+        let device_id = device_id.to_string();
+        let base_url = base_url.to_string();
+        let filename = js_sys::encode_uri_component(filename).as_string().unwrap_or_default();
+
+        spawn_local(async move {
+            let url = format!("{}/api/devices/{}/model?board={}&name={}", base_url, device_id, for_board, filename);
+
+            tracing::info!("Uploading model override for device: {} ({} bytes)", device_id, model_data.len());
+
+            let body = js_sys::Uint8Array::from(model_data.as_slice());
+            match DaemonRequest::put(&url)
+                .header("Content-Type", "model/gltf-binary")
+                .body(body)
+                .unwrap()
+                .send()
+                .await
+            {
+                Ok(response) => {
+                    if response.ok() {
+                        tracing::info!("Model override uploaded for device: {}", device_id);
+                    } else {
+                        let status = response.status();
+                        let text = response.text().await.unwrap_or_default();
+                        tracing::error!("Failed to upload model override: {} - {}", status, text);
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("Failed to upload model override: {:?}", e);
+                }
+            }
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = (device_id, filename, model_data, for_board, base_url);
+        tracing::warn!("Model override upload not available in native mode");
+    }
+}
+
+/// Remove a device's model override, including one set for its whole board
+pub fn remove_model_override(device_id: &str, base_url: &str) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        use wasm_bindgen_futures::spawn_local;
+
+        let device_id = device_id.to_string();
+        let base_url = base_url.to_string();
+
+        spawn_local(async move {
+            let url = format!("{}/api/devices/{}/model?board=true", base_url, device_id);
+            match DaemonRequest::delete(&url).build().unwrap().send().await {
+                Ok(response) => {
+                    if response.ok() {
+                        tracing::info!("Model override removed for device: {}", device_id);
+                    } else {
+                        tracing::error!("Failed to remove model override: {}", response.status());
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("Failed to remove model override: {:?}", e);
+                }
+            }
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = (device_id, base_url);
+        tracing::warn!("Model override removal not available in native mode");
+    }
+}
+
+// ============================================================================
+// HCDF Import/Export (for file picker)
+// ============================================================================
+
+/// Pending HCDF export data (used when fetching HCDF for file save)
+#[derive(Resource, Default)]
+pub struct PendingHcdfExport(pub Arc<Mutex<Option<Vec<u8>>>>);
+
+/// Pending URDF export data, converted from the fetched HCDF
+#[derive(Resource, Default)]
+pub struct PendingUrdfExport(pub Arc<Mutex<Option<Vec<u8>>>>);
+
+/// Options for HCDF downloads
+#[derive(Resource)]
+pub struct HcdfExportOptions {
+    /// Keep poses the daemon suggested for devices nobody has placed yet
+    pub include_provisional: bool,
+    /// Reference uploaded model overrides instead of fragment models
+    pub include_model_overrides: bool,
+    /// Comment each element with where it came from
+    pub annotate: bool,
+}
+
+impl Default for HcdfExportOptions {
+    fn default() -> Self {
+        Self { include_provisional: true, include_model_overrides: true, annotate: false }
+    }
+}
+
+/// Export HCDF (fetch from backend for file save)
+///
+/// `toggles` lists (device_id, toggle_group, hidden) for groups whose visibility
+/// differs from the loaded default; they are written as `default_hidden`.
+pub fn export_hcdf(base_url: &str, toggles: Vec<(String, String, bool)>, options: &HcdfExportOptions, pending: &PendingHcdfExport) {
+    fetch_hcdf_export(base_url, toggles, options, pending.0.clone(), |xml| Some(xml.into_bytes()));
+}
+
+/// Export URDF: fetch the HCDF export as for [`export_hcdf`] and convert it here
+pub fn export_urdf(base_url: &str, toggles: Vec<(String, String, bool)>, options: &HcdfExportOptions, pending: &PendingUrdfExport) {
+    fetch_hcdf_export(base_url, toggles, options, pending.0.clone(), |xml| {
+        match dendrite_core::Hcdf::from_xml(&xml).and_then(|hcdf| dendrite_core::export_urdf(&hcdf)) {
+            Ok(urdf) => Some(urdf.into_bytes()),
+            Err(e) => {
+                tracing::error!("Failed to convert HCDF to URDF: {}", e);
+                None
+            }
+        }
+    });
+}
+
+/// Fetch the HCDF export and store `convert(xml)` in `sink`
+fn fetch_hcdf_export(
+    base_url: &str,
+    toggles: Vec<(String, String, bool)>,
+    options: &HcdfExportOptions,
+    sink: Arc<Mutex<Option<Vec<u8>>>>,
+    convert: impl FnOnce(String) -> Option<Vec<u8>> + 'static,
+) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        use wasm_bindgen_futures::spawn_local;
+
+        let base_url = base_url.to_string();
+        let exclude_provisional = !options.include_provisional;
+        let exclude_model_overrides = !options.include_model_overrides;
+        let annotate = options.annotate;
+
+        spawn_local(async move {
+            let url = format!("{}/api/hcdf/export", base_url);
+
+            tracing::info!("Fetching HCDF for export ({} toggle overrides)", toggles.len());
+
+            let body = serde_json::json!({
+                "toggles": toggles.iter().map(|(device_id, toggle, hidden)| serde_json::json!({
+                    "device_id": device_id,
+                    "toggle": toggle,
+                    "hidden": hidden,
+                })).collect::<Vec<_>>(),
+                "exclude_provisional": exclude_provisional,
+                "exclude_model_overrides": exclude_model_overrides,
+                "annotate": annotate,
+            });
+
+            match DaemonRequest::post(&url)
+                .header("Content-Type", "application/json")
+                .body(body.to_string())
+                .unwrap()
+                .send()
+                .await
+            {
+                Ok(response) => {
+                    if response.ok() {
+                        if let Ok(text) = response.text().await {
+                            // Parse as JSON to extract the XML content
+                            if let Ok(json) = serde_json::from_str::<serde_json::Value>(&text) {
+                                if let Some(xml) = json.get("xml").and_then(|v| v.as_str()) {
+                                    if let Some(content) = convert(xml.to_string()) {
+                                        if let Ok(mut data) = sink.lock() {
+                                            *data = Some(content);
+                                        }
+                                        tracing::info!("HCDF export fetched successfully");
+                                    }
+                                }
+                            }
+                        }
+                    } else {
+                        tracing::error!("Failed to export HCDF: {}", response.status());
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("Failed to export HCDF: {:?}", e);
+                }
+            }
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = (base_url, toggles, options, sink, convert);
+        tracing::warn!("HCDF export not available in native mode");
+    }
+}
+
+/// Import HCDF (send to backend from file picker)
+pub fn import_hcdf(xml_content: String, merge: bool, base_url: &str) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        use wasm_bindgen_futures::spawn_local;
+
+        let base_url = base_url.to_string();
+
+        spawn_local(async move {
+            let url = format!("{}/api/hcdf/import", base_url);
+
+            tracing::info!("Importing HCDF ({} bytes, merge={})", xml_content.len(), merge);
+
+            let body = serde_json::json!({
+                "xml": xml_content,
+                "merge": merge
+            });
+
+            match DaemonRequest::post(&url)
+                .header("Content-Type", "application/json")
+                .body(body.to_string())
+                .unwrap()
+                .send()
+                .await
+            {
+                Ok(response) => {
+                    if response.ok() {
+                        tracing::info!("HCDF imported successfully");
+                    } else {
+                        let status = response.status();
+                        let text = response.text().await.unwrap_or_default();
+                        tracing::error!("Failed to import HCDF: {} - {}", status, text);
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("Failed to import HCDF: {:?}", e);
+                }
+            }
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = (xml_content, merge, base_url);
+        tracing::warn!("HCDF import not available in native mode");
+    }
+}
+
+/// Save HCDF to server filesystem (not browser download)
+pub fn save_hcdf_to_server(base_url: &str, filename: Option<&str>) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        use wasm_bindgen_futures::spawn_local;
+
+        let base_url = base_url.to_string();
+        let filename = filename.map(|s| s.to_string());
+
+        spawn_local(async move {
+            let url = format!("{}/api/hcdf/save", base_url);
+
+            tracing::info!("Saving HCDF to server");
+
+            let body = serde_json::json!({
+                "filename": filename
+            });
+
+            match DaemonRequest::post(&url)
+                .header("Content-Type", "application/json")
+                .body(body.to_string())
+                .unwrap()
+                .send()
+                .await
+            {
+                Ok(response) => {
+                    if response.ok() {
+                        if let Ok(text) = response.text().await {
+                            tracing::info!("HCDF saved to server: {}", text);
+                        }
+                    } else {
+                        let status = response.status();
+                        let text = response.text().await.unwrap_or_default();
+                        tracing::error!("Failed to save HCDF to server: {} - {}", status, text);
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("Failed to save HCDF to server: {:?}", e);
+                }
+            }
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = (base_url, filename);
+        tracing::warn!("HCDF save to server not available in native mode");
+    }
+}
+
+// ============================================================================
+// HCDF Regeneration Functions
+// ============================================================================
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RegenerationDeviceJson {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RegenerationFailureJson {
+    pub id: String,
+    pub name: String,
+    pub reason: String,
+}
+
+/// Regeneration preview response from the daemon
+#[derive(Debug, Clone, Deserialize)]
+pub struct RegenerationPreviewJson {
+    pub token: String,
+    pub changes: Vec<HcdfChange>,
+    pub refreshed: Vec<RegenerationDeviceJson>,
+    pub stale: Vec<RegenerationDeviceJson>,
+    pub failed: Vec<RegenerationFailureJson>,
+}
+
+/// Outcome of an async regeneration request
+#[derive(Debug, Clone)]
+pub enum RegenerationResult {
+    Preview(RegenerationPreviewJson),
+    Applied(usize),
+    Failed(String),
+}
+
+/// Pending regeneration result from async fetch
+#[derive(Resource, Default)]
+pub struct PendingRegeneration(pub Arc<Mutex<Option<RegenerationResult>>>);
+
+/// State of the "Refresh from devices" flow
+#[derive(Resource, Default)]
+pub struct HcdfRegeneration {
+    /// A preview or apply request is in flight
+    pub in_progress: bool,
+    /// Preview waiting for the user to confirm or cancel
+    pub preview: Option<RegenerationPreviewJson>,
+}
+
+/// Process completed regeneration requests
+fn process_regeneration_data(
+    pending: Res<PendingRegeneration>,
+    mut regeneration: ResMut<HcdfRegeneration>,
+    mut notifications: ResMut<crate::app::Notifications>,
+) {
+    let Ok(mut data) = pending.0.lock() else {
+        return;
+    };
+    let Some(result) = data.take() else {
+        return;
+    };
+
+    regeneration.in_progress = false;
+    match result {
+        RegenerationResult::Preview(preview) => regeneration.preview = Some(preview),
+        RegenerationResult::Applied(count) => {
+            notifications.push(format!("Refreshed HCDF from {} device(s)", count));
+        }
+        RegenerationResult::Failed(message) => {
+            notifications.push(format!("HCDF refresh failed: {}", message));
+        }
+    }
+}
+
+/// Ask the daemon to re-query all online devices and preview the merged HCDF
+pub fn request_hcdf_regeneration(base_url: &str, pending: &PendingRegeneration) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        use wasm_bindgen_futures::spawn_local;
+
+        let pending_clone = pending.0.clone();
+        let url = format!("{}/api/hcdf/regenerate", base_url);
+
+        spawn_local(async move {
+            tracing::info!("Requesting HCDF regeneration preview");
+
+            let result = match DaemonRequest::post(&url).send().await {
+                Ok(response) if response.ok() => match response.text().await {
+                    Ok(text) => match serde_json::from_str::<RegenerationPreviewJson>(&text) {
+                        Ok(preview) => RegenerationResult::Preview(preview),
+                        Err(e) => RegenerationResult::Failed(format!("Invalid preview: {}", e)),
+                    },
+                    Err(e) => RegenerationResult::Failed(format!("{:?}", e)),
+                },
+                Ok(response) => RegenerationResult::Failed(format!("HTTP {}", response.status())),
+                Err(e) => RegenerationResult::Failed(format!("{:?}", e)),
+            };
+
+            if let RegenerationResult::Failed(ref message) = result {
+                tracing::error!("Failed to preview HCDF regeneration: {}", message);
+            }
+            if let Ok(mut data) = pending_clone.lock() {
+                *data = Some(result);
+            }
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = (base_url, pending);
+        tracing::warn!("HCDF regeneration not available in native mode");
+    }
+}
+
+/// Apply a previously fetched regeneration preview
+pub fn apply_hcdf_regeneration(token: &str, base_url: &str, pending: &PendingRegeneration) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        use wasm_bindgen_futures::spawn_local;
+
+        let pending_clone = pending.0.clone();
+        let url = format!("{}/api/hcdf/regenerate/apply", base_url);
+        let body = serde_json::json!({ "token": token });
+
+        spawn_local(async move {
+            let result = match DaemonRequest::post(&url)
+                .header("Content-Type", "application/json")
+                .body(body.to_string())
+                .unwrap()
+                .send()
+                .await
+            {
+                Ok(response) if response.ok() => {
+                    let text = response.text().await.unwrap_or_default();
+                    let count = serde_json::from_str::<serde_json::Value>(&text)
+                        .ok()
+                        .and_then(|json| json.get("devices").and_then(|d| d.as_array()).map(|d| d.len()))
+                        .unwrap_or(0);
+                    tracing::info!("HCDF regeneration applied to {} devices", count);
+                    RegenerationResult::Applied(count)
+                }
+                Ok(response) => {
+                    let status = response.status();
+                    let text = response.text().await.unwrap_or_default();
+                    let message = serde_json::from_str::<serde_json::Value>(&text)
+                        .ok()
+                        .and_then(|json| json.get("error").and_then(|e| e.as_str()).map(str::to_string))
+                        .unwrap_or_else(|| format!("HTTP {}", status));
+                    tracing::error!("Failed to apply HCDF regeneration: {}", message);
+                    RegenerationResult::Failed(message)
+                }
+                Err(e) => {
+                    tracing::error!("Failed to apply HCDF regeneration: {:?}", e);
+                    RegenerationResult::Failed(format!("{:?}", e))
+                }
+            };
+
+            if let Ok(mut data) = pending_clone.lock() {
+                *data = Some(result);
+            }
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = (token, base_url, pending);
+        tracing::warn!("HCDF regeneration not available in native mode");
+    }
+}
+
+// ============================================================================
+// HCDF Import Preview
+// ============================================================================
+
+/// Validation warning reported with an import preview
+#[derive(Debug, Clone, Deserialize)]
+pub struct ImportWarningJson {
+    pub line: usize,
+    pub message: String,
+}
+
+/// Import preview response from the daemon
+#[derive(Debug, Clone, Deserialize)]
+pub struct ImportDiffJson {
+    pub changes: Vec<HcdfChange>,
+    #[serde(default)]
+    pub validation_warnings: Vec<ImportWarningJson>,
+}
+
+/// File picked for import, with the changes it would make
+#[derive(Debug, Clone)]
+pub struct ImportPreviewData {
+    pub filename: String,
+    pub xml: String,
+    pub diff: ImportDiffJson,
+}
+
+/// Pending import preview from async fetch
+#[derive(Resource, Default)]
+pub struct PendingImportPreview(pub Arc<Mutex<Option<Result<ImportPreviewData, String>>>>);
+
+/// State of the "Import HCDF" flow
+#[derive(Resource, Default)]
+pub struct ImportPreview {
+    /// A preview request is in flight
+    pub in_progress: bool,
+    /// Preview waiting for the user to confirm or cancel
+    pub preview: Option<ImportPreviewData>,
+}
+
+/// Process completed import previews
+fn process_import_preview(
+    pending: Res<PendingImportPreview>,
+    mut import: ResMut<ImportPreview>,
+    mut notifications: ResMut<crate::app::Notifications>,
+) {
+    let Ok(mut data) = pending.0.lock() else {
+        return;
+    };
+    let Some(result) = data.take() else {
+        return;
+    };
+
+    import.in_progress = false;
+    match result {
+        Ok(preview) => import.preview = Some(preview),
+        Err(message) => notifications.push(format!("HCDF import failed: {}", message)),
+    }
+}
+
+/// Ask the daemon what importing a file would change, without applying it
+pub fn preview_hcdf_import(filename: String, xml: String, base_url: &str, pending: &PendingImportPreview) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        use wasm_bindgen_futures::spawn_local;
+
+        let pending_clone = pending.0.clone();
+        let url = format!("{}/api/hcdf/diff", base_url);
+
+        spawn_local(async move {
+            tracing::info!("Previewing HCDF import: {} ({} bytes)", filename, xml.len());
+
+            let body = serde_json::json!({ "xml": xml, "merge": false });
+            let result = match DaemonRequest::post(&url)
+                .header("Content-Type", "application/json")
+                .body(body.to_string())
+                .unwrap()
+                .send()
+                .await
+            {
+                Ok(response) if response.ok() => match response.text().await {
+                    Ok(text) => serde_json::from_str::<ImportDiffJson>(&text)
+                        .map(|diff| ImportPreviewData { filename, xml, diff })
+                        .map_err(|e| format!("Invalid preview: {}", e)),
+                    Err(e) => Err(format!("{:?}", e)),
+                },
+                Ok(response) => {
+                    let status = response.status();
+                    let text = response.text().await.unwrap_or_default();
+                    Err(serde_json::from_str::<serde_json::Value>(&text)
+                        .ok()
+                        .and_then(|json| json.get("error").and_then(|e| e.as_str()).map(str::to_string))
+                        .unwrap_or_else(|| format!("HTTP {}", status)))
+                }
+                Err(e) => Err(format!("{:?}", e)),
+            };
+
+            if let Err(ref message) = result {
+                tracing::error!("Failed to preview HCDF import: {}", message);
+            }
+            if let Ok(mut data) = pending_clone.lock() {
+                *data = Some(result);
+            }
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = (filename, xml, base_url, pending);
+        tracing::warn!("HCDF import not available in native mode");
+    }
+}
+
+// ============================================================================
+// Fragment Reload
+// ============================================================================
+
+/// Pending fragment reload result from async fetch
+#[derive(Resource, Default)]
+pub struct PendingFragmentReload(pub Arc<Mutex<Option<Result<FragmentReloadResponse, String>>>>);
+
+/// State of the "Refresh fragments" button
+#[derive(Resource, Default)]
+pub struct FragmentReload {
+    /// A reload request is in flight
+    pub in_progress: bool,
+}
+
+/// Process completed fragment reload requests
+///
+/// Failed fragments and model refreshes arrive over the WebSocket, which also
+/// covers reloads started by the daemon's fragment watcher.
+fn process_fragment_reload(
+    pending: Res<PendingFragmentReload>,
+    mut reload: ResMut<FragmentReload>,
+    mut notifications: ResMut<crate::app::Notifications>,
+) {
+    let Ok(mut data) = pending.0.lock() else {
+        return;
+    };
+    let Some(result) = data.take() else {
+        return;
+    };
+
+    reload.in_progress = false;
+    match result {
+        Ok(response) => notifications.push(format!(
+            "Reloaded {} fragment(s): {} added, {} changed, {} removed, {} device(s) updated",
+            response.fragments,
+            response.added.len(),
+            response.changed.len(),
+            response.removed.len(),
+            response.devices.len()
+        )),
+        Err(message) => notifications.push(format!("Fragment reload failed: {}", message)),
+    }
+}
+
+/// Ask the daemon to reload its fragment database from disk
+pub fn reload_fragments(base_url: &str, pending: &PendingFragmentReload) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        use wasm_bindgen_futures::spawn_local;
+
+        let pending_clone = pending.0.clone();
+        let url = format!("{}/api/fragments/reload", base_url);
+
+        spawn_local(async move {
+            let result = match DaemonRequest::post(&url).send().await {
+                Ok(response) if response.ok() => match response.text().await {
+                    Ok(text) => serde_json::from_str::<FragmentReloadResponse>(&text)
+                        .map_err(|e| format!("Invalid response: {}", e)),
+                    Err(e) => Err(format!("{:?}", e)),
+                },
+                Ok(response) => {
+                    let status = response.status();
+                    let text = response.text().await.unwrap_or_default();
+                    Err(serde_json::from_str::<serde_json::Value>(&text)
+                        .ok()
+                        .and_then(|json| json.get("error").and_then(|e| e.as_str()).map(str::to_string))
+                        .unwrap_or_else(|| format!("HTTP {}", status)))
+                }
+                Err(e) => Err(format!("{:?}", e)),
+            };
+
+            if let Err(ref message) = result {
+                tracing::error!("Failed to reload fragments: {}", message);
+            }
+            if let Ok(mut data) = pending_clone.lock() {
+                *data = Some(result);
+            }
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = (base_url, pending);
+        tracing::warn!("Fragment reload not available in native mode");
+    }
+}
+
+/// Minimum interval between position sync requests while a value is being dragged
+pub const POSITION_SYNC_INTERVAL_SECS: f64 = 0.2;
+
+/// A pending pose update for one device (matches the daemon's batch entry format)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PoseUpdate {
+    pub id: String,
+    pub position: [f64; 3],
+    pub orientation: Option<[f64; 3]>,
+    /// Sequence number of the batch carrying it; the daemon discards a pose
+    /// older than the last one this client applied to the device
+    pub seq: u64,
+    /// This page's session, which `seq` counts in
+    pub client: String,
+}
+
+/// Coalesces position/orientation edits per device and throttles how often they
+/// are sent to the daemon. The UI queues every change; `flush_position_sync`
+/// sends at most one batch per `POSITION_SYNC_INTERVAL_SECS`, plus a final batch
+/// as soon as the drag is released. Each batch gets the next sequence number, so
+/// a batch overtaken by a later one on the way is dropped by the daemon. The
+/// numbers count per page load, under a random client session ID.
+#[derive(Resource)]
+pub struct PositionSync {
+    /// Latest pose per device, keyed by device ID (only the newest value is kept)
+    pending: Vec<PoseUpdate>,
+    /// Time (seconds since startup) of the last batch sent
+    last_sent: Option<f64>,
+    /// Set when the user releases a drag, forcing the next flush regardless of the interval
+    final_requested: bool,
+    /// Client session the daemon orders this page's poses in
+    client: String,
+    /// Sequence number of the last batch sent
+    seq: u64,
+}
+
+impl Default for PositionSync {
+    /// Starts a new client session, so its sequence numbers are never
+    /// compared with another tab's or an earlier page load's
+    fn default() -> Self {
+        #[cfg(target_arch = "wasm32")]
+        let random = (js_sys::Math::random() * u64::MAX as f64) as u64;
+        #[cfg(not(target_arch = "wasm32"))]
+        let random = {
+            use std::hash::BuildHasher;
+            std::collections::hash_map::RandomState::new().hash_one(0u8)
+        };
+
+        Self { pending: Vec::new(), last_sent: None, final_requested: false, client: format!("{:016x}", random), seq: 0 }
+    }
+}
+
+impl PositionSync {
+    /// Queue the latest pose for a device, replacing any unsent pose for it
+    pub fn queue(&mut self, device_id: &str, position: [f32; 3], orientation: Option<[f32; 3]>) {
+        let update = PoseUpdate {
+            id: device_id.to_string(),
+            position: [position[0] as f64, position[1] as f64, position[2] as f64],
+            orientation: orientation.map(|o| [o[0] as f64, o[1] as f64, o[2] as f64]),
+            seq: 0,
+            client: String::new(),
+        };
+        if let Some(existing) = self.pending.iter_mut().find(|u| u.id == update.id) {
+            *existing = update;
+        } else {
+            self.pending.push(update);
+        }
+    }
+
+    /// Whether an edited pose for a device hasn't been sent yet
+    pub fn is_pending(&self, device_id: &str) -> bool {
+        self.pending.iter().any(|u| u.id == device_id)
+    }
+
+    /// Mark the end of a drag so pending poses are sent on the next flush
+    pub fn finish(&mut self) {
+        self.final_requested = true;
+    }
+
+    /// Take the batch to send at time `now` (seconds), if one is due
+    pub fn take_due(&mut self, now: f64) -> Option<Vec<PoseUpdate>> {
+        if self.pending.is_empty() {
+            self.final_requested = false;
+            return None;
+        }
+
+        let interval_elapsed = self
+            .last_sent
+            .map(|t| now - t >= POSITION_SYNC_INTERVAL_SECS)
+            .unwrap_or(true);
+
+        if !(interval_elapsed || self.final_requested) {
+            return None;
+        }
+
+        self.last_sent = Some(now);
+        self.final_requested = false;
+        self.seq += 1;
+        let mut batch = std::mem::take(&mut self.pending);
+        for update in &mut batch {
+            update.seq = self.seq;
+            update.client.clone_from(&self.client);
+        }
+        Some(batch)
+    }
+}
+
+/// Send throttled position updates to the daemon
+fn flush_position_sync(
+    time: Res<Time>,
+    mut position_sync: ResMut<PositionSync>,
+    daemons: Res<Daemons>,
+) {
+    if let Some(updates) = position_sync.take_due(time.elapsed_secs_f64()) {
+        for (base_url, batch) in daemons.route_poses(updates) {
+            let Some(link) = daemons.links.iter().find(|l| l.config.http_url == base_url) else {
+                continue;
+            };
+            send_position_batch(batch, &base_url, &link.messages);
+        }
+    }
+}
+
+/// Update device positions and orientations on the backend in a single request
+/// This syncs position changes to the HCDF so they're persisted on export
+///
+/// Poses the daemon reports as stale lost to a newer one from this page;
+/// those devices are fetched again, so the scene shows what the daemon kept.
+fn send_position_batch(updates: Vec<PoseUpdate>, base_url: &str, pending: &PendingMessages) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        use wasm_bindgen_futures::spawn_local;
+
+        let base_url = base_url.to_string();
+        let pending = pending.clone();
+
+        spawn_local(async move {
+            let url = format!("{}/api/devices/positions", base_url);
+
+            let body = serde_json::json!({ "updates": updates });
+
+            tracing::debug!("Syncing {} device position(s)", updates.len());
+
+            match DaemonRequest::put(&url)
+                .header("Content-Type", "application/json")
+                .body(body.to_string())
+                .unwrap()
+                .send()
+                .await
+            {
+                Ok(response) if response.ok() => {
+                    let reply = response.json::<serde_json::Value>().await.unwrap_or_default();
+                    for id in stale_pose_ids(&reply) {
+                        tracing::debug!("Position update for {} was stale, fetching the daemon's pose", id);
+                        fetch_device(&base_url, id, &pending);
+                    }
+                }
+                Ok(response) => {
+                    let status = response.status();
+                    let text = response.text().await.unwrap_or_default();
+                    tracing::error!("Failed to update device positions: {} - {}", status, text);
+                }
+                Err(e) => {
+                    tracing::error!("Failed to update device positions: {:?}", e);
+                }
+            }
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = (updates, base_url, pending);
+        tracing::warn!("Device position update not available in native mode");
+    }
+}
+
+/// Devices whose pose a batch position reply reports as stale
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+fn stale_pose_ids(reply: &serde_json::Value) -> Vec<&str> {
+    reply["stale"].as_array().into_iter().flatten().filter_map(|id| id.as_str()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dendrite_api_types::fixtures;
+
+    #[test]
+    fn test_device_fixture_parses() {
+        let message = format!(r#"{{"type": "device_discovered", "data": {}}}"#, fixtures::DEVICE);
+        let Ok(WsMessage::DeviceDiscovered(device)) = serde_json::from_str::<WsMessage>(&message) else {
+            panic!("device_discovered fixture did not parse");
+        };
+
+        // A second daemon's copy is namespaced and shifted into its region
+        let remote = localize_device(device.clone(), 2, Vec3::new(10.0, 0.0, 0.0));
+        assert_eq!(remote.id.0, "d2/0x1a2b3c4d");
+        assert_eq!(remote.pose.unwrap()[0], 10.25);
+
+        let data: DeviceData = device.into();
+        assert_eq!(data.id, "0x1a2b3c4d");
+        assert_eq!(data.status, DeviceStatus::Online);
+        assert_eq!(data.board.as_deref(), Some("mr_mcxn_t1"));
+        assert_eq!(data.version.as_deref(), Some("1.2.0"));
+        assert_eq!(data.position, Some([0.25, 0.0, 0.05]));
+        assert_eq!(data.heartbeat_method.as_deref(), Some("smp-echo"));
+        assert_eq!(data.visuals.len(), 2);
+        assert_eq!(data.frames[1].parent.as_deref(), Some("sensor"));
+        assert!(data.ports[0].original_pose.is_some());
+        assert!(matches!(data.ports[0].geometry[0], GeometryData::Box { .. }));
+        assert_eq!(data.sensors[0].fovs.len(), 1);
+    }
+
+    #[test]
+    fn test_compact_snapshot_parses_like_full_messages() {
+        let message = format!(r#"{{"type": "device_discovered", "data": {}}}"#, fixtures::DEVICE);
+        let Ok(WsMessage::DeviceDiscovered(full)) = serde_json::from_str::<WsMessage>(&message) else {
+            panic!("device_discovered fixture did not parse");
+        };
+
+        let value: serde_json::Value = serde_json::from_str(&message).unwrap();
+        let snapshot = CompactSnapshot::compact(vec![value.clone(), value]);
+        let text = serde_json::json!({ "type": SNAPSHOT_MESSAGE_TYPE, "data": snapshot }).to_string();
+        assert!(text.len() < message.len() * 2);
+
+        let messages = parse_ws_text(&text, &mut DeviceRecords::default());
+        assert_eq!(messages.len(), 2);
+        for msg in messages {
+            let WsMessage::DeviceDiscovered(device) = msg else {
+                panic!("expected device_discovered");
+            };
+            assert_eq!(device, full);
+        }
+    }
+
+    #[test]
+    fn test_refetch_queues_changed_devices_only() {
+        let serde_json::Value::Object(record) = serde_json::from_str(fixtures::DEVICE).unwrap() else {
+            panic!("fixture is not an object");
+        };
+        let mut records = DeviceRecords::default();
+        let messages = refetched_changes(&mut records, vec![record.clone()]);
+        let [WsMessage::DeviceChanged(change)] = messages.as_slice() else {
+            panic!("expected one device change");
+        };
+        assert!(change.changed.iter().any(|c| c == "visuals"));
+        assert!(refetched_changes(&mut records, vec![record.clone()]).is_empty());
+
+        let mut offline = record;
+        offline.insert("status".to_string(), "offline".into());
+        let messages = refetched_changes(&mut records, vec![offline]);
+        let [WsMessage::DeviceChanged(change)] = messages.as_slice() else {
+            panic!("expected one device change");
+        };
+        assert_eq!(change.changed, ["status"]);
+        assert_eq!(change.device.visuals.len(), 2);
+    }
+
+    #[test]
+    fn test_refetch_corrects_the_websocket_records() {
+        let serde_json::Value::Object(record) = serde_json::from_str(fixtures::DEVICE).unwrap() else {
+            panic!("fixture is not an object");
+        };
+        let mut records = DeviceRecords::default();
+        let sync = FullSync { protocol: DELTA_PROTOCOL_VERSION, devices: CompactSnapshot::compact(vec![serde_json::Value::Object(record.clone())]) };
+        parse_ws_text(&serde_json::json!({ "type": "full_sync", "data": sync }).to_string(), &mut records);
+
+        // The device went offline and came back while the delta was lost;
+        // the WebSocket's record still says offline
+        let text = r#"{"type": "status_changed", "data": {"id": "0x1a2b3c4d", "status": "offline"}}"#;
+        parse_ws_text(text, &mut records);
+        let messages = refetched_changes(&mut records, vec![record.clone()]);
+        let [WsMessage::DeviceChanged(change)] = messages.as_slice() else {
+            panic!("expected one device change");
+        };
+        assert_eq!(change.changed, ["status"]);
+        assert_eq!(change.device.status, "online");
+
+        // Removed while the socket was down
+        let messages = refetched_changes(&mut records, Vec::new());
+        let [WsMessage::DeviceRemoved { id }] = messages.as_slice() else {
+            panic!("expected one removal");
+        };
+        assert_eq!(id, "0x1a2b3c4d");
+        assert!(records.get("0x1a2b3c4d").is_none());
+    }
+
+    #[test]
+    fn test_deltas_apply_to_connection_records() {
+        let record: serde_json::Value = serde_json::from_str(fixtures::DEVICE).unwrap();
+        let sync = FullSync { protocol: DELTA_PROTOCOL_VERSION, devices: CompactSnapshot::compact(vec![record]) };
+        let mut records = DeviceRecords::default();
+
+        let text = serde_json::json!({ "type": "full_sync", "data": sync }).to_string();
+        let messages = parse_ws_text(&text, &mut records);
+        let [WsMessage::DeviceSync(changes)] = messages.as_slice() else {
+            panic!("expected one device sync");
+        };
+        assert_eq!(changes.len(), 1);
+        assert!(changes[0].changed.iter().any(|c| c == "visuals"));
+
+        let text = r#"{"type": "device_delta", "data": {"id": "0x1a2b3c4d", "changed_fields": {"pose": [1, 2, 0, 0, 0, 0]}}}"#;
+        let messages = parse_ws_text(text, &mut records);
+        let [WsMessage::DeviceChanged(change)] = messages.as_slice() else {
+            panic!("expected one device change");
+        };
+        assert_eq!(change.changed, ["pose"]);
+        assert_eq!(change.device.pose, Some([1.0, 2.0, 0.0, 0.0, 0.0, 0.0]));
+        assert_eq!(change.device.visuals.len(), 2);
+
+        let text = r#"{"type": "status_changed", "data": {"id": "0x1a2b3c4d", "status": "offline"}}"#;
+        let messages = parse_ws_text(text, &mut records);
+        let [WsMessage::DeviceChanged(change)] = messages.as_slice() else {
+            panic!("expected one device change");
+        };
+        assert_eq!(change.device.status, "offline");
+        assert_eq!(change.device.pose, Some([1.0, 2.0, 0.0, 0.0, 0.0, 0.0]));
+
+        // Removed devices take their record with them
+        assert!(matches!(
+            parse_ws_text(r#"{"type": "device_removed", "data": {"id": "0x1a2b3c4d"}}"#, &mut records).as_slice(),
+            [WsMessage::DeviceRemoved { .. }]
+        ));
+        let text = r#"{"type": "status_changed", "data": {"id": "0x1a2b3c4d", "status": "online"}}"#;
+        assert!(parse_ws_text(text, &mut records).is_empty());
+    }
+
+    #[test]
+    fn test_delta_keeps_locally_edited_pose() {
+        let message = format!(r#"{{"type": "device_discovered", "data": {}}}"#, fixtures::DEVICE);
+        let Ok(WsMessage::DeviceDiscovered(device)) = serde_json::from_str::<WsMessage>(&message) else {
+            panic!("device_discovered fixture did not parse");
+        };
+        let mut existing: DeviceData = device.clone().into();
+        existing.position = Some([5.0, 5.0, 0.0]);
+
+        // A status change leaves the user's placement alone
+        let mut update: DeviceData = device.clone().into();
+        update.status = DeviceStatus::Offline;
+        update.visuals.clear();
+        merge_changed(&mut existing, update, &["status".to_string()], false);
+        assert_eq!(existing.status, DeviceStatus::Offline);
+        assert_eq!(existing.position, Some([5.0, 5.0, 0.0]));
+        assert_eq!(existing.visuals.len(), 2);
+
+        // A moved device follows the daemon, unless the user is still dragging it
+        let mut update: DeviceData = device.into();
+        update.position = Some([1.0, 0.0, 0.0]);
+        merge_changed(&mut existing, update.clone(), &["pose".to_string()], true);
+        assert_eq!(existing.position, Some([5.0, 5.0, 0.0]));
+        merge_changed(&mut existing, update, &["pose".to_string()], false);
+        assert_eq!(existing.position, Some([1.0, 0.0, 0.0]));
+    }
+
+    #[test]
+    fn test_scan_lifecycle_messages_parse() {
+        let Ok(WsMessage::ScanProgress { done, total }) =
+            serde_json::from_str::<WsMessage>(r#"{"type": "scan_progress", "done": 45, "total": 254}"#)
+        else {
+            panic!("scan_progress did not parse");
+        };
+        assert_eq!((done, total), (45, 254));
+
+        // Daemons from before progress reporting send no host count
+        let Ok(WsMessage::ScanStarted { total_hosts }) = serde_json::from_str::<WsMessage>(r#"{"type": "scan_started"}"#)
+        else {
+            panic!("scan_started did not parse");
+        };
+        assert_eq!(total_hosts, 0);
+    }
+
+    #[test]
+    fn test_fragments_reloaded_is_namespaced() {
+        let message = r#"{"type": "fragments_reloaded", "data": {"devices": ["0x1a2b"], "failed": []}}"#;
+        let Ok(msg) = serde_json::from_str::<WsMessage>(message) else {
+            panic!("fragments_reloaded did not parse");
+        };
+        let WsMessage::FragmentsReloaded { devices, failed } = msg.localize(3, Vec3::ZERO) else {
+            panic!("fragments_reloaded changed type");
+        };
+        assert_eq!(devices, ["d3/0x1a2b"]);
+        assert!(failed.is_empty());
+    }
+
+    #[test]
+    fn test_firmware_and_ota_fixtures_parse() {
+        let response: FirmwareCheckResponse = serde_json::from_str(fixtures::FIRMWARE_CHECK).unwrap();
+        assert_eq!(
+            FirmwareStatusData::from(response.status),
+            FirmwareStatusData::UpdateAvailable {
+                latest_version: "1.3.0".to_string(),
+                changelog: Some("Faster flow estimation".to_string()),
+            }
+        );
+
+        let Ok(WsMessage::OtaProgress(event)) = serde_json::from_str::<WsMessage>(fixtures::OTA_PROGRESS) else {
+            panic!("ota_progress fixture did not parse");
+        };
+        assert_eq!(event.device_id, "0x1a2b3c4d");
+        assert_eq!(event.state.progress_value(), Some(0.5));
+    }
+
+    #[test]
+    fn test_position_burst_is_throttled() {
+        let mut sync = PositionSync::default();
+        let mut batches = Vec::new();
+
+        // 100 drag frames at ~60 fps
+        for i in 0..100 {
+            let now = i as f64 / 60.0;
+            sync.queue("dev-1", [i as f32, 0.0, 0.0], Some([0.0, 0.0, 0.0]));
+            if let Some(batch) = sync.take_due(now) {
+                batches.push(batch);
+            }
+        }
+
+        // Release: final update goes out immediately
+        sync.finish();
+        if let Some(batch) = sync.take_due(100.0 / 60.0) {
+            batches.push(batch);
+        }
+
+        // ~1.65 s of dragging at 200 ms -> at most 9 requests, plus the final one
+        let duration = 100.0 / 60.0;
+        let max_calls = (duration / POSITION_SYNC_INTERVAL_SECS).ceil() as usize + 1;
+        assert!(batches.len() <= max_calls, "{} calls exceeds {}", batches.len(), max_calls);
+        assert!(batches.len() > 1);
+
+        // Every batch coalesces to a single entry for the device
+        assert!(batches.iter().all(|b| b.len() == 1));
+
+        // The last batch carries the final pose, and nothing is left pending
+        let last = batches.last().unwrap();
+        assert_eq!(last[0].position, [99.0, 0.0, 0.0]);
+        assert!(sync.take_due(1000.0).is_none());
+    }
+
+    #[test]
+    fn test_batches_are_numbered_within_a_client_session() {
+        let mut sync = PositionSync::default();
+        let mut sent = Vec::new();
+        for i in 0..3 {
+            sync.queue("a", [i as f32, 0.0, 0.0], None);
+            sync.finish();
+            sent.extend(sync.take_due(i as f64));
+        }
+        let seqs: Vec<_> = sent.iter().map(|batch| batch[0].seq).collect();
+        assert_eq!(seqs, [1, 2, 3]);
+        assert!(sent.iter().all(|batch| batch[0].client == sent[0][0].client));
+
+        // Another page load numbers from 1 again, under its own session
+        let mut other = PositionSync::default();
+        other.queue("a", [9.0, 0.0, 0.0], None);
+        let batch = other.take_due(0.0).unwrap();
+        assert_eq!(batch[0].seq, 1);
+        assert_ne!(batch[0].client, sent[0][0].client);
+
+        let reply = serde_json::json!({ "status": "updated", "updated": ["b"], "stale": ["a"], "not_found": [] });
+        assert_eq!(stale_pose_ids(&reply), ["a"]);
+    }
+
+    fn daemon_version(ws_protocol: u32) -> DaemonVersion {
+        DaemonVersion {
+            version: "0.1.0".to_string(),
+            git_hash: "abc123".to_string(),
+            features: Vec::new(),
+            hcdf_versions: vec!["2.1".to_string()],
+            ws_protocol,
+        }
+    }
+
+    #[test]
+    fn test_protocol_compatibility() {
+        assert_eq!(ProtocolCompatibility::check(2, Some(&daemon_version(2))), ProtocolCompatibility::Compatible);
+        assert!(ProtocolCompatibility::check(2, Some(&daemon_version(2))).warning().is_none());
+
+        // Newer frontend against an older daemon warns
+        let older = ProtocolCompatibility::check(3, Some(&daemon_version(2)));
+        assert_eq!(older, ProtocolCompatibility::FrontendNewer { frontend: 3, daemon: Some(2) });
+        assert!(older.warning().unwrap().contains("v2"));
+
+        // Daemons without the version endpoint are treated as older
+        let missing = ProtocolCompatibility::check(1, None);
+        assert_eq!(missing, ProtocolCompatibility::FrontendNewer { frontend: 1, daemon: None });
+        assert!(missing.warning().is_some());
+
+        // A newer daemon still serves everything this frontend uses
+        let newer = ProtocolCompatibility::check(1, Some(&daemon_version(4)));
+        assert_eq!(newer, ProtocolCompatibility::DaemonNewer { frontend: 1, daemon: 4 });
+        assert!(newer.warning().is_none());
+    }
+
+    #[test]
+    fn test_daemon_version_parses_minimal_payload() {
+        let version: DaemonVersion = serde_json::from_str(r#"{"version":"0.2.0","ws_protocol":1}"#).unwrap();
+        assert_eq!(version.ws_protocol, 1);
+        assert!(version.features.is_empty());
+    }
+
+    #[test]
+    fn test_position_updates_coalesce_per_device() {
+        let mut sync = PositionSync::default();
+        assert!(sync.take_due(0.0).is_none());
+
+        sync.queue("a", [1.0, 0.0, 0.0], None);
+        sync.queue("b", [2.0, 0.0, 0.0], None);
+        sync.queue("a", [3.0, 0.0, 0.0], None);
+
+        let batch = sync.take_due(0.0).unwrap();
+        assert_eq!(batch.len(), 2);
+        assert_eq!(batch[0].position, [3.0, 0.0, 0.0]);
+        assert_eq!(batch[1].position, [2.0, 0.0, 0.0]);
+
+        // Within the interval nothing is sent until release
+        sync.queue("a", [4.0, 0.0, 0.0], None);
+        assert!(sync.take_due(0.05).is_none());
+        sync.finish();
+        assert_eq!(sync.take_due(0.06).unwrap()[0].position, [4.0, 0.0, 0.0]);
+    }
+
+    fn device_message(kind: &str, name: &str, visuals: &str) -> WsMessage {
+        let json = format!(
+            r#"{{"type": "{kind}", "data": {{
+                "id": "a1b2", "name": "{name}", "status": "online",
+                "discovery": {{"ip": "10.0.0.2", "port": 1337, "switch_port": null, "last_seen": null}},
+                "info": {{"board": "mr_mcxn_t1"}}, "firmware": {{"version": "1.4.2"}},
+                "model_path": null, "pose": [0.1, 0, 0, 0, 0, 0]{visuals}
+            }}}}"#
+        );
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn test_summary_keeps_cached_visuals() {
+        let WsMessage::DeviceDiscovered(full) =
+            device_message("device_discovered", "gps", r#", "visuals": [{"name": "board", "model_path": "models/board.glb"}]"#)
+        else {
+            panic!("expected device_discovered");
+        };
+        let mut cached: DeviceData = full.into();
+        assert_eq!(cached.visuals.len(), 1);
+
+        let WsMessage::DeviceSummary(summary) = device_message("device_summary", "gps-renamed", "") else {
+            panic!("expected device_summary");
+        };
+        merge_summary(&mut cached, summary.into());
+        assert_eq!(cached.name, "gps-renamed");
+        assert_eq!(cached.visuals.len(), 1);
+        assert_eq!(cached.visuals[0].model_path.as_deref(), Some("models/board.glb"));
+    }
+
+    #[test]
+    fn test_data_usage_label() {
+        assert_eq!(DataUsage { bytes: 512, elapsed_secs: 10.0 }.label(), "512 B");
+        assert_eq!(DataUsage { bytes: 1536, elapsed_secs: 10.0 }.label(), "1.5 KB");
+        // 1 MB in 6 minutes
+        assert_eq!(DataUsage { bytes: 1024 * 1024, elapsed_secs: 360.0 }.label(), "1.0 MB (~10.0 MB/h)");
+    }
+
+    #[test]
+    fn test_access_token_goes_only_to_its_daemon() {
+        let config = DaemonConfig { token: Some("s3/cret".to_string()), ..DaemonConfig::from_daemon_address("10.9.0.1:80") };
+        register_access_token(&config);
+
+        assert_eq!(access_token_for("http://10.9.0.1:80/api/devices").as_deref(), Some("s3/cret"));
+        assert_eq!(access_token_for("http://10.9.0.1:8080/api/devices"), None);
+        assert_eq!(access_token_for("http://10.9.0.2:80/api/devices"), None);
+
+        // The WebSocket can't carry headers, so it gets the token in its query
+        let url = subscription_ws_url(&config, true);
+        assert_eq!(url, format!("ws://10.9.0.1:80/ws?compact=true&protocol={}&lite=true&token=s3%2Fcret", DELTA_PROTOCOL_VERSION));
+        assert_eq!(percent_decode("s3%2Fcret"), "s3/cret");
+        assert_eq!(percent_decode("100%"), "100%");
+
+        register_access_token(&DaemonConfig::from_daemon_address("10.9.0.1:80"));
+        assert_eq!(access_token_for("http://10.9.0.1:80/api/devices"), None);
+    }
+
+    #[test]
+    fn test_console_strips_ansi_and_keeps_scrollback() {
+        let mut console = DeviceConsole::default();
+        console.append("\x1b[1;32muart:~$ \x1b[m");
+        console.append("kernel uptime\r\nUptime: 42 ms\r\n\x1b[1;3");
+        // The rest of the split color sequence comes with the next message
+        console.append("2muart:~$ \x1b]0;title\x07\x08\x1b[J");
+        assert_eq!(console.scrollback, "uart:~$ kernel uptime\nUptime: 42 ms\nuart:~$ ");
+
+        let line = "[00:00:01.000] <inf> imu: sample °\n";
+        for _ in 0..CONSOLE_SCROLLBACK_BYTES / line.len() + 10 {
+            console.append(line);
+        }
+        assert!(console.scrollback.len() <= CONSOLE_SCROLLBACK_BYTES);
+        assert!(console.scrollback.ends_with(line));
+    }
+
+    #[test]
+    fn test_console_ws_url_carries_the_token() {
+        let mut config = DaemonConfig::from_daemon_address("https://10.9.0.1:8443");
+        assert_eq!(console_ws_url(&config, "0x1a2b"), "wss://10.9.0.1:8443/api/devices/0x1a2b/console");
+        config.token = Some("s3/cret".to_string());
+        assert_eq!(console_ws_url(&config, "can:can0:12"), "wss://10.9.0.1:8443/api/devices/can%3Acan0%3A12/console?token=s3%2Fcret");
+    }
+}
+
+// ============================================================================
+// Device Shell
+// ============================================================================
+
+/// Number of commands the Shell section keeps
+pub const SHELL_HISTORY_LEN: usize = 10;
+
+/// A command run from the Shell section, with its output or the daemon's error
+#[derive(Debug, Clone)]
+pub struct ShellEntry {
+    /// Device the command ran on, as selected in the UI
+    pub device_id: String,
+    pub command: String,
+    pub result: Result<ShellResponse, String>,
+}
+
+/// Pending shell command result from async fetch
+#[derive(Resource, Default)]
+pub struct PendingShellOutput(pub Arc<Mutex<Option<ShellEntry>>>);
+
+/// Shell section state for the selected device
+#[derive(Resource, Default)]
+pub struct DeviceShell {
+    /// Device the history belongs to
+    pub device_id: Option<String>,
+    /// Command being typed
+    pub input: String,
+    /// Commands run on this device, newest first
+    pub history: VecDeque<ShellEntry>,
+    /// A command is in flight
+    pub running: bool,
+}
+
+/// Clear the shell on selection change and collect finished commands
+fn process_shell_output(selected: Res<SelectedDevice>, pending: Res<PendingShellOutput>, mut shell: ResMut<DeviceShell>) {
+    if shell.device_id != selected.0 {
+        *shell = DeviceShell { device_id: selected.0.clone(), ..Default::default() };
+    }
+
+    let Ok(mut data) = pending.0.lock() else {
+        return;
+    };
+    let Some(entry) = data.take() else {
+        return;
+    };
+    // Ignore output for a device that is no longer selected
+    if shell.device_id.as_deref() != Some(entry.device_id.as_str()) {
+        return;
+    }
+    shell.running = false;
+    shell.history.push_front(entry);
+    shell.history.truncate(SHELL_HISTORY_LEN);
+}
+
+/// Run an allowlisted shell command on a device, called from UI
+///
+/// `device_id` is the selected (namespaced) id the output is filed under,
+/// `raw_id` the id the daemon at `base_url` knows the device by.
+pub fn run_device_shell(device_id: &str, raw_id: &str, command: &str, base_url: &str, pending: &PendingShellOutput) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        use wasm_bindgen_futures::spawn_local;
+
+        let pending_clone = pending.0.clone();
+        let url = format!("{}/api/devices/{}/shell", base_url, raw_id);
+        let device_id = device_id.to_string();
+        let command = command.to_string();
+        let body = serde_json::json!({ "command": command });
+
+        spawn_local(async move {
+            let result = match DaemonRequest::post(&url)
+                .header("Content-Type", "application/json")
+                .body(body.to_string())
+                .unwrap()
+                .send()
+                .await
+            {
+                Ok(response) if response.ok() => match response.text().await {
+                    Ok(text) => {
+                        record_received(text.len());
+                        serde_json::from_str::<ShellResponse>(&text).map_err(|e| format!("Invalid response: {}", e))
+                    }
+                    Err(e) => Err(format!("{:?}", e)),
+                },
+                Ok(response) => {
+                    let status = response.status();
+                    let text = response.text().await.unwrap_or_default();
+                    Err(serde_json::from_str::<serde_json::Value>(&text)
+                        .ok()
+                        .and_then(|json| json.get("error").and_then(|e| e.as_str()).map(str::to_string))
+                        .unwrap_or_else(|| format!("HTTP {}", status)))
+                }
+                Err(e) => Err(format!("{:?}", e)),
+            };
+
+            if let Err(ref message) = result {
+                tracing::error!("Shell command '{}' failed: {}", command, message);
+            }
+            if let Ok(mut data) = pending_clone.lock() {
+                *data = Some(ShellEntry { device_id, command, result });
+            }
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = (device_id, raw_id, command, base_url, pending);
+        tracing::warn!("Device shell not available in native mode");
+    }
+}
+
+// ============================================================================
+// Device Console
+// ============================================================================
+
+/// Console text the Console section keeps; older output scrolls away
+pub const CONSOLE_SCROLLBACK_BYTES: usize = 64 * 1024;
+
+/// Where the console session of the selected device stands
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum ConsoleState {
+    #[default]
+    Closed,
+    Connecting,
+    Open,
+    /// The daemon refused the session or the port failed
+    Failed(String),
+}
+
+/// What the console WebSocket reported, queued for `process_console`
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+#[derive(Debug, Clone)]
+enum ConsoleEvent {
+    Opened,
+    Message(ConsoleMessage),
+    /// The socket closed; true if it had been open
+    Closed(bool),
+}
+
+/// Pending console events from the WebSocket callbacks
+#[derive(Resource, Default)]
+pub struct PendingConsole(Arc<Mutex<Vec<ConsoleEvent>>>);
+
+/// Console section state for the selected device
+#[derive(Resource, Default)]
+pub struct DeviceConsole {
+    /// Device the session belongs to
+    pub device_id: Option<String>,
+    pub state: ConsoleState,
+    /// Line being typed
+    pub input: String,
+    /// Output with ANSI escapes stripped, at most `CONSOLE_SCROLLBACK_BYTES`
+    pub scrollback: String,
+    /// Bytes the daemon dropped because this client didn't keep up
+    pub dropped: u64,
+    ansi: AnsiStripper,
+}
+
+impl DeviceConsole {
+    /// Add output to the scrollback, dropping the oldest beyond the limit
+    fn append(&mut self, data: &str) {
+        let text = self.ansi.push(data);
+        self.scrollback.push_str(&text);
+        if self.scrollback.len() > CONSOLE_SCROLLBACK_BYTES {
+            let mut cut = self.scrollback.len() - CONSOLE_SCROLLBACK_BYTES;
+            while !self.scrollback.is_char_boundary(cut) {
+                cut += 1;
+            }
+            self.scrollback.drain(..cut);
+        }
+    }
+}
+
+/// Longest escape sequence held back for the next message; anything longer
+/// is dropped as noise
+const MAX_ESCAPE_LEN: usize = 64;
+
+/// Removes ANSI escape sequences (colors, cursor moves) from console output,
+/// holding back one split across messages
+#[derive(Debug, Default)]
+struct AnsiStripper {
+    pending: String,
+}
+
+impl AnsiStripper {
+    fn push(&mut self, data: &str) -> String {
+        let text = std::mem::take(&mut self.pending) + data;
+        let mut out = String::with_capacity(text.len());
+        let mut chars = text.char_indices().peekable();
+        while let Some((start, c)) = chars.next() {
+            match c {
+                '\x1b' => {
+                    let complete = match chars.next() {
+                        // CSI: parameters up to a final byte in @..~
+                        Some((_, '[')) => chars.any(|(_, c)| ('@'..='~').contains(&c)),
+                        // OSC: up to BEL or ESC \
+                        Some((_, ']')) => {
+                            let mut ended = false;
+                            while let Some((_, c)) = chars.next() {
+                                if c == '\x07' || (c == '\x1b' && chars.next_if(|&(_, c)| c == '\\').is_some()) {
+                                    ended = true;
+                                    break;
+                                }
+                            }
+                            ended
+                        }
+                        Some(_) => true,
+                        None => false,
+                    };
+                    if !complete {
+                        if text.len() - start <= MAX_ESCAPE_LEN {
+                            self.pending = text[start..].to_string();
+                        }
+                        break;
+                    }
+                }
+                '\n' | '\t' => out.push(c),
+                // Carriage returns, bells and backspaces from line editing
+                c if c.is_control() => {}
+                c => out.push(c),
+            }
+        }
+        out
+    }
+}
+
+/// Console WebSocket URL of a device on the daemon `daemon_config` points at
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+fn console_ws_url(daemon_config: &DaemonConfig, raw_id: &str) -> String {
+    let base = daemon_config.ws_url.strip_suffix("/ws").unwrap_or(&daemon_config.ws_url);
+    let mut url = format!("{}/api/devices/{}/console", base, percent_encode(raw_id));
+    if let Some(token) = &daemon_config.token {
+        url.push_str(&format!("?token={}", percent_encode(token)));
+    }
+    url
+}
+
+#[cfg(target_arch = "wasm32")]
+thread_local! {
+    /// Console WebSocket of the selected device
+    static CONSOLE_SOCKET: std::cell::RefCell<Option<web_sys::WebSocket>> = const { std::cell::RefCell::new(None) };
+}
+
+/// Close the console session, if one is open
+pub fn close_device_console() {
+    #[cfg(target_arch = "wasm32")]
+    CONSOLE_SOCKET.with(|socket| {
+        if let Some(ws) = socket.borrow_mut().take() {
+            ws.set_onclose(None);
+            ws.set_onmessage(None);
+            let _ = ws.close();
+        }
+    });
+}
+
+/// Open the serial console of a device, called from UI
+///
+/// The daemon only admits an operator token, and only for a device with a
+/// `serial_port` override; a browser isn't told why an upgrade was refused.
+pub fn open_device_console(raw_id: &str, daemon_config: &DaemonConfig, pending: &PendingConsole) {
+    close_device_console();
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        use wasm_bindgen::prelude::*;
+        use web_sys::{MessageEvent, WebSocket};
+
+        let url = console_ws_url(daemon_config, raw_id);
+        let ws = match WebSocket::new(&url) {
+            Ok(ws) => ws,
+            Err(e) => {
+                tracing::error!("Failed to open console WebSocket: {:?}", e);
+                if let Ok(mut events) = pending.0.lock() {
+                    events.push(ConsoleEvent::Closed(false));
+                }
+                return;
+            }
+        };
+
+        let events = pending.0.clone();
+        let opened = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let open_flag = opened.clone();
+        let onopen = Closure::wrap(Box::new(move |_| {
+            open_flag.store(true, Ordering::Relaxed);
+            if let Ok(mut events) = events.lock() {
+                events.push(ConsoleEvent::Opened);
+            }
+        }) as Box<dyn FnMut(JsValue)>);
+        ws.set_onopen(Some(onopen.as_ref().unchecked_ref()));
+        onopen.forget();
+
+        let events = pending.0.clone();
+        let onmessage = Closure::wrap(Box::new(move |e: MessageEvent| {
+            if let Ok(text) = e.data().dyn_into::<js_sys::JsString>() {
+                let text: String = text.into();
+                record_received(text.len());
+                match serde_json::from_str::<ConsoleMessage>(&text) {
+                    Ok(msg) => {
+                        if let Ok(mut events) = events.lock() {
+                            events.push(ConsoleEvent::Message(msg));
+                        }
+                    }
+                    Err(e) => tracing::warn!("Invalid console message: {}", e),
+                }
+            }
+        }) as Box<dyn FnMut(MessageEvent)>);
+        ws.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
+        onmessage.forget();
+
+        let events = pending.0.clone();
+        let onclose = Closure::wrap(Box::new(move |_| {
+            if let Ok(mut events) = events.lock() {
+                events.push(ConsoleEvent::Closed(opened.load(Ordering::Relaxed)));
+            }
+        }) as Box<dyn FnMut(JsValue)>);
+        ws.set_onclose(Some(onclose.as_ref().unchecked_ref()));
+        onclose.forget();
+
+        CONSOLE_SOCKET.with(|socket| *socket.borrow_mut() = Some(ws));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = (raw_id, daemon_config, pending);
+        tracing::warn!("Device console not available in native mode");
+    }
+}
+
+/// Send a typed line to the open console, ended with a carriage return as a
+/// terminal would
+pub fn send_console_line(line: &str) {
+    #[cfg(target_arch = "wasm32")]
+    CONSOLE_SOCKET.with(|socket| {
+        if let Some(ws) = socket.borrow().as_ref() {
+            if ws.ready_state() == web_sys::WebSocket::OPEN {
+                let _ = ws.send_with_str(&format!("{}\r", line));
+            }
+        }
+    });
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let _ = line;
+}
+
+/// Close the console on selection change and apply what its socket reported
+fn process_console(selected: Res<SelectedDevice>, pending: Res<PendingConsole>, mut console: ResMut<DeviceConsole>) {
+    if console.device_id != selected.0 {
+        if console.state != ConsoleState::Closed {
+            close_device_console();
+        }
+        *console = DeviceConsole { device_id: selected.0.clone(), ..Default::default() };
+        if let Ok(mut events) = pending.0.lock() {
+            events.clear();
+        }
+        return;
+    }
+
+    let events = match pending.0.lock() {
+        Ok(mut events) => std::mem::take(&mut *events),
+        Err(_) => return,
+    };
+    for event in events {
+        match event {
+            ConsoleEvent::Opened => console.state = ConsoleState::Open,
+            ConsoleEvent::Message(ConsoleMessage::Output { data, dropped }) => {
+                console.append(&data);
+                console.dropped = dropped;
+            }
+            ConsoleEvent::Message(ConsoleMessage::Closed { error }) => {
+                console.state = ConsoleState::Failed(error.unwrap_or_else(|| "Console closed".to_string()));
+            }
+            ConsoleEvent::Closed(was_open) => {
+                if matches!(console.state, ConsoleState::Failed(_)) {
+                    continue;
+                }
+                console.state = if was_open {
+                    ConsoleState::Closed
+                } else {
+                    ConsoleState::Failed("Refused: the console needs an operator token and a serial_port override".to_string())
+                };
+            }
+        }
     }
 }
@@ -0,0 +1,240 @@
+//! Sensor FOV occlusion checks
+//!
+//! "Check occlusion" in the sensor details casts rays through each of the
+//! sensor's FOVs against the rendered meshes of every other device and
+//! keeps the reports until any device moves. Blocked cells are drawn in red
+//! on the far plane of the FOV.
+
+use bevy::prelude::*;
+use dendrite_core::{check_occlusion, FovShape, Occluder, OcclusionReport};
+use dendrite_scene::occlusion::{blocked_cells_mesh, mesh_triangles, pose_from_transform};
+use std::collections::HashMap;
+
+use crate::app::{DeviceRegistry, GeometryData, SensorData};
+use crate::models::{pose_to_transform, ExcludeFromBounds};
+use crate::scene::DeviceEntity;
+
+/// Rays per side of the sample grid
+const OCCLUSION_GRID: usize = 32;
+
+const OVERLAY_COLOR: Color = Color::srgba(1.0, 0.1, 0.1, 0.6);
+
+/// Occlusion report for one FOV of a sensor
+#[derive(Debug, Clone)]
+pub struct FovOcclusion {
+    /// FOV name, None for a sensor's legacy single geometry
+    pub fov: Option<String>,
+    pub report: OcclusionReport,
+}
+
+/// Requested checks and their cached results, keyed by (device id, sensor name)
+#[derive(Resource, Default)]
+pub struct Occlusion {
+    /// Sensor to check on the next frame
+    pub requested: Option<(String, String)>,
+    pub results: HashMap<(String, String), Vec<FovOcclusion>>,
+}
+
+impl Occlusion {
+    pub fn get(&self, device_id: &str, sensor_name: &str) -> Option<&Vec<FovOcclusion>> {
+        self.results.get(&(device_id.to_string(), sensor_name.to_string()))
+    }
+
+    pub fn request(&mut self, device_id: &str, sensor_name: &str) {
+        self.requested = Some((device_id.to_string(), sensor_name.to_string()));
+    }
+
+    pub fn clear(&mut self, device_id: &str, sensor_name: &str) {
+        self.results.remove(&(device_id.to_string(), sensor_name.to_string()));
+    }
+}
+
+/// Red overlay of the cells blocked in one FOV
+#[derive(Component)]
+pub struct OcclusionOverlay;
+
+pub struct OcclusionPlugin;
+
+impl Plugin for OcclusionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Occlusion>()
+            .add_systems(Update, (invalidate_occlusion, run_occlusion_check, sync_occlusion_overlays).chain());
+    }
+}
+
+/// FOV volume of a sensor geometry, or None for solid shapes
+pub fn fov_shape(geometry: &GeometryData) -> Option<FovShape> {
+    match *geometry {
+        GeometryData::Frustum { near, far, hfov, vfov } | GeometryData::PyramidalFrustum { near, far, hfov, vfov } => {
+            Some(FovShape::Pyramid { near, far, hfov, vfov })
+        }
+        GeometryData::ConicalFrustum { near, far, fov } => Some(FovShape::Cone { near, far, half_angle: fov }),
+        GeometryData::Cone { radius, length } if length > 0.0 => Some(FovShape::Cone {
+            near: 0.0,
+            far: length,
+            half_angle: (radius / length).atan(),
+        }),
+        _ => None,
+    }
+}
+
+/// A sensor's FOVs as (name, shape, transform relative to the device)
+fn sensor_fovs(sensor: &SensorData) -> Vec<(Option<String>, FovShape, Transform)> {
+    let sensor_transform = pose_to_transform(sensor.pose);
+    if sensor.fovs.is_empty() {
+        return sensor
+            .geometry
+            .as_ref()
+            .and_then(fov_shape)
+            .map(|shape| vec![(None, shape, sensor_transform)])
+            .unwrap_or_default();
+    }
+    sensor
+        .fovs
+        .iter()
+        .filter_map(|fov| {
+            let shape = fov_shape(fov.geometry.as_ref()?)?;
+            Some((Some(fov.name.clone()), shape, sensor_transform * pose_to_transform(fov.pose)))
+        })
+        .collect()
+}
+
+/// Drop every result once any device moves
+fn invalidate_occlusion(
+    mut occlusion: ResMut<Occlusion>,
+    moved: Query<(), (With<DeviceEntity>, Changed<Transform>)>,
+) {
+    if !moved.is_empty() && !occlusion.results.is_empty() {
+        occlusion.results.clear();
+    }
+}
+
+fn run_occlusion_check(
+    mut occlusion: ResMut<Occlusion>,
+    registry: Res<DeviceRegistry>,
+    moved: Query<(), (With<DeviceEntity>, Changed<Transform>)>,
+    device_query: Query<(Entity, &DeviceEntity, &GlobalTransform)>,
+    children_query: Query<&Children>,
+    mesh_query: Query<(&Mesh3d, &GlobalTransform, &InheritedVisibility)>,
+    exclude_query: Query<(), With<ExcludeFromBounds>>,
+    meshes: Res<Assets<Mesh>>,
+) {
+    // Global transforms of moved devices are updated after this frame
+    if occlusion.requested.is_none() || !moved.is_empty() {
+        return;
+    }
+    let Some((device_id, sensor_name)) = occlusion.requested.take() else {
+        return;
+    };
+    let Some(sensor) = registry
+        .devices
+        .iter()
+        .find(|d| d.id == device_id)
+        .and_then(|d| d.sensors.iter().find(|s| s.name == sensor_name))
+    else {
+        return;
+    };
+    let Some((_, _, device_transform)) = device_query.iter().find(|(_, d, _)| d.device_id == device_id) else {
+        return;
+    };
+
+    // One occluder per visible mesh, so each mesh's bounding box filters rays
+    let mut occluders = Vec::new();
+    for (entity, device, _) in device_query.iter() {
+        if device.device_id != device_id {
+            collect_occluders(entity, &device.device_id, &children_query, &mesh_query, &exclude_query, &meshes, &mut occluders);
+        }
+    }
+
+    let fovs = sensor_fovs(sensor)
+        .into_iter()
+        .map(|(fov, shape, local)| {
+            let pose = pose_from_transform(&device_transform.mul_transform(local).compute_transform());
+            FovOcclusion { fov, report: check_occlusion(&pose, &shape, &occluders, OCCLUSION_GRID) }
+        })
+        .collect();
+    occlusion.results.insert((device_id, sensor_name), fovs);
+}
+
+/// Triangles of the visible meshes under `entity`, skipping sensor and port geometry
+fn collect_occluders(
+    entity: Entity,
+    device_id: &str,
+    children_query: &Query<&Children>,
+    mesh_query: &Query<(&Mesh3d, &GlobalTransform, &InheritedVisibility)>,
+    exclude_query: &Query<(), With<ExcludeFromBounds>>,
+    meshes: &Assets<Mesh>,
+    occluders: &mut Vec<Occluder>,
+) {
+    if exclude_query.contains(entity) {
+        return;
+    }
+    if let Ok((mesh_handle, transform, visibility)) = mesh_query.get(entity) {
+        if let Some(mesh) = meshes.get(&mesh_handle.0).filter(|_| visibility.get()) {
+            occluders.extend(Occluder::from_triangles(device_id, mesh_triangles(mesh, transform)));
+        }
+    }
+    if let Ok(children) = children_query.get(entity) {
+        for child in children.iter() {
+            collect_occluders(child, device_id, children_query, mesh_query, exclude_query, meshes, occluders);
+        }
+    }
+}
+
+/// Respawn the red overlays when the results change
+fn sync_occlusion_overlays(
+    mut commands: Commands,
+    occlusion: Res<Occlusion>,
+    registry: Res<DeviceRegistry>,
+    device_query: Query<(&DeviceEntity, &GlobalTransform)>,
+    overlay_query: Query<Entity, With<OcclusionOverlay>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    if !occlusion.is_changed() {
+        return;
+    }
+    for entity in overlay_query.iter() {
+        commands.entity(entity).despawn();
+    }
+    if occlusion.results.is_empty() {
+        return;
+    }
+
+    let material = materials.add(StandardMaterial {
+        base_color: OVERLAY_COLOR,
+        alpha_mode: AlphaMode::Blend,
+        unlit: true,
+        double_sided: true,
+        cull_mode: None,
+        ..default()
+    });
+    for ((device_id, sensor_name), fovs) in &occlusion.results {
+        let Some(sensor) = registry
+            .devices
+            .iter()
+            .find(|d| &d.id == device_id)
+            .and_then(|d| d.sensors.iter().find(|s| &s.name == sensor_name))
+        else {
+            continue;
+        };
+        let Some((_, device_transform)) = device_query.iter().find(|(d, _)| &d.device_id == device_id) else {
+            continue;
+        };
+        for (name, shape, local) in sensor_fovs(sensor) {
+            let Some(result) = fovs.iter().find(|f| f.fov == name) else {
+                continue;
+            };
+            let Some(mesh) = blocked_cells_mesh(&shape, &result.report) else {
+                continue;
+            };
+            commands.spawn((
+                Mesh3d(meshes.add(mesh)),
+                MeshMaterial3d(material.clone()),
+                device_transform.mul_transform(local).compute_transform(),
+                ExcludeFromBounds,
+                OcclusionOverlay,
+            ));
+        }
+    }
+}
@@ -0,0 +1,274 @@
+//! Batch firmware update
+//!
+//! Opened from the Fleet window for every device with a firmware update
+//! available. The pre-flight check asks each owning daemon for a dry run
+//! (`POST /api/ota/batch?dry_run=true`), which downloads and checks the
+//! firmware and the device without uploading anything, and lists which
+//! devices are ready and why the others would fail. "Proceed" then starts
+//! the update on the ready ones (`POST /api/ota/batch`); progress shows per
+//! device as for single updates.
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts, EguiPrimaryContextPass};
+use dendrite_api_types::{OtaReadiness, OtaStartResponse, Readiness};
+use std::sync::{Arc, Mutex};
+
+use crate::app::{DeviceRegistry, FirmwareCheckState, FirmwareStatusData};
+use crate::daemons::Daemons;
+use crate::network::ReadOnlyMode;
+
+/// Answer of one daemon to a batch request: (scene device ID, entry) pairs
+type Reply<T> = Result<Vec<(String, T)>, String>;
+
+#[derive(Resource, Default)]
+pub struct OtaBatch {
+    pub open: bool,
+    /// Daemons still to answer
+    waiting: usize,
+    /// Pre-flight results of the last check
+    readiness: Vec<(String, OtaReadiness)>,
+    /// Updates that didn't start after "Proceed"
+    failed: Vec<(String, String)>,
+    /// Updates started after "Proceed"
+    started: usize,
+    errors: Vec<String>,
+}
+
+#[derive(Resource, Default)]
+struct PendingBatch {
+    readiness: Arc<Mutex<Vec<Reply<OtaReadiness>>>>,
+    started: Arc<Mutex<Vec<Reply<OtaStartResponse>>>>,
+}
+
+pub struct OtaBatchPlugin;
+
+impl Plugin for OtaBatchPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<OtaBatch>()
+            .init_resource::<PendingBatch>()
+            .add_systems(Update, process_batch_replies)
+            .add_systems(EguiPrimaryContextPass, render_ota_batch);
+    }
+}
+
+/// Devices grouped by owning daemon: (base URL, [(scene ID, daemon's ID)])
+fn group_by_daemon(daemons: &Daemons, device_ids: &[String]) -> Vec<(String, Vec<(String, String)>)> {
+    let mut groups: Vec<(String, Vec<(String, String)>)> = Vec::new();
+    for id in device_ids {
+        let Some((base_url, raw)) = daemons.route(id) else {
+            continue;
+        };
+        let entry = (id.clone(), raw.to_string());
+        match groups.iter_mut().find(|(url, _)| url == base_url) {
+            Some((_, devices)) => devices.push(entry),
+            None => groups.push((base_url.to_string(), vec![entry])),
+        }
+    }
+    groups
+}
+
+/// Devices the pre-flight check found ready
+fn ready_devices(readiness: &[(String, OtaReadiness)]) -> Vec<String> {
+    readiness.iter().filter(|(_, r)| r.is_ok()).map(|(id, _)| id.clone()).collect()
+}
+
+/// Post one daemon's share of the batch; the daemon answers in the order asked
+fn post_batch<T>(base_url: &str, devices: Vec<(String, String)>, dry_run: bool, sink: Arc<Mutex<Vec<Reply<T>>>>)
+where
+    T: serde::de::DeserializeOwned + 'static,
+{
+    #[cfg(target_arch = "wasm32")]
+    {
+        let url = format!("{}/api/ota/batch?dry_run={}", base_url, dry_run);
+        wasm_bindgen_futures::spawn_local(async move {
+            let device_ids: Vec<&str> = devices.iter().map(|(_, raw)| raw.as_str()).collect();
+            let body = serde_json::json!({ "device_ids": device_ids });
+            let request = crate::network::DaemonRequest::post(&url)
+                .header("Content-Type", "application/json")
+                .body(body.to_string())
+                .unwrap();
+            let reply = match request.send().await {
+                Ok(response) if response.ok() => match response.json::<Vec<T>>().await {
+                    Ok(entries) => Ok(devices.into_iter().map(|(id, _)| id).zip(entries).collect()),
+                    Err(e) => Err(format!("Invalid batch response: {:?}", e)),
+                },
+                // Older daemons have no batch endpoint
+                Ok(response) => Err(format!("Batch update unavailable ({})", response.status())),
+                Err(e) => Err(format!("{:?}", e)),
+            };
+            if let Ok(mut replies) = sink.lock() {
+                replies.push(reply);
+            }
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = (base_url, devices, dry_run, sink);
+    }
+}
+
+fn process_batch_replies(pending: Res<PendingBatch>, mut batch: ResMut<OtaBatch>) {
+    let readiness: Vec<_> = pending.readiness.lock().map(|mut r| r.drain(..).collect()).unwrap_or_default();
+    let started: Vec<_> = pending.started.lock().map(|mut r| r.drain(..).collect()).unwrap_or_default();
+    for reply in readiness {
+        batch.waiting = batch.waiting.saturating_sub(1);
+        match reply {
+            Ok(entries) => batch.readiness.extend(entries),
+            Err(e) => batch.errors.push(e),
+        }
+    }
+    for reply in started {
+        batch.waiting = batch.waiting.saturating_sub(1);
+        match reply {
+            Ok(entries) => {
+                for (id, response) in entries {
+                    match response.error {
+                        Some(error) => batch.failed.push((id, error)),
+                        None => batch.started += 1,
+                    }
+                }
+            }
+            Err(e) => batch.errors.push(e),
+        }
+    }
+}
+
+/// The batch update window
+fn render_ota_batch(
+    mut contexts: EguiContexts,
+    mut batch: ResMut<OtaBatch>,
+    pending: Res<PendingBatch>,
+    firmware_state: Res<FirmwareCheckState>,
+    registry: Res<DeviceRegistry>,
+    daemons: Res<Daemons>,
+    read_only: Res<ReadOnlyMode>,
+) {
+    if !batch.open {
+        return;
+    }
+    let Ok(ctx) = contexts.ctx_mut() else { return };
+
+    let mut outdated: Vec<String> = firmware_state
+        .device_status
+        .iter()
+        .filter(|(_, status)| matches!(status, FirmwareStatusData::UpdateAvailable { .. }))
+        .map(|(id, _)| id.clone())
+        .collect();
+    outdated.sort();
+    let name = |id: &str| {
+        registry.devices.iter().find(|d| d.id == id).map(|d| d.name.clone()).unwrap_or_else(|| id.to_string())
+    };
+    let read_only = read_only.is_read_only();
+
+    let mut open = batch.open;
+    let mut check = false;
+    let mut proceed = false;
+    egui::Window::new("Update Firmware")
+        .open(&mut open)
+        .default_width(380.0)
+        .show(ctx, |ui| {
+            ui.label(format!("{} device(s) have an update available", outdated.len()));
+            let busy = batch.waiting > 0;
+            check = ui
+                .add_enabled(!outdated.is_empty() && !busy && !read_only, egui::Button::new("Pre-flight check"))
+                .on_hover_text("Download and check the firmware and each device without uploading anything")
+                .clicked();
+            if busy {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Waiting for the daemons...");
+                });
+            }
+            for error in &batch.errors {
+                ui.colored_label(egui::Color32::from_rgb(255, 100, 100), error);
+            }
+
+            if !batch.readiness.is_empty() {
+                ui.separator();
+                egui::Grid::new("ota_batch_readiness").striped(true).num_columns(2).show(ui, |ui| {
+                    for (id, readiness) in &batch.readiness {
+                        ui.label(name(id));
+                        match &readiness.readiness {
+                            Readiness::Ok => {
+                                let mut text = "Ready".to_string();
+                                if let Some(version) = &readiness.version {
+                                    text.push_str(&format!(": v{}", version));
+                                }
+                                if let Some(secs) = readiness.estimated_secs {
+                                    text.push_str(&format!(", ~{:.0} s", secs));
+                                }
+                                ui.colored_label(egui::Color32::from_rgb(100, 200, 100), text);
+                            }
+                            Readiness::WouldFail { reason } => {
+                                let text = format!("Would fail: {}", reason);
+                                ui.colored_label(egui::Color32::from_rgb(200, 100, 100), text);
+                            }
+                        }
+                        ui.end_row();
+                    }
+                });
+                let ready = ready_devices(&batch.readiness).len();
+                let button = egui::Button::new(format!("Proceed ({} ready)", ready));
+                proceed = ui.add_enabled(ready > 0 && !busy && !read_only, button).clicked();
+            }
+
+            if batch.started > 0 || !batch.failed.is_empty() {
+                ui.separator();
+                ui.label(format!("Started {} update(s)", batch.started));
+                for (id, error) in &batch.failed {
+                    ui.colored_label(egui::Color32::from_rgb(200, 100, 100), format!("{}: {}", name(id), error));
+                }
+            }
+        });
+    batch.open = open;
+
+    if check {
+        batch.readiness.clear();
+        batch.failed.clear();
+        batch.started = 0;
+        batch.errors.clear();
+        for (base_url, devices) in group_by_daemon(&daemons, &outdated) {
+            batch.waiting += 1;
+            post_batch(&base_url, devices, true, pending.readiness.clone());
+        }
+    } else if proceed {
+        let ready = ready_devices(&batch.readiness);
+        batch.readiness.clear();
+        batch.errors.clear();
+        for (base_url, devices) in group_by_daemon(&daemons, &ready) {
+            batch.waiting += 1;
+            post_batch(&base_url, devices, false, pending.started.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::{DaemonConfig, PendingMessages};
+
+    #[test]
+    fn test_ready_devices_grouped_by_daemon() {
+        let mut daemons = Daemons::new(&DaemonConfig::from_daemon_address("10.0.0.1:8080"), PendingMessages::default());
+        daemons.add("10.0.0.2:8080").unwrap();
+
+        let readiness: Vec<(String, OtaReadiness)> = serde_json::from_value(serde_json::json!([
+            ["0x01", {"device_id": "0x01", "status": "ok", "version": "1.2.0"}],
+            ["d1/0x02", {"device_id": "0x02", "status": "ok"}],
+            ["0x03", {"device_id": "0x03", "status": "would_fail", "reason": "Device not reachable"}],
+        ]))
+        .unwrap();
+        let ready = ready_devices(&readiness);
+        assert_eq!(ready, ["0x01", "d1/0x02"]);
+
+        let groups = group_by_daemon(&daemons, &ready);
+        assert_eq!(
+            groups,
+            [
+                ("http://10.0.0.1:8080".to_string(), vec![("0x01".to_string(), "0x01".to_string())]),
+                ("http://10.0.0.2:8080".to_string(), vec![("d1/0x02".to_string(), "0x02".to_string())]),
+            ]
+        );
+    }
+}
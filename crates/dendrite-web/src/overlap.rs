@@ -0,0 +1,236 @@
+//! Overlap checks between device visuals
+//!
+//! With "Check Overlaps" on in the world settings, the world-space bounding
+//! boxes of every device's visual meshes are compared whenever a device
+//! moves or its meshes load. Sensor FOVs, ports and frame gizmos are marked
+//! `ExcludeFromBounds` and left out, so overlapping sensor cones are not
+//! reported. Colliding devices get a red outline and a warning in the
+//! details panel.
+
+use bevy::camera::primitives::MeshAabb;
+use bevy::prelude::*;
+use dendrite_scene::keepout::box_edge_transforms;
+
+use crate::app::WorldSettings;
+use crate::models::ExcludeFromBounds;
+use crate::scene::{DeviceBounds, DeviceEntity};
+
+/// Radius of the outline edges (meters)
+const OUTLINE_THICKNESS: f32 = 0.0008;
+
+const OUTLINE_COLOR: Color = Color::srgb(1.0, 0.15, 0.15);
+
+/// Boxes must overlap by more than this on every axis (meters), so boards
+/// placed flush against each other are not reported
+const TOLERANCE: f32 = 1e-4;
+
+/// Pairs of devices whose visuals intersect, each pair sorted by id
+#[derive(Resource, Default, PartialEq)]
+pub struct Overlaps {
+    pub pairs: Vec<(String, String)>,
+}
+
+impl Overlaps {
+    /// Devices overlapping `id`
+    pub fn colliding_with<'a>(&'a self, id: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.pairs.iter().filter_map(move |(a, b)| {
+            if a == id {
+                Some(b.as_str())
+            } else if b == id {
+                Some(a.as_str())
+            } else {
+                None
+            }
+        })
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.colliding_with(id).next().is_some()
+    }
+}
+
+/// Root of a colliding device's outline, spawned as a child of the device
+#[derive(Component)]
+pub struct OverlapOutline;
+
+pub struct OverlapPlugin;
+
+impl Plugin for OverlapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Overlaps>()
+            .add_systems(Update, (detect_overlaps, sync_overlap_outlines).chain());
+    }
+}
+
+/// World-space (min, max) of two boxes intersect by more than `TOLERANCE`
+fn boxes_intersect(a: (Vec3, Vec3), b: (Vec3, Vec3)) -> bool {
+    let overlap = a.1.min(b.1) - a.0.max(b.0);
+    overlap.min_element() > TOLERANCE
+}
+
+/// Every pair of intersecting boxes, sorted
+pub fn overlapping_pairs(boxes: &[(String, (Vec3, Vec3))]) -> Vec<(String, String)> {
+    let mut pairs = Vec::new();
+    for (i, (a, a_box)) in boxes.iter().enumerate() {
+        for (b, b_box) in &boxes[i + 1..] {
+            if boxes_intersect(*a_box, *b_box) {
+                pairs.push(if a <= b { (a.clone(), b.clone()) } else { (b.clone(), a.clone()) });
+            }
+        }
+    }
+    pairs.sort();
+    pairs
+}
+
+/// Recompute the overlapping pairs when a device moves or meshes load
+///
+/// Meshes added this frame have no global transform yet, so the check waits
+/// a frame after them, like the device bounds.
+#[allow(clippy::too_many_arguments)]
+fn detect_overlaps(
+    mut overlaps: ResMut<Overlaps>,
+    mut pending: Local<bool>,
+    world_settings: Res<WorldSettings>,
+    moved: Query<(), (With<DeviceEntity>, Changed<GlobalTransform>)>,
+    added_meshes: Query<(), Added<Mesh3d>>,
+    mut removed: RemovedComponents<DeviceEntity>,
+    device_query: Query<(Entity, &DeviceEntity)>,
+    children_query: Query<&Children>,
+    mesh_query: Query<(&Mesh3d, &GlobalTransform)>,
+    exclude_query: Query<(), With<ExcludeFromBounds>>,
+    meshes: Res<Assets<Mesh>>,
+) {
+    if !world_settings.check_overlaps {
+        overlaps.set_if_neq(Overlaps::default());
+        return;
+    }
+    let removed_any = removed.read().count() > 0;
+    if !added_meshes.is_empty() {
+        *pending = true;
+        return;
+    }
+    let stale = std::mem::take(&mut *pending) || removed_any || !moved.is_empty() || world_settings.is_changed();
+    if !stale {
+        return;
+    }
+
+    let boxes: Vec<(String, (Vec3, Vec3))> = device_query
+        .iter()
+        .filter_map(|(entity, device)| {
+            let mut bounds = None;
+            grow_world_bounds(entity, &children_query, &mesh_query, &exclude_query, &meshes, &mut bounds);
+            Some((device.device_id.clone(), bounds?))
+        })
+        .collect();
+    overlaps.set_if_neq(Overlaps { pairs: overlapping_pairs(&boxes) });
+}
+
+/// Grow `bounds` to cover the world-space boxes of the meshes under `entity`
+fn grow_world_bounds(
+    entity: Entity,
+    children_query: &Query<&Children>,
+    mesh_query: &Query<(&Mesh3d, &GlobalTransform)>,
+    exclude_query: &Query<(), With<ExcludeFromBounds>>,
+    meshes: &Assets<Mesh>,
+    bounds: &mut Option<(Vec3, Vec3)>,
+) {
+    if exclude_query.contains(entity) {
+        return;
+    }
+    if let Ok((mesh_handle, transform)) = mesh_query.get(entity) {
+        if let Some(aabb) = meshes.get(&mesh_handle.0).and_then(|mesh| mesh.compute_aabb()) {
+            let center = Vec3::from(aabb.center);
+            let half = Vec3::from(aabb.half_extents);
+            for sign in [-1.0, 1.0] {
+                for corner in [
+                    Vec3::new(half.x, half.y, half.z),
+                    Vec3::new(-half.x, half.y, half.z),
+                    Vec3::new(half.x, -half.y, half.z),
+                    Vec3::new(half.x, half.y, -half.z),
+                ] {
+                    let world = transform.transform_point(center + corner * sign);
+                    *bounds = Some(match *bounds {
+                        Some((min, max)) => (min.min(world), max.max(world)),
+                        None => (world, world),
+                    });
+                }
+            }
+        }
+    }
+    if let Ok(children) = children_query.get(entity) {
+        for child in children.iter() {
+            grow_world_bounds(child, children_query, mesh_query, exclude_query, meshes, bounds);
+        }
+    }
+}
+
+/// Respawn the red outlines when the overlaps change or devices are respawned
+#[allow(clippy::too_many_arguments)]
+fn sync_overlap_outlines(
+    mut commands: Commands,
+    overlaps: Res<Overlaps>,
+    bounds: Res<DeviceBounds>,
+    added_devices: Query<(), Added<DeviceEntity>>,
+    device_query: Query<(Entity, &DeviceEntity)>,
+    outline_query: Query<Entity, With<OverlapOutline>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    if !overlaps.is_changed() && added_devices.is_empty() {
+        return;
+    }
+    for entity in outline_query.iter() {
+        commands.entity(entity).despawn();
+    }
+    if overlaps.pairs.is_empty() {
+        return;
+    }
+
+    let material = materials.add(StandardMaterial {
+        base_color: OUTLINE_COLOR,
+        unlit: true,
+        ..default()
+    });
+    for (entity, device) in device_query.iter().filter(|(_, d)| overlaps.contains(&d.device_id)) {
+        // Device-local box, so the outline turns with the device
+        let (min, max) = bounds.get(&device.device_id);
+        let frame = Transform::from_translation((min + max) / 2.0);
+        commands
+            .spawn((Transform::default(), Visibility::default(), ExcludeFromBounds, OverlapOutline, ChildOf(entity)))
+            .with_children(|parent| {
+                for (transform, length) in box_edge_transforms(frame, max - min) {
+                    parent.spawn((
+                        Mesh3d(meshes.add(Cylinder::new(OUTLINE_THICKNESS, length))),
+                        MeshMaterial3d(material.clone()),
+                        transform,
+                    ));
+                }
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cube(id: &str, center: Vec3, half: f32) -> (String, (Vec3, Vec3)) {
+        (id.to_string(), (center - Vec3::splat(half), center + Vec3::splat(half)))
+    }
+
+    #[test]
+    fn test_overlapping_pairs() {
+        let boxes = [
+            cube("b", Vec3::ZERO, 0.01),
+            cube("a", Vec3::new(0.015, 0.0, 0.0), 0.01),
+            // Flush against "a" on +X: touching is not overlapping
+            cube("c", Vec3::new(0.035, 0.0, 0.0), 0.01),
+            cube("d", Vec3::new(0.0, 0.5, 0.0), 0.01),
+        ];
+        let overlaps = Overlaps { pairs: overlapping_pairs(&boxes) };
+        assert_eq!(overlaps.pairs, vec![("a".to_string(), "b".to_string())]);
+        assert_eq!(overlaps.colliding_with("b").collect::<Vec<_>>(), vec!["a"]);
+        assert!(overlaps.contains("a"));
+        assert!(!overlaps.contains("c"));
+        assert!(!overlaps.contains("d"));
+    }
+}
@@ -7,6 +7,11 @@ use bevy::render::alpha::AlphaMode;
 use bevy::camera::primitives::MeshAabb;  // Trait for compute_aabb
 use bevy_egui::{egui, EguiContexts};
 use bevy_picking::prelude::{Click, Out, Over, Pointer, PointerButton};
+use dendrite_core::resolve_frame_poses;
+use dendrite_scene::axes::{axis_transform, TRIAD_AXES};
+use dendrite_scene::camera_views::ease_orbit;
+use dendrite_scene::frame_overlay::{FrameOverlayPlugin, FrameOverlayTarget};
+use dendrite_scene::keepout::{pose_rotation, KeepoutPlugin, KeepoutTargets};
 
 use crate::app::{ActiveRotationAxis, ActiveRotationField, CameraSettings, DeviceOrientations, DevicePositions, DeviceRegistry, FirmwareCheckState, FirmwareStatusData, FrameVisibility, SelectedDevice, ShowRotationAxis, UiLayout, WorldSettings};
 use crate::models::{ExcludeFromBounds, PortEntity, PortMeshTarget, SensorAxisEntity, SensorFovEntity};
@@ -20,14 +25,19 @@ pub struct ScenePlugin;
 
 impl Plugin for ScenePlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, setup_scene)
+        app.add_plugins((FrameOverlayPlugin, KeepoutPlugin))
+            .init_resource::<DeviceBounds>()
+            .add_systems(Startup, setup_scene)
             .add_systems(Update, (
                 update_camera,
                 handle_deselection,
                 update_device_positions,
                 update_device_orientations,
                 update_selection_highlight,
+                update_device_bounds,
                 update_effective_rotation_axis,
+                update_frame_overlay_target,
+                update_keepout_targets,
                 update_world_visibility,
                 update_grid_spacing,
                 update_frame_gizmos,
@@ -417,6 +427,9 @@ fn update_camera(
 
     // Orbit with left mouse drag (only when UI doesn't want pointer)
     if mouse_button.pressed(MouseButton::Left) && !egui_wants_pointer {
+        if total_motion != Vec2::ZERO {
+            settings.target_orbit = None;
+        }
         settings.azimuth -= total_motion.x * settings.sensitivity;
         settings.elevation = (settings.elevation - total_motion.y * settings.sensitivity)
             .clamp(-1.5, 1.5);
@@ -456,6 +469,7 @@ fn update_camera(
         if let Some(touch) = touch_input.iter().next() {
             let delta = touch.delta();
             if delta != Vec2::ZERO {
+                settings.target_orbit = None;
                 settings.azimuth -= delta.x * settings.sensitivity;
                 settings.elevation = (settings.elevation - delta.y * settings.sensitivity)
                     .clamp(-1.5, 1.5);
@@ -492,12 +506,18 @@ fn update_camera(
         }
     }
 
-    // Smooth interpolation for zoom and target
+    // Smooth interpolation for zoom, target and recalled view angles
     let dt = time.delta_secs();
     let lerp_factor = 1.0 - (-settings.smooth_factor * 60.0 * dt).exp();
     settings.distance += (settings.target_distance - settings.distance) * lerp_factor;
     let target_delta = (settings.target_focus - settings.target) * lerp_factor;
     settings.target += target_delta;
+    if let Some(target_orbit) = settings.target_orbit {
+        let CameraSettings { azimuth, elevation, .. } = &mut *settings;
+        if ease_orbit(azimuth, elevation, target_orbit, lerp_factor) {
+            settings.target_orbit = None;
+        }
+    }
 
     // Update camera position (ENU: Z is up, spherical coordinates)
     if let Ok(mut transform) = camera_query.single_mut() {
@@ -548,6 +568,141 @@ fn update_device_orientations(
     }
 }
 
+/// Half size of the box assumed for a device whose meshes haven't loaded
+const DEFAULT_HALF_EXTENT: f32 = 0.04;
+
+/// Bounding box of all of a device's meshes in device-local space, None if it has no meshes yet
+fn device_local_bounds(
+    entity: Entity,
+    children_query: &Query<&Children>,
+    mesh_query: &Query<(&Mesh3d, &GlobalTransform)>,
+    mesh_assets: &Assets<Mesh>,
+    device_world_pos: Vec3,
+    device_rotation_inv: Quat,
+    skip_entities: &std::collections::HashSet<Entity>,
+) -> Option<(Vec3, Vec3)> {
+    let mut min = Vec3::splat(f32::MAX);
+    let mut max = Vec3::splat(f32::MIN);
+    let mut found = false;
+    collect_bounds(entity, children_query, mesh_query, mesh_assets, device_world_pos, device_rotation_inv, &mut min, &mut max, &mut found, skip_entities);
+    found.then_some((min, max))
+}
+
+/// Recursively find all mesh children and grow `min`/`max` to cover them in device-local space
+#[allow(clippy::too_many_arguments)]
+fn collect_bounds(
+    entity: Entity,
+    children_query: &Query<&Children>,
+    mesh_query: &Query<(&Mesh3d, &GlobalTransform)>,
+    mesh_assets: &Assets<Mesh>,
+    device_world_pos: Vec3,
+    device_rotation_inv: Quat,
+    min: &mut Vec3,
+    max: &mut Vec3,
+    found: &mut bool,
+    skip_entities: &std::collections::HashSet<Entity>,
+) {
+    // Skip visualization entities (sensors, ports, FOV geometry)
+    if skip_entities.contains(&entity) {
+        return;
+    }
+
+    // Check if this entity has a mesh
+    if let Ok((mesh_handle, global_transform)) = mesh_query.get(entity) {
+        if let Some(mesh) = mesh_assets.get(&mesh_handle.0) {
+            if let Some(aabb) = mesh.compute_aabb() {
+                // Transform AABB corners from mesh-local to device-local space
+                let center = Vec3::from(aabb.center);
+                let half = Vec3::from(aabb.half_extents);
+
+                // Get the 8 corners of the AABB in mesh-local space
+                let corners = [
+                    center + Vec3::new(-half.x, -half.y, -half.z),
+                    center + Vec3::new( half.x, -half.y, -half.z),
+                    center + Vec3::new(-half.x,  half.y, -half.z),
+                    center + Vec3::new( half.x,  half.y, -half.z),
+                    center + Vec3::new(-half.x, -half.y,  half.z),
+                    center + Vec3::new( half.x, -half.y,  half.z),
+                    center + Vec3::new(-half.x,  half.y,  half.z),
+                    center + Vec3::new( half.x,  half.y,  half.z),
+                ];
+
+                // Transform corners: mesh-local -> world -> device-local
+                for corner in corners {
+                    // Mesh-local to world
+                    let world_corner = global_transform.transform_point(corner);
+                    // World to device-local (undo device translation and rotation)
+                    let local_corner = device_rotation_inv * (world_corner - device_world_pos);
+                    *min = min.min(local_corner);
+                    *max = max.max(local_corner);
+                }
+                *found = true;
+            }
+        }
+    }
+
+    // Check children
+    if let Ok(children) = children_query.get(entity) {
+        for child in children.iter() {
+            collect_bounds(child, children_query, mesh_query, mesh_assets, device_world_pos, device_rotation_inv, min, max, found, skip_entities);
+        }
+    }
+}
+
+/// Device-local bounding box of every device whose meshes have loaded,
+/// for the pose constraints applied while editing
+#[derive(Resource, Default)]
+pub struct DeviceBounds(pub std::collections::HashMap<String, (Vec3, Vec3)>);
+
+impl DeviceBounds {
+    /// A device's bounding box, or the default highlight box until its meshes load
+    pub fn get(&self, id: &str) -> (Vec3, Vec3) {
+        self.0.get(id).copied().unwrap_or((Vec3::splat(-DEFAULT_HALF_EXTENT), Vec3::splat(DEFAULT_HALF_EXTENT)))
+    }
+}
+
+/// Recompute device bounding boxes after meshes are added
+///
+/// Runs a frame after the meshes appear so their global transforms have
+/// been propagated.
+#[allow(clippy::too_many_arguments)]
+fn update_device_bounds(
+    mut bounds: ResMut<DeviceBounds>,
+    mut pending: Local<bool>,
+    added_meshes: Query<(), Added<Mesh3d>>,
+    device_query: Query<(Entity, &DeviceEntity, &GlobalTransform)>,
+    children_query: Query<&Children>,
+    mesh_query: Query<(&Mesh3d, &GlobalTransform)>,
+    exclude_query: Query<Entity, With<ExcludeFromBounds>>,
+    meshes: Res<Assets<Mesh>>,
+) {
+    if !added_meshes.is_empty() {
+        *pending = true;
+        return;
+    }
+    if !std::mem::take(&mut *pending) {
+        return;
+    }
+
+    let skip_entities: std::collections::HashSet<Entity> = exclude_query.iter().collect();
+    bounds.0.clear();
+    for (entity, device, transform) in device_query.iter() {
+        let (_, rotation, translation) = transform.to_scale_rotation_translation();
+        let device_bounds = device_local_bounds(
+            entity,
+            &children_query,
+            &mesh_query,
+            meshes.as_ref(),
+            translation,
+            rotation.inverse(),
+            &skip_entities,
+        );
+        if let Some(device_bounds) = device_bounds {
+            bounds.0.insert(device.device_id.clone(), device_bounds);
+        }
+    }
+}
+
 /// Grouped system parameters for the selection highlight system to work around Bevy's 16-param limit
 #[derive(SystemParam)]
 pub struct SelectionHighlightParams<'w, 's> {
@@ -677,85 +832,23 @@ fn update_selection_highlight(mut params: SelectionHighlightParams) {
         }
     } else {
         // Create highlight box
-        // Compute bounding box from all child meshes in device-local space
-        let mut min = Vec3::splat(f32::MAX);
-        let mut max = Vec3::splat(f32::MIN);
-        let mut found_mesh = false;
-
         // Collect entities to skip (visualization entities that shouldn't affect bounding box)
         let skip_entities: std::collections::HashSet<Entity> = params.exclude_query.iter().collect();
 
-        // Recursively find all mesh children and compute bounds in device-local space
-        fn collect_bounds(
-            entity: Entity,
-            children_query: &Query<&Children>,
-            mesh_query: &Query<(&Mesh3d, &GlobalTransform)>,
-            mesh_assets: &Assets<Mesh>,
-            device_world_pos: Vec3,
-            device_rotation_inv: Quat,
-            min: &mut Vec3,
-            max: &mut Vec3,
-            found: &mut bool,
-            skip_entities: &std::collections::HashSet<Entity>,
-        ) {
-            // Skip visualization entities (sensors, ports, FOV geometry)
-            if skip_entities.contains(&entity) {
-                return;
-            }
-
-            // Check if this entity has a mesh
-            if let Ok((mesh_handle, global_transform)) = mesh_query.get(entity) {
-                if let Some(mesh) = mesh_assets.get(&mesh_handle.0) {
-                    if let Some(aabb) = mesh.compute_aabb() {
-                        // Transform AABB corners from mesh-local to device-local space
-                        let center = Vec3::from(aabb.center);
-                        let half = Vec3::from(aabb.half_extents);
-
-                        // Get the 8 corners of the AABB in mesh-local space
-                        let corners = [
-                            center + Vec3::new(-half.x, -half.y, -half.z),
-                            center + Vec3::new( half.x, -half.y, -half.z),
-                            center + Vec3::new(-half.x,  half.y, -half.z),
-                            center + Vec3::new( half.x,  half.y, -half.z),
-                            center + Vec3::new(-half.x, -half.y,  half.z),
-                            center + Vec3::new( half.x, -half.y,  half.z),
-                            center + Vec3::new(-half.x,  half.y,  half.z),
-                            center + Vec3::new( half.x,  half.y,  half.z),
-                        ];
-
-                        // Transform corners: mesh-local -> world -> device-local
-                        for corner in corners {
-                            // Mesh-local to world
-                            let world_corner = global_transform.transform_point(corner);
-                            // World to device-local (undo device translation and rotation)
-                            let local_corner = device_rotation_inv * (world_corner - device_world_pos);
-                            *min = min.min(local_corner);
-                            *max = max.max(local_corner);
-                        }
-                        *found = true;
-                    }
-                }
-            }
-
-            // Check children
-            if let Ok(children) = children_query.get(entity) {
-                for child in children.iter() {
-                    collect_bounds(child, children_query, mesh_query, mesh_assets, device_world_pos, device_rotation_inv, min, max, found, skip_entities);
-                }
-            }
-        }
-
         // Get inverse of device rotation for converting world -> device-local
         let device_rotation_inv = device_transform.rotation.inverse();
-        collect_bounds(entity, &params.children_query, &params.mesh_query, params.meshes.as_ref(), device_pos, device_rotation_inv, &mut min, &mut max, &mut found_mesh, &skip_entities);
+        let bounds = device_local_bounds(
+            entity,
+            &params.children_query,
+            &params.mesh_query,
+            params.meshes.as_ref(),
+            device_pos,
+            device_rotation_inv,
+            &skip_entities,
+        );
 
         // Use default size if no mesh bounds found
-        let (box_min, box_max) = if found_mesh {
-            (min, max)
-        } else {
-            // Default fallback size
-            (Vec3::splat(-0.04), Vec3::splat(0.04))
-        };
+        let (box_min, box_max) = bounds.unwrap_or((Vec3::splat(-DEFAULT_HALF_EXTENT), Vec3::splat(DEFAULT_HALF_EXTENT)));
 
         // Add padding to the actual min/max bounds
         let padding = 0.005;
@@ -1233,6 +1326,31 @@ fn update_effective_rotation_axis(
     ));
 }
 
+/// Point the FLU/ENU overlay at the selected device
+fn update_frame_overlay_target(
+    selected: Res<SelectedDevice>,
+    device_query: Query<(Entity, &DeviceEntity)>,
+    mut target: ResMut<FrameOverlayTarget>,
+) {
+    let entity = selected.0.as_ref().and_then(|id| {
+        device_query.iter()
+            .find(|(_, d)| &d.device_id == id)
+            .map(|(e, _)| e)
+    });
+    target.set_if_neq(FrameOverlayTarget(entity));
+}
+
+/// Map device ids to entities so keep-out outlines can follow them
+fn update_keepout_targets(
+    device_query: Query<(Entity, &DeviceEntity)>,
+    mut targets: ResMut<KeepoutTargets>,
+) {
+    let entities = device_query.iter()
+        .map(|(e, d)| (d.device_id.clone(), e))
+        .collect();
+    targets.set_if_neq(KeepoutTargets(entities));
+}
+
 /// Update visibility of world grid and axis based on settings
 fn update_world_visibility(
     world_settings: Res<WorldSettings>,
@@ -1445,71 +1563,50 @@ fn update_frame_gizmos(
             None => continue, // Device not yet spawned
         };
 
-        for frame in &device.frames {
+        // Resolve parent chains to device-relative poses; a broken hierarchy
+        // falls back to treating every pose as device-relative
+        let resolved_poses = resolve_frame_poses(&device.frames).ok();
+
+        for (index, frame) in device.frames.iter().enumerate() {
             // Skip frames that are individually hidden
             if !frame_visibility.is_frame_visible(&device.id, &frame.name) {
                 continue;
             }
 
-            // Parse frame pose (local to device)
-            let frame_pose = frame.pose.unwrap_or([0.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+            // Frame pose (local to device)
+            let resolved = resolved_poses.as_ref().map(|poses| &poses[index]);
+            let frame_pose = resolved
+                .map(|pose| pose.to_array())
+                .or(frame.pose)
+                .unwrap_or([0.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
             let frame_translation = Vec3::new(
                 frame_pose[0] as f32,
                 frame_pose[1] as f32,
                 frame_pose[2] as f32,
             );
-            let frame_rotation = Quat::from_euler(
-                EulerRot::ZYX,
-                frame_pose[5] as f32, // yaw
-                frame_pose[4] as f32, // pitch
-                frame_pose[3] as f32, // roll
+            // A quaternion from the HCDF avoids the Euler singularity at ±90° pitch
+            let frame_rotation = pose_rotation(
+                [frame_pose[3], frame_pose[4], frame_pose[5]],
+                resolved.and_then(|pose| pose.quaternion),
             );
 
             let description = frame.description.clone().unwrap_or_default();
 
-            // X axis (red) - cylinder rotated to point along X, positioned in local space
-            let x_axis_local_pos = frame_translation + frame_rotation * Vec3::X * (axis_length / 2.0);
-            let x_rotation = frame_rotation * Quat::from_rotation_z(-std::f32::consts::FRAC_PI_2);
-            let x_entity = commands.spawn((
-                Mesh3d(axis_mesh.clone()),
-                MeshMaterial3d(x_material.clone()),
-                Transform::from_translation(x_axis_local_pos).with_rotation(x_rotation),
-                FrameGizmo {
-                    device_id: device.id.clone(),
-                    frame_name: frame.name.clone(),
-                    description: description.clone(),
-                },
-            )).id();
-            commands.entity(device_entity).add_child(x_entity);
-
-            // Y axis (green) - cylinder along Y (default orientation)
-            let y_axis_local_pos = frame_translation + frame_rotation * Vec3::Y * (axis_length / 2.0);
-            let y_entity = commands.spawn((
-                Mesh3d(axis_mesh.clone()),
-                MeshMaterial3d(y_material.clone()),
-                Transform::from_translation(y_axis_local_pos).with_rotation(frame_rotation),
-                FrameGizmo {
-                    device_id: device.id.clone(),
-                    frame_name: frame.name.clone(),
-                    description: description.clone(),
-                },
-            )).id();
-            commands.entity(device_entity).add_child(y_entity);
-
-            // Z axis (blue) - cylinder rotated to point along Z
-            let z_axis_local_pos = frame_translation + frame_rotation * Vec3::Z * (axis_length / 2.0);
-            let z_rotation = frame_rotation * Quat::from_rotation_x(std::f32::consts::FRAC_PI_2);
-            let z_entity = commands.spawn((
-                Mesh3d(axis_mesh.clone()),
-                MeshMaterial3d(z_material.clone()),
-                Transform::from_translation(z_axis_local_pos).with_rotation(z_rotation),
-                FrameGizmo {
-                    device_id: device.id.clone(),
-                    frame_name: frame.name.clone(),
-                    description: description.clone(),
-                },
-            )).id();
-            commands.entity(device_entity).add_child(z_entity);
+            // One cylinder per axis (X red, Y green, Z blue), positioned in device space
+            let frame_transform = Transform::from_translation(frame_translation).with_rotation(frame_rotation);
+            for (axis, material) in TRIAD_AXES.into_iter().zip([&x_material, &y_material, &z_material]) {
+                let axis_entity = commands.spawn((
+                    Mesh3d(axis_mesh.clone()),
+                    MeshMaterial3d(material.clone()),
+                    axis_transform(frame_transform, axis, axis_length),
+                    FrameGizmo {
+                        device_id: device.id.clone(),
+                        frame_name: frame.name.clone(),
+                        description: description.clone(),
+                    },
+                )).id();
+                commands.entity(device_entity).add_child(axis_entity);
+            }
         }
     }
 }
@@ -1977,7 +2074,10 @@ fn on_port_click(
         if frame_visibility.hovered_port.as_ref() == Some(&port_key) && frame_visibility.hovered_port_from_ui {
             frame_visibility.hovered_port = None;
             frame_visibility.hovered_port_from_ui = false;
+            frame_visibility.selected_port = None;
         } else {
+            // Fallback-geometry ports have a pose that can be edited
+            frame_visibility.selected_port = Some(port_key.clone());
             frame_visibility.hovered_port = Some(port_key);
             frame_visibility.hovered_port_from_ui = true; // Reuse this flag to make it sticky
             // Clear other sticky selections
@@ -0,0 +1,672 @@
+//! First-run setup wizard
+//!
+//! Shown when the daemon reports it has no configuration file (or was
+//! started with `--setup`): pick interfaces, discovery settings and TLS/auth,
+//! run a test scan, then write the config with `PUT /api/config`. Skipping
+//! writes the defaults so the wizard doesn't come back either way.
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts, EguiPrimaryContextPass};
+use serde::Deserialize;
+use std::sync::{Arc, Mutex};
+
+use crate::app::DeviceRegistry;
+use crate::daemons::{split_id, PRIMARY_DAEMON};
+use crate::network::{DaemonConfig, NetworkInterfaceInfo, ReadOnlyMode};
+
+/// Placeholder the daemon puts in place of secrets; written back unchanged
+const REDACTED: &str = "<redacted>";
+
+/// `GET /api/setup` response
+#[derive(Debug, Clone, Deserialize)]
+pub struct SetupStatus {
+    pub required: bool,
+    pub config_path: String,
+    pub interfaces: Vec<NetworkInterfaceInfo>,
+    pub suggested_subnets: Vec<SubnetSuggestion>,
+    /// Effective configuration the wizard starts from (secrets redacted)
+    pub config: serde_json::Value,
+}
+
+/// A subnet the daemon suggests scanning
+#[derive(Debug, Clone, Deserialize)]
+pub struct SubnetSuggestion {
+    pub interface: String,
+    pub subnet: String,
+    pub prefix_len: u8,
+    pub hosts: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SetupStep {
+    #[default]
+    Interfaces,
+    Discovery,
+    Security,
+    TestScan,
+    Save,
+}
+
+impl SetupStep {
+    const ALL: [SetupStep; 5] = [
+        SetupStep::Interfaces,
+        SetupStep::Discovery,
+        SetupStep::Security,
+        SetupStep::TestScan,
+        SetupStep::Save,
+    ];
+
+    fn title(&self) -> &'static str {
+        match self {
+            SetupStep::Interfaces => "Interfaces",
+            SetupStep::Discovery => "Discovery",
+            SetupStep::Security => "Security",
+            SetupStep::TestScan => "Test scan",
+            SetupStep::Save => "Save",
+        }
+    }
+
+    fn index(&self) -> usize {
+        Self::ALL.iter().position(|s| s == self).unwrap_or(0)
+    }
+
+    fn next(&self) -> Option<Self> {
+        Self::ALL.get(self.index() + 1).copied()
+    }
+
+    fn previous(&self) -> Option<Self> {
+        self.index().checked_sub(1).map(|i| Self::ALL[i])
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum TestScan {
+    #[default]
+    Idle,
+    Running,
+    Done { found: usize },
+    Failed(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum SaveState {
+    #[default]
+    Idle,
+    Saving,
+    /// Validation problems ("key: message") or a transport error
+    Failed(Vec<String>),
+    Saved { restart_required: bool },
+}
+
+/// Wizard state and the choices made so far
+#[derive(Resource, Default)]
+pub struct SetupWizard {
+    pub status: Option<SetupStatus>,
+    pub open: bool,
+    pub step: SetupStep,
+    /// Interfaces to announce this host on via LLDP
+    pub interfaces: Vec<String>,
+    pub subnet: String,
+    pub prefix_len: u8,
+    pub discovery_interval_secs: u64,
+    pub heartbeat_enabled: bool,
+    pub lldp_transmit: bool,
+    pub tls_enabled: bool,
+    pub tls_cert: String,
+    pub tls_key: String,
+    pub require_token: bool,
+    pub test_scan: TestScan,
+    pub save: SaveState,
+}
+
+impl SetupWizard {
+    /// Start from the daemon's effective configuration
+    pub fn from_status(status: SetupStatus) -> Self {
+        let config = &status.config;
+        let daemon = &config["daemon"];
+        let discovery = &config["discovery"];
+        let tls = &daemon["tls"];
+
+        let mut subnet = discovery["subnet"].as_str().unwrap_or("192.168.186.0").to_string();
+        let mut prefix_len = discovery["prefix_len"].as_u64().unwrap_or(24) as u8;
+        // The built-in default subnet rarely matches; prefer a real interface
+        if let Some(first) = status.suggested_subnets.first() {
+            if !status.suggested_subnets.iter().any(|s| s.subnet == subnet && s.prefix_len == prefix_len) {
+                subnet = first.subnet.clone();
+                prefix_len = first.prefix_len;
+            }
+        }
+
+        let str_list = |v: &serde_json::Value| -> Vec<String> {
+            v.as_array()
+                .map(|items| items.iter().filter_map(|i| i.as_str().map(str::to_string)).collect())
+                .unwrap_or_default()
+        };
+
+        Self {
+            open: status.required,
+            step: SetupStep::Interfaces,
+            interfaces: str_list(&discovery["lldp_tx_interfaces"]),
+            subnet,
+            prefix_len,
+            discovery_interval_secs: daemon["discovery_interval_secs"].as_u64().unwrap_or(60),
+            heartbeat_enabled: daemon["heartbeat_enabled"].as_bool().unwrap_or(false),
+            lldp_transmit: discovery["lldp_transmit"].as_bool().unwrap_or(false),
+            tls_enabled: tls.is_object(),
+            tls_cert: tls["cert"].as_str().unwrap_or_default().to_string(),
+            tls_key: tls["key"].as_str().unwrap_or_default().to_string(),
+            require_token: config["auth"]["require_token"].as_bool().unwrap_or(false),
+            test_scan: TestScan::Idle,
+            save: SaveState::Idle,
+            status: Some(status),
+        }
+    }
+
+    /// The configuration to submit: the daemon's config with the wizard's
+    /// choices applied, so settings the wizard doesn't cover are kept
+    pub fn config_json(&self) -> serde_json::Value {
+        let mut config = self
+            .status
+            .as_ref()
+            .map(|s| s.config.clone())
+            .filter(|c| c.is_object())
+            .unwrap_or_else(|| serde_json::json!({}));
+
+        config["daemon"]["discovery_interval_secs"] = self.discovery_interval_secs.into();
+        config["daemon"]["heartbeat_enabled"] = self.heartbeat_enabled.into();
+        config["daemon"]["tls"] = if self.tls_enabled {
+            serde_json::json!({ "cert": self.tls_cert.trim(), "key": self.tls_key.trim() })
+        } else {
+            serde_json::Value::Null
+        };
+        config["discovery"]["subnet"] = self.subnet.trim().into();
+        config["discovery"]["prefix_len"] = self.prefix_len.into();
+        config["discovery"]["lldp_transmit"] = self.lldp_transmit.into();
+        config["discovery"]["lldp_tx_interfaces"] = self.interfaces.clone().into();
+        config["auth"]["require_token"] = self.require_token.into();
+        config
+    }
+
+    fn toggle_interface(&mut self, name: &str, selected: bool) {
+        self.interfaces.retain(|i| i != name);
+        if selected {
+            self.interfaces.push(name.to_string());
+        }
+    }
+}
+
+/// Responses from the daemon, applied on the next frame
+enum SetupReply {
+    Status(SetupStatus),
+    TestScan(Result<usize, String>),
+    Saved(Result<bool, Vec<String>>),
+}
+
+#[derive(Resource, Default)]
+struct PendingSetup(Arc<Mutex<Vec<SetupReply>>>);
+
+pub struct SetupPlugin;
+
+impl Plugin for SetupPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SetupWizard>()
+            .init_resource::<PendingSetup>()
+            .add_systems(Startup, fetch_setup_status)
+            .add_systems(Update, process_setup_replies)
+            .add_systems(EguiPrimaryContextPass, render_setup_wizard);
+    }
+}
+
+/// Ask the daemon whether it still needs to be configured
+fn fetch_setup_status(pending: Res<PendingSetup>, daemon_config: Res<DaemonConfig>, read_only: Res<ReadOnlyMode>) {
+    // Spectators can't write the config, so there's nothing to offer them
+    if read_only.spectator {
+        return;
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        let pending = pending.0.clone();
+        let url = format!("{}/api/setup", daemon_config.http_url);
+        wasm_bindgen_futures::spawn_local(async move {
+            match crate::network::DaemonRequest::get(&url).send().await {
+                Ok(response) if response.ok() => match response.json::<SetupStatus>().await {
+                    Ok(status) => {
+                        if let Ok(mut replies) = pending.lock() {
+                            replies.push(SetupReply::Status(status));
+                        }
+                    }
+                    Err(e) => tracing::warn!("Invalid setup status: {:?}", e),
+                },
+                // Older daemons have no setup endpoint
+                Ok(_) => {}
+                Err(e) => tracing::error!("Failed to fetch setup status: {:?}", e),
+            }
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = (&pending, &daemon_config);
+    }
+}
+
+fn process_setup_replies(
+    pending: Res<PendingSetup>,
+    mut wizard: ResMut<SetupWizard>,
+    read_only: Res<ReadOnlyMode>,
+) {
+    let replies: Vec<_> = match pending.0.lock() {
+        Ok(mut replies) => replies.drain(..).collect(),
+        Err(_) => return,
+    };
+    for reply in replies {
+        match reply {
+            SetupReply::Status(status) => {
+                *wizard = SetupWizard::from_status(status);
+                wizard.open &= !read_only.is_read_only();
+            }
+            SetupReply::TestScan(Ok(found)) => wizard.test_scan = TestScan::Done { found },
+            SetupReply::TestScan(Err(e)) => wizard.test_scan = TestScan::Failed(e),
+            SetupReply::Saved(Ok(restart_required)) => wizard.save = SaveState::Saved { restart_required },
+            SetupReply::Saved(Err(problems)) => wizard.save = SaveState::Failed(problems),
+        }
+    }
+}
+
+fn start_test_scan(base_url: &str, subnet: &str, prefix_len: u8, pending: &PendingSetup) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let pending = pending.0.clone();
+        let url = format!("{}/api/setup/scan", base_url);
+        let body = serde_json::json!({ "subnet": subnet, "prefix_len": prefix_len });
+        wasm_bindgen_futures::spawn_local(async move {
+            let result = match crate::network::DaemonRequest::post(&url)
+                .header("Content-Type", "application/json")
+                .body(body.to_string())
+                .unwrap()
+                .send()
+                .await
+            {
+                Ok(response) => {
+                    let ok = response.ok();
+                    let json: serde_json::Value = response.json().await.unwrap_or_default();
+                    if ok {
+                        Ok(json["devices"].as_array().map_or(0, |d| d.len()))
+                    } else {
+                        Err(json["error"].as_str().unwrap_or("Scan failed").to_string())
+                    }
+                }
+                Err(e) => Err(format!("{:?}", e)),
+            };
+            if let Ok(mut replies) = pending.lock() {
+                replies.push(SetupReply::TestScan(result));
+            }
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = (base_url, subnet, prefix_len, pending);
+    }
+}
+
+fn save_config(base_url: &str, config: serde_json::Value, pending: &PendingSetup) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let pending = pending.0.clone();
+        let url = format!("{}/api/config", base_url);
+        wasm_bindgen_futures::spawn_local(async move {
+            let result = match crate::network::DaemonRequest::put(&url)
+                .header("Content-Type", "application/json")
+                .body(config.to_string())
+                .unwrap()
+                .send()
+                .await
+            {
+                Ok(response) => {
+                    let ok = response.ok();
+                    let json: serde_json::Value = response.json().await.unwrap_or_default();
+                    if ok {
+                        Ok(json["restart_required"].as_bool().unwrap_or(false))
+                    } else if let Some(issues) = json["issues"].as_array() {
+                        Err(issues
+                            .iter()
+                            .map(|i| format!("{}: {}", i["key"].as_str().unwrap_or(""), i["message"].as_str().unwrap_or("")))
+                            .collect())
+                    } else {
+                        Err(vec![json["error"].as_str().unwrap_or("Failed to save configuration").to_string()])
+                    }
+                }
+                Err(e) => Err(vec![format!("{:?}", e)]),
+            };
+            if let Ok(mut replies) = pending.lock() {
+                replies.push(SetupReply::Saved(result));
+            }
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = (base_url, config, pending);
+    }
+}
+
+fn skip_setup(base_url: &str) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let url = format!("{}/api/setup/skip", base_url);
+        wasm_bindgen_futures::spawn_local(async move {
+            if let Err(e) = crate::network::DaemonRequest::post(&url).send().await {
+                tracing::error!("Failed to skip setup: {:?}", e);
+            }
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = base_url;
+    }
+}
+
+fn render_setup_wizard(
+    mut contexts: EguiContexts,
+    mut wizard: ResMut<SetupWizard>,
+    pending: Res<PendingSetup>,
+    daemon_config: Res<DaemonConfig>,
+    registry: Res<DeviceRegistry>,
+) {
+    if !wizard.open {
+        return;
+    }
+    let Ok(ctx) = contexts.ctx_mut() else { return };
+    let base_url = daemon_config.http_url.clone();
+
+    egui::Window::new("Set up Dendrite")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+        .show(ctx, |ui| {
+            ui.set_min_width(380.0);
+
+            // Step indicator
+            ui.horizontal(|ui| {
+                for step in SetupStep::ALL {
+                    let text = egui::RichText::new(step.title());
+                    ui.label(if step == wizard.step { text.strong() } else { text.weak() });
+                }
+            });
+            ui.separator();
+
+            match wizard.step {
+                SetupStep::Interfaces => interfaces_step(ui, &mut wizard),
+                SetupStep::Discovery => discovery_step(ui, &mut wizard),
+                SetupStep::Security => security_step(ui, &mut wizard),
+                SetupStep::TestScan => test_scan_step(ui, &mut wizard, &registry, &base_url, &pending),
+                SetupStep::Save => save_step(ui, &mut wizard, &base_url, &pending),
+            }
+
+            ui.add_space(12.0);
+            ui.separator();
+            let saved = matches!(wizard.save, SaveState::Saved { .. });
+            ui.horizontal(|ui| {
+                if saved {
+                    if ui.button("Close").clicked() {
+                        wizard.open = false;
+                    }
+                    return;
+                }
+                if ui
+                    .button("Skip setup")
+                    .on_hover_text("Keep the defaults; edit dendrite.toml later")
+                    .clicked()
+                {
+                    skip_setup(&base_url);
+                    wizard.open = false;
+                }
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if let Some(next) = wizard.step.next() {
+                        if ui.button("Next").clicked() {
+                            wizard.step = next;
+                        }
+                    }
+                    if let Some(previous) = wizard.step.previous() {
+                        if ui.button("Back").clicked() {
+                            wizard.step = previous;
+                        }
+                    }
+                });
+            });
+        });
+}
+
+fn interfaces_step(ui: &mut egui::Ui, wizard: &mut SetupWizard) {
+    let Some(status) = wizard.status.clone() else { return };
+
+    ui.label("Subnet to scan for devices:");
+    if status.suggested_subnets.is_empty() {
+        ui.weak("No network interfaces found; enter a subnet on the next step.");
+    }
+    for suggestion in &status.suggested_subnets {
+        let selected = wizard.subnet == suggestion.subnet && wizard.prefix_len == suggestion.prefix_len;
+        let label = format!(
+            "{}/{} on {} ({} hosts)",
+            suggestion.subnet, suggestion.prefix_len, suggestion.interface, suggestion.hosts
+        );
+        if ui.radio(selected, label).clicked() {
+            wizard.subnet = suggestion.subnet.clone();
+            wizard.prefix_len = suggestion.prefix_len;
+        }
+    }
+
+    ui.add_space(8.0);
+    ui.checkbox(&mut wizard.lldp_transmit, "Announce this host via LLDP")
+        .on_hover_text("Lets switches and devices see which port the host is on");
+    if wizard.lldp_transmit {
+        ui.indent("lldp_interfaces", |ui| {
+            for iface in &status.interfaces {
+                let mut selected = wizard.interfaces.contains(&iface.name);
+                if ui.checkbox(&mut selected, format!("{} ({})", iface.name, iface.ip)).changed() {
+                    wizard.toggle_interface(&iface.name, selected);
+                }
+            }
+            ui.weak("None selected announces on every active interface.");
+        });
+    }
+}
+
+fn discovery_step(ui: &mut egui::Ui, wizard: &mut SetupWizard) {
+    egui::Grid::new("setup_discovery").num_columns(2).spacing([12.0, 6.0]).show(ui, |ui| {
+        ui.label("Subnet");
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut wizard.subnet).desired_width(140.0));
+            ui.label("/");
+            ui.add(egui::DragValue::new(&mut wizard.prefix_len).range(8..=32));
+        });
+        ui.end_row();
+
+        ui.label("Scan interval");
+        ui.add(egui::DragValue::new(&mut wizard.discovery_interval_secs).range(1..=3600).suffix(" s"));
+        ui.end_row();
+
+        ui.label("Connection checks");
+        ui.checkbox(&mut wizard.heartbeat_enabled, "Ping devices every few seconds");
+        ui.end_row();
+    });
+}
+
+fn security_step(ui: &mut egui::Ui, wizard: &mut SetupWizard) {
+    ui.checkbox(&mut wizard.tls_enabled, "Serve over HTTPS");
+    if wizard.tls_enabled {
+        egui::Grid::new("setup_tls").num_columns(2).spacing([12.0, 6.0]).show(ui, |ui| {
+            ui.label("Certificate");
+            ui.add(egui::TextEdit::singleline(&mut wizard.tls_cert).hint_text("/etc/dendrite/cert.pem"));
+            ui.end_row();
+            ui.label("Private key");
+            ui.add(egui::TextEdit::singleline(&mut wizard.tls_key).hint_text("/etc/dendrite/key.pem"));
+            ui.end_row();
+        });
+        if wizard.tls_key == REDACTED {
+            ui.weak("The current key path is kept.");
+        }
+        ui.weak("Paths are on the daemon's host, in PEM format.");
+    }
+
+    ui.add_space(8.0);
+    ui.checkbox(&mut wizard.require_token, "Require an access token for the API");
+    if wizard.require_token {
+        ui.weak("Tokens are issued by dendrite-se051d; this page will need one after the restart.");
+    }
+}
+
+fn test_scan_step(
+    ui: &mut egui::Ui,
+    wizard: &mut SetupWizard,
+    registry: &DeviceRegistry,
+    base_url: &str,
+    pending: &PendingSetup,
+) {
+    ui.label(format!("Scan {}/{} once with these settings.", wizard.subnet.trim(), wizard.prefix_len));
+    ui.add_space(4.0);
+
+    let running = wizard.test_scan == TestScan::Running;
+    ui.horizontal(|ui| {
+        if ui.add_enabled(!running, egui::Button::new("Run test scan")).clicked() {
+            wizard.test_scan = TestScan::Running;
+            start_test_scan(base_url, wizard.subnet.trim(), wizard.prefix_len, pending);
+        }
+        match &wizard.test_scan {
+            TestScan::Idle => {}
+            TestScan::Running => {
+                ui.spinner();
+            }
+            TestScan::Done { found } => {
+                ui.label(format!("{} device(s) found", found));
+            }
+            TestScan::Failed(e) => {
+                ui.colored_label(egui::Color32::RED, e);
+            }
+        }
+    });
+
+    // Devices stream in over the WebSocket while the scan runs
+    if wizard.test_scan != TestScan::Idle {
+        ui.add_space(4.0);
+        egui::ScrollArea::vertical().max_height(160.0).show(ui, |ui| {
+            for device in registry.devices.iter().filter(|d| split_id(&d.id).0 == PRIMARY_DAEMON) {
+                ui.label(format!("{}  {}", device.name, device.ip));
+            }
+        });
+    }
+}
+
+fn save_step(ui: &mut egui::Ui, wizard: &mut SetupWizard, base_url: &str, pending: &PendingSetup) {
+    let path = wizard.status.as_ref().map(|s| s.config_path.clone()).unwrap_or_default();
+
+    match wizard.save.clone() {
+        SaveState::Saved { restart_required } => {
+            ui.label(format!("Saved to {}. Discovery restarted with the new settings.", path));
+            if restart_required {
+                ui.colored_label(
+                    egui::Color32::from_rgb(230, 180, 60),
+                    "Restart the daemon to apply the HTTPS and access token settings.",
+                );
+            }
+            return;
+        }
+        SaveState::Failed(problems) => {
+            ui.colored_label(egui::Color32::RED, "The configuration was not saved:");
+            for problem in problems {
+                ui.colored_label(egui::Color32::RED, format!("  {}", problem));
+            }
+            ui.add_space(4.0);
+        }
+        SaveState::Idle | SaveState::Saving => {}
+    }
+
+    egui::Grid::new("setup_review").num_columns(2).spacing([12.0, 4.0]).show(ui, |ui| {
+        ui.label("Subnet");
+        ui.label(format!("{}/{}", wizard.subnet.trim(), wizard.prefix_len));
+        ui.end_row();
+        ui.label("Scan interval");
+        ui.label(format!("{} s", wizard.discovery_interval_secs));
+        ui.end_row();
+        ui.label("LLDP announce");
+        ui.label(match (wizard.lldp_transmit, wizard.interfaces.is_empty()) {
+            (false, _) => "off".to_string(),
+            (true, true) => "all interfaces".to_string(),
+            (true, false) => wizard.interfaces.join(", "),
+        });
+        ui.end_row();
+        ui.label("HTTPS");
+        ui.label(if wizard.tls_enabled { "on" } else { "off" });
+        ui.end_row();
+        ui.label("Access token");
+        ui.label(if wizard.require_token { "required" } else { "not required" });
+        ui.end_row();
+    });
+
+    ui.add_space(8.0);
+    let saving = wizard.save == SaveState::Saving;
+    if ui.add_enabled(!saving, egui::Button::new(format!("Save to {}", path))).clicked() {
+        wizard.save = SaveState::Saving;
+        save_config(base_url, wizard.config_json(), pending);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(config: serde_json::Value) -> SetupStatus {
+        SetupStatus {
+            required: true,
+            config_path: "dendrite.toml".to_string(),
+            interfaces: Vec::new(),
+            suggested_subnets: vec![SubnetSuggestion {
+                interface: "eth0".to_string(),
+                subnet: "10.0.3.0".to_string(),
+                prefix_len: 24,
+                hosts: 254,
+            }],
+            config,
+        }
+    }
+
+    #[test]
+    fn test_prefers_interface_subnet_over_default() {
+        let wizard = SetupWizard::from_status(status(serde_json::json!({
+            "daemon": { "discovery_interval_secs": 60, "tls": null },
+            "discovery": { "subnet": "192.168.186.0", "prefix_len": 24 },
+        })));
+        assert!(wizard.open);
+        assert_eq!((wizard.subnet.as_str(), wizard.prefix_len), ("10.0.3.0", 24));
+        assert!(!wizard.tls_enabled);
+    }
+
+    #[test]
+    fn test_config_keeps_settings_the_wizard_does_not_cover() {
+        let mut wizard = SetupWizard::from_status(status(serde_json::json!({
+            "daemon": { "bind": "0.0.0.0:9000", "tls": { "cert": "/c.pem", "key": REDACTED } },
+            "discovery": { "subnet": "10.0.3.0", "prefix_len": 24, "mcumgr_port": 1337 },
+            "parent": { "name": "navq95", "board": "imx95-navq", "ports": 6 },
+        })));
+        assert!(wizard.tls_enabled);
+        wizard.discovery_interval_secs = 15;
+        wizard.lldp_transmit = true;
+        wizard.toggle_interface("eth0", true);
+        wizard.require_token = true;
+
+        let config = wizard.config_json();
+        assert_eq!(config["daemon"]["bind"], "0.0.0.0:9000");
+        assert_eq!(config["daemon"]["discovery_interval_secs"], 15);
+        assert_eq!(config["daemon"]["tls"]["key"], REDACTED);
+        assert_eq!(config["discovery"]["mcumgr_port"], 1337);
+        assert_eq!(config["discovery"]["lldp_tx_interfaces"], serde_json::json!(["eth0"]));
+        assert_eq!(config["parent"]["name"], "navq95");
+        assert_eq!(config["auth"]["require_token"], true);
+
+        wizard.tls_enabled = false;
+        assert!(wizard.config_json()["daemon"]["tls"].is_null());
+    }
+}
@@ -0,0 +1,307 @@
+//! Topology graph hierarchy
+//!
+//! "View Topology Graph" first shows the registry's devices flat. When the
+//! graph opens the primary daemon's `GET /api/hcdf` is fetched and the graph
+//! is rebuilt from `Topology::from_hcdf`, so switches and parents get their
+//! children. Devices the HCDF doesn't mention stay as they were.
+//!
+//! `GET /api/lldp/neighbors` is fetched alongside, and again whenever the
+//! daemon reports an LLDP neighbor changed or expired while the graph is open.
+//! A neighbor's switch port labels the edge to its device; neighbors without
+//! a device are drawn as grey "unverified" nodes under the root.
+
+use bevy::prelude::*;
+use dendrite_api_types::LldpNeighborJson;
+use dendrite_core::{Hcdf, Topology};
+use std::sync::{Arc, Mutex};
+
+use crate::app::{DeviceData, DeviceRegistry, GraphVisualization, TopologyBus, TopologyData, TopologyNode};
+use crate::network::DaemonConfig;
+
+/// Id prefix of graph nodes only known from LLDP
+const LLDP_NODE_PREFIX: &str = "lldp:";
+
+#[derive(Resource, Default)]
+struct PendingTopologyHcdf(Arc<Mutex<Option<Hcdf>>>);
+
+/// The primary daemon's LLDP neighbors, as last fetched
+#[derive(Resource, Default)]
+pub struct LldpNeighbors {
+    pub neighbors: Vec<LldpNeighborJson>,
+    /// The daemon reported a change since the last fetch
+    pub stale: bool,
+    pending: Arc<Mutex<Option<Vec<LldpNeighborJson>>>>,
+}
+
+pub struct TopologyGraphPlugin;
+
+impl Plugin for TopologyGraphPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PendingTopologyHcdf>()
+            .init_resource::<LldpNeighbors>()
+            .add_systems(Update, (fetch_on_open, process_topology_hcdf, process_lldp_neighbors));
+    }
+}
+
+/// Registry graph with the hierarchy of an HCDF topology applied
+///
+/// Nodes the registry doesn't have (the parent comp, say) are added after
+/// the registry's, by name.
+pub fn from_topology(topology: &Topology, devices: &[DeviceData]) -> TopologyData {
+    let mut data = TopologyData::from_devices(devices);
+    let mut graph = topology.to_graph();
+    graph.nodes.sort_by(|a, b| a.name.cmp(&b.name));
+
+    for node in graph.nodes {
+        let children = node.children.into_iter().map(|c| c.0).collect();
+        if let Some(existing) = data.nodes.iter_mut().find(|n| n.id == node.id.0) {
+            existing.is_parent = node.is_parent;
+            existing.port = node.port.or(existing.port);
+            existing.children = children;
+            continue;
+        }
+        data.nodes.push(TopologyNode {
+            id: node.id.0,
+            name: node.name,
+            board: node.board,
+            is_parent: node.is_parent,
+            port: node.port,
+            can_node_id: None,
+            children,
+            unverified: false,
+        });
+    }
+    for bus in graph.buses {
+        if !data.buses.iter().any(|b| b.name == bus.name) {
+            data.buses.push(TopologyBus { name: bus.name, members: bus.members.into_iter().map(|m| m.0).collect() });
+        }
+    }
+    data.root = graph.root.map(|r| r.0);
+    data
+}
+
+/// Put LLDP neighbors into a graph, replacing those put in before
+///
+/// A neighbor whose device is in the graph gives that node its switch port;
+/// any other becomes an unverified node under the root.
+pub fn apply_lldp(data: &mut TopologyData, neighbors: &[LldpNeighborJson]) {
+    data.nodes.retain(|n| !n.unverified);
+    for node in &mut data.nodes {
+        node.children.retain(|c| !c.starts_with(LLDP_NODE_PREFIX));
+    }
+
+    let mut unverified = Vec::new();
+    for neighbor in neighbors {
+        let device = neighbor.device_id.as_deref().and_then(|id| data.nodes.iter_mut().find(|n| n.id == id));
+        match device {
+            Some(node) => node.port = neighbor.port.or(node.port),
+            None => unverified.push(TopologyNode {
+                id: format!("{}{}", LLDP_NODE_PREFIX, neighbor.chassis_id),
+                name: neighbor.system_name.clone().unwrap_or_else(|| neighbor.chassis_id.clone()),
+                board: None,
+                is_parent: false,
+                port: neighbor.port,
+                can_node_id: None,
+                children: Vec::new(),
+                unverified: true,
+            }),
+        }
+    }
+    if let Some(root) = data.root.as_deref().and_then(|root| data.nodes.iter_mut().find(|n| n.id == root)) {
+        root.children.extend(unverified.iter().map(|n| n.id.clone()));
+    }
+    data.nodes.extend(unverified);
+}
+
+fn fetch_lldp_neighbors(base_url: &str, lldp: &LldpNeighbors) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let pending = lldp.pending.clone();
+        let url = format!("{}/api/lldp/neighbors", base_url);
+        wasm_bindgen_futures::spawn_local(async move {
+            match crate::network::DaemonRequest::get(&url).send().await {
+                Ok(response) if response.ok() => match response.json::<Vec<LldpNeighborJson>>().await {
+                    Ok(neighbors) => {
+                        if let Ok(mut data) = pending.lock() {
+                            *data = Some(neighbors);
+                        }
+                    }
+                    Err(e) => tracing::warn!("Invalid LLDP neighbors: {:?}", e),
+                },
+                Ok(response) => tracing::warn!("LLDP neighbors unavailable ({})", response.status()),
+                Err(e) => tracing::warn!("Failed to fetch LLDP neighbors: {:?}", e),
+            }
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = (base_url, lldp);
+    }
+}
+
+fn fetch_hcdf(base_url: &str, pending: &PendingTopologyHcdf) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let pending = pending.0.clone();
+        let url = format!("{}/api/hcdf", base_url);
+        wasm_bindgen_futures::spawn_local(async move {
+            match crate::network::DaemonRequest::get(&url).send().await {
+                Ok(response) if response.ok() => match response.text().await.map(|xml| Hcdf::from_xml(&xml)) {
+                    Ok(Ok(hcdf)) => {
+                        if let Ok(mut data) = pending.lock() {
+                            *data = Some(hcdf);
+                        }
+                    }
+                    Ok(Err(e)) => tracing::warn!("Invalid HCDF for topology: {}", e),
+                    Err(e) => tracing::warn!("Failed to read HCDF for topology: {:?}", e),
+                },
+                // The flat graph stays up
+                Ok(response) => tracing::warn!("HCDF unavailable for topology ({})", response.status()),
+                Err(e) => tracing::warn!("Failed to fetch HCDF for topology: {:?}", e),
+            }
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = (base_url, pending);
+    }
+}
+
+/// Fetch each time the graph opens, and LLDP neighbors again when they change
+fn fetch_on_open(
+    graph_vis: Res<GraphVisualization>,
+    pending: Res<PendingTopologyHcdf>,
+    mut lldp: ResMut<LldpNeighbors>,
+    daemon_config: Res<DaemonConfig>,
+    mut was_open: Local<bool>,
+) {
+    if graph_vis.show && !*was_open {
+        fetch_hcdf(&daemon_config.http_url, &pending);
+    }
+    if graph_vis.show && (!*was_open || lldp.stale) {
+        lldp.stale = false;
+        fetch_lldp_neighbors(&daemon_config.http_url, &lldp);
+    }
+    *was_open = graph_vis.show;
+}
+
+fn process_topology_hcdf(
+    pending: Res<PendingTopologyHcdf>,
+    registry: Res<DeviceRegistry>,
+    lldp: Res<LldpNeighbors>,
+    mut graph_vis: ResMut<GraphVisualization>,
+) {
+    let Some(hcdf) = pending.0.lock().ok().and_then(|mut data| data.take()) else {
+        return;
+    };
+    if graph_vis.show {
+        let mut topology = from_topology(&Topology::from_hcdf(&hcdf), &registry.devices);
+        apply_lldp(&mut topology, &lldp.neighbors);
+        graph_vis.topology = Some(topology);
+    }
+}
+
+fn process_lldp_neighbors(mut lldp: ResMut<LldpNeighbors>, mut graph_vis: ResMut<GraphVisualization>) {
+    let Some(neighbors) = lldp.pending.lock().ok().and_then(|mut data| data.take()) else {
+        return;
+    };
+    if let Some(topology) = graph_vis.topology.as_mut() {
+        apply_lldp(topology, &neighbors);
+    }
+    lldp.neighbors = neighbors;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dendrite_scene::graph_layout::radial_layout;
+
+    #[test]
+    fn test_graph_from_hcdf_topology() {
+        let hcdf = Hcdf::from_xml(
+            r#"<hcdf version="2.0">
+  <comp name="navq95" role="parent" hwid="0xaa"/>
+  <mcu name="spinali" hwid="0x01"><discovered><ip>192.168.186.10</ip><port>2</port></discovered></mcu>
+  <mcu name="esc" hwid="0x02"><discovered><ip>0.0.0.0</ip><can bus="can0" node="7"/></discovered></mcu>
+</hcdf>"#,
+        )
+        .unwrap();
+        let graph = from_topology(&Topology::from_hcdf(&hcdf), &[]);
+
+        assert_eq!(graph.root.as_deref(), Some("0xaa"));
+        let parent = graph.nodes.iter().find(|n| n.id == "0xaa").unwrap();
+        assert!(parent.is_parent);
+        assert_eq!(parent.children, vec!["0x01".to_string()]);
+        let spinali = graph.nodes.iter().find(|n| n.id == "0x01").unwrap();
+        assert!(!spinali.is_parent);
+        assert_eq!(spinali.port, Some(2));
+        assert_eq!(graph.buses.len(), 1);
+        assert_eq!(graph.buses[0].members, vec!["0x02".to_string()]);
+    }
+
+    #[test]
+    fn test_lldp_ports_and_unverified_nodes() {
+        let hcdf = Hcdf::from_xml(
+            r#"<hcdf version="2.0">
+  <comp name="switch" role="parent" hwid="0xaa"/>
+  <mcu name="spinali" hwid="0x01"><discovered><ip>192.168.186.10</ip></discovered></mcu>
+</hcdf>"#,
+        )
+        .unwrap();
+        let neighbor = |chassis_id: &str, port, device_id: Option<&str>| LldpNeighborJson {
+            interface: "eth0".to_string(),
+            chassis_id: chassis_id.to_string(),
+            port_id: format!("swp{}", port),
+            port: Some(port),
+            system_name: None,
+            ttl_secs: Some(120),
+            last_refresh: "2026-01-01T00:00:00Z".to_string(),
+            device_id: device_id.map(str::to_string),
+        };
+        let neighbors = [neighbor("02:00:00:00:00:01", 3, Some("0x01")), neighbor("02:00:00:00:00:09", 7, None)];
+        let mut graph = from_topology(&Topology::from_hcdf(&hcdf), &[]);
+        apply_lldp(&mut graph, &neighbors);
+
+        let spinali = graph.nodes.iter().find(|n| n.id == "0x01").unwrap();
+        assert_eq!(spinali.port, Some(3));
+        assert!(!spinali.unverified);
+        let unverified = graph.nodes.iter().find(|n| n.unverified).unwrap();
+        assert_eq!(unverified.name, "02:00:00:00:00:09");
+        assert_eq!(unverified.port, Some(7));
+        let switch = graph.nodes.iter().find(|n| n.id == "0xaa").unwrap();
+        assert!(switch.children.contains(&unverified.id));
+
+        // Applying again replaces rather than adds, and an expired neighbor goes
+        apply_lldp(&mut graph, &neighbors[..1]);
+        assert!(!graph.nodes.iter().any(|n| n.unverified));
+        let switch = graph.nodes.iter().find(|n| n.id == "0xaa").unwrap();
+        assert_eq!(switch.children, vec!["0x01".to_string()]);
+    }
+
+    #[test]
+    fn test_switch_is_laid_out_in_the_center() {
+        let mcus: String = (1..=6)
+            .map(|i| {
+                format!(r#"<mcu name="mcu-{i}" hwid="0x0{i}"><discovered><ip>10.0.0.{i}</ip><port>{i}</port></discovered></mcu>"#)
+            })
+            .collect();
+        let xml = format!(r#"<hcdf version="2.1"><comp name="switch" role="parent" hwid="0xaa"/>{mcus}</hcdf>"#);
+        let hcdf = Hcdf::from_xml(&xml).unwrap();
+        let graph = from_topology(&Topology::from_hcdf(&hcdf), &[]);
+        let switch = graph.nodes.iter().find(|n| n.id == "0xaa").unwrap();
+        assert_eq!(switch.children.len(), 6);
+
+        let layout = radial_layout(
+            graph.root.as_deref(),
+            graph.nodes.iter().map(|n| (n.id.as_str(), n.children.as_slice())),
+            &Default::default(),
+        );
+        assert_eq!(layout.position("0xaa"), Some([0.0, 0.0]));
+        for child in &switch.children {
+            let [x, y] = layout.position(child).unwrap();
+            assert!((x.hypot(y) - 1.0).abs() < 1e-5, "{child}");
+        }
+    }
+}
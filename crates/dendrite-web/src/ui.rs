@@ -4,21 +4,47 @@ use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 use bevy_egui::{egui, EguiContexts, EguiPrimaryContextPass};
 
-use crate::app::{ActiveRotationAxis, ActiveRotationField, CameraSettings, ConnectionDialog, DeviceOrientations, DevicePositions, DeviceRegistry, DeviceStatus, FirmwareCheckState, FirmwareStatusData, FrameVisibility, GraphVisualization, OtaState, SelectedDevice, ShowRotationAxis, TopologyData, TopologyNode, UiLayout, WorldSettings};
-use crate::network::{cancel_ota_update, check_all_firmware, DaemonConfig, HeartbeatState, NetworkInterfaces, OtaUpdateState, PendingFirmwareData, PendingHcdfExport, ReconnectEvent, start_ota_update, toggle_heartbeat, trigger_scan_on_interface, upload_local_firmware, export_hcdf, import_hcdf, save_hcdf_to_server, update_device_position};
+use crate::app::{ActiveRotationAxis, ActiveRotationField, CameraSettings, ConnectionDialog, DeviceData, DeviceOrientations, DevicePositions, DeviceRegistry, DeviceStatus, FirmwareCheckState, FirmwareStatusData, FrameVisibility, GraphVisualization, Notifications, OtaState, PortData, SelectedDevice, ShowRotationAxis, TopologyData, UiLayout, WorldSettings};
+use crate::network::{cancel_ota_update, check_all_firmware, DaemonConfig, DataUsage, HeartbeatState, LowBandwidthMode, ReadOnlyMode, NetworkInterfaces, OtaUpdateState, PendingFirmwareData, PendingHcdfExport, PendingUrdfExport, HcdfExportOptions, ReconnectEvent, start_ota_update, toggle_heartbeat, trigger_scan_on_interface, upload_local_firmware, upload_model_override, remove_model_override, export_hcdf, export_urdf, import_hcdf, save_hcdf_to_server, PositionSync, RemoveMode, DeviceLatency, PendingLatencyData, set_heartbeat_method, DeviceImages, PendingDeviceImages, confirm_device_image, fetch_device_images, HEARTBEAT_METHODS, HcdfRegeneration, PendingRegeneration, request_hcdf_regeneration, ImportPreview, PendingImportPreview, preview_hcdf_import, FragmentReload, PendingFragmentReload, reload_fragments, apply_hcdf_regeneration, set_pose_constraint, sync_port_pose, DeviceShell, PendingShellOutput, run_device_shell, DeviceConsole, PendingConsole, ConsoleState, open_device_console, close_device_console, send_console_line};
+use crate::device_filter::{filter_bar, DeviceListFilter};
+use crate::fleet::Fleet;
+use crate::history::History;
+use crate::attention::Attention;
 use crate::file_picker::{FileFilter, FilePickerContext, FilePickerState, PendingFileResults, trigger_file_open, trigger_file_save};
+use crate::daemons::{split_id, Daemons, LinkState, PRIMARY_DAEMON};
+use crate::assemblies::{send_assembly_pose, AssemblyInfo, Assemblies};
+use crate::occlusion::Occlusion;
+use crate::overlap::Overlaps;
+use crate::heatmap::DeviceHeatmap;
+use crate::scene::DeviceBounds;
+use dendrite_scene::constraints::{self, BoundedPose, ConstraintViolation};
+use dendrite_scene::camera_views::{views_section, CameraView, CameraViews};
+use dendrite_scene::edit_history::{EditHistory, EditPose, PoseEdit};
+use dendrite_scene::frame_overlay::{forward_enu, heading_degrees, ShowFrameOverlay};
+use dendrite_scene::graph_layout::radial_layout;
+use dendrite_core::{ChangeKind, Pose, MODEL_OVERRIDE_VISUAL};
+use dendrite_scene::heatmap::Heatmap;
+use dendrite_scene::keepout::{KeepoutScene, ShowKeepouts};
+use dendrite_scene::ui::render_heatmap_legend;
 
 /// Grouped system parameters for the main UI system to work around Bevy's 16-param limit
 #[derive(SystemParam)]
 pub struct UiParams<'w, 's> {
     pub contexts: EguiContexts<'w, 's>,
-    pub registry: Res<'w, DeviceRegistry>,
+    pub registry: ResMut<'w, DeviceRegistry>,
     pub selected: ResMut<'w, SelectedDevice>,
     pub camera_settings: ResMut<'w, CameraSettings>,
     pub positions: ResMut<'w, DevicePositions>,
     pub orientations: ResMut<'w, DeviceOrientations>,
+    pub device_bounds: Res<'w, DeviceBounds>,
+    pub overlaps: Res<'w, Overlaps>,
     pub active_rotation_field: ResMut<'w, ActiveRotationField>,
     pub show_rotation_axis: ResMut<'w, ShowRotationAxis>,
+    pub show_frame_overlay: ResMut<'w, ShowFrameOverlay>,
+    pub keepouts: Res<'w, KeepoutScene>,
+    pub show_keepouts: ResMut<'w, ShowKeepouts>,
+    pub device_heatmap: ResMut<'w, DeviceHeatmap>,
+    pub heatmap: Res<'w, Heatmap>,
     pub world_settings: ResMut<'w, WorldSettings>,
     pub frame_visibility: ResMut<'w, FrameVisibility>,
     pub device_query: Query<'w, 's, (&'static crate::scene::DeviceEntity, &'static mut Transform)>,
@@ -28,21 +54,56 @@ pub struct UiParams<'w, 's> {
     pub pending_firmware: Res<'w, PendingFirmwareData>,
     pub ui_layout: ResMut<'w, UiLayout>,
     pub daemon_config: Res<'w, DaemonConfig>,
+    pub read_only: Res<'w, ReadOnlyMode>,
+    pub low_bandwidth: ResMut<'w, LowBandwidthMode>,
+    pub data_usage: Res<'w, DataUsage>,
     pub connection_dialog: ResMut<'w, ConnectionDialog>,
     pub reconnect_events: MessageWriter<'w, ReconnectEvent>,
     pub ota_state: ResMut<'w, OtaState>,
     pub file_picker_state: ResMut<'w, FilePickerState>,
     pub pending_file_results: Res<'w, PendingFileResults>,
     pub pending_hcdf_export: Res<'w, PendingHcdfExport>,
+    pub pending_urdf_export: Res<'w, PendingUrdfExport>,
+    pub hcdf_export_options: ResMut<'w, HcdfExportOptions>,
     pub graph_vis: ResMut<'w, GraphVisualization>,
+    pub position_sync: ResMut<'w, PositionSync>,
+    pub notifications: Res<'w, Notifications>,
+    pub device_latency: ResMut<'w, DeviceLatency>,
+    pub pending_latency: Res<'w, PendingLatencyData>,
+    pub device_images: ResMut<'w, DeviceImages>,
+    pub pending_images: Res<'w, PendingDeviceImages>,
+    pub regeneration: ResMut<'w, HcdfRegeneration>,
+    pub pending_regeneration: Res<'w, PendingRegeneration>,
+    pub fragment_reload: ResMut<'w, FragmentReload>,
+    pub pending_fragment_reload: Res<'w, PendingFragmentReload>,
+    pub import_preview: ResMut<'w, ImportPreview>,
+    pub occlusion: ResMut<'w, Occlusion>,
+    pub assemblies: ResMut<'w, Assemblies>,
+    pub history: ResMut<'w, History>,
+    pub fleet: ResMut<'w, Fleet>,
+    pub attention: ResMut<'w, Attention>,
+    pub daemons: ResMut<'w, Daemons>,
+    pub device_filter: ResMut<'w, DeviceListFilter>,
+    pub edit_history: ResMut<'w, EditHistory>,
+    pub camera_views: ResMut<'w, CameraViews>,
+    pub device_shell: ResMut<'w, DeviceShell>,
+    pub pending_shell: Res<'w, PendingShellOutput>,
+    pub device_console: ResMut<'w, DeviceConsole>,
+    pub pending_console: Res<'w, PendingConsole>,
+    pub time: Res<'w, Time>,
 }
 
+/// Tooltip for controls disabled in a read-only session
+const READ_ONLY_HINT: &str = "Read-only session: spectators can't change devices or the HCDF";
+
 pub struct UiPlugin;
 
 impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
         // UI layout updates run in Update
-        app.add_systems(Update, (update_ui_layout, process_file_picker_results))
+        app.init_resource::<EditHistory>()
+            .insert_resource(CameraViews::load())
+            .add_systems(Update, (update_ui_layout, process_file_picker_results, expire_notifications))
             // Main UI system runs in EguiPrimaryContextPass for proper input handling (bevy_egui 0.38+)
             .add_systems(EguiPrimaryContextPass, ui_system);
     }
@@ -53,6 +114,9 @@ fn process_file_picker_results(
     mut file_picker_state: ResMut<FilePickerState>,
     pending_hcdf_export: Res<PendingHcdfExport>,
     daemon_config: Res<DaemonConfig>,
+    daemons: Res<Daemons>,
+    mut import_preview: ResMut<ImportPreview>,
+    pending_import_preview: Res<PendingImportPreview>,
 ) {
     // Process completed file picker results
     while let Some(result) = file_picker_state.take_result() {
@@ -63,18 +127,27 @@ fn process_file_picker_results(
 
         match result.context {
             FilePickerContext::FirmwareUpload { device_id } => {
-                if let Some(content) = result.content {
+                if let (Some(content), Some((base_url, id))) = (result.content, daemons.route(&device_id)) {
                     tracing::info!("Uploading local firmware to device {}: {} ({} bytes)",
                         device_id, result.filename, content.len());
-                    upload_local_firmware(&device_id, content, &daemon_config.http_url);
+                    upload_local_firmware(id, content, base_url);
+                }
+            }
+            FilePickerContext::ModelOverride { device_id, for_board } => {
+                if let (Some(content), Some((base_url, id))) = (result.content, daemons.route(&device_id)) {
+                    tracing::info!("Uploading model override for device {}: {} ({} bytes)",
+                        device_id, result.filename, content.len());
+                    upload_model_override(id, &result.filename, content, for_board, base_url);
                 }
             }
             FilePickerContext::HcdfImport => {
                 if let Some(content) = result.content {
                     // Convert bytes to string
                     if let Ok(xml) = String::from_utf8(content) {
-                        tracing::warn!("Importing HCDF file: {} ({} bytes)", result.filename, xml.len());
-                        import_hcdf(xml, false, &daemon_config.http_url);
+                        // Show what the file would change before importing it
+                        import_preview.in_progress = true;
+                        import_preview.preview = None;
+                        preview_hcdf_import(result.filename, xml, &daemon_config.http_url, &pending_import_preview);
                     } else {
                         tracing::error!("HCDF file is not valid UTF-8");
                     }
@@ -84,6 +157,9 @@ fn process_file_picker_results(
                 // Export was completed (file saved via browser download)
                 tracing::info!("HCDF export completed: {}", result.filename);
             }
+            FilePickerContext::UrdfExport => {
+                tracing::info!("URDF export completed: {}", result.filename);
+            }
             FilePickerContext::Custom(name) => {
                 tracing::info!("Custom file picker result for '{}': {}", name, result.filename);
             }
@@ -120,14 +196,61 @@ fn update_ui_layout(
     }
 }
 
+/// Count down and remove expired notifications
+fn expire_notifications(time: Res<Time>, mut notifications: ResMut<Notifications>) {
+    if !notifications.messages.is_empty() {
+        notifications.tick(time.delta_secs());
+    }
+}
+
+/// Put a device back to the pose before `edit` (`undo`) or after it (redo) and sync it to the daemon
+fn apply_pose_edit(
+    edit: &PoseEdit,
+    undo: bool,
+    positions: &mut DevicePositions,
+    orientations: &mut DeviceOrientations,
+    device_query: &mut Query<(&crate::scene::DeviceEntity, &mut Transform)>,
+    position_sync: &mut PositionSync,
+) {
+    let (from, to) = if undo { (edit.new_pose, edit.old_pose) } else { (edit.old_pose, edit.new_pose) };
+    positions.positions.insert(edit.device_id.clone(), to.position);
+    // Only touch the rotation if the edit did, so a position edit keeps it exactly
+    if from.orientation != to.orientation {
+        orientations.orientations.insert(edit.device_id.clone(), to.orientation);
+    }
+    for (device, mut transform) in device_query.iter_mut() {
+        if device.device_id == edit.device_id {
+            transform.translation = to.position;
+            if from.orientation != to.orientation {
+                transform.rotation = Quat::from_euler(EulerRot::XYZ, to.orientation.x, to.orientation.y, to.orientation.z);
+            }
+            break;
+        }
+    }
+    let (position, orientation) = (to.position, to.orientation);
+    position_sync.queue(&edit.device_id, [position.x, position.y, position.z], Some([orientation.x, orientation.y, orientation.z]));
+    position_sync.finish();
+}
+
 fn ui_system(mut params: UiParams) {
     let is_mobile = params.ui_layout.is_mobile;
     let panel_width = params.ui_layout.panel_width();
     let ui_scale = params.ui_layout.ui_scale;
+    let read_only = params.read_only.is_read_only();
 
     // Get the egui context - early return if not available
     let Ok(ctx) = params.contexts.ctx_mut() else { return };
 
+    // Undo/redo pose edits, unless a text field has the keyboard
+    if !read_only && !ctx.wants_keyboard_input() {
+        // Checked first: Ctrl+Z alone would also match with Shift held
+        let redo = ctx.input_mut(|i| i.consume_shortcut(&egui::KeyboardShortcut::new(egui::Modifiers::COMMAND | egui::Modifiers::SHIFT, egui::Key::Z)));
+        let undo = !redo && ctx.input_mut(|i| i.consume_shortcut(&egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Z)));
+        if let Some(edit) = if undo { params.edit_history.undo() } else if redo { params.edit_history.redo() } else { None } {
+            apply_pose_edit(&edit, undo, &mut params.positions, &mut params.orientations, &mut params.device_query, &mut params.position_sync);
+        }
+    }
+
     // Set up style for mobile - compact but still touch-friendly
     if is_mobile {
         let mut style = (*ctx.style()).clone();
@@ -161,6 +284,11 @@ fn ui_system(mut params: UiParams) {
                         egui::Color32::RED
                     };
                     ui.colored_label(status_color, "●");
+                    if read_only {
+                        read_only_badge(ui, ui_scale);
+                    }
+                    keepout_warning(ui, &params.keepouts);
+                    bandwidth_status(ui, &mut params.low_bandwidth, &params.data_usage, ui_scale);
 
                     ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                         // Details toggle (only if device selected)
@@ -199,6 +327,9 @@ fn ui_system(mut params: UiParams) {
                     ui.heading("Devices");
                 }
 
+                // Search stays above the scroll area so it's at hand in a long list
+                filter_bar(ui, &mut params.device_filter, params.firmware_state.enabled, ui_scale);
+
                 ui.separator();
 
                 // Wrap everything in a scroll area so the panel is scrollable
@@ -223,6 +354,11 @@ fn ui_system(mut params: UiParams) {
                     } else {
                         ui.label("Disconnected");
                     }
+                    if read_only {
+                        read_only_badge(ui, ui_scale);
+                    }
+                    keepout_warning(ui, &params.keepouts);
+                    bandwidth_status(ui, &mut params.low_bandwidth, &params.data_usage, ui_scale);
                     ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                         if ui.small_button("Connect").clicked() {
                             params.connection_dialog.show = true;
@@ -239,6 +375,17 @@ fn ui_system(mut params: UiParams) {
                         }
                     });
                 });
+                if params.low_bandwidth.should_suggest() {
+                    ui.horizontal_wrapped(|ui| {
+                        ui.label(egui::RichText::new("Cellular connection detected").small());
+                        if ui.small_button("Use low bandwidth").clicked() {
+                            params.low_bandwidth.enabled = true;
+                        }
+                        if ui.small_button("Dismiss").clicked() {
+                            params.low_bandwidth.dismissed = true;
+                        }
+                    });
+                }
                 ui.separator();
 
                 // Network Interface Selector
@@ -289,18 +436,74 @@ fn ui_system(mut params: UiParams) {
                                     } else {
                                         egui::Button::new("Scan Network")
                                     };
-                                    if ui.add(button).clicked() {
+                                    if ui.add_enabled(!read_only, button).on_disabled_hover_text(READ_ONLY_HINT).clicked() {
                                         trigger_scan_on_interface(&subnet, prefix, &params.daemon_config.http_url);
                                         params.network_interfaces.scan_in_progress = true;
+                                        params.network_interfaces.scan_progress = None;
+                                    }
+
+                                    // Progress of the probe phase, from the daemon's scan events
+                                    let interfaces = &params.network_interfaces;
+                                    if interfaces.scan_in_progress {
+                                        match interfaces.scan_progress {
+                                            Some((done, total)) if total > 0 => {
+                                                ui.add(
+                                                    egui::ProgressBar::new(done as f32 / total as f32)
+                                                        .text(format!("Scanning {}/{} hosts", done, total))
+                                                );
+                                            }
+                                            _ => {
+                                                ui.horizontal(|ui| {
+                                                    ui.spinner();
+                                                    if interfaces.scan_total_hosts > 0 {
+                                                        ui.label(format!("Finding hosts among {} addresses...", interfaces.scan_total_hosts));
+                                                    } else {
+                                                        ui.label("Finding hosts...");
+                                                    }
+                                                });
+                                            }
+                                        }
+                                    } else if let Some(found) = interfaces.last_scan_found {
+                                        ui.label(
+                                            egui::RichText::new(format!("Last scan found {} device{}", found, if found == 1 { "" } else { "s" }))
+                                                .size(11.0 * ui_scale)
+                                                .color(egui::Color32::GRAY)
+                                        );
                                     }
                                 }
                             }
                         }
 
+                        // Re-read the daemon's fragment database after fragments were edited
+                        ui.add_space(8.0);
+                        ui.horizontal(|ui| {
+                            let busy = params.fragment_reload.in_progress;
+                            if ui
+                                .add_enabled(!busy && !read_only, egui::Button::new("Refresh fragments"))
+                                .on_disabled_hover_text(READ_ONLY_HINT)
+                                .clicked()
+                            {
+                                params.fragment_reload.in_progress = true;
+                                reload_fragments(&params.daemon_config.http_url, &params.pending_fragment_reload);
+                            }
+                            if busy {
+                                ui.spinner();
+                            }
+                        });
+                        ui.label(
+                            egui::RichText::new("Reloads fragment files and rematches devices")
+                                .size(11.0 * ui_scale)
+                                .color(egui::Color32::GRAY)
+                        );
+
                         // Connection checking checkbox
                         ui.add_space(8.0);
                         let mut check_connection = params.heartbeat_state.enabled;
-                        if ui.checkbox(&mut check_connection, "Check connection").changed() {
+                        if ui
+                            .add_enabled(!read_only, egui::Checkbox::new(&mut check_connection, "Check connection"))
+                            .on_disabled_hover_text(READ_ONLY_HINT)
+                            .changed()
+                        {
                             params.heartbeat_state.enabled = check_connection;
                             toggle_heartbeat(check_connection, &params.daemon_config.http_url);
                         }
@@ -333,89 +536,76 @@ fn ui_system(mut params: UiParams) {
                 ui.separator();
 
                 // Device list
+                let selected_id = params.selected.0.clone();
                 egui::ScrollArea::vertical().show(ui, |ui| {
-                    for device in &params.registry.devices {
-                        let is_selected = params.selected.0.as_ref() == Some(&device.id);
-
-                        // Device name color depends on device status, firmware status, and heartbeat state
-                        // Priority: Offline (red) > Firmware outdated (yellow) > Online (green/white)
-                        let name_color = if device.status == DeviceStatus::Offline {
-                            egui::Color32::from_rgb(200, 100, 100) // Always red for offline
-                        } else if params.firmware_state.enabled {
-                            // Check firmware status when enabled
-                            match params.firmware_state.device_status.get(&device.id) {
-                                Some(FirmwareStatusData::UpdateAvailable { .. }) => {
-                                    egui::Color32::from_rgb(230, 200, 50) // Yellow for outdated
-                                }
-                                Some(FirmwareStatusData::UpToDate) => {
-                                    egui::Color32::from_rgb(100, 200, 100) // Green for up to date
+                    let mut remove_link = None;
+                    let link_keys: Vec<u32> = params.daemons.links.iter().map(|l| l.key).collect();
+                    for key in link_keys {
+                        // Group devices by daemon while several are followed
+                        if params.daemons.is_multi() {
+                            if let Some(link) = params.daemons.get(key) {
+                                daemon_header(ui, link, &mut remove_link, ui_scale);
+                            }
+                        }
+                        // Assemblies are primary-daemon HCDF elements
+                        if key == PRIMARY_DAEMON {
+                            for assembly in params.assemblies.list.clone() {
+                                let (positions, orientations) = (&params.positions, &params.orientations);
+                                let current = |id: &str| {
+                                    let p = positions.positions.get(id)?;
+                                    let o = orientations.orientations.get(id).copied().unwrap_or(Vec3::ZERO);
+                                    Some(Pose::from_array([p.x as f64, p.y as f64, p.z as f64, o.x as f64, o.y as f64, o.z as f64]))
+                                };
+                                let (registry, selected, ui_layout) = (&params.registry, &mut params.selected, &mut params.ui_layout);
+                                let (firmware_state, heartbeat_state) = (&params.firmware_state, &params.heartbeat_state);
+                                let filter = &params.device_filter;
+                                let shown = |d: &&DeviceData| {
+                                    assembly.members.contains(&d.id) && filter.shows(d, firmware_state, selected_id.as_deref())
+                                };
+                                if filter.is_active() && !registry.devices.iter().any(|d| shown(&d)) {
+                                    continue;
                                 }
-                                _ => {
-                                    // Unknown or loading - use connection status color
-                                    if params.heartbeat_state.enabled && device.status == DeviceStatus::Online {
-                                        egui::Color32::from_rgb(100, 200, 100) // Green
-                                    } else {
-                                        egui::Color32::from_rgb(200, 200, 200) // White
+                                let members = |ui: &mut egui::Ui| {
+                                    for device in registry.devices.iter().filter(shown) {
+                                        device_row(ui, device, selected, firmware_state, heartbeat_state, ui_layout, is_mobile, ui_scale);
                                     }
-                                }
-                            }
-                        } else {
-                            // Firmware checking disabled - use connection status
-                            if params.heartbeat_state.enabled && device.status == DeviceStatus::Online {
-                                egui::Color32::from_rgb(100, 200, 100) // Green
-                            } else if device.status == DeviceStatus::Unknown {
-                                egui::Color32::GRAY
-                            } else {
-                                egui::Color32::from_rgb(200, 200, 200) // White
-                            }
-                        };
-
-                        let text = egui::RichText::new(&device.name)
-                            .color(name_color)
-                            .size(14.0 * ui_scale);
-
-                        // On mobile, make the entire row a larger touch target
-                        let response = if is_mobile {
-                            ui.add_sized(
-                                [ui.available_width(), 36.0 * ui_scale],
-                                egui::Button::new(text).selected(is_selected)
-                            )
-                        } else {
-                            ui.selectable_label(is_selected, text)
-                        };
-
-                        if response.clicked() {
-                            params.selected.0 = Some(device.id.clone());
-                            // On mobile, show the details panel when a device is selected
-                            if is_mobile {
-                                params.ui_layout.show_right_panel = true;
-                                params.ui_layout.show_left_panel = false;
+                                    let missing = assembly.members.len()
+                                        - registry.devices.iter().filter(|d| assembly.members.contains(&d.id)).count();
+                                    if missing > 0 {
+                                        ui.label(
+                                            egui::RichText::new(format!("{} member(s) not discovered", missing))
+                                                .size(11.0 * ui_scale)
+                                                .color(egui::Color32::GRAY)
+                                        );
+                                    }
+                                };
+                                let moved = assembly_group(ui, &assembly, &mut params.assemblies, current, &params.daemon_config.http_url, read_only, ui_scale, members);
+                                apply_member_poses(moved, &mut params.positions, &mut params.orientations, &mut params.device_query);
                             }
                         }
-
-                        // Show inline details on desktop only (mobile uses right panel)
-                        // Note: last_seen is shown in right panel, not here
-                        if is_selected && !is_mobile {
-                            ui.indent("device_details", |ui| {
-                                ui.label(format!("ID: {}", &device.id));
-                                ui.label(format!("IP: {}", &device.ip));
-                                if let Some(board) = &device.board {
-                                    ui.label(format!("Board: {}", board));
-                                }
-                                if let Some(port) = device.port {
-                                    ui.label(format!("Port: {}", port));
-                                }
-                                if let Some(version) = &device.version {
-                                    ui.label(format!("Firmware: {}", version));
-                                }
-                            });
+                        for device in params.registry.devices.iter().filter(|d| split_id(&d.id).0 == key) {
+                            if params.assemblies.of(&device.id).is_some()
+                                || !params.device_filter.shows(device, &params.firmware_state, selected_id.as_deref())
+                            {
+                                continue;
+                            }
+                            device_row(ui, device, &mut params.selected, &params.firmware_state, &params.heartbeat_state, &mut params.ui_layout, is_mobile, ui_scale);
                         }
                     }
+                    if let Some(key) = remove_link {
+                        params.daemons.remove(key);
+                    }
                 });
 
                 ui.separator();
 
-                ui.label(format!("{} devices", params.registry.devices.len()));
+                let shown = params
+                    .registry
+                    .devices
+                    .iter()
+                    .filter(|d| params.device_filter.shows(d, &params.firmware_state, selected_id.as_deref()))
+                    .count();
+                ui.label(params.device_filter.count_label(shown, params.registry.devices.len()));
 
                 ui.separator();
 
@@ -431,7 +621,8 @@ fn ui_system(mut params: UiParams) {
                             } else {
                                 egui::Button::new("Import")
                             };
-                            if ui.add(import_button).clicked() {
+                            let busy = params.import_preview.in_progress;
+                            if ui.add_enabled(!read_only && !busy, import_button).on_disabled_hover_text(READ_ONLY_HINT).clicked() {
                                 tracing::warn!("Import button clicked, triggering file picker");
                                 trigger_file_open(
                                     &params.pending_file_results,
@@ -446,6 +637,30 @@ fn ui_system(mut params: UiParams) {
                             );
                         });
 
+                        // Refresh from devices (re-query each device's embedded HCDF)
+                        ui.horizontal(|ui| {
+                            let refresh_button = if is_mobile {
+                                egui::Button::new(egui::RichText::new("Refresh from devices").size(14.0 * ui_scale))
+                                    .min_size(egui::vec2(0.0, 32.0))
+                            } else {
+                                egui::Button::new("Refresh from devices")
+                            };
+                            let busy = params.regeneration.in_progress;
+                            if ui.add_enabled(!busy && !read_only, refresh_button).on_disabled_hover_text(READ_ONLY_HINT).clicked() {
+                                params.regeneration.in_progress = true;
+                                params.regeneration.preview = None;
+                                request_hcdf_regeneration(&params.daemon_config.http_url, &params.pending_regeneration);
+                            }
+                            if busy {
+                                ui.spinner();
+                            }
+                        });
+                        ui.label(
+                            egui::RichText::new("Re-query online devices for their HCDF")
+                                .size(10.0 * ui_scale)
+                                .color(egui::Color32::GRAY)
+                        );
+
                         ui.add_space(4.0);
 
                         // Export options
@@ -459,7 +674,7 @@ fn ui_system(mut params: UiParams) {
                             } else {
                                 egui::Button::new("Save to Server")
                             };
-                            if ui.add(save_server_button).clicked() {
+                            if ui.add_enabled(!read_only, save_server_button).on_disabled_hover_text(READ_ONLY_HINT).clicked() {
                                 save_hcdf_to_server(&params.daemon_config.http_url, None);
                             }
                         });
@@ -479,7 +694,24 @@ fn ui_system(mut params: UiParams) {
                             };
                             if ui.add(download_button).clicked() {
                                 // Fetch HCDF from backend, then trigger browser download
-                                export_hcdf(&params.daemon_config.http_url, &params.pending_hcdf_export);
+                                // Include toggle groups the user changed so they're recorded as default_hidden
+                                let mut toggles = params.frame_visibility.changed_toggle_states(&params.registry.devices);
+                                // The HCDF only covers the primary daemon's devices
+                                toggles.retain(|(id, _, _)| split_id(id).0 == PRIMARY_DAEMON);
+                                export_hcdf(&params.daemon_config.http_url, toggles, &params.hcdf_export_options, &params.pending_hcdf_export);
+                            }
+
+                            // Same export, converted to URDF for simulation
+                            let urdf_button = if is_mobile {
+                                egui::Button::new(egui::RichText::new("Export URDF").size(14.0 * ui_scale))
+                                    .min_size(egui::vec2(0.0, 32.0))
+                            } else {
+                                egui::Button::new("Export URDF")
+                            };
+                            if ui.add(urdf_button).clicked() {
+                                let mut toggles = params.frame_visibility.changed_toggle_states(&params.registry.devices);
+                                toggles.retain(|(id, _, _)| split_id(id).0 == PRIMARY_DAEMON);
+                                export_urdf(&params.daemon_config.http_url, toggles, &params.hcdf_export_options, &params.pending_urdf_export);
                             }
                         });
                         ui.label(
@@ -487,6 +719,12 @@ fn ui_system(mut params: UiParams) {
                                 .size(10.0 * ui_scale)
                                 .color(egui::Color32::GRAY)
                         );
+                        ui.checkbox(&mut params.hcdf_export_options.include_provisional, "Include suggested positions")
+                            .on_hover_text("Keep positions the daemon picked for devices nobody has placed yet");
+                        ui.checkbox(&mut params.hcdf_export_options.include_model_overrides, "Include model overrides")
+                            .on_hover_text("Reference uploaded models by their local path and hash instead of the fragment models");
+                        ui.checkbox(&mut params.hcdf_export_options.annotate, "Annotate element sources")
+                            .on_hover_text("Add a comment above each device saying whether it came from the device, a fragment, an import or a manual edit");
 
                         // Check if we have pending HCDF export data to save (for browser download)
                         if let Ok(mut export_data) = params.pending_hcdf_export.0.lock() {
@@ -501,6 +739,38 @@ fn ui_system(mut params: UiParams) {
                                 );
                             }
                         }
+                        if let Ok(mut export_data) = params.pending_urdf_export.0.lock() {
+                            if let Some(content) = export_data.take() {
+                                trigger_file_save(
+                                    &params.pending_file_results,
+                                    FilePickerContext::UrdfExport,
+                                    "dendrite_config.urdf",
+                                    &content,
+                                    "application/xml",
+                                );
+                            }
+                        }
+                    });
+
+                ui.separator();
+
+                // Views - presets and saved camera viewpoints
+                egui::CollapsingHeader::new(egui::RichText::new("Views").size(14.0 * ui_scale))
+                    .default_open(false)
+                    .show(ui, |ui| {
+                        let camera = &params.camera_settings;
+                        let current = CameraView::current("", camera.azimuth, camera.elevation, camera.target_distance, camera.target_focus);
+                        if let Some(view) = views_section(ui, &mut params.camera_views, current) {
+                            // Only the camera moves: the selection stays as it is
+                            let camera = &mut params.camera_settings;
+                            camera.target_orbit = Some((view.azimuth, view.elevation));
+                            if let Some(distance) = view.distance {
+                                camera.target_distance = distance.clamp(0.05, 5.0);
+                            }
+                            if let Some(focus) = view.focus {
+                                camera.target_focus = Vec3::from_array(focus);
+                            }
+                        }
                     });
 
                 ui.separator();
@@ -531,6 +801,46 @@ fn ui_system(mut params: UiParams) {
                         // Axis toggle
                         ui.checkbox(&mut params.world_settings.show_axis, "Show World Axis");
 
+                        // Overlap check toggle (recomputed whenever a device moves)
+                        ui.checkbox(&mut params.world_settings.check_overlaps, "Check Overlaps")
+                            .on_hover_text("Outline devices whose visuals intersect");
+
+                        // Keep-out volume toggle
+                        if !params.keepouts.volumes.is_empty() {
+                            ui.checkbox(&mut params.show_keepouts.0, "Show Keep-out Volumes");
+                        }
+
+                        ui.separator();
+
+                        // Heatmap coloring by a daemon metric
+                        let selected_text = params.device_heatmap.selected()
+                            .map(|m| m.label.clone())
+                            .unwrap_or_else(|| "None".to_string());
+                        let mut new_metric: Option<Option<String>> = None;
+                        ui.add_enabled_ui(!params.device_heatmap.available().is_empty(), |ui| {
+                            egui::ComboBox::from_label("Color by")
+                                .selected_text(selected_text)
+                                .show_ui(ui, |ui| {
+                                    if ui.selectable_label(params.device_heatmap.metric.is_none(), "None").clicked() {
+                                        new_metric = Some(None);
+                                    }
+                                    for metric in params.device_heatmap.available() {
+                                        let current = params.device_heatmap.metric.as_deref() == Some(metric.key.as_str());
+                                        let label = if metric.unit.is_empty() {
+                                            metric.label.clone()
+                                        } else {
+                                            format!("{} ({})", metric.label, metric.unit)
+                                        };
+                                        if ui.selectable_label(current, label).clicked() {
+                                            new_metric = Some(Some(metric.key.clone()));
+                                        }
+                                    }
+                                });
+                        }).response.on_disabled_hover_text("The daemon doesn't provide device metrics");
+                        if let Some(metric) = new_metric {
+                            params.device_heatmap.metric = metric;
+                        }
+
                         ui.separator();
 
                         // Grid spacing control
@@ -557,6 +867,15 @@ fn ui_system(mut params: UiParams) {
                             egui::Slider::new(&mut params.world_settings.grid_alpha, 0.0..=1.0)
                         );
 
+                        ui.separator();
+
+                        // How many pose edits can be undone
+                        ui.label("Undo Depth:");
+                        let mut depth = params.edit_history.depth();
+                        if ui.add(egui::DragValue::new(&mut depth).range(1..=1000).suffix(" edits")).changed() {
+                            params.edit_history.set_depth(depth);
+                        }
+
                         // NOTE: Render scale feature removed - scale_factor_override doesn't work
                         // correctly in WASM (renders to partial canvas instead of downscaling)
                     });
@@ -572,24 +891,55 @@ fn ui_system(mut params: UiParams) {
                 };
                 if ui.add_sized([ui.available_width(), 0.0], graph_button).clicked() {
                     params.graph_vis.show = true;
-                    // Build topology from current device registry
-                    let nodes: Vec<TopologyNode> = params.registry.devices.iter().map(|d| {
-                        TopologyNode {
-                            id: d.id.clone(),
-                            name: d.name.clone(),
-                            board: d.board.clone(),
-                            is_parent: false, // TODO: detect parent from HCDF
-                            port: d.port,
-                            children: Vec::new(),
-                        }
-                    }).collect();
-                    params.graph_vis.topology = Some(TopologyData {
-                        nodes,
-                        root: None,
-                    });
+                    // Flat until the daemon's HCDF arrives with the hierarchy
+                    params.graph_vis.topology = Some(TopologyData::from_devices(&params.registry.devices));
                     params.graph_vis.pan_offset = [0.0, 0.0];
                     params.graph_vis.zoom = 1.0;
                 }
+
+                // Past registry states from the daemon's journal
+                let history_button = if is_mobile {
+                    egui::Button::new(egui::RichText::new("History").size(14.0 * ui_scale))
+                        .min_size(egui::vec2(0.0, 40.0))
+                } else {
+                    egui::Button::new("History")
+                };
+                if ui.add_sized([ui.available_width(), 0.0], history_button).clicked() {
+                    params.history.open = !params.history.open;
+                }
+
+                // Board, sensor, port and firmware counts across the fleet
+                let fleet_button = if is_mobile {
+                    egui::Button::new(egui::RichText::new("Fleet").size(14.0 * ui_scale))
+                        .min_size(egui::vec2(0.0, 40.0))
+                } else {
+                    egui::Button::new("Fleet")
+                };
+                if ui.add_sized([ui.available_width(), 0.0], fleet_button).clicked() {
+                    params.fleet.open = !params.fleet.open;
+                }
+
+                // Findings from the daemon's attention inbox
+                let open_findings = params.attention.open_count();
+                let bell_label = if open_findings > 0 {
+                    format!("🔔 Attention ({})", open_findings)
+                } else {
+                    "🔔 Attention".to_string()
+                };
+                let bell_text = if open_findings > 0 {
+                    egui::RichText::new(bell_label).color(egui::Color32::from_rgb(255, 200, 50))
+                } else {
+                    egui::RichText::new(bell_label)
+                };
+                let bell_button = if is_mobile {
+                    egui::Button::new(bell_text.size(14.0 * ui_scale))
+                        .min_size(egui::vec2(0.0, 40.0))
+                } else {
+                    egui::Button::new(bell_text)
+                };
+                if ui.add_sized([ui.available_width(), 0.0], bell_button).clicked() {
+                    params.attention.open = !params.attention.open;
+                }
                 }); // End ScrollArea
             });
     }
@@ -609,6 +959,9 @@ fn ui_system(mut params: UiParams) {
             });
     }
 
+    // Port pose edits are applied after the details panel releases the registry
+    let mut port_edit = None;
+
     // Selected device details (right side, only if selected)
     if let Some(id) = params.selected.0.clone() {
         if let Some(device) = params.registry.devices.iter().find(|d| d.id == id) {
@@ -664,16 +1017,26 @@ fn ui_system(mut params: UiParams) {
                                     ui.label("Status:");
                                     // Show "Unknown" when heartbeat checking is off (only for online devices)
                                     // Offline devices always show "Offline" - they were seen offline
+                                    // Offline devices the daemon is retrying count down to the next check
+                                    let retry_in = params
+                                        .registry
+                                        .retry_at
+                                        .get(&device.id)
+                                        .map(|at| at - params.time.elapsed_secs_f64())
+                                        .filter(|secs| *secs > 0.0);
                                     let status_str = match device.status {
-                                        DeviceStatus::Offline => "Offline",
+                                        DeviceStatus::Offline => match retry_in {
+                                            Some(secs) => format!("Offline (retrying in {:.0}s)", secs.ceil()),
+                                            None => "Offline".to_string(),
+                                        },
                                         DeviceStatus::Online => {
                                             if params.heartbeat_state.enabled {
-                                                "Online"
+                                                "Online".to_string()
                                             } else {
-                                                "Unknown"
+                                                "Unknown".to_string()
                                             }
                                         }
-                                        DeviceStatus::Unknown => "Unknown",
+                                        DeviceStatus::Unknown => "Unknown".to_string(),
                                     };
                                     ui.label(status_str);
                                     ui.end_row();
@@ -711,6 +1074,56 @@ fn ui_system(mut params: UiParams) {
                                     }
                                     ui.end_row();
 
+                                    // Heartbeat method (override or auto-selected by the daemon)
+                                    ui.label("Heartbeat:");
+                                    let current_method = device.heartbeat_method.clone();
+                                    let selected_text = match (&current_method, &params.device_latency.method) {
+                                        (Some(method), _) => method.clone(),
+                                        (None, Some(auto)) => format!("Auto ({})", auto),
+                                        (None, None) => "Auto".to_string(),
+                                    };
+                                    let mut new_method: Option<Option<String>> = None;
+                                    ui.horizontal(|ui| {
+                                        ui.add_enabled_ui(!read_only, |ui| {
+                                            egui::ComboBox::from_id_salt("heartbeat_method")
+                                                .selected_text(selected_text)
+                                                .show_ui(ui, |ui| {
+                                                    if ui.selectable_label(current_method.is_none(), "Auto").clicked() {
+                                                        new_method = Some(None);
+                                                    }
+                                                    for method in HEARTBEAT_METHODS {
+                                                        if ui.selectable_label(current_method.as_deref() == Some(method), method).clicked() {
+                                                            new_method = Some(Some(method.to_string()));
+                                                        }
+                                                    }
+                                                    let is_tcp = current_method.as_deref().is_some_and(|m| m.starts_with("tcp-connect:"));
+                                                    if ui.selectable_label(is_tcp, "tcp-connect").clicked() {
+                                                        new_method = Some(Some(format!("tcp-connect:{}", params.device_latency.tcp_port)));
+                                                    }
+                                                });
+                                            // Port for TCP connect checks
+                                            if current_method.as_deref().is_some_and(|m| m.starts_with("tcp-connect:")) {
+                                                let response = ui.add(egui::DragValue::new(&mut params.device_latency.tcp_port).range(1..=65535));
+                                                if response.drag_stopped() || response.lost_focus() {
+                                                    new_method = Some(Some(format!("tcp-connect:{}", params.device_latency.tcp_port)));
+                                                }
+                                            }
+                                        }).response.on_disabled_hover_text(READ_ONLY_HINT);
+                                    });
+                                    if let Some(method) = new_method {
+                                        if let Some((base_url, raw_id)) = params.daemons.route(&id).filter(|_| method != current_method) {
+                                            set_heartbeat_method(raw_id, method, base_url, &params.pending_latency);
+                                        }
+                                    }
+                                    ui.end_row();
+
+                                    // Latency sparkline (samples only exist while heartbeat is enabled)
+                                    if params.heartbeat_state.enabled {
+                                        ui.label("Latency:");
+                                        latency_sparkline(ui, &params.device_latency.samples);
+                                        ui.end_row();
+                                    }
+
                                     // Firmware status (when checking is enabled)
                                     if params.firmware_state.enabled {
                                         ui.label("Firmware Status:");
@@ -788,10 +1201,10 @@ fn ui_system(mut params: UiParams) {
                                     }
                                     _ => {
                                         // In-progress states - show cancel button
-                                        let id_clone = id.clone();
-                                        let base_url = params.daemon_config.http_url.clone();
-                                        if ui.button("Cancel Update").clicked() {
-                                            cancel_ota_update(&id_clone, &base_url);
+                                        if ui.add_enabled(!read_only, egui::Button::new("Cancel Update")).on_disabled_hover_text(READ_ONLY_HINT).clicked() {
+                                            if let Some((base_url, raw_id)) = params.daemons.route(&id) {
+                                                cancel_ota_update(raw_id, base_url);
+                                            }
                                         }
                                     }
                                 }
@@ -799,8 +1212,6 @@ fn ui_system(mut params: UiParams) {
                                 // Show update button if firmware is outdated and no update in progress
                                 if let Some(FirmwareStatusData::UpdateAvailable { .. }) = params.firmware_state.device_status.get(&id) {
                                     ui.separator();
-                                    let id_clone = id.clone();
-                                    let base_url = params.daemon_config.http_url.clone();
                                     let update_button = if is_mobile {
                                         egui::Button::new(
                                             egui::RichText::new("Update Firmware")
@@ -813,11 +1224,75 @@ fn ui_system(mut params: UiParams) {
                                                 .color(egui::Color32::from_rgb(100, 180, 255))
                                         )
                                     };
-                                    if ui.add(update_button).clicked() {
-                                        start_ota_update(&id_clone, &base_url);
+                                    if ui.add_enabled(!read_only, update_button).on_disabled_hover_text(READ_ONLY_HINT).clicked() {
+                                        if let Some((base_url, raw_id)) = params.daemons.route(&id) {
+                                            start_ota_update(raw_id, base_url);
+                                        }
+                                    }
+                                }
+                            }
+
+                            // Image slots reported by MCUmgr, with confirm for unconfirmed/pending images
+                            ui.separator();
+                            ui.horizontal(|ui| {
+                                ui.label(egui::RichText::new("Image Slots").size(12.0 * ui_scale).color(egui::Color32::GRAY));
+                                if ui.small_button("Refresh").clicked() {
+                                    if let Some((base_url, raw_id)) = params.daemons.route(&id) {
+                                        params.device_images.loading = true;
+                                        fetch_device_images(raw_id, base_url, &params.pending_images);
+                                    }
+                                }
+                            });
+                            let mut confirm_hash: Option<String> = None;
+                            match &params.device_images.response {
+                                None if params.device_images.loading => {
+                                    ui.label(egui::RichText::new("Loading...").size(11.0 * ui_scale).color(egui::Color32::GRAY));
+                                }
+                                None => {}
+                                Some(response) if response.images.is_empty() => {
+                                    let text = response.error.as_deref().unwrap_or("No image slots");
+                                    ui.label(egui::RichText::new(text).size(11.0 * ui_scale).color(egui::Color32::GRAY));
+                                }
+                                Some(response) => {
+                                    for image in &response.images {
+                                        ui.horizontal(|ui| {
+                                            let short_hash = image.hash.get(..8).unwrap_or(&image.hash);
+                                            ui.label(format!("{}: {}", image.slot, image.version))
+                                                .on_hover_text(&image.hash);
+                                            ui.label(egui::RichText::new(short_hash).monospace().size(10.0 * ui_scale).color(egui::Color32::GRAY));
+                                            let flags: Vec<&str> = [
+                                                (image.active, "active"),
+                                                (image.confirmed, "confirmed"),
+                                                (image.pending, "pending"),
+                                            ]
+                                            .into_iter()
+                                            .filter_map(|(set, name)| set.then_some(name))
+                                            .collect();
+                                            if !flags.is_empty() {
+                                                ui.label(egui::RichText::new(flags.join(", ")).size(10.0 * ui_scale).color(egui::Color32::GRAY));
+                                            }
+                                            if (image.active && !image.confirmed) || image.pending {
+                                                let confirm = egui::Button::new(
+                                                    egui::RichText::new("Confirm").color(egui::Color32::from_rgb(100, 200, 100))
+                                                );
+                                                if ui.add_enabled(!read_only, confirm)
+                                                    .on_hover_text("Make this image permanent so MCUboot does not revert it")
+                                                    .on_disabled_hover_text(READ_ONLY_HINT)
+                                                    .clicked()
+                                                {
+                                                    confirm_hash = Some(image.hash.clone());
+                                                }
+                                            }
+                                        });
                                     }
                                 }
                             }
+                            if let Some(hash) = confirm_hash {
+                                if let Some((base_url, raw_id)) = params.daemons.route(&id) {
+                                    params.device_images.loading = true;
+                                    confirm_device_image(raw_id, &hash, base_url, &params.pending_images);
+                                }
+                            }
 
                             // Always show local firmware upload button (for dev images)
                             ui.separator();
@@ -836,7 +1311,7 @@ fn ui_system(mut params: UiParams) {
                                             .color(egui::Color32::from_rgb(200, 150, 50))
                                     )
                                 };
-                                if ui.add(upload_button).clicked() {
+                                if ui.add_enabled(!read_only, upload_button).on_disabled_hover_text(READ_ONLY_HINT).clicked() {
                                     // Open file picker for firmware
                                     trigger_file_open(
                                         &params.pending_file_results,
@@ -851,6 +1326,244 @@ fn ui_system(mut params: UiParams) {
                                 );
                             }
 
+                            // Replace a wrong fragment model with a local .glb
+                            {
+                                let has_override = device.visuals.iter().any(|v| v.name == MODEL_OVERRIDE_VISUAL);
+                                ui.horizontal_wrapped(|ui| {
+                                    let override_button = egui::Button::new(
+                                        egui::RichText::new("Override Model").color(egui::Color32::from_rgb(200, 150, 50))
+                                    );
+                                    if ui.add_enabled(!read_only, override_button)
+                                        .on_hover_text("Use a .glb from your computer instead of this device's model")
+                                        .on_disabled_hover_text(READ_ONLY_HINT)
+                                        .clicked()
+                                    {
+                                        trigger_file_open(
+                                            &params.pending_file_results,
+                                            FilePickerContext::ModelOverride { device_id: id.clone(), for_board: false },
+                                            FileFilter::model(),
+                                        );
+                                    }
+                                    if let Some(ref board) = device.board {
+                                        let board_button = egui::Button::new(format!("For all {}", board));
+                                        if ui.add_enabled(!read_only, board_button)
+                                            .on_hover_text("Use the .glb for every device of this board")
+                                            .on_disabled_hover_text(READ_ONLY_HINT)
+                                            .clicked()
+                                        {
+                                            trigger_file_open(
+                                                &params.pending_file_results,
+                                                FilePickerContext::ModelOverride { device_id: id.clone(), for_board: true },
+                                                FileFilter::model(),
+                                            );
+                                        }
+                                    }
+                                    if has_override
+                                        && ui.add_enabled(!read_only, egui::Button::new("Remove Override"))
+                                            .on_hover_text("Go back to the model from the device's HCDF fragment")
+                                            .on_disabled_hover_text(READ_ONLY_HINT)
+                                            .clicked()
+                                    {
+                                        if let Some((base_url, raw_id)) = params.daemons.route(&id) {
+                                            remove_model_override(raw_id, base_url);
+                                        }
+                                    }
+                                });
+                            }
+
+                            // Allowlisted Zephyr shell commands over MCUmgr
+                            egui::CollapsingHeader::new(egui::RichText::new("Shell").size(12.0 * ui_scale))
+                                .default_open(false)
+                                .show(ui, |ui| {
+                                    let shell = &mut params.device_shell;
+                                    let mut run = false;
+                                    ui.horizontal(|ui| {
+                                        let response = ui.add_enabled(
+                                            !read_only,
+                                            egui::TextEdit::singleline(&mut shell.input)
+                                                .font(egui::TextStyle::Monospace)
+                                                .hint_text("kernel uptime")
+                                                .desired_width(160.0 * ui_scale),
+                                        );
+                                        let entered = response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+                                        let ready = !read_only && !shell.running && !shell.input.trim().is_empty();
+                                        let label = if shell.running { "Running..." } else { "Run" };
+                                        if ui.add_enabled(ready, egui::Button::new(label))
+                                            .on_hover_text("Commands must be on the daemon's [shell] allowlist")
+                                            .on_disabled_hover_text(if read_only { READ_ONLY_HINT } else { "Type a command" })
+                                            .clicked()
+                                            || (entered && ready)
+                                        {
+                                            run = true;
+                                        }
+                                    });
+                                    if run {
+                                        if let Some((base_url, raw_id)) = params.daemons.route(&id) {
+                                            shell.running = true;
+                                            run_device_shell(&id, raw_id, shell.input.trim(), base_url, &params.pending_shell);
+                                        }
+                                    }
+
+                                    let mut recall = None;
+                                    for entry in &shell.history {
+                                        ui.separator();
+                                        ui.horizontal(|ui| {
+                                            let command = egui::RichText::new(format!("$ {}", entry.command)).monospace().size(11.0 * ui_scale);
+                                            if ui.add(egui::Label::new(command).sense(egui::Sense::click()))
+                                                .on_hover_text("Click to edit and run again")
+                                                .clicked()
+                                            {
+                                                recall = Some(entry.command.clone());
+                                            }
+                                            if let Ok(response) = &entry.result {
+                                                let color = if response.ret == 0 { egui::Color32::GRAY } else { egui::Color32::from_rgb(220, 150, 50) };
+                                                ui.label(egui::RichText::new(format!("ret {}", response.ret)).size(10.0 * ui_scale).color(color));
+                                            }
+                                        });
+                                        match &entry.result {
+                                            Ok(response) => {
+                                                ui.label(egui::RichText::new(response.output.trim_end()).monospace().size(10.0 * ui_scale));
+                                            }
+                                            Err(message) => {
+                                                ui.label(egui::RichText::new(message).size(10.0 * ui_scale).color(egui::Color32::from_rgb(220, 100, 100)));
+                                            }
+                                        }
+                                    }
+                                    if let Some(command) = recall {
+                                        shell.input = command;
+                                    }
+                                });
+
+                            // Live serial console, for devices with a serial_port override
+                            egui::CollapsingHeader::new(egui::RichText::new("Console").size(12.0 * ui_scale))
+                                .default_open(false)
+                                .show(ui, |ui| {
+                                    let console = &mut params.device_console;
+                                    let active = matches!(console.state, ConsoleState::Connecting | ConsoleState::Open);
+                                    ui.horizontal(|ui| {
+                                        if active {
+                                            if ui.button("Disconnect").clicked() {
+                                                close_device_console();
+                                                console.state = ConsoleState::Closed;
+                                            }
+                                        } else if ui.add_enabled(!read_only, egui::Button::new("Connect"))
+                                            .on_hover_text("Needs an operator token and a serial_port override on the daemon")
+                                            .on_disabled_hover_text(READ_ONLY_HINT)
+                                            .clicked()
+                                        {
+                                            let (key, raw_id) = split_id(&id);
+                                            if let Some(link) = params.daemons.get(key) {
+                                                console.state = ConsoleState::Connecting;
+                                                open_device_console(raw_id, &link.config, &params.pending_console);
+                                            }
+                                        }
+                                        let (status, color) = match &console.state {
+                                            ConsoleState::Closed => ("Closed".to_string(), egui::Color32::GRAY),
+                                            ConsoleState::Connecting => ("Connecting...".to_string(), egui::Color32::GRAY),
+                                            ConsoleState::Open => ("Open".to_string(), egui::Color32::from_rgb(100, 200, 100)),
+                                            ConsoleState::Failed(message) => (message.clone(), egui::Color32::from_rgb(220, 100, 100)),
+                                        };
+                                        ui.label(egui::RichText::new(status).size(10.0 * ui_scale).color(color));
+                                    });
+                                    ui.horizontal(|ui| {
+                                        if console.dropped > 0 {
+                                            ui.label(egui::RichText::new(format!("{} bytes dropped", console.dropped))
+                                                .size(10.0 * ui_scale)
+                                                .color(egui::Color32::from_rgb(220, 150, 50)))
+                                                .on_hover_text("Output the daemon discarded because this client fell behind");
+                                        }
+                                        if !console.scrollback.is_empty() && ui.small_button("Clear").clicked() {
+                                            console.scrollback.clear();
+                                        }
+                                    });
+
+                                    egui::ScrollArea::vertical()
+                                        .id_salt("device_console")
+                                        .max_height(200.0 * ui_scale)
+                                        .stick_to_bottom(true)
+                                        .show(ui, |ui| {
+                                            ui.label(egui::RichText::new(&console.scrollback).monospace().size(10.0 * ui_scale));
+                                        });
+
+                                    let open = console.state == ConsoleState::Open;
+                                    let response = ui.add_enabled(
+                                        open && !read_only,
+                                        egui::TextEdit::singleline(&mut console.input)
+                                            .font(egui::TextStyle::Monospace)
+                                            .hint_text("Enter sends the line")
+                                            .desired_width(f32::INFINITY),
+                                    );
+                                    if response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter)) {
+                                        send_console_line(&console.input);
+                                        console.input.clear();
+                                        response.request_focus();
+                                    }
+                                });
+
+                            ui.separator();
+
+                            // Placement limits applied while the pose is edited
+                            {
+                                let mut keep_above_ground = device.keep_above_ground;
+                                let mut stack_on = device.stack_on.clone();
+                                let (daemon, _) = split_id(&id);
+                                let base_name = |base: &str| {
+                                    params.registry.devices.iter()
+                                        .find(|d| d.id == base)
+                                        .map(|d| d.name.clone())
+                                        .unwrap_or_else(|| base.to_string())
+                                };
+                                let keep_response = ui.add_enabled(
+                                    !read_only,
+                                    egui::Checkbox::new(&mut keep_above_ground, "Keep above ground"),
+                                )
+                                    .on_hover_text("Position edits keep the bounding box above Z = 0")
+                                    .on_disabled_hover_text(READ_ONLY_HINT);
+                                let mut stack_changed = false;
+                                ui.add_enabled_ui(!read_only, |ui| {
+                                    ui.horizontal(|ui| {
+                                        ui.label("Stack on:");
+                                        let selected = stack_on.as_deref().map(base_name).unwrap_or_else(|| "Nothing".to_string());
+                                        egui::ComboBox::from_id_salt("stack_on")
+                                            .selected_text(selected)
+                                            .show_ui(ui, |ui| {
+                                                stack_changed |= ui.selectable_value(&mut stack_on, None, "Nothing").changed();
+                                                // Bases come from the same daemon, which stores the constraint
+                                                for base in params.registry.devices.iter().filter(|d| d.id != id && split_id(&d.id).0 == daemon) {
+                                                    stack_changed |= ui
+                                                        .selectable_value(&mut stack_on, Some(base.id.clone()), &base.name)
+                                                        .changed();
+                                                }
+                                            });
+                                    });
+                                });
+                                if keep_response.changed() || stack_changed {
+                                    if let Some((base_url, raw_id)) = params.daemons.route(&id) {
+                                        let base = stack_on.as_deref().map(|base| split_id(base).1);
+                                        set_pose_constraint(raw_id, keep_above_ground, base, base_url);
+                                    }
+                                }
+
+                                // A saved pose that breaks its limits is reported, not moved
+                                if let Some(violation) = constraint_violation(device, &params.positions, &params.orientations, &params.device_bounds) {
+                                    ui.label(
+                                        egui::RichText::new(format!("⚠ Pose is {}", violation))
+                                            .color(egui::Color32::from_rgb(230, 160, 40))
+                                    ).on_hover_text("Edit the position to apply the placement limits");
+                                }
+                            }
+
+                            // Devices whose visuals intersect this one
+                            let colliding: Vec<String> = params.overlaps.colliding_with(&id)
+                                .map(|other| params.registry.devices.iter().find(|d| d.id == other).map(|d| d.name.clone()).unwrap_or_else(|| other.to_string()))
+                                .collect();
+                            if !colliding.is_empty() {
+                                ui.label(
+                                    egui::RichText::new(format!("⚠ Overlaps {}", colliding.join(", ")))
+                                        .color(egui::Color32::from_rgb(230, 60, 60))
+                                ).on_hover_text("Bounding boxes of the device visuals intersect");
+                            }
+
                             ui.separator();
 
                             // Continue with position editing (re-enter grid)
@@ -864,6 +1577,10 @@ fn ui_system(mut params: UiParams) {
                                     ui.end_row();
 
                                     let current_pos = params.positions.positions.get(&id).cloned().unwrap_or(Vec3::ZERO);
+                                    let before = EditPose {
+                                        position: current_pos,
+                                        orientation: params.orientations.orientations.get(&id).cloned().unwrap_or(Vec3::ZERO),
+                                    };
 
                                     // Position labels - shorter on mobile
                                     let (x_label, y_label, z_label) = if is_mobile {
@@ -875,36 +1592,47 @@ fn ui_system(mut params: UiParams) {
                                     // Editable X field
                                     ui.label(x_label);
                                     let mut x_val = current_pos.x;
-                                    let x_response = ui.add(
+                                    let x_response = ui.add_enabled(
+                                        !read_only,
                                         egui::DragValue::new(&mut x_val)
                                             .speed(0.01)
                                             .suffix(" m")
-                                    );
+                                    ).on_disabled_hover_text(READ_ONLY_HINT);
                                     ui.end_row();
 
                                     // Editable Y field
                                     ui.label(y_label);
                                     let mut y_val = current_pos.y;
-                                    let y_response = ui.add(
+                                    let y_response = ui.add_enabled(
+                                        !read_only,
                                         egui::DragValue::new(&mut y_val)
                                             .speed(0.01)
                                             .suffix(" m")
-                                    );
+                                    ).on_disabled_hover_text(READ_ONLY_HINT);
                                     ui.end_row();
 
                                     // Editable Z field
                                     ui.label(z_label);
                                     let mut z_val = current_pos.z;
-                                    let z_response = ui.add(
+                                    let z_response = ui.add_enabled(
+                                        !read_only,
                                         egui::DragValue::new(&mut z_val)
                                             .speed(0.01)
                                             .suffix(" m")
-                                    );
+                                    ).on_disabled_hover_text(READ_ONLY_HINT);
                                     ui.end_row();
 
                                     // Apply position changes if any field was modified
                                     if x_response.changed() || y_response.changed() || z_response.changed() {
-                                        let new_pos = Vec3::new(x_val, y_val, z_val);
+                                        let orient = params.orientations.orientations.get(&id).cloned().unwrap_or(Vec3::ZERO);
+                                        let new_pos = constrained_position(
+                                            device,
+                                            Vec3::new(x_val, y_val, z_val),
+                                            Quat::from_euler(EulerRot::XYZ, orient.x, orient.y, orient.z),
+                                            &params.positions,
+                                            &params.orientations,
+                                            &params.device_bounds,
+                                        );
 
                                         // Update stored position
                                         params.positions.positions.insert(id.clone(), new_pos);
@@ -917,16 +1645,22 @@ fn ui_system(mut params: UiParams) {
                                             }
                                         }
 
-                                        // Sync position to backend (updates HCDF)
-                                        let orient = params.orientations.orientations.get(&id).cloned().unwrap_or(Vec3::ZERO);
-                                        update_device_position(
+                                        // Queue position sync to backend (throttled, updates HCDF)
+                                        params.position_sync.queue(
                                             &id,
                                             [new_pos.x, new_pos.y, new_pos.z],
                                             Some([orient.x, orient.y, orient.z]),
-                                            &params.daemon_config.http_url,
                                         );
                                     }
 
+                                    // Send the final position as soon as the drag/edit ends
+                                    if [&x_response, &y_response, &z_response]
+                                        .iter()
+                                        .any(|r| r.drag_stopped() || r.lost_focus())
+                                    {
+                                        params.position_sync.finish();
+                                    }
+
                                     // Show rotation axis checkbox (unchecked by default)
                                     ui.label("Show Rotation Axis:");
                                     if ui.checkbox(&mut params.show_rotation_axis.0, "").changed() {
@@ -934,6 +1668,12 @@ fn ui_system(mut params: UiParams) {
                                     }
                                     ui.end_row();
 
+                                    // Body FLU vs world ENU overlay (unchecked by default)
+                                    ui.label("Show FLU/ENU Axes:");
+                                    ui.checkbox(&mut params.show_frame_overlay.0, "")
+                                        .on_hover_text("Body axes (solid F/L/U) and world axes (dashed E/N/U) at the device origin");
+                                    ui.end_row();
+
                                     // Show orientation from 3D scene
                                     // Get stored Euler angles (these are display values, not used to compute rotation)
                                     let orient = params.orientations.orientations.get(&id).cloned().unwrap_or(Vec3::ZERO);
@@ -945,33 +1685,36 @@ fn ui_system(mut params: UiParams) {
                                     // Editable Roll field
                                     ui.label("  Roll:");
                                     let mut roll_deg = orient.x.to_degrees();
-                                    let roll_response = ui.add(
+                                    let roll_response = ui.add_enabled(
+                                        !read_only,
                                         egui::DragValue::new(&mut roll_deg)
                                             .speed(1.0)
                                             .suffix("°")
-                                    );
+                                    ).on_disabled_hover_text(READ_ONLY_HINT);
                                     let roll_active = roll_response.has_focus() || roll_response.dragged() || roll_response.hovered();
                                     ui.end_row();
 
                                     // Editable Pitch field
                                     ui.label("  Pitch:");
                                     let mut pitch_deg = orient.y.to_degrees();
-                                    let pitch_response = ui.add(
+                                    let pitch_response = ui.add_enabled(
+                                        !read_only,
                                         egui::DragValue::new(&mut pitch_deg)
                                             .speed(1.0)
                                             .suffix("°")
-                                    );
+                                    ).on_disabled_hover_text(READ_ONLY_HINT);
                                     let pitch_active = pitch_response.has_focus() || pitch_response.dragged() || pitch_response.hovered();
                                     ui.end_row();
 
                                     // Editable Yaw field
                                     ui.label("  Yaw:");
                                     let mut yaw_deg = orient.z.to_degrees();
-                                    let yaw_response = ui.add(
+                                    let yaw_response = ui.add_enabled(
+                                        !read_only,
                                         egui::DragValue::new(&mut yaw_deg)
                                             .speed(1.0)
                                             .suffix("°")
-                                    );
+                                    ).on_disabled_hover_text(READ_ONLY_HINT);
                                     let yaw_active = yaw_response.has_focus() || yaw_response.dragged() || yaw_response.hovered();
                                     ui.end_row();
 
@@ -1003,30 +1746,81 @@ fn ui_system(mut params: UiParams) {
                                             Vec3::new(roll_rad, pitch_rad, yaw_rad)
                                         );
 
+                                        // A rotated box may reach below the ground or off its base
+                                        let rotation = Quat::from_euler(EulerRot::XYZ, roll_rad, pitch_rad, yaw_rad);
+                                        let current = params.positions.positions.get(&id).cloned().unwrap_or(Vec3::ZERO);
+                                        let pos = constrained_position(
+                                            device,
+                                            current,
+                                            rotation,
+                                            &params.positions,
+                                            &params.orientations,
+                                            &params.device_bounds,
+                                        );
+                                        params.positions.positions.insert(id.clone(), pos);
+
                                         // Update the device's rotation quaternion using XYZ Euler order
                                         for (device, mut transform) in params.device_query.iter_mut() {
                                             if device.device_id == id {
-                                                transform.rotation = Quat::from_euler(
-                                                    EulerRot::XYZ,
-                                                    roll_rad,
-                                                    pitch_rad,
-                                                    yaw_rad
-                                                );
+                                                transform.rotation = rotation;
+                                                transform.translation = pos;
                                                 break;
                                             }
                                         }
 
-                                        // Sync orientation to backend (updates HCDF)
-                                        let pos = params.positions.positions.get(&id).cloned().unwrap_or(Vec3::ZERO);
-                                        update_device_position(
+                                        // Queue orientation sync to backend (throttled, updates HCDF)
+                                        params.position_sync.queue(
                                             &id,
                                             [pos.x, pos.y, pos.z],
                                             Some([roll_rad, pitch_rad, yaw_rad]),
-                                            &params.daemon_config.http_url,
                                         );
                                     }
+
+                                    if [&roll_response, &pitch_response, &yaw_response]
+                                        .iter()
+                                        .any(|r| r.drag_stopped() || r.lost_focus())
+                                    {
+                                        params.position_sync.finish();
+                                    }
+
+                                    // A drag or typed value becomes one undo entry once it ends
+                                    let after = EditPose {
+                                        position: params.positions.positions.get(&id).cloned().unwrap_or(Vec3::ZERO),
+                                        orientation: params.orientations.orientations.get(&id).cloned().unwrap_or(Vec3::ZERO),
+                                    };
+                                    let editing = [&x_response, &y_response, &z_response, &roll_response, &pitch_response, &yaw_response]
+                                        .iter()
+                                        .any(|response| response.dragged() || response.has_focus());
+                                    params.edit_history.track(&id, before, after, editing);
+
+                                    // Body forward axis expressed in world coordinates
+                                    let rotation = params.device_query.iter()
+                                        .find(|(device, _)| device.device_id == id)
+                                        .map(|(_, transform)| transform.rotation)
+                                        .unwrap_or(Quat::IDENTITY);
+                                    let forward = forward_enu(rotation);
+                                    ui.label("Forward (ENU):");
+                                    ui.label(format!("E {:.2}  N {:.2}  U {:.2}", forward.x, forward.y, forward.z));
+                                    ui.end_row();
+
+                                    ui.label("  Heading:");
+                                    ui.label(heading_degrees(forward).map_or("-".to_string(), |h| format!("{:.1}°", h)));
+                                    ui.end_row();
                                 });
 
+                            // Undo/redo of pose edits (any device, not just this one)
+                            ui.horizontal(|ui| {
+                                let undo = ui.add_enabled(!read_only && params.edit_history.can_undo(), egui::Button::new("Undo"))
+                                    .on_hover_text("Undo the last pose edit (Ctrl+Z)")
+                                    .clicked();
+                                let redo = ui.add_enabled(!read_only && params.edit_history.can_redo(), egui::Button::new("Redo"))
+                                    .on_hover_text("Redo the last undone pose edit (Ctrl+Shift+Z)")
+                                    .clicked();
+                                if let Some(edit) = if undo { params.edit_history.undo() } else if redo { params.edit_history.redo() } else { None } {
+                                    apply_pose_edit(&edit, undo, &mut params.positions, &mut params.orientations, &mut params.device_query, &mut params.position_sync);
+                                }
+                            });
+
                             ui.separator();
 
                             // Per-device frame visibility toggle (if device has frames or sensors)
@@ -1063,8 +1857,11 @@ fn ui_system(mut params: UiParams) {
                                                         .size(10.0 * ui_scale)
                                                         .color(egui::Color32::GRAY)
                                                 );
-                                                for frame in &device.frames {
+                                                // Child frames are indented under their parent
+                                                for (index, depth) in dendrite_core::frame_tree_order(&device.frames) {
+                                                    let frame = &device.frames[index];
                                                     ui.horizontal(|ui| {
+                                                        ui.add_space(depth as f32 * 12.0 * ui_scale);
                                                         let mut frame_vis = params.frame_visibility.is_frame_visible(&id, &frame.name);
                                                         if ui.checkbox(&mut frame_vis, "").changed() {
                                                             params.frame_visibility.set_frame_visible(&id, &frame.name, frame_vis);
@@ -1218,11 +2015,18 @@ fn ui_system(mut params: UiParams) {
                                                         .color(egui::Color32::GRAY)
                                                 );
                                                 if let Some(ref driver) = sensor.driver {
-                                                    ui.label(
-                                                        egui::RichText::new(format!("Driver: {}", driver))
-                                                            .size(10.0 * ui_scale)
-                                                            .color(egui::Color32::GRAY)
-                                                    );
+                                                    // Prefer the registry label; unknown drivers show the raw name
+                                                    let text = egui::RichText::new(format!("Driver: {}", sensor.driver_label.as_deref().unwrap_or(driver)))
+                                                        .size(10.0 * ui_scale)
+                                                        .color(egui::Color32::GRAY);
+                                                    match sensor.datasheet {
+                                                        Some(ref url) => {
+                                                            ui.hyperlink_to(text, url).on_hover_text(driver);
+                                                        }
+                                                        None => {
+                                                            ui.label(text);
+                                                        }
+                                                    }
                                                 }
                                                 // Per-sensor FOV visibility toggle (only for sensors with FOV)
                                                 if has_fov {
@@ -1267,6 +2071,40 @@ fn ui_system(mut params: UiParams) {
                                                             }
                                                         });
                                                     }
+                                                    // Rays through the FOV against other devices' meshes
+                                                    ui.horizontal(|ui| {
+                                                        if ui.small_button("Check occlusion")
+                                                            .on_hover_text("Raycast the FOV against other devices; cleared when any device moves")
+                                                            .clicked()
+                                                        {
+                                                            params.occlusion.request(&id, &sensor.name);
+                                                        }
+                                                        if params.occlusion.get(&id, &sensor.name).is_some() && ui.small_button("Clear").clicked() {
+                                                            params.occlusion.clear(&id, &sensor.name);
+                                                        }
+                                                    });
+                                                    if let Some(results) = params.occlusion.get(&id, &sensor.name) {
+                                                        for result in results {
+                                                            let blocked = result.report.blocked_percent();
+                                                            let label = match result.fov {
+                                                                Some(ref fov) => format!("{}: {:.1}% blocked", fov, blocked),
+                                                                None => format!("{:.1}% blocked", blocked),
+                                                            };
+                                                            let color = if blocked > 0.0 { egui::Color32::from_rgb(255, 100, 100) } else { egui::Color32::LIGHT_GREEN };
+                                                            ui.label(egui::RichText::new(label).size(10.0 * ui_scale).color(color));
+                                                            for intruder in &result.report.intruders {
+                                                                let name = params.registry.devices.iter()
+                                                                    .find(|d| d.id == intruder.id)
+                                                                    .map(|d| d.name.as_str())
+                                                                    .unwrap_or(&intruder.id);
+                                                                ui.label(
+                                                                    egui::RichText::new(format!("  {} ({:.1}%)", name, intruder.percent))
+                                                                        .size(9.0 * ui_scale)
+                                                                        .color(egui::Color32::GRAY)
+                                                                );
+                                                            }
+                                                        }
+                                                    }
                                                 }
                                                 // Axis alignment toggle (only for sensors with axis_align)
                                                 if let Some(ref axis_align) = sensor.axis_align {
@@ -1321,6 +2159,7 @@ fn ui_system(mut params: UiParams) {
                                         for port in &device.ports {
                                             let port_key = format!("{}:{}", id, port.name);
                                             let is_hovered = params.frame_visibility.hovered_port.as_ref() == Some(&port_key);
+                                            let is_selected = params.frame_visibility.selected_port.as_ref() == Some(&port_key);
                                             let port_color = match port.port_type.to_lowercase().as_str() {
                                                 "ethernet" => egui::Color32::from_rgb(50, 200, 50),
                                                 "can" => egui::Color32::from_rgb(255, 200, 50),
@@ -1338,16 +2177,27 @@ fn ui_system(mut params: UiParams) {
                                             };
 
                                             // Build port label text
-                                            let label_text = format!("{} ({})", port.name, port.port_type);
+                                            let label_text = if port.original_pose.is_some() {
+                                                format!("{} ({}) - edited", port.name, port.port_type)
+                                            } else {
+                                                format!("{} ({})", port.name, port.port_type)
+                                            };
 
                                             // Use selectable_label for built-in hover detection
                                             let response = ui.selectable_label(
-                                                is_hovered,
+                                                is_hovered || is_selected,
                                                 egui::RichText::new(&label_text)
                                                     .size(12.0 * ui_scale)
                                                     .color(display_color)
                                             );
 
+                                            // Click selects a port for pose editing; mesh-placed
+                                            // ports follow their GLTF node and have no pose
+                                            if response.clicked() && port.mesh_name.is_none() {
+                                                params.frame_visibility.selected_port =
+                                                    if is_selected { None } else { Some(port_key.clone()) };
+                                            }
+
                                             // Set hovered_port when hovering over port name in UI
                                             if response.hovered() {
                                                 params.frame_visibility.hovered_port = Some(port_key);
@@ -1355,6 +2205,15 @@ fn ui_system(mut params: UiParams) {
                                                 any_port_hovered = true;
                                             }
                                         }
+
+                                        // Pose editor for the selected port
+                                        let selected = params.frame_visibility.selected_port.clone();
+                                        if let Some(port) = device.ports.iter().find(|p| {
+                                            p.mesh_name.is_none() && selected.as_deref() == Some(format!("{}:{}", id, p.name).as_str())
+                                        }) {
+                                            ui.add_space(4.0);
+                                            port_edit = port_pose_editor(ui, &id, port, ui_scale, read_only);
+                                        }
                                     });
 
                                     // Clear hovered_port only if:
@@ -1396,19 +2255,33 @@ fn ui_system(mut params: UiParams) {
                                 ui.separator();
                             }
 
-                            // Show remove button only for offline devices
+                            // Show remove options only for offline devices
                             if device.status == DeviceStatus::Offline {
-                                let remove_button = if is_mobile {
-                                    egui::Button::new(
-                                        egui::RichText::new("Remove Device")
-                                            .size(16.0 * ui_scale)
-                                            .color(egui::Color32::from_rgb(200, 100, 100))
-                                    ).min_size(egui::vec2(0.0, 40.0))
-                                } else {
-                                    egui::Button::new("Remove Device")
-                                };
-                                if ui.add(remove_button).clicked() {
-                                    crate::network::remove_device(&device.id, &params.daemon_config.http_url);
+                                let mut remove_mode = None;
+                                for (mode, label, help) in [
+                                    (RemoveMode::Archive, "Archive Device", "Hide until it is seen again on the network"),
+                                    (RemoveMode::Forget, "Forget Device", "Delete it, including its saved position"),
+                                ] {
+                                    let remove_button = if is_mobile {
+                                        egui::Button::new(
+                                            egui::RichText::new(label)
+                                                .size(16.0 * ui_scale)
+                                                .color(egui::Color32::from_rgb(200, 100, 100))
+                                        ).min_size(egui::vec2(0.0, 40.0))
+                                    } else {
+                                        egui::Button::new(label)
+                                    };
+                                    if ui.add_enabled(!read_only, remove_button).on_disabled_hover_text(READ_ONLY_HINT).clicked() {
+                                        remove_mode = Some(mode);
+                                    }
+                                    ui.label(
+                                        egui::RichText::new(help)
+                                            .size(10.0 * ui_scale)
+                                            .color(egui::Color32::GRAY)
+                                    );
+                                }
+                                if let Some((mode, (base_url, raw_id))) = remove_mode.zip(params.daemons.route(&device.id)) {
+                                    crate::network::remove_device(raw_id, mode, base_url);
                                     params.selected.0 = None;
                                     params.ui_layout.show_right_panel = false;
                                 }
@@ -1431,6 +2304,28 @@ fn ui_system(mut params: UiParams) {
         }
     }
 
+    if let Some(edit) = port_edit {
+        apply_port_pose_edit(&mut params.registry, &params.daemons, edit);
+    }
+
+    // Heatmap legend (bottom left of the scene)
+    if let Some(metric) = &params.heatmap.0 {
+        render_heatmap_legend(ctx, metric, ui_scale);
+    }
+
+    // Notification overlay (top center)
+    if !params.notifications.messages.is_empty() {
+        egui::Area::new(egui::Id::new("notifications"))
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 10.0))
+            .show(ctx, |ui| {
+                for (message, _) in &params.notifications.messages {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.label(egui::RichText::new(message).size(13.0 * ui_scale));
+                    });
+                }
+            });
+    }
+
     // Connection dialog modal
     if params.connection_dialog.show {
         egui::Window::new("Connect to Daemon")
@@ -1440,7 +2335,7 @@ fn ui_system(mut params: UiParams) {
             .show(ctx, |ui| {
                 ui.set_min_width(300.0);
 
-                ui.label("Enter the daemon address (host:port):");
+                ui.label("Enter a daemon address (host:port):");
                 ui.add_space(8.0);
 
                 // Address input
@@ -1457,13 +2352,30 @@ fn ui_system(mut params: UiParams) {
 
                 ui.add_space(8.0);
 
-                // Show current connection info
-                ui.label(format!("Current: {}", params.daemon_config.http_url));
+                // Followed daemons; the primary is replaced by "Connect"
+                let mut remove_link = None;
+                for link in &params.daemons.links {
+                    daemon_header(ui, link, &mut remove_link, 1.0);
+                }
+                if let Some(key) = remove_link {
+                    params.daemons.remove(key);
+                }
 
                 ui.add_space(12.0);
 
                 ui.horizontal(|ui| {
-                    if ui.button("Connect").clicked() || (response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter))) {
+                    if ui.button("Add").on_hover_text("Follow this daemon as well, in its own region of the scene").clicked() {
+                        let addr = params.connection_dialog.daemon_address.clone();
+                        match params.daemons.add(&addr) {
+                            Ok(_) => {
+                                params.connection_dialog.daemon_address.clear();
+                                params.connection_dialog.error = None;
+                            }
+                            Err(e) => params.connection_dialog.error = Some(e),
+                        }
+                    }
+
+                    if ui.button("Connect").on_hover_text("Replace the primary daemon").clicked() || (response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter))) {
                         let addr = params.connection_dialog.daemon_address.trim();
                         if !addr.is_empty() {
                             params.reconnect_events.write(ReconnectEvent {
@@ -1489,9 +2401,156 @@ fn ui_system(mut params: UiParams) {
                 // Help text
                 ui.label("Tip: You can also use URL parameters:");
                 ui.label("?daemon=192.168.1.100:8080");
+                ui.label("Repeat it to follow several daemons:");
+                ui.label("?daemon=10.0.0.1:8080&daemon=10.0.0.2:8080");
             });
     }
 
+    // HCDF regeneration preview - confirm before applying
+    if let Some(preview) = params.regeneration.preview.clone() {
+        let mut apply = false;
+        let mut cancel = false;
+
+        egui::Window::new("Refresh from devices")
+            .collapsible(false)
+            .resizable(true)
+            .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "Fetched HCDF from {} device(s). {} change(s):",
+                    preview.refreshed.len(),
+                    preview.changes.len()
+                ));
+
+                egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+                    if preview.changes.is_empty() {
+                        ui.label(egui::RichText::new("No changes").color(egui::Color32::GRAY));
+                    }
+                    for change in &preview.changes {
+                        let (symbol, color) = match change.kind {
+                            ChangeKind::Added => ("+", egui::Color32::from_rgb(100, 200, 100)),
+                            ChangeKind::Removed => ("-", egui::Color32::from_rgb(220, 100, 100)),
+                            ChangeKind::Modified => ("~", egui::Color32::from_rgb(220, 180, 80)),
+                        };
+                        let mut text = format!("{} {} {}", symbol, change.element, change.name);
+                        if !change.changes.is_empty() {
+                            text.push_str(&format!(" ({})", change.fields().join(", ")));
+                        }
+                        ui.label(egui::RichText::new(text).color(color).monospace());
+                    }
+                });
+
+                if !preview.stale.is_empty() {
+                    ui.add_space(4.0);
+                    ui.label(egui::RichText::new("Offline (kept as-is):").strong());
+                    for device in &preview.stale {
+                        ui.label(egui::RichText::new(&device.name).color(egui::Color32::GRAY));
+                    }
+                }
+
+                if !preview.failed.is_empty() {
+                    ui.add_space(4.0);
+                    ui.label(egui::RichText::new("Could not refresh (kept as-is):").strong());
+                    for failure in &preview.failed {
+                        ui.label(
+                            egui::RichText::new(format!("{}: {}", failure.name, failure.reason))
+                                .color(egui::Color32::from_rgb(220, 100, 100))
+                        );
+                    }
+                }
+
+                ui.add_space(8.0);
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(!preview.refreshed.is_empty() && !read_only, egui::Button::new("Apply"))
+                        .on_disabled_hover_text(READ_ONLY_HINT)
+                        .clicked()
+                    {
+                        apply = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancel = true;
+                    }
+                });
+            });
+
+        if apply {
+            params.regeneration.preview = None;
+            params.regeneration.in_progress = true;
+            apply_hcdf_regeneration(&preview.token, &params.daemon_config.http_url, &params.pending_regeneration);
+            params.edit_history.clear();
+        } else if cancel {
+            params.regeneration.preview = None;
+        }
+    }
+
+    // HCDF import preview - confirm before importing
+    if let Some(preview) = params.import_preview.preview.clone() {
+        let mut import = false;
+        let mut cancel = false;
+
+        egui::Window::new("Import HCDF")
+            .collapsible(false)
+            .resizable(true)
+            .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+            .show(ctx, |ui| {
+                ui.label(format!("{}: {} change(s):", preview.filename, preview.diff.changes.len()));
+
+                egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+                    if preview.diff.changes.is_empty() {
+                        ui.label(egui::RichText::new("No changes").color(egui::Color32::GRAY));
+                    }
+                    for change in &preview.diff.changes {
+                        let (symbol, color) = match change.kind {
+                            ChangeKind::Added => ("+", egui::Color32::from_rgb(100, 200, 100)),
+                            ChangeKind::Removed => ("-", egui::Color32::from_rgb(220, 100, 100)),
+                            ChangeKind::Modified => ("~", egui::Color32::from_rgb(220, 180, 80)),
+                        };
+                        let mut text = format!("{} {} {}", symbol, change.element, change.name);
+                        if !change.changes.is_empty() {
+                            text.push_str(&format!(" ({})", change.fields().join(", ")));
+                        }
+                        ui.label(egui::RichText::new(text).color(color).monospace());
+                    }
+                });
+
+                if !preview.diff.validation_warnings.is_empty() {
+                    ui.add_space(4.0);
+                    ui.label(egui::RichText::new("Warnings:").strong());
+                    for warning in &preview.diff.validation_warnings {
+                        ui.label(
+                            egui::RichText::new(format!("line {}: {}", warning.line, warning.message))
+                                .color(egui::Color32::from_rgb(220, 180, 80))
+                        );
+                    }
+                }
+
+                ui.add_space(8.0);
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(!read_only, egui::Button::new("Import"))
+                        .on_disabled_hover_text(READ_ONLY_HINT)
+                        .clicked()
+                    {
+                        import = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancel = true;
+                    }
+                });
+            });
+
+        if import {
+            params.import_preview.preview = None;
+            tracing::warn!("Importing HCDF file: {} ({} bytes)", preview.filename, preview.xml.len());
+            import_hcdf(preview.xml, false, &params.daemon_config.http_url);
+            // Undoing would move devices of the previous file
+            params.edit_history.clear();
+        } else if cancel {
+            params.import_preview.preview = None;
+        }
+    }
+
     // Graph visualization overlay
     if params.graph_vis.show {
         let screen_rect = ctx.screen_rect();
@@ -1509,6 +2568,22 @@ fn ui_system(mut params: UiParams) {
                 // Header with close button and controls
                 ui.horizontal(|ui| {
                     ui.heading("Network Topology");
+                    // Ring the members of one assembly
+                    let names: Vec<String> = params.assemblies.list.iter().map(|a| a.name.clone()).collect();
+                    if !names.is_empty() {
+                        let mut highlighted = params.assemblies.highlighted.clone();
+                        egui::ComboBox::from_id_salt("graph_assembly")
+                            .selected_text(highlighted.clone().unwrap_or_else(|| "No assembly".to_string()))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut highlighted, None, "No assembly");
+                                for name in names {
+                                    ui.selectable_value(&mut highlighted, Some(name.clone()), name);
+                                }
+                            });
+                        if highlighted != params.assemblies.highlighted {
+                            params.assemblies.highlighted = highlighted;
+                        }
+                    }
                     ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                         if ui.button("Close").clicked() {
                             params.graph_vis.show = false;
@@ -1562,33 +2637,81 @@ fn ui_system(mut params: UiParams) {
                     let pan = params.graph_vis.pan_offset;
                     let center = rect.center();
 
-                    // Calculate node positions in a radial layout
-                    let node_count = topology.nodes.len();
-                    let radius = 150.0 * zoom;
+                    // Radial layout: the root in the center, each level of its subtree a ring further out
+                    let layout = radial_layout(
+                        topology.root.as_deref(),
+                        topology.nodes.iter().map(|n| (n.id.as_str(), n.children.as_slice())),
+                        &params.graph_vis.collapsed,
+                    );
+                    let ring = 150.0 * zoom;
+                    let radius = ring * layout.radius().max(1.0);
+                    let position_of = |id: &str| {
+                        layout.position(id).map(|[x, y]| egui::pos2(center.x + pan[0] + ring * x, center.y + pan[1] + ring * y))
+                    };
 
                     // Track hover/click state changes to apply after rendering
                     let mut new_hovered: Option<String> = None;
                     let mut clicked_node: Option<String> = None;
+                    let mut toggled_node: Option<String> = None;
+
+                    // Draw shared buses as a bar below the graph with a drop line
+                    // from each member, stacked when there are several
+                    let bus_color = egui::Color32::from_rgb(120, 200, 140);
+                    for (b, bus) in topology.buses.iter().enumerate() {
+                        let members: Vec<egui::Pos2> = bus
+                            .members
+                            .iter()
+                            .filter_map(|id| position_of(id))
+                            .collect();
+                        if members.is_empty() {
+                            continue;
+                        }
+                        let bar_y = center.y + pan[1] + radius + (70.0 + 30.0 * b as f32) * zoom;
+                        let left = members.iter().map(|p| p.x).fold(f32::INFINITY, f32::min) - 20.0 * zoom;
+                        let right = members.iter().map(|p| p.x).fold(f32::NEG_INFINITY, f32::max) + 20.0 * zoom;
+                        for pos in &members {
+                            painter.line_segment(
+                                [*pos, egui::pos2(pos.x, bar_y)],
+                                egui::Stroke::new(1.5 * zoom, bus_color),
+                            );
+                        }
+                        painter.line_segment(
+                            [egui::pos2(left, bar_y), egui::pos2(right, bar_y)],
+                            egui::Stroke::new(4.0 * zoom, bus_color),
+                        );
+                        painter.text(
+                            egui::pos2(left - 6.0 * zoom, bar_y),
+                            egui::Align2::RIGHT_CENTER,
+                            &bus.name,
+                            egui::FontId::proportional(11.0 * zoom),
+                            bus_color,
+                        );
+                    }
 
                     // Draw connections and nodes
-                    for (i, node) in topology.nodes.iter().enumerate() {
-                        let angle = (i as f32 / node_count.max(1) as f32) * std::f32::consts::TAU;
-                        let node_x = center.x + pan[0] + radius * angle.cos();
-                        let node_y = center.y + pan[1] + radius * angle.sin();
-                        let node_pos = egui::pos2(node_x, node_y);
+                    for node in &topology.nodes {
+                        // Inside a collapsed subtree
+                        let Some(node_pos) = position_of(&node.id) else {
+                            continue;
+                        };
 
-                        // Draw connections to children
+                        // Draw connections to children, labelled with the switch port
                         for child_id in &node.children {
-                            if let Some((j, _)) = topology.nodes.iter().enumerate().find(|(_, n)| &n.id == child_id) {
-                                let child_angle = (j as f32 / node_count.max(1) as f32) * std::f32::consts::TAU;
-                                let child_x = center.x + pan[0] + radius * child_angle.cos();
-                                let child_y = center.y + pan[1] + radius * child_angle.sin();
-                                let child_pos = egui::pos2(child_x, child_y);
-
+                            if let Some(child_pos) = position_of(child_id) {
                                 painter.line_segment(
                                     [node_pos, child_pos],
                                     egui::Stroke::new(2.0 * zoom, egui::Color32::from_rgb(100, 150, 200)),
                                 );
+                                let port = topology.nodes.iter().find(|n| &n.id == child_id).and_then(|n| n.port);
+                                if let Some(port) = port {
+                                    painter.text(
+                                        node_pos + (child_pos - node_pos) * 0.5,
+                                        egui::Align2::CENTER_CENTER,
+                                        format!("Port {}", port),
+                                        egui::FontId::proportional(8.4 * zoom),
+                                        egui::Color32::from_rgb(180, 180, 100),
+                                    );
+                                }
                             }
                         }
 
@@ -1601,6 +2724,8 @@ fn ui_system(mut params: UiParams) {
                             egui::Color32::from_rgb(80, 180, 255)
                         } else if node.is_parent {
                             egui::Color32::from_rgb(255, 180, 80)
+                        } else if node.unverified {
+                            egui::Color32::from_rgb(110, 110, 110)
                         } else {
                             egui::Color32::from_rgb(60, 140, 200)
                         };
@@ -1617,6 +2742,13 @@ fn ui_system(mut params: UiParams) {
                             fill_color,
                             egui::Stroke::new(if is_hovered { 3.0 } else { 1.5 }, stroke_color),
                         );
+                        if params.assemblies.is_highlighted(&node.id) {
+                            painter.circle_stroke(
+                                node_pos,
+                                node_radius + 5.0 * zoom,
+                                egui::Stroke::new(2.5 * zoom, egui::Color32::from_rgb(255, 190, 50)),
+                            );
+                        }
 
                         // Draw node label
                         let font_size = 12.0 * zoom;
@@ -1632,22 +2764,43 @@ fn ui_system(mut params: UiParams) {
                         );
 
                         // Board type below
-                        if let Some(ref board) = node.board {
+                        let below = if node.unverified { Some("unverified") } else { node.board.as_deref() };
+                        if let Some(below) = below {
                             painter.text(
                                 egui::pos2(node_pos.x, node_pos.y + node_radius + 8.0 * zoom),
                                 egui::Align2::CENTER_TOP,
-                                board,
+                                below,
                                 egui::FontId::proportional(font_size * 0.8),
                                 egui::Color32::from_rgb(150, 160, 180),
                             );
                         }
 
-                        // Port number if available
-                        if let Some(port) = node.port {
+                        // Count of the nodes a collapsed parent hides
+                        let hidden = layout.hidden_under(&node.id);
+                        if hidden > 0 {
+                            painter.text(
+                                egui::pos2(node_pos.x + node_radius + 4.0 * zoom, node_pos.y),
+                                egui::Align2::LEFT_CENTER,
+                                format!("+{}", hidden),
+                                egui::FontId::proportional(font_size * 0.9),
+                                egui::Color32::from_rgb(255, 180, 80),
+                            );
+                        }
+
+                        // Port number (or CAN node id) if available; a port
+                        // already labels the edge from the node's parent
+                        let on_edge = topology.nodes.iter().any(|n| n.children.contains(&node.id));
+                        let attachment = match (node.port, node.can_node_id) {
+                            (Some(_), _) if on_edge => None,
+                            (Some(port), _) => Some(format!("Port {}", port)),
+                            (None, Some(node_id)) => Some(format!("Node {}", node_id)),
+                            (None, None) => None,
+                        };
+                        if let Some(attachment) = attachment {
                             painter.text(
                                 egui::pos2(node_pos.x, node_pos.y - node_radius - 5.0 * zoom),
                                 egui::Align2::CENTER_BOTTOM,
-                                format!("Port {}", port),
+                                attachment,
                                 egui::FontId::proportional(font_size * 0.7),
                                 egui::Color32::from_rgb(180, 180, 100),
                             );
@@ -1659,9 +2812,12 @@ fn ui_system(mut params: UiParams) {
                             if node_rect.contains(pointer_pos) {
                                 new_hovered = Some(node.id.clone());
 
-                                // Click to select
-                                if response.clicked() {
+                                // Click to select, right-click a parent to fold its subtree;
+                                // unverified nodes have no device to select
+                                if response.clicked() && !node.unverified {
                                     clicked_node = Some(node.id.clone());
+                                } else if response.secondary_clicked() && !node.children.is_empty() {
+                                    toggled_node = Some(node.id.clone());
                                 }
                             }
                         }
@@ -1669,6 +2825,11 @@ fn ui_system(mut params: UiParams) {
 
                     // Apply state changes after iteration
                     params.graph_vis.hovered_node = new_hovered;
+                    if let Some(node_id) = toggled_node {
+                        if !params.graph_vis.collapsed.remove(&node_id) {
+                            params.graph_vis.collapsed.insert(node_id);
+                        }
+                    }
                     if let Some(node_id) = clicked_node {
                         params.selected.0 = Some(node_id);
                         params.graph_vis.show = false; // Close graph and show device details
@@ -1687,12 +2848,345 @@ fn ui_system(mut params: UiParams) {
                 // Instructions at bottom
                 ui.add_space(4.0);
                 ui.horizontal(|ui| {
-                    ui.label(egui::RichText::new("Drag to pan | Scroll to zoom | Click node to select").small().color(egui::Color32::GRAY));
+                    ui.label(egui::RichText::new("Drag to pan | Scroll to zoom | Click node to select | Right-click parent to collapse").small().color(egui::Color32::GRAY));
                 });
             });
     }
 }
 
+/// Badge shown next to the connection status in a read-only session
+fn read_only_badge(ui: &mut egui::Ui, ui_scale: f32) {
+    ui.label(
+        egui::RichText::new("read-only")
+            .size(10.0 * ui_scale)
+            .color(egui::Color32::from_rgb(255, 200, 50))
+    )
+    .on_hover_text(READ_ONLY_HINT);
+}
+
+/// A device's stored placement with its bounding box, None if it hasn't been placed
+fn bounded_pose(id: &str, positions: &DevicePositions, orientations: &DeviceOrientations, bounds: &DeviceBounds) -> Option<BoundedPose> {
+    let translation = *positions.positions.get(id)?;
+    let orient = orientations.orientations.get(id).cloned().unwrap_or(Vec3::ZERO);
+    let (min, max) = bounds.get(id);
+    Some(BoundedPose::new(translation, Quat::from_euler(EulerRot::XYZ, orient.x, orient.y, orient.z), min, max))
+}
+
+/// Where a device's placement limits allow an edited position to go
+fn constrained_position(
+    device: &DeviceData,
+    translation: Vec3,
+    rotation: Quat,
+    positions: &DevicePositions,
+    orientations: &DeviceOrientations,
+    bounds: &DeviceBounds,
+) -> Vec3 {
+    let (min, max) = bounds.get(&device.id);
+    let pose = BoundedPose::new(translation, rotation, min, max);
+    let base = device.stack_on.as_deref().and_then(|base| bounded_pose(base, positions, orientations, bounds));
+    constraints::constrain(&pose, device.keep_above_ground, base.as_ref())
+}
+
+/// How a device's stored pose breaks its placement limits, if it does
+fn constraint_violation(
+    device: &DeviceData,
+    positions: &DevicePositions,
+    orientations: &DeviceOrientations,
+    bounds: &DeviceBounds,
+) -> Option<ConstraintViolation> {
+    let pose = bounded_pose(&device.id, positions, orientations, bounds)?;
+    let base = device.stack_on.as_deref().and_then(|base| bounded_pose(base, positions, orientations, bounds));
+    constraints::violation(&pose, device.keep_above_ground, base.as_ref())
+}
+
+/// Collapsible assembly in the device list: highlight toggle, an editor for
+/// the assembly origin, and the member rows drawn by `members`
+///
+/// Returns the previewed member poses while the origin is being edited; the
+/// daemon is told to move the members once the edit ends.
+#[allow(clippy::too_many_arguments)]
+fn assembly_group(
+    ui: &mut egui::Ui,
+    assembly: &AssemblyInfo,
+    assemblies: &mut Assemblies,
+    current: impl Fn(&str) -> Option<Pose>,
+    base_url: &str,
+    read_only: bool,
+    ui_scale: f32,
+    members: impl FnOnce(&mut egui::Ui),
+) -> Vec<(String, Pose)> {
+    let mut moved = Vec::new();
+    let header = egui::RichText::new(format!("▣ {} ({})", assembly.name, assembly.members.len())).size(14.0 * ui_scale);
+    egui::CollapsingHeader::new(header)
+        .id_salt(("assembly", &assembly.name))
+        .show(ui, |ui| {
+            if let Some(description) = &assembly.description {
+                ui.label(egui::RichText::new(description).size(11.0 * ui_scale).color(egui::Color32::GRAY));
+            }
+
+            let mut highlighted = assemblies.highlighted.as_ref() == Some(&assembly.name);
+            if ui.checkbox(&mut highlighted, "Highlight").changed() {
+                assemblies.highlighted = highlighted.then(|| assembly.name.clone());
+            }
+
+            // Origin position (ENU) and heading; roll and pitch are kept
+            let origin = assembly.origin;
+            let mut values = [origin[0] as f32, origin[1] as f32, origin[2] as f32, (origin[5] as f32).to_degrees()];
+            let (mut changed, mut finished) = (false, false);
+            egui::Grid::new(("assembly_pose", &assembly.name)).num_columns(2).show(ui, |ui| {
+                let fields = [("X (East):", " m", 0.01), ("Y (North):", " m", 0.01), ("Z (Up):", " m", 0.01), ("Yaw:", "°", 1.0)];
+                for (value, (label, suffix, speed)) in values.iter_mut().zip(fields) {
+                    ui.label(label);
+                    let response = ui
+                        .add_enabled(!read_only, egui::DragValue::new(value).speed(speed).suffix(suffix))
+                        .on_disabled_hover_text(READ_ONLY_HINT);
+                    changed |= response.changed();
+                    finished |= response.drag_stopped() || response.lost_focus();
+                    ui.end_row();
+                }
+            });
+
+            if changed {
+                let target = Pose::from_array([
+                    values[0] as f64,
+                    values[1] as f64,
+                    values[2] as f64,
+                    origin[3],
+                    origin[4],
+                    (values[3] as f64).to_radians(),
+                ]);
+                moved = assemblies.preview(&assembly.name, target, current);
+            }
+            // The daemon moves the members itself, so only the origin is sent
+            if finished {
+                if let Some((name, pose)) = assemblies.finish() {
+                    send_assembly_pose(base_url, &name, pose);
+                }
+            }
+
+            members(ui);
+        });
+    moved
+}
+
+/// Show previewed member poses in the scene and the transform editors
+fn apply_member_poses(
+    moved: Vec<(String, Pose)>,
+    positions: &mut DevicePositions,
+    orientations: &mut DeviceOrientations,
+    device_query: &mut Query<(&crate::scene::DeviceEntity, &mut Transform)>,
+) {
+    for (id, pose) in moved {
+        let position = Vec3::new(pose.x as f32, pose.y as f32, pose.z as f32);
+        let rotation = Vec3::new(pose.roll as f32, pose.pitch as f32, pose.yaw as f32);
+        positions.positions.insert(id.clone(), position);
+        orientations.orientations.insert(id.clone(), rotation);
+        for (device, mut transform) in device_query.iter_mut() {
+            if device.device_id == id {
+                transform.translation = position;
+                transform.rotation = Quat::from_euler(EulerRot::XYZ, rotation.x, rotation.y, rotation.z);
+                break;
+            }
+        }
+    }
+}
+
+/// Row in the device list: name colored by status, with details when selected
+#[allow(clippy::too_many_arguments)]
+fn device_row(
+    ui: &mut egui::Ui,
+    device: &crate::app::DeviceData,
+    selected: &mut SelectedDevice,
+    firmware_state: &FirmwareCheckState,
+    heartbeat_state: &HeartbeatState,
+    ui_layout: &mut UiLayout,
+    is_mobile: bool,
+    ui_scale: f32,
+) {
+    let is_selected = selected.0.as_ref() == Some(&device.id);
+
+    // Device name color depends on device status, firmware status, and heartbeat state
+    // Priority: Offline (red) > Firmware outdated (yellow) > Online (green/white)
+    let name_color = if device.status == DeviceStatus::Offline {
+        egui::Color32::from_rgb(200, 100, 100) // Always red for offline
+    } else if firmware_state.enabled {
+        // Check firmware status when enabled
+        match firmware_state.device_status.get(&device.id) {
+            Some(FirmwareStatusData::UpdateAvailable { .. }) => {
+                egui::Color32::from_rgb(230, 200, 50) // Yellow for outdated
+            }
+            Some(FirmwareStatusData::UpToDate) => {
+                egui::Color32::from_rgb(100, 200, 100) // Green for up to date
+            }
+            _ => {
+                // Unknown or loading - use connection status color
+                if heartbeat_state.enabled && device.status == DeviceStatus::Online {
+                    egui::Color32::from_rgb(100, 200, 100) // Green
+                } else {
+                    egui::Color32::from_rgb(200, 200, 200) // White
+                }
+            }
+        }
+    } else {
+        // Firmware checking disabled - use connection status
+        if heartbeat_state.enabled && device.status == DeviceStatus::Online {
+            egui::Color32::from_rgb(100, 200, 100) // Green
+        } else if device.status == DeviceStatus::Unknown {
+            egui::Color32::GRAY
+        } else {
+            egui::Color32::from_rgb(200, 200, 200) // White
+        }
+    };
+
+    let text = egui::RichText::new(&device.name)
+        .color(name_color)
+        .size(14.0 * ui_scale);
+
+    // On mobile, make the entire row a larger touch target
+    let response = if is_mobile {
+        ui.add_sized(
+            [ui.available_width(), 36.0 * ui_scale],
+            egui::Button::new(text).selected(is_selected)
+        )
+    } else {
+        ui.selectable_label(is_selected, text)
+    };
+
+    if response.clicked() {
+        selected.0 = Some(device.id.clone());
+        // On mobile, show the details panel when a device is selected
+        if is_mobile {
+            ui_layout.show_right_panel = true;
+            ui_layout.show_left_panel = false;
+        }
+    }
+
+    // Show inline details on desktop only (mobile uses right panel)
+    // Note: last_seen is shown in right panel, not here
+    if is_selected && !is_mobile {
+        ui.indent("device_details", |ui| {
+            ui.label(format!("ID: {}", &device.id));
+            ui.label(format!("IP: {}", &device.ip));
+            if let Some(board) = &device.board {
+                ui.label(format!("Board: {}", board));
+            }
+            if let Some(port) = device.port {
+                ui.label(format!("Port: {}", port));
+            }
+            if let Some(version) = &device.version {
+                ui.label(format!("Firmware: {}", version));
+            }
+        });
+    }
+}
+
+/// Daemon group header in the device list: connection state, address and,
+/// for daemons other than the primary, a button to stop following it
+fn daemon_header(ui: &mut egui::Ui, link: &crate::daemons::DaemonLink, remove_link: &mut Option<u32>, ui_scale: f32) {
+    let (color, hint) = match link.state() {
+        LinkState::Connected => (egui::Color32::GREEN, "Connected"),
+        LinkState::Connecting => (egui::Color32::GRAY, "Connecting"),
+        LinkState::Disconnected => (egui::Color32::RED, "Disconnected; devices shown as last seen, retrying"),
+    };
+    ui.horizontal(|ui| {
+        ui.colored_label(color, "●").on_hover_text(hint);
+        ui.label(egui::RichText::new(&link.address).size(12.0 * ui_scale).strong());
+        if link.key == PRIMARY_DAEMON {
+            ui.label(egui::RichText::new("primary").size(10.0 * ui_scale).color(egui::Color32::GRAY))
+                .on_hover_text("Scans, heartbeat, firmware checks, HCDF and keep-outs use this daemon");
+        } else if ui.small_button("✕").on_hover_text("Stop following this daemon").clicked() {
+            *remove_link = Some(link.key);
+        }
+    });
+}
+
+/// Low-bandwidth toggle and estimated data received from the daemon
+fn bandwidth_status(ui: &mut egui::Ui, low_bandwidth: &mut ResMut<LowBandwidthMode>, usage: &DataUsage, ui_scale: f32) {
+    let enabled = low_bandwidth.enabled;
+    let text = egui::RichText::new("lite").size(10.0 * ui_scale);
+    let text = if enabled { text.color(egui::Color32::from_rgb(100, 180, 255)) } else { text };
+    let hint = if enabled {
+        "Low-bandwidth mode: models skipped, summaries only, 30 s polling. Click for full fidelity."
+    } else if low_bandwidth.suggested {
+        "Cellular connection detected. Click to skip model downloads and poll every 30 s."
+    } else {
+        "Click to skip model downloads and poll every 30 s."
+    };
+    if ui.selectable_label(enabled, text).on_hover_text(hint).clicked() {
+        low_bandwidth.enabled = !enabled;
+    }
+    ui.label(egui::RichText::new(usage.label()).size(10.0 * ui_scale).color(egui::Color32::GRAY))
+        .on_hover_text("Estimated data received from the daemon");
+}
+
+/// Warning count for devices intruding into keep-out volumes, listing them on hover
+fn keepout_warning(ui: &mut egui::Ui, keepouts: &KeepoutScene) {
+    if keepouts.violations.is_empty() {
+        return;
+    }
+    let details = keepouts
+        .violations
+        .iter()
+        .map(|v| format!("{} in {} ({:.1} mm)", v.device, v.keepout, v.depth * 1000.0))
+        .collect::<Vec<_>>()
+        .join("\n");
+    ui.colored_label(egui::Color32::from_rgb(255, 80, 80), format!("⚠ {}", keepouts.violating_devices()))
+        .on_hover_text(format!("Keep-out violations:\n{}", details));
+}
+
+/// Draw a compact latency sparkline with the latest value
+/// Missed heartbeats are marked with red ticks along the baseline
+fn latency_sparkline(ui: &mut egui::Ui, samples: &[Option<f64>]) {
+    ui.vertical(|ui| {
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(160.0, 32.0), egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+
+        if samples.is_empty() {
+            ui.label(egui::RichText::new("No samples").small().weak());
+            return;
+        }
+
+        let max = samples.iter().flatten().cloned().fold(1.0_f64, f64::max);
+        let step = rect.width() / (crate::network::LATENCY_SPARKLINE_LEN.max(samples.len()) - 1).max(1) as f32;
+        let x_for = |i: usize| rect.left() + i as f32 * step;
+
+        // Draw each run of successful samples as its own line
+        let mut run: Vec<egui::Pos2> = Vec::new();
+        let stroke = egui::Stroke::new(1.5, egui::Color32::from_rgb(100, 200, 100));
+        for (i, sample) in samples.iter().enumerate() {
+            match sample {
+                Some(ms) => {
+                    let y = rect.bottom() - 2.0 - (*ms / max) as f32 * (rect.height() - 4.0);
+                    run.push(egui::pos2(x_for(i), y));
+                }
+                None => {
+                    if run.len() > 1 {
+                        painter.add(egui::Shape::line(std::mem::take(&mut run), stroke));
+                    }
+                    run.clear();
+                    let x = x_for(i);
+                    painter.line_segment(
+                        [egui::pos2(x, rect.bottom()), egui::pos2(x, rect.bottom() - 6.0)],
+                        egui::Stroke::new(1.5, egui::Color32::from_rgb(220, 80, 80)),
+                    );
+                }
+            }
+        }
+        if run.len() > 1 {
+            painter.add(egui::Shape::line(run, stroke));
+        } else if let Some(point) = run.first() {
+            painter.circle_filled(*point, 1.5, stroke.color);
+        }
+
+        let summary = match samples.last() {
+            Some(Some(ms)) => format!("{:.1} ms (max {:.1} ms)", ms, max),
+            _ => "No response".to_string(),
+        };
+        ui.label(egui::RichText::new(summary).small());
+    });
+}
+
 /// Format a timestamp string (ISO 8601) to a human-readable format
 fn format_last_seen(timestamp: &str) -> String {
     // Try to parse the ISO 8601 timestamp and format it nicely
@@ -1721,3 +3215,124 @@ fn capitalize_first(s: &str) -> String {
         Some(c) => c.to_uppercase().collect::<String>() + chars.as_str(),
     }
 }
+
+/// A port pose edit made in the details panel
+enum PortPoseEdit {
+    /// New pose; `commit` sends it to the daemon (end of a drag or a typed value)
+    Set { device_id: String, port: String, pose: [f64; 6], commit: bool },
+    /// Restore the pose from the device's HCDF
+    Reset { device_id: String, port: String },
+}
+
+/// Pose fields for a port's fallback geometry, in millimeters and degrees
+fn port_pose_editor(ui: &mut egui::Ui, device_id: &str, port: &PortData, ui_scale: f32, read_only: bool) -> Option<PortPoseEdit> {
+    let pose = port.pose.unwrap_or_default();
+    let mut values = [
+        pose[0] * 1000.0,
+        pose[1] * 1000.0,
+        pose[2] * 1000.0,
+        pose[3].to_degrees(),
+        pose[4].to_degrees(),
+        pose[5].to_degrees(),
+    ];
+    let mut changed = false;
+    let mut commit = false;
+
+    ui.label(
+        egui::RichText::new(format!("{} pose", port.name))
+            .size(12.0 * ui_scale)
+            .strong()
+    );
+    egui::Grid::new(("port_pose", device_id, &port.name))
+        .num_columns(2)
+        .show(ui, |ui| {
+            for (i, label) in ["  X:", "  Y:", "  Z:", "  Roll:", "  Pitch:", "  Yaw:"].iter().enumerate() {
+                ui.label(*label);
+                let drag = if i < 3 {
+                    egui::DragValue::new(&mut values[i]).speed(0.1).suffix(" mm")
+                } else {
+                    egui::DragValue::new(&mut values[i]).speed(1.0).suffix("°")
+                };
+                let response = ui.add_enabled(!read_only, drag).on_disabled_hover_text(READ_ONLY_HINT);
+                changed |= response.changed();
+                commit |= response.drag_stopped() || (response.changed() && !response.dragged());
+                ui.end_row();
+            }
+        });
+
+    let mut reset = false;
+    if port.original_pose.is_some() {
+        ui.horizontal(|ui| {
+            ui.label(
+                egui::RichText::new("edited")
+                    .size(11.0 * ui_scale)
+                    .italics()
+                    .color(egui::Color32::from_rgb(255, 200, 50))
+            );
+            reset = ui
+                .add_enabled(!read_only, egui::Button::new("Reset").small())
+                .on_hover_text("Restore the pose from the device's HCDF")
+                .on_disabled_hover_text(READ_ONLY_HINT)
+                .clicked();
+        });
+    }
+
+    if reset {
+        return Some(PortPoseEdit::Reset {
+            device_id: device_id.to_string(),
+            port: port.name.clone(),
+        });
+    }
+    if !(changed || commit) {
+        return None;
+    }
+    Some(PortPoseEdit::Set {
+        device_id: device_id.to_string(),
+        port: port.name.clone(),
+        pose: [
+            values[0] / 1000.0,
+            values[1] / 1000.0,
+            values[2] / 1000.0,
+            values[3].to_radians(),
+            values[4].to_radians(),
+            values[5].to_radians(),
+        ],
+        commit,
+    })
+}
+
+/// Apply a port pose edit to the registry (the marker follows it) and send
+/// committed edits to the daemon
+fn apply_port_pose_edit(registry: &mut DeviceRegistry, daemons: &Daemons, edit: PortPoseEdit) {
+    let (PortPoseEdit::Set { device_id, port: port_name, .. } | PortPoseEdit::Reset { device_id, port: port_name }) = &edit;
+    let Some(port) = registry
+        .devices
+        .iter_mut()
+        .find(|d| d.id == *device_id)
+        .and_then(|d| d.ports.iter_mut().find(|p| p.name == *port_name))
+    else {
+        return;
+    };
+    let Some((base_url, raw_id)) = daemons.route(device_id) else {
+        return;
+    };
+
+    match &edit {
+        PortPoseEdit::Set { pose, commit, .. } => {
+            if port.original_pose.is_none() && port.pose != Some(*pose) {
+                port.original_pose = Some(port.pose.unwrap_or_default());
+            }
+            port.pose = Some(*pose);
+            // A click that didn't move an unedited port has nothing to persist
+            if *commit && port.original_pose.is_some() {
+                sync_port_pose(raw_id, port_name, Some(*pose), base_url);
+            }
+        }
+        PortPoseEdit::Reset { .. } => {
+            if let Some(original) = port.original_pose.take() {
+                port.pose = Some(original);
+            }
+            sync_port_pose(raw_id, port_name, None, base_url);
+        }
+    }
+}